@@ -0,0 +1,369 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use shared::engine::{
+    asset::{handle::Handle, manager::Asset, AssetManager},
+    block::AIR,
+    math::{coords::WorldPos, rng::WorldRng, vector::Vec3},
+    world::{time::WorldTime, World}
+};
+
+use crate::{
+    audio::{mixer::{PlaySound, SoundCategory, VoiceId}, sound::Sound, Audio},
+    particles::{Emitter, ParticleStyle, ParticleSystem}
+};
+
+/// Ambience of biomes without a file of their own, and of worlds without biomes.
+pub const DEFAULT_AMBIENCE: &str = "default";
+/// Seconds an ambient loop takes to fade from silent to full volume, or back, when what's around the player changes.
+pub const FADE_SECONDS: f32 = 3.0;
+/// Blocks around the player ambient particles spawn within, unless their file says otherwise.
+pub const DEFAULT_PARTICLE_RADIUS: f32 = 16.0;
+
+/// Part of the day an ambient sound or particle is limited to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeOfDay {
+    #[default]
+    Always,
+    Day,
+    Night
+}
+
+/// Where and when an ambient sound plays or particles spawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AmbientConditions {
+    pub time: TimeOfDay,
+    /// Lowest and highest Y of the player, so caves can sound different from the surface.
+    pub min_height: Option<f64>,
+    pub max_height: Option<f64>
+}
+
+impl AmbientConditions {
+    pub fn matches(&self, height: f64, time: WorldTime) -> bool {
+        let time_matches = match self.time {
+            TimeOfDay::Always => true,
+            TimeOfDay::Day => time.is_day(),
+            TimeOfDay::Night => !time.is_day()
+        };
+        return time_matches && self.min_height.is_none_or(|min| height >= min) && self.max_height.is_none_or(|max| height <= max);
+    }
+}
+
+/// A sound looped while its conditions match.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AmbientSound {
+    /// Name of the sound asset.
+    pub sound: String,
+    #[serde(default = "full_volume")]
+    pub volume: f32,
+    #[serde(flatten)]
+    pub when: AmbientConditions
+}
+
+fn full_volume() -> f32 {
+    return 1.0;
+}
+
+/// Particles spawned around the player while their conditions match, such as fireflies at night or drips in caves.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AmbientParticles {
+    /// Particles spawned per second.
+    pub rate: f32,
+    #[serde(default = "default_particle_radius")]
+    pub radius: f32,
+    pub lifetime: [f32; 2],
+    #[serde(default)]
+    pub velocity: [f32; 3],
+    #[serde(default)]
+    pub spread: f32,
+    #[serde(default)]
+    pub gravity: f32,
+    #[serde(default)]
+    pub drag: f32,
+    pub size: [f32; 2],
+    pub color: [[f32; 4]; 2],
+    #[serde(flatten)]
+    pub when: AmbientConditions
+}
+
+fn default_particle_radius() -> f32 {
+    return DEFAULT_PARTICLE_RADIUS;
+}
+
+impl AmbientParticles {
+    pub fn style(&self) -> ParticleStyle {
+        return ParticleStyle {
+            lifetime: self.lifetime,
+            velocity: Vec3::new(self.velocity[0], self.velocity[1], self.velocity[2]),
+            spread: self.spread,
+            gravity: self.gravity,
+            drag: self.drag,
+            size: self.size,
+            color: self.color,
+            texture: None,
+            collides: false
+        };
+    }
+}
+
+/// The ambient sounds and particles of a biome, from a TOML file in the ambience directory of the assets, so resource
+/// packs can replace them. A biome's file is named after it, with its namespace as a directory.
+/// ```
+/// # use client::ambience::{AmbienceDefinition, TimeOfDay};
+/// # use shared::engine::{asset::manager::Asset, world::time::{WorldTime, MIDNIGHT, NOON}};
+/// let forest = AmbienceDefinition::decode(br#"
+///     [[sounds]]
+///     sound = "ambient/birds"
+///     time = "day"
+///
+///     [[particles]]
+///     rate = 2.0
+///     lifetime = [2.0, 4.0]
+///     size = [0.05, 0.05]
+///     color = [[0.8, 1.0, 0.3, 1.0], [0.8, 1.0, 0.3, 0.0]]
+///     time = "night"
+///     min_height = 40.0
+/// "#).unwrap();
+/// assert_eq!((forest.sounds[0].volume, forest.sounds[0].when.time), (1.0, TimeOfDay::Day));
+/// assert!(forest.particles[0].when.matches(64.0, WorldTime::new(MIDNIGHT)));
+/// assert!(!forest.particles[0].when.matches(64.0, WorldTime::new(NOON)));
+/// assert!(!forest.particles[0].when.matches(12.0, WorldTime::new(MIDNIGHT)));
+/// assert!(AmbienceDefinition::decode(b"[[sounds]]\nsound = \"wind\"\nvolume = 2.0").is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AmbienceDefinition {
+    pub sounds: Vec<AmbientSound>,
+    pub particles: Vec<AmbientParticles>
+}
+
+impl AmbienceDefinition {
+    /// Asset name of a biome's ambience, with its namespace as a directory, so cube:forest is cube/forest.
+    /// Worlds without biomes use the default.
+    pub fn asset_name(biome: Option<&str>) -> String {
+        return biome.map_or(DEFAULT_AMBIENCE.to_string(), |biome| biome.replace(':', "/"));
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let Some(sound) = self.sounds.iter().find(|sound| !(0.0..=1.0).contains(&sound.volume)) {
+            return Err(format!("volume of {} must be between 0 and 1", sound.sound));
+        }
+        for particles in self.particles.iter() {
+            if particles.rate < 0.0 || particles.radius <= 0.0 {
+                return Err("particles need a rate of at least 0 and a radius above 0".to_string());
+            }
+            if particles.lifetime[0] <= 0.0 || particles.lifetime[0] > particles.lifetime[1] {
+                return Err("particle lifetimes must be above 0, shortest first".to_string());
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl Asset for AmbienceDefinition {
+    const DIRECTORY: &'static str = "ambience";
+    const EXTENSION: &'static str = "toml";
+
+    fn decode(data: &[u8]) -> Result<AmbienceDefinition, String> {
+        let text = std::str::from_utf8(data).map_err(|error| error.to_string())?;
+        let definition: AmbienceDefinition = toml::from_str(text).map_err(|error| error.to_string())?;
+        definition.validate()?;
+        return Ok(definition);
+    }
+}
+
+/// An ambient sound loop, fading in while it's wanted and out once it isn't.
+struct AmbientLoop {
+    sound: String,
+    handle: Handle<Sound>,
+    /// Playing once its sound loaded.
+    voice: Option<VoiceId>,
+    volume: f32,
+    /// Volume it's fading to.
+    target: f32
+}
+
+/// Plays the ambience of where the player is: loops the sounds of their biome, crossfading as they move between
+/// biomes, go underground, or night falls, and spawns its particles around them. Ambience files load on the asset
+/// manager's IO jobs, and the ambience changes once they're loaded.
+/// ```
+/// # use std::sync::Arc;
+/// # use client::{ambience::{Ambience, FADE_SECONDS}, audio::Audio, particles::ParticleSystem};
+/// # use shared::engine::{asset::AssetManager, job::system::JobSystem, math::coords::WorldPos, world::World};
+/// let directory = std::env::temp_dir().join(format!("ambience_doctest_{}", std::process::id()));
+/// std::fs::create_dir_all(directory.join("ambience")).unwrap();
+/// std::fs::write(directory.join("ambience/default.toml"), r#"
+///     [[sounds]]
+///     sound = "ambient/wind"
+///
+///     [[particles]]
+///     rate = 10.0
+///     lifetime = [5.0, 5.0]
+///     size = [0.1, 0.1]
+///     color = [[1.0, 1.0, 1.0, 1.0], [1.0, 1.0, 1.0, 0.0]]
+/// "#).unwrap();
+/// std::fs::create_dir_all(directory.join("sounds/ambient")).unwrap();
+/// let spec = hound::WavSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+/// let mut writer = hound::WavWriter::create(directory.join("sounds/ambient/wind.wav"), spec).unwrap();
+/// (0..800).for_each(|_| writer.write_sample(1000i16).unwrap());
+/// writer.finalize().unwrap();
+///
+/// let jobs = Arc::new(JobSystem::new(1));
+/// let assets = Arc::new(AssetManager::new(&directory, jobs.clone()));
+/// let mut audio = Audio::new(assets.clone());
+/// let mut ambience = Ambience::new(assets);
+/// let mut particles = ParticleSystem::new(jobs);
+/// let (world, player) = (World::new(), WorldPos::new(0.0, 64.0, 0.0));
+/// ambience.update(0.0, player, Some(&world), Some(&mut audio), &mut particles);
+/// while ambience.is_loading() {
+///     ambience.update(0.0, player, Some(&world), Some(&mut audio), &mut particles);
+/// }
+/// ambience.update(1.0, player, Some(&world), Some(&mut audio), &mut particles);
+/// particles.update(0.0, None);
+/// assert_eq!(particles.len(), 10);
+/// // The wind fades in, and out again once there's no world.
+/// assert_eq!(ambience.sounds(), [("ambient/wind", 1.0 / FADE_SECONDS)]);
+/// ambience.update(FADE_SECONDS, player, None, Some(&mut audio), &mut particles);
+/// assert!(ambience.sounds().is_empty());
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct Ambience {
+    assets: Arc<AssetManager>,
+    /// Every ambience file asked for, by asset name, kept loaded so moving back into a biome is instant.
+    definitions: HashMap<String, Handle<AmbienceDefinition>>,
+    /// Asset name of the ambience playing.
+    current: Option<String>,
+    loops: Vec<AmbientLoop>,
+    /// Particles owed by each particle rule of the current ambience, as fractions add up at low rates.
+    owed: Vec<f32>,
+    rng: WorldRng
+}
+
+impl Ambience {
+    pub fn new(assets: Arc<AssetManager>) -> Ambience {
+        return Ambience { assets, definitions: HashMap::new(), current: None, loops: Vec::new(), owed: Vec::new(), rng: WorldRng::new(0) };
+    }
+
+    /// Whether the ambience of where the player last was is still loading, or the default if it has no file.
+    pub fn is_loading(&self) -> bool {
+        let loading = |name: &str| self.definitions.get(name).is_some_and(|handle| handle.get().is_none() && handle.error().is_none());
+        let Some(current) = self.current.as_ref() else {
+            return false;
+        };
+        let missing = self.definitions.get(current).is_some_and(|handle| handle.error().is_some());
+        return loading(current) || (missing && loading(DEFAULT_AMBIENCE));
+    }
+
+    /// Sounds looping or fading out, and how loud they are.
+    pub fn sounds(&self) -> Vec<(&str, f32)> {
+        return self.loops.iter().map(|ambient| (ambient.sound.as_str(), ambient.volume)).collect();
+    }
+
+    /// Restart loops of a sound that was reloaded, so they play its new data. Ambience files reload in place.
+    pub fn sound_reloaded(&mut self, name: &str, audio: &mut Audio) {
+        let mut mixer = audio.mixer().lock().unwrap();
+        for ambient in self.loops.iter_mut().filter(|ambient| ambient.sound == name) {
+            if let Some(voice) = ambient.voice.take() {
+                mixer.stop(voice);
+            }
+        }
+    }
+
+    fn definition(&mut self, name: &str) -> Option<Arc<AmbienceDefinition>> {
+        let assets = &self.assets;
+        let handle = self.definitions.entry(name.to_string()).or_insert_with(|| assets.load::<AmbienceDefinition>(name));
+        return handle.get();
+    }
+
+    /// Fade loops towards the ambience of the player's position, and spawn its particles. Called once a frame with the
+    /// frame's time. Without a world everything fades out. Sounds need the client's audio, if it has any.
+    pub fn update(&mut self, seconds: f32, player: WorldPos, world: Option<&World>, audio: Option<&mut Audio>, particles: &mut ParticleSystem) {
+        let mut definition = None;
+        if let Some(world) = world {
+            let name = AmbienceDefinition::asset_name(world.biome(player.block()).map(|biome| biome.name.as_str()));
+            if self.current.as_ref() != Some(&name) {
+                self.owed.clear();
+            }
+            // A biome without a file of its own sounds like the default.
+            definition = match self.definition(&name) {
+                Some(found) => Some(found),
+                None if self.definitions[&name].error().is_some() => self.definition(DEFAULT_AMBIENCE),
+                None => None
+            };
+            self.current = Some(name);
+        } else {
+            self.current = None;
+        }
+        let time = world.map_or(WorldTime::default(), World::world_time);
+        let definition = definition.unwrap_or_default();
+
+        let wanted: Vec<&AmbientSound> = definition.sounds.iter().filter(|sound| sound.when.matches(player.y, time)).collect();
+        if let Some(audio) = audio {
+            self.update_loops(seconds, &wanted, audio);
+        }
+        if let Some(world) = world {
+            self.spawn_particles(seconds, player, world, time, &definition, particles);
+        }
+    }
+
+    /// Start loops that became wanted, fade every loop towards its volume, and stop those that faded out.
+    fn update_loops(&mut self, seconds: f32, wanted: &[&AmbientSound], audio: &mut Audio) {
+        for sound in wanted.iter() {
+            if !self.loops.iter().any(|ambient| ambient.sound == sound.sound) {
+                let handle = audio.load(&sound.sound);
+                self.loops.push(AmbientLoop { sound: sound.sound.clone(), handle, voice: None, volume: 0.0, target: 0.0 });
+            }
+        }
+        let mut mixer = audio.mixer().lock().unwrap();
+        let step = seconds / FADE_SECONDS;
+        self.loops.retain_mut(|ambient| {
+            ambient.target = wanted.iter().find(|sound| sound.sound == ambient.sound).map_or(0.0, |sound| sound.volume);
+            ambient.volume = if ambient.volume < ambient.target {
+                (ambient.volume + step).min(ambient.target)
+            } else {
+                (ambient.volume - step).max(ambient.target)
+            };
+            if ambient.voice.is_none() {
+                if let Some(sound) = ambient.handle.get() {
+                    ambient.voice = Some(mixer.play(PlaySound::new(sound, SoundCategory::Ambient).with_volume(ambient.volume).looping()));
+                }
+            }
+            let playing = ambient.voice.is_some_and(|voice| mixer.set_volume(voice, ambient.volume));
+            if ambient.volume <= 0.0 && ambient.target <= 0.0 {
+                if let Some(voice) = ambient.voice {
+                    mixer.stop(voice);
+                }
+                return false;
+            }
+            // A loop the mixer dropped for other sounds starts again, and one that failed to load is given up on.
+            if ambient.voice.is_some() && !playing {
+                ambient.voice = None;
+            }
+            return ambient.handle.error().is_none();
+        });
+    }
+
+    /// Spawn the particles owed this frame in the air around the player.
+    fn spawn_particles(&mut self, seconds: f32, player: WorldPos, world: &World, time: WorldTime, definition: &AmbienceDefinition, particles: &mut ParticleSystem) {
+        self.owed.resize(definition.particles.len(), 0.0);
+        for (rule, owed) in definition.particles.iter().zip(self.owed.iter_mut()) {
+            if !rule.when.matches(player.y, time) {
+                *owed = 0.0;
+                continue;
+            }
+            *owed += rule.rate * seconds;
+            let style = rule.style();
+            while *owed >= 1.0 {
+                *owed -= 1.0;
+                let mut offset = || self.rng.range_f32(-rule.radius..rule.radius) as f64;
+                let position = player + WorldPos::new(offset(), offset(), offset());
+                // Particles inside blocks would be hidden, so they're skipped rather than moved.
+                if world.get_block(position.block()).is_none_or(|id| id == AIR) {
+                    particles.spawn(Emitter::new(position, style).with_burst(1));
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
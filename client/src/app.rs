@@ -0,0 +1,960 @@
+use std::{collections::HashSet, fs::File, io::{self, BufWriter}, path::PathBuf, sync::{Arc, Mutex}, time::{Instant, SystemTime}};
+
+use shared::engine::{
+    asset::{manager::{Asset, AssetReloaded}, texture::Texture, AssetManager},
+    block::AIR,
+    command::{argument::{ArgumentKind, CommandEnvironment}, dispatcher::{CommandSource, PermissionLevel}, CommandDispatcher},
+    entity::kinematics::TICKS_PER_SECOND,
+    event::bus::{MessageBus, Subscription},
+    job::system::JobSystem,
+    light::probe::LightProbeGrid,
+    lod::LodPolicy,
+    math::{aabb::Aabb, coords::{ChunkPos, WorldPos, CHUNK_SIZE}, direction::Direction, vector::Vec3},
+    memory::{MemoryCategory, MemoryTracker},
+    mesh::{greedy::OpacityFn, remesh::{RemeshJob, RemeshQueue}},
+    net::{chat::{ChatKind, ChatLine, TextSpan, FORMAT_CODE}, replay::{MAX_REPLAY_SPEED, MIN_REPLAY_SPEED}, sign::{self as sign_net, EditSign}},
+    world::{chunk::Chunk, time::{WorldTime, NOON}, World}
+};
+use winit::{
+    application::ApplicationHandler,
+    dpi::PhysicalSize,
+    event::{DeviceEvent, DeviceId, ElementState, MouseScrollDelta, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow},
+    keyboard::Key,
+    window::{CursorGrabMode, Fullscreen, Window, WindowId}
+};
+
+use crate::{
+    ambience::Ambience,
+    audio::{mixer::Listener, sound::Sound, Audio, SoundEvent},
+    blocks::{client_blocks, BlockArt},
+    camera::{Camera, CameraMode},
+    chat::{ChatInput, ChatWindow},
+    culling::{self, ChunkBounds},
+    debug_overlay::{self, DebugInfo, DebugOverlay},
+    input::{self, InputMap, InputState, PIXELS_PER_LINE},
+    particles::ParticleSystem,
+    recording::{InputPlayback, InputRecorder, RecordedInput},
+    renderer::Renderer,
+    replay::ReplayViewer,
+    screenshot::{ScreenshotError, Screenshots, SCREENSHOTS_DIRECTORY},
+    settings::{Settings, MAX_FOV, MIN_FOV},
+    signs::{self, SignEditor},
+    sky::SkyState,
+    standby::StandbyWorld,
+    targeting::Targeting
+};
+
+pub const WINDOW_TITLE: &str = "Cube Universe";
+/// Where the camera starts, above the ground.
+pub const SPAWN_POSITION: WorldPos = WorldPos::new(0.0, 80.0, 0.0);
+/// Radians per second the camera turns with a look stick pushed all the way.
+pub const STICK_LOOK_SPEED: f32 = 3.0;
+/// Blocks walked between footsteps.
+pub const STEP_LENGTH: f64 = 1.8;
+/// Blocks from the camera down to the feet, where footsteps are heard from.
+pub const EYE_HEIGHT: f64 = 1.6;
+/// Name the player chats and runs commands under until a server gives them one.
+pub const PLAYER_NAME: &str = "Player";
+/// Light probes resampled each frame, so a large light change is spread over a few frames.
+pub const PROBE_BUDGET: usize = 256;
+/// Seconds between checks for changed asset files.
+pub const RELOAD_SECONDS: f32 = 1.0;
+
+/// A world left for the menu, with where the camera was and the replay that was building it, if any.
+struct Standby {
+    world: StandbyWorld,
+    camera: Camera,
+    replay: Option<ReplayViewer>
+}
+
+/// The client application, driven by the winit event loop. The window and renderer are created once the
+/// event loop resumes, as some platforms can't create windows before then.
+pub struct App {
+    jobs: Arc<JobSystem>,
+    renderer: Option<Renderer>,
+    /// The world being played in. Nothing is targeted without one.
+    world: Option<Arc<World>>,
+    /// A replay being watched, which builds the world instead of a server.
+    replay: Option<ReplayViewer>,
+    /// The world left for the menu, kept to go back to.
+    standby: Option<Standby>,
+    camera: Camera,
+    targeting: Targeting,
+    particles: ParticleSystem,
+    /// Light around the camera, sampled from the world's light, shading particles.
+    light_probes: Arc<Mutex<LightProbeGrid>>,
+    /// Keeps the light probes in step with the world's light, while the world has events to follow.
+    probe_subscriptions: Vec<Subscription>,
+    /// Video and audio settings. Controls are kept by the input map.
+    settings: Settings,
+    /// Memory of chunk meshes, kept within the budget in the video settings.
+    memory: Arc<MemoryTracker>,
+    /// Textures and opacity of blocks, if the app was given assets to load them from. Blocks are untextured and
+    /// everything but air is opaque without them.
+    art: Option<BlockArt>,
+    /// Assets the app draws and plays, with the bus their reloads are published on, if it was given any.
+    assets: Option<(Arc<AssetManager>, Arc<MessageBus>)>,
+    /// Assets reloaded since the last frame.
+    reloaded: Arc<Mutex<Vec<AssetReloaded>>>,
+    /// Seconds since changed asset files were last checked for.
+    since_reload: f32,
+    /// Sections of chunks waiting to be meshed.
+    remesh: RemeshQueue,
+    /// Meshing jobs not yet uploaded.
+    mesh_jobs: Vec<RemeshJob>,
+    /// Loaded chunks that have been queued for meshing, to find the chunks loaded and unloaded since.
+    meshed: HashSet<ChunkPos>,
+    /// Chunks whose meshes were freed for memory, meshed again once they're back in view.
+    evicted: HashSet<ChunkPos>,
+    /// Sound, if the app was given any.
+    audio: Option<Audio>,
+    /// Sounds and particles of the biome the camera is in, if the app was given assets to load them from.
+    ambience: Option<Ambience>,
+    /// Blocks walked since the last footstep.
+    walked: f64,
+    input: InputMap,
+    /// The mouse turns the camera only while the cursor is captured. Breaking a block captures it, and
+    /// release_cursor releases it.
+    captured: bool,
+    debug: DebugOverlay,
+    /// While open, keys type into the chat instead of controlling the game.
+    chat: ChatWindow,
+    /// While open, keys type onto the sign the player placed.
+    sign_editor: SignEditor,
+    /// Commands typed in chat, run on the app itself.
+    commands: Arc<CommandDispatcher<App>>,
+    screenshots: Screenshots,
+    /// Where the player's input is being recorded to, if anywhere.
+    recorder: Option<InputRecorder<BufWriter<File>>>,
+    /// Recorded input played in place of the player's, if any.
+    playback: Option<InputPlayback>,
+    /// Set once the input played back finishes or closes the window.
+    exit_requested: bool,
+    last_frame: Option<Instant>,
+    /// Seconds of frames run, which recorded input is timed by.
+    elapsed: f64,
+    /// Set when the renderer fails, to be returned once the event loop exits.
+    error: Option<Box<dyn std::error::Error>>
+}
+
+impl App {
+    pub fn new(jobs: Arc<JobSystem>, settings: Settings) -> App {
+        let particles = ParticleSystem::new(jobs.clone()).with_lod(LodPolicy::for_view_distance(settings.video.fog_distance()));
+        let camera = Camera::new(SPAWN_POSITION).with_fov(settings.video.fov.to_radians());
+        let input = InputMap::new(settings.controls.clone());
+        let screenshots = Screenshots::new(jobs.clone(), PathBuf::from(SCREENSHOTS_DIRECTORY));
+        let memory = Arc::new(MemoryTracker::new().with_budget(MemoryCategory::Meshes, settings.video.mesh_budget()));
+        return App { jobs, renderer: None, world: None, replay: None, standby: None, camera, targeting: Targeting::new(), particles, light_probes: Arc::new(Mutex::new(LightProbeGrid::default())), probe_subscriptions: Vec::new(), settings, memory, art: None, assets: None, reloaded: Arc::new(Mutex::new(Vec::new())), since_reload: 0.0, remesh: RemeshQueue::new(), mesh_jobs: Vec::new(), meshed: HashSet::new(), evicted: HashSet::new(), audio: None, ambience: None, walked: 0.0, input, captured: false, debug: DebugOverlay::new(), chat: ChatWindow::new(), sign_editor: SignEditor::new(), commands: Arc::new(App::commands()), screenshots, recorder: None, playback: None, exit_requested: false, last_frame: None, elapsed: 0.0, error: None };
+    }
+
+    pub fn with_audio(self, audio: Audio) -> Self {
+        audio.set_volumes(self.settings.audio);
+        let mut app = self;
+        app.audio = Some(audio);
+        return app;
+    }
+
+    pub fn with_ambience(mut self, ambience: Ambience) -> Self {
+        self.ambience = Some(ambience);
+        return self;
+    }
+
+    /// Texture blocks from the block definitions, models and textures in some assets, waiting for them to load.
+    /// Blocks that fail to load draw with the missing texture. Changed asset files are reloaded while the app runs,
+    /// with the assets publishing AssetReloaded on the bus given, so what's built from them is rebuilt.
+    pub fn with_assets(mut self, assets: Arc<AssetManager>, events: Arc<MessageBus>) -> Self {
+        self.load_block_art(&assets);
+        let reloaded = self.reloaded.clone();
+        events.subscribe(move |event: &AssetReloaded| reloaded.lock().unwrap().push(event.clone()));
+        self.assets = Some((assets, events));
+        return self;
+    }
+
+    fn load_block_art(&mut self, assets: &AssetManager) {
+        let (art, errors) = BlockArt::load(assets, &client_blocks());
+        for error in errors {
+            eprintln!("{}", error);
+        }
+        self.set_block_art(art);
+    }
+
+    /// Check for changed asset files every RELOAD_SECONDS, and rebuild what was built from the assets reloaded
+    /// since the last frame: the block atlas, remeshing every chunk, and ambient loops of reloaded sounds.
+    fn reload_assets(&mut self, seconds: f32) {
+        let Some((assets, events)) = self.assets.clone() else {
+            return;
+        };
+        self.since_reload += seconds;
+        if self.since_reload >= RELOAD_SECONDS {
+            self.since_reload = 0.0;
+            assets.reload_changed();
+        }
+        events.dispatch();
+        let reloaded: Vec<AssetReloaded> = self.reloaded.lock().unwrap().drain(..).collect();
+        for event in reloaded.iter() {
+            match event.error.as_ref() {
+                Some(error) => eprintln!("{}", error),
+                None => println!("reloaded {}/{}", event.directory, event.name)
+            }
+            if event.directory == Sound::DIRECTORY {
+                if let (Some(ambience), Some(audio)) = (self.ambience.as_mut(), self.audio.as_mut()) {
+                    ambience.sound_reloaded(&event.name, audio);
+                }
+            }
+        }
+        // One rebuild however many of its assets changed, such as a texture pack dropped in.
+        if reloaded.iter().any(BlockArt::depends_on) {
+            self.load_block_art(&assets);
+        }
+    }
+
+    /// Record what the player does, to play back with with_input_playback().
+    pub fn with_input_recording(mut self, recorder: InputRecorder<BufWriter<File>>) -> Self {
+        self.recorder = Some(recorder);
+        return self;
+    }
+
+    /// Play recorded input in place of the player's, which is ignored, and exit once it's all played.
+    pub fn with_input_playback(mut self, playback: InputPlayback) -> Self {
+        self.playback = Some(playback);
+        return self;
+    }
+
+    /// Watch a replay instead of playing, flying freely through the world it builds.
+    pub fn with_replay(mut self, replay: ReplayViewer) -> Self {
+        self.set_world(Some(replay.world().clone()));
+        self.replay = Some(replay);
+        if self.camera.mode() != CameraMode::Fly {
+            self.camera.toggle_mode();
+        }
+        return self;
+    }
+
+    /// The settings in use, including any controls rebound while playing, to be saved.
+    pub fn settings(&self) -> Settings {
+        return Settings { controls: self.input.bindings().clone(), ..self.settings.clone() };
+    }
+
+    /// Change settings while running, applying only what changed, such as reconfiguring the swapchain when vsync
+    /// is toggled.
+    pub fn apply_settings(&mut self, settings: Settings) {
+        let settings = settings.clamped();
+        let old = std::mem::replace(&mut self.settings, settings.clone());
+        if settings.video.fov != old.video.fov {
+            self.camera.set_fov(settings.video.fov.to_radians());
+        }
+        self.memory.set_budget(MemoryCategory::Meshes, settings.video.mesh_budget());
+        if settings.video.render_distance != old.video.render_distance {
+            self.particles.set_lod(LodPolicy::for_view_distance(settings.video.fog_distance()));
+        }
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.set_vsync(settings.video.vsync);
+            if settings.video.fullscreen != old.video.fullscreen {
+                renderer.window().set_fullscreen(settings.video.fullscreen.then_some(Fullscreen::Borderless(None)));
+            }
+        }
+        if let Some(audio) = self.audio.as_ref() {
+            audio.set_volumes(settings.audio);
+        }
+        if &settings.controls != self.input.bindings() {
+            *self.input.bindings_mut() = settings.controls;
+        }
+    }
+
+    pub fn audio_mut(&mut self) -> Option<&mut Audio> {
+        return self.audio.as_mut();
+    }
+
+    pub fn camera(&self) -> &Camera {
+        return &self.camera;
+    }
+
+    pub fn input(&self) -> &InputMap {
+        return &self.input;
+    }
+
+    /// What the crosshair is on, for breaking and placing blocks.
+    pub fn targeting(&self) -> &Targeting {
+        return &self.targeting;
+    }
+
+    /// Particles in the world, for gameplay to spawn emitters into.
+    pub fn particles_mut(&mut self) -> &mut ParticleSystem {
+        return &mut self.particles;
+    }
+
+    pub fn chat(&self) -> &ChatWindow {
+        return &self.chat;
+    }
+
+    /// Chat from the server, for whoever holds the connection to pass its chat packets to.
+    pub fn chat_mut(&mut self) -> &mut ChatWindow {
+        return &mut self.chat;
+    }
+
+    /// Handle a sign packet from the server: the editor opening for a sign the player placed, or a sign's text.
+    /// False if the packet isn't one, for whoever holds the connection to pass on.
+    pub fn receive_sign_packet(&mut self, data: &[u8]) -> bool {
+        if self.sign_editor.receive(data) {
+            self.input.clear();
+            self.capture_cursor(false);
+            return true;
+        }
+        return self.world.as_ref().is_some_and(|world| sign_net::apply_packet(world, data).unwrap_or(false));
+    }
+
+    /// Signs the player finished writing on, for whoever holds the connection to send. Their text is shown in the
+    /// world straight away.
+    pub fn take_sign_edits(&mut self) -> Vec<EditSign> {
+        let edits = self.sign_editor.take_edits();
+        if let Some(world) = self.world.as_ref() {
+            for edit in edits.iter() {
+                signs::predict_edit(world, edit);
+            }
+        }
+        return edits;
+    }
+
+    /// Enter a world, or leave it with None.
+    pub fn set_world(&mut self, world: Option<Arc<World>>) {
+        if let Some(events) = self.world.as_ref().and_then(|world| world.events()) {
+            for subscription in self.probe_subscriptions.drain(..) {
+                events.unsubscribe(subscription);
+            }
+        }
+        self.probe_subscriptions.clear();
+        self.light_probes = Arc::new(Mutex::new(LightProbeGrid::default()));
+        if let Some(events) = world.as_ref().and_then(|world| world.events()) {
+            self.probe_subscriptions = LightProbeGrid::track(&self.light_probes, events).to_vec();
+        }
+        self.world = world;
+        self.targeting.clear();
+        self.particles.clear();
+        self.mesh_jobs.clear();
+        self.evicted.clear();
+        for pos in self.meshed.drain() {
+            if let Some(renderer) = self.renderer.as_mut() {
+                renderer.remove_chunk(pos);
+            }
+        }
+    }
+
+    /// Draw blocks with new art, remeshing every chunk with its textures.
+    fn set_block_art(&mut self, art: BlockArt) {
+        self.remesh.set_textures(Some(art.faces().clone()));
+        self.remesh.set_translucency(Some(art.translucent().clone()));
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.set_atlas(art.atlas());
+        }
+        for pos in self.meshed.iter() {
+            self.remesh.mark_chunk(*pos);
+        }
+        self.art = Some(art);
+    }
+
+    /// Queue chunks loaded since the last frame for meshing, along with the faces of their neighbors they touch, and
+    /// stop drawing unloaded ones. Then start jobs meshing everything queued or edited, and upload finished meshes.
+    fn update_meshes(&mut self) {
+        let (Some(renderer), Some(world)) = (self.renderer.as_mut(), self.world.as_ref()) else {
+            return;
+        };
+        let loaded: HashSet<ChunkPos> = world.loaded_chunks().into_iter().collect();
+        let unloaded: Vec<ChunkPos> = self.meshed.difference(&loaded).copied().collect();
+        for pos in unloaded.iter() {
+            renderer.remove_chunk(*pos);
+        }
+        let added: Vec<ChunkPos> = loaded.difference(&self.meshed).copied().collect();
+        for pos in unloaded.iter().chain(added.iter()) {
+            for direction in Direction::ALL {
+                let neighbor = *pos + direction.chunk_offset();
+                if self.meshed.contains(&neighbor) && loaded.contains(&neighbor) {
+                    self.remesh.mark_sections(neighbor, Chunk::sections_on_face(direction.opposite()));
+                }
+            }
+        }
+        for pos in added.iter() {
+            self.remesh.mark_chunk(*pos);
+        }
+        self.meshed = loaded;
+        self.evicted.retain(|pos| self.meshed.contains(pos));
+        let bounds: Vec<ChunkBounds> = self.evicted.iter()
+            .map(|pos| ChunkBounds { pos: *pos, bounds: Aabb::new(Vec3::ZERO, Vec3::splat(CHUNK_SIZE as f32)) })
+            .collect();
+        for chunk in culling::cull_chunks(&self.camera.frustum(), self.camera.origin(), &bounds) {
+            self.evicted.remove(&chunk.pos);
+            self.remesh.mark_chunk(chunk.pos);
+        }
+        self.remesh.collect_from_world(world);
+        let opaque: Arc<OpacityFn> = match self.art.as_ref() {
+            Some(art) => art.opaque().clone(),
+            None => Arc::new(|block| block != AIR)
+        };
+        self.mesh_jobs.extend(self.remesh.dispatch(&self.jobs, world, &opaque));
+        renderer.receive_meshes(&mut self.mesh_jobs);
+    }
+
+    /// Leave the world for the menu, keeping it on standby with its chunks compressed and nothing in it running, so
+    /// resume_world() can go back to it without loading. Only one world is kept, so one already on standby is
+    /// dropped. False if there's no world to leave.
+    /// ```
+    /// # use client::{app::App, settings::Settings};
+    /// # use shared::engine::{job::system::JobSystem, math::coords::{BlockPos, ChunkPos}, world::{chunk::Chunk, World}};
+    /// # use std::sync::Arc;
+    /// let mut app = App::new(Arc::new(JobSystem::new(1)), Settings::default());
+    /// let world = Arc::new(World::new());
+    /// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+    /// world.set_block(BlockPos::new(0, 1, 0), 3);
+    /// app.set_world(Some(world));
+    /// assert!(app.suspend_world().unwrap());
+    /// assert!(app.world().is_none() && app.has_standby_world());
+    /// assert!(app.resume_world().unwrap());
+    /// assert_eq!(app.world().unwrap().get_block(BlockPos::new(0, 1, 0)), Some(3));
+    /// assert!(!app.resume_world().unwrap());
+    /// ```
+    pub fn suspend_world(&mut self) -> io::Result<bool> {
+        let Some(world) = self.world.clone() else {
+            return Ok(false);
+        };
+        self.set_world(None);
+        self.standby = None;
+        let replay = self.replay.take();
+        let world = StandbyWorld::suspend(world, &self.jobs)?;
+        self.standby = Some(Standby { world, camera: self.camera.clone(), replay });
+        return Ok(true);
+    }
+
+    /// Go back to the world on standby, where the camera left it. False if there's none, leaving the current world.
+    pub fn resume_world(&mut self) -> io::Result<bool> {
+        let Some(standby) = self.standby.take() else {
+            return Ok(false);
+        };
+        let world = standby.world.resume(&self.jobs)?;
+        self.set_world(Some(world));
+        self.replay = standby.replay;
+        self.camera = standby.camera;
+        return Ok(true);
+    }
+
+    pub fn has_standby_world(&self) -> bool {
+        return self.standby.is_some();
+    }
+
+    /// The world being played in, if any.
+    pub fn world(&self) -> Option<&Arc<World>> {
+        return self.world.as_ref();
+    }
+
+    /// The error that stopped the app, if any.
+    pub fn take_error(&mut self) -> Option<Box<dyn std::error::Error>> {
+        return self.error.take();
+    }
+
+    fn fail(&mut self, event_loop: &ActiveEventLoop, error: Box<dyn std::error::Error>) {
+        self.error = Some(error);
+        event_loop.exit();
+    }
+
+    /// Capture or release the cursor. Locking it in place isn't supported everywhere, so confining it to the
+    /// window is the fallback.
+    fn capture_cursor(&mut self, capture: bool) {
+        let Some(renderer) = self.renderer.as_ref() else {
+            return;
+        };
+        let window = renderer.window();
+        let grabbed = match capture {
+            true => window.set_cursor_grab(CursorGrabMode::Locked).or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined)).is_ok(),
+            false => {
+                let _ = window.set_cursor_grab(CursorGrabMode::None);
+                false
+            }
+        };
+        window.set_cursor_visible(!grabbed);
+        self.captured = grabbed;
+    }
+
+    /// Commands the player can run from chat without a server.
+    fn commands() -> CommandDispatcher<App> {
+        let mut commands = CommandDispatcher::new();
+        commands.register("help")
+            .description("List the commands.")
+            .executes(|app: &mut App, context| {
+                let lines: Vec<String> = app.commands.commands(context.source).into_iter().map(|command| format!("/{} - {}", command.usage, command.description)).collect();
+                return Ok(lines.join("\n"));
+            }).expect("help is a valid command");
+        commands.register("tp")
+            .description("Move to a block.")
+            .argument("destination", ArgumentKind::Position)
+            .executes(|app: &mut App, context| {
+                let destination = context.position("destination").unwrap();
+                app.camera.set_position(destination.center());
+                return Ok(format!("teleported to {} {} {}", destination.x, destination.y, destination.z));
+            }).expect("tp is a valid command");
+        commands.register("fly")
+            .description("Switch between flying and walking.")
+            .executes(|app: &mut App, _| {
+                app.camera.toggle_mode();
+                return Ok(format!("now {:?}", app.camera.mode()).to_lowercase());
+            }).expect("fly is a valid command");
+        commands.register("fov")
+            .description("Set the field of view, in degrees.")
+            .argument("degrees", ArgumentKind::Integer { min: MIN_FOV as i64, max: MAX_FOV as i64 })
+            .executes(|app: &mut App, context| {
+                let degrees = context.integer("degrees").unwrap();
+                let mut settings = app.settings();
+                settings.video.fov = degrees as f32;
+                app.apply_settings(settings);
+                return Ok(format!("field of view set to {}", degrees));
+            }).expect("fov is a valid command");
+        commands.register("pause")
+            .description("Pause or resume the replay.")
+            .executes(|app: &mut App, _| {
+                let player = app.replay.as_mut().ok_or("no replay is playing")?.player_mut();
+                player.set_paused(!player.is_paused());
+                return Ok(String::from(if player.is_paused() { "replay paused" } else { "replay resumed" }));
+            }).expect("pause is a valid command");
+        commands.register("speed")
+            .description("Set how fast the replay plays, in percent.")
+            .argument("percent", ArgumentKind::Integer { min: (MIN_REPLAY_SPEED * 100.0).ceil() as i64, max: (MAX_REPLAY_SPEED * 100.0) as i64 })
+            .executes(|app: &mut App, context| {
+                let percent = context.integer("percent").unwrap();
+                app.replay.as_mut().ok_or("no replay is playing")?.player_mut().set_speed(percent as f64 / 100.0);
+                return Ok(format!("replay speed set to {}%", percent));
+            }).expect("speed is a valid command");
+        commands.register("seek")
+            .description("Jump to a point in the replay, in seconds from the start.")
+            .argument("seconds", ArgumentKind::Integer { min: 0, max: i64::MAX })
+            .executes(|app: &mut App, context| {
+                let seconds = context.integer("seconds").unwrap();
+                let replay = app.replay.as_mut().ok_or("no replay is playing")?;
+                let restarted = replay.seek(seconds as f64 * TICKS_PER_SECOND as f64).map_err(|error| error.to_string())?;
+                if restarted {
+                    let world = replay.world().clone();
+                    app.set_world(Some(world));
+                }
+                return Ok(format!("moved to {}s", seconds));
+            }).expect("seek is a valid command");
+        commands.register("menu")
+            .description("Leave the world for the menu, keeping it on standby to come back to.")
+            .executes(|app: &mut App, _| {
+                if !app.suspend_world().map_err(|error| error.to_string())? {
+                    return Err(String::from("not in a world"));
+                }
+                return Ok(String::from("left the world, /return to go back"));
+            }).expect("menu is a valid command");
+        commands.register("return")
+            .description("Go back to the world left for the menu.")
+            .executes(|app: &mut App, _| {
+                if !app.resume_world().map_err(|error| error.to_string())? {
+                    return Err(String::from("no world is on standby"));
+                }
+                return Ok(String::from("back in the world"));
+            }).expect("return is a valid command");
+        commands.register("clear")
+            .description("Clear the chat.")
+            .executes(|app: &mut App, _| {
+                app.chat.clear();
+                return Ok(String::new());
+            }).expect("clear is a valid command");
+        return commands;
+    }
+
+    /// Open the chat, letting go of the mouse and any keys held so the game stops moving.
+    fn open_chat(&mut self, text: &str) {
+        self.chat.open(text);
+        self.input.clear();
+        self.capture_cursor(false);
+    }
+
+    /// Run a command typed in chat, showing its output or why it failed. There's no server to send messages to,
+    /// so they're only shown locally.
+    fn submit_chat(&mut self, input: ChatInput) {
+        match input {
+            ChatInput::Message(text) => {
+                let spans = vec![TextSpan { text, ..TextSpan::default() }];
+                self.chat.push(ChatLine { kind: ChatKind::Player, sender: Some(PLAYER_NAME.to_string()), spans });
+            }
+            ChatInput::Command(line) => {
+                let source = CommandSource::player(0, PLAYER_NAME, self.camera.position().block()).with_permission(PermissionLevel::Owner);
+                let commands = self.commands.clone();
+                match commands.execute(self, &source, &line) {
+                    Ok(output) => {
+                        for line in output.lines() {
+                            self.chat.system(line);
+                        }
+                    }
+                    Err(error) => self.chat.system(&format!("{}c{}", FORMAT_CODE, error))
+                }
+            }
+        }
+    }
+
+    /// Play the recorded input given to with_input_playback() without a window, a frame of some seconds at a time
+    /// and as fast as it runs, such as to test the client. Returns the seconds played.
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use client::{app::App, input::{Binding, Bindings, InputMap}, recording::{InputPlayback, InputRecorder}, settings::Settings};
+    /// # use shared::engine::job::system::JobSystem;
+    /// # use winit::keyboard::KeyCode;
+    /// let mut input = InputMap::new(Bindings::defaults());
+    /// let mut recorder = InputRecorder::new(Vec::new()).unwrap();
+    /// input.set(Binding::Key(KeyCode::KeyW), 1.0);
+    /// recorder.record_frame(0.0, input.update()).unwrap();
+    /// input.set(Binding::Key(KeyCode::KeyW), 0.0);
+    /// recorder.record_frame(1.0, input.update()).unwrap();
+    /// let playback = InputPlayback::read(recorder.finish().unwrap().as_slice()).unwrap();
+    ///
+    /// let mut app = App::new(Arc::new(JobSystem::new(1)), Settings::default()).with_input_playback(playback);
+    /// let start = app.camera().position();
+    /// assert!(app.run_headless(0.05) >= 1.0);
+    /// // Walked forward for the second W was held.
+    /// assert!(app.camera().position().distance(start) > 1.0);
+    /// ```
+    pub fn run_headless(&mut self, frame_seconds: f32) -> f64 {
+        if self.playback.is_none() {
+            return 0.0;
+        }
+        while !self.exit_requested {
+            self.step(frame_seconds, Instant::now());
+        }
+        return self.elapsed;
+    }
+
+    /// Run a frame by the time since the last one.
+    fn update(&mut self) {
+        let now = Instant::now();
+        let seconds = self.last_frame.map_or(0.0, |last| (now - last).as_secs_f32());
+        self.last_frame = Some(now);
+        self.step(seconds, now);
+    }
+
+    /// This frame's actions, from the player or the recording being played, recorded if recording.
+    fn frame_input(&mut self, seconds: f32) -> InputState {
+        let mut state = self.input.update().clone();
+        if let Some((played, inputs)) = self.playback.as_mut().map(|playback| playback.advance(seconds as f64)) {
+            state = played;
+            for input in inputs {
+                self.play_input(input);
+            }
+            if self.playback.as_ref().is_some_and(InputPlayback::is_finished) {
+                self.exit_requested = true;
+            }
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(error) = recorder.record_frame(self.elapsed, &state).and_then(|_| recorder.flush()) {
+                eprintln!("stopped recording input: {}", error);
+                self.recorder = None;
+            }
+        }
+        return state;
+    }
+
+    /// Do what a recorded input did when it was recorded.
+    fn play_input(&mut self, input: RecordedInput) {
+        match input {
+            RecordedInput::Frame(_) => {}
+            RecordedInput::Key { key, text } => self.type_key(&key, text.as_deref()),
+            RecordedInput::Resized { width, height } => {
+                // The window resizing comes back as a resize event, like the player resizing it.
+                match self.renderer.as_ref() {
+                    Some(renderer) => {
+                        let _ = renderer.window().request_inner_size(PhysicalSize::new(width, height));
+                    }
+                    None => self.camera.set_aspect(width, height)
+                }
+            }
+            RecordedInput::Focused(focused) => {
+                if !focused {
+                    self.capture_cursor(false);
+                }
+            }
+            RecordedInput::CloseRequested => self.exit_requested = true
+        }
+    }
+
+    /// Add a window event to the input recording, if it's one that's played back.
+    fn record_window_event(&mut self, event: &WindowEvent) {
+        let Some(recorder) = self.recorder.as_mut() else {
+            return;
+        };
+        let input = match event {
+            WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed => {
+                RecordedInput::Key { key: event.logical_key.clone(), text: event.text.as_ref().map(|text| text.to_string()) }
+            }
+            WindowEvent::Resized(size) => RecordedInput::Resized { width: size.width, height: size.height },
+            WindowEvent::Focused(focused) => RecordedInput::Focused(*focused),
+            WindowEvent::CloseRequested => RecordedInput::CloseRequested,
+            _ => return
+        };
+        if let Err(error) = recorder.record(self.elapsed, input) {
+            eprintln!("stopped recording input: {}", error);
+            self.recorder = None;
+        }
+    }
+
+    /// Type a key into the sign editor or chat, whichever is open.
+    fn type_key(&mut self, key: &Key, text: Option<&str>) {
+        if self.sign_editor.is_open() {
+            self.sign_editor.key(key);
+            if let Some(text) = text {
+                self.sign_editor.type_text(text);
+            }
+        } else if self.chat.is_open() {
+            let sent = self.chat.key(key);
+            if let Some(text) = text {
+                self.chat.type_text(text);
+            }
+            if let Some(input) = sent {
+                self.submit_chat(input);
+            }
+        }
+    }
+
+    /// Give keys and the mouse wheel to the sign editor or chat while one is open, instead of the game.
+    /// Returns whether the event was theirs.
+    fn route_to_editors(&mut self, event: &WindowEvent) -> bool {
+        if let WindowEvent::KeyboardInput { event, .. } = event {
+            if self.sign_editor.is_open() || self.chat.is_open() {
+                if event.state == ElementState::Pressed {
+                    self.type_key(&event.logical_key, event.text.as_deref());
+                }
+                return true;
+            }
+        }
+        if let WindowEvent::MouseWheel { delta, .. } = event {
+            if self.chat.is_open() {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y as f64,
+                    MouseScrollDelta::PixelDelta(position) => position.y / PIXELS_PER_LINE
+                };
+                self.chat.scroll(lines.round() as isize);
+                return true;
+            }
+        }
+        return false;
+    }
+
+    /// Apply a frame's input, move the camera by the frame's time, and give the renderer the new view.
+    fn step(&mut self, seconds: f32, now: Instant) {
+        if seconds > 0.0 {
+            self.debug.record_frame(seconds);
+        }
+        self.elapsed += seconds as f64;
+        let state = self.frame_input(seconds);
+        if state.pressed(input::RELEASE_CURSOR) {
+            self.capture_cursor(false);
+        } else if state.pressed(input::BREAK_BLOCK) && !self.captured {
+            self.capture_cursor(true);
+        }
+        if state.pressed(input::TOGGLE_FLY) {
+            self.camera.toggle_mode();
+        }
+        if state.pressed(input::OPEN_CHAT) {
+            self.open_chat("");
+        } else if state.pressed(input::OPEN_COMMAND) {
+            self.open_chat("/");
+        }
+        if state.pressed(input::TOGGLE_DEBUG) {
+            self.debug.toggle();
+        }
+        if state.pressed(input::SCREENSHOT) {
+            if let Some(Err(error)) = self.renderer.as_mut().map(|renderer| renderer.request_screenshot()) {
+                eprintln!("no screenshot: {}", error);
+            }
+        }
+        if self.captured {
+            let (dx, dy) = state.mouse_delta();
+            self.camera.look(dx, dy);
+        }
+        let stick = STICK_LOOK_SPEED * seconds;
+        let yaw = self.camera.yaw() - state.axis(input::LOOK_RIGHT, input::LOOK_LEFT) * stick;
+        self.camera.set_rotation(yaw, self.camera.pitch() + state.axis(input::LOOK_UP, input::LOOK_DOWN) * stick);
+        let movement = Vec3::new(
+            state.axis(input::MOVE_RIGHT, input::MOVE_LEFT),
+            state.axis(input::JUMP, input::SNEAK),
+            state.axis(input::MOVE_FORWARD, input::MOVE_BACK)
+        );
+        let before = self.camera.position();
+        self.camera.update(movement, seconds);
+        self.update_replay(seconds);
+        self.reload_assets(seconds);
+        self.update_audio(before);
+        if let Some(ambience) = self.ambience.as_mut() {
+            ambience.update(seconds, self.camera.position(), self.world.as_deref(), self.audio.as_mut(), &mut self.particles);
+        }
+        if let Some(world) = self.world.as_ref() {
+            self.targeting.update(world, &self.camera, None);
+        }
+        self.particles.set_viewer(self.camera.position());
+        self.particles.update(seconds, self.world.as_ref());
+        if let Some(world) = self.world.as_ref() {
+            self.light_probes.lock().unwrap().update_from_world(PROBE_BUDGET, world);
+        }
+        let frames = self.renderer.as_mut().map_or(Vec::new(), |renderer| renderer.captured_frames());
+        self.save_screenshots(frames);
+        self.update_meshes();
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        // Always noon without a world to take the time from.
+        let time = self.world.as_ref().map_or(WorldTime::new(NOON), |world| world.world_time());
+        let sky = SkyState::at(time);
+        renderer.set_view(self.camera.chunk_view(sky.ambient, sky.fog(self.settings.video.fog_distance())));
+        let evicted = renderer.evict_meshes(&self.memory, self.camera.position().chunk());
+        self.evicted.extend(evicted);
+        renderer.set_sky(sky);
+        renderer.set_outline(self.targeting.outline(self.camera.origin()));
+        let particles = self.particles.instances(self.camera.origin(), &self.light_probes.lock().unwrap(), time.daylight());
+        renderer.set_particles(particles, &self.camera);
+        let size = renderer.size();
+        if let Some(world) = self.world.as_ref() {
+            signs::draw_signs(renderer.text_mut(), size, world, &self.camera);
+        }
+        self.sign_editor.draw(renderer.text_mut(), size);
+        self.chat.draw(renderer.text_mut(), size, now);
+        if self.debug.is_visible() {
+            let info = DebugInfo {
+                position: self.camera.position(),
+                yaw: self.camera.yaw().to_degrees(),
+                pitch: self.camera.pitch().to_degrees(),
+                mode: self.camera.mode(),
+                chunks: renderer.chunks().len(),
+                cull: renderer.chunks().cull_stats(),
+                jobs: self.jobs.debug_dump(),
+                mesh_memory: renderer.chunks().memory_used(),
+                mesh_budget: self.memory.budget(MemoryCategory::Meshes),
+                process_memory: debug_overlay::process_memory()
+            };
+            self.debug.draw(renderer.text_mut(), &info);
+        }
+    }
+
+    /// Save frames captured for screenshots, and say where the ones that finished saving went.
+    fn save_screenshots(&mut self, frames: Vec<Result<Texture, ScreenshotError>>) {
+        for frame in frames {
+            match frame {
+                Ok(image) => self.screenshots.save(image, SystemTime::now()),
+                Err(error) => eprintln!("no screenshot: {}", error)
+            }
+        }
+        for saved in self.screenshots.finished() {
+            match saved {
+                Ok(path) => println!("saved screenshot to {}", path.display()),
+                Err(error) => eprintln!("no screenshot: {}", error)
+            }
+        }
+    }
+
+    /// Play the replay on by the frame's time. A replay that can't be read any further is stopped, leaving the
+    /// world as far as it got.
+    fn update_replay(&mut self, seconds: f32) {
+        let Some(replay) = self.replay.as_mut() else {
+            return;
+        };
+        if let Err(error) = replay.update(seconds as f64) {
+            self.replay = None;
+            self.chat.system(&format!("{}creplay stopped: {}", FORMAT_CODE, error));
+            return;
+        }
+        for mismatch in replay.take_mismatches() {
+            self.chat.system(&format!("{}c{}", FORMAT_CODE, mismatch));
+        }
+    }
+
+    /// Hear from the camera, play a footstep every STEP_LENGTH blocks walked, and start sounds that loaded.
+    fn update_audio(&mut self, before: WorldPos) {
+        let Some(audio) = self.audio.as_mut() else {
+            return;
+        };
+        audio.set_listener(Listener::from_camera(&self.camera));
+        let position = self.camera.position();
+        if self.camera.mode() == CameraMode::Walk {
+            let moved = position - before;
+            self.walked += (moved.x * moved.x + moved.z * moved.z).sqrt();
+            if self.walked >= STEP_LENGTH {
+                self.walked -= STEP_LENGTH;
+                audio.play_event(SoundEvent::Footstep, position - WorldPos::new(0.0, EYE_HEIGHT, 0.0));
+            }
+        }
+        audio.update();
+    }
+}
+
+impl CommandEnvironment for App {
+    fn player_names(&self) -> Vec<String> {
+        return vec![PLAYER_NAME.to_string()];
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.renderer.is_some() {
+            return;
+        }
+        let fullscreen = self.settings.video.fullscreen.then_some(Fullscreen::Borderless(None));
+        let window = match event_loop.create_window(Window::default_attributes().with_title(WINDOW_TITLE).with_fullscreen(fullscreen)) {
+            Ok(window) => Arc::new(window),
+            Err(error) => return self.fail(event_loop, Box::new(error))
+        };
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_with_display_handle_from_env(Box::new(event_loop.owned_display_handle())));
+        let size = window.inner_size();
+        self.camera.set_aspect(size.width, size.height);
+        match Renderer::new(instance, window, self.jobs.clone()) {
+            Ok(mut renderer) => {
+                renderer.set_vsync(self.settings.video.vsync);
+                if let Some(art) = self.art.as_ref() {
+                    renderer.set_atlas(art.atlas());
+                }
+                self.renderer = Some(renderer);
+            }
+            Err(error) => return self.fail(event_loop, Box::new(error))
+        }
+        event_loop.set_control_flow(ControlFlow::Poll);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window: WindowId, event: WindowEvent) {
+        self.record_window_event(&event);
+        // While input is played back, the player's keys and mouse are ignored so they can't change what happens.
+        if self.playback.is_none() {
+            if self.route_to_editors(&event) {
+                return;
+            }
+            self.input.handle_window_event(&event);
+        }
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => {
+                renderer.resize(size);
+                self.camera.set_aspect(size.width, size.height);
+            }
+            WindowEvent::Focused(false) => self.capture_cursor(false),
+            WindowEvent::RedrawRequested => {
+                self.update();
+                if self.exit_requested {
+                    return event_loop.exit();
+                }
+                if let Err(error) = self.renderer.as_mut().unwrap().render() {
+                    self.fail(event_loop, Box::new(error));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Raw mouse motion turns the camera, as it keeps coming while the cursor is locked in place.
+    fn device_event(&mut self, _event_loop: &ActiveEventLoop, _device: DeviceId, event: DeviceEvent) {
+        if self.playback.is_none() {
+            self.input.handle_device_event(&event);
+        }
+    }
+
+    /// Draw continuously, asking for the next frame once every event has been handled.
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(renderer) = self.renderer.as_ref() {
+            renderer.window().request_redraw();
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use shared::engine::{
+    asset::{handle::AssetError, manager::AssetManager, model::Model, texture::Texture},
+    block::BlockId,
+    math::direction::Direction,
+    mesh::texture::{FaceTextures, UvRect}
+};
+
+/// Pixels each texture is surrounded by, copied from its edges, so filtering and smaller mips don't bleed
+/// neighbouring textures into it.
+pub const DEFAULT_PADDING: u32 = 4;
+/// Size of the texture drawn in place of ones that are missing or failed to load.
+pub const MISSING_TEXTURE_SIZE: u32 = 16;
+
+/// Magenta and black checkers, so missing textures stand out.
+fn missing_texture() -> Texture {
+    let mut pixels = Vec::with_capacity((MISSING_TEXTURE_SIZE * MISSING_TEXTURE_SIZE * 4) as usize);
+    for y in 0..MISSING_TEXTURE_SIZE {
+        for x in 0..MISSING_TEXTURE_SIZE {
+            let magenta = ((x / 8) + (y / 8)) % 2 == 0;
+            pixels.extend_from_slice(if magenta { &[255, 0, 255, 255] } else { &[0, 0, 0, 255] });
+        }
+    }
+    return Texture { width: MISSING_TEXTURE_SIZE, height: MISSING_TEXTURE_SIZE, pixels };
+}
+
+fn align(value: u32, alignment: u32) -> u32 {
+    return value.div_ceil(alignment) * alignment;
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+    return if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) };
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = if value <= 0.003_130_8 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 };
+    return (value * 255.0).round().clamp(0.0, 255.0) as u8;
+}
+
+/// Half size mip of RGBA pixels, averaging each 2x2 block. Color is averaged in linear space, as the atlas is sRGB.
+fn downsample(pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (half_width, half_height) = (width / 2, height / 2);
+    let mut mip = Vec::with_capacity((half_width * half_height * 4) as usize);
+    for y in 0..half_height {
+        for x in 0..half_width {
+            let texels = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| (((y * 2 + dy) * width + x * 2 + dx) * 4) as usize);
+            for channel in 0..3 {
+                let sum: f32 = texels.iter().map(|texel| srgb_to_linear(pixels[texel + channel])).sum();
+                mip.push(linear_to_srgb(sum / 4.0));
+            }
+            let alpha: u32 = texels.iter().map(|texel| pixels[texel + 3] as u32).sum();
+            mip.push(((alpha + 2) / 4) as u8);
+        }
+    }
+    return mip;
+}
+
+/// Packs block textures into one atlas, so chunks draw with a single texture, and meshes find their faces'
+/// textures by UV rect. A missing texture is always included, for faces whose texture isn't in the atlas.
+/// ```
+/// # use client::atlas::AtlasBuilder;
+/// # use shared::engine::asset::texture::Texture;
+/// let stone = Texture { width: 16, height: 16, pixels: vec![128; 16 * 16 * 4] };
+/// let mut builder = AtlasBuilder::new();
+/// builder.add("blocks/stone", stone);
+/// let atlas = builder.build();
+/// let rect = atlas.rect("blocks/stone");
+/// assert_ne!(rect, atlas.missing());
+/// assert_eq!(rect.size, [16.0 / atlas.width as f32, 16.0 / atlas.height as f32]);
+/// assert_eq!(atlas.rect("blocks/dirt"), atlas.missing());
+/// // Mips go down until the padding is a single texel.
+/// assert_eq!(atlas.mips.len(), 3);
+/// ```
+pub struct AtlasBuilder {
+    textures: Vec<(String, Texture)>,
+    padding: u32
+}
+
+impl AtlasBuilder {
+    pub fn new() -> AtlasBuilder {
+        return AtlasBuilder { textures: Vec::new(), padding: DEFAULT_PADDING };
+    }
+
+    /// Pixels of padding around each texture, rounded up to a power of two. More padding allows more mips,
+    /// as each mip halves it. No padding means no mips.
+    pub fn with_padding(mut self, padding: u32) -> AtlasBuilder {
+        self.padding = if padding == 0 { 0 } else { padding.next_power_of_two() };
+        return self;
+    }
+
+    /// Add a texture, replacing one added with the same name.
+    pub fn add(&mut self, name: &str, texture: Texture) {
+        self.textures.retain(|(other, _)| other != name);
+        self.textures.push((name.to_string(), texture));
+    }
+
+    pub fn len(&self) -> usize {
+        return self.textures.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.textures.is_empty();
+    }
+
+    /// Mip levels the padding allows, including the full size.
+    fn mip_levels(&self) -> u32 {
+        return if self.padding == 0 { 1 } else { self.padding.trailing_zeros() + 1 };
+    }
+
+    /// Pack the textures in rows from tallest to shortest. Each cell's size is a multiple of the smallest mip's
+    /// texel, so texture edges line up with texels on every mip.
+    pub fn build(self) -> TextureAtlas {
+        let padding = self.padding;
+        let mip_levels = self.mip_levels();
+        let alignment = 1 << (mip_levels - 1);
+        let mut textures: Vec<(Option<String>, Texture)> = self.textures.into_iter().map(|(name, texture)| (Some(name), texture)).collect();
+        textures.push((None, missing_texture()));
+        textures.sort_by_key(|(_, texture)| std::cmp::Reverse(texture.height));
+
+        let cell = |texture: &Texture| (align(texture.width + padding * 2, alignment), align(texture.height + padding * 2, alignment));
+        let area: u32 = textures.iter().map(|(_, texture)| cell(texture).0 * cell(texture).1).sum();
+        let widest = textures.iter().map(|(_, texture)| cell(texture).0).max().unwrap();
+        let width = widest.max(area.isqrt()).next_power_of_two();
+        let mut positions = Vec::with_capacity(textures.len());
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        for (_, texture) in textures.iter() {
+            let (cell_width, cell_height) = cell(texture);
+            if x + cell_width > width {
+                (x, y, row_height) = (0, y + row_height, 0);
+            }
+            positions.push((x, y));
+            x += cell_width;
+            row_height = row_height.max(cell_height);
+        }
+        let height = (y + row_height).next_power_of_two();
+
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let mut rects = HashMap::new();
+        let mut missing = UvRect::FULL;
+        for ((name, texture), (x, y)) in textures.iter().zip(positions) {
+            let (cell_width, cell_height) = cell(texture);
+            // Padding repeats the nearest edge pixel.
+            for cell_y in 0..cell_height {
+                let source_y = cell_y.saturating_sub(padding).min(texture.height - 1);
+                for cell_x in 0..cell_width {
+                    let source_x = cell_x.saturating_sub(padding).min(texture.width - 1);
+                    let index = (((y + cell_y) * width + x + cell_x) * 4) as usize;
+                    pixels[index..index + 4].copy_from_slice(&texture.pixel(source_x, source_y));
+                }
+            }
+            let rect = UvRect::from_pixels([x + padding, y + padding], [texture.width, texture.height], [width, height]);
+            match name {
+                Some(name) => { rects.insert(name.clone(), rect); },
+                None => missing = rect
+            }
+        }
+
+        let mut mips = vec![pixels];
+        for level in 1..mip_levels {
+            let mip = downsample(mips.last().unwrap(), width >> (level - 1), height >> (level - 1));
+            mips.push(mip);
+        }
+        return TextureAtlas { width, height, mips, rects, missing };
+    }
+}
+
+impl Default for AtlasBuilder {
+    fn default() -> Self {
+        return AtlasBuilder::new();
+    }
+}
+
+/// Block textures packed into one image by an AtlasBuilder, ready to upload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureAtlas {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA pixels of each mip, from the full size down.
+    pub mips: Vec<Vec<u8>>,
+    rects: HashMap<String, UvRect>,
+    missing: UvRect
+}
+
+impl TextureAtlas {
+    /// Where a texture is in the atlas, or the missing texture if it wasn't added.
+    pub fn rect(&self, name: &str) -> UvRect {
+        return self.rects.get(name).copied().unwrap_or(self.missing);
+    }
+
+    pub fn missing(&self) -> UvRect {
+        return self.missing;
+    }
+
+    /// Rects of each face of blocks, from the first element of their models, for the mesher.
+    /// ```
+    /// # use client::atlas::AtlasBuilder;
+    /// # use shared::engine::asset::{manager::Asset, model::Model, texture::Texture};
+    /// # use shared::engine::math::direction::Direction;
+    /// let model = Model::decode(br#"{
+    ///     "textures": { "top": "blocks/grass_top" },
+    ///     "elements": [{ "from": [0, 0, 0], "to": [16, 16, 16], "faces": { "PosY": "top" } }]
+    /// }"#).unwrap();
+    /// let mut builder = AtlasBuilder::new();
+    /// builder.add("blocks/grass_top", Texture { width: 16, height: 16, pixels: vec![64; 16 * 16 * 4] });
+    /// let atlas = builder.build();
+    /// let textures = atlas.face_textures(&[(3, &model)]);
+    /// assert_eq!(textures.get(3, Direction::PosY), atlas.rect("blocks/grass_top"));
+    /// assert_eq!(textures.get(3, Direction::NegY), atlas.missing());
+    /// ```
+    pub fn face_textures(&self, blocks: &[(BlockId, &Model)]) -> FaceTextures {
+        let mut textures = FaceTextures::new(self.missing);
+        for (block, model) in blocks {
+            for face in Direction::ALL {
+                if let Some(name) = model.face_texture(0, face) {
+                    textures.set(*block, face, self.rect(name));
+                }
+            }
+        }
+        return textures;
+    }
+}
+
+/// Load every texture used by some models and pack them into an atlas. Textures that fail to load are left out,
+/// so they draw as missing, and their errors returned.
+pub fn load_atlas(assets: &AssetManager, models: &[&Model]) -> (TextureAtlas, Vec<AssetError>) {
+    let mut names: Vec<&str> = models.iter().flat_map(|model| model.textures.values().map(String::as_str)).collect();
+    names.sort_unstable();
+    names.dedup();
+    // Request everything before waiting, so the textures load in parallel.
+    let handles: Vec<_> = names.iter().map(|name| (*name, assets.load::<Texture>(name))).collect();
+    let mut builder = AtlasBuilder::new();
+    let mut errors = Vec::new();
+    for (name, handle) in handles {
+        match handle.wait() {
+            Ok(texture) => builder.add(name, (*texture).clone()),
+            Err(error) => errors.push(error)
+        }
+    }
+    return (builder.build(), errors);
+}
\ No newline at end of file
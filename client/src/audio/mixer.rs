@@ -0,0 +1,318 @@
+use std::{f32::consts::FRAC_PI_4, sync::Arc};
+
+use serde::{Serialize, Deserialize};
+use shared::engine::math::{coords::WorldPos, vector::Vec3};
+
+use crate::camera::Camera;
+
+use super::sound::Sound;
+
+/// The mixer always mixes to stereo, which the output spreads over however many channels the device has.
+pub const OUTPUT_CHANNELS: usize = 2;
+/// Blocks from the listener within which sounds play at full volume.
+pub const REFERENCE_DISTANCE: f32 = 1.0;
+/// Blocks from the listener sounds in the world fade out by.
+pub const DEFAULT_MAX_DISTANCE: f32 = 32.0;
+/// Sounds that play at once. Starting another replaces the one closest to finishing.
+pub const MAX_VOICES: usize = 64;
+
+/// What a sound is for, each with its own volume.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SoundCategory {
+    /// Blocks breaking and being placed.
+    Blocks,
+    Footsteps,
+    Ambient,
+    /// Menus and the HUD, which are never placed in the world.
+    Interface
+}
+
+/// Volume of each category of sound, from 0 to 1, all scaled by the master volume.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Volumes {
+    pub master: f32,
+    pub blocks: f32,
+    pub footsteps: f32,
+    pub ambient: f32,
+    pub interface: f32
+}
+
+impl Volumes {
+    /// Volume a category plays at, including the master volume.
+    pub fn get(&self, category: SoundCategory) -> f32 {
+        let volume = match category {
+            SoundCategory::Blocks => self.blocks,
+            SoundCategory::Footsteps => self.footsteps,
+            SoundCategory::Ambient => self.ambient,
+            SoundCategory::Interface => self.interface
+        };
+        return (volume * self.master).clamp(0.0, 1.0);
+    }
+}
+
+impl Default for Volumes {
+    fn default() -> Self {
+        return Volumes { master: 1.0, blocks: 1.0, footsteps: 1.0, ambient: 1.0, interface: 1.0 };
+    }
+}
+
+/// Where sounds are heard from, normally the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Listener {
+    pub position: WorldPos,
+    /// Unit vector to the listener's right, which the right speaker plays sounds from.
+    pub right: Vec3
+}
+
+impl Listener {
+    pub fn from_camera(camera: &Camera) -> Listener {
+        return Listener { position: camera.position(), right: camera.right() };
+    }
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        return Listener { position: WorldPos::ORIGIN, right: Vec3::X };
+    }
+}
+
+/// Volume of a sound some distance away. Full within the reference distance, then falling off with distance, and
+/// fading to nothing at the max distance.
+/// ```
+/// # use client::audio::mixer::attenuation;
+/// assert_eq!(attenuation(0.5, 32.0), 1.0);
+/// assert!(attenuation(4.0, 32.0) < attenuation(2.0, 32.0));
+/// assert_eq!(attenuation(32.0, 32.0), 0.0);
+/// ```
+pub fn attenuation(distance: f32, max_distance: f32) -> f32 {
+    let falloff = REFERENCE_DISTANCE / distance.max(REFERENCE_DISTANCE);
+    let fade = 1.0 - ((distance - REFERENCE_DISTANCE) / (max_distance - REFERENCE_DISTANCE)).clamp(0.0, 1.0);
+    return falloff * fade;
+}
+
+/// Left and right gains of a sound in a direction from the listener, panned so its total power stays the same.
+/// ```
+/// # use client::audio::mixer::pan;
+/// # use shared::engine::math::vector::Vec3;
+/// let [left, right] = pan(Vec3::X, Vec3::X);
+/// assert!(left.abs() < 1e-6 && (right - 1.0).abs() < 1e-6);
+/// let [left, right] = pan(Vec3::new(0.0, 0.0, -1.0), Vec3::X);
+/// assert!((left - right).abs() < 1e-6);
+/// ```
+pub fn pan(direction: Vec3, right: Vec3) -> [f32; 2] {
+    let side = direction.normalize_or_zero().dot(right).clamp(-1.0, 1.0);
+    let angle = (side + 1.0) * FRAC_PI_4;
+    return [angle.cos(), angle.sin()];
+}
+
+/// A sound to play, and how.
+#[derive(Clone, Debug)]
+pub struct PlaySound {
+    pub sound: Arc<Sound>,
+    pub category: SoundCategory,
+    /// Where in the world it plays from, or None to play evenly from both speakers, such as for the interface.
+    pub position: Option<WorldPos>,
+    pub volume: f32,
+    /// Speed it plays at, which also raises or lowers it.
+    pub pitch: f32,
+    /// Blocks away it can no longer be heard from.
+    pub max_distance: f32,
+    /// Whether it starts again from the beginning when it ends, playing until stopped.
+    pub looping: bool
+}
+
+impl PlaySound {
+    pub fn new(sound: Arc<Sound>, category: SoundCategory) -> PlaySound {
+        return PlaySound { sound, category, position: None, volume: 1.0, pitch: 1.0, max_distance: DEFAULT_MAX_DISTANCE, looping: false };
+    }
+
+    pub fn at(mut self, position: WorldPos) -> Self {
+        self.position = Some(position);
+        return self;
+    }
+
+    pub fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        return self;
+    }
+
+    pub fn with_pitch(mut self, pitch: f32) -> Self {
+        self.pitch = pitch;
+        return self;
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        return self;
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        return self;
+    }
+}
+
+/// Handle to a playing sound, to stop it early.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VoiceId(u64);
+
+struct Voice {
+    id: VoiceId,
+    play: PlaySound,
+    /// Position in the sound's samples.
+    cursor: f64
+}
+
+impl Voice {
+    /// Samples left to play. Loops never run out, unless they have no samples at all.
+    fn remaining(&self) -> f64 {
+        let length = self.play.sound.samples.len() as f64;
+        if self.play.looping && length > 0.0 {
+            return f64::INFINITY;
+        }
+        return length - self.cursor;
+    }
+}
+
+/// Mixes the sounds playing into stereo samples for the output. Sounds in the world are quieter the further they are
+/// from the listener, and panned towards the side they're on. Shared with the output's callback behind a mutex.
+/// ```
+/// # use std::sync::Arc;
+/// # use client::audio::{mixer::{Listener, Mixer, PlaySound, SoundCategory}, sound::Sound};
+/// # use shared::engine::math::{coords::WorldPos, vector::Vec3};
+/// let tone = Arc::new(Sound { sample_rate: 100, samples: vec![1.0; 100] });
+/// let mut mixer = Mixer::new(100);
+/// mixer.set_listener(Listener { position: WorldPos::ORIGIN, right: Vec3::X });
+/// mixer.play(PlaySound::new(tone.clone(), SoundCategory::Blocks).at(WorldPos::new(4.0, 0.0, 0.0)));
+/// let mut output = [0.0; 20];
+/// mixer.mix(&mut output);
+/// // Off to the right, and quieter than a sound right next to the listener.
+/// assert!(output[1] > output[0]);
+/// assert!(output[1] < 1.0);
+/// // Sounds finish once every sample has been played.
+/// mixer.mix(&mut vec![0.0; 200]);
+/// assert_eq!(mixer.voice_count(), 0);
+/// ```
+pub struct Mixer {
+    sample_rate: u32,
+    voices: Vec<Voice>,
+    listener: Listener,
+    volumes: Volumes,
+    next_id: u64
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Mixer {
+        return Mixer { sample_rate, voices: Vec::new(), listener: Listener::default(), volumes: Volumes::default(), next_id: 0 };
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        return self.sample_rate;
+    }
+
+    /// Match the output device's sample rate. Sounds are resampled to it as they're mixed.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+    }
+
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+    }
+
+    pub fn volumes(&self) -> Volumes {
+        return self.volumes;
+    }
+
+    pub fn set_volumes(&mut self, volumes: Volumes) {
+        self.volumes = volumes;
+    }
+
+    /// Sounds playing.
+    pub fn voice_count(&self) -> usize {
+        return self.voices.len();
+    }
+
+    pub fn play(&mut self, play: PlaySound) -> VoiceId {
+        if self.voices.len() >= MAX_VOICES {
+            let (closest, _) = self.voices.iter().enumerate().min_by(|(_, a), (_, b)| a.remaining().total_cmp(&b.remaining())).unwrap();
+            self.voices.swap_remove(closest);
+        }
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+        self.voices.push(Voice { id, play, cursor: 0.0 });
+        return id;
+    }
+
+    /// Stop a sound. False if it already finished.
+    pub fn stop(&mut self, id: VoiceId) -> bool {
+        let count = self.voices.len();
+        self.voices.retain(|voice| voice.id != id);
+        return self.voices.len() != count;
+    }
+
+    /// Change how loud a playing sound is, such as to fade a loop in or out. False if it already finished.
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use client::audio::{mixer::{Mixer, PlaySound, SoundCategory}, sound::Sound};
+    /// let tone = Arc::new(Sound { sample_rate: 100, samples: vec![1.0; 10] });
+    /// let mut mixer = Mixer::new(100);
+    /// let wind = mixer.play(PlaySound::new(tone, SoundCategory::Ambient).looping());
+    /// let mut output = [0.0; 200];
+    /// mixer.mix(&mut output);
+    /// // Still playing after ten times its length.
+    /// assert_eq!((output[198], mixer.voice_count()), (1.0, 1));
+    /// assert!(mixer.set_volume(wind, 0.5));
+    /// mixer.mix(&mut output);
+    /// assert_eq!(output[0], 0.5);
+    /// ```
+    pub fn set_volume(&mut self, id: VoiceId, volume: f32) -> bool {
+        let Some(voice) = self.voices.iter_mut().find(|voice| voice.id == id) else {
+            return false;
+        };
+        voice.play.volume = volume;
+        return true;
+    }
+
+    pub fn stop_all(&mut self) {
+        self.voices.clear();
+    }
+
+    /// Left and right gains of a voice, from its volume, category, and where it is.
+    fn gains(&self, play: &PlaySound) -> [f32; 2] {
+        let volume = play.volume * self.volumes.get(play.category);
+        let Some(position) = play.position else {
+            return [volume; 2];
+        };
+        let offset = position - self.listener.position;
+        let direction = Vec3::new(offset.x as f32, offset.y as f32, offset.z as f32);
+        let gain = volume * attenuation(direction.length(), play.max_distance);
+        return pan(direction, self.listener.right).map(|side| side * gain);
+    }
+
+    /// Fill interleaved stereo samples with the sounds playing, moving them on, and dropping the ones that finished.
+    pub fn mix(&mut self, output: &mut [f32]) {
+        output.fill(0.0);
+        for index in 0..self.voices.len() {
+            let gains = self.gains(&self.voices[index].play);
+            let voice = &mut self.voices[index];
+            let step = voice.play.sound.sample_rate as f64 / self.sample_rate as f64 * voice.play.pitch as f64;
+            for frame in output.chunks_exact_mut(OUTPUT_CHANNELS) {
+                if voice.remaining() <= 0.0 {
+                    break;
+                }
+                let sample = voice.play.sound.sample(voice.cursor);
+                frame[0] += sample * gains[0];
+                frame[1] += sample * gains[1];
+                voice.cursor += step;
+                if voice.play.looping {
+                    voice.cursor %= voice.play.sound.samples.len() as f64;
+                }
+            }
+        }
+        self.voices.retain(|voice| voice.remaining() > 0.0);
+        for sample in output.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,175 @@
+pub mod mixer;
+pub mod output;
+pub mod sound;
+
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use shared::engine::{
+    asset::{handle::{AssetState, Handle}, AssetManager},
+    math::{coords::WorldPos, rng::WorldRng}
+};
+
+use mixer::{Listener, Mixer, PlaySound, SoundCategory, Volumes};
+use output::{AudioError, AudioOutput};
+use sound::Sound;
+
+/// Sample rate the mixer starts at, until an output device gives it its own.
+pub const DEFAULT_SAMPLE_RATE: u32 = 48000;
+/// How far the pitch of event sounds is randomly raised or lowered, so repeated sounds don't all sound the same.
+pub const PITCH_VARIATION: f32 = 0.1;
+
+/// Something happening in the game that makes a sound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEvent {
+    BlockBreak,
+    BlockPlace,
+    Footstep
+}
+
+impl SoundEvent {
+    pub const ALL: [SoundEvent; 3] = [SoundEvent::BlockBreak, SoundEvent::BlockPlace, SoundEvent::Footstep];
+
+    /// Name of the sound asset played for the event.
+    pub fn sound(&self) -> &'static str {
+        return match self {
+            SoundEvent::BlockBreak => "blocks/break",
+            SoundEvent::BlockPlace => "blocks/place",
+            SoundEvent::Footstep => "steps/step"
+        };
+    }
+
+    pub fn category(&self) -> SoundCategory {
+        return match self {
+            SoundEvent::BlockBreak | SoundEvent::BlockPlace => SoundCategory::Blocks,
+            SoundEvent::Footstep => SoundCategory::Footsteps
+        };
+    }
+}
+
+/// A sound asked to play before it finished loading.
+struct PendingSound {
+    handle: Handle<Sound>,
+    category: SoundCategory,
+    position: Option<WorldPos>,
+    pitch: f32
+}
+
+/// The client's sound. Sounds are loaded by the asset manager, so decoding happens on its IO jobs, and play once
+/// they're loaded, mixed relative to the listener by a Mixer that an AudioOutput plays on the output device.
+/// Without an output, sounds are still loaded and mixed, but nothing is heard.
+/// ```
+/// # use std::sync::Arc;
+/// # use client::audio::{Audio, SoundEvent};
+/// # use shared::engine::{asset::AssetManager, job::system::JobSystem, math::coords::WorldPos};
+/// let directory = std::env::temp_dir().join(format!("audio_doctest_{}", std::process::id()));
+/// std::fs::create_dir_all(directory.join("sounds/blocks")).unwrap();
+/// let spec = hound::WavSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+/// let mut writer = hound::WavWriter::create(directory.join("sounds/blocks/break.wav"), spec).unwrap();
+/// (0..800).for_each(|_| writer.write_sample(1000i16).unwrap());
+/// writer.finalize().unwrap();
+///
+/// let mut audio = Audio::new(Arc::new(AssetManager::new(&directory, Arc::new(JobSystem::new(1)))));
+/// audio.play_event(SoundEvent::BlockBreak, WorldPos::new(1.0, 64.0, 0.0));
+/// // Plays once it's loaded.
+/// while audio.pending_count() > 0 {
+///     audio.update();
+/// }
+/// assert_eq!(audio.mixer().lock().unwrap().voice_count(), 1);
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct Audio {
+    assets: Arc<AssetManager>,
+    mixer: Arc<Mutex<Mixer>>,
+    output: Option<AudioOutput>,
+    /// Handles of every sound played, so they stay loaded.
+    sounds: HashMap<String, Handle<Sound>>,
+    pending: Vec<PendingSound>,
+    rng: WorldRng
+}
+
+impl Audio {
+    /// Sound from an asset manager, without an output until start_output() is called. Event sounds start
+    /// loading straight away, so they're ready the first time they play.
+    pub fn new(assets: Arc<AssetManager>) -> Audio {
+        let mut audio = Audio {
+            assets,
+            mixer: Arc::new(Mutex::new(Mixer::new(DEFAULT_SAMPLE_RATE))),
+            output: None,
+            sounds: HashMap::new(),
+            pending: Vec::new(),
+            rng: WorldRng::new(0)
+        };
+        for event in SoundEvent::ALL {
+            audio.load(event.sound());
+        }
+        return audio;
+    }
+
+    /// Start playing on the default output device. Sound stays silent if it fails.
+    pub fn start_output(&mut self) -> Result<(), AudioError> {
+        self.output = Some(AudioOutput::start(self.mixer.clone())?);
+        return Ok(());
+    }
+
+    pub fn has_output(&self) -> bool {
+        return self.output.is_some();
+    }
+
+    /// The mixer the output plays, shared with the output's thread.
+    pub fn mixer(&self) -> &Arc<Mutex<Mixer>> {
+        return &self.mixer;
+    }
+
+    pub fn volumes(&self) -> Volumes {
+        return self.mixer.lock().unwrap().volumes();
+    }
+
+    pub fn set_volumes(&self, volumes: Volumes) {
+        self.mixer.lock().unwrap().set_volumes(volumes);
+    }
+
+    /// Hear sounds from somewhere else, normally once a frame from the camera.
+    pub fn set_listener(&self, listener: Listener) {
+        self.mixer.lock().unwrap().set_listener(listener);
+    }
+
+    /// Sounds waiting to finish loading before they play.
+    pub fn pending_count(&self) -> usize {
+        return self.pending.len();
+    }
+
+    /// Start loading a sound asset, or get the handle of the one already requested. It stays loaded from then on.
+    pub fn load(&mut self, name: &str) -> Handle<Sound> {
+        return self.sounds.entry(name.to_string()).or_insert_with(|| self.assets.load::<Sound>(name)).clone();
+    }
+
+    /// Play a sound asset, from a position in the world or from nowhere in particular. Plays on the next update()
+    /// after it's loaded, or never if it fails to load.
+    pub fn play(&mut self, name: &str, category: SoundCategory, position: Option<WorldPos>, pitch: f32) {
+        let handle = self.load(name);
+        self.pending.push(PendingSound { handle, category, position, pitch });
+    }
+
+    /// Play the sound of a game event where it happened, with a slightly random pitch.
+    pub fn play_event(&mut self, event: SoundEvent, position: WorldPos) {
+        let pitch = 1.0 + self.rng.range_f32(-PITCH_VARIATION..PITCH_VARIATION);
+        self.play(event.sound(), event.category(), Some(position), pitch);
+    }
+
+    /// Start the sounds that finished loading. Called once a frame.
+    pub fn update(&mut self) {
+        let mut mixer = self.mixer.lock().unwrap();
+        self.pending.retain(|pending| {
+            return match pending.handle.state() {
+                AssetState::Loading => true,
+                AssetState::Failed => false,
+                AssetState::Loaded => {
+                    let mut play = PlaySound::new(pending.handle.get().unwrap(), pending.category).with_pitch(pending.pitch);
+                    play.position = pending.position;
+                    mixer.play(play);
+                    false
+                }
+            };
+        });
+    }
+}
\ No newline at end of file
@@ -0,0 +1,81 @@
+use std::{fmt, sync::{Arc, Mutex}};
+
+use super::mixer::Mixer;
+#[cfg(feature = "cpal")]
+use super::mixer::OUTPUT_CHANNELS;
+
+/// Reason sound can't be played.
+#[derive(Debug)]
+pub enum AudioError {
+    /// The client was built without the cpal feature, so has no way to play sound.
+    Unsupported,
+    /// There's no output device to play sound on.
+    NoDevice,
+    /// The output device couldn't be opened.
+    Device(String)
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            AudioError::Unsupported => write!(f, "built without audio output support"),
+            AudioError::NoDevice => write!(f, "no audio output device"),
+            AudioError::Device(error) => write!(f, "couldn't open the audio output device: {}", error)
+        };
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+/// Plays a mixer on the default output device, mixing on the device's own thread whenever it needs more samples.
+/// Playback stops when this is dropped.
+pub struct AudioOutput {
+    #[cfg(feature = "cpal")]
+    _stream: cpal::Stream,
+    sample_rate: u32
+}
+
+impl AudioOutput {
+    /// Open the default output device and start playing the mixer on it, at the device's sample rate.
+    #[cfg(feature = "cpal")]
+    pub fn start(mixer: Arc<Mutex<Mixer>>) -> Result<AudioOutput, AudioError> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let device = cpal::default_host().default_output_device().ok_or(AudioError::NoDevice)?;
+        let config = device.default_output_config().map_err(|error| AudioError::Device(error.to_string()))?.config();
+        let sample_rate = config.sample_rate.0;
+        let channels = config.channels as usize;
+        mixer.lock().unwrap().set_sample_rate(sample_rate);
+        let mut stereo = Vec::new();
+        let stream = device.build_output_stream(
+            &config,
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                let frames = data.len() / channels;
+                stereo.resize(frames * OUTPUT_CHANNELS, 0.0);
+                mixer.lock().unwrap().mix(&mut stereo);
+                // Mono devices get both sides, and channels past the first two stay silent.
+                for (frame, mixed) in data.chunks_exact_mut(channels).zip(stereo.chunks_exact(OUTPUT_CHANNELS)) {
+                    frame.fill(0.0);
+                    match channels {
+                        1 => frame[0] = (mixed[0] + mixed[1]) * 0.5,
+                        _ => frame[..OUTPUT_CHANNELS].copy_from_slice(mixed)
+                    }
+                }
+            },
+            |_| {},
+            None
+        ).map_err(|error| AudioError::Device(error.to_string()))?;
+        stream.play().map_err(|error| AudioError::Device(error.to_string()))?;
+        return Ok(AudioOutput { _stream: stream, sample_rate });
+    }
+
+    /// Without the cpal feature there's nothing to play sound on.
+    #[cfg(not(feature = "cpal"))]
+    pub fn start(_mixer: Arc<Mutex<Mixer>>) -> Result<AudioOutput, AudioError> {
+        return Err(AudioError::Unsupported);
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        return self.sample_rate;
+    }
+}
\ No newline at end of file
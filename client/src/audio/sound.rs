@@ -0,0 +1,66 @@
+use std::io::Cursor;
+
+use shared::engine::asset::manager::Asset;
+
+/// A decoded sound, as mono samples from -1 to 1. Sounds are mono so they can be placed in the world, with
+/// stereo files mixed down when decoded.
+/// ```
+/// # use client::audio::sound::Sound;
+/// # use shared::engine::asset::manager::Asset;
+/// let spec = hound::WavSpec { channels: 2, sample_rate: 22050, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+/// let mut file = std::io::Cursor::new(Vec::new());
+/// let mut writer = hound::WavWriter::new(&mut file, spec).unwrap();
+/// for sample in [16384, 16384, i16::MIN, i16::MIN] {
+///     writer.write_sample(sample).unwrap();
+/// }
+/// writer.finalize().unwrap();
+/// let sound = Sound::decode(file.get_ref()).unwrap();
+/// assert_eq!(sound.sample_rate, 22050);
+/// assert_eq!(sound.samples, vec![0.5, -1.0]);
+/// assert!(Sound::decode(b"not a wav file").is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sound {
+    /// Samples per second.
+    pub sample_rate: u32,
+    pub samples: Vec<f32>
+}
+
+impl Sound {
+    /// Length in seconds.
+    pub fn duration(&self) -> f32 {
+        return self.samples.len() as f32 / self.sample_rate as f32;
+    }
+
+    /// Sample at a fractional position, interpolated between the samples either side. Silent past the end.
+    pub fn sample(&self, position: f64) -> f32 {
+        let index = position as usize;
+        let Some(current) = self.samples.get(index) else {
+            return 0.0;
+        };
+        let next = self.samples.get(index + 1).copied().unwrap_or(0.0);
+        let t = position.fract() as f32;
+        return current + (next - current) * t;
+    }
+}
+
+impl Asset for Sound {
+    const DIRECTORY: &'static str = "sounds";
+    const EXTENSION: &'static str = "wav";
+
+    /// Decode a WAV file of integer or float samples, averaging its channels.
+    fn decode(data: &[u8]) -> Result<Sound, String> {
+        let reader = hound::WavReader::new(Cursor::new(data)).map_err(|error| error.to_string())?;
+        let spec = reader.spec();
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<_, _>>(),
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u32 << (spec.bits_per_sample - 1)) as f32;
+                reader.into_samples::<i32>().map(|sample| sample.map(|sample| sample as f32 * scale)).collect::<Result<_, _>>()
+            }
+        }.map_err(|error| error.to_string())?;
+        let channels = spec.channels as usize;
+        let samples = interleaved.chunks_exact(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32).collect();
+        return Ok(Sound { sample_rate: spec.sample_rate, samples });
+    }
+}
\ No newline at end of file
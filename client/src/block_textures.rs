@@ -0,0 +1,56 @@
+use crate::atlas::{AtlasBuilder, TextureAtlas};
+
+/// The block texture atlas on the GPU, with its mips. Starts with only the missing texture, until an atlas
+/// built from the loaded block textures is uploaded in its place.
+pub struct BlockTextures {
+    view: wgpu::TextureView,
+    sampler: wgpu::Sampler
+}
+
+impl BlockTextures {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, atlas: &TextureAtlas) -> BlockTextures {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("block textures"),
+            size: wgpu::Extent3d { width: atlas.width, height: atlas.height, depth_or_array_layers: 1 },
+            mip_level_count: atlas.mips.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+        for (level, pixels) in atlas.mips.iter().enumerate() {
+            let (width, height) = (atlas.width >> level, atlas.height >> level);
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo { texture: &texture, mip_level: level as u32, origin: wgpu::Origin3d::ZERO, aspect: wgpu::TextureAspect::All },
+                pixels,
+                wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(width * 4), rows_per_image: Some(height) },
+                wgpu::Extent3d { width, height, depth_or_array_layers: 1 }
+            );
+        }
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // Nearest filtering keeps pixel art sharp. Faces repeat their texture in the shader, as the rest of the
+        // atlas is around it.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("block textures"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
+            ..Default::default()
+        });
+        return BlockTextures { view, sampler };
+    }
+
+    /// An atlas of just the missing texture, for before block textures are loaded.
+    pub fn placeholder(device: &wgpu::Device, queue: &wgpu::Queue) -> BlockTextures {
+        return BlockTextures::new(device, queue, &AtlasBuilder::new().build());
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        return &self.view;
+    }
+
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        return &self.sampler;
+    }
+}
\ No newline at end of file
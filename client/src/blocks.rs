@@ -0,0 +1,133 @@
+use std::{collections::HashMap, sync::Arc};
+
+use shared::engine::{
+    asset::{block::BlockDefinition, handle::AssetError, manager::{Asset, AssetManager, AssetReloaded}, model::Model, texture::Texture},
+    block::{registry::BlockRegistry, sign::SIGN_BLOCK, BlockId, AIR},
+    mesh::{greedy::{OpacityFn, TranslucencyFn}, texture::FaceTextures},
+    worldgen::blocks::TerrainBlocks
+};
+
+use crate::atlas::{load_atlas, TextureAtlas};
+
+/// Namespace of the engine's own blocks, left out of their definitions' asset names.
+const ENGINE_NAMESPACE: &str = "cube";
+
+/// The blocks the client draws, registered in the same order as the server registers them, so their ids match.
+pub fn client_blocks() -> BlockRegistry {
+    let mut blocks = BlockRegistry::new();
+    TerrainBlocks::register(&mut blocks).expect("the terrain blocks have valid names");
+    blocks.register(SIGN_BLOCK).expect("the sign block has a valid name");
+    blocks.freeze();
+    return blocks;
+}
+
+/// Asset name of a block's definition: the engine's blocks by their path, so cube:stone is blocks/stone.json,
+/// and others under their namespace, so gems:ruby is blocks/gems/ruby.json.
+pub fn definition_name(block: &str) -> String {
+    return match block.split_once(':') {
+        Some((ENGINE_NAMESPACE, path)) => path.to_string(),
+        Some((namespace, path)) => format!("{}/{}", namespace, path),
+        None => block.to_string()
+    };
+}
+
+/// What the client draws blocks with, from each block's definition and the model and textures it uses: the block
+/// texture atlas, where each face of each block is in it, and which blocks hide the faces behind them or are blended
+/// over them. Blocks without a definition draw as opaque cubes of the missing texture.
+/// ```
+/// # use std::sync::Arc;
+/// # use client::{blocks::{client_blocks, BlockArt}, screenshot::encode_png};
+/// # use shared::engine::{asset::{texture::Texture, AssetManager}, job::system::JobSystem, math::direction::Direction};
+/// let directory = std::env::temp_dir().join(format!("block_art_doctest_{}", std::process::id()));
+/// for subdirectory in ["blocks", "models", "textures/blocks"] {
+///     std::fs::create_dir_all(directory.join(subdirectory)).unwrap();
+/// }
+/// std::fs::write(directory.join("blocks/stone.json"), r#"{ "name": "cube:stone", "model": "cube" }"#).unwrap();
+/// std::fs::write(directory.join("blocks/water.json"), r#"{ "name": "cube:water", "model": "cube", "solid": false, "opaque": false }"#).unwrap();
+/// std::fs::write(directory.join("models/cube.json"), r#"{ "textures": { "all": "blocks/stone" },
+///     "elements": [{ "from": [0, 0, 0], "to": [16, 16, 16], "faces": { "PosY": "all" } }] }"#).unwrap();
+/// let stone = Texture { width: 16, height: 16, pixels: vec![128; 16 * 16 * 4] };
+/// std::fs::write(directory.join("textures/blocks/stone.png"), encode_png(&stone).unwrap()).unwrap();
+///
+/// let blocks = client_blocks();
+/// let assets = AssetManager::new(&directory, Arc::new(JobSystem::new(1)));
+/// let (art, errors) = BlockArt::load(&assets, &blocks);
+/// // Every other block is missing its definition.
+/// assert_eq!(errors.len(), blocks.len() - 3);
+/// let (stone, water, dirt) = (blocks.id("cube:stone").unwrap(), blocks.id("cube:water").unwrap(), blocks.id("cube:dirt").unwrap());
+/// assert_ne!(art.atlas().rect("blocks/stone"), art.atlas().missing());
+/// assert_eq!(art.faces().get(stone, Direction::PosY), art.atlas().rect("blocks/stone"));
+/// assert_eq!(art.faces().get(dirt, Direction::PosY), art.atlas().missing());
+/// assert!((art.opaque())(stone) && (art.opaque())(dirt) && !(art.opaque())(water) && !(art.opaque())(0));
+/// assert!((art.translucent())(water) && !(art.translucent())(stone));
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct BlockArt {
+    atlas: TextureAtlas,
+    faces: Arc<FaceTextures>,
+    opaque: Arc<OpacityFn>,
+    translucent: Arc<TranslucencyFn>
+}
+
+impl BlockArt {
+    /// Load the definition of every registered block, then their models and textures, waiting for them all.
+    /// Anything that fails to load is left out and its error returned, so it draws as missing.
+    pub fn load(assets: &AssetManager, blocks: &BlockRegistry) -> (BlockArt, Vec<AssetError>) {
+        let mut errors = Vec::new();
+        // Request everything before waiting, so it loads in parallel.
+        let definitions: Vec<_> = (0..blocks.len() as BlockId)
+            .filter(|id| *id != AIR)
+            .filter_map(|id| blocks.name(id).map(|name| (id, assets.load::<BlockDefinition>(&definition_name(name)))))
+            .collect();
+        let definitions: Vec<(BlockId, Arc<BlockDefinition>)> = definitions.into_iter().filter_map(|(id, handle)| {
+            return handle.wait().map_err(|error| errors.push(error)).ok().map(|definition| (id, definition));
+        }).collect();
+        let mut models = HashMap::new();
+        for (_, definition) in definitions.iter() {
+            models.entry(definition.model.as_str()).or_insert_with(|| assets.load::<Model>(&definition.model));
+        }
+        let models: HashMap<&str, Arc<Model>> = models.into_iter().filter_map(|(name, handle)| {
+            return handle.wait().map_err(|error| errors.push(error)).ok().map(|model| (name, model));
+        }).collect();
+
+        let (atlas, texture_errors) = load_atlas(assets, &models.values().map(|model| &**model).collect::<Vec<_>>());
+        errors.extend(texture_errors);
+        let drawn: Vec<(BlockId, &Model)> = definitions.iter()
+            .filter_map(|(id, definition)| models.get(definition.model.as_str()).map(|model| (*id, &**model)))
+            .collect();
+        let faces = Arc::new(atlas.face_textures(&drawn));
+
+        let mut opaque = vec![true; blocks.len()];
+        let mut translucent = vec![false; blocks.len()];
+        opaque[AIR as usize] = false;
+        for (id, definition) in definitions.iter() {
+            opaque[*id as usize] = definition.opaque;
+            translucent[*id as usize] = !definition.opaque;
+        }
+        let opaque: Arc<OpacityFn> = Arc::new(move |block| opaque.get(block as usize).copied().unwrap_or(true));
+        let translucent: Arc<TranslucencyFn> = Arc::new(move |block| translucent.get(block as usize).copied().unwrap_or(false));
+        return (BlockArt { atlas, faces, opaque, translucent }, errors);
+    }
+
+    /// Whether an asset that was reloaded is one block art is made from, so it needs loading again.
+    pub fn depends_on(reloaded: &AssetReloaded) -> bool {
+        return [BlockDefinition::DIRECTORY, Model::DIRECTORY, Texture::DIRECTORY].contains(&reloaded.directory);
+    }
+
+    pub fn atlas(&self) -> &TextureAtlas {
+        return &self.atlas;
+    }
+
+    /// Atlas rects of each face of each block, for the mesher.
+    pub fn faces(&self) -> &Arc<FaceTextures> {
+        return &self.faces;
+    }
+
+    pub fn opaque(&self) -> &Arc<OpacityFn> {
+        return &self.opaque;
+    }
+
+    pub fn translucent(&self) -> &Arc<TranslucencyFn> {
+        return &self.translucent;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,205 @@
+use std::f32::consts::FRAC_PI_2;
+
+use shared::engine::math::{coords::WorldPos, frustum::Frustum, matrix::Mat4, precision::RenderOrigin, vector::Vec3};
+
+use crate::chunk_renderer::{ChunkView, Fog};
+
+/// Default vertical field of view, in radians.
+pub const DEFAULT_FOV: f32 = 70.0 * std::f32::consts::PI / 180.0;
+pub const NEAR_PLANE: f32 = 0.05;
+pub const FAR_PLANE: f32 = 1024.0;
+/// Default movement speed, in blocks per second.
+pub const DEFAULT_SPEED: f32 = 10.0;
+/// Default radians turned per pixel of mouse movement.
+pub const DEFAULT_SENSITIVITY: f32 = 0.002;
+/// Pitch stops just short of straight up or down, where yaw stops meaning anything and the view flips.
+pub const MAX_PITCH: f32 = FRAC_PI_2 - 0.001;
+
+/// How the camera moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CameraMode {
+    /// Moves the way it faces, including up and down, and rises or sinks freely.
+    #[default]
+    Fly,
+    /// Moves level with the ground whichever way it faces, and never rises or sinks on its own.
+    Walk
+}
+
+/// First person camera. The position is kept in world space at full precision, while the view is built relative
+/// to a RenderOrigin that follows the camera, so what's drawn stays precise however far from the world origin it goes.
+/// Yaw 0 faces -Z, and turns towards -X as it increases. Positive pitch looks up.
+/// ```
+/// # use client::camera::Camera;
+/// # use shared::engine::math::{coords::WorldPos, vector::Vec3};
+/// let mut camera = Camera::new(WorldPos::new(0.0, 64.0, 0.0));
+/// assert!(camera.forward().approx_eq(Vec3::new(0.0, 0.0, -1.0), 1e-6));
+/// // Walk forward for a second.
+/// camera.update(Vec3::new(0.0, 0.0, 1.0), 1.0);
+/// assert_eq!(camera.position(), WorldPos::new(0.0, 64.0, -10.0));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Camera {
+    position: WorldPos,
+    yaw: f32,
+    pitch: f32,
+    origin: RenderOrigin,
+    mode: CameraMode,
+    fov_y: f32,
+    aspect: f32,
+    speed: f32,
+    sensitivity: f32
+}
+
+impl Camera {
+    pub fn new(position: WorldPos) -> Camera {
+        return Camera {
+            position,
+            yaw: 0.0,
+            pitch: 0.0,
+            origin: RenderOrigin::around(position),
+            mode: CameraMode::Fly,
+            fov_y: DEFAULT_FOV,
+            aspect: 16.0 / 9.0,
+            speed: DEFAULT_SPEED,
+            sensitivity: DEFAULT_SENSITIVITY
+        };
+    }
+
+    pub fn with_mode(mut self, mode: CameraMode) -> Self {
+        self.mode = mode;
+        return self;
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        return self;
+    }
+
+    pub fn with_sensitivity(mut self, sensitivity: f32) -> Self {
+        self.sensitivity = sensitivity;
+        return self;
+    }
+
+    /// Vertical field of view, in radians.
+    pub fn with_fov(mut self, fov_y: f32) -> Self {
+        self.fov_y = fov_y;
+        return self;
+    }
+
+    /// Change the vertical field of view, in radians.
+    pub fn set_fov(&mut self, fov_y: f32) {
+        self.fov_y = fov_y;
+    }
+
+    pub fn position(&self) -> WorldPos {
+        return self.position;
+    }
+
+    /// Move the camera straight to a position, such as when the player respawns.
+    pub fn set_position(&mut self, position: WorldPos) {
+        self.position = position;
+        self.origin.rebase(position);
+    }
+
+    pub fn yaw(&self) -> f32 {
+        return self.yaw;
+    }
+
+    pub fn pitch(&self) -> f32 {
+        return self.pitch;
+    }
+
+    /// Face a direction, in radians. Yaw is wrapped to 0..2π and pitch is clamped to MAX_PITCH.
+    pub fn set_rotation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw.rem_euclid(std::f32::consts::TAU);
+        self.pitch = pitch.clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        return self.mode;
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+    }
+
+    /// Swap between flying and walking.
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::Fly => CameraMode::Walk,
+            CameraMode::Walk => CameraMode::Fly
+        };
+    }
+
+    pub fn origin(&self) -> RenderOrigin {
+        return self.origin;
+    }
+
+    /// Match the projection to the window's width / height.
+    pub fn set_aspect(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    /// Unit vector the camera faces.
+    pub fn forward(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        return Vec3::new(-sin_yaw * cos_pitch, sin_pitch, -cos_yaw * cos_pitch);
+    }
+
+    /// Unit vector to the camera's right, always level.
+    pub fn right(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        return Vec3::new(cos_yaw, 0.0, -sin_yaw);
+    }
+
+    /// Unit vector up the screen, perpendicular to forward and right.
+    pub fn up(&self) -> Vec3 {
+        return self.right().cross(self.forward());
+    }
+
+    /// Turn by a mouse movement in pixels. Moving right turns right, and moving down looks down.
+    pub fn look(&mut self, dx: f64, dy: f64) {
+        self.set_rotation(self.yaw - dx as f32 * self.sensitivity, self.pitch - dy as f32 * self.sensitivity);
+    }
+
+    /// Move for a frame. Movement is relative to where the camera faces, as x right, y up, and z forward, each
+    /// from -1 to 1, and is normalized so moving diagonally isn't faster.
+    /// Flying moves along the full view direction and up. Walking keeps forward movement level, and ignores y.
+    pub fn update(&mut self, movement: Vec3, seconds: f32) {
+        let (forward, up) = match self.mode {
+            CameraMode::Fly => (self.forward(), movement.y),
+            CameraMode::Walk => (Vec3::UP.cross(self.right()), 0.0)
+        };
+        let direction = (self.right() * movement.x + Vec3::UP * up + forward * movement.z).normalize_or_zero();
+        let step = direction * self.speed * seconds;
+        self.position = self.position + WorldPos::new(step.x as f64, step.y as f64, step.z as f64);
+        self.origin.rebase(self.position);
+    }
+
+    /// View matrix, relative to the render origin.
+    pub fn view(&self) -> Mat4 {
+        let eye = self.origin.to_render(self.position);
+        return Mat4::look_at_rh(eye, eye + self.forward(), Vec3::UP);
+    }
+
+    pub fn projection(&self) -> Mat4 {
+        return Mat4::perspective_rh(self.fov_y, self.aspect, NEAR_PLANE, FAR_PLANE);
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        return self.projection() * self.view();
+    }
+
+    /// Frustum of what the camera sees, relative to the render origin, for culling.
+    pub fn frustum(&self) -> Frustum {
+        return Frustum::from_view_projection(&self.view_projection());
+    }
+
+    /// The renderer's per frame view from this camera, with sky light and fog from the sky at this time of day.
+    pub fn chunk_view(&self, ambient: Vec3, fog: Fog) -> ChunkView {
+        return ChunkView { origin: self.origin, view_projection: self.view_projection(), eye: self.origin.to_render(self.position), ambient, fog };
+    }
+}
\ No newline at end of file
@@ -0,0 +1,306 @@
+use std::{collections::VecDeque, time::{Duration, Instant}};
+
+use shared::engine::net::chat::{parse_formatting, ChatColor, ChatHistory, ChatKind, ChatLine, MAX_CHAT_LENGTH};
+use winit::{dpi::PhysicalSize, keyboard::{Key, NamedKey}};
+
+use crate::text::{measure, TextRenderer, GLYPH_HEIGHT, GLYPH_SPACING, GLYPH_WIDTH, LINE_SPACING};
+
+/// Size of each pixel of the chat's font, in screen pixels.
+pub const CHAT_SCALE: f32 = 2.0;
+pub const CHAT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+pub const CHAT_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+/// Gap between the chat and the edges of the window, in screen pixels.
+pub const CHAT_MARGIN: f32 = 4.0;
+/// Lines shown while chat is open. Scrolling moves back through older ones.
+pub const OPEN_LINES: usize = 12;
+/// Newest lines shown over the game while chat is closed, for SHOW_CLOSED_FOR after the last one arrives.
+pub const CLOSED_LINES: usize = 5;
+pub const SHOW_CLOSED_FOR: Duration = Duration::from_secs(10);
+/// Lines sent that can be stepped back through with the up arrow.
+pub const SENT_HISTORY: usize = 50;
+
+/// Something the player typed and sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChatInput {
+    Message(String),
+    /// A line typed with a leading slash, without the slash.
+    Command(String)
+}
+
+/// Color to draw a chat color in. Chat colors are sRGB, and the window's frames are converted back to sRGB when shown.
+fn linear_color(color: ChatColor) -> [f32; 4] {
+    let [r, g, b] = color.rgb().map(|value| {
+        let value = value as f32 / 255.0;
+        return if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) };
+    });
+    return [r, g, b, 1.0];
+}
+
+/// The chat window. Shows chat received from the server, and while open, a line to type in with the lines sent
+/// before a key press away. While closed, the newest lines show briefly over the game as they arrive.
+/// ```
+/// # use std::time::{Duration, Instant};
+/// # use client::chat::{ChatInput, ChatWindow, SHOW_CLOSED_FOR};
+/// # use winit::keyboard::{Key, NamedKey};
+/// let mut chat = ChatWindow::new();
+/// chat.open("/");
+/// chat.type_text("tp 1 2 3\r");
+/// assert_eq!(chat.key(&Key::Named(NamedKey::Enter)), Some(ChatInput::Command("tp 1 2 3".to_string())));
+/// assert!(!chat.is_open());
+///
+/// chat.open("");
+/// chat.type_text("hllo");
+/// chat.key(&Key::Named(NamedKey::Home));
+/// chat.key(&Key::Named(NamedKey::ArrowRight));
+/// chat.type_text("e");
+/// assert_eq!(chat.input(), "hello");
+/// // Up steps back through what was sent, and down returns to the line being typed.
+/// chat.key(&Key::Named(NamedKey::ArrowUp));
+/// assert_eq!(chat.input(), "/tp 1 2 3");
+/// chat.key(&Key::Named(NamedKey::ArrowDown));
+/// assert_eq!(chat.input(), "hello");
+/// assert_eq!(chat.key(&Key::Named(NamedKey::Enter)), Some(ChatInput::Message("hello".to_string())));
+///
+/// chat.system("§aWelcome");
+/// let now = Instant::now();
+/// assert_eq!(chat.visible_lines(now).len(), 1);
+/// assert!(chat.visible_lines(now + SHOW_CLOSED_FOR + Duration::from_secs(1)).is_empty());
+/// ```
+pub struct ChatWindow {
+    history: ChatHistory,
+    open: bool,
+    input: String,
+    /// Position in the input, in characters.
+    cursor: usize,
+    /// Lines sent, oldest first.
+    sent: VecDeque<String>,
+    /// Line of sent being shown while stepping back through them, and the line being typed before that.
+    recalled: Option<usize>,
+    draft: String,
+    /// Lines scrolled back from the newest while open.
+    scroll: usize,
+    last_received: Option<Instant>
+}
+
+impl ChatWindow {
+    pub fn new() -> ChatWindow {
+        return ChatWindow { history: ChatHistory::default(), open: false, input: String::new(), cursor: 0, sent: VecDeque::new(), recalled: None, draft: String::new(), scroll: 0, last_received: None };
+    }
+
+    pub fn history(&self) -> &ChatHistory {
+        return &self.history;
+    }
+
+    pub fn is_open(&self) -> bool {
+        return self.open;
+    }
+
+    /// Open the chat with some text already typed, such as a slash for a command.
+    pub fn open(&mut self, text: &str) {
+        self.open = true;
+        self.input = text.to_string();
+        self.cursor = self.input.chars().count();
+        self.recalled = None;
+        self.scroll = 0;
+    }
+
+    /// Close the chat, throwing away what was typed.
+    pub fn close(&mut self) {
+        self.open = false;
+        self.input.clear();
+        self.cursor = 0;
+        self.recalled = None;
+        self.scroll = 0;
+    }
+
+    /// The line being typed.
+    pub fn input(&self) -> &str {
+        return &self.input;
+    }
+
+    /// Handle an encoded ChatBroadcast from the server. False if the packet isn't one.
+    pub fn receive(&mut self, packet: &[u8]) -> bool {
+        if !self.history.handle(packet) {
+            return false;
+        }
+        self.last_received = Some(Instant::now());
+        return true;
+    }
+
+    /// Add a line the client made itself.
+    pub fn push(&mut self, line: ChatLine) {
+        self.history.push(line);
+        self.last_received = Some(Instant::now());
+    }
+
+    /// Add a system line, with formatting codes, such as a command's output.
+    pub fn system(&mut self, text: &str) {
+        self.push(ChatLine { kind: ChatKind::System, sender: None, spans: parse_formatting(text) });
+    }
+
+    /// Forget every line received.
+    pub fn clear(&mut self) {
+        self.history = ChatHistory::default();
+        self.scroll = 0;
+    }
+
+    /// Type text at the cursor while open. Control characters, such as the enter key's, are left out, and typing
+    /// stops at MAX_CHAT_LENGTH.
+    pub fn type_text(&mut self, text: &str) {
+        if !self.open {
+            return;
+        }
+        for c in text.chars().filter(|c| !c.is_control()) {
+            if self.input.chars().count() >= MAX_CHAT_LENGTH {
+                break;
+            }
+            let index = self.byte_index(self.cursor);
+            self.input.insert(index, c);
+            self.cursor += 1;
+        }
+    }
+
+    /// Handle a key pressed while open, for editing and sending the line. Returns the line if it was sent.
+    /// Typing text is left to type_text().
+    pub fn key(&mut self, key: &Key) -> Option<ChatInput> {
+        if !self.open {
+            return None;
+        }
+        let Key::Named(key) = key else {
+            return None;
+        };
+        let length = self.input.chars().count();
+        match key {
+            NamedKey::Enter => return self.send(),
+            NamedKey::Escape => self.close(),
+            NamedKey::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.input.remove(self.byte_index(self.cursor));
+            }
+            NamedKey::Delete if self.cursor < length => {
+                self.input.remove(self.byte_index(self.cursor));
+            }
+            NamedKey::ArrowLeft => self.cursor = self.cursor.saturating_sub(1),
+            NamedKey::ArrowRight => self.cursor = (self.cursor + 1).min(length),
+            NamedKey::Home => self.cursor = 0,
+            NamedKey::End => self.cursor = length,
+            NamedKey::ArrowUp => self.recall(true),
+            NamedKey::ArrowDown => self.recall(false),
+            NamedKey::PageUp => self.scroll((OPEN_LINES - 1) as isize),
+            NamedKey::PageDown => self.scroll(-((OPEN_LINES - 1) as isize)),
+            _ => {}
+        }
+        return None;
+    }
+
+    /// Scroll back through older lines, or forward with a negative count.
+    pub fn scroll(&mut self, lines: isize) {
+        let most = self.history.len().saturating_sub(OPEN_LINES);
+        self.scroll = self.scroll.saturating_add_signed(lines).min(most);
+    }
+
+    /// Lines to show, oldest first. The scrolled to lines while open, or the newest while closed if they arrived
+    /// recently.
+    pub fn visible_lines(&self, now: Instant) -> Vec<&ChatLine> {
+        let count = match self.open {
+            true => OPEN_LINES,
+            false if self.last_received.is_some_and(|received| now.saturating_duration_since(received) < SHOW_CLOSED_FOR) => CLOSED_LINES,
+            false => 0
+        };
+        let skip = if self.open { self.scroll } else { 0 };
+        let mut lines: Vec<&ChatLine> = self.history.lines().rev().skip(skip).take(count).collect();
+        lines.reverse();
+        return lines;
+    }
+
+    /// Queue the chat in the bottom left corner, with the line being typed under it while open.
+    pub fn draw(&self, text: &mut TextRenderer, size: PhysicalSize<u32>, now: Instant) {
+        let line_height = (GLYPH_HEIGHT + LINE_SPACING) as f32 * CHAT_SCALE;
+        let advance = (GLYPH_WIDTH + GLYPH_SPACING) as f32 * CHAT_SCALE;
+        let input_top = size.height as f32 - CHAT_MARGIN - line_height;
+        if self.open {
+            // Long lines scroll sideways to keep the cursor in view.
+            let columns = ((size.width as f32 - 2.0 * CHAT_MARGIN) / advance).max(1.0) as usize - 1;
+            let first = self.cursor.saturating_sub(columns);
+            let shown: String = self.input.chars().skip(first).take(columns).collect();
+            text.rect([CHAT_MARGIN, input_top], [size.width as f32 - 2.0 * CHAT_MARGIN, line_height], CHAT_BACKGROUND);
+            text.text([CHAT_MARGIN + CHAT_SCALE, input_top + CHAT_SCALE], CHAT_SCALE, CHAT_COLOR, &shown);
+            let cursor_x = CHAT_MARGIN + CHAT_SCALE + (self.cursor - first) as f32 * advance;
+            text.text([cursor_x, input_top + CHAT_SCALE], CHAT_SCALE, CHAT_COLOR, "_");
+        }
+        let lines = self.visible_lines(now);
+        for (row, line) in lines.iter().rev().enumerate() {
+            let y = input_top - (row + 1) as f32 * line_height;
+            let (width, _) = measure(&line.plain_text());
+            text.rect([CHAT_MARGIN, y], [width as f32 * CHAT_SCALE + CHAT_SCALE, line_height], CHAT_BACKGROUND);
+            let mut x = CHAT_MARGIN + CHAT_SCALE;
+            if let Some(sender) = line.sender.as_ref() {
+                let name = format!("<{}> ", sender);
+                text.text([x, y + CHAT_SCALE], CHAT_SCALE, CHAT_COLOR, &name);
+                x += name.chars().count() as f32 * advance;
+            }
+            for span in line.spans.iter() {
+                let color = span.color.map_or(CHAT_COLOR, linear_color);
+                text.text([x, y + CHAT_SCALE], CHAT_SCALE, color, &span.text);
+                x += span.text.chars().count() as f32 * advance;
+            }
+        }
+    }
+
+    /// Send the line typed, closing the chat. Nothing is sent for a blank line.
+    fn send(&mut self) -> Option<ChatInput> {
+        let line = self.input.trim().to_string();
+        self.close();
+        if line.is_empty() {
+            return None;
+        }
+        if self.sent.back() != Some(&line) {
+            self.sent.push_back(line.clone());
+            if self.sent.len() > SENT_HISTORY {
+                self.sent.pop_front();
+            }
+        }
+        return Some(match line.strip_prefix('/') {
+            Some(command) => ChatInput::Command(command.to_string()),
+            None => ChatInput::Message(line)
+        });
+    }
+
+    /// Step back through the lines sent, or forward towards the line being typed.
+    fn recall(&mut self, older: bool) {
+        let index = match (self.recalled, older) {
+            (None, true) => self.sent.len().checked_sub(1),
+            (None, false) => None,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) => Some(index + 1)
+        };
+        let Some(index) = index else {
+            return;
+        };
+        if self.recalled.is_none() {
+            self.draft = self.input.clone();
+        }
+        match self.sent.get(index) {
+            Some(line) => {
+                self.input = line.clone();
+                self.recalled = Some(index);
+            }
+            None => {
+                self.input = std::mem::take(&mut self.draft);
+                self.recalled = None;
+            }
+        }
+        self.cursor = self.input.chars().count();
+    }
+
+    /// Byte index of a character in the input.
+    fn byte_index(&self, char_index: usize) -> usize {
+        return self.input.char_indices().nth(char_index).map_or(self.input.len(), |(index, _)| index);
+    }
+}
+
+impl Default for ChatWindow {
+    fn default() -> Self {
+        return ChatWindow::new();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,492 @@
+use std::{collections::HashMap, mem::size_of, ops::Range, sync::Arc};
+
+use shared::engine::{
+    job::system::JobSystem,
+    math::{aabb::Aabb, coords::ChunkPos, frustum::Frustum, matrix::Mat4, precision::RenderOrigin, vector::Vec3},
+    memory::{self, MemoryCategory, MemoryTracker},
+    mesh::{allocator::RangeAllocator, remesh::{RemeshJob, SectionMeshes}, vertex::{ChunkVertex, MeshData}},
+    world::{chunk::{Chunk, SECTIONS_PER_CHUNK}, palette::SECTION_SIZE}
+};
+
+use crate::{atlas::TextureAtlas, block_textures::BlockTextures, culling::{ChunkBounds, ChunkCuller, CullStats}, renderer::as_bytes};
+
+/// Vertices the shared vertex buffer starts with room for. It doubles whenever it runs out.
+pub const INITIAL_VERTEX_CAPACITY: u64 = 1 << 20;
+/// Indices the shared index buffer starts with room for. It doubles whenever it runs out.
+pub const INITIAL_INDEX_CAPACITY: u64 = 3 << 19;
+/// Format of the depth buffer chunks are drawn with.
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Distance fog, fading chunks into a color between two distances from the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fog {
+    pub color: Vec3,
+    /// Blocks from the camera where fog starts.
+    pub start: f32,
+    /// Blocks from the camera where fog hides everything.
+    pub end: f32
+}
+
+/// View the visible chunks are drawn from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChunkView {
+    /// Everything is drawn relative to the origin, so precision doesn't depend on distance from the world origin.
+    pub origin: RenderOrigin,
+    /// Projection and view of the camera, in origin relative space.
+    pub view_projection: Mat4,
+    /// Position of the camera, in origin relative space.
+    pub eye: Vec3,
+    /// Color of full sky light, dimmed by the time of day, from SkyState::ambient.
+    pub ambient: Vec3,
+    pub fog: Fog
+}
+
+/// Uniforms of the chunk shader. Matches Globals in chunk.wgsl.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Globals {
+    view_projection: Mat4,
+    ambient: [f32; 3],
+    fog_start: f32,
+    eye: [f32; 3],
+    fog_end: f32,
+    fog_color: [f32; 3],
+    padding: f32
+}
+
+/// One GPU buffer holding many meshes, with ranges handed out by a RangeAllocator rather than a buffer per mesh.
+/// When full, it's replaced by a buffer twice the size, with the old contents copied over.
+struct PooledBuffer {
+    label: &'static str,
+    buffer: wgpu::Buffer,
+    allocator: RangeAllocator,
+    usage: wgpu::BufferUsages,
+    /// Size of each element, in bytes.
+    stride: u64
+}
+
+impl PooledBuffer {
+    fn new(device: &wgpu::Device, label: &'static str, usage: wgpu::BufferUsages, stride: u64, capacity: u64) -> PooledBuffer {
+        let usage = usage | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor { label: Some(label), size: capacity * stride, usage, mapped_at_creation: false });
+        return PooledBuffer { label, buffer, allocator: RangeAllocator::new(capacity), usage, stride };
+    }
+
+    /// Upload elements, returning the range of elements they were put in.
+    fn upload<T: Copy>(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[T]) -> Range<u64> {
+        debug_assert_eq!(size_of::<T>() as u64, self.stride);
+        let length = data.len() as u64;
+        let range = match self.allocator.alloc(length) {
+            Some(range) => range,
+            None => {
+                let mut capacity = self.allocator.capacity() * 2;
+                while capacity - self.allocator.capacity() + self.allocator.largest_free() < length {
+                    capacity *= 2;
+                }
+                self.grow(device, queue, capacity);
+                self.allocator.alloc(length).unwrap()
+            }
+        };
+        queue.write_buffer(&self.buffer, range.start * self.stride, as_bytes(data));
+        return range;
+    }
+
+    /// Replace the buffer with a larger one. The copy is submitted straight away, so writes queued after
+    /// it land in the new buffer on top of the copied contents.
+    fn grow(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, capacity: u64) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor { label: Some(self.label), size: capacity * self.stride, usage: self.usage, mapped_at_creation: false });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some(self.label) });
+        encoder.copy_buffer_to_buffer(&self.buffer, 0, &buffer, 0, self.allocator.capacity() * self.stride);
+        queue.submit([encoder.finish()]);
+        self.buffer = buffer;
+        self.allocator.grow(capacity);
+    }
+
+    fn free(&mut self, range: Range<u64>) {
+        self.allocator.free(range);
+    }
+}
+
+/// Where one section's mesh is in the pooled buffers. Sections with only opaque or only translucent blocks have
+/// no range for the other.
+struct SectionMesh {
+    vertices: Range<u64>,
+    indices: Option<Range<u64>>,
+    translucent: Option<Range<u64>>
+}
+
+/// Draws chunk meshes made by meshing jobs. Every mesh shares one vertex and one index buffer, with each
+/// section of a chunk in its own range, so remeshing a section only replaces that section's range.
+/// Chunks outside the view frustum are culled on the job system, and skipped. Translucent blocks are drawn by
+/// their own pass after everything opaque, blended over it from the furthest section to the nearest.
+pub struct ChunkRenderer {
+    pipeline: wgpu::RenderPipeline,
+    translucent_pipeline: wgpu::RenderPipeline,
+    globals: wgpu::Buffer,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    textures: BlockTextures,
+    vertices: PooledBuffer,
+    indices: PooledBuffer,
+    /// Offset of each chunk drawn this frame from the render origin, one per instance.
+    offsets: wgpu::Buffer,
+    chunks: HashMap<ChunkPos, [Option<SectionMesh>; SECTIONS_PER_CHUNK]>,
+    culler: ChunkCuller,
+    /// Bounds of every chunk for the culling jobs. Rebuilt only after chunks are uploaded or removed.
+    bounds: Option<Arc<Vec<ChunkBounds>>>,
+    /// Chunks to draw this frame, found by prepare(). Each chunk's offset is the instance of the same index.
+    visible: Vec<ChunkPos>,
+    /// Visible sections with translucent blocks, as (instance, chunk, section), furthest from the camera first.
+    translucent: Vec<(u32, ChunkPos, usize)>,
+    /// Frames prepared so far.
+    frame: u64,
+    /// Frame each chunk was last in view, or uploaded if it hasn't been since, for choosing meshes to evict.
+    drawn: HashMap<ChunkPos, u64>
+}
+
+impl ChunkRenderer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, color_format: wgpu::TextureFormat, jobs: Arc<JobSystem>) -> ChunkRenderer {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/chunk.wgsl"));
+        let globals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("chunk globals"),
+            size: size_of::<Globals>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        let textures = BlockTextures::placeholder(device, queue);
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("chunk"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None
+                }
+            ]
+        });
+        let bind_group = ChunkRenderer::create_bind_group(device, &layout, &globals, &textures);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("chunk"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0
+        });
+        let pipeline = ChunkRenderer::create_pipeline(device, &pipeline_layout, &shader, color_format, false);
+        let translucent_pipeline = ChunkRenderer::create_pipeline(device, &pipeline_layout, &shader, color_format, true);
+        return ChunkRenderer {
+            pipeline,
+            translucent_pipeline,
+            globals,
+            layout,
+            bind_group,
+            textures,
+            vertices: PooledBuffer::new(device, "chunk vertices", wgpu::BufferUsages::VERTEX, size_of::<ChunkVertex>() as u64, INITIAL_VERTEX_CAPACITY),
+            indices: PooledBuffer::new(device, "chunk indices", wgpu::BufferUsages::INDEX, size_of::<u32>() as u64, INITIAL_INDEX_CAPACITY),
+            offsets: device.create_buffer(&wgpu::BufferDescriptor { label: Some("chunk offsets"), size: 0, usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST, mapped_at_creation: false }),
+            chunks: HashMap::new(),
+            culler: ChunkCuller::new(jobs),
+            bounds: None,
+            visible: Vec::new(),
+            translucent: Vec::new(),
+            frame: 0,
+            drawn: HashMap::new()
+        };
+    }
+
+    /// Opaque chunks write depth and are culled from behind. Translucent ones blend over them without writing
+    /// depth, so what's behind still shows, and both sides are drawn, so a water surface shows from below too.
+    fn create_pipeline(device: &wgpu::Device, layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule, color_format: wgpu::TextureFormat, translucent: bool) -> wgpu::RenderPipeline {
+        let (label, fragment, blend, cull_mode) = match translucent {
+            false => ("chunk", "fs_main", None, Some(wgpu::Face::Back)),
+            true => ("translucent chunk", "fs_translucent", Some(wgpu::BlendState::ALPHA_BLENDING), None)
+        };
+        return device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[
+                    Some(wgpu::VertexBufferLayout {
+                        array_stride: size_of::<ChunkVertex>() as u64,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x4, 3 => Uint32, 4 => Uint32, 5 => Uint32, 6 => Uint32]
+                    }),
+                    Some(wgpu::VertexBufferLayout {
+                        array_stride: size_of::<[f32; 3]>() as u64,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &wgpu::vertex_attr_array![7 => Float32x3]
+                    })
+                ]
+            },
+            primitive: wgpu::PrimitiveState { cull_mode, ..Default::default() },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: Some(!translucent),
+                depth_compare: Some(wgpu::CompareFunction::Less),
+                stencil: Default::default(),
+                bias: Default::default()
+            }),
+            multisample: Default::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some(fragment),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState { format: color_format, blend, write_mask: wgpu::ColorWrites::ALL })]
+            }),
+            multiview_mask: None,
+            cache: None
+        });
+    }
+
+    fn create_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, globals: &wgpu::Buffer, textures: &BlockTextures) -> wgpu::BindGroup {
+        return device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("chunk"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: globals.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(textures.view()) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(textures.sampler()) }
+            ]
+        });
+    }
+
+    pub fn textures(&self) -> &BlockTextures {
+        return &self.textures;
+    }
+
+    /// Draw with a new block texture atlas. Meshes made before keep the UVs of the old one, so chunks should be
+    /// remeshed with the atlas' face textures.
+    pub fn set_atlas(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, atlas: &TextureAtlas) {
+        self.textures = BlockTextures::new(device, queue, atlas);
+        self.bind_group = ChunkRenderer::create_bind_group(device, &self.layout, &self.globals, &self.textures);
+    }
+
+    /// Number of chunks with at least one section uploaded.
+    pub fn len(&self) -> usize {
+        return self.chunks.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.chunks.is_empty();
+    }
+
+    /// Chunks drawn and culled last frame, for the debug overlay.
+    pub fn cull_stats(&self) -> CullStats {
+        return self.culler.stats();
+    }
+
+    /// Vertices and indices uploaded, for the debug overlay.
+    pub fn memory_used(&self) -> u64 {
+        return self.vertices.allocator.used() * self.vertices.stride + self.indices.allocator.used() * self.indices.stride;
+    }
+
+    /// Upload meshes of some of a chunk's sections, replacing what those sections had before.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, pos: ChunkPos, meshes: SectionMeshes) {
+        self.bounds = None;
+        let sections = self.chunks.entry(pos).or_default();
+        for (section, mesh) in meshes {
+            if let Some(old) = sections[section].take() {
+                ChunkRenderer::free_section(&mut self.vertices, &mut self.indices, old);
+            }
+            sections[section] = ChunkRenderer::upload_section(&mut self.vertices, &mut self.indices, device, queue, &mesh);
+        }
+        if sections.iter().all(|section| section.is_none()) {
+            self.chunks.remove(&pos);
+            self.drawn.remove(&pos);
+        } else {
+            self.drawn.insert(pos, self.frame);
+        }
+    }
+
+    fn upload_section(vertices: &mut PooledBuffer, indices: &mut PooledBuffer, device: &wgpu::Device, queue: &wgpu::Queue, mesh: &MeshData) -> Option<SectionMesh> {
+        if mesh.is_empty() {
+            return None;
+        }
+        return Some(SectionMesh {
+            vertices: vertices.upload(device, queue, &mesh.vertices),
+            indices: (!mesh.indices.is_empty()).then(|| indices.upload(device, queue, &mesh.indices)),
+            translucent: (!mesh.translucent.is_empty()).then(|| indices.upload(device, queue, &mesh.translucent))
+        });
+    }
+
+    fn free_section(vertices: &mut PooledBuffer, indices: &mut PooledBuffer, section: SectionMesh) {
+        vertices.free(section.vertices);
+        for range in [section.indices, section.translucent].into_iter().flatten() {
+            indices.free(range);
+        }
+    }
+
+    /// Upload the meshes of every finished job, keeping the ones still running. Returns how many were uploaded.
+    pub fn receive(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, jobs: &mut Vec<RemeshJob>) -> usize {
+        let mut finished = Vec::new();
+        jobs.retain(|job| {
+            if !job.future.is_ready() {
+                return true;
+            }
+            finished.push((job.pos, job.future.wait()));
+            return false;
+        });
+        let count = finished.len();
+        for (pos, meshes) in finished {
+            self.upload(device, queue, pos, meshes);
+        }
+        return count;
+    }
+
+    /// Free a chunk's meshes, such as when it unloads. False if it had none.
+    pub fn remove(&mut self, pos: ChunkPos) -> bool {
+        let Some(sections) = self.chunks.remove(&pos) else {
+            return false;
+        };
+        self.drawn.remove(&pos);
+        self.bounds = None;
+        for section in sections.into_iter().flatten() {
+            ChunkRenderer::free_section(&mut self.vertices, &mut self.indices, section);
+        }
+        return true;
+    }
+
+    /// Register the meshes' memory, and when it's over budget free the meshes of chunks out of view, furthest
+    /// from the camera's chunk first, then those out of view longest. Returns the chunks evicted, which need
+    /// meshing again before they're drawn.
+    pub fn evict(&mut self, memory: &MemoryTracker, center: ChunkPos) -> Vec<ChunkPos> {
+        memory.set(MemoryCategory::Meshes, self.memory_used());
+        let over = memory.over_budget(MemoryCategory::Meshes);
+        if over == 0 {
+            return Vec::new();
+        }
+        let candidates: Vec<(ChunkPos, u64)> = self.drawn.iter()
+            .filter(|(_, frame)| **frame < self.frame)
+            .map(|(pos, frame)| (*pos, *frame))
+            .collect();
+        let sizes: HashMap<ChunkPos, u64> = candidates.iter().map(|(pos, _)| (*pos, self.chunk_memory(*pos))).collect();
+        let evicted = memory::choose_evictions(&candidates, &[center], &sizes, over);
+        for pos in evicted.iter() {
+            self.remove(*pos);
+        }
+        memory.set(MemoryCategory::Meshes, self.memory_used());
+        return evicted;
+    }
+
+    /// Bytes of a chunk's meshes in the pooled buffers.
+    fn chunk_memory(&self, pos: ChunkPos) -> u64 {
+        return self.chunks.get(&pos).into_iter().flatten().flatten().map(|section| {
+            let indices: u64 = [&section.indices, &section.translucent].into_iter().flatten().map(|range| range.end - range.start).sum();
+            return (section.vertices.end - section.vertices.start) * self.vertices.stride + indices * self.indices.stride;
+        }).sum();
+    }
+
+    /// Bounds of the sections of a chunk that have meshes, so chunks with only a few sections meshed, such as
+    /// ones with just the ground at their bottom, cull more tightly.
+    fn chunk_bounds(sections: &[Option<SectionMesh>; SECTIONS_PER_CHUNK]) -> Option<Aabb> {
+        return sections.iter().enumerate().filter(|(_, section)| section.is_some()).map(|(section, _)| {
+            let origin = Chunk::section_origin(section);
+            let min = Vec3::new(origin.x as f32, origin.y as f32, origin.z as f32);
+            return Aabb::new(min, min + Vec3::splat(SECTION_SIZE as f32));
+        }).reduce(|a, b| a.union(&b));
+    }
+
+    /// Find the chunks in view and upload this frame's uniforms and chunk offsets. Called before the render pass.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, view: &ChunkView) {
+        let chunks = &self.chunks;
+        let bounds = self.bounds.get_or_insert_with(|| {
+            return Arc::new(chunks.iter().filter_map(|(pos, sections)| {
+                return ChunkRenderer::chunk_bounds(sections).map(|bounds| ChunkBounds { pos: *pos, bounds });
+            }).collect());
+        });
+        let visible = self.culler.cull(Frustum::from_view_projection(&view.view_projection), view.origin, bounds);
+        self.visible = visible.iter().map(|chunk| chunk.pos).collect();
+        self.frame += 1;
+        for pos in self.visible.iter() {
+            self.drawn.insert(*pos, self.frame);
+        }
+        let mut translucent: Vec<(f32, u32, ChunkPos, usize)> = visible.iter().enumerate().flat_map(|(instance, chunk)| {
+            return self.chunks[&chunk.pos].iter().enumerate().filter(|(_, section)| section.as_ref().is_some_and(|section| section.translucent.is_some())).map(move |(section, _)| {
+                let origin = Chunk::section_origin(section);
+                let center = chunk.offset + Vec3::new(origin.x as f32, origin.y as f32, origin.z as f32) + Vec3::splat(SECTION_SIZE as f32 * 0.5);
+                return (center.distance(view.eye), instance as u32, chunk.pos, section);
+            });
+        }).collect();
+        translucent.sort_by(|a, b| b.0.total_cmp(&a.0));
+        self.translucent = translucent.into_iter().map(|(_, instance, pos, section)| (instance, pos, section)).collect();
+        let offsets: Vec<[f32; 3]> = visible.iter().map(|chunk| chunk.offset.to_array()).collect();
+        let globals = Globals {
+            view_projection: view.view_projection,
+            ambient: view.ambient.to_array(),
+            fog_start: view.fog.start,
+            eye: view.eye.to_array(),
+            fog_end: view.fog.end,
+            fog_color: view.fog.color.to_array(),
+            padding: 0.0
+        };
+        queue.write_buffer(&self.globals, 0, as_bytes(&[globals]));
+        let size = (offsets.len() * size_of::<[f32; 3]>()) as u64;
+        if size > self.offsets.size() {
+            self.offsets = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("chunk offsets"),
+                size: size.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            });
+        }
+        if size > 0 {
+            queue.write_buffer(&self.offsets, 0, as_bytes(&offsets));
+        }
+    }
+
+    /// Draw the chunks found by prepare(). The pass needs a DEPTH_FORMAT depth attachment.
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>) {
+        if self.visible.is_empty() {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertices.buffer.slice(..));
+        pass.set_vertex_buffer(1, self.offsets.slice(..));
+        pass.set_index_buffer(self.indices.buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for (instance, pos) in self.visible.iter().enumerate() {
+            let instance = instance as u32;
+            for section in self.chunks[pos].iter().flatten() {
+                if let Some(indices) = section.indices.as_ref() {
+                    pass.draw_indexed(indices.start as u32..indices.end as u32, section.vertices.start as i32, instance..instance + 1);
+                }
+            }
+        }
+    }
+
+    /// Draw the translucent blocks of the chunks found by prepare(), blended back to front. Drawn after every
+    /// opaque pass, into the same DEPTH_FORMAT depth attachment.
+    pub fn draw_translucent(&self, pass: &mut wgpu::RenderPass<'_>) {
+        if self.translucent.is_empty() {
+            return;
+        }
+        pass.set_pipeline(&self.translucent_pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertices.buffer.slice(..));
+        pass.set_vertex_buffer(1, self.offsets.slice(..));
+        pass.set_index_buffer(self.indices.buffer.slice(..), wgpu::IndexFormat::Uint32);
+        for &(instance, pos, section) in self.translucent.iter() {
+            let section = self.chunks[&pos][section].as_ref().unwrap();
+            let indices = section.translucent.as_ref().unwrap();
+            pass.draw_indexed(indices.start as u32..indices.end as u32, section.vertices.start as i32, instance..instance + 1);
+        }
+    }
+}
\ No newline at end of file
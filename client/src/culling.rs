@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use shared::engine::{
+    job::system::JobSystem,
+    math::{aabb::Aabb, coords::ChunkPos, frustum::Frustum, precision::RenderOrigin, vector::Vec3}
+};
+
+/// Chunks each culling job tests. Small enough to spread a large view distance over the job threads, large
+/// enough that a job does more than the cost of scheduling it.
+pub const DEFAULT_CULL_BATCH_SIZE: usize = 512;
+
+/// A chunk to cull, with the bounds of its meshed sections relative to its minimum corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChunkBounds {
+    pub pos: ChunkPos,
+    pub bounds: Aabb
+}
+
+/// A chunk that passed culling, with its offset from the render origin.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VisibleChunk {
+    pub pos: ChunkPos,
+    pub offset: Vec3
+}
+
+/// How many chunks were drawn and culled in a frame, for the debug overlay.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize
+}
+
+impl CullStats {
+    pub fn total(&self) -> usize {
+        return self.drawn + self.culled;
+    }
+}
+
+/// Chunks inside the frustum. The frustum is relative to the render origin, as the camera's is.
+/// ```
+/// # use client::culling::{cull_chunks, ChunkBounds};
+/// # use shared::engine::math::{aabb::Aabb, coords::{ChunkPos, WorldPos}, frustum::Frustum, matrix::Mat4, precision::RenderOrigin, vector::Vec3};
+/// let origin = RenderOrigin::around(WorldPos::new(16.0, 16.0, 48.0));
+/// let view = Mat4::look_at_rh(origin.to_render(WorldPos::new(16.0, 16.0, 48.0)), origin.to_render(WorldPos::new(16.0, 16.0, 0.0)), Vec3::UP);
+/// let frustum = Frustum::from_view_projection(&(Mat4::perspective_rh(1.2, 1.0, 0.1, 100.0) * view));
+/// let bounds = Aabb::new(Vec3::ZERO, Vec3::splat(32.0));
+/// let chunks = [ChunkBounds { pos: ChunkPos::new(0, 0, 0), bounds }, ChunkBounds { pos: ChunkPos::new(0, 0, 3), bounds }];
+/// // The camera looks towards -Z, so only the chunk in front of it is visible.
+/// let visible = cull_chunks(&frustum, origin, &chunks);
+/// assert_eq!(visible.len(), 1);
+/// assert_eq!(visible[0].pos, ChunkPos::new(0, 0, 0));
+/// ```
+pub fn cull_chunks(frustum: &Frustum, origin: RenderOrigin, chunks: &[ChunkBounds]) -> Vec<VisibleChunk> {
+    return chunks.iter().filter_map(|chunk| {
+        let offset = origin.block_to_render(chunk.pos.origin());
+        return match frustum.intersects_aabb(&chunk.bounds.translate(offset)) {
+            true => Some(VisibleChunk { pos: chunk.pos, offset }),
+            false => None
+        };
+    }).collect();
+}
+
+/// Culls chunks against the view frustum on the job system, in batches tested in parallel, so the main thread
+/// only waits for the list of visible chunks.
+/// ```
+/// # use std::sync::Arc;
+/// # use client::culling::{ChunkBounds, ChunkCuller};
+/// # use shared::engine::{job::system::JobSystem, math::{aabb::Aabb, coords::{ChunkPos, WorldPos}, frustum::Frustum, matrix::Mat4, precision::RenderOrigin, vector::Vec3}};
+/// let mut culler = ChunkCuller::new(Arc::new(JobSystem::new(2))).with_batch_size(16);
+/// let bounds = Aabb::new(Vec3::ZERO, Vec3::splat(32.0));
+/// // A row of chunks along X, seen by a camera looking down -Z from the middle of chunk 0.
+/// let chunks = Arc::new((-50..50).map(|x| ChunkBounds { pos: ChunkPos::new(x, 0, -1), bounds }).collect());
+/// let origin = RenderOrigin::around(WorldPos::ORIGIN);
+/// let view = Mat4::look_at_rh(Vec3::new(16.0, 16.0, 16.0), Vec3::new(16.0, 16.0, 0.0), Vec3::UP);
+/// let frustum = Frustum::from_view_projection(&(Mat4::perspective_rh(1.2, 1.0, 0.1, 200.0) * view));
+/// let visible = culler.cull(frustum, origin, &chunks);
+/// assert!(visible.iter().any(|chunk| chunk.pos == ChunkPos::new(0, 0, -1)));
+/// assert!(!visible.iter().any(|chunk| chunk.pos == ChunkPos::new(-50, 0, -1)));
+/// assert_eq!(culler.stats().total(), 100);
+/// assert_eq!(culler.stats().drawn, visible.len());
+/// ```
+pub struct ChunkCuller {
+    jobs: Arc<JobSystem>,
+    batch_size: usize,
+    stats: CullStats
+}
+
+impl ChunkCuller {
+    pub fn new(jobs: Arc<JobSystem>) -> ChunkCuller {
+        return ChunkCuller { jobs, batch_size: DEFAULT_CULL_BATCH_SIZE, stats: CullStats::default() };
+    }
+
+    /// Chunks tested by each job. Will panic in debug mode if 0.
+    pub fn with_batch_size(mut self, batch_size: usize) -> ChunkCuller {
+        debug_assert!(batch_size > 0, "Cull batch size must be positive");
+        self.batch_size = batch_size;
+        return self;
+    }
+
+    /// Counts from the last cull.
+    pub fn stats(&self) -> CullStats {
+        return self.stats;
+    }
+
+    /// Find the visible chunks, in the same order as they're given. The list is shared with the jobs rather than
+    /// copied, so it only needs rebuilding when chunks are added or removed.
+    pub fn cull(&mut self, frustum: Frustum, origin: RenderOrigin, chunks: &Arc<Vec<ChunkBounds>>) -> Vec<VisibleChunk> {
+        let futures: Vec<_> = (0..chunks.len()).step_by(self.batch_size).map(|start| {
+            let chunks = chunks.clone();
+            let end = (start + self.batch_size).min(chunks.len());
+            return self.jobs.run_job(move || cull_chunks(&frustum, origin, &chunks[start..end]));
+        }).collect();
+        let visible: Vec<VisibleChunk> = futures.into_iter().flat_map(|future| future.wait()).collect();
+        self.stats = CullStats { drawn: visible.len(), culled: chunks.len() - visible.len() };
+        return visible;
+    }
+}
\ No newline at end of file
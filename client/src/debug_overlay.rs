@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+use shared::engine::{job::debug::JobSystemDebugDump, math::coords::WorldPos};
+
+use crate::{camera::CameraMode, culling::CullStats, text::TextRenderer};
+
+/// Frames the frame rate is averaged over.
+pub const FRAME_SAMPLES: usize = 120;
+/// Size of each pixel of the overlay's font, in screen pixels.
+pub const OVERLAY_SCALE: f32 = 2.0;
+pub const OVERLAY_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+pub const OVERLAY_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+/// Gap between the overlay and the edges of the window, in screen pixels.
+pub const OVERLAY_MARGIN: f32 = 4.0;
+
+/// Rolling frame times, for the frame rate.
+/// ```
+/// # use client::debug_overlay::FrameTimer;
+/// let mut frames = FrameTimer::new();
+/// frames.record(1.0 / 50.0);
+/// frames.record(1.0 / 25.0);
+/// assert!((frames.average_seconds() - 0.03).abs() < 1e-6);
+/// assert!((frames.fps() - 33.333).abs() < 0.01);
+/// assert!((frames.worst_seconds() - 0.04).abs() < 1e-6);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FrameTimer {
+    seconds: VecDeque<f32>
+}
+
+impl FrameTimer {
+    pub fn new() -> FrameTimer {
+        return FrameTimer { seconds: VecDeque::with_capacity(FRAME_SAMPLES) };
+    }
+
+    /// Record how long a frame took. Only the last FRAME_SAMPLES frames are kept.
+    pub fn record(&mut self, seconds: f32) {
+        if self.seconds.len() == FRAME_SAMPLES {
+            self.seconds.pop_front();
+        }
+        self.seconds.push_back(seconds);
+    }
+
+    pub fn average_seconds(&self) -> f32 {
+        if self.seconds.is_empty() {
+            return 0.0;
+        }
+        return self.seconds.iter().sum::<f32>() / self.seconds.len() as f32;
+    }
+
+    /// The slowest recorded frame, which shows stutters the average hides.
+    pub fn worst_seconds(&self) -> f32 {
+        return self.seconds.iter().copied().fold(0.0, f32::max);
+    }
+
+    /// Frames per second over the recorded frames. 0 until a frame is recorded.
+    pub fn fps(&self) -> f32 {
+        let average = self.average_seconds();
+        if average <= 0.0 {
+            return 0.0;
+        }
+        return 1.0 / average;
+    }
+}
+
+/// Everything the overlay shows, gathered once a frame while it's open.
+pub struct DebugInfo {
+    pub position: WorldPos,
+    /// Degrees, as shown to players.
+    pub yaw: f32,
+    pub pitch: f32,
+    pub mode: CameraMode,
+    /// Chunks with meshes uploaded to the GPU.
+    pub chunks: usize,
+    pub cull: CullStats,
+    pub jobs: JobSystemDebugDump,
+    /// Bytes of mesh data on the GPU.
+    pub mesh_memory: u64,
+    /// Bytes of mesh data allowed before meshes out of view are freed, if there's a limit.
+    pub mesh_budget: Option<u64>,
+    /// Bytes of memory the process has resident, where the platform reports it.
+    pub process_memory: Option<u64>
+}
+
+/// The F3 screen. Shows the frame rate, where the camera is, chunk and job system counts, and memory use.
+/// Frames are timed while it's hidden as well, so it opens with an accurate frame rate.
+#[derive(Clone, Debug, Default)]
+pub struct DebugOverlay {
+    visible: bool,
+    frames: FrameTimer
+}
+
+impl DebugOverlay {
+    pub fn new() -> DebugOverlay {
+        return DebugOverlay { visible: false, frames: FrameTimer::new() };
+    }
+
+    pub fn is_visible(&self) -> bool {
+        return self.visible;
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn frames(&self) -> &FrameTimer {
+        return &self.frames;
+    }
+
+    pub fn record_frame(&mut self, seconds: f32) {
+        self.frames.record(seconds);
+    }
+
+    /// Lines of text shown for some info.
+    pub fn lines(&self, info: &DebugInfo) -> Vec<String> {
+        let block = info.position.block();
+        let chunk = info.position.chunk();
+        let busy = info.jobs.threads.iter().filter(|thread| thread.is_executing).count();
+        let queued: usize = info.jobs.threads.iter().map(|thread| thread.queued_jobs.len()).sum();
+        let executed: usize = info.jobs.threads.iter().map(|thread| thread.jobs_executed).sum();
+        let process_memory = info.process_memory.map_or("unknown".to_string(), format_bytes);
+        let mesh_memory = match info.mesh_budget {
+            Some(budget) => format!("{} of {}", format_bytes(info.mesh_memory), format_bytes(budget)),
+            None => format_bytes(info.mesh_memory)
+        };
+        return vec![
+            format!("Cube Universe {}", env!("CARGO_PKG_VERSION")),
+            format!("FPS: {:.0} ({:.1} ms, worst {:.1} ms)", self.frames.fps(), self.frames.average_seconds() * 1000.0, self.frames.worst_seconds() * 1000.0),
+            format!("XYZ: {:.3} / {:.3} / {:.3}", info.position.x, info.position.y, info.position.z),
+            format!("Block: {} {} {}  Chunk: {} {} {}", block.x, block.y, block.z, chunk.x, chunk.y, chunk.z),
+            format!("Facing: yaw {:.1} pitch {:.1} ({:?})", info.yaw, info.pitch, info.mode),
+            format!("Chunks: {} meshed, {} drawn, {} culled", info.chunks, info.cull.drawn, info.cull.culled),
+            format!("Jobs: {} threads, {} busy, {} queued, {} run", info.jobs.thread_count, busy, queued, executed),
+            format!("Memory: {} meshes, {} process", mesh_memory, process_memory)
+        ];
+    }
+
+    /// Queue the overlay's text in the top left corner, if it's open.
+    pub fn draw(&self, text: &mut TextRenderer, info: &DebugInfo) {
+        if !self.visible {
+            return;
+        }
+        text.panel([OVERLAY_MARGIN, OVERLAY_MARGIN], OVERLAY_SCALE, OVERLAY_COLOR, OVERLAY_BACKGROUND, &self.lines(info));
+    }
+}
+
+/// Bytes in the largest unit that keeps the number at least 1.
+/// ```
+/// # use client::debug_overlay::format_bytes;
+/// assert_eq!(format_bytes(512), "512 B");
+/// assert_eq!(format_bytes(3 * 1024 * 1024 / 2), "1.5 MiB");
+/// ```
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    return format!("{:.1} {}", value, UNITS[unit]);
+}
+
+/// Resident memory of this process, from /proc on Linux. None on other platforms, or if it can't be read.
+pub fn process_memory() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.trim_start_matches("VmRSS:").trim().trim_end_matches("kB").trim().parse().ok()?;
+    return Some(kilobytes * 1024);
+}
\ No newline at end of file
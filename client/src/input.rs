@@ -0,0 +1,516 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt, path::Path};
+
+use serde::{Serialize, Deserialize};
+use winit::{
+    event::{DeviceEvent, ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey}
+};
+
+pub const MOVE_FORWARD: &str = "move_forward";
+pub const MOVE_BACK: &str = "move_back";
+pub const MOVE_LEFT: &str = "move_left";
+pub const MOVE_RIGHT: &str = "move_right";
+/// Jump while walking, rise while flying.
+pub const JUMP: &str = "jump";
+/// Sneak while walking, sink while flying.
+pub const SNEAK: &str = "sneak";
+pub const BREAK_BLOCK: &str = "break_block";
+pub const PLACE_BLOCK: &str = "place_block";
+pub const TOGGLE_FLY: &str = "toggle_fly";
+/// Open or close the debug overlay.
+pub const TOGGLE_DEBUG: &str = "toggle_debug";
+/// Open the chat to type a message, or with a slash already typed for a command.
+pub const OPEN_CHAT: &str = "open_chat";
+pub const OPEN_COMMAND: &str = "open_command";
+/// Save the next frame to the screenshots directory.
+pub const SCREENSHOT: &str = "screenshot";
+/// Let go of the mouse cursor, so it can leave the window.
+pub const RELEASE_CURSOR: &str = "release_cursor";
+/// Turning the camera with a stick, as the mouse turns it by raw motion instead.
+pub const LOOK_LEFT: &str = "look_left";
+pub const LOOK_RIGHT: &str = "look_right";
+pub const LOOK_UP: &str = "look_up";
+pub const LOOK_DOWN: &str = "look_down";
+
+/// Analog values at or above this count as held.
+pub const PRESS_THRESHOLD: f32 = 0.5;
+/// Gamepad sticks rarely rest at exactly 0, so anything closer than this is treated as 0.
+pub const GAMEPAD_DEADZONE: f32 = 0.15;
+/// Pixels of touchpad scrolling counted as one line of a mouse wheel.
+pub const PIXELS_PER_LINE: f64 = 40.0;
+
+/// Buttons of a standard layout gamepad, named by position rather than label, as labels differ between brands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    /// Triggers are analog on most gamepads, and report how far they're pulled.
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    /// Up is positive.
+    LeftStickY,
+    RightStickX,
+    /// Up is positive.
+    RightStickY
+}
+
+/// Which half of an axis a binding follows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisDirection {
+    Positive,
+    Negative
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WheelDirection {
+    Up,
+    Down
+}
+
+/// A physical input an action can be bound to. Keys are bound by position on the keyboard, so WASD stays in
+/// the same place on every layout.
+/// In a bindings file, each is written as a table such as `{ key = "KeyW" }`, `{ mouse = "Left" }`,
+/// or `{ gamepad_axis = ["LeftStickY", "positive"] }`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// A notch of the mouse wheel. Counts as a press and release within a single frame.
+    Wheel(WheelDirection),
+    GamepadButton(GamepadButton),
+    GamepadAxis(GamepadAxis, AxisDirection)
+}
+
+/// Reason a bindings file couldn't be loaded.
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(std::io::Error),
+    Parse(toml::de::Error)
+}
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            BindingsError::Io(error) => write!(f, "couldn't read the bindings: {}", error),
+            BindingsError::Parse(error) => write!(f, "couldn't parse the bindings: {}", error)
+        };
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+/// Table of which inputs trigger each named action. An action can have any number of bindings, and an input
+/// can be bound to any number of actions. Saved as TOML, with a line per action listing its bindings.
+/// ```
+/// # use client::input::{Bindings, Binding, MOVE_FORWARD};
+/// # use winit::keyboard::KeyCode;
+/// let mut bindings = Bindings::default();
+/// bindings.rebind(MOVE_FORWARD, Binding::Key(KeyCode::ArrowUp));
+/// let text = bindings.to_toml();
+/// assert!(text.contains(r#"move_forward = [{ key = "ArrowUp" }]"#));
+/// assert_eq!(Bindings::from_toml(&text).unwrap(), bindings);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bindings {
+    actions: BTreeMap<String, Vec<Binding>>
+}
+
+impl Bindings {
+    /// No actions bound at all.
+    pub fn new() -> Bindings {
+        return Bindings { actions: BTreeMap::new() };
+    }
+
+    /// Keyboard and mouse, plus a standard gamepad.
+    pub fn defaults() -> Bindings {
+        let mut bindings = Bindings::new();
+        let stick = |axis, direction| Binding::GamepadAxis(axis, direction);
+        let defaults = [
+            (MOVE_FORWARD, vec![Binding::Key(KeyCode::KeyW), stick(GamepadAxis::LeftStickY, AxisDirection::Positive)]),
+            (MOVE_BACK, vec![Binding::Key(KeyCode::KeyS), stick(GamepadAxis::LeftStickY, AxisDirection::Negative)]),
+            (MOVE_LEFT, vec![Binding::Key(KeyCode::KeyA), stick(GamepadAxis::LeftStickX, AxisDirection::Negative)]),
+            (MOVE_RIGHT, vec![Binding::Key(KeyCode::KeyD), stick(GamepadAxis::LeftStickX, AxisDirection::Positive)]),
+            (JUMP, vec![Binding::Key(KeyCode::Space), Binding::GamepadButton(GamepadButton::South)]),
+            (SNEAK, vec![Binding::Key(KeyCode::ShiftLeft), Binding::GamepadButton(GamepadButton::East)]),
+            (BREAK_BLOCK, vec![Binding::Mouse(MouseButton::Left), Binding::GamepadButton(GamepadButton::RightTrigger)]),
+            (PLACE_BLOCK, vec![Binding::Mouse(MouseButton::Right), Binding::GamepadButton(GamepadButton::LeftTrigger)]),
+            (TOGGLE_FLY, vec![Binding::Key(KeyCode::KeyF), Binding::GamepadButton(GamepadButton::North)]),
+            (TOGGLE_DEBUG, vec![Binding::Key(KeyCode::F3), Binding::GamepadButton(GamepadButton::Select)]),
+            (OPEN_CHAT, vec![Binding::Key(KeyCode::KeyT)]),
+            (OPEN_COMMAND, vec![Binding::Key(KeyCode::Slash)]),
+            (SCREENSHOT, vec![Binding::Key(KeyCode::F2)]),
+            (RELEASE_CURSOR, vec![Binding::Key(KeyCode::Escape), Binding::GamepadButton(GamepadButton::Start)]),
+            (LOOK_LEFT, vec![stick(GamepadAxis::RightStickX, AxisDirection::Negative)]),
+            (LOOK_RIGHT, vec![stick(GamepadAxis::RightStickX, AxisDirection::Positive)]),
+            (LOOK_UP, vec![stick(GamepadAxis::RightStickY, AxisDirection::Positive)]),
+            (LOOK_DOWN, vec![stick(GamepadAxis::RightStickY, AxisDirection::Negative)])
+        ];
+        for (action, inputs) in defaults {
+            for binding in inputs {
+                bindings.bind(action, binding);
+            }
+        }
+        return bindings;
+    }
+
+    pub fn from_toml(text: &str) -> Result<Bindings, toml::de::Error> {
+        return toml::from_str(text);
+    }
+
+    /// Save as TOML. Bindings are written inline rather than as arrays of tables, to keep the file easy to edit by hand.
+    pub fn to_toml(&self) -> String {
+        let mut text = String::new();
+        for (action, bindings) in self.iter() {
+            let bindings: Vec<String> = bindings.iter().map(|binding| {
+                return inline_toml(&toml::Value::try_from(binding).expect("bindings always serialize"));
+            }).collect();
+            text += &format!("{} = [{}]\n", toml_key(action), bindings.join(", "));
+        }
+        return text;
+    }
+
+    /// Load a bindings file, or the defaults if there isn't one yet.
+    pub fn load_or_default(path: &Path) -> Result<Bindings, BindingsError> {
+        return match std::fs::read_to_string(path) {
+            Ok(text) => Bindings::from_toml(&text).map_err(BindingsError::Parse),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Bindings::defaults()),
+            Err(error) => Err(BindingsError::Io(error))
+        };
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        return std::fs::write(path, self.to_toml());
+    }
+
+    /// Add a binding to an action, keeping its others. False if it was already bound.
+    pub fn bind(&mut self, action: &str, binding: Binding) -> bool {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if bindings.contains(&binding) {
+            return false;
+        }
+        bindings.push(binding);
+        return true;
+    }
+
+    /// Make a binding the only one an action has.
+    pub fn rebind(&mut self, action: &str, binding: Binding) {
+        self.actions.insert(action.to_string(), vec![binding]);
+    }
+
+    /// Remove one binding from an action. False if the action didn't have it.
+    pub fn unbind(&mut self, action: &str, binding: Binding) -> bool {
+        let Some(bindings) = self.actions.get_mut(action) else {
+            return false;
+        };
+        let count = bindings.len();
+        bindings.retain(|bound| *bound != binding);
+        let removed = bindings.len() != count;
+        if bindings.is_empty() {
+            self.actions.remove(action);
+        }
+        return removed;
+    }
+
+    /// Remove every binding of an action, leaving it unbound.
+    pub fn clear(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    /// Bindings of an action. Empty if it isn't bound.
+    pub fn get(&self, action: &str) -> &[Binding] {
+        return self.actions.get(action).map_or(&[], |bindings| bindings.as_slice());
+    }
+
+    /// Every bound action, with its bindings.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[Binding])> {
+        return self.actions.iter().map(|(action, bindings)| (action.as_str(), bindings.as_slice()));
+    }
+
+    /// Actions a binding triggers, such as to warn about conflicts when rebinding.
+    pub fn actions(&self, binding: Binding) -> impl Iterator<Item = &str> {
+        return self.actions.iter().filter(move |(_, bindings)| bindings.contains(&binding)).map(|(action, _)| action.as_str());
+    }
+}
+
+/// Keys that aren't only letters, digits, underscores, and dashes must be quoted.
+fn toml_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return key.to_string();
+    }
+    return toml::Value::String(key.to_string()).to_string();
+}
+
+/// Write a value on a single line, with tables written inline.
+fn inline_toml(value: &toml::Value) -> String {
+    return match value {
+        toml::Value::Array(values) => format!("[{}]", values.iter().map(inline_toml).collect::<Vec<_>>().join(", ")),
+        toml::Value::Table(table) => {
+            let entries: Vec<String> = table.iter().map(|(key, value)| format!("{} = {}", toml_key(key), inline_toml(value))).collect();
+            format!("{{ {} }}", entries.join(", "))
+        }
+        value => value.to_string()
+    };
+}
+
+/// What every action was doing over one frame. Made by InputMap::update() once a frame, and queried by gameplay
+/// code by action name. Actions that aren't bound, or don't exist, read as released with a value of 0.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputState {
+    values: HashMap<String, f32>,
+    pressed: HashSet<String>,
+    released: HashSet<String>,
+    mouse_delta: (f64, f64),
+    scroll: f32
+}
+
+impl InputState {
+    /// How far an action is pushed, from 0 to 1. Buttons are 0 or 1, sticks and triggers anywhere between.
+    pub fn value(&self, action: &str) -> f32 {
+        return self.values.get(action).copied().unwrap_or(0.0);
+    }
+
+    /// Whether an action is held down this frame.
+    pub fn held(&self, action: &str) -> bool {
+        return self.value(action) >= PRESS_THRESHOLD;
+    }
+
+    /// Whether an action went down this frame. Stays true for only the one frame, however long it's held.
+    pub fn pressed(&self, action: &str) -> bool {
+        return self.pressed.contains(action);
+    }
+
+    /// Whether an action came up this frame.
+    pub fn released(&self, action: &str) -> bool {
+        return self.released.contains(action);
+    }
+
+    /// A pair of opposing actions as one axis, from -1 to 1, such as move_right against move_left.
+    pub fn axis(&self, positive: &str, negative: &str) -> f32 {
+        return self.value(positive) - self.value(negative);
+    }
+
+    /// Raw mouse movement over the frame, in pixels. Right and down are positive.
+    pub fn mouse_delta(&self) -> (f64, f64) {
+        return self.mouse_delta;
+    }
+
+    /// Lines scrolled over the frame. Up is positive.
+    pub fn scroll(&self) -> f32 {
+        return self.scroll;
+    }
+
+    /// The state of the frame after this one if nothing changes: actions stay held, but nothing is pressed,
+    /// released, moved, or scrolled again.
+    pub fn continued(&self) -> InputState {
+        return InputState { values: self.values.clone(), ..InputState::default() };
+    }
+
+    /// Fold a later frame into this one, such as when several recorded frames are played back in one. Actions are
+    /// held as in the later frame, but presses and releases of either are kept, and motion and scrolling add up.
+    /// ```
+    /// # use client::input::{Binding, Bindings, InputMap, JUMP, MOVE_FORWARD};
+    /// # use winit::keyboard::KeyCode;
+    /// let mut input = InputMap::new(Bindings::defaults());
+    /// input.set(Binding::Key(KeyCode::Space), 1.0);
+    /// let mut state = input.update().clone();
+    /// input.set(Binding::Key(KeyCode::KeyW), 1.0);
+    /// state.merge(input.update());
+    /// assert!(state.pressed(JUMP) && state.pressed(MOVE_FORWARD) && state.held(JUMP));
+    /// ```
+    pub fn merge(&mut self, later: &InputState) {
+        self.values = later.values.clone();
+        self.pressed.extend(later.pressed.iter().cloned());
+        self.released.extend(later.released.iter().cloned());
+        self.mouse_delta.0 += later.mouse_delta.0;
+        self.mouse_delta.1 += later.mouse_delta.1;
+        self.scroll += later.scroll;
+    }
+}
+
+/// Turns raw window, mouse, and gamepad events into actions through a Bindings table.
+/// Events are fed in as they arrive, then update() makes the frame's InputState.
+/// winit doesn't read gamepads, so a gamepad backend feeds them in through set().
+/// ```
+/// # use client::input::{Binding, Bindings, InputMap, JUMP};
+/// # use winit::keyboard::KeyCode;
+/// let mut input = InputMap::new(Bindings::defaults());
+/// input.set(Binding::Key(KeyCode::Space), 1.0);
+/// assert!(input.update().pressed(JUMP));
+/// // Still held next frame, but it only went down once.
+/// let state = input.update();
+/// assert!(state.held(JUMP) && !state.pressed(JUMP));
+/// input.set(Binding::Key(KeyCode::Space), 0.0);
+/// assert!(input.update().released(JUMP));
+/// ```
+pub struct InputMap {
+    bindings: Bindings,
+    /// Value of every input that isn't at rest.
+    raw: HashMap<Binding, f32>,
+    /// Inputs that went down since the last update, so a tap too quick to still be held is still a press.
+    tapped: HashSet<Binding>,
+    mouse_delta: (f64, f64),
+    scroll: f64,
+    /// Action the next input pressed gets bound to, in place of triggering anything.
+    rebinding: Option<String>,
+    state: InputState
+}
+
+impl InputMap {
+    pub fn new(bindings: Bindings) -> InputMap {
+        return InputMap {
+            bindings,
+            raw: HashMap::new(),
+            tapped: HashSet::new(),
+            mouse_delta: (0.0, 0.0),
+            scroll: 0.0,
+            rebinding: None,
+            state: InputState::default()
+        };
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        return &self.bindings;
+    }
+
+    /// Change bindings while playing. Held inputs take effect through their new bindings next update.
+    pub fn bindings_mut(&mut self) -> &mut Bindings {
+        return &mut self.bindings;
+    }
+
+    /// The state made by the last update.
+    pub fn state(&self) -> &InputState {
+        return &self.state;
+    }
+
+    /// Bind the next input pressed to an action, replacing its bindings, such as from a controls menu.
+    /// The input doesn't trigger anything else when pressed.
+    pub fn rebind_next(&mut self, action: &str) {
+        self.rebinding = Some(action.to_string());
+    }
+
+    /// Action waiting for rebind_next()'s input, if any.
+    pub fn rebinding(&self) -> Option<&str> {
+        return self.rebinding.as_deref();
+    }
+
+    pub fn cancel_rebind(&mut self) {
+        self.rebinding = None;
+    }
+
+    /// Set the value of an input, from 0 (at rest) to 1 (fully pushed).
+    pub fn set(&mut self, binding: Binding, value: f32) {
+        let value = value.clamp(0.0, 1.0);
+        if value >= PRESS_THRESHOLD && !self.raw.get(&binding).is_some_and(|old| *old >= PRESS_THRESHOLD) {
+            if let Some(action) = self.rebinding.take() {
+                self.bindings.rebind(&action, binding);
+                return;
+            }
+            self.tapped.insert(binding);
+        }
+        match value > 0.0 {
+            true => self.raw.insert(binding, value),
+            false => self.raw.remove(&binding)
+        };
+    }
+
+    /// Set a gamepad axis from its raw value from -1 to 1, splitting it into its two directions.
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        let value = match value.abs() < GAMEPAD_DEADZONE {
+            true => 0.0,
+            false => (value.abs() - GAMEPAD_DEADZONE) / (1.0 - GAMEPAD_DEADZONE) * value.signum()
+        };
+        self.set(Binding::GamepadAxis(axis, AxisDirection::Positive), value.max(0.0));
+        self.set(Binding::GamepadAxis(axis, AxisDirection::Negative), (-value).max(0.0));
+    }
+
+    /// Release everything, such as when the window loses focus and stops hearing about releases.
+    pub fn clear(&mut self) {
+        self.raw.clear();
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } if !event.repeat => {
+                if let PhysicalKey::Code(key) = event.physical_key {
+                    self.set(Binding::Key(key), (event.state == ElementState::Pressed) as i32 as f32);
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.set(Binding::Mouse(*button), (*state == ElementState::Pressed) as i32 as f32);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y as f64,
+                    MouseScrollDelta::PixelDelta(position) => position.y / PIXELS_PER_LINE
+                };
+                self.scroll += lines;
+                let direction = match lines > 0.0 {
+                    true => WheelDirection::Up,
+                    false => WheelDirection::Down
+                };
+                if lines != 0.0 {
+                    self.set(Binding::Wheel(direction), 1.0);
+                    self.set(Binding::Wheel(direction), 0.0);
+                }
+            }
+            WindowEvent::Focused(false) => self.clear(),
+            _ => {}
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.mouse_delta.0 += delta.0;
+            self.mouse_delta.1 += delta.1;
+        }
+    }
+
+    /// Make this frame's state from the input since the last update. Called once a frame, before gameplay reads it.
+    pub fn update(&mut self) -> &InputState {
+        let mut values = HashMap::new();
+        let mut pressed = HashSet::new();
+        let mut released = HashSet::new();
+        for (action, bindings) in self.bindings.iter() {
+            let value = bindings.iter().map(|binding| self.raw.get(binding).copied().unwrap_or(0.0)).fold(0.0, f32::max);
+            let was_held = self.state.held(action);
+            let is_held = value >= PRESS_THRESHOLD;
+            let tapped = bindings.iter().any(|binding| self.tapped.contains(binding));
+            if !was_held && (is_held || tapped) {
+                pressed.insert(action.to_string());
+            }
+            if (was_held || tapped) && !is_held {
+                released.insert(action.to_string());
+            }
+            if value > 0.0 {
+                values.insert(action.to_string(), value);
+            }
+        }
+        self.state = InputState { values, pressed, released, mouse_delta: self.mouse_delta, scroll: self.scroll as f32 };
+        self.tapped.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll = 0.0;
+        return &self.state;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,26 @@
+pub mod ambience;
+pub mod app;
+pub mod atlas;
+pub mod audio;
+pub mod block_textures;
+pub mod blocks;
+pub mod camera;
+pub mod chat;
+pub mod chunk_renderer;
+pub mod culling;
+pub mod debug_overlay;
+pub mod input;
+pub mod outline;
+pub mod particle_renderer;
+pub mod particles;
+pub mod recording;
+pub mod render_graph;
+pub mod renderer;
+pub mod replay;
+pub mod screenshot;
+pub mod settings;
+pub mod signs;
+pub mod sky;
+pub mod standby;
+pub mod targeting;
+pub mod text;
\ No newline at end of file
@@ -1,116 +1,88 @@
-#![feature(fn_traits)]
-
-use std::{sync::{Arc, Mutex}, cell::UnsafeCell, vec};
-
-use shared::engine::job::job::{JobRunDataBuffer, Job};
-
-fn func(x: u32, y: u32, z: u32) {
-
-}
-
-struct Example {
-    num: u32
-}
-
-impl Example {
-    fn display_mut(&mut self) {
-        println!("{}", self.num);
-    }
-    fn display(&self) {
-        println!("{}", self.num);
+use std::{env, path::{Path, PathBuf}, sync::Arc};
+
+use winit::event_loop::EventLoop;
+
+use client::{ambience::Ambience, app::App, audio::Audio, recording::{InputPlayback, InputRecorder}, replay::ReplayViewer, settings::Settings};
+use shared::engine::{asset::AssetManager, config::EngineConfig, event::bus::MessageBus, job::{system::JobSystem, topology::ThreadProfile}, net::replay::Replay};
+
+/// Engine config, such as how many job threads to run, relative to the working directory.
+const CONFIG_PATH: &str = "client.toml";
+/// Video, audio and control settings, relative to the working directory.
+const SETTINGS_PATH: &str = "settings.toml";
+/// Textures, models and sounds, relative to the working directory.
+const ASSETS_PATH: &str = "assets";
+/// Watch the replay at the path after it instead of playing.
+const REPLAY_FLAG: &str = "--replay";
+/// Record the player's input to the path after it.
+const RECORD_INPUT_FLAG: &str = "--record-input";
+/// Play the input recorded at the path after it in place of the player's, then exit.
+const PLAY_INPUT_FLAG: &str = "--play-input";
+/// Play input back without a window or sound, as fast as it runs, printing where the camera ended up.
+const HEADLESS_FLAG: &str = "--headless";
+/// Seconds each frame of headless playback steps by.
+const HEADLESS_FRAME_SECONDS: f32 = 1.0 / 60.0;
+
+/// The path after a flag, if the flag was given.
+fn flag_path(flag: &str) -> Result<Option<PathBuf>, String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next().map(|path| Some(PathBuf::from(path))).ok_or_else(|| format!("usage: {} <file>", flag));
+        }
     }
-}
-/* 
-trait JobRun {
-    fn invoke(&mut self);
+    return Ok(None);
 }
 
-struct Job<T: Default> {
-    val: T,
-    func: fn (&mut T)
+/// Play recorded input without a window, printing where it left the camera, so it can be checked by a script.
+fn play_headless(jobs: Arc<JobSystem>, settings: Settings, playback: InputPlayback) -> Result<(), Box<dyn std::error::Error>> {
+    let mut app = App::new(jobs, settings).with_input_playback(playback);
+    let seconds = app.run_headless(HEADLESS_FRAME_SECONDS);
+    let position = app.camera().position();
+    println!("played {:.2}s of input, camera at {:.2} {:.2} {:.2}", seconds, position.x, position.y, position.z);
+    return match app.take_error() {
+        Some(error) => Err(error),
+        None => Ok(())
+    };
 }
 
-impl<T: Default> Job<T> {
-    fn new(val: T, func: fn (&mut T)) -> Box<dyn JobRun>
-    where T: 'static {
-        return Box::new(
-            Job {
-                val,
-                func
-            }
-        )
-
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let replay = match flag_path(REPLAY_FLAG)? {
+        Some(path) => Some(Replay::open(&path).map_err(|error| format!("can't open replay {}: {}", path.display(), error))?),
+        None => None
+    };
+    let playback = match flag_path(PLAY_INPUT_FLAG)? {
+        Some(path) => Some(InputPlayback::open(&path).map_err(|error| format!("can't open input recording {}: {}", path.display(), error))?),
+        None => None
+    };
+    let config = EngineConfig::load(Path::new(CONFIG_PATH))?;
+    let settings = Settings::load_or_default(Path::new(SETTINGS_PATH))?;
+    let jobs = Arc::new(JobSystem::new(config.jobs.thread_count(ThreadProfile::Client)));
+    if env::args().any(|arg| arg == HEADLESS_FLAG) {
+        let playback = playback.ok_or_else(|| format!("{} needs {} <file>", HEADLESS_FLAG, PLAY_INPUT_FLAG))?;
+        return play_headless(jobs, settings, playback);
     }
-}
-
-impl<T: Default> JobRun for Job<T> { 
-    fn invoke(&mut self) {
-        let temp = (std::mem::replace(&mut self.val, T::default()), );
-        std::ops::Fn::call(&self.func, temp);
+    let event_loop = EventLoop::new()?;
+    let events = Arc::new(MessageBus::new());
+    let assets = Arc::new(AssetManager::new(Path::new(ASSETS_PATH), jobs.clone()).with_events(events.clone()));
+    let mut audio = Audio::new(assets.clone());
+    // The game is playable without sound, so carry on silently without an output device.
+    if let Err(error) = audio.start_output() {
+        eprintln!("sound is off: {}", error);
     }
-}*/
-
-fn empty() { println!("hi")}
-
-
-fn main() {
-    //println!("Hello, world!");
-
-    //let mut a = Example {num: 1};
-
-    //println!("{}", std::mem::size_of::<JobFunc>());
-
-    //let buf = JobRunDataBuffer::new::<String>(String::from("hello world!"));
-    //let s = buf.get::<String>();
-    //println!("did the thing? {}", s);
-
-    //let mut vec = Arc::new(vec![0u32]);
-
-    /*
-    let job1 = unsafe { JobData::from_obj_mut::<Example>(&mut a, Example::display_mut) };
-    let job2 = unsafe { JobData::from_obj::<Example>(&a, Example::display) };
-    unsafe {
-        a.num = 2;
-        job1.invoke();
-        a.num = 3;
-        job2.invoke();
-    } */
-    
-    //unsafe { job.invoke_member(JobRunDataBuffer::default()) };
-    //unsafe { job2.invoke_member(JobRunDataBuffer::default()) };
-
-    //let job = JobFunc::new( move || a.display() );
-    //job.func.as_mut()();
-
-    //let job2 = JobFunc::new( || a.display() );
-    //job.func.as_mut()();
-
-
-    //let bounded = || func(1, 2, 3);
-
-
-
-    let mut v = vec![1, 2, 3, 4, 5];
-
-    let job = Job::from_closure(move || v.push(1));
-
-
-    /*
-    let mut v = vec![];
-
-    for _ in 0..10 {
-        let job = Job::from_func(empty);
-        v.push(job);
+    let mut app = App::new(jobs, settings).with_audio(audio).with_ambience(Ambience::new(assets.clone())).with_assets(assets, events);
+    if let Some(replay) = replay {
+        app = app.with_replay(ReplayViewer::new(replay));
     }
-
-    for job in v.iter() {
-        job.invoke();
+    if let Some(playback) = playback {
+        app = app.with_input_playback(playback);
     }
-    
-    for job in v {
-        job.invoke();
-    } */
-    
-  
-
-}
+    if let Some(path) = flag_path(RECORD_INPUT_FLAG)? {
+        app = app.with_input_recording(InputRecorder::create(&path)?);
+    }
+    event_loop.run_app(&mut app)?;
+    app.settings().save(Path::new(SETTINGS_PATH))?;
+    return match app.take_error() {
+        Some(error) => Err(error),
+        None => Ok(())
+    };
+}
\ No newline at end of file
@@ -0,0 +1,161 @@
+use std::mem::size_of;
+
+use shared::engine::math::{aabb::Aabb, matrix::Mat4};
+
+use crate::{chunk_renderer::DEPTH_FORMAT, renderer::as_bytes};
+
+/// Color of block outlines. Dark and translucent, so it shows on bright blocks without hiding dark ones.
+pub const OUTLINE_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+/// Each box is drawn as its 12 edges, each a line of 2 vertices.
+const VERTICES_PER_BOX: usize = 24;
+/// Boxes that fit in the vertex buffer before it grows. Most blocks have a single box.
+const INITIAL_BOX_CAPACITY: u64 = 16;
+
+/// Uniforms of the outline shader. Matches Globals in outline.wgsl.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Globals {
+    view_projection: Mat4,
+    color: [f32; 4]
+}
+
+/// The 12 edges of a box, as pairs of corners.
+fn box_edges(aabb: &Aabb) -> [[f32; 3]; VERTICES_PER_BOX] {
+    let corner = |i: usize| {
+        return [
+            if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+            if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+            if i & 4 == 0 { aabb.min.z } else { aabb.max.z }
+        ];
+    };
+    // Corner indices are a bit per axis, so an edge joins two corners differing in one bit.
+    const EDGES: [(usize, usize); 12] = [(0, 1), (2, 3), (4, 5), (6, 7), (0, 2), (1, 3), (4, 6), (5, 7), (0, 4), (1, 5), (2, 6), (3, 7)];
+    let mut vertices = [[0.0; 3]; VERTICES_PER_BOX];
+    for (i, (a, b)) in EDGES.iter().enumerate() {
+        vertices[i * 2] = corner(*a);
+        vertices[i * 2 + 1] = corner(*b);
+    }
+    return vertices;
+}
+
+/// Draws the edges of boxes over the world, such as the outline of the targeted block.
+/// Boxes are relative to the render origin, the same as the view's view_projection.
+pub struct OutlineRenderer {
+    pipeline: wgpu::RenderPipeline,
+    globals: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertices: wgpu::Buffer,
+    boxes: Vec<Aabb>,
+    /// Vertices uploaded by the last prepare().
+    vertex_count: u32
+}
+
+impl OutlineRenderer {
+    pub fn new(device: &wgpu::Device, color_format: wgpu::TextureFormat) -> OutlineRenderer {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/outline.wgsl"));
+        let globals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("outline globals"),
+            size: size_of::<Globals>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("outline"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+                count: None
+            }]
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("outline"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: globals.as_entire_binding() }]
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("outline"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("outline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[Some(wgpu::VertexBufferLayout {
+                    array_stride: size_of::<[f32; 3]>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3]
+                })]
+            },
+            primitive: wgpu::PrimitiveState { topology: wgpu::PrimitiveTopology::LineList, ..Default::default() },
+            // Tested against the world's depth so outlines hide behind other blocks, but not written.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: Some(false),
+                depth_compare: Some(wgpu::CompareFunction::LessEqual),
+                stencil: Default::default(),
+                bias: Default::default()
+            }),
+            multisample: Default::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            multiview_mask: None,
+            cache: None
+        });
+        let vertices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("outline vertices"),
+            size: INITIAL_BOX_CAPACITY * (VERTICES_PER_BOX * size_of::<[f32; 3]>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        return OutlineRenderer { pipeline, globals, bind_group, vertices, boxes: Vec::new(), vertex_count: 0 };
+    }
+
+    /// Replace the boxes outlined from now on. Empty to outline nothing.
+    pub fn set_boxes(&mut self, boxes: Vec<Aabb>) {
+        self.boxes = boxes;
+    }
+
+    /// Upload the boxes' edges and this frame's view. Called before the render pass.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, view_projection: Mat4) {
+        let vertices: Vec<[f32; 3]> = self.boxes.iter().flat_map(box_edges).collect();
+        self.vertex_count = vertices.len() as u32;
+        if vertices.is_empty() {
+            return;
+        }
+        queue.write_buffer(&self.globals, 0, as_bytes(&[Globals { view_projection, color: OUTLINE_COLOR }]));
+        let size = (vertices.len() * size_of::<[f32; 3]>()) as u64;
+        if size > self.vertices.size() {
+            self.vertices = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("outline vertices"),
+                size: size.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            });
+        }
+        queue.write_buffer(&self.vertices, 0, as_bytes(&vertices));
+    }
+
+    /// Draw the outlines uploaded by prepare(). Drawn after the world, so they're depth tested against it.
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertices.slice(..));
+        pass.draw(0..self.vertex_count, 0..1);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,177 @@
+use std::mem::size_of;
+
+use shared::engine::math::{matrix::Mat4, vector::Vec3};
+
+use crate::{block_textures::BlockTextures, chunk_renderer::DEPTH_FORMAT, particles::ParticleInstance, renderer::as_bytes};
+
+/// Particles that fit in the instance buffer before it grows.
+const INITIAL_PARTICLE_CAPACITY: u64 = 1024;
+
+/// Uniforms of the particle shader. Matches Globals in particle.wgsl.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Globals {
+    view_projection: Mat4,
+    /// Directions across and up the screen, which particles are drawn facing.
+    right: [f32; 3],
+    padding: f32,
+    up: [f32; 3],
+    padding2: f32
+}
+
+/// Draws particles as instanced quads facing the camera, textured from the block texture atlas.
+pub struct ParticleRenderer {
+    pipeline: wgpu::RenderPipeline,
+    globals: wgpu::Buffer,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    instances: wgpu::Buffer,
+    particles: Vec<ParticleInstance>,
+    /// Camera right and up for the particles set.
+    axes: (Vec3, Vec3),
+    /// Instances uploaded by the last prepare().
+    instance_count: u32
+}
+
+impl ParticleRenderer {
+    pub fn new(device: &wgpu::Device, color_format: wgpu::TextureFormat, textures: &BlockTextures) -> ParticleRenderer {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/particle.wgsl"));
+        let globals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle globals"),
+            size: size_of::<Globals>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particle"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None
+                }
+            ]
+        });
+        let bind_group = ParticleRenderer::create_bind_group(device, &layout, &globals, textures);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particle"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("particle"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[Some(wgpu::VertexBufferLayout {
+                    array_stride: size_of::<ParticleInstance>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32, 2 => Float32x4, 3 => Float32x4]
+                })]
+            },
+            primitive: Default::default(),
+            // Hidden behind blocks, but not written, so particles don't cut holes in each other.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: Some(false),
+                depth_compare: Some(wgpu::CompareFunction::LessEqual),
+                stencil: Default::default(),
+                bias: Default::default()
+            }),
+            multisample: Default::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            multiview_mask: None,
+            cache: None
+        });
+        let instances = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle instances"),
+            size: INITIAL_PARTICLE_CAPACITY * size_of::<ParticleInstance>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        return ParticleRenderer { pipeline, globals, layout, bind_group, instances, particles: Vec::new(), axes: (Vec3::X, Vec3::Y), instance_count: 0 };
+    }
+
+    fn create_bind_group(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, globals: &wgpu::Buffer, textures: &BlockTextures) -> wgpu::BindGroup {
+        return device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particle"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: globals.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(textures.view()) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(textures.sampler()) }
+            ]
+        });
+    }
+
+    /// Draw textured particles from a new block texture atlas.
+    pub fn set_textures(&mut self, device: &wgpu::Device, textures: &BlockTextures) {
+        self.bind_group = ParticleRenderer::create_bind_group(device, &self.layout, &self.globals, textures);
+    }
+
+    /// Replace the particles drawn from now on, facing a camera with the given right and up directions.
+    pub fn set_particles(&mut self, particles: Vec<ParticleInstance>, right: Vec3, up: Vec3) {
+        self.particles = particles;
+        self.axes = (right, up);
+    }
+
+    /// Upload the particles and this frame's view. Called before the render pass.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, view_projection: Mat4) {
+        self.instance_count = self.particles.len() as u32;
+        if self.particles.is_empty() {
+            return;
+        }
+        let (right, up) = self.axes;
+        let globals = Globals { view_projection, right: right.to_array(), padding: 0.0, up: up.to_array(), padding2: 0.0 };
+        queue.write_buffer(&self.globals, 0, as_bytes(&[globals]));
+        let size = (self.particles.len() * size_of::<ParticleInstance>()) as u64;
+        if size > self.instances.size() {
+            self.instances = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("particle instances"),
+                size: size.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            });
+        }
+        queue.write_buffer(&self.instances, 0, as_bytes(&self.particles));
+    }
+
+    /// Draw the particles uploaded by prepare(), as 6 vertices an instance. Drawn after the world.
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>) {
+        if self.instance_count == 0 {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.instances.slice(..));
+        pass.draw(0..6, 0..self.instance_count);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,466 @@
+use std::sync::Arc;
+
+use shared::engine::{
+    job::system::JobSystem,
+    light::probe::LightProbeGrid,
+    lod::LodPolicy,
+    math::{coords::{BlockPos, WorldPos}, precision::RenderOrigin, rng::WorldRng, vector::Vec3},
+    mesh::texture::UvRect,
+    physics::body::RigidBody,
+    world::World
+};
+
+/// Particles alive at once. Emitters stop spawning while the pool is full.
+pub const MAX_PARTICLES: usize = 16384;
+/// Particles simulated by each job.
+pub const DEFAULT_PARTICLE_BATCH_SIZE: usize = 1024;
+/// Speed kept, as a fraction, when a particle hits a block.
+pub const BOUNCE: f32 = 0.2;
+
+/// How the particles of an emitter look and move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParticleStyle {
+    /// Seconds each particle lives, picked between the two.
+    pub lifetime: [f32; 2],
+    /// Velocity every particle starts with, in blocks per second.
+    pub velocity: Vec3,
+    /// Top speed added in a random direction, so particles spread out.
+    pub spread: f32,
+    /// Blocks per second squared pulling particles down. Negative makes them rise.
+    pub gravity: f32,
+    /// Fraction of speed lost per second to the air.
+    pub drag: f32,
+    /// Width in blocks when spawned and when it dies.
+    pub size: [f32; 2],
+    /// RGBA when spawned and when it dies, multiplied with the texture.
+    pub color: [[f32; 4]; 2],
+    /// Part of the block texture atlas to draw. Each particle draws a random piece of it a quarter of its size.
+    /// None draws soft round particles of just the color.
+    pub texture: Option<UvRect>,
+    /// Whether particles stop at solid blocks rather than passing through them.
+    pub collides: bool
+}
+
+impl ParticleStyle {
+    /// Bits of a broken block, textured with pieces of its texture, that fall and bounce off the ground.
+    pub fn debris(texture: UvRect) -> ParticleStyle {
+        return ParticleStyle {
+            lifetime: [0.5, 1.5],
+            velocity: Vec3::new(0.0, 2.0, 0.0),
+            spread: 3.0,
+            gravity: RigidBody::DEFAULT_GRAVITY * 0.5,
+            drag: 0.5,
+            size: [0.12, 0.12],
+            color: [[1.0; 4]; 2],
+            texture: Some(texture),
+            collides: true
+        };
+    }
+
+    /// Grey puffs that drift up, grow, and fade.
+    pub fn smoke() -> ParticleStyle {
+        return ParticleStyle {
+            lifetime: [1.5, 3.0],
+            velocity: Vec3::new(0.0, 0.8, 0.0),
+            spread: 0.3,
+            gravity: -0.5,
+            drag: 1.0,
+            size: [0.2, 0.6],
+            color: [[0.35, 0.35, 0.35, 0.7], [0.6, 0.6, 0.6, 0.0]],
+            texture: None,
+            collides: false
+        };
+    }
+}
+
+/// Spawns particles at a point in the world. A burst spawns when it starts, then particles keep coming at a rate
+/// until it's been going for its duration.
+/// ```
+/// # use client::particles::{Emitter, ParticleStyle};
+/// # use shared::engine::math::{coords::WorldPos, vector::Vec3};
+/// let chimney = Emitter::new(WorldPos::new(4.5, 70.0, 4.5), ParticleStyle::smoke()).with_rate(8.0, 10.0);
+/// let sparks = Emitter::new(WorldPos::new(0.0, 64.0, 0.0), ParticleStyle::smoke()).with_burst(20).with_area(Vec3::splat(0.25));
+/// # assert_eq!((chimney.burst, sparks.duration), (0, 0.0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Emitter {
+    pub position: WorldPos,
+    pub style: ParticleStyle,
+    /// Particles spawned at once when it starts.
+    pub burst: u32,
+    /// Particles spawned per second after the burst.
+    pub rate: f32,
+    /// Seconds it keeps spawning particles at its rate for.
+    pub duration: f32,
+    /// Half the size of the box around the position particles spawn in.
+    pub area: Vec3
+}
+
+impl Emitter {
+    pub fn new(position: WorldPos, style: ParticleStyle) -> Emitter {
+        return Emitter { position, style, burst: 0, rate: 0.0, duration: 0.0, area: Vec3::ZERO };
+    }
+
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        return self;
+    }
+
+    pub fn with_rate(mut self, rate: f32, duration: f32) -> Self {
+        self.rate = rate;
+        self.duration = duration;
+        return self;
+    }
+
+    pub fn with_area(mut self, area: Vec3) -> Self {
+        self.area = area;
+        return self;
+    }
+
+    /// Debris of a block breaking, from all over the block, drawn with pieces of the block's texture.
+    pub fn block_break(block: BlockPos, texture: UvRect) -> Emitter {
+        let center = WorldPos::new(block.x as f64 + 0.5, block.y as f64 + 0.5, block.z as f64 + 0.5);
+        return Emitter::new(center, ParticleStyle::debris(texture)).with_burst(32).with_area(Vec3::splat(0.4));
+    }
+
+    /// Smoke rising from a point, such as a torch or fire, for some seconds.
+    pub fn smoke(position: WorldPos, duration: f32) -> Emitter {
+        return Emitter::new(position, ParticleStyle::smoke()).with_rate(6.0, duration).with_area(Vec3::new(0.1, 0.0, 0.1));
+    }
+}
+
+/// One live particle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub position: WorldPos,
+    pub velocity: Vec3,
+    /// Seconds since it spawned. It dies once this reaches its lifetime.
+    pub age: f32,
+    pub lifetime: f32,
+    /// Part of the atlas it draws, already picked from its style's texture.
+    pub texture: Option<UvRect>,
+    pub style: ParticleStyle
+}
+
+impl Particle {
+    /// How far through its life it is, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        return (self.age / self.lifetime).min(1.0);
+    }
+
+    pub fn is_alive(&self) -> bool {
+        return self.age < self.lifetime;
+    }
+
+    /// Width in blocks at its age.
+    pub fn size(&self) -> f32 {
+        let [start, end] = self.style.size;
+        return start + (end - start) * self.progress();
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        let [start, end] = self.style.color;
+        let progress = self.progress();
+        return [0, 1, 2, 3].map(|channel| start[channel] + (end[channel] - start[channel]) * progress);
+    }
+}
+
+/// Whether a point is inside the collision boxes of the block it's in.
+fn inside_block(world: &World, position: WorldPos) -> bool {
+    let block = position.block();
+    let Some(id) = world.get_block(block) else {
+        return false;
+    };
+    let local = Vec3::new((position.x - block.x as f64) as f32, (position.y - block.y as f64) as f32, (position.z - block.z as f64) as f32);
+    return world.block_shapes().boxes(id).iter().any(|bounds| bounds.contains_point(local));
+}
+
+/// Move particles forward in time, and remove the ones that died. Particles that collide move one axis at a time,
+/// and bounce off blocks on the axes they hit.
+/// ```
+/// # use client::particles::{simulate_particles, Particle, ParticleStyle};
+/// # use shared::engine::math::{coords::WorldPos, vector::Vec3};
+/// let style = ParticleStyle { gravity: 0.0, drag: 0.0, ..ParticleStyle::smoke() };
+/// let particle = Particle { position: WorldPos::ORIGIN, velocity: Vec3::new(1.0, 0.0, 0.0), age: 0.0, lifetime: 1.0, texture: None, style };
+/// let mut particles = vec![particle, Particle { age: 0.9, ..particle }];
+/// simulate_particles(&mut particles, 0.5, None);
+/// assert_eq!(particles.len(), 1);
+/// assert_eq!(particles[0].position, WorldPos::new(0.5, 0.0, 0.0));
+/// ```
+pub fn simulate_particles(particles: &mut Vec<Particle>, seconds: f32, world: Option<&World>) {
+    for particle in particles.iter_mut() {
+        particle.age += seconds;
+        particle.velocity.y -= particle.style.gravity * seconds;
+        particle.velocity *= (1.0 - particle.style.drag * seconds).max(0.0);
+        let step = particle.velocity * seconds;
+        let world = world.filter(|_| particle.style.collides);
+        for axis in 0..3 {
+            let mut position = particle.position;
+            match axis {
+                0 => position.x += step.x as f64,
+                1 => position.y += step.y as f64,
+                _ => position.z += step.z as f64
+            }
+            if world.is_some_and(|world| inside_block(world, position)) {
+                // Bounce back, losing most of the speed, and slide along the ground.
+                match axis {
+                    0 => particle.velocity.x *= -BOUNCE,
+                    1 => {
+                        particle.velocity.y *= -BOUNCE;
+                        particle.velocity.x *= 1.0 - BOUNCE;
+                        particle.velocity.z *= 1.0 - BOUNCE;
+                    }
+                    _ => particle.velocity.z *= -BOUNCE
+                }
+                continue;
+            }
+            particle.position = position;
+        }
+    }
+    particles.retain(Particle::is_alive);
+}
+
+/// Handle to an emitter, to stop it early.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EmitterId(u64);
+
+struct ActiveEmitter {
+    id: EmitterId,
+    emitter: Emitter,
+    /// Seconds since it started.
+    age: f32,
+    /// Part of a particle owed from earlier frames, as fractions of a particle add up at low rates.
+    owed: f32,
+    /// Whole particles owed since the emitter entered its LOD band, of which the band's budget is spawned.
+    requested: u32,
+    spawned: u32,
+    /// LOD band the emitter was in last frame. Counting starts again in a new band, so nothing is spawned to catch up.
+    level: Option<usize>
+}
+
+/// A pool of CPU simulated particles, and the emitters spawning them. Particles are simulated in batches on the
+/// job system each frame, then drawn as instances.
+/// ```
+/// # use std::sync::Arc;
+/// # use client::particles::{Emitter, ParticleSystem};
+/// # use shared::engine::{job::system::JobSystem, math::coords::WorldPos};
+/// let mut particles = ParticleSystem::new(Arc::new(JobSystem::new(2)));
+/// let smoke = particles.spawn(Emitter::smoke(WorldPos::new(0.0, 64.0, 0.0), 5.0).with_burst(10));
+/// particles.update(0.5, None);
+/// // The burst, then 3 more at 6 a second.
+/// assert_eq!(particles.len(), 13);
+/// assert!(particles.stop(smoke));
+/// particles.update(0.5, None);
+/// assert_eq!((particles.len(), particles.emitter_count()), (13, 0));
+/// ```
+pub struct ParticleSystem {
+    jobs: Arc<JobSystem>,
+    particles: Vec<Particle>,
+    emitters: Vec<ActiveEmitter>,
+    next_id: u64,
+    rng: WorldRng,
+    max_particles: usize,
+    batch_size: usize,
+    /// Spawns fewer particles from emitters far from the viewer, if set.
+    lod: Option<LodPolicy>,
+    viewer: WorldPos
+}
+
+impl ParticleSystem {
+    pub fn new(jobs: Arc<JobSystem>) -> ParticleSystem {
+        return ParticleSystem {
+            jobs,
+            particles: Vec::new(),
+            emitters: Vec::new(),
+            next_id: 0,
+            rng: WorldRng::new(0),
+            max_particles: MAX_PARTICLES,
+            batch_size: DEFAULT_PARTICLE_BATCH_SIZE,
+            lod: None,
+            viewer: WorldPos::ORIGIN
+        };
+    }
+
+    /// Spawn only the share of each emitter's particles its distance from the viewer allows.
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use client::particles::{Emitter, ParticleSystem};
+    /// # use shared::engine::{job::system::JobSystem, lod::LodPolicy, math::coords::WorldPos};
+    /// let mut particles = ParticleSystem::new(Arc::new(JobSystem::new(1))).with_lod(LodPolicy::for_view_distance(256.0));
+    /// particles.set_viewer(WorldPos::new(0.0, 64.0, 0.0));
+    /// particles.spawn(Emitter::smoke(WorldPos::new(4.0, 64.0, 0.0), 10.0).with_burst(20));
+    /// particles.spawn(Emitter::smoke(WorldPos::new(100.0, 64.0, 0.0), 10.0).with_burst(20));
+    /// particles.spawn(Emitter::smoke(WorldPos::new(300.0, 64.0, 0.0), 10.0).with_burst(20));
+    /// particles.update(0.0, None);
+    /// // All of the near burst, half of the one further off, and none past the view distance.
+    /// assert_eq!(particles.len(), 30);
+    /// ```
+    pub fn with_lod(mut self, policy: LodPolicy) -> Self {
+        self.lod = Some(policy);
+        return self;
+    }
+
+    /// Change the level of detail, such as when the render distance changes.
+    pub fn set_lod(&mut self, policy: LodPolicy) {
+        self.lod = Some(policy);
+    }
+
+    /// Where LOD distances are measured from, normally the camera.
+    pub fn set_viewer(&mut self, viewer: WorldPos) {
+        self.viewer = viewer;
+    }
+
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        return self;
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        debug_assert!(batch_size > 0, "Particle batch size must be positive");
+        self.batch_size = batch_size;
+        return self;
+    }
+
+    /// Live particles.
+    pub fn len(&self) -> usize {
+        return self.particles.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.particles.is_empty();
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        return &self.particles;
+    }
+
+    /// Emitters still spawning particles.
+    pub fn emitter_count(&self) -> usize {
+        return self.emitters.len();
+    }
+
+    /// Start an emitter. Its burst spawns on the next update.
+    pub fn spawn(&mut self, emitter: Emitter) -> EmitterId {
+        let id = EmitterId(self.next_id);
+        self.next_id += 1;
+        self.emitters.push(ActiveEmitter { id, emitter, age: 0.0, owed: emitter.burst as f32, requested: 0, spawned: 0, level: None });
+        return id;
+    }
+
+    /// Stop an emitter spawning particles. The ones it spawned live out their lives. False if it already stopped.
+    pub fn stop(&mut self, id: EmitterId) -> bool {
+        let count = self.emitters.len();
+        self.emitters.retain(|active| active.id != id);
+        return self.emitters.len() != count;
+    }
+
+    /// Remove every particle and emitter, such as when leaving a world.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.emitters.clear();
+    }
+
+    fn spawn_particle(&mut self, emitter: &Emitter) {
+        let style = emitter.style;
+        let mut random = |range: f32| self.rng.range_f32(-1.0..1.0) * range;
+        let offset = Vec3::new(random(emitter.area.x), random(emitter.area.y), random(emitter.area.z));
+        let spread = Vec3::new(random(1.0), random(1.0), random(1.0)).normalize_or_zero() * random(style.spread).abs();
+        let texture = style.texture.map(|rect| {
+            // A random quarter of the texture, snapped to quarters so it lines up with its pixels.
+            let piece = [rect.size[0] / 4.0, rect.size[1] / 4.0];
+            let (x, y) = (self.rng.range_i32(0..4) as f32, self.rng.range_i32(0..4) as f32);
+            return UvRect::new([rect.min[0] + x * piece[0], rect.min[1] + y * piece[1]], piece);
+        });
+        let [shortest, longest] = style.lifetime;
+        self.particles.push(Particle {
+            position: emitter.position + WorldPos::new(offset.x as f64, offset.y as f64, offset.z as f64),
+            velocity: style.velocity + spread,
+            age: 0.0,
+            lifetime: shortest + (longest - shortest) * self.rng.next_f32(),
+            texture,
+            style
+        });
+    }
+
+    /// Spawn what emitters owe this frame, and drop emitters that finished.
+    fn emit(&mut self, seconds: f32) {
+        let mut emitters = std::mem::take(&mut self.emitters);
+        for active in emitters.iter_mut() {
+            let emitting = seconds.min(active.emitter.duration - active.age).max(0.0);
+            active.owed += active.emitter.rate * emitting;
+            active.age += seconds;
+            let whole = active.owed.floor();
+            active.owed -= whole;
+            let budget = match self.lod.as_ref() {
+                Some(policy) => {
+                    let distance = self.viewer.distance(active.emitter.position) as f32;
+                    let level = policy.level(distance);
+                    if level != active.level {
+                        active.level = level;
+                        active.requested = 0;
+                        active.spawned = 0;
+                    }
+                    active.requested += whole as u32;
+                    policy.particle_budget(distance, active.requested)
+                }
+                None => {
+                    active.requested += whole as u32;
+                    active.requested
+                }
+            };
+            while active.spawned < budget && self.particles.len() < self.max_particles {
+                self.spawn_particle(&active.emitter);
+                active.spawned += 1;
+            }
+        }
+        emitters.retain(|active| active.age < active.emitter.duration);
+        // Emitters spawned while emitting, if any, come after the ones kept.
+        emitters.append(&mut self.emitters);
+        self.emitters = emitters;
+    }
+
+    /// Spawn particles from the emitters, then move every particle forward in time on the job system, colliding with
+    /// the world's blocks if there is one.
+    pub fn update(&mut self, seconds: f32, world: Option<&Arc<World>>) {
+        self.emit(seconds);
+        if self.particles.is_empty() {
+            return;
+        }
+        let futures: Vec<_> = self.particles.chunks(self.batch_size).map(|batch| {
+            let mut batch = batch.to_vec();
+            let world = world.cloned();
+            return self.jobs.run_job(move || {
+                simulate_particles(&mut batch, seconds, world.as_deref());
+                return std::mem::take(&mut batch);
+            });
+        }).collect();
+        self.particles = futures.into_iter().flat_map(|future| future.wait()).collect();
+    }
+
+    /// Every particle as an instance to draw, relative to the render origin, shaded by the light probes around it
+    /// and the daylight from WorldTime::daylight(), so particles match the terrain they're in.
+    pub fn instances(&self, origin: RenderOrigin, light: &LightProbeGrid, daylight: f32) -> Vec<ParticleInstance> {
+        return self.particles.iter().map(|particle| {
+            let shade = light.sample(particle.position).color(daylight);
+            let [red, green, blue, alpha] = particle.color();
+            return ParticleInstance {
+                position: origin.to_render(particle.position).to_array(),
+                size: particle.size(),
+                color: [red * shade.x, green * shade.y, blue * shade.z, alpha],
+                texture: particle.texture.map_or([0.0; 4], UvRect::to_array)
+            };
+        }).collect();
+    }
+}
+
+/// A particle as drawn, laid out to be uploaded to the GPU as is. Matches InstanceInput in particle.wgsl.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ParticleInstance {
+    /// Center, relative to the render origin.
+    pub position: [f32; 3],
+    pub size: f32,
+    pub color: [f32; 4],
+    /// Rect of the block texture atlas drawn, as min u, min v, width, height. No width draws a soft round particle.
+    pub texture: [f32; 4]
+}
\ No newline at end of file
@@ -0,0 +1,196 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path
+};
+
+use serde::{Deserialize, Serialize};
+use winit::keyboard::Key;
+
+use crate::input::InputState;
+
+/// First line of an input recording, naming the format and its version.
+pub const RECORDING_HEADER: &str = "cube input recording 1";
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// Something the player did, as recorded. Actions are recorded rather than keys, so a recording plays back the
+/// same whatever the bindings were.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedInput {
+    /// Actions as InputMap::update() made them for a frame. Only frames that changed something are recorded.
+    Frame(InputState),
+    /// A key pressed, with the text it typed, as the chat and sign editor read them.
+    Key {
+        key: Key,
+        text: Option<String>
+    },
+    Resized {
+        width: u32,
+        height: u32
+    },
+    Focused(bool),
+    CloseRequested
+}
+
+/// An input, and the seconds since recording started that it happened at.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimedInput {
+    pub seconds: f64,
+    pub input: RecordedInput
+}
+
+/// Writes an input recording: the header line, then a line of JSON per input, so a recording cut short by a
+/// crash can still be played up to where it stopped, and tests can be written or edited by hand.
+pub struct InputRecorder<W: Write> {
+    out: W,
+    seconds: f64,
+    /// Last frame recorded, so frames that only carry on holding the same actions are left out.
+    last_frame: InputState
+}
+
+impl InputRecorder<BufWriter<File>> {
+    /// Start a recording file, creating its directory if needed.
+    pub fn create(path: &Path) -> io::Result<InputRecorder<BufWriter<File>>> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        return InputRecorder::new(BufWriter::new(File::create(path)?));
+    }
+}
+
+impl<W: Write> InputRecorder<W> {
+    pub fn new(mut out: W) -> io::Result<InputRecorder<W>> {
+        writeln!(out, "{}", RECORDING_HEADER)?;
+        return Ok(InputRecorder { out, seconds: 0.0, last_frame: InputState::default() });
+    }
+
+    /// Add an input that happened some seconds after recording started. Time can't go backwards.
+    pub fn record(&mut self, seconds: f64, input: RecordedInput) -> io::Result<()> {
+        if seconds < self.seconds {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("input recording went back from {}s to {}s", self.seconds, seconds)));
+        }
+        let line = serde_json::to_string(&TimedInput { seconds, input }).map_err(io::Error::other)?;
+        writeln!(self.out, "{}", line)?;
+        self.seconds = seconds;
+        return Ok(());
+    }
+
+    /// Add a frame's actions, unless nothing changed since the last frame.
+    pub fn record_frame(&mut self, seconds: f64, state: &InputState) -> io::Result<()> {
+        if *state == self.last_frame.continued() {
+            return Ok(());
+        }
+        self.last_frame = state.clone();
+        return self.record(seconds, RecordedInput::Frame(state.clone()));
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        return self.out.flush();
+    }
+
+    /// Flush and give back the output.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        return Ok(self.out);
+    }
+}
+
+/// Plays an input recording back a frame at a time, in place of the window and devices, so a session can be run
+/// again in a window or headless, such as to test the client.
+/// ```
+/// # use client::{input::{Binding, Bindings, InputMap, JUMP, MOVE_FORWARD}, recording::{InputPlayback, InputRecorder, RecordedInput}};
+/// # use winit::keyboard::{Key, KeyCode};
+/// let mut input = InputMap::new(Bindings::defaults());
+/// let mut recorder = InputRecorder::new(Vec::new()).unwrap();
+/// input.set(Binding::Key(KeyCode::KeyW), 1.0);
+/// recorder.record_frame(0.0, input.update()).unwrap();
+/// // Still held, which isn't recorded again.
+/// recorder.record_frame(0.5, input.update()).unwrap();
+/// recorder.record(0.75, RecordedInput::Key { key: Key::Character("t".into()), text: Some("t".into()) }).unwrap();
+/// input.set(Binding::Key(KeyCode::Space), 1.0);
+/// recorder.record_frame(1.0, input.update()).unwrap();
+/// let recording = recorder.finish().unwrap();
+/// assert_eq!(String::from_utf8_lossy(&recording).lines().count(), 4);
+///
+/// let mut playback = InputPlayback::read(recording.as_slice()).unwrap();
+/// let (state, _) = playback.advance(0.0);
+/// assert!(state.pressed(MOVE_FORWARD));
+/// let (state, inputs) = playback.advance(0.9);
+/// assert!(state.held(MOVE_FORWARD) && !state.pressed(MOVE_FORWARD));
+/// assert!(matches!(inputs[..], [RecordedInput::Key { .. }]));
+/// let (state, _) = playback.advance(0.25);
+/// assert!(state.pressed(JUMP) && playback.is_finished());
+/// ```
+pub struct InputPlayback {
+    inputs: Vec<TimedInput>,
+    next: usize,
+    seconds: f64,
+    /// Actions of the last frame played, held on until the next recorded frame changes them.
+    state: InputState
+}
+
+impl InputPlayback {
+    pub fn new(inputs: Vec<TimedInput>) -> InputPlayback {
+        return InputPlayback { inputs, next: 0, seconds: 0.0, state: InputState::default() };
+    }
+
+    /// Read a whole recording, checking its header.
+    pub fn read(input: impl io::Read) -> io::Result<InputPlayback> {
+        let mut lines = BufReader::new(input).lines();
+        if lines.next().transpose()?.as_deref() != Some(RECORDING_HEADER) {
+            return Err(invalid("not an input recording, or from a newer version"));
+        }
+        let mut inputs = Vec::new();
+        for (number, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let input: TimedInput = serde_json::from_str(&line).map_err(|error| invalid(&format!("line {}: {}", number + 2, error)))?;
+            inputs.push(input);
+        }
+        return Ok(InputPlayback::new(inputs));
+    }
+
+    pub fn open(path: &Path) -> io::Result<InputPlayback> {
+        return InputPlayback::read(File::open(path)?);
+    }
+
+    /// Seconds played.
+    pub fn seconds(&self) -> f64 {
+        return self.seconds;
+    }
+
+    /// Seconds from the start to the last input.
+    pub fn duration(&self) -> f64 {
+        return self.inputs.last().map_or(0.0, |input| input.seconds);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        return self.next >= self.inputs.len();
+    }
+
+    /// Play on by a frame's time, giving the frame's actions and the other inputs that came up in it, in order.
+    /// Frames recorded closer together than the frames played are folded into one, so no press is missed.
+    pub fn advance(&mut self, seconds: f64) -> (InputState, Vec<RecordedInput>) {
+        self.seconds += seconds;
+        let mut state: Option<InputState> = None;
+        let mut inputs = Vec::new();
+        while let Some(next) = self.inputs.get(self.next).filter(|input| input.seconds <= self.seconds) {
+            match &next.input {
+                RecordedInput::Frame(frame) => match state.as_mut() {
+                    Some(state) => state.merge(frame),
+                    None => state = Some(frame.clone())
+                },
+                input => inputs.push(input.clone())
+            }
+            self.next += 1;
+        }
+        self.state = state.unwrap_or_else(|| self.state.continued());
+        return (self.state.clone(), inputs);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,265 @@
+use std::{collections::{HashMap, HashSet}, fmt};
+
+/// The window's frame, which every graph draws to.
+pub const FRAME: &str = "frame";
+/// The depth buffer shared by the passes drawing the world.
+pub const DEPTH: &str = "depth";
+
+/// Reason passes couldn't be ordered into a graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphError {
+    /// Two passes have the same name.
+    DuplicatePass(&'static str),
+    /// A pass runs after one that isn't in the graph.
+    UnknownDependency { pass: &'static str, dependency: &'static str },
+    /// A pass reads an attachment nothing draws to, and that isn't imported.
+    UnwrittenAttachment { pass: &'static str, attachment: &'static str },
+    /// A pass draws to neither a color nor a depth attachment.
+    NoAttachments(&'static str),
+    /// The passes left that each wait on another of them.
+    Cycle(Vec<&'static str>)
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            GraphError::DuplicatePass(pass) => write!(f, "there's more than one {} pass", pass),
+            GraphError::UnknownDependency { pass, dependency } => write!(f, "the {} pass runs after {}, which isn't a pass", pass, dependency),
+            GraphError::UnwrittenAttachment { pass, attachment } => write!(f, "the {} pass reads {}, which nothing draws to", pass, attachment),
+            GraphError::NoAttachments(pass) => write!(f, "the {} pass doesn't draw to anything", pass),
+            GraphError::Cycle(passes) => write!(f, "the {} passes depend on each other", passes.join(", "))
+        };
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+/// A pass of a frame, and what it draws to and reads from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PassDesc {
+    pub name: &'static str,
+    pub color: Option<&'static str>,
+    pub depth: Option<&'static str>,
+    /// Attachments sampled as textures, such as a shadow map. Passes drawing to them run first.
+    pub reads: Vec<&'static str>,
+    /// Passes that must run first, such as opaque geometry before what's blended over it.
+    pub after: Vec<&'static str>
+}
+
+impl PassDesc {
+    pub fn new(name: &'static str) -> PassDesc {
+        return PassDesc { name, color: None, depth: None, reads: Vec::new(), after: Vec::new() };
+    }
+
+    pub fn with_color(mut self, attachment: &'static str) -> Self {
+        self.color = Some(attachment);
+        return self;
+    }
+
+    pub fn with_depth(mut self, attachment: &'static str) -> Self {
+        self.depth = Some(attachment);
+        return self;
+    }
+
+    pub fn with_read(mut self, attachment: &'static str) -> Self {
+        self.reads.push(attachment);
+        return self;
+    }
+
+    pub fn with_dependency(mut self, pass: &'static str) -> Self {
+        self.after.push(pass);
+        return self;
+    }
+
+    fn writes(&self, attachment: &'static str) -> bool {
+        return self.color == Some(attachment) || self.depth == Some(attachment);
+    }
+}
+
+/// The passes making up a frame. Passes run in the order they were added unless their dependencies say otherwise,
+/// so a new pass only needs to say what it draws to and what it comes after.
+/// ```
+/// # use client::render_graph::{GraphError, PassDesc, RenderGraph, DEPTH, FRAME};
+/// let graph = RenderGraph::new().with_imported(FRAME)
+///     .with_pass(PassDesc::new("ui").with_color(FRAME).with_dependency("transparent"))
+///     .with_pass(PassDesc::new("transparent").with_color(FRAME).with_depth(DEPTH).with_dependency("opaque"))
+///     .with_pass(PassDesc::new("opaque").with_color(FRAME).with_depth(DEPTH).with_read("shadow"))
+///     .with_pass(PassDesc::new("shadow").with_depth("shadow"));
+/// let compiled = graph.compile().unwrap();
+/// assert_eq!(compiled.order(), vec!["shadow", "opaque", "transparent", "ui"]);
+/// // Opaque and transparent draw to the same attachments, so share a render pass.
+/// assert_eq!(compiled.render_pass_count(), 3);
+///
+/// let cycle = RenderGraph::new()
+///     .with_pass(PassDesc::new("a").with_color(FRAME).with_dependency("b"))
+///     .with_pass(PassDesc::new("b").with_color(FRAME).with_dependency("a"));
+/// assert_eq!(cycle.compile().unwrap_err(), GraphError::Cycle(vec!["a", "b"]));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RenderGraph {
+    passes: Vec<PassDesc>,
+    /// Attachments that outlive the frame, such as the window's frame, so are always kept.
+    imported: HashSet<&'static str>
+}
+
+impl RenderGraph {
+    pub fn new() -> RenderGraph {
+        return RenderGraph { passes: Vec::new(), imported: HashSet::new() };
+    }
+
+    pub fn with_pass(mut self, pass: PassDesc) -> Self {
+        self.passes.push(pass);
+        return self;
+    }
+
+    pub fn with_imported(mut self, attachment: &'static str) -> Self {
+        self.imported.insert(attachment);
+        return self;
+    }
+
+    /// Order the passes so each runs after its dependencies and whatever draws to the attachments it reads, then
+    /// merge neighbouring passes with the same attachments into one render pass. Attachments are cleared by the
+    /// first pass to use them, and thrown away after the last unless imported.
+    pub fn compile(&self) -> Result<CompiledGraph, GraphError> {
+        let mut indices = HashMap::new();
+        for (index, pass) in self.passes.iter().enumerate() {
+            if indices.insert(pass.name, index).is_some() {
+                return Err(GraphError::DuplicatePass(pass.name));
+            }
+            if pass.color.is_none() && pass.depth.is_none() {
+                return Err(GraphError::NoAttachments(pass.name));
+            }
+        }
+        let mut waiting_on: Vec<HashSet<usize>> = vec![HashSet::new(); self.passes.len()];
+        for (index, pass) in self.passes.iter().enumerate() {
+            for dependency in pass.after.iter() {
+                let Some(&dependency_index) = indices.get(dependency) else {
+                    return Err(GraphError::UnknownDependency { pass: pass.name, dependency });
+                };
+                waiting_on[index].insert(dependency_index);
+            }
+            for attachment in pass.reads.iter() {
+                let writers: Vec<usize> = (0..self.passes.len()).filter(|&writer| writer != index && self.passes[writer].writes(attachment)).collect();
+                if writers.is_empty() && !self.imported.contains(attachment) {
+                    return Err(GraphError::UnwrittenAttachment { pass: pass.name, attachment });
+                }
+                waiting_on[index].extend(writers);
+            }
+        }
+        // Always take the earliest added pass that's ready, so passes without dependencies keep their order.
+        let mut order = Vec::with_capacity(self.passes.len());
+        let mut done = vec![false; self.passes.len()];
+        while order.len() < self.passes.len() {
+            let Some(next) = (0..self.passes.len()).find(|&index| !done[index] && waiting_on[index].iter().all(|&dependency| done[dependency])) else {
+                let stuck = (0..self.passes.len()).filter(|&index| !done[index]).map(|index| self.passes[index].name).collect();
+                return Err(GraphError::Cycle(stuck));
+            };
+            done[next] = true;
+            order.push(next);
+        }
+        let mut groups: Vec<PassGroup> = Vec::new();
+        for index in order {
+            let pass = &self.passes[index];
+            match groups.last_mut() {
+                Some(group) if group.color == pass.color && group.depth == pass.depth => group.passes.push(pass.name),
+                _ => groups.push(PassGroup { color: pass.color, depth: pass.depth, passes: vec![pass.name], clear_color: false, clear_depth: false, store_color: true, store_depth: true })
+            }
+        }
+        let uses = |group: &PassGroup, attachment: &'static str| {
+            return group.color == Some(attachment) || group.depth == Some(attachment) || group.passes.iter().any(|name| self.passes[indices[name]].reads.contains(&attachment));
+        };
+        for index in 0..groups.len() {
+            let (before, after) = groups.split_at(index);
+            let group = &after[0];
+            let first = |attachment: Option<&'static str>| attachment.is_some_and(|attachment| !before.iter().any(|earlier| uses(earlier, attachment)));
+            let kept = |attachment: Option<&'static str>| attachment.is_some_and(|attachment| self.imported.contains(attachment) || after[1..].iter().any(|later| uses(later, attachment)));
+            let (clear_color, clear_depth, store_color, store_depth) = (first(group.color), first(group.depth), kept(group.color), kept(group.depth));
+            let group = &mut groups[index];
+            (group.clear_color, group.clear_depth, group.store_color, group.store_depth) = (clear_color, clear_depth, store_color, store_depth);
+        }
+        return Ok(CompiledGraph { groups });
+    }
+}
+
+/// Passes sharing one render pass, as they draw to the same attachments one after another.
+#[derive(Clone, Debug)]
+struct PassGroup {
+    color: Option<&'static str>,
+    depth: Option<&'static str>,
+    passes: Vec<&'static str>,
+    clear_color: bool,
+    clear_depth: bool,
+    store_color: bool,
+    store_depth: bool
+}
+
+/// Views of a frame's attachments, and what they're cleared to by the first pass using them.
+#[derive(Default)]
+pub struct Targets<'a> {
+    colors: HashMap<&'static str, (&'a wgpu::TextureView, wgpu::Color)>,
+    depths: HashMap<&'static str, (&'a wgpu::TextureView, f32)>
+}
+
+impl<'a> Targets<'a> {
+    pub fn new() -> Targets<'a> {
+        return Targets { colors: HashMap::new(), depths: HashMap::new() };
+    }
+
+    pub fn with_color(mut self, attachment: &'static str, view: &'a wgpu::TextureView, clear: wgpu::Color) -> Self {
+        self.colors.insert(attachment, (view, clear));
+        return self;
+    }
+
+    pub fn with_depth(mut self, attachment: &'static str, view: &'a wgpu::TextureView, clear: f32) -> Self {
+        self.depths.insert(attachment, (view, clear));
+        return self;
+    }
+}
+
+/// Passes in the order they run, ready to record each frame.
+#[derive(Clone, Debug)]
+pub struct CompiledGraph {
+    groups: Vec<PassGroup>
+}
+
+impl CompiledGraph {
+    /// Names of the passes, in the order they run.
+    pub fn order(&self) -> Vec<&'static str> {
+        return self.groups.iter().flat_map(|group| group.passes.iter().copied()).collect();
+    }
+
+    /// Render passes recorded each frame, after merging passes with the same attachments.
+    pub fn render_pass_count(&self) -> usize {
+        return self.groups.len();
+    }
+
+    /// Record the frame's render passes, calling draw with each pass's name to record its draws.
+    /// Panics if the targets are missing an attachment, which is a mistake in the renderer rather than something
+    /// that can go wrong while running.
+    pub fn execute(&self, encoder: &mut wgpu::CommandEncoder, targets: &Targets<'_>, mut draw: impl FnMut(&'static str, &mut wgpu::RenderPass<'_>)) {
+        let store = |kept: bool| if kept { wgpu::StoreOp::Store } else { wgpu::StoreOp::Discard };
+        for group in self.groups.iter() {
+            let color = group.color.map(|attachment| {
+                let (view, clear) = targets.colors.get(attachment).unwrap_or_else(|| panic!("no target for the {} attachment", attachment));
+                let load = if group.clear_color { wgpu::LoadOp::Clear(*clear) } else { wgpu::LoadOp::Load };
+                return wgpu::RenderPassColorAttachment { view, depth_slice: None, resolve_target: None, ops: wgpu::Operations { load, store: store(group.store_color) } };
+            });
+            let depth = group.depth.map(|attachment| {
+                let (view, clear) = targets.depths.get(attachment).unwrap_or_else(|| panic!("no target for the {} attachment", attachment));
+                let load = if group.clear_depth { wgpu::LoadOp::Clear(*clear) } else { wgpu::LoadOp::Load };
+                return wgpu::RenderPassDepthStencilAttachment { view, depth_ops: Some(wgpu::Operations { load, store: store(group.store_depth) }), stencil_ops: None };
+            });
+            // Depth only passes, such as shadows, have no color targets at all.
+            let colors: Vec<_> = color.into_iter().map(Some).collect();
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(group.passes[0]),
+                color_attachments: &colors,
+                depth_stencil_attachment: depth,
+                ..Default::default()
+            });
+            for name in group.passes.iter() {
+                draw(name, &mut pass);
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,321 @@
+use std::{fmt, sync::Arc};
+
+use shared::engine::{asset::texture::Texture, job::system::JobSystem, math::{aabb::Aabb, coords::ChunkPos}, memory::MemoryTracker, mesh::remesh::RemeshJob, world::time::{WorldTime, NOON}};
+use wgpu::CurrentSurfaceTexture;
+use winit::{dpi::PhysicalSize, window::Window};
+
+use crate::{
+    atlas::TextureAtlas,
+    camera::Camera,
+    chunk_renderer::{ChunkRenderer, ChunkView, DEPTH_FORMAT},
+    outline::OutlineRenderer,
+    particle_renderer::ParticleRenderer,
+    particles::ParticleInstance,
+    render_graph::{CompiledGraph, GraphError, PassDesc, RenderGraph, Targets, DEPTH, FRAME},
+    screenshot::{FrameCapture, ScreenshotError},
+    sky::{SkyRenderer, SkyState},
+    text::TextRenderer
+};
+
+/// Passes drawn each frame, by the renderer drawing them.
+pub const SKY_PASS: &str = "sky";
+pub const CHUNKS_PASS: &str = "chunks";
+pub const OUTLINE_PASS: &str = "outline";
+/// Translucent blocks, blended over everything opaque.
+pub const TRANSLUCENT_PASS: &str = "translucent";
+pub const PARTICLES_PASS: &str = "particles";
+/// Text over everything else.
+pub const UI_PASS: &str = "ui";
+
+/// View plain old data as bytes to upload.
+/// Only for repr(C) types without padding bytes, such as vertices, u32, and uniforms.
+pub(crate) fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    // Safety: T is Copy, so has no drop glue, and the callers' types have no uninitialized padding.
+    return unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, std::mem::size_of_val(data)) };
+}
+
+/// Reason the renderer couldn't start, or stopped being able to draw.
+#[derive(Debug)]
+pub enum RendererError {
+    Surface(wgpu::CreateSurfaceError),
+    /// No GPU can draw to the window.
+    Adapter(wgpu::RequestAdapterError),
+    Device(wgpu::RequestDeviceError),
+    /// The surface can't be drawn to with this adapter.
+    UnsupportedSurface,
+    /// Acquiring the next frame raised a validation error.
+    Validation,
+    /// The frame's passes couldn't be ordered.
+    Graph(GraphError)
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            RendererError::Surface(error) => write!(f, "couldn't create the window surface: {}", error),
+            RendererError::Adapter(error) => write!(f, "couldn't find a graphics adapter: {}", error),
+            RendererError::Device(error) => write!(f, "couldn't create the graphics device: {}", error),
+            RendererError::UnsupportedSurface => write!(f, "the window surface isn't supported by the graphics adapter"),
+            RendererError::Validation => write!(f, "acquiring the next frame failed validation"),
+            RendererError::Graph(error) => write!(f, "couldn't order the frame's passes: {}", error)
+        };
+    }
+}
+
+impl std::error::Error for RendererError {}
+
+/// Draws to a window with wgpu. Owns the device, and the surface's swapchain, which is reconfigured as the window
+/// resizes, and recreated if it's lost. Each frame runs the passes of a render graph: the sky, then the chunk meshes
+/// it's given from wherever the view was last set, then the outlines, translucent blocks and particles, then any
+/// text queued over the frame on top. Frames can be captured for
+/// screenshots, which are read back over the following frames rather than waiting on the GPU.
+pub struct Renderer {
+    window: Arc<Window>,
+    instance: wgpu::Instance,
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    depth: wgpu::TextureView,
+    sky: SkyRenderer,
+    sky_state: SkyState,
+    chunks: ChunkRenderer,
+    outline: OutlineRenderer,
+    particles: ParticleRenderer,
+    text: TextRenderer,
+    /// Nothing but the sky is drawn until a view is set.
+    view: Option<ChunkView>,
+    graph: CompiledGraph,
+    /// Whether the swapchain's frames can be copied from, for screenshots.
+    can_capture: bool,
+    /// Capture the next frame drawn.
+    capture_requested: bool,
+    /// Frames captured but not yet read back.
+    captures: Vec<FrameCapture>
+}
+
+impl Renderer {
+    /// Pick an adapter able to draw to the window, and configure its swapchain to the window's size.
+    /// Blocks until the device is ready. Chunk culling runs on the job system.
+    pub fn new(instance: wgpu::Instance, window: Arc<Window>, jobs: Arc<JobSystem>) -> Result<Renderer, RendererError> {
+        let surface = instance.create_surface(window.clone()).map_err(RendererError::Surface)?;
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: Some(&surface),
+            ..Default::default()
+        })).map_err(RendererError::Adapter)?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("cube device"),
+            ..Default::default()
+        })).map_err(RendererError::Device)?;
+        let size = window.inner_size();
+        let mut config = surface.get_default_config(&adapter, size.width.max(1), size.height.max(1)).ok_or(RendererError::UnsupportedSurface)?;
+        let can_capture = surface.get_capabilities(&adapter).usages.contains(wgpu::TextureUsages::COPY_SRC) && FrameCapture::supports(config.format);
+        if can_capture {
+            config.usage |= wgpu::TextureUsages::COPY_SRC;
+        }
+        surface.configure(&device, &config);
+        let depth = Renderer::create_depth(&device, &config);
+        let sky = SkyRenderer::new(&device, config.format);
+        let chunks = ChunkRenderer::new(&device, &queue, config.format, jobs);
+        let outline = OutlineRenderer::new(&device, config.format);
+        let particles = ParticleRenderer::new(&device, config.format, chunks.textures());
+        let text = TextRenderer::new(&device, &queue, config.format);
+        let graph = Renderer::graph().compile().map_err(RendererError::Graph)?;
+        return Ok(Renderer { window, instance, surface, device, queue, config, depth, sky, sky_state: SkyState::at(WorldTime::new(NOON)), chunks, outline, particles, text, view: None, graph, can_capture, capture_requested: false, captures: Vec::new() });
+    }
+
+    /// Passes drawn each frame. Everything shares the depth buffer, so outlines and particles are hidden behind blocks,
+    /// and text draws over it all.
+    fn graph() -> RenderGraph {
+        let world = |name| PassDesc::new(name).with_color(FRAME).with_depth(DEPTH);
+        return RenderGraph::new().with_imported(FRAME)
+            .with_pass(world(SKY_PASS))
+            .with_pass(world(CHUNKS_PASS).with_dependency(SKY_PASS))
+            .with_pass(world(OUTLINE_PASS).with_dependency(CHUNKS_PASS))
+            .with_pass(world(TRANSLUCENT_PASS).with_dependency(CHUNKS_PASS))
+            .with_pass(world(PARTICLES_PASS).with_dependency(TRANSLUCENT_PASS))
+            .with_pass(world(UI_PASS).with_dependency(OUTLINE_PASS).with_dependency(PARTICLES_PASS));
+    }
+
+    fn create_depth(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth"),
+            size: wgpu::Extent3d { width: config.width, height: config.height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[]
+        });
+        return texture.create_view(&wgpu::TextureViewDescriptor::default());
+    }
+
+    pub fn window(&self) -> &Arc<Window> {
+        return &self.window;
+    }
+
+    /// Size of the swapchain's frames, in pixels.
+    pub fn size(&self) -> PhysicalSize<u32> {
+        return PhysicalSize::new(self.config.width, self.config.height);
+    }
+
+    pub fn chunks(&self) -> &ChunkRenderer {
+        return &self.chunks;
+    }
+
+    /// Outline boxes, relative to the view's render origin, such as around the targeted block. Empty for none.
+    pub fn set_outline(&mut self, boxes: Vec<Aabb>) {
+        self.outline.set_boxes(boxes);
+    }
+
+    /// Particles to draw, relative to the view's render origin, facing the camera.
+    pub fn set_particles(&mut self, particles: Vec<ParticleInstance>, camera: &Camera) {
+        self.particles.set_particles(particles, camera.right(), camera.up());
+    }
+
+    /// Text to draw over this frame, in pixels from the top left of the window.
+    pub fn text_mut(&mut self) -> &mut TextRenderer {
+        return &mut self.text;
+    }
+
+    /// Set where chunks are drawn from, normally once a frame from the camera.
+    pub fn set_view(&mut self, view: ChunkView) {
+        self.view = Some(view);
+    }
+
+    /// Set the sky for the time of day. Its view follows the chunks' view.
+    pub fn set_sky(&mut self, sky: SkyState) {
+        self.sky_state = sky;
+    }
+
+    /// Draw chunks with a new block texture atlas, such as once block textures are loaded.
+    pub fn set_atlas(&mut self, atlas: &TextureAtlas) {
+        self.chunks.set_atlas(&self.device, &self.queue, atlas);
+        self.particles.set_textures(&self.device, self.chunks.textures());
+    }
+
+    /// Upload the meshes of finished meshing jobs, keeping the unfinished ones. Returns how many were uploaded.
+    pub fn receive_meshes(&mut self, jobs: &mut Vec<RemeshJob>) -> usize {
+        return self.chunks.receive(&self.device, &self.queue, jobs);
+    }
+
+    /// Stop drawing a chunk and free its meshes, such as when it unloads.
+    pub fn remove_chunk(&mut self, pos: ChunkPos) -> bool {
+        return self.chunks.remove(pos);
+    }
+
+    /// Free meshes of chunks out of view while meshes are over their memory budget. Returns the chunks freed.
+    pub fn evict_meshes(&mut self, memory: &MemoryTracker, center: ChunkPos) -> Vec<ChunkPos> {
+        return self.chunks.evict(memory, center);
+    }
+
+    /// Turn vsync on or off, reconfiguring the swapchain if it changed. Presenting falls back to vsync where turning
+    /// it off isn't supported.
+    pub fn set_vsync(&mut self, vsync: bool) {
+        let present_mode = if vsync { wgpu::PresentMode::AutoVsync } else { wgpu::PresentMode::AutoNoVsync };
+        if present_mode == self.config.present_mode {
+            return;
+        }
+        self.config.present_mode = present_mode;
+        self.surface.configure(&self.device, &self.config);
+    }
+
+    /// Capture the next frame drawn, to be taken from captured_frames() once it's read back.
+    pub fn request_screenshot(&mut self) -> Result<(), ScreenshotError> {
+        if !self.can_capture {
+            return Err(ScreenshotError::Unsupported);
+        }
+        self.capture_requested = true;
+        return Ok(());
+    }
+
+    /// Frames captured that the GPU has finished copying back, checking without blocking.
+    pub fn captured_frames(&mut self) -> Vec<Result<Texture, ScreenshotError>> {
+        if self.captures.is_empty() {
+            return Vec::new();
+        }
+        let _ = self.device.poll(wgpu::PollType::Poll);
+        let mut frames = Vec::new();
+        self.captures.retain(|capture| {
+            return match capture.try_read() {
+                Some(frame) => {
+                    frames.push(frame);
+                    false
+                }
+                None => true
+            };
+        });
+        return frames;
+    }
+
+    /// Resize the swapchain to a new window size. Ignored while the window is minimized, as a surface can't be
+    /// configured with no area.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 || size == self.size() {
+            return;
+        }
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(&self.device, &self.config);
+        self.depth = Renderer::create_depth(&self.device, &self.config);
+    }
+
+    /// Draw a frame and present it. Frames that can't be acquired right now, such as while the window is hidden,
+    /// or after the swapchain went out of date, are skipped, and the swapchain is fixed for the next frame.
+    pub fn render(&mut self) -> Result<(), RendererError> {
+        let (frame, suboptimal) = match self.surface.get_current_texture() {
+            CurrentSurfaceTexture::Success(frame) => (frame, false),
+            CurrentSurfaceTexture::Suboptimal(frame) => (frame, true),
+            CurrentSurfaceTexture::Timeout | CurrentSurfaceTexture::Occluded => return Ok(()),
+            CurrentSurfaceTexture::Outdated => {
+                self.surface.configure(&self.device, &self.config);
+                return Ok(());
+            }
+            CurrentSurfaceTexture::Lost => {
+                self.surface = self.instance.create_surface(self.window.clone()).map_err(RendererError::Surface)?;
+                self.surface.configure(&self.device, &self.config);
+                return Ok(());
+            }
+            CurrentSurfaceTexture::Validation => return Err(RendererError::Validation)
+        };
+        if let Some(view) = self.view.as_ref() {
+            self.sky.prepare(&self.queue, view, &self.sky_state);
+            self.chunks.prepare(&self.device, &self.queue, view);
+            self.outline.prepare(&self.device, &self.queue, view.view_projection);
+            self.particles.prepare(&self.device, &self.queue, view.view_projection);
+        }
+        self.text.prepare(&self.device, &self.queue, self.size());
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("frame") });
+        // Cleared to the horizon, for frames without a view to draw the sky from.
+        let horizon = self.sky_state.horizon;
+        let clear_color = wgpu::Color { r: horizon.x as f64, g: horizon.y as f64, b: horizon.z as f64, a: 1.0 };
+        let targets = Targets::new().with_color(FRAME, &view, clear_color).with_depth(DEPTH, &self.depth, 1.0);
+        let has_view = self.view.is_some();
+        self.graph.execute(&mut encoder, &targets, |name, pass| {
+            match name {
+                SKY_PASS if has_view => self.sky.draw(pass),
+                CHUNKS_PASS if has_view => self.chunks.draw(pass),
+                OUTLINE_PASS if has_view => self.outline.draw(pass),
+                TRANSLUCENT_PASS if has_view => self.chunks.draw_translucent(pass),
+                PARTICLES_PASS if has_view => self.particles.draw(pass),
+                UI_PASS => self.text.draw(pass),
+                _ => {}
+            }
+        });
+        let capture = std::mem::take(&mut self.capture_requested).then(|| FrameCapture::copy(&self.device, &mut encoder, &frame.texture));
+        self.queue.submit([encoder.finish()]);
+        if let Some(capture) = capture {
+            capture.map();
+            self.captures.push(capture);
+        }
+        self.window.pre_present_notify();
+        self.queue.present(frame);
+        if suboptimal {
+            self.surface.configure(&self.device, &self.config);
+        }
+        return Ok(());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,114 @@
+use std::{io, sync::Arc};
+
+use shared::engine::{
+    net::{
+        checksum::{BlockVerifier, ChecksumMismatch},
+        chunk_stream,
+        interaction,
+        packet::{self as codec, Packet},
+        replay::{Replay, ReplayPlayer},
+        time_sync::TimeUpdate
+    },
+    world::World
+};
+
+/// Plays a recorded replay into a world of its own, applying the server's packets the way the client did when
+/// they arrived. Nothing is sent back, so the camera is free to fly anywhere while it plays. Moving back in the
+/// replay rebuilds the world from the start. The server's block checksums are checked as they come up, so a desync
+/// can be replayed until the tick it started on.
+/// ```
+/// # use client::replay::ReplayViewer;
+/// # use shared::engine::net::{checksum::TickChecksum, chunk_stream::ChunkData, interaction::BlockUpdate, packet, replay::{Replay, ReplayWriter}};
+/// # use shared::engine::world::chunk::Chunk;
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let mut writer = ReplayWriter::new(Vec::new()).unwrap();
+/// writer.record(0, &packet::encode(&ChunkData::new(&Chunk::new(ChunkPos::ORIGIN)).unwrap())).unwrap();
+/// writer.record(20, &packet::encode(&BlockUpdate { block: BlockPos::new(1, 2, 3), id: 5 })).unwrap();
+/// writer.record(20, &packet::encode(&TickChecksum { tick: 20, blocks: 0 })).unwrap();
+/// let mut viewer = ReplayViewer::new(Replay::from_bytes(&writer.finish().unwrap()).unwrap());
+///
+/// viewer.update(0.5).unwrap();
+/// assert_eq!(viewer.world().get_block(BlockPos::new(1, 2, 3)), Some(0));
+/// viewer.update(0.5).unwrap();
+/// assert_eq!(viewer.world().get_block(BlockPos::new(1, 2, 3)), Some(5));
+/// assert!(viewer.player().is_finished());
+/// // The checksum recorded doesn't match the block.
+/// assert_eq!(viewer.take_mismatches()[0].step, 20);
+///
+/// // Going back gives a new world with only what had arrived by then.
+/// assert!(viewer.seek(10.0).unwrap());
+/// assert_eq!(viewer.world().get_block(BlockPos::new(1, 2, 3)), Some(0));
+/// ```
+pub struct ReplayViewer {
+    player: ReplayPlayer,
+    world: Arc<World>,
+    verifier: BlockVerifier,
+    /// Checksums that didn't match since the last call to take_mismatches().
+    mismatches: Vec<ChecksumMismatch>
+}
+
+impl ReplayViewer {
+    pub fn new(replay: Replay) -> ReplayViewer {
+        return ReplayViewer { player: ReplayPlayer::new(replay), world: Arc::new(World::new()), verifier: BlockVerifier::new(), mismatches: Vec::new() };
+    }
+
+    /// The world as the replay has built it so far. Replaced by a new one when seeking back.
+    pub fn world(&self) -> &Arc<World> {
+        return &self.world;
+    }
+
+    pub fn player(&self) -> &ReplayPlayer {
+        return &self.player;
+    }
+
+    /// Ticks where the world didn't end up with the blocks the server had, since the last call.
+    pub fn take_mismatches(&mut self) -> Vec<ChecksumMismatch> {
+        return std::mem::take(&mut self.mismatches);
+    }
+
+    /// Pause and change the speed through the player. Seeking goes through seek(), as the world may need rebuilding.
+    pub fn player_mut(&mut self) -> &mut ReplayPlayer {
+        return &mut self.player;
+    }
+
+    /// Play on by some real time, applying the packets that came up and advancing the world's time in between.
+    pub fn update(&mut self, seconds: f64) -> io::Result<()> {
+        let before = self.player.tick();
+        return self.play(seconds, before);
+    }
+
+    /// Move to a tick, returning true if the world had to be rebuilt from the start to get there.
+    pub fn seek(&mut self, tick: f64) -> io::Result<bool> {
+        let mut before = self.player.tick();
+        let restarted = self.player.seek(tick);
+        if restarted {
+            self.world = Arc::new(World::new());
+            self.verifier = BlockVerifier::new();
+            before = 0.0;
+        }
+        self.play(0.0, before.min(self.player.tick()))?;
+        return Ok(restarted);
+    }
+
+    fn play(&mut self, seconds: f64, before: f64) -> io::Result<()> {
+        for record in self.player.advance(seconds) {
+            ReplayViewer::apply(&self.world, &record.packet)?;
+            if let Some(Err(mismatch)) = self.verifier.handle(&self.world, &record.packet)? {
+                self.mismatches.push(mismatch);
+            }
+        }
+        self.world.advance_time(self.player.tick() as u64 - before as u64);
+        return Ok(());
+    }
+
+    /// Apply one packet from the server. Packets the client only answers, or that nothing here draws, are skipped.
+    fn apply(world: &World, packet: &[u8]) -> io::Result<()> {
+        if codec::packet_id(packet)? == <TimeUpdate as Packet>::ID {
+            codec::decode::<TimeUpdate>(packet)?.apply(world);
+            return Ok(());
+        }
+        chunk_stream::apply_packet(world, packet)?;
+        interaction::apply_packet(world, packet)?;
+        return Ok(());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,185 @@
+use std::{fmt, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::{SystemTime, UNIX_EPOCH}};
+
+use shared::engine::{asset::texture::Texture, job::{future::JobFuture, priority::JobPriority, system::JobSystem}};
+
+/// Where screenshots are saved, relative to the working directory.
+pub const SCREENSHOTS_DIRECTORY: &str = "screenshots";
+
+/// Reason a screenshot couldn't be taken or saved.
+#[derive(Debug)]
+pub enum ScreenshotError {
+    /// The window's frames can't be copied from, or are in a format that can't be saved.
+    Unsupported,
+    /// The frame couldn't be read back from the GPU.
+    Map(wgpu::BufferAsyncError),
+    Encode(png::EncodingError),
+    Io(std::io::Error)
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ScreenshotError::Unsupported => write!(f, "the window's frames can't be captured"),
+            ScreenshotError::Map(error) => write!(f, "couldn't read the frame back: {}", error),
+            ScreenshotError::Encode(error) => write!(f, "couldn't encode the screenshot: {}", error),
+            ScreenshotError::Io(error) => write!(f, "couldn't write the screenshot: {}", error)
+        };
+    }
+}
+
+impl std::error::Error for ScreenshotError {}
+
+/// Encode an image as an 8 bit RGBA PNG.
+/// ```
+/// # use client::screenshot::encode_png;
+/// # use shared::engine::asset::{manager::Asset, texture::Texture};
+/// let image = Texture { width: 2, height: 1, pixels: vec![255, 0, 0, 255, 0, 0, 255, 255] };
+/// let png = encode_png(&image).unwrap();
+/// assert_eq!(Texture::decode(&png).unwrap(), image);
+/// ```
+pub fn encode_png(image: &Texture) -> Result<Vec<u8>, png::EncodingError> {
+    let mut data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut data, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.pixels)?;
+    writer.finish()?;
+    return Ok(data);
+}
+
+/// A frame being copied back from the GPU. The copy is recorded with the frame's own commands, then read once the
+/// GPU has finished with it, so taking a screenshot never waits on the GPU.
+pub struct FrameCapture {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    /// Bytes per row in the buffer, which copies pad to a multiple of 256.
+    padded_row: u32,
+    /// Surfaces are often blue first, which is swapped back to red first when read.
+    bgra: bool,
+    /// Set by the GPU once the buffer is mapped.
+    mapped: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>
+}
+
+impl FrameCapture {
+    /// Whether frames of a format can be captured.
+    pub fn supports(format: wgpu::TextureFormat) -> bool {
+        return matches!(format,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb |
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+    }
+
+    /// Record copying a frame into a buffer the CPU can read. The texture needs COPY_SRC usage, and a supported format.
+    pub fn copy(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) -> FrameCapture {
+        let (width, height) = (texture.width(), texture.height());
+        let padded_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame capture"),
+            size: padded_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(padded_row), rows_per_image: None }
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 }
+        );
+        let bgra = matches!(texture.format(), wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb);
+        return FrameCapture { buffer, width, height, padded_row, bgra, mapped: Arc::new(Mutex::new(None)) };
+    }
+
+    /// Start reading the buffer back, once the commands copying to it have been submitted.
+    pub fn map(&self) {
+        let mapped = self.mapped.clone();
+        self.buffer.map_async(wgpu::MapMode::Read, .., move |result| {
+            *mapped.lock().unwrap() = Some(result);
+        });
+    }
+
+    /// The frame as RGBA, once the GPU has finished copying it. None until then, while the device is polled.
+    pub fn try_read(&self) -> Option<Result<Texture, ScreenshotError>> {
+        match self.mapped.lock().unwrap().take()? {
+            Ok(()) => {},
+            Err(error) => return Some(Err(ScreenshotError::Map(error)))
+        }
+        let view = self.buffer.get_mapped_range(..).expect("the buffer is mapped");
+        let row = self.width as usize * 4;
+        let mut pixels = Vec::with_capacity(row * self.height as usize);
+        for padded in view.chunks_exact(self.padded_row as usize) {
+            pixels.extend_from_slice(&padded[..row]);
+        }
+        drop(view);
+        self.buffer.unmap();
+        for pixel in pixels.chunks_exact_mut(4) {
+            if self.bgra {
+                pixel.swap(0, 2);
+            }
+            // What's behind the window doesn't show through, so the image shouldn't be see-through either.
+            pixel[3] = 255;
+        }
+        return Some(Ok(Texture { width: self.width, height: self.height, pixels }));
+    }
+}
+
+/// Saves captured frames as PNGs, encoding and writing each on a low priority job so the frame never waits on it.
+/// Screenshots are named by the time they were taken.
+/// ```
+/// # use std::{sync::Arc, time::{Duration, UNIX_EPOCH}};
+/// # use client::screenshot::Screenshots;
+/// # use shared::engine::{asset::texture::Texture, job::system::JobSystem};
+/// let directory = std::env::temp_dir().join(format!("screenshot_doctest_{}", std::process::id()));
+/// let mut screenshots = Screenshots::new(Arc::new(JobSystem::new(1)), directory.clone());
+/// screenshots.save(Texture { width: 1, height: 1, pixels: vec![0, 0, 0, 255] }, UNIX_EPOCH + Duration::from_millis(1500));
+/// let mut saved = Vec::new();
+/// while saved.is_empty() {
+///     saved = screenshots.finished();
+/// }
+/// assert_eq!(saved[0].as_ref().unwrap(), &directory.join("screenshot-1500.png"));
+/// assert_eq!(screenshots.saving_count(), 0);
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct Screenshots {
+    jobs: Arc<JobSystem>,
+    directory: PathBuf,
+    saving: Vec<JobFuture<Result<PathBuf, ScreenshotError>>>
+}
+
+impl Screenshots {
+    pub fn new(jobs: Arc<JobSystem>, directory: PathBuf) -> Screenshots {
+        return Screenshots { jobs, directory, saving: Vec::new() };
+    }
+
+    pub fn directory(&self) -> &Path {
+        return &self.directory;
+    }
+
+    /// Screenshots still being encoded or written.
+    pub fn saving_count(&self) -> usize {
+        return self.saving.len();
+    }
+
+    /// Start saving a screenshot taken at a time, creating the directory if there isn't one yet.
+    pub fn save(&mut self, image: Texture, taken: SystemTime) {
+        let millis = taken.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let path = self.directory.join(format!("screenshot-{}.png", millis));
+        let directory = self.directory.clone();
+        self.saving.push(self.jobs.run_job_with_priority(JobPriority::Low, move || {
+            let data = encode_png(&image).map_err(ScreenshotError::Encode)?;
+            std::fs::create_dir_all(&directory).map_err(ScreenshotError::Io)?;
+            std::fs::write(&path, data).map_err(ScreenshotError::Io)?;
+            return Ok(path.clone());
+        }));
+    }
+
+    /// Where each screenshot that finished saving since last asked was saved, or why it couldn't be.
+    pub fn finished(&mut self) -> Vec<Result<PathBuf, ScreenshotError>> {
+        let (finished, saving): (Vec<_>, Vec<_>) = std::mem::take(&mut self.saving).into_iter().partition(|future| future.is_ready());
+        self.saving = saving;
+        return finished.into_iter().map(|future| future.wait()).collect();
+    }
+}
\ No newline at end of file
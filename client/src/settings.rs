@@ -0,0 +1,138 @@
+use std::{fmt, path::Path};
+
+use serde::{Serialize, Deserialize};
+use shared::engine::{math::coords::CHUNK_SIZE, memory::{DEFAULT_MESH_BUDGET, MEBIBYTE}};
+
+use crate::{audio::mixer::Volumes, camera::DEFAULT_FOV, input::Bindings};
+
+/// Chunks from the camera the world is drawn to, and the range allowed.
+pub const DEFAULT_RENDER_DISTANCE: u32 = 8;
+pub const MIN_RENDER_DISTANCE: u32 = 2;
+pub const MAX_RENDER_DISTANCE: u32 = 32;
+/// Vertical field of view range allowed, in degrees.
+pub const MIN_FOV: f32 = 30.0;
+pub const MAX_FOV: f32 = 110.0;
+
+/// Reason a settings file couldn't be loaded.
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(std::io::Error),
+    Parse(toml::de::Error)
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            SettingsError::Io(error) => write!(f, "couldn't read the settings: {}", error),
+            SettingsError::Parse(error) => write!(f, "couldn't parse the settings: {}", error)
+        };
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// How the world is drawn and the window shown.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoSettings {
+    /// Chunks from the camera the world is drawn to, with fog hiding where it ends.
+    pub render_distance: u32,
+    /// Vertical field of view, in degrees.
+    pub fov: f32,
+    /// Wait for the display to refresh before showing each frame, so frames never tear.
+    pub vsync: bool,
+    /// Fill the monitor with a borderless window.
+    pub fullscreen: bool,
+    /// MiB of chunk meshes kept on the GPU before meshes out of view are freed, or 0 for no limit.
+    pub mesh_memory: u64
+}
+
+impl VideoSettings {
+    /// Blocks from the camera where the world fades fully into fog.
+    pub fn fog_distance(&self) -> f32 {
+        return (self.render_distance * CHUNK_SIZE as u32) as f32;
+    }
+
+    /// Budget of chunk meshes in bytes, or None for no limit.
+    pub fn mesh_budget(&self) -> Option<u64> {
+        return (self.mesh_memory > 0).then(|| self.mesh_memory * MEBIBYTE);
+    }
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        return VideoSettings { render_distance: DEFAULT_RENDER_DISTANCE, fov: DEFAULT_FOV.to_degrees().round(), vsync: true, fullscreen: false, mesh_memory: DEFAULT_MESH_BUDGET };
+    }
+}
+
+/// Everything the player can change about the client, saved as TOML. Settings left out of the file keep their
+/// defaults, and controls are written with a line per action, the same as Bindings::to_toml().
+/// ```
+/// # use client::settings::Settings;
+/// # use client::input::{Binding, JUMP};
+/// # use winit::keyboard::KeyCode;
+/// let mut settings = Settings::default();
+/// settings.video.vsync = false;
+/// settings.audio.master = 0.5;
+/// settings.controls.rebind(JUMP, Binding::Key(KeyCode::KeyE));
+/// let text = settings.to_toml();
+/// assert!(text.contains("vsync = false"));
+/// assert!(text.contains(r#"jump = [{ key = "KeyE" }]"#));
+/// assert_eq!(Settings::from_toml(&text).unwrap(), settings);
+///
+/// // Out of range settings are brought back into range.
+/// let settings = Settings::from_toml("[video]\nfov = 400.0\nrender_distance = 0").unwrap();
+/// assert_eq!((settings.video.fov, settings.video.render_distance), (110.0, 2));
+/// assert_eq!(settings.controls, Settings::default().controls);
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub video: VideoSettings,
+    pub audio: Volumes,
+    /// Written by to_toml() itself, to keep each action on one line.
+    #[serde(skip_serializing)]
+    pub controls: Bindings
+}
+
+impl Settings {
+    pub fn from_toml(text: &str) -> Result<Settings, toml::de::Error> {
+        let settings: Settings = toml::from_str(text)?;
+        return Ok(settings.clamped());
+    }
+
+    pub fn to_toml(&self) -> String {
+        let text = toml::to_string(self).expect("settings always serialize");
+        return format!("{}\n[controls]\n{}", text, self.controls.to_toml());
+    }
+
+    /// Load a settings file, or the defaults if there isn't one yet.
+    pub fn load_or_default(path: &Path) -> Result<Settings, SettingsError> {
+        return match std::fs::read_to_string(path) {
+            Ok(text) => Settings::from_toml(&text).map_err(SettingsError::Parse),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(error) => Err(SettingsError::Io(error))
+        };
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        return std::fs::write(path, self.to_toml());
+    }
+
+    /// The same settings, with any out of range brought back into range, such as after editing the file by hand.
+    pub fn clamped(mut self) -> Settings {
+        self.video.render_distance = self.video.render_distance.clamp(MIN_RENDER_DISTANCE, MAX_RENDER_DISTANCE);
+        self.video.fov = self.video.fov.clamp(MIN_FOV, MAX_FOV);
+        let audio = &mut self.audio;
+        for volume in [&mut audio.master, &mut audio.blocks, &mut audio.footsteps, &mut audio.ambient, &mut audio.interface] {
+            *volume = volume.clamp(0.0, 1.0);
+        }
+        return self;
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        return Settings { video: VideoSettings::default(), audio: Volumes::default(), controls: Bindings::defaults() };
+    }
+}
\ No newline at end of file
@@ -0,0 +1,79 @@
+// Draws chunk meshes, with each chunk's offset from the render origin given per instance.
+
+struct Globals {
+    view_projection: mat4x4<f32>,
+    // Color of full sky light at this time of day.
+    ambient: vec3<f32>,
+    fog_start: f32,
+    // Camera position, relative to the same origin as the chunk offsets.
+    eye: vec3<f32>,
+    fog_end: f32,
+    fog_color: vec3<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(0) @binding(1) var block_textures: texture_2d<f32>;
+@group(0) @binding(2) var block_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    // Rect of the face's texture in the atlas, as min u, min v, width, height.
+    @location(2) tile: vec4<f32>,
+    @location(3) block: u32,
+    @location(4) face: u32,
+    @location(5) light: u32,
+    // Ambient occlusion of the corner, from 0, fully hidden, to 3, open.
+    @location(6) ao: u32,
+    @location(7) chunk_offset: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) tile: vec4<f32>,
+    @location(2) shade: vec3<f32>,
+    @location(3) fog: f32,
+}
+
+// Brightness of each face, in Direction order, so sides are told apart without lighting.
+const FACE_SHADE = array<f32, 6>(0.6, 0.6, 0.5, 1.0, 0.8, 0.8);
+const MIN_LIGHT: f32 = 0.05;
+// Brightness of a corner by its ambient occlusion, interpolated across faces so corners fade into shadow.
+const AO_SHADE = array<f32, 4>(0.45, 0.65, 0.82, 1.0);
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    let position = input.position + input.chunk_offset;
+    output.clip_position = globals.view_projection * vec4<f32>(position, 1.0);
+    output.uv = input.uv;
+    output.tile = input.tile;
+    // Light is packed as 4 bits per channel, with sky in the lowest bits.
+    let sky = f32(input.light & 0xFu) / 15.0 * globals.ambient;
+    let block = vec3<f32>(f32((input.light >> 4u) & 0xFu), f32((input.light >> 8u) & 0xFu), f32((input.light >> 12u) & 0xFu)) / 15.0;
+    output.shade = max(max(sky, block), vec3<f32>(MIN_LIGHT)) * FACE_SHADE[input.face] * AO_SHADE[min(input.ao, 3u)];
+    output.fog = smoothstep(globals.fog_start, globals.fog_end, distance(position, globals.eye));
+    return output;
+}
+
+// Lit and fogged color of a fragment, with the texture's alpha.
+fn shade(input: VertexOutput) -> vec4<f32> {
+    // Merged faces repeat their texture within its rect. Derivatives come from the unwrapped coordinates, so
+    // the mip doesn't jump where the texture repeats.
+    let scaled = input.uv * input.tile.zw;
+    let uv = input.tile.xy + fract(input.uv) * input.tile.zw;
+    let color = textureSampleGrad(block_textures, block_sampler, uv, dpdx(scaled), dpdy(scaled));
+    return vec4<f32>(mix(color.rgb * input.shade, globals.fog_color, input.fog), color.a);
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(shade(input).rgb, 1.0);
+}
+
+// Blended over what's behind, such as water.
+@fragment
+fn fs_translucent(input: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(input);
+}
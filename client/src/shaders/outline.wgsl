@@ -0,0 +1,18 @@
+// Lines drawn over the world, such as the outline of the targeted block.
+
+struct Globals {
+    view_projection: mat4x4<f32>,
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+
+@vertex
+fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return globals.view_projection * vec4<f32>(position, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return globals.color;
+}
@@ -0,0 +1,59 @@
+// Draws particles as quads facing the camera, one instance each.
+
+struct Globals {
+    view_projection: mat4x4<f32>,
+    right: vec3<f32>,
+    up: vec3<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(0) @binding(1) var block_textures: texture_2d<f32>;
+@group(0) @binding(2) var block_sampler: sampler;
+
+struct InstanceInput {
+    @location(0) position: vec3<f32>,
+    @location(1) size: f32,
+    @location(2) color: vec4<f32>,
+    // Rect of the atlas drawn, or no width for a soft round particle.
+    @location(3) tile: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // Position on the quad from 0 to 1.
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) tile: vec4<f32>,
+}
+
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 1.0), vec2<f32>(1.0, 0.0),
+    vec2<f32>(0.0, 1.0), vec2<f32>(1.0, 0.0), vec2<f32>(0.0, 0.0),
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, input: InstanceInput) -> VertexOutput {
+    let corner = CORNERS[index];
+    // Texture rows go down, so the top of the quad is v 0.
+    let offset = (globals.right * (corner.x - 0.5) + globals.up * (0.5 - corner.y)) * input.size;
+    var output: VertexOutput;
+    output.clip_position = globals.view_projection * vec4<f32>(input.position + offset, 1.0);
+    output.corner = corner;
+    output.color = input.color;
+    output.tile = input.tile;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    if (input.tile.z == 0.0) {
+        let fade = 1.0 - smoothstep(0.5, 1.0, length(input.corner * 2.0 - 1.0));
+        return vec4<f32>(input.color.rgb, input.color.a * fade);
+    }
+    let color = textureSampleLevel(block_textures, block_sampler, input.tile.xy + input.corner * input.tile.zw, 0.0) * input.color;
+    // Cut out transparent pixels, such as the gaps in leaves.
+    if (color.a < 0.1) {
+        discard;
+    }
+    return color;
+}
@@ -0,0 +1,109 @@
+// Draws the sky: a gradient from the horizon up with stars at night, then the sun and moon as billboards.
+
+struct Globals {
+    // Rotation and projection of the view, without its position.
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+    zenith: vec3<f32>,
+    stars: f32,
+    horizon: vec3<f32>,
+    moon_fullness: f32,
+    sun_direction: vec3<f32>,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+
+// Half the width of the sun and moon, as a fraction of their distance.
+const SUN_SIZE: f32 = 0.08;
+const MOON_SIZE: f32 = 0.06;
+const SUN_COLOR = vec3<f32>(1.0, 0.95, 0.8);
+const MOON_COLOR = vec3<f32>(0.8, 0.85, 1.0);
+// Cells the sky is split into along each axis, one star at most in each.
+const STAR_CELLS: f32 = 200.0;
+const STAR_DENSITY: f32 = 0.004;
+
+struct SkyOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+// A triangle covering the whole screen.
+@vertex
+fn vs_sky(@builtin(vertex_index) index: u32) -> SkyOutput {
+    let ndc = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u)) * 2.0 - 1.0;
+    var output: SkyOutput;
+    output.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    output.ndc = ndc;
+    return output;
+}
+
+fn hash(cell: vec3<f32>) -> f32 {
+    return fract(sin(dot(cell, vec3<f32>(12.9898, 78.233, 37.719))) * 43758.5453);
+}
+
+@fragment
+fn fs_sky(input: SkyOutput) -> @location(0) vec4<f32> {
+    // The view is at the origin, so any point along a pixel's ray gives its direction.
+    let point = globals.inverse_view_projection * vec4<f32>(input.ndc, 0.5, 1.0);
+    let direction = normalize(point.xyz / point.w);
+    let height = max(direction.y, 0.0);
+    var color = mix(globals.horizon, globals.zenith, sqrt(height));
+    // Below the horizon fades to a darker horizon color.
+    color = mix(color, globals.horizon * 0.5, clamp(-direction.y * 4.0, 0.0, 1.0));
+    let cell = floor(direction * STAR_CELLS);
+    if (hash(cell) < STAR_DENSITY) {
+        color += vec3<f32>(globals.stars * smoothstep(0.0, 0.1, direction.y) * hash(cell + 1.0));
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+struct BodyOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // Position on the billboard from -1 to 1.
+    @location(0) corner: vec2<f32>,
+    @location(1) @interpolate(flat) moon: u32,
+    // Fades the body out as it sets below the horizon.
+    @location(2) @interpolate(flat) visibility: f32,
+}
+
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0),
+);
+
+// A quad facing the view, towards the sun for instance 0 and the moon for instance 1.
+@vertex
+fn vs_body(@builtin(vertex_index) index: u32, @builtin(instance_index) instance: u32) -> BodyOutput {
+    let moon = instance == 1u;
+    let direction = select(globals.sun_direction, -globals.sun_direction, moon);
+    // The sun moves around the Z axis, so Z is always across it.
+    let right = vec3<f32>(0.0, 0.0, 1.0);
+    let up = cross(right, direction);
+    let corner = CORNERS[index];
+    let size = select(SUN_SIZE, MOON_SIZE, moon);
+    let position = direction + (right * corner.x + up * corner.y) * size;
+    var output: BodyOutput;
+    output.clip_position = globals.view_projection * vec4<f32>(position, 1.0);
+    output.corner = corner;
+    output.moon = u32(moon);
+    output.visibility = smoothstep(-0.05, 0.05, direction.y);
+    return output;
+}
+
+@fragment
+fn fs_body(input: BodyOutput) -> @location(0) vec4<f32> {
+    if (input.moon == 0u) {
+        // A square sun with a soft glow around its core.
+        let edge = max(abs(input.corner.x), abs(input.corner.y));
+        let glow = 1.0 - smoothstep(0.5, 1.0, edge);
+        return vec4<f32>(SUN_COLOR, glow * input.visibility);
+    }
+    let distance = length(input.corner);
+    if (distance > 1.0) {
+        discard;
+    }
+    // The lit side shrinks from the whole disc at a full moon to nothing at a new moon.
+    let lit = step(1.0 - 2.0 * globals.moon_fullness, input.corner.x);
+    let brightness = mix(0.05, 1.0, lit);
+    return vec4<f32>(MOON_COLOR * brightness, input.visibility);
+}
@@ -0,0 +1,228 @@
+use shared::engine::{
+    block::sign::{self, SignBlockEntity, SignText, SIGN_LINES, SIGN_MAX_LINE_CHARS},
+    math::{coords::{BlockPos, WorldPos, CHUNK_SIZE}, ray::Ray, vector::Vec3},
+    net::{packet::{self, Packet}, sign::{EditSign, OpenSignEditor}},
+    world::World
+};
+use winit::{dpi::PhysicalSize, keyboard::{Key, NamedKey}};
+
+use crate::{camera::Camera, text::{measure, TextRenderer, GLYPH_HEIGHT, GLYPH_SPACING, GLYPH_WIDTH, LINE_SPACING}};
+
+/// Size of each pixel of the sign editor's font, in screen pixels.
+pub const EDITOR_SCALE: f32 = 3.0;
+pub const EDITOR_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+pub const EDITOR_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+/// Signs further from the camera than this aren't drawn, as their text would be too small to read.
+pub const SIGN_DRAW_DISTANCE: f64 = 24.0;
+/// Size of each pixel of a sign's font one block from the camera, in screen pixels. Text shrinks with distance.
+pub const SIGN_TEXT_SCALE: f32 = 6.0;
+pub const SIGN_TEXT_COLOR: [f32; 4] = [0.05, 0.03, 0.01, 1.0];
+pub const SIGN_BACKGROUND: [f32; 4] = [0.4, 0.26, 0.12, 0.85];
+
+/// The editor for writing on a sign the player just placed, opened when the server says so.
+/// While open, keys type onto the sign instead of controlling the game. Finishing queues the text to send.
+/// ```
+/// # use client::signs::SignEditor;
+/// # use shared::engine::math::coords::BlockPos;
+/// # use winit::keyboard::{Key, NamedKey};
+/// let mut editor = SignEditor::new();
+/// editor.open(BlockPos::new(1, 2, 3));
+/// editor.type_text("Spawn\r");
+/// editor.key(&Key::Named(NamedKey::Enter));
+/// editor.type_text("this way, through the long tunnel");
+/// editor.key(&Key::Named(NamedKey::Escape));
+/// assert!(!editor.is_open());
+/// let edits = editor.take_edits();
+/// assert_eq!(edits[0].block, BlockPos::new(1, 2, 3));
+/// assert_eq!(edits[0].lines, ["Spawn", "this way, through the lo", "", ""]);
+/// assert!(editor.take_edits().is_empty());
+/// ```
+pub struct SignEditor {
+    /// Sign being edited, while open.
+    block: Option<BlockPos>,
+    lines: [String; SIGN_LINES],
+    /// Line being typed on.
+    line: usize,
+    /// Finished edits to send to the server.
+    edits: Vec<EditSign>
+}
+
+impl SignEditor {
+    pub fn new() -> SignEditor {
+        return SignEditor { block: None, lines: Default::default(), line: 0, edits: Vec::new() };
+    }
+
+    pub fn is_open(&self) -> bool {
+        return self.block.is_some();
+    }
+
+    /// Start writing on a blank sign.
+    pub fn open(&mut self, block: BlockPos) {
+        self.block = Some(block);
+        self.lines = Default::default();
+        self.line = 0;
+    }
+
+    /// Handle an encoded OpenSignEditor from the server. False if the packet isn't one.
+    pub fn receive(&mut self, data: &[u8]) -> bool {
+        if packet::packet_id(data).ok() != Some(<OpenSignEditor as Packet>::ID) {
+            return false;
+        }
+        if let Ok(open) = packet::decode::<OpenSignEditor>(data) {
+            self.open(open.block);
+        }
+        return true;
+    }
+
+    /// Lines typed so far.
+    pub fn lines(&self) -> &[String; SIGN_LINES] {
+        return &self.lines;
+    }
+
+    /// Type text on the current line while open. Control characters, such as the enter key's, are left out, and
+    /// typing stops at SIGN_MAX_LINE_CHARS, so the server never has to refuse the text.
+    pub fn type_text(&mut self, text: &str) {
+        if !self.is_open() {
+            return;
+        }
+        let line = &mut self.lines[self.line];
+        for c in text.chars().filter(|c| !c.is_control()) {
+            if line.chars().count() >= SIGN_MAX_LINE_CHARS {
+                break;
+            }
+            line.push(c);
+        }
+    }
+
+    /// Handle a key pressed while open. Enter moves to the next line, finishing on the last, and escape finishes.
+    /// Typing text is left to type_text().
+    pub fn key(&mut self, key: &Key) {
+        if !self.is_open() {
+            return;
+        }
+        let Key::Named(key) = key else {
+            return;
+        };
+        match key {
+            NamedKey::Enter if self.line + 1 < SIGN_LINES => self.line += 1,
+            NamedKey::Enter | NamedKey::Escape => self.finish(),
+            NamedKey::Backspace => {
+                self.lines[self.line].pop();
+            }
+            NamedKey::ArrowUp => self.line = self.line.saturating_sub(1),
+            NamedKey::ArrowDown => self.line = (self.line + 1).min(SIGN_LINES - 1),
+            _ => {}
+        }
+    }
+
+    /// Edits finished since last called, for whoever holds the connection to send.
+    pub fn take_edits(&mut self) -> Vec<EditSign> {
+        return std::mem::take(&mut self.edits);
+    }
+
+    /// Queue the text in the middle of the window, with a cursor on the line being typed on.
+    pub fn draw(&self, text: &mut TextRenderer, size: PhysicalSize<u32>) {
+        if !self.is_open() {
+            return;
+        }
+        let line_height = (GLYPH_HEIGHT + LINE_SPACING) as f32 * EDITOR_SCALE;
+        let width = ((GLYPH_WIDTH + GLYPH_SPACING) as usize * (SIGN_MAX_LINE_CHARS + 1)) as f32 * EDITOR_SCALE;
+        let left = (size.width as f32 - width) / 2.0;
+        let top = (size.height as f32 - line_height * SIGN_LINES as f32) / 2.0;
+        text.rect([left - EDITOR_SCALE, top - EDITOR_SCALE], [width + 2.0 * EDITOR_SCALE, line_height * SIGN_LINES as f32 + 2.0 * EDITOR_SCALE], EDITOR_BACKGROUND);
+        for (row, line) in self.lines.iter().enumerate() {
+            let shown = if row == self.line { format!("{}_", line) } else { line.clone() };
+            let (line_width, _) = measure(&shown);
+            let x = left + (width - line_width as f32 * EDITOR_SCALE) / 2.0;
+            text.text([x, top + row as f32 * line_height], EDITOR_SCALE, EDITOR_COLOR, &shown);
+        }
+    }
+
+    /// Close the editor, queueing what was written.
+    fn finish(&mut self) {
+        let Some(block) = self.block.take() else {
+            return;
+        };
+        let lines = std::mem::take(&mut self.lines);
+        self.edits.push(EditSign { block, lines: lines.to_vec() });
+    }
+}
+
+impl Default for SignEditor {
+    fn default() -> Self {
+        return SignEditor::new();
+    }
+}
+
+/// Show text the player finished writing straight away, rather than waiting for the server to send it back.
+/// The server sends the sign's text back if it refuses the edit.
+pub fn predict_edit(world: &World, edit: &EditSign) {
+    if let Ok(text) = SignText::new(&edit.lines) {
+        sign::set_sign_text(world, edit.block, text);
+    }
+}
+
+/// Signs with text near the camera that it can see, nearest first.
+pub fn visible_signs(world: &World, camera: &Camera) -> Vec<(BlockPos, SignText)> {
+    let eye = camera.position();
+    let radius = (SIGN_DRAW_DISTANCE / CHUNK_SIZE as f64).ceil() as i32;
+    let mut signs = Vec::new();
+    for chunk in eye.chunk().within_radius(radius) {
+        let Some(entities) = world.block_entities(chunk) else {
+            continue;
+        };
+        for (block, entity) in entities.read().unwrap().iter() {
+            let entity = entity.lock().unwrap();
+            let Some(sign) = entity.as_any().downcast_ref::<SignBlockEntity>() else {
+                continue;
+            };
+            if !sign.text.is_empty() && eye.distance(block.center()) <= SIGN_DRAW_DISTANCE {
+                signs.push((*block, sign.text.clone()));
+            }
+        }
+    }
+    // Text is drawn over the world without depth, so signs behind something are left out.
+    signs.retain(|(block, _)| {
+        let origin = to_vec3(eye);
+        let ray = Ray::new(origin, to_vec3(block.center()) - origin);
+        return world.raycast(ray, 1.0).is_none_or(|hit| hit.block == *block);
+    });
+    signs.sort_by(|(a, _), (b, _)| eye.distance(a.center()).total_cmp(&eye.distance(b.center())));
+    return signs;
+}
+
+/// Where a point in the world is on the screen, in pixels from the top left. None if it's behind the camera.
+pub fn project(camera: &Camera, size: PhysicalSize<u32>, position: WorldPos) -> Option<[f32; 2]> {
+    let clip = camera.view_projection() * camera.origin().to_render(position).extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let x = (clip.x / clip.w * 0.5 + 0.5) * size.width as f32;
+    let y = (0.5 - clip.y / clip.w * 0.5) * size.height as f32;
+    return Some([x, y]);
+}
+
+/// Queue the text of the signs the camera can see, over each sign and smaller further away.
+/// Further signs are queued first, so nearer ones are drawn over them.
+pub fn draw_signs(text: &mut TextRenderer, size: PhysicalSize<u32>, world: &World, camera: &Camera) {
+    for (block, sign) in visible_signs(world, camera).iter().rev() {
+        let center = block.center();
+        let Some([x, y]) = project(camera, size, center) else {
+            continue;
+        };
+        let scale = (SIGN_TEXT_SCALE / camera.position().distance(center) as f32).clamp(1.0, SIGN_TEXT_SCALE);
+        let lines: Vec<&String> = sign.lines().iter().collect();
+        let line_height = (GLYPH_HEIGHT + LINE_SPACING) as f32 * scale;
+        let width = lines.iter().map(|line| measure(line).0).max().unwrap_or(0) as f32 * scale + scale;
+        let top = y - line_height * SIGN_LINES as f32 / 2.0;
+        text.rect([x - width / 2.0, top], [width, line_height * SIGN_LINES as f32], SIGN_BACKGROUND);
+        for (row, line) in lines.iter().enumerate() {
+            let line_width = measure(line).0 as f32 * scale;
+            text.text([x - line_width / 2.0, top + row as f32 * line_height + scale], scale, SIGN_TEXT_COLOR, line);
+        }
+    }
+}
+
+fn to_vec3(pos: WorldPos) -> Vec3 {
+    return Vec3::new(pos.x as f32, pos.y as f32, pos.z as f32);
+}
\ No newline at end of file
@@ -0,0 +1,191 @@
+use std::mem::size_of;
+
+use shared::engine::{
+    math::{matrix::Mat4, vector::Vec3},
+    world::time::{WorldTime, MIN_DAYLIGHT}
+};
+
+use crate::{chunk_renderer::{ChunkView, Fog, DEPTH_FORMAT}, renderer::as_bytes};
+
+/// Colors of the sky straight up and at the horizon, at noon and at midnight.
+pub const DAY_ZENITH: Vec3 = Vec3::new(0.25, 0.45, 1.0);
+pub const DAY_HORIZON: Vec3 = Vec3::new(0.62, 0.78, 1.0);
+pub const NIGHT_ZENITH: Vec3 = Vec3::new(0.002, 0.003, 0.012);
+pub const NIGHT_HORIZON: Vec3 = Vec3::new(0.01, 0.014, 0.035);
+/// Color the horizon turns as the sun rises and sets.
+pub const TWILIGHT_HORIZON: Vec3 = Vec3::new(1.0, 0.42, 0.16);
+/// Tint of sky light by day, at twilight, and by night, before it's dimmed by the daylight.
+pub const DAY_AMBIENT: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+pub const TWILIGHT_AMBIENT: Vec3 = Vec3::new(1.0, 0.75, 0.55);
+pub const NIGHT_AMBIENT: Vec3 = Vec3::new(0.55, 0.65, 1.0);
+/// Fraction of the fog distance where fog starts.
+pub const FOG_START: f32 = 0.6;
+
+/// Everything about the sky that changes over the day, from the world time. Drives the sky's colors, the sun
+/// and moon, the fog the world fades into, and the tint and brightness of sky light.
+/// ```
+/// # use client::sky::{SkyState, DAY_HORIZON, NIGHT_HORIZON};
+/// # use shared::engine::world::time::{WorldTime, NOON, MIDNIGHT, SUNSET};
+/// let noon = SkyState::at(WorldTime::new(NOON));
+/// assert!(noon.horizon.approx_eq(DAY_HORIZON, 1e-6));
+/// assert_eq!(noon.stars, 0.0);
+/// assert!(noon.sun_direction.y > 0.99);
+/// let midnight = SkyState::at(WorldTime::new(MIDNIGHT));
+/// assert!(midnight.horizon.approx_eq(NIGHT_HORIZON, 1e-6));
+/// assert_eq!(midnight.stars, 1.0);
+/// assert!(midnight.ambient.y < noon.ambient.y);
+/// // Sunsets are redder than noon.
+/// let sunset = SkyState::at(WorldTime::new(SUNSET));
+/// assert!(sunset.horizon.x / sunset.horizon.z > noon.horizon.x / noon.horizon.z);
+/// assert_eq!(noon.fog(100.0).color, noon.horizon);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkyState {
+    pub zenith: Vec3,
+    pub horizon: Vec3,
+    /// Unit vector towards the sun. The moon is opposite it.
+    pub sun_direction: Vec3,
+    /// From 0.0 for a new moon to 1.0 for a full moon.
+    pub moon_fullness: f32,
+    /// Brightness of the stars, from 0.0 by day to 1.0 by night.
+    pub stars: f32,
+    /// Color of sky light, dimmed by the daylight.
+    pub ambient: Vec3
+}
+
+impl SkyState {
+    pub fn at(time: WorldTime) -> SkyState {
+        let daylight = time.daylight();
+        let day = (daylight - MIN_DAYLIGHT) / (1.0 - MIN_DAYLIGHT);
+        let sun_direction = time.sun_direction();
+        // Strongest with the sun on the horizon, gone once it's well up or down.
+        let twilight = (1.0 - sun_direction.y.abs() * 4.0).clamp(0.0, 1.0);
+        let horizon = NIGHT_HORIZON.lerp(DAY_HORIZON, day).lerp(TWILIGHT_HORIZON, twilight * 0.7);
+        let ambient = NIGHT_AMBIENT.lerp(DAY_AMBIENT, day).lerp(TWILIGHT_AMBIENT, twilight * 0.5);
+        return SkyState {
+            zenith: NIGHT_ZENITH.lerp(DAY_ZENITH, day),
+            horizon,
+            sun_direction,
+            moon_fullness: time.moon_fullness(),
+            stars: 1.0 - day,
+            ambient: ambient * daylight
+        };
+    }
+
+    /// Fog fading the world into the horizon by a distance from the camera.
+    pub fn fog(&self, distance: f32) -> Fog {
+        return Fog { color: self.horizon, start: distance * FOG_START, end: distance };
+    }
+}
+
+/// Uniforms of the sky shader. Matches Globals in sky.wgsl.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Globals {
+    /// The view's rotation and projection, without its position, as the sky is infinitely far away.
+    view_projection: Mat4,
+    inverse_view_projection: Mat4,
+    zenith: [f32; 3],
+    stars: f32,
+    horizon: [f32; 3],
+    moon_fullness: f32,
+    sun_direction: [f32; 3],
+    padding: f32
+}
+
+/// Draws the sky behind the world: a gradient from the horizon up, stars at night, and the sun and moon as
+/// billboards. Drawn first, without depth, so everything else covers it.
+pub struct SkyRenderer {
+    gradient: wgpu::RenderPipeline,
+    bodies: wgpu::RenderPipeline,
+    globals: wgpu::Buffer,
+    bind_group: wgpu::BindGroup
+}
+
+impl SkyRenderer {
+    pub fn new(device: &wgpu::Device, color_format: wgpu::TextureFormat) -> SkyRenderer {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/sky.wgsl"));
+        let globals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sky globals"),
+            size: size_of::<Globals>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sky"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+                count: None
+            }]
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sky"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: globals.as_entire_binding() }]
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("sky"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0
+        });
+        let pipeline = |label: &str, vertex: &str, fragment: &str, blend: Option<wgpu::BlendState>| {
+            return device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState { module: &shader, entry_point: Some(vertex), compilation_options: Default::default(), buffers: &[] },
+                primitive: Default::default(),
+                // The pass has the world's depth buffer, but the sky is behind everything, so it neither tests nor writes depth.
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: Some(false),
+                    depth_compare: Some(wgpu::CompareFunction::Always),
+                    stencil: Default::default(),
+                    bias: Default::default()
+                }),
+                multisample: Default::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some(fragment),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState { format: color_format, blend, write_mask: wgpu::ColorWrites::ALL })]
+                }),
+                multiview_mask: None,
+                cache: None
+            });
+        };
+        let gradient = pipeline("sky", "vs_sky", "fs_sky", None);
+        // The sun and moon add their light to the sky behind them.
+        let additive = wgpu::BlendComponent { src_factor: wgpu::BlendFactor::SrcAlpha, dst_factor: wgpu::BlendFactor::One, operation: wgpu::BlendOperation::Add };
+        let bodies = pipeline("sun and moon", "vs_body", "fs_body", Some(wgpu::BlendState { color: additive, alpha: additive }));
+        return SkyRenderer { gradient, bodies, globals, bind_group };
+    }
+
+    /// Upload the sky as seen from a view. Called before the render pass.
+    pub fn prepare(&self, queue: &wgpu::Queue, view: &ChunkView, sky: &SkyState) {
+        // Moving the camera back to the origin leaves only its rotation.
+        let view_projection = view.view_projection * Mat4::from_translation(view.eye);
+        let globals = Globals {
+            view_projection,
+            inverse_view_projection: view_projection.inverse().unwrap_or(Mat4::IDENTITY),
+            zenith: sky.zenith.to_array(),
+            stars: sky.stars,
+            horizon: sky.horizon.to_array(),
+            moon_fullness: sky.moon_fullness,
+            sun_direction: sky.sun_direction.to_array(),
+            padding: 0.0
+        };
+        queue.write_buffer(&self.globals, 0, as_bytes(&[globals]));
+    }
+
+    /// Draw the sky uploaded by prepare(). Drawn before anything else in the pass.
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>) {
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_pipeline(&self.gradient);
+        pass.draw(0..3, 0..1);
+        // A quad each for the sun and the moon.
+        pass.set_pipeline(&self.bodies);
+        pass.draw(0..6, 0..2);
+    }
+}
\ No newline at end of file
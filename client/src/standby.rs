@@ -0,0 +1,145 @@
+use std::{io, sync::Arc};
+
+use shared::engine::{
+    compression::Compression,
+    job::system::JobSystem,
+    light::storage::ChunkLight,
+    math::coords::ChunkPos,
+    save::chunk::{decode_chunk, encode_chunk},
+    world::{block_entity::BlockEntityMap, chunk::Chunk, container::{SharedChunk, SharedLight}, World}
+};
+
+/// Chunks compressed or decompressed by each job while suspending or resuming a world.
+pub const STANDBY_BATCH_SIZE: usize = 64;
+/// Codec for suspended chunks, fast both ways so switching back is quick.
+pub const STANDBY_COMPRESSION: Compression = Compression::Lz4;
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// A chunk taken out of a suspended world, with its blocks and light compressed.
+struct SuspendedChunk {
+    pos: ChunkPos,
+    blocks: Vec<u8>,
+    light: Vec<u8>,
+    /// Kept as they are, as they're few and can't all be encoded without knowing their types.
+    block_entities: BlockEntityMap
+}
+
+/// A world kept loaded but suspended, such as while in the menu, so going back to it doesn't wait on the server
+/// or the disk. Nothing in it runs, as nothing holds it but the standby, and its chunks stay in memory compressed.
+/// ```
+/// # use client::standby::StandbyWorld;
+/// # use shared::engine::block::sign::{self, SignText};
+/// # use shared::engine::job::system::JobSystem;
+/// # use shared::engine::light::storage::SKY_CHANNEL;
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos, LocalPos};
+/// # use shared::engine::world::{chunk::Chunk, World};
+/// # use std::sync::Arc;
+/// let jobs = JobSystem::new(2);
+/// let world = Arc::new(World::new());
+/// for x in 0..3 {
+///     world.insert_chunk(Chunk::new(ChunkPos::new(x, 0, 0)));
+/// }
+/// world.set_block(BlockPos::new(1, 2, 3), 7);
+/// world.light(ChunkPos::ORIGIN).unwrap().write().unwrap().set_channel(LocalPos::new(1, 3, 3), SKY_CHANNEL, 15);
+/// let text = SignText::new(&["Home"]).unwrap();
+/// sign::set_sign_text(&world, BlockPos::new(1, 2, 3), text.clone());
+///
+/// let standby = StandbyWorld::suspend(world, &jobs).unwrap();
+/// assert_eq!(standby.chunk_count(), 3);
+/// assert_eq!(standby.world().chunk_count(), 0);
+///
+/// let world = standby.resume(&jobs).unwrap();
+/// assert_eq!(world.chunk_count(), 3);
+/// assert_eq!(world.get_block(BlockPos::new(1, 2, 3)), Some(7));
+/// assert_eq!(world.light(ChunkPos::ORIGIN).unwrap().read().unwrap().channel(LocalPos::new(1, 3, 3), SKY_CHANNEL), 15);
+/// assert_eq!(sign::sign_text(&world, BlockPos::new(1, 2, 3)), Some(text));
+/// ```
+pub struct StandbyWorld {
+    /// The world with its chunks taken out, keeping its entities, level, and events.
+    world: Arc<World>,
+    chunks: Vec<SuspendedChunk>
+}
+
+impl StandbyWorld {
+    /// Take every chunk out of a world, compressing them on the job system.
+    pub fn suspend(world: Arc<World>, jobs: &JobSystem) -> io::Result<StandbyWorld> {
+        let mut taken: Vec<(ChunkPos, SharedChunk, SharedLight)> = Vec::new();
+        let mut block_entities = Vec::new();
+        for pos in world.loaded_chunks() {
+            let light = world.light(pos);
+            let entities = world.block_entities(pos).map(|entities| std::mem::take(&mut *entities.write().unwrap()));
+            if let (Some(chunk), Some(light)) = (world.remove_chunk(pos), light) {
+                taken.push((pos, chunk, light));
+                block_entities.push(entities.unwrap_or_default());
+            }
+        }
+
+        let futures: Vec<_> = taken.chunks(STANDBY_BATCH_SIZE).map(|batch| {
+            let mut batch = batch.to_vec();
+            return jobs.run_job(move || {
+                return std::mem::take(&mut batch).into_iter().map(|(pos, chunk, light)| {
+                    let blocks = STANDBY_COMPRESSION.compress(&encode_chunk(&chunk.read().unwrap()))?;
+                    let light = STANDBY_COMPRESSION.compress(&light.read().unwrap().to_bytes())?;
+                    return Ok((pos, blocks, light));
+                }).collect::<io::Result<Vec<_>>>();
+            });
+        }).collect();
+        let mut compressed = Vec::with_capacity(taken.len());
+        for future in futures {
+            compressed.extend(future.wait()?);
+        }
+        let chunks = compressed.into_iter().zip(block_entities)
+            .map(|((pos, blocks, light), block_entities)| SuspendedChunk { pos, blocks, light, block_entities })
+            .collect();
+        return Ok(StandbyWorld { world, chunks });
+    }
+
+    /// Put every chunk back, decompressing them on the job system, and give back the world to play in again.
+    pub fn resume(self, jobs: &JobSystem) -> io::Result<Arc<World>> {
+        let StandbyWorld { world, mut chunks } = self;
+        let futures: Vec<_> = chunks.chunks_mut(STANDBY_BATCH_SIZE).map(|batch| {
+            let mut batch: Vec<(ChunkPos, Vec<u8>, Vec<u8>)> = batch.iter_mut()
+                .map(|chunk| (chunk.pos, std::mem::take(&mut chunk.blocks), std::mem::take(&mut chunk.light)))
+                .collect();
+            return jobs.run_job(move || {
+                return std::mem::take(&mut batch).into_iter().map(|(pos, blocks, light)| {
+                    let chunk = decode_chunk(pos, &STANDBY_COMPRESSION.decompress(&blocks)?)?;
+                    let light = ChunkLight::from_bytes(&STANDBY_COMPRESSION.decompress(&light)?).ok_or_else(|| invalid("suspended light is the wrong size"))?;
+                    return Ok((chunk, light));
+                }).collect::<io::Result<Vec<(Chunk, ChunkLight)>>>();
+            });
+        }).collect();
+        let mut decoded = Vec::with_capacity(chunks.len());
+        for future in futures {
+            decoded.extend(future.wait()?);
+        }
+        for ((chunk, light), suspended) in decoded.into_iter().zip(chunks) {
+            let pos = chunk.pos();
+            world.insert_chunk(chunk);
+            if let Some(shared) = world.light(pos) {
+                *shared.write().unwrap() = light;
+            }
+            if let Some(shared) = world.block_entities(pos) {
+                *shared.write().unwrap() = suspended.block_entities;
+            }
+        }
+        return Ok(world);
+    }
+
+    /// The suspended world, with no chunks loaded.
+    pub fn world(&self) -> &Arc<World> {
+        return &self.world;
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        return self.chunks.len();
+    }
+
+    /// Bytes of compressed blocks and light held.
+    pub fn memory_usage(&self) -> usize {
+        return self.chunks.iter().map(|chunk| chunk.blocks.len() + chunk.light.len()).sum();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,122 @@
+use shared::engine::{
+    entity::id::EntityId,
+    math::{aabb::Aabb, coords::BlockPos, direction::Direction, precision::RenderOrigin, ray::Ray, vector::Vec3},
+    net::interaction::{DigBlock, PlaceBlock},
+    world::{raycast::{BlockHit, PickHit}, World}
+};
+
+use crate::camera::Camera;
+
+/// How far away players can reach blocks and entities, in blocks. The server checks digging and placing against it.
+pub use shared::engine::net::interaction::REACH_DISTANCE;
+/// How far outlines stand off the block's faces, so they aren't hidden inside them by depth testing.
+pub const OUTLINE_OFFSET: f32 = 0.002;
+
+/// Finds what the crosshair points at each frame, for the selection outline and for breaking and placing.
+/// ```
+/// # use client::{camera::Camera, targeting::Targeting};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::{coords::{BlockPos, ChunkPos, WorldPos}, direction::Direction};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// world.set_block(BlockPos::new(8, 4, 0), 1);
+/// // The camera faces -Z, towards the block 3 blocks away.
+/// let camera = Camera::new(WorldPos::new(8.5, 4.5, 3.5));
+/// let mut targeting = Targeting::new();
+/// targeting.update(&world, &camera, None);
+/// assert_eq!(targeting.break_target(), Some(BlockPos::new(8, 4, 0)));
+/// assert_eq!(targeting.place_target(), Some((BlockPos::new(8, 4, 1), Direction::PosZ)));
+/// assert_eq!(targeting.dig().unwrap().face, Direction::PosZ);
+/// assert_eq!(targeting.place(2).unwrap().against, BlockPos::new(8, 4, 0));
+///
+/// // Out of reach.
+/// targeting.update(&world, &Camera::new(WorldPos::new(8.5, 4.5, 9.5)), None);
+/// assert_eq!(targeting.target(), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Targeting {
+    reach: f32,
+    target: Option<PickHit>,
+    /// Collision boxes of the targeted block, relative to its minimum corner.
+    outline: Vec<Aabb>
+}
+
+impl Targeting {
+    pub fn new() -> Targeting {
+        return Targeting { reach: REACH_DISTANCE, target: None, outline: Vec::new() };
+    }
+
+    pub fn with_reach(mut self, reach: f32) -> Self {
+        self.reach = reach;
+        return self;
+    }
+
+    /// Cast a ray out from the camera, skipping the caster, which is the player's own entity.
+    pub fn update(&mut self, world: &World, camera: &Camera, caster: Option<EntityId>) {
+        let position = camera.position();
+        let ray = Ray::new(Vec3::new(position.x as f32, position.y as f32, position.z as f32), camera.forward());
+        self.target = world.pick(ray, self.reach, caster);
+        self.outline.clear();
+        if let Some(PickHit::Block(hit)) = self.target {
+            self.outline.extend_from_slice(world.block_shapes().boxes(hit.id));
+        }
+    }
+
+    /// Stop targeting anything, such as when the world unloads.
+    pub fn clear(&mut self) {
+        self.target = None;
+        self.outline.clear();
+    }
+
+    /// The block or entity the crosshair is on, if it's within reach.
+    pub fn target(&self) -> Option<PickHit> {
+        return self.target;
+    }
+
+    /// The targeted block, if the crosshair is on a block rather than an entity.
+    pub fn block(&self) -> Option<BlockHit> {
+        return match self.target {
+            Some(PickHit::Block(hit)) => Some(hit),
+            _ => None
+        };
+    }
+
+    /// Block that breaking would break.
+    pub fn break_target(&self) -> Option<BlockPos> {
+        return self.block().map(|hit| hit.block);
+    }
+
+    /// Where placing would put a block, and the face of the targeted block it's placed against.
+    /// None when the camera is inside the targeted block, as there's no face to place against.
+    pub fn place_target(&self) -> Option<(BlockPos, Direction)> {
+        let hit = self.block()?;
+        return hit.face.map(|face| (hit.adjacent(), face));
+    }
+
+    /// Packet asking the server to break the targeted block.
+    pub fn dig(&self) -> Option<DigBlock> {
+        let hit = self.block()?;
+        return hit.face.map(|face| DigBlock { block: hit.block, face });
+    }
+
+    /// Packet asking the server to place the block item in a hotbar slot against the targeted face.
+    pub fn place(&self, slot: u8) -> Option<PlaceBlock> {
+        let hit = self.block()?;
+        return hit.face.map(|face| PlaceBlock { against: hit.block, face, slot });
+    }
+
+    /// Boxes to outline around the targeted block, relative to a render origin, pushed slightly out from its faces.
+    pub fn outline(&self, origin: RenderOrigin) -> Vec<Aabb> {
+        let Some(hit) = self.block() else {
+            return Vec::new();
+        };
+        let corner = origin.block_to_render(hit.block);
+        return self.outline.iter().map(|shape| shape.translate(corner).expand(Vec3::splat(OUTLINE_OFFSET))).collect();
+    }
+}
+
+impl Default for Targeting {
+    fn default() -> Targeting {
+        return Targeting::new();
+    }
+}
\ No newline at end of file
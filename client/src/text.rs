@@ -0,0 +1,332 @@
+use std::mem::size_of;
+
+use winit::dpi::PhysicalSize;
+
+use crate::{chunk_renderer::DEPTH_FORMAT, renderer::as_bytes};
+
+/// Size of a character before scaling, in pixels.
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+/// Gap after each character, and below each line, before scaling.
+pub const GLYPH_SPACING: u32 = 1;
+pub const LINE_SPACING: u32 = 2;
+/// Quads queued before the vertex buffer grows past its starting size.
+const INITIAL_QUAD_CAPACITY: u64 = 4096;
+/// Each glyph sits in a cell one pixel wider than it, so the empty column keeps neighbours from bleeding in.
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+
+/// Built in 5x7 pixel font, so debug text needs no font files. Each row is a bit per column, with the most
+/// significant bit on the left. Only upper case letters are drawn, and lower case is drawn as upper case.
+const GLYPHS: [(char, [u8; GLYPH_HEIGHT as usize]); 60] = [
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+    ('"', [0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('#', [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('\'', [0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+    ('*', [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    ('/', [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    (';', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000]),
+    ('<', [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('>', [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('[', [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110]),
+    (']', [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110]),
+    ('_', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111]),
+    ('|', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+];
+
+/// Index of the glyph drawn for a character. Characters the font doesn't have are drawn as '?'.
+fn glyph_index(c: char) -> usize {
+    let c = c.to_ascii_uppercase();
+    return GLYPHS.binary_search_by_key(&c, |(glyph, _)| *glyph)
+        .unwrap_or_else(|_| GLYPHS.binary_search_by_key(&'?', |(glyph, _)| *glyph).unwrap());
+}
+
+/// Size of text before scaling, in pixels, with a line per '\n'.
+/// ```
+/// # use client::text::{measure, GLYPH_HEIGHT, GLYPH_SPACING, GLYPH_WIDTH, LINE_SPACING};
+/// assert_eq!(measure("FPS"), (3 * (GLYPH_WIDTH + GLYPH_SPACING), GLYPH_HEIGHT + LINE_SPACING));
+/// assert_eq!(measure("A\nLONGER"), (6 * (GLYPH_WIDTH + GLYPH_SPACING), 2 * (GLYPH_HEIGHT + LINE_SPACING)));
+/// ```
+pub fn measure(text: &str) -> (u32, u32) {
+    let width = text.lines().map(|line| line.chars().count() as u32).max().unwrap_or(0) * (GLYPH_WIDTH + GLYPH_SPACING);
+    return (width, text.lines().count() as u32 * (GLYPH_HEIGHT + LINE_SPACING));
+}
+
+/// Pixels of every glyph, one byte each, side by side in a single row of cells, followed by a solid cell panels
+/// are drawn with.
+fn glyph_atlas() -> Vec<u8> {
+    let width = (GLYPHS.len() as u32 + 1) * CELL_WIDTH;
+    let mut pixels = vec![0u8; (width * GLYPH_HEIGHT) as usize];
+    for (cell, (_, rows)) in GLYPHS.iter().enumerate() {
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                if row & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                    pixels[y * width as usize + cell * CELL_WIDTH as usize + x as usize] = 255;
+                }
+            }
+        }
+    }
+    for y in 0..GLYPH_HEIGHT {
+        let start = (y * width + GLYPHS.len() as u32 * CELL_WIDTH) as usize;
+        pixels[start..start + CELL_WIDTH as usize].fill(255);
+    }
+    return pixels;
+}
+
+/// Vertex of a text quad. Matches VertexInput in text.wgsl.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct TextVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4]
+}
+
+/// Draws text on top of everything else, in pixels from the top left of the window, with the built in font.
+/// Text is queued over a frame, then uploaded by prepare() and drawn in one call, and cleared for the next frame.
+pub struct TextRenderer {
+    pipeline: wgpu::RenderPipeline,
+    screen: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertices: wgpu::Buffer,
+    queued: Vec<TextVertex>,
+    /// Vertices uploaded by the last prepare().
+    vertex_count: u32
+}
+
+impl TextRenderer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, color_format: wgpu::TextureFormat) -> TextRenderer {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/text.wgsl"));
+        let screen = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("text screen"),
+            size: size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        let size = wgpu::Extent3d { width: (GLYPHS.len() as u32 + 1) * CELL_WIDTH, height: GLYPH_HEIGHT, depth_or_array_layers: 1 };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("glyphs"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo { texture: &texture, mip_level: 0, origin: wgpu::Origin3d::ZERO, aspect: wgpu::TextureAspect::All },
+            &glyph_atlas(),
+            wgpu::TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(size.width), rows_per_image: Some(size.height) },
+            size
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor { label: Some("glyphs"), ..Default::default() });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("text"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None
+                }
+            ]
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("text"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: screen.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&sampler) }
+            ]
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("text"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("text"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[Some(wgpu::VertexBufferLayout {
+                    array_stride: size_of::<TextVertex>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4]
+                })]
+            },
+            primitive: Default::default(),
+            // Drawn in the same pass as the world, so it needs the depth attachment's format, but ignores depth.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: Some(false),
+                depth_compare: Some(wgpu::CompareFunction::Always),
+                stencil: Default::default(),
+                bias: Default::default()
+            }),
+            multisample: Default::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            multiview_mask: None,
+            cache: None
+        });
+        let vertices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("text vertices"),
+            size: INITIAL_QUAD_CAPACITY * 6 * size_of::<TextVertex>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        return TextRenderer { pipeline, screen, bind_group, vertices, queued: Vec::new(), vertex_count: 0 };
+    }
+
+    /// Two triangles covering a rectangle of the screen, showing a rectangle of the atlas, in cells.
+    fn quad(&mut self, position: [f32; 2], size: [f32; 2], cell: u32, cell_size: [u32; 2], color: [f32; 4]) {
+        let atlas_width = ((GLYPHS.len() as u32 + 1) * CELL_WIDTH) as f32;
+        let u = (cell * CELL_WIDTH) as f32 / atlas_width;
+        let uv_size = [cell_size[0] as f32 / atlas_width, cell_size[1] as f32 / GLYPH_HEIGHT as f32];
+        let corner = |x: f32, y: f32| TextVertex {
+            position: [position[0] + size[0] * x, position[1] + size[1] * y],
+            uv: [u + uv_size[0] * x, uv_size[1] * y],
+            color
+        };
+        let corners = [corner(0.0, 0.0), corner(1.0, 0.0), corner(0.0, 1.0), corner(1.0, 1.0)];
+        self.queued.extend_from_slice(&[corners[0], corners[2], corners[1], corners[1], corners[2], corners[3]]);
+    }
+
+    /// Queue a solid rectangle, such as a panel behind text.
+    pub fn rect(&mut self, position: [f32; 2], size: [f32; 2], color: [f32; 4]) {
+        self.quad(position, size, GLYPHS.len() as u32, [1, 1], color);
+    }
+
+    /// Queue text with its top left corner at a position. Each pixel of the font is drawn scale pixels wide.
+    pub fn text(&mut self, position: [f32; 2], scale: f32, color: [f32; 4], text: &str) {
+        let advance = (GLYPH_WIDTH + GLYPH_SPACING) as f32 * scale;
+        let line_height = (GLYPH_HEIGHT + LINE_SPACING) as f32 * scale;
+        let glyph_size = [GLYPH_WIDTH as f32 * scale, GLYPH_HEIGHT as f32 * scale];
+        for (row, line) in text.lines().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                if c == ' ' {
+                    continue;
+                }
+                let at = [position[0] + column as f32 * advance, position[1] + row as f32 * line_height];
+                self.quad(at, glyph_size, glyph_index(c) as u32, [GLYPH_WIDTH, GLYPH_HEIGHT], color);
+            }
+        }
+    }
+
+    /// Queue lines of text, each on a translucent panel so it's readable over anything.
+    pub fn panel(&mut self, position: [f32; 2], scale: f32, color: [f32; 4], background: [f32; 4], lines: &[String]) {
+        let line_height = (GLYPH_HEIGHT + LINE_SPACING) as f32 * scale;
+        for (row, line) in lines.iter().enumerate() {
+            let (width, _) = measure(line);
+            let y = position[1] + row as f32 * line_height;
+            self.rect([position[0], y], [width as f32 * scale + scale, line_height], background);
+            self.text([position[0] + scale, y + scale], scale, color, line);
+        }
+    }
+
+    /// Upload the queued text for drawing this frame, and clear it for the next.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, size: PhysicalSize<u32>) {
+        queue.write_buffer(&self.screen, 0, as_bytes(&[size.width as f32, size.height as f32, 0.0, 0.0]));
+        let bytes = (self.queued.len() * size_of::<TextVertex>()) as u64;
+        if bytes > self.vertices.size() {
+            self.vertices = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("text vertices"),
+                size: bytes.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            });
+        }
+        if bytes > 0 {
+            queue.write_buffer(&self.vertices, 0, as_bytes(&self.queued));
+        }
+        self.vertex_count = self.queued.len() as u32;
+        self.queued.clear();
+    }
+
+    /// Draw the text uploaded by prepare(). Drawn last in a pass, so it covers what's beneath.
+    pub fn draw(&self, pass: &mut wgpu::RenderPass<'_>) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertices.slice(..));
+        pass.draw(0..self.vertex_count, 0..1);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,339 @@
+use std::{fs, io, path::{Path, PathBuf}, sync::{Arc, RwLock}, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use shared::engine::{command::dispatcher::PermissionLevel, net::auth::{format_uuid, parse_uuid, Authenticator}};
+
+/// Players let in while the whitelist is on, in the server's directory.
+pub const WHITELIST_FILE: &str = "whitelist.json";
+pub const BANS_FILE: &str = "banned-players.json";
+/// Players with a permission level above a player's.
+pub const OPERATORS_FILE: &str = "ops.json";
+
+/// Seconds since the Unix epoch, which expiry times are in.
+pub fn unix_time() -> u64 {
+    return SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+}
+
+const UNITS: [(char, u64); 4] = [('d', 86400), ('h', 3600), ('m', 60), ('s', 1)];
+
+/// Parse a length of time such as "90s", "30m", "12h" or "7d", as typed in commands.
+/// ```
+/// # use server::access::parse_duration;
+/// # use std::time::Duration;
+/// assert_eq!(parse_duration("12h"), Some(Duration::from_secs(12 * 3600)));
+/// assert_eq!(parse_duration("12"), None);
+/// assert_eq!(parse_duration("0d"), None);
+/// ```
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let unit = text.chars().last()?;
+    let (_, seconds) = UNITS.iter().find(|(name, _)| *name == unit)?;
+    let count: u64 = text[..text.len() - 1].parse().ok()?;
+    if count == 0 {
+        return None;
+    }
+    return Some(Duration::from_secs(count.checked_mul(*seconds)?));
+}
+
+/// A length of time in its two largest units, such as "2d 3h" or "45s".
+/// ```
+/// # use server::access::format_duration;
+/// # use std::time::Duration;
+/// assert_eq!(format_duration(Duration::from_secs(2 * 86400 + 3 * 3600 + 59)), "2d 3h");
+/// assert_eq!(format_duration(Duration::from_secs(45)), "45s");
+/// ```
+pub fn format_duration(duration: Duration) -> String {
+    let mut remaining = duration.as_secs();
+    let mut parts = Vec::new();
+    for (name, seconds) in UNITS.iter() {
+        if remaining >= *seconds && parts.len() < 2 {
+            parts.push(format!("{}{}", remaining / seconds, name));
+            remaining %= seconds;
+        }
+    }
+    if parts.is_empty() {
+        return "0s".to_string();
+    }
+    return parts.join(" ");
+}
+
+fn invalid(path: &Path, error: serde_json::Error) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), error));
+}
+
+/// Read a list of entries from a JSON file, empty if there isn't one yet.
+fn load_json<T: DeserializeOwned>(path: &Path) -> io::Result<Vec<T>> {
+    return match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|error| invalid(path, error)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(error)
+    };
+}
+
+/// Write a list of entries as indented JSON, so it's easy to edit by hand. Written to a temporary file that
+/// replaces the old one, so a crash while saving leaves the previous list.
+fn save_json<T: Serialize>(path: &Path, entries: &[T]) -> io::Result<()> {
+    let text = serde_json::to_string_pretty(entries).map_err(|error| invalid(path, error))?;
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, text)?;
+    return fs::rename(&temporary, path);
+}
+
+/// A player on the whitelist or ban list.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessEntry {
+    /// The player's UUID in the usual dashed form. Players are matched by it rather than by name.
+    pub uuid: String,
+    /// The player's name when they were added, for people reading the list.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Unix time the entry stops applying, or None for never.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>
+}
+
+impl AccessEntry {
+    pub fn new(uuid: u128, name: &str) -> AccessEntry {
+        return AccessEntry { uuid: format_uuid(uuid), name: name.to_string(), reason: None, expires: None };
+    }
+
+    pub fn with_reason(mut self, reason: &str) -> AccessEntry {
+        self.reason = Some(reason.to_string());
+        return self;
+    }
+
+    /// Stop applying at a Unix time.
+    pub fn with_expiry(mut self, expires: u64) -> AccessEntry {
+        self.expires = Some(expires);
+        return self;
+    }
+
+    /// The UUID, or None if the file was edited into something that isn't one.
+    pub fn uuid(&self) -> Option<u128> {
+        return parse_uuid(&self.uuid);
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        return self.expires.is_some_and(|expires| expires <= now);
+    }
+}
+
+/// A whitelist or ban list, saved as a JSON array of entries whenever it changes. Expired entries stop
+/// applying, and are dropped the next time the list is saved.
+/// ```
+/// # use server::access::{AccessEntry, AccessList};
+/// let directory = std::env::temp_dir().join(format!("access_list_doctest_{}", std::process::id()));
+/// std::fs::create_dir_all(&directory).unwrap();
+/// let mut bans = AccessList::load(&directory.join("banned-players.json")).unwrap();
+/// bans.add(AccessEntry::new(1, "griefer").with_reason("burned spawn"), 100).unwrap();
+/// bans.add(AccessEntry::new(2, "spammer").with_expiry(200), 100).unwrap();
+/// assert!(bans.get(2, 150).is_some());
+/// assert!(bans.get(2, 200).is_none());
+///
+/// let bans = AccessList::load(&directory.join("banned-players.json")).unwrap();
+/// assert_eq!(bans.get(1, 300).unwrap().reason.as_deref(), Some("burned spawn"));
+/// assert_eq!(bans.find("GRIEFER", 300).unwrap().name, "griefer");
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct AccessList {
+    path: PathBuf,
+    entries: Vec<AccessEntry>
+}
+
+impl AccessList {
+    /// Read a list from a file, empty if there isn't one yet. Fails if the file isn't a valid list, rather than
+    /// letting everyone in.
+    pub fn load(path: &Path) -> io::Result<AccessList> {
+        return Ok(AccessList { path: path.to_path_buf(), entries: load_json(path)? });
+    }
+
+    pub fn path(&self) -> &Path {
+        return &self.path;
+    }
+
+    /// A player's entry, unless it has expired.
+    pub fn get(&self, uuid: u128, now: u64) -> Option<&AccessEntry> {
+        return self.entries.iter().find(|entry| entry.uuid() == Some(uuid) && !entry.is_expired(now));
+    }
+
+    /// A player's entry by name, ignoring case, unless it has expired.
+    pub fn find(&self, name: &str, now: u64) -> Option<&AccessEntry> {
+        return self.entries.iter().find(|entry| entry.name.eq_ignore_ascii_case(name) && !entry.is_expired(now));
+    }
+
+    /// Entries still applying, oldest first.
+    pub fn entries(&self, now: u64) -> Vec<&AccessEntry> {
+        return self.entries.iter().filter(|entry| !entry.is_expired(now)).collect();
+    }
+
+    /// Add a player, replacing any entry they had, and save the list.
+    pub fn add(&mut self, entry: AccessEntry, now: u64) -> io::Result<()> {
+        self.entries.retain(|existing| existing.uuid() != entry.uuid());
+        self.entries.push(entry);
+        return self.save(now);
+    }
+
+    /// Remove a player and save the list, returning whether they were on it.
+    pub fn remove(&mut self, uuid: u128, now: u64) -> io::Result<bool> {
+        let count = self.entries.len();
+        self.entries.retain(|entry| entry.uuid() != Some(uuid));
+        if self.entries.len() == count {
+            return Ok(false);
+        }
+        self.save(now)?;
+        return Ok(true);
+    }
+
+    fn save(&mut self, now: u64) -> io::Result<()> {
+        self.entries.retain(|entry| !entry.is_expired(now));
+        return save_json(&self.path, &self.entries);
+    }
+}
+
+/// A player with a permission level above a player's.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Operator {
+    pub uuid: String,
+    pub name: String,
+    /// Permission level from 1 to 4, as in PermissionLevel.
+    pub level: u8
+}
+
+/// The whitelist, ban list and operators of a server, kept in JSON files in its directory. Checked as players log
+/// in, and by every command a player runs, so edits by command apply at once.
+/// ```
+/// # use server::access::{AccessControl, AccessEntry};
+/// # use shared::engine::command::dispatcher::PermissionLevel;
+/// let directory = std::env::temp_dir().join(format!("access_control_doctest_{}", std::process::id()));
+/// std::fs::create_dir_all(&directory).unwrap();
+/// let mut access = AccessControl::load(&directory).unwrap();
+/// assert!(access.check(1, 0).is_ok());
+///
+/// access.bans_mut().add(AccessEntry::new(1, "griefer").with_reason("burned spawn").with_expiry(7200), 0).unwrap();
+/// assert_eq!(access.check(1, 0), Err("You are banned from this server: burned spawn. The ban ends in 2h.".to_string()));
+/// assert!(access.check(1, 7200).is_ok());
+///
+/// access.set_whitelist_enabled(true);
+/// assert!(access.check(2, 0).is_err());
+/// access.whitelist_mut().add(AccessEntry::new(2, "builder"), 0).unwrap();
+/// assert!(access.check(2, 0).is_ok());
+///
+/// access.set_permission(2, "builder", PermissionLevel::GameMaster).unwrap();
+/// assert_eq!(access.permission(2), PermissionLevel::GameMaster);
+/// assert_eq!(AccessControl::load(&directory).unwrap().permission(2), PermissionLevel::GameMaster);
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct AccessControl {
+    whitelist: AccessList,
+    bans: AccessList,
+    operators_path: PathBuf,
+    operators: Vec<Operator>,
+    whitelist_enabled: bool
+}
+
+impl AccessControl {
+    /// Read the lists from a server's directory. The whitelist starts off.
+    pub fn load(directory: &Path) -> io::Result<AccessControl> {
+        let operators_path = directory.join(OPERATORS_FILE);
+        return Ok(AccessControl {
+            whitelist: AccessList::load(&directory.join(WHITELIST_FILE))?,
+            bans: AccessList::load(&directory.join(BANS_FILE))?,
+            operators: load_json(&operators_path)?,
+            operators_path,
+            whitelist_enabled: false
+        });
+    }
+
+    /// Only let in players on the whitelist.
+    pub fn with_whitelist(mut self, enabled: bool) -> AccessControl {
+        self.whitelist_enabled = enabled;
+        return self;
+    }
+
+    pub fn whitelist(&self) -> &AccessList {
+        return &self.whitelist;
+    }
+
+    pub fn whitelist_mut(&mut self) -> &mut AccessList {
+        return &mut self.whitelist;
+    }
+
+    pub fn bans(&self) -> &AccessList {
+        return &self.bans;
+    }
+
+    pub fn bans_mut(&mut self) -> &mut AccessList {
+        return &mut self.bans;
+    }
+
+    pub fn is_whitelist_enabled(&self) -> bool {
+        return self.whitelist_enabled;
+    }
+
+    pub fn set_whitelist_enabled(&mut self, enabled: bool) {
+        self.whitelist_enabled = enabled;
+    }
+
+    /// Whether a player may log in at a Unix time, or the message telling them why not.
+    pub fn check(&self, uuid: u128, now: u64) -> Result<(), String> {
+        if let Some(ban) = self.bans.get(uuid, now) {
+            let mut message = match ban.reason.as_ref() {
+                Some(reason) => format!("You are banned from this server: {}.", reason),
+                None => "You are banned from this server.".to_string()
+            };
+            if let Some(expires) = ban.expires {
+                message += &format!(" The ban ends in {}.", format_duration(Duration::from_secs(expires - now)));
+            }
+            return Err(message);
+        }
+        if self.whitelist_enabled && self.whitelist.get(uuid, now).is_none() {
+            return Err("You aren't whitelisted on this server.".to_string());
+        }
+        return Ok(());
+    }
+
+    /// Operators, by name.
+    pub fn operators(&self) -> &[Operator] {
+        return &self.operators;
+    }
+
+    /// What a player may do, from their operator entry.
+    pub fn permission(&self, uuid: u128) -> PermissionLevel {
+        return self.operators.iter()
+            .find(|operator| parse_uuid(&operator.uuid) == Some(uuid))
+            .and_then(|operator| PermissionLevel::from_level(operator.level))
+            .unwrap_or(PermissionLevel::Player);
+    }
+
+    /// Give a player a permission level and save the operators, removing them from the operators at
+    /// PermissionLevel::Player.
+    pub fn set_permission(&mut self, uuid: u128, name: &str, level: PermissionLevel) -> io::Result<()> {
+        let formatted = format_uuid(uuid);
+        self.operators.retain(|operator| parse_uuid(&operator.uuid) != Some(uuid));
+        if level != PermissionLevel::Player {
+            self.operators.push(Operator { uuid: formatted, name: name.to_string(), level: level.level() });
+        }
+        return save_json(&self.operators_path, &self.operators);
+    }
+}
+
+/// Checks logins against a server's access lists, once another authenticator has said who the player is.
+/// Turned away players are told why as they're disconnected.
+pub struct AccessAuthenticator {
+    inner: Arc<dyn Authenticator>,
+    access: Arc<RwLock<AccessControl>>
+}
+
+impl AccessAuthenticator {
+    pub fn new(inner: Arc<dyn Authenticator>, access: Arc<RwLock<AccessControl>>) -> AccessAuthenticator {
+        return AccessAuthenticator { inner, access };
+    }
+}
+
+impl Authenticator for AccessAuthenticator {
+    fn authenticate(&self, name: &str, token: &str) -> Result<u128, String> {
+        let uuid = self.inner.authenticate(name, token)?;
+        self.access.read().unwrap().check(uuid, unix_time())?;
+        return Ok(uuid);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,436 @@
+use std::{collections::{HashMap, HashSet}, sync::Arc};
+
+use shared::engine::{
+    config::DEFAULT_VIEW_DISTANCE,
+    entity::replication::ClientId,
+    job::future::JobFuture,
+    math::coords::ChunkPos,
+    memory::{self, MemoryCategory, MemoryTracker, ENTITY_MEMORY_ESTIMATE},
+    save::{entities::EntityStorage, manager::SaveManager},
+    world::{loader::{ChunkLoadError, ChunkLoadResult, ChunkLoader, ChunkOrigin}, World}
+};
+
+/// Ticks a chunk stays loaded after losing its last ticket, so a player walking back and forth over a border
+/// doesn't save and reload the same chunks over and over.
+pub const DEFAULT_UNLOAD_DELAY: u64 = 100;
+/// Most chunks saved and unloaded in one tick, so a player teleporting away doesn't stall the server.
+pub const DEFAULT_UNLOAD_BUDGET: usize = 64;
+
+/// A reason for a chunk to stay loaded. A chunk is loaded while it has any ticket, and saved and unloaded once it
+/// has none.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Ticket {
+    /// The chunk is within a player's view distance.
+    Player(ClientId),
+    /// The chunk is in a region forced to stay loaded, such as by the forceload command.
+    Forced,
+    /// The chunk is being read or generated. Held until it arrives, so generating it isn't wasted.
+    Loading
+}
+
+/// Chunks with tickets, and which tickets each has.
+/// ```
+/// # use server::chunks::{ChunkTickets, Ticket};
+/// # use shared::engine::math::coords::ChunkPos;
+/// let mut tickets = ChunkTickets::new();
+/// assert!(tickets.add(ChunkPos::ORIGIN, Ticket::Player(1)));
+/// assert!(!tickets.add(ChunkPos::ORIGIN, Ticket::Forced));
+/// assert!(!tickets.remove(ChunkPos::ORIGIN, Ticket::Player(1)));
+/// assert!(tickets.remove(ChunkPos::ORIGIN, Ticket::Forced));
+/// assert!(!tickets.has_tickets(ChunkPos::ORIGIN));
+/// ```
+pub struct ChunkTickets {
+    tickets: HashMap<ChunkPos, HashSet<Ticket>>
+}
+
+impl ChunkTickets {
+    pub fn new() -> ChunkTickets {
+        return ChunkTickets { tickets: HashMap::new() };
+    }
+
+    /// Give a chunk a ticket. True if the chunk had none before.
+    pub fn add(&mut self, pos: ChunkPos, ticket: Ticket) -> bool {
+        let tickets = self.tickets.entry(pos).or_default();
+        let first = tickets.is_empty();
+        tickets.insert(ticket);
+        return first;
+    }
+
+    /// Take a ticket from a chunk. True if that was the chunk's last ticket.
+    pub fn remove(&mut self, pos: ChunkPos, ticket: Ticket) -> bool {
+        let Some(tickets) = self.tickets.get_mut(&pos) else {
+            return false;
+        };
+        if !tickets.remove(&ticket) || !tickets.is_empty() {
+            return false;
+        }
+        self.tickets.remove(&pos);
+        return true;
+    }
+
+    pub fn has_tickets(&self, pos: ChunkPos) -> bool {
+        return self.tickets.contains_key(&pos);
+    }
+
+    pub fn get(&self, pos: ChunkPos) -> impl Iterator<Item = &Ticket> {
+        return self.tickets.get(&pos).into_iter().flatten();
+    }
+
+    /// Every chunk with a ticket.
+    pub fn chunks(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        return self.tickets.keys().copied();
+    }
+
+    /// Number of chunks with a ticket.
+    pub fn len(&self) -> usize {
+        return self.tickets.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.tickets.is_empty();
+    }
+}
+
+impl Default for ChunkTickets {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/// Chunks loaded within a radius of a center, as players see them.
+fn view_area(center: ChunkPos, radius: i32) -> HashSet<ChunkPos> {
+    let limit = radius as i64 * radius as i64;
+    return center.within_radius(radius).filter(|pos| pos.distance_squared(center) <= limit).collect();
+}
+
+/// Keeps the chunks of a world loaded while they have tickets. Chunks within view distance of a player and in
+/// forced regions get tickets, and are read from the save or generated on the job system. Chunks that lose their
+/// last ticket are saved and unloaded after a delay, so memory only holds what's in use.
+/// ```
+/// # use server::chunks::ChunkManager;
+/// # use shared::engine::job::system::JobSystem;
+/// # use shared::engine::save::{manager::SaveManager, region::RegionStorage};
+/// # use shared::engine::world::{chunk::Chunk, loader::{ChunkLoader, ChunkStorage, NoChunkStorage}, World};
+/// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+/// # use std::sync::Arc;
+/// let directory = std::env::temp_dir().join(format!("chunk_manager_doctest_{}", std::process::id()));
+/// let jobs = Arc::new(JobSystem::new(2));
+/// let storage = Arc::new(RegionStorage::new(&directory).unwrap());
+/// let mut saves = SaveManager::new(jobs.clone(), jobs.clone(), storage.clone());
+/// let generator = Arc::new(|pos: ChunkPos| Chunk::filled(pos, 1));
+/// let world = Arc::new(World::new());
+/// let mut chunks = ChunkManager::new(world.clone(), ChunkLoader::new(jobs.clone(), jobs, Arc::new(NoChunkStorage), generator))
+///     .with_view_distance(1)
+///     .with_unload_delay(0);
+///
+/// // The chunk a player is in and its six neighbours, and a forced chunk.
+/// chunks.set_player_view(1, ChunkPos::ORIGIN);
+/// assert!(chunks.force(ChunkPos::new(5, 0, 0)));
+/// while world.chunk_count() < 8 {
+///     assert!(chunks.tick(&mut saves).is_empty());
+/// }
+///
+/// // Once they leave, only the forced chunk is kept, and the rest are saved.
+/// chunks.remove_player(1);
+/// while world.chunk_count() > 1 {
+///     chunks.tick(&mut saves);
+/// }
+/// assert!(world.is_loaded(ChunkPos::new(5, 0, 0)));
+/// saves.flush_all(&world).unwrap();
+/// assert_eq!(storage.read(ChunkPos::ORIGIN).unwrap().unwrap().get_block(LocalPos::new(0, 0, 0)), 1);
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct ChunkManager {
+    world: Arc<World>,
+    loader: ChunkLoader,
+    entities: Option<Arc<EntityStorage>>,
+    tickets: ChunkTickets,
+    view_distance: i32,
+    /// Center of each player's view, and the chunks it gave tickets to.
+    views: HashMap<ClientId, (ChunkPos, HashSet<ChunkPos>)>,
+    forced: HashSet<ChunkPos>,
+    /// Chunks given a ticket that aren't loaded yet, to request next tick.
+    wanted: HashSet<ChunkPos>,
+    loading: Vec<(ChunkPos, JobFuture<ChunkLoadResult>)>,
+    /// Chunks that couldn't be read, which aren't requested again until they lose their tickets.
+    failed: HashSet<ChunkPos>,
+    /// Loaded chunks with no tickets, and the tick they lost their last one.
+    unloading: HashMap<ChunkPos, u64>,
+    unload_delay: u64,
+    unload_budget: usize,
+    /// Where loaded chunks' and entities' memory is registered, and whose budgets unload chunks early.
+    memory: Option<Arc<MemoryTracker>>,
+    /// Bytes registered for each loaded chunk, measured as it loaded.
+    sizes: HashMap<ChunkPos, u64>,
+    tick: u64
+}
+
+impl ChunkManager {
+    pub fn new(world: Arc<World>, loader: ChunkLoader) -> ChunkManager {
+        return ChunkManager {
+            world,
+            loader,
+            entities: None,
+            tickets: ChunkTickets::new(),
+            view_distance: DEFAULT_VIEW_DISTANCE as i32,
+            views: HashMap::new(),
+            forced: HashSet::new(),
+            wanted: HashSet::new(),
+            loading: Vec::new(),
+            failed: HashSet::new(),
+            unloading: HashMap::new(),
+            unload_delay: DEFAULT_UNLOAD_DELAY,
+            unload_budget: DEFAULT_UNLOAD_BUDGET,
+            memory: None,
+            sizes: HashMap::new(),
+            tick: 0
+        };
+    }
+
+    /// Spawn the saved entities of chunks as they load.
+    pub fn with_entities(mut self, entities: Arc<EntityStorage>) -> ChunkManager {
+        self.entities = Some(entities);
+        return self;
+    }
+
+    /// Radius in chunks around each player given tickets.
+    pub fn with_view_distance(mut self, chunks: u32) -> ChunkManager {
+        self.view_distance = chunks as i32;
+        return self;
+    }
+
+    /// Ticks a chunk stays loaded after losing its last ticket.
+    pub fn with_unload_delay(mut self, ticks: u64) -> ChunkManager {
+        self.unload_delay = ticks;
+        return self;
+    }
+
+    /// Most chunks unloaded in one tick.
+    pub fn with_unload_budget(mut self, chunks: usize) -> ChunkManager {
+        self.unload_budget = chunks;
+        return self;
+    }
+
+    /// Register the memory of chunks and entities, and when either is over budget, unload chunks waiting to
+    /// unload without waiting out their delay, furthest from every player first.
+    /// ```
+    /// # use server::chunks::ChunkManager;
+    /// # use shared::engine::job::system::JobSystem;
+    /// # use shared::engine::memory::{MemoryCategory, MemoryTracker};
+    /// # use shared::engine::save::{manager::SaveManager, region::RegionStorage};
+    /// # use shared::engine::world::{chunk::Chunk, loader::{ChunkLoader, NoChunkStorage}, World};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// # use std::sync::Arc;
+    /// # let directory = std::env::temp_dir().join(format!("chunk_memory_doctest_{}", std::process::id()));
+    /// # let jobs = Arc::new(JobSystem::new(2));
+    /// # let mut saves = SaveManager::new(jobs.clone(), jobs.clone(), Arc::new(RegionStorage::new(&directory).unwrap()));
+    /// # let generator = Arc::new(|pos: ChunkPos| Chunk::filled(pos, 1));
+    /// let world = Arc::new(World::new());
+    /// let memory = Arc::new(MemoryTracker::new());
+    /// let mut chunks = ChunkManager::new(world.clone(), ChunkLoader::new(jobs.clone(), jobs, Arc::new(NoChunkStorage), generator))
+    ///     .with_view_distance(1)
+    ///     .with_memory(memory.clone());
+    /// chunks.set_player_view(1, ChunkPos::ORIGIN);
+    /// while world.chunk_count() < 7 {
+    ///     chunks.tick(&mut saves);
+    /// }
+    /// let used = memory.used(MemoryCategory::Chunks);
+    /// assert!(used > 0);
+    ///
+    /// // Within budget, chunks no one needs wait out their delay.
+    /// chunks.remove_player(1);
+    /// chunks.tick(&mut saves);
+    /// assert_eq!(world.chunk_count(), 7);
+    /// // Over it, just enough of them unload at once.
+    /// memory.set_budget(MemoryCategory::Chunks, Some(used / 2));
+    /// chunks.tick(&mut saves);
+    /// assert_eq!(world.chunk_count(), 3);
+    /// assert_eq!(memory.over_budget(MemoryCategory::Chunks), 0);
+    /// # saves.flush_all(&world).unwrap();
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn with_memory(mut self, memory: Arc<MemoryTracker>) -> ChunkManager {
+        self.memory = Some(memory);
+        return self;
+    }
+
+    pub fn tickets(&self) -> &ChunkTickets {
+        return &self.tickets;
+    }
+
+    /// Number of chunks being read or generated.
+    pub fn loading_count(&self) -> usize {
+        return self.loading.len() + self.wanted.len();
+    }
+
+    /// Number of loaded chunks waiting to be unloaded.
+    pub fn unloading_count(&self) -> usize {
+        return self.unloading.len();
+    }
+
+    fn add_ticket(&mut self, pos: ChunkPos, ticket: Ticket) {
+        if self.tickets.add(pos, ticket) {
+            self.unloading.remove(&pos);
+            if !self.world.is_loaded(pos) {
+                self.wanted.insert(pos);
+            }
+        }
+    }
+
+    fn remove_ticket(&mut self, pos: ChunkPos, ticket: Ticket) {
+        if self.tickets.remove(pos, ticket) {
+            self.wanted.remove(&pos);
+            self.failed.remove(&pos);
+            if self.world.is_loaded(pos) {
+                self.unloading.insert(pos, self.tick);
+            }
+        }
+    }
+
+    /// Give the chunks around a player tickets, moving them from where the player was before.
+    pub fn set_player_view(&mut self, client: ClientId, center: ChunkPos) {
+        if self.views.get(&client).is_some_and(|(previous, _)| *previous == center) {
+            return;
+        }
+        let previous = self.views.remove(&client).map(|(_, area)| area).unwrap_or_default();
+        let area = view_area(center, self.view_distance);
+        for pos in area.iter().filter(|pos| !previous.contains(pos)) {
+            self.add_ticket(*pos, Ticket::Player(client));
+        }
+        for pos in previous.iter().filter(|pos| !area.contains(pos)) {
+            self.remove_ticket(*pos, Ticket::Player(client));
+        }
+        self.views.insert(client, (center, area));
+    }
+
+    /// Take the tickets of a player who left.
+    pub fn remove_player(&mut self, client: ClientId) {
+        if let Some((_, area)) = self.views.remove(&client) {
+            for pos in area {
+                self.remove_ticket(pos, Ticket::Player(client));
+            }
+        }
+    }
+
+    /// Keep a chunk loaded whether or not anyone is near it. False if it already was forced.
+    pub fn force(&mut self, pos: ChunkPos) -> bool {
+        if !self.forced.insert(pos) {
+            return false;
+        }
+        self.add_ticket(pos, Ticket::Forced);
+        return true;
+    }
+
+    /// Stop forcing a chunk to stay loaded. False if it wasn't forced.
+    pub fn unforce(&mut self, pos: ChunkPos) -> bool {
+        if !self.forced.remove(&pos) {
+            return false;
+        }
+        self.remove_ticket(pos, Ticket::Forced);
+        return true;
+    }
+
+    /// Chunks forced to stay loaded, sorted.
+    pub fn forced(&self) -> Vec<ChunkPos> {
+        let mut forced: Vec<ChunkPos> = self.forced.iter().copied().collect();
+        forced.sort_unstable_by_key(|pos| (pos.x, pos.y, pos.z));
+        return forced;
+    }
+
+    /// Put chunks that finished loading into the world, request chunks that gained tickets, and save and unload
+    /// chunks whose tickets ran out, returning chunks that couldn't be read. Generated chunks are marked to be saved.
+    pub fn tick(&mut self, saves: &mut SaveManager) -> Vec<ChunkLoadError> {
+        self.tick += 1;
+        let mut errors = Vec::new();
+        let (finished, loading): (Vec<_>, Vec<_>) = std::mem::take(&mut self.loading).into_iter().partition(|(_, future)| future.is_ready());
+        self.loading = loading;
+        for (pos, future) in finished {
+            match future.wait() {
+                Ok(loaded) => {
+                    let size = loaded.chunk.memory_usage() as u64;
+                    if let Some(memory) = self.memory.as_ref() {
+                        memory.allocate(MemoryCategory::Chunks, size);
+                    }
+                    self.sizes.insert(pos, size);
+                    self.world.insert_chunk(loaded.chunk);
+                    if loaded.origin == ChunkOrigin::Generated {
+                        saves.mark_chunk(pos);
+                    } else if let Some(entities) = self.entities.as_ref() {
+                        if let Err(error) = entities.load_chunk(self.world.entities(), pos) {
+                            errors.push(ChunkLoadError { pos, error });
+                        }
+                    }
+                },
+                Err(error) => {
+                    self.failed.insert(pos);
+                    errors.push(error);
+                }
+            }
+            self.remove_ticket(pos, Ticket::Loading);
+        }
+
+        for pos in std::mem::take(&mut self.wanted) {
+            if self.world.is_loaded(pos) || self.failed.contains(&pos) {
+                continue;
+            }
+            self.tickets.add(pos, Ticket::Loading);
+            self.loading.push((pos, self.loader.request(pos)));
+        }
+
+        let tick = self.tick;
+        let mut due: Vec<ChunkPos> = self.unloading.iter()
+            .filter(|(_, since)| tick - **since >= self.unload_delay)
+            .map(|(pos, _)| *pos)
+            .collect();
+        due.sort_unstable_by_key(|pos| (pos.x, pos.y, pos.z));
+        due.truncate(self.unload_budget);
+        let evicted = self.evictions(&due, self.unload_budget - due.len());
+        for pos in due.into_iter().chain(evicted) {
+            self.unload(saves, pos);
+        }
+        return errors;
+    }
+
+    fn unload(&mut self, saves: &mut SaveManager, pos: ChunkPos) {
+        self.unloading.remove(&pos);
+        saves.mark_entities(pos);
+        saves.unload_chunk(&self.world, pos);
+        let size = self.sizes.remove(&pos).unwrap_or(0);
+        if let Some(memory) = self.memory.as_ref() {
+            memory.free(MemoryCategory::Chunks, size);
+        }
+    }
+
+    /// Chunks waiting to unload to unload early to get back within the memory budgets, besides those already due.
+    /// Chunks with tickets are never evicted, so with too many players or forced chunks memory stays over budget.
+    fn evictions(&self, due: &[ChunkPos], limit: usize) -> Vec<ChunkPos> {
+        let Some(memory) = self.memory.as_ref() else {
+            return Vec::new();
+        };
+        memory.set(MemoryCategory::Entities, self.world.entities().len() as u64 * ENTITY_MEMORY_ESTIMATE);
+        let freed: u64 = due.iter().map(|pos| self.sizes.get(pos).copied().unwrap_or(0)).sum();
+        let chunks_over = memory.over_budget(MemoryCategory::Chunks).saturating_sub(freed);
+        let entities_over = memory.over_budget(MemoryCategory::Entities);
+        if chunks_over == 0 && entities_over == 0 {
+            return Vec::new();
+        }
+        let candidates: Vec<(ChunkPos, u64)> = self.unloading.iter()
+            .filter(|(pos, _)| !due.contains(pos))
+            .map(|(pos, since)| (*pos, *since))
+            .collect();
+        let centers: Vec<ChunkPos> = self.views.values().map(|(center, _)| *center).collect();
+        let mut evicted = memory::choose_evictions(&candidates, &centers, &self.sizes, chunks_over);
+        if entities_over > 0 {
+            let sizes: HashMap<ChunkPos, u64> = candidates.iter()
+                .map(|(pos, _)| (*pos, self.world.entities().in_chunk(*pos).len() as u64 * ENTITY_MEMORY_ESTIMATE))
+                .collect();
+            for pos in memory::choose_evictions(&candidates, &centers, &sizes, entities_over) {
+                if !evicted.contains(&pos) {
+                    evicted.push(pos);
+                }
+            }
+        }
+        evicted.truncate(limit);
+        return evicted;
+    }
+}
\ No newline at end of file
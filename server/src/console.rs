@@ -0,0 +1,62 @@
+use std::{io::{self, BufRead, BufReader}, sync::mpsc::{self, Receiver, TryRecvError}, thread};
+
+/// Lines typed into the server's terminal. They're read on a thread of their own, so the tick loop never waits
+/// on input, and picked up once a tick. Blank lines are skipped.
+/// ```
+/// # use server::console::ConsoleInput;
+/// let mut console = ConsoleInput::from_reader(std::io::Cursor::new("list\n\n  say hi \n"));
+/// let mut lines = Vec::new();
+/// while !console.is_closed() {
+///     lines.extend(console.lines());
+/// }
+/// assert_eq!(lines, vec!["list", "say hi"]);
+/// ```
+pub struct ConsoleInput {
+    lines: Receiver<String>,
+    /// Set once the input ends, such as when the server runs without a terminal.
+    closed: bool
+}
+
+impl ConsoleInput {
+    /// Read the process's standard input.
+    pub fn stdin() -> ConsoleInput {
+        return ConsoleInput::from_reader(BufReader::new(io::stdin()));
+    }
+
+    pub fn from_reader<R: BufRead + Send + 'static>(reader: R) -> ConsoleInput {
+        let (sender, lines) = mpsc::channel();
+        thread::Builder::new().name("console".to_string()).spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                let line = line.trim();
+                if !line.is_empty() && sender.send(line.to_string()).is_err() {
+                    break;
+                }
+            }
+        }).expect("the console thread starts");
+        return ConsoleInput { lines, closed: false };
+    }
+
+    /// Lines typed since the previous call, oldest first.
+    pub fn lines(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+        loop {
+            match self.lines.try_recv() {
+                Ok(line) => lines.push(line),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+        return lines;
+    }
+
+    /// Whether the input has ended and every line was taken.
+    pub fn is_closed(&self) -> bool {
+        return self.closed;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+pub mod access;
+pub mod chunks;
+pub mod console;
+pub mod mobs;
+pub mod player;
+pub mod pregen;
+pub mod repair;
+pub mod server;
+pub mod tps;
\ No newline at end of file
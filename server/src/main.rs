@@ -1,3 +1,165 @@
-fn main() {
-    println!("Hello, world!");
+use std::{path::{Path, PathBuf}, sync::{atomic::{AtomicU32, Ordering}, Arc}};
+
+use server::{console::ConsoleInput, repair, server::{generator, Server}};
+use shared::engine::{
+    bench::{Bench, Scenario},
+    block::BlockRegistry,
+    config::EngineConfig,
+    crash::CrashHandler,
+    entity::kinematics::TICKS_PER_SECOND,
+    job::{system::JobSystem, topology::ThreadProfile},
+    net::admin::CommandConsole,
+    progress::ProgressTracker,
+    save::region::RegionPos,
+    tick::GameLoop,
+    worldgen::blocks::TerrainBlocks
+};
+
+const CONFIG_PATH: &str = "server.toml";
+const WORLD_DIRECTORY: &str = "world";
+const CRASH_REPORTS_DIRECTORY: &str = "crash-reports";
+/// Starts the server to generate the spawn region, given a radius in chunks, then exit.
+const PREGEN_FLAG: &str = "--pregen";
+/// Checks every chunk of a world, given its directory, restoring damaged ones from backups or generating them
+/// again, then exits. Run while no server has the world open.
+const REPAIR_FLAG: &str = "--repair";
+
+/// Runs a benchmark scenario on the server's job threads and prints its timings as JSON, then exits.
+const BENCH_FLAG: &str = "--bench";
+const BENCH_USAGE: &str = "usage: --bench jobs <count> <microseconds> | generate <generator> <chunks> | mesh <region directory> <x> <y> <z>";
+/// Seed benchmarks generate terrain from, so every commit generates the same chunks.
+const BENCH_SEED: u64 = 1;
+
+/// Radius given with --pregen, if the server was started to pregenerate the world rather than to play it.
+fn pregen_radius() -> Result<Option<u32>, String> {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(index) = args.iter().position(|arg| arg == PREGEN_FLAG) else {
+        return Ok(None);
+    };
+    return match args.get(index + 1).map(|radius| radius.parse::<u32>()) {
+        Some(Ok(radius)) => Ok(Some(radius)),
+        _ => Err(format!("usage: {} <radius in chunks>", PREGEN_FLAG))
+    };
 }
+
+/// Generate and save the chunks around spawn without letting anyone in. Stopping it part way and running it
+/// again carries on where it stopped.
+fn pregenerate(config: &EngineConfig, radius: u32) -> Result<(), Box<dyn std::error::Error>> {
+    // On a port of its own, so players can't join a server that isn't running ticks.
+    let mut server = Server::bind("127.0.0.1:0", config, Path::new(WORLD_DIRECTORY))?;
+    let progress = ProgressTracker::new("pregen", 0);
+    let reported = AtomicU32::new(u32::MAX);
+    progress.on_progress(move |event| {
+        let percent = event.percent();
+        if reported.swap(percent, Ordering::Relaxed) != percent {
+            println!("pregenerating: {}/{} chunks ({}%)", event.completed, event.total, percent);
+        }
+    });
+    println!("pregenerating chunks within {} chunks of spawn", radius);
+    let report = server.pregenerate(radius, &progress)?;
+    println!("{}", report);
+    server.shutdown()?;
+    return Ok(());
+}
+
+/// World directory given with --repair, if the server was started to repair a world rather than to play it.
+fn repair_directory() -> Result<Option<PathBuf>, String> {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(index) = args.iter().position(|arg| arg == REPAIR_FLAG) else {
+        return Ok(None);
+    };
+    return match args.get(index + 1) {
+        Some(directory) => Ok(Some(PathBuf::from(directory))),
+        None => Err(format!("usage: {} <world directory>", REPAIR_FLAG))
+    };
+}
+
+/// Repair a world offline, printing what was damaged and how it was fixed.
+fn repair_world(directory: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if !directory.is_dir() {
+        return Err(format!("there's no world at {}", directory.display()).into());
+    }
+    println!("repairing {}", directory.display());
+    let report = repair::repair(directory)?;
+    println!("{}", report);
+    return Ok(());
+}
+
+/// Scenario given with --bench, if the server was started to run a benchmark.
+fn bench_scenario() -> Result<Option<Scenario>, String> {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(index) = args.iter().position(|arg| arg == BENCH_FLAG) else {
+        return Ok(None);
+    };
+    let args = &args[index + 1..];
+    let number = |index: usize| args.get(index).and_then(|arg| arg.parse::<i64>().ok()).ok_or(BENCH_USAGE.to_string());
+    let scenario = match args.first().map(String::as_str) {
+        Some("jobs") => Scenario::Jobs { count: number(1)?.max(0) as usize, micros: number(2)?.max(0) as u64 },
+        Some("generate") => {
+            let name = args.get(1).ok_or(BENCH_USAGE.to_string())?;
+            let blocks = TerrainBlocks::register(&mut BlockRegistry::new()).expect("the terrain blocks have valid names");
+            let generator = generator(name, BENCH_SEED, blocks).ok_or(format!("there's no generator called {}", name))?;
+            Scenario::Generate { generator, chunks: number(2)?.max(0) as usize }
+        }
+        Some("mesh") => {
+            let directory = PathBuf::from(args.get(1).ok_or(BENCH_USAGE.to_string())?);
+            Scenario::MeshRegion { directory, region: RegionPos { x: number(2)? as i32, y: number(3)? as i32, z: number(4)? as i32 } }
+        }
+        _ => return Err(BENCH_USAGE.to_string())
+    };
+    return Ok(Some(scenario));
+}
+
+/// Time a scenario, printing a summary to stderr and the report as JSON to stdout, so it can be piped to a file
+/// and compared with other commits.
+fn bench(config: &EngineConfig, scenario: Scenario) -> Result<(), Box<dyn std::error::Error>> {
+    let jobs = Arc::new(JobSystem::new(config.jobs.thread_count(ThreadProfile::Server)));
+    eprintln!("benchmarking {}", scenario);
+    let report = Bench::new(jobs).run(&scenario)?;
+    eprintln!("{}", report);
+    println!("{}", serde_json::to_string_pretty(&report.to_json())?);
+    return Ok(());
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(directory) = repair_directory()? {
+        return repair_world(&directory);
+    }
+    let config = EngineConfig::load(Path::new(CONFIG_PATH))?;
+    if let Some(radius) = pregen_radius()? {
+        return pregenerate(&config, radius);
+    }
+    if let Some(scenario) = bench_scenario()? {
+        return bench(&config, scenario);
+    }
+    let mut server = Server::bind(("0.0.0.0", config.server.port), &config, Path::new(WORLD_DIRECTORY))?;
+    CrashHandler::new(Path::new(CRASH_REPORTS_DIRECTORY))
+        .with_job_system("server", server.jobs())
+        .with_universe(server.universe())
+        .with_emergency_save(server.emergency_save())
+        .install();
+    println!("listening on {}", server.local_addr());
+    if let Some(identity) = server.identity() {
+        println!("connections are encrypted with key {}", identity.fingerprint());
+    }
+
+    let mut console = ConsoleInput::stdin();
+    let running = server.running();
+    let skipped = server.tick_monitor().skipped_counter();
+    let mut game_loop = GameLoop::new(TICKS_PER_SECOND).with_skip(move |ticks| {
+        skipped.add(ticks as u64);
+        eprintln!("can't keep up, skipping {} ticks ({}ms behind); is the server overloaded?", ticks, ticks as u64 * 1000 / TICKS_PER_SECOND as u64);
+    });
+    game_loop.run_while(|| running.load(Ordering::Acquire), |_| {
+        for line in console.lines() {
+            match server.execute_line(&line) {
+                Ok(output) if output.is_empty() => {}
+                Ok(output) => println!("{}", output),
+                Err(error) => eprintln!("{}", error)
+            }
+        }
+        server.tick();
+    });
+    server.shutdown()?;
+    return Ok(());
+}
\ No newline at end of file
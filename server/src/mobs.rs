@@ -0,0 +1,169 @@
+use std::{io, sync::Arc};
+
+use shared::engine::{
+    ai::{
+        behaviors::{Flee, LookAt, Wander},
+        system::{AiSystem, Brain},
+        tree::{BehaviorTree, Selector}
+    },
+    entity::{
+        kinematics::{Transform, Velocity},
+        query::Without,
+        replication::Replicated,
+        schedule::{System, SystemAccess, SystemContext, SystemSchedule},
+        serialize::ComponentTypes,
+        EntityId
+    },
+    config::SpawningConfig,
+    job::system::JobSystem,
+    light::MAX_LIGHT,
+    lod::LodPolicy,
+    math::coords::WorldPos,
+    path::Pathfinder,
+    physics::{body::RigidBody, PhysicsSystem},
+    spawning::{SpawnRules, Spawner},
+    world::World,
+    worldgen::blocks::TerrainBlocks
+};
+
+use crate::player::Player;
+
+pub const MOB_WIDTH: f32 = 0.9;
+pub const MOB_HEIGHT: f32 = 1.3;
+/// Blocks per second mobs wander at.
+pub const WANDER_SPEED: f32 = 2.0;
+/// Blocks per second mobs flee at.
+pub const FLEE_SPEED: f32 = 4.5;
+/// Blocks from where it stands a mob wanders up to at a time.
+pub const WANDER_RADIUS: u32 = 8;
+/// Mobs flee players closer than this, in blocks.
+pub const FLEE_RADIUS: f32 = 3.0;
+/// Mobs watch players closer than this, in blocks.
+pub const LOOK_RADIUS: f32 = 8.0;
+/// Ticks between attempts to spawn and despawn mobs.
+pub const SPAWN_INTERVAL: u64 = 20;
+/// Darkest light mobs spawn in, so they spawn by day.
+pub const MIN_SPAWN_LIGHT: u8 = 9;
+
+/// Marks a server controlled mob, which gets a brain when it's spawned or loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mob;
+
+/// Register the mob marker so mobs are saved with their chunks. Their brains aren't saved, as they're given a
+/// new one when they're loaded.
+/// ```
+/// # use server::mobs::{register_components, Mob};
+/// # use shared::engine::entity::{serialize::ComponentTypes, Entities};
+/// let mut types = ComponentTypes::new();
+/// register_components(&mut types);
+/// let entities = Entities::new();
+/// let mob = entities.spawn();
+/// entities.insert(mob, Mob).unwrap();
+/// let loaded = types.decode(&entities, &types.encode(&entities, &[mob])).unwrap();
+/// assert!(entities.has::<Mob>(loaded[0]));
+/// ```
+pub fn register_components(types: &mut ComponentTypes) {
+    types.register::<Mob>("cube:mob", |_, _| {}, |data| {
+        if !data.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Mob data has the wrong length"));
+        }
+        return Ok(Mob);
+    });
+}
+
+/// Spawn a mob with its feet at a position. It gets its brain the next time the mob systems run.
+pub fn spawn_mob(world: &World, position: WorldPos) -> EntityId {
+    let entities = world.entities();
+    let mob = entities.spawn();
+    let _ = entities.insert(mob, Transform::new(position));
+    let _ = entities.insert(mob, Velocity::default());
+    let _ = entities.insert(mob, RigidBody::new(MOB_WIDTH, MOB_HEIGHT));
+    let _ = entities.insert(mob, Mob);
+    let _ = entities.insert(mob, Replicated);
+    entities.set_chunk(mob, position.chunk());
+    return mob;
+}
+
+/// What a mob does: flee players that come too close, watch those a little further away, or else wander.
+pub fn mob_behavior(pathfinder: &Pathfinder, seed: u64) -> BehaviorTree {
+    let root = Selector::new(vec![
+        Box::new(Flee::<Player>::new(pathfinder.clone(), FLEE_SPEED, FLEE_RADIUS, WANDER_RADIUS)),
+        Box::new(LookAt::<Player>::new(LOOK_RADIUS)),
+        Box::new(Wander::new(pathfinder.clone(), WANDER_SPEED, WANDER_RADIUS))
+    ]);
+    return BehaviorTree::new(Box::new(root), seed);
+}
+
+/// Gives a brain to every mob without one, seeded from the world seed and the mob's id so the same mobs in the
+/// same world make the same choices.
+pub struct BrainSystem {
+    pathfinder: Pathfinder,
+    seed: u64
+}
+
+impl BrainSystem {
+    pub fn new(pathfinder: Pathfinder, seed: u64) -> BrainSystem {
+        return BrainSystem { pathfinder, seed };
+    }
+}
+
+impl System for BrainSystem {
+    fn name(&self) -> &str {
+        return "brains";
+    }
+
+    fn access(&self) -> SystemAccess {
+        return SystemAccess::new().read::<Mob>().write::<Brain>();
+    }
+
+    fn run(&mut self, context: &SystemContext) {
+        for mob in context.query::<&Mob, Without<Brain>>().entities() {
+            let tree = mob_behavior(&self.pathfinder, self.seed ^ mob.to_bits());
+            let _ = context.entities().insert(mob, Brain::new(tree));
+        }
+    }
+}
+
+/// The systems run on the overworld's entities every tick: brains for new mobs, then their AI, thinking less often
+/// far from players as the level of detail says, then physics.
+/// ```
+/// # use server::mobs::{mob_systems, spawn_mob};
+/// # use shared::engine::ai::system::Brain;
+/// # use shared::engine::entity::kinematics::Transform;
+/// # use shared::engine::job::system::JobSystem;
+/// # use shared::engine::lod::{LodPolicy, Viewer};
+/// # use shared::engine::path::Pathfinder;
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{ChunkPos, WorldPos};
+/// # use std::sync::Arc;
+/// let jobs = Arc::new(JobSystem::new(2));
+/// let world = Arc::new(World::new());
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// let mob = spawn_mob(&world, WorldPos::new(8.5, 0.0, 8.5));
+/// let player = world.entities().spawn();
+/// world.entities().insert(player, Viewer).unwrap();
+/// world.entities().insert(player, Transform::new(WorldPos::new(12.0, 0.0, 12.0))).unwrap();
+/// let mut systems = mob_systems(jobs.clone(), Pathfinder::new(Arc::new(|id| id != 0)), 7, LodPolicy::for_view_distance(128.0));
+/// for _ in 0..200 {
+///     systems.run(&jobs, &world);
+/// }
+/// assert!(world.entities().has::<Brain>(mob));
+/// // Wandered off somewhere, standing on the ground.
+/// let transform = world.entities().get::<Transform>(mob).unwrap();
+/// assert_ne!(transform.position, WorldPos::new(8.5, 0.0, 8.5));
+/// assert!(transform.position.y.abs() < 1e-3);
+/// ```
+pub fn mob_systems(jobs: Arc<JobSystem>, pathfinder: Pathfinder, seed: u64, lod: LodPolicy) -> SystemSchedule {
+    let mut systems = SystemSchedule::new();
+    systems.add_system(BrainSystem::new(pathfinder, seed));
+    systems.add_system(AiSystem::new(jobs.clone()).with_lod(lod));
+    systems.add_system(PhysicsSystem::new(jobs));
+    return systems;
+}
+
+/// Where mobs spawn: on grass in daylight, up to the caps of a config's spawning section.
+pub fn mob_spawner(config: &SpawningConfig, terrain: TerrainBlocks) -> Spawner<Mob> {
+    let rules = SpawnRules::new(Arc::new(move |id| id == terrain.grass), MOB_WIDTH, MOB_HEIGHT).with_light(MIN_SPAWN_LIGHT..=MAX_LIGHT);
+    return Spawner::new(rules).with_caps(config.mob_cap, config.chunk_cap);
+}
\ No newline at end of file
@@ -0,0 +1,381 @@
+use std::{collections::HashMap, io, sync::Arc, time::{Duration, Instant}};
+
+use shared::engine::{
+    config::{DEFAULT_PLAYER_TIMEOUT, DEFAULT_VIEW_DISTANCE},
+    entity::{kinematics::Transform, replication::{ClientId, InterestManager, Replicated}, serialize::Unsaved, EntityId},
+    item::Inventory,
+    lod::{LodPolicy, Viewer},
+    math::coords::{BlockPos, ChunkPos, WorldPos, CHUNK_SIZE},
+    net::{chunk_stream::ChunkStreamer, movement::{MovementValidator, MovementViolation, PlayerPosition, PositionCorrection}, packet},
+    physics::body::RigidBody,
+    save::players::PlayerStorage,
+    world::World
+};
+
+/// Slots in a player's inventory, the first nine of which are their hotbar.
+pub const INVENTORY_SLOTS: usize = 36;
+/// Slots at the start of the inventory that players can place blocks from.
+pub const HOTBAR_SLOTS: usize = 9;
+/// Blocks from a player's feet, where their entity is, up to their eyes.
+pub const EYE_HEIGHT: f64 = 1.6;
+/// Width and depth of the box a player takes up, centered on their feet.
+pub const PLAYER_WIDTH: f64 = 0.6;
+pub const PLAYER_HEIGHT: f64 = 1.8;
+
+/// Level of detail within a player's view, thinning out from full detail around them to the far corners of the
+/// chunks streamed to them, so nothing they can see is past the last band.
+/// ```
+/// # use server::player::view_lod;
+/// let lod = view_lod(4);
+/// assert_eq!(lod.band(10.0).unwrap().update_interval, 1);
+/// assert!(!lod.is_culled(5.0 * 32.0 * 3f32.sqrt() - 1.0));
+/// ```
+pub fn view_lod(view_distance: u32) -> LodPolicy {
+    return LodPolicy::for_view_distance(((view_distance + 1) * CHUNK_SIZE as u32) as f32 * 3f32.sqrt());
+}
+
+/// Marks the entity of a logged in player, with who they are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Player {
+    pub client: ClientId,
+    pub name: String,
+    pub uuid: u128
+}
+
+/// A logged in player: their entity, and what their client has been sent of the world around it.
+pub struct PlayerSession {
+    player: Player,
+    entity: EntityId,
+    /// Chunk the player was in as of the last tick, which streaming and entity interest are centered on.
+    view: ChunkPos,
+    streamer: ChunkStreamer,
+    /// Where the player's client says it moved them, checked before the entity follows.
+    movement: MovementValidator,
+    last_heard: Instant
+}
+
+impl PlayerSession {
+    pub fn player(&self) -> &Player {
+        return &self.player;
+    }
+
+    pub fn entity(&self) -> EntityId {
+        return self.entity;
+    }
+
+    pub fn view(&self) -> ChunkPos {
+        return self.view;
+    }
+
+    pub fn movement(&self) -> &MovementValidator {
+        return &self.movement;
+    }
+
+    /// When the client last sent anything.
+    pub fn last_heard(&self) -> Instant {
+        return self.last_heard;
+    }
+}
+
+/// Everyone logged in to a world. Joining spawns a player's entity where they last logged out, or at the world's
+/// spawn point the first time, and starts streaming them the chunks and entities around it. Leaving saves the
+/// entity to their player data, despawns it, and forgets what their client was sent.
+/// ```
+/// # use server::player::{Player, PlayerManager};
+/// # use shared::engine::{entity::{kinematics::{self, Transform}, serialize::ComponentTypes}, math::coords::{BlockPos, WorldPos}, save::players::PlayerStorage, world::World};
+/// # use std::{sync::Arc, time::{Duration, Instant}};
+/// let directory = std::env::temp_dir().join(format!("player_manager_doctest_{}", std::process::id()));
+/// let mut types = ComponentTypes::new();
+/// kinematics::register_components(&mut types);
+/// let world = Arc::new(World::new());
+/// world.set_spawn_point(BlockPos::new(0, 64, 0));
+/// let mut players = PlayerManager::new(world.clone(), PlayerStorage::new(&directory, Arc::new(types)).unwrap());
+///
+/// let start = Instant::now();
+/// let steve = players.join(1, "steve", 7, start).unwrap();
+/// assert_eq!(world.entities().get::<Transform>(steve).unwrap().position, WorldPos::new(0.5, 64.0, 0.5));
+/// assert_eq!(world.entities().get::<Player>(steve).unwrap().name, "steve");
+/// world.entities().insert(steve, Transform::new(WorldPos::new(100.0, 70.0, 0.0))).unwrap();
+/// players.leave(1).unwrap().1.unwrap();
+/// assert!(!world.entities().is_alive(steve));
+///
+/// // Back where they left, and dropped once they go quiet for too long.
+/// let steve = players.join(1, "steve", 7, start).unwrap();
+/// assert_eq!(world.entities().get::<Transform>(steve).unwrap().position, WorldPos::new(100.0, 70.0, 0.0));
+/// assert_eq!(players.eye(1), Some(WorldPos::new(100.0, 71.6, 0.0)));
+/// assert!(players.occupying(BlockPos::new(99, 71, 0)) && !players.occupying(BlockPos::new(100, 72, 0)));
+/// players.tick();
+/// assert_eq!(players.session(1).unwrap().view(), WorldPos::new(100.0, 70.0, 0.0).chunk());
+/// // Moved by the server, so their client is corrected.
+/// world.entities().insert(steve, Transform::new(WorldPos::new(100.0, 80.0, 0.0))).unwrap();
+/// assert_eq!(players.tick_movement().len(), 1);
+/// assert_eq!(players.correction(1).unwrap().position, WorldPos::new(100.0, 80.0, 0.0));
+/// assert_eq!(players.timed_out(start + Duration::from_secs(60)), vec![1]);
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct PlayerManager {
+    world: Arc<World>,
+    storage: PlayerStorage,
+    sessions: HashMap<ClientId, PlayerSession>,
+    interest: InterestManager,
+    view_distance: i32,
+    timeout: Duration
+}
+
+impl PlayerManager {
+    pub fn new(world: Arc<World>, storage: PlayerStorage) -> PlayerManager {
+        let mut interest = InterestManager::new();
+        interest.set_lod(view_lod(DEFAULT_VIEW_DISTANCE));
+        return PlayerManager {
+            world,
+            storage,
+            sessions: HashMap::new(),
+            interest,
+            view_distance: DEFAULT_VIEW_DISTANCE as i32,
+            timeout: Duration::from_secs(DEFAULT_PLAYER_TIMEOUT)
+        };
+    }
+
+    /// Radius in chunks of the area streamed to each player.
+    pub fn with_view_distance(mut self, chunks: u32) -> PlayerManager {
+        self.view_distance = chunks as i32;
+        self.interest.set_lod(view_lod(chunks));
+        return self;
+    }
+
+    /// How long a player may send nothing before timed_out() gives them.
+    pub fn with_timeout(mut self, timeout: Duration) -> PlayerManager {
+        self.timeout = timeout;
+        return self;
+    }
+
+    pub fn session(&self, client: ClientId) -> Option<&PlayerSession> {
+        return self.sessions.get(&client);
+    }
+
+    pub fn sessions(&self) -> impl Iterator<Item = &PlayerSession> {
+        return self.sessions.values();
+    }
+
+    /// The client of a player, by name.
+    pub fn find(&self, name: &str) -> Option<ClientId> {
+        return self.sessions.values().find(|session| session.player.name == name).map(|session| session.player.client);
+    }
+
+    /// Names of the players logged in, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.sessions.values().map(|session| session.player.name.clone()).collect();
+        names.sort_unstable();
+        return names;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.sessions.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.sessions.is_empty();
+    }
+
+    /// Where a player's eyes are, which is where they reach blocks from.
+    pub fn eye(&self, client: ClientId) -> Option<WorldPos> {
+        let session = self.sessions.get(&client)?;
+        let transform = self.world.entities().get::<Transform>(session.entity)?;
+        return Some(transform.position + WorldPos::new(0.0, EYE_HEIGHT, 0.0));
+    }
+
+    /// Clients that have been sent a chunk, and so should hear about changes to its blocks. Sorted.
+    pub fn watching(&self, chunk: ChunkPos) -> Vec<ClientId> {
+        let mut clients: Vec<ClientId> = self.sessions.values()
+            .filter(|session| session.streamer.is_sent(chunk))
+            .map(|session| session.player.client)
+            .collect();
+        clients.sort_unstable();
+        return clients;
+    }
+
+    /// Whether any player is standing in a block, so a block can't be placed there.
+    pub fn occupying(&self, block: BlockPos) -> bool {
+        let entities = self.world.entities();
+        let min = block.corner();
+        let max = min + WorldPos::new(1.0, 1.0, 1.0);
+        let half = PLAYER_WIDTH / 2.0;
+        return self.sessions.values()
+            .filter_map(|session| entities.get::<Transform>(session.entity))
+            .any(|transform| {
+                let feet = transform.position;
+                return feet.x + half > min.x && feet.x - half < max.x
+                    && feet.y + PLAYER_HEIGHT > min.y && feet.y < max.y
+                    && feet.z + half > min.z && feet.z - half < max.z;
+            });
+    }
+
+    /// Which entities each player's client knows about, to send them snapshots of.
+    pub fn interest(&self) -> &InterestManager {
+        return &self.interest;
+    }
+
+    pub fn interest_mut(&mut self) -> &mut InterestManager {
+        return &mut self.interest;
+    }
+
+    /// Spawn a player who finished logging in, returning their entity. Fails if their player data can't be read,
+    /// rather than spawning them afresh over it.
+    pub fn join(&mut self, client: ClientId, name: &str, uuid: u128, now: Instant) -> io::Result<EntityId> {
+        if let Some((_, saved)) = self.leave(client) {
+            saved?;
+        }
+        let entities = self.world.entities();
+        let entity = match self.storage.load(entities, uuid)? {
+            Some(entity) => entity,
+            None => entities.spawn()
+        };
+        if !entities.has::<Transform>(entity) {
+            let spawn = self.world.spawn_point().corner();
+            let _ = entities.insert(entity, Transform::new(WorldPos::new(spawn.x + 0.5, spawn.y, spawn.z + 0.5)));
+        }
+        if !entities.has::<Inventory>(entity) {
+            let _ = entities.insert(entity, Inventory::new(INVENTORY_SLOTS));
+        }
+        let player = Player { client, name: name.to_string(), uuid };
+        let _ = entities.insert(entity, player.clone());
+        let _ = entities.insert(entity, Replicated);
+        let _ = entities.insert(entity, Viewer);
+        let _ = entities.insert(entity, Unsaved);
+        let position = entities.get::<Transform>(entity).unwrap().position;
+        let view = position.chunk();
+        entities.set_chunk(entity, view);
+        let movement = MovementValidator::new(position, RigidBody::new(PLAYER_WIDTH as f32, PLAYER_HEIGHT as f32));
+
+        self.interest.add_client(client, view, self.view_distance);
+        let streamer = ChunkStreamer::new(view, self.view_distance);
+        self.sessions.insert(client, PlayerSession { player, entity, view, streamer, movement, last_heard: now });
+        return Ok(entity);
+    }
+
+    /// Save and despawn a player who disconnected, returning who they were and whether they were saved.
+    /// Their entity is despawned even if saving fails.
+    pub fn leave(&mut self, client: ClientId) -> Option<(Player, io::Result<()>)> {
+        let session = self.sessions.remove(&client)?;
+        self.interest.remove_client(client);
+        let saved = self.save(&session);
+        self.world.entities().despawn(session.entity);
+        return Some((session.player, saved));
+    }
+
+    fn save(&self, session: &PlayerSession) -> io::Result<()> {
+        return self.storage.save(self.world.entities(), session.player.uuid, session.entity);
+    }
+
+    /// Save every player's data, such as on autosave, returning how many were saved.
+    pub fn save_all(&self) -> io::Result<usize> {
+        for session in self.sessions.values() {
+            self.save(session)?;
+        }
+        return Ok(self.sessions.len());
+    }
+
+    /// Save every logged in player from their entities alone, for a crash handler, which can't borrow the manager.
+    /// Fails without saving anyone if the players are locked, such as by the panicking thread.
+    pub fn emergency_save(&self) -> impl Fn() -> io::Result<usize> + Send + Sync + 'static {
+        let (world, storage) = (self.world.clone(), self.storage.clone());
+        return move || {
+            let players: Vec<(u128, EntityId)> = match world.entities().storage::<Player>().try_read() {
+                Ok(players) => players.iter().map(|(entity, player)| (player.uuid, entity)).collect(),
+                Err(_) => return Err(io::Error::other("the players are locked"))
+            };
+            for (uuid, entity) in players.iter() {
+                storage.save(world.entities(), *uuid, *entity)?;
+            }
+            return Ok(players.len());
+        };
+    }
+
+    /// Note that a player's client sent something, so it hasn't timed out.
+    pub fn heard_from(&mut self, client: ClientId, now: Instant) {
+        if let Some(session) = self.sessions.get_mut(&client) {
+            session.last_heard = now;
+        }
+    }
+
+    /// Handle a packet about streaming from a player's client, such as a chunk acknowledgement.
+    /// False if it wasn't one.
+    pub fn handle(&mut self, client: ClientId, packet: &[u8]) -> bool {
+        return self.sessions.get_mut(&client).is_some_and(|session| session.streamer.handle(packet));
+    }
+
+    /// Let a player fly, or stop them.
+    pub fn set_may_fly(&mut self, client: ClientId, may_fly: bool) {
+        if let Some(session) = self.sessions.get_mut(&client) {
+            session.movement.set_may_fly(may_fly);
+        }
+    }
+
+    /// Check a move from a player's client in an encoded PlayerPosition, moving their entity if it's allowed.
+    /// None if the packet isn't one. Clients whose moves are refused should be sent their correction().
+    pub fn handle_movement(&mut self, client: ClientId, packet: &[u8]) -> Option<Result<(), MovementViolation>> {
+        let movement = packet::decode::<PlayerPosition>(packet).ok()?;
+        let session = self.sessions.get_mut(&client)?;
+        let result = session.movement.check(&self.world, &movement);
+        if result.is_ok() {
+            let entities = self.world.entities();
+            if let Some(mut transform) = entities.get::<Transform>(session.entity) {
+                transform.position = session.movement.position();
+                let _ = entities.insert(session.entity, transform);
+            }
+        }
+        return Some(result);
+    }
+
+    /// Where a player's client should move them back to.
+    pub fn correction(&self, client: ClientId) -> Option<PositionCorrection> {
+        return self.sessions.get(&client).map(|session| session.movement.correction());
+    }
+
+    /// Players whose clients have sent nothing for longer than the timeout, to disconnect.
+    pub fn timed_out(&self, now: Instant) -> Vec<ClientId> {
+        let mut clients: Vec<ClientId> = self.sessions.values()
+            .filter(|session| now.saturating_duration_since(session.last_heard) > self.timeout)
+            .map(|session| session.player.client)
+            .collect();
+        clients.sort_unstable();
+        return clients;
+    }
+
+    /// Give each player another tick of movement, returning corrections for players whose entity was moved by
+    /// something other than their client, such as a command, to send them right away.
+    pub fn tick_movement(&mut self) -> Vec<(ClientId, Vec<u8>)> {
+        let entities = self.world.entities();
+        let mut corrections = Vec::new();
+        for session in self.sessions.values_mut() {
+            session.movement.tick();
+            let Some(transform) = entities.get::<Transform>(session.entity) else {
+                continue;
+            };
+            if transform.position != session.movement.position() {
+                let correction = session.movement.teleport_to(transform.position);
+                corrections.push((session.player.client, packet::encode(&correction)));
+            }
+        }
+        return corrections;
+    }
+
+    /// Follow each player's entity with their view, returning the chunk packets to send each of them this tick.
+    pub fn tick(&mut self) -> Vec<(ClientId, Vec<u8>)> {
+        let entities = self.world.entities();
+        let mut packets = Vec::new();
+        for session in self.sessions.values_mut() {
+            if let Some(transform) = entities.get::<Transform>(session.entity) {
+                let view = transform.position.chunk();
+                if view != session.view {
+                    session.view = view;
+                    entities.set_chunk(session.entity, view);
+                    session.streamer.set_center(view);
+                    self.interest.set_center(session.player.client, view);
+                }
+            }
+            packets.extend(session.streamer.tick(&self.world).into_iter().map(|packet| (session.player.client, packet)));
+        }
+        return packets;
+    }
+}
\ No newline at end of file
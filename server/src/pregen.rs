@@ -0,0 +1,242 @@
+use std::{collections::VecDeque, fmt, fs, io, path::{Path, PathBuf}, sync::Arc, time::{Duration, Instant}};
+
+use serde::{Deserialize, Serialize};
+use shared::engine::{
+    job::{future::JobFuture, system::JobSystem},
+    math::coords::{BlockPos, ChunkPos, CHUNK_SIZE},
+    progress::ProgressTracker,
+    universe::Dimension,
+    world::loader::{ChunkLoader, ChunkOrigin},
+    worldgen::generator::WorldGenerator
+};
+
+/// File in a dimension's directory recording how far an unfinished pregeneration got.
+pub const PREGEN_FILE: &str = "pregen.json";
+/// Chunks generated above and below the surface of each column, covering the terrain players walk on.
+pub const DEFAULT_PREGEN_DEPTH: i32 = 4;
+
+/// Chunks queued per job thread, so every thread has the next chunk ready when it finishes one.
+const CHUNKS_PER_THREAD: usize = 4;
+/// Chunks finished between checkpoints. Stopping loses at most this many, which are read back rather than
+/// generated again when resuming.
+const CHECKPOINT_INTERVAL: usize = 256;
+
+/// Columns of chunks around a center column, and how far above and below the surface to generate each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PregenArea {
+    pub center_x: i32,
+    pub center_z: i32,
+    /// Radius in chunks of the circle of columns.
+    pub radius: i32,
+    /// Chunks above and below the surface chunk of each column.
+    pub depth: i32
+}
+
+impl PregenArea {
+    /// Columns within a radius of the column holding a block, such as the spawn point.
+    pub fn around(block: BlockPos, radius: u32) -> PregenArea {
+        let chunk = block.chunk();
+        return PregenArea { center_x: chunk.x, center_z: chunk.z, radius: radius as i32, depth: DEFAULT_PREGEN_DEPTH };
+    }
+
+    pub fn with_depth(mut self, depth: u32) -> PregenArea {
+        self.depth = depth as i32;
+        return self;
+    }
+
+    /// Every chunk in the area, nearest columns first so an interrupted run has finished the middle, and bottom
+    /// to top within a column. The order only depends on the area and the generator, so a run can be resumed.
+    /// ```
+    /// # use server::pregen::PregenArea;
+    /// # use shared::engine::{math::coords::{BlockPos, ChunkPos}, worldgen::generator::VoidGenerator};
+    /// let area = PregenArea::around(BlockPos::new(40, 0, 40), 1).with_depth(0);
+    /// let chunks = area.chunks(&VoidGenerator);
+    /// // The center column and its four neighbours, at the height of the center as the void has no surface.
+    /// assert_eq!(chunks.len(), 5);
+    /// assert_eq!(chunks[0], ChunkPos::new(1, 0, 1));
+    /// assert!(chunks.contains(&ChunkPos::new(0, 0, 1)) && !chunks.contains(&ChunkPos::new(0, 0, 0)));
+    /// ```
+    pub fn chunks(&self, generator: &dyn WorldGenerator) -> Vec<ChunkPos> {
+        let limit = self.radius as i64 * self.radius as i64;
+        let mut columns = Vec::new();
+        for z in -self.radius..=self.radius {
+            for x in -self.radius..=self.radius {
+                if (x as i64 * x as i64 + z as i64 * z as i64) <= limit {
+                    columns.push((x, z));
+                }
+            }
+        }
+        columns.sort_by_key(|(x, z)| (x * x + z * z, *z, *x));
+
+        let half = CHUNK_SIZE / 2;
+        let mut chunks = Vec::with_capacity(columns.len() * (2 * self.depth as usize + 1));
+        for (x, z) in columns {
+            let (x, z) = (self.center_x + x, self.center_z + z);
+            let surface = generator.surface_height(x * CHUNK_SIZE + half, z * CHUNK_SIZE + half).unwrap_or(0);
+            let surface = BlockPos::new(0, surface, 0).chunk().y;
+            chunks.extend((surface - self.depth..=surface + self.depth).map(|y| ChunkPos::new(x, y, z)));
+        }
+        return chunks;
+    }
+}
+
+/// How far a run got, saved so it can pick up where it left off.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Checkpoint {
+    area: PregenArea,
+    /// Chunks at the start of the area's order that are saved.
+    done: usize
+}
+
+/// What a pregeneration run did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PregenReport {
+    /// Chunks in the area.
+    pub total: usize,
+    /// Chunks generated and saved by this run.
+    pub generated: usize,
+    /// Chunks already saved, by the game or a previous run.
+    pub existing: usize,
+    /// Chunks skipped because an interrupted run had done them.
+    pub resumed: usize,
+    pub elapsed: Duration
+}
+
+impl PregenReport {
+    /// Whether every chunk in the area is saved, rather than the run being cancelled.
+    pub fn is_finished(&self) -> bool {
+        return self.resumed + self.generated + self.existing == self.total;
+    }
+}
+
+impl fmt::Display for PregenReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "generated {} chunks in {:.1}s, {} were already saved and {} were done by an earlier run",
+            self.generated, self.elapsed.as_secs_f64(), self.existing, self.resumed);
+    }
+}
+
+/// Generates and saves every chunk in an area of a dimension ahead of time, such as a server's spawn region,
+/// so players don't wait on generation there. Chunks are generated and saved on every thread of the job system,
+/// without being added to the world. The run can be cancelled through its progress tracker and resumed later
+/// from a checkpoint in the dimension's directory.
+pub struct Pregenerator {
+    dimension: Arc<Dimension>,
+    jobs: Arc<JobSystem>,
+    area: PregenArea,
+    checkpoint: PathBuf
+}
+
+impl Pregenerator {
+    pub fn new(dimension: Arc<Dimension>, jobs: Arc<JobSystem>, area: PregenArea) -> Pregenerator {
+        let checkpoint = dimension.directory().join(PREGEN_FILE);
+        return Pregenerator { dimension, jobs, area, checkpoint };
+    }
+
+    pub fn area(&self) -> PregenArea {
+        return self.area;
+    }
+
+    /// Chunks an interrupted run of the same area saved. Checkpoints of other areas are ignored.
+    fn resume_point(&self) -> io::Result<usize> {
+        let text = match fs::read_to_string(&self.checkpoint) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(error) => return Err(error)
+        };
+        let checkpoint: Checkpoint = serde_json::from_str(&text).map_err(|error| invalid(&self.checkpoint, error))?;
+        return Ok(if checkpoint.area == self.area { checkpoint.done } else { 0 });
+    }
+
+    /// Flush the chunks saved so far to disk, then record how many there are. Written to a temporary file that
+    /// replaces the old one, so a crash while saving leaves the previous checkpoint.
+    fn save_checkpoint(&self, done: usize) -> io::Result<()> {
+        self.dimension.storage().sync_all()?;
+        let text = serde_json::to_string_pretty(&Checkpoint { area: self.area, done }).map_err(|error| invalid(&self.checkpoint, error))?;
+        let temporary = self.checkpoint.with_extension("tmp");
+        fs::write(&temporary, text)?;
+        return fs::rename(&temporary, &self.checkpoint);
+    }
+
+    /// Load a chunk, saving it if it had to be generated, on the job system.
+    fn queue(&self, loader: &ChunkLoader, pos: ChunkPos) -> JobFuture<io::Result<ChunkOrigin>> {
+        let storage = self.dimension.storage().clone();
+        return loader.request(pos).then(move |loaded| {
+            let loaded = loaded.map_err(|error| io::Error::new(error.error.kind(), error.to_string()))?;
+            if loaded.origin == ChunkOrigin::Generated {
+                storage.write_chunk(&loaded.chunk)?;
+            }
+            return Ok(loaded.origin);
+        });
+    }
+
+    /// Generate and save the area, reporting each chunk to a progress tracker, until it's done or the tracker is
+    /// cancelled. The checkpoint is removed once the whole area is saved.
+    /// ```
+    /// # use server::pregen::{PregenArea, Pregenerator};
+    /// # use shared::engine::{job::system::JobSystem, math::coords::BlockPos, progress::ProgressTracker, universe::Universe, world::tick::TickHandlers, worldgen::generator::VoidGenerator};
+    /// # use std::sync::Arc;
+    /// let directory = std::env::temp_dir().join(format!("pregen_doctest_{}", std::process::id()));
+    /// let universe = Universe::new(&directory);
+    /// let overworld = universe.create_dimension("overworld", Arc::new(VoidGenerator), TickHandlers::new(), 1).unwrap();
+    /// let jobs = Arc::new(JobSystem::new(2));
+    /// let pregen = Pregenerator::new(overworld.clone(), jobs, PregenArea::around(BlockPos::new(0, 0, 0), 2).with_depth(1));
+    ///
+    /// let report = pregen.run(&ProgressTracker::new("pregen", 0)).unwrap();
+    /// assert!(report.is_finished());
+    /// assert_eq!((report.total, report.generated), (39, 39));
+    /// // Everything is on disk, so running again generates nothing.
+    /// assert_eq!(pregen.run(&ProgressTracker::new("pregen", 0)).unwrap().existing, 39);
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn run(&self, progress: &ProgressTracker) -> io::Result<PregenReport> {
+        let start = Instant::now();
+        let chunks = self.area.chunks(self.dimension.generator().as_ref());
+        let resumed = self.resume_point()?.min(chunks.len());
+        progress.set_stage("pregen", chunks.len());
+        progress.advance(resumed);
+
+        let loader = self.dimension.loader(self.jobs.clone(), self.jobs.clone());
+        let window = self.jobs.debug_dump().thread_count * CHUNKS_PER_THREAD;
+        let mut report = PregenReport { total: chunks.len(), generated: 0, existing: 0, resumed, elapsed: Duration::ZERO };
+        let mut queued = VecDeque::with_capacity(window);
+        let mut next = resumed;
+        let mut done = resumed;
+        loop {
+            while queued.len() < window && next < chunks.len() && !progress.is_cancelled() {
+                queued.push_back(self.queue(&loader, chunks[next]));
+                next += 1;
+            }
+            // Finished in order, so the checkpoint only counts chunks with every chunk before them saved.
+            let Some(future) = queued.pop_front() else {
+                break;
+            };
+            match future.wait()? {
+                ChunkOrigin::Generated => report.generated += 1,
+                ChunkOrigin::Loaded => report.existing += 1
+            }
+            done += 1;
+            progress.advance(1);
+            if done % CHECKPOINT_INTERVAL == 0 {
+                self.save_checkpoint(done)?;
+            }
+        }
+
+        report.elapsed = start.elapsed();
+        if report.is_finished() {
+            self.dimension.storage().sync_all()?;
+            if let Err(error) = fs::remove_file(&self.checkpoint) {
+                if error.kind() != io::ErrorKind::NotFound {
+                    return Err(error);
+                }
+            }
+        } else {
+            self.save_checkpoint(done)?;
+        }
+        return Ok(report);
+    }
+}
+
+fn invalid(path: &Path, error: serde_json::Error) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), error));
+}
\ No newline at end of file
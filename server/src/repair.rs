@@ -0,0 +1,169 @@
+use std::{collections::HashSet, fmt, fs, io, path::{Path, PathBuf}, sync::Arc};
+
+use shared::engine::{
+    block::BlockRegistry,
+    math::coords::ChunkPos,
+    save::{backup::{self, BACKUP_DIRECTORY}, migration::Migrations, region::{RegionPos, RegionStorage}},
+    universe::{Universe, REGION_DIRECTORY},
+    world::loader::ChunkStorage,
+    worldgen::blocks::TerrainBlocks
+};
+
+use crate::server::generator;
+
+/// Directory in a dimension's directory that backups are extracted into while their chunks are copied out.
+const REPAIR_DIRECTORY: &str = "repairing";
+
+/// What repairing a world found, and how each damaged chunk was fixed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RepairReport {
+    pub dimensions: usize,
+    pub regions: usize,
+    /// Chunks that read back intact.
+    pub intact: usize,
+    /// Dimension, position, and what was wrong, of each damaged chunk.
+    pub damaged: Vec<(String, ChunkPos, String)>,
+    /// Region files that couldn't be read at all, where they were moved to, and why.
+    pub set_aside: Vec<(PathBuf, String)>,
+    /// Chunks copied back from backups, including those of regions that were set aside.
+    pub restored: usize,
+    /// Damaged chunks no backup had intact, generated again from their dimension's seed.
+    pub regenerated: usize,
+    /// Damaged chunks of dimensions whose generator isn't known, removed so they're generated when next loaded.
+    pub removed: usize
+}
+
+impl RepairReport {
+    /// Check if nothing was damaged, so nothing was changed.
+    pub fn is_clean(&self) -> bool {
+        return self.damaged.is_empty() && self.set_aside.is_empty();
+    }
+}
+
+impl fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (dimension, chunk, reason) in self.damaged.iter() {
+            writeln!(f, "{} chunk {} {} {} was damaged: {}", dimension, chunk.x, chunk.y, chunk.z, reason)?;
+        }
+        for (path, reason) in self.set_aside.iter() {
+            writeln!(f, "{} couldn't be read and was moved aside: {}", path.display(), reason)?;
+        }
+        return write!(
+            f, "checked {} chunks in {} regions of {} dimensions: {} damaged, {} restored from backups, {} regenerated, {} left to generate",
+            self.intact + self.damaged.len(), self.regions, self.dimensions, self.damaged.len(), self.restored, self.regenerated, self.removed
+        );
+    }
+}
+
+/// Read back every chunk saved in a world, fixing the damaged ones without starting a server, so the world must not
+/// be open in one. Each damaged chunk is copied back from the newest backup of its dimension that has it intact, or
+/// generated again if none do, losing what was built in it since. Region files too damaged to read are moved aside,
+/// and their chunks copied back from the newest backup that has the region.
+/// ```
+/// # use server::repair;
+/// # use shared::engine::{math::coords::ChunkPos, save::region::{RegionPos, SECTOR_SIZE}, universe::{Universe, OVERWORLD, REGION_DIRECTORY}};
+/// # use shared::engine::world::{chunk::Chunk, tick::TickHandlers};
+/// # use shared::engine::worldgen::generator::VoidGenerator;
+/// # use std::{io::{Seek, SeekFrom, Write}, sync::Arc};
+/// let directory = std::env::temp_dir().join(format!("cube_repair_doc_{}", std::process::id()));
+/// let universe = Universe::new(&directory);
+/// let overworld = universe.create_dimension(OVERWORLD, Arc::new(VoidGenerator), TickHandlers::new(), 1).unwrap();
+/// overworld.storage().write_chunk(&Chunk::new(ChunkPos::ORIGIN)).unwrap();
+/// overworld.storage().write_chunk(&Chunk::new(ChunkPos::new(1, 0, 0))).unwrap();
+/// universe.sync_all().unwrap();
+/// drop((overworld, universe));
+///
+/// // Damage the first chunk written, which is stored right after the header.
+/// let region = directory.join("dimensions").join(OVERWORLD).join(REGION_DIRECTORY).join(RegionPos::of_chunk(ChunkPos::ORIGIN).file_name());
+/// let mut file = std::fs::OpenOptions::new().write(true).open(region).unwrap();
+/// file.seek(SeekFrom::Start(3 * SECTOR_SIZE + 4)).unwrap();
+/// file.write_all(&[0xFF; 4]).unwrap();
+/// drop(file);
+///
+/// let report = repair::repair(&directory).unwrap();
+/// assert_eq!((report.intact, report.damaged.len(), report.regenerated), (1, 1, 1));
+/// assert!(repair::repair(&directory).unwrap().is_clean());
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub fn repair(directory: &Path) -> io::Result<RepairReport> {
+    let universe = Universe::new(directory);
+    let terrain = TerrainBlocks::register(&mut BlockRegistry::new()).expect("the terrain blocks have valid names");
+    let mut report = RepairReport::default();
+    for dimension in universe.saved_dimension_directories()? {
+        let name = dimension.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        report.dimensions += 1;
+        let storage = RegionStorage::new(&dimension.join(REGION_DIRECTORY))?.with_migrations(universe.migrations().clone());
+        let check = storage.check()?;
+        report.regions += check.regions;
+        report.intact += check.chunks;
+        let mut lost: Vec<ChunkPos> = check.damaged.iter().map(|(chunk, _)| *chunk).collect();
+        report.damaged.extend(check.damaged.into_iter().map(|(chunk, reason)| (name.clone(), chunk, reason)));
+        let mut lost_regions = Vec::new();
+        for (region, reason) in check.unreadable {
+            report.set_aside.push((storage.set_aside(region)?, reason));
+            lost_regions.push(region);
+        }
+        if lost.is_empty() && lost_regions.is_empty() {
+            continue;
+        }
+
+        report.restored += restore_from_backups(&dimension, &storage, universe.migrations(), &mut lost, lost_regions)?;
+        // A damaged level file shouldn't stop its chunks being repaired, they're just left to generate on load.
+        let level = universe.load_level(&name).ok().flatten();
+        match level.and_then(|level| generator(&level.generator, level.seed, terrain)) {
+            Some(generator) => {
+                for chunk in lost {
+                    storage.write_chunk(&generator.generate(chunk))?;
+                    report.regenerated += 1;
+                }
+            }
+            None => {
+                for chunk in lost {
+                    storage.remove_data(chunk)?;
+                    report.removed += 1;
+                }
+            }
+        }
+        storage.sync_all()?;
+    }
+    return Ok(report);
+}
+
+/// Copy chunks back from a dimension's backups, newest first, taking those restored out of lost. A region that
+/// couldn't be read gets every chunk the newest backup with that region has. Returns how many chunks were copied.
+fn restore_from_backups(
+    dimension: &Path, storage: &RegionStorage, migrations: &Arc<Migrations>, lost: &mut Vec<ChunkPos>, mut lost_regions: Vec<RegionPos>
+) -> io::Result<usize> {
+    let staging = dimension.join(REPAIR_DIRECTORY);
+    let mut restored = 0;
+    for archive in backup::list_backups(&dimension.join(BACKUP_DIRECTORY))? {
+        if lost.is_empty() && lost_regions.is_empty() {
+            break;
+        }
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        if let Err(error) = backup::extract_backup(&archive, &staging) {
+            eprintln!("couldn't read backup {}: {}", archive.display(), error);
+            continue;
+        }
+        let backup = RegionStorage::new(&staging.join(REGION_DIRECTORY))?.with_migrations(migrations.clone());
+        let saved = backup.saved_chunks()?;
+        let regions: HashSet<RegionPos> = saved.iter().map(|chunk| RegionPos::of_chunk(*chunk)).collect();
+        let mut wanted = lost.clone();
+        wanted.extend(saved.into_iter().filter(|chunk| lost_regions.contains(&RegionPos::of_chunk(*chunk)) && !lost.contains(chunk)));
+        for chunk in wanted {
+            // A chunk damaged in this backup too may be intact in an older one.
+            if let Ok(Some(saved)) = backup.read(chunk) {
+                storage.write_chunk(&saved)?;
+                lost.retain(|pos| *pos != chunk);
+                restored += 1;
+            }
+        }
+        // Older backups of a region would overwrite the newer chunks just copied.
+        lost_regions.retain(|region| !regions.contains(region));
+        drop(backup);
+        fs::remove_dir_all(&staging)?;
+    }
+    return Ok(restored);
+}
\ No newline at end of file
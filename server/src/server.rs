@@ -0,0 +1,1502 @@
+use std::{
+    collections::HashMap,
+    fmt, io,
+    net::{SocketAddr, ToSocketAddrs},
+    path::Path,
+    sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH}
+};
+
+use shared::engine::{
+    asset::pack::{ContentPacks, PackError, PACKS_DIRECTORY},
+    block::{sign::{self, SignBlockEntity, SIGN_BLOCK}, BlockId, BlockRegistry, AIR},
+    command::{argument::{ArgumentKind, CommandEnvironment}, dispatcher::{CommandSource, PermissionLevel}, CommandDispatcher},
+    config::{EngineConfig, SpawningConfig},
+    entity::{kinematics::{self, Transform, TICKS_PER_SECOND}, replication::ClientId, schedule::SystemSchedule, serialize::ComponentTypes},
+    event::events::{BlockBroken, BlockChanged, BlockPlaced, PlayerJoined, PlayerLeft},
+    item::{inventory::{self, Inventory}, ItemRegistry, ItemStack},
+    job::{system::JobSystem, topology::ThreadProfile},
+    math::{coords::{BlockPos, ChunkPos, WorldPos}, rng::WorldRng},
+    memory::{MemoryCategory, MemoryTracker},
+    metrics::{http::{MetricsEndpoint, ServerStatus}, registry::global_registry},
+    net::{
+        admin::{AdminCommand, CommandConsole},
+        auth::{offline_uuid, Authenticator, OfflineAuthenticator},
+        chat::{ChatKind, ChatRouter},
+        checksum::{self, StateHasher, TickChecksum},
+        crafting::{CraftItem, CraftResult},
+        encryption::{ServerIdentity, IDENTITY_FILE},
+        handshake::{Disconnect, DisconnectReason, HandshakeState, ServerHandshake},
+        interaction::{self, BlockUpdate, DigBlock, InteractionError, PlaceBlock, REACH_DISTANCE, REACH_TOLERANCE},
+        movement::MovementViolation,
+        packet,
+        sign::{self as sign_net, EditSign, OpenSignEditor, SignEditError},
+        transport::{Channel, ConnectionId, Priority, Transport}
+    },
+    path::Pathfinder,
+    physics::body,
+    progress::ProgressTracker,
+    protection::{ProtectedAction, ProtectionRegion, ProtectionRegions},
+    recipe::{self, RecipeRegistry},
+    save::{autosave::Autosave, backup::Backup, manager::SaveManager, players::{PlayerStorage, PLAYERS_DIRECTORY}},
+    spawning::{despawn_far, Spawner},
+    universe::{Dimension, Universe, UniverseError, OVERWORLD},
+    version::VersionManifest,
+    world::{
+        block_entity::BlockEntityTypes,
+        edit::{EditHistory, WorldEdit},
+        schematic::{Schematic, SCHEMATICS_DIRECTORY, SCHEMATIC_EXTENSION},
+        tick::TickHandlers,
+        World
+    },
+    worldgen::{blocks::TerrainBlocks, generator::{VoidGenerator, WorldGenerator}, terrain::NoiseGenerator}
+};
+
+use crate::{
+    access::{format_duration, parse_duration, unix_time, AccessAuthenticator, AccessControl, AccessEntry},
+    chunks::ChunkManager,
+    mobs::{self, spawn_mob, Mob, SPAWN_INTERVAL},
+    player::{self, PlayerManager, HOTBAR_SLOTS},
+    pregen::{PregenArea, PregenReport, Pregenerator},
+    tps::{TickMonitor, RECENT_WINDOW}
+};
+
+/// Generator of new worlds.
+pub const DEFAULT_GENERATOR: &str = "noise";
+/// Most chunks one forceload command may force, so a typo can't load a continent.
+pub const MAX_FORCED_CHUNKS: i64 = 4096;
+/// Ticks between updates of the status served beside metrics.
+pub const STATUS_INTERVAL: u64 = TICKS_PER_SECOND as u64;
+/// Most blocks one fill or copy command may cover, so a typo can't stall the server.
+pub const MAX_FILL_BLOCKS: i64 = 32768;
+
+/// Reason the server couldn't start or shut down cleanly.
+#[derive(Debug)]
+pub enum ServerError {
+    Io(io::Error),
+    Universe(UniverseError),
+    /// The world's content packs couldn't be loaded.
+    Pack(PackError),
+    /// The world was made by a generator this server doesn't have.
+    UnknownGenerator(String)
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ServerError::Io(error) => write!(f, "server io error: {}", error),
+            ServerError::Universe(error) => write!(f, "couldn't open the world: {}", error),
+            ServerError::Pack(error) => write!(f, "couldn't load content packs: {}", error),
+            ServerError::UnknownGenerator(name) => write!(f, "the world was made by the {} generator, which this server doesn't have", name)
+        };
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+impl From<io::Error> for ServerError {
+    fn from(error: io::Error) -> ServerError {
+        return ServerError::Io(error);
+    }
+}
+
+impl From<UniverseError> for ServerError {
+    fn from(error: UniverseError) -> ServerError {
+        return ServerError::Universe(error);
+    }
+}
+
+impl From<PackError> for ServerError {
+    fn from(error: PackError) -> ServerError {
+        return ServerError::Pack(error);
+    }
+}
+
+/// The generator a world was saved with, by name.
+/// ```
+/// # use server::server::generator;
+/// # use shared::engine::{block::BlockRegistry, worldgen::blocks::TerrainBlocks};
+/// let blocks = TerrainBlocks::register(&mut BlockRegistry::new()).unwrap();
+/// assert_eq!(generator("noise", 1, blocks).unwrap().name(), "noise");
+/// assert!(generator("amplified", 1, blocks).is_none());
+/// ```
+pub fn generator(name: &str, seed: u64, blocks: TerrainBlocks) -> Option<Arc<dyn WorldGenerator>> {
+    return match name {
+        "noise" => Some(Arc::new(NoiseGenerator::new(seed, blocks))),
+        "void" => Some(Arc::new(VoidGenerator)),
+        _ => None
+    };
+}
+
+/// Seed for a new world, from the clock.
+fn new_seed() -> u64 {
+    return SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+}
+
+/// A connection still logging in. Connections that finished logging in are players in the PlayerManager.
+struct Client {
+    handshake: ServerHandshake
+}
+
+/// The dedicated server. Plays the overworld of a save, letting players log in over the network to chat and run
+/// commands, and runs console commands typed into its terminal or sent by admin clients. Driven by tick(),
+/// called at the tick rate by a GameLoop until stopped.
+/// ```
+/// # use server::server::Server;
+/// # use shared::engine::{config::EngineConfig, net::{admin::CommandConsole, encryption::{ServerIdentity, IDENTITY_FILE}, handshake::{ClientHandshake, HandshakeState}, transport::{Channel, Connection}}, version::VersionManifest};
+/// # use std::time::{Duration, Instant};
+/// let directory = std::env::temp_dir().join(format!("server_doctest_{}", std::process::id()));
+/// let mut config = EngineConfig::new();
+/// config.server.view_distance = 2;
+/// let mut server = Server::bind("127.0.0.1:0", &config, &directory).unwrap();
+///
+/// // Connections are encrypted by default, with a key the server keeps in its save.
+/// let key = server.identity().unwrap().public_key();
+/// let client = Connection::connect_with(server.local_addr(), &config.net, Some(key)).unwrap();
+/// let mut handshake = ClientHandshake::new(VersionManifest::current(vec![]), "steve", None);
+/// client.send(Channel::Reliable, handshake.start());
+/// let start = Instant::now();
+/// while handshake.state() != &HandshakeState::Play {
+///     assert!(start.elapsed() < Duration::from_secs(10), "never logged in");
+///     server.tick();
+///     client.pump().unwrap();
+///     for packet in client.receive() {
+///         for reply in handshake.handle(&packet) {
+///             client.send(Channel::Reliable, reply);
+///         }
+///     }
+/// }
+/// assert_eq!(server.player_names(), vec!["steve"]);
+/// assert_eq!(client.server_key(), Some(key));
+/// assert_eq!(server.execute_line("list"), Ok("1 player online: steve".to_string()));
+/// assert_eq!(server.status().player_names, vec!["steve"]);
+/// assert!(server.execute_line("fly").is_err());
+/// assert!(server.execute_line("tps").unwrap().starts_with("TPS: "));
+/// assert_eq!(server.execute_line("give steve cube:stone 100"), Ok("gave steve 100 cube:stone".to_string()));
+/// assert_eq!(server.execute_line("forceload add 0 0 0"), Ok("forced 1 chunks to stay loaded".to_string()));
+/// assert!(server.execute_line("chunks").unwrap().ends_with("1 forced"));
+/// assert_eq!(server.execute_line("op steve 1"), Ok("made steve moderator".to_string()));
+/// assert_eq!(server.execute_line("tempban steve 2h griefing"), Ok("banned steve for 2h".to_string()));
+/// server.tick();
+/// assert!(server.player_names().is_empty());
+/// assert!(server.execute_line("banlist").unwrap().starts_with("1 banned\nsteve: griefing"));
+///
+/// assert_eq!(server.execute_line("stop"), Ok("stopping".to_string()));
+/// assert!(!server.is_running());
+/// server.shutdown().unwrap();
+/// assert!(directory.join("world.dat").exists());
+/// assert_eq!(ServerIdentity::load_or_generate(&directory.join(IDENTITY_FILE)).unwrap().public_key(), key);
+/// assert_eq!(std::fs::read_dir(directory.join("players")).unwrap().count(), 1);
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct Server {
+    jobs: Arc<JobSystem>,
+    universe: Arc<Universe>,
+    overworld: Arc<Dimension>,
+    blocks: BlockRegistry,
+    sign_block: BlockId,
+    items: Arc<ItemRegistry>,
+    recipes: Arc<RecipeRegistry>,
+    saves: SaveManager,
+    chunks: ChunkManager,
+    /// Entity systems run on the overworld every tick, such as mob AI and physics.
+    systems: SystemSchedule,
+    spawner: Spawner<Mob>,
+    spawning: SpawningConfig,
+    autosave: Autosave,
+    /// Blocks changed since the last tick, from the overworld's events, to be saved and sent to players.
+    changed: Arc<Mutex<Vec<BlockChanged>>>,
+    /// Regions of the overworld players need to be members of to change. Operators bypass them.
+    protection: ProtectionRegions,
+    /// World edits each command source can undo, by source name.
+    edits: HashMap<String, EditHistory>,
+    /// The last region each command source copied or loaded, by source name.
+    clipboards: HashMap<String, Schematic>,
+    /// Loaders for the block entities in pasted schematics.
+    block_entity_types: BlockEntityTypes,
+    /// Sign each player placed and may still write on, by client.
+    sign_editors: HashMap<ClientId, BlockPos>,
+    backups: Vec<Backup>,
+    transport: Transport,
+    manifest: VersionManifest,
+    clients: HashMap<ConnectionId, Client>,
+    players: PlayerManager,
+    access: Arc<RwLock<AccessControl>>,
+    /// Checks who players logging in are, and that the access lists let them in.
+    authenticator: Arc<dyn Authenticator>,
+    /// Connections to close once the packet saying why has been sent.
+    closing: Vec<ConnectionId>,
+    chat: ChatRouter,
+    commands: Arc<CommandDispatcher<Server>>,
+    metrics: Option<MetricsEndpoint>,
+    started: Instant,
+    /// Memory of loaded chunks and entities, and their budgets.
+    memory: Arc<MemoryTracker>,
+    ticks: TickMonitor,
+    /// See checksum().
+    checksum: u64,
+    running: Arc<AtomicBool>
+}
+
+impl Server {
+    /// Open the save in a directory, creating it if there isn't one, and listen for players on an address.
+    /// Metrics are served if the config gives them a port.
+    pub fn bind(address: impl ToSocketAddrs, config: &EngineConfig, directory: &Path) -> Result<Server, ServerError> {
+        let jobs = Arc::new(JobSystem::new(config.jobs.thread_count(ThreadProfile::Server)));
+        let io = Arc::new(JobSystem::new(config.jobs.io_threads));
+        let mut blocks = BlockRegistry::new();
+        let terrain = TerrainBlocks::register(&mut blocks).expect("the terrain blocks have valid names");
+        let sign_block = blocks.register(SIGN_BLOCK).expect("the sign block has a valid name");
+        blocks.freeze();
+        let mut items = ItemRegistry::new();
+        items.register_blocks(&blocks).expect("every block has a valid item name");
+        items.freeze();
+        let items = Arc::new(items);
+        let mut recipes = RecipeRegistry::new();
+        let packs = ContentPacks::load(&directory.join(PACKS_DIRECTORY))?;
+        packs.register_recipes(&items, &mut recipes)?;
+        let manifest = VersionManifest::current(packs.mods());
+        let recipes = Arc::new(recipes);
+
+        let mut types = ComponentTypes::new();
+        kinematics::register_components(&mut types);
+        inventory::register_components(&mut types, items.clone());
+        body::register_components(&mut types);
+        mobs::register_components(&mut types);
+        let mut block_entity_types = BlockEntityTypes::new();
+        inventory::register_block_entities(&mut block_entity_types, items.clone());
+        sign::register_block_entities(&mut block_entity_types);
+        let universe = Arc::new(Universe::new(directory).with_component_types(Arc::new(types)));
+        universe.check_version(&manifest)?;
+        if let Some(remap) = universe.remap_blocks(&blocks)? {
+            if remap.missing().is_empty() {
+                println!("remapped saved chunks to the current blocks");
+            } else {
+                eprintln!("{}", remap);
+            }
+        }
+        universe.record_version(&manifest)?;
+        let (seed, generator_name) = match universe.load_level(OVERWORLD)? {
+            Some(level) => (level.seed, level.generator),
+            None => (new_seed(), DEFAULT_GENERATOR.to_string())
+        };
+        let generator = generator(&generator_name, seed, terrain).ok_or(ServerError::UnknownGenerator(generator_name))?;
+        let overworld = universe.create_dimension(OVERWORLD, generator, TickHandlers::new(), seed)?;
+        let memory = Arc::new(MemoryTracker::from_config(&config.memory));
+        // Mobs walk on anything but air and water.
+        let pathfinder = Pathfinder::new(Arc::new(move |id| id != AIR && id != terrain.water));
+        let systems = mobs::mob_systems(jobs.clone(), pathfinder, overworld.world().level().seed, player::view_lod(config.server.view_distance));
+        let spawner = mobs::mob_spawner(&config.spawning, terrain);
+        let chunks = ChunkManager::new(overworld.world().clone(), overworld.loader(io.clone(), jobs.clone()))
+            .with_entities(overworld.entity_storage().clone())
+            .with_view_distance(config.server.view_distance)
+            .with_memory(memory.clone());
+        let saves = SaveManager::new(io, jobs.clone(), overworld.storage().clone()).with_entities(overworld.entity_storage().clone());
+        let changed = Arc::new(Mutex::new(Vec::new()));
+        if let Some(events) = overworld.world().events() {
+            let captured = changed.clone();
+            events.subscribe(move |change: &BlockChanged| captured.lock().unwrap().push(*change));
+        }
+
+        let storage = PlayerStorage::new(&universe.directory().join(PLAYERS_DIRECTORY), universe.component_types().clone())?
+            .with_migrations(universe.migrations().clone());
+        let players = PlayerManager::new(overworld.world().clone(), storage)
+            .with_view_distance(config.server.view_distance)
+            .with_timeout(config.server.player_timeout());
+
+        let access = Arc::new(RwLock::new(AccessControl::load(directory)?.with_whitelist(config.server.whitelist)));
+        let authenticator = Arc::new(AccessAuthenticator::new(Arc::new(OfflineAuthenticator), access.clone()));
+
+        let mut transport = Transport::bind(address, true)?;
+        if config.net.encryption {
+            transport = transport.with_encryption(ServerIdentity::load_or_generate(&directory.join(IDENTITY_FILE))?);
+        }
+        let metrics = match config.server.metrics_port {
+            Some(port) => Some(MetricsEndpoint::bind(("0.0.0.0", port), global_registry().clone())?),
+            None => None
+        };
+        return Ok(Server {
+            jobs,
+            universe,
+            overworld,
+            blocks,
+            sign_block,
+            items,
+            recipes,
+            saves,
+            chunks,
+            systems,
+            spawner,
+            spawning: config.spawning.clone(),
+            memory,
+            autosave: Autosave::from_config(&config.save),
+            changed,
+            protection: ProtectionRegions::load(directory)?,
+            edits: HashMap::new(),
+            clipboards: HashMap::new(),
+            block_entity_types,
+            sign_editors: HashMap::new(),
+            backups: Vec::new(),
+            transport,
+            manifest,
+            clients: HashMap::new(),
+            players,
+            access,
+            authenticator,
+            closing: Vec::new(),
+            chat: ChatRouter::new(),
+            commands: Arc::new(Server::commands()),
+            metrics,
+            started: Instant::now(),
+            ticks: TickMonitor::new(TICKS_PER_SECOND, global_registry()),
+            checksum: StateHasher::new().finish(),
+            running: Arc::new(AtomicBool::new(true))
+        });
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        return self.transport.local_addr();
+    }
+
+    /// Identity players' connections are encrypted with, kept in the save's directory. None if the config turned
+    /// encryption off.
+    pub fn identity(&self) -> Option<&ServerIdentity> {
+        return self.transport.identity();
+    }
+
+    pub fn jobs(&self) -> &Arc<JobSystem> {
+        return &self.jobs;
+    }
+
+    pub fn universe(&self) -> &Arc<Universe> {
+        return &self.universe;
+    }
+
+    pub fn world(&self) -> &Arc<World> {
+        return self.overworld.world();
+    }
+
+    pub fn memory(&self) -> &Arc<MemoryTracker> {
+        return &self.memory;
+    }
+
+    pub fn items(&self) -> &Arc<ItemRegistry> {
+        return &self.items;
+    }
+
+    pub fn recipes(&self) -> &Arc<RecipeRegistry> {
+        return &self.recipes;
+    }
+
+    /// Checksum of the blocks changed and every entity's transform at the end of the last tick. Two servers
+    /// running the same ticks from the same save should agree, however their jobs were scheduled.
+    pub fn checksum(&self) -> u64 {
+        return self.checksum;
+    }
+
+    /// Times of the ticks run so far.
+    pub fn tick_monitor(&self) -> &TickMonitor {
+        return &self.ticks;
+    }
+
+    /// Cleared by the stop command. The tick loop runs while it's set.
+    pub fn running(&self) -> Arc<AtomicBool> {
+        return self.running.clone();
+    }
+
+    pub fn is_running(&self) -> bool {
+        return self.running.load(Ordering::Acquire);
+    }
+
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Release);
+    }
+
+    /// The whitelist, bans and operators, kept in the save's directory.
+    pub fn access(&self) -> &Arc<RwLock<AccessControl>> {
+        return &self.access;
+    }
+
+    pub fn protection(&self) -> &ProtectionRegions {
+        return &self.protection;
+    }
+
+    /// The regions and teams, to edit. save_protection() keeps the edits.
+    pub fn protection_mut(&mut self) -> &mut ProtectionRegions {
+        return &mut self.protection;
+    }
+
+    /// Write the protection regions and teams into the save's directory.
+    pub fn save_protection(&self) -> io::Result<()> {
+        return self.protection.save(self.universe.directory());
+    }
+
+    /// Everyone logged in.
+    pub fn players(&self) -> &PlayerManager {
+        return &self.players;
+    }
+
+    /// Names of the players logged in, sorted.
+    pub fn player_names(&self) -> Vec<String> {
+        return self.players.names();
+    }
+
+    /// What the status endpoint serves: who's online, how fast the server is ticking, and how much is loaded.
+    pub fn status(&self) -> ServerStatus {
+        let names = self.player_names();
+        return ServerStatus {
+            version: self.manifest.engine.to_string(),
+            players: names.len(),
+            player_names: names,
+            tps: self.ticks.tps(RECENT_WINDOW, Instant::now()),
+            mspt: self.ticks.mean().as_secs_f64() * 1000.0,
+            loaded_chunks: self.world().chunk_count(),
+            uptime_seconds: self.started.elapsed().as_secs()
+        };
+    }
+
+    fn player_name(&self, id: ClientId) -> Option<String> {
+        return self.players.session(id).map(|session| session.player().name.clone());
+    }
+
+    /// Run one tick: take in what players sent, run the world, then save and back up what's due.
+    pub fn tick(&mut self) {
+        let start = Instant::now();
+        for id in std::mem::take(&mut self.closing) {
+            self.transport.disconnect(id);
+            self.leave(id);
+        }
+        for (id, error) in self.transport.pump(&self.jobs) {
+            if let Some(name) = self.player_name(id) {
+                println!("{} lost connection: {}", name, error);
+            }
+            self.leave(id);
+        }
+        for (peer, error) in self.transport.take_rejected() {
+            eprintln!("dropped connection from {}: {}", peer, error);
+        }
+        for id in self.transport.connection_ids() {
+            self.receive(id);
+        }
+        for id in self.players.timed_out(start) {
+            if let Some(name) = self.player_name(id) {
+                println!("{} timed out", name);
+            }
+            self.disconnect(id, DisconnectReason::Kicked("Timed out".to_string()));
+        }
+        for (client, line) in self.chat.take_commands() {
+            self.run_player_command(client, &line);
+        }
+
+        self.universe.tick_all(&self.jobs);
+        if !self.overworld.is_paused() {
+            self.spawn_mobs();
+            self.systems.run(&self.jobs, self.overworld.world());
+        }
+        for (id, packet) in self.players.tick_movement() {
+            if let Some(connection) = self.transport.connection(id) {
+                connection.send_with_priority(Channel::Reliable, Priority::High, packet);
+            }
+        }
+        for (id, packet) in self.players.tick() {
+            if let Some(connection) = self.transport.connection(id) {
+                connection.send_with_priority(Channel::Reliable, Priority::Low, packet);
+            }
+        }
+        let changes = self.take_changes();
+        let mut sent: HashMap<ClientId, Vec<BlockPos>> = HashMap::new();
+        for change in changes.iter() {
+            // Sent at the same priority as chunks, so an update can't overtake the chunk it changes.
+            let update = packet::encode(&BlockUpdate { block: change.pos, id: change.new });
+            for id in self.players.watching(change.pos.chunk()) {
+                if let Some(connection) = self.transport.connection(id) {
+                    connection.send_with_priority(Channel::Reliable, Priority::Low, update.clone());
+                    sent.entry(id).or_default().push(change.pos);
+                }
+            }
+        }
+        self.send_checksums(&changes, sent);
+        for session in self.players.sessions() {
+            self.chunks.set_player_view(session.player().client, session.view());
+        }
+        for error in self.chunks.tick(&mut self.saves) {
+            eprintln!("{}", error);
+        }
+        if let Some(report) = self.autosave.update(&mut self.saves, self.overworld.world(), Instant::now()) {
+            println!("saved {} chunks in {:.1}s", report.chunks, report.elapsed.as_secs_f64());
+            for error in report.errors {
+                eprintln!("{}", error);
+            }
+            if let Err(error) = self.players.save_all() {
+                eprintln!("couldn't save players: {}", error);
+            }
+        }
+        let (finished, running): (Vec<_>, Vec<_>) = std::mem::take(&mut self.backups).into_iter().partition(|backup| backup.is_done());
+        self.backups = running;
+        for backup in finished {
+            match backup.wait() {
+                Ok(path) => println!("backed up to {}", path.display()),
+                Err(error) => eprintln!("backup failed: {}", error)
+            }
+        }
+        if let Some(metrics) = self.metrics.as_ref() {
+            if self.overworld.tick_count().is_multiple_of(STATUS_INTERVAL) {
+                metrics.set_status(&self.status());
+            }
+            if let Err(error) = metrics.pump() {
+                eprintln!("metrics endpoint failed: {}", error);
+            }
+        }
+        self.ticks.record(start, start.elapsed());
+    }
+
+    /// Every SPAWN_INTERVAL ticks, despawn mobs far from every player and spawn new ones around them, marking
+    /// the chunks they left or entered for saving. Positions come from the world seed and tick, so a server
+    /// replaying the same ticks spawns the same mobs.
+    fn spawn_mobs(&mut self) {
+        let tick = self.overworld.tick_count();
+        if !self.spawning.enabled || !tick.is_multiple_of(SPAWN_INTERVAL) {
+            return;
+        }
+        let world = self.overworld.world().clone();
+        let players: Vec<WorldPos> = self.players.sessions()
+            .filter_map(|session| world.entities().get::<Transform>(session.entity()))
+            .map(|transform| transform.position)
+            .collect();
+        for (_, position) in despawn_far::<Mob>(&world, &players, self.spawning.despawn_distance as f64) {
+            self.saves.mark_chunk(position.chunk());
+        }
+        let mut rng = WorldRng::new(world.level().seed.wrapping_add(tick));
+        for position in self.spawner.positions(&world, &players, &mut rng) {
+            spawn_mob(&world, position);
+            self.saves.mark_chunk(position.chunk());
+        }
+    }
+
+    /// Mark the chunks of blocks changed since the last call for saving, returning the changes.
+    fn take_changes(&mut self) -> Vec<BlockChanged> {
+        let changes = std::mem::take(&mut *self.changed.lock().unwrap());
+        let world = self.overworld.world();
+        for change in changes.iter() {
+            self.saves.mark_chunk(change.pos.chunk());
+            // A sign that was broken or replaced takes its text with it.
+            if change.old == self.sign_block && world.get_block(change.pos) != Some(self.sign_block) {
+                world.remove_block_entity(change.pos);
+            }
+        }
+        return changes;
+    }
+
+    /// Update the tick's checksum, and send each player one of the blocks they were sent updates for, after the
+    /// updates, so their client can check it ended up with the same blocks.
+    fn send_checksums(&mut self, changes: &[BlockChanged], sent: HashMap<ClientId, Vec<BlockPos>>) {
+        let world = self.overworld.world();
+        let positions: Vec<BlockPos> = changes.iter().map(|change| change.pos).collect();
+        let mut hasher = StateHasher::new();
+        hasher.write_u64(checksum::block_checksum(world, &positions));
+        hasher.write_u64(checksum::entity_checksum(world.entities()));
+        self.checksum = hasher.finish();
+
+        let tick = self.overworld.tick_count();
+        for (id, blocks) in sent {
+            if let Some(connection) = self.transport.connection(id) {
+                let packet = packet::encode(&TickChecksum { tick, blocks: checksum::block_checksum(world, &blocks) });
+                connection.send_with_priority(Channel::Reliable, Priority::Low, packet);
+            }
+        }
+    }
+
+    /// Generate and save the overworld's chunks within a radius of its spawn point ahead of time, on every job
+    /// thread. Picks up where an interrupted run of the same radius left off.
+    pub fn pregenerate(&self, radius: u32, progress: &ProgressTracker) -> io::Result<PregenReport> {
+        let area = PregenArea::around(self.world().spawn_point(), radius);
+        return Pregenerator::new(self.overworld.clone(), self.jobs.clone(), area).run(progress);
+    }
+
+    /// What a crash handler should run to save as much as it can: the chunks changed since the last save, the
+    /// entities of loaded chunks and every online player, then the level and region files.
+    pub fn emergency_save(&self) -> impl Fn() -> io::Result<()> + Send + Sync + 'static {
+        let flush = self.saves.emergency_flush(self.overworld.world().clone());
+        let players = self.players.emergency_save();
+        let (changed, universe) = (self.changed.clone(), self.universe.clone());
+        return move || {
+            if let Ok(changes) = changed.try_lock() {
+                for change in changes.iter() {
+                    flush.mark_chunk(change.pos.chunk());
+                }
+            }
+            let chunks = flush.flush();
+            let players = players();
+            universe.sync_all()?;
+            chunks?;
+            players?;
+            return Ok(());
+        };
+    }
+
+    /// Tell every player the server is closing, wait for backups, then save everything.
+    pub fn shutdown(&mut self) -> Result<(), ServerError> {
+        self.stop();
+        let ids: Vec<ConnectionId> = self.transport.connection_ids();
+        for id in ids {
+            self.disconnect(id, DisconnectReason::Quit);
+        }
+        self.transport.pump(&self.jobs);
+        for id in std::mem::take(&mut self.closing) {
+            self.transport.disconnect(id);
+            self.leave(id);
+        }
+        for backup in self.backups.drain(..) {
+            if let Err(error) = backup.wait() {
+                eprintln!("backup failed: {}", error);
+            }
+        }
+        self.take_changes();
+        self.saves.flush_all(self.overworld.world())?;
+        self.save_protection()?;
+        self.universe.sync_all()?;
+        return Ok(());
+    }
+
+    /// Handle what a connection sent: its handshake until it logs in, then play packets.
+    fn receive(&mut self, id: ConnectionId) {
+        let Some(connection) = self.transport.connection(id) else {
+            return;
+        };
+        let now = Instant::now();
+        for data in connection.receive() {
+            if self.players.session(id).is_none() {
+                if self.closing.contains(&id) {
+                    return;
+                }
+                let (manifest, authenticator) = (&self.manifest, &self.authenticator);
+                let client = self.clients.entry(id).or_insert_with(|| Client {
+                    handshake: ServerHandshake::new(manifest.clone()).with_authenticator(authenticator.clone())
+                });
+                for reply in client.handshake.handle(&data, id as u64) {
+                    connection.send(Channel::Reliable, reply);
+                }
+                match client.handshake.state() {
+                    HandshakeState::Play => {
+                        let login = client.handshake.login().expect("logged in clients have a login").clone();
+                        let uuid = client.handshake.uuid().expect("logged in clients have a UUID");
+                        self.clients.remove(&id);
+                        if let Some(port) = login.unreliable_port {
+                            self.transport.open_unreliable(id, SocketAddr::new(connection.peer_addr().ip(), port));
+                        }
+                        self.join(id, &login.name, uuid, now);
+                    }
+                    HandshakeState::Disconnected(_) => {
+                        self.closing.push(id);
+                        return;
+                    }
+                    HandshakeState::Hello | HandshakeState::Login => {}
+                }
+                continue;
+            }
+            self.players.heard_from(id, now);
+            if let Ok(disconnect) = packet::decode::<Disconnect>(&data) {
+                if let Some(name) = self.player_name(id) {
+                    println!("{} disconnected: {}", name, disconnect.reason);
+                }
+                self.closing.push(id);
+                return;
+            }
+            if self.players.handle(id, &data) || self.interact(id, &data) || self.craft(id, &data) || self.edit_sign(id, &data) {
+                continue;
+            }
+            if let Some(moved) = self.players.handle_movement(id, &data) {
+                if let Err(violation) = moved {
+                    self.correct(id, violation);
+                }
+                continue;
+            }
+            if let Ok(routed) = self.chat.handle(id, &data) {
+                self.send_all(routed);
+            }
+        }
+    }
+
+    /// Handle a player breaking or placing a block. False if the packet wasn't either.
+    fn interact(&mut self, id: ClientId, data: &[u8]) -> bool {
+        if let Ok(dig) = packet::decode::<DigBlock>(data) {
+            if self.dig(id, &dig).is_err() {
+                self.refuse(id, dig.block);
+            }
+            return true;
+        }
+        if let Ok(place) = packet::decode::<PlaceBlock>(data) {
+            if self.place(id, &place).is_err() {
+                self.refuse(id, place.against + place.face.offset());
+            }
+            return true;
+        }
+        return false;
+    }
+
+    /// Check a player may change a block, returning where their eyes are.
+    fn check_player(&self, id: ClientId, block: BlockPos, action: ProtectedAction) -> Result<WorldPos, InteractionError> {
+        let session = self.players.session(id).ok_or(InteractionError::NotAllowed)?;
+        let eye = self.players.eye(id).ok_or(InteractionError::NotAllowed)?;
+        let uuid = session.player().uuid;
+        let teams = self.protection.teams().teams_of(uuid);
+        if self.access.read().unwrap().permission(uuid) < PermissionLevel::OPERATOR && !self.protection.can(uuid, &teams, block, action) {
+            return Err(InteractionError::NotAllowed);
+        }
+        return Ok(eye);
+    }
+
+    /// Break a block for a player, giving them its item.
+    fn dig(&mut self, id: ClientId, dig: &DigBlock) -> Result<(), InteractionError> {
+        let eye = self.check_player(id, dig.block, ProtectedAction::Break)?;
+        let world = self.overworld.world();
+        let block = interaction::validate_dig(world, eye, dig, REACH_DISTANCE + REACH_TOLERANCE)?;
+        world.set_block(dig.block, AIR);
+        let item = self.blocks.block(block).and_then(|kind| self.items.item_for_block(kind.default_state()));
+        let entity = self.players.session(id).map(|session| session.entity());
+        if let (Some(item), Some(entity)) = (item, entity) {
+            let entities = world.entities();
+            if let Some(mut inventory) = entities.get::<Inventory>(entity) {
+                // Items that don't fit are lost until there are item entities to drop them as.
+                inventory.insert(ItemStack::new(item, 1), &self.items);
+                let _ = entities.insert(entity, inventory);
+            }
+        }
+        if let Some(events) = world.events() {
+            events.publish(BlockBroken { client: id, pos: dig.block, block });
+        }
+        return Ok(());
+    }
+
+    /// Place a block from a player's hotbar, taking it from their inventory.
+    fn place(&mut self, id: ClientId, place: &PlaceBlock) -> Result<(), InteractionError> {
+        let target = place.against + place.face.offset();
+        let eye = self.check_player(id, target, ProtectedAction::Build)?;
+        let world = self.overworld.world();
+        interaction::validate_place(world, eye, place, REACH_DISTANCE + REACH_TOLERANCE)?;
+        if self.players.occupying(target) {
+            return Err(InteractionError::Occupied);
+        }
+        let slot = place.slot as usize;
+        if slot >= HOTBAR_SLOTS {
+            return Err(InteractionError::NoBlockItem);
+        }
+        let entity = self.players.session(id).ok_or(InteractionError::NotAllowed)?.entity();
+        let entities = world.entities();
+        let mut inventory = entities.get::<Inventory>(entity).ok_or(InteractionError::NoBlockItem)?;
+        let block = inventory.get(slot)
+            .and_then(|stack| self.items.item(stack.item))
+            .and_then(|item| item.block())
+            .ok_or(InteractionError::NoBlockItem)?;
+        inventory.extract_slot(slot, 1);
+        let _ = entities.insert(entity, inventory);
+        world.set_block(target, block);
+        if block == self.sign_block {
+            let _ = world.insert_block_entity(target, Box::new(SignBlockEntity::default()));
+            self.sign_editors.insert(id, target);
+            if let Some(connection) = self.transport.connection(id) {
+                connection.send_with_priority(Channel::Reliable, Priority::Low, packet::encode(&OpenSignEditor { block: target }));
+            }
+        }
+        if let Some(events) = world.events() {
+            events.publish(BlockPlaced { client: id, pos: target, block });
+        }
+        return Ok(());
+    }
+
+    /// Handle a player writing on the sign they placed. False if the packet wasn't a sign edit.
+    fn edit_sign(&mut self, id: ClientId, data: &[u8]) -> bool {
+        let Ok(edit) = packet::decode::<EditSign>(data) else {
+            return false;
+        };
+        if self.write_sign(id, &edit).is_err() {
+            // Undo the text their client predicted.
+            if let (Some(connection), Some(update)) = (self.transport.connection(id), sign_net::sign_update(self.world(), edit.block)) {
+                connection.send_with_priority(Channel::Reliable, Priority::Low, update);
+            }
+        }
+        return true;
+    }
+
+    /// Set the text of a sign for a player, sending it to everyone who has the sign's chunk.
+    /// Players may only write on the last sign they placed, once.
+    fn write_sign(&mut self, id: ClientId, edit: &EditSign) -> Result<(), SignEditError> {
+        if self.sign_editors.remove(&id) != Some(edit.block) {
+            return Err(SignEditError::NotAllowed);
+        }
+        let eye = self.check_player(id, edit.block, ProtectedAction::Build).map_err(|_| SignEditError::NotAllowed)?;
+        let world = self.overworld.world();
+        let text = sign_net::validate_edit(world, eye, edit, REACH_DISTANCE + REACH_TOLERANCE)?;
+        sign::set_sign_text(world, edit.block, text);
+        let Some(update) = sign_net::sign_update(world, edit.block) else {
+            return Ok(());
+        };
+        for client in self.players.watching(edit.block.chunk()) {
+            if let Some(connection) = self.transport.connection(client) {
+                connection.send_with_priority(Channel::Reliable, Priority::Low, update.clone());
+            }
+        }
+        return Ok(());
+    }
+
+    /// Craft for a player from the slots of their inventory they laid out, telling them whether it worked.
+    fn craft(&mut self, id: ClientId, data: &[u8]) -> bool {
+        let Ok(craft) = packet::decode::<CraftItem>(data) else {
+            return false;
+        };
+        let world = self.overworld.world();
+        let entity = self.players.session(id).map(|session| session.entity());
+        let mut crafted = None;
+        if let Some(entity) = entity {
+            let entities = world.entities();
+            if let Some(mut inventory) = entities.get::<Inventory>(entity) {
+                if let Ok(recipe) = recipe::craft(&mut inventory, &craft.inventory_slots(), craft.width as usize, &self.recipes, &self.items) {
+                    let _ = entities.insert(entity, inventory);
+                    crafted = self.recipes.get(recipe).map(|recipe| recipe.name.clone());
+                }
+            }
+        }
+        if let Some(connection) = self.transport.connection(id) {
+            connection.send_with_priority(Channel::Reliable, Priority::Low, packet::encode(&CraftResult { recipe: crafted }));
+        }
+        return true;
+    }
+
+    /// Move a player's client back to where the server has them after refusing a move.
+    fn correct(&self, id: ClientId, violation: MovementViolation) {
+        if let Some(name) = self.player_name(id) {
+            println!("{} {}", name, violation);
+        }
+        if let (Some(connection), Some(correction)) = (self.transport.connection(id), self.players.correction(id)) {
+            connection.send_with_priority(Channel::Reliable, Priority::High, packet::encode(&correction));
+        }
+    }
+
+    /// Let operators fly, going by a player's current permission level.
+    fn update_flying(&mut self, id: ClientId) {
+        let Some(session) = self.players.session(id) else {
+            return;
+        };
+        let permission = self.access.read().unwrap().permission(session.player().uuid);
+        self.players.set_may_fly(id, permission >= PermissionLevel::OPERATOR);
+    }
+
+    /// Send a player the actual block where they were refused a change, undoing what their client predicted.
+    fn refuse(&self, id: ClientId, block: BlockPos) {
+        let Some(current) = self.world().get_block(block) else {
+            return;
+        };
+        if let Some(connection) = self.transport.connection(id) {
+            connection.send_with_priority(Channel::Reliable, Priority::Low, packet::encode(&BlockUpdate { block, id: current }));
+        }
+    }
+
+    fn join(&mut self, id: ConnectionId, name: &str, uuid: u128, now: Instant) {
+        if let Err(error) = self.players.join(id, name, uuid, now) {
+            eprintln!("couldn't load the player data of {}: {}", name, error);
+            self.disconnect(id, DisconnectReason::Kicked("Your player data couldn't be loaded".to_string()));
+            return;
+        }
+        self.update_flying(id);
+        println!("{} joined the game", name);
+        let routed = self.chat.join(id, name);
+        self.send_all(routed);
+        if let Some(events) = self.overworld.world().events() {
+            events.publish(PlayerJoined { client: id, name: name.to_string() });
+        }
+    }
+
+    /// Forget a connection. Players are saved and despawned, and everyone is told they left.
+    fn leave(&mut self, id: ConnectionId) {
+        self.clients.remove(&id);
+        self.sign_editors.remove(&id);
+        self.chunks.remove_player(id);
+        let Some((player, saved)) = self.players.leave(id) else {
+            return;
+        };
+        if let Err(error) = saved {
+            eprintln!("couldn't save {}: {}", player.name, error);
+        }
+        println!("{} left the game", player.name);
+        let routed = self.chat.leave(id);
+        self.send_all(routed);
+        if let Some(events) = self.overworld.world().events() {
+            events.publish(PlayerLeft { client: id, name: player.name });
+        }
+    }
+
+    /// Tell a connection why it's being closed, and close it next tick once that's sent.
+    fn disconnect(&mut self, id: ConnectionId, reason: DisconnectReason) {
+        if let Some(connection) = self.transport.connection(id) {
+            connection.send(Channel::Reliable, packet::encode(&Disconnect { reason }));
+        }
+        if !self.closing.contains(&id) {
+            self.closing.push(id);
+        }
+    }
+
+    fn send_all(&self, packets: Vec<(ClientId, Vec<u8>)>) {
+        for (id, packet) in packets {
+            if let Some(connection) = self.transport.connection(id) {
+                connection.send(Channel::Reliable, packet);
+            }
+        }
+    }
+
+    /// Run a command a player typed in chat, sending them its output.
+    fn run_player_command(&mut self, id: ClientId, line: &str) {
+        let Some(session) = self.players.session(id) else {
+            return;
+        };
+        let position = self.world().entities().get::<Transform>(session.entity()).map_or(self.world().spawn_point(), |transform| transform.position.block());
+        let permission = self.access.read().unwrap().permission(session.player().uuid);
+        let source = CommandSource::player(id, &session.player().name, position).with_permission(permission);
+        let commands = self.commands.clone();
+        let output = match commands.execute(self, &source, line) {
+            Ok(output) => output,
+            Err(error) => format!("§c{}", error)
+        };
+        if output.is_empty() {
+            return;
+        }
+        if let Some(packet) = self.chat.whisper(id, ChatKind::System, &output) {
+            self.send_all(vec![(id, packet)]);
+        }
+    }
+
+    /// Commands for the console and players.
+    fn commands() -> CommandDispatcher<Server> {
+        let mut commands = CommandDispatcher::new();
+        commands.register("help")
+            .description("List the commands.")
+            .executes(|server: &mut Server, context| {
+                let lines: Vec<String> = server.commands.commands(context.source).into_iter().map(|command| format!("/{} - {}", command.usage, command.description)).collect();
+                return Ok(lines.join("\n"));
+            }).expect("help is a valid command");
+        commands.register("list")
+            .description("List the players online.")
+            .executes(|server: &mut Server, _| {
+                let names = server.player_names();
+                let players = if names.len() == 1 { "player" } else { "players" };
+                return Ok(format!("{} {} online: {}", names.len(), players, names.join(", ")));
+            }).expect("list is a valid command");
+        commands.register("tps")
+            .description("Show the ticks per second reached and how long ticks take.")
+            .executes(|server: &mut Server, _| {
+                return Ok(server.ticks.report(Instant::now()).to_string());
+            }).expect("tps is a valid command");
+        commands.register("say")
+            .description("Send a message to every player.")
+            .argument("message", ArgumentKind::Text)
+            .permission(PermissionLevel::Moderator)
+            .executes(|server: &mut Server, context| {
+                let message = format!("[{}] {}", context.source.name, context.text("message").unwrap());
+                let routed = server.chat.broadcast(ChatKind::System, &message);
+                server.send_all(routed);
+                return Ok(String::new());
+            }).expect("say is a valid command");
+        commands.register("kick")
+            .description("Disconnect a player.")
+            .argument("player", ArgumentKind::Player)
+            .optional("reason", ArgumentKind::Text)
+            .permission(PermissionLevel::Moderator)
+            .executes(|server: &mut Server, context| {
+                let player = context.player("player").unwrap().to_string();
+                let reason = context.text("reason").unwrap_or("Kicked by an operator").to_string();
+                let id = server.players.find(&player);
+                let Some(id) = id else {
+                    return Err(format!("{} isn't online", player));
+                };
+                server.disconnect(id, DisconnectReason::Kicked(reason.clone()));
+                return Ok(format!("kicked {}: {}", player, reason));
+            }).expect("kick is a valid command");
+        commands.register("save-all")
+            .description("Save every changed chunk now.")
+            .permission(PermissionLevel::Owner)
+            .executes(|server: &mut Server, _| {
+                server.take_changes();
+                server.autosave.start(&server.saves, Instant::now());
+                return Ok(format!("saving {} chunks", server.saves.dirty_count()));
+            }).expect("save-all is a valid command");
+        commands.register("backup")
+            .description("Write a backup of the save under a name.")
+            .argument("name", ArgumentKind::Word)
+            .permission(PermissionLevel::Owner)
+            .executes(|server: &mut Server, context| {
+                let backup = server.saves.create_backup(context.text("name").unwrap()).map_err(|error| error.to_string())?;
+                let output = format!("backing up to {}", backup.path().display());
+                server.backups.push(backup);
+                return Ok(output);
+            }).expect("backup is a valid command");
+        commands.register("seed")
+            .description("Show the world's seed.")
+            .operator_only()
+            .executes(|server: &mut Server, _| {
+                return Ok(format!("seed: {}", server.overworld.world().level().seed));
+            }).expect("seed is a valid command");
+        commands.register("stop")
+            .description("Save and shut the server down.")
+            .permission(PermissionLevel::Owner)
+            .executes(|server: &mut Server, _| {
+                server.stop();
+                return Ok("stopping".to_string());
+            }).expect("stop is a valid command");
+        commands.register("give")
+            .description("Give a player some of an item, such as cube:stone.")
+            .argument("player", ArgumentKind::Player)
+            .argument("item", ArgumentKind::Word)
+            .optional("count", ArgumentKind::Integer { min: 1, max: 6400 })
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let name = context.player("player").unwrap();
+                let entity = server.players.find(name).and_then(|id| server.players.session(id)).map(|session| session.entity())
+                    .ok_or_else(|| format!("{} isn't online", name))?;
+                let item_name = context.text("item").unwrap();
+                let item = server.items.id(item_name).ok_or_else(|| format!("there's no item {}", item_name))?;
+                let count = context.integer("count").unwrap_or(1) as u32;
+                let entities = server.world().entities();
+                let mut inventory = entities.get::<Inventory>(entity).ok_or_else(|| format!("{} has no inventory", name))?;
+                let given = count - inventory.insert(ItemStack::new(item, count), &server.items).map_or(0, |leftover| leftover.count);
+                let _ = entities.insert(entity, inventory);
+                return Ok(format!("gave {} {} {}", name, given, item_name));
+            }).expect("give is a valid command");
+        commands.register("chunks")
+            .description("Show how many chunks are loaded, and why.")
+            .operator_only()
+            .executes(|server: &mut Server, _| {
+                return Ok(format!("{} chunks loaded, {} with tickets, {} loading, {} waiting to unload, {} forced",
+                    server.world().chunk_count(), server.chunks.tickets().len(), server.chunks.loading_count(),
+                    server.chunks.unloading_count(), server.chunks.forced().len()));
+            }).expect("chunks is a valid command");
+        commands.register("memory")
+            .description("Show the memory used by loaded chunks and entities, and their budgets.")
+            .operator_only()
+            .executes(|server: &mut Server, _| {
+                let usage: Vec<String> = server.memory.usage().iter()
+                    .filter(|usage| usage.category != MemoryCategory::Meshes)
+                    .map(|usage| usage.to_string())
+                    .collect();
+                return Ok(usage.join(", "));
+            }).expect("memory is a valid command");
+        commands.register("summon")
+            .description("Spawn mobs at a position.")
+            .argument("position", ArgumentKind::Position)
+            .optional("count", ArgumentKind::Integer { min: 1, max: 100 })
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let position = context.position("position").unwrap();
+                let count = context.integer("count").unwrap_or(1);
+                for _ in 0..count {
+                    spawn_mob(server.world(), WorldPos::new(position.x as f64 + 0.5, position.y as f64, position.z as f64 + 0.5));
+                }
+                return Ok(format!("summoned {} mobs at ({}, {}, {})", count, position.x, position.y, position.z));
+            }).expect("summon is a valid command");
+        commands.register("fill")
+            .description("Set every block between two positions, which can be undone.")
+            .argument("from", ArgumentKind::Position)
+            .argument("to", ArgumentKind::Position)
+            .argument("block", ArgumentKind::Block)
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let (from, to) = (context.position("from").unwrap(), context.position("to").unwrap());
+                let count = ((from.x - to.x).abs() + 1) as i64 * ((from.y - to.y).abs() + 1) as i64 * ((from.z - to.z).abs() + 1) as i64;
+                if count > MAX_FILL_BLOCKS {
+                    return Err(format!("that's {} blocks, more than the {} that can be filled at once", count, MAX_FILL_BLOCKS));
+                }
+                let mut edit = WorldEdit::new();
+                edit.fill(from, to, context.block("block").unwrap());
+                let world = server.overworld.world().clone();
+                let changed = server.edits.entry(context.source.name.clone()).or_default().apply(&world, edit, None).map_err(|error| error.to_string())?;
+                return Ok(format!("filled {} blocks", changed));
+            }).expect("fill is a valid command");
+        commands.register("copy")
+            .description("Copy the blocks between two positions, to paste or save as a schematic.")
+            .argument("from", ArgumentKind::Position)
+            .argument("to", ArgumentKind::Position)
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let (from, to) = (context.position("from").unwrap(), context.position("to").unwrap());
+                let count = ((from.x - to.x).abs() + 1) as i64 * ((from.y - to.y).abs() + 1) as i64 * ((from.z - to.z).abs() + 1) as i64;
+                if count > MAX_FILL_BLOCKS {
+                    return Err(format!("that's {} blocks, more than the {} that can be copied at once", count, MAX_FILL_BLOCKS));
+                }
+                let schematic = server.world().copy_region(from, to, &server.blocks).map_err(|error| error.to_string())?;
+                let (width, height, length) = schematic.size();
+                server.clipboards.insert(context.source.name.clone(), schematic);
+                return Ok(format!("copied {}x{}x{} blocks", width, height, length));
+            }).expect("copy is a valid command");
+        commands.register("paste")
+            .description("Paste what you copied with its lowest corner at a position, which can be undone.")
+            .optional("position", ArgumentKind::Position)
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let origin = context.position("position").or(context.source.position).ok_or_else(|| "usage: paste <position>".to_string())?;
+                let schematic = server.clipboards.get(&context.source.name).ok_or_else(|| "you haven't copied anything".to_string())?;
+                let world = server.overworld.world().clone();
+                let transaction = world.paste_region(schematic, origin, &server.blocks, &server.block_entity_types, None).map_err(|error| error.to_string())?;
+                let changed = transaction.len();
+                if !transaction.is_empty() {
+                    server.edits.entry(context.source.name.clone()).or_default().push(transaction);
+                }
+                return Ok(format!("pasted, changing {} blocks", changed));
+            }).expect("paste is a valid command");
+        commands.register("schematic")
+            .description("Save what you copied as a schematic file in the world, or load one to paste.")
+            .argument("action", ArgumentKind::Word)
+            .argument("name", ArgumentKind::Word)
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let name = context.text("name").unwrap();
+                if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                    return Err("schematic names may only have letters, digits, underscores and dashes".to_string());
+                }
+                let path = server.universe.directory().join(SCHEMATICS_DIRECTORY).join(name).with_extension(SCHEMATIC_EXTENSION);
+                return match context.text("action").unwrap() {
+                    "save" => {
+                        let schematic = server.clipboards.get(&context.source.name).ok_or_else(|| "you haven't copied anything".to_string())?;
+                        schematic.save(&path).map_err(|error| format!("couldn't save {}: {}", name, error))?;
+                        Ok(format!("saved schematic {}", name))
+                    },
+                    "load" => {
+                        let schematic = Schematic::load(&path).map_err(|error| format!("couldn't load {}: {}", name, error))?;
+                        let (width, height, length) = schematic.size();
+                        server.clipboards.insert(context.source.name.clone(), schematic);
+                        Ok(format!("loaded {}x{}x{} schematic {}, ready to paste", width, height, length, name))
+                    },
+                    action => Err(format!("unknown action {}, expected save or load", action))
+                };
+            }).expect("schematic is a valid command");
+        commands.register("undo")
+            .description("Undo your last world edit.")
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let world = server.overworld.world().clone();
+                let history = server.edits.entry(context.source.name.clone()).or_default();
+                return match history.undo(&world, None).map_err(|error| error.to_string())? {
+                    Some(changed) => Ok(format!("undid an edit of {} blocks", changed)),
+                    None => Err("there's nothing to undo".to_string())
+                };
+            }).expect("undo is a valid command");
+        commands.register("redo")
+            .description("Redo the last world edit you undid.")
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let world = server.overworld.world().clone();
+                let history = server.edits.entry(context.source.name.clone()).or_default();
+                return match history.redo(&world, None).map_err(|error| error.to_string())? {
+                    Some(changed) => Ok(format!("redid an edit of {} blocks", changed)),
+                    None => Err("there's nothing to redo".to_string())
+                };
+            }).expect("redo is a valid command");
+        commands.register("forceload")
+            .description("Keep the chunks between two positions loaded with nobody near them, stop keeping them, or list them.")
+            .argument("action", ArgumentKind::Word)
+            .optional("from", ArgumentKind::Position)
+            .optional("to", ArgumentKind::Position)
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let action = context.text("action").unwrap();
+                if action == "list" {
+                    let forced: Vec<String> = server.chunks.forced().iter().map(|pos| format!("({}, {}, {})", pos.x, pos.y, pos.z)).collect();
+                    return Ok(format!("{} chunks forced loaded: {}", forced.len(), forced.join(", ")));
+                }
+                let Some(from) = context.position("from") else {
+                    return Err("usage: forceload list, or forceload add|remove <from> [to]".to_string());
+                };
+                let (from, to) = (from.chunk(), context.position("to").unwrap_or(from).chunk());
+                let (min, max) = (ChunkPos::new(from.x.min(to.x), from.y.min(to.y), from.z.min(to.z)), ChunkPos::new(from.x.max(to.x), from.y.max(to.y), from.z.max(to.z)));
+                let count = (max.x - min.x + 1) as i64 * (max.y - min.y + 1) as i64 * (max.z - min.z + 1) as i64;
+                if count > MAX_FORCED_CHUNKS {
+                    return Err(format!("that's {} chunks, more than the {} that can be forced at once", count, MAX_FORCED_CHUNKS));
+                }
+                let mut changed = 0;
+                for x in min.x..=max.x {
+                    for y in min.y..=max.y {
+                        for z in min.z..=max.z {
+                            let pos = ChunkPos::new(x, y, z);
+                            changed += match action {
+                                "add" => server.chunks.force(pos),
+                                "remove" => server.chunks.unforce(pos),
+                                _ => return Err(format!("unknown action {}, expected add, remove or list", action))
+                            } as usize;
+                        }
+                    }
+                }
+                return Ok(match action {
+                    "add" => format!("forced {} chunks to stay loaded", changed),
+                    _ => format!("stopped forcing {} chunks", changed)
+                });
+            }).expect("forceload is a valid command");
+        Server::register_access_commands(&mut commands);
+        Server::register_protection_commands(&mut commands);
+        return commands;
+    }
+
+    /// The UUID of a player by name: theirs if they're online, or else the one they'd log in with.
+    fn uuid_of(&self, name: &str) -> (u128, String) {
+        if let Some(session) = self.players.find(name).and_then(|id| self.players.session(id)) {
+            return (session.player().uuid, session.player().name.clone());
+        }
+        return (offline_uuid(name), name.to_string());
+    }
+
+    /// Commands editing the whitelist, bans and operators.
+    fn register_access_commands(commands: &mut CommandDispatcher<Server>) {
+        commands.register("ban")
+            .description("Ban a player for good, disconnecting them if they're online.")
+            .argument("player", ArgumentKind::Word)
+            .optional("reason", ArgumentKind::Text)
+            .permission(PermissionLevel::Admin)
+            .executes(|server: &mut Server, context| {
+                return server.ban(context.text("player").unwrap(), None, context.text("reason"));
+            }).expect("ban is a valid command");
+        commands.register("tempban")
+            .description("Ban a player for a time, such as 30m, 12h or 7d.")
+            .argument("player", ArgumentKind::Word)
+            .argument("duration", ArgumentKind::Word)
+            .optional("reason", ArgumentKind::Text)
+            .permission(PermissionLevel::Admin)
+            .executes(|server: &mut Server, context| {
+                let duration = context.text("duration").unwrap();
+                let duration = parse_duration(duration).ok_or_else(|| format!("{} isn't a duration such as 30m, 12h or 7d", duration))?;
+                return server.ban(context.text("player").unwrap(), Some(duration), context.text("reason"));
+            }).expect("tempban is a valid command");
+        commands.register("pardon")
+            .description("Lift a player's ban.")
+            .argument("player", ArgumentKind::Word)
+            .permission(PermissionLevel::Admin)
+            .executes(|server: &mut Server, context| {
+                let name = context.text("player").unwrap();
+                let now = unix_time();
+                let mut access = server.access.write().unwrap();
+                let uuid = match access.bans().find(name, now) {
+                    Some(ban) => ban.uuid().ok_or_else(|| format!("{}'s ban has an invalid UUID", name))?,
+                    None => return Err(format!("{} isn't banned", name))
+                };
+                access.bans_mut().remove(uuid, now).map_err(|error| error.to_string())?;
+                return Ok(format!("unbanned {}", name));
+            }).expect("pardon is a valid command");
+        commands.register("banlist")
+            .description("List the banned players.")
+            .permission(PermissionLevel::Admin)
+            .executes(|server: &mut Server, _| {
+                let now = unix_time();
+                let access = server.access.read().unwrap();
+                let bans = access.bans().entries(now);
+                let mut lines = vec![format!("{} banned", bans.len())];
+                for ban in bans {
+                    let mut line = ban.name.clone();
+                    if let Some(reason) = ban.reason.as_ref() {
+                        line += &format!(": {}", reason);
+                    }
+                    if let Some(expires) = ban.expires {
+                        line += &format!(" ({} left)", format_duration(Duration::from_secs(expires - now)));
+                    }
+                    lines.push(line);
+                }
+                return Ok(lines.join("\n"));
+            }).expect("banlist is a valid command");
+        commands.register("whitelist")
+            .description("Turn the whitelist on or off, list it, or add or remove a player.")
+            .argument("action", ArgumentKind::Word)
+            .optional("player", ArgumentKind::Word)
+            .permission(PermissionLevel::Admin)
+            .executes(|server: &mut Server, context| {
+                let action = context.text("action").unwrap();
+                let player = context.text("player").map(|name| server.uuid_of(name));
+                let now = unix_time();
+                let mut access = server.access.write().unwrap();
+                return match (action, player) {
+                    ("on", None) => {
+                        access.set_whitelist_enabled(true);
+                        Ok("the whitelist is on".to_string())
+                    },
+                    ("off", None) => {
+                        access.set_whitelist_enabled(false);
+                        Ok("the whitelist is off".to_string())
+                    },
+                    ("list", None) => {
+                        let names: Vec<String> = access.whitelist().entries(now).into_iter().map(|entry| entry.name.clone()).collect();
+                        let state = if access.is_whitelist_enabled() { "on" } else { "off" };
+                        Ok(format!("the whitelist is {}, with {} players: {}", state, names.len(), names.join(", ")))
+                    },
+                    ("add", Some((uuid, name))) => {
+                        access.whitelist_mut().add(AccessEntry::new(uuid, &name), now).map_err(|error| error.to_string())?;
+                        Ok(format!("added {} to the whitelist", name))
+                    },
+                    ("remove", Some((uuid, name))) => match access.whitelist_mut().remove(uuid, now).map_err(|error| error.to_string())? {
+                        true => Ok(format!("removed {} from the whitelist", name)),
+                        false => Err(format!("{} isn't on the whitelist", name))
+                    },
+                    _ => Err("usage: whitelist on|off|list, or whitelist add|remove <player>".to_string())
+                };
+            }).expect("whitelist is a valid command");
+        commands.register("op")
+            .description("Give a player a permission level from 1, moderator, to 4, owner. Admin by default.")
+            .argument("player", ArgumentKind::Word)
+            .optional("level", ArgumentKind::Integer { min: 1, max: 4 })
+            .permission(PermissionLevel::Admin)
+            .executes(|server: &mut Server, context| {
+                let level = context.integer("level").map_or(Some(PermissionLevel::Admin), |level| PermissionLevel::from_level(level as u8)).unwrap();
+                // Nobody may hand out more than they have.
+                if level > context.source.permission {
+                    return Err(format!("you can't give a higher permission level than your own, {}", context.source.permission.name()));
+                }
+                let (uuid, name) = server.uuid_of(context.text("player").unwrap());
+                server.access.write().unwrap().set_permission(uuid, &name, level).map_err(|error| error.to_string())?;
+                if let Some(id) = server.players.find(&name) {
+                    server.update_flying(id);
+                }
+                return Ok(format!("made {} {}", name, level.name()));
+            }).expect("op is a valid command");
+        commands.register("deop")
+            .description("Take away a player's permission level.")
+            .argument("player", ArgumentKind::Word)
+            .permission(PermissionLevel::Admin)
+            .executes(|server: &mut Server, context| {
+                let (uuid, name) = server.uuid_of(context.text("player").unwrap());
+                let mut access = server.access.write().unwrap();
+                if access.permission(uuid) > context.source.permission {
+                    return Err(format!("{} has a higher permission level than you", name));
+                }
+                access.set_permission(uuid, &name, PermissionLevel::Player).map_err(|error| error.to_string())?;
+                drop(access);
+                if let Some(id) = server.players.find(&name) {
+                    server.update_flying(id);
+                }
+                return Ok(format!("{} is no longer an operator", name));
+            }).expect("deop is a valid command");
+    }
+
+    /// Commands editing the protection regions and the teams they let in, saved with the world as they change.
+    fn register_protection_commands(commands: &mut CommandDispatcher<Server>) {
+        commands.register("region")
+            .description("Protect the blocks between two positions, stop protecting them, or list the regions.")
+            .argument("action", ArgumentKind::Word)
+            .optional("name", ArgumentKind::Word)
+            .optional("from", ArgumentKind::Position)
+            .optional("to", ArgumentKind::Position)
+            .optional("priority", ArgumentKind::Integer { min: i32::MIN as i64, max: i32::MAX as i64 })
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let message = match (context.text("action").unwrap(), context.text("name"), context.position("from"), context.position("to")) {
+                    ("list", None, None, None) => {
+                        let regions: Vec<String> = server.protection.iter().map(|region| {
+                            return format!("{} ({}, {}, {}) to ({}, {}, {}), priority {}", region.name, region.min.x, region.min.y, region.min.z, region.max.x, region.max.y, region.max.z, region.priority);
+                        }).collect();
+                        return Ok(format!("{} regions\n{}", regions.len(), regions.join("\n")).trim_end().to_string());
+                    },
+                    ("add", Some(name), Some(from), Some(to)) => {
+                        let mut region = ProtectionRegion::new(name, from, to);
+                        region.priority = context.integer("priority").unwrap_or(0) as i32;
+                        server.protection.add(region).map_err(|error| error.to_string())?;
+                        format!("protected region {}", name)
+                    },
+                    ("remove", Some(name), None, None) => {
+                        server.protection.remove(name).map_err(|error| error.to_string())?;
+                        format!("removed region {}", name)
+                    },
+                    _ => return Err("usage: region list, region add <name> <from> <to> [priority], or region remove <name>".to_string())
+                };
+                server.save_protection().map_err(|error| format!("couldn't save the regions: {}", error))?;
+                return Ok(message);
+            }).expect("region is a valid command");
+        commands.register("regionmember")
+            .description("Let a player, or a team as team:<name>, build in a region, or stop letting them.")
+            .argument("action", ArgumentKind::Word)
+            .argument("region", ArgumentKind::Word)
+            .argument("member", ArgumentKind::Word)
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let (action, name, member) = (context.text("action").unwrap(), context.text("region").unwrap(), context.text("member").unwrap());
+                let player = server.uuid_of(member);
+                let region = server.protection.get_mut(name).ok_or_else(|| format!("no region named {}", name))?;
+                let changed = match (action, member.strip_prefix("team:")) {
+                    ("add", Some(team)) => region.teams.insert(team.to_string()),
+                    ("remove", Some(team)) => region.teams.remove(team),
+                    ("add", None) => region.members.insert(player.0),
+                    ("remove", None) => region.members.remove(&player.0),
+                    _ => return Err("usage: regionmember add|remove <region> <player|team:name>".to_string())
+                };
+                if !changed {
+                    return Err(format!("{} is already {} {}", member, if action == "add" { "a member of" } else { "not a member of" }, name));
+                }
+                server.save_protection().map_err(|error| format!("couldn't save the regions: {}", error))?;
+                return Ok(format!("{} {} {} {}", if action == "add" { "added" } else { "removed" }, member, if action == "add" { "to" } else { "from" }, name));
+            }).expect("regionmember is a valid command");
+        commands.register("team")
+            .description("Add a player to a team, take them out of one, or list the teams.")
+            .argument("action", ArgumentKind::Word)
+            .optional("team", ArgumentKind::Word)
+            .optional("player", ArgumentKind::Word)
+            .permission(PermissionLevel::GameMaster)
+            .executes(|server: &mut Server, context| {
+                let player = context.text("player").map(|name| server.uuid_of(name));
+                let teams = server.protection.teams_mut();
+                let message = match (context.text("action").unwrap(), context.text("team"), player) {
+                    ("list", None, None) => {
+                        let names: Vec<String> = teams.names().map(|name| format!("{} ({})", name, teams.members(name).map_or(0, |members| members.len()))).collect();
+                        return Ok(format!("{} teams: {}", names.len(), names.join(", ")));
+                    },
+                    ("join", Some(team), Some((uuid, name))) => match teams.join(team, uuid) {
+                        true => format!("added {} to team {}", name, team),
+                        false => return Err(format!("{} is already in team {}", name, team))
+                    },
+                    ("leave", Some(team), Some((uuid, name))) => match teams.leave(team, uuid) {
+                        true => format!("took {} out of team {}", name, team),
+                        false => return Err(format!("{} isn't in team {}", name, team))
+                    },
+                    _ => return Err("usage: team list, or team join|leave <team> <player>".to_string())
+                };
+                server.save_protection().map_err(|error| format!("couldn't save the teams: {}", error))?;
+                return Ok(message);
+            }).expect("team is a valid command");
+    }
+
+    /// Ban a player, for a time or for good, disconnecting them if they're online.
+    fn ban(&mut self, name: &str, duration: Option<Duration>, reason: Option<&str>) -> Result<String, String> {
+        let (uuid, name) = self.uuid_of(name);
+        let now = unix_time();
+        let mut entry = AccessEntry::new(uuid, &name);
+        if let Some(reason) = reason {
+            entry = entry.with_reason(reason);
+        }
+        if let Some(duration) = duration {
+            entry = entry.with_expiry(now + duration.as_secs());
+        }
+        let message = {
+            let mut access = self.access.write().unwrap();
+            access.bans_mut().add(entry, now).map_err(|error| error.to_string())?;
+            access.check(uuid, now).unwrap_err()
+        };
+        if let Some(id) = self.players.find(&name) {
+            self.disconnect(id, DisconnectReason::Kicked(message));
+        }
+        return Ok(match duration {
+            Some(duration) => format!("banned {} for {}", name, format_duration(duration)),
+            None => format!("banned {}", name)
+        });
+    }
+}
+
+impl CommandEnvironment for Server {
+    fn player_names(&self) -> Vec<String> {
+        return Server::player_names(self);
+    }
+
+    fn blocks(&self) -> Option<&BlockRegistry> {
+        return Some(&self.blocks);
+    }
+}
+
+impl CommandConsole for Server {
+    fn execute(&mut self, command: AdminCommand) -> Result<String, String> {
+        return self.execute_line(&command.to_string());
+    }
+
+    /// Run a command typed into the terminal, or sent by an admin client, as the console.
+    fn execute_line(&mut self, line: &str) -> Result<String, String> {
+        let commands = self.commands.clone();
+        return commands.execute(self, &CommandSource::console(), line).map_err(|error| error.to_string());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,164 @@
+use std::{collections::VecDeque, fmt, sync::Arc, time::{Duration, Instant}};
+
+use shared::engine::metrics::registry::{Counter, Gauge, Histogram, MetricsRegistry, DURATION_BUCKETS};
+
+/// How far back the tick times are kept for averages and percentiles.
+pub const TICK_WINDOW: Duration = Duration::from_secs(60);
+/// Window of the TPS reported to metrics, short enough to show a lag spike.
+pub const RECENT_WINDOW: Duration = Duration::from_secs(5);
+
+/// When a tick started and how long it ran.
+#[derive(Clone, Copy, Debug)]
+struct TickTime {
+    start: Instant,
+    duration: Duration
+}
+
+/// Times every server tick, keeping the last minute of them to measure the TPS actually reached and how much
+/// of each tick's budget is used. Every tick is also observed by the server_tick_duration_seconds histogram,
+/// and the recent TPS and mean tick time are kept in gauges, for dashboards.
+/// ```
+/// # use server::tps::TickMonitor;
+/// # use shared::engine::metrics::MetricsRegistry;
+/// # use std::time::{Duration, Instant};
+/// let registry = MetricsRegistry::new();
+/// let mut monitor = TickMonitor::new(20, &registry);
+/// let start = Instant::now();
+/// for tick in 0..40 {
+///     // Every tenth tick takes twice its budget.
+///     let duration = if tick % 10 == 9 { Duration::from_millis(100) } else { Duration::from_millis(10) };
+///     monitor.record(start + Duration::from_millis(50 * tick), duration);
+/// }
+/// let now = start + Duration::from_secs(2);
+/// assert!((monitor.tps(Duration::from_secs(60), now) - 20.0).abs() <= 0.5);
+/// let report = monitor.report(now);
+/// assert_eq!(report.overruns, 4);
+/// assert_eq!(report.max, Duration::from_millis(100));
+/// assert_eq!(registry.snapshot().histogram("server_tick_duration_seconds").unwrap().count, 40);
+/// ```
+pub struct TickMonitor {
+    budget: Duration,
+    ticks: VecDeque<TickTime>,
+    /// When the first tick started, so the TPS of a server up for less than a window isn't counted short.
+    first: Option<Instant>,
+    overruns: u64,
+    duration: Arc<Histogram>,
+    tps: Arc<Gauge>,
+    mspt: Arc<Gauge>,
+    skipped: Arc<Counter>
+}
+
+impl TickMonitor {
+    /// Monitor ticks run at a tick rate, with metrics in a registry.
+    pub fn new(tick_rate: u32, registry: &MetricsRegistry) -> TickMonitor {
+        return TickMonitor {
+            budget: Duration::from_secs_f64(1.0 / tick_rate as f64),
+            ticks: VecDeque::new(),
+            first: None,
+            overruns: 0,
+            duration: registry.histogram("server_tick_duration_seconds", "Time taken by each server tick, including networking and saving.", &DURATION_BUCKETS),
+            tps: registry.gauge("server_ticks_per_second", "Server ticks run per second over the last five seconds."),
+            mspt: registry.gauge("server_tick_mean_seconds", "Mean time taken by server ticks over the last minute."),
+            skipped: registry.counter("server_ticks_skipped_total", "Ticks skipped by the server for falling too far behind.")
+        };
+    }
+
+    /// Time each tick may take without the server falling behind.
+    pub fn budget(&self) -> Duration {
+        return self.budget;
+    }
+
+    /// Counts the ticks the game loop skipped. Given to the loop's skip hook, as the loop is driven outside the
+    /// server.
+    pub fn skipped_counter(&self) -> Arc<Counter> {
+        return self.skipped.clone();
+    }
+
+    /// Record a tick that started at a time and ran for a duration.
+    pub fn record(&mut self, start: Instant, duration: Duration) {
+        self.first.get_or_insert(start);
+        if duration > self.budget {
+            self.overruns += 1;
+        }
+        self.duration.observe_duration(duration);
+        self.ticks.push_back(TickTime { start, duration });
+        while self.ticks.front().is_some_and(|tick| start.duration_since(tick.start) > TICK_WINDOW) {
+            self.ticks.pop_front();
+        }
+        self.tps.set(self.tps(RECENT_WINDOW, start + duration));
+        self.mspt.set(self.mean().as_secs_f64());
+    }
+
+    /// Ticks started per second over a window ending now, up to a minute. A server up for less than the window
+    /// is measured over the time it has been up.
+    pub fn tps(&self, window: Duration, now: Instant) -> f64 {
+        let Some(first) = self.first else {
+            return 0.0;
+        };
+        let window = window.min(TICK_WINDOW).min(now.saturating_duration_since(first));
+        if window.is_zero() {
+            return 0.0;
+        }
+        let ticks = self.ticks.iter().rev().take_while(|tick| now.saturating_duration_since(tick.start) < window).count();
+        return ticks as f64 / window.as_secs_f64();
+    }
+
+    /// Mean duration of the ticks in the window.
+    pub fn mean(&self) -> Duration {
+        if self.ticks.is_empty() {
+            return Duration::ZERO;
+        }
+        return self.ticks.iter().map(|tick| tick.duration).sum::<Duration>() / self.ticks.len() as u32;
+    }
+
+    /// Tick duration a fraction of the ticks in the window were within, such as 0.95 for the 95th percentile.
+    pub fn percentile(&self, fraction: f64) -> Duration {
+        let mut durations: Vec<Duration> = self.ticks.iter().map(|tick| tick.duration).collect();
+        if durations.is_empty() {
+            return Duration::ZERO;
+        }
+        durations.sort_unstable();
+        let rank = (fraction.clamp(0.0, 1.0) * durations.len() as f64).ceil().max(1.0) as usize;
+        return durations[rank - 1];
+    }
+
+    /// Everything the tps command shows.
+    pub fn report(&self, now: Instant) -> TickReport {
+        return TickReport {
+            recent_tps: self.tps(RECENT_WINDOW, now),
+            minute_tps: self.tps(TICK_WINDOW, now),
+            mean: self.mean(),
+            p95: self.percentile(0.95),
+            max: self.ticks.iter().map(|tick| tick.duration).max().unwrap_or_default(),
+            budget: self.budget,
+            overruns: self.overruns,
+            skipped: self.skipped.get()
+        };
+    }
+}
+
+/// The server's tick rate and times, as shown by the tps command.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TickReport {
+    /// TPS over the last five seconds.
+    pub recent_tps: f64,
+    /// TPS over the last minute.
+    pub minute_tps: f64,
+    pub mean: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+    pub budget: Duration,
+    /// Ticks since the server started that took longer than their budget.
+    pub overruns: u64,
+    /// Ticks since the server started skipped for falling too far behind.
+    pub skipped: u64
+}
+
+impl fmt::Display for TickReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        writeln!(f, "TPS: {:.1} (5s), {:.1} (1m)", self.recent_tps, self.minute_tps)?;
+        writeln!(f, "tick time: {:.1}ms mean, {:.1}ms p95, {:.1}ms max of a {:.0}ms budget", millis(self.mean), millis(self.p95), millis(self.max), millis(self.budget))?;
+        return write!(f, "{} ticks overran, {} skipped", self.overruns, self.skipped);
+    }
+}
\ No newline at end of file
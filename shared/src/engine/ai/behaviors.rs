@@ -0,0 +1,404 @@
+use std::{marker::PhantomData, ops::Range};
+
+use crate::engine::{
+    entity::{kinematics::Transform, storage::Component, EntityId},
+    math::{coords::{BlockPos, WorldPos}, vector::Vec3},
+    path::{Movement, PathNode, Pathfinder},
+    progress::CancelToken
+};
+
+use super::tree::{AiContext, Behavior, Status};
+
+/// Most positions explored finding a mob's path. Far fewer than the pathfinder's default, as every mob searches
+/// on its own and they only go a few blocks at a time.
+pub const MOB_PATH_NODES: usize = 1000;
+/// Ticks a wandering mob stands still between walks.
+pub const DEFAULT_WANDER_PAUSE: Range<u64> = 40..160;
+/// Blocks from the center of a path node a mob has to come within to have reached it.
+const ARRIVE_DISTANCE: f64 = 0.35;
+/// Ticks a mob may take to reach the next node of its path before giving up, such as after being pushed off it.
+const STUCK_TICKS: u64 = 60;
+/// Blocks above and below a spot searched for ground to stand on.
+const GROUND_SEARCH: i32 = 4;
+/// Spots tried before giving up on finding one to wander or flee to this tick.
+const SPOT_ATTEMPTS: usize = 8;
+
+/// The block a mob's feet are in. Lifted a little, as a mob resting on a block can sit a hair below its top.
+pub fn feet(position: WorldPos) -> BlockPos {
+    return WorldPos::new(position.x, position.y + 0.01, position.z).block();
+}
+
+/// Middle of the bottom of a block, where mobs walk to.
+fn block_center(pos: BlockPos) -> WorldPos {
+    return WorldPos::new(pos.x as f64 + 0.5, pos.y as f64, pos.z as f64 + 0.5);
+}
+
+/// Ground a walker can stand on in the column of a position, nearest its height first.
+fn ground_near(pathfinder: &Pathfinder, context: &AiContext, pos: BlockPos) -> Option<BlockPos> {
+    return (0..=GROUND_SEARCH).flat_map(|dy| [dy, -dy]).map(|dy| pos.offset(0, dy, 0)).find(|pos| pathfinder.can_stand_at(context.world, *pos));
+}
+
+/// The nearest other entity with a component within a radius of the mob, and where it is.
+fn nearest<T: Component>(context: &AiContext, radius: f32) -> Option<(EntityId, WorldPos)> {
+    let position = context.position;
+    let found = context.world.entities_in_radius(Vec3::new(position.x as f32, position.y as f32, position.z as f32), radius);
+    let entities = context.world.entities();
+    let marked = entities.storage::<T>();
+    let marked = marked.read().unwrap();
+    let transforms = entities.storage::<Transform>();
+    let transforms = transforms.read().unwrap();
+    return found.into_iter()
+        .filter(|id| *id != context.entity && marked.contains(*id))
+        .find_map(|id| transforms.get(id).map(|transform| (id, transform.position)));
+}
+
+/// Walks a mob to a block along a path. The path is found on the brain's job the first tick after the goal is set,
+/// so however the jobs are scheduled, the mob sets off on the same tick.
+/// ```
+/// # use shared::engine::ai::{behaviors::MoveTo, tree::{BehaviorTree, Status}};
+/// # use shared::engine::path::Pathfinder;
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos, WorldPos};
+/// # use shared::engine::entity::EntityId;
+/// # use std::sync::Arc;
+/// let world = World::new();
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// world.set_block(BlockPos::new(3, 0, 1), 1);
+/// let mut move_to = MoveTo::new(Pathfinder::new(Arc::new(|id| id != 0)), 4.0);
+/// move_to.set_goal(BlockPos::new(3, 1, 1));
+/// let mut tree = BehaviorTree::new(Box::new(move_to), 1);
+///
+/// // Teleport the mob along its way each tick, steering it to the next node until it arrives.
+/// let mob = EntityId::from_bits(1);
+/// let mut position = WorldPos::new(1.5, 0.0, 1.5);
+/// let mut jumped = false;
+/// for tick in 0..10 {
+///     let (status, steering) = tree.tick(&world, mob, position, true, tick);
+///     if status == Status::Success {
+///         break;
+///     }
+///     assert_eq!(status, Status::Running);
+///     jumped |= steering.jump;
+///     position = steering.walk_to.unwrap();
+/// }
+/// assert_eq!(position, WorldPos::new(3.5, 1.0, 1.5));
+/// assert!(jumped);
+/// ```
+pub struct MoveTo {
+    pathfinder: Pathfinder,
+    speed: f32,
+    goal: Option<BlockPos>,
+    path: Option<Vec<PathNode>>,
+    /// Index of the path node being walked to.
+    next: usize,
+    /// Tick the mob set off for the node being walked to.
+    since: u64
+}
+
+impl MoveTo {
+    /// Walk at some blocks per second. Searches explore at most MOB_PATH_NODES unless the pathfinder explores fewer.
+    pub fn new(pathfinder: Pathfinder, speed: f32) -> MoveTo {
+        return MoveTo { pathfinder: pathfinder.with_max_nodes(MOB_PATH_NODES), speed, goal: None, path: None, next: 0, since: 0 };
+    }
+
+    /// Walk somewhere else, finding a new path. Fails without a goal.
+    pub fn set_goal(&mut self, goal: BlockPos) {
+        self.goal = Some(goal);
+        self.path = None;
+    }
+
+    pub fn goal(&self) -> Option<BlockPos> {
+        return self.goal;
+    }
+
+    pub fn pathfinder(&self) -> &Pathfinder {
+        return &self.pathfinder;
+    }
+
+    fn reached(position: WorldPos, node: BlockPos) -> bool {
+        let center = block_center(node);
+        let (dx, dz) = (position.x - center.x, position.z - center.z);
+        return (dx * dx + dz * dz).sqrt() <= ARRIVE_DISTANCE && (position.y - center.y).abs() < 0.5;
+    }
+}
+
+impl Behavior for MoveTo {
+    fn tick(&mut self, context: &mut AiContext) -> Status {
+        let Some(goal) = self.goal else {
+            return Status::Failure;
+        };
+        if self.path.is_none() {
+            match self.pathfinder.find(context.world, feet(context.position), goal, &CancelToken::new()) {
+                Ok(path) => {
+                    self.path = Some(path.nodes().to_vec());
+                    self.next = 1;
+                    self.since = context.tick;
+                },
+                Err(_) => {
+                    self.reset();
+                    return Status::Failure;
+                }
+            }
+        }
+        let path = self.path.as_ref().unwrap();
+        while self.next < path.len() && MoveTo::reached(context.position, path[self.next].pos) {
+            self.next += 1;
+            self.since = context.tick;
+        }
+        if self.next >= path.len() {
+            self.reset();
+            return Status::Success;
+        }
+        if context.tick - self.since > STUCK_TICKS {
+            self.reset();
+            return Status::Failure;
+        }
+        let node = path[self.next];
+        context.steering.walk_to = Some(block_center(node.pos));
+        context.steering.speed = self.speed;
+        context.steering.jump = context.on_ground && (node.pos.y > feet(context.position).y || node.movement == Movement::Jump);
+        return Status::Running;
+    }
+
+    fn reset(&mut self) {
+        self.goal = None;
+        self.path = None;
+    }
+}
+
+/// Walks to random spots nearby, pausing in between. Always running, other than when it arrives somewhere, so it
+/// suits the last child of a selector, for what a mob does with nothing better to do.
+/// ```
+/// # use shared::engine::ai::{behaviors::Wander, tree::{BehaviorTree, Status}};
+/// # use shared::engine::path::Pathfinder;
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{ChunkPos, WorldPos};
+/// # use shared::engine::entity::EntityId;
+/// # use std::sync::Arc;
+/// let world = World::new();
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// let wander = Wander::new(Pathfinder::new(Arc::new(|id| id != 0)), 2.0, 5).with_pause(10..11);
+/// let mut tree = BehaviorTree::new(Box::new(wander), 7);
+/// let (mob, position) = (EntityId::from_bits(1), WorldPos::new(8.5, 0.0, 8.5));
+///
+/// // Stands still for the pause, then sets off somewhere within five blocks.
+/// for tick in 0..10 {
+///     assert_eq!(tree.tick(&world, mob, position, true, tick), (Status::Running, Default::default()));
+/// }
+/// let (status, steering) = tree.tick(&world, mob, position, true, 10);
+/// assert_eq!(status, Status::Running);
+/// let next = steering.walk_to.unwrap();
+/// assert!((next.x - position.x).abs() <= 1.0 && (next.z - position.z).abs() <= 1.0);
+/// ```
+pub struct Wander {
+    move_to: MoveTo,
+    radius: i32,
+    pause: Range<u64>,
+    /// Tick to set off again, while pausing between walks.
+    resume: Option<u64>
+}
+
+impl Wander {
+    /// Walk at some blocks per second to spots up to a radius of blocks away.
+    pub fn new(pathfinder: Pathfinder, speed: f32, radius: u32) -> Wander {
+        return Wander { move_to: MoveTo::new(pathfinder, speed), radius: radius as i32, pause: DEFAULT_WANDER_PAUSE, resume: None };
+    }
+
+    /// Ticks to stand still between walks, picked at random from a range.
+    pub fn with_pause(mut self, ticks: Range<u64>) -> Wander {
+        self.pause = ticks;
+        return self;
+    }
+
+    fn pause_until(&self, context: &mut AiContext) -> u64 {
+        let span = self.pause.end.saturating_sub(self.pause.start).max(1);
+        return context.tick + self.pause.start + context.rng.next_u64() % span;
+    }
+
+    fn pick_spot(&self, context: &mut AiContext) -> Option<BlockPos> {
+        let from = feet(context.position);
+        for _ in 0..SPOT_ATTEMPTS {
+            let dx = context.rng.range_i32(-self.radius..self.radius + 1);
+            let dz = context.rng.range_i32(-self.radius..self.radius + 1);
+            if dx == 0 && dz == 0 {
+                continue;
+            }
+            if let Some(spot) = ground_near(self.move_to.pathfinder(), context, from.offset(dx, 0, dz)) {
+                return Some(spot);
+            }
+        }
+        return None;
+    }
+}
+
+impl Behavior for Wander {
+    fn tick(&mut self, context: &mut AiContext) -> Status {
+        if self.move_to.goal().is_none() {
+            let resume = match self.resume {
+                Some(resume) => resume,
+                None => *self.resume.insert(self.pause_until(context))
+            };
+            if context.tick < resume {
+                return Status::Running;
+            }
+            self.resume = None;
+            let Some(spot) = self.pick_spot(context) else {
+                self.resume = Some(self.pause_until(context));
+                return Status::Running;
+            };
+            self.move_to.set_goal(spot);
+        }
+        let status = self.move_to.tick(context);
+        if status != Status::Running {
+            self.resume = Some(self.pause_until(context));
+        }
+        return status;
+    }
+
+    fn reset(&mut self) {
+        self.move_to.reset();
+        self.resume = None;
+    }
+}
+
+/// Looks at the nearest entity with a component within a radius, such as Player, succeeding while there is one.
+/// ```
+/// # use shared::engine::ai::{behaviors::LookAt, tree::{BehaviorTree, Status}};
+/// # use shared::engine::entity::kinematics::Transform;
+/// # use shared::engine::world::World;
+/// # use shared::engine::math::coords::WorldPos;
+/// #[derive(Debug)]
+/// struct Player;
+///
+/// let world = World::new();
+/// let spawn = |position: WorldPos| {
+///     let entity = world.entities().spawn();
+///     world.entities().insert(entity, Transform::new(position)).unwrap();
+///     world.entities().set_chunk(entity, position.chunk());
+///     entity
+/// };
+/// let mob = spawn(WorldPos::new(0.0, 0.0, 0.0));
+/// let player = spawn(WorldPos::new(3.0, 0.0, 4.0));
+/// let mut tree = BehaviorTree::new(Box::new(LookAt::<Player>::new(8.0)), 1);
+/// // Not a player yet.
+/// assert_eq!(tree.tick(&world, mob, WorldPos::new(0.0, 0.0, 0.0), true, 0).0, Status::Failure);
+/// world.entities().insert(player, Player).unwrap();
+/// let (status, steering) = tree.tick(&world, mob, WorldPos::new(0.0, 0.0, 0.0), true, 1);
+/// assert_eq!((status, steering.look_at), (Status::Success, Some(WorldPos::new(3.0, 0.0, 4.0))));
+/// ```
+pub struct LookAt<T> {
+    radius: f32,
+    target: PhantomData<fn() -> T>
+}
+
+impl<T: Component> LookAt<T> {
+    pub fn new(radius: f32) -> LookAt<T> {
+        return LookAt { radius, target: PhantomData };
+    }
+}
+
+impl<T: Component> Behavior for LookAt<T> {
+    fn tick(&mut self, context: &mut AiContext) -> Status {
+        let Some((_, target)) = nearest::<T>(context, self.radius) else {
+            return Status::Failure;
+        };
+        context.steering.look_at = Some(target);
+        return Status::Success;
+    }
+}
+
+/// Runs from the nearest entity with a component that comes within a radius, such as Player, until it's left
+/// further behind than the radius. Fails when there's nothing to flee, or nowhere to flee to.
+/// ```
+/// # use shared::engine::ai::{behaviors::Flee, tree::{BehaviorTree, Status}};
+/// # use shared::engine::entity::kinematics::Transform;
+/// # use shared::engine::path::Pathfinder;
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{ChunkPos, WorldPos};
+/// # use std::sync::Arc;
+/// #[derive(Debug)]
+/// struct Wolf;
+///
+/// let world = World::new();
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// let mob = world.entities().spawn();
+/// let wolf = world.entities().spawn();
+/// let position = WorldPos::new(8.5, 0.0, 8.5);
+/// world.entities().insert(wolf, Transform::new(WorldPos::new(6.5, 0.0, 8.5))).unwrap();
+/// world.entities().set_chunk(wolf, ChunkPos::ORIGIN);
+///
+/// let flee = Flee::<Wolf>::new(Pathfinder::new(Arc::new(|id| id != 0)), 4.0, 6.0, 5);
+/// let mut tree = BehaviorTree::new(Box::new(flee), 1);
+/// assert_eq!(tree.tick(&world, mob, position, true, 0).0, Status::Failure);
+/// world.entities().insert(wolf, Wolf).unwrap();
+/// let (status, steering) = tree.tick(&world, mob, position, true, 1);
+/// assert_eq!(status, Status::Running);
+/// // Away from the wolf, to the east.
+/// assert!(steering.walk_to.unwrap().x > position.x);
+/// ```
+pub struct Flee<T> {
+    move_to: MoveTo,
+    radius: f32,
+    distance: i32,
+    fleeing: bool,
+    threat: PhantomData<fn() -> T>
+}
+
+impl<T: Component> Flee<T> {
+    /// Run at some blocks per second from entities coming within a radius, to spots a distance in blocks away.
+    pub fn new(pathfinder: Pathfinder, speed: f32, radius: f32, distance: u32) -> Flee<T> {
+        return Flee { move_to: MoveTo::new(pathfinder, speed), radius, distance: distance as i32, fleeing: false, threat: PhantomData };
+    }
+
+    /// A spot away from a position, trying straight away first, then turning further aside each attempt.
+    fn pick_spot(&self, context: &mut AiContext, from: WorldPos) -> Option<BlockPos> {
+        let feet = feet(context.position);
+        let away = Vec3::new((context.position.x - from.x) as f32, 0.0, (context.position.z - from.z) as f32);
+        let base = if away.length() > 1e-3 { away.z.atan2(away.x) } else { context.rng.range_f32(0.0..std::f32::consts::TAU) };
+        for attempt in 0..SPOT_ATTEMPTS {
+            let spread = std::f32::consts::FRAC_PI_4 * attempt as f32 / SPOT_ATTEMPTS as f32;
+            let angle = base + if attempt % 2 == 0 { spread } else { -spread };
+            let (dx, dz) = ((angle.cos() * self.distance as f32).round() as i32, (angle.sin() * self.distance as f32).round() as i32);
+            if let Some(spot) = ground_near(self.move_to.pathfinder(), context, feet.offset(dx, 0, dz)) {
+                return Some(spot);
+            }
+        }
+        return None;
+    }
+}
+
+impl<T: Component> Behavior for Flee<T> {
+    fn tick(&mut self, context: &mut AiContext) -> Status {
+        // Once running, the threat has to be left a little further behind than where it scared the mob.
+        let radius = if self.fleeing { self.radius * 1.5 } else { self.radius };
+        let Some((_, threat)) = nearest::<T>(context, radius) else {
+            let fled = self.fleeing;
+            self.reset();
+            return if fled { Status::Success } else { Status::Failure };
+        };
+        if self.move_to.goal().is_none() {
+            let Some(spot) = self.pick_spot(context, threat) else {
+                self.reset();
+                return Status::Failure;
+            };
+            self.move_to.set_goal(spot);
+            self.fleeing = true;
+        }
+        return match self.move_to.tick(context) {
+            // Still too close, so another spot is picked next tick.
+            Status::Success | Status::Running => Status::Running,
+            Status::Failure => {
+                self.reset();
+                Status::Failure
+            }
+        };
+    }
+
+    fn reset(&mut self) {
+        self.move_to.reset();
+        self.fleeing = false;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,3 @@
+pub mod tree;
+pub mod behaviors;
+pub mod system;
\ No newline at end of file
@@ -0,0 +1,225 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use crate::engine::{
+    entity::{
+        kinematics::{Transform, Velocity},
+        schedule::{System, SystemAccess, SystemContext},
+        EntityId
+    },
+    job::system::JobSystem,
+    lod::{self, LodPolicy},
+    math::{coords::WorldPos, quat::Quat, vector::Vec3},
+    physics::RigidBody,
+    world::World
+};
+
+use super::tree::{BehaviorTree, Steering};
+
+/// Brains run by each job. Fewer spreads mobs over more threads, at the cost of more jobs to queue.
+pub const DEFAULT_BRAINS_PER_JOB: usize = 32;
+/// Upward speed of a mob's jump, enough to clear a block under RigidBody::DEFAULT_GRAVITY.
+pub const JUMP_SPEED: f32 = 9.0;
+
+/// The behavior tree of a mob. Locked only by the job running it, so a brain is never run twice at once.
+#[derive(Clone)]
+pub struct Brain {
+    tree: Arc<Mutex<BehaviorTree>>
+}
+
+impl Brain {
+    pub fn new(tree: BehaviorTree) -> Brain {
+        return Brain { tree: Arc::new(Mutex::new(tree)) };
+    }
+
+    pub fn tree(&self) -> &Arc<Mutex<BehaviorTree>> {
+        return &self.tree;
+    }
+}
+
+/// Rotation facing from one position towards another, turning only around the vertical axis to look up or down
+/// no further than straight, or None if they're the same.
+/// ```
+/// # use shared::engine::ai::system::facing;
+/// # use shared::engine::math::{coords::WorldPos, vector::Vec3};
+/// let rotation = facing(WorldPos::new(0.0, 0.0, 0.0), WorldPos::new(5.0, 0.0, 0.0)).unwrap();
+/// assert!((rotation * -Vec3::Z).approx_eq(Vec3::X, 1e-6));
+/// let rotation = facing(WorldPos::new(0.0, 0.0, 0.0), WorldPos::new(0.0, 1.0, 1.0)).unwrap();
+/// assert!((rotation * -Vec3::Z).approx_eq(Vec3::new(0.0, 1.0, 1.0).normalize(), 1e-6));
+/// assert_eq!(facing(WorldPos::new(1.0, 2.0, 3.0), WorldPos::new(1.0, 2.0, 3.0)), None);
+/// ```
+pub fn facing(from: WorldPos, to: WorldPos) -> Option<Quat> {
+    let (dx, dy, dz) = ((to.x - from.x) as f32, (to.y - from.y) as f32, (to.z - from.z) as f32);
+    let horizontal = (dx * dx + dz * dz).sqrt();
+    if horizontal < 1e-4 && dy.abs() < 1e-4 {
+        return None;
+    }
+    return Some(Quat::from_yaw_pitch((-dx).atan2(-dz), dy.atan2(horizontal)));
+}
+
+/// A brain's mob, copied out of the entity storages so brains can run without holding their locks.
+type BrainState = (EntityId, Arc<Mutex<BehaviorTree>>, WorldPos, bool);
+
+fn think(world: &World, brains: &[BrainState], tick: u64) -> Vec<(EntityId, WorldPos, Steering)> {
+    return brains.iter().map(|(id, tree, position, on_ground)| {
+        let (_, steering) = tree.lock().unwrap().tick(world, *id, *position, *on_ground, tick);
+        (*id, *position, steering)
+    }).collect();
+}
+
+/// Runs the brain of every mob with a Brain and a Transform, then steers each by setting its horizontal velocity
+/// towards where it's walking, jumping, and turning it to face where it looks. Moving it is left to the
+/// PhysicsSystem, or the KinematicsSystem for mobs without a RigidBody, so it runs before them.
+/// Brains are batched into jobs in id order, and each brain has its own generator, so mobs make the same choices
+/// however the jobs are scheduled.
+/// ```
+/// # use shared::engine::ai::{system::{AiSystem, Brain}, tree::{AiContext, BehaviorTree, Status}};
+/// # use shared::engine::entity::{kinematics::{KinematicsSystem, Transform, Velocity}, schedule::SystemSchedule};
+/// # use shared::engine::job::system::JobSystem;
+/// # use shared::engine::world::World;
+/// # use shared::engine::math::coords::WorldPos;
+/// # use std::sync::Arc;
+/// let jobs = Arc::new(JobSystem::new(2));
+/// let world = Arc::new(World::new());
+/// // Mobs that walk east forever.
+/// let mobs: Vec<_> = (0..100).map(|i| {
+///     let mob = world.entities().spawn();
+///     let east = |context: &mut AiContext| {
+///         context.steering.walk_to = Some(context.position + WorldPos::new(10.0, 0.0, 0.0));
+///         context.steering.speed = 2.0;
+///         return Status::Running;
+///     };
+///     assert!(world.entities().insert(mob, Brain::new(BehaviorTree::new(Box::new(east), i))).is_ok());
+///     world.entities().insert(mob, Transform::new(WorldPos::new(0.0, 0.0, i as f64))).unwrap();
+///     world.entities().insert(mob, Velocity::default()).unwrap();
+///     mob
+/// }).collect();
+///
+/// let mut schedule = SystemSchedule::new();
+/// schedule.add_system(AiSystem::new(jobs.clone()).with_brains_per_job(8));
+/// schedule.add_system(KinematicsSystem);
+/// for _ in 0..20 {
+///     schedule.run(&jobs, &world);
+/// }
+/// // A second at two blocks a second.
+/// let transform = world.entities().get::<Transform>(mobs[50]).unwrap();
+/// assert!((transform.position.x - 2.0).abs() < 1e-4);
+/// ```
+pub struct AiSystem {
+    jobs: Arc<JobSystem>,
+    brains_per_job: usize,
+    /// Thins out how often brains far from every Viewer think, if set. Mobs keep their steering between thoughts.
+    lod: Option<LodPolicy>,
+    tick: u64
+}
+
+impl AiSystem {
+    pub fn new(jobs: Arc<JobSystem>) -> AiSystem {
+        return AiSystem { jobs, brains_per_job: DEFAULT_BRAINS_PER_JOB, lod: None, tick: 0 };
+    }
+
+    /// Run brains less often the further they are from the nearest Viewer, and not at all past the last band.
+    /// ```
+    /// # use shared::engine::ai::{system::{AiSystem, Brain}, tree::{AiContext, BehaviorTree, Status}};
+    /// # use shared::engine::entity::{kinematics::Transform, schedule::SystemSchedule};
+    /// # use shared::engine::job::system::JobSystem;
+    /// # use shared::engine::lod::{LodPolicy, Viewer};
+    /// # use shared::engine::world::World;
+    /// # use shared::engine::math::coords::WorldPos;
+    /// # use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+    /// let jobs = Arc::new(JobSystem::new(2));
+    /// let world = Arc::new(World::new());
+    /// let viewer = world.entities().spawn();
+    /// world.entities().insert(viewer, Viewer).unwrap();
+    /// world.entities().insert(viewer, Transform::new(WorldPos::ORIGIN)).unwrap();
+    /// let thoughts: Vec<Arc<AtomicUsize>> = [10.0, 200.0, 1000.0].iter().enumerate().map(|(i, x)| {
+    ///     let count = Arc::new(AtomicUsize::new(0));
+    ///     let counted = count.clone();
+    ///     let think = move |_: &mut AiContext| {
+    ///         counted.fetch_add(1, Ordering::Relaxed);
+    ///         return Status::Running;
+    ///     };
+    ///     let mob = world.entities().spawn();
+    ///     assert!(world.entities().insert(mob, Brain::new(BehaviorTree::new(Box::new(think), i as u64))).is_ok());
+    ///     world.entities().insert(mob, Transform::new(WorldPos::new(*x, 0.0, 0.0))).unwrap();
+    ///     count
+    /// }).collect();
+    ///
+    /// let mut schedule = SystemSchedule::new();
+    /// schedule.add_system(AiSystem::new(jobs.clone()).with_lod(LodPolicy::for_view_distance(256.0)));
+    /// for _ in 0..100 {
+    ///     schedule.run(&jobs, &world);
+    /// }
+    /// let counts: Vec<usize> = thoughts.iter().map(|count| count.load(Ordering::Relaxed)).collect();
+    /// assert_eq!(counts, vec![100, 10, 0]);
+    /// ```
+    pub fn with_lod(mut self, policy: LodPolicy) -> AiSystem {
+        self.lod = Some(policy);
+        return self;
+    }
+
+    pub fn with_brains_per_job(mut self, brains: usize) -> AiSystem {
+        self.brains_per_job = brains.max(1);
+        return self;
+    }
+}
+
+impl System for AiSystem {
+    fn name(&self) -> &str {
+        return "ai";
+    }
+
+    fn access(&self) -> SystemAccess {
+        return SystemAccess::new().read::<Brain>().read::<RigidBody>().write::<Velocity>().write::<Transform>();
+    }
+
+    fn run(&mut self, context: &SystemContext) {
+        let tick = self.tick;
+        self.tick += 1;
+        let mut brains: Vec<BrainState> = Vec::new();
+        {
+            let bodies = context.entities().storage::<RigidBody>();
+            let bodies = bodies.read().unwrap();
+            context.query::<(&Brain, &Transform), ()>().for_each(|id, (brain, transform)| {
+                let on_ground = bodies.get(id).is_none_or(|body| body.on_ground);
+                brains.push((id, brain.tree.clone(), transform.position, on_ground));
+            });
+        }
+        if let Some(policy) = self.lod.as_ref() {
+            let viewers = lod::viewer_positions(context.entities());
+            brains.retain(|(id, _, position, _)| policy.should_update(lod::nearest_viewer_distance(&viewers, *position), tick, id.to_bits()));
+        }
+        brains.sort_unstable_by_key(|(id, ..)| *id);
+        let steered: Vec<_> = if brains.len() <= self.brains_per_job {
+            think(context.world, &brains, tick)
+        }
+        else {
+            let futures: Vec<_> = brains.chunks(self.brains_per_job).map(|batch| {
+                let (world, batch) = (context.world.clone(), batch.to_vec());
+                self.jobs.run_job(move || think(&world, &batch, tick))
+            }).collect();
+            futures.into_iter().flat_map(|future| future.wait()).collect()
+        };
+
+        let mut steered: HashMap<EntityId, (WorldPos, Steering)> = steered.into_iter().map(|(id, position, steering)| (id, (position, steering))).collect();
+        context.query::<(&mut Transform, &mut Velocity), ()>().for_each(|id, (mut transform, mut velocity)| {
+            let Some((position, steering)) = steered.remove(&id) else {
+                return;
+            };
+            let walk = steering.walk_to.map_or(Vec3::ZERO, |target| {
+                return Vec3::new((target.x - position.x) as f32, 0.0, (target.z - position.z) as f32).normalize_or_zero() * steering.speed;
+            });
+            let vertical = if steering.jump { JUMP_SPEED } else { velocity.linear.y };
+            let linear = Vec3::new(walk.x, vertical, walk.z);
+            // Only written when they change, so mobs standing still don't count as changed every tick.
+            if velocity.linear != linear {
+                velocity.linear = linear;
+            }
+            let target = steering.look_at.or(steering.walk_to.map(|target| WorldPos::new(target.x, position.y, target.z)));
+            if let Some(rotation) = target.and_then(|target| facing(position, target)) {
+                if transform.rotation != rotation {
+                    transform.rotation = rotation;
+                }
+            }
+        });
+    }
+}
\ No newline at end of file
@@ -0,0 +1,206 @@
+use crate::engine::{
+    entity::EntityId,
+    math::{coords::WorldPos, rng::WorldRng},
+    world::World
+};
+
+/// What a behavior did this tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Done, such as having arrived or seen what it looked for.
+    Success,
+    /// Couldn't be done, or there was nothing to do, such as with no path or nothing to flee from.
+    Failure,
+    /// Not done yet, to carry on next tick.
+    Running
+}
+
+/// How a mob wants to move and where it wants to look this tick. Behaviors set it, and it's applied to the mob's
+/// velocity and rotation once every brain has run.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Steering {
+    /// Point to walk straight towards, normally the next node of a path. Standing still without one.
+    pub walk_to: Option<WorldPos>,
+    /// Blocks per second to walk at.
+    pub speed: f32,
+    /// Jump this tick, such as to step up onto a block or over a gap.
+    pub jump: bool,
+    /// Point to face. Mobs face the way they walk without one.
+    pub look_at: Option<WorldPos>
+}
+
+/// What a behavior can see of the mob it runs for, and the steering it sets.
+pub struct AiContext<'a> {
+    pub world: &'a World,
+    pub entity: EntityId,
+    /// Where the mob's feet are, as of the start of the tick.
+    pub position: WorldPos,
+    /// Whether the mob stood on a block at the start of the tick. Always true for mobs without a RigidBody.
+    pub on_ground: bool,
+    /// Ticks the AI has run, for behaviors that wait.
+    pub tick: u64,
+    /// The brain's own generator, so mobs make the same choices however their jobs were scheduled.
+    pub rng: &'a mut WorldRng,
+    pub steering: Steering
+}
+
+/// A node of a behavior tree. Leaves act, such as walking somewhere or looking at a player, and composites decide
+/// which of their children run. Closures taking the context and returning a status are leaves, for conditions
+/// and one off actions.
+pub trait Behavior: Send {
+    fn tick(&mut self, context: &mut AiContext) -> Status;
+
+    /// Forget what was running, as when something more important interrupts it. Called on behaviors that
+    /// returned Running and weren't ticked again to finish.
+    fn reset(&mut self) {}
+}
+
+impl<F: FnMut(&mut AiContext) -> Status + Send> Behavior for F {
+    fn tick(&mut self, context: &mut AiContext) -> Status {
+        return self(context);
+    }
+}
+
+/// Runs its children in order until one fails, succeeding once they all have. A running child is carried on
+/// with next tick rather than starting from the first again.
+/// ```
+/// # use shared::engine::ai::tree::{AiContext, Behavior, BehaviorTree, Sequence, Status};
+/// # use shared::engine::world::World;
+/// # use shared::engine::math::coords::WorldPos;
+/// # use shared::engine::entity::EntityId;
+/// let world = World::new();
+/// let mut waited = 0;
+/// let wait_twice = move |_: &mut AiContext| {
+///     waited += 1;
+///     return if waited < 3 { Status::Running } else { Status::Success };
+/// };
+/// let jump = |context: &mut AiContext| {
+///     context.steering.jump = true;
+///     return Status::Success;
+/// };
+/// let mut tree = BehaviorTree::new(Box::new(Sequence::new(vec![Box::new(wait_twice), Box::new(jump)])), 1);
+/// let mob = EntityId::from_bits(1);
+/// assert_eq!(tree.tick(&world, mob, WorldPos::new(0.0, 0.0, 0.0), true, 0).0, Status::Running);
+/// assert_eq!(tree.tick(&world, mob, WorldPos::new(0.0, 0.0, 0.0), true, 1).0, Status::Running);
+/// let (status, steering) = tree.tick(&world, mob, WorldPos::new(0.0, 0.0, 0.0), true, 2);
+/// assert_eq!(status, Status::Success);
+/// assert!(steering.jump);
+/// ```
+pub struct Sequence {
+    children: Vec<Box<dyn Behavior>>,
+    current: usize
+}
+
+impl Sequence {
+    pub fn new(children: Vec<Box<dyn Behavior>>) -> Sequence {
+        return Sequence { children, current: 0 };
+    }
+}
+
+impl Behavior for Sequence {
+    fn tick(&mut self, context: &mut AiContext) -> Status {
+        while self.current < self.children.len() {
+            match self.children[self.current].tick(context) {
+                Status::Success => self.current += 1,
+                Status::Failure => {
+                    self.current = 0;
+                    return Status::Failure;
+                },
+                Status::Running => return Status::Running
+            }
+        }
+        self.current = 0;
+        return Status::Success;
+    }
+
+    fn reset(&mut self) {
+        if let Some(child) = self.children.get_mut(self.current) {
+            child.reset();
+        }
+        self.current = 0;
+    }
+}
+
+/// Runs the first of its children, in order of priority, that doesn't fail. Every tick starts again from the
+/// first, so a more important child, such as fleeing, interrupts a less important one that was running, such as
+/// wandering.
+/// ```
+/// # use shared::engine::ai::tree::{AiContext, BehaviorTree, Selector, Status};
+/// # use shared::engine::world::World;
+/// # use shared::engine::math::coords::WorldPos;
+/// # use shared::engine::entity::EntityId;
+/// let world = World::new();
+/// // Flee anything below y 0, or else stand still.
+/// let flee = |context: &mut AiContext| {
+///     if context.position.y >= 0.0 {
+///         return Status::Failure;
+///     }
+///     context.steering.walk_to = Some(WorldPos::new(0.0, 10.0, 0.0));
+///     return Status::Running;
+/// };
+/// let idle = |_: &mut AiContext| Status::Running;
+/// let mut tree = BehaviorTree::new(Box::new(Selector::new(vec![Box::new(flee), Box::new(idle)])), 1);
+/// let mob = EntityId::from_bits(1);
+/// assert_eq!(tree.tick(&world, mob, WorldPos::new(0.0, 5.0, 0.0), true, 0).1.walk_to, None);
+/// assert!(tree.tick(&world, mob, WorldPos::new(0.0, -5.0, 0.0), true, 1).1.walk_to.is_some());
+/// ```
+pub struct Selector {
+    children: Vec<Box<dyn Behavior>>,
+    /// Child that returned Running last tick.
+    running: Option<usize>
+}
+
+impl Selector {
+    pub fn new(children: Vec<Box<dyn Behavior>>) -> Selector {
+        return Selector { children, running: None };
+    }
+}
+
+impl Behavior for Selector {
+    fn tick(&mut self, context: &mut AiContext) -> Status {
+        for index in 0..self.children.len() {
+            let status = self.children[index].tick(context);
+            if status == Status::Failure {
+                continue;
+            }
+            if let Some(previous) = self.running.filter(|previous| *previous != index) {
+                self.children[previous].reset();
+            }
+            self.running = (status == Status::Running).then_some(index);
+            return status;
+        }
+        self.running = None;
+        return Status::Failure;
+    }
+
+    fn reset(&mut self) {
+        if let Some(running) = self.running.take() {
+            self.children[running].reset();
+        }
+    }
+}
+
+/// A mob's behaviors, and the generator they make choices with.
+pub struct BehaviorTree {
+    root: Box<dyn Behavior>,
+    rng: WorldRng
+}
+
+impl BehaviorTree {
+    /// A tree choosing with a generator seeded from the seed given, such as the world seed mixed with the mob's id.
+    pub fn new(root: Box<dyn Behavior>, seed: u64) -> BehaviorTree {
+        return BehaviorTree { root, rng: WorldRng::new(seed) };
+    }
+
+    /// Run the tree for a mob, returning the root's status and how the mob wants to move.
+    pub fn tick(&mut self, world: &World, entity: EntityId, position: WorldPos, on_ground: bool, tick: u64) -> (Status, Steering) {
+        let mut context = AiContext { world, entity, position, on_ground, tick, rng: &mut self.rng, steering: Steering::default() };
+        let status = self.root.tick(&mut context);
+        return (status, context.steering);
+    }
+
+    /// Stop whatever was running, such as when the mob is teleported.
+    pub fn reset(&mut self) {
+        self.root.reset();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,64 @@
+use serde::{Serialize, Deserialize};
+
+use crate::engine::{block::{registry::{BlockRegistry, BlockRegistryError}, BlockId}, physics::shape::CollisionShape};
+
+use super::manager::Asset;
+
+/// A block added by a data file, saved as JSON, so blocks can be added without changing code.
+/// ```
+/// # use shared::engine::asset::{block::BlockDefinition, manager::Asset};
+/// # use shared::engine::block::registry::BlockRegistry;
+/// let glass = BlockDefinition::decode(br#"{ "name": "cube:glass", "model": "cube_all", "opaque": false }"#).unwrap();
+/// assert!(glass.solid && !glass.opaque);
+/// let mut registry = BlockRegistry::new();
+/// let id = glass.register(&mut registry).unwrap();
+/// assert_eq!(registry.name(id), Some("cube:glass"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockDefinition {
+    /// Registry name, such as cube:stone.
+    pub name: String,
+    /// Model asset the block is drawn with.
+    pub model: String,
+    /// Whether entities collide with it.
+    #[serde(default = "default_true")]
+    pub solid: bool,
+    /// Whether it hides the faces of blocks behind it and blocks light.
+    #[serde(default = "default_true")]
+    pub opaque: bool,
+    /// Light it gives off, from 0 to 15.
+    #[serde(default)]
+    pub light: u8
+}
+
+fn default_true() -> bool {
+    return true;
+}
+
+impl BlockDefinition {
+    /// Add the block to a registry before it's frozen, as a full cube if it's solid.
+    pub fn register(&self, registry: &mut BlockRegistry) -> Result<BlockId, BlockRegistryError> {
+        let shape = if self.solid { CollisionShape::Full } else { CollisionShape::Empty };
+        return registry.builder(&self.name).collision(shape).register();
+    }
+
+    /// Check values serde can't, such as light being at most 15.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.light > 15 {
+            return Err(format!("light {} is over 15", self.light));
+        }
+        return Ok(());
+    }
+}
+
+impl Asset for BlockDefinition {
+    const DIRECTORY: &'static str = "blocks";
+    const EXTENSION: &'static str = "json";
+
+    fn decode(data: &[u8]) -> Result<BlockDefinition, String> {
+        let definition: BlockDefinition = serde_json::from_slice(data).map_err(|error| error.to_string())?;
+        definition.validate()?;
+        return Ok(definition);
+    }
+}
\ No newline at end of file
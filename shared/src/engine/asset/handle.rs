@@ -0,0 +1,121 @@
+use std::{fmt, sync::{atomic::{AtomicU64, Ordering}, Arc, Condvar, Mutex}};
+
+/// Why an asset couldn't be loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetError {
+    /// Path of the asset within the assets directory.
+    pub path: String,
+    pub message: String
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "couldn't load asset {}: {}", self.path, self.message);
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AssetState {
+    Loading,
+    Loaded,
+    Failed
+}
+
+struct Slot<T> {
+    result: Mutex<Option<Result<Arc<T>, AssetError>>>,
+    /// Times the asset has been loaded or reloaded.
+    version: AtomicU64,
+    done: Condvar
+}
+
+/// An asset that may still be loading. Cloning the handle shares the same asset, so every request for the
+/// same asset gets a handle to one copy of it, which resolves for all of them once it's loaded.
+pub struct Handle<T> {
+    slot: Arc<Slot<T>>
+}
+
+impl<T> Handle<T> {
+    pub(crate) fn loading() -> Handle<T> {
+        return Handle { slot: Arc::new(Slot { result: Mutex::new(None), version: AtomicU64::new(0), done: Condvar::new() }) };
+    }
+
+    pub(crate) fn resolve(&self, result: Result<T, AssetError>) {
+        let mut current = self.slot.result.lock().unwrap();
+        *current = Some(result.map(Arc::new));
+        self.slot.version.fetch_add(1, Ordering::AcqRel);
+        drop(current);
+        self.slot.done.notify_all();
+    }
+
+    /// Replace the asset with a reloaded one. A failed reload of an asset that loaded keeps the old asset,
+    /// returning false, so a half saved file doesn't take a working texture away.
+    pub(crate) fn reload(&self, result: Result<T, AssetError>) -> bool {
+        let mut current = self.slot.result.lock().unwrap();
+        if result.is_err() && matches!(*current, Some(Ok(_))) {
+            return false;
+        }
+        *current = Some(result.map(Arc::new));
+        self.slot.version.fetch_add(1, Ordering::AcqRel);
+        return true;
+    }
+
+    /// Times the asset has been loaded, starting at 0 while it first loads. Things built from an asset, such as
+    /// a texture atlas, can keep the version they were built from to tell when they need rebuilding.
+    pub fn version(&self) -> u64 {
+        return self.slot.version.load(Ordering::Acquire);
+    }
+
+    pub fn state(&self) -> AssetState {
+        return match self.slot.result.lock().unwrap().as_ref() {
+            None => AssetState::Loading,
+            Some(Ok(_)) => AssetState::Loaded,
+            Some(Err(_)) => AssetState::Failed
+        };
+    }
+
+    /// The asset, once it's loaded.
+    pub fn get(&self) -> Option<Arc<T>> {
+        return self.slot.result.lock().unwrap().as_ref().and_then(|result| result.as_ref().ok().cloned());
+    }
+
+    /// Why the asset failed to load, if it did.
+    pub fn error(&self) -> Option<AssetError> {
+        return self.slot.result.lock().unwrap().as_ref().and_then(|result| result.as_ref().err().cloned());
+    }
+
+    /// Block until the asset has loaded or failed to. Must not be called from an IO job, as the job loading
+    /// the asset may be queued behind it.
+    pub fn wait(&self) -> Result<Arc<T>, AssetError> {
+        let mut result = self.slot.result.lock().unwrap();
+        while result.is_none() {
+            result = self.slot.done.wait(result).unwrap();
+        }
+        return result.as_ref().unwrap().clone();
+    }
+
+    /// Number of handles to the asset, including the asset manager's own.
+    pub(crate) fn handle_count(&self) -> usize {
+        return Arc::strong_count(&self.slot);
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Handle<T> {
+        return Handle { slot: self.slot.clone() };
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    /// Whether both handles are to the same asset.
+    fn eq(&self, other: &Handle<T>) -> bool {
+        return Arc::ptr_eq(&self.slot, &other.slot);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "Handle({:?})", self.state());
+    }
+}
\ No newline at end of file
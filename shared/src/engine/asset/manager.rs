@@ -0,0 +1,211 @@
+use std::{any::{Any, TypeId}, collections::HashMap, fs, path::{Component, Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, time::SystemTime};
+
+use crate::engine::{event::bus::MessageBus, job::system::JobSystem};
+
+use super::handle::{AssetError, Handle};
+
+/// A kind of asset, decoded from a file in its own directory of the assets directory.
+pub trait Asset: Sized + Send + Sync + 'static {
+    /// Directory within the assets directory this kind of asset is in.
+    const DIRECTORY: &'static str;
+    /// Extension of the files, which isn't part of asset names.
+    const EXTENSION: &'static str;
+
+    /// Decode an asset from its file, returning why it couldn't be if it's invalid.
+    fn decode(data: &[u8]) -> Result<Self, String>;
+}
+
+/// Published on the asset manager's bus when a changed asset file has been reloaded, so whatever was built
+/// from the asset, such as a texture atlas or the client's block render data, can rebuild.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetReloaded {
+    /// Directory of the asset's kind, from Asset::DIRECTORY.
+    pub directory: &'static str,
+    pub name: String,
+    /// Why the changed file couldn't be loaded. The asset loaded before it changed is kept.
+    pub error: Option<AssetError>
+}
+
+/// Modification time and size of an asset's file when it was last read. None if it couldn't be read.
+type FileStamp = Option<(SystemTime, u64)>;
+
+fn file_stamp(path: &Path) -> FileStamp {
+    let metadata = fs::metadata(path).ok()?;
+    return Some((metadata.modified().ok()?, metadata.len()));
+}
+
+/// An asset requested from the manager, of any type.
+trait Entry: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn is_unused(&self) -> bool;
+
+    /// Reload the asset on an IO job if its file changed since it was read. Returns whether it's reloading.
+    fn reload_if_changed(self: Arc<Self>, io: &JobSystem, events: Option<Arc<MessageBus>>) -> bool;
+}
+
+struct AssetEntry<T: Asset> {
+    name: String,
+    handle: Handle<T>,
+    /// The asset's file, within the assets directory. None if its name was invalid.
+    relative: Option<PathBuf>,
+    path: PathBuf,
+    stamp: Mutex<FileStamp>,
+    /// Loading or reloading, so a file changing again mid load doesn't queue a second one.
+    busy: AtomicBool
+}
+
+impl<T: Asset> AssetEntry<T> {
+    /// Read and decode the asset's file, noting its stamp first, so a change while it's read is seen next check.
+    fn read(&self) -> Result<T, AssetError> {
+        *self.stamp.lock().unwrap() = file_stamp(&self.path);
+        let relative = self.relative.as_ref().unwrap();
+        return fs::read(&self.path)
+            .map_err(|error| error.to_string())
+            .and_then(|data| T::decode(&data))
+            .map_err(|message| AssetError { path: relative.to_string_lossy().replace('\\', "/"), message });
+    }
+}
+
+impl<T: Asset> Entry for AssetEntry<T> {
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn is_unused(&self) -> bool {
+        return self.handle.handle_count() == 1 && !self.busy.load(Ordering::Acquire);
+    }
+
+    fn reload_if_changed(self: Arc<Self>, io: &JobSystem, events: Option<Arc<MessageBus>>) -> bool {
+        if self.relative.is_none() || self.busy.load(Ordering::Acquire) || file_stamp(&self.path) == *self.stamp.lock().unwrap() {
+            return false;
+        }
+        self.busy.store(true, Ordering::Release);
+        io.run_job(move || {
+            let result = self.read();
+            let error = result.as_ref().err().cloned();
+            self.handle.reload(result);
+            self.busy.store(false, Ordering::Release);
+            if let Some(events) = events.as_ref() {
+                events.publish(AssetReloaded { directory: T::DIRECTORY, name: self.name.clone(), error });
+            }
+        });
+        return true;
+    }
+}
+
+/// Loads assets from an assets directory on IO jobs, so reading and decoding them never stalls the caller.
+/// Each asset is loaded once: requesting an asset already loaded or loading returns a handle to the same one.
+/// Assets are named by their path within their kind's directory, without the extension,
+/// so the texture "blocks/stone" is read from textures/blocks/stone.png.
+/// ```
+/// # use shared::engine::asset::{AssetManager, handle::AssetState, block::BlockDefinition};
+/// # use shared::engine::job::system::JobSystem;
+/// # use std::sync::Arc;
+/// let directory = std::env::temp_dir().join(format!("asset_manager_doctest_{}", std::process::id()));
+/// std::fs::create_dir_all(directory.join("blocks")).unwrap();
+/// std::fs::write(directory.join("blocks/stone.json"), r#"{ "name": "cube:stone", "model": "cube" }"#).unwrap();
+/// let assets = AssetManager::new(&directory, Arc::new(JobSystem::new(1)));
+///
+/// let stone = assets.load::<BlockDefinition>("stone");
+/// assert_eq!(assets.load::<BlockDefinition>("stone"), stone);
+/// assert_eq!(stone.wait().unwrap().name, "cube:stone");
+/// let missing = assets.load::<BlockDefinition>("dirt");
+/// assert!(missing.wait().is_err());
+/// assert_eq!(missing.state(), AssetState::Failed);
+/// assert!(assets.load::<BlockDefinition>("../secrets").wait().is_err());
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct AssetManager {
+    directory: PathBuf,
+    io: Arc<JobSystem>,
+    events: Option<Arc<MessageBus>>,
+    /// Every asset requested, by type and name.
+    assets: Mutex<HashMap<(TypeId, String), Arc<dyn Entry>>>
+}
+
+impl AssetManager {
+    pub fn new(directory: &Path, io: Arc<JobSystem>) -> AssetManager {
+        return AssetManager { directory: directory.to_path_buf(), io, events: None, assets: Mutex::new(HashMap::new()) };
+    }
+
+    /// Publish AssetReloaded on a bus whenever reload_changed() reloads an asset.
+    pub fn with_events(mut self, events: Arc<MessageBus>) -> AssetManager {
+        self.events = Some(events);
+        return self;
+    }
+
+    pub fn directory(&self) -> &Path {
+        return &self.directory;
+    }
+
+    /// Path of an asset's file within the assets directory, or None if its name would leave its directory.
+    fn relative_path<T: Asset>(name: &str) -> Option<PathBuf> {
+        if name.is_empty() || !Path::new(name).components().all(|component| matches!(component, Component::Normal(_))) {
+            return None;
+        }
+        return Some(PathBuf::from(T::DIRECTORY).join(format!("{}.{}", name, T::EXTENSION)));
+    }
+
+    fn entry<T: Asset>(entry: &Arc<dyn Entry>) -> &AssetEntry<T> {
+        return entry.as_any().downcast_ref::<AssetEntry<T>>().unwrap();
+    }
+
+    /// Start loading an asset, or get the handle of the one already requested.
+    pub fn load<T: Asset>(&self, name: &str) -> Handle<T> {
+        let mut assets = self.assets.lock().unwrap();
+        if let Some(entry) = assets.get(&(TypeId::of::<T>(), name.to_string())) {
+            return AssetManager::entry::<T>(entry).handle.clone();
+        }
+        let relative = AssetManager::relative_path::<T>(name);
+        let path = self.directory.join(relative.as_deref().unwrap_or(Path::new("")));
+        let entry = Arc::new(AssetEntry { name: name.to_string(), handle: Handle::loading(), relative, path, stamp: Mutex::new(None), busy: AtomicBool::new(true) });
+        assets.insert((TypeId::of::<T>(), name.to_string()), entry.clone());
+        drop(assets);
+
+        let handle = entry.handle.clone();
+        if entry.relative.is_none() {
+            handle.resolve(Err(AssetError { path: name.to_string(), message: "asset names must stay within their directory".to_string() }));
+            entry.busy.store(false, Ordering::Release);
+            return handle;
+        }
+        self.io.run_job(move || {
+            entry.handle.resolve(entry.read());
+            entry.busy.store(false, Ordering::Release);
+        });
+        return handle;
+    }
+
+    /// Handle of an asset already requested.
+    pub fn get<T: Asset>(&self, name: &str) -> Option<Handle<T>> {
+        return self.assets.lock().unwrap().get(&(TypeId::of::<T>(), name.to_string()))
+            .map(|entry| AssetManager::entry::<T>(entry).handle.clone());
+    }
+
+    /// Reload every asset whose file changed since it was read, including ones that failed to load because their
+    /// file was missing or broken. Handles keep working and return the reloaded asset once it's loaded, and
+    /// AssetReloaded is published for each. Checks the file of every asset, so is called every second or so
+    /// rather than every frame. Returns the number of assets being reloaded.
+    pub fn reload_changed(&self) -> usize {
+        let entries: Vec<Arc<dyn Entry>> = self.assets.lock().unwrap().values().cloned().collect();
+        return entries.into_iter().filter(|entry| entry.clone().reload_if_changed(&self.io, self.events.clone())).count();
+    }
+
+    /// Forget loaded assets of a type nothing else has a handle to, freeing them.
+    /// Returns the number of assets freed. Assets still loading are kept.
+    pub fn unload_unused<T: Asset>(&self) -> usize {
+        let mut assets = self.assets.lock().unwrap();
+        let before = assets.len();
+        assets.retain(|(type_id, _), entry| *type_id != TypeId::of::<T>() || !entry.is_unused());
+        return before - assets.len();
+    }
+
+    /// Number of assets of every type requested and not unloaded.
+    pub fn len(&self) -> usize {
+        return self.assets.lock().unwrap().len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,8 @@
+pub mod handle;
+pub mod manager;
+pub mod texture;
+pub mod model;
+pub mod block;
+pub mod pack;
+
+pub use manager::AssetManager;
\ No newline at end of file
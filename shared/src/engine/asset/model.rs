@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::engine::math::direction::Direction;
+
+use super::manager::Asset;
+
+/// A box of a model, in 16ths of a block.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelElement {
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+    /// Texture variable of each face drawn. Faces left out aren't drawn.
+    pub faces: BTreeMap<Direction, String>
+}
+
+/// Shape of a block or item, made of boxes, saved as JSON.
+/// ```
+/// # use shared::engine::asset::{manager::Asset, model::Model};
+/// # use shared::engine::math::direction::Direction;
+/// let model = Model::decode(br#"{
+///     "textures": { "side": "blocks/log_side", "end": "blocks/log_top" },
+///     "elements": [{ "from": [0, 0, 0], "to": [16, 16, 16], "faces": { "PosY": "end", "NegX": "side" } }]
+/// }"#).unwrap();
+/// assert_eq!(model.face_texture(0, Direction::PosY), Some("blocks/log_top"));
+/// assert_eq!(model.face_texture(0, Direction::NegY), None);
+/// assert!(Model::decode(br#"{ "elements": [{ "from": [0, 0, 0], "to": [16, 16, 16], "faces": { "PosY": "missing" } }] }"#).is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Model {
+    /// Texture each variable stands for, by name.
+    pub textures: BTreeMap<String, String>,
+    pub elements: Vec<ModelElement>
+}
+
+impl Model {
+    /// Name of the texture drawn on a face of an element.
+    pub fn face_texture(&self, element: usize, face: Direction) -> Option<&str> {
+        let variable = self.elements.get(element)?.faces.get(&face)?;
+        return self.textures.get(variable).map(String::as_str);
+    }
+}
+
+impl Asset for Model {
+    const DIRECTORY: &'static str = "models";
+    const EXTENSION: &'static str = "json";
+
+    fn decode(data: &[u8]) -> Result<Model, String> {
+        let model: Model = serde_json::from_slice(data).map_err(|error| error.to_string())?;
+        for element in model.elements.iter() {
+            if let Some(variable) = element.faces.values().find(|variable| !model.textures.contains_key(*variable)) {
+                return Err(format!("texture variable {} isn't defined", variable));
+            }
+            if (0..3).any(|axis| element.from[axis] > element.to[axis]) {
+                return Err("element ends before it starts".to_string());
+            }
+        }
+        return Ok(model);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,288 @@
+use std::{fmt, fs, io, path::{Path, PathBuf}};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::engine::{
+    block::{registry::{BlockRegistry, BlockRegistryError}, BlockId},
+    config::ConfigFormat,
+    item::ItemRegistry,
+    recipe::{registry::RecipeRegistryError, RecipeDefinition, RecipeId, RecipeRegistry},
+    version::{ModInfo, Version},
+    worldgen::biome::{BiomeDefinition, BiomeId, BiomeRegistry, BiomeRegistryError}
+};
+
+use super::block::BlockDefinition;
+
+/// File name of a pack's manifest, as pack.toml or pack.json.
+pub const PACK_MANIFEST: &str = "pack";
+/// Directory of a world holding the content packs its server loads.
+pub const PACKS_DIRECTORY: &str = "packs";
+/// Directories in a pack holding a file per block, biome and recipe, in TOML or JSON.
+pub const BLOCK_DIRECTORY: &str = "blocks";
+pub const BIOME_DIRECTORY: &str = "biomes";
+pub const RECIPE_DIRECTORY: &str = "recipes";
+
+/// Why content packs couldn't be loaded or registered.
+#[derive(Debug)]
+pub enum PackError {
+    Io {
+        path: PathBuf,
+        error: io::Error
+    },
+    /// A file isn't valid TOML or JSON, or doesn't describe what its directory says it does.
+    Parse {
+        path: PathBuf,
+        message: String
+    },
+    /// A pack's content breaks the rules, such as adding a block outside its namespace.
+    Invalid {
+        pack: String,
+        message: String
+    },
+    Block(BlockRegistryError),
+    Biome(BiomeRegistryError),
+    Recipe(RecipeRegistryError)
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            PackError::Io { path, error } => write!(f, "couldn't read {}: {}", path.display(), error),
+            PackError::Parse { path, message } => write!(f, "couldn't parse {}: {}", path.display(), message),
+            PackError::Invalid { pack, message } => write!(f, "invalid content pack {}: {}", pack, message),
+            PackError::Block(error) => write!(f, "{}", error),
+            PackError::Biome(error) => write!(f, "{}", error),
+            PackError::Recipe(error) => write!(f, "{}", error)
+        };
+    }
+}
+
+impl std::error::Error for PackError {}
+
+impl From<BlockRegistryError> for PackError {
+    fn from(error: BlockRegistryError) -> PackError {
+        return PackError::Block(error);
+    }
+}
+
+impl From<BiomeRegistryError> for PackError {
+    fn from(error: BiomeRegistryError) -> PackError {
+        return PackError::Biome(error);
+    }
+}
+
+impl From<RecipeRegistryError> for PackError {
+    fn from(error: RecipeRegistryError) -> PackError {
+        return PackError::Recipe(error);
+    }
+}
+
+/// What a pack says about itself in its manifest.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackManifest {
+    /// Namespace of everything the pack adds, such as "ores" for ores:ruby.
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    /// Version worlds using the pack record, such as "1.2.0", so they refuse to load without a compatible one.
+    #[serde(default = "first_version")]
+    pub version: Version,
+    /// Packs load from lowest to highest priority, then by id, with later packs overriding earlier ones.
+    #[serde(default)]
+    pub priority: i32
+}
+
+fn first_version() -> Version {
+    return Version::new(1, 0, 0);
+}
+
+/// A block, biome or recipe definition, and the packs it came from.
+struct Entry<T> {
+    name: String,
+    /// Pack that first defined the name, whose namespace a new name must be in.
+    origin: String,
+    /// Pack whose definition won.
+    pack: String,
+    definition: T
+}
+
+/// Definitions merged in pack order. Overridden names keep their place, so their ids don't depend on which pack won.
+fn merge<T>(entries: &mut Vec<Entry<T>>, pack: &str, definitions: Vec<(String, T)>) {
+    for (name, definition) in definitions {
+        match entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => {
+                entry.pack = pack.to_string();
+                entry.definition = definition;
+            },
+            None => entries.push(Entry { name, origin: pack.to_string(), pack: pack.to_string(), definition })
+        }
+    }
+}
+
+fn namespace(name: &str) -> &str {
+    return name.split_once(':').map(|(namespace, _)| namespace).unwrap_or("");
+}
+
+fn parse<T: DeserializeOwned>(path: &Path) -> Result<T, PackError> {
+    let text = fs::read_to_string(path).map_err(|error| PackError::Io { path: path.to_path_buf(), error })?;
+    let parsed = match ConfigFormat::of_path(path) {
+        ConfigFormat::Toml => toml::from_str(&text).map_err(|error| error.to_string()),
+        ConfigFormat::Json => serde_json::from_str(&text).map_err(|error| error.to_string())
+    };
+    return parsed.map_err(|message| PackError::Parse { path: path.to_path_buf(), message });
+}
+
+/// TOML and JSON files in a directory, sorted by name. Empty if the directory doesn't exist.
+fn definition_files(directory: &Path) -> Result<Vec<PathBuf>, PackError> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(PackError::Io { path: directory.to_path_buf(), error })
+    };
+    let mut files: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| matches!(path.extension().and_then(|extension| extension.to_str()), Some("toml" | "json")))
+        .collect();
+    files.sort();
+    return Ok(files);
+}
+
+/// Definitions in one of a pack's directories, refusing a name defined twice in the same pack.
+fn read_definitions<T: DeserializeOwned>(pack: &str, directory: &Path, name: impl Fn(&T) -> &str, validate: impl Fn(&T) -> Result<(), String>) -> Result<Vec<(String, T)>, PackError> {
+    let mut definitions: Vec<(String, T)> = Vec::new();
+    for path in definition_files(directory)? {
+        let definition: T = parse(&path)?;
+        validate(&definition).map_err(|message| PackError::Parse { path: path.clone(), message })?;
+        let name = name(&definition).to_string();
+        if definitions.iter().any(|(existing, _)| *existing == name) {
+            return Err(PackError::Invalid { pack: pack.to_string(), message: format!("{} is defined twice", name) });
+        }
+        definitions.push((name, definition));
+    }
+    return Ok(definitions);
+}
+
+/// Blocks, biomes and recipes added by content packs, registered at startup so content can be added without changing
+/// code. Every directory in the packs directory is a pack, with a manifest and blocks, biomes and recipes directories.
+/// Packs may add content in their own namespace, and replace any block, biome or recipe registered before them,
+/// whether by the engine or a pack loaded earlier. Replacing a block changes its definition but keeps its id.
+/// ```no_run
+/// # use shared::engine::{asset::pack::ContentPacks, block::BlockRegistry, worldgen::biome::BiomeRegistry};
+/// # use std::path::Path;
+/// let (mut blocks, mut biomes) = (BlockRegistry::new(), BiomeRegistry::new());
+/// let content = ContentPacks::load(Path::new("packs")).unwrap().register(&mut blocks, &mut biomes).unwrap();
+/// println!("{} blocks and {} biomes from packs", content.blocks.len(), content.biomes.len());
+/// ```
+pub struct ContentPacks {
+    packs: Vec<PackManifest>,
+    blocks: Vec<Entry<BlockDefinition>>,
+    biomes: Vec<Entry<BiomeDefinition>>,
+    recipes: Vec<Entry<RecipeDefinition>>
+}
+
+/// What registering content packs added or replaced.
+pub struct PackContent {
+    /// Every block the packs define, with the definition that won.
+    pub blocks: Vec<(BlockId, BlockDefinition)>,
+    pub biomes: Vec<BiomeId>
+}
+
+impl ContentPacks {
+    /// Read every pack in a directory. No packs if it doesn't exist.
+    pub fn load(directory: &Path) -> Result<ContentPacks, PackError> {
+        let mut found = Vec::new();
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).filter(|path| path.is_dir()).collect(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(PackError::Io { path: directory.to_path_buf(), error })
+        };
+        for path in entries {
+            let manifest = ["toml", "json"].iter().map(|extension| path.join(PACK_MANIFEST).with_extension(extension)).find(|manifest| manifest.is_file());
+            let Some(manifest) = manifest else {
+                return Err(PackError::Invalid { pack: path.display().to_string(), message: "no pack.toml or pack.json".to_string() });
+            };
+            found.push((parse::<PackManifest>(&manifest)?, path));
+        }
+        found.sort_by(|(a, _), (b, _)| a.priority.cmp(&b.priority).then_with(|| a.id.cmp(&b.id)));
+
+        let mut packs = ContentPacks { packs: Vec::new(), blocks: Vec::new(), biomes: Vec::new(), recipes: Vec::new() };
+        for (manifest, path) in found {
+            if packs.packs.iter().any(|pack| pack.id == manifest.id) {
+                return Err(PackError::Invalid { pack: manifest.id, message: "another pack has the same id".to_string() });
+            }
+            let blocks = read_definitions(&manifest.id, &path.join(BLOCK_DIRECTORY), |block: &BlockDefinition| &block.name, BlockDefinition::validate)?;
+            let biomes = read_definitions(&manifest.id, &path.join(BIOME_DIRECTORY), |biome: &BiomeDefinition| &biome.name, |_| Ok(()))?;
+            let recipes = read_definitions(&manifest.id, &path.join(RECIPE_DIRECTORY), |recipe: &RecipeDefinition| &recipe.name, RecipeDefinition::validate)?;
+            merge(&mut packs.blocks, &manifest.id, blocks);
+            merge(&mut packs.biomes, &manifest.id, biomes);
+            merge(&mut packs.recipes, &manifest.id, recipes);
+            packs.packs.push(manifest);
+        }
+        return Ok(packs);
+    }
+
+    /// Packs in the order they're applied.
+    pub fn packs(&self) -> &[PackManifest] {
+        return &self.packs;
+    }
+
+    /// Each pack as a mod, to record in the version manifest of worlds using them.
+    pub fn mods(&self) -> Vec<ModInfo> {
+        return self.packs.iter().map(|pack| ModInfo { id: pack.id.clone(), version: pack.version }).collect();
+    }
+
+    /// Pack whose definition of a block, biome or recipe won. None if no pack defines it.
+    pub fn source(&self, name: &str) -> Option<&str> {
+        return self.blocks.iter().map(|entry| (&entry.name, &entry.pack))
+            .chain(self.biomes.iter().map(|entry| (&entry.name, &entry.pack)))
+            .chain(self.recipes.iter().map(|entry| (&entry.name, &entry.pack)))
+            .find(|(entry, _)| *entry == name)
+            .map(|(_, pack)| pack.as_str());
+    }
+
+    /// Register blocks, then biomes, which can use the blocks. The block registry must not be frozen yet.
+    pub fn register(&self, blocks: &mut BlockRegistry, biomes: &mut BiomeRegistry) -> Result<PackContent, PackError> {
+        let outside_namespace = |entry_name: &str, origin: &str| PackError::Invalid {
+            pack: origin.to_string(),
+            message: format!("{} isn't in the pack's namespace and doesn't replace anything", entry_name)
+        };
+        let mut content = PackContent { blocks: Vec::new(), biomes: Vec::new() };
+        for entry in self.blocks.iter() {
+            let id = match blocks.id(&entry.name) {
+                Some(id) => id,
+                None if namespace(&entry.name) == entry.origin => entry.definition.register(blocks)?,
+                None => return Err(outside_namespace(&entry.name, &entry.origin))
+            };
+            content.blocks.push((id, entry.definition.clone()));
+        }
+        for entry in self.biomes.iter() {
+            let biome = entry.definition.resolve(blocks).map_err(|message| PackError::Invalid { pack: entry.pack.clone(), message })?;
+            let id = match biomes.replace(biome.clone()) {
+                Some(id) => id,
+                None if namespace(&entry.name) == entry.origin => biomes.register(biome)?,
+                None => return Err(outside_namespace(&entry.name, &entry.origin))
+            };
+            content.biomes.push(id);
+        }
+        return Ok(content);
+    }
+
+    /// Register recipes, once every item they use is registered, returning the id of each recipe the packs define.
+    /// Like blocks and biomes, packs add recipes in their own namespace and replace any registered before them.
+    pub fn register_recipes(&self, items: &ItemRegistry, recipes: &mut RecipeRegistry) -> Result<Vec<RecipeId>, PackError> {
+        let mut ids = Vec::new();
+        for entry in self.recipes.iter() {
+            let recipe = entry.definition.resolve(items).map_err(|message| PackError::Invalid { pack: entry.pack.clone(), message })?;
+            let id = match recipes.replace(recipe.clone()) {
+                Some(id) => id,
+                None if namespace(&entry.name) == entry.origin => recipes.register(recipe)?,
+                None => return Err(PackError::Invalid {
+                    pack: entry.origin.clone(),
+                    message: format!("{} isn't in the pack's namespace and doesn't replace anything", entry.name)
+                })
+            };
+            ids.push(id);
+        }
+        return Ok(ids);
+    }
+}
\ No newline at end of file
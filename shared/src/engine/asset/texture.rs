@@ -0,0 +1,38 @@
+use super::manager::Asset;
+
+/// An image, with 8 bit RGBA pixels in rows from the top.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>
+}
+
+impl Texture {
+    /// RGBA of a pixel.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let index = ((y * self.width + x) * 4) as usize;
+        return self.pixels[index..index + 4].try_into().unwrap();
+    }
+}
+
+impl Asset for Texture {
+    const DIRECTORY: &'static str = "textures";
+    const EXTENSION: &'static str = "png";
+
+    /// Decode a PNG of any color type, converting it to 8 bit RGBA.
+    fn decode(data: &[u8]) -> Result<Texture, String> {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::normalize_to_color8() | png::Transformations::ALPHA);
+        let mut reader = decoder.read_info().map_err(|error| error.to_string())?;
+        let mut buffer = vec![0u8; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buffer).map_err(|error| error.to_string())?;
+        buffer.truncate(frame.buffer_size());
+        let pixels = match frame.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]]).collect(),
+            color_type => return Err(format!("unsupported color type {:?}", color_type))
+        };
+        return Ok(Texture { width: frame.width, height: frame.height, pixels });
+    }
+}
\ No newline at end of file
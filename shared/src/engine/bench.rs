@@ -0,0 +1,262 @@
+use std::{fmt, io, path::{Path, PathBuf}, sync::Arc, time::{Duration, Instant}};
+
+use serde_json::{json, Value};
+
+use crate::engine::{
+    block::AIR,
+    job::{future::JobFuture, system::JobSystem},
+    math::coords::{BlockPos, ChunkPos, CHUNK_SIZE},
+    mesh::greedy::{queue_world_mesh_job, OpacityFn},
+    save::region::{RegionFile, RegionPos, RegionStorage},
+    version::engine_version,
+    world::{loader::ChunkStorage, World},
+    worldgen::generator::{queue_generate_job, WorldGenerator}
+};
+
+/// Times each scenario is run and timed, after an untimed warmup run.
+pub const DEFAULT_BENCH_ITERATIONS: usize = 5;
+
+/// Work a benchmark times. Each run does exactly the same work, so runs, and reports from different commits,
+/// can be compared.
+#[derive(Clone)]
+pub enum Scenario {
+    /// Jobs that each keep a thread busy for some microseconds, timing how fast the job system gets through them.
+    Jobs { count: usize, micros: u64 },
+    /// Chunks generated at the surface of the columns nearest the origin, on every job thread.
+    Generate { generator: Arc<dyn WorldGenerator>, chunks: usize },
+    /// Every chunk stored in a region file meshed against its neighbours, on every job thread. The chunks are read
+    /// from the region directory once, before timing.
+    MeshRegion { directory: PathBuf, region: RegionPos }
+}
+
+impl Scenario {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Scenario::Jobs { .. } => "jobs",
+            Scenario::Generate { .. } => "generate",
+            Scenario::MeshRegion { .. } => "mesh"
+        };
+    }
+
+    /// What the scenario was given, to tell its reports apart.
+    pub fn parameters(&self) -> Value {
+        return match self {
+            Scenario::Jobs { count, micros } => json!({ "count": count, "micros": micros }),
+            Scenario::Generate { generator, chunks } => json!({ "generator": generator.name(), "chunks": chunks }),
+            Scenario::MeshRegion { directory, region } => json!({ "directory": directory.display().to_string(), "region": [region.x, region.y, region.z] })
+        };
+    }
+}
+
+impl fmt::Display for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Scenario::Jobs { count, micros } => write!(f, "{} jobs of {}µs", count, micros),
+            Scenario::Generate { generator, chunks } => write!(f, "generating {} {} chunks", chunks, generator.name()),
+            Scenario::MeshRegion { region, .. } => write!(f, "meshing region {}", region.file_name())
+        };
+    }
+}
+
+/// How long each run of a scenario took.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchReport {
+    pub scenario: &'static str,
+    pub parameters: Value,
+    /// Job threads the scenario ran on.
+    pub threads: usize,
+    /// Jobs or chunks each run got through.
+    pub units: usize,
+    pub runs: Vec<Duration>
+}
+
+impl BenchReport {
+    pub fn min(&self) -> Duration {
+        return self.runs.iter().copied().min().unwrap_or_default();
+    }
+
+    pub fn max(&self) -> Duration {
+        return self.runs.iter().copied().max().unwrap_or_default();
+    }
+
+    pub fn mean(&self) -> Duration {
+        return self.runs.iter().sum::<Duration>().checked_div(self.runs.len() as u32).unwrap_or_default();
+    }
+
+    /// The middle run, which a run slowed down by something else on the machine doesn't move.
+    pub fn median(&self) -> Duration {
+        let mut runs = self.runs.clone();
+        runs.sort();
+        return runs.get(runs.len() / 2).copied().unwrap_or_default();
+    }
+
+    /// Jobs or chunks per second in the median run.
+    pub fn throughput(&self) -> f64 {
+        let median = self.median().as_secs_f64();
+        return if median > 0.0 { self.units as f64 / median } else { 0.0 };
+    }
+
+    /// The report as JSON, with times in milliseconds, for scripts comparing commits.
+    /// ```
+    /// # use shared::engine::bench::BenchReport;
+    /// # use std::time::Duration;
+    /// let report = BenchReport {
+    ///     scenario: "jobs",
+    ///     parameters: serde_json::json!({ "count": 100 }),
+    ///     threads: 4,
+    ///     units: 100,
+    ///     runs: vec![Duration::from_millis(30), Duration::from_millis(10), Duration::from_millis(20)]
+    /// };
+    /// let json = report.to_json();
+    /// assert_eq!(json["median_ms"], 20.0);
+    /// assert_eq!(json["per_second"], 5000.0);
+    /// assert_eq!(json["runs_ms"].as_array().unwrap().len(), 3);
+    /// ```
+    pub fn to_json(&self) -> Value {
+        return json!({
+            "scenario": self.scenario,
+            "parameters": self.parameters,
+            "engine_version": engine_version().to_string(),
+            "threads": self.threads,
+            "units": self.units,
+            "runs_ms": self.runs.iter().map(|run| millis(*run)).collect::<Vec<_>>(),
+            "min_ms": millis(self.min()),
+            "median_ms": millis(self.median()),
+            "mean_ms": millis(self.mean()),
+            "max_ms": millis(self.max()),
+            "per_second": self.throughput()
+        });
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}: median {:.2}ms, min {:.2}ms, max {:.2}ms over {} runs on {} threads ({:.0}/s)",
+            self.scenario, millis(self.median()), millis(self.min()), millis(self.max()), self.runs.len(), self.threads, self.throughput());
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    return duration.as_secs_f64() * 1000.0;
+}
+
+/// Runs scenarios on a job system and times them. A run that hasn't warmed up, with threads asleep and caches
+/// cold, is left out of the report.
+/// ```
+/// # use shared::engine::bench::{Bench, Scenario};
+/// # use shared::engine::job::system::JobSystem;
+/// # use shared::engine::worldgen::generator::VoidGenerator;
+/// # use std::sync::Arc;
+/// let bench = Bench::new(Arc::new(JobSystem::new(2))).with_iterations(3);
+/// let report = bench.run(&Scenario::Jobs { count: 20, micros: 100 }).unwrap();
+/// assert_eq!((report.runs.len(), report.units, report.threads), (3, 20, 2));
+/// // Twenty jobs of 100µs on two threads can't take less than a millisecond.
+/// assert!(report.min().as_micros() >= 1000);
+///
+/// let report = bench.run(&Scenario::Generate { generator: Arc::new(VoidGenerator), chunks: 10 }).unwrap();
+/// assert_eq!(report.units, 10);
+/// ```
+pub struct Bench {
+    jobs: Arc<JobSystem>,
+    iterations: usize
+}
+
+impl Bench {
+    pub fn new(jobs: Arc<JobSystem>) -> Bench {
+        return Bench { jobs, iterations: DEFAULT_BENCH_ITERATIONS };
+    }
+
+    pub fn with_iterations(mut self, iterations: usize) -> Bench {
+        self.iterations = iterations.max(1);
+        return self;
+    }
+
+    pub fn run(&self, scenario: &Scenario) -> io::Result<BenchReport> {
+        let run: Box<dyn Fn() -> usize> = match scenario {
+            Scenario::Jobs { count, micros } => {
+                let (count, duration) = (*count, Duration::from_micros(*micros));
+                Box::new(move || self.run_jobs(count, duration))
+            }
+            Scenario::Generate { generator, chunks } => {
+                let positions = surface_chunks(generator.as_ref(), *chunks);
+                let generator = generator.clone();
+                Box::new(move || self.generate(&generator, &positions))
+            }
+            Scenario::MeshRegion { directory, region } => {
+                let world = Arc::new(load_region(directory, *region)?);
+                let opaque: Arc<OpacityFn> = Arc::new(|id| id != AIR);
+                Box::new(move || self.mesh(&world, &opaque))
+            }
+        };
+        run();
+        let mut runs = Vec::with_capacity(self.iterations);
+        let mut units = 0;
+        for _ in 0..self.iterations {
+            let start = Instant::now();
+            units = run();
+            runs.push(start.elapsed());
+        }
+        let threads = self.jobs.debug_dump().thread_count;
+        return Ok(BenchReport { scenario: scenario.name(), parameters: scenario.parameters(), threads, units, runs });
+    }
+
+    fn run_jobs(&self, count: usize, duration: Duration) -> usize {
+        let futures: Vec<JobFuture<()>> = (0..count).map(|_| self.jobs.run_job(move || {
+            // Spinning rather than sleeping, so the job holds its thread the way real work does.
+            let start = Instant::now();
+            while start.elapsed() < duration {
+                std::hint::spin_loop();
+            }
+        })).collect();
+        futures.into_iter().for_each(|future| future.wait());
+        return count;
+    }
+
+    fn generate(&self, generator: &Arc<dyn WorldGenerator>, positions: &[ChunkPos]) -> usize {
+        let futures: Vec<_> = positions.iter().map(|pos| queue_generate_job(&self.jobs, generator.clone(), *pos)).collect();
+        futures.into_iter().for_each(|future| drop(future.wait()));
+        return positions.len();
+    }
+
+    fn mesh(&self, world: &Arc<World>, opaque: &Arc<OpacityFn>) -> usize {
+        let futures: Vec<_> = world.loaded_chunks().into_iter()
+            .filter_map(|pos| queue_world_mesh_job(&self.jobs, world.clone(), pos, opaque.clone()))
+            .collect();
+        let count = futures.len();
+        futures.into_iter().for_each(|future| drop(future.wait()));
+        return count;
+    }
+}
+
+/// A number of chunks at the surface of the columns nearest the origin, nearest first.
+fn surface_chunks(generator: &dyn WorldGenerator, count: usize) -> Vec<ChunkPos> {
+    let mut radius = 0;
+    while ((2 * radius + 1) * (2 * radius + 1)) < count as i32 {
+        radius += 1;
+    }
+    let mut columns: Vec<(i32, i32)> = (-radius..=radius).flat_map(|z| (-radius..=radius).map(move |x| (x, z))).collect();
+    columns.sort_by_key(|(x, z)| (x * x + z * z, *z, *x));
+    let half = CHUNK_SIZE / 2;
+    return columns.into_iter().take(count).map(|(x, z)| {
+        let surface = generator.surface_height(x * CHUNK_SIZE + half, z * CHUNK_SIZE + half).unwrap_or(0);
+        return ChunkPos::new(x, BlockPos::new(0, surface, 0).chunk().y, z);
+    }).collect();
+}
+
+/// Read every chunk stored in a region into a world of its own.
+fn load_region(directory: &Path, region: RegionPos) -> io::Result<World> {
+    let path = directory.join(region.file_name());
+    // Opening creates missing region files, which a benchmark shouldn't leave behind.
+    if !path.is_file() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} doesn't exist", path.display())));
+    }
+    let chunks = RegionFile::open(&path, region)?.chunks();
+    let storage = RegionStorage::new(directory)?;
+    let world = World::new();
+    for pos in chunks {
+        if let Some(chunk) = storage.read(pos)? {
+            world.insert_chunk(chunk);
+        }
+    }
+    return Ok(world);
+}
\ No newline at end of file
@@ -0,0 +1,12 @@
+/// Dense numeric runtime id of a block. Only stable within a session; saves persist the namespaced string id.
+pub type BlockId = u16;
+
+/// Id of air. The registry always registers air first, so empty chunks are all zeros.
+pub const AIR: BlockId = 0;
+
+pub mod remap;
+pub mod sign;
+pub mod state;
+pub mod registry;
+
+pub use registry::BlockRegistry;
\ No newline at end of file
@@ -0,0 +1,310 @@
+use std::{collections::HashMap, fmt};
+
+use crate::engine::physics::shape::CollisionShape;
+
+use super::{BlockId, AIR, remap::BlockIdRemap, state::{BlockType, Property, PropertyValue}};
+
+/// Namespaced id of air, which is always registered first.
+pub const AIR_NAME: &str = "cube:air";
+
+/// Reason a block could not be registered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockRegistryError {
+    /// Registration happens at startup, and the registry is frozen before any world is loaded.
+    Frozen,
+    /// Names must be "namespace:path", using only lowercase letters, digits, and underscores.
+    InvalidName(String),
+    Duplicate(String),
+    /// A default value that the property can't take, or a property the block doesn't have.
+    InvalidProperty(String),
+    /// Every numeric id is in use.
+    Full
+}
+
+impl fmt::Display for BlockRegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            BlockRegistryError::Frozen => write!(f, "blocks cannot be registered after the registry is frozen"),
+            BlockRegistryError::InvalidName(name) => write!(f, "invalid block id {}, expected namespace:path", name),
+            BlockRegistryError::Duplicate(name) => write!(f, "block {} is already registered", name),
+            BlockRegistryError::InvalidProperty(property) => write!(f, "invalid block property {}", property),
+            BlockRegistryError::Full => write!(f, "no more block ids are available")
+        };
+    }
+}
+
+impl std::error::Error for BlockRegistryError {}
+
+/// Maps namespaced string ids such as "cube:stone" to dense numeric BlockIds.
+/// Blocks are registered at startup, then the registry is frozen and shared immutably, so lookups never lock.
+/// Every state of a block (each combination of its property values) gets its own id, with a block's states
+/// occupying a contiguous range so state and property conversion is just arithmetic.
+/// Numeric ids are assigned in registration order and are only stable within a session.
+/// Saves persist saved_names(), and remap their palettes with remap_saved() on load.
+pub struct BlockRegistry {
+    blocks: Vec<BlockType>,
+    /// Index into blocks of every state id.
+    state_blocks: Vec<u32>,
+    state_names: Vec<String>,
+    /// Collision shape of every state id.
+    collision: Vec<CollisionShape>,
+    /// Both state names and plain block names, with plain names mapping to the default state.
+    ids: HashMap<String, BlockId>,
+    frozen: bool
+}
+
+/// Collision shape of one state of a block, from the block and the state's id.
+type StateCollisionFn<'a> = dyn Fn(&BlockType, BlockId) -> CollisionShape + 'a;
+
+/// Declares the properties of a block before registering it.
+pub struct BlockBuilder<'a> {
+    registry: &'a mut BlockRegistry,
+    name: String,
+    properties: Vec<Property>,
+    defaults: Vec<(String, PropertyValue)>,
+    collision: Box<StateCollisionFn<'a>>
+}
+
+impl<'a> BlockBuilder<'a> {
+    pub fn property(mut self, property: Property) -> Self {
+        self.properties.push(property);
+        return self;
+    }
+
+    /// Value of a property in the default state. Properties without a default use their first value.
+    pub fn default_value(mut self, property: &str, value: PropertyValue) -> Self {
+        self.defaults.push((property.to_string(), value));
+        return self;
+    }
+
+    /// Collision shape of every state of the block. Blocks are full cubes unless given another shape.
+    pub fn collision(self, shape: CollisionShape) -> Self {
+        return self.state_collision(move |_, _| shape.clone());
+    }
+
+    /// Collision shape of each state of the block, such as slabs with a top and bottom half.
+    /// ```
+    /// # use shared::engine::block::{BlockRegistry, state::{Property, PropertyValue}};
+    /// # use shared::engine::math::{aabb::Aabb, vector::Vec3};
+    /// # use shared::engine::physics::shape::CollisionShape;
+    /// let mut registry = BlockRegistry::new();
+    /// let slab = registry.builder("cube:stone_slab")
+    ///     .property(Property::boolean("top"))
+    ///     .state_collision(|block, id| match block.get(id, "top") {
+    ///         Some(PropertyValue::Bool(true)) => CollisionShape::Boxes(vec![Aabb::new(Vec3::new(0.0, 0.5, 0.0), Vec3::ONE)]),
+    ///         _ => CollisionShape::slab()
+    ///     })
+    ///     .register()
+    ///     .unwrap();
+    /// let top = registry.with(slab, "top", PropertyValue::Bool(true)).unwrap();
+    /// assert_eq!(registry.collision_shape(slab), &CollisionShape::slab());
+    /// assert_eq!(registry.collision_shape(top).boxes()[0].min.y, 0.5);
+    /// ```
+    pub fn state_collision<F>(mut self, shape: F) -> Self
+    where F: Fn(&BlockType, BlockId) -> CollisionShape + 'a {
+        self.collision = Box::new(shape);
+        return self;
+    }
+
+    /// Register the block, returning the id of its default state.
+    pub fn register(self) -> Result<BlockId, BlockRegistryError> {
+        return self.registry.register_block(self.name, self.properties, self.defaults, &*self.collision);
+    }
+}
+
+impl BlockRegistry {
+    /// Create a registry with only air registered, as id 0.
+    /// ```
+    /// # use shared::engine::block::{BlockRegistry, AIR};
+    /// let registry = BlockRegistry::new();
+    /// assert_eq!(registry.id("cube:air"), Some(AIR));
+    /// ```
+    pub fn new() -> BlockRegistry {
+        let mut registry = BlockRegistry { blocks: Vec::new(), state_blocks: Vec::new(), state_names: Vec::new(), collision: Vec::new(), ids: HashMap::new(), frozen: false };
+        let air = registry.builder(AIR_NAME).collision(CollisionShape::Empty).register().unwrap();
+        debug_assert_eq!(air, AIR);
+        return registry;
+    }
+
+    /// Register a block without properties, returning its numeric id.
+    /// ```
+    /// # use shared::engine::block::{BlockRegistry, registry::BlockRegistryError};
+    /// let mut registry = BlockRegistry::new();
+    /// let stone = registry.register("cube:stone").unwrap();
+    /// assert_eq!(registry.name(stone), Some("cube:stone"));
+    /// assert_eq!(registry.register("cube:stone"), Err(BlockRegistryError::Duplicate("cube:stone".to_string())));
+    /// assert!(registry.register("Stone").is_err());
+    /// registry.freeze();
+    /// assert_eq!(registry.register("cube:dirt"), Err(BlockRegistryError::Frozen));
+    /// ```
+    pub fn register(&mut self, name: &str) -> Result<BlockId, BlockRegistryError> {
+        return self.builder(name).register();
+    }
+
+    /// Start declaring a block with state properties.
+    /// ```
+    /// # use shared::engine::block::{BlockRegistry, state::{Property, PropertyValue}};
+    /// # use shared::engine::math::direction::Direction;
+    /// let mut registry = BlockRegistry::new();
+    /// let door = registry.builder("cube:door")
+    ///     .property(Property::direction("facing", &Direction::HORIZONTAL))
+    ///     .property(Property::boolean("open"))
+    ///     .default_value("facing", PropertyValue::Direction(Direction::PosX))
+    ///     .register()
+    ///     .unwrap();
+    /// assert_eq!(registry.block(door).unwrap().state_count(), 8);
+    /// assert_eq!(registry.get(door, "facing"), Some(PropertyValue::Direction(Direction::PosX)));
+    ///
+    /// let open = registry.with(door, "open", PropertyValue::Bool(true)).unwrap();
+    /// assert_eq!(registry.get(open, "open"), Some(PropertyValue::Bool(true)));
+    /// assert_eq!(registry.get(open, "facing"), Some(PropertyValue::Direction(Direction::PosX)));
+    /// assert_eq!(registry.state_name(open), "cube:door[facing=pos_x,open=true]");
+    /// assert_eq!(registry.id("cube:door[facing=pos_x,open=true]"), Some(open));
+    /// assert_eq!(registry.id("cube:door"), Some(door));
+    /// ```
+    pub fn builder(&mut self, name: &str) -> BlockBuilder<'_> {
+        return BlockBuilder { registry: self, name: name.to_string(), properties: Vec::new(), defaults: Vec::new(), collision: Box::new(|_, _| CollisionShape::Full) };
+    }
+
+    fn register_block(&mut self, name: String, properties: Vec<Property>, defaults: Vec<(String, PropertyValue)>, collision: &StateCollisionFn) -> Result<BlockId, BlockRegistryError> {
+        if self.frozen {
+            return Err(BlockRegistryError::Frozen);
+        }
+        if !is_valid_name(&name) {
+            return Err(BlockRegistryError::InvalidName(name));
+        }
+        if self.ids.contains_key(&name) {
+            return Err(BlockRegistryError::Duplicate(name));
+        }
+        let first_id = self.state_blocks.len();
+        let mut block = BlockType::new(name, first_id as BlockId, properties);
+        let state_count = block.state_count();
+        if first_id + state_count > BlockId::MAX as usize + 1 {
+            return Err(BlockRegistryError::Full);
+        }
+        for (property, value) in defaults {
+            match block.with(block.default_id, &property, value) {
+                Some(id) => block.default_id = id,
+                None => return Err(BlockRegistryError::InvalidProperty(format!("{}.{}={}", block.name, property, value)))
+            }
+        }
+
+        let block_index = self.blocks.len() as u32;
+        for offset in 0..state_count {
+            let id = (first_id + offset) as BlockId;
+            let state_name = block.state_name(id);
+            self.ids.insert(state_name.clone(), id);
+            self.state_names.push(state_name);
+            self.state_blocks.push(block_index);
+            self.collision.push(collision(&block, id));
+        }
+        self.ids.insert(block.name.clone(), block.default_id);
+        let default_id = block.default_id;
+        self.blocks.push(block);
+        return Ok(default_id);
+    }
+
+    /// Prevent further registration. Called once startup registration is complete, before worlds load.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+        self.blocks.shrink_to_fit();
+        self.state_blocks.shrink_to_fit();
+        self.state_names.shrink_to_fit();
+        self.collision.shrink_to_fit();
+        self.ids.shrink_to_fit();
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        return self.frozen;
+    }
+
+    /// Numeric id of a namespaced id. Plain block names give the default state,
+    /// while names with properties, as produced by state_name(), give that exact state.
+    pub fn id(&self, name: &str) -> Option<BlockId> {
+        return self.ids.get(name).copied();
+    }
+
+    /// Namespaced id of the block a state belongs to.
+    pub fn name(&self, id: BlockId) -> Option<&str> {
+        return self.block(id).map(|block| block.name());
+    }
+
+    /// Namespaced id including property values. Empty if the id is not registered.
+    pub fn state_name(&self, id: BlockId) -> &str {
+        return self.state_names.get(id as usize).map(|name| name.as_str()).unwrap_or("");
+    }
+
+    /// Boxes entities collide with in a state. Ids that aren't registered are full cubes.
+    pub fn collision_shape(&self, id: BlockId) -> &CollisionShape {
+        return self.collision.get(id as usize).unwrap_or(&CollisionShape::Full);
+    }
+
+    /// The block a state belongs to.
+    pub fn block(&self, id: BlockId) -> Option<&BlockType> {
+        let index = *self.state_blocks.get(id as usize)?;
+        return Some(&self.blocks[index as usize]);
+    }
+
+    /// Value of a property in a state. None if the block has no such property.
+    pub fn get(&self, id: BlockId, property: &str) -> Option<PropertyValue> {
+        return self.block(id)?.get(id, property);
+    }
+
+    /// The state with one property changed. None if the block has no such property or value.
+    pub fn with(&self, id: BlockId, property: &str, value: PropertyValue) -> Option<BlockId> {
+        return self.block(id)?.with(id, property, value);
+    }
+
+    /// Registered blocks, in registration order.
+    pub fn blocks(&self) -> &[BlockType] {
+        return &self.blocks;
+    }
+
+    /// Number of registered states, which is the number of ids in use.
+    pub fn len(&self) -> usize {
+        return self.state_blocks.len();
+    }
+
+    /// Always false, as air is always registered.
+    pub fn is_empty(&self) -> bool {
+        return self.state_blocks.is_empty();
+    }
+
+    /// Every state name, indexed by numeric id. Persisted with world data.
+    pub fn saved_names(&self) -> &[String] {
+        return &self.state_names;
+    }
+
+    /// Build a remap from the names a world was saved with to this registry's ids.
+    /// Blocks that are no longer registered become air.
+    /// ```
+    /// # use shared::engine::block::BlockRegistry;
+    /// let mut old = BlockRegistry::new();
+    /// let old_dirt = old.register("cube:dirt").unwrap();
+    /// old.register("somemod:ruby").unwrap();
+    ///
+    /// let mut current = BlockRegistry::new();
+    /// current.register("cube:stone").unwrap();
+    /// let dirt = current.register("cube:dirt").unwrap();
+    /// let remap = current.remap_saved(old.saved_names());
+    /// assert_eq!(remap.map(old_dirt), dirt);
+    /// assert_eq!(remap.missing()[0].name, "somemod:ruby");
+    /// ```
+    pub fn remap_saved(&self, saved_names: &[String]) -> BlockIdRemap {
+        return BlockIdRemap::from_saved_names(saved_names, |name| self.id(name), AIR);
+    }
+}
+
+impl Default for BlockRegistry {
+    fn default() -> BlockRegistry {
+        return BlockRegistry::new();
+    }
+}
+
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    let valid_part = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    return match name.split_once(':') {
+        Some((namespace, path)) => valid_part(namespace) && valid_part(path),
+        None => false
+    };
+}
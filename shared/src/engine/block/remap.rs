@@ -0,0 +1,109 @@
+use std::fmt;
+
+use super::BlockId;
+
+/// A block that was present when a world was saved, but is no longer registered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissingBlock {
+    pub saved_id: BlockId,
+    pub name: String
+}
+
+/// Translates block ids from the registry a world was saved with into the current registry.
+/// Saves persist the string id of every numeric id they use, so when mods add or remove blocks between sessions,
+/// the saved chunk palettes can be rewritten instead of silently turning into the wrong blocks.
+/// Blocks that no longer exist are mapped to a placeholder and reported.
+pub struct BlockIdRemap {
+    table: Vec<BlockId>,
+    missing: Vec<MissingBlock>,
+    placeholder: BlockId
+}
+
+impl BlockIdRemap {
+    /// Build a remap from the saved id table, where the index is the saved numeric id and the value is the namespaced id.
+    /// The lookup resolves a namespaced id against the current registry.
+    /// ```
+    /// # use shared::engine::block::remap::BlockIdRemap;
+    /// let saved = vec!["cube:air".to_string(), "cube:stone".to_string(), "somemod:ruby".to_string()];
+    /// let current = |name: &str| match name {
+    ///     "cube:air" => Some(0),
+    ///     "cube:stone" => Some(5),
+    ///     _ => None
+    /// };
+    /// let remap = BlockIdRemap::from_saved_names(&saved, current, 1);
+    /// assert_eq!(remap.map(1), 5);
+    /// assert_eq!(remap.map(2), 1);
+    /// assert_eq!(remap.missing()[0].name, "somemod:ruby");
+    /// ```
+    pub fn from_saved_names<F>(saved_names: &[String], lookup: F, placeholder: BlockId) -> BlockIdRemap
+    where F: Fn(&str) -> Option<BlockId> {
+        debug_assert!(saved_names.len() <= (BlockId::MAX as usize) + 1, "Too many saved block ids");
+        let mut table = Vec::with_capacity(saved_names.len());
+        let mut missing = Vec::new();
+        for (saved_id, name) in saved_names.iter().enumerate() {
+            match lookup(name) {
+                Some(id) => table.push(id),
+                None => {
+                    missing.push(MissingBlock { saved_id: saved_id as BlockId, name: name.clone() });
+                    table.push(placeholder);
+                }
+            }
+        }
+        return BlockIdRemap { table, missing, placeholder };
+    }
+
+    /// Translate a saved id into the current registry.
+    /// Ids beyond the saved table are treated as missing, and map to the placeholder.
+    pub fn map(&self, saved_id: BlockId) -> BlockId {
+        return match self.table.get(saved_id as usize) {
+            Some(id) => *id,
+            None => self.placeholder
+        };
+    }
+
+    /// Rewrite every entry of a chunk palette in place.
+    /// Because chunk data references palette indices rather than block ids, only the palette needs remapping.
+    /// ```
+    /// # use shared::engine::block::remap::BlockIdRemap;
+    /// let saved = vec!["cube:air".to_string(), "cube:dirt".to_string()];
+    /// let remap = BlockIdRemap::from_saved_names(&saved, |name| if name == "cube:dirt" { Some(9) } else { Some(0) }, 1);
+    /// let mut palette = vec![1, 0];
+    /// remap.remap_palette(&mut palette);
+    /// assert_eq!(palette, vec![9, 0]);
+    /// ```
+    pub fn remap_palette(&self, palette: &mut [BlockId]) {
+        for id in palette.iter_mut() {
+            *id = self.map(*id);
+        }
+    }
+
+    /// Check if the saved ids exactly match the current registry, in which case remapping can be skipped.
+    /// ```
+    /// # use shared::engine::block::remap::BlockIdRemap;
+    /// let saved = vec!["cube:air".to_string(), "cube:stone".to_string()];
+    /// let remap = BlockIdRemap::from_saved_names(&saved, |name| if name == "cube:air" { Some(0) } else { Some(1) }, 0);
+    /// assert!(remap.is_identity());
+    /// ```
+    pub fn is_identity(&self) -> bool {
+        return self.missing.is_empty() && self.table.iter().enumerate().all(|(saved_id, id)| saved_id == *id as usize);
+    }
+
+    /// Blocks the world was saved with that are no longer registered.
+    pub fn missing(&self) -> &[MissingBlock] {
+        return &self.missing;
+    }
+}
+
+impl fmt::Display for BlockIdRemap {
+    /// Human readable report of the missing blocks, suitable for the console or a load warning.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.missing.is_empty() {
+            return write!(f, "all {} saved block ids resolved", self.table.len());
+        }
+        writeln!(f, "{} of {} saved block ids are no longer registered and were replaced with the placeholder:", self.missing.len(), self.table.len())?;
+        for block in self.missing.iter() {
+            writeln!(f, "  {} (saved id {})", block.name, block.saved_id)?;
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,216 @@
+use std::{any::Any, fmt, io};
+
+use serde::{Serialize, Deserialize};
+
+use crate::engine::{
+    math::coords::BlockPos,
+    world::{block_entity::{BlockEntity, BlockEntityTypes}, World}
+};
+
+/// Number of lines of text a sign can display.
+pub const SIGN_LINES: usize = 4;
+/// Maximum characters per sign line. Counted in chars rather than bytes, so non-ASCII text isn't penalized.
+pub const SIGN_MAX_LINE_CHARS: usize = 24;
+/// Name of the sign block. Placing one gives it a SignBlockEntity to hold its text.
+pub const SIGN_BLOCK: &str = "cube:sign";
+/// Kind of the block entity holding a sign's text.
+pub const SIGN_KIND: &str = "cube:sign";
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// Reason sign text submitted by a client was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignTextError {
+    TooManyLines(usize),
+    LineTooLong { line: usize, chars: usize },
+    /// Control characters (including newlines) would break layout in the text renderer.
+    InvalidCharacter { line: usize, character: char }
+}
+
+impl fmt::Display for SignTextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            SignTextError::TooManyLines(count) => write!(f, "sign has {} lines, maximum is {}", count, SIGN_LINES),
+            SignTextError::LineTooLong { line, chars } =>
+                write!(f, "sign line {} has {} characters, maximum is {}", line, chars, SIGN_MAX_LINE_CHARS),
+            SignTextError::InvalidCharacter { line, character } =>
+                write!(f, "sign line {} contains invalid character {:?}", line, character)
+        };
+    }
+}
+
+impl std::error::Error for SignTextError {}
+
+/// Text shown on a sign, stored in its SignBlockEntity.
+/// Always valid once constructed, so the server can replicate it to other clients without checking again.
+/// Deserializing goes through the same validation, so text edited by a modified client is still rejected.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct SignText {
+    lines: [String; SIGN_LINES]
+}
+
+impl SignText {
+    /// Validate and store sign text. Missing lines are left empty.
+    /// ```
+    /// # use shared::engine::block::sign::{SignText, SignTextError};
+    /// let text = SignText::new(&["Welcome to", "Spawn"]).unwrap();
+    /// assert_eq!(text.lines()[1], "Spawn");
+    /// assert_eq!(text.lines()[3], "");
+    ///
+    /// let too_long = SignText::new(&["This line is far too long to fit"]);
+    /// assert_eq!(too_long, Err(SignTextError::LineTooLong { line: 0, chars: 32 }));
+    ///
+    /// let newline = SignText::new(&["a\nb"]);
+    /// assert_eq!(newline, Err(SignTextError::InvalidCharacter { line: 0, character: '\n' }));
+    /// ```
+    pub fn new<S: AsRef<str>>(lines: &[S]) -> Result<SignText, SignTextError> {
+        if lines.len() > SIGN_LINES {
+            return Err(SignTextError::TooManyLines(lines.len()));
+        }
+        let mut text = SignText::default();
+        for (index, line) in lines.iter().enumerate() {
+            let line = line.as_ref();
+            let chars = line.chars().count();
+            if chars > SIGN_MAX_LINE_CHARS {
+                return Err(SignTextError::LineTooLong { line: index, chars });
+            }
+            if let Some(character) = line.chars().find(|c| c.is_control()) {
+                return Err(SignTextError::InvalidCharacter { line: index, character });
+            }
+            text.lines[index] = line.to_string();
+        }
+        return Ok(text);
+    }
+
+    pub fn lines(&self) -> &[String; SIGN_LINES] {
+        return &self.lines;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.lines.iter().all(|line| line.is_empty());
+    }
+
+    /// Encode as each line, length prefixed.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for line in self.lines.iter() {
+            out.extend_from_slice(&(line.len() as u16).to_le_bytes());
+            out.extend_from_slice(line.as_bytes());
+        }
+        return out;
+    }
+
+    /// Decode text written by encode(), validating it as new() does.
+    /// ```
+    /// # use shared::engine::block::sign::SignText;
+    /// let text = SignText::new(&["", "Café"]).unwrap();
+    /// assert_eq!(SignText::decode(&text.encode()).unwrap(), text);
+    /// assert!(SignText::decode(&[3, 0, b'a']).is_err());
+    /// ```
+    pub fn decode(data: &[u8]) -> io::Result<SignText> {
+        let mut reader = data;
+        let mut lines = Vec::with_capacity(SIGN_LINES);
+        for _ in 0..SIGN_LINES {
+            if reader.len() < 2 {
+                return Err(invalid("Sign text ended early"));
+            }
+            let length = u16::from_le_bytes([reader[0], reader[1]]) as usize;
+            if reader.len() < 2 + length {
+                return Err(invalid("Sign text ended early"));
+            }
+            let line = std::str::from_utf8(&reader[2..2 + length]).map_err(|_| invalid("Sign text is not UTF-8"))?;
+            lines.push(line.to_string());
+            reader = &reader[2 + length..];
+        }
+        return SignText::new(&lines).map_err(|error| invalid(&error.to_string()));
+    }
+}
+
+impl TryFrom<Vec<String>> for SignText {
+    type Error = SignTextError;
+
+    fn try_from(lines: Vec<String>) -> Result<SignText, SignTextError> {
+        return SignText::new(&lines);
+    }
+}
+
+impl From<SignText> for Vec<String> {
+    fn from(text: SignText) -> Vec<String> {
+        return text.lines.into();
+    }
+}
+
+
+/// The text of a sign block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SignBlockEntity {
+    pub text: SignText
+}
+
+impl BlockEntity for SignBlockEntity {
+    fn kind(&self) -> &str {
+        return SIGN_KIND;
+    }
+
+    fn save(&self, out: &mut Vec<u8>) {
+        out.extend(self.text.encode());
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
+}
+
+/// Load sign block entities, saved with their chunks.
+pub fn register_block_entities(types: &mut BlockEntityTypes) {
+    types.register(SIGN_KIND, |data| Ok(Box::new(SignBlockEntity { text: SignText::decode(data)? })));
+}
+
+/// Text of the sign at a block. None if there's no sign there, or its chunk isn't loaded.
+pub fn sign_text(world: &World, block: BlockPos) -> Option<SignText> {
+    let entity = world.block_entity(block)?;
+    let entity = entity.lock().unwrap();
+    return entity.as_any().downcast_ref::<SignBlockEntity>().map(|sign| sign.text.clone());
+}
+
+/// Set the text of the sign at a block, giving the block a sign block entity if it has none.
+/// False if the block's chunk isn't loaded, or it holds another kind of block entity.
+/// ```
+/// # use shared::engine::block::sign::{self, SignBlockEntity, SignText};
+/// # use shared::engine::world::{block_entity::{BlockEntityMap, BlockEntityTypes}, chunk::Chunk, World};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// let text = SignText::new(&["Spawn", "this way"]).unwrap();
+/// assert!(sign::set_sign_text(&world, BlockPos::new(1, 2, 3), text.clone()));
+/// assert!(!sign::set_sign_text(&world, BlockPos::new(1, 200, 3), text.clone()));
+/// assert_eq!(sign::sign_text(&world, BlockPos::new(1, 2, 3)), Some(text.clone()));
+///
+/// // Signs are saved with their chunk.
+/// let mut types = BlockEntityTypes::new();
+/// sign::register_block_entities(&mut types);
+/// let saved = world.block_entities(ChunkPos::ORIGIN).unwrap().read().unwrap().encode();
+/// let loaded = BlockEntityMap::decode(&saved, ChunkPos::ORIGIN, &types).unwrap();
+/// let entity = loaded.get(BlockPos::new(1, 2, 3)).unwrap().lock().unwrap();
+/// assert_eq!(entity.as_any().downcast_ref::<SignBlockEntity>().unwrap().text, text);
+/// ```
+pub fn set_sign_text(world: &World, block: BlockPos, text: SignText) -> bool {
+    if let Some(entity) = world.block_entity(block) {
+        let mut entity = entity.lock().unwrap();
+        return match entity.as_any_mut().downcast_mut::<SignBlockEntity>() {
+            Some(sign) => {
+                sign.text = text;
+                true
+            },
+            None => false
+        };
+    }
+    return world.insert_block_entity(block, Box::new(SignBlockEntity { text })).is_ok();
+}
\ No newline at end of file
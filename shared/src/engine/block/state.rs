@@ -0,0 +1,151 @@
+use std::fmt;
+
+use crate::engine::math::direction::Direction;
+
+use super::BlockId;
+
+/// Value of a block state property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(u8),
+    Direction(Direction)
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            PropertyValue::Bool(value) => write!(f, "{}", value),
+            PropertyValue::Int(value) => write!(f, "{}", value),
+            PropertyValue::Direction(direction) => write!(f, "{}", match direction {
+                Direction::NegX => "neg_x",
+                Direction::PosX => "pos_x",
+                Direction::NegY => "neg_y",
+                Direction::PosY => "pos_y",
+                Direction::NegZ => "neg_z",
+                Direction::PosZ => "pos_z"
+            })
+        };
+    }
+}
+
+/// A named block state property and every value it can take, such as a door's "facing" or a fluid's "level".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Property {
+    name: String,
+    values: Vec<PropertyValue>
+}
+
+impl Property {
+    pub fn boolean(name: &str) -> Property {
+        return Property { name: name.to_string(), values: vec![PropertyValue::Bool(false), PropertyValue::Bool(true)] };
+    }
+
+    /// Integer property from min to max inclusive.
+    pub fn int(name: &str, min: u8, max: u8) -> Property {
+        debug_assert!(min <= max, "Property range is empty");
+        return Property { name: name.to_string(), values: (min..=max).map(PropertyValue::Int).collect() };
+    }
+
+    pub fn direction(name: &str, directions: &[Direction]) -> Property {
+        debug_assert!(!directions.is_empty(), "Property has no values");
+        return Property { name: name.to_string(), values: directions.iter().map(|d| PropertyValue::Direction(*d)).collect() };
+    }
+
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    pub fn values(&self) -> &[PropertyValue] {
+        return &self.values;
+    }
+
+    pub(crate) fn value_index(&self, value: PropertyValue) -> Option<usize> {
+        return self.values.iter().position(|v| *v == value);
+    }
+}
+
+/// A registered block and the contiguous range of ids its states occupy.
+/// Each combination of property values is its own BlockId, so chunks store states directly,
+/// and converting between a state and its property values is only integer arithmetic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockType {
+    pub(crate) name: String,
+    pub(crate) first_id: BlockId,
+    pub(crate) default_id: BlockId,
+    pub(crate) properties: Vec<Property>,
+    /// Distance in ids between consecutive values of each property. The last property varies fastest.
+    pub(crate) strides: Vec<usize>
+}
+
+impl BlockType {
+    pub(crate) fn new(name: String, first_id: BlockId, properties: Vec<Property>) -> BlockType {
+        let mut strides = vec![1; properties.len()];
+        for i in (0..properties.len().saturating_sub(1)).rev() {
+            strides[i] = strides[i + 1] * properties[i + 1].values.len();
+        }
+        return BlockType { name, first_id, default_id: first_id, properties, strides };
+    }
+
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    pub fn properties(&self) -> &[Property] {
+        return &self.properties;
+    }
+
+    pub fn first_id(&self) -> BlockId {
+        return self.first_id;
+    }
+
+    /// State used when the block is placed without any specific properties.
+    pub fn default_state(&self) -> BlockId {
+        return self.default_id;
+    }
+
+    /// Number of ids this block occupies.
+    pub fn state_count(&self) -> usize {
+        return self.properties.iter().map(|property| property.values.len()).product();
+    }
+
+    pub fn contains(&self, id: BlockId) -> bool {
+        return id >= self.first_id && ((id - self.first_id) as usize) < self.state_count();
+    }
+
+    fn property_index(&self, name: &str) -> Option<usize> {
+        return self.properties.iter().position(|property| property.name == name);
+    }
+
+    /// Value of a property in a state of this block.
+    pub fn get(&self, id: BlockId, property: &str) -> Option<PropertyValue> {
+        debug_assert!(self.contains(id), "State does not belong to this block");
+        let index = self.property_index(property)?;
+        let offset = (id - self.first_id) as usize;
+        let value_index = (offset / self.strides[index]) % self.properties[index].values.len();
+        return Some(self.properties[index].values[value_index]);
+    }
+
+    /// The state with one property changed. None if the block has no such property or value.
+    pub fn with(&self, id: BlockId, property: &str, value: PropertyValue) -> Option<BlockId> {
+        debug_assert!(self.contains(id), "State does not belong to this block");
+        let index = self.property_index(property)?;
+        let new_value = self.properties[index].value_index(value)?;
+        let offset = (id - self.first_id) as usize;
+        let stride = self.strides[index];
+        let old_value = (offset / stride) % self.properties[index].values.len();
+        return Some((offset - old_value * stride + new_value * stride) as BlockId + self.first_id);
+    }
+
+    /// Namespaced id with every property value, such as "cube:door[facing=pos_x,open=true]".
+    /// Blocks without properties are just their name.
+    pub fn state_name(&self, id: BlockId) -> String {
+        if self.properties.is_empty() {
+            return self.name.clone();
+        }
+        let values: Vec<String> = self.properties.iter()
+            .map(|property| format!("{}={}", property.name, self.get(id, &property.name).unwrap()))
+            .collect();
+        return format!("{}[{}]", self.name, values.join(","));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::engine::{block::{registry::BlockRegistry, BlockId}, math::coords::BlockPos};
+
+/// Namespace tried for block names typed without one, so "stone" means "cube:stone".
+pub const DEFAULT_NAMESPACE: &str = "cube";
+
+/// What the server knows that arguments are checked against and completed from.
+/// Implemented by the state commands run on.
+pub trait CommandEnvironment {
+    /// Names of the players online.
+    fn player_names(&self) -> Vec<String> {
+        return Vec::new();
+    }
+
+    /// Blocks that block arguments can name.
+    fn blocks(&self) -> Option<&BlockRegistry> {
+        return None;
+    }
+}
+
+impl CommandEnvironment for () {}
+
+/// Kind of value an argument takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgumentKind {
+    /// Whole number within a range, inclusive.
+    Integer { min: i64, max: i64 },
+    /// A single word.
+    Word,
+    /// The rest of the line, spaces included. Must be the last argument.
+    Text,
+    /// Name of a player online.
+    Player,
+    /// Namespaced block name, with or without properties.
+    Block,
+    /// Three block coordinates. Each may be relative to the source with ~, such as "~ ~1 ~-3".
+    Position
+}
+
+impl ArgumentKind {
+    pub const INTEGER: ArgumentKind = ArgumentKind::Integer { min: i64::MIN, max: i64::MAX };
+
+    /// Words of the command line the argument takes up.
+    pub fn token_count(&self) -> usize {
+        return match self {
+            ArgumentKind::Position => 3,
+            _ => 1
+        };
+    }
+
+    /// Name of the kind, as sent to clients for completing it.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            ArgumentKind::Integer { .. } => "integer",
+            ArgumentKind::Word => "word",
+            ArgumentKind::Text => "text",
+            ArgumentKind::Player => "player",
+            ArgumentKind::Block => "block",
+            ArgumentKind::Position => "position"
+        };
+    }
+}
+
+/// One coordinate of a position argument, either where it is or how far from the source it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coordinate {
+    Absolute(i32),
+    Relative(i32)
+}
+
+impl Coordinate {
+    /// Parse "5", "~" or "~-2".
+    /// ```
+    /// # use shared::engine::command::argument::Coordinate;
+    /// assert_eq!(Coordinate::parse("-12"), Ok(Coordinate::Absolute(-12)));
+    /// assert_eq!(Coordinate::parse("~"), Ok(Coordinate::Relative(0)));
+    /// assert_eq!(Coordinate::parse("~3").unwrap().resolve(10), 13);
+    /// assert!(Coordinate::parse("~~").is_err());
+    /// ```
+    pub fn parse(token: &str) -> Result<Coordinate, String> {
+        let invalid = || format!("invalid coordinate {}", token);
+        return match token.strip_prefix('~') {
+            Some("") => Ok(Coordinate::Relative(0)),
+            Some(offset) => offset.parse().map(Coordinate::Relative).map_err(|_| invalid()),
+            None => token.parse().map(Coordinate::Absolute).map_err(|_| invalid())
+        };
+    }
+
+    pub fn is_relative(&self) -> bool {
+        return matches!(self, Coordinate::Relative(_));
+    }
+
+    pub fn resolve(&self, origin: i32) -> i32 {
+        return match self {
+            Coordinate::Absolute(value) => *value,
+            Coordinate::Relative(offset) => origin.saturating_add(*offset)
+        };
+    }
+}
+
+/// A parsed argument.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgumentValue {
+    Integer(i64),
+    Word(String),
+    Text(String),
+    /// The player's name as they're known online, whatever case it was typed in.
+    Player(String),
+    Block(BlockId),
+    /// With relative coordinates already resolved against the source.
+    Position(BlockPos)
+}
+
+fn block_id(blocks: &BlockRegistry, name: &str) -> Option<BlockId> {
+    return blocks.id(name).or_else(|| match name.contains(':') {
+        true => None,
+        false => blocks.id(&format!("{}:{}", DEFAULT_NAMESPACE, name))
+    });
+}
+
+/// Parse an argument from the words it takes up, or the rest of the line for text.
+/// Relative positions resolve against origin, and are refused without one, such as from the console.
+pub(crate) fn parse_argument(kind: ArgumentKind, tokens: &[&str], origin: Option<BlockPos>, environment: &dyn CommandEnvironment) -> Result<ArgumentValue, String> {
+    return match kind {
+        ArgumentKind::Integer { min, max } => {
+            let value: i64 = tokens[0].parse().map_err(|_| format!("{} isn't a whole number", tokens[0]))?;
+            if value < min || value > max {
+                return Err(format!("{} must be from {} to {}", value, min, max));
+            }
+            Ok(ArgumentValue::Integer(value))
+        },
+        ArgumentKind::Word => Ok(ArgumentValue::Word(tokens[0].to_string())),
+        ArgumentKind::Text => Ok(ArgumentValue::Text(tokens[0].to_string())),
+        ArgumentKind::Player => environment.player_names().into_iter()
+            .find(|name| name.eq_ignore_ascii_case(tokens[0]))
+            .map(ArgumentValue::Player)
+            .ok_or_else(|| format!("no player {} is online", tokens[0])),
+        ArgumentKind::Block => environment.blocks()
+            .and_then(|blocks| block_id(blocks, tokens[0]))
+            .map(ArgumentValue::Block)
+            .ok_or_else(|| format!("unknown block {}", tokens[0])),
+        ArgumentKind::Position => {
+            let coordinates = [Coordinate::parse(tokens[0])?, Coordinate::parse(tokens[1])?, Coordinate::parse(tokens[2])?];
+            let origin = match (origin, coordinates.iter().any(Coordinate::is_relative)) {
+                (Some(origin), _) => origin,
+                (None, false) => BlockPos::ORIGIN,
+                (None, true) => return Err("relative coordinates need a position to be relative to".to_string())
+            };
+            Ok(ArgumentValue::Position(BlockPos::new(coordinates[0].resolve(origin.x), coordinates[1].resolve(origin.y), coordinates[2].resolve(origin.z))))
+        }
+    };
+}
+
+/// Values that complete a partly typed argument word.
+pub(crate) fn complete_argument(kind: ArgumentKind, partial: &str, origin: Option<BlockPos>, environment: &dyn CommandEnvironment) -> Vec<String> {
+    let mut suggestions: Vec<String> = match kind {
+        ArgumentKind::Player => environment.player_names(),
+        ArgumentKind::Block => environment.blocks()
+            .map(|blocks| blocks.blocks().iter().map(|block| block.name().to_string()).collect())
+            .unwrap_or_default(),
+        ArgumentKind::Position if origin.is_some() => vec!["~".to_string()],
+        _ => Vec::new()
+    };
+    let partial = partial.to_ascii_lowercase();
+    suggestions.retain(|suggestion| {
+        let suggestion = suggestion.to_ascii_lowercase();
+        let without_namespace = suggestion.split_once(':').map(|(_, path)| path).unwrap_or("");
+        suggestion.starts_with(&partial) || without_namespace.starts_with(&partial)
+    });
+    suggestions.sort();
+    return suggestions;
+}
\ No newline at end of file
@@ -0,0 +1,513 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::Serialize;
+
+use crate::engine::{block::BlockId, entity::replication::ClientId, math::coords::BlockPos};
+
+use super::argument::{complete_argument, parse_argument, ArgumentKind, ArgumentValue, CommandEnvironment};
+
+/// Who ran a command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandSender {
+    /// The server's own terminal.
+    Console,
+    /// An admin client on the server's admin port.
+    Remote,
+    /// A player typing a command in chat.
+    Player(ClientId)
+}
+
+/// How far a command source is trusted, from players with no special rights up to the server's owner.
+/// Each level may run every command of the levels below it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PermissionLevel {
+    Player,
+    /// May keep order, such as by muting and kicking players.
+    Moderator,
+    /// May change the game, such as by teleporting and editing the world. The lowest operator level.
+    GameMaster,
+    /// May manage players, such as by banning them and making them operators.
+    Admin,
+    /// May run anything, including stopping the server. The console and admin clients have this level.
+    Owner
+}
+
+impl PermissionLevel {
+    pub const OPERATOR: PermissionLevel = PermissionLevel::GameMaster;
+
+    /// The level numbered from 0 for players to 4 for owners, as saved.
+    /// ```
+    /// # use shared::engine::command::dispatcher::PermissionLevel;
+    /// assert_eq!(PermissionLevel::from_level(PermissionLevel::Admin.level()), Some(PermissionLevel::Admin));
+    /// assert_eq!(PermissionLevel::from_level(5), None);
+    /// ```
+    pub fn from_level(level: u8) -> Option<PermissionLevel> {
+        return match level {
+            0 => Some(PermissionLevel::Player),
+            1 => Some(PermissionLevel::Moderator),
+            2 => Some(PermissionLevel::GameMaster),
+            3 => Some(PermissionLevel::Admin),
+            4 => Some(PermissionLevel::Owner),
+            _ => None
+        };
+    }
+
+    pub fn level(self) -> u8 {
+        return self as u8;
+    }
+
+    pub fn name(self) -> &'static str {
+        return match self {
+            PermissionLevel::Player => "player",
+            PermissionLevel::Moderator => "moderator",
+            PermissionLevel::GameMaster => "game master",
+            PermissionLevel::Admin => "admin",
+            PermissionLevel::Owner => "owner"
+        };
+    }
+}
+
+/// Who ran a command, where from, and what they may run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandSource {
+    pub sender: CommandSender,
+    pub name: String,
+    /// What relative coordinates are relative to. The console and admin clients have none.
+    pub position: Option<BlockPos>,
+    pub permission: PermissionLevel
+}
+
+impl CommandSource {
+    pub fn console() -> CommandSource {
+        return CommandSource { sender: CommandSender::Console, name: "Server".to_string(), position: None, permission: PermissionLevel::Owner };
+    }
+
+    pub fn remote() -> CommandSource {
+        return CommandSource { sender: CommandSender::Remote, name: "Remote".to_string(), position: None, permission: PermissionLevel::Owner };
+    }
+
+    /// A player without special rights, standing at a position.
+    pub fn player(client: ClientId, name: &str, position: BlockPos) -> CommandSource {
+        return CommandSource { sender: CommandSender::Player(client), name: name.to_string(), position: Some(position), permission: PermissionLevel::Player };
+    }
+
+    pub fn with_permission(mut self, permission: PermissionLevel) -> CommandSource {
+        self.permission = permission;
+        return self;
+    }
+}
+
+/// The arguments a command was run with, parsed, and who ran it.
+pub struct CommandContext<'a> {
+    pub source: &'a CommandSource,
+    arguments: Vec<(String, ArgumentValue)>
+}
+
+impl CommandContext<'_> {
+    /// An argument's value. None if it's optional and was left out.
+    pub fn get(&self, name: &str) -> Option<&ArgumentValue> {
+        return self.arguments.iter().find(|(argument, _)| argument == name).map(|(_, value)| value);
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        return match self.get(name) {
+            Some(ArgumentValue::Integer(value)) => Some(*value),
+            _ => None
+        };
+    }
+
+    /// A word or text argument.
+    pub fn text(&self, name: &str) -> Option<&str> {
+        return match self.get(name) {
+            Some(ArgumentValue::Word(value)) | Some(ArgumentValue::Text(value)) => Some(value),
+            _ => None
+        };
+    }
+
+    pub fn player(&self, name: &str) -> Option<&str> {
+        return match self.get(name) {
+            Some(ArgumentValue::Player(value)) => Some(value),
+            _ => None
+        };
+    }
+
+    pub fn block(&self, name: &str) -> Option<BlockId> {
+        return match self.get(name) {
+            Some(ArgumentValue::Block(value)) => Some(*value),
+            _ => None
+        };
+    }
+
+    pub fn position(&self, name: &str) -> Option<BlockPos> {
+        return match self.get(name) {
+            Some(ArgumentValue::Position(value)) => Some(*value),
+            _ => None
+        };
+    }
+}
+
+/// Why a command didn't run, or failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommandError {
+    Unknown(String),
+    /// The source's permission level is too low for the command.
+    PermissionDenied(String),
+    /// Arguments were missing or left over.
+    Usage { usage: String, message: String },
+    InvalidArgument { argument: String, message: String },
+    /// The command ran, and returned why it couldn't do what was asked.
+    Failed(String)
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            CommandError::Unknown(name) => write!(f, "unknown command {}", name),
+            CommandError::PermissionDenied(name) => write!(f, "you don't have permission to run {}", name),
+            CommandError::Usage { usage, message } => write!(f, "{}, usage: {}", message, usage),
+            CommandError::InvalidArgument { argument, message } => write!(f, "invalid {}: {}", argument, message),
+            CommandError::Failed(message) => write!(f, "{}", message)
+        };
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Why a command couldn't be registered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommandRegistryError {
+    /// A command or alias with the name is already registered.
+    Duplicate(String),
+    /// Names must be lowercase letters, digits, dashes and underscores.
+    InvalidName(String),
+    /// Required arguments after optional ones, or text that isn't last.
+    InvalidArguments(String)
+}
+
+impl fmt::Display for CommandRegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            CommandRegistryError::Duplicate(name) => write!(f, "command {} is already registered", name),
+            CommandRegistryError::InvalidName(name) => write!(f, "invalid command name {}", name),
+            CommandRegistryError::InvalidArguments(name) => write!(f, "command {} has required arguments after optional ones or text that isn't last", name)
+        };
+    }
+}
+
+impl std::error::Error for CommandRegistryError {}
+
+/// An argument a command takes, sent to clients for completing it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ArgumentInfo {
+    pub name: String,
+    /// From ArgumentKind::name().
+    pub kind: String,
+    pub optional: bool
+}
+
+/// A command a source can run, sent to clients so they can complete command names and show usage locally.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CommandInfo {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub description: String,
+    pub usage: String,
+    pub arguments: Vec<ArgumentInfo>
+}
+
+type Handler<S> = Box<dyn Fn(&mut S, &CommandContext) -> Result<String, String> + Send + Sync>;
+
+struct Argument {
+    name: String,
+    kind: ArgumentKind,
+    optional: bool
+}
+
+struct Command<S> {
+    aliases: Vec<String>,
+    description: String,
+    arguments: Vec<Argument>,
+    permission: PermissionLevel,
+    handler: Handler<S>
+}
+
+impl<S> Command<S> {
+    fn usage(&self, name: &str) -> String {
+        let mut usage = name.to_string();
+        for argument in self.arguments.iter() {
+            let name = match argument.kind {
+                ArgumentKind::Position => format!("{}: x y z", argument.name),
+                _ => argument.name.clone()
+            };
+            usage += &match argument.optional {
+                true => format!(" [{}]", name),
+                false => format!(" <{}>", name)
+            };
+        }
+        return usage;
+    }
+}
+
+fn is_valid_command_name(name: &str) -> bool {
+    return !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+}
+
+/// Words of a command line and where each starts, so text arguments can take the rest of the line as typed.
+fn tokenize(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (index, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(begin)) => {
+                tokens.push((begin, &line[begin..index]));
+                start = None;
+            },
+            (false, None) => start = Some(index),
+            _ => ()
+        }
+    }
+    if let Some(begin) = start {
+        tokens.push((begin, &line[begin..]));
+    }
+    return tokens;
+}
+
+/// Adds a command to a dispatcher once it's given a handler.
+pub struct CommandBuilder<'a, S> {
+    dispatcher: &'a mut CommandDispatcher<S>,
+    name: String,
+    aliases: Vec<String>,
+    description: String,
+    arguments: Vec<Argument>,
+    permission: PermissionLevel
+}
+
+impl<'a, S> CommandBuilder<'a, S> {
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        return self;
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.aliases.push(alias.to_string());
+        return self;
+    }
+
+    pub fn argument(mut self, name: &str, kind: ArgumentKind) -> Self {
+        self.arguments.push(Argument { name: name.to_string(), kind, optional: false });
+        return self;
+    }
+
+    /// An argument that can be left out, along with every argument after it.
+    pub fn optional(mut self, name: &str, kind: ArgumentKind) -> Self {
+        self.arguments.push(Argument { name: name.to_string(), kind, optional: true });
+        return self;
+    }
+
+    /// Only let sources of at least a permission level run the command.
+    pub fn permission(mut self, permission: PermissionLevel) -> Self {
+        self.permission = permission;
+        return self;
+    }
+
+    /// Only let operators, the console and admin clients run the command.
+    pub fn operator_only(self) -> Self {
+        return self.permission(PermissionLevel::OPERATOR);
+    }
+
+    /// Register the command, run by a handler returning its output or why it failed.
+    pub fn executes<F>(self, handler: F) -> Result<(), CommandRegistryError>
+    where F: Fn(&mut S, &CommandContext) -> Result<String, String> + Send + Sync + 'static {
+        for name in std::iter::once(&self.name).chain(self.aliases.iter()) {
+            if !is_valid_command_name(name) {
+                return Err(CommandRegistryError::InvalidName(name.clone()));
+            }
+            if self.dispatcher.commands.contains_key(name) || self.dispatcher.aliases.contains_key(name) {
+                return Err(CommandRegistryError::Duplicate(name.clone()));
+            }
+        }
+        let optional_then_required = self.arguments.windows(2).any(|pair| pair[0].optional && !pair[1].optional);
+        let text_not_last = self.arguments.iter().rev().skip(1).any(|argument| argument.kind == ArgumentKind::Text);
+        if optional_then_required || text_not_last {
+            return Err(CommandRegistryError::InvalidArguments(self.name));
+        }
+        for alias in self.aliases.iter() {
+            self.dispatcher.aliases.insert(alias.clone(), self.name.clone());
+        }
+        let command = Command { aliases: self.aliases, description: self.description, arguments: self.arguments, permission: self.permission, handler: Box::new(handler) };
+        self.dispatcher.commands.insert(self.name, command);
+        return Ok(());
+    }
+}
+
+/// Every command the server knows, parsing command lines against each command's arguments before running it.
+/// The same commands serve the server's console, admin clients and players typing them in chat, with the
+/// source deciding what they may run and what relative positions are relative to.
+/// ```
+/// # use shared::engine::command::{CommandDispatcher, argument::{ArgumentKind, CommandEnvironment}, dispatcher::{CommandError, CommandSource, PermissionLevel}};
+/// # use shared::engine::math::coords::BlockPos;
+/// struct Server { players: Vec<String>, teleports: Vec<(String, BlockPos)> }
+/// impl CommandEnvironment for Server {
+///     fn player_names(&self) -> Vec<String> {
+///         return self.players.clone();
+///     }
+/// }
+/// let mut commands = CommandDispatcher::new();
+/// commands.register("tp")
+///     .description("Move a player.")
+///     .argument("player", ArgumentKind::Player)
+///     .argument("destination", ArgumentKind::Position)
+///     .operator_only()
+///     .executes(|server: &mut Server, context| {
+///         let (player, destination) = (context.player("player").unwrap(), context.position("destination").unwrap());
+///         server.teleports.push((player.to_string(), destination));
+///         return Ok(format!("teleported {}", player));
+///     }).unwrap();
+///
+/// let mut server = Server { players: vec!["Steve".to_string(), "Alex".to_string()], teleports: Vec::new() };
+/// let alex = CommandSource::player(2, "Alex", BlockPos::new(10, 64, -3)).with_permission(PermissionLevel::OPERATOR);
+/// assert_eq!(commands.execute(&mut server, &alex, "/tp steve ~ ~5 100"), Ok("teleported Steve".to_string()));
+/// assert_eq!(server.teleports, vec![("Steve".to_string(), BlockPos::new(10, 69, 100))]);
+/// assert_eq!(commands.complete(&server, &alex, "tp "), vec!["Alex", "Steve"]);
+/// assert!(matches!(commands.execute(&mut server, &CommandSource::console(), "tp alex ~ 0 0"), Err(CommandError::InvalidArgument { .. })));
+/// assert_eq!(commands.execute(&mut server, &alex.clone().with_permission(PermissionLevel::Player), "tp alex 0 0 0"), Err(CommandError::PermissionDenied("tp".to_string())));
+/// ```
+pub struct CommandDispatcher<S> {
+    commands: BTreeMap<String, Command<S>>,
+    /// Command each alias stands for.
+    aliases: BTreeMap<String, String>
+}
+
+impl<S> CommandDispatcher<S> {
+    pub fn new() -> CommandDispatcher<S> {
+        return CommandDispatcher { commands: BTreeMap::new(), aliases: BTreeMap::new() };
+    }
+
+    /// Start registering a command, which is added once its handler is given.
+    pub fn register(&mut self, name: &str) -> CommandBuilder<'_, S> {
+        return CommandBuilder { dispatcher: self, name: name.to_string(), aliases: Vec::new(), description: String::new(), arguments: Vec::new(), permission: PermissionLevel::Player };
+    }
+
+    /// The command a name or alias stands for, and its name.
+    fn find(&self, name: &str) -> Option<(&str, &Command<S>)> {
+        let name = name.to_ascii_lowercase();
+        let name = self.aliases.get(&name).unwrap_or(&name);
+        return self.commands.get_key_value(name).map(|(name, command)| (name.as_str(), command));
+    }
+
+    fn may_run(source: &CommandSource, command: &Command<S>) -> bool {
+        return source.permission >= command.permission;
+    }
+
+    /// How to type a command, such as "kick <player> [reason]".
+    pub fn usage(&self, name: &str) -> Option<String> {
+        return self.find(name).map(|(name, command)| command.usage(name));
+    }
+
+    /// Every command a source may run, by name.
+    pub fn commands(&self, source: &CommandSource) -> Vec<CommandInfo> {
+        return self.commands.iter().filter(|(_, command)| CommandDispatcher::may_run(source, command)).map(|(name, command)| CommandInfo {
+            name: name.clone(),
+            aliases: command.aliases.clone(),
+            description: command.description.clone(),
+            usage: command.usage(name),
+            arguments: command.arguments.iter().map(|argument| ArgumentInfo { name: argument.name.clone(), kind: argument.kind.name().to_string(), optional: argument.optional }).collect()
+        }).collect();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.commands.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.commands.is_empty();
+    }
+}
+
+impl<S: CommandEnvironment> CommandDispatcher<S> {
+    /// Parse and run a command line, with or without a leading slash, returning its output.
+    pub fn execute(&self, state: &mut S, source: &CommandSource, line: &str) -> Result<String, CommandError> {
+        let line = line.trim();
+        let line = line.strip_prefix('/').unwrap_or(line);
+        let tokens = tokenize(line);
+        let Some(&(_, typed_name)) = tokens.first() else {
+            return Err(CommandError::Unknown(String::new()));
+        };
+        let (name, command) = self.find(typed_name).ok_or_else(|| CommandError::Unknown(typed_name.to_string()))?;
+        if !CommandDispatcher::may_run(source, command) {
+            return Err(CommandError::PermissionDenied(name.to_string()));
+        }
+        let usage = |message: String| CommandError::Usage { usage: command.usage(name), message };
+
+        let mut arguments = Vec::new();
+        let mut next = 1;
+        for argument in command.arguments.iter() {
+            let count = argument.kind.token_count();
+            if next + count > tokens.len() {
+                if argument.optional {
+                    break;
+                }
+                return Err(usage(format!("missing {}", argument.name)));
+            }
+            let words: Vec<&str> = match argument.kind {
+                ArgumentKind::Text => vec![&line[tokens[next].0..]],
+                _ => tokens[next..next + count].iter().map(|(_, word)| *word).collect()
+            };
+            next = match argument.kind {
+                ArgumentKind::Text => tokens.len(),
+                _ => next + count
+            };
+            let value = parse_argument(argument.kind, &words, source.position, state)
+                .map_err(|message| CommandError::InvalidArgument { argument: argument.name.clone(), message })?;
+            arguments.push((argument.name.clone(), value));
+        }
+        if next < tokens.len() {
+            return Err(usage("too many arguments".to_string()));
+        }
+        let context = CommandContext { source, arguments };
+        return (command.handler)(state, &context).map_err(CommandError::Failed);
+    }
+
+    /// Ways to finish the last word of a partly typed command line, for tab completion.
+    /// Completes command names for the first word, and online players, block names and relative
+    /// coordinates for arguments of those kinds.
+    pub fn complete(&self, state: &S, source: &CommandSource, line: &str) -> Vec<String> {
+        let line = line.trim_start();
+        let line = line.strip_prefix('/').unwrap_or(line);
+        let mut words: Vec<&str> = tokenize(line).into_iter().map(|(_, word)| word).collect();
+        let partial = match line.is_empty() || line.ends_with(char::is_whitespace) {
+            true => "",
+            false => words.pop().unwrap()
+        };
+        let Some(&typed_name) = words.first() else {
+            let mut names: Vec<String> = self.commands.iter()
+                .filter(|(_, command)| CommandDispatcher::may_run(source, command))
+                .flat_map(|(name, command)| std::iter::once(name).chain(command.aliases.iter()))
+                .filter(|name| name.starts_with(&partial.to_ascii_lowercase()))
+                .cloned()
+                .collect();
+            names.sort();
+            return names;
+        };
+        let Some((_, command)) = self.find(typed_name).filter(|(_, command)| CommandDispatcher::may_run(source, command)) else {
+            return Vec::new();
+        };
+        let mut completed = words.len() - 1;
+        for argument in command.arguments.iter() {
+            if argument.kind == ArgumentKind::Text {
+                return Vec::new();
+            }
+            if completed < argument.kind.token_count() {
+                return complete_argument(argument.kind, partial, source.position, state);
+            }
+            completed -= argument.kind.token_count();
+        }
+        return Vec::new();
+    }
+}
+
+impl<S> Default for CommandDispatcher<S> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
\ No newline at end of file
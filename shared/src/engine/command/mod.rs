@@ -0,0 +1,4 @@
+pub mod argument;
+pub mod dispatcher;
+
+pub use dispatcher::CommandDispatcher;
\ No newline at end of file
@@ -0,0 +1,116 @@
+use std::io;
+
+use serde::{Serialize, Deserialize};
+
+/// Largest size data is allowed to decompress to, so corrupt or malicious data can't exhaust memory.
+/// Far larger than any encoded chunk.
+pub const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default zstd level for saves, which are written in the background so can afford a slower, smaller codec.
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// Codec used for chunk data, both in saves and when streamed to clients.
+/// Compressed data is tagged with its codec, so the codec can be changed without breaking existing data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compression {
+    None,
+    /// Fast enough to compress every chunk sent over the network.
+    Lz4,
+    /// Smaller output at a higher cost, from level 1 to 22.
+    Zstd { level: i32 }
+}
+
+impl Compression {
+    /// Codec for saves.
+    pub const SAVE: Compression = Compression::Zstd { level: DEFAULT_ZSTD_LEVEL };
+    /// Codec for chunk streaming.
+    pub const NETWORK: Compression = Compression::Lz4;
+
+    /// Byte identifying the codec in tagged data. Tags are part of the save format and must never change.
+    pub fn tag(&self) -> u8 {
+        return match self {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+            Compression::Zstd { .. } => 2
+        };
+    }
+
+    /// Codec for a tag. Zstd's level only matters when compressing, so the default is used.
+    pub fn from_tag(tag: u8) -> Option<Compression> {
+        return match tag {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Lz4),
+            2 => Some(Compression::Zstd { level: DEFAULT_ZSTD_LEVEL }),
+            _ => None
+        };
+    }
+
+    /// Compress without a tag. The reader must already know the codec.
+    pub fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        return match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => Ok(lz4_flex::block::compress_prepend_size(data)),
+            Compression::Zstd { level } => zstd::bulk::compress(data, *level)
+        };
+    }
+
+    /// Decompress untagged data compressed with this codec.
+    pub fn decompress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        return match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => {
+                if data.len() < 4 {
+                    return Err(invalid("truncated lz4 data"));
+                }
+                let size = u32::from_le_bytes(data[0..4].try_into().unwrap()) as usize;
+                if size > MAX_DECOMPRESSED_SIZE {
+                    return Err(invalid("lz4 data decompresses past the size limit"));
+                }
+                lz4_flex::block::decompress(&data[4..], size).map_err(|e| invalid(&e.to_string()))
+            },
+            Compression::Zstd { .. } => zstd::bulk::decompress(data, MAX_DECOMPRESSED_SIZE)
+        };
+    }
+
+    /// Compress, prefixed with the codec's tag.
+    /// ```
+    /// # use shared::engine::compression::Compression;
+    /// let data = vec![5u8; 100_000];
+    /// for codec in [Compression::None, Compression::Lz4, Compression::Zstd { level: 9 }] {
+    ///     let compressed = codec.compress_tagged(&data).unwrap();
+    ///     assert_eq!(Compression::decompress_tagged(&compressed).unwrap(), data);
+    /// }
+    /// assert!(Compression::Lz4.compress_tagged(&data).unwrap().len() < 1000);
+    /// ```
+    pub fn compress_tagged(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = vec![self.tag()];
+        out.extend_from_slice(&self.compress(data)?);
+        return Ok(out);
+    }
+
+    /// Decompress data prefixed with a codec tag, using whichever codec it was compressed with.
+    /// ```
+    /// # use shared::engine::compression::Compression;
+    /// assert!(Compression::decompress_tagged(&[9, 1, 2, 3]).is_err());
+    /// assert!(Compression::decompress_tagged(&[]).is_err());
+    /// ```
+    pub fn decompress_tagged(data: &[u8]) -> io::Result<Vec<u8>> {
+        let Some((tag, payload)) = data.split_first() else {
+            return Err(invalid("missing compression tag"));
+        };
+        let Some(codec) = Compression::from_tag(*tag) else {
+            return Err(invalid("unknown compression tag"));
+        };
+        return codec.decompress(payload);
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Compression {
+        return Compression::None;
+    }
+}
@@ -0,0 +1,356 @@
+use std::{fmt, fs, io, path::Path, time::Duration};
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::engine::{
+    job::{system::recommended_job_threads, topology::ThreadProfile},
+    memory::{DEFAULT_CHUNK_BUDGET, DEFAULT_ENTITY_BUDGET, MEBIBYTE},
+    save::autosave::{DEFAULT_AUTOSAVE_CHUNKS_PER_TICK, DEFAULT_AUTOSAVE_INTERVAL},
+    spawning::{DEFAULT_CHUNK_CAP, DEFAULT_DESPAWN_DISTANCE, DEFAULT_GLOBAL_CAP, DEFAULT_MIN_SPAWN_DISTANCE}
+};
+
+/// Prefix of environment variables overriding config values, as in CUBE_SERVER_PORT.
+pub const ENV_PREFIX: &str = "CUBE";
+
+pub const DEFAULT_SERVER_PORT: u16 = 25600;
+/// Default radius in chunks of the area the server streams to each player.
+pub const DEFAULT_VIEW_DISTANCE: u32 = 10;
+/// Default seconds a player may send nothing before the server drops them.
+pub const DEFAULT_PLAYER_TIMEOUT: u64 = 30;
+/// Default radius in chunks of the area loaded and drawn around the player.
+pub const DEFAULT_RENDER_DISTANCE: u32 = 12;
+pub const MIN_RENDER_DISTANCE: u32 = 2;
+pub const MAX_RENDER_DISTANCE: u32 = 64;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    /// The file or an override isn't valid TOML or JSON, or has a value of the wrong type.
+    Parse(String),
+    /// A value is out of range.
+    Invalid {
+        key: &'static str,
+        message: String
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ConfigError::Io(error) => write!(f, "couldn't read config: {}", error),
+            ConfigError::Parse(message) => write!(f, "couldn't parse config: {}", message),
+            ConfigError::Invalid { key, message } => write!(f, "invalid config value {}: {}", key, message)
+        };
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(error: io::Error) -> ConfigError {
+        return ConfigError::Io(error);
+    }
+}
+
+/// Format of a config file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json
+}
+
+impl ConfigFormat {
+    /// Format a file is in from its extension. TOML unless it ends in .json.
+    pub fn of_path(path: &Path) -> ConfigFormat {
+        return match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml
+        };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobConfig {
+    /// Threads of the global job system, or 0 for recommended_job_threads().
+    pub threads: usize,
+    /// Threads of the job system saves and assets are read and written on.
+    pub io_threads: usize
+}
+
+impl JobConfig {
+    /// Job threads to start for a profile.
+    pub fn thread_count(&self, profile: ThreadProfile) -> usize {
+        if self.threads == 0 {
+            return recommended_job_threads(profile);
+        }
+        return self.threads;
+    }
+}
+
+impl Default for JobConfig {
+    fn default() -> JobConfig {
+        return JobConfig { threads: 0, io_threads: 2 };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderConfig {
+    /// Radius in chunks of the area loaded and drawn around the player.
+    pub distance: u32
+}
+
+impl Default for RenderConfig {
+    fn default() -> RenderConfig {
+        return RenderConfig { distance: DEFAULT_RENDER_DISTANCE };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub port: u16,
+    /// Port the dedicated server serves metrics on for Prometheus to scrape, and its status as JSON for hosting
+    /// panels, or None to not serve them.
+    pub metrics_port: Option<u16>,
+    /// Radius in chunks of the area streamed to each player, and in which they see entities.
+    pub view_distance: u32,
+    /// Seconds a player may send nothing before they're dropped.
+    pub player_timeout: u64,
+    /// Only let in players on the whitelist. Can also be turned on and off while the server runs.
+    pub whitelist: bool
+}
+
+impl ServerConfig {
+    pub fn player_timeout(&self) -> Duration {
+        return Duration::from_secs(self.player_timeout);
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        return ServerConfig { port: DEFAULT_SERVER_PORT, metrics_port: None, view_distance: DEFAULT_VIEW_DISTANCE, player_timeout: DEFAULT_PLAYER_TIMEOUT, whitelist: false };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetConfig {
+    /// Encrypt connections between clients and servers. Both ends must agree, as neither understands the other
+    /// otherwise.
+    pub encryption: bool
+}
+
+impl Default for NetConfig {
+    fn default() -> NetConfig {
+        return NetConfig { encryption: true };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SaveConfig {
+    /// Seconds between autosaves.
+    pub autosave_interval: u64,
+    /// Most dirty chunks an autosave queues each tick.
+    pub autosave_chunks_per_tick: usize
+}
+
+impl SaveConfig {
+    pub fn autosave_interval(&self) -> Duration {
+        return Duration::from_secs(self.autosave_interval);
+    }
+}
+
+impl Default for SaveConfig {
+    fn default() -> SaveConfig {
+        return SaveConfig { autosave_interval: DEFAULT_AUTOSAVE_INTERVAL.as_secs(), autosave_chunks_per_tick: DEFAULT_AUTOSAVE_CHUNKS_PER_TICK };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MemoryConfig {
+    /// MiB of loaded chunks the server keeps before unloading distant chunks no one needs early, or 0 for no limit.
+    pub chunks: u64,
+    /// MiB of entities the server keeps before unloading distant chunks, and the entities in them, early, or 0
+    /// for no limit.
+    pub entities: u64
+}
+
+impl MemoryConfig {
+    /// Budget of loaded chunks in bytes, or None for no limit.
+    pub fn chunk_budget(&self) -> Option<u64> {
+        return (self.chunks > 0).then(|| self.chunks * MEBIBYTE);
+    }
+
+    /// Budget of entities in bytes, or None for no limit.
+    pub fn entity_budget(&self) -> Option<u64> {
+        return (self.entities > 0).then(|| self.entities * MEBIBYTE);
+    }
+}
+
+impl Default for MemoryConfig {
+    fn default() -> MemoryConfig {
+        return MemoryConfig { chunks: DEFAULT_CHUNK_BUDGET, entities: DEFAULT_ENTITY_BUDGET };
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpawningConfig {
+    /// Spawn mobs around players, and despawn those far from every player.
+    pub enabled: bool,
+    /// Most mobs in the world.
+    pub mob_cap: usize,
+    /// Most mobs spawned into any one chunk.
+    pub chunk_cap: usize,
+    /// Blocks from every player beyond which mobs are despawned.
+    pub despawn_distance: u32
+}
+
+impl Default for SpawningConfig {
+    fn default() -> SpawningConfig {
+        return SpawningConfig { enabled: true, mob_cap: DEFAULT_GLOBAL_CAP, chunk_cap: DEFAULT_CHUNK_CAP, despawn_distance: DEFAULT_DESPAWN_DISTANCE as u32 };
+    }
+}
+
+/// Settings of the engine, read from a TOML or JSON file with a section for each part of the engine.
+/// Anything left out of the file keeps its default, and any value can be overridden by an environment variable
+/// named after its section and key, such as CUBE_SERVER_PORT for port in [server].
+/// ```
+/// # use shared::engine::config::{ConfigFormat, EngineConfig, DEFAULT_SERVER_PORT};
+/// let config = EngineConfig::parse("[render]\ndistance = 20\n\n[jobs]\nthreads = 4", ConfigFormat::Toml).unwrap();
+/// assert_eq!((config.render.distance, config.jobs.threads), (20, 4));
+/// assert_eq!(config.server.port, DEFAULT_SERVER_PORT);
+/// assert_eq!(EngineConfig::parse(r#"{ "render": { "distance": 20 }, "jobs": { "threads": 4 } }"#, ConfigFormat::Json).unwrap(), config);
+///
+/// let config = config.with_overrides([("CUBE_SERVER_PORT".to_string(), "4000".to_string())]).unwrap();
+/// assert_eq!(config.server.port, 4000);
+/// // Typos are caught rather than silently ignored, and values are checked.
+/// assert!(EngineConfig::parse("[render]\ndistnace = 20", ConfigFormat::Toml).is_err());
+/// assert!(EngineConfig::parse("[render]\ndistance = 1000", ConfigFormat::Toml).is_err());
+/// assert!(config.with_overrides([("CUBE_SERVER_PORT".to_string(), "lots".to_string())]).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    pub jobs: JobConfig,
+    pub render: RenderConfig,
+    pub server: ServerConfig,
+    pub net: NetConfig,
+    pub save: SaveConfig,
+    pub memory: MemoryConfig,
+    pub spawning: SpawningConfig
+}
+
+impl EngineConfig {
+    pub fn new() -> EngineConfig {
+        return EngineConfig { jobs: JobConfig::default(), render: RenderConfig::default(), server: ServerConfig::default(), net: NetConfig::default(), save: SaveConfig::default(), memory: MemoryConfig::default(),
+            spawning: SpawningConfig::default() };
+    }
+
+    /// Parse a config file's text, then validate it.
+    pub fn parse(text: &str, format: ConfigFormat) -> Result<EngineConfig, ConfigError> {
+        let config: EngineConfig = match format {
+            ConfigFormat::Toml => toml::from_str(text).map_err(|error| ConfigError::Parse(error.to_string()))?,
+            ConfigFormat::Json => serde_json::from_str(text).map_err(|error| ConfigError::Parse(error.to_string()))?
+        };
+        config.validate()?;
+        return Ok(config);
+    }
+
+    /// Read a config file, applying overrides from the environment. A missing file means every value is
+    /// its default, so the engine runs without one.
+    /// ```
+    /// # use shared::engine::config::EngineConfig;
+    /// let directory = std::env::temp_dir().join(format!("config_doctest_{}", std::process::id()));
+    /// # std::fs::create_dir_all(&directory).unwrap();
+    /// assert_eq!(EngineConfig::load(&directory.join("missing.toml")).unwrap(), EngineConfig::new());
+    /// std::fs::write(directory.join("engine.json"), r#"{ "save": { "autosave_interval": 60 } }"#).unwrap();
+    /// assert_eq!(EngineConfig::load(&directory.join("engine.json")).unwrap().save.autosave_interval().as_secs(), 60);
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn load(path: &Path) -> Result<EngineConfig, ConfigError> {
+        let config = match fs::read_to_string(path) {
+            Ok(text) => EngineConfig::parse(&text, ConfigFormat::of_path(path))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => EngineConfig::new(),
+            Err(error) => return Err(ConfigError::Io(error))
+        };
+        return config.with_overrides(std::env::vars());
+    }
+
+    /// Override values from variables named CUBE_<SECTION>_<KEY>, such as the process's environment, then
+    /// validate the result. Values are read as JSON, so numbers and booleans are typed as they would be in
+    /// a file, and anything else is a string. Variables not naming a value are ignored.
+    pub fn with_overrides<I>(self, variables: I) -> Result<EngineConfig, ConfigError>
+    where I: IntoIterator<Item = (String, String)> {
+        let mut value = serde_json::to_value(&self).unwrap();
+        let mut overridden = false;
+        for (name, text) in variables {
+            let Some(rest) = name.strip_prefix(ENV_PREFIX).and_then(|rest| rest.strip_prefix('_')) else {
+                continue;
+            };
+            let Value::Object(sections) = &mut value else {
+                unreachable!();
+            };
+            for (section_name, section) in sections.iter_mut() {
+                let Value::Object(keys) = section else {
+                    continue;
+                };
+                for (key, old) in keys.iter_mut() {
+                    if rest.eq_ignore_ascii_case(&format!("{}_{}", section_name, key)) {
+                        *old = serde_json::from_str(&text).unwrap_or(Value::String(text.clone()));
+                        overridden = true;
+                    }
+                }
+            }
+        }
+        if !overridden {
+            return Ok(self);
+        }
+        let config: EngineConfig = serde_json::from_value(value).map_err(|error| ConfigError::Parse(format!("environment override: {}", error)))?;
+        config.validate()?;
+        return Ok(config);
+    }
+
+    /// Check every value is in range.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(MIN_RENDER_DISTANCE..=MAX_RENDER_DISTANCE).contains(&self.render.distance) {
+            return Err(ConfigError::Invalid { key: "render.distance", message: format!("must be from {} to {} chunks", MIN_RENDER_DISTANCE, MAX_RENDER_DISTANCE) });
+        }
+        if self.jobs.io_threads == 0 {
+            return Err(ConfigError::Invalid { key: "jobs.io_threads", message: "must be at least 1".to_string() });
+        }
+        if self.server.port == 0 {
+            return Err(ConfigError::Invalid { key: "server.port", message: "must not be 0".to_string() });
+        }
+        if self.server.metrics_port.is_some_and(|port| port == 0 || port == self.server.port) {
+            return Err(ConfigError::Invalid { key: "server.metrics_port", message: "must not be 0 or the server's port".to_string() });
+        }
+        if !(MIN_RENDER_DISTANCE..=MAX_RENDER_DISTANCE).contains(&self.server.view_distance) {
+            return Err(ConfigError::Invalid { key: "server.view_distance", message: format!("must be from {} to {} chunks", MIN_RENDER_DISTANCE, MAX_RENDER_DISTANCE) });
+        }
+        if self.server.player_timeout == 0 {
+            return Err(ConfigError::Invalid { key: "server.player_timeout", message: "must be at least a second".to_string() });
+        }
+        if self.save.autosave_interval == 0 {
+            return Err(ConfigError::Invalid { key: "save.autosave_interval", message: "must be at least a second".to_string() });
+        }
+        if self.save.autosave_chunks_per_tick == 0 {
+            return Err(ConfigError::Invalid { key: "save.autosave_chunks_per_tick", message: "must be at least 1".to_string() });
+        }
+        if (self.spawning.despawn_distance as f64) <= DEFAULT_MIN_SPAWN_DISTANCE {
+            return Err(ConfigError::Invalid { key: "spawning.despawn_distance", message: format!("must be over the {} blocks from players mobs spawn at", DEFAULT_MIN_SPAWN_DISTANCE) });
+        }
+        return Ok(());
+    }
+}
+
+impl Default for EngineConfig {
+    fn default() -> EngineConfig {
+        return EngineConfig::new();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,191 @@
+use std::{backtrace::Backtrace, fmt::Write as _, fs::{self, OpenOptions}, io::{self, Write}, panic::{self, PanicHookInfo}, path::{Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, Arc, Weak}, thread, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::engine::{job::system::JobSystem, universe::Universe, version::engine_version, world::World};
+
+/// Extension of crash report files.
+pub const CRASH_REPORT_EXTENSION: &str = "txt";
+
+/// Set by the first panic to be reported, so panics on other threads while it's reported don't write their own.
+static CRASHING: AtomicBool = AtomicBool::new(false);
+
+/// UTC date and time of a unix timestamp, as (year, month, day, hour, minute, second).
+fn utc_date_time(unix_seconds: u64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = (unix_seconds / 86400) as i64;
+    let seconds = (unix_seconds % 86400) as u32;
+    // Days since 1970-01-01 to a civil date, shifted so years start in March.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    return (year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60);
+}
+
+/// File name of a crash report written at a unix timestamp, such as crash-2024-05-01_13.45.09.txt.
+/// ```
+/// # use shared::engine::crash::crash_report_name;
+/// assert_eq!(crash_report_name(1714571109), "crash-2024-05-01_13.45.09.txt");
+/// ```
+pub fn crash_report_name(unix_seconds: u64) -> String {
+    return format!("crash-{}.{}", timestamp(unix_seconds), CRASH_REPORT_EXTENSION);
+}
+
+fn timestamp(unix_seconds: u64) -> String {
+    let (year, month, day, hour, minute, second) = utc_date_time(unix_seconds);
+    return format!("{:04}-{:02}-{:02}_{:02}.{:02}.{:02}", year, month, day, hour, minute, second);
+}
+
+/// Writes a crash report when a thread panics, then tries to save the world so as little as possible is lost.
+/// The report is written before anything that takes locks, so a panic while a lock is held still leaves
+/// the panic and its backtrace on disk even if gathering the rest of the report hangs.
+/// ```no_run
+/// # use shared::engine::{crash::CrashHandler, job::system::JobSystem};
+/// # use std::{path::Path, sync::Arc};
+/// let jobs = Arc::new(JobSystem::new(2));
+/// CrashHandler::new(Path::new("crash-reports"))
+///     .with_job_system("compute", &jobs)
+///     .with_emergency_save(|| Ok(()))
+///     .install();
+/// ```
+pub struct CrashHandler {
+    directory: PathBuf,
+    job_systems: Vec<(String, Weak<JobSystem>)>,
+    universe: Option<Weak<Universe>>,
+    worlds: Vec<(String, Weak<World>)>,
+    emergency_save: Option<Box<dyn Fn() -> io::Result<()> + Send + Sync>>,
+    abort: bool
+}
+
+impl CrashHandler {
+    /// Write crash reports into a directory, which is made when the first crash is reported.
+    pub fn new(directory: &Path) -> CrashHandler {
+        return CrashHandler { directory: directory.to_path_buf(), job_systems: Vec::new(), universe: None, worlds: Vec::new(), emergency_save: None, abort: true };
+    }
+
+    /// Include a job system's scheduler state in reports, for as long as it exists.
+    pub fn with_job_system(mut self, name: &str, jobs: &Arc<JobSystem>) -> CrashHandler {
+        self.job_systems.push((name.to_string(), Arc::downgrade(jobs)));
+        return self;
+    }
+
+    /// Include the chunks loaded in every dimension of a universe in reports.
+    pub fn with_universe(mut self, universe: &Arc<Universe>) -> CrashHandler {
+        self.universe = Some(Arc::downgrade(universe));
+        return self;
+    }
+
+    /// Include the chunks loaded in a world that isn't part of a universe, such as the client's, in reports.
+    pub fn with_world(mut self, name: &str, world: &Arc<World>) -> CrashHandler {
+        self.worlds.push((name.to_string(), Arc::downgrade(world)));
+        return self;
+    }
+
+    /// Called after the report is written, such as to flush every save manager. Runs on the panicking
+    /// thread, so it must not wait on that thread's jobs or locks it may hold.
+    pub fn with_emergency_save<F>(mut self, save: F) -> CrashHandler
+    where F: Fn() -> io::Result<()> + Send + Sync + 'static {
+        self.emergency_save = Some(Box::new(save));
+        return self;
+    }
+
+    /// Whether to abort the process once the report is written, rather than letting the panic unwind.
+    /// On by default, as the engine's threads can't recover from one of them panicking.
+    pub fn with_abort(mut self, abort: bool) -> CrashHandler {
+        self.abort = abort;
+        return self;
+    }
+
+    pub fn directory(&self) -> &Path {
+        return &self.directory;
+    }
+
+    /// Replace the panic hook with one reporting crashes. The previous hook still runs first,
+    /// so the panic is printed as usual.
+    pub fn install(self) {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            previous(info);
+            if CRASHING.swap(true, Ordering::AcqRel) {
+                return;
+            }
+            let message = match info.payload().downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => info.payload().downcast_ref::<String>().cloned().unwrap_or_else(|| "unknown panic".to_string())
+            };
+            match self.report(&message, info) {
+                Ok(path) => eprintln!("crash report written to {}", path.display()),
+                Err(error) => eprintln!("couldn't write crash report: {}", error)
+            }
+            if self.abort {
+                std::process::abort();
+            }
+            CRASHING.store(false, Ordering::Release);
+        }));
+    }
+
+    /// Write the report, section by section, then run the emergency save. Returns the report's path.
+    fn report(&self, message: &str, info: &PanicHookInfo) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.directory)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        let mut path = self.directory.join(crash_report_name(now));
+        let mut copy = 1;
+        while path.exists() {
+            path = self.directory.join(format!("crash-{}-{}.{}", timestamp(now), copy, CRASH_REPORT_EXTENSION));
+            copy += 1;
+        }
+        let mut file = OpenOptions::new().write(true).create_new(true).open(&path)?;
+
+        let mut text = String::new();
+        let _ = writeln!(text, "---- Cube Universe crash report ----");
+        let _ = writeln!(text, "Time: {} UTC", timestamp(now));
+        let _ = writeln!(text, "Engine version: {}", engine_version());
+        let _ = writeln!(text, "Thread: {}", thread::current().name().unwrap_or("unnamed"));
+        let _ = writeln!(text, "Panic: {}", message);
+        if let Some(location) = info.location() {
+            let _ = writeln!(text, "Location: {}:{}:{}", location.file(), location.line(), location.column());
+        }
+        let _ = writeln!(text, "\n-- Backtrace --\n{}", Backtrace::force_capture());
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+
+        let mut text = String::from("\n-- Job systems --\n");
+        for (name, jobs) in self.job_systems.iter() {
+            match jobs.upgrade() {
+                Some(jobs) => {
+                    let _ = write!(text, "{}: {}", name, jobs.debug_dump());
+                },
+                None => {
+                    let _ = writeln!(text, "{}: shut down", name);
+                }
+            }
+        }
+        let _ = writeln!(text, "\n-- Loaded chunks --");
+        if let Some(universe) = self.universe.as_ref().and_then(Weak::upgrade) {
+            for name in universe.dimension_names() {
+                if let Some(dimension) = universe.dimension(&name) {
+                    let _ = writeln!(text, "{}: {}", name, dimension.world().chunk_count());
+                }
+            }
+        }
+        for (name, world) in self.worlds.iter() {
+            if let Some(world) = world.upgrade() {
+                let _ = writeln!(text, "{}: {}", name, world.chunk_count());
+            }
+        }
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+
+        let save = match self.emergency_save.as_ref().map(|save| save()) {
+            Some(Ok(())) => "saved".to_string(),
+            Some(Err(error)) => format!("failed: {}", error),
+            None => "not configured".to_string()
+        };
+        file.write_all(format!("\n-- Emergency save --\n{}\n", save).as_bytes())?;
+        file.sync_all()?;
+        return Ok(path);
+    }
+}
\ No newline at end of file
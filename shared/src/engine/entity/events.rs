@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+
+use crate::engine::math::coords::ChunkPos;
+
+use super::EntityId;
+
+/// A change to which entities exist or where they are, for systems such as replication and rendering to react to
+/// without checking every entity each tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntityEvent {
+    Spawned(EntityId),
+    /// Sent after LeftChunk, once the entity's components are gone.
+    Despawned(EntityId),
+    EnteredChunk { entity: EntityId, chunk: ChunkPos },
+    /// Sent before EnteredChunk when moving between chunks, and before Despawned.
+    LeftChunk { entity: EntityId, chunk: ChunkPos }
+}
+
+impl EntityEvent {
+    pub fn entity(&self) -> EntityId {
+        return match self {
+            EntityEvent::Spawned(entity) | EntityEvent::Despawned(entity) => *entity,
+            EntityEvent::EnteredChunk { entity, .. } | EntityEvent::LeftChunk { entity, .. } => *entity
+        };
+    }
+}
+
+/// Position of one consumer in an entity's event log. Each consumer keeps its own, so every consumer sees
+/// every event exactly once, as long as it reads at least once between calls to EventLog::update().
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventReader {
+    next: u64
+}
+
+impl EventReader {
+    /// A reader that starts with the oldest retained event.
+    pub fn new() -> EventReader {
+        return EventReader::default();
+    }
+}
+
+/// Entity events kept for two updates, so readers running anywhere in a tick see events from the whole tick.
+#[derive(Debug, Default)]
+pub struct EventLog {
+    events: VecDeque<EntityEvent>,
+    /// Sequence number of the front event.
+    first: u64,
+    /// Sequence number of the first event since the last update.
+    current: u64
+}
+
+impl EventLog {
+    pub fn new() -> EventLog {
+        return EventLog::default();
+    }
+
+    pub fn push(&mut self, event: EntityEvent) {
+        self.events.push_back(event);
+    }
+
+    /// Sequence number the next event will have.
+    fn end(&self) -> u64 {
+        return self.first + self.events.len() as u64;
+    }
+
+    /// A reader that only sees events pushed after it was created.
+    pub fn reader(&self) -> EventReader {
+        return EventReader { next: self.end() };
+    }
+
+    /// Every event the reader hasn't seen, in the order they happened. Events a reader missed by not reading
+    /// for more than one update are skipped.
+    /// ```
+    /// # use shared::engine::entity::{events::{EntityEvent, EventLog, EventReader}, Entities};
+    /// let id = Entities::new().spawn();
+    /// let mut log = EventLog::new();
+    /// let mut reader = EventReader::new();
+    /// log.push(EntityEvent::Spawned(id));
+    /// log.update();
+    /// let mut late = log.reader();
+    /// log.push(EntityEvent::Despawned(id));
+    /// assert_eq!(log.read(&mut reader), vec![EntityEvent::Spawned(id), EntityEvent::Despawned(id)]);
+    /// assert_eq!(log.read(&mut late), vec![EntityEvent::Despawned(id)]);
+    /// assert!(log.read(&mut reader).is_empty());
+    /// ```
+    pub fn read(&self, reader: &mut EventReader) -> Vec<EntityEvent> {
+        let start = reader.next.max(self.first);
+        reader.next = self.end();
+        return self.events.iter().skip((start - self.first) as usize).copied().collect();
+    }
+
+    /// Drop events from before the previous update. Called once per tick.
+    pub fn update(&mut self) {
+        let dropped = (self.current - self.first) as usize;
+        self.events.drain(..dropped);
+        self.first = self.current;
+        self.current = self.end();
+    }
+
+    /// Number of retained events.
+    pub fn len(&self) -> usize {
+        return self.events.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.events.is_empty();
+    }
+}
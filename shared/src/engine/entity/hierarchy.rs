@@ -0,0 +1,207 @@
+use crate::engine::math::{coords::WorldPos, quat::Quat, vector::Vec3};
+
+use super::{
+    kinematics::Transform,
+    query::{Query, With, Without},
+    schedule::{System, SystemAccess, SystemContext},
+    Entities, EntityId
+};
+
+/// The entity this one is attached to, such as the mob a player rides or the player holding an item.
+/// Set with Entities::set_parent, which keeps the parent's Children in sync.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Parent(pub(crate) EntityId);
+
+/// Entities attached to this one, in the order they were attached.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Children(pub(crate) Vec<EntityId>);
+
+/// Where a child sits relative to its parent, in the parent's rotated frame.
+/// Children with a LocalTransform have their Transform set from their parent's every tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LocalTransform {
+    pub translation: Vec3,
+    pub rotation: Quat
+}
+
+impl Parent {
+    pub fn entity(&self) -> EntityId {
+        return self.0;
+    }
+}
+
+impl Children {
+    pub fn entities(&self) -> &[EntityId] {
+        return &self.0;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.0.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.0.is_empty();
+    }
+}
+
+impl LocalTransform {
+    pub fn new(translation: Vec3) -> LocalTransform {
+        return LocalTransform { translation, rotation: Quat::IDENTITY };
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> LocalTransform {
+        self.rotation = rotation;
+        return self;
+    }
+
+    /// World transform of a child attached to a parent with this offset.
+    /// ```
+    /// # use shared::engine::entity::{hierarchy::LocalTransform, kinematics::Transform};
+    /// # use shared::engine::math::{coords::WorldPos, quat::Quat, vector::Vec3};
+    /// let horse = Transform::new(WorldPos::new(10.0, 64.0, 10.0)).with_rotation(Quat::from_yaw_pitch(std::f32::consts::FRAC_PI_2, 0.0));
+    /// let saddle = LocalTransform::new(Vec3::new(0.0, 1.0, 0.5));
+    /// let rider = saddle.apply(&horse);
+    /// assert!((rider.position.y - 65.0).abs() < 1e-5);
+    /// assert!((rider.position.distance(horse.position) - 1.25f64.sqrt()).abs() < 1e-5);
+    /// assert!(rider.rotation.approx_eq(horse.rotation, 1e-5));
+    /// ```
+    pub fn apply(&self, parent: &Transform) -> Transform {
+        let offset = parent.rotation * self.translation;
+        return Transform {
+            position: parent.position + WorldPos::new(offset.x as f64, offset.y as f64, offset.z as f64),
+            rotation: (parent.rotation * self.rotation).normalize()
+        };
+    }
+}
+
+impl Entities {
+    /// Attach an entity to a parent, detaching it from its previous parent.
+    /// False, changing nothing, if either entity isn't alive or the parent is the entity or one of its descendants.
+    /// ```
+    /// # use shared::engine::entity::Entities;
+    /// let entities = Entities::new();
+    /// let (horse, player, sword) = (entities.spawn(), entities.spawn(), entities.spawn());
+    /// assert!(entities.set_parent(player, horse));
+    /// assert!(entities.set_parent(sword, player));
+    /// assert!(!entities.set_parent(horse, sword));
+    /// assert_eq!(entities.children(horse), vec![player]);
+    /// assert_eq!(entities.parent(sword), Some(player));
+    ///
+    /// // Despawning an entity despawns everything attached to it.
+    /// entities.despawn(horse);
+    /// assert!(!entities.is_alive(player));
+    /// assert!(!entities.is_alive(sword));
+    /// ```
+    pub fn set_parent(&self, child: EntityId, parent: EntityId) -> bool {
+        if !self.is_alive(child) || !self.is_alive(parent) {
+            return false;
+        }
+        let mut ancestor = Some(parent);
+        while let Some(id) = ancestor {
+            if id == child {
+                return false;
+            }
+            ancestor = self.parent(id);
+        }
+        self.remove_parent(child);
+        if self.insert(child, Parent(parent)).is_err() {
+            return false;
+        }
+        let children = self.storage::<Children>();
+        let mut children = children.write().unwrap();
+        match children.get_mut(parent) {
+            Some(mut siblings) => siblings.0.push(child),
+            None => {
+                children.insert(parent, Children(vec![child]));
+            }
+        }
+        return true;
+    }
+
+    /// Detach an entity from its parent, returning the parent. The entity keeps its current Transform.
+    pub fn remove_parent(&self, child: EntityId) -> Option<EntityId> {
+        let Parent(parent) = self.remove::<Parent>(child)?;
+        let children = self.storage::<Children>();
+        let mut children = children.write().unwrap();
+        let now_empty = match children.get_mut(parent) {
+            Some(mut siblings) => {
+                siblings.0.retain(|sibling| *sibling != child);
+                siblings.0.is_empty()
+            },
+            None => false
+        };
+        if now_empty {
+            children.remove(parent);
+        }
+        return Some(parent);
+    }
+
+    pub fn parent(&self, id: EntityId) -> Option<EntityId> {
+        return self.storage::<Parent>().read().unwrap().get(id).map(Parent::entity);
+    }
+
+    /// Entities attached directly to an entity.
+    pub fn children(&self, id: EntityId) -> Vec<EntityId> {
+        return self.storage::<Children>().read().unwrap().get(id).map_or(Vec::new(), |children| children.0.clone());
+    }
+
+    /// Detach a despawning entity from its parent, returning its children so they can be despawned too.
+    pub(crate) fn detach_hierarchy(&self, id: EntityId) -> Vec<EntityId> {
+        self.remove_parent(id);
+        return self.remove::<Children>(id).map_or(Vec::new(), |children| children.0);
+    }
+}
+
+/// Sets the Transform of every child with a LocalTransform from its parent's, top down, so riders, held items
+/// and the parts of multi-part entities follow whatever they're attached to. Add it after the systems that move
+/// entities, which conflicts on Transform place in an earlier stage.
+pub struct TransformPropagationSystem;
+
+impl System for TransformPropagationSystem {
+    fn name(&self) -> &str {
+        return "transform_propagation";
+    }
+
+    fn access(&self) -> SystemAccess {
+        return SystemAccess::new().read::<Parent>().read::<Children>().read::<LocalTransform>().write::<Transform>();
+    }
+
+    fn run(&mut self, context: &SystemContext) {
+        let entities = context.entities();
+        let roots = Query::<&Transform, (With<Children>, Without<Parent>)>::new(entities).entities();
+        let children = entities.storage::<Children>();
+        let locals = entities.storage::<LocalTransform>();
+        let transforms = entities.storage::<Transform>();
+        let mut crossed = Vec::new();
+        {
+            let children = children.read().unwrap();
+            let locals = locals.read().unwrap();
+            let mut transforms = transforms.write().unwrap();
+            let mut stack: Vec<(EntityId, Transform)> = roots.iter().map(|root| (*root, *transforms.get(*root).unwrap())).collect();
+            while let Some((parent, parent_transform)) = stack.pop() {
+                for child in children.get(parent).map_or(&[][..], |children| children.entities()) {
+                    let Some(local) = locals.get(*child) else {
+                        // Attached without an offset, so it moves on its own, but its children still follow it.
+                        if let Some(transform) = transforms.get(*child) {
+                            stack.push((*child, *transform));
+                        }
+                        continue;
+                    };
+                    let transform = local.apply(&parent_transform);
+                    let old = transforms.get(*child).copied();
+                    // Only written when it moved, so unmoved children don't count as changed.
+                    if old != Some(transform) {
+                        transforms.insert(*child, transform);
+                    }
+                    if old.is_none_or(|old| old.position.chunk() != transform.position.chunk()) || entities.chunk_of(*child).is_none() {
+                        crossed.push((*child, transform.position.chunk()));
+                    }
+                    stack.push((*child, transform));
+                }
+            }
+        }
+        for (id, chunk) in crossed {
+            entities.set_chunk(id, chunk);
+        }
+    }
+}
\ No newline at end of file
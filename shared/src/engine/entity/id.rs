@@ -0,0 +1,122 @@
+use std::fmt;
+
+/// Handle to an entity. Indices are reused once an entity despawns, with the generation incremented,
+/// so a stale handle to a despawned entity never refers to whatever later takes its index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId {
+    index: u32,
+    generation: u32
+}
+
+impl EntityId {
+    pub(crate) fn new(index: u32, generation: u32) -> EntityId {
+        return EntityId { index, generation };
+    }
+
+    /// Slot of the entity, used to index component storage.
+    pub fn index(&self) -> u32 {
+        return self.index;
+    }
+
+    pub fn generation(&self) -> u32 {
+        return self.generation;
+    }
+
+    /// Pack into a single integer, such as for sending over the network.
+    /// ```
+    /// # use shared::engine::entity::{Entities, EntityId};
+    /// let entities = Entities::new();
+    /// let id = entities.spawn();
+    /// assert_eq!(EntityId::from_bits(id.to_bits()), id);
+    /// ```
+    pub fn to_bits(&self) -> u64 {
+        return (self.generation as u64) << 32 | self.index as u64;
+    }
+
+    pub fn from_bits(bits: u64) -> EntityId {
+        return EntityId { index: bits as u32, generation: (bits >> 32) as u32 };
+    }
+}
+
+impl fmt::Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}v{}", self.index, self.generation);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    generation: u32,
+    alive: bool
+}
+
+/// Hands out entity ids, reusing the indices of despawned entities.
+#[derive(Debug, Default)]
+pub struct EntityAllocator {
+    slots: Vec<Slot>,
+    free: Vec<u32>
+}
+
+impl EntityAllocator {
+    pub fn new() -> EntityAllocator {
+        return EntityAllocator { slots: Vec::new(), free: Vec::new() };
+    }
+
+    /// Allocate an id, reusing the most recently freed index if there is one.
+    /// ```
+    /// # use shared::engine::entity::id::EntityAllocator;
+    /// let mut allocator = EntityAllocator::new();
+    /// let first = allocator.alloc();
+    /// assert!(allocator.free(first));
+    /// let second = allocator.alloc();
+    /// assert_eq!(second.index(), first.index());
+    /// assert_ne!(second, first);
+    /// assert!(!allocator.is_alive(first));
+    /// assert!(!allocator.free(first));
+    /// ```
+    pub fn alloc(&mut self) -> EntityId {
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.alive = true;
+            return EntityId::new(index, slot.generation);
+        }
+        let index = self.slots.len() as u32;
+        self.slots.push(Slot { generation: 0, alive: true });
+        return EntityId::new(index, 0);
+    }
+
+    /// Free an id so its index can be reused. False if the entity was already freed.
+    pub fn free(&mut self, id: EntityId) -> bool {
+        if !self.is_alive(id) {
+            return false;
+        }
+        let slot = &mut self.slots[id.index as usize];
+        slot.alive = false;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(id.index);
+        return true;
+    }
+
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        return match self.slots.get(id.index as usize) {
+            Some(slot) => slot.alive && slot.generation == id.generation,
+            None => false
+        };
+    }
+
+    /// Number of living entities.
+    pub fn len(&self) -> usize {
+        return self.slots.len() - self.free.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    /// Every living entity, in index order.
+    pub fn alive(&self) -> impl Iterator<Item = EntityId> + '_ {
+        return self.slots.iter().enumerate()
+            .filter(|(_, slot)| slot.alive)
+            .map(|(index, slot)| EntityId::new(index as u32, slot.generation));
+    }
+}
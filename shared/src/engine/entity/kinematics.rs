@@ -0,0 +1,254 @@
+use std::io;
+
+use serde::{Serialize, Deserialize};
+
+use crate::engine::{math::{coords::WorldPos, quat::Quat, vector::Vec3}, physics::RigidBody};
+
+use super::{query::{Changed, Without}, schedule::{System, SystemAccess, SystemContext}, serialize::ComponentTypes};
+
+/// Simulation ticks per second. Movement always advances by exactly this step, on both client and server,
+/// so the same inputs give the same positions no matter the frame rate.
+pub const TICKS_PER_SECOND: u32 = 20;
+
+/// Seconds simulated by each tick.
+pub const FIXED_TIMESTEP: f32 = 1.0 / TICKS_PER_SECOND as f32;
+
+/// Where an entity is, and which way it faces.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub position: WorldPos,
+    pub rotation: Quat
+}
+
+/// How fast an entity moves, in blocks per second, and rotates, as an axis scaled by radians per second.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Velocity {
+    pub linear: Vec3,
+    pub angular: Vec3
+}
+
+/// Forces acting on an entity's velocity. Entities with a Velocity but no Kinematics move at a constant speed.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Kinematics {
+    /// Constant acceleration such as gravity, in blocks per second squared.
+    pub acceleration: Vec3,
+    /// Fraction of linear velocity lost per second, from 0 to 1.
+    pub drag: f32,
+    /// Largest linear speed, in blocks per second.
+    pub max_speed: f32
+}
+
+impl Transform {
+    pub fn new(position: WorldPos) -> Transform {
+        return Transform { position, rotation: Quat::IDENTITY };
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Transform {
+        self.rotation = rotation;
+        return self;
+    }
+}
+
+impl Velocity {
+    pub fn new(linear: Vec3) -> Velocity {
+        return Velocity { linear, angular: Vec3::ZERO };
+    }
+}
+
+impl Kinematics {
+    /// Falling under gravity, as for mobs and dropped items.
+    pub const FALLING: Kinematics = Kinematics { acceleration: Vec3::new(0.0, -32.0, 0.0), drag: 0.4, max_speed: 78.4 };
+
+    /// Move one fixed step. Velocity changes before position, which stays stable at any step size.
+    /// ```
+    /// # use shared::engine::entity::kinematics::{Kinematics, Transform, Velocity, FIXED_TIMESTEP};
+    /// # use shared::engine::math::{coords::WorldPos, vector::Vec3};
+    /// let mut transform = Transform::new(WorldPos::new(0.0, 100.0, 0.0));
+    /// let mut velocity = Velocity::new(Vec3::new(4.0, 0.0, 0.0));
+    /// let kinematics = Kinematics { acceleration: Vec3::ZERO, drag: 0.0, max_speed: 10.0 };
+    /// for _ in 0..20 {
+    ///     kinematics.step(&mut transform, &mut velocity, FIXED_TIMESTEP);
+    /// }
+    /// assert!((transform.position.x - 4.0).abs() < 1e-4);
+    ///
+    /// // Falling speed is capped.
+    /// for _ in 0..1000 {
+    ///     Kinematics::FALLING.step(&mut transform, &mut velocity, FIXED_TIMESTEP);
+    /// }
+    /// assert!(velocity.linear.length() <= Kinematics::FALLING.max_speed + 1e-3);
+    /// ```
+    pub fn step(&self, transform: &mut Transform, velocity: &mut Velocity, seconds: f32) {
+        velocity.linear += self.acceleration * seconds;
+        velocity.linear *= (1.0 - self.drag * seconds).max(0.0);
+        let speed = velocity.linear.length();
+        if speed > self.max_speed {
+            velocity.linear *= self.max_speed / speed;
+        }
+        integrate(transform, velocity, seconds);
+    }
+}
+
+/// Move a transform by a velocity over a number of seconds.
+pub fn integrate(transform: &mut Transform, velocity: &Velocity, seconds: f32) {
+    let moved = velocity.linear * seconds;
+    transform.position = transform.position + WorldPos::new(moved.x as f64, moved.y as f64, moved.z as f64);
+    let angle = velocity.angular.length() * seconds;
+    if angle > 0.0 {
+        transform.rotation = (Quat::from_axis_angle(velocity.angular.normalize(), angle) * transform.rotation).normalize();
+    }
+}
+
+/// Turns variable frame times into a whole number of fixed ticks, carrying the remainder to the next frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedTimestep {
+    step: f64,
+    accumulated: f64,
+    /// Most ticks run in one frame, so a long stall doesn't make the next frame even slower.
+    max_steps: u32,
+    dropped: u32
+}
+
+impl FixedTimestep {
+    pub fn new(step: f64, max_steps: u32) -> FixedTimestep {
+        debug_assert!(step > 0.0, "Fixed timestep must be positive");
+        return FixedTimestep { step, accumulated: 0.0, max_steps, dropped: 0 };
+    }
+
+    /// Add a frame's elapsed seconds, returning how many ticks to run.
+    /// ```
+    /// # use shared::engine::entity::kinematics::FixedTimestep;
+    /// let mut timestep = FixedTimestep::new(0.05, 10);
+    /// assert_eq!(timestep.advance(0.03), 0);
+    /// assert_eq!(timestep.advance(0.03), 1);
+    /// assert!((timestep.alpha() - 0.2).abs() < 1e-9);
+    /// // A long stall only runs up to the limit, dropping the rest.
+    /// assert_eq!(timestep.advance(5.0), 10);
+    /// assert_eq!(timestep.dropped(), 90);
+    /// ```
+    pub fn advance(&mut self, seconds: f64) -> u32 {
+        self.accumulated += seconds;
+        let steps = (self.accumulated / self.step).floor();
+        if steps > self.max_steps as f64 {
+            self.accumulated = 0.0;
+            self.dropped = steps as u32 - self.max_steps;
+            return self.max_steps;
+        }
+        self.accumulated -= steps * self.step;
+        self.dropped = 0;
+        return steps as u32;
+    }
+
+    /// Ticks the last advance dropped for being past the limit.
+    pub fn dropped(&self) -> u32 {
+        return self.dropped;
+    }
+
+    /// Progress towards the next tick from 0 to 1, for interpolating rendered positions between ticks.
+    pub fn alpha(&self) -> f64 {
+        return self.accumulated / self.step;
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> FixedTimestep {
+        return FixedTimestep::new(FIXED_TIMESTEP as f64, TICKS_PER_SECOND);
+    }
+}
+
+/// Moves every entity with a Transform and Velocity by one fixed timestep, applying its Kinematics if it has any,
+/// and moves it between chunks in the entity registry as it crosses chunk borders.
+/// Entities with a RigidBody are left to the PhysicsSystem, but any entity whose Transform changed is moved
+/// to the chunk it's now in.
+pub struct KinematicsSystem;
+
+impl System for KinematicsSystem {
+    fn name(&self) -> &str {
+        return "kinematics";
+    }
+
+    fn access(&self) -> SystemAccess {
+        return SystemAccess::new().read::<Kinematics>().read::<RigidBody>().write::<Velocity>().write::<Transform>();
+    }
+
+    fn run(&mut self, context: &SystemContext) {
+        let entities = context.entities();
+        let kinematics = entities.storage::<Kinematics>();
+        let kinematics = kinematics.read().unwrap();
+        context.query::<(&mut Transform, &mut Velocity), Without<RigidBody>>().for_each(|id, (mut transform, mut velocity)| {
+            if velocity.linear == Vec3::ZERO && velocity.angular == Vec3::ZERO && !kinematics.contains(id) {
+                return;
+            }
+            match kinematics.get(id) {
+                Some(kinematics) => kinematics.step(&mut transform, &mut velocity, FIXED_TIMESTEP),
+                None => integrate(&mut transform, &velocity, FIXED_TIMESTEP)
+            }
+        });
+        // Entities moved above, placed without a chunk, or moved by anything else such as teleports, go to the chunk
+        // they're in, keeping the chunk index right for spatial queries.
+        let mut crossed = Vec::new();
+        context.query::<&Transform, Changed<Transform>>().for_each(|id, transform| {
+            if entities.chunk_of(id) != Some(transform.position.chunk()) {
+                crossed.push((id, transform.position.chunk()));
+            }
+        });
+        for (id, chunk) in crossed {
+            entities.set_chunk(id, chunk);
+        }
+    }
+}
+
+fn read_f32s<const N: usize>(data: &[u8]) -> io::Result<[f32; N]> {
+    if data.len() != N * 4 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Component data has the wrong length"));
+    }
+    return Ok(std::array::from_fn(|i| f32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap())));
+}
+
+fn write_f32s(values: &[f32], out: &mut Vec<u8>) {
+    for value in values {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+/// Save transforms, velocities and kinematics with entities.
+/// ```
+/// # use shared::engine::entity::{kinematics::{self, Transform}, serialize::ComponentTypes, Entities};
+/// # use shared::engine::math::{coords::WorldPos, quat::Quat};
+/// let mut types = ComponentTypes::new();
+/// kinematics::register_components(&mut types);
+/// let entities = Entities::new();
+/// let arrow = entities.spawn();
+/// let transform = Transform::new(WorldPos::new(1e7 + 0.25, -3.5, 2.0)).with_rotation(Quat::from_yaw_pitch(1.0, 0.5));
+/// entities.insert(arrow, transform).unwrap();
+/// let loaded = types.decode(&entities, &types.encode(&entities, &[arrow])).unwrap();
+/// assert_eq!(entities.get::<Transform>(loaded[0]), Some(transform));
+/// ```
+pub fn register_components(types: &mut ComponentTypes) {
+    types.register::<Transform>("cube:transform", |transform, out| {
+        for value in [transform.position.x, transform.position.y, transform.position.z] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        let rotation = transform.rotation;
+        write_f32s(&[rotation.x, rotation.y, rotation.z, rotation.w], out);
+    }, |data| {
+        if data.len() != 40 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Transform data has the wrong length"));
+        }
+        let position: [f64; 3] = std::array::from_fn(|i| f64::from_le_bytes(data[i * 8..i * 8 + 8].try_into().unwrap()));
+        let [x, y, z, w] = read_f32s::<4>(&data[24..])?;
+        return Ok(Transform { position: WorldPos::new(position[0], position[1], position[2]), rotation: Quat::from_xyzw(x, y, z, w) });
+    });
+    types.register::<Velocity>("cube:velocity", |velocity, out| {
+        write_f32s(&[velocity.linear.x, velocity.linear.y, velocity.linear.z, velocity.angular.x, velocity.angular.y, velocity.angular.z], out);
+    }, |data| {
+        let [x, y, z, angular_x, angular_y, angular_z] = read_f32s::<6>(data)?;
+        return Ok(Velocity { linear: Vec3::new(x, y, z), angular: Vec3::new(angular_x, angular_y, angular_z) });
+    });
+    types.register::<Kinematics>("cube:kinematics", |kinematics, out| {
+        let acceleration = kinematics.acceleration;
+        write_f32s(&[acceleration.x, acceleration.y, acceleration.z, kinematics.drag, kinematics.max_speed], out);
+    }, |data| {
+        let [x, y, z, drag, max_speed] = read_f32s::<5>(data)?;
+        return Ok(Kinematics { acceleration: Vec3::new(x, y, z), drag, max_speed });
+    });
+}
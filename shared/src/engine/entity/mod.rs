@@ -0,0 +1,13 @@
+pub mod id;
+pub mod storage;
+pub mod registry;
+pub mod events;
+pub mod query;
+pub mod schedule;
+pub mod serialize;
+pub mod kinematics;
+pub mod hierarchy;
+pub mod replication;
+
+pub use id::EntityId;
+pub use registry::Entities;
\ No newline at end of file
@@ -0,0 +1,412 @@
+use std::{marker::PhantomData, sync::{RwLockReadGuard, RwLockWriteGuard}};
+
+use super::{schedule::SystemAccess, storage::{Component, ComponentStorage, Mut, SharedStorage}, Entities, EntityId};
+
+/// Components fetched by a query: &T, &mut T, or a tuple of them.
+pub trait QueryData {
+    type State: QueryState;
+    type Item<'a>;
+
+    fn state(entities: &Entities) -> Self::State;
+
+    /// Fetch the components of an entity the locked storages contain.
+    fn fetch<'a>(guard: &'a mut <Self::State as QueryState>::Guard<'_>, id: EntityId) -> Self::Item<'a>;
+
+    fn access(access: SystemAccess) -> SystemAccess;
+}
+
+/// The storages a query uses, locked while it runs.
+pub trait QueryState {
+    type Guard<'s>: QueryGuard where Self: 's;
+
+    fn lock(&self) -> Self::Guard<'_>;
+}
+
+/// Locked storages of a query.
+pub trait QueryGuard {
+    /// Entities that may match, from whichever component has the fewest.
+    fn candidates(&self) -> &[EntityId];
+
+    fn contains(&self, id: EntityId) -> bool;
+}
+
+/// Filters which entities a query visits, without fetching their components: With, Without, Changed, Added,
+/// or a tuple of them, all of which must match.
+pub trait QueryFilter {
+    type State: FilterState;
+
+    fn state(entities: &Entities) -> Self::State;
+
+    fn access(access: SystemAccess) -> SystemAccess;
+}
+
+pub trait FilterState {
+    type Guard<'s>: FilterGuard where Self: 's;
+
+    fn lock(&self) -> Self::Guard<'_>;
+}
+
+pub trait FilterGuard {
+    /// Check an entity, where last_run is the change tick changes must be newer than.
+    fn matches(&self, id: EntityId, last_run: u64) -> bool;
+}
+
+pub struct ReadState<T>(SharedStorage<T>);
+
+pub struct WriteState<T>(SharedStorage<T>);
+
+pub struct ReadGuard<'s, T>(RwLockReadGuard<'s, ComponentStorage<T>>);
+
+pub struct WriteGuard<'s, T>(RwLockWriteGuard<'s, ComponentStorage<T>>);
+
+impl<T: Component> QueryData for &T {
+    type State = ReadState<T>;
+    type Item<'a> = &'a T;
+
+    fn state(entities: &Entities) -> ReadState<T> {
+        return ReadState(entities.storage::<T>());
+    }
+
+    fn fetch<'a>(guard: &'a mut ReadGuard<'_, T>, id: EntityId) -> &'a T {
+        return guard.0.get(id).unwrap();
+    }
+
+    fn access(access: SystemAccess) -> SystemAccess {
+        return access.read::<T>();
+    }
+}
+
+impl<T: Component> QueryData for &mut T {
+    type State = WriteState<T>;
+    type Item<'a> = Mut<'a, T>;
+
+    fn state(entities: &Entities) -> WriteState<T> {
+        return WriteState(entities.storage::<T>());
+    }
+
+    fn fetch<'a>(guard: &'a mut WriteGuard<'_, T>, id: EntityId) -> Mut<'a, T> {
+        return guard.0.get_mut(id).unwrap();
+    }
+
+    fn access(access: SystemAccess) -> SystemAccess {
+        return access.write::<T>();
+    }
+}
+
+impl<T: Component> QueryState for ReadState<T> {
+    type Guard<'s> = ReadGuard<'s, T>;
+
+    fn lock(&self) -> ReadGuard<'_, T> {
+        return ReadGuard(self.0.read().unwrap());
+    }
+}
+
+impl<T: Component> QueryState for WriteState<T> {
+    type Guard<'s> = WriteGuard<'s, T>;
+
+    fn lock(&self) -> WriteGuard<'_, T> {
+        return WriteGuard(self.0.write().unwrap());
+    }
+}
+
+impl<T: Component> QueryGuard for ReadGuard<'_, T> {
+    fn candidates(&self) -> &[EntityId] {
+        return self.0.entities();
+    }
+
+    fn contains(&self, id: EntityId) -> bool {
+        return self.0.contains(id);
+    }
+}
+
+impl<T: Component> QueryGuard for WriteGuard<'_, T> {
+    fn candidates(&self) -> &[EntityId] {
+        return self.0.entities();
+    }
+
+    fn contains(&self, id: EntityId) -> bool {
+        return self.0.contains(id);
+    }
+}
+
+macro_rules! impl_query_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: QueryData),+> QueryData for ($($name,)+) {
+            type State = ($($name::State,)+);
+            type Item<'a> = ($($name::Item<'a>,)+);
+
+            fn state(entities: &Entities) -> Self::State {
+                return ($($name::state(entities),)+);
+            }
+
+            #[allow(non_snake_case)]
+            fn fetch<'a>(guard: &'a mut <Self::State as QueryState>::Guard<'_>, id: EntityId) -> Self::Item<'a> {
+                let ($($name,)+) = guard;
+                return ($($name::fetch($name, id),)+);
+            }
+
+            fn access(access: SystemAccess) -> SystemAccess {
+                $(let access = $name::access(access);)+
+                return access;
+            }
+        }
+
+        impl<$($name: QueryState),+> QueryState for ($($name,)+) {
+            type Guard<'s> = ($($name::Guard<'s>,)+) where Self: 's;
+
+            #[allow(non_snake_case)]
+            fn lock(&self) -> Self::Guard<'_> {
+                let ($($name,)+) = self;
+                return ($($name.lock(),)+);
+            }
+        }
+
+        impl<$($name: QueryGuard),+> QueryGuard for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn candidates(&self) -> &[EntityId] {
+                let ($($name,)+) = self;
+                let mut shortest: Option<&[EntityId]> = None;
+                $(
+                    let candidates = $name.candidates();
+                    if shortest.is_none_or(|shortest| candidates.len() < shortest.len()) {
+                        shortest = Some(candidates);
+                    }
+                )+
+                return shortest.unwrap();
+            }
+
+            #[allow(non_snake_case)]
+            fn contains(&self, id: EntityId) -> bool {
+                let ($($name,)+) = self;
+                return $($name.contains(id))&&+;
+            }
+        }
+
+        impl<$($name: QueryFilter),+> QueryFilter for ($($name,)+) {
+            type State = ($($name::State,)+);
+
+            fn state(entities: &Entities) -> Self::State {
+                return ($($name::state(entities),)+);
+            }
+
+            fn access(access: SystemAccess) -> SystemAccess {
+                $(let access = $name::access(access);)+
+                return access;
+            }
+        }
+
+        impl<$($name: FilterState),+> FilterState for ($($name,)+) {
+            type Guard<'s> = ($($name::Guard<'s>,)+) where Self: 's;
+
+            #[allow(non_snake_case)]
+            fn lock(&self) -> Self::Guard<'_> {
+                let ($($name,)+) = self;
+                return ($($name.lock(),)+);
+            }
+        }
+
+        impl<$($name: FilterGuard),+> FilterGuard for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn matches(&self, id: EntityId, last_run: u64) -> bool {
+                let ($($name,)+) = self;
+                return $($name.matches(id, last_run))&&+;
+            }
+        }
+    };
+}
+
+impl_query_tuple!(A);
+impl_query_tuple!(A, B);
+impl_query_tuple!(A, B, C);
+impl_query_tuple!(A, B, C, D);
+impl_query_tuple!(A, B, C, D, E);
+impl_query_tuple!(A, B, C, D, E, F);
+
+impl QueryFilter for () {
+    type State = ();
+
+    fn state(_entities: &Entities) {}
+
+    fn access(access: SystemAccess) -> SystemAccess {
+        return access;
+    }
+}
+
+impl FilterState for () {
+    type Guard<'s> = ();
+
+    fn lock(&self) {}
+}
+
+impl FilterGuard for () {
+    fn matches(&self, _id: EntityId, _last_run: u64) -> bool {
+        return true;
+    }
+}
+
+type FilterTest<T> = fn(&ComponentStorage<T>, EntityId, u64) -> bool;
+
+/// Storage a filter checks, and how it checks it.
+pub struct ComponentFilter<T> {
+    storage: SharedStorage<T>,
+    test: FilterTest<T>
+}
+
+pub struct ComponentFilterGuard<'s, T> {
+    storage: RwLockReadGuard<'s, ComponentStorage<T>>,
+    test: FilterTest<T>
+}
+
+impl<T: Component> FilterState for ComponentFilter<T> {
+    type Guard<'s> = ComponentFilterGuard<'s, T>;
+
+    fn lock(&self) -> ComponentFilterGuard<'_, T> {
+        return ComponentFilterGuard { storage: self.storage.read().unwrap(), test: self.test };
+    }
+}
+
+impl<T: Component> FilterGuard for ComponentFilterGuard<'_, T> {
+    fn matches(&self, id: EntityId, last_run: u64) -> bool {
+        return (self.test)(&self.storage, id, last_run);
+    }
+}
+
+/// Only entities with a component, without fetching it.
+pub struct With<T>(PhantomData<T>);
+
+/// Only entities without a component.
+pub struct Without<T>(PhantomData<T>);
+
+/// Only entities whose component changed, or was added, since the query's last run.
+pub struct Changed<T>(PhantomData<T>);
+
+/// Only entities whose component was added since the query's last run.
+pub struct Added<T>(PhantomData<T>);
+
+macro_rules! impl_component_filter {
+    ($filter:ident, $test:expr) => {
+        impl<T: Component> QueryFilter for $filter<T> {
+            type State = ComponentFilter<T>;
+
+            fn state(entities: &Entities) -> ComponentFilter<T> {
+                return ComponentFilter { storage: entities.storage::<T>(), test: $test };
+            }
+
+            fn access(access: SystemAccess) -> SystemAccess {
+                return access.read::<T>();
+            }
+        }
+    };
+}
+
+impl_component_filter!(With, |storage, id, _| storage.contains(id));
+impl_component_filter!(Without, |storage, id, _| !storage.contains(id));
+impl_component_filter!(Changed, |storage, id, last_run| storage.ticks(id).is_some_and(|ticks| ticks.changed > last_run));
+impl_component_filter!(Added, |storage, id, last_run| storage.ticks(id).is_some_and(|ticks| ticks.added > last_run));
+
+/// Components of type D fetched from every entity matching the filter F.
+/// Only the storages of the query are locked, and only while it runs. Filters are checked before the components
+/// are locked, so a query can both write a component and filter on it changing. Running two queries at once that
+/// lock the same storages in a different order can deadlock, which schedules avoid by never running systems
+/// that write the same components at the same time.
+pub struct Query<D: QueryData, F: QueryFilter = ()> {
+    data: D::State,
+    filter: F::State,
+    last_run: u64
+}
+
+/// An item fetched by a query.
+pub type QueryItem<'a, D> = <D as QueryData>::Item<'a>;
+
+impl<D: QueryData, F: QueryFilter> Query<D, F> {
+    /// Query where every component counts as added and changed.
+    pub fn new(entities: &Entities) -> Query<D, F> {
+        return Query::since(entities, 0);
+    }
+
+    /// Query where Changed and Added only match changes after a change tick, usually when the system last ran.
+    pub fn since(entities: &Entities, last_run: u64) -> Query<D, F> {
+        debug_assert!(!D::access(F::access(SystemAccess::new())).has_write_overlap(), "Query uses a component it writes twice");
+        return Query { data: D::state(entities), filter: F::state(entities), last_run };
+    }
+
+    /// Components and filters the query uses, to declare as a system's access.
+    pub fn access(access: SystemAccess) -> SystemAccess {
+        return F::access(D::access(access));
+    }
+
+    /// Entities the query matches, in storage order of the component with the fewest entities.
+    pub fn entities(&self) -> Vec<EntityId> {
+        let candidates: Vec<EntityId> = {
+            let data = self.data.lock();
+            data.candidates().iter().copied().filter(|id| data.contains(*id)).collect()
+        };
+        let filter = self.filter.lock();
+        return candidates.into_iter().filter(|id| filter.matches(*id, self.last_run)).collect();
+    }
+
+    /// Number of matching entities.
+    pub fn count(&self) -> usize {
+        return self.entities().len();
+    }
+
+    /// Call a function with the components of every matching entity.
+    /// ```
+    /// # use shared::engine::entity::{query::{Changed, Query, With, Without}, Entities};
+    /// #[derive(Debug)]
+    /// struct Position(f32);
+    /// #[derive(Debug)]
+    /// struct Velocity(f32);
+    /// #[derive(Debug)]
+    /// struct Frozen;
+    ///
+    /// let entities = Entities::new();
+    /// for i in 0..4 {
+    ///     let id = entities.spawn();
+    ///     entities.insert(id, Position(0.0)).unwrap();
+    ///     entities.insert(id, Velocity(i as f32)).unwrap();
+    ///     if i == 3 {
+    ///         entities.insert(id, Frozen).unwrap();
+    ///     }
+    /// }
+    /// let spawned = entities.change_tick();
+    /// entities.increment_change_tick();
+    /// Query::<(&mut Position, &Velocity), Without<Frozen>>::new(&entities).for_each(|_, (mut position, velocity)| {
+    ///     if velocity.0 > 0.0 {
+    ///         position.0 += velocity.0;
+    ///     }
+    /// });
+    /// let moved = entities.change_tick();
+    /// entities.increment_change_tick();
+    /// Query::<&mut Position, With<Frozen>>::new(&entities).for_each(|_, mut position| position.0 = 10.0);
+    ///
+    /// // Only the entities that were actually written count as changed.
+    /// let mut changed = Vec::new();
+    /// Query::<&Position, Changed<Position>>::since(&entities, spawned).for_each(|_, position| changed.push(position.0));
+    /// assert_eq!(changed, vec![1.0, 2.0, 10.0]);
+    /// assert_eq!(Query::<&Position, Changed<Position>>::since(&entities, moved).count(), 1);
+    /// ```
+    pub fn for_each<C>(&self, mut func: C)
+    where C: for<'a> FnMut(EntityId, QueryItem<'a, D>) {
+        let matching = self.entities();
+        let mut data = self.data.lock();
+        for id in matching {
+            if data.contains(id) {
+                func(id, D::fetch(&mut data, id));
+            }
+        }
+    }
+
+    /// Call a function with the components of one entity, if it matches.
+    pub fn get<R, C>(&self, id: EntityId, func: C) -> Option<R>
+    where C: for<'a> FnOnce(QueryItem<'a, D>) -> R {
+        if !self.filter.lock().matches(id, self.last_run) {
+            return None;
+        }
+        let mut data = self.data.lock();
+        if !data.contains(id) {
+            return None;
+        }
+        return Some(func(D::fetch(&mut data, id)));
+    }
+}
@@ -0,0 +1,251 @@
+use std::{any::TypeId, collections::{HashMap, HashSet}, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, RwLock}};
+
+use crate::engine::math::coords::ChunkPos;
+
+use super::{events::{EntityEvent, EventLog, EventReader}, id::EntityAllocator, storage::{Component, ComponentStorage, ErasedStorage, SharedStorage}, EntityId};
+
+/// Every entity of a world, such as players, mobs, and dropped items, and their components.
+/// Each component type has its own sparse set storage behind its own lock, so systems working on different
+/// components never block each other, and entities can be spawned and despawned from any thread.
+/// Spawning, despawning and moving between chunks are recorded as events, read through an EventReader.
+pub struct Entities {
+    allocator: Mutex<EntityAllocator>,
+    storages: RwLock<HashMap<TypeId, Arc<dyn ErasedStorage>>>,
+    chunks: RwLock<ChunkIndex>,
+    events: Mutex<EventLog>,
+    /// Stamped onto components as they're added and changed, advanced by schedules between stages.
+    change_tick: Arc<AtomicU64>
+}
+
+/// Chunk each entity is in, so entities can be saved and unloaded with their chunk.
+#[derive(Default)]
+struct ChunkIndex {
+    of_entity: HashMap<EntityId, ChunkPos>,
+    in_chunk: HashMap<ChunkPos, HashSet<EntityId>>
+}
+
+impl ChunkIndex {
+    fn remove(&mut self, id: EntityId) -> Option<ChunkPos> {
+        let old = self.of_entity.remove(&id)?;
+        let entities = self.in_chunk.get_mut(&old).unwrap();
+        entities.remove(&id);
+        if entities.is_empty() {
+            self.in_chunk.remove(&old);
+        }
+        return Some(old);
+    }
+}
+
+impl Entities {
+    pub fn new() -> Entities {
+        return Entities { allocator: Mutex::new(EntityAllocator::new()), storages: RwLock::new(HashMap::new()), chunks: RwLock::new(ChunkIndex::default()), events: Mutex::new(EventLog::new()), change_tick: Arc::new(AtomicU64::new(1)) };
+    }
+
+    /// Create an entity without any components.
+    pub fn spawn(&self) -> EntityId {
+        let id = self.allocator.lock().unwrap().alloc();
+        self.events.lock().unwrap().push(EntityEvent::Spawned(id));
+        return id;
+    }
+
+    /// Despawn an entity, dropping all of its components, along with every entity attached to it.
+    /// False if it was already despawned.
+    /// Must not be called while holding a lock on one of the entity's component storages.
+    /// ```
+    /// # use shared::engine::entity::Entities;
+    /// let entities = Entities::new();
+    /// let item = entities.spawn();
+    /// entities.insert(item, "cube:stone").unwrap();
+    /// assert!(entities.despawn(item));
+    /// assert!(!entities.is_alive(item));
+    /// assert!(!entities.has::<&str>(item));
+    /// assert!(!entities.despawn(item));
+    /// ```
+    pub fn despawn(&self, id: EntityId) -> bool {
+        if !self.allocator.lock().unwrap().free(id) {
+            return false;
+        }
+        if let Some(chunk) = self.chunks.write().unwrap().remove(id) {
+            self.events.lock().unwrap().push(EntityEvent::LeftChunk { entity: id, chunk });
+        }
+        let children = self.detach_hierarchy(id);
+        let storages: Vec<_> = self.storages.read().unwrap().values().cloned().collect();
+        for storage in storages {
+            storage.remove_entity(id);
+        }
+        self.events.lock().unwrap().push(EntityEvent::Despawned(id));
+        for child in children {
+            self.despawn(child);
+        }
+        return true;
+    }
+
+    /// Move an entity into a chunk, returning the chunk it was in. Called by whatever moves the entity,
+    /// whenever it crosses into another chunk. Does nothing if the entity isn't alive.
+    /// ```
+    /// # use shared::engine::entity::Entities;
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let entities = Entities::new();
+    /// let mob = entities.spawn();
+    /// assert_eq!(entities.set_chunk(mob, ChunkPos::new(0, 0, 0)), None);
+    /// assert_eq!(entities.set_chunk(mob, ChunkPos::new(1, 0, 0)), Some(ChunkPos::new(0, 0, 0)));
+    /// assert_eq!(entities.in_chunk(ChunkPos::new(1, 0, 0)), vec![mob]);
+    /// assert!(entities.in_chunk(ChunkPos::new(0, 0, 0)).is_empty());
+    /// entities.despawn(mob);
+    /// assert!(entities.in_chunk(ChunkPos::new(1, 0, 0)).is_empty());
+    /// ```
+    pub fn set_chunk(&self, id: EntityId, chunk: ChunkPos) -> Option<ChunkPos> {
+        // Locked before checking, so a concurrent despawn can't leave the entity in the index.
+        let mut chunks = self.chunks.write().unwrap();
+        if !self.is_alive(id) {
+            return None;
+        }
+        let old = chunks.remove(id);
+        chunks.of_entity.insert(id, chunk);
+        chunks.in_chunk.entry(chunk).or_default().insert(id);
+        if old != Some(chunk) {
+            let mut events = self.events.lock().unwrap();
+            if let Some(old) = old {
+                events.push(EntityEvent::LeftChunk { entity: id, chunk: old });
+            }
+            events.push(EntityEvent::EnteredChunk { entity: id, chunk });
+        }
+        return old;
+    }
+
+    /// Chunk an entity is in, None if it was never placed in one.
+    pub fn chunk_of(&self, id: EntityId) -> Option<ChunkPos> {
+        return self.chunks.read().unwrap().of_entity.get(&id).copied();
+    }
+
+    /// Every entity in a chunk, in index order.
+    pub fn in_chunk(&self, chunk: ChunkPos) -> Vec<EntityId> {
+        let mut entities: Vec<EntityId> = match self.chunks.read().unwrap().in_chunk.get(&chunk) {
+            Some(entities) => entities.iter().copied().collect(),
+            None => Vec::new()
+        };
+        entities.sort_unstable();
+        return entities;
+    }
+
+    /// Tick stamped onto components changed now.
+    pub fn change_tick(&self) -> u64 {
+        return self.change_tick.load(Ordering::Acquire);
+    }
+
+    /// Advance the change tick, returning the new tick. Changes before this compare as older than changes after it.
+    pub fn increment_change_tick(&self) -> u64 {
+        return self.change_tick.fetch_add(1, Ordering::AcqRel) + 1;
+    }
+
+    /// A reader that only sees events from now on.
+    pub fn event_reader(&self) -> EventReader {
+        return self.events.lock().unwrap().reader();
+    }
+
+    /// Every event the reader hasn't seen yet, in the order they happened.
+    /// ```
+    /// # use shared::engine::entity::{events::EntityEvent, Entities};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let entities = Entities::new();
+    /// let mut renderer = entities.event_reader();
+    /// let item = entities.spawn();
+    /// entities.set_chunk(item, ChunkPos::new(0, 0, 0));
+    /// entities.set_chunk(item, ChunkPos::new(0, -1, 0));
+    /// entities.despawn(item);
+    /// assert_eq!(entities.read_events(&mut renderer), vec![
+    ///     EntityEvent::Spawned(item),
+    ///     EntityEvent::EnteredChunk { entity: item, chunk: ChunkPos::new(0, 0, 0) },
+    ///     EntityEvent::LeftChunk { entity: item, chunk: ChunkPos::new(0, 0, 0) },
+    ///     EntityEvent::EnteredChunk { entity: item, chunk: ChunkPos::new(0, -1, 0) },
+    ///     EntityEvent::LeftChunk { entity: item, chunk: ChunkPos::new(0, -1, 0) },
+    ///     EntityEvent::Despawned(item)
+    /// ]);
+    /// ```
+    pub fn read_events(&self, reader: &mut EventReader) -> Vec<EntityEvent> {
+        return self.events.lock().unwrap().read(reader);
+    }
+
+    /// Drop events from before the previous update. Called once per tick, so readers that read every tick never miss one.
+    pub fn update_events(&self) {
+        self.events.lock().unwrap().update();
+    }
+
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        return self.allocator.lock().unwrap().is_alive(id);
+    }
+
+    /// Number of living entities.
+    pub fn len(&self) -> usize {
+        return self.allocator.lock().unwrap().len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    /// Every living entity at the time of the call, in index order.
+    pub fn alive(&self) -> Vec<EntityId> {
+        return self.allocator.lock().unwrap().alive().collect();
+    }
+
+    /// Storage of a component type, created empty if no entity has had one yet.
+    /// Lock it to iterate or modify many components at once.
+    /// ```
+    /// # use shared::engine::entity::Entities;
+    /// #[derive(Debug)]
+    /// struct Health(u32);
+    ///
+    /// let entities = Entities::new();
+    /// for health in [5, 10, 20] {
+    ///     let mob = entities.spawn();
+    ///     entities.insert(mob, Health(health)).unwrap();
+    /// }
+    /// let storage = entities.storage::<Health>();
+    /// for (_, mut health) in storage.write().unwrap().iter_mut() {
+    ///     health.0 -= 5;
+    /// }
+    /// let total: u32 = storage.read().unwrap().iter().map(|(_, health)| health.0).sum();
+    /// assert_eq!(total, 20);
+    /// ```
+    pub fn storage<T: Component>(&self) -> SharedStorage<T> {
+        let type_id = TypeId::of::<T>();
+        let existing = self.storages.read().unwrap().get(&type_id).cloned();
+        let storage = match existing {
+            Some(storage) => storage,
+            None => self.storages.write().unwrap().entry(type_id)
+                .or_insert_with(|| Arc::new(RwLock::new(ComponentStorage::<T>::with_change_tick(self.change_tick.clone()))))
+                .clone()
+        };
+        return storage.into_any().downcast().unwrap();
+    }
+
+    /// Add a component to an entity, returning the one it replaced.
+    /// Err with the component, without adding it, if the entity isn't alive.
+    pub fn insert<T: Component>(&self, id: EntityId, component: T) -> Result<Option<T>, T> {
+        if !self.is_alive(id) {
+            return Err(component);
+        }
+        return Ok(self.storage::<T>().write().unwrap().insert(id, component));
+    }
+
+    pub fn remove<T: Component>(&self, id: EntityId) -> Option<T> {
+        return self.storage::<T>().write().unwrap().remove(id);
+    }
+
+    /// Copy of an entity's component. Lock the storage instead to borrow it.
+    pub fn get<T: Component + Clone>(&self, id: EntityId) -> Option<T> {
+        return self.storage::<T>().read().unwrap().get(id).cloned();
+    }
+
+    pub fn has<T: Component>(&self, id: EntityId) -> bool {
+        let storage = self.storages.read().unwrap().get(&TypeId::of::<T>()).cloned();
+        return storage.is_some_and(|storage| storage.contains_entity(id));
+    }
+}
+
+impl Default for Entities {
+    fn default() -> Entities {
+        return Entities::new();
+    }
+}
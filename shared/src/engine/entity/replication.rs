@@ -0,0 +1,213 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::engine::{lod::LodPolicy, math::coords::{ChunkPos, CHUNK_SIZE}};
+
+use super::{storage::Component, Entities, EntityId};
+
+/// Connection a server replicates entities to.
+pub type ClientId = u32;
+
+/// Marks an entity the server sends to clients. Entities without it, such as server only markers, are never sent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Replicated;
+
+/// What one client needs to hear about since its previous update, each list sorted by id.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplicationDelta {
+    /// Entities the client doesn't know yet, to send in full.
+    pub creates: Vec<EntityId>,
+    /// Known entities with a watched component that changed, to send the changes of.
+    pub updates: Vec<EntityId>,
+    /// Known entities the client should forget, because they despawned, stopped being replicated or left its radius.
+    pub destroys: Vec<EntityId>
+}
+
+impl ReplicationDelta {
+    pub fn is_empty(&self) -> bool {
+        return self.creates.is_empty() && self.updates.is_empty() && self.destroys.is_empty();
+    }
+}
+
+type ChangedSince = fn(&Entities, u64) -> Vec<EntityId>;
+
+struct ClientInterest {
+    center: ChunkPos,
+    radius: i32,
+    known: HashSet<EntityId>,
+    /// Known entities that changed but weren't due to be replicated yet, so their update isn't lost.
+    pending: HashSet<EntityId>
+}
+
+/// Decides which replicated entities each client can see, only those within a chunk radius of the client's player,
+/// and tracks what each client already knows so only the differences are sent.
+pub struct InterestManager {
+    clients: HashMap<ClientId, ClientInterest>,
+    watched: Vec<ChangedSince>,
+    /// Thins out updates of entities far from each client's center, if set.
+    lod: Option<LodPolicy>,
+    /// Updates run, which staggered replication intervals count.
+    updates: u64,
+    last_update: u64
+}
+
+impl InterestManager {
+    pub fn new() -> InterestManager {
+        return InterestManager { clients: HashMap::new(), watched: Vec::new(), lod: None, updates: 0, last_update: 0 };
+    }
+
+    /// Send updates of entities less often the further their chunk is from a client's center. Entities past the
+    /// last band are still created and destroyed, but their updates wait until they come closer.
+    /// ```
+    /// # use shared::engine::entity::{kinematics::Transform, replication::{InterestManager, Replicated}, Entities};
+    /// # use shared::engine::lod::LodPolicy;
+    /// # use shared::engine::math::coords::{ChunkPos, WorldPos};
+    /// let entities = Entities::new();
+    /// let mut interest = InterestManager::new();
+    /// interest.watch::<Transform>();
+    /// interest.set_lod(LodPolicy::for_view_distance(256.0));
+    /// interest.add_client(1, ChunkPos::ORIGIN, 16);
+    /// let (near, far) = (entities.spawn(), entities.spawn());
+    /// for (id, chunk) in [(near, ChunkPos::new(1, 0, 0)), (far, ChunkPos::new(7, 0, 0))] {
+    ///     entities.insert(id, Replicated).unwrap();
+    ///     entities.insert(id, Transform::default()).unwrap();
+    ///     entities.set_chunk(id, chunk);
+    /// }
+    /// assert_eq!(interest.update(&entities)[&1].creates.len(), 2);
+    ///
+    /// let mut near_updates = 0;
+    /// let mut far_updates = 0;
+    /// for tick in 0..20 {
+    ///     for id in [near, far] {
+    ///         entities.storage::<Transform>().write().unwrap().get_mut(id).unwrap().position.x = tick as f64;
+    ///     }
+    ///     let delta = &interest.update(&entities)[&1];
+    ///     near_updates += delta.updates.contains(&near) as u32;
+    ///     far_updates += delta.updates.contains(&far) as u32;
+    /// }
+    /// assert_eq!((near_updates, far_updates), (20, 2));
+    /// ```
+    pub fn set_lod(&mut self, policy: LodPolicy) {
+        self.lod = Some(policy);
+    }
+
+    /// Send an update for a known entity whenever its T changes.
+    pub fn watch<T: Component>(&mut self) {
+        self.watched.push(|entities, last_update| {
+            let storage = entities.storage::<T>();
+            let storage = storage.read().unwrap();
+            return storage.entities().iter().copied().filter(|id| storage.ticks(*id).is_some_and(|ticks| ticks.changed > last_update)).collect();
+        });
+    }
+
+    /// Start replicating to a client, which at first knows no entities.
+    pub fn add_client(&mut self, client: ClientId, center: ChunkPos, radius: i32) {
+        debug_assert!(radius >= 0, "Interest radius must not be negative");
+        self.clients.insert(client, ClientInterest { center, radius, known: HashSet::new(), pending: HashSet::new() });
+    }
+
+    pub fn remove_client(&mut self, client: ClientId) -> bool {
+        return self.clients.remove(&client).is_some();
+    }
+
+    /// Move the chunk a client sees around, as its player moves.
+    pub fn set_center(&mut self, client: ClientId, center: ChunkPos) {
+        if let Some(interest) = self.clients.get_mut(&client) {
+            interest.center = center;
+        }
+    }
+
+    pub fn set_radius(&mut self, client: ClientId, radius: i32) {
+        debug_assert!(radius >= 0, "Interest radius must not be negative");
+        if let Some(interest) = self.clients.get_mut(&client) {
+            interest.radius = radius;
+        }
+    }
+
+    pub fn client_count(&self) -> usize {
+        return self.clients.len();
+    }
+
+    /// Entities a client has been sent and not told to destroy.
+    pub fn known(&self, client: ClientId) -> Vec<EntityId> {
+        let mut known: Vec<EntityId> = self.clients.get(&client).map_or(Vec::new(), |interest| interest.known.iter().copied().collect());
+        known.sort_unstable();
+        return known;
+    }
+
+    /// Work out what every client needs to hear since the previous update, and assume it was sent.
+    /// Called once per tick after the schedule runs, with each client's center already moved.
+    /// ```
+    /// # use shared::engine::entity::{kinematics::Transform, replication::{InterestManager, Replicated}, Entities};
+    /// # use shared::engine::math::coords::{ChunkPos, WorldPos};
+    /// let entities = Entities::new();
+    /// let mut interest = InterestManager::new();
+    /// interest.watch::<Transform>();
+    /// interest.add_client(1, ChunkPos::ORIGIN, 2);
+    /// let (near, far) = (entities.spawn(), entities.spawn());
+    /// for (id, chunk) in [(near, ChunkPos::new(1, 0, 0)), (far, ChunkPos::new(3, 0, 0))] {
+    ///     entities.insert(id, Replicated).unwrap();
+    ///     entities.insert(id, Transform::default()).unwrap();
+    ///     entities.set_chunk(id, chunk);
+    /// }
+    /// assert_eq!(interest.update(&entities)[&1].creates, vec![near]);
+    /// assert!(interest.update(&entities)[&1].is_empty());
+    ///
+    /// entities.storage::<Transform>().write().unwrap().get_mut(near).unwrap().position = WorldPos::new(40.0, 0.0, 0.0);
+    /// entities.set_chunk(far, ChunkPos::new(2, 0, 0));
+    /// let delta = &interest.update(&entities)[&1];
+    /// assert_eq!((delta.creates.clone(), delta.updates.clone()), (vec![far], vec![near]));
+    ///
+    /// interest.set_center(1, ChunkPos::new(-2, 0, 0));
+    /// assert_eq!(interest.update(&entities)[&1].destroys, vec![near, far]);
+    /// ```
+    pub fn update(&mut self, entities: &Entities) -> HashMap<ClientId, ReplicationDelta> {
+        let now = entities.change_tick();
+        entities.increment_change_tick();
+        let tick = self.updates;
+        self.updates += 1;
+        let changed: HashSet<EntityId> = self.watched.iter().flat_map(|changed_since| changed_since(entities, self.last_update)).collect();
+        self.last_update = now;
+        let replicated = entities.storage::<Replicated>();
+        let replicated = replicated.read().unwrap();
+
+        let mut chunk_entities: HashMap<ChunkPos, Vec<EntityId>> = HashMap::new();
+        let mut deltas = HashMap::new();
+        for (client, interest) in self.clients.iter_mut() {
+            let mut visible = BTreeSet::new();
+            for chunk in interest.center.within_radius(interest.radius) {
+                let in_chunk = chunk_entities.entry(chunk)
+                    .or_insert_with(|| entities.in_chunk(chunk).into_iter().filter(|id| replicated.contains(*id)).collect());
+                visible.extend(in_chunk.iter().copied());
+            }
+            let mut delta = ReplicationDelta::default();
+            for id in visible.iter() {
+                if !interest.known.contains(id) {
+                    delta.creates.push(*id);
+                } else if changed.contains(id) || interest.pending.contains(id) {
+                    let due = self.lod.as_ref().is_none_or(|policy| {
+                        let chunks = entities.chunk_of(*id).map_or(0.0, |chunk| (chunk.distance_squared(interest.center) as f32).sqrt());
+                        return policy.should_replicate(chunks * CHUNK_SIZE as f32, tick, id.to_bits());
+                    });
+                    if due {
+                        interest.pending.remove(id);
+                        delta.updates.push(*id);
+                    } else {
+                        interest.pending.insert(*id);
+                    }
+                }
+            }
+            delta.destroys = interest.known.iter().copied().filter(|id| !visible.contains(id)).collect();
+            delta.destroys.sort_unstable();
+            interest.pending.retain(|id| visible.contains(id));
+            interest.known = visible.into_iter().collect();
+            deltas.insert(*client, delta);
+        }
+        return deltas;
+    }
+}
+
+impl Default for InterestManager {
+    fn default() -> InterestManager {
+        return InterestManager::new();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,253 @@
+use std::{any::{type_name, TypeId}, sync::{Arc, Mutex}};
+
+use crate::engine::{job::system::JobSystem, world::World};
+
+use super::{query::{Query, QueryData, QueryFilter}, storage::Component, Entities};
+
+/// The component types a system reads and writes, used to decide which systems can run at the same time.
+/// Access isn't enforced, as every storage has its own lock, but a system using a component it didn't declare
+/// may block on, or race in ordering with, a system running beside it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SystemAccess {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+    exclusive: bool
+}
+
+impl SystemAccess {
+    pub fn new() -> SystemAccess {
+        return SystemAccess::default();
+    }
+
+    pub fn read<T: Component>(mut self) -> SystemAccess {
+        self.reads.push((TypeId::of::<T>(), type_name::<T>()));
+        return self;
+    }
+
+    pub fn write<T: Component>(mut self) -> SystemAccess {
+        self.writes.push((TypeId::of::<T>(), type_name::<T>()));
+        return self;
+    }
+
+    /// Run alone, such as for systems that spawn and despawn entities, or touch every storage.
+    pub fn exclusive(mut self) -> SystemAccess {
+        self.exclusive = true;
+        return self;
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        return self.exclusive;
+    }
+
+    /// Check if a component is written while also being read or written, which would lock its storage twice.
+    pub(crate) fn has_write_overlap(&self) -> bool {
+        return self.writes.iter().enumerate().any(|(i, (id, _))| {
+            self.writes[i + 1..].iter().any(|(other, _)| other == id) || self.reads.iter().any(|(other, _)| other == id)
+        });
+    }
+
+    /// Check if two systems can't run at the same time, because one writes a component the other uses.
+    /// ```
+    /// # use shared::engine::entity::schedule::SystemAccess;
+    /// struct Position;
+    /// struct Velocity;
+    ///
+    /// let movement = SystemAccess::new().read::<Velocity>().write::<Position>();
+    /// let render = SystemAccess::new().read::<Position>();
+    /// let gravity = SystemAccess::new().write::<Velocity>();
+    /// assert!(movement.conflicts(&render));
+    /// assert!(movement.conflicts(&gravity));
+    /// assert!(!render.conflicts(&gravity));
+    /// assert!(!render.conflicts(&SystemAccess::new().read::<Position>()));
+    /// ```
+    pub fn conflicts(&self, other: &SystemAccess) -> bool {
+        if self.exclusive || other.exclusive {
+            return true;
+        }
+        let uses = |access: &SystemAccess, type_id: TypeId| access.reads.iter().chain(access.writes.iter()).any(|(id, _)| *id == type_id);
+        return self.writes.iter().any(|(id, _)| uses(other, *id)) || other.writes.iter().any(|(id, _)| uses(self, *id));
+    }
+}
+
+/// What a system can reach while it runs.
+pub struct SystemContext<'w> {
+    /// Shared so systems can hand the world to jobs of their own.
+    pub world: &'w Arc<World>,
+    /// Change tick of the system's previous run, so its queries only see changes since then. 0 on the first run.
+    pub last_run: u64
+}
+
+impl SystemContext<'_> {
+    pub fn entities(&self) -> &Entities {
+        return self.world.entities();
+    }
+
+    /// Query whose Changed and Added filters match changes since the system last ran.
+    pub fn query<D: QueryData, F: QueryFilter>(&self) -> Query<D, F> {
+        return Query::since(self.world.entities(), self.last_run);
+    }
+}
+
+/// Logic run over entities every tick, such as movement or AI.
+pub trait System: Send {
+    fn name(&self) -> &str;
+
+    /// Components the system reads and writes. Only called when the system is added to a schedule.
+    fn access(&self) -> SystemAccess;
+
+    fn run(&mut self, context: &SystemContext);
+}
+
+struct FnSystem<F> {
+    name: String,
+    access: SystemAccess,
+    func: F
+}
+
+impl<F: FnMut(&SystemContext) + Send> System for FnSystem<F> {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn access(&self) -> SystemAccess {
+        return self.access.clone();
+    }
+
+    fn run(&mut self, context: &SystemContext) {
+        (self.func)(context);
+    }
+}
+
+struct ScheduledSystem {
+    system: Arc<Mutex<Box<dyn System>>>,
+    access: SystemAccess,
+    last_run: u64
+}
+
+/// Runs systems as jobs, with systems that don't conflict running at the same time.
+/// Systems are grouped into stages, with a barrier between each stage. A system is placed in the stage after
+/// the last one holding a system it conflicts with, so conflicting systems always run in the order they were added,
+/// while independent systems share a stage no matter where they were added.
+/// The entity change tick advances before every stage, so each system's queries see the changes made since it last ran,
+/// other than its own.
+pub struct SystemSchedule {
+    systems: Vec<ScheduledSystem>,
+    /// Indices into systems of each stage.
+    stages: Vec<Vec<usize>>
+}
+
+impl SystemSchedule {
+    pub fn new() -> SystemSchedule {
+        return SystemSchedule { systems: Vec::new(), stages: Vec::new() };
+    }
+
+    /// Add a system, to run after any system already added that it conflicts with.
+    /// ```
+    /// # use shared::engine::entity::schedule::{SystemAccess, SystemSchedule};
+    /// struct Position(f32);
+    /// struct Velocity(f32);
+    /// struct Health(u32);
+    ///
+    /// let mut schedule = SystemSchedule::new();
+    /// schedule.add_fn("gravity", SystemAccess::new().write::<Velocity>(), |_| {});
+    /// schedule.add_fn("movement", SystemAccess::new().read::<Velocity>().write::<Position>(), |_| {});
+    /// schedule.add_fn("regeneration", SystemAccess::new().write::<Health>(), |_| {});
+    /// assert_eq!(schedule.stage_names(), vec![vec!["gravity", "regeneration"], vec!["movement"]]);
+    /// ```
+    pub fn add_system<S: System + 'static>(&mut self, system: S) -> &mut SystemSchedule {
+        let access = system.access();
+        let index = self.systems.len();
+        let first_stage = self.stages.iter()
+            .rposition(|stage| stage.iter().any(|other| self.systems[*other].access.conflicts(&access)))
+            .map_or(0, |stage| stage + 1);
+        match self.stages.get_mut(first_stage) {
+            Some(stage) => stage.push(index),
+            None => self.stages.push(vec![index])
+        }
+        self.systems.push(ScheduledSystem { system: Arc::new(Mutex::new(Box::new(system))), access, last_run: 0 });
+        return self;
+    }
+
+    /// Add a closure as a system.
+    pub fn add_fn<F>(&mut self, name: &str, access: SystemAccess, func: F) -> &mut SystemSchedule
+    where F: FnMut(&SystemContext) + Send + 'static {
+        return self.add_system(FnSystem { name: name.to_string(), access, func });
+    }
+
+    pub fn len(&self) -> usize {
+        return self.systems.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.systems.is_empty();
+    }
+
+    pub fn stage_count(&self) -> usize {
+        return self.stages.len();
+    }
+
+    /// Names of the systems in each stage, for debugging.
+    pub fn stage_names(&self) -> Vec<Vec<String>> {
+        return self.stages.iter()
+            .map(|stage| stage.iter().map(|index| self.systems[*index].system.lock().unwrap().name().to_string()).collect())
+            .collect();
+    }
+
+    /// Run every system once. Each stage's systems run as jobs, and every job of a stage finishes before the next
+    /// stage starts. Stages with a single system run it on the calling thread.
+    /// ```
+    /// # use shared::engine::entity::{query::Query, schedule::{SystemAccess, SystemSchedule}};
+    /// # use shared::engine::job::system::JobSystem;
+    /// # use shared::engine::world::World;
+    /// # use std::sync::Arc;
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Position(f32);
+    /// #[derive(Debug)]
+    /// struct Velocity(f32);
+    ///
+    /// let world = Arc::new(World::new());
+    /// let entity = world.entities().spawn();
+    /// world.entities().insert(entity, Position(0.0)).unwrap();
+    /// world.entities().insert(entity, Velocity(0.0)).unwrap();
+    ///
+    /// let mut schedule = SystemSchedule::new();
+    /// schedule.add_fn("gravity", SystemAccess::new().write::<Velocity>(), |context| {
+    ///     for (_, mut velocity) in context.entities().storage::<Velocity>().write().unwrap().iter_mut() {
+    ///         velocity.0 -= 1.0;
+    ///     }
+    /// });
+    /// schedule.add_fn("movement", Query::<(&mut Position, &Velocity)>::access(SystemAccess::new()), |context| {
+    ///     context.query::<(&mut Position, &Velocity), ()>().for_each(|_, (mut position, velocity)| position.0 += velocity.0);
+    /// });
+    /// let jobs = JobSystem::new(2);
+    /// schedule.run(&jobs, &world);
+    /// schedule.run(&jobs, &world);
+    /// assert_eq!(world.entities().get::<Position>(entity), Some(Position(-3.0)));
+    /// ```
+    pub fn run(&mut self, jobs: &JobSystem, world: &Arc<World>) {
+        for stage in self.stages.iter() {
+            let tick = world.entities().increment_change_tick();
+            let last_runs: Vec<u64> = stage.iter().map(|index| std::mem::replace(&mut self.systems[*index].last_run, tick)).collect();
+            if let [index] = stage.as_slice() {
+                self.systems[*index].system.lock().unwrap().run(&SystemContext { world, last_run: last_runs[0] });
+                continue;
+            }
+            let futures: Vec<_> = stage.iter().zip(last_runs).map(|(index, last_run)| {
+                let system = self.systems[*index].system.clone();
+                let world = world.clone();
+                jobs.run_job(move || system.lock().unwrap().run(&SystemContext { world: &world, last_run }))
+            }).collect();
+            for future in futures {
+                future.wait();
+            }
+        }
+        // Changes made between runs are newer than any system's last run.
+        world.entities().increment_change_tick();
+    }
+}
+
+impl Default for SystemSchedule {
+    fn default() -> SystemSchedule {
+        return SystemSchedule::new();
+    }
+}
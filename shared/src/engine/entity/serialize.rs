@@ -0,0 +1,174 @@
+use std::{any::TypeId, io};
+
+use super::{storage::Component, Entities, EntityId};
+
+/// Version of the entity encoding, stored at the start of every encoded list of entities.
+pub const ENTITY_FORMAT_VERSION: u8 = 1;
+
+type ErasedSaver = Box<dyn Fn(&Entities, &[EntityId]) -> Vec<Option<Vec<u8>>> + Send + Sync>;
+type ErasedLoader = Box<dyn Fn(&Entities, EntityId, &[u8]) -> io::Result<()> + Send + Sync>;
+
+/// Marks an entity that isn't saved with the chunk it's in, being saved some other way, such as a player saved
+/// with their player data. Chunks unloading leave it spawned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Unsaved;
+
+struct ComponentType {
+    name: String,
+    type_id: TypeId,
+    save: ErasedSaver,
+    load: ErasedLoader
+}
+
+/// Component types that are saved, each under a namespaced name.
+/// Components are saved by name rather than by type or registration order, so saves keep loading after component
+/// types are added or removed. Components of unregistered types, such as runtime only state, aren't saved.
+#[derive(Default)]
+pub struct ComponentTypes {
+    types: Vec<ComponentType>
+}
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+impl ComponentTypes {
+    pub fn new() -> ComponentTypes {
+        return ComponentTypes::default();
+    }
+
+    /// Save a component type under a name, replacing any type already registered with that name.
+    /// The saver writes a component's data, to be read back by the loader. Either may capture what it needs, such as
+    /// a registry to save ids by name.
+    pub fn register<T: Component>(&mut self, name: &str, save: impl Fn(&T, &mut Vec<u8>) + Send + Sync + 'static,
+        load: impl Fn(&[u8]) -> io::Result<T> + Send + Sync + 'static) {
+        self.types.retain(|registered| registered.name != name && registered.type_id != TypeId::of::<T>());
+        self.types.push(ComponentType {
+            name: name.to_string(),
+            type_id: TypeId::of::<T>(),
+            save: Box::new(move |entities, ids| {
+                let storage = entities.storage::<T>();
+                let storage = storage.read().unwrap();
+                return ids.iter().map(|id| storage.get(*id).map(|component| {
+                    let mut data = Vec::new();
+                    save(component, &mut data);
+                    data
+                })).collect();
+            }),
+            load: Box::new(move |entities, id, data| {
+                let _ = entities.insert(id, load(data)?);
+                return Ok(());
+            })
+        });
+    }
+
+    pub fn is_registered<T: Component>(&self) -> bool {
+        return self.types.iter().any(|registered| registered.type_id == TypeId::of::<T>());
+    }
+
+    /// Name a component type is saved under.
+    pub fn name_of<T: Component>(&self) -> Option<&str> {
+        return self.types.iter().find(|registered| registered.type_id == TypeId::of::<T>()).map(|registered| registered.name.as_str());
+    }
+
+    pub fn len(&self) -> usize {
+        return self.types.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.types.is_empty();
+    }
+
+    /// Encode entities' registered components as little-endian binary: the format version and entity count,
+    /// then for each entity its component count, and each component's name and data, length prefixed.
+    /// Entity ids aren't included, as loading spawns new entities.
+    pub fn encode(&self, entities: &Entities, ids: &[EntityId]) -> Vec<u8> {
+        let saved: Vec<Vec<Option<Vec<u8>>>> = self.types.iter().map(|registered| (registered.save)(entities, ids)).collect();
+        let mut out = vec![ENTITY_FORMAT_VERSION];
+        out.extend_from_slice(&(ids.len() as u32).to_le_bytes());
+        for entity in 0..ids.len() {
+            let components: Vec<(&str, &Vec<u8>)> = self.types.iter().zip(saved.iter())
+                .filter_map(|(registered, data)| data[entity].as_ref().map(|data| (registered.name.as_str(), data)))
+                .collect();
+            out.extend_from_slice(&(components.len() as u16).to_le_bytes());
+            for (name, data) in components {
+                out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                out.extend_from_slice(name.as_bytes());
+                out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                out.extend_from_slice(data);
+            }
+        }
+        return out;
+    }
+
+    /// Spawn the entities written by encode(), returning their new ids in the order they were encoded.
+    /// Components whose type is no longer registered are skipped. If the data is corrupt, any entities already
+    /// spawned from it are despawned again.
+    /// ```
+    /// # use shared::engine::entity::{serialize::ComponentTypes, Entities};
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Health(u32);
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Target(u32);
+    ///
+    /// let mut types = ComponentTypes::new();
+    /// types.register::<Health>("cube:health", |health, out| out.extend_from_slice(&health.0.to_le_bytes()),
+    ///     |data| Ok(Health(u32::from_le_bytes(data.try_into().unwrap()))));
+    /// let entities = Entities::new();
+    /// let zombie = entities.spawn();
+    /// entities.insert(zombie, Health(20)).unwrap();
+    /// // Not registered, so not saved.
+    /// entities.insert(zombie, Target(3)).unwrap();
+    /// let data = types.encode(&entities, &[zombie]);
+    ///
+    /// let loaded = Entities::new();
+    /// let ids = types.decode(&loaded, &data).unwrap();
+    /// assert_eq!(loaded.get::<Health>(ids[0]), Some(Health(20)));
+    /// assert!(!loaded.has::<Target>(ids[0]));
+    /// // A save from a version with the health component removed.
+    /// assert!(ComponentTypes::new().decode(&loaded, &data).is_ok());
+    /// assert!(types.decode(&loaded, &data[..data.len() - 1]).is_err());
+    /// assert_eq!(loaded.len(), 2);
+    /// ```
+    pub fn decode(&self, entities: &Entities, data: &[u8]) -> io::Result<Vec<EntityId>> {
+        let mut spawned = Vec::new();
+        let result = self.decode_into(entities, data, &mut spawned);
+        if result.is_err() {
+            for id in spawned.iter() {
+                entities.despawn(*id);
+            }
+        }
+        return result.map(|_| spawned);
+    }
+
+    fn decode_into(&self, entities: &Entities, data: &[u8], spawned: &mut Vec<EntityId>) -> io::Result<()> {
+        let mut reader = data;
+        let mut take = |count: usize| -> io::Result<&[u8]> {
+            if reader.len() < count {
+                return Err(invalid("Entity data ended early"));
+            }
+            let (taken, rest) = reader.split_at(count);
+            reader = rest;
+            return Ok(taken);
+        };
+        if take(1)?[0] != ENTITY_FORMAT_VERSION {
+            return Err(invalid("Unsupported entity format version"));
+        }
+        let count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        for _ in 0..count {
+            let id = entities.spawn();
+            spawned.push(id);
+            let component_count = u16::from_le_bytes(take(2)?.try_into().unwrap());
+            for _ in 0..component_count {
+                let name_len = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+                let name = std::str::from_utf8(take(name_len)?).map_err(|_| invalid("Component name is not UTF-8"))?.to_string();
+                let data_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+                let component_data = take(data_len)?;
+                if let Some(registered) = self.types.iter().find(|registered| registered.name == name) {
+                    (registered.load)(entities, id, component_data)?;
+                }
+            }
+        }
+        return Ok(());
+    }
+}
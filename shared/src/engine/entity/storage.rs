@@ -0,0 +1,224 @@
+use std::{any::Any, ops::{Deref, DerefMut}, sync::{atomic::{AtomicU64, Ordering}, Arc, RwLock}};
+
+use super::EntityId;
+
+/// Data attached to an entity, such as its position or health. Any thread safe type can be a component.
+pub trait Component: Send + Sync + 'static {}
+
+impl<T: Send + Sync + 'static> Component for T {}
+
+const NONE: u32 = u32::MAX;
+
+/// Change ticks of when a component was added and last changed, for change detection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ComponentTicks {
+    pub added: u64,
+    pub changed: u64
+}
+
+/// Every component of one type, stored as a sparse set. Components are packed densely so iterating them is
+/// a linear walk, while the sparse array maps an entity index to its component in constant time.
+/// Each component records the change tick it was added and last changed at, read from the registry's change tick.
+pub struct ComponentStorage<T> {
+    /// Index into dense of each entity index, or NONE.
+    sparse: Vec<u32>,
+    dense: Vec<EntityId>,
+    components: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+    change_tick: Arc<AtomicU64>
+}
+
+/// Mutable access to a component, which marks it as changed only when it is written through.
+pub struct Mut<'a, T> {
+    value: &'a mut T,
+    ticks: &'a mut ComponentTicks,
+    change_tick: u64
+}
+
+impl<'a, T> Mut<'a, T> {
+    /// Modify the component without marking it as changed, such as for caches nothing else reads.
+    pub fn bypass_change_detection(&mut self) -> &mut T {
+        return self.value;
+    }
+
+    pub fn into_inner(self) -> &'a mut T {
+        self.ticks.changed = self.change_tick;
+        return self.value;
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        return self.value;
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.ticks.changed = self.change_tick;
+        return self.value;
+    }
+}
+
+/// A component storage, shared between the entity registry and any systems using it.
+pub type SharedStorage<T> = Arc<RwLock<ComponentStorage<T>>>;
+
+impl<T: Component> ComponentStorage<T> {
+    /// Create a storage with its own change tick, which stays at 1.
+    pub fn new() -> ComponentStorage<T> {
+        return ComponentStorage::with_change_tick(Arc::new(AtomicU64::new(1)));
+    }
+
+    pub(crate) fn with_change_tick(change_tick: Arc<AtomicU64>) -> ComponentStorage<T> {
+        return ComponentStorage { sparse: Vec::new(), dense: Vec::new(), components: Vec::new(), ticks: Vec::new(), change_tick };
+    }
+
+    fn tick(&self) -> u64 {
+        return self.change_tick.load(Ordering::Acquire);
+    }
+
+    fn dense_index(&self, id: EntityId) -> Option<usize> {
+        let dense = *self.sparse.get(id.index() as usize)?;
+        if dense == NONE || self.dense[dense as usize] != id {
+            return None;
+        }
+        return Some(dense as usize);
+    }
+
+    /// Add a component to an entity, returning the one it replaced.
+    /// A component left on an older entity with the same index is replaced without being returned.
+    /// ```
+    /// # use shared::engine::entity::{Entities, storage::ComponentStorage};
+    /// let entities = Entities::new();
+    /// let (a, b) = (entities.spawn(), entities.spawn());
+    /// let mut health = ComponentStorage::new();
+    /// assert_eq!(health.insert(a, 20), None);
+    /// assert_eq!(health.insert(b, 10), None);
+    /// assert_eq!(health.insert(a, 15), Some(20));
+    /// assert_eq!(health.remove(a), Some(15));
+    /// assert_eq!(health.get(b), Some(&10));
+    /// assert_eq!(health.len(), 1);
+    /// ```
+    pub fn insert(&mut self, id: EntityId, component: T) -> Option<T> {
+        let index = id.index() as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, NONE);
+        }
+        let tick = self.tick();
+        let dense = self.sparse[index];
+        if dense != NONE {
+            let old = std::mem::replace(&mut self.components[dense as usize], component);
+            let previous = std::mem::replace(&mut self.dense[dense as usize], id);
+            let ticks = &mut self.ticks[dense as usize];
+            if previous != id {
+                ticks.added = tick;
+            }
+            ticks.changed = tick;
+            return if previous == id { Some(old) } else { None };
+        }
+        self.sparse[index] = self.dense.len() as u32;
+        self.dense.push(id);
+        self.components.push(component);
+        self.ticks.push(ComponentTicks { added: tick, changed: tick });
+        return None;
+    }
+
+    /// Remove an entity's component, moving the last component into its place.
+    pub fn remove(&mut self, id: EntityId) -> Option<T> {
+        let dense = self.dense_index(id)?;
+        self.sparse[id.index() as usize] = NONE;
+        let last = *self.dense.last().unwrap();
+        if last != id {
+            self.sparse[last.index() as usize] = dense as u32;
+        }
+        self.dense.swap_remove(dense);
+        self.ticks.swap_remove(dense);
+        return Some(self.components.swap_remove(dense));
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&T> {
+        return self.dense_index(id).map(|dense| &self.components[dense]);
+    }
+
+    /// Mutable access to a component, marking it as changed once written to.
+    /// ```
+    /// # use shared::engine::entity::{Entities, storage::ComponentStorage};
+    /// let entities = Entities::new();
+    /// let id = entities.spawn();
+    /// let mut storage = ComponentStorage::new();
+    /// storage.insert(id, 1);
+    /// let added = storage.ticks(id).unwrap().changed;
+    /// let mut value = storage.get_mut(id).unwrap();
+    /// *value.bypass_change_detection() += 1;
+    /// assert_eq!(storage.get(id), Some(&2));
+    /// assert_eq!(storage.ticks(id).unwrap().changed, added);
+    /// ```
+    pub fn get_mut(&mut self, id: EntityId) -> Option<Mut<'_, T>> {
+        let dense = self.dense_index(id)?;
+        let change_tick = self.tick();
+        return Some(Mut { value: &mut self.components[dense], ticks: &mut self.ticks[dense], change_tick });
+    }
+
+    /// Change ticks of an entity's component.
+    pub fn ticks(&self, id: EntityId) -> Option<ComponentTicks> {
+        return self.dense_index(id).map(|dense| self.ticks[dense]);
+    }
+
+    pub fn contains(&self, id: EntityId) -> bool {
+        return self.dense_index(id).is_some();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.dense.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.dense.is_empty();
+    }
+
+    /// Entities with this component, in storage order.
+    pub fn entities(&self) -> &[EntityId] {
+        return &self.dense;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
+        return self.dense.iter().copied().zip(self.components.iter());
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, Mut<'_, T>)> {
+        let change_tick = self.tick();
+        return self.dense.iter().copied().zip(self.components.iter_mut().zip(self.ticks.iter_mut()))
+            .map(move |(id, (value, ticks))| (id, Mut { value, ticks, change_tick }));
+    }
+}
+
+impl<T: Component> Default for ComponentStorage<T> {
+    fn default() -> ComponentStorage<T> {
+        return ComponentStorage::new();
+    }
+}
+
+/// A component storage of unknown type, so the registry can remove every component of a despawned entity.
+pub(crate) trait ErasedStorage: Send + Sync {
+    fn remove_entity(&self, id: EntityId);
+
+    fn contains_entity(&self, id: EntityId) -> bool;
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+impl<T: Component> ErasedStorage for RwLock<ComponentStorage<T>> {
+    fn remove_entity(&self, id: EntityId) {
+        self.write().unwrap().remove(id);
+    }
+
+    fn contains_entity(&self, id: EntityId) -> bool {
+        return self.read().unwrap().contains(id);
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        return self;
+    }
+}
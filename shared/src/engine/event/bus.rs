@@ -0,0 +1,174 @@
+use std::{any::{Any, TypeId}, collections::HashMap, sync::{Arc, Mutex}};
+
+type Handler = Arc<dyn Fn(&dyn Any) + Send + Sync>;
+
+struct Subscriber {
+    id: u64,
+    handler: Handler
+}
+
+struct Inner {
+    subscribers: HashMap<TypeId, Vec<Subscriber>>,
+    pending: Vec<(TypeId, Box<dyn Any + Send>)>,
+    next_subscriber_id: u64
+}
+
+/// Returned when subscribing to a message bus, used to unsubscribe later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Subscription {
+    type_id: TypeId,
+    id: u64
+}
+
+/// Typed publish/subscribe bus so loosely coupled modules can react to each other
+/// without direct dependencies, such as audio reacting to world events.
+/// Messages are queued when published, and delivered in publish order when dispatch() is called,
+/// so delivery happens at well defined sync points rather than in the middle of a system's work.
+/// Worlds dispatch at the end of every tick. Messages that can't wait are delivered with publish_now() instead.
+/// Thread safe, so messages can be published from jobs.
+pub struct MessageBus {
+    inner: Mutex<Inner>
+}
+
+impl MessageBus {
+    /// Create a new message bus with no subscribers.
+    /// ```
+    /// # use shared::engine::event::bus::MessageBus;
+    /// let bus = MessageBus::new();
+    /// assert_eq!(bus.pending_count(), 0);
+    /// ```
+    pub fn new() -> MessageBus {
+        return MessageBus { inner: Mutex::new(Inner {
+            subscribers: HashMap::new(),
+            pending: Vec::new(),
+            next_subscriber_id: 0
+        })};
+    }
+
+    /// Subscribe to every message of type T. The handler is called during dispatch(), on the dispatching thread.
+    /// ```
+    /// # use shared::engine::event::bus::MessageBus;
+    /// # use std::sync::{Arc, atomic::{AtomicU32, Ordering}};
+    /// struct NetworkStatus { connected: bool }
+    ///
+    /// let bus = MessageBus::new();
+    /// let disconnects = Arc::new(AtomicU32::new(0));
+    /// let captured = disconnects.clone();
+    /// bus.subscribe(move |status: &NetworkStatus| {
+    ///     if !status.connected { captured.fetch_add(1, Ordering::Relaxed); }
+    /// });
+    /// bus.publish(NetworkStatus { connected: false });
+    /// bus.dispatch();
+    /// assert_eq!(disconnects.load(Ordering::Relaxed), 1);
+    /// ```
+    pub fn subscribe<T, F>(&self, handler: F) -> Subscription
+    where T: Any + Send, F: Fn(&T) + Send + Sync + 'static {
+        let type_id = TypeId::of::<T>();
+        let handler: Handler = Arc::new(move |message: &dyn Any| {
+            handler(message.downcast_ref::<T>().unwrap());
+        });
+
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_subscriber_id;
+        inner.next_subscriber_id += 1;
+        inner.subscribers.entry(type_id).or_default().push(Subscriber { id, handler });
+        return Subscription { type_id, id };
+    }
+
+    /// Remove a subscription. Returns false if it was already removed.
+    /// ```
+    /// # use shared::engine::event::bus::MessageBus;
+    /// let bus = MessageBus::new();
+    /// let subscription = bus.subscribe(|_: &u32| panic!("should not be called"));
+    /// assert!(bus.unsubscribe(subscription));
+    /// bus.publish(5u32);
+    /// bus.dispatch();
+    /// ```
+    pub fn unsubscribe(&self, subscription: Subscription) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let subscribers = match inner.subscribers.get_mut(&subscription.type_id) {
+            Some(subscribers) => subscribers,
+            None => return false
+        };
+        let count_before = subscribers.len();
+        subscribers.retain(|subscriber| subscriber.id != subscription.id);
+        return subscribers.len() != count_before;
+    }
+
+    /// Queue a message for delivery on the next dispatch().
+    /// Messages with no subscribers are dropped on dispatch.
+    pub fn publish<T>(&self, message: T)
+    where T: Any + Send {
+        self.inner.lock().unwrap().pending.push((TypeId::of::<T>(), Box::new(message)));
+    }
+
+    /// Deliver a message to its subscribers right away, on the calling thread, before returning.
+    /// For messages subscribers must see before the publisher carries on, such as a chunk about to be unloaded.
+    /// ```
+    /// # use shared::engine::event::bus::MessageBus;
+    /// # use std::sync::{Arc, atomic::{AtomicU32, Ordering}};
+    /// let bus = MessageBus::new();
+    /// let received = Arc::new(AtomicU32::new(0));
+    /// let captured = received.clone();
+    /// bus.subscribe(move |num: &u32| { captured.fetch_add(*num, Ordering::Relaxed); });
+    /// bus.publish_now(5u32);
+    /// assert_eq!(received.load(Ordering::Relaxed), 5);
+    /// assert_eq!(bus.pending_count(), 0);
+    /// ```
+    pub fn publish_now<T>(&self, message: T)
+    where T: Any + Send {
+        self.deliver(TypeId::of::<T>(), &message);
+    }
+
+    /// Number of messages waiting for the next dispatch().
+    pub fn pending_count(&self) -> usize {
+        return self.inner.lock().unwrap().pending.len();
+    }
+
+    /// Deliver every queued message to its subscribers, in the order they were published.
+    /// Messages published by handlers during dispatch are delivered on the following dispatch.
+    /// Returns the number of messages delivered.
+    /// ```
+    /// # use shared::engine::event::bus::MessageBus;
+    /// # use std::sync::{Arc, Mutex};
+    /// let bus = Arc::new(MessageBus::new());
+    /// let received = Arc::new(Mutex::new(Vec::new()));
+    /// let captured = received.clone();
+    /// let republish = bus.clone();
+    /// bus.subscribe(move |num: &u32| {
+    ///     captured.lock().unwrap().push(*num);
+    ///     if *num == 1 { republish.publish(3u32); }
+    /// });
+    /// bus.publish(1u32);
+    /// bus.publish(2u32);
+    /// assert_eq!(bus.dispatch(), 2);
+    /// assert_eq!(*received.lock().unwrap(), vec![1, 2]);
+    /// assert_eq!(bus.dispatch(), 1);
+    /// assert_eq!(*received.lock().unwrap(), vec![1, 2, 3]);
+    /// ```
+    pub fn dispatch(&self) -> usize {
+        let pending = std::mem::take(&mut self.inner.lock().unwrap().pending);
+        let count = pending.len();
+        for (type_id, message) in pending {
+            self.deliver(type_id, message.as_ref());
+        }
+        return count;
+    }
+
+    fn deliver(&self, type_id: TypeId, message: &dyn Any) {
+        // Handlers are cloned out so they run without the lock, allowing them to publish or subscribe.
+        let handlers: Vec<Handler> = match self.inner.lock().unwrap().subscribers.get(&type_id) {
+            Some(subscribers) => subscribers.iter().map(|subscriber| subscriber.handler.clone()).collect(),
+            None => return
+        };
+        for handler in handlers.iter() {
+            handler(message);
+        }
+    }
+}
+
+impl Default for MessageBus {
+    fn default() -> Self {
+        return MessageBus::new();
+    }
+}
@@ -0,0 +1,65 @@
+use crate::engine::{block::BlockId, entity::{replication::ClientId, EntityId}, math::coords::{BlockPos, ChunkPos}};
+
+/// A block was set to a different id. Published by World::set_block() for the end of the tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockChanged {
+    pub pos: BlockPos,
+    pub old: BlockId,
+    pub new: BlockId
+}
+
+/// A chunk was added to the world, whether loaded from disk or generated. Published for the end of the tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkLoaded {
+    pub pos: ChunkPos
+}
+
+/// A chunk was removed from the world. Delivered right away, as its data is gone by the end of the tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkUnloaded {
+    pub pos: ChunkPos
+}
+
+/// Light was written in a chunk, whether it was lit from scratch or relit around changed blocks.
+/// Published by light propagation for the end of the tick, once per chunk each pass touched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LightChanged {
+    pub pos: ChunkPos
+}
+
+/// An entity was spawned during the tick. Published for the end of the tick it was spawned in, once its
+/// components have been added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntitySpawned {
+    pub entity: EntityId
+}
+
+/// A player finished logging in. Published by the server, for chat, replication and the like to greet them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayerJoined {
+    pub client: ClientId,
+    pub name: String
+}
+
+/// A player disconnected or was kicked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayerLeft {
+    pub client: ClientId,
+    pub name: String
+}
+
+/// A player broke a block. Published by the server once it has checked they may, after the block is set to air.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockBroken {
+    pub client: ClientId,
+    pub pos: BlockPos,
+    pub block: BlockId
+}
+
+/// A player placed a block. Published by the server once it has checked they may, after the block is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockPlaced {
+    pub client: ClientId,
+    pub pos: BlockPos,
+    pub block: BlockId
+}
\ No newline at end of file
@@ -0,0 +1,173 @@
+use std::sync::Arc;
+
+use crate::engine::{
+    block::{state::{Property, PropertyValue}, BlockId, BlockRegistry, AIR, registry::BlockRegistryError},
+    math::{coords::BlockPos, direction::Direction},
+    world::tick::{BlockTicker, TickContext, TickHandlers, TickScheduler}
+};
+
+/// Level of a source block, which never drains.
+pub const SOURCE_LEVEL: u8 = 0;
+/// Level of fluid fed from directly above. It spreads like a source but drains once its feed is gone.
+pub const FALLING_LEVEL: u8 = 8;
+
+/// How a fluid flows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FluidSettings {
+    /// Furthest level flowing fluid reaches from a source, at most 7.
+    pub max_distance: u8,
+    /// Ticks between each step of flow.
+    pub tick_delay: u64,
+    /// Whether flowing fluid between 2 sources becomes a source itself, making pools infinite.
+    pub infinite: bool
+}
+
+impl FluidSettings {
+    pub const WATER: FluidSettings = FluidSettings { max_distance: 7, tick_delay: 5, infinite: true };
+    pub const LAVA: FluidSettings = FluidSettings { max_distance: 3, tick_delay: 30, infinite: false };
+}
+
+/// A fluid, stored as block states with a "level" property from SOURCE_LEVEL to FALLING_LEVEL,
+/// where the levels between are how far flowing fluid is from its source.
+/// Flow is a cellular automaton run by scheduled block ticks: each tick a fluid block recalculates its level from its
+/// neighbors, then spreads downwards, or sideways when it can't fall.
+pub struct FluidType {
+    name: String,
+    states: [BlockId; FALLING_LEVEL as usize + 1],
+    settings: FluidSettings
+}
+
+impl FluidType {
+    /// Register a fluid's block states, and its tick handler for each of them.
+    /// Will panic in debug mode if the max distance is not between 1 and 7.
+    pub fn register(registry: &mut BlockRegistry, handlers: &mut TickHandlers, name: &str, settings: FluidSettings) -> Result<Arc<FluidType>, BlockRegistryError> {
+        debug_assert!(settings.max_distance > 0 && settings.max_distance < FALLING_LEVEL, "Fluid max distance must be between 1 and 7");
+        let source = registry.builder(name).property(Property::int("level", SOURCE_LEVEL, FALLING_LEVEL)).register()?;
+        let states = std::array::from_fn(|level| registry.with(source, "level", PropertyValue::Int(level as u8)).unwrap());
+        let fluid = Arc::new(FluidType { name: name.to_string(), states, settings });
+        for id in states {
+            handlers.register(id, Arc::new(FluidTicker(fluid.clone())));
+        }
+        return Ok(fluid);
+    }
+
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    pub fn settings(&self) -> FluidSettings {
+        return self.settings;
+    }
+
+    /// Block state of a level.
+    pub fn state(&self, level: u8) -> BlockId {
+        return self.states[level as usize];
+    }
+
+    pub fn source(&self) -> BlockId {
+        return self.states[SOURCE_LEVEL as usize];
+    }
+
+    /// Level of a block state. None if it isn't this fluid.
+    pub fn level(&self, id: BlockId) -> Option<u8> {
+        return self.states.iter().position(|state| *state == id).map(|level| level as u8);
+    }
+
+    pub fn is_fluid(&self, id: BlockId) -> bool {
+        return self.level(id).is_some();
+    }
+
+    /// Schedule updates around a block that changed, such as one broken next to water, so fluid flows into or out of it.
+    pub fn notify(&self, scheduler: &TickScheduler, block: BlockPos) {
+        scheduler.schedule(block, self.settings.tick_delay);
+        for direction in Direction::ALL {
+            scheduler.schedule(block + direction.offset(), self.settings.tick_delay);
+        }
+    }
+
+    fn schedule_around(&self, context: &TickContext, block: BlockPos) {
+        context.schedule(block, self.settings.tick_delay);
+        for direction in Direction::ALL {
+            context.schedule(block + direction.offset(), self.settings.tick_delay);
+        }
+    }
+
+    /// Level a flowing block should have from its neighbors, given in Direction order. None if it should drain away.
+    fn expected_level(&self, neighbors: &[BlockId; 6]) -> Option<u8> {
+        if self.is_fluid(neighbors[Direction::PosY.index()]) {
+            return Some(FALLING_LEVEL);
+        }
+        let horizontal = Direction::HORIZONTAL.map(|direction| self.level(neighbors[direction.index()]));
+        let sources = horizontal.iter().filter(|level| **level == Some(SOURCE_LEVEL)).count();
+        let below = neighbors[Direction::NegY.index()];
+        let supported = below != AIR && (!self.is_fluid(below) || self.level(below) == Some(SOURCE_LEVEL));
+        if self.settings.infinite && sources >= 2 && supported {
+            return Some(SOURCE_LEVEL);
+        }
+        // Falling fluid spreads as if it were a source where it lands.
+        let nearest = horizontal.iter().flatten().map(|level| if *level == FALLING_LEVEL { SOURCE_LEVEL } else { *level }).min()?;
+        let level = nearest + 1;
+        if level > self.settings.max_distance {
+            return None;
+        }
+        return Some(level);
+    }
+
+    fn tick(&self, context: &TickContext, block: BlockPos, id: BlockId) {
+        let Some(mut level) = self.level(id) else {
+            return;
+        };
+        let mut neighbors = [AIR; 6];
+        for direction in Direction::ALL {
+            match context.world.get_block(block + direction.offset()) {
+                Some(neighbor) => neighbors[direction.index()] = neighbor,
+                None => {
+                    // A neighbor's chunk isn't loaded, so wait until it is rather than guessing at what's there.
+                    context.schedule(block, self.settings.tick_delay);
+                    return;
+                }
+            }
+        }
+
+        if level != SOURCE_LEVEL {
+            let expected = self.expected_level(&neighbors);
+            if expected != Some(level) {
+                context.world.set_block(block, expected.map(|level| self.state(level)).unwrap_or(AIR));
+                self.schedule_around(context, block);
+                match expected {
+                    Some(expected) => level = expected,
+                    None => return
+                }
+            }
+        }
+
+        let below = neighbors[Direction::NegY.index()];
+        if below == AIR {
+            context.world.set_block(block + Direction::NegY.offset(), self.state(FALLING_LEVEL));
+            self.schedule_around(context, block + Direction::NegY.offset());
+            return;
+        }
+        if self.is_fluid(below) {
+            return;
+        }
+        let next = if level == FALLING_LEVEL { 1 } else { level + 1 };
+        if next > self.settings.max_distance {
+            return;
+        }
+        for direction in Direction::HORIZONTAL {
+            if neighbors[direction.index()] == AIR {
+                let neighbor = block + direction.offset();
+                context.world.set_block(neighbor, self.state(next));
+                self.schedule_around(context, neighbor);
+            }
+        }
+    }
+}
+
+struct FluidTicker(Arc<FluidType>);
+
+impl BlockTicker for FluidTicker {
+    fn scheduled_tick(&self, context: &TickContext, block: BlockPos, id: BlockId) {
+        self.0.tick(context, block, id);
+    }
+}
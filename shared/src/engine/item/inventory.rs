@@ -0,0 +1,344 @@
+use std::{any::Any, io, sync::Arc};
+
+use crate::engine::{entity::serialize::ComponentTypes, world::block_entity::{BlockEntity, BlockEntityTypes}};
+
+use super::{ItemId, ItemRegistry, ItemStack};
+
+/// Kind of the block entity holding a container's items, such as a chest's.
+pub const CONTAINER_KIND: &str = "cube:container";
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+struct Reader<'a> {
+    data: &'a [u8]
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < count {
+            return Err(invalid("Inventory data ended early"));
+        }
+        let (taken, rest) = self.data.split_at(count);
+        self.data = rest;
+        return Ok(taken);
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+}
+
+/// Numbered slots each holding a stack of items or nothing, such as a player's inventory or a chest.
+/// ```
+/// # use shared::engine::item::{Inventory, ItemRegistry, ItemStack};
+/// let mut items = ItemRegistry::new();
+/// let stone = items.register("cube:stone").unwrap();
+/// let pickaxe = items.builder("cube:pickaxe").max_stack(1).register().unwrap();
+/// let mut inventory = Inventory::new(3);
+///
+/// // Inserting tops up stacks of the same item before filling empty slots.
+/// assert_eq!(inventory.insert(ItemStack::new(stone, 40), &items), None);
+/// assert_eq!(inventory.insert(ItemStack::new(stone, 40), &items), None);
+/// assert_eq!(inventory.get(0).unwrap().count, 64);
+/// assert_eq!(inventory.get(1).unwrap().count, 16);
+/// assert_eq!(inventory.insert(ItemStack::new(pickaxe, 2), &items), Some(ItemStack::new(pickaxe, 1)));
+///
+/// assert_eq!(inventory.extract(stone, 70), Some(ItemStack::new(stone, 70)));
+/// assert_eq!(inventory.count(stone), 10);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>
+}
+
+impl Inventory {
+    /// An empty inventory with a number of slots.
+    pub fn new(size: usize) -> Inventory {
+        return Inventory { slots: vec![None; size] };
+    }
+
+    pub fn size(&self) -> usize {
+        return self.slots.len();
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        return &self.slots;
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&ItemStack> {
+        return self.slots.get(slot)?.as_ref();
+    }
+
+    /// Put a stack in a slot, returning what was there. Empty stacks leave the slot empty.
+    /// Does nothing but give the stack back for slots out of range.
+    pub fn set(&mut self, slot: usize, stack: Option<ItemStack>) -> Option<ItemStack> {
+        let stack = stack.filter(|stack| !stack.is_empty());
+        return match self.slots.get_mut(slot) {
+            Some(current) => std::mem::replace(current, stack),
+            None => stack
+        };
+    }
+
+    /// Empty a slot, returning what was in it.
+    pub fn take(&mut self, slot: usize) -> Option<ItemStack> {
+        return self.set(slot, None);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.slots.iter().all(|slot| slot.is_none());
+    }
+
+    /// How many of an item the inventory holds, across every slot.
+    pub fn count(&self, item: ItemId) -> u32 {
+        return self.slots.iter().flatten().filter(|stack| stack.item == item).map(|stack| stack.count).sum();
+    }
+
+    /// Add a stack, topping up stacks of the same item in slot order, then filling empty slots.
+    /// Returns what didn't fit.
+    pub fn insert(&mut self, mut stack: ItemStack, items: &ItemRegistry) -> Option<ItemStack> {
+        for existing in self.slots.iter_mut().flatten() {
+            existing.merge(&mut stack, items);
+        }
+        let max_stack = items.max_stack(stack.item);
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if stack.is_empty() {
+                break;
+            }
+            *slot = Some(stack.split(max_stack));
+        }
+        return Some(stack).filter(|stack| !stack.is_empty());
+    }
+
+    /// Add a stack to one slot, if it's empty or holds the same thing. Returns what didn't fit.
+    pub fn insert_into(&mut self, slot: usize, mut stack: ItemStack, items: &ItemRegistry) -> Option<ItemStack> {
+        match self.slots.get_mut(slot) {
+            Some(Some(existing)) => {
+                existing.merge(&mut stack, items);
+            },
+            Some(empty) => {
+                *empty = Some(stack.split(items.max_stack(stack.item)));
+            },
+            None => {}
+        }
+        return Some(stack).filter(|stack| !stack.is_empty());
+    }
+
+    /// Take up to a number of an item, from stacks of the same thing as the first one found.
+    /// None if the inventory has none of the item.
+    pub fn extract(&mut self, item: ItemId, count: u32) -> Option<ItemStack> {
+        let first = self.slots.iter().flatten().find(|stack| stack.item == item)?;
+        let mut taken = ItemStack { item, count: 0, data: first.data.clone() };
+        for slot in self.slots.iter_mut() {
+            let Some(stack) = slot.as_mut().filter(|stack| stack.stacks_with(&taken)) else {
+                continue;
+            };
+            taken.count += stack.split(count - taken.count).count;
+            if stack.is_empty() {
+                *slot = None;
+            }
+            if taken.count == count {
+                break;
+            }
+        }
+        return Some(taken);
+    }
+
+    /// Take up to a number of items out of one slot. None if it's empty.
+    pub fn extract_slot(&mut self, slot: usize, count: u32) -> Option<ItemStack> {
+        let stack = self.slots.get_mut(slot)?.as_mut()?;
+        let taken = stack.split(count);
+        if stack.is_empty() {
+            self.slots[slot] = None;
+        }
+        return Some(taken).filter(|taken| !taken.is_empty());
+    }
+
+    /// Move the stack in one slot onto another, as when clicking a stack into a slot: merged as far as it fits
+    /// with a stack of the same thing, or else swapped with whatever is there.
+    /// ```
+    /// # use shared::engine::item::{Inventory, ItemRegistry, ItemStack};
+    /// let mut items = ItemRegistry::new();
+    /// let (stone, dirt) = (items.register("cube:stone").unwrap(), items.register("cube:dirt").unwrap());
+    /// let mut inventory = Inventory::new(3);
+    /// inventory.set(0, Some(ItemStack::new(stone, 50)));
+    /// inventory.set(1, Some(ItemStack::new(stone, 50)));
+    /// inventory.set(2, Some(ItemStack::new(dirt, 1)));
+    ///
+    /// inventory.merge(0, 1, &items);
+    /// assert_eq!((inventory.get(0).unwrap().count, inventory.get(1).unwrap().count), (36, 64));
+    /// inventory.merge(2, 1, &items);
+    /// assert_eq!(inventory.get(1), Some(&ItemStack::new(dirt, 1)));
+    /// assert_eq!(inventory.get(2).unwrap().count, 64);
+    /// ```
+    pub fn merge(&mut self, from: usize, to: usize, items: &ItemRegistry) {
+        if from == to || from >= self.slots.len() || to >= self.slots.len() {
+            return;
+        }
+        let Some(mut moving) = self.slots[from].take() else {
+            return;
+        };
+        match self.slots[to].as_mut() {
+            Some(target) if target.stacks_with(&moving) => {
+                target.merge(&mut moving, items);
+                self.slots[from] = Some(moving).filter(|stack| !stack.is_empty());
+            },
+            _ => self.slots[from] = self.slots[to].replace(moving)
+        }
+    }
+
+    /// Encode as little-endian binary, with items by name as their ids change between sessions: the slot count and
+    /// the number of stacks, then for each stack its slot, length prefixed item name, count, and length prefixed
+    /// data, if it has any.
+    pub fn encode(&self, items: &ItemRegistry) -> Vec<u8> {
+        let stacks: Vec<(usize, &ItemStack, &str)> = self.slots.iter().enumerate()
+            .filter_map(|(slot, stack)| {
+                let stack = stack.as_ref()?;
+                return Some((slot, stack, items.name(stack.item)?));
+            })
+            .collect();
+        let mut out = Vec::new();
+        out.extend_from_slice(&(self.slots.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(stacks.len() as u32).to_le_bytes());
+        for (slot, stack, name) in stacks {
+            out.extend_from_slice(&(slot as u32).to_le_bytes());
+            out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(&stack.count.to_le_bytes());
+            match stack.data.as_ref() {
+                Some(data) => {
+                    out.push(1);
+                    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                    out.extend_from_slice(data);
+                },
+                None => out.push(0)
+            }
+        }
+        return out;
+    }
+
+    /// Decode an inventory written by encode(). Items that are no longer registered, such as from a removed mod,
+    /// are dropped.
+    /// ```
+    /// # use shared::engine::item::{Inventory, ItemRegistry, ItemStack};
+    /// let mut old = ItemRegistry::new();
+    /// old.register("somemod:ruby").unwrap();
+    /// let old_stone = old.register("cube:stone").unwrap();
+    /// let mut inventory = Inventory::new(9);
+    /// inventory.set(4, Some(ItemStack::new(old_stone, 12).with_data(vec![7])));
+    /// inventory.set(5, Some(ItemStack::new(0, 1)));
+    ///
+    /// let mut items = ItemRegistry::new();
+    /// let stone = items.register("cube:stone").unwrap();
+    /// let loaded = Inventory::decode(&inventory.encode(&old), &items).unwrap();
+    /// assert_eq!(loaded.get(4), Some(&ItemStack::new(stone, 12).with_data(vec![7])));
+    /// assert_eq!(loaded.get(5), None);
+    /// ```
+    pub fn decode(data: &[u8], items: &ItemRegistry) -> io::Result<Inventory> {
+        let mut reader = Reader { data };
+        let mut inventory = Inventory::new(reader.u32()? as usize);
+        let count = reader.u32()?;
+        for _ in 0..count {
+            let slot = reader.u32()? as usize;
+            let name_len = reader.u32()? as usize;
+            let name = std::str::from_utf8(reader.take(name_len)?).map_err(|_| invalid("Item name is not UTF-8"))?;
+            let count = reader.u32()?;
+            let data = match reader.take(1)?[0] {
+                0 => None,
+                _ => {
+                    let data_len = reader.u32()? as usize;
+                    Some(reader.take(data_len)?.to_vec())
+                }
+            };
+            if slot >= inventory.size() {
+                return Err(invalid("Item stack is in a slot outside its inventory"));
+            }
+            if let Some(item) = items.id(name) {
+                inventory.set(slot, Some(ItemStack { item, count, data }));
+            }
+        }
+        return Ok(inventory);
+    }
+}
+
+/// Save inventories of entities, such as players', under "cube:inventory".
+/// ```
+/// # use shared::engine::entity::{serialize::ComponentTypes, Entities};
+/// # use shared::engine::item::{inventory, Inventory, ItemRegistry, ItemStack};
+/// # use std::sync::Arc;
+/// let mut items = ItemRegistry::new();
+/// let stone = items.register("cube:stone").unwrap();
+/// let mut types = ComponentTypes::new();
+/// inventory::register_components(&mut types, Arc::new(items));
+/// let entities = Entities::new();
+/// let player = entities.spawn();
+/// let mut held = Inventory::new(36);
+/// held.set(0, Some(ItemStack::new(stone, 5)));
+/// entities.insert(player, held.clone()).unwrap();
+///
+/// let loaded = types.decode(&entities, &types.encode(&entities, &[player])).unwrap();
+/// assert_eq!(entities.get::<Inventory>(loaded[0]), Some(held));
+/// ```
+pub fn register_components(types: &mut ComponentTypes, items: Arc<ItemRegistry>) {
+    let saving = items.clone();
+    types.register::<Inventory>("cube:inventory", move |inventory, out| out.extend(inventory.encode(&saving)),
+        move |data| Inventory::decode(data, &items));
+}
+
+/// The items of a container block, such as a chest.
+pub struct ContainerBlockEntity {
+    pub inventory: Inventory,
+    items: Arc<ItemRegistry>
+}
+
+impl ContainerBlockEntity {
+    pub fn new(size: usize, items: Arc<ItemRegistry>) -> ContainerBlockEntity {
+        return ContainerBlockEntity { inventory: Inventory::new(size), items };
+    }
+}
+
+impl BlockEntity for ContainerBlockEntity {
+    fn kind(&self) -> &str {
+        return CONTAINER_KIND;
+    }
+
+    fn save(&self, out: &mut Vec<u8>) {
+        out.extend(self.inventory.encode(&self.items));
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
+}
+
+/// Load container block entities, saved with their chunks.
+/// ```
+/// # use shared::engine::item::{inventory::{self, ContainerBlockEntity}, ItemRegistry, ItemStack};
+/// # use shared::engine::world::block_entity::{BlockEntityMap, BlockEntityTypes};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// # use std::sync::Arc;
+/// let mut items = ItemRegistry::new();
+/// let stone = items.register("cube:stone").unwrap();
+/// let items = Arc::new(items);
+/// let mut types = BlockEntityTypes::new();
+/// inventory::register_block_entities(&mut types, items.clone());
+///
+/// let mut chest = ContainerBlockEntity::new(27, items);
+/// chest.inventory.set(3, Some(ItemStack::new(stone, 9)));
+/// let mut map = BlockEntityMap::new();
+/// map.insert(BlockPos::new(1, 2, 3), Box::new(chest));
+/// let decoded = BlockEntityMap::decode(&map.encode(), ChunkPos::ORIGIN, &types).unwrap();
+/// let entity = decoded.get(BlockPos::new(1, 2, 3)).unwrap().lock().unwrap();
+/// assert_eq!(entity.as_any().downcast_ref::<ContainerBlockEntity>().unwrap().inventory.count(stone), 9);
+/// ```
+pub fn register_block_entities(types: &mut BlockEntityTypes, items: Arc<ItemRegistry>) {
+    types.register(CONTAINER_KIND, move |data| {
+        let inventory = Inventory::decode(data, &items)?;
+        return Ok(Box::new(ContainerBlockEntity { inventory, items: items.clone() }));
+    });
+}
\ No newline at end of file
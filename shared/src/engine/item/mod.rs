@@ -0,0 +1,10 @@
+/// Dense numeric runtime id of an item. Only stable within a session; saves persist the namespaced string id.
+pub type ItemId = u16;
+
+pub mod registry;
+pub mod stack;
+pub mod inventory;
+
+pub use registry::ItemRegistry;
+pub use stack::ItemStack;
+pub use inventory::Inventory;
\ No newline at end of file
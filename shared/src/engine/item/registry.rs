@@ -0,0 +1,211 @@
+use std::{collections::HashMap, fmt};
+
+use crate::engine::block::{registry::is_valid_name, BlockId, BlockRegistry, AIR};
+
+use super::ItemId;
+
+/// Most items of one kind a slot holds, unless the item says otherwise.
+pub const DEFAULT_MAX_STACK: u32 = 64;
+
+/// Reason an item could not be registered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ItemRegistryError {
+    /// Registration happens at startup, and the registry is frozen before any world is loaded.
+    Frozen,
+    /// Names must be "namespace:path", using only lowercase letters, digits, and underscores.
+    InvalidName(String),
+    Duplicate(String),
+    /// Stacks must hold at least one item.
+    InvalidMaxStack(String),
+    /// Every numeric id is in use.
+    Full
+}
+
+impl fmt::Display for ItemRegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ItemRegistryError::Frozen => write!(f, "items cannot be registered after the registry is frozen"),
+            ItemRegistryError::InvalidName(name) => write!(f, "invalid item id {}, expected namespace:path", name),
+            ItemRegistryError::Duplicate(name) => write!(f, "item {} is already registered", name),
+            ItemRegistryError::InvalidMaxStack(name) => write!(f, "item {} must stack to at least 1", name),
+            ItemRegistryError::Full => write!(f, "no more item ids are available")
+        };
+    }
+}
+
+impl std::error::Error for ItemRegistryError {}
+
+/// A kind of item, such as a stone block or a pickaxe.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemType {
+    name: String,
+    max_stack: u32,
+    /// Block placed by using the item, if it places one.
+    block: Option<BlockId>
+}
+
+impl ItemType {
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    pub fn max_stack(&self) -> u32 {
+        return self.max_stack;
+    }
+
+    pub fn block(&self) -> Option<BlockId> {
+        return self.block;
+    }
+}
+
+/// Declares the properties of an item before registering it.
+pub struct ItemBuilder<'a> {
+    registry: &'a mut ItemRegistry,
+    item: ItemType
+}
+
+impl<'a> ItemBuilder<'a> {
+    /// Most of the item a slot holds, such as 1 for tools.
+    pub fn max_stack(mut self, max_stack: u32) -> Self {
+        self.item.max_stack = max_stack;
+        return self;
+    }
+
+    /// Block the item places.
+    pub fn places(mut self, block: BlockId) -> Self {
+        self.item.block = Some(block);
+        return self;
+    }
+
+    pub fn register(self) -> Result<ItemId, ItemRegistryError> {
+        return self.registry.register_item(self.item);
+    }
+}
+
+/// Maps namespaced string ids such as "cube:stone" to dense numeric ItemIds, in the same way as the BlockRegistry.
+/// Items are registered at startup, then the registry is frozen and shared immutably. Numeric ids are only stable
+/// within a session, so saved items are stored by name.
+/// ```
+/// # use shared::engine::block::BlockRegistry;
+/// # use shared::engine::item::{ItemRegistry, registry::ItemRegistryError};
+/// let mut blocks = BlockRegistry::new();
+/// let stone = blocks.register("cube:stone").unwrap();
+/// let mut items = ItemRegistry::new();
+/// items.register_blocks(&blocks).unwrap();
+/// let pickaxe = items.builder("cube:pickaxe").max_stack(1).register().unwrap();
+/// items.freeze();
+///
+/// let stone_item = items.id("cube:stone").unwrap();
+/// assert_eq!(items.item_for_block(stone), Some(stone_item));
+/// assert_eq!(items.item(stone_item).unwrap().block(), Some(stone));
+/// assert_eq!((items.max_stack(stone_item), items.max_stack(pickaxe)), (64, 1));
+/// assert_eq!(items.id("cube:air"), None);
+/// assert_eq!(items.register("cube:shovel"), Err(ItemRegistryError::Frozen));
+/// ```
+pub struct ItemRegistry {
+    items: Vec<ItemType>,
+    ids: HashMap<String, ItemId>,
+    block_items: HashMap<BlockId, ItemId>,
+    frozen: bool
+}
+
+impl ItemRegistry {
+    pub fn new() -> ItemRegistry {
+        return ItemRegistry { items: Vec::new(), ids: HashMap::new(), block_items: HashMap::new(), frozen: false };
+    }
+
+    /// Register an item that stacks to the default size and places nothing.
+    pub fn register(&mut self, name: &str) -> Result<ItemId, ItemRegistryError> {
+        return self.builder(name).register();
+    }
+
+    pub fn builder(&mut self, name: &str) -> ItemBuilder<'_> {
+        return ItemBuilder { registry: self, item: ItemType { name: name.to_string(), max_stack: DEFAULT_MAX_STACK, block: None } };
+    }
+
+    /// Register an item placing each block other than air, named after the block and placing its default state.
+    pub fn register_blocks(&mut self, blocks: &BlockRegistry) -> Result<(), ItemRegistryError> {
+        for block in blocks.blocks().iter().filter(|block| block.default_state() != AIR) {
+            self.builder(block.name()).places(block.default_state()).register()?;
+        }
+        return Ok(());
+    }
+
+    fn register_item(&mut self, item: ItemType) -> Result<ItemId, ItemRegistryError> {
+        if self.frozen {
+            return Err(ItemRegistryError::Frozen);
+        }
+        if !is_valid_name(&item.name) {
+            return Err(ItemRegistryError::InvalidName(item.name));
+        }
+        if self.ids.contains_key(&item.name) {
+            return Err(ItemRegistryError::Duplicate(item.name));
+        }
+        if item.max_stack == 0 {
+            return Err(ItemRegistryError::InvalidMaxStack(item.name));
+        }
+        if self.items.len() > ItemId::MAX as usize {
+            return Err(ItemRegistryError::Full);
+        }
+        let id = self.items.len() as ItemId;
+        self.ids.insert(item.name.clone(), id);
+        if let Some(block) = item.block {
+            self.block_items.entry(block).or_insert(id);
+        }
+        self.items.push(item);
+        return Ok(id);
+    }
+
+    /// Prevent further registration. Called once startup registration is complete, before worlds load.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+        self.items.shrink_to_fit();
+        self.ids.shrink_to_fit();
+        self.block_items.shrink_to_fit();
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        return self.frozen;
+    }
+
+    pub fn id(&self, name: &str) -> Option<ItemId> {
+        return self.ids.get(name).copied();
+    }
+
+    pub fn name(&self, id: ItemId) -> Option<&str> {
+        return self.item(id).map(|item| item.name());
+    }
+
+    pub fn item(&self, id: ItemId) -> Option<&ItemType> {
+        return self.items.get(id as usize);
+    }
+
+    /// Most of an item a slot holds. Ids that aren't registered don't stack.
+    pub fn max_stack(&self, id: ItemId) -> u32 {
+        return self.item(id).map_or(1, |item| item.max_stack);
+    }
+
+    /// The item placing a block, by the block's default state.
+    pub fn item_for_block(&self, block: BlockId) -> Option<ItemId> {
+        return self.block_items.get(&block).copied();
+    }
+
+    /// Registered items, indexed by numeric id.
+    pub fn items(&self) -> &[ItemType] {
+        return &self.items;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.items.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.items.is_empty();
+    }
+}
+
+impl Default for ItemRegistry {
+    fn default() -> ItemRegistry {
+        return ItemRegistry::new();
+    }
+}
\ No newline at end of file
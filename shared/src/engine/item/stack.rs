@@ -0,0 +1,67 @@
+use super::{ItemId, ItemRegistry};
+
+/// Some number of one item, as held in an inventory slot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub count: u32,
+    /// Per stack data beyond the item, such as a tool's wear or a book's text, encoded by whatever uses the item.
+    /// Stacks only merge with stacks of the same item and data.
+    pub data: Option<Vec<u8>>
+}
+
+impl ItemStack {
+    pub fn new(item: ItemId, count: u32) -> ItemStack {
+        return ItemStack { item, count, data: None };
+    }
+
+    pub fn with_data(mut self, data: Vec<u8>) -> ItemStack {
+        self.data = Some(data);
+        return self;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.count == 0;
+    }
+
+    /// Whether another stack holds the same thing, so the two can be merged.
+    pub fn stacks_with(&self, other: &ItemStack) -> bool {
+        return self.item == other.item && self.data == other.data;
+    }
+
+    /// Take up to a number of items off the stack into a stack of their own.
+    /// ```
+    /// # use shared::engine::item::ItemStack;
+    /// let mut stack = ItemStack::new(3, 10);
+    /// assert_eq!(stack.split(4), ItemStack::new(3, 4));
+    /// assert_eq!(stack.count, 6);
+    /// assert_eq!(stack.split(100).count, 6);
+    /// assert!(stack.is_empty());
+    /// ```
+    pub fn split(&mut self, count: u32) -> ItemStack {
+        let taken = count.min(self.count);
+        self.count -= taken;
+        return ItemStack { item: self.item, count: taken, data: self.data.clone() };
+    }
+
+    /// Move as much of another stack of the same thing onto this one as fits, returning how many moved.
+    /// ```
+    /// # use shared::engine::item::{ItemRegistry, ItemStack};
+    /// let mut items = ItemRegistry::new();
+    /// let stone = items.register("cube:stone").unwrap();
+    /// let mut stack = ItemStack::new(stone, 60);
+    /// let mut other = ItemStack::new(stone, 10);
+    /// assert_eq!(stack.merge(&mut other, &items), 4);
+    /// assert_eq!((stack.count, other.count), (64, 6));
+    /// assert_eq!(stack.merge(&mut ItemStack::new(stone, 1).with_data(vec![1]), &items), 0);
+    /// ```
+    pub fn merge(&mut self, other: &mut ItemStack, items: &ItemRegistry) -> u32 {
+        if !self.stacks_with(other) {
+            return 0;
+        }
+        let moved = items.max_stack(self.item).saturating_sub(self.count).min(other.count);
+        self.count += moved;
+        other.count -= moved;
+        return moved;
+    }
+}
\ No newline at end of file
@@ -17,6 +17,11 @@ impl ActiveJobs {
         }
     }
 
+    /// Number of jobs that can still be collected before invoking.
+    pub(crate) fn remaining_capacity(&self) -> usize {
+        return QUEUE_CAPACITY - self.count;
+    }
+
     pub(crate) fn collect_jobs(&mut self, queue: &mut JobRingQueue) {
         debug_assert!((self.count + queue.length) <= QUEUE_CAPACITY, "Too many job to be worked on");
         // All jobs in the unused part of the work array should have no bind.
@@ -33,8 +38,11 @@ impl ActiveJobs {
         queue.write_index = 0;
     }
 
-    pub(crate) fn invoke_all_jobs(&mut self) {
+    /// Invokes every collected job, calling on_invoke with the name of each job before it runs.
+    pub(crate) fn invoke_all_jobs<F>(&mut self, mut on_invoke: F)
+    where F: FnMut(&'static str) {
         for i in 0..self.count {
+            on_invoke(self.work[i].name());
             self.work[i].invoke();
             //let job = std::mem::take(&mut self.work[i]);
             //job.invoke();
@@ -0,0 +1,51 @@
+use std::{cell::Cell, marker::PhantomData, thread};
+
+use super::thread::CURRENT_JOB_THREAD;
+
+/// Handle given to background jobs, allowing them to periodically let other work run.
+/// Multi-second tasks such as world pre-generation should call yield_now() between units of work.
+///
+/// The context is bound to the thread executing the background job, so it cannot be sent elsewhere.
+pub struct BackgroundJobContext {
+    yielded_jobs: Cell<usize>,
+    _not_send: PhantomData<*const ()>
+}
+
+impl BackgroundJobContext {
+    pub(crate) fn new() -> Self {
+        return BackgroundJobContext { yielded_jobs: Cell::new(0), _not_send: PhantomData };
+    }
+
+    /// Executes any jobs that were queued onto the current job thread while this background job was running.
+    /// If called outside of a job thread, this simply yields the OS thread.
+    /// Returns the number of jobs that were executed.
+    /// ```
+    /// # use shared::engine::job::system::JobSystem;
+    /// let job_system = JobSystem::new(1);
+    /// let future = job_system.run_background_job(|ctx| {
+    ///     for _ in 0..10 {
+    ///         std::thread::sleep(std::time::Duration::from_millis(1));
+    ///         ctx.yield_now();
+    ///     }
+    /// });
+    /// // With a single thread, this job can only run when the background job yields.
+    /// let short = job_system.run_job(|| 5);
+    /// assert_eq!(short.wait(), 5);
+    /// future.wait();
+    /// ```
+    pub fn yield_now(&self) -> usize {
+        let job_thread = CURRENT_JOB_THREAD.with(|current| current.get());
+        if job_thread.is_null() {
+            thread::yield_now();
+            return 0;
+        }
+        let executed = unsafe { (*job_thread).execute_yielded_jobs() };
+        self.yielded_jobs.set(self.yielded_jobs.get() + executed);
+        return executed;
+    }
+
+    /// Total number of other jobs this background job has let run through yield_now().
+    pub fn yielded_job_count(&self) -> usize {
+        return self.yielded_jobs.get();
+    }
+}
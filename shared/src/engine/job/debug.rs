@@ -0,0 +1,47 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// Snapshot of a single job thread's scheduling state.
+/// Job names are the type names of the job closures, which include the function that created them.
+#[derive(Clone, Debug, Serialize)]
+pub struct JobThreadDebugDump {
+    pub index: usize,
+    pub is_executing: bool,
+    pub executing_job: Option<&'static str>,
+    /// Highest priority first, then in the order they were queued.
+    pub queued_jobs: Vec<&'static str>,
+    /// Total jobs this thread has executed, including ones run while yielding.
+    pub jobs_executed: usize,
+    /// Jobs that ran interleaved inside a background job's yield point, or a job waiting on a future.
+    pub jobs_run_while_yielding: usize,
+    /// Jobs that were queued onto this thread while it was already busy executing.
+    pub jobs_queued_while_busy: usize
+}
+
+/// Snapshot of the whole job system's scheduling state, from JobSystem::debug_dump().
+#[derive(Clone, Debug, Serialize)]
+pub struct JobSystemDebugDump {
+    pub thread_count: usize,
+    pub current_optimal_thread: usize,
+    pub threads: Vec<JobThreadDebugDump>
+}
+
+impl fmt::Display for JobSystemDebugDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "job system: {} threads, next optimal thread {}", self.thread_count, self.current_optimal_thread)?;
+        for thread in self.threads.iter() {
+            writeln!(f, "  thread {}: executing={} current={} executed={} yielded={} queued_while_busy={}",
+                thread.index,
+                thread.is_executing,
+                thread.executing_job.unwrap_or("none"),
+                thread.jobs_executed,
+                thread.jobs_run_while_yielding,
+                thread.jobs_queued_while_busy)?;
+            for job in thread.queued_jobs.iter() {
+                writeln!(f, "    queued: {}", job)?;
+            }
+        }
+        return Ok(());
+    }
+}
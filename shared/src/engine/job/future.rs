@@ -1,7 +1,10 @@
 use std::sync::{Mutex, Arc, TryLockError};
 
+use super::thread::CURRENT_JOB_THREAD;
+
 struct Inner<T> {
-    data: Option<T>
+    data: Option<T>,
+    continuation: Option<Box<dyn FnOnce(T)>>
 }
 
 pub struct JobFuture<T> {
@@ -20,7 +23,22 @@ impl<T> JobFuture<T> {
     /// let num = future.wait();
     /// assert_eq!(num, 10);
     /// ```
+    /// When called from within a job, the jobs queued on the current job thread are executed while waiting,
+    /// so a job waiting on a job that was queued behind it will not deadlock.
+    /// ```
+    /// # use shared::engine::job::system::JobSystem;
+    /// # use std::sync::Arc;
+    /// let job_system = Arc::new(JobSystem::new(1));
+    /// let inner_system = job_system.clone();
+    /// let outer = job_system.run_job(move || {
+    ///     // Queued onto the only job thread, which is busy running this job.
+    ///     let inner = inner_system.run_job(|| 2);
+    ///     inner.wait() * 2
+    /// });
+    /// assert_eq!(outer.wait(), 4);
+    /// ```
     pub fn wait(&self) -> T {
+        let job_thread = CURRENT_JOB_THREAD.with(|current| current.get());
         loop {
             match self.value.try_lock() {
                 Ok(mut inner) => {
@@ -34,9 +52,57 @@ impl<T> JobFuture<T> {
                     }
                 }
             }
-            std::thread::yield_now();
+            if job_thread.is_null() || unsafe { (*job_thread).execute_yielded_jobs() } == 0 {
+                std::thread::yield_now();
+            }
         }
     }
+
+    /// Atomically check if the job has finished and its value can be taken without waiting.
+    /// ```
+    /// # use shared::engine::job::thread::JobThread;
+    /// let mut job_thread = JobThread::new();
+    /// let future = job_thread.queue_job(|| 10);
+    /// assert!(!future.is_ready());
+    /// job_thread.execute();
+    /// job_thread.wait();
+    /// assert!(future.is_ready());
+    /// ```
+    pub fn is_ready(&self) -> bool {
+        return self.value.lock().unwrap().data.is_some();
+    }
+}
+
+impl<T: 'static> JobFuture<T> {
+    /// Attach a continuation that runs once the job completes, instead of blocking a thread waiting for it.
+    /// The continuation executes on the job thread that completed the job, or immediately
+    /// on the calling thread if the job has already completed.
+    /// Returns a future for the result of the continuation, allowing chains of dependent work.
+    /// ```
+    /// # use shared::engine::job::system::JobSystem;
+    /// let job_system = JobSystem::new(2);
+    /// let chunk_future = job_system.run_job(|| vec![1u16; 16]);
+    /// let mesh_future = chunk_future
+    ///     .then(|blocks| blocks.len())
+    ///     .then(|vertex_count| vertex_count * 4);
+    /// assert_eq!(mesh_future.wait(), 64);
+    /// ```
+    pub fn then<U, F>(self, func: F) -> JobFuture<U>
+    where U: 'static, F: FnOnce(T) -> U + 'static {
+        let (wait_future, in_job_future) = WithinJobFuture::<U>::new();
+        let data = {
+            let mut inner = self.value.lock().unwrap();
+            match inner.data.take() {
+                Some(data) => data,
+                None => {
+                    inner.continuation = Some(Box::new(move |data: T| in_job_future.set(func(data))));
+                    return wait_future;
+                }
+            }
+        };
+        in_job_future.set(func(data));
+        return wait_future;
+    }
 }
 
 
@@ -47,7 +113,7 @@ pub(crate) struct WithinJobFuture<T> {
 impl<T> WithinJobFuture<T> {
     pub(crate) fn new() -> (JobFuture<T>, WithinJobFuture<T>) {
         let wait_job_future = JobFuture {
-            value: Arc::new(Mutex::new(Inner { data: None }))};
+            value: Arc::new(Mutex::new(Inner { data: None, continuation: None }))};
 
         let within_job_future = WithinJobFuture {
             value: wait_job_future.value.clone(),
@@ -57,7 +123,17 @@ impl<T> WithinJobFuture<T> {
     }
 
     pub(crate) fn set(&self, data: T) {
-        let mut inner = self.value.lock().unwrap();
-        (*inner).data = Some(data);
+        let continuation = {
+            let mut inner = self.value.lock().unwrap();
+            match inner.continuation.take() {
+                Some(continuation) => continuation,
+                None => {
+                    inner.data = Some(data);
+                    return;
+                }
+            }
+        };
+        // Run outside of the lock, as the continuation may be arbitrarily long.
+        continuation(data);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,72 @@
+use std::sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Arc, RwLock};
+
+/// Told when every job thread starts and finishes a job, on the thread running it.
+/// Jobs run while a background job yields are started and finished within it, so they nest.
+/// Hooks are called around every job, so they must be cheap and must not add or remove hooks.
+pub trait JobHook: Send + Sync {
+    fn job_started(&self, name: &'static str);
+
+    fn job_finished(&self, name: &'static str);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct JobHookId(u64);
+
+static HOOKS: RwLock<Vec<(JobHookId, Arc<dyn JobHook>)>> = RwLock::new(Vec::new());
+/// Number of hooks added, so running a job doesn't lock the hooks when there are none.
+static HOOK_COUNT: AtomicUsize = AtomicUsize::new(0);
+static NEXT_HOOK_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Call a hook around every job run on any job thread, until it's removed.
+/// ```
+/// # use shared::engine::job::{hooks::{add_job_hook, remove_job_hook, JobHook}, system::JobSystem};
+/// # use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+/// struct Counter(AtomicUsize);
+/// impl JobHook for Counter {
+///     fn job_started(&self, _name: &'static str) {}
+///     fn job_finished(&self, _name: &'static str) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+/// let counter = Arc::new(Counter(AtomicUsize::new(0)));
+/// let id = add_job_hook(counter.clone());
+/// let job_system = JobSystem::new(1);
+/// job_system.run_job(|| 1);
+/// job_system.wait();
+/// assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+/// assert!(remove_job_hook(id));
+/// ```
+pub fn add_job_hook(hook: Arc<dyn JobHook>) -> JobHookId {
+    let id = JobHookId(NEXT_HOOK_ID.fetch_add(1, Ordering::Relaxed));
+    let mut hooks = HOOKS.write().unwrap();
+    hooks.push((id, hook));
+    HOOK_COUNT.store(hooks.len(), Ordering::Release);
+    return id;
+}
+
+/// Stop calling a hook. Returns false if it was already removed.
+pub fn remove_job_hook(id: JobHookId) -> bool {
+    let mut hooks = HOOKS.write().unwrap();
+    let before = hooks.len();
+    hooks.retain(|(hook_id, _)| *hook_id != id);
+    HOOK_COUNT.store(hooks.len(), Ordering::Release);
+    return hooks.len() != before;
+}
+
+pub(crate) fn job_started(name: &'static str) {
+    if HOOK_COUNT.load(Ordering::Acquire) == 0 {
+        return;
+    }
+    for (_, hook) in HOOKS.read().unwrap().iter() {
+        hook.job_started(name);
+    }
+}
+
+pub(crate) fn job_finished(name: &'static str) {
+    if HOOK_COUNT.load(Ordering::Acquire) == 0 {
+        return;
+    }
+    for (_, hook) in HOOKS.read().unwrap().iter().rev() {
+        hook.job_finished(name);
+    }
+}
\ No newline at end of file
@@ -432,26 +432,46 @@ impl Default for Job {
     }
 } */
 
+use std::time::Instant;
+
+use crate::engine::metrics::standard::engine_metrics;
+
+use super::hooks;
+
 
 pub(crate) struct JobContainer {
-    func: Option<Box<dyn FnMut()>>
+    func: Option<Box<dyn FnMut()>>,
+    name: &'static str,
+    queued_at: Option<Instant>
 }
 
 impl JobContainer {
-    pub(crate) fn new<F>(func: F) -> Self
+    /// The name is only used for debugging, such as the job system debug dump.
+    pub(crate) fn new<F>(name: &'static str, func: F) -> Self
     where F: FnMut() + 'static {
-        return JobContainer { func: Some(Box::new(func)) }
+        return JobContainer { func: Some(Box::new(func)), name, queued_at: Some(Instant::now()) }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        return self.name;
+    }
+
+    pub(crate) fn queued_at(&self) -> Option<Instant> {
+        return self.queued_at;
     }
 
     /// Cannot invoke again
     pub(crate) fn invoke(&mut self) {
         let mut f = self.func.take().expect("Cannot invoke None Job func");
+        hooks::job_started(self.name);
         f();
+        engine_metrics().jobs_executed.inc();
+        hooks::job_finished(self.name);
     }
 }
 
 impl Default for JobContainer {
     fn default() -> Self {
-        Self { func: None }
+        Self { func: None, name: "", queued_at: None }
     }
 }
\ No newline at end of file
@@ -4,4 +4,9 @@ mod job_container;
 
 pub mod thread;
 pub mod system;
-pub mod future;
\ No newline at end of file
+pub mod future;
+pub mod topology;
+pub mod background;
+pub mod debug;
+pub mod priority;
+pub mod hooks;
\ No newline at end of file
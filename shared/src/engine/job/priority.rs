@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+/// Number of distinct job priorities.
+pub const JOB_PRIORITY_COUNT: usize = 3;
+
+/// Default time a job can wait behind higher priority jobs before it is run regardless.
+pub const DEFAULT_AGING_WINDOW: Duration = Duration::from_millis(50);
+
+/// Scheduling priority of a job. Each job thread runs its highest priority queued jobs first.
+///
+/// Lower priority jobs age while waiting: once the oldest job of a lower priority has waited longer than
+/// the job thread's aging window, its whole queue runs in the next batch, ahead of any higher priority work.
+/// This bounds how long low priority work (autosave, far chunk generation) can be starved by a sustained stream
+/// of higher priority work, to the aging window plus the duration of the batch that was executing at the time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum JobPriority {
+    High = 0,
+    Normal = 1,
+    Low = 2
+}
+
+impl JobPriority {
+    /// Every priority, from highest to lowest.
+    pub const ALL: [JobPriority; JOB_PRIORITY_COUNT] = [JobPriority::High, JobPriority::Normal, JobPriority::Low];
+
+    /// Index of the priority, where 0 is the highest.
+    pub fn index(&self) -> usize {
+        return *self as usize;
+    }
+}
+
+impl Default for JobPriority {
+    fn default() -> Self {
+        return JobPriority::Normal;
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::{Duration, Instant};
+
 use super::{job_container::JobContainer, system::QUEUE_CAPACITY};
 
 pub(crate) struct JobRingQueue {
@@ -37,6 +39,37 @@ impl JobRingQueue {
         self.length += 1;
     }
 
+    /// Takes every queued job in FIFO order, leaving the queue empty with its indices reset.
+    pub(crate) fn drain(&mut self) -> Vec<JobContainer> {
+        let mut jobs = Vec::with_capacity(self.length);
+        for i in 0..self.length {
+            let index = (self.read_index + i) % QUEUE_CAPACITY;
+            jobs.push(std::mem::take(&mut self.buffer[index]));
+        }
+        self.length = 0;
+        self.read_index = 0;
+        self.write_index = 0;
+        return jobs;
+    }
+
+    /// How long the oldest queued job has been waiting, or zero if the queue is empty.
+    pub(crate) fn oldest_wait(&self, now: Instant) -> Duration {
+        if self.length == 0 {
+            return Duration::ZERO;
+        }
+        return match self.buffer[self.read_index].queued_at() {
+            Some(queued_at) => now.saturating_duration_since(queued_at),
+            None => Duration::ZERO
+        };
+    }
+
+    /// Names of every queued job in FIFO order.
+    pub(crate) fn job_names(&self) -> Vec<&'static str> {
+        return (0..self.length)
+            .map(|i| self.buffer[(self.read_index + i) % QUEUE_CAPACITY].name())
+            .collect();
+    }
+
     // pub(crate) fn pop(&mut self) -> Job {
     //     assert!(!self.is_empty(), "Job ring queue is empty");
 
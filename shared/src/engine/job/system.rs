@@ -1,5 +1,6 @@
-use std::{sync::{Mutex, Arc, RwLock}, thread};
-use super::{thread::JobThread, future::JobFuture};
+use std::{sync::{Mutex, Arc, RwLock}, thread, time::Duration};
+use crate::engine::config::EngineConfig;
+use super::{thread::JobThread, future::JobFuture, background::BackgroundJobContext, debug::JobSystemDebugDump, priority::JobPriority, topology::{CpuTopology, ThreadProfile}};
 
 pub(crate) const QUEUE_CAPACITY: usize = 8192;
 
@@ -48,6 +49,21 @@ impl JobSystem {
     /// assert_eq!(future2.wait(), 456);
     /// ```
     pub fn run_job<T, F>(&self, func: F) -> JobFuture<T>
+    where T: 'static, F: FnMut() -> T + 'static {
+        return self.run_job_with_priority(JobPriority::Normal, func);
+    }
+
+    /// Queue and execute a job at a specific priority on one of the job threads. Automatic load balancing is done.
+    /// Lower priority jobs are guaranteed to run within the aging window, even under sustained higher priority load.
+    /// ```
+    /// # use shared::engine::job::{system::JobSystem, priority::JobPriority};
+    /// let job_system = JobSystem::new(2);
+    /// let autosave = job_system.run_job_with_priority(JobPriority::Low, || "saved");
+    /// let mesh = job_system.run_job_with_priority(JobPriority::High, || 24);
+    /// assert_eq!(mesh.wait(), 24);
+    /// assert_eq!(autosave.wait(), "saved");
+    /// ```
+    pub fn run_job_with_priority<T, F>(&self, priority: JobPriority, func: F) -> JobFuture<T>
     where T: 'static, F: FnMut() -> T + 'static {
         let job_thread = {
             let mut lock = self.inner.lock().unwrap();
@@ -55,12 +71,74 @@ impl JobSystem {
             &mut (*lock).threads[optimal_thread_index] as *mut Box<JobThread>
         };
         unsafe {
-            let future = (*job_thread).queue_job(func);
+            let future = (*job_thread).queue_job_with_priority(priority, func);
+            (*job_thread).execute();
+            return future;
+        }
+    }
+
+    /// Queue and execute a long running job that can yield to other jobs through its BackgroundJobContext.
+    /// Background jobs run at low priority. Automatic load balancing is done, the same as run_job().
+    /// ```
+    /// # use shared::engine::job::system::JobSystem;
+    /// let job_system = JobSystem::new(2);
+    /// let future = job_system.run_background_job(|ctx| {
+    ///     let mut chunks_generated = 0;
+    ///     while chunks_generated < 64 {
+    ///         chunks_generated += 1;
+    ///         ctx.yield_now();
+    ///     }
+    ///     chunks_generated
+    /// });
+    /// assert_eq!(future.wait(), 64);
+    /// ```
+    pub fn run_background_job<T, F>(&self, func: F) -> JobFuture<T>
+    where T: 'static, F: FnMut(&BackgroundJobContext) -> T + 'static {
+        let job_thread = {
+            let mut lock = self.inner.lock().unwrap();
+            let optimal_thread_index = (*lock).get_optimal_thread_for_execution();
+            &mut lock.threads[optimal_thread_index] as *mut Box<JobThread>
+        };
+        unsafe {
+            let future = (*job_thread).queue_background_job(func);
             (*job_thread).execute();
             return future;
         }
     }
 
+    /// Set how long a queued job can be delayed by higher priority jobs on every job thread, before it is run regardless.
+    /// ```
+    /// # use shared::engine::job::system::JobSystem;
+    /// # use std::time::Duration;
+    /// let job_system = JobSystem::new(2);
+    /// job_system.set_aging_window(Duration::from_millis(20));
+    /// ```
+    pub fn set_aging_window(&self, window: Duration) {
+        let lock = self.inner.lock().unwrap();
+        for job_thread in lock.threads.iter() {
+            job_thread.set_aging_window(window);
+        }
+    }
+
+    /// Snapshot the scheduler state of every job thread, for diagnosing misbehaving scheduling in the field.
+    /// The snapshot is not atomic across threads, as jobs continue executing while it is taken.
+    /// ```
+    /// # use shared::engine::job::system::JobSystem;
+    /// let job_system = JobSystem::new(2);
+    /// job_system.run_job(|| 1).wait();
+    /// let dump = job_system.debug_dump();
+    /// assert_eq!(dump.threads.len(), 2);
+    /// println!("{}", dump);
+    /// ```
+    pub fn debug_dump(&self) -> JobSystemDebugDump {
+        let lock = self.inner.lock().unwrap();
+        return JobSystemDebugDump {
+            thread_count: lock.thread_count,
+            current_optimal_thread: lock.current_optimal_thread,
+            threads: lock.threads.iter().enumerate().map(|(i, job_thread)| job_thread.debug_dump(i)).collect()
+        };
+    }
+
     /// Wait for all of the job threads to finish execution.
     /// After wait is called, it can be assumed that there are no active jobs running.
     /// 
@@ -75,9 +153,13 @@ impl JobSystem {
     /// ```
     pub fn wait(&self) {
         thread::yield_now();
-        let lock = self.inner.lock().unwrap();
-        for job_thread in (*lock).threads.iter() {
-            job_thread.wait();
+        let job_threads: Vec<*const JobThread> = {
+            let lock = self.inner.lock().unwrap();
+            lock.threads.iter().map(|job_thread| &**job_thread as *const JobThread).collect()
+        };
+        // Waiting happens without the lock, otherwise executing jobs that queue more jobs would deadlock.
+        for job_thread in job_threads {
+            unsafe { (*job_thread).wait() };
         }
     }
 }
@@ -131,15 +213,33 @@ static mut JOB_SYSTEM_PTR: JobSystemHandle = JobSystemHandle(std::ptr::null_mut(
 /// assert!(max_available_job_threads() > 0);
 /// ```
 pub fn max_available_job_threads() -> usize {
-    return std::thread::available_parallelism().unwrap().get() - 1;
+    return (std::thread::available_parallelism().unwrap().get() - 1).max(1);
+}
+
+/// Get the number of job threads best suited to this machine for a given profile.
+/// Unlike max_available_job_threads(), SMT siblings and efficiency cores are accounted for,
+/// so job threads don't oversubscribe the cores the main thread needs.
+/// Will always be non-zero.
+/// ```
+/// # use shared::engine::job::system::{recommended_job_threads, max_available_job_threads};
+/// # use shared::engine::job::topology::ThreadProfile;
+/// let client_threads = recommended_job_threads(ThreadProfile::Client);
+/// let server_threads = recommended_job_threads(ThreadProfile::Server);
+/// assert!(client_threads > 0);
+/// assert!(client_threads <= server_threads);
+/// assert!(server_threads <= max_available_job_threads());
+/// ```
+pub fn recommended_job_threads(profile: ThreadProfile) -> usize {
+    return CpuTopology::detect().recommended_job_threads(profile);
 }
 
 /// Initializes the job system given a specified thread count.
-/// max_available_job_threads() is a sensible default, because it is total system threads - 1.
+/// recommended_job_threads() is a sensible default, because it accounts for the main thread and the CPU topology.
 /// ```
-/// # use shared::engine::job::system::{job_system_init, max_available_job_threads};
+/// # use shared::engine::job::system::{job_system_init, recommended_job_threads};
+/// # use shared::engine::job::topology::ThreadProfile;
 /// // Initializes the global job system with N threads.
-/// job_system_init(max_available_job_threads());
+/// job_system_init(recommended_job_threads(ThreadProfile::Client));
 /// ```
 pub fn job_system_init(thread_count: usize) {   
     unsafe { 
@@ -154,6 +254,17 @@ pub fn job_system_init(thread_count: usize) {
     }
 }
 
+/// Initializes the job system with the thread count from the engine config, which defaults to recommended_job_threads().
+/// ```
+/// # use shared::engine::config::EngineConfig;
+/// # use shared::engine::job::system::job_system_init_with_config;
+/// # use shared::engine::job::topology::ThreadProfile;
+/// job_system_init_with_config(&EngineConfig::new(), ThreadProfile::Server);
+/// ```
+pub fn job_system_init_with_config(config: &EngineConfig, profile: ThreadProfile) {
+    job_system_init(config.jobs.thread_count(profile));
+}
+
 /// Run a job on the global job system, returning a future for the job.
 /// ```
 /// # use shared::engine::job::system::{job_system_init, job_system_run, max_available_job_threads};
@@ -177,6 +288,60 @@ where T: 'static, F: FnMut() -> T + 'static {
     }; 
 }
 
+/// Run a job at a specific priority on the global job system, returning a future for the job.
+/// ```
+/// # use shared::engine::job::system::{job_system_init, job_system_run_with_priority, max_available_job_threads};
+/// # use shared::engine::job::priority::JobPriority;
+/// job_system_init(max_available_job_threads());
+/// let future = job_system_run_with_priority(JobPriority::Low, || 123);
+/// assert_eq!(future.wait(), 123);
+/// ```
+/// Will panic in debug mode if job_system_init() wasn't called sometime prior.
+pub fn job_system_run_with_priority<T, F>(priority: JobPriority, func: F) -> JobFuture<T>
+where T: 'static, F: FnMut() -> T + 'static {
+    return unsafe { 
+        debug_assert!(!JOB_SYSTEM_PTR.0.is_null(), "Cannot run a job on the global job system because it hasn't been intiailized");
+        (*JOB_SYSTEM_PTR.0).run_job_with_priority(priority, func) 
+    }; 
+}
+
+/// Run a long running job on the global job system, returning a future for the job.
+/// The job should periodically call yield_now() on its context so short jobs aren't blocked behind it.
+/// ```
+/// # use shared::engine::job::system::{job_system_init, job_system_run_background, max_available_job_threads};
+/// job_system_init(max_available_job_threads());
+/// let future = job_system_run_background(|ctx| {
+///     for _ in 0..8 {
+///         ctx.yield_now();
+///     }
+///     123
+/// });
+/// assert_eq!(future.wait(), 123);
+/// ```
+/// Will panic in debug mode if job_system_init() wasn't called sometime prior.
+pub fn job_system_run_background<T, F>(func: F) -> JobFuture<T>
+where T: 'static, F: FnMut(&BackgroundJobContext) -> T + 'static {
+    return unsafe { 
+        debug_assert!(!JOB_SYSTEM_PTR.0.is_null(), "Cannot run a job on the global job system because it hasn't been intiailized");
+        (*JOB_SYSTEM_PTR.0).run_background_job(func) 
+    }; 
+}
+
+/// Snapshot the scheduler state of the global job system. See JobSystem::debug_dump().
+/// ```
+/// # use shared::engine::job::system::{job_system_init, job_system_debug_dump, max_available_job_threads};
+/// job_system_init(max_available_job_threads());
+/// let dump = job_system_debug_dump();
+/// assert!(dump.thread_count > 0);
+/// ```
+/// Will panic in debug mode if job_system_init() wasn't called sometime prior.
+pub fn job_system_debug_dump() -> JobSystemDebugDump {
+    return unsafe { 
+        debug_assert!(!JOB_SYSTEM_PTR.0.is_null(), "Cannot dump the global job system because it hasn't been intiailized");
+        (*JOB_SYSTEM_PTR.0).debug_dump() 
+    }; 
+}
+
 /// Waits for the global job system to finish execution of the current jobs.
 /// After wait is called, it can be assumed that there are no active jobs running.
 /// 
@@ -1,8 +1,13 @@
 #[allow(invalid_reference_casting)]
 
-use std::{sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Condvar, Mutex}, thread};
+use std::{sync::{atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering}, Condvar, Mutex, MutexGuard}, thread, cell::Cell, time::{Duration, Instant}};
 
-use super::{job_container::JobContainer, future::{JobFuture, WithinJobFuture}, ring_queue::JobRingQueue, active_jobs::ActiveJobs};
+use super::{job_container::JobContainer, future::{JobFuture, WithinJobFuture}, ring_queue::JobRingQueue, active_jobs::ActiveJobs, background::BackgroundJobContext, debug::JobThreadDebugDump, priority::{JobPriority, JOB_PRIORITY_COUNT, DEFAULT_AGING_WINDOW}};
+
+thread_local! {
+    /// The job thread that owns the current OS thread, or null if this isn't a job thread.
+    pub(crate) static CURRENT_JOB_THREAD: Cell<*mut JobThread> = const { Cell::new(std::ptr::null_mut()) };
+}
 
 pub struct JobThread {
     is_executing: AtomicBool,
@@ -11,10 +16,17 @@ pub struct JobThread {
 
     pub queued_job_count: AtomicUsize,
 
+    jobs_executed: AtomicUsize,
+    jobs_run_while_yielding: AtomicUsize,
+    jobs_queued_while_busy: AtomicUsize,
+    executing_job: Mutex<Option<&'static str>>,
+    aging_window_nanos: AtomicU64,
+
     thread: Option<thread::JoinHandle<()>>,
     cond_var: (Mutex<bool>, Condvar),
 
-    queue: Mutex<JobRingQueue>,
+    /// One queue per priority, indexed by JobPriority::index().
+    queues: [Mutex<JobRingQueue>; JOB_PRIORITY_COUNT],
     active_work: Mutex<ActiveJobs>
 }
 
@@ -36,8 +48,13 @@ impl JobThread {
             is_pending_kill: AtomicBool::new(false), 
             should_execute: AtomicBool::new(false),
             queued_job_count: AtomicUsize::new(0), 
+            jobs_executed: AtomicUsize::new(0),
+            jobs_run_while_yielding: AtomicUsize::new(0),
+            jobs_queued_while_busy: AtomicUsize::new(0),
+            executing_job: Mutex::new(None),
+            aging_window_nanos: AtomicU64::new(DEFAULT_AGING_WINDOW.as_nanos() as u64),
             cond_var: (Mutex::new(true), Condvar::new()), 
-            queue: Mutex::new(JobRingQueue::new()), 
+            queues: std::array::from_fn(|_| Mutex::new(JobRingQueue::new())), 
             active_work: Mutex::new(ActiveJobs::new()),
             thread: Option::None, 
         });
@@ -47,15 +64,12 @@ impl JobThread {
         job_thread.thread = Option::Some(
             thread::spawn(move || {
                 let _ = &thread_ptr; // Will allow the pointer shenanigans
+                CURRENT_JOB_THREAD.with(|current| current.set(thread_ptr.0));
                 unsafe {
                     while (*thread_ptr.0).is_pending_kill.load(Ordering::Acquire) == false {
                         let (lock, cvar) = &mut (*thread_ptr.0).cond_var;
 
-                        let count = {
-                            // scoped to release lock
-                            (*thread_ptr.0).queue.lock().unwrap().length                      
-                        };
-                        if count > 0 {
+                        if (*thread_ptr.0).has_queued_jobs() {
                             (*thread_ptr.0).execute_queued_jobs();
                             continue;
                         }
@@ -85,18 +99,62 @@ impl JobThread {
     /// // Will not execute until JobThread::execute() is called
     /// let future = job_thread.queue_job(|| 10);
     /// ```
-    pub fn queue_job<T, F>(&mut self, mut func: F) -> JobFuture<T>
+    pub fn queue_job<T, F>(&mut self, func: F) -> JobFuture<T>
+    where T: 'static, F: FnMut() -> T + 'static {
+        return self.queue_job_with_priority(JobPriority::Normal, func);
+    }
+
+    /// Adds a job to this job thread's queue at a specific priority, returning a future for completion.
+    /// Will not execute the queue until JobThread::execute() is called.
+    /// ```
+    /// # use shared::engine::job::thread::JobThread;
+    /// # use shared::engine::job::priority::JobPriority;
+    /// # use std::sync::{Arc, Mutex};
+    /// let mut job_thread = JobThread::new();
+    /// let order = Arc::new(Mutex::new(Vec::new()));
+    /// let (low, high) = (order.clone(), order.clone());
+    /// job_thread.queue_job_with_priority(JobPriority::Low, move || low.lock().unwrap().push("autosave"));
+    /// job_thread.queue_job_with_priority(JobPriority::High, move || high.lock().unwrap().push("mesh"));
+    /// job_thread.execute();
+    /// job_thread.wait();
+    /// assert_eq!(*order.lock().unwrap(), vec!["mesh", "autosave"]);
+    /// ```
+    pub fn queue_job_with_priority<T, F>(&mut self, priority: JobPriority, mut func: F) -> JobFuture<T>
     where T: 'static, F: FnMut() -> T + 'static {
         let (wait_future, in_job_future) = WithinJobFuture::<T>::new();
-        let job = JobContainer::new(move ||
+        let job = JobContainer::new(std::any::type_name::<F>(), move ||
             in_job_future.set(func())
         );
+        self.push_job(priority, job);
 
-        {
-            let mut queue_lock = self.queue.lock().unwrap();
-            (*queue_lock).push(job);
-            self.queued_job_count.fetch_add(1, Ordering::Release);
-        }
+        return wait_future;
+    }
+
+    /// Adds a long running job to this job thread's queue at low priority, returning a future for completion.
+    /// The job receives a BackgroundJobContext, and should periodically call yield_now() on it
+    /// so that short jobs queued onto this thread in the meantime aren't blocked for the entire duration.
+    /// ```
+    /// # use shared::engine::job::thread::JobThread;
+    /// let mut job_thread = JobThread::new();
+    /// let future = job_thread.queue_background_job(|ctx| {
+    ///     let mut sum = 0;
+    ///     for i in 0..100 {
+    ///         sum += i;
+    ///         ctx.yield_now();
+    ///     }
+    ///     sum
+    /// });
+    /// job_thread.execute();
+    /// assert_eq!(future.wait(), 4950);
+    /// ```
+    pub fn queue_background_job<T, F>(&mut self, mut func: F) -> JobFuture<T>
+    where T: 'static, F: FnMut(&BackgroundJobContext) -> T + 'static {
+        let (wait_future, in_job_future) = WithinJobFuture::<T>::new();
+        let job = JobContainer::new(std::any::type_name::<F>(), move || {
+            let ctx = BackgroundJobContext::new();
+            in_job_future.set(func(&ctx))
+        });
+        self.push_job(JobPriority::Low, job);
 
         return wait_future;
     }
@@ -169,15 +227,119 @@ impl JobThread {
         return self.is_executing.load(Ordering::Acquire);
     }
 
+    /// Set how long a queued job can be delayed by higher priority jobs before it is run regardless.
+    /// ```
+    /// # use shared::engine::job::thread::JobThread;
+    /// # use std::time::Duration;
+    /// let job_thread = JobThread::new();
+    /// job_thread.set_aging_window(Duration::from_millis(10));
+    /// assert_eq!(job_thread.aging_window(), Duration::from_millis(10));
+    /// ```
+    pub fn set_aging_window(&self, window: Duration) {
+        self.aging_window_nanos.store(window.as_nanos() as u64, Ordering::Release);
+    }
+
+    pub fn aging_window(&self) -> Duration {
+        return Duration::from_nanos(self.aging_window_nanos.load(Ordering::Acquire));
+    }
+
+    fn push_job(&mut self, priority: JobPriority, job: JobContainer) {
+        {
+            let mut queue_lock = self.queues[priority.index()].lock().unwrap();
+            (*queue_lock).push(job);
+            self.queued_job_count.fetch_add(1, Ordering::Release);
+        }
+        if self.is_executing() {
+            self.jobs_queued_while_busy.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Snapshot this thread's state for debugging the scheduler.
+    /// Counters are read with relaxed ordering, so they may be slightly behind while jobs are executing.
+    /// ```
+    /// # use shared::engine::job::thread::JobThread;
+    /// let mut job_thread = JobThread::new();
+    /// job_thread.queue_job(|| 1);
+    /// let dump = job_thread.debug_dump(0);
+    /// assert_eq!(dump.queued_jobs.len(), 1);
+    /// job_thread.execute();
+    /// job_thread.wait();
+    /// assert_eq!(job_thread.debug_dump(0).jobs_executed, 1);
+    /// ```
+    pub fn debug_dump(&self, index: usize) -> JobThreadDebugDump {
+        let mut queued_jobs = Vec::new();
+        for queue in self.queues.iter() {
+            queued_jobs.extend(queue.lock().unwrap().job_names());
+        }
+        return JobThreadDebugDump {
+            index,
+            is_executing: self.is_executing(),
+            executing_job: *self.executing_job.lock().unwrap(),
+            queued_jobs,
+            jobs_executed: self.jobs_executed.load(Ordering::Relaxed),
+            jobs_run_while_yielding: self.jobs_run_while_yielding.load(Ordering::Relaxed),
+            jobs_queued_while_busy: self.jobs_queued_while_busy.load(Ordering::Relaxed)
+        };
+    }
+
+    fn has_queued_jobs(&self) -> bool {
+        return self.queues.iter().any(|queue| queue.lock().unwrap().length > 0);
+    }
+
     fn execute_queued_jobs(&mut self) {
         let mut active_lock = self.active_work.lock().unwrap();
         {
-            let mut queue_lock = self.queue.lock().unwrap();
-            self.queued_job_count.store(0, Ordering::Release);
-            (*active_lock).collect_jobs(&mut *queue_lock);
-            // queue lock is unlocked here.
+            // Queues are always locked in priority order.
+            let mut queue_locks: Vec<MutexGuard<JobRingQueue>> = self.queues.iter().map(|queue| queue.lock().unwrap()).collect();
+            let highest = queue_locks.iter().position(|queue| queue.length > 0);
+            let aging_window = self.aging_window();
+            let now = Instant::now();
+            let mut collected = 0;
+
+            // Aged queues are collected first, so they can't be crowded out of the active work by higher priorities.
+            for (index, queue_lock) in queue_locks.iter_mut().enumerate() {
+                let is_aged = Some(index) != highest && queue_lock.length > 0 && queue_lock.oldest_wait(now) >= aging_window;
+                if is_aged && queue_lock.length <= (*active_lock).remaining_capacity() {
+                    collected += queue_lock.length;
+                    (*active_lock).collect_jobs(&mut *queue_lock);
+                }
+            }
+            if let Some(index) = highest {
+                let queue_lock = &mut queue_locks[index];
+                if queue_lock.length <= (*active_lock).remaining_capacity() {
+                    collected += queue_lock.length;
+                    (*active_lock).collect_jobs(&mut *queue_lock);
+                }
+            }
+            self.queued_job_count.fetch_sub(collected, Ordering::Release);
+            // queue locks are unlocked here.
+        }
+        (*active_lock).invoke_all_jobs(|name| {
+            *self.executing_job.lock().unwrap() = Some(name);
+            self.jobs_executed.fetch_add(1, Ordering::Relaxed);
+        });
+        *self.executing_job.lock().unwrap() = None;
+    }
+
+    /// Runs the jobs queued while a background job is executing on this thread.
+    /// The active work is still owned by the yielding job, so the queue is drained separately.
+    pub(crate) fn execute_yielded_jobs(&self) -> usize {
+        let mut jobs = Vec::new();
+        for queue in self.queues.iter() {
+            let mut queue_lock = queue.lock().unwrap();
+            let drained = (*queue_lock).drain();
+            self.queued_job_count.fetch_sub(drained.len(), Ordering::Release);
+            jobs.extend(drained);
+        }
+        let interrupted_job = *self.executing_job.lock().unwrap();
+        for job in jobs.iter_mut() {
+            *self.executing_job.lock().unwrap() = Some(job.name());
+            job.invoke();
         }
-        (*active_lock).invoke_all_jobs();
+        *self.executing_job.lock().unwrap() = interrupted_job;
+        self.jobs_executed.fetch_add(jobs.len(), Ordering::Relaxed);
+        self.jobs_run_while_yielding.fetch_add(jobs.len(), Ordering::Relaxed);
+        return jobs.len();
     }
 
 }
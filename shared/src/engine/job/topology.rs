@@ -0,0 +1,141 @@
+/// What the job threads will be competing with, which changes how many of them are sensible.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThreadProfile {
+    /// The main thread renders and handles input, so latency matters more than throughput.
+    /// Only performance cores are used, leaving one for the main thread.
+    Client,
+    /// A headless server wants throughput, so efficiency cores are used as well.
+    /// One physical core is left for the tick thread.
+    Server
+}
+
+/// Description of the CPU cores available to the process.
+/// SMT siblings are counted as logical cores, but not physical cores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CpuTopology {
+    pub logical_cores: usize,
+    pub physical_cores: usize,
+    pub performance_cores: usize,
+    pub efficiency_cores: usize
+}
+
+impl CpuTopology {
+    /// Detects the CPU topology of the system. Always reports at least one core of every count
+    /// except efficiency cores, which are zero on non-hybrid CPUs.
+    /// If the platform can't provide physical core information, every logical core is treated as a physical performance core.
+    /// ```
+    /// # use shared::engine::job::topology::CpuTopology;
+    /// let topology = CpuTopology::detect();
+    /// assert!(topology.physical_cores > 0);
+    /// assert!(topology.physical_cores <= topology.logical_cores);
+    /// assert_eq!(topology.performance_cores + topology.efficiency_cores, topology.physical_cores);
+    /// ```
+    pub fn detect() -> CpuTopology {
+        let logical_cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let topology = match detect_platform() {
+            Some(topology) => topology,
+            None => CpuTopology::uniform(logical_cores, logical_cores)
+        };
+        return topology.clamped(logical_cores);
+    }
+
+    /// Makes a topology with no efficiency cores.
+    /// ```
+    /// # use shared::engine::job::topology::CpuTopology;
+    /// let topology = CpuTopology::uniform(16, 8);
+    /// assert_eq!(topology.performance_cores, 8);
+    /// assert_eq!(topology.efficiency_cores, 0);
+    /// ```
+    pub fn uniform(logical_cores: usize, physical_cores: usize) -> CpuTopology {
+        return CpuTopology {
+            logical_cores,
+            physical_cores,
+            performance_cores: physical_cores,
+            efficiency_cores: 0
+        };
+    }
+
+    /// Get the number of job threads that should be used on this topology for a given profile.
+    /// Will always be non-zero.
+    /// ```
+    /// # use shared::engine::job::topology::{CpuTopology, ThreadProfile};
+    /// // 8 performance cores with SMT, plus 8 efficiency cores
+    /// let topology = CpuTopology { logical_cores: 24, physical_cores: 16, performance_cores: 8, efficiency_cores: 8 };
+    /// assert_eq!(topology.recommended_job_threads(ThreadProfile::Client), 7);
+    /// assert_eq!(topology.recommended_job_threads(ThreadProfile::Server), 15);
+    ///
+    /// let single_core = CpuTopology::uniform(1, 1);
+    /// assert_eq!(single_core.recommended_job_threads(ThreadProfile::Client), 1);
+    /// ```
+    pub fn recommended_job_threads(&self, profile: ThreadProfile) -> usize {
+        let usable_cores = match profile {
+            ThreadProfile::Client => self.performance_cores,
+            ThreadProfile::Server => self.physical_cores
+        };
+        return usable_cores.saturating_sub(1).max(1);
+    }
+
+    fn clamped(mut self, logical_cores: usize) -> CpuTopology {
+        // The process may be restricted to fewer cores than the machine has (affinity masks, containers).
+        self.logical_cores = logical_cores.max(1);
+        self.physical_cores = self.physical_cores.clamp(1, self.logical_cores);
+        self.efficiency_cores = self.efficiency_cores.min(self.physical_cores - 1);
+        self.performance_cores = self.physical_cores - self.efficiency_cores;
+        return self;
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn detect_platform() -> Option<CpuTopology> {
+    use std::collections::HashSet;
+
+    let read = |path: String| std::fs::read_to_string(path).ok().map(|s| s.trim().to_string());
+
+    let online = parse_cpu_list(&read("/sys/devices/system/cpu/online".to_string())?)?;
+    // Hybrid Intel CPUs list their efficiency cores separately.
+    let efficiency_cpus: HashSet<usize> = read("/sys/devices/cpu_atom/cpus".to_string())
+        .and_then(|list| parse_cpu_list(&list))
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+
+    let mut physical: HashSet<(String, String)> = HashSet::new();
+    let mut efficiency_physical: HashSet<(String, String)> = HashSet::new();
+    for cpu in online.iter() {
+        let package = read(format!("/sys/devices/system/cpu/cpu{}/topology/physical_package_id", cpu))?;
+        let core = read(format!("/sys/devices/system/cpu/cpu{}/topology/core_id", cpu))?;
+        if efficiency_cpus.contains(cpu) {
+            efficiency_physical.insert((package.clone(), core.clone()));
+        }
+        physical.insert((package, core));
+    }
+
+    return Some(CpuTopology {
+        logical_cores: online.len(),
+        physical_cores: physical.len(),
+        performance_cores: physical.len() - efficiency_physical.len(),
+        efficiency_cores: efficiency_physical.len()
+    });
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect_platform() -> Option<CpuTopology> {
+    return None;
+}
+
+/// Parses the kernel cpu list format, such as "0-3,8,10-11".
+#[cfg(target_os = "linux")]
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for range in list.split(',').filter(|s| !s.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => {
+                let start: usize = start.parse().ok()?;
+                let end: usize = end.parse().ok()?;
+                cpus.extend(start..=end);
+            },
+            None => cpus.push(range.parse().ok()?)
+        }
+    }
+    return Some(cpus);
+}
@@ -0,0 +1,32 @@
+use crate::engine::math::vector::Vec3;
+
+/// Maximum value of any light channel.
+pub const MAX_LIGHT: u8 = 15;
+
+/// Light at a single point. Sky light is white and scaled by time of day when shaded,
+/// while block light is colored, with a separate level for each of red, green, and blue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LightSample {
+    pub sky: u8,
+    pub block: [u8; 3]
+}
+
+impl LightSample {
+    /// Final color multiplier, with each channel from 0.0 to 1.0, given the daylight from WorldTime::daylight().
+    /// ```
+    /// # use shared::engine::light::LightSample;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let torch_outside = LightSample { sky: 15, block: [12, 9, 6] };
+    /// assert_eq!(torch_outside.color(1.0), Vec3::splat(1.0));
+    /// assert_eq!(torch_outside.color(0.2), Vec3::new(0.8, 0.6, 0.4));
+    /// ```
+    pub fn color(&self, daylight: f32) -> Vec3 {
+        let max = MAX_LIGHT as f32;
+        let block = Vec3::new(self.block[0] as f32 / max, self.block[1] as f32 / max, self.block[2] as f32 / max);
+        return Vec3::splat(self.sky as f32 / max * daylight).max(block);
+    }
+}
+
+pub mod probe;
+pub mod storage;
+pub mod propagation;
\ No newline at end of file
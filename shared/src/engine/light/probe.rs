@@ -0,0 +1,232 @@
+use std::{collections::{HashMap, HashSet}, sync::{Arc, Mutex}};
+
+use crate::engine::{
+    event::{bus::{MessageBus, Subscription}, events::{ChunkUnloaded, LightChanged}},
+    math::{coords::{BlockPos, ChunkPos, WorldPos, CHUNK_SIZE}, vector::Vec3},
+    world::World
+};
+
+use super::{LightSample, MAX_LIGHT};
+
+/// Blocks between probes along each axis. Must divide CHUNK_SIZE, so every chunk holds the same probes.
+pub const DEFAULT_PROBE_SPACING: i32 = 4;
+
+/// Interpolated light at a position between probes, normalized so each channel is 0.0 to 1.0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProbeLight {
+    pub sky: f32,
+    pub block: Vec3
+}
+
+impl ProbeLight {
+    /// Light used where no probes exist yet, such as chunks that have not finished loading.
+    pub const UNLIT_SKY: ProbeLight = ProbeLight { sky: 1.0, block: Vec3::ZERO };
+
+    /// Final color multiplier for a shaded entity, given the current sky brightness from 0.0 to 1.0.
+    pub fn color(&self, sky_brightness: f32) -> Vec3 {
+        return Vec3::splat(self.sky * sky_brightness).max(self.block);
+    }
+}
+
+/// Coarse grid of light samples taken from the voxel lighting data, so entities and particles
+/// can be lit consistently with the terrain without sampling individual blocks every frame.
+/// Probes sit on block corners every spacing blocks, and are resampled incrementally when light changes.
+pub struct LightProbeGrid {
+    spacing: i32,
+    probes: HashMap<BlockPos, LightSample>,
+    dirty: HashSet<BlockPos>
+}
+
+impl LightProbeGrid {
+    /// Will panic in debug mode if spacing does not evenly divide CHUNK_SIZE.
+    pub fn new(spacing: i32) -> LightProbeGrid {
+        debug_assert!(spacing > 0 && CHUNK_SIZE % spacing == 0, "Probe spacing must divide the chunk size");
+        return LightProbeGrid { spacing, probes: HashMap::new(), dirty: HashSet::new() };
+    }
+
+    pub fn spacing(&self) -> i32 {
+        return self.spacing;
+    }
+
+    /// Number of probes that have been sampled at least once.
+    pub fn probe_count(&self) -> usize {
+        return self.probes.len();
+    }
+
+    /// Number of probes waiting to be resampled.
+    pub fn dirty_count(&self) -> usize {
+        return self.dirty.len();
+    }
+
+    /// Queue every probe in a chunk for sampling, once it's lit and whenever its light changes.
+    pub fn add_chunk(&mut self, chunk: ChunkPos) {
+        let per_axis = CHUNK_SIZE / self.spacing;
+        let origin = self.probe_containing(chunk.origin());
+        for x in 0..per_axis {
+            for y in 0..per_axis {
+                for z in 0..per_axis {
+                    self.dirty.insert(origin.offset(x, y, z));
+                }
+            }
+        }
+    }
+
+    /// Drop the probes of an unloaded chunk.
+    pub fn remove_chunk(&mut self, chunk: ChunkPos) {
+        let spacing = self.spacing;
+        let keep = |probe: &BlockPos| BlockPos::new(probe.x * spacing, probe.y * spacing, probe.z * spacing).chunk() != chunk;
+        self.probes.retain(|probe, _| keep(probe));
+        self.dirty.retain(keep);
+    }
+
+    /// Mark the probes that may be affected by a light change at a block.
+    /// Light travels at most MAX_LIGHT blocks, so only probes within that distance are resampled.
+    /// ```
+    /// # use shared::engine::light::probe::LightProbeGrid;
+    /// # use shared::engine::math::coords::BlockPos;
+    /// let mut grid = LightProbeGrid::new(4);
+    /// grid.invalidate(BlockPos::new(0, 0, 0));
+    /// // Probes from block -16 to 12 are within reach on each axis.
+    /// assert_eq!(grid.dirty_count(), 8 * 8 * 8);
+    /// ```
+    pub fn invalidate(&mut self, block: BlockPos) {
+        let reach = MAX_LIGHT as i32;
+        let min = self.probe_containing(block.offset(-reach, -reach, -reach));
+        let max = self.probe_containing(block.offset(reach, reach, reach));
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    self.dirty.insert(BlockPos::new(x, y, z));
+                }
+            }
+        }
+    }
+
+    /// Resample up to budget dirty probes from the voxel lighting data. Returns the number resampled.
+    /// Called once per frame with a small budget, so large light changes are spread across frames.
+    /// ```
+    /// # use shared::engine::light::{LightSample, probe::LightProbeGrid};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let mut grid = LightProbeGrid::new(4);
+    /// grid.add_chunk(ChunkPos::new(0, 0, 0));
+    /// let lookup = |_| LightSample { sky: 15, block: [0, 0, 0] };
+    /// assert_eq!(grid.update(100, lookup), 100);
+    /// assert_eq!(grid.update(usize::MAX, lookup), 512 - 100);
+    /// assert_eq!(grid.dirty_count(), 0);
+    /// ```
+    pub fn update<F>(&mut self, budget: usize, light_at: F) -> usize
+    where F: Fn(BlockPos) -> LightSample {
+        let batch: Vec<BlockPos> = self.dirty.iter().take(budget).copied().collect();
+        for probe in batch.iter() {
+            self.dirty.remove(probe);
+            self.probes.insert(*probe, light_at(self.probe_block(*probe)));
+        }
+        return batch.len();
+    }
+
+    /// Resample up to budget dirty probes from a world's light, as update() does. Probes in chunks that aren't
+    /// loaded read as full sky, as they would be if they were.
+    pub fn update_from_world(&mut self, budget: usize, world: &World) -> usize {
+        return self.update(budget, |block| match world.light(block.chunk()) {
+            Some(light) => light.read().unwrap().get(block.local()),
+            None => LightSample { sky: MAX_LIGHT, block: [0; 3] }
+        });
+    }
+
+    /// Keep a shared grid in step with a world's light, by subscribing to its events: the probes of chunks whose
+    /// light changes are queued for resampling, and those of unloaded chunks dropped.
+    /// Returns the subscriptions, to unsubscribe on leaving the world.
+    /// ```
+    /// # use shared::engine::light::{probe::LightProbeGrid, propagation::{light_chunk, update_block, BlockLighting}};
+    /// # use shared::engine::event::bus::MessageBus;
+    /// # use shared::engine::world::{World, chunk::Chunk};
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos, WorldPos};
+    /// # use std::sync::{Arc, Mutex};
+    /// let bus = Arc::new(MessageBus::new());
+    /// let world = World::new().with_events(bus.clone());
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    /// let grid = Arc::new(Mutex::new(LightProbeGrid::new(4)));
+    /// LightProbeGrid::track(&grid, &bus);
+    /// let lighting = |id| if id == 2 { BlockLighting { emission: [15, 0, 0], opacity: 0 } } else { BlockLighting::TRANSPARENT };
+    /// light_chunk(&world, ChunkPos::new(0, 0, 0), &lighting);
+    /// world.dispatch_events();
+    /// assert_eq!(grid.lock().unwrap().update_from_world(usize::MAX, &world), 512);
+    /// assert_eq!(grid.lock().unwrap().sample(WorldPos::new(8.0, 8.0, 8.0)).block.x, 0.0);
+    ///
+    /// // A torch placed later relights the probes around it.
+    /// world.set_block(BlockPos::new(8, 8, 8), 2);
+    /// update_block(&world, BlockPos::new(8, 8, 8), &lighting);
+    /// world.dispatch_events();
+    /// grid.lock().unwrap().update_from_world(usize::MAX, &world);
+    /// assert_eq!(grid.lock().unwrap().sample(WorldPos::new(8.0, 8.0, 8.0)).block.x, 1.0);
+    /// world.remove_chunk(ChunkPos::new(0, 0, 0));
+    /// assert_eq!(grid.lock().unwrap().probe_count(), 0);
+    /// ```
+    pub fn track(grid: &Arc<Mutex<LightProbeGrid>>, events: &MessageBus) -> [Subscription; 2] {
+        let relit = grid.clone();
+        let unloaded = grid.clone();
+        return [
+            events.subscribe(move |changed: &LightChanged| relit.lock().unwrap().add_chunk(changed.pos)),
+            events.subscribe(move |chunk: &ChunkUnloaded| unloaded.lock().unwrap().remove_chunk(chunk.pos))
+        ];
+    }
+
+    /// Trilinearly interpolate the 8 probes surrounding a position.
+    /// Probes that have not been sampled are ignored, and if none have, the light is full sky.
+    /// ```
+    /// # use shared::engine::light::{LightSample, probe::{LightProbeGrid, ProbeLight}};
+    /// # use shared::engine::math::coords::{ChunkPos, WorldPos};
+    /// let mut grid = LightProbeGrid::new(4);
+    /// assert_eq!(grid.sample(WorldPos::new(1.0, 1.0, 1.0)), ProbeLight::UNLIT_SKY);
+    /// grid.add_chunk(ChunkPos::new(0, 0, 0));
+    /// // A red torch lighting everything with x below 4.
+    /// grid.update(usize::MAX, |block| LightSample { sky: 0, block: [if block.x < 4 { 15 } else { 0 }, 0, 0] });
+    /// let light = grid.sample(WorldPos::new(2.0, 5.0, 5.0));
+    /// assert!((light.block.x - 0.5).abs() < 1e-6);
+    /// assert_eq!(light.block.y, 0.0);
+    /// ```
+    pub fn sample(&self, position: WorldPos) -> ProbeLight {
+        let spacing = self.spacing as f64;
+        let (gx, gy, gz) = (position.x / spacing, position.y / spacing, position.z / spacing);
+        let base = BlockPos::new(gx.floor() as i32, gy.floor() as i32, gz.floor() as i32);
+        let fraction = [(gx - gx.floor()) as f32, (gy - gy.floor()) as f32, (gz - gz.floor()) as f32];
+
+        let mut total_weight = 0.0;
+        let mut sky = 0.0;
+        let mut block = Vec3::ZERO;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let Some(sample) = self.probes.get(&base.offset(offset[0], offset[1], offset[2])) else {
+                continue;
+            };
+            let mut weight = 1.0;
+            for axis in 0..3 {
+                weight *= if offset[axis] == 1 { fraction[axis] } else { 1.0 - fraction[axis] };
+            }
+            total_weight += weight;
+            sky += sample.sky as f32 * weight;
+            block += Vec3::new(sample.block[0] as f32, sample.block[1] as f32, sample.block[2] as f32) * weight;
+        }
+        if total_weight <= 0.0 {
+            return ProbeLight::UNLIT_SKY;
+        }
+        let scale = 1.0 / (total_weight * MAX_LIGHT as f32);
+        return ProbeLight { sky: sky * scale, block: block * scale };
+    }
+
+    /// Probe grid coordinate of the probe at or below a block on every axis.
+    fn probe_containing(&self, block: BlockPos) -> BlockPos {
+        return BlockPos::new(block.x.div_euclid(self.spacing), block.y.div_euclid(self.spacing), block.z.div_euclid(self.spacing));
+    }
+
+    /// Block a probe samples from.
+    fn probe_block(&self, probe: BlockPos) -> BlockPos {
+        return BlockPos::new(probe.x * self.spacing, probe.y * self.spacing, probe.z * self.spacing);
+    }
+}
+
+impl Default for LightProbeGrid {
+    fn default() -> LightProbeGrid {
+        return LightProbeGrid::new(DEFAULT_PROBE_SPACING);
+    }
+}
@@ -0,0 +1,349 @@
+use std::{collections::{HashMap, HashSet, VecDeque}, sync::Arc};
+
+use crate::engine::{
+    block::{BlockId, AIR},
+    event::events::LightChanged,
+    job::{system::JobSystem, future::JobFuture},
+    math::{coords::{BlockPos, ChunkPos, LocalPos, CHUNK_SIZE}, direction::Direction},
+    world::{container::{SharedChunk, SharedLight}, World}
+};
+
+use super::{storage::{LIGHT_CHANNELS, SKY_CHANNEL}, MAX_LIGHT};
+
+/// How a block interacts with light.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockLighting {
+    /// Red, green, and blue light the block gives off.
+    pub emission: [u8; 3],
+    /// Light lost passing into the block, on top of the 1 lost per block travelled. MAX_LIGHT blocks light entirely.
+    pub opacity: u8
+}
+
+impl BlockLighting {
+    pub const TRANSPARENT: BlockLighting = BlockLighting { emission: [0; 3], opacity: 0 };
+    pub const OPAQUE: BlockLighting = BlockLighting { emission: [0; 3], opacity: MAX_LIGHT };
+
+    fn emission(&self, channel: usize) -> u8 {
+        if channel == SKY_CHANNEL {
+            return 0;
+        }
+        return self.emission[channel - 1];
+    }
+}
+
+/// How each block interacts with light.
+pub type LightingFn = dyn Fn(BlockId) -> BlockLighting + Send + Sync;
+
+/// Light after moving from a block at level into a neighbor. Sky light at full strength travels straight down without fading.
+fn spread(level: u8, channel: usize, direction: Direction, lighting: BlockLighting) -> u8 {
+    if lighting.opacity >= MAX_LIGHT {
+        return 0;
+    }
+    if channel == SKY_CHANNEL && level == MAX_LIGHT && direction == Direction::NegY && lighting.opacity == 0 {
+        return MAX_LIGHT;
+    }
+    return level.saturating_sub(1 + lighting.opacity);
+}
+
+/// Breadth first light propagation over the loaded chunks of a world. Light spreads into and out of loaded neighbors,
+/// and stops at unloaded chunks. Chunks are locked for each access, so the world stays usable while lighting runs.
+struct Propagator<'a> {
+    world: &'a World,
+    lighting: &'a LightingFn,
+    chunks: HashMap<ChunkPos, Option<(SharedChunk, SharedLight)>>,
+    /// Chunks whose light was written, to publish LightChanged for.
+    changed: HashSet<ChunkPos>
+}
+
+impl<'a> Propagator<'a> {
+    fn new(world: &'a World, lighting: &'a LightingFn) -> Propagator<'a> {
+        return Propagator { world, lighting, chunks: HashMap::new(), changed: HashSet::new() };
+    }
+
+    fn entry(&mut self, chunk: ChunkPos) -> Option<&(SharedChunk, SharedLight)> {
+        let world = self.world;
+        return self.chunks.entry(chunk).or_insert_with(|| world.chunk_and_light(chunk)).as_ref();
+    }
+
+    fn lighting(&mut self, block: BlockPos) -> Option<BlockLighting> {
+        let (chunk, _) = self.entry(block.chunk())?;
+        let id = chunk.read().unwrap().get_block(block.local());
+        return Some((self.lighting)(id));
+    }
+
+    fn level(&mut self, block: BlockPos, channel: usize) -> Option<u8> {
+        let (_, light) = self.entry(block.chunk())?;
+        return Some(light.read().unwrap().channel(block.local(), channel));
+    }
+
+    fn set_level(&mut self, block: BlockPos, channel: usize, level: u8) {
+        if let Some((_, light)) = self.entry(block.chunk()) {
+            light.write().unwrap().set_channel(block.local(), channel, level);
+            self.changed.insert(block.chunk());
+        }
+    }
+
+    /// Publish LightChanged for every chunk whose light was written since the last call, for the end of the tick.
+    fn publish_changes(&mut self) {
+        let Some(events) = self.world.events() else {
+            self.changed.clear();
+            return;
+        };
+        for pos in self.changed.drain() {
+            events.publish(LightChanged { pos });
+        }
+    }
+
+    /// Spread light outwards from every queued block, raising neighbors that are darker than the light reaching them.
+    fn propagate(&mut self, mut queue: VecDeque<(BlockPos, usize)>) {
+        while let Some((block, channel)) = queue.pop_front() {
+            let Some(level) = self.level(block, channel) else {
+                continue;
+            };
+            if level <= 1 {
+                continue;
+            }
+            for direction in Direction::ALL {
+                let neighbor = block + direction.offset();
+                let Some(lighting) = self.lighting(neighbor) else {
+                    continue;
+                };
+                let next = spread(level, channel, direction, lighting);
+                if next > self.level(neighbor, channel).unwrap_or(MAX_LIGHT) {
+                    self.set_level(neighbor, channel, next);
+                    queue.push_back((neighbor, channel));
+                }
+            }
+        }
+    }
+
+    /// Darken every block lit by the removed light, returning the blocks bordering the darkened area
+    /// that are lit from elsewhere, which must propagate again to fill it back in.
+    fn remove(&mut self, mut queue: VecDeque<(BlockPos, usize, u8)>) -> VecDeque<(BlockPos, usize)> {
+        let mut relight = VecDeque::new();
+        while let Some((block, channel, level)) = queue.pop_front() {
+            for direction in Direction::ALL {
+                let neighbor = block + direction.offset();
+                let Some(neighbor_level) = self.level(neighbor, channel) else {
+                    continue;
+                };
+                if neighbor_level == 0 {
+                    continue;
+                }
+                let from_sky_column = channel == SKY_CHANNEL && direction == Direction::NegY && level == MAX_LIGHT;
+                if neighbor_level < level || from_sky_column {
+                    self.set_level(neighbor, channel, 0);
+                    queue.push_back((neighbor, channel, neighbor_level));
+                    // Light sources inside the darkened area keep shining.
+                    let emission = self.lighting(neighbor).map(|lighting| lighting.emission(channel)).unwrap_or(0);
+                    if emission > 0 {
+                        self.set_level(neighbor, channel, emission);
+                        relight.push_back((neighbor, channel));
+                    }
+                } else {
+                    relight.push_back((neighbor, channel));
+                }
+            }
+        }
+        return relight;
+    }
+
+    fn light_chunk(&mut self, pos: ChunkPos) {
+        let Some((chunk, light)) = self.entry(pos).cloned() else {
+            return;
+        };
+        self.changed.insert(pos);
+        let above = self.world.light(pos.offset(0, 1, 0));
+        let origin = pos.origin();
+        let mut queue = VecDeque::new();
+        {
+            let chunk = chunk.read().unwrap();
+            let mut light = light.write().unwrap();
+            let above = above.as_ref().map(|above| above.read().unwrap());
+            (*light).clear();
+            let air = (self.lighting)(AIR);
+            for z in 0..CHUNK_SIZE as u8 {
+                for x in 0..CHUNK_SIZE as u8 {
+                    // Everything above the column's highest block is air, so needs no lookup.
+                    let height = chunk.height(x, z);
+                    // Chunks without a loaded chunk above are treated as open to the sky.
+                    let mut level = match &above {
+                        Some(above) => above.channel(LocalPos::new(x, 0, z), SKY_CHANNEL),
+                        None => MAX_LIGHT
+                    };
+                    for y in (0..CHUNK_SIZE as u8).rev() {
+                        let local = LocalPos::new(x, y, z);
+                        let lighting = if height.is_none_or(|height| y > height) { air } else { (self.lighting)(chunk.get_block(local)) };
+                        level = spread(level, SKY_CHANNEL, Direction::NegY, lighting);
+                        if level == 0 {
+                            break;
+                        }
+                        light.set_channel(local, SKY_CHANNEL, level);
+                        queue.push_back((origin + BlockPos::new(x as i32, y as i32, z as i32), SKY_CHANNEL));
+                    }
+                }
+            }
+            for local in LocalPos::all() {
+                let lighting = (self.lighting)(chunk.get_block(local));
+                for channel in 1..LIGHT_CHANNELS {
+                    let emission = lighting.emission(channel);
+                    if emission > 0 {
+                        light.set_channel(local, channel, emission);
+                        queue.push_back((origin + BlockPos::new(local.x as i32, local.y as i32, local.z as i32), channel));
+                    }
+                }
+            }
+        }
+        // Pull light in from the borders of lit neighbors.
+        for direction in Direction::ALL {
+            let neighbor = pos + direction.chunk_offset();
+            if self.entry(neighbor).is_none() {
+                continue;
+            }
+            let axis = direction.axis() as usize;
+            for v in 0..CHUNK_SIZE {
+                for u in 0..CHUNK_SIZE {
+                    let mut offset = [0; 3];
+                    offset[axis] = if direction.is_positive() { CHUNK_SIZE } else { -1 };
+                    offset[(axis + 1) % 3] = u;
+                    offset[(axis + 2) % 3] = v;
+                    let block = origin + BlockPos::new(offset[0], offset[1], offset[2]);
+                    for channel in 0..LIGHT_CHANNELS {
+                        if self.level(block, channel).unwrap_or(0) > 1 {
+                            queue.push_back((block, channel));
+                        }
+                    }
+                }
+            }
+        }
+        self.propagate(queue);
+    }
+
+    /// Relight around changed blocks in one pass, darkening everything they lit before spreading light again.
+    fn update_blocks(&mut self, blocks: &[BlockPos]) {
+        let mut removal = VecDeque::new();
+        let mut relight = VecDeque::new();
+        for block in blocks.iter() {
+            for channel in 0..LIGHT_CHANNELS {
+                let level = self.level(*block, channel).unwrap_or(0);
+                if level > 0 {
+                    self.set_level(*block, channel, 0);
+                    removal.push_back((*block, channel, level));
+                }
+            }
+        }
+        relight.extend(self.remove(removal));
+        for block in blocks.iter() {
+            let Some(lighting) = self.lighting(*block) else {
+                continue;
+            };
+            for channel in 0..LIGHT_CHANNELS {
+                // The block may now let through light its neighbors were blocked from spreading.
+                for direction in Direction::ALL {
+                    relight.push_back((*block + direction.offset(), channel));
+                }
+                let emission = lighting.emission(channel);
+                if emission > self.level(*block, channel).unwrap_or(0) {
+                    self.set_level(*block, channel, emission);
+                    relight.push_back((*block, channel));
+                }
+            }
+        }
+        self.propagate(relight);
+    }
+}
+
+/// Light a chunk from scratch, including sky light, its light sources, and light entering from its loaded neighbors.
+/// Light also spreads out of the chunk into loaded neighbors that are darker.
+/// ```
+/// # use shared::engine::light::propagation::{light_chunk, BlockLighting};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos, LocalPos};
+/// let world = World::new();
+/// let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+/// chunk.set_block(LocalPos::new(8, 8, 8), 2);
+/// world.insert_chunk(chunk);
+/// let lighting = |id| match id {
+///     0 => BlockLighting::TRANSPARENT,
+///     2 => BlockLighting { emission: [14, 0, 7], opacity: 15 },
+///     _ => BlockLighting::OPAQUE
+/// };
+/// light_chunk(&world, ChunkPos::new(0, 0, 0), &lighting);
+/// let light = world.light(ChunkPos::new(0, 0, 0)).unwrap();
+/// let light = light.read().unwrap();
+/// assert_eq!(light.get(LocalPos::new(8, 8, 8)).block, [14, 0, 7]);
+/// assert_eq!(light.get(LocalPos::new(10, 8, 8)).block, [12, 0, 5]);
+/// assert_eq!(light.get(LocalPos::new(0, 0, 0)).sky, 15);
+/// ```
+pub fn light_chunk(world: &World, pos: ChunkPos, lighting: &LightingFn) {
+    let mut propagator = Propagator::new(world, lighting);
+    propagator.light_chunk(pos);
+    propagator.publish_changes();
+}
+
+/// Relight around a block after it changed, removing light it now blocks or no longer emits,
+/// and spreading light it now lets through or emits.
+/// ```
+/// # use shared::engine::light::propagation::{light_chunk, update_block, BlockLighting};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// let lighting = |id| match id {
+///     0 => BlockLighting::TRANSPARENT,
+///     2 => BlockLighting { emission: [15, 15, 15], opacity: 0 },
+///     _ => BlockLighting::OPAQUE
+/// };
+/// light_chunk(&world, ChunkPos::new(0, 0, 0), &lighting);
+/// let light = world.light(ChunkPos::new(0, 0, 0)).unwrap();
+///
+/// world.set_block(BlockPos::new(4, 4, 4), 2);
+/// update_block(&world, BlockPos::new(4, 4, 4), &lighting);
+/// assert_eq!(light.read().unwrap().get(BlockPos::new(4, 4, 6).local()).block, [13, 13, 13]);
+///
+/// world.set_block(BlockPos::new(4, 4, 4), 0);
+/// update_block(&world, BlockPos::new(4, 4, 4), &lighting);
+/// assert_eq!(light.read().unwrap().get(BlockPos::new(4, 4, 6).local()).block, [0, 0, 0]);
+/// ```
+pub fn update_block(world: &World, block: BlockPos, lighting: &LightingFn) {
+    update_blocks(world, &[block], lighting);
+}
+
+/// Relight around many blocks after they changed together, such as by a world edit, in a single pass rather than
+/// one per block.
+/// ```
+/// # use shared::engine::light::propagation::{light_chunk, update_blocks, BlockLighting};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// let lighting = |id| if id == 0 { BlockLighting::TRANSPARENT } else { BlockLighting::OPAQUE };
+/// light_chunk(&world, ChunkPos::new(0, 0, 0), &lighting);
+///
+/// // A roof over the corner of the chunk shades what's below it.
+/// let roof: Vec<BlockPos> = (0..4).flat_map(|x| (0..4).map(move |z| BlockPos::new(x, 10, z))).collect();
+/// for block in roof.iter() {
+///     world.set_block(*block, 1);
+/// }
+/// update_blocks(&world, &roof, &lighting);
+/// let light = world.light(ChunkPos::new(0, 0, 0)).unwrap();
+/// assert_eq!(light.read().unwrap().get(BlockPos::new(0, 5, 0).local()).sky, 11);
+/// assert_eq!(light.read().unwrap().get(BlockPos::new(8, 5, 8).local()).sky, 15);
+/// ```
+pub fn update_blocks(world: &World, blocks: &[BlockPos], lighting: &LightingFn) {
+    let mut propagator = Propagator::new(world, lighting);
+    propagator.update_blocks(blocks);
+    propagator.publish_changes();
+}
+
+/// Queue a job lighting a batch of chunks, such as newly loaded ones, in order.
+/// Batches running at the same time should cover separate areas, as light spreading between them may otherwise be lost.
+pub fn queue_light_job(jobs: &JobSystem, world: Arc<World>, chunks: Vec<ChunkPos>, lighting: Arc<LightingFn>) -> JobFuture<()> {
+    return jobs.run_job(move || {
+        let mut propagator = Propagator::new(&world, &*lighting);
+        for pos in chunks.iter() {
+            propagator.light_chunk(*pos);
+        }
+        propagator.publish_changes();
+    });
+}
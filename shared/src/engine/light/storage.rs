@@ -0,0 +1,99 @@
+use crate::engine::math::coords::{LocalPos, CHUNK_VOLUME};
+
+use super::{LightSample, MAX_LIGHT};
+
+/// Index of the sky channel. Block light red, green, and blue follow it.
+pub const SKY_CHANNEL: usize = 0;
+/// Sky light plus the 3 block light colors.
+pub const LIGHT_CHANNELS: usize = 4;
+
+/// Light of every block in a chunk, packed as 4 bits per channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkLight {
+    data: Box<[u16]>
+}
+
+impl ChunkLight {
+    /// Every block dark.
+    pub fn new() -> ChunkLight {
+        return ChunkLight { data: vec![0; CHUNK_VOLUME].into_boxed_slice() };
+    }
+
+    /// Light of one channel, see SKY_CHANNEL.
+    /// ```
+    /// # use shared::engine::light::storage::{ChunkLight, SKY_CHANNEL};
+    /// # use shared::engine::math::coords::LocalPos;
+    /// let mut light = ChunkLight::new();
+    /// light.set_channel(LocalPos::new(1, 2, 3), SKY_CHANNEL, 15);
+    /// light.set_channel(LocalPos::new(1, 2, 3), 2, 7);
+    /// assert_eq!(light.get(LocalPos::new(1, 2, 3)).sky, 15);
+    /// assert_eq!(light.get(LocalPos::new(1, 2, 3)).block, [0, 7, 0]);
+    /// ```
+    pub fn channel(&self, local: LocalPos, channel: usize) -> u8 {
+        return ((self.data[local.index()] >> (channel * 4)) & 0xF) as u8;
+    }
+
+    /// Will panic in debug mode if the level is above MAX_LIGHT.
+    pub fn set_channel(&mut self, local: LocalPos, channel: usize, level: u8) {
+        debug_assert!(level <= MAX_LIGHT, "Light level {} is above the maximum", level);
+        let shift = channel * 4;
+        let packed = &mut self.data[local.index()];
+        *packed = (*packed & !(0xF << shift)) | ((level as u16) << shift);
+    }
+
+    pub fn get(&self, local: LocalPos) -> LightSample {
+        return unpack(self.data[local.index()]);
+    }
+
+    pub fn set(&mut self, local: LocalPos, sample: LightSample) {
+        self.data[local.index()] = pack(sample);
+    }
+
+    /// All channels of a block, 4 bits each with sky in the lowest bits, as given to meshes.
+    pub fn packed(&self, local: LocalPos) -> u16 {
+        return self.data[local.index()];
+    }
+
+    /// Make every block dark.
+    pub fn clear(&mut self) {
+        self.data.fill(0);
+    }
+
+    /// Every block's packed light as little endian bytes, such as to compress a chunk's light while it's set aside.
+    /// ```
+    /// # use shared::engine::light::storage::{ChunkLight, SKY_CHANNEL};
+    /// # use shared::engine::math::coords::LocalPos;
+    /// let mut light = ChunkLight::new();
+    /// light.set_channel(LocalPos::new(4, 5, 6), SKY_CHANNEL, 12);
+    /// assert_eq!(ChunkLight::from_bytes(&light.to_bytes()), Some(light));
+    /// assert!(ChunkLight::from_bytes(&[0; 3]).is_none());
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        return self.data.iter().flat_map(|packed| packed.to_le_bytes()).collect();
+    }
+
+    /// Light from to_bytes(). None unless there are exactly 2 bytes for every block.
+    pub fn from_bytes(bytes: &[u8]) -> Option<ChunkLight> {
+        if bytes.len() != CHUNK_VOLUME * 2 {
+            return None;
+        }
+        let data = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+        return Some(ChunkLight { data });
+    }
+}
+
+impl Default for ChunkLight {
+    fn default() -> ChunkLight {
+        return ChunkLight::new();
+    }
+}
+
+/// Pack a sample as 4 bits per channel, with sky in the lowest bits.
+pub fn pack(sample: LightSample) -> u16 {
+    return sample.sky as u16 | (sample.block[0] as u16) << 4 | (sample.block[1] as u16) << 8 | (sample.block[2] as u16) << 12;
+}
+
+pub fn unpack(packed: u16) -> LightSample {
+    let channel = |channel: usize| ((packed >> (channel * 4)) & 0xF) as u8;
+    return LightSample { sky: channel(0), block: [channel(1), channel(2), channel(3)] };
+}
@@ -0,0 +1,146 @@
+use crate::engine::{entity::{kinematics::Transform, Entities}, math::coords::WorldPos};
+
+/// Level of detail settings for everything within a distance band around the viewer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodBand {
+    /// Upper bound (exclusive) of this band, in blocks from the viewer.
+    pub max_distance: f32,
+    /// Entities in this band update once every N ticks.
+    pub update_interval: u32,
+    /// Entities in this band send state to clients once every N ticks.
+    pub replication_interval: u32,
+    /// Whether animations are evaluated at all.
+    pub animate: bool,
+    /// Fraction of particles that are simulated and drawn, from 0.0 to 1.0.
+    pub particle_fraction: f32
+}
+
+/// Distance based level of detail policy shared by the AiSystem, the InterestManager, and client particles,
+/// so all of them agree on how much work something far from every Viewer deserves.
+/// Anything further than the last band is culled entirely.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LodPolicy {
+    bands: Vec<LodBand>
+}
+
+impl LodPolicy {
+    /// Create a policy from distance bands. Bands are sorted by their max distance.
+    /// ```
+    /// # use shared::engine::lod::{LodPolicy, LodBand};
+    /// let policy = LodPolicy::new(vec![
+    ///     LodBand { max_distance: 64.0, update_interval: 2, replication_interval: 2, animate: false, particle_fraction: 0.5 },
+    ///     LodBand { max_distance: 32.0, update_interval: 1, replication_interval: 1, animate: true, particle_fraction: 1.0 },
+    /// ]);
+    /// assert_eq!(policy.band(10.0).unwrap().update_interval, 1);
+    /// assert_eq!(policy.band(40.0).unwrap().update_interval, 2);
+    /// assert!(policy.band(100.0).is_none());
+    /// ```
+    pub fn new(mut bands: Vec<LodBand>) -> LodPolicy {
+        debug_assert!(!bands.is_empty(), "Cannot create a LOD policy without any bands");
+        debug_assert!(bands.iter().all(|band| band.update_interval > 0 && band.replication_interval > 0), "LOD intervals must be non-zero");
+        bands.sort_by(|a, b| a.max_distance.total_cmp(&b.max_distance));
+        return LodPolicy { bands };
+    }
+
+    /// The policy scaled to a view distance (in blocks), with full detail up close
+    /// and progressively cheaper bands out to the view distance.
+    /// ```
+    /// # use shared::engine::lod::LodPolicy;
+    /// let policy = LodPolicy::for_view_distance(256.0);
+    /// assert!(policy.band(16.0).unwrap().animate);
+    /// assert!(!policy.band(200.0).unwrap().animate);
+    /// assert!(policy.is_culled(300.0));
+    /// ```
+    pub fn for_view_distance(view_distance: f32) -> LodPolicy {
+        return LodPolicy::new(vec![
+            LodBand { max_distance: view_distance * 0.25, update_interval: 1, replication_interval: 1, animate: true, particle_fraction: 1.0 },
+            LodBand { max_distance: view_distance * 0.5, update_interval: 2, replication_interval: 2, animate: true, particle_fraction: 0.5 },
+            LodBand { max_distance: view_distance * 0.75, update_interval: 4, replication_interval: 5, animate: false, particle_fraction: 0.1 },
+            LodBand { max_distance: view_distance, update_interval: 10, replication_interval: 10, animate: false, particle_fraction: 0.0 }
+        ]);
+    }
+
+    /// Get the band for a distance from the viewer, or None if it is beyond every band.
+    pub fn band(&self, distance: f32) -> Option<&LodBand> {
+        return self.bands.iter().find(|band| distance < band.max_distance);
+    }
+
+    /// Index of the band for a distance, where 0 is the most detailed. None if culled.
+    pub fn level(&self, distance: f32) -> Option<usize> {
+        return self.bands.iter().position(|band| distance < band.max_distance);
+    }
+
+    /// Check if something at this distance should not be updated or drawn at all.
+    pub fn is_culled(&self, distance: f32) -> bool {
+        return self.band(distance).is_none();
+    }
+
+    /// Check if an entity at this distance should update on a tick.
+    /// The stagger (typically the entity id) spreads reduced-rate entities across ticks, so they don't all update on the same one.
+    /// ```
+    /// # use shared::engine::lod::LodPolicy;
+    /// let policy = LodPolicy::for_view_distance(256.0);
+    /// // Close entities update every tick.
+    /// assert!((0..10).all(|tick| policy.should_update(10.0, tick, 7)));
+    /// // Far entities update once every 10 ticks.
+    /// assert_eq!((0..100).filter(|tick| policy.should_update(250.0, *tick, 7)).count(), 10);
+    /// ```
+    pub fn should_update(&self, distance: f32, tick: u64, stagger: u64) -> bool {
+        return match self.band(distance) {
+            Some(band) => tick.wrapping_add(stagger).is_multiple_of(band.update_interval as u64),
+            None => false
+        };
+    }
+
+    /// Check if an entity at this distance should be replicated on a tick. See should_update().
+    pub fn should_replicate(&self, distance: f32, tick: u64, stagger: u64) -> bool {
+        return match self.band(distance) {
+            Some(band) => tick.wrapping_add(stagger).is_multiple_of(band.replication_interval as u64),
+            None => false
+        };
+    }
+
+    /// Number of particles out of a requested count that should be simulated at this distance.
+    /// ```
+    /// # use shared::engine::lod::LodPolicy;
+    /// let policy = LodPolicy::for_view_distance(256.0);
+    /// assert_eq!(policy.particle_budget(10.0, 100), 100);
+    /// assert_eq!(policy.particle_budget(100.0, 100), 50);
+    /// assert_eq!(policy.particle_budget(1000.0, 100), 0);
+    /// ```
+    pub fn particle_budget(&self, distance: f32, requested: u32) -> u32 {
+        return match self.band(distance) {
+            Some(band) => ((requested as f32) * band.particle_fraction).round() as u32,
+            None => 0
+        };
+    }
+
+    pub fn bands(&self) -> &[LodBand] {
+        return &self.bands;
+    }
+}
+
+/// Marks an entity that distances for level of detail are measured from, such as a player.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Viewer;
+
+/// Positions of every entity with a Viewer and a Transform.
+pub fn viewer_positions(entities: &Entities) -> Vec<WorldPos> {
+    let viewers = entities.storage::<Viewer>();
+    let viewers = viewers.read().unwrap();
+    let transforms = entities.storage::<Transform>();
+    let transforms = transforms.read().unwrap();
+    return viewers.entities().iter().filter_map(|id| transforms.get(*id).map(|transform| transform.position)).collect();
+}
+
+/// Distance in blocks from a position to the nearest viewer, or infinity without any, so nothing is in a band.
+/// ```
+/// # use shared::engine::lod::nearest_viewer_distance;
+/// # use shared::engine::math::coords::WorldPos;
+/// let viewers = [WorldPos::new(10.0, 0.0, 0.0), WorldPos::new(-3.0, 4.0, 0.0)];
+/// assert_eq!(nearest_viewer_distance(&viewers, WorldPos::ORIGIN), 5.0);
+/// assert_eq!(nearest_viewer_distance(&[], WorldPos::ORIGIN), f32::INFINITY);
+/// ```
+pub fn nearest_viewer_distance(viewers: &[WorldPos], position: WorldPos) -> f32 {
+    return viewers.iter().map(|viewer| viewer.distance(position) as f32).fold(f32::INFINITY, f32::min);
+}
\ No newline at end of file
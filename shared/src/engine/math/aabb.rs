@@ -0,0 +1,126 @@
+use serde::{Serialize, Deserialize};
+
+use super::{vector::Vec3, ray::{Ray, RayHit}, coords::BlockPos};
+
+/// Axis aligned bounding box, stored as its minimum and maximum corners.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3
+}
+
+impl Aabb {
+    /// Will panic in debug mode if any component of min is greater than max.
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        debug_assert!(min.x <= max.x && min.y <= max.y && min.z <= max.z, "Aabb min must not exceed max");
+        return Aabb { min, max };
+    }
+
+    pub fn from_center_half_extents(center: Vec3, half_extents: Vec3) -> Aabb {
+        return Aabb::new(center - half_extents, center + half_extents);
+    }
+
+    /// The unit box occupied by a block, relative to a block origin such as a RenderOrigin's.
+    /// Positions are made relative first, as f32 cannot represent far out block coordinates.
+    /// ```
+    /// # use shared::engine::math::aabb::Aabb;
+    /// # use shared::engine::math::coords::BlockPos;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let aabb = Aabb::block(BlockPos::new(1_000_005, 3, 2), BlockPos::new(1_000_000, 0, 0));
+    /// assert_eq!(aabb.min, Vec3::new(5.0, 3.0, 2.0));
+    /// assert_eq!(aabb.max, Vec3::new(6.0, 4.0, 3.0));
+    /// ```
+    pub fn block(block: BlockPos, origin: BlockPos) -> Aabb {
+        let relative = block - origin;
+        let min = Vec3::new(relative.x as f32, relative.y as f32, relative.z as f32);
+        return Aabb::new(min, min + Vec3::ONE);
+    }
+
+    pub fn center(&self) -> Vec3 {
+        return (self.min + self.max) * 0.5;
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        return (self.max - self.min) * 0.5;
+    }
+
+    pub fn size(&self) -> Vec3 {
+        return self.max - self.min;
+    }
+
+    /// Check if a point is inside, or on the surface of, the box.
+    /// ```
+    /// # use shared::engine::math::aabb::Aabb;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let aabb = Aabb::new(Vec3::ZERO, Vec3::ONE);
+    /// assert!(aabb.contains_point(Vec3::splat(0.5)));
+    /// assert!(aabb.contains_point(Vec3::ONE));
+    /// assert!(!aabb.contains_point(Vec3::new(0.5, 1.5, 0.5)));
+    /// ```
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        return point.x >= self.min.x && point.x <= self.max.x
+            && point.y >= self.min.y && point.y <= self.max.y
+            && point.z >= self.min.z && point.z <= self.max.z;
+    }
+
+    /// Check if two boxes overlap. Boxes that only touch on a face do not overlap,
+    /// so an entity standing on a block is not considered to be colliding with it.
+    /// ```
+    /// # use shared::engine::math::aabb::Aabb;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let a = Aabb::new(Vec3::ZERO, Vec3::ONE);
+    /// assert!(a.intersects(&Aabb::new(Vec3::splat(0.5), Vec3::splat(1.5))));
+    /// assert!(!a.intersects(&Aabb::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 2.0, 1.0))));
+    /// ```
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        return self.min.x < other.max.x && self.max.x > other.min.x
+            && self.min.y < other.max.y && self.max.y > other.min.y
+            && self.min.z < other.max.z && self.max.z > other.min.z;
+    }
+
+    /// Smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        return Aabb::new(self.min.min(other.min), self.max.max(other.max));
+    }
+
+    /// Grow the box by amount in every direction.
+    pub fn expand(&self, amount: Vec3) -> Aabb {
+        return Aabb::new(self.min - amount, self.max + amount);
+    }
+
+    pub fn translate(&self, offset: Vec3) -> Aabb {
+        return Aabb::new(self.min + offset, self.max + offset);
+    }
+
+    /// The point on or in the box closest to point.
+    pub fn closest_point(&self, point: Vec3) -> Vec3 {
+        return point.max(self.min).min(self.max);
+    }
+
+    /// Move this box by velocity and find the first time it touches other, as a fraction of velocity from 0 to 1.
+    /// Works by shrinking this box to a point and growing other by its size, turning the sweep into a ray cast.
+    /// Returns None if the boxes don't touch during the move, or are already overlapping.
+    /// ```
+    /// # use shared::engine::math::aabb::Aabb;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let player = Aabb::from_center_half_extents(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.3, 0.9, 0.3));
+    /// let ground = Aabb::new(Vec3::new(-5.0, -1.0, -5.0), Vec3::new(5.0, 0.0, 5.0));
+    /// // Falling 0.2 blocks with 0.1 blocks of air below the feet.
+    /// let hit = player.sweep(&ground, Vec3::new(0.0, -0.2, 0.0)).unwrap();
+    /// assert!((hit.distance - 0.5).abs() < 1e-5);
+    /// assert_eq!(hit.normal, Vec3::Y);
+    /// assert!(player.sweep(&ground, Vec3::new(0.0, 0.2, 0.0)).is_none());
+    /// ```
+    pub fn sweep(&self, other: &Aabb, velocity: Vec3) -> Option<RayHit> {
+        if self.intersects(other) {
+            return None;
+        }
+        let expanded = other.expand(self.half_extents());
+        let hit = Ray::new(self.center(), velocity).intersect_aabb(&expanded, 1.0)?;
+        // Only touching a face without moving into it is not a collision.
+        if hit.normal.dot(velocity) >= 0.0 {
+            return None;
+        }
+        return Some(hit);
+    }
+}
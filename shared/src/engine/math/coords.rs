@@ -0,0 +1,349 @@
+use std::ops::{Add, Sub, Mul, Neg};
+
+use serde::{Serialize, Deserialize};
+
+use super::morton::MortonKey;
+
+/// Number of blocks along each axis of a chunk. Chunks are cubes.
+pub const CHUNK_SIZE: i32 = 32;
+/// Number of blocks in a chunk.
+pub const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// Integer position of a block in the world.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32
+}
+
+/// Position of a chunk in the world, in units of chunks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32
+}
+
+/// Position of a block within its chunk. Each axis is in the range 0 to CHUNK_SIZE - 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LocalPos {
+    pub x: u8,
+    pub y: u8,
+    pub z: u8
+}
+
+/// Continuous position in the world, in units of blocks. Used by entities and the camera.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct WorldPos {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64
+}
+
+impl BlockPos {
+    pub const ORIGIN: BlockPos = BlockPos { x: 0, y: 0, z: 0 };
+
+    pub const fn new(x: i32, y: i32, z: i32) -> BlockPos {
+        return BlockPos { x, y, z };
+    }
+
+    /// Get the chunk that holds this block. Negative coordinates round towards negative infinity.
+    /// ```
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// assert_eq!(BlockPos::new(33, 0, -1).chunk(), ChunkPos::new(1, 0, -1));
+    /// ```
+    pub fn chunk(&self) -> ChunkPos {
+        return ChunkPos {
+            x: self.x.div_euclid(CHUNK_SIZE),
+            y: self.y.div_euclid(CHUNK_SIZE),
+            z: self.z.div_euclid(CHUNK_SIZE)
+        };
+    }
+
+    /// Get the position of this block within its chunk.
+    /// ```
+    /// # use shared::engine::math::coords::{BlockPos, LocalPos};
+    /// assert_eq!(BlockPos::new(33, 0, -1).local(), LocalPos::new(1, 0, 31));
+    /// ```
+    pub fn local(&self) -> LocalPos {
+        return LocalPos {
+            x: self.x.rem_euclid(CHUNK_SIZE) as u8,
+            y: self.y.rem_euclid(CHUNK_SIZE) as u8,
+            z: self.z.rem_euclid(CHUNK_SIZE) as u8
+        };
+    }
+
+    /// Position of the minimum corner of this block.
+    pub fn corner(&self) -> WorldPos {
+        return WorldPos::new(self.x as f64, self.y as f64, self.z as f64);
+    }
+
+    /// Position of the center of this block.
+    /// ```
+    /// # use shared::engine::math::coords::{BlockPos, WorldPos};
+    /// assert_eq!(BlockPos::new(1, 2, 3).center(), WorldPos::new(1.5, 2.5, 3.5));
+    /// ```
+    pub fn center(&self) -> WorldPos {
+        return WorldPos::new(self.x as f64 + 0.5, self.y as f64 + 0.5, self.z as f64 + 0.5);
+    }
+
+    pub fn offset(&self, x: i32, y: i32, z: i32) -> BlockPos {
+        return BlockPos::new(self.x + x, self.y + y, self.z + z);
+    }
+
+    /// The 6 blocks sharing a face with this block, in the order -X, +X, -Y, +Y, -Z, +Z.
+    /// ```
+    /// # use shared::engine::math::coords::BlockPos;
+    /// let neighbors = BlockPos::ORIGIN.neighbors();
+    /// assert_eq!(neighbors.len(), 6);
+    /// assert!(neighbors.contains(&BlockPos::new(0, 1, 0)));
+    /// ```
+    pub fn neighbors(&self) -> [BlockPos; 6] {
+        return [
+            self.offset(-1, 0, 0), self.offset(1, 0, 0),
+            self.offset(0, -1, 0), self.offset(0, 1, 0),
+            self.offset(0, 0, -1), self.offset(0, 0, 1)
+        ];
+    }
+
+    /// Squared euclidean distance between block positions. Differences are taken as i64, so positions on
+    /// opposite sides of the world don't overflow.
+    /// ```
+    /// # use shared::engine::math::coords::BlockPos;
+    /// let far = BlockPos::new(1_500_000_000, 0, 0);
+    /// let opposite = BlockPos::new(-1_500_000_000, 0, 0);
+    /// assert_eq!(far.distance_squared(opposite), 9_000_000_000_000_000_000);
+    /// ```
+    pub fn distance_squared(&self, other: BlockPos) -> i64 {
+        let dx = self.x as i64 - other.x as i64;
+        let dy = self.y as i64 - other.y as i64;
+        let dz = self.z as i64 - other.z as i64;
+        return dx * dx + dy * dy + dz * dz;
+    }
+
+    /// Sum of the absolute differences along each axis.
+    pub fn manhattan_distance(&self, other: BlockPos) -> i64 {
+        return (self.x as i64 - other.x as i64).abs()
+            + (self.y as i64 - other.y as i64).abs()
+            + (self.z as i64 - other.z as i64).abs();
+    }
+}
+
+impl ChunkPos {
+    pub const ORIGIN: ChunkPos = ChunkPos { x: 0, y: 0, z: 0 };
+
+    pub const fn new(x: i32, y: i32, z: i32) -> ChunkPos {
+        return ChunkPos { x, y, z };
+    }
+
+    /// Get the block at the minimum corner of this chunk.
+    /// ```
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// assert_eq!(ChunkPos::new(1, 0, -1).origin(), BlockPos::new(32, 0, -32));
+    /// ```
+    pub fn origin(&self) -> BlockPos {
+        return BlockPos::new(self.x * CHUNK_SIZE, self.y * CHUNK_SIZE, self.z * CHUNK_SIZE);
+    }
+
+    /// Get the world position of a block within this chunk.
+    /// ```
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos, LocalPos};
+    /// let block = BlockPos::new(-5, 70, 12);
+    /// assert_eq!(block.chunk().block(block.local()), block);
+    /// ```
+    pub fn block(&self, local: LocalPos) -> BlockPos {
+        return self.origin().offset(local.x as i32, local.y as i32, local.z as i32);
+    }
+
+    pub fn offset(&self, x: i32, y: i32, z: i32) -> ChunkPos {
+        return ChunkPos::new(self.x + x, self.y + y, self.z + z);
+    }
+
+    /// Z-order key used to hash and sort chunks in storage.
+    /// ```
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// # use shared::engine::math::morton::MortonKey;
+    /// let chunk = ChunkPos::new(4, -2, 9);
+    /// assert_eq!(chunk.morton(), MortonKey::from_chunk(chunk));
+    /// ```
+    pub fn morton(&self) -> MortonKey {
+        return MortonKey::from_chunk(*self);
+    }
+
+    /// The 6 chunks sharing a face with this chunk, in the order -X, +X, -Y, +Y, -Z, +Z.
+    pub fn neighbors(&self) -> [ChunkPos; 6] {
+        return [
+            self.offset(-1, 0, 0), self.offset(1, 0, 0),
+            self.offset(0, -1, 0), self.offset(0, 1, 0),
+            self.offset(0, 0, -1), self.offset(0, 0, 1)
+        ];
+    }
+
+    /// Iterate every chunk within a cube of chebyshev radius around this chunk, including this chunk.
+    /// ```
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// assert_eq!(ChunkPos::ORIGIN.within_radius(1).count(), 27);
+    /// ```
+    pub fn within_radius(&self, radius: i32) -> impl Iterator<Item = ChunkPos> {
+        let center = *self;
+        return (-radius..=radius).flat_map(move |y| {
+            (-radius..=radius).flat_map(move |z| {
+                (-radius..=radius).map(move |x| center.offset(x, y, z))
+            })
+        });
+    }
+
+    /// Squared euclidean distance between chunk positions, in chunks.
+    pub fn distance_squared(&self, other: ChunkPos) -> i64 {
+        let dx = self.x as i64 - other.x as i64;
+        let dy = self.y as i64 - other.y as i64;
+        let dz = self.z as i64 - other.z as i64;
+        return dx * dx + dy * dy + dz * dz;
+    }
+}
+
+impl LocalPos {
+    /// Create a local position. Will panic in debug mode if any axis is outside of the chunk.
+    pub fn new(x: u8, y: u8, z: u8) -> LocalPos {
+        debug_assert!((x as i32) < CHUNK_SIZE && (y as i32) < CHUNK_SIZE && (z as i32) < CHUNK_SIZE, "Local position is outside of the chunk");
+        return LocalPos { x, y, z };
+    }
+
+    /// Flat index into chunk block storage. X varies fastest, then Z, then Y.
+    /// ```
+    /// # use shared::engine::math::coords::{LocalPos, CHUNK_VOLUME};
+    /// assert_eq!(LocalPos::new(0, 0, 0).index(), 0);
+    /// assert_eq!(LocalPos::new(31, 31, 31).index(), CHUNK_VOLUME - 1);
+    /// assert_eq!(LocalPos::from_index(LocalPos::new(3, 4, 5).index()), LocalPos::new(3, 4, 5));
+    /// ```
+    pub fn index(&self) -> usize {
+        let size = CHUNK_SIZE as usize;
+        return self.x as usize + (self.z as usize * size) + (self.y as usize * size * size);
+    }
+
+    /// Inverse of index().
+    pub fn from_index(index: usize) -> LocalPos {
+        debug_assert!(index < CHUNK_VOLUME, "Local index is outside of the chunk");
+        let size = CHUNK_SIZE as usize;
+        return LocalPos {
+            x: (index % size) as u8,
+            z: ((index / size) % size) as u8,
+            y: (index / (size * size)) as u8
+        };
+    }
+
+    /// Iterate every local position in a chunk, in index order.
+    pub fn all() -> impl Iterator<Item = LocalPos> {
+        return (0..CHUNK_VOLUME).map(LocalPos::from_index);
+    }
+}
+
+impl WorldPos {
+    pub const ORIGIN: WorldPos = WorldPos { x: 0.0, y: 0.0, z: 0.0 };
+
+    pub const fn new(x: f64, y: f64, z: f64) -> WorldPos {
+        return WorldPos { x, y, z };
+    }
+
+    /// Get the block containing this position.
+    /// ```
+    /// # use shared::engine::math::coords::{BlockPos, WorldPos};
+    /// assert_eq!(WorldPos::new(1.5, -0.5, 0.0).block(), BlockPos::new(1, -1, 0));
+    /// ```
+    pub fn block(&self) -> BlockPos {
+        return BlockPos::new(self.x.floor() as i32, self.y.floor() as i32, self.z.floor() as i32);
+    }
+
+    /// Get the chunk containing this position.
+    pub fn chunk(&self) -> ChunkPos {
+        return self.block().chunk();
+    }
+
+    pub fn length(&self) -> f64 {
+        return self.length_squared().sqrt();
+    }
+
+    pub fn length_squared(&self) -> f64 {
+        return self.x * self.x + self.y * self.y + self.z * self.z;
+    }
+
+    pub fn distance(&self, other: WorldPos) -> f64 {
+        return (*self - other).length();
+    }
+}
+
+macro_rules! impl_integer_ops {
+    ($t:ty) => {
+        impl Add for $t {
+            type Output = $t;
+            fn add(self, rhs: $t) -> $t {
+                return <$t>::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z);
+            }
+        }
+
+        impl Sub for $t {
+            type Output = $t;
+            fn sub(self, rhs: $t) -> $t {
+                return <$t>::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z);
+            }
+        }
+
+        impl Neg for $t {
+            type Output = $t;
+            fn neg(self) -> $t {
+                return <$t>::new(-self.x, -self.y, -self.z);
+            }
+        }
+
+        impl Mul<i32> for $t {
+            type Output = $t;
+            fn mul(self, rhs: i32) -> $t {
+                return <$t>::new(self.x * rhs, self.y * rhs, self.z * rhs);
+            }
+        }
+    };
+}
+
+impl_integer_ops!(BlockPos);
+impl_integer_ops!(ChunkPos);
+
+impl Add for WorldPos {
+    type Output = WorldPos;
+    fn add(self, rhs: WorldPos) -> WorldPos {
+        return WorldPos::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z);
+    }
+}
+
+impl Sub for WorldPos {
+    type Output = WorldPos;
+    fn sub(self, rhs: WorldPos) -> WorldPos {
+        return WorldPos::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z);
+    }
+}
+
+impl Neg for WorldPos {
+    type Output = WorldPos;
+    fn neg(self) -> WorldPos {
+        return WorldPos::new(-self.x, -self.y, -self.z);
+    }
+}
+
+impl Mul<f64> for WorldPos {
+    type Output = WorldPos;
+    fn mul(self, rhs: f64) -> WorldPos {
+        return WorldPos::new(self.x * rhs, self.y * rhs, self.z * rhs);
+    }
+}
+
+impl From<BlockPos> for WorldPos {
+    fn from(pos: BlockPos) -> WorldPos {
+        return pos.corner();
+    }
+}
+
+impl From<(i32, i32, i32)> for BlockPos {
+    fn from(pos: (i32, i32, i32)) -> BlockPos {
+        return BlockPos::new(pos.0, pos.1, pos.2);
+    }
+}
@@ -0,0 +1,167 @@
+use serde::{Serialize, Deserialize};
+
+use super::{coords::{BlockPos, ChunkPos}, vector::Vec3};
+
+/// One of the 3 world axes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Axis {
+    X,
+    Y,
+    Z
+}
+
+/// One of the 6 faces of a block. Ordered the same as BlockPos::neighbors() and ChunkPos::neighbors(),
+/// so the index of a direction is the index of the neighbor in that direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum Direction {
+    NegX = 0,
+    PosX = 1,
+    NegY = 2,
+    PosY = 3,
+    NegZ = 4,
+    PosZ = 5
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    /// The direction along this axis, either positive or negative.
+    pub fn direction(&self, positive: bool) -> Direction {
+        let index = (*self as usize) * 2 + positive as usize;
+        return Direction::ALL[index];
+    }
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [Direction::NegX, Direction::PosX, Direction::NegY, Direction::PosY, Direction::NegZ, Direction::PosZ];
+    /// Directions perpendicular to up, in clockwise order when viewed from above.
+    pub const HORIZONTAL: [Direction; 4] = [Direction::NegZ, Direction::PosX, Direction::PosZ, Direction::NegX];
+
+    pub fn index(&self) -> usize {
+        return *self as usize;
+    }
+
+    /// Will panic if index is not less than 6.
+    pub fn from_index(index: usize) -> Direction {
+        return Direction::ALL[index];
+    }
+
+    /// ```
+    /// # use shared::engine::math::direction::Direction;
+    /// for direction in Direction::ALL {
+    ///     assert_eq!(direction.opposite().opposite(), direction);
+    /// }
+    /// assert_eq!(Direction::PosY.opposite(), Direction::NegY);
+    /// ```
+    pub fn opposite(&self) -> Direction {
+        return Direction::ALL[self.index() ^ 1];
+    }
+
+    pub fn axis(&self) -> Axis {
+        return Axis::ALL[self.index() / 2];
+    }
+
+    pub fn is_positive(&self) -> bool {
+        return self.index() & 1 == 1;
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        return self.axis() != Axis::Y;
+    }
+
+    /// Unit block offset in this direction.
+    /// ```
+    /// # use shared::engine::math::direction::Direction;
+    /// # use shared::engine::math::coords::BlockPos;
+    /// let block = BlockPos::new(4, 5, 6);
+    /// for direction in Direction::ALL {
+    ///     assert_eq!(block + direction.offset(), block.neighbors()[direction.index()]);
+    /// }
+    /// ```
+    pub fn offset(&self) -> BlockPos {
+        let sign = if self.is_positive() { 1 } else { -1 };
+        return match self.axis() {
+            Axis::X => BlockPos::new(sign, 0, 0),
+            Axis::Y => BlockPos::new(0, sign, 0),
+            Axis::Z => BlockPos::new(0, 0, sign)
+        };
+    }
+
+    /// Unit chunk offset in this direction.
+    pub fn chunk_offset(&self) -> ChunkPos {
+        let offset = self.offset();
+        return ChunkPos::new(offset.x, offset.y, offset.z);
+    }
+
+    /// Face normal.
+    pub fn normal(&self) -> Vec3 {
+        let offset = self.offset();
+        return Vec3::new(offset.x as f32, offset.y as f32, offset.z as f32);
+    }
+
+    /// The direction closest to a vector, such as the face a player is looking at.
+    /// ```
+    /// # use shared::engine::math::direction::Direction;
+    /// # use shared::engine::math::vector::Vec3;
+    /// assert_eq!(Direction::nearest(Vec3::new(0.2, -0.9, 0.3)), Direction::NegY);
+    /// ```
+    pub fn nearest(vector: Vec3) -> Direction {
+        let abs = vector.abs();
+        if abs.x >= abs.y && abs.x >= abs.z {
+            return Axis::X.direction(vector.x > 0.0);
+        }
+        if abs.y >= abs.z {
+            return Axis::Y.direction(vector.y > 0.0);
+        }
+        return Axis::Z.direction(vector.z > 0.0);
+    }
+
+    /// Rotate a quarter turn clockwise around the Y axis, when viewed from above.
+    /// Up and down are unchanged.
+    /// ```
+    /// # use shared::engine::math::direction::Direction;
+    /// assert_eq!(Direction::NegZ.rotate_y_clockwise(), Direction::PosX);
+    /// assert_eq!(Direction::PosY.rotate_y_clockwise(), Direction::PosY);
+    /// ```
+    pub fn rotate_y_clockwise(&self) -> Direction {
+        return self.rotate_y(1);
+    }
+
+    pub fn rotate_y_counter_clockwise(&self) -> Direction {
+        return self.rotate_y(3);
+    }
+
+    /// Rotate a number of quarter turns clockwise around the Y axis, when viewed from above.
+    /// Used to orient blocks like stairs and doors relative to the placing player.
+    /// ```
+    /// # use shared::engine::math::direction::Direction;
+    /// assert_eq!(Direction::PosX.rotate_y(2), Direction::NegX);
+    /// assert_eq!(Direction::PosX.rotate_y(-1), Direction::NegZ);
+    /// ```
+    pub fn rotate_y(&self, quarter_turns: i32) -> Direction {
+        if !self.is_horizontal() {
+            return *self;
+        }
+        let current = Direction::HORIZONTAL.iter().position(|d| d == self).unwrap() as i32;
+        return Direction::HORIZONTAL[(current + quarter_turns).rem_euclid(4) as usize];
+    }
+
+    /// Rotate a quarter turn around an axis, following the right hand rule.
+    /// ```
+    /// # use shared::engine::math::direction::{Direction, Axis};
+    /// assert_eq!(Direction::PosX.rotate_around(Axis::Z), Direction::PosY);
+    /// assert_eq!(Direction::PosY.rotate_around(Axis::X), Direction::PosZ);
+    /// assert_eq!(Direction::PosZ.rotate_around(Axis::Y), Direction::PosX);
+    /// assert_eq!(Direction::PosZ.rotate_around(Axis::Z), Direction::PosZ);
+    /// ```
+    pub fn rotate_around(&self, axis: Axis) -> Direction {
+        let o = self.offset();
+        let rotated = match axis {
+            Axis::X => BlockPos::new(o.x, -o.z, o.y),
+            Axis::Y => BlockPos::new(o.z, o.y, -o.x),
+            Axis::Z => BlockPos::new(-o.y, o.x, o.z)
+        };
+        return Direction::nearest(Vec3::new(rotated.x as f32, rotated.y as f32, rotated.z as f32));
+    }
+}
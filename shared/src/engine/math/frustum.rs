@@ -0,0 +1,141 @@
+use super::{vector::{Vec3, Vec4}, matrix::Mat4, aabb::Aabb};
+
+/// Plane where normal.dot(point) + distance is zero. Points on the side the normal faces are positive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub distance: f32
+}
+
+impl Plane {
+    /// Normalize a plane stored as (a, b, c, d).
+    fn from_vec4(v: Vec4) -> Plane {
+        let normal = v.truncate();
+        let length = normal.length();
+        return Plane { normal: normal / length, distance: v.w / length };
+    }
+
+    /// Signed distance from the plane to a point.
+    pub fn distance_to(&self, point: Vec3) -> f32 {
+        return self.normal.dot(point) + self.distance;
+    }
+}
+
+/// Result of testing a shape against a frustum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Containment {
+    Outside,
+    Intersecting,
+    Inside
+}
+
+/// The volume visible to a camera, as 6 inward facing planes.
+/// Used by the client to skip drawing chunk meshes that are off screen, and by the server
+/// to prioritize what a player can see.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near, far.
+    pub planes: [Plane; 6]
+}
+
+impl Frustum {
+    /// Extract the planes from a view-projection matrix, using the 0..1 depth range of Mat4::perspective_rh().
+    /// Anything tested against the frustum must be in the space the view matrix transforms from,
+    /// which is render origin relative space on the client.
+    pub fn from_view_projection(view_projection: &Mat4) -> Frustum {
+        let (r0, r1, r2, r3) = (view_projection.row(0), view_projection.row(1), view_projection.row(2), view_projection.row(3));
+        return Frustum {
+            planes: [
+                Plane::from_vec4(r3 + r0),
+                Plane::from_vec4(r3 - r0),
+                Plane::from_vec4(r3 + r1),
+                Plane::from_vec4(r3 - r1),
+                Plane::from_vec4(r2),
+                Plane::from_vec4(r3 - r2)
+            ]
+        };
+    }
+
+    /// ```
+    /// # use shared::engine::math::frustum::Frustum;
+    /// # use shared::engine::math::matrix::Mat4;
+    /// # use shared::engine::math::vector::Vec3;
+    /// // Camera at the origin looking down -Z.
+    /// let frustum = Frustum::from_view_projection(&Mat4::perspective_rh(1.5, 1.0, 0.1, 100.0));
+    /// assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -10.0)));
+    /// assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 10.0)));
+    /// assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -200.0)));
+    /// ```
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        return self.planes.iter().all(|plane| plane.distance_to(point) >= 0.0);
+    }
+
+    /// ```
+    /// # use shared::engine::math::frustum::{Frustum, Containment};
+    /// # use shared::engine::math::matrix::Mat4;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let frustum = Frustum::from_view_projection(&Mat4::perspective_rh(1.5, 1.0, 0.1, 100.0));
+    /// assert_eq!(frustum.test_sphere(Vec3::new(0.0, 0.0, -50.0), 1.0), Containment::Inside);
+    /// assert_eq!(frustum.test_sphere(Vec3::new(0.0, 0.0, 0.0), 1.0), Containment::Intersecting);
+    /// assert_eq!(frustum.test_sphere(Vec3::new(0.0, 0.0, 50.0), 1.0), Containment::Outside);
+    /// ```
+    pub fn test_sphere(&self, center: Vec3, radius: f32) -> Containment {
+        let mut result = Containment::Inside;
+        for plane in self.planes.iter() {
+            let distance = plane.distance_to(center);
+            if distance < -radius {
+                return Containment::Outside;
+            }
+            if distance < radius {
+                result = Containment::Intersecting;
+            }
+        }
+        return result;
+    }
+
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        return self.test_sphere(center, radius) != Containment::Outside;
+    }
+
+    /// Conservative box test. Boxes near a frustum corner may be reported as intersecting when they are
+    /// just outside, which only costs drawing something unnecessarily.
+    /// ```
+    /// # use shared::engine::math::frustum::{Frustum, Containment};
+    /// # use shared::engine::math::matrix::Mat4;
+    /// # use shared::engine::math::aabb::Aabb;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::UP);
+    /// let frustum = Frustum::from_view_projection(&(Mat4::perspective_rh(1.5, 1.0, 0.1, 100.0) * view));
+    /// let chunk_ahead = Aabb::new(Vec3::new(32.0, -16.0, -16.0), Vec3::new(64.0, 16.0, 16.0));
+    /// let chunk_behind = Aabb::new(Vec3::new(-64.0, -16.0, -16.0), Vec3::new(-32.0, 16.0, 16.0));
+    /// assert_eq!(frustum.test_aabb(&chunk_ahead), Containment::Inside);
+    /// assert_eq!(frustum.test_aabb(&chunk_behind), Containment::Outside);
+    /// ```
+    pub fn test_aabb(&self, aabb: &Aabb) -> Containment {
+        let mut result = Containment::Inside;
+        for plane in self.planes.iter() {
+            // Corner furthest along the plane normal, and the one furthest against it.
+            let positive = Vec3::new(
+                if plane.normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.normal.z >= 0.0 { aabb.max.z } else { aabb.min.z }
+            );
+            if plane.distance_to(positive) < 0.0 {
+                return Containment::Outside;
+            }
+            let negative = Vec3::new(
+                if plane.normal.x >= 0.0 { aabb.min.x } else { aabb.max.x },
+                if plane.normal.y >= 0.0 { aabb.min.y } else { aabb.max.y },
+                if plane.normal.z >= 0.0 { aabb.min.z } else { aabb.max.z }
+            );
+            if plane.distance_to(negative) < 0.0 {
+                result = Containment::Intersecting;
+            }
+        }
+        return result;
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        return self.test_aabb(aabb) != Containment::Outside;
+    }
+}
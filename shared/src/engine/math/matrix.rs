@@ -0,0 +1,275 @@
+use std::ops::{Mul, MulAssign};
+
+use serde::{Serialize, Deserialize};
+
+use super::{vector::{Vec3, Vec4}, quat::Quat};
+
+/// Column major 4x4 f32 matrix, made of 16 byte aligned columns so it can be uploaded directly to the GPU.
+/// Transforms use column vectors, so `a * b` applies b first, then a.
+/// ```
+/// # use shared::engine::math::matrix::Mat4;
+/// assert_eq!(std::mem::size_of::<Mat4>(), 64);
+/// assert_eq!(std::mem::align_of::<Mat4>(), 16);
+/// ```
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Mat4 {
+    pub cols: [Vec4; 4]
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4::from_cols(
+        Vec4::new(1.0, 0.0, 0.0, 0.0),
+        Vec4::new(0.0, 1.0, 0.0, 0.0),
+        Vec4::new(0.0, 0.0, 1.0, 0.0),
+        Vec4::new(0.0, 0.0, 0.0, 1.0)
+    );
+
+    pub const fn from_cols(x: Vec4, y: Vec4, z: Vec4, w: Vec4) -> Mat4 {
+        return Mat4 { cols: [x, y, z, w] };
+    }
+
+    /// ```
+    /// # use shared::engine::math::matrix::Mat4;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let translation = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0));
+    /// assert_eq!(translation.transform_point(Vec3::ZERO), Vec3::new(1.0, 2.0, 3.0));
+    /// // Directions are unaffected by translation.
+    /// assert_eq!(translation.transform_vector(Vec3::X), Vec3::X);
+    /// ```
+    pub fn from_translation(translation: Vec3) -> Mat4 {
+        let mut m = Mat4::IDENTITY;
+        m.cols[3] = translation.extend(1.0);
+        return m;
+    }
+
+    pub fn from_scale(scale: Vec3) -> Mat4 {
+        return Mat4::from_cols(
+            Vec4::new(scale.x, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, scale.y, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, scale.z, 0.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0)
+        );
+    }
+
+    /// ```
+    /// # use shared::engine::math::matrix::Mat4;
+    /// # use shared::engine::math::quat::Quat;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let rotation = Quat::from_axis_angle(Vec3::X, 0.7);
+    /// let v = Vec3::new(1.0, 2.0, 3.0);
+    /// assert!(Mat4::from_quat(rotation).transform_vector(v).approx_eq(rotation * v, 1e-5));
+    /// ```
+    pub fn from_quat(rotation: Quat) -> Mat4 {
+        let Quat { x, y, z, w } = rotation;
+        let (x2, y2, z2) = (x + x, y + y, z + z);
+        let (xx, xy, xz) = (x * x2, x * y2, x * z2);
+        let (yy, yz, zz) = (y * y2, y * z2, z * z2);
+        let (wx, wy, wz) = (w * x2, w * y2, w * z2);
+        return Mat4::from_cols(
+            Vec4::new(1.0 - (yy + zz), xy + wz, xz - wy, 0.0),
+            Vec4::new(xy - wz, 1.0 - (xx + zz), yz + wx, 0.0),
+            Vec4::new(xz + wy, yz - wx, 1.0 - (xx + yy), 0.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0)
+        );
+    }
+
+    /// Scale, then rotate, then translate.
+    pub fn from_scale_rotation_translation(scale: Vec3, rotation: Quat, translation: Vec3) -> Mat4 {
+        let r = Mat4::from_quat(rotation);
+        return Mat4::from_cols(
+            r.cols[0] * scale.x,
+            r.cols[1] * scale.y,
+            r.cols[2] * scale.z,
+            translation.extend(1.0)
+        );
+    }
+
+    /// Right handed view matrix looking from eye towards target.
+    /// ```
+    /// # use shared::engine::math::matrix::Mat4;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::UP);
+    /// // The target ends up directly in front of the camera, down -Z.
+    /// assert!(view.transform_point(Vec3::ZERO).approx_eq(Vec3::new(0.0, 0.0, -5.0), 1e-6));
+    /// ```
+    pub fn look_at_rh(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+        let forward = (target - eye).normalize();
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+        return Mat4::from_cols(
+            Vec4::new(right.x, up.x, -forward.x, 0.0),
+            Vec4::new(right.y, up.y, -forward.y, 0.0),
+            Vec4::new(right.z, up.z, -forward.z, 0.0),
+            Vec4::new(-right.dot(eye), -up.dot(eye), forward.dot(eye), 1.0)
+        );
+    }
+
+    /// Right handed perspective projection mapping depth to the 0..1 range used by Vulkan and wgpu.
+    /// fov_y is in radians.
+    /// ```
+    /// # use shared::engine::math::matrix::Mat4;
+    /// # use shared::engine::math::vector::{Vec3, Vec4};
+    /// let projection = Mat4::perspective_rh(1.2, 16.0 / 9.0, 0.1, 100.0);
+    /// let near = projection * Vec4::new(0.0, 0.0, -0.1, 1.0);
+    /// let far = projection * Vec4::new(0.0, 0.0, -100.0, 1.0);
+    /// assert!((near.z / near.w).abs() < 1e-6);
+    /// assert!((far.z / far.w - 1.0).abs() < 1e-6);
+    /// ```
+    pub fn perspective_rh(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let f = 1.0 / (fov_y * 0.5).tan();
+        let range = far / (near - far);
+        return Mat4::from_cols(
+            Vec4::new(f / aspect, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, f, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, range, -1.0),
+            Vec4::new(0.0, 0.0, range * near, 0.0)
+        );
+    }
+
+    /// Right handed orthographic projection mapping depth to the 0..1 range.
+    pub fn orthographic_rh(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+        let rw = 1.0 / (right - left);
+        let rh = 1.0 / (top - bottom);
+        let rd = 1.0 / (near - far);
+        return Mat4::from_cols(
+            Vec4::new(2.0 * rw, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 * rh, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, rd, 0.0),
+            Vec4::new(-(left + right) * rw, -(top + bottom) * rh, rd * near, 1.0)
+        );
+    }
+
+    /// Get a row. Rows are not stored contiguously, so this gathers from every column.
+    pub fn row(&self, index: usize) -> Vec4 {
+        return Vec4::new(self.cols[0][index], self.cols[1][index], self.cols[2][index], self.cols[3][index]);
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        return Mat4::from_cols(self.row(0), self.row(1), self.row(2), self.row(3));
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let [a, b, c, d] = self.cols;
+        let s0 = a.x * b.y - b.x * a.y;
+        let s1 = a.x * b.z - b.x * a.z;
+        let s2 = a.x * b.w - b.x * a.w;
+        let s3 = a.y * b.z - b.y * a.z;
+        let s4 = a.y * b.w - b.y * a.w;
+        let s5 = a.z * b.w - b.z * a.w;
+        let c5 = c.z * d.w - d.z * c.w;
+        let c4 = c.y * d.w - d.y * c.w;
+        let c3 = c.y * d.z - d.y * c.z;
+        let c2 = c.x * d.w - d.x * c.w;
+        let c1 = c.x * d.z - d.x * c.z;
+        let c0 = c.x * d.y - d.x * c.y;
+        return s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0;
+    }
+
+    /// General inverse. Returns None if the matrix is singular.
+    /// ```
+    /// # use shared::engine::math::matrix::Mat4;
+    /// # use shared::engine::math::quat::Quat;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let m = Mat4::from_scale_rotation_translation(
+    ///     Vec3::new(2.0, 3.0, 4.0), Quat::from_axis_angle(Vec3::Y, 0.5), Vec3::new(1.0, 2.0, 3.0));
+    /// let p = Vec3::new(-4.0, 5.0, 6.0);
+    /// let inverse = m.inverse().unwrap();
+    /// assert!(inverse.transform_point(m.transform_point(p)).approx_eq(p, 1e-4));
+    /// assert!(Mat4::from_scale(Vec3::ZERO).inverse().is_none());
+    /// ```
+    pub fn inverse(&self) -> Option<Mat4> {
+        let [a, b, c, d] = self.cols;
+        let s0 = a.x * b.y - b.x * a.y;
+        let s1 = a.x * b.z - b.x * a.z;
+        let s2 = a.x * b.w - b.x * a.w;
+        let s3 = a.y * b.z - b.y * a.z;
+        let s4 = a.y * b.w - b.y * a.w;
+        let s5 = a.z * b.w - b.z * a.w;
+        let c5 = c.z * d.w - d.z * c.w;
+        let c4 = c.y * d.w - d.y * c.w;
+        let c3 = c.y * d.z - d.y * c.z;
+        let c2 = c.x * d.w - d.x * c.w;
+        let c1 = c.x * d.z - d.x * c.z;
+        let c0 = c.x * d.y - d.x * c.y;
+        let det = s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0;
+        if det.abs() <= f32::EPSILON * f32::EPSILON {
+            return None;
+        }
+        let inv = 1.0 / det;
+        return Some(Mat4::from_cols(
+            Vec4::new(
+                (b.y * c5 - b.z * c4 + b.w * c3) * inv,
+                (-a.y * c5 + a.z * c4 - a.w * c3) * inv,
+                (d.y * s5 - d.z * s4 + d.w * s3) * inv,
+                (-c.y * s5 + c.z * s4 - c.w * s3) * inv
+            ),
+            Vec4::new(
+                (-b.x * c5 + b.z * c2 - b.w * c1) * inv,
+                (a.x * c5 - a.z * c2 + a.w * c1) * inv,
+                (-d.x * s5 + d.z * s2 - d.w * s1) * inv,
+                (c.x * s5 - c.z * s2 + c.w * s1) * inv
+            ),
+            Vec4::new(
+                (b.x * c4 - b.y * c2 + b.w * c0) * inv,
+                (-a.x * c4 + a.y * c2 - a.w * c0) * inv,
+                (d.x * s4 - d.y * s2 + d.w * s0) * inv,
+                (-c.x * s4 + c.y * s2 - c.w * s0) * inv
+            ),
+            Vec4::new(
+                (-b.x * c3 + b.y * c1 - b.z * c0) * inv,
+                (a.x * c3 - a.y * c1 + a.z * c0) * inv,
+                (-d.x * s3 + d.y * s1 - d.z * s0) * inv,
+                (c.x * s3 - c.y * s1 + c.z * s0) * inv
+            )
+        ));
+    }
+
+    /// Transform a position, applying translation and the perspective divide.
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        let v = *self * point.extend(1.0);
+        if v.w == 1.0 || v.w == 0.0 {
+            return v.truncate();
+        }
+        return v.truncate() / v.w;
+    }
+
+    /// Transform a direction, ignoring translation.
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        return (*self * vector.extend(0.0)).truncate();
+    }
+
+    pub fn to_cols_array(&self) -> [f32; 16] {
+        let mut out = [0.0; 16];
+        for (i, col) in self.cols.iter().enumerate() {
+            out[i * 4..i * 4 + 4].copy_from_slice(&col.to_array());
+        }
+        return out;
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Mat4 {
+        return Mat4::IDENTITY;
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+    fn mul(self, rhs: Vec4) -> Vec4 {
+        return self.cols[0] * rhs.x + self.cols[1] * rhs.y + self.cols[2] * rhs.z + self.cols[3] * rhs.w;
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        return Mat4::from_cols(self * rhs.cols[0], self * rhs.cols[1], self * rhs.cols[2], self * rhs.cols[3]);
+    }
+}
+
+impl MulAssign for Mat4 {
+    fn mul_assign(&mut self, rhs: Mat4) {
+        *self = *self * rhs;
+    }
+}
@@ -0,0 +1,12 @@
+pub mod coords;
+pub mod precision;
+pub mod vector;
+pub mod quat;
+pub mod matrix;
+pub mod aabb;
+pub mod ray;
+pub mod morton;
+pub mod rng;
+pub mod direction;
+pub mod frustum;
+pub mod noise;
\ No newline at end of file
@@ -0,0 +1,90 @@
+use serde::{Serialize, Deserialize};
+
+use super::coords::ChunkPos;
+
+/// Bits of each coordinate interleaved into a Morton code. 3 * 21 fits in a u64.
+pub const MORTON_BITS: u32 = 21;
+/// Signed coordinates are biased by this to become unsigned while keeping their ordering.
+/// Chunk coordinates must be in the range -2^20..2^20, which is over 33 million blocks in each direction.
+pub const MORTON_BIAS: i32 = 1 << (MORTON_BITS - 1);
+
+const MORTON_MASK: u64 = (1 << MORTON_BITS) - 1;
+
+/// Spread the low 21 bits of v so there are 2 zero bits between each.
+fn spread_bits(v: u32) -> u64 {
+    let mut x = (v as u64) & MORTON_MASK;
+    x = (x | (x << 32)) & 0x1f00000000ffff;
+    x = (x | (x << 16)) & 0x1f0000ff0000ff;
+    x = (x | (x << 8)) & 0x100f00f00f00f00f;
+    x = (x | (x << 4)) & 0x10c30c30c30c30c3;
+    x = (x | (x << 2)) & 0x1249249249249249;
+    return x;
+}
+
+/// Inverse of spread_bits.
+fn compact_bits(v: u64) -> u32 {
+    let mut x = v & 0x1249249249249249;
+    x = (x | (x >> 2)) & 0x10c30c30c30c30c3;
+    x = (x | (x >> 4)) & 0x100f00f00f00f00f;
+    x = (x | (x >> 8)) & 0x1f0000ff0000ff;
+    x = (x | (x >> 16)) & 0x1f00000000ffff;
+    x = (x | (x >> 32)) & MORTON_MASK;
+    return x as u32;
+}
+
+/// Interleave the low 21 bits of each coordinate, x in the lowest bit.
+/// ```
+/// # use shared::engine::math::morton::morton_encode;
+/// assert_eq!(morton_encode(1, 0, 0), 0b001);
+/// assert_eq!(morton_encode(0, 1, 0), 0b010);
+/// assert_eq!(morton_encode(0, 0, 1), 0b100);
+/// assert_eq!(morton_encode(3, 3, 3), 0b111111);
+/// ```
+pub fn morton_encode(x: u32, y: u32, z: u32) -> u64 {
+    return spread_bits(x) | (spread_bits(y) << 1) | (spread_bits(z) << 2);
+}
+
+/// ```
+/// # use shared::engine::math::morton::{morton_encode, morton_decode};
+/// let code = morton_encode(123_456, 7, 2_000_000);
+/// assert_eq!(morton_decode(code), (123_456, 7, 2_000_000));
+/// ```
+pub fn morton_decode(code: u64) -> (u32, u32, u32) {
+    return (compact_bits(code), compact_bits(code >> 1), compact_bits(code >> 2));
+}
+
+/// Z-order key of a chunk position. Chunk storage is keyed and sorted by this, so chunks that are close
+/// in the world are mostly close in memory and iteration order, which keeps neighbourhood queries
+/// during lighting and meshing cache friendly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct MortonKey(pub u64);
+
+impl MortonKey {
+    /// Will panic in debug mode if the chunk is outside the encodable range.
+    /// ```
+    /// # use shared::engine::math::morton::MortonKey;
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let chunk = ChunkPos::new(-3, 12, -700_000);
+    /// assert_eq!(MortonKey::from_chunk(chunk).chunk(), chunk);
+    /// // Ordering of each axis is preserved across zero.
+    /// assert!(MortonKey::from_chunk(ChunkPos::new(-1, 0, 0)) < MortonKey::from_chunk(ChunkPos::new(0, 0, 0)));
+    /// ```
+    pub fn from_chunk(chunk: ChunkPos) -> MortonKey {
+        let bias = |v: i32| {
+            debug_assert!((-MORTON_BIAS..MORTON_BIAS).contains(&v), "Chunk coordinate {} out of Morton range", v);
+            return (v + MORTON_BIAS) as u32;
+        };
+        return MortonKey(morton_encode(bias(chunk.x), bias(chunk.y), bias(chunk.z)));
+    }
+
+    pub fn chunk(&self) -> ChunkPos {
+        let (x, y, z) = morton_decode(self.0);
+        return ChunkPos::new(x as i32 - MORTON_BIAS, y as i32 - MORTON_BIAS, z as i32 - MORTON_BIAS);
+    }
+}
+
+impl From<ChunkPos> for MortonKey {
+    fn from(chunk: ChunkPos) -> MortonKey {
+        return MortonKey::from_chunk(chunk);
+    }
+}
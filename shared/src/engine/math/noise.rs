@@ -0,0 +1,130 @@
+use super::rng::WorldRng;
+
+/// Improved Perlin gradient noise, seeded so worlds are reproducible.
+#[derive(Clone)]
+pub struct Perlin {
+    permutation: Box<[u8; 512]>
+}
+
+fn fade(t: f64) -> f64 {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    return a + t * (b - a);
+}
+
+/// Dot product of the offset with one of 12 edge gradients, selected by the hash.
+fn gradient(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+    return (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v });
+}
+
+impl Perlin {
+    /// ```
+    /// # use shared::engine::math::noise::Perlin;
+    /// let a = Perlin::new(1);
+    /// assert_eq!(a.noise3(0.5, 1.25, 2.75), Perlin::new(1).noise3(0.5, 1.25, 2.75));
+    /// assert_ne!(a.noise3(0.5, 1.25, 2.75), Perlin::new(2).noise3(0.5, 1.25, 2.75));
+    /// // Zero on every lattice point.
+    /// assert_eq!(a.noise3(3.0, 4.0, 5.0), 0.0);
+    /// ```
+    pub fn new(seed: u64) -> Perlin {
+        let mut values: Vec<u8> = (0..=255).collect();
+        WorldRng::new(seed).shuffle(&mut values);
+        let mut permutation = Box::new([0u8; 512]);
+        for i in 0..512 {
+            permutation[i] = values[i & 255];
+        }
+        return Perlin { permutation };
+    }
+
+    /// Noise at a point, roughly in the range -1.0 to 1.0.
+    pub fn noise3(&self, x: f64, y: f64, z: f64) -> f64 {
+        let p = &self.permutation;
+        let (xf, yf, zf) = (x.floor(), y.floor(), z.floor());
+        let (xi, yi, zi) = ((xf as i64 & 255) as usize, (yf as i64 & 255) as usize, (zf as i64 & 255) as usize);
+        let (x, y, z) = (x - xf, y - yf, z - zf);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let a = p[xi] as usize + yi;
+        let (aa, ab) = (p[a] as usize + zi, p[a + 1] as usize + zi);
+        let b = p[xi + 1] as usize + yi;
+        let (ba, bb) = (p[b] as usize + zi, p[b + 1] as usize + zi);
+
+        return lerp(w,
+            lerp(v,
+                lerp(u, gradient(p[aa], x, y, z), gradient(p[ba], x - 1.0, y, z)),
+                lerp(u, gradient(p[ab], x, y - 1.0, z), gradient(p[bb], x - 1.0, y - 1.0, z))),
+            lerp(v,
+                lerp(u, gradient(p[aa + 1], x, y, z - 1.0), gradient(p[ba + 1], x - 1.0, y, z - 1.0)),
+                lerp(u, gradient(p[ab + 1], x, y - 1.0, z - 1.0), gradient(p[bb + 1], x - 1.0, y - 1.0, z - 1.0))));
+    }
+
+    /// 2D noise, for heightmaps and climate.
+    pub fn noise2(&self, x: f64, z: f64) -> f64 {
+        // Offset off the lattice plane, which is zero everywhere.
+        return self.noise3(x, 0.5, z);
+    }
+}
+
+/// Fractal noise summing octaves of Perlin noise, each at a higher frequency and lower amplitude.
+#[derive(Clone)]
+pub struct FractalNoise {
+    octaves: Vec<Perlin>,
+    frequency: f64,
+    lacunarity: f64,
+    gain: f64
+}
+
+impl FractalNoise {
+    /// Frequency is of the first octave, in cycles per block.
+    /// ```
+    /// # use shared::engine::math::noise::FractalNoise;
+    /// let noise = FractalNoise::new(42, 4, 1.0 / 64.0);
+    /// for x in 0..100 {
+    ///     let v = noise.sample2(x as f64 * 3.7, 12.0);
+    ///     assert!(v >= -1.0 && v <= 1.0);
+    /// }
+    /// ```
+    pub fn new(seed: u64, octaves: usize, frequency: f64) -> FractalNoise {
+        let mut rng = WorldRng::new(seed);
+        return FractalNoise {
+            octaves: (0..octaves).map(|_| Perlin::new(rng.next_u64())).collect(),
+            frequency,
+            lacunarity: 2.0,
+            gain: 0.5
+        };
+    }
+
+    /// Normalized to roughly -1.0 to 1.0.
+    pub fn sample3(&self, x: f64, y: f64, z: f64) -> f64 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut max = 0.0;
+        let mut frequency = self.frequency;
+        for octave in self.octaves.iter() {
+            total += octave.noise3(x * frequency, y * frequency, z * frequency) * amplitude;
+            max += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+        return (total / max).clamp(-1.0, 1.0);
+    }
+
+    pub fn sample2(&self, x: f64, z: f64) -> f64 {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut max = 0.0;
+        let mut frequency = self.frequency;
+        for octave in self.octaves.iter() {
+            total += octave.noise2(x * frequency, z * frequency) * amplitude;
+            max += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+        return (total / max).clamp(-1.0, 1.0);
+    }
+}
@@ -0,0 +1,90 @@
+use super::{coords::{BlockPos, WorldPos}, vector::Vec3};
+
+/// Size in blocks of the grid that render origins snap to.
+/// Snapping keeps the origin's own coordinates exact, and means rebasing only happens after crossing a cell.
+pub const ORIGIN_CELL_SIZE: i32 = 1024;
+
+/// Positions are stored in 64-bit floats on top of 32-bit block coordinates, which stay exact millions of blocks out.
+/// GPUs (and most client math) work in 32-bit floats, which lose sub-block precision past roughly 100,000 blocks.
+/// A RenderOrigin is a block aligned point near the camera that everything is made relative to before
+/// being converted to f32, so rendering precision only depends on distance from the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderOrigin {
+    origin: BlockPos
+}
+
+impl RenderOrigin {
+    /// Create an origin in the cell containing a position.
+    /// ```
+    /// # use shared::engine::math::precision::RenderOrigin;
+    /// # use shared::engine::math::coords::{BlockPos, WorldPos};
+    /// let origin = RenderOrigin::around(WorldPos::new(5_000_000.25, 64.0, -3_000_000.75));
+    /// assert_eq!(origin.block(), BlockPos::new(4_999_168, 0, -3_000_320));
+    /// ```
+    pub fn around(position: WorldPos) -> RenderOrigin {
+        let block = position.block();
+        let snap = |v: i32| v.div_euclid(ORIGIN_CELL_SIZE) * ORIGIN_CELL_SIZE;
+        return RenderOrigin { origin: BlockPos::new(snap(block.x), snap(block.y), snap(block.z)) };
+    }
+
+    pub fn block(&self) -> BlockPos {
+        return self.origin;
+    }
+
+    /// Convert a world position into a position relative to this origin, in 32-bit floats.
+    /// Subtraction is done in 64 bits, so no precision is lost before the conversion.
+    /// ```
+    /// # use shared::engine::math::precision::RenderOrigin;
+    /// # use shared::engine::math::coords::WorldPos;
+    /// let far = WorldPos::new(10_000_000.125, 0.0, 0.0);
+    /// // Directly converting loses the fraction entirely.
+    /// assert_eq!(far.x as f32, 10_000_000.0);
+    /// let origin = RenderOrigin::around(far);
+    /// let relative = origin.to_render(far);
+    /// assert_eq!(relative.x.fract(), 0.125);
+    /// ```
+    pub fn to_render(&self, position: WorldPos) -> Vec3 {
+        let relative = position - self.origin.corner();
+        return Vec3::new(relative.x as f32, relative.y as f32, relative.z as f32);
+    }
+
+    /// Convert a block's minimum corner into a position relative to this origin.
+    /// Exact as long as the block is within 16 million blocks of the origin.
+    pub fn block_to_render(&self, block: BlockPos) -> Vec3 {
+        let relative = block - self.origin;
+        return Vec3::new(relative.x as f32, relative.y as f32, relative.z as f32);
+    }
+
+    /// Convert a position relative to this origin back into a world position.
+    /// ```
+    /// # use shared::engine::math::precision::RenderOrigin;
+    /// # use shared::engine::math::coords::WorldPos;
+    /// let position = WorldPos::new(-7_654_321.5, 12.0, 123.25);
+    /// let origin = RenderOrigin::around(position);
+    /// assert_eq!(origin.to_world(origin.to_render(position)), position);
+    /// ```
+    pub fn to_world(&self, render: Vec3) -> WorldPos {
+        return self.origin.corner() + WorldPos::new(render.x as f64, render.y as f64, render.z as f64);
+    }
+
+    /// Move the origin if the camera has left its cell. Returns the block offset everything relative to the
+    /// old origin must be shifted by (subtracted), or None if no rebase was needed.
+    /// Cached render data such as chunk mesh transforms must be shifted when this returns Some.
+    /// ```
+    /// # use shared::engine::math::precision::{RenderOrigin, ORIGIN_CELL_SIZE};
+    /// # use shared::engine::math::coords::{BlockPos, WorldPos};
+    /// let mut origin = RenderOrigin::around(WorldPos::new(10.0, 10.0, 10.0));
+    /// assert_eq!(origin.rebase(WorldPos::new(500.0, 10.0, 10.0)), None);
+    /// let shift = origin.rebase(WorldPos::new(1500.0, 10.0, 10.0));
+    /// assert_eq!(shift, Some(BlockPos::new(ORIGIN_CELL_SIZE, 0, 0)));
+    /// ```
+    pub fn rebase(&mut self, camera: WorldPos) -> Option<BlockPos> {
+        let new_origin = RenderOrigin::around(camera);
+        if new_origin == *self {
+            return None;
+        }
+        let shift = new_origin.origin - self.origin;
+        *self = new_origin;
+        return Some(shift);
+    }
+}
@@ -0,0 +1,147 @@
+use std::ops::{Mul, MulAssign};
+
+use serde::{Serialize, Deserialize};
+
+use super::vector::Vec3;
+
+/// Rotation stored as a unit quaternion, 16 byte aligned. The vector part is (x, y, z) and the scalar part is w.
+/// ```
+/// # use shared::engine::math::quat::Quat;
+/// assert_eq!(std::mem::size_of::<Quat>(), 16);
+/// assert_eq!(std::mem::align_of::<Quat>(), 16);
+/// ```
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat::from_xyzw(0.0, 0.0, 0.0, 1.0);
+
+    /// Construct from raw components. The caller is responsible for the result being normalized.
+    pub const fn from_xyzw(x: f32, y: f32, z: f32, w: f32) -> Quat {
+        return Quat { x, y, z, w };
+    }
+
+    /// Rotation of angle radians counter-clockwise around a normalized axis.
+    /// ```
+    /// # use shared::engine::math::quat::Quat;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let rotation = Quat::from_axis_angle(Vec3::Y, std::f32::consts::FRAC_PI_2);
+    /// assert!((rotation * Vec3::X).approx_eq(-Vec3::Z, 1e-6));
+    /// ```
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let (sin, cos) = (angle * 0.5).sin_cos();
+        let v = axis * sin;
+        return Quat::from_xyzw(v.x, v.y, v.z, cos);
+    }
+
+    /// Camera style rotation. Yaw around +Y, then pitch around the local +X, in radians.
+    pub fn from_yaw_pitch(yaw: f32, pitch: f32) -> Quat {
+        return Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch);
+    }
+
+    pub fn dot(self, rhs: Quat) -> f32 {
+        return self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w;
+    }
+
+    pub fn length(self) -> f32 {
+        return self.dot(self).sqrt();
+    }
+
+    /// Re-normalize, correcting drift from accumulated multiplications.
+    pub fn normalize(self) -> Quat {
+        let length = self.length();
+        debug_assert!(length > 0.0, "Cannot normalize a zero length quaternion");
+        return Quat::from_xyzw(self.x / length, self.y / length, self.z / length, self.w / length);
+    }
+
+    /// The inverse rotation. Only valid for unit quaternions.
+    /// ```
+    /// # use shared::engine::math::quat::Quat;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let rotation = Quat::from_axis_angle(Vec3::Z, 1.0);
+    /// let v = Vec3::new(1.0, 2.0, 3.0);
+    /// assert!((rotation.conjugate() * (rotation * v)).approx_eq(v, 1e-5));
+    /// ```
+    pub fn conjugate(self) -> Quat {
+        return Quat::from_xyzw(-self.x, -self.y, -self.z, self.w);
+    }
+
+    /// Spherical interpolation along the shortest path, where t of 0 is self and 1 is rhs.
+    /// ```
+    /// # use shared::engine::math::quat::Quat;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let end = Quat::from_axis_angle(Vec3::Y, 1.0);
+    /// let half = Quat::IDENTITY.slerp(end, 0.5);
+    /// assert!(half.approx_eq(Quat::from_axis_angle(Vec3::Y, 0.5), 1e-6));
+    /// ```
+    pub fn slerp(self, rhs: Quat, t: f32) -> Quat {
+        let mut cos = self.dot(rhs);
+        let mut end = rhs;
+        if cos < 0.0 {
+            cos = -cos;
+            end = Quat::from_xyzw(-rhs.x, -rhs.y, -rhs.z, -rhs.w);
+        }
+        // Nearly parallel, fall back to a normalized lerp to avoid dividing by a tiny sine.
+        let (a, b) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        return Quat::from_xyzw(
+            self.x * a + end.x * b,
+            self.y * a + end.y * b,
+            self.z * a + end.z * b,
+            self.w * a + end.w * b
+        ).normalize();
+    }
+
+    pub fn approx_eq(self, rhs: Quat, epsilon: f32) -> bool {
+        return (self.x - rhs.x).abs() <= epsilon
+            && (self.y - rhs.y).abs() <= epsilon
+            && (self.z - rhs.z).abs() <= epsilon
+            && (self.w - rhs.w).abs() <= epsilon;
+    }
+}
+
+impl Default for Quat {
+    fn default() -> Quat {
+        return Quat::IDENTITY;
+    }
+}
+
+/// Combine rotations. The result applies rhs first, then self.
+impl Mul for Quat {
+    type Output = Quat;
+    fn mul(self, rhs: Quat) -> Quat {
+        return Quat::from_xyzw(
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z
+        );
+    }
+}
+
+impl MulAssign for Quat {
+    fn mul_assign(&mut self, rhs: Quat) {
+        *self = *self * rhs;
+    }
+}
+
+/// Rotate a vector.
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        let q = Vec3::new(self.x, self.y, self.z);
+        let t = q.cross(rhs) * 2.0;
+        return rhs + t * self.w + q.cross(t);
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Serialize, Deserialize};
+
+use super::{vector::Vec3, aabb::Aabb};
+
+/// Half line starting at origin and extending along direction.
+/// The direction does not need to be normalized, but distances returned by intersection tests
+/// are in multiples of its length.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3
+}
+
+/// Where a ray or swept box first touched an Aabb.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    /// Distance along the ray, or fraction of the sweep, at which the hit occurred.
+    pub distance: f32,
+    /// Normal of the face that was hit. Zero if the ray started inside the box.
+    pub normal: Vec3
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+        return Ray { origin, direction };
+    }
+
+    /// The point at distance t along the ray.
+    /// ```
+    /// # use shared::engine::math::ray::Ray;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let ray = Ray::new(Vec3::ZERO, Vec3::new(0.0, 2.0, 0.0));
+    /// assert_eq!(ray.at(1.5), Vec3::new(0.0, 3.0, 0.0));
+    /// ```
+    pub fn at(&self, t: f32) -> Vec3 {
+        return self.origin + self.direction * t;
+    }
+
+    /// Slab test against a box, returning the first hit within max_distance.
+    /// A ray starting inside the box hits at distance 0.
+    /// ```
+    /// # use shared::engine::math::ray::Ray;
+    /// # use shared::engine::math::aabb::Aabb;
+    /// # use shared::engine::math::vector::Vec3;
+    /// let block = Aabb::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(3.0, 1.0, 1.0));
+    /// let ray = Ray::new(Vec3::new(0.0, 0.5, 0.5), Vec3::X);
+    /// let hit = ray.intersect_aabb(&block, 10.0).unwrap();
+    /// assert_eq!(hit.distance, 2.0);
+    /// assert_eq!(hit.normal, -Vec3::X);
+    /// // Too short to reach it.
+    /// assert!(ray.intersect_aabb(&block, 1.0).is_none());
+    /// // Pointing away.
+    /// assert!(Ray::new(Vec3::new(0.0, 0.5, 0.5), -Vec3::X).intersect_aabb(&block, 10.0).is_none());
+    /// ```
+    pub fn intersect_aabb(&self, aabb: &Aabb, max_distance: f32) -> Option<RayHit> {
+        let mut t_enter = f32::NEG_INFINITY;
+        let mut t_exit = f32::INFINITY;
+        let mut normal = Vec3::ZERO;
+        for axis in 0..3 {
+            let origin = self.origin[axis];
+            let direction = self.direction[axis];
+            let (min, max) = (aabb.min[axis], aabb.max[axis]);
+            if direction == 0.0 {
+                // Parallel to this slab, so it either never enters it or is always within it.
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let inverse = 1.0 / direction;
+            let (mut near, mut far) = ((min - origin) * inverse, (max - origin) * inverse);
+            let mut sign = -1.0;
+            if near > far {
+                std::mem::swap(&mut near, &mut far);
+                sign = 1.0;
+            }
+            if near > t_enter {
+                t_enter = near;
+                normal = Vec3::ZERO;
+                match axis {
+                    0 => normal.x = sign,
+                    1 => normal.y = sign,
+                    _ => normal.z = sign
+                }
+            }
+            t_exit = t_exit.min(far);
+            if t_enter > t_exit {
+                return None;
+            }
+        }
+        if t_exit < 0.0 || t_enter > max_distance {
+            return None;
+        }
+        if t_enter < 0.0 {
+            return Some(RayHit { distance: 0.0, normal: Vec3::ZERO });
+        }
+        return Some(RayHit { distance: t_enter, normal });
+    }
+}
@@ -0,0 +1,160 @@
+use std::ops::Range;
+
+use super::coords::{ChunkPos, BlockPos};
+
+/// SplitMix64 step, used to turn seeds and coordinates into well mixed state.
+fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    return z ^ (z >> 31);
+}
+
+/// Mix a value into a hash. Order dependent, so (a, b) and (b, a) produce different results.
+pub(crate) fn mix(hash: u64, value: u64) -> u64 {
+    let mut state = hash ^ value.wrapping_mul(0xff51afd7ed558ccd);
+    return split_mix(&mut state);
+}
+
+/// FNV-1a of a string. Unlike std's DefaultHasher, this is guaranteed to never change between Rust versions or platforms.
+pub(crate) fn hash_str(s: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in s.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    return hash;
+}
+
+/// Deterministic xoshiro256** generator for world generation.
+/// Generators are derived from the world seed and what is being generated, never shared between jobs,
+/// so the same seed produces identical worlds on every platform regardless of the order chunks generate in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorldRng {
+    state: [u64; 4]
+}
+
+impl WorldRng {
+    /// ```
+    /// # use shared::engine::math::rng::WorldRng;
+    /// let mut a = WorldRng::new(1234);
+    /// let mut b = WorldRng::new(1234);
+    /// assert_eq!(a.next_u64(), b.next_u64());
+    /// assert_ne!(WorldRng::new(1234).next_u64(), WorldRng::new(1235).next_u64());
+    /// // Output is part of the world format. Changing it changes every generated world.
+    /// assert_eq!(WorldRng::new(0).next_u64(), 11091344671253066420);
+    /// ```
+    pub fn new(seed: u64) -> WorldRng {
+        let mut sm = seed;
+        return WorldRng { state: [split_mix(&mut sm), split_mix(&mut sm), split_mix(&mut sm), split_mix(&mut sm)] };
+    }
+
+    /// Generator for terrain in a chunk. Independent of every other chunk's generator.
+    /// ```
+    /// # use shared::engine::math::rng::WorldRng;
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let chunk = ChunkPos::new(3, -1, 7);
+    /// assert_eq!(WorldRng::for_chunk(42, chunk).next_u64(), WorldRng::for_chunk(42, chunk).next_u64());
+    /// assert_ne!(WorldRng::for_chunk(42, chunk).next_u64(), WorldRng::for_chunk(42, ChunkPos::new(7, -1, 3)).next_u64());
+    /// assert_eq!(WorldRng::for_chunk(12345, ChunkPos::new(1, 2, 3)).next_u64(), 15850937938070014382);
+    /// ```
+    pub fn for_chunk(seed: u64, chunk: ChunkPos) -> WorldRng {
+        return WorldRng::new(mix(mix(mix(seed, chunk.x as u64), chunk.y as u64), chunk.z as u64));
+    }
+
+    /// Generator for placing a named feature (such as "cube:oak_tree") in a chunk.
+    /// Adding or removing one feature does not change where any other feature is placed.
+    /// ```
+    /// # use shared::engine::math::rng::WorldRng;
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let chunk = ChunkPos::new(0, 0, 0);
+    /// assert_ne!(
+    ///     WorldRng::for_feature(42, chunk, "cube:oak_tree").next_u64(),
+    ///     WorldRng::for_feature(42, chunk, "cube:iron_ore").next_u64());
+    /// ```
+    pub fn for_feature(seed: u64, chunk: ChunkPos, feature: &str) -> WorldRng {
+        let chunk_seed = mix(mix(mix(seed, chunk.x as u64), chunk.y as u64), chunk.z as u64);
+        return WorldRng::new(mix(chunk_seed, hash_str(feature)));
+    }
+
+    /// Generator for a single block, such as random ticks or per-block texture variation.
+    pub fn for_block(seed: u64, block: BlockPos) -> WorldRng {
+        return WorldRng::new(mix(mix(mix(mix(seed, 0x626c6f636b), block.x as u64), block.y as u64), block.z as u64));
+    }
+
+    /// Create an independent child generator, advancing this one.
+    /// Used to hand generators to sub-tasks without them consuming from each other.
+    pub fn split(&mut self) -> WorldRng {
+        return WorldRng::new(self.next_u64());
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.state[1] << 17;
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= t;
+        self.state[3] = self.state[3].rotate_left(45);
+        return result;
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        return (self.next_u64() >> 32) as u32;
+    }
+
+    /// Uniform in the range 0.0 to 1.0, excluding 1.0.
+    pub fn next_f64(&mut self) -> f64 {
+        return (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64);
+    }
+
+    /// Uniform in the range 0.0 to 1.0, excluding 1.0.
+    pub fn next_f32(&mut self) -> f32 {
+        return (self.next_u64() >> 40) as f32 * (1.0 / (1u32 << 24) as f32);
+    }
+
+    /// Uniform integer within a range. Will panic if the range is empty.
+    /// ```
+    /// # use shared::engine::math::rng::WorldRng;
+    /// let mut rng = WorldRng::new(7);
+    /// for _ in 0..1000 {
+    ///     let v = rng.range_i32(-3..5);
+    ///     assert!(v >= -3 && v < 5);
+    /// }
+    /// ```
+    pub fn range_i32(&mut self, range: Range<i32>) -> i32 {
+        assert!(range.start < range.end, "Cannot generate a number in an empty range");
+        let span = (range.end as i64 - range.start as i64) as u64;
+        // Multiply-shift instead of modulo, avoiding most of the modulo bias without a retry loop.
+        let offset = ((self.next_u32() as u64 * span) >> 32) as i64;
+        return (range.start as i64 + offset) as i32;
+    }
+
+    /// Uniform float within a range.
+    pub fn range_f32(&mut self, range: Range<f32>) -> f32 {
+        return range.start + (range.end - range.start) * self.next_f32();
+    }
+
+    /// True with the given probability, from 0.0 to 1.0.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        return self.next_f64() < probability;
+    }
+
+    /// Pick a random element. Returns None if the slice is empty.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        return items.get(self.range_i32(0..items.len() as i32) as usize);
+    }
+
+    /// Fisher-Yates shuffle.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.range_i32(0..(i + 1) as i32) as usize;
+            items.swap(i, j);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::ops::{Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Div, DivAssign, Neg, Index};
+
+use serde::{Serialize, Deserialize};
+
+/// 3 component vector of f32, padded to 16 bytes so it can be loaded into a SIMD register
+/// and matches the std140 layout used by GPU uniform buffers.
+/// ```
+/// # use shared::engine::math::vector::Vec3;
+/// assert_eq!(std::mem::size_of::<Vec3>(), 16);
+/// assert_eq!(std::mem::align_of::<Vec3>(), 16);
+/// ```
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+
+/// 4 component vector of f32, 16 byte aligned.
+/// ```
+/// # use shared::engine::math::vector::Vec4;
+/// assert_eq!(std::mem::size_of::<Vec4>(), 16);
+/// assert_eq!(std::mem::align_of::<Vec4>(), 16);
+/// ```
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32
+}
+
+impl Vec3 {
+    pub const ZERO: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+    pub const ONE: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+    pub const X: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+    pub const Y: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    pub const Z: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+    /// The world's up direction.
+    pub const UP: Vec3 = Vec3::Y;
+
+    pub const fn new(x: f32, y: f32, z: f32) -> Vec3 {
+        return Vec3 { x, y, z };
+    }
+
+    pub const fn splat(v: f32) -> Vec3 {
+        return Vec3::new(v, v, v);
+    }
+
+    /// ```
+    /// # use shared::engine::math::vector::Vec3;
+    /// assert_eq!(Vec3::new(1.0, 2.0, 3.0).dot(Vec3::new(4.0, 5.0, 6.0)), 32.0);
+    /// ```
+    pub fn dot(self, rhs: Vec3) -> f32 {
+        return self.x * rhs.x + self.y * rhs.y + self.z * rhs.z;
+    }
+
+    /// Right handed cross product.
+    /// ```
+    /// # use shared::engine::math::vector::Vec3;
+    /// assert_eq!(Vec3::X.cross(Vec3::Y), Vec3::Z);
+    /// ```
+    pub fn cross(self, rhs: Vec3) -> Vec3 {
+        return Vec3::new(
+            self.y * rhs.z - self.z * rhs.y,
+            self.z * rhs.x - self.x * rhs.z,
+            self.x * rhs.y - self.y * rhs.x
+        );
+    }
+
+    pub fn length(self) -> f32 {
+        return self.length_squared().sqrt();
+    }
+
+    pub fn length_squared(self) -> f32 {
+        return self.dot(self);
+    }
+
+    pub fn distance(self, rhs: Vec3) -> f32 {
+        return (self - rhs).length();
+    }
+
+    /// Get a vector with the same direction and a length of 1.
+    /// Will panic in debug mode if the vector has zero length.
+    /// ```
+    /// # use shared::engine::math::vector::Vec3;
+    /// assert_eq!(Vec3::new(0.0, 3.0, 4.0).normalize(), Vec3::new(0.0, 0.6, 0.8));
+    /// ```
+    pub fn normalize(self) -> Vec3 {
+        let length = self.length();
+        debug_assert!(length > 0.0, "Cannot normalize a zero length vector");
+        return self / length;
+    }
+
+    /// Same as normalize(), but returns zero for vectors too small to normalize.
+    /// ```
+    /// # use shared::engine::math::vector::Vec3;
+    /// assert_eq!(Vec3::ZERO.normalize_or_zero(), Vec3::ZERO);
+    /// ```
+    pub fn normalize_or_zero(self) -> Vec3 {
+        let length = self.length();
+        if length <= f32::EPSILON {
+            return Vec3::ZERO;
+        }
+        return self / length;
+    }
+
+    /// Linear interpolation, where t of 0 is self and 1 is rhs.
+    pub fn lerp(self, rhs: Vec3, t: f32) -> Vec3 {
+        return self + (rhs - self) * t;
+    }
+
+    pub fn min(self, rhs: Vec3) -> Vec3 {
+        return Vec3::new(self.x.min(rhs.x), self.y.min(rhs.y), self.z.min(rhs.z));
+    }
+
+    pub fn max(self, rhs: Vec3) -> Vec3 {
+        return Vec3::new(self.x.max(rhs.x), self.y.max(rhs.y), self.z.max(rhs.z));
+    }
+
+    pub fn abs(self) -> Vec3 {
+        return Vec3::new(self.x.abs(), self.y.abs(), self.z.abs());
+    }
+
+    /// Multiply each component by the matching component of rhs.
+    pub fn mul_elements(self, rhs: Vec3) -> Vec3 {
+        return Vec3::new(self.x * rhs.x, self.y * rhs.y, self.z * rhs.z);
+    }
+
+    pub fn min_element(self) -> f32 {
+        return self.x.min(self.y).min(self.z);
+    }
+
+    pub fn max_element(self) -> f32 {
+        return self.x.max(self.y).max(self.z);
+    }
+
+    /// Extend into a Vec4 with the given w.
+    pub fn extend(self, w: f32) -> Vec4 {
+        return Vec4::new(self.x, self.y, self.z, w);
+    }
+
+    pub fn to_array(self) -> [f32; 3] {
+        return [self.x, self.y, self.z];
+    }
+
+    /// Check if every component is within epsilon of rhs.
+    pub fn approx_eq(self, rhs: Vec3, epsilon: f32) -> bool {
+        let diff = (self - rhs).abs();
+        return diff.x <= epsilon && diff.y <= epsilon && diff.z <= epsilon;
+    }
+}
+
+impl Vec4 {
+    pub const ZERO: Vec4 = Vec4::new(0.0, 0.0, 0.0, 0.0);
+    pub const ONE: Vec4 = Vec4::new(1.0, 1.0, 1.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Vec4 {
+        return Vec4 { x, y, z, w };
+    }
+
+    pub const fn splat(v: f32) -> Vec4 {
+        return Vec4::new(v, v, v, v);
+    }
+
+    pub fn dot(self, rhs: Vec4) -> f32 {
+        return self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w;
+    }
+
+    pub fn length(self) -> f32 {
+        return self.dot(self).sqrt();
+    }
+
+    /// Will panic in debug mode if the vector has zero length.
+    pub fn normalize(self) -> Vec4 {
+        let length = self.length();
+        debug_assert!(length > 0.0, "Cannot normalize a zero length vector");
+        return self / length;
+    }
+
+    pub fn lerp(self, rhs: Vec4, t: f32) -> Vec4 {
+        return self + (rhs - self) * t;
+    }
+
+    /// Drop the w component.
+    pub fn truncate(self) -> Vec3 {
+        return Vec3::new(self.x, self.y, self.z);
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        return [self.x, self.y, self.z, self.w];
+    }
+
+    pub fn approx_eq(self, rhs: Vec4, epsilon: f32) -> bool {
+        return (self.x - rhs.x).abs() <= epsilon
+            && (self.y - rhs.y).abs() <= epsilon
+            && (self.z - rhs.z).abs() <= epsilon
+            && (self.w - rhs.w).abs() <= epsilon;
+    }
+}
+
+macro_rules! impl_vector_ops {
+    ($t:ident, $($field:ident),+) => {
+        impl Add for $t {
+            type Output = $t;
+            fn add(self, rhs: $t) -> $t {
+                return $t { $($field: self.$field + rhs.$field),+ };
+            }
+        }
+
+        impl Sub for $t {
+            type Output = $t;
+            fn sub(self, rhs: $t) -> $t {
+                return $t { $($field: self.$field - rhs.$field),+ };
+            }
+        }
+
+        impl Mul<f32> for $t {
+            type Output = $t;
+            fn mul(self, rhs: f32) -> $t {
+                return $t { $($field: self.$field * rhs),+ };
+            }
+        }
+
+        impl Mul<$t> for f32 {
+            type Output = $t;
+            fn mul(self, rhs: $t) -> $t {
+                return rhs * self;
+            }
+        }
+
+        impl Div<f32> for $t {
+            type Output = $t;
+            fn div(self, rhs: f32) -> $t {
+                return $t { $($field: self.$field / rhs),+ };
+            }
+        }
+
+        impl Neg for $t {
+            type Output = $t;
+            fn neg(self) -> $t {
+                return $t { $($field: -self.$field),+ };
+            }
+        }
+
+        impl AddAssign for $t {
+            fn add_assign(&mut self, rhs: $t) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl SubAssign for $t {
+            fn sub_assign(&mut self, rhs: $t) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl MulAssign<f32> for $t {
+            fn mul_assign(&mut self, rhs: f32) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl DivAssign<f32> for $t {
+            fn div_assign(&mut self, rhs: f32) {
+                *self = *self / rhs;
+            }
+        }
+    };
+}
+
+impl_vector_ops!(Vec3, x, y, z);
+impl_vector_ops!(Vec4, x, y, z, w);
+
+impl Index<usize> for Vec3 {
+    type Output = f32;
+    fn index(&self, index: usize) -> &f32 {
+        return match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 index {} out of range", index)
+        };
+    }
+}
+
+impl Index<usize> for Vec4 {
+    type Output = f32;
+    fn index(&self, index: usize) -> &f32 {
+        return match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            3 => &self.w,
+            _ => panic!("Vec4 index {} out of range", index)
+        };
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from(v: [f32; 3]) -> Vec3 {
+        return Vec3::new(v[0], v[1], v[2]);
+    }
+}
+
+impl From<[f32; 4]> for Vec4 {
+    fn from(v: [f32; 4]) -> Vec4 {
+        return Vec4::new(v[0], v[1], v[2], v[3]);
+    }
+}
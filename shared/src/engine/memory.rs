@@ -0,0 +1,201 @@
+use std::{collections::HashMap, fmt, sync::atomic::{AtomicU64, Ordering}};
+
+use crate::engine::{config::MemoryConfig, math::coords::ChunkPos};
+
+pub const MEBIBYTE: u64 = 1024 * 1024;
+/// Default budget of loaded chunks' blocks, in MiB.
+pub const DEFAULT_CHUNK_BUDGET: u64 = 2048;
+/// Default budget of chunk meshes uploaded to the GPU, in MiB.
+pub const DEFAULT_MESH_BUDGET: u64 = 1024;
+/// Default budget of entities, in MiB.
+pub const DEFAULT_ENTITY_BUDGET: u64 = 256;
+/// Rough bytes an entity takes with its components and index entries, as entities don't know their own size.
+pub const ENTITY_MEMORY_ESTIMATE: u64 = 512;
+
+/// What memory is used for, each with its own budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MemoryCategory {
+    /// Blocks and light of loaded chunks.
+    Chunks,
+    /// Vertices and indices of chunk meshes.
+    Meshes,
+    Entities
+}
+
+impl MemoryCategory {
+    pub const ALL: [MemoryCategory; 3] = [MemoryCategory::Chunks, MemoryCategory::Meshes, MemoryCategory::Entities];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            MemoryCategory::Chunks => "chunks",
+            MemoryCategory::Meshes => "meshes",
+            MemoryCategory::Entities => "entities"
+        };
+    }
+
+    fn index(&self) -> usize {
+        return *self as usize;
+    }
+}
+
+impl fmt::Display for MemoryCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str(self.name());
+    }
+}
+
+/// Bytes used by one category, and its budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryUsage {
+    pub category: MemoryCategory,
+    pub used: u64,
+    /// None when the category may grow without limit.
+    pub budget: Option<u64>
+}
+
+impl fmt::Display for MemoryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let used = self.used as f64 / MEBIBYTE as f64;
+        return match self.budget {
+            Some(budget) => write!(f, "{} {:.1}/{:.0} MiB", self.category, used, budget as f64 / MEBIBYTE as f64),
+            None => write!(f, "{} {:.1} MiB", self.category, used)
+        };
+    }
+}
+
+/// Bytes allocated for each category of memory, registered by whatever owns them, and the budget each may use.
+/// Shared between threads, so owners register allocations wherever they make them. Going over budget doesn't
+/// stop anything being allocated; it's up to owners to check over_budget() and evict what they can.
+/// ```
+/// # use shared::engine::memory::{MemoryCategory, MemoryTracker};
+/// let memory = MemoryTracker::new().with_budget(MemoryCategory::Chunks, Some(1000));
+/// memory.allocate(MemoryCategory::Chunks, 600);
+/// memory.allocate(MemoryCategory::Meshes, 5000);
+/// assert_eq!(memory.over_budget(MemoryCategory::Chunks), 0);
+/// memory.allocate(MemoryCategory::Chunks, 600);
+/// assert_eq!(memory.over_budget(MemoryCategory::Chunks), 200);
+/// // Meshes have no budget to go over.
+/// assert_eq!(memory.over_budget(MemoryCategory::Meshes), 0);
+///
+/// memory.free(MemoryCategory::Chunks, 600);
+/// assert_eq!((memory.used(MemoryCategory::Chunks), memory.total()), (600, 5600));
+/// // Freeing more than was allocated is a bug somewhere else, but doesn't wrap around.
+/// memory.free(MemoryCategory::Chunks, 10_000);
+/// assert_eq!(memory.used(MemoryCategory::Chunks), 0);
+/// ```
+pub struct MemoryTracker {
+    used: [AtomicU64; 3],
+    /// Budget of each category, with u64::MAX for none.
+    budgets: [AtomicU64; 3]
+}
+
+impl MemoryTracker {
+    /// A tracker with no budgets.
+    pub fn new() -> MemoryTracker {
+        return MemoryTracker { used: Default::default(), budgets: [u64::MAX, u64::MAX, u64::MAX].map(AtomicU64::new) };
+    }
+
+    /// A tracker with the budgets of a config's memory section. Meshes are left without one, as the client
+    /// sets their budget from its video settings.
+    pub fn from_config(config: &MemoryConfig) -> MemoryTracker {
+        return MemoryTracker::new()
+            .with_budget(MemoryCategory::Chunks, config.chunk_budget())
+            .with_budget(MemoryCategory::Entities, config.entity_budget());
+    }
+
+    /// Budget in bytes of a category, or None for no limit.
+    pub fn with_budget(self, category: MemoryCategory, bytes: Option<u64>) -> MemoryTracker {
+        self.set_budget(category, bytes);
+        return self;
+    }
+
+    pub fn set_budget(&self, category: MemoryCategory, bytes: Option<u64>) {
+        self.budgets[category.index()].store(bytes.unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+
+    pub fn budget(&self, category: MemoryCategory) -> Option<u64> {
+        let budget = self.budgets[category.index()].load(Ordering::Relaxed);
+        return (budget != u64::MAX).then_some(budget);
+    }
+
+    pub fn allocate(&self, category: MemoryCategory, bytes: u64) {
+        self.used[category.index()].fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn free(&self, category: MemoryCategory, bytes: u64) {
+        let _ = self.used[category.index()].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| Some(used.saturating_sub(bytes)));
+    }
+
+    /// Replace what a category uses, for owners that measure their memory as a whole rather than each allocation.
+    pub fn set(&self, category: MemoryCategory, bytes: u64) {
+        self.used[category.index()].store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn used(&self, category: MemoryCategory) -> u64 {
+        return self.used[category.index()].load(Ordering::Relaxed);
+    }
+
+    /// Bytes used by every category together.
+    pub fn total(&self) -> u64 {
+        return MemoryCategory::ALL.iter().map(|category| self.used(*category)).sum();
+    }
+
+    /// Bytes a category would have to free to be back within its budget, or 0 when it's within it.
+    pub fn over_budget(&self, category: MemoryCategory) -> u64 {
+        return self.budget(category).map_or(0, |budget| self.used(category).saturating_sub(budget));
+    }
+
+    /// What each category uses, for the debug overlay and logs.
+    pub fn usage(&self) -> Vec<MemoryUsage> {
+        return MemoryCategory::ALL.iter().map(|category| {
+            return MemoryUsage { category: *category, used: self.used(*category), budget: self.budget(*category) };
+        }).collect();
+    }
+}
+
+impl Default for MemoryTracker {
+    fn default() -> Self {
+        return MemoryTracker::new();
+    }
+}
+
+/// Order chunks to evict in: the furthest from every center first, and of those as far, the one used longest
+/// ago. Centers are where players or the camera are; with none, chunks are ordered by last use alone.
+/// ```
+/// # use shared::engine::memory::eviction_order;
+/// # use shared::engine::math::coords::ChunkPos;
+/// let (near, far, old_far) = (ChunkPos::new(1, 0, 0), ChunkPos::new(9, 0, 0), ChunkPos::new(0, 0, -9));
+/// let order = eviction_order(&[(near, 1), (far, 20), (old_far, 5)], &[ChunkPos::ORIGIN]);
+/// assert_eq!(order, vec![old_far, far, near]);
+/// assert_eq!(eviction_order(&[(near, 1), (far, 20)], &[]), vec![near, far]);
+/// ```
+pub fn eviction_order(candidates: &[(ChunkPos, u64)], centers: &[ChunkPos]) -> Vec<ChunkPos> {
+    let mut order: Vec<(i64, u64, ChunkPos)> = candidates.iter().map(|(pos, last_used)| {
+        let distance = centers.iter().map(|center| pos.distance_squared(*center)).min().unwrap_or(0);
+        return (distance, *last_used, *pos);
+    }).collect();
+    order.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then((a.2.x, a.2.y, a.2.z).cmp(&(b.2.x, b.2.y, b.2.z))));
+    return order.into_iter().map(|(_, _, pos)| pos).collect();
+}
+
+/// Chunks to evict, in eviction_order(), until together they free at least the bytes asked for. Fewer if even
+/// every candidate wouldn't free enough.
+/// ```
+/// # use shared::engine::memory::choose_evictions;
+/// # use shared::engine::math::coords::ChunkPos;
+/// # use std::collections::HashMap;
+/// let candidates = [(ChunkPos::new(1, 0, 0), 0), (ChunkPos::new(2, 0, 0), 0), (ChunkPos::new(3, 0, 0), 0)];
+/// let sizes = HashMap::from(candidates.map(|(pos, _)| (pos, 100)));
+/// assert_eq!(choose_evictions(&candidates, &[ChunkPos::ORIGIN], &sizes, 150), vec![ChunkPos::new(3, 0, 0), ChunkPos::new(2, 0, 0)]);
+/// assert!(choose_evictions(&candidates, &[ChunkPos::ORIGIN], &sizes, 0).is_empty());
+/// ```
+pub fn choose_evictions(candidates: &[(ChunkPos, u64)], centers: &[ChunkPos], sizes: &HashMap<ChunkPos, u64>, bytes: u64) -> Vec<ChunkPos> {
+    let mut freed = 0;
+    return eviction_order(candidates, centers).into_iter().take_while(|pos| {
+        if freed >= bytes {
+            return false;
+        }
+        freed += sizes.get(pos).copied().unwrap_or(0);
+        return true;
+    }).collect();
+}
\ No newline at end of file
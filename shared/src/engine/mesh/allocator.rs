@@ -0,0 +1,98 @@
+use std::{collections::BTreeMap, ops::Range};
+
+/// Hands out ranges of a fixed size pool, such as the vertices of one big GPU buffer shared by every chunk mesh,
+/// so meshes don't each need their own buffer. First fit, with freed ranges merged into their free neighbors.
+/// ```
+/// # use shared::engine::mesh::allocator::RangeAllocator;
+/// let mut pool = RangeAllocator::new(100);
+/// let a = pool.alloc(40).unwrap();
+/// let b = pool.alloc(40).unwrap();
+/// assert_eq!((a.clone(), b.clone()), (0..40, 40..80));
+/// assert!(pool.alloc(30).is_none());
+///
+/// // Freeing both merges them back into one range.
+/// pool.free(a);
+/// pool.free(b);
+/// assert_eq!(pool.largest_free(), 100);
+///
+/// // Growing adds space at the end.
+/// pool.alloc(100).unwrap();
+/// pool.grow(150);
+/// assert_eq!(pool.alloc(50), Some(100..150));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RangeAllocator {
+    capacity: u64,
+    /// Length of every free range, by its start.
+    free: BTreeMap<u64, u64>,
+    used: u64
+}
+
+impl RangeAllocator {
+    pub fn new(capacity: u64) -> RangeAllocator {
+        let mut free = BTreeMap::new();
+        if capacity > 0 {
+            free.insert(0, capacity);
+        }
+        return RangeAllocator { capacity, free, used: 0 };
+    }
+
+    pub fn capacity(&self) -> u64 {
+        return self.capacity;
+    }
+
+    /// Total length of every allocated range.
+    pub fn used(&self) -> u64 {
+        return self.used;
+    }
+
+    pub fn largest_free(&self) -> u64 {
+        return self.free.values().copied().max().unwrap_or(0);
+    }
+
+    /// The first free range long enough, or None if the pool needs to grow. Empty ranges are never allocated.
+    pub fn alloc(&mut self, length: u64) -> Option<Range<u64>> {
+        if length == 0 {
+            return None;
+        }
+        let (start, free_length) = self.free.iter().map(|(start, free)| (*start, *free)).find(|(_, free)| *free >= length)?;
+        self.free.remove(&start);
+        if free_length > length {
+            self.free.insert(start + length, free_length - length);
+        }
+        self.used += length;
+        return Some(start..start + length);
+    }
+
+    /// Return a range from alloc() to the pool. Will panic in debug mode if part of it is already free.
+    pub fn free(&mut self, range: Range<u64>) {
+        let (mut start, mut length) = (range.start, range.end - range.start);
+        debug_assert!(range.end <= self.capacity, "Freed range is outside the pool");
+        debug_assert!(self.free.range(..range.end).next_back().is_none_or(|(free, free_length)| free + free_length <= range.start), "Range freed twice");
+        self.used -= length;
+        if let Some((before, before_length)) = self.free.range(..start).next_back().map(|(start, length)| (*start, *length)) {
+            if before + before_length == start {
+                self.free.remove(&before);
+                start = before;
+                length += before_length;
+            }
+        }
+        if let Some(after_length) = self.free.remove(&(start + length)) {
+            length += after_length;
+        }
+        self.free.insert(start, length);
+    }
+
+    /// Extend the pool to a larger capacity, such as after copying its buffer into a bigger one.
+    pub fn grow(&mut self, capacity: u64) {
+        debug_assert!(capacity >= self.capacity, "Pools can only grow");
+        let added = capacity - self.capacity;
+        let old_capacity = self.capacity;
+        self.capacity = capacity;
+        if added > 0 {
+            // Freed like an allocation, so it merges with any free range at the old end.
+            self.used += added;
+            self.free(old_capacity..capacity);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,366 @@
+use std::sync::Arc;
+
+use crate::engine::{
+    block::{BlockId, AIR},
+    job::{system::JobSystem, future::JobFuture},
+    light::{storage::ChunkLight, MAX_LIGHT},
+    math::{coords::{ChunkPos, LocalPos, CHUNK_SIZE}, direction::{Axis, Direction}},
+    world::{chunk::Chunk, container::SharedChunk, palette::SECTION_SIZE, World}
+};
+
+use super::{texture::UvRect, vertex::{ChunkVertex, MeshData}};
+
+const SIZE: usize = CHUNK_SIZE as usize;
+
+/// Light of faces meshed without light, full sky light so they are never dark.
+const UNLIT: u16 = MAX_LIGHT as u16;
+/// Ambient occlusion of a corner with nothing around it.
+pub const AO_OPEN: u32 = 3;
+/// Corners of a face, as steps along u and v from its middle, in the order quads are made from.
+const CORNERS: [(i32, i32); 4] = [(-1, -1), (1, -1), (1, 1), (-1, 1)];
+
+/// Whether a block hides the faces of blocks next to it.
+pub type OpacityFn = dyn Fn(BlockId) -> bool + Send + Sync;
+/// Whether a block is see-through and blended over what's behind it, such as water, so is meshed apart from opaque blocks.
+pub type TranslucencyFn = dyn Fn(BlockId) -> bool + Send + Sync;
+
+/// The layer of blocks just outside each face of a chunk, in Direction order.
+/// Missing layers, such as next to chunks that aren't loaded, are treated as air, and missing light as full sky light.
+pub struct ChunkBorders {
+    layers: [Option<Box<[BlockId]>>; 6],
+    light: [Option<Box<[u16]>>; 6]
+}
+
+impl ChunkBorders {
+    /// No neighbors, so every outer face of the chunk is visible.
+    pub fn empty() -> ChunkBorders {
+        return ChunkBorders { layers: Default::default(), light: Default::default() };
+    }
+
+    /// Copy the bordering layer of each neighbor, given in Direction order.
+    pub fn from_neighbors(neighbors: [Option<&Chunk>; 6]) -> ChunkBorders {
+        let mut borders = ChunkBorders::empty();
+        for direction in Direction::ALL {
+            let Some(neighbor) = neighbors[direction.index()] else {
+                continue;
+            };
+            // The neighbor's layer touching this chunk is on its opposite side.
+            let axis = direction.axis() as usize;
+            let slice = if direction.is_positive() { 0 } else { SIZE - 1 };
+            let mut layer = vec![AIR; SIZE * SIZE].into_boxed_slice();
+            for v in 0..SIZE {
+                for u in 0..SIZE {
+                    layer[u + v * SIZE] = neighbor.get_block(local_at(axis, slice, u, v));
+                }
+            }
+            borders.layers[direction.index()] = Some(layer);
+        }
+        return borders;
+    }
+
+    /// Copy the bordering layer of each neighbor's light, given in Direction order, used to light faces on the chunk's edges.
+    pub fn with_light(mut self, neighbors: [Option<&ChunkLight>; 6]) -> ChunkBorders {
+        for direction in Direction::ALL {
+            let Some(neighbor) = neighbors[direction.index()] else {
+                continue;
+            };
+            let axis = direction.axis() as usize;
+            let slice = if direction.is_positive() { 0 } else { SIZE - 1 };
+            let mut layer = vec![UNLIT; SIZE * SIZE].into_boxed_slice();
+            for v in 0..SIZE {
+                for u in 0..SIZE {
+                    layer[u + v * SIZE] = neighbor.packed(local_at(axis, slice, u, v));
+                }
+            }
+            self.light[direction.index()] = Some(layer);
+        }
+        return self;
+    }
+
+    /// Block at a position relative to the chunk, at most a block outside it. Positions past the edges and corners
+    /// of the chunk aren't in any layer, so are treated as air.
+    fn around(&self, chunk: &Chunk, pos: [i32; 3]) -> BlockId {
+        let inside = |coord: i32| (0..SIZE as i32).contains(&coord);
+        return match pos.map(inside) {
+            [true, true, true] => chunk.get_block(LocalPos::new(pos[0] as u8, pos[1] as u8, pos[2] as u8)),
+            [false, true, true] => self.get(Axis::X.direction(pos[0] >= 0), pos[1] as usize, pos[2] as usize),
+            [true, false, true] => self.get(Axis::Y.direction(pos[1] >= 0), pos[2] as usize, pos[0] as usize),
+            [true, true, false] => self.get(Axis::Z.direction(pos[2] >= 0), pos[0] as usize, pos[1] as usize),
+            _ => AIR
+        };
+    }
+
+    fn get(&self, direction: Direction, u: usize, v: usize) -> BlockId {
+        return match &self.layers[direction.index()] {
+            Some(layer) => layer[u + v * SIZE],
+            None => AIR
+        };
+    }
+
+    fn get_light(&self, direction: Direction, u: usize, v: usize) -> u16 {
+        return match &self.light[direction.index()] {
+            Some(layer) => layer[u + v * SIZE],
+            None => UNLIT
+        };
+    }
+}
+
+/// Local position from a slice along an axis, and coordinates on the other two axes.
+/// u is the next axis after it and v the one after that, so u cross v points along the axis.
+fn local_at(axis: usize, slice: usize, u: usize, v: usize) -> LocalPos {
+    let mut pos = [0usize; 3];
+    pos[axis] = slice;
+    pos[(axis + 1) % 3] = u;
+    pos[(axis + 2) % 3] = v;
+    return LocalPos::new(pos[0] as u8, pos[1] as u8, pos[2] as u8);
+}
+
+/// Ambient occlusion of a face's corner, from whether the three blocks around it in the layer in front of the face
+/// are opaque: the two beside the corner's edges, and the one diagonally between them. A corner between two
+/// opaque blocks is fully hidden, whatever is between them.
+/// ```
+/// # use shared::engine::mesh::greedy::{corner_occlusion, AO_OPEN};
+/// assert_eq!(corner_occlusion(false, false, false), AO_OPEN);
+/// assert_eq!(corner_occlusion(false, false, true), 2);
+/// assert_eq!(corner_occlusion(true, false, true), 1);
+/// assert_eq!(corner_occlusion(true, true, false), 0);
+/// ```
+pub fn corner_occlusion(side_a: bool, side_b: bool, corner: bool) -> u32 {
+    if side_a && side_b {
+        return 0;
+    }
+    return AO_OPEN - side_a as u32 - side_b as u32 - corner as u32;
+}
+
+/// Build a mesh of every visible block face, merging adjacent coplanar faces of the same block into larger quads.
+/// A face is visible when the block beside it is not opaque, unless it is the same block, so glass next to glass isn't drawn.
+/// ```
+/// # use shared::engine::mesh::greedy::{greedy_mesh, ChunkBorders};
+/// # use shared::engine::world::chunk::Chunk;
+/// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+/// let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+/// // A 2x1x1 bar of stone is 6 merged quads, rather than 10 block faces.
+/// chunk.set_block(LocalPos::new(4, 4, 4), 1);
+/// chunk.set_block(LocalPos::new(5, 4, 4), 1);
+/// let mesh = greedy_mesh(&chunk, &ChunkBorders::empty(), &|id| id != 0);
+/// assert_eq!(mesh.quad_count(), 6);
+///
+/// // A full chunk of stone is one quad per side.
+/// let full = Chunk::filled(ChunkPos::new(0, 0, 0), 1);
+/// assert_eq!(greedy_mesh(&full, &ChunkBorders::empty(), &|id| id != 0).quad_count(), 6);
+/// ```
+pub fn greedy_mesh(chunk: &Chunk, borders: &ChunkBorders, opaque: &OpacityFn) -> MeshData {
+    return mesh_faces(chunk, None, borders, opaque, [0; 3], [SIZE; 3]);
+}
+
+/// Greedy mesh with each face lit by the block in front of it. Faces only merge when their light matches too.
+/// Every mesh darkens the corners of faces tucked against opaque blocks, and faces only merge when their corners
+/// are darkened the same, so a floor beside a wall has a row of quads along the wall.
+/// ```
+/// # use shared::engine::mesh::greedy::{greedy_mesh, ChunkBorders, AO_OPEN};
+/// # use shared::engine::world::chunk::Chunk;
+/// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+/// let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+/// for x in 0..4 {
+///     chunk.set_block(LocalPos::new(x, 0, 0), 1);
+/// }
+/// chunk.set_block(LocalPos::new(3, 1, 0), 1);
+/// let mesh = greedy_mesh(&chunk, &ChunkBorders::empty(), &|id| id != 0);
+/// // The top of the floor next to the block on it is darkened at the corners along the block.
+/// let top_corners: Vec<_> = mesh.vertices.iter().filter(|vertex| vertex.face == 3 && vertex.position[1] == 1.0).collect();
+/// assert!(top_corners.iter().any(|vertex| vertex.position[0] == 3.0 && vertex.ao == 2));
+/// assert!(top_corners.iter().any(|vertex| vertex.position[0] == 0.0 && vertex.ao == AO_OPEN));
+/// ```
+/// ```
+/// # use shared::engine::mesh::greedy::{greedy_mesh_lit, ChunkBorders};
+/// # use shared::engine::light::storage::ChunkLight;
+/// # use shared::engine::world::chunk::Chunk;
+/// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+/// let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+/// chunk.set_block(LocalPos::new(4, 4, 4), 1);
+/// chunk.set_block(LocalPos::new(5, 4, 4), 1);
+/// let mut light = ChunkLight::new();
+/// light.set_channel(LocalPos::new(4, 5, 4), 0, 15);
+/// let mesh = greedy_mesh_lit(&chunk, &light, &ChunkBorders::empty(), &|id| id != 0);
+/// // The top face is split in two, as only one half is lit.
+/// assert_eq!(mesh.quad_count(), 7);
+/// assert!(mesh.vertices.iter().any(|vertex| vertex.light == 15));
+/// ```
+pub fn greedy_mesh_lit(chunk: &Chunk, light: &ChunkLight, borders: &ChunkBorders, opaque: &OpacityFn) -> MeshData {
+    return mesh_faces(chunk, Some(light), borders, opaque, [0; 3], [SIZE; 3]);
+}
+
+/// Mesh only the faces of blocks within one section of a chunk, so an edit only remeshes the sections it affects.
+/// Positions are still relative to the chunk's minimum corner, and faces are lit if light is given.
+/// ```
+/// # use shared::engine::mesh::greedy::{greedy_mesh, greedy_mesh_section, ChunkBorders};
+/// # use shared::engine::world::chunk::{Chunk, SECTIONS_PER_CHUNK};
+/// # use shared::engine::math::coords::ChunkPos;
+/// let chunk = Chunk::filled(ChunkPos::new(0, 0, 0), 1);
+/// let borders = ChunkBorders::empty();
+/// let sections: usize = (0..SECTIONS_PER_CHUNK).map(|section| greedy_mesh_section(&chunk, None, &borders, &|id| id != 0, section).quad_count()).sum();
+/// // Each section draws its 3 outer faces, rather than sharing quads across the whole chunk.
+/// assert_eq!(sections, 24);
+/// assert_eq!(greedy_mesh(&chunk, &borders, &|id| id != 0).quad_count(), 6);
+/// ```
+pub fn greedy_mesh_section(chunk: &Chunk, light: Option<&ChunkLight>, borders: &ChunkBorders, opaque: &OpacityFn, section: usize) -> MeshData {
+    let origin = Chunk::section_origin(section);
+    let min = [origin.x as usize, origin.y as usize, origin.z as usize];
+    return mesh_faces(chunk, light, borders, opaque, min, min.map(|coord| coord + SECTION_SIZE));
+}
+
+/// Mesh the faces of blocks from min to max (exclusive) in chunk coordinates.
+fn mesh_faces(chunk: &Chunk, light: Option<&ChunkLight>, borders: &ChunkBorders, opaque: &OpacityFn, min: [usize; 3], max: [usize; 3]) -> MeshData {
+    let mut mesh = MeshData::new();
+    if chunk.is_empty() {
+        return mesh;
+    }
+    let mut mask = vec![(AIR, 0, 0); SIZE * SIZE];
+    for direction in Direction::ALL {
+        let axis = direction.axis() as usize;
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        for slice in min[axis]..max[axis] {
+            mask.fill((AIR, 0, 0));
+            // Find every visible face in this slice.
+            for v in min[v_axis]..max[v_axis] {
+                for u in min[u_axis]..max[u_axis] {
+                    let block = chunk.get_block(local_at(axis, slice, u, v));
+                    let neighbor_slice = slice as i32 + if direction.is_positive() { 1 } else { -1 };
+                    let outside = neighbor_slice < 0 || neighbor_slice >= SIZE as i32;
+                    let neighbor = if outside {
+                        borders.get(direction, u, v)
+                    } else {
+                        chunk.get_block(local_at(axis, neighbor_slice as usize, u, v))
+                    };
+                    let visible = block != AIR && neighbor != block && !opaque(neighbor);
+                    if !visible {
+                        mask[u + v * SIZE] = (AIR, 0, 0);
+                        continue;
+                    }
+                    let face_light = match light {
+                        _ if outside => borders.get_light(direction, u, v),
+                        Some(light) => light.packed(local_at(axis, neighbor_slice as usize, u, v)),
+                        None => UNLIT
+                    };
+                    // Each corner's occlusion, 2 bits each in CORNERS order.
+                    let mut ao = 0u8;
+                    for (index, (du, dv)) in CORNERS.into_iter().enumerate() {
+                        let opaque_at = |du: i32, dv: i32| {
+                            let mut pos = [0; 3];
+                            pos[axis] = neighbor_slice;
+                            pos[u_axis] = u as i32 + du;
+                            pos[v_axis] = v as i32 + dv;
+                            return opaque(borders.around(chunk, pos));
+                        };
+                        ao |= (corner_occlusion(opaque_at(du, 0), opaque_at(0, dv), opaque_at(du, dv)) as u8) << (index * 2);
+                    }
+                    mask[u + v * SIZE] = (block, face_light, ao);
+                }
+            }
+            merge_mask(&mut mask, &mut mesh, direction, slice);
+        }
+    }
+    return mesh;
+}
+
+/// Greedily cover the faces in a mask with as few rectangles as possible, emitting a quad for each.
+fn merge_mask(mask: &mut [(BlockId, u16, u8)], mesh: &mut MeshData, direction: Direction, slice: usize) {
+    let axis = direction.axis() as usize;
+    let plane = (slice + direction.is_positive() as usize) as f32;
+    for v in 0..SIZE {
+        let mut u = 0;
+        while u < SIZE {
+            let face = mask[u + v * SIZE];
+            let (block, light, ao) = face;
+            if block == AIR {
+                u += 1;
+                continue;
+            }
+            let mut width = 1;
+            while u + width < SIZE && mask[u + width + v * SIZE] == face {
+                width += 1;
+            }
+            let mut height = 1;
+            'grow: while v + height < SIZE {
+                for du in 0..width {
+                    if mask[u + du + (v + height) * SIZE] != face {
+                        break 'grow;
+                    }
+                }
+                height += 1;
+            }
+            for dv in 0..height {
+                for du in 0..width {
+                    mask[u + du + (v + dv) * SIZE] = (AIR, 0, 0);
+                }
+            }
+
+            let corner = |du: usize, dv: usize, index: usize| {
+                let mut position = [0.0; 3];
+                position[axis] = plane;
+                position[(axis + 1) % 3] = (u + du) as f32;
+                position[(axis + 2) % 3] = (v + dv) as f32;
+                return ChunkVertex {
+                    position,
+                    uv: [du as f32, dv as f32],
+                    texture: UvRect::FULL.to_array(),
+                    block: block as u32,
+                    face: direction.index() as u32,
+                    light: light as u32,
+                    ao: (ao >> (index * 2) & 3) as u32
+                };
+            };
+            let corners = [corner(0, 0, 0), corner(width, 0, 1), corner(width, height, 2), corner(0, height, 3)];
+            let mut quad = if direction.is_positive() { corners } else { [corners[0], corners[3], corners[2], corners[1]] };
+            // Split the quad along the diagonal through the odd corner out, so its shading spreads evenly rather than
+            // showing the triangles.
+            if quad[0].ao + quad[2].ao > quad[1].ao + quad[3].ao {
+                quad.rotate_left(1);
+            }
+            mesh.push_quad(quad);
+            u += width;
+        }
+    }
+}
+
+/// Queue a job meshing a chunk against its neighbors, given in Direction order.
+/// Chunks are only read locked while their data is copied or meshed, so the world can keep changing,
+/// though a block edited during meshing may not show until the chunk is remeshed.
+/// ```
+/// # use shared::engine::mesh::greedy::queue_mesh_job;
+/// # use shared::engine::job::system::JobSystem;
+/// # use shared::engine::world::chunk::Chunk;
+/// # use shared::engine::math::coords::ChunkPos;
+/// # use std::sync::{Arc, RwLock};
+/// let jobs = JobSystem::new(2);
+/// let chunk = Arc::new(RwLock::new(Chunk::filled(ChunkPos::new(0, 0, 0), 1)));
+/// let below = Arc::new(RwLock::new(Chunk::filled(ChunkPos::new(0, -1, 0), 1)));
+/// let mut neighbors: [Option<_>; 6] = Default::default();
+/// neighbors[2] = Some(below);
+/// let mesh = queue_mesh_job(&jobs, chunk, neighbors, Arc::new(|id| id != 0)).wait();
+/// // The bottom face is hidden by the chunk below.
+/// assert_eq!(mesh.quad_count(), 5);
+/// ```
+pub fn queue_mesh_job(jobs: &JobSystem, chunk: SharedChunk, neighbors: [Option<SharedChunk>; 6], opaque: Arc<OpacityFn>) -> JobFuture<MeshData> {
+    return jobs.run_job(move || {
+        let neighbor_locks: Vec<_> = neighbors.iter().map(|neighbor| neighbor.as_ref().map(|n| n.read().unwrap())).collect();
+        let borders = ChunkBorders::from_neighbors(std::array::from_fn(|i| neighbor_locks[i].as_deref()));
+        drop(neighbor_locks);
+        return greedy_mesh(&chunk.read().unwrap(), &borders, &*opaque);
+    });
+}
+
+/// Queue a job meshing a loaded chunk of a world with its light, against its loaded neighbors. None if the chunk isn't loaded.
+pub fn queue_world_mesh_job(jobs: &JobSystem, world: Arc<World>, pos: ChunkPos, opaque: Arc<OpacityFn>) -> Option<JobFuture<MeshData>> {
+    let (chunk, light) = world.chunk_and_light(pos)?;
+    return Some(jobs.run_job(move || {
+        let neighbors: Vec<_> = Direction::ALL.iter().map(|direction| world.chunk_and_light(pos + direction.chunk_offset())).collect();
+        let borders = {
+            let chunk_locks: Vec<_> = neighbors.iter().map(|neighbor| neighbor.as_ref().map(|(chunk, _)| chunk.read().unwrap())).collect();
+            let light_locks: Vec<_> = neighbors.iter().map(|neighbor| neighbor.as_ref().map(|(_, light)| light.read().unwrap())).collect();
+            ChunkBorders::from_neighbors(std::array::from_fn(|i| chunk_locks[i].as_deref()))
+                .with_light(std::array::from_fn(|i| light_locks[i].as_deref()))
+        };
+        return greedy_mesh_lit(&chunk.read().unwrap(), &light.read().unwrap(), &borders, &*opaque);
+    }));
+}
\ No newline at end of file
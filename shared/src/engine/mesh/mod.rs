@@ -0,0 +1,5 @@
+pub mod vertex;
+pub mod greedy;
+pub mod remesh;
+pub mod allocator;
+pub mod texture;
\ No newline at end of file
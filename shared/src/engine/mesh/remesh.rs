@@ -0,0 +1,155 @@
+use std::{collections::HashMap, sync::{Arc, Mutex, RwLock}};
+
+use crate::engine::{
+    job::{system::JobSystem, future::JobFuture},
+    math::{coords::ChunkPos, direction::Direction},
+    world::{chunk::{Chunk, SECTIONS_PER_CHUNK}, World}
+};
+
+use super::{greedy::{greedy_mesh_section, ChunkBorders, OpacityFn, TranslucencyFn}, texture::FaceTextures, vertex::MeshData};
+
+/// Meshes of the remeshed sections of one chunk, as (section index, mesh), replacing those sections' previous meshes.
+pub type SectionMeshes = Vec<(usize, MeshData)>;
+
+/// A queued remesh of one chunk.
+pub struct RemeshJob {
+    pub pos: ChunkPos,
+    /// Bit per section being remeshed.
+    pub sections: u8,
+    pub future: JobFuture<SectionMeshes>
+}
+
+/// Sections waiting to be remeshed, gathered from chunk edits over a frame.
+/// Many edits to the same chunk, such as an explosion, are coalesced into a single job per chunk when dispatched,
+/// and each job only meshes the sections that changed rather than the whole chunk.
+pub struct RemeshQueue {
+    pending: Mutex<HashMap<ChunkPos, u8>>,
+    /// Atlas rects given to meshes by the jobs, if textures are loaded.
+    textures: RwLock<Option<Arc<FaceTextures>>>,
+    /// Blocks the jobs mesh apart as translucent. Everything is opaque without it.
+    translucent: RwLock<Option<Arc<TranslucencyFn>>>
+}
+
+impl RemeshQueue {
+    pub fn new() -> RemeshQueue {
+        return RemeshQueue { pending: Mutex::new(HashMap::new()), textures: RwLock::new(None), translucent: RwLock::new(None) };
+    }
+
+    /// Give meshes from now on the atlas rects of their faces, such as after the block atlas is rebuilt.
+    /// Meshes already made keep the rects they were made with, so chunks should be marked to remesh them.
+    pub fn set_textures(&self, textures: Option<Arc<FaceTextures>>) {
+        *self.textures.write().unwrap() = textures;
+    }
+
+    /// Mesh translucent blocks apart from now on, so they can be drawn blended after opaque ones.
+    /// Like set_textures(), meshes already made aren't changed.
+    pub fn set_translucency(&self, translucent: Option<Arc<TranslucencyFn>>) {
+        *self.translucent.write().unwrap() = translucent;
+    }
+
+    /// Queue sections of a chunk, as a bit per section index.
+    pub fn mark_sections(&self, pos: ChunkPos, sections: u8) {
+        if sections == 0 {
+            return;
+        }
+        *self.pending.lock().unwrap().entry(pos).or_insert(0) |= sections;
+    }
+
+    /// Queue every section of a chunk, such as when it or a neighbor is loaded.
+    pub fn mark_chunk(&self, pos: ChunkPos) {
+        self.mark_sections(pos, u8::MAX);
+    }
+
+    /// Number of chunks with queued sections.
+    pub fn pending_count(&self) -> usize {
+        return self.pending.lock().unwrap().len();
+    }
+
+    /// Queued sections of a chunk.
+    pub fn pending_sections(&self, pos: ChunkPos) -> u8 {
+        return self.pending.lock().unwrap().get(&pos).copied().unwrap_or(0);
+    }
+
+    /// Take the dirty flags of every loaded chunk, queueing its edited sections along with the sections of
+    /// loaded neighbors across any face an edit touched. Chunks without edits are only read locked.
+    /// ```
+    /// # use shared::engine::mesh::remesh::RemeshQueue;
+    /// # use shared::engine::world::{World, chunk::Chunk};
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// let world = World::new();
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(-1, 0, 0)));
+    /// let queue = RemeshQueue::new();
+    /// world.set_block(BlockPos::new(0, 1, 1), 1);
+    /// queue.collect_from_world(&world);
+    /// assert_eq!(queue.pending_sections(ChunkPos::new(0, 0, 0)), 0b1);
+    /// // Sections on the neighbor's positive x face.
+    /// assert_eq!(queue.pending_sections(ChunkPos::new(-1, 0, 0)), 0b10101010);
+    /// ```
+    pub fn collect_from_world(&self, world: &World) {
+        for chunk in world.snapshot() {
+            if !chunk.read().unwrap().is_dirty() {
+                continue;
+            }
+            let (pos, (sections, borders)) = {
+                let mut lock = chunk.write().unwrap();
+                (lock.pos(), lock.take_dirty())
+            };
+            self.mark_sections(pos, sections);
+            for direction in Direction::ALL {
+                let neighbor = pos + direction.chunk_offset();
+                if borders & (1 << direction.index()) != 0 && world.is_loaded(neighbor) {
+                    self.mark_sections(neighbor, Chunk::sections_on_face(direction.opposite()));
+                }
+            }
+        }
+    }
+
+    /// Start a job for every chunk with queued sections, clearing the queue. Called once per frame, so all of a
+    /// frame's edits to a chunk share one job. Queued chunks that have since unloaded are dropped.
+    pub fn dispatch(&self, jobs: &JobSystem, world: &Arc<World>, opaque: &Arc<OpacityFn>) -> Vec<RemeshJob> {
+        let pending: Vec<(ChunkPos, u8)> = self.pending.lock().unwrap().drain().collect();
+        let mut queued = Vec::with_capacity(pending.len());
+        for (pos, sections) in pending {
+            let Some((chunk, light)) = world.chunk_and_light(pos) else {
+                continue;
+            };
+            let world = world.clone();
+            let opaque = opaque.clone();
+            let textures = self.textures.read().unwrap().clone();
+            let translucent = self.translucent.read().unwrap().clone();
+            let future = jobs.run_job(move || {
+                let neighbors: Vec<_> = Direction::ALL.iter().map(|direction| world.chunk_and_light(pos + direction.chunk_offset())).collect();
+                let borders = {
+                    let chunk_locks: Vec<_> = neighbors.iter().map(|neighbor| neighbor.as_ref().map(|(chunk, _)| chunk.read().unwrap())).collect();
+                    let light_locks: Vec<_> = neighbors.iter().map(|neighbor| neighbor.as_ref().map(|(_, light)| light.read().unwrap())).collect();
+                    ChunkBorders::from_neighbors(std::array::from_fn(|i| chunk_locks[i].as_deref()))
+                        .with_light(std::array::from_fn(|i| light_locks[i].as_deref()))
+                };
+                let chunk = chunk.read().unwrap();
+                let light = light.read().unwrap();
+                return (0..SECTIONS_PER_CHUNK)
+                    .filter(|section| sections & (1 << section) != 0)
+                    .map(|section| {
+                        let mut mesh = greedy_mesh_section(&chunk, Some(&light), &borders, &*opaque, section);
+                        if let Some(textures) = textures.as_ref() {
+                            textures.apply(&mut mesh);
+                        }
+                        if let Some(translucent) = translucent.as_ref() {
+                            mesh.split_translucent(&**translucent);
+                        }
+                        return (section, mesh);
+                    })
+                    .collect::<SectionMeshes>();
+            });
+            queued.push(RemeshJob { pos, sections, future });
+        }
+        return queued;
+    }
+}
+
+impl Default for RemeshQueue {
+    fn default() -> RemeshQueue {
+        return RemeshQueue::new();
+    }
+}
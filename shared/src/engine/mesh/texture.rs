@@ -0,0 +1,95 @@
+use crate::engine::{block::BlockId, math::direction::Direction};
+
+use super::vertex::MeshData;
+
+/// A rectangle of a texture atlas, in texture coordinates from 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub min: [f32; 2],
+    pub size: [f32; 2]
+}
+
+impl UvRect {
+    /// The whole texture.
+    pub const FULL: UvRect = UvRect { min: [0.0, 0.0], size: [1.0, 1.0] };
+
+    pub const fn new(min: [f32; 2], size: [f32; 2]) -> UvRect {
+        return UvRect { min, size };
+    }
+
+    /// Rect of a region of a texture, in pixels.
+    /// ```
+    /// # use shared::engine::mesh::texture::UvRect;
+    /// let rect = UvRect::from_pixels([16, 32], [16, 16], [64, 64]);
+    /// assert_eq!(rect, UvRect::new([0.25, 0.5], [0.25, 0.25]));
+    /// ```
+    pub fn from_pixels(min: [u32; 2], size: [u32; 2], texture_size: [u32; 2]) -> UvRect {
+        let scale = |value: u32, axis: usize| value as f32 / texture_size[axis] as f32;
+        return UvRect { min: [scale(min[0], 0), scale(min[1], 1)], size: [scale(size[0], 0), scale(size[1], 1)] };
+    }
+
+    /// As stored in ChunkVertex::texture.
+    pub fn to_array(self) -> [f32; 4] {
+        return [self.min[0], self.min[1], self.size[0], self.size[1]];
+    }
+}
+
+/// Where each face of each block's texture is in the block texture atlas, so meshes can be given atlas UVs.
+/// Blocks and faces without a texture use the missing texture's rect.
+/// ```
+/// # use shared::engine::mesh::{greedy::{greedy_mesh, ChunkBorders}, texture::{FaceTextures, UvRect}};
+/// # use shared::engine::world::chunk::Chunk;
+/// # use shared::engine::math::{coords::ChunkPos, direction::Direction};
+/// let missing = UvRect::new([0.0, 0.0], [0.5, 0.5]);
+/// let grass = UvRect::new([0.5, 0.0], [0.5, 0.5]);
+/// let mut textures = FaceTextures::new(missing);
+/// textures.set(1, Direction::PosY, grass);
+/// let mut mesh = greedy_mesh(&Chunk::filled(ChunkPos::new(0, 0, 0), 1), &ChunkBorders::empty(), &|id| id != 0);
+/// textures.apply(&mut mesh);
+/// for vertex in mesh.vertices.iter() {
+///     let expected = if vertex.face == Direction::PosY.index() as u32 { grass } else { missing };
+///     assert_eq!(vertex.texture, expected.to_array());
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaceTextures {
+    /// Rect of each face in Direction order, by block id.
+    blocks: Vec<[UvRect; 6]>,
+    missing: UvRect
+}
+
+impl FaceTextures {
+    pub fn new(missing: UvRect) -> FaceTextures {
+        return FaceTextures { blocks: Vec::new(), missing };
+    }
+
+    pub fn missing(&self) -> UvRect {
+        return self.missing;
+    }
+
+    pub fn set(&mut self, block: BlockId, face: Direction, rect: UvRect) {
+        let index = block as usize;
+        if index >= self.blocks.len() {
+            self.blocks.resize(index + 1, [self.missing; 6]);
+        }
+        self.blocks[index][face.index()] = rect;
+    }
+
+    /// Use one texture on every face of a block.
+    pub fn set_all(&mut self, block: BlockId, rect: UvRect) {
+        for face in Direction::ALL {
+            self.set(block, face, rect);
+        }
+    }
+
+    pub fn get(&self, block: BlockId, face: Direction) -> UvRect {
+        return self.blocks.get(block as usize).map_or(self.missing, |faces| faces[face.index()]);
+    }
+
+    /// Give every vertex of a mesh the rect of its block's face.
+    pub fn apply(&self, mesh: &mut MeshData) {
+        for vertex in mesh.vertices.iter_mut() {
+            vertex.texture = self.get(vertex.block as BlockId, Direction::from_index(vertex.face as usize)).to_array();
+        }
+    }
+}
\ No newline at end of file
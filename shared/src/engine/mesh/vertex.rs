@@ -0,0 +1,83 @@
+use crate::engine::block::BlockId;
+
+use super::greedy::TranslucencyFn;
+
+/// Vertex of a chunk mesh, laid out to be uploaded to the GPU as is.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChunkVertex {
+    /// Position relative to the chunk's minimum corner.
+    pub position: [f32; 3],
+    /// Texture coordinates in blocks, so merged faces repeat the block texture rather than stretching it.
+    pub uv: [f32; 2],
+    /// Rect of the face's texture in the block texture atlas, as min u, min v, width, height, which uv repeats
+    /// within. The whole texture until set from a FaceTextures.
+    pub texture: [f32; 4],
+    pub block: u32,
+    /// Index of the face's Direction.
+    pub face: u32,
+    /// Light of the block in front of the face, packed as by light::storage::pack().
+    pub light: u32,
+    /// Ambient occlusion of the corner by the blocks around it, from 0, fully hidden, to AO_OPEN.
+    pub ao: u32
+}
+
+/// Vertex and index buffers for one chunk.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshData {
+    pub vertices: Vec<ChunkVertex>,
+    pub indices: Vec<u32>,
+    /// Indices of the quads of translucent blocks, such as water, into the same vertices. They're drawn after
+    /// everything opaque, blended over it, so are kept apart.
+    pub translucent: Vec<u32>
+}
+
+impl MeshData {
+    pub fn new() -> MeshData {
+        return MeshData { vertices: Vec::new(), indices: Vec::new(), translucent: Vec::new() };
+    }
+
+    /// Check if there is nothing to draw, so the client can skip uploading.
+    pub fn is_empty(&self) -> bool {
+        return self.indices.is_empty() && self.translucent.is_empty();
+    }
+
+    /// Quads drawn, opaque and translucent.
+    pub fn quad_count(&self) -> usize {
+        return (self.indices.len() + self.translucent.len()) / 6;
+    }
+
+    /// Move the quads of translucent blocks out of the opaque indices, keeping their order.
+    /// ```
+    /// # use shared::engine::mesh::greedy::{greedy_mesh, ChunkBorders};
+    /// # use shared::engine::world::chunk::Chunk;
+    /// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+    /// const WATER: u16 = 2;
+    /// let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    /// chunk.set_block(LocalPos::new(4, 4, 4), 1);
+    /// chunk.set_block(LocalPos::new(5, 4, 4), WATER);
+    /// let mut mesh = greedy_mesh(&chunk, &ChunkBorders::empty(), &|id| id == 1);
+    /// mesh.split_translucent(&|id| id == WATER);
+    /// // Water doesn't hide the stone's face beside it.
+    /// assert_eq!((mesh.indices.len() / 6, mesh.translucent.len() / 6), (6, 5));
+    /// assert!(mesh.translucent.iter().all(|&index| mesh.vertices[index as usize].block == WATER as u32));
+    /// ```
+    pub fn split_translucent(&mut self, translucent: &TranslucencyFn) {
+        let (translucent_quads, opaque_quads): (Vec<&[u32]>, Vec<&[u32]>) = self.indices.chunks_exact(6).partition(|quad| {
+            return translucent(self.vertices[quad[0] as usize].block as BlockId);
+        });
+        if translucent_quads.is_empty() {
+            return;
+        }
+        let translucent_indices: Vec<u32> = translucent_quads.concat();
+        self.indices = opaque_quads.concat();
+        self.translucent.extend(translucent_indices);
+    }
+
+    /// Add a quad from 4 corners in counter-clockwise order when viewed from the front.
+    pub fn push_quad(&mut self, corners: [ChunkVertex; 4]) {
+        let first = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&corners);
+        self.indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+}
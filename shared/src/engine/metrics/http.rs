@@ -0,0 +1,191 @@
+use std::{io::{self, Read, Write}, net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs}, sync::{Arc, Mutex, RwLock}, time::{Duration, Instant}};
+
+use serde::{Deserialize, Serialize};
+
+use super::registry::MetricsRegistry;
+
+/// Path Prometheus scrapes.
+pub const METRICS_PATH: &str = "/metrics";
+/// Content type of Prometheus' text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// Path hosting panels read the server's status from.
+pub const STATUS_PATH: &str = "/status";
+/// Content type of the status document.
+pub const STATUS_CONTENT_TYPE: &str = "application/json";
+/// Largest request read, which is only ever a short GET from a scraper.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+/// How long a scraper has from connecting to being answered before it's dropped.
+pub const CONNECTION_TIMEOUT: Duration = Duration::from_secs(2);
+/// Scrapers served at once. Any more are hung up on as soon as they connect.
+pub const MAX_CONNECTIONS: usize = 16;
+
+/// State of a dedicated server, served as JSON at STATUS_PATH so hosting panels can show it without RCON.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerStatus {
+    pub version: String,
+    pub players: usize,
+    /// Names of the players logged in, sorted.
+    pub player_names: Vec<String>,
+    /// Ticks per second over the last five seconds.
+    pub tps: f64,
+    /// Mean milliseconds each tick took over the last minute.
+    pub mspt: f64,
+    pub loaded_chunks: usize,
+    pub uptime_seconds: u64
+}
+
+/// Serves a metrics registry over HTTP for Prometheus to scrape, and the server's status for hosting panels,
+/// for the dedicated server. Like Transport, it never blocks: pump() accepts scrapers each tick, and reads
+/// their requests and writes their responses as far as their sockets allow, keeping the rest for the next pump.
+/// ```
+/// # use shared::engine::metrics::{http::MetricsEndpoint, MetricsRegistry};
+/// # use std::{io::{Read, Write}, net::TcpStream, sync::Arc};
+/// let registry = Arc::new(MetricsRegistry::new());
+/// registry.counter("players_joined_total", "Players that have joined.").add(2);
+/// let endpoint = MetricsEndpoint::bind("127.0.0.1:0", registry).unwrap();
+///
+/// let mut scraper = TcpStream::connect(endpoint.local_addr()).unwrap();
+/// scraper.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+/// while endpoint.pump().unwrap() == 0 {}
+/// let mut response = String::new();
+/// scraper.read_to_string(&mut response).unwrap();
+/// assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+/// assert!(response.ends_with("players_joined_total 2\n"));
+/// ```
+pub struct MetricsEndpoint {
+    listener: TcpListener,
+    registry: Arc<MetricsRegistry>,
+    /// The last status set, as JSON. None until the server sets one.
+    status: RwLock<Option<String>>,
+    connections: Mutex<Vec<Scrape>>
+}
+
+impl MetricsEndpoint {
+    pub fn bind(address: impl ToSocketAddrs, registry: Arc<MetricsRegistry>) -> io::Result<MetricsEndpoint> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        return Ok(MetricsEndpoint { listener, registry, status: RwLock::new(None), connections: Mutex::new(Vec::new()) });
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        return self.listener.local_addr().unwrap();
+    }
+
+    /// Serve a status at STATUS_PATH until the next is set. Requests for it before the first is set are not found.
+    /// ```
+    /// # use shared::engine::metrics::{http::{MetricsEndpoint, ServerStatus}, MetricsRegistry};
+    /// # use std::{io::{Read, Write}, net::TcpStream, sync::Arc};
+    /// let endpoint = MetricsEndpoint::bind("127.0.0.1:0", Arc::new(MetricsRegistry::new())).unwrap();
+    /// endpoint.set_status(&ServerStatus { players: 1, player_names: vec!["steve".to_string()], tps: 20.0, ..Default::default() });
+    ///
+    /// let mut panel = TcpStream::connect(endpoint.local_addr()).unwrap();
+    /// panel.write_all(b"GET /status HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    /// while endpoint.pump().unwrap() == 0 {}
+    /// let mut response = String::new();
+    /// panel.read_to_string(&mut response).unwrap();
+    /// assert!(response.contains("Content-Type: application/json\r\n"));
+    /// let status: ServerStatus = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    /// assert_eq!(status.player_names, vec!["steve".to_string()]);
+    /// ```
+    pub fn set_status(&self, status: &ServerStatus) {
+        let json = serde_json::to_string(status).expect("statuses serialize to JSON");
+        *self.status.write().unwrap() = Some(json);
+    }
+
+    /// Accept every pending scraper, then serve each connection as far as it can go without blocking.
+    /// Scrapers past MAX_CONNECTIONS are hung up on, and any not answered within CONNECTION_TIMEOUT are dropped,
+    /// so slow or idle clients can't pile up. Returns the number of requests answered.
+    pub fn pump(&self) -> io::Result<usize> {
+        let mut connections = self.connections.lock().unwrap();
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    // Dropping the stream hangs up, as does failing to make it non-blocking.
+                    if connections.len() < MAX_CONNECTIONS && stream.set_nonblocking(true).is_ok() {
+                        connections.push(Scrape { stream, request: Vec::new(), response: None, written: 0, deadline: Instant::now() + CONNECTION_TIMEOUT });
+                    }
+                },
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error)
+            }
+        }
+        let mut answered = 0;
+        connections.retain_mut(|scrape| match scrape.advance(&self.registry, &self.status) {
+            Ok(true) => {
+                answered += 1;
+                false
+            },
+            Ok(false) => true,
+            // A scraper that hangs up, sends garbage or takes too long only loses its own response.
+            Err(_) => false
+        });
+        return Ok(answered);
+    }
+}
+
+/// A connected scraper, its request read and its response written a piece at a time as its socket allows.
+struct Scrape {
+    stream: TcpStream,
+    request: Vec<u8>,
+    /// Set once the request's head has been read.
+    response: Option<Vec<u8>>,
+    written: usize,
+    deadline: Instant
+}
+
+impl Scrape {
+    /// Read and write as much as the socket allows without blocking. Ok(true) once the whole response is written.
+    fn advance(&mut self, registry: &MetricsRegistry, document: &RwLock<Option<String>>) -> io::Result<bool> {
+        if Instant::now() > self.deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "metrics request timed out"));
+        }
+        if self.response.is_none() {
+            let mut buffer = [0u8; 1024];
+            while !has_head(&self.request) {
+                if self.request.len() > MAX_REQUEST_SIZE {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "metrics request is too large"));
+                }
+                match self.stream.read(&mut buffer) {
+                    Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "scraper hung up before finishing its request")),
+                    Ok(read) => self.request.extend_from_slice(&buffer[..read]),
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(error) => return Err(error)
+                }
+            }
+            self.response = Some(respond(&String::from_utf8_lossy(&self.request), registry, document));
+        }
+        let response = self.response.as_ref().unwrap();
+        while self.written < response.len() {
+            match self.stream.write(&response[self.written..]) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "scraper stopped reading its response")),
+                Ok(written) => self.written += written,
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error)
+            }
+        }
+        return Ok(true);
+    }
+}
+
+/// Check if a request's head has been read, up to the blank line ending it.
+fn has_head(request: &[u8]) -> bool {
+    return request.windows(4).any(|window| window == b"\r\n\r\n");
+}
+
+/// Full response to a request's head.
+fn respond(request: &str, registry: &MetricsRegistry, document: &RwLock<Option<String>>) -> Vec<u8> {
+    let mut parts = request.lines().next().unwrap_or("").split(' ');
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = target.split('?').next().unwrap();
+    let (status, content_type, body) = match (method, path, document.read().unwrap().clone()) {
+        ("GET", METRICS_PATH, _) => ("200 OK", CONTENT_TYPE, registry.prometheus_text()),
+        ("GET", STATUS_PATH, Some(document)) => ("200 OK", STATUS_CONTENT_TYPE, document),
+        ("GET", _, _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "only GET is supported\n".to_string())
+    };
+    let head = format!("HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, content_type, body.len());
+    return [head.into_bytes(), body.into_bytes()].concat();
+}
\ No newline at end of file
@@ -0,0 +1,5 @@
+pub mod registry;
+pub mod standard;
+pub mod http;
+
+pub use registry::MetricsRegistry;
\ No newline at end of file
@@ -0,0 +1,354 @@
+use std::{collections::BTreeMap, fmt::Write, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, OnceLock}, time::{Duration, Instant}};
+
+/// Buckets of a histogram of durations in seconds, from a millisecond to a second.
+pub const DURATION_BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// Add to an f64 stored as bits in an atomic.
+fn add_f64(bits: &AtomicU64, value: f64) {
+    let _ = bits.fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| Some((f64::from_bits(current) + value).to_bits()));
+}
+
+/// A count that only goes up, such as jobs run or bytes sent.
+#[derive(Debug, Default)]
+pub struct Counter {
+    value: AtomicU64
+}
+
+impl Counter {
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, amount: u64) {
+        self.value.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        return self.value.load(Ordering::Relaxed);
+    }
+}
+
+/// A value that goes up and down, such as chunks loaded.
+#[derive(Debug)]
+pub struct Gauge {
+    bits: AtomicU64
+}
+
+impl Gauge {
+    pub fn set(&self, value: f64) {
+        self.bits.store(value.to_bits(), Ordering::Release);
+    }
+
+    pub fn add(&self, amount: f64) {
+        add_f64(&self.bits, amount);
+    }
+
+    pub fn inc(&self) {
+        self.add(1.0);
+    }
+
+    pub fn dec(&self) {
+        self.add(-1.0);
+    }
+
+    pub fn get(&self) -> f64 {
+        return f64::from_bits(self.bits.load(Ordering::Acquire));
+    }
+}
+
+impl Default for Gauge {
+    fn default() -> Gauge {
+        return Gauge { bits: AtomicU64::new(0f64.to_bits()) };
+    }
+}
+
+/// Counts of values by which bucket they fall in, such as tick durations.
+/// Each bucket counts the values up to its upper bound that didn't fit a smaller one.
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: Box<[f64]>,
+    /// One more than the bounds, for values over the largest.
+    counts: Box<[AtomicU64]>,
+    sum_bits: AtomicU64
+}
+
+impl Histogram {
+    /// Bounds must be in increasing order.
+    pub fn new(bounds: &[f64]) -> Histogram {
+        debug_assert!(bounds.windows(2).all(|pair| pair[0] < pair[1]), "histogram bounds must increase");
+        return Histogram {
+            bounds: bounds.into(),
+            counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_bits: AtomicU64::new(0f64.to_bits())
+        };
+    }
+
+    pub fn observe(&self, value: f64) {
+        let bucket = self.bounds.iter().position(|bound| value <= *bound).unwrap_or(self.bounds.len());
+        self.counts[bucket].fetch_add(1, Ordering::Relaxed);
+        add_f64(&self.sum_bits, value);
+    }
+
+    /// Observe a duration in seconds.
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration.as_secs_f64());
+    }
+
+    /// Time until the returned timer is dropped, then observe it.
+    pub fn start_timer(&self) -> HistogramTimer<'_> {
+        return HistogramTimer { histogram: self, start: Instant::now() };
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut buckets = Vec::with_capacity(self.bounds.len() + 1);
+        let mut count = 0;
+        for (index, bucket_count) in self.counts.iter().enumerate() {
+            count += bucket_count.load(Ordering::Relaxed);
+            buckets.push((self.bounds.get(index).copied().unwrap_or(f64::INFINITY), count));
+        }
+        return HistogramSnapshot { buckets, sum: f64::from_bits(self.sum_bits.load(Ordering::Acquire)), count };
+    }
+}
+
+pub struct HistogramTimer<'a> {
+    histogram: &'a Histogram,
+    start: Instant
+}
+
+impl Drop for HistogramTimer<'_> {
+    fn drop(&mut self) {
+        self.histogram.observe_duration(self.start.elapsed());
+    }
+}
+
+/// A histogram's values at one point in time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramSnapshot {
+    /// Upper bound of each bucket, ending with infinity, and how many values were at most it.
+    pub buckets: Vec<(f64, u64)>,
+    pub sum: f64,
+    pub count: u64
+}
+
+impl HistogramSnapshot {
+    pub fn mean(&self) -> Option<f64> {
+        return (self.count > 0).then(|| self.sum / self.count as f64);
+    }
+
+    /// Upper bound of the bucket the given fraction of values are within, such as 0.99 for the 99th percentile.
+    pub fn quantile(&self, fraction: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (fraction.clamp(0.0, 1.0) * self.count as f64).ceil().max(1.0) as u64;
+        return self.buckets.iter().find(|(_, count)| *count >= rank).map(|(bound, _)| *bound);
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Metric {
+    Counter(Arc<Counter>),
+    Gauge(Arc<Gauge>),
+    Histogram(Arc<Histogram>)
+}
+
+impl Metric {
+    fn kind(&self) -> &'static str {
+        return match self {
+            Metric::Counter(_) => "counter",
+            Metric::Gauge(_) => "gauge",
+            Metric::Histogram(_) => "histogram"
+        };
+    }
+}
+
+struct Registered {
+    help: String,
+    metric: Metric
+}
+
+/// Whether a name is a valid Prometheus metric name.
+pub fn is_valid_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    return chars.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_' || first == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':');
+}
+
+fn write_value(text: &mut String, name: &str, value: f64) {
+    let _ = match value {
+        value if value == f64::INFINITY => writeln!(text, "{} +Inf", name),
+        value if value == f64::NEG_INFINITY => writeln!(text, "{} -Inf", name),
+        value => writeln!(text, "{} {}", name, value)
+    };
+}
+
+/// Named metrics, read by the debug overlay and exported in Prometheus' text format.
+/// Metrics are made once and their handles kept by whatever updates them, which is a single atomic operation.
+/// ```
+/// # use shared::engine::metrics::MetricsRegistry;
+/// let registry = MetricsRegistry::new();
+/// let chunks = registry.gauge("chunks_loaded", "Chunks loaded in every world.");
+/// chunks.add(3.0);
+/// let ticks = registry.histogram("tick_duration_seconds", "Time taken by each tick.", &[0.01, 0.05]);
+/// ticks.observe(0.02);
+/// assert_eq!(registry.snapshot().gauge("chunks_loaded"), Some(3.0));
+/// let text = registry.prometheus_text();
+/// assert!(text.contains("# TYPE chunks_loaded gauge\nchunks_loaded 3\n"));
+/// assert!(text.contains("tick_duration_seconds_bucket{le=\"0.05\"} 1\n"));
+/// ```
+pub struct MetricsRegistry {
+    metrics: Mutex<BTreeMap<String, Registered>>
+}
+
+impl MetricsRegistry {
+    pub fn new() -> MetricsRegistry {
+        return MetricsRegistry { metrics: Mutex::new(BTreeMap::new()) };
+    }
+
+    /// Register a metric, or get the one already registered with the name.
+    /// Panics if the name isn't valid or is registered as another kind of metric, as both are mistakes in code.
+    fn register(&self, name: &str, help: &str, make: impl FnOnce() -> Metric) -> Metric {
+        assert!(is_valid_metric_name(name), "invalid metric name {}", name);
+        let mut metrics = self.metrics.lock().unwrap();
+        let registered = metrics.entry(name.to_string()).or_insert_with(|| Registered { help: help.to_string(), metric: make() });
+        return registered.metric.clone();
+    }
+
+    pub fn counter(&self, name: &str, help: &str) -> Arc<Counter> {
+        return match self.register(name, help, || Metric::Counter(Arc::default())) {
+            Metric::Counter(counter) => counter,
+            metric => panic!("metric {} is a {}, not a counter", name, metric.kind())
+        };
+    }
+
+    pub fn gauge(&self, name: &str, help: &str) -> Arc<Gauge> {
+        return match self.register(name, help, || Metric::Gauge(Arc::default())) {
+            Metric::Gauge(gauge) => gauge,
+            metric => panic!("metric {} is a {}, not a gauge", name, metric.kind())
+        };
+    }
+
+    /// The bounds are only used if the histogram isn't already registered.
+    pub fn histogram(&self, name: &str, help: &str, bounds: &[f64]) -> Arc<Histogram> {
+        return match self.register(name, help, || Metric::Histogram(Arc::new(Histogram::new(bounds)))) {
+            Metric::Histogram(histogram) => histogram,
+            metric => panic!("metric {} is a {}, not a histogram", name, metric.kind())
+        };
+    }
+
+    /// Every metric's current value.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let metrics = self.metrics.lock().unwrap();
+        let values = metrics.iter().map(|(name, registered)| {
+            let value = match &registered.metric {
+                Metric::Counter(counter) => MetricValue::Counter(counter.get()),
+                Metric::Gauge(gauge) => MetricValue::Gauge(gauge.get()),
+                Metric::Histogram(histogram) => MetricValue::Histogram(histogram.snapshot())
+            };
+            (name.clone(), value)
+        }).collect();
+        return MetricsSnapshot { taken_at: Instant::now(), values };
+    }
+
+    /// Every metric in Prometheus' text exposition format, sorted by name.
+    pub fn prometheus_text(&self) -> String {
+        let mut text = String::new();
+        let metrics = self.metrics.lock().unwrap();
+        for (name, registered) in metrics.iter() {
+            if !registered.help.is_empty() {
+                let help = registered.help.replace('\\', "\\\\").replace('\n', "\\n");
+                let _ = writeln!(text, "# HELP {} {}", name, help);
+            }
+            let _ = writeln!(text, "# TYPE {} {}", name, registered.metric.kind());
+            match &registered.metric {
+                Metric::Counter(counter) => {
+                    let _ = writeln!(text, "{} {}", name, counter.get());
+                },
+                Metric::Gauge(gauge) => write_value(&mut text, name, gauge.get()),
+                Metric::Histogram(histogram) => {
+                    let snapshot = histogram.snapshot();
+                    for (bound, count) in snapshot.buckets.iter() {
+                        let bound = match *bound == f64::INFINITY {
+                            true => "+Inf".to_string(),
+                            false => bound.to_string()
+                        };
+                        let _ = writeln!(text, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+                    }
+                    write_value(&mut text, &format!("{}_sum", name), snapshot.sum);
+                    let _ = writeln!(text, "{}_count {}", name, snapshot.count);
+                }
+            }
+        }
+        return text;
+    }
+}
+
+impl Default for MetricsRegistry {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetricValue {
+    Counter(u64),
+    Gauge(f64),
+    Histogram(HistogramSnapshot)
+}
+
+/// Every metric's value at one point in time, such as for the debug overlay to draw.
+#[derive(Clone, Debug)]
+pub struct MetricsSnapshot {
+    pub taken_at: Instant,
+    pub values: BTreeMap<String, MetricValue>
+}
+
+impl MetricsSnapshot {
+    pub fn counter(&self, name: &str) -> Option<u64> {
+        return match self.values.get(name) {
+            Some(MetricValue::Counter(value)) => Some(*value),
+            _ => None
+        };
+    }
+
+    pub fn gauge(&self, name: &str) -> Option<f64> {
+        return match self.values.get(name) {
+            Some(MetricValue::Gauge(value)) => Some(*value),
+            _ => None
+        };
+    }
+
+    pub fn histogram(&self, name: &str) -> Option<&HistogramSnapshot> {
+        return match self.values.get(name) {
+            Some(MetricValue::Histogram(value)) => Some(value),
+            _ => None
+        };
+    }
+
+    /// How fast a counter went up per second since an earlier snapshot, such as jobs per second.
+    /// ```
+    /// # use shared::engine::metrics::MetricsRegistry;
+    /// # use std::{thread, time::Duration};
+    /// let registry = MetricsRegistry::new();
+    /// let jobs = registry.counter("jobs_executed_total", "Jobs run by job threads.");
+    /// let before = registry.snapshot();
+    /// thread::sleep(Duration::from_millis(10));
+    /// jobs.add(5);
+    /// let rate = registry.snapshot().rate(&before, "jobs_executed_total").unwrap();
+    /// assert!(rate > 0.0 && rate <= 500.0);
+    /// ```
+    pub fn rate(&self, earlier: &MetricsSnapshot, name: &str) -> Option<f64> {
+        let elapsed = self.taken_at.saturating_duration_since(earlier.taken_at).as_secs_f64();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        let increase = self.counter(name)?.saturating_sub(earlier.counter(name).unwrap_or(0));
+        return Some(increase as f64 / elapsed);
+    }
+}
+
+/// The registry the engine's own metrics are in, shared by the whole process.
+pub fn global_registry() -> &'static Arc<MetricsRegistry> {
+    static REGISTRY: OnceLock<Arc<MetricsRegistry>> = OnceLock::new();
+    return REGISTRY.get_or_init(|| Arc::new(MetricsRegistry::new()));
+}
\ No newline at end of file
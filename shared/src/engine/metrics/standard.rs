@@ -0,0 +1,36 @@
+use std::sync::{Arc, OnceLock};
+
+use super::registry::{global_registry, Counter, Gauge, Histogram, DURATION_BUCKETS};
+
+/// The metrics the engine keeps itself, in the global registry.
+pub struct EngineMetrics {
+    /// Chunks loaded across every world.
+    pub chunks_loaded: Arc<Gauge>,
+    /// Jobs finished by every job thread. The debug overlay shows its rate as jobs per second.
+    pub jobs_executed: Arc<Counter>,
+    /// Time taken by each universe tick.
+    pub tick_duration: Arc<Histogram>,
+    /// Bytes every connection has written to its sockets, including framing.
+    pub bytes_sent: Arc<Counter>,
+    pub bytes_received: Arc<Counter>
+}
+
+/// The engine's own metrics, registered in the global registry the first time they're used.
+/// ```
+/// # use shared::engine::metrics::{registry::global_registry, standard::engine_metrics};
+/// engine_metrics().bytes_sent.add(64);
+/// assert!(global_registry().prometheus_text().contains("# TYPE net_sent_bytes_total counter\nnet_sent_bytes_total 64\n"));
+/// ```
+pub fn engine_metrics() -> &'static EngineMetrics {
+    static METRICS: OnceLock<EngineMetrics> = OnceLock::new();
+    return METRICS.get_or_init(|| {
+        let registry = global_registry();
+        EngineMetrics {
+            chunks_loaded: registry.gauge("world_chunks_loaded", "Chunks loaded across every world."),
+            jobs_executed: registry.counter("jobs_executed_total", "Jobs finished by every job thread."),
+            tick_duration: registry.histogram("tick_duration_seconds", "Time taken by each universe tick.", &DURATION_BUCKETS),
+            bytes_sent: registry.counter("net_sent_bytes_total", "Bytes written to sockets by every connection."),
+            bytes_received: registry.counter("net_received_bytes_total", "Bytes read from sockets by every connection.")
+        }
+    });
+}
\ No newline at end of file
@@ -1 +1,34 @@
-pub mod job;
\ No newline at end of file
+pub mod job;
+pub mod config;
+pub mod asset;
+pub mod progress;
+pub mod profile;
+pub mod bench;
+pub mod memory;
+pub mod metrics;
+pub mod crash;
+pub mod event;
+pub mod lod;
+pub mod math;
+pub mod block;
+pub mod item;
+pub mod recipe;
+pub mod light;
+pub mod protection;
+pub mod version;
+pub mod world;
+pub mod mesh;
+pub mod save;
+pub mod compression;
+pub mod worldgen;
+pub mod fluid;
+pub mod universe;
+pub mod tick;
+pub mod command;
+pub mod script;
+pub mod path;
+pub mod ai;
+pub mod spawning;
+pub mod physics;
+pub mod entity;
+pub mod net;
\ No newline at end of file
@@ -0,0 +1,355 @@
+use std::{
+    collections::HashMap,
+    fmt, io,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    time::{Duration, Instant}
+};
+
+use crate::{engine::job::system::JobSystem, packet};
+
+use super::{
+    encryption::ServerIdentity,
+    packet as codec,
+    transport::{Channel, Connection, ConnectionId, Transport}
+};
+
+/// Wrong passwords an address can send before its connections are closed and its logins refused.
+pub const MAX_LOGIN_ATTEMPTS: u32 = 3;
+
+/// How long an address is refused after too many wrong passwords, by default. Its wrong passwords are forgotten
+/// once this long passes without another.
+pub const DEFAULT_LOGIN_LOCKOUT: Duration = Duration::from_secs(5 * 60);
+
+packet! {
+    /// First packet an admin client sends.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct AdminLogin = 60 {
+        pub password: String
+    }
+}
+
+packet! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct AdminLoginResult = 61 {
+        pub accepted: bool
+    }
+}
+
+packet! {
+    /// A console command, answered by an AdminResponse with the same request number.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct AdminRequest = 62 {
+        pub request: u32,
+        pub command: String
+    }
+}
+
+packet! {
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct AdminResponse = 63 {
+        pub request: u32,
+        pub success: bool,
+        /// What the command printed, or why it failed.
+        pub output: String
+    }
+}
+
+/// A command for the server console, typed locally or sent by an admin client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AdminCommand {
+    /// List the players online.
+    List,
+    Kick {
+        player: String,
+        reason: Option<String>
+    },
+    /// Save every loaded region now.
+    SaveAll,
+    /// Write a backup of the save under a name.
+    Backup {
+        name: String
+    },
+    /// Replace the save with a named backup.
+    Restore {
+        name: String
+    },
+    /// Save and shut the server down.
+    Stop
+}
+
+impl AdminCommand {
+    /// Help text listing every command.
+    pub const HELP: &'static str = "commands: list, kick <player> [reason], save-all, backup <name>, restore <name>, stop";
+
+    /// Parse a command as typed, with or without a leading slash.
+    /// ```
+    /// # use shared::engine::net::admin::AdminCommand;
+    /// assert_eq!(AdminCommand::parse("/list"), Ok(AdminCommand::List));
+    /// assert_eq!(AdminCommand::parse("kick steve too  many creepers"),
+    ///     Ok(AdminCommand::Kick { player: "steve".to_string(), reason: Some("too many creepers".to_string()) }));
+    /// assert!(AdminCommand::parse("kick").is_err());
+    /// assert_eq!(AdminCommand::parse("backup nightly"), Ok(AdminCommand::Backup { name: "nightly".to_string() }));
+    /// assert!(AdminCommand::parse("restore").is_err());
+    /// assert!(AdminCommand::parse("op steve").is_err());
+    /// ```
+    pub fn parse(text: &str) -> Result<AdminCommand, String> {
+        let text = text.trim();
+        let text = text.strip_prefix('/').unwrap_or(text);
+        let mut words = text.split_whitespace();
+        let command = match words.next() {
+            Some("list") => AdminCommand::List,
+            Some("kick") => {
+                let Some(player) = words.next() else {
+                    return Err("usage: kick <player> [reason]".to_string());
+                };
+                let reason: Vec<&str> = words.by_ref().collect();
+                AdminCommand::Kick { player: player.to_string(), reason: (!reason.is_empty()).then(|| reason.join(" ")) }
+            },
+            Some("save-all") => AdminCommand::SaveAll,
+            Some(command @ ("backup" | "restore")) => {
+                let Some(name) = words.next() else {
+                    return Err(format!("usage: {} <name>", command));
+                };
+                let name = name.to_string();
+                if command == "backup" { AdminCommand::Backup { name } } else { AdminCommand::Restore { name } }
+            },
+            Some("stop") => AdminCommand::Stop,
+            Some(unknown) => return Err(format!("unknown command {}, {}", unknown, AdminCommand::HELP)),
+            None => return Err(AdminCommand::HELP.to_string())
+        };
+        if words.next().is_some() {
+            return Err(format!("too many arguments, {}", AdminCommand::HELP));
+        }
+        return Ok(command);
+    }
+}
+
+impl fmt::Display for AdminCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            AdminCommand::List => write!(f, "list"),
+            AdminCommand::Kick { player, reason: Some(reason) } => write!(f, "kick {} {}", player, reason),
+            AdminCommand::Kick { player, reason: None } => write!(f, "kick {}", player),
+            AdminCommand::SaveAll => write!(f, "save-all"),
+            AdminCommand::Backup { name } => write!(f, "backup {}", name),
+            AdminCommand::Restore { name } => write!(f, "restore {}", name),
+            AdminCommand::Stop => write!(f, "stop")
+        };
+    }
+}
+
+/// What runs console commands on the server, whether typed into its terminal or sent remotely.
+pub trait CommandConsole {
+    /// Run a command, returning its output or why it failed.
+    fn execute(&mut self, command: AdminCommand) -> Result<String, String>;
+
+    /// Run a command line as typed. Parses it as an AdminCommand by default; consoles backed by a
+    /// CommandDispatcher override it to run every registered command.
+    fn execute_line(&mut self, line: &str) -> Result<String, String> {
+        return AdminCommand::parse(line).and_then(|command| self.execute(command));
+    }
+}
+
+/// Compare passwords in time that only depends on their lengths, so timing doesn't give away how much matched.
+fn passwords_match(expected: &str, given: &str) -> bool {
+    let (expected, given) = (expected.as_bytes(), given.as_bytes());
+    let difference = expected.iter().zip(given.iter()).fold(0u8, |difference, (a, b)| difference | (a ^ b));
+    return difference == 0 && expected.len() == given.len();
+}
+
+#[derive(Default)]
+struct AdminSession {
+    authenticated: bool
+}
+
+/// Wrong passwords sent from an address, over every connection it made.
+struct LoginFailures {
+    count: u32,
+    last: Instant
+}
+
+/// Accepts admin clients on their own port and runs their commands on the server's console once they log in
+/// with the admin password. Connections are encrypted, so the password and commands can't be read off the network.
+pub struct AdminServer {
+    transport: Transport,
+    password: String,
+    sessions: HashMap<ConnectionId, AdminSession>,
+    /// Kept by address rather than session, so reconnecting doesn't give another set of guesses.
+    failures: HashMap<IpAddr, LoginFailures>,
+    lockout: Duration,
+    /// Connections to close once their last reply is sent.
+    closing: Vec<ConnectionId>
+}
+
+impl AdminServer {
+    /// Listen for admin clients, proving to them that the server holds the identity.
+    /// An empty password is refused, as it would let anyone run commands.
+    pub fn bind(address: impl ToSocketAddrs, password: &str, identity: ServerIdentity) -> io::Result<AdminServer> {
+        if password.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Admin password must not be empty"));
+        }
+        return Ok(AdminServer {
+            transport: Transport::bind(address, false)?.with_encryption(identity),
+            password: password.to_string(),
+            sessions: HashMap::new(),
+            failures: HashMap::new(),
+            lockout: DEFAULT_LOGIN_LOCKOUT,
+            closing: Vec::new()
+        });
+    }
+
+    /// Refuse an address for this long after too many wrong passwords.
+    pub fn with_lockout(mut self, lockout: Duration) -> AdminServer {
+        self.lockout = lockout;
+        return self;
+    }
+
+    /// Whether an address sent too many wrong passwords lately to be let in.
+    pub fn is_locked_out(&self, address: IpAddr) -> bool {
+        return self.failures.get(&address).is_some_and(|failures| failures.count >= MAX_LOGIN_ATTEMPTS && failures.last.elapsed() < self.lockout);
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        return self.transport.local_addr();
+    }
+
+    /// Admin clients connected, logged in or not.
+    pub fn session_count(&self) -> usize {
+        return self.transport.len();
+    }
+
+    /// Send replies, accept clients and run the commands of logged in ones. Called every tick on the main thread,
+    /// as commands such as saving need the whole server.
+    /// A session being closed is read no further. Packets after the one that closed it, and any arriving before
+    /// its last reply goes out, are dropped unanswered.
+    pub fn pump(&mut self, jobs: &JobSystem, console: &mut dyn CommandConsole) {
+        self.transport.pump(jobs);
+        // Closed before reading, so nothing they sent since is handled.
+        for id in self.closing.drain(..) {
+            self.transport.disconnect(id);
+        }
+        let lockout = self.lockout;
+        self.failures.retain(|_, failures| failures.last.elapsed() < lockout);
+        let ids = self.transport.connection_ids();
+        self.sessions.retain(|id, _| ids.contains(id));
+        for id in ids {
+            let Some(connection) = self.transport.connection(id) else {
+                continue;
+            };
+            let address = connection.peer_addr().ip();
+            for packet in connection.receive() {
+                if let Ok(login) = codec::decode::<AdminLogin>(&packet) {
+                    // A locked out address is turned away without its password being checked, so it can't keep guessing.
+                    let accepted = !self.is_locked_out(address) && passwords_match(&self.password, &login.password);
+                    self.sessions.entry(id).or_default().authenticated = accepted;
+                    connection.send(Channel::Reliable, codec::encode(&AdminLoginResult { accepted }));
+                    if accepted {
+                        self.failures.remove(&address);
+                        continue;
+                    }
+                    let failures = self.failures.entry(address).or_insert(LoginFailures { count: 0, last: Instant::now() });
+                    failures.count += 1;
+                    failures.last = Instant::now();
+                    if failures.count >= MAX_LOGIN_ATTEMPTS {
+                        self.closing.push(id);
+                        break;
+                    }
+                    continue;
+                }
+                let session = self.sessions.entry(id).or_default();
+                // Anything else before logging in, or anything that isn't a request, ends the session.
+                let request = match codec::decode::<AdminRequest>(&packet) {
+                    Ok(request) if session.authenticated => request,
+                    _ => {
+                        self.closing.push(id);
+                        break;
+                    }
+                };
+                let result = console.execute_line(&request.command);
+                let (success, output) = match result {
+                    Ok(output) => (true, output),
+                    Err(error) => (false, error)
+                };
+                connection.send(Channel::Reliable, codec::encode(&AdminResponse { request: request.request, success, output }));
+            }
+        }
+    }
+}
+
+/// Connection to a server's admin port.
+pub struct AdminClient {
+    connection: Connection,
+    authenticated: Option<bool>,
+    next_request: u32,
+    responses: Vec<AdminResponse>
+}
+
+impl AdminClient {
+    /// Connect and send the password once the connection is encrypted. Whether it was accepted is known after
+    /// a few pumps. With the server's public key, the connection fails rather than sending the password to
+    /// any other server.
+    pub fn connect(address: impl ToSocketAddrs, password: &str, server_key: Option<[u8; 32]>) -> io::Result<AdminClient> {
+        let connection = Connection::connect_encrypted(address, server_key)?;
+        let mut client = AdminClient { connection, authenticated: None, next_request: 1, responses: Vec::new() };
+        client.login(password);
+        return Ok(client);
+    }
+
+    /// Try another password, after the server turned one down.
+    pub fn login(&mut self, password: &str) {
+        self.authenticated = None;
+        self.connection.send(Channel::Reliable, codec::encode(&AdminLogin { password: password.to_string() }));
+    }
+
+    /// The server's public key once the connection is encrypted, for pinning it next time.
+    pub fn server_key(&self) -> Option<[u8; 32]> {
+        return self.connection.server_key();
+    }
+
+    /// Whether the server accepted the password, once it has answered.
+    pub fn is_authenticated(&self) -> Option<bool> {
+        return self.authenticated;
+    }
+
+    /// Send a command, returning the request number its response will have.
+    pub fn send(&mut self, command: &str) -> u32 {
+        let request = self.next_request;
+        self.next_request += 1;
+        self.connection.send(Channel::Reliable, codec::encode(&AdminRequest { request, command: command.to_string() }));
+        return request;
+    }
+
+    /// Send and receive, failing once the server closes the connection.
+    pub fn pump(&mut self) -> io::Result<()> {
+        let result = self.connection.pump();
+        for packet in self.connection.receive() {
+            if let Ok(login) = codec::decode::<AdminLoginResult>(&packet) {
+                self.authenticated = Some(login.accepted);
+            } else if let Ok(response) = codec::decode::<AdminResponse>(&packet) {
+                self.responses.push(response);
+            }
+        }
+        return result;
+    }
+
+    /// Responses received since the last call.
+    pub fn responses(&mut self) -> Vec<AdminResponse> {
+        return std::mem::take(&mut self.responses);
+    }
+
+    /// Send a command and wait for its response, as a command line admin tool would.
+    pub fn run(&mut self, command: &str, timeout: Duration) -> io::Result<AdminResponse> {
+        let request = self.send(command);
+        let start = Instant::now();
+        loop {
+            self.pump()?;
+            if let Some(index) = self.responses.iter().position(|response| response.request == request) {
+                return Ok(self.responses.remove(index));
+            }
+            if start.elapsed() > timeout {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Server didn't answer the command"));
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,74 @@
+use crate::engine::math::rng::{hash_str, mix};
+
+/// Shortest and longest player names, in characters.
+pub const NAME_LENGTH: (usize, usize) = (3, 16);
+
+/// Checks who a logging in player is, giving them the UUID saves and protection regions know them by.
+/// Called during the handshake, so it should answer quickly or cache.
+pub trait Authenticator: Send + Sync {
+    /// The player's UUID if the token proves they're who their name says, or why not, shown to the player.
+    fn authenticate(&self, name: &str, token: &str) -> Result<u128, String>;
+}
+
+/// Whether a name is 3 to 16 ASCII letters, digits and underscores.
+/// ```
+/// # use shared::engine::net::auth;
+/// assert!(auth::is_valid_name("Steve_2"));
+/// assert!(!auth::is_valid_name("no"));
+/// assert!(!auth::is_valid_name("§cSteve"));
+/// ```
+pub fn is_valid_name(name: &str) -> bool {
+    return (NAME_LENGTH.0..=NAME_LENGTH.1).contains(&name.len()) && name.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'_');
+}
+
+/// Accepts any valid name without checking tokens, for servers without accounts such as LAN games.
+/// Anyone can join under any name, so the UUID only stays the same as long as the name does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OfflineAuthenticator;
+
+impl Authenticator for OfflineAuthenticator {
+    fn authenticate(&self, name: &str, _token: &str) -> Result<u128, String> {
+        if !is_valid_name(name) {
+            return Err(format!("\"{}\" isn't a valid name", name));
+        }
+        return Ok(offline_uuid(name));
+    }
+}
+
+/// UUID of a player in offline mode, the same for the same name on every server and platform.
+/// Marked as a version 3 name based UUID.
+/// ```
+/// # use shared::engine::net::auth;
+/// assert_eq!(auth::offline_uuid("steve"), auth::offline_uuid("steve"));
+/// assert_ne!(auth::offline_uuid("steve"), auth::offline_uuid("Steve"));
+/// assert_eq!(auth::format_uuid(auth::offline_uuid("steve")).as_bytes()[14], b'3');
+/// ```
+pub fn offline_uuid(name: &str) -> u128 {
+    let hash = hash_str(&format!("OfflinePlayer:{}", name));
+    let uuid = ((mix(hash, 1) as u128) << 64) | mix(hash, 2) as u128;
+    let version = (uuid & !(0xf << 76)) | (0x3 << 76);
+    return (version & !(0x3 << 62)) | (0x2 << 62);
+}
+
+/// UUID in the usual 8-4-4-4-12 hex form.
+/// ```
+/// # use shared::engine::net::auth;
+/// let uuid = 0x123e4567_e89b_12d3_a456_426614174000;
+/// assert_eq!(auth::format_uuid(uuid), "123e4567-e89b-12d3-a456-426614174000");
+/// assert_eq!(auth::parse_uuid("123e4567-e89b-12d3-a456-426614174000"), Some(uuid));
+/// assert_eq!(auth::parse_uuid("123e4567e89b12d3a456426614174000"), Some(uuid));
+/// assert_eq!(auth::parse_uuid("steve"), None);
+/// ```
+pub fn format_uuid(uuid: u128) -> String {
+    let hex = format!("{:032x}", uuid);
+    return format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32]);
+}
+
+/// Parse a UUID with or without dashes.
+pub fn parse_uuid(text: &str) -> Option<u128> {
+    let hex: String = text.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    return u128::from_str_radix(&hex, 16).ok();
+}
\ No newline at end of file
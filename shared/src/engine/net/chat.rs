@@ -0,0 +1,437 @@
+use std::{collections::{HashMap, VecDeque}, io};
+
+use crate::{
+    engine::{command::dispatcher::{ArgumentInfo, CommandInfo}, entity::replication::ClientId},
+    packet, wire_struct
+};
+
+use super::packet::{self as codec, PacketReader, PacketWriter, Wire};
+
+/// Longest chat message a player can send, in characters. Longer messages are cut off.
+pub const MAX_CHAT_LENGTH: usize = 256;
+
+/// Lines a client's chat history keeps by default.
+pub const DEFAULT_CHAT_HISTORY: usize = 100;
+
+/// Starts a formatting code. The character after it picks a color, 0 to 9 and a to f, or a style.
+pub const FORMAT_CODE: char = '§';
+
+/// The 16 chat colors, in formatting code order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChatColor {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White
+}
+
+const COLORS: [ChatColor; 16] = [
+    ChatColor::Black, ChatColor::DarkBlue, ChatColor::DarkGreen, ChatColor::DarkAqua,
+    ChatColor::DarkRed, ChatColor::DarkPurple, ChatColor::Gold, ChatColor::Gray,
+    ChatColor::DarkGray, ChatColor::Blue, ChatColor::Green, ChatColor::Aqua,
+    ChatColor::Red, ChatColor::LightPurple, ChatColor::Yellow, ChatColor::White
+];
+
+impl ChatColor {
+    /// Color for a formatting code character.
+    pub fn from_code(code: char) -> Option<ChatColor> {
+        return code.to_digit(16).map(|index| COLORS[index as usize]);
+    }
+
+    /// Formatting code character for the color.
+    pub fn code(self) -> char {
+        return char::from_digit(self as u32, 16).unwrap();
+    }
+
+    /// sRGB color to draw text in.
+    pub fn rgb(self) -> [u8; 3] {
+        return match self {
+            ChatColor::Black => [0x00, 0x00, 0x00],
+            ChatColor::DarkBlue => [0x00, 0x00, 0xaa],
+            ChatColor::DarkGreen => [0x00, 0xaa, 0x00],
+            ChatColor::DarkAqua => [0x00, 0xaa, 0xaa],
+            ChatColor::DarkRed => [0xaa, 0x00, 0x00],
+            ChatColor::DarkPurple => [0xaa, 0x00, 0xaa],
+            ChatColor::Gold => [0xff, 0xaa, 0x00],
+            ChatColor::Gray => [0xaa, 0xaa, 0xaa],
+            ChatColor::DarkGray => [0x55, 0x55, 0x55],
+            ChatColor::Blue => [0x55, 0x55, 0xff],
+            ChatColor::Green => [0x55, 0xff, 0x55],
+            ChatColor::Aqua => [0x55, 0xff, 0xff],
+            ChatColor::Red => [0xff, 0x55, 0x55],
+            ChatColor::LightPurple => [0xff, 0x55, 0xff],
+            ChatColor::Yellow => [0xff, 0xff, 0x55],
+            ChatColor::White => [0xff, 0xff, 0xff]
+        };
+    }
+}
+
+/// A run of text drawn the same way.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TextSpan {
+    pub text: String,
+    /// None for the UI's default color.
+    pub color: Option<ChatColor>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool
+}
+
+/// Split text into spans by its formatting codes. A color code resets the styles, as does §r, which also resets the color.
+/// §l is bold, §o italic and §n underline. Unknown codes are dropped.
+/// ```
+/// # use shared::engine::net::chat::{self, ChatColor};
+/// let spans = chat::parse_formatting("§6Gold §lbold§r plain");
+/// assert_eq!(spans.len(), 3);
+/// assert_eq!((spans[0].text.as_str(), spans[0].color), ("Gold ", Some(ChatColor::Gold)));
+/// assert!(spans[1].bold && spans[1].color == Some(ChatColor::Gold));
+/// assert_eq!((spans[2].text.as_str(), spans[2].color, spans[2].bold), (" plain", None, false));
+/// ```
+pub fn parse_formatting(text: &str) -> Vec<TextSpan> {
+    let mut spans = Vec::new();
+    let mut current = TextSpan::default();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != FORMAT_CODE {
+            current.text.push(c);
+            continue;
+        }
+        let Some(code) = chars.next() else {
+            break;
+        };
+        let mut next = TextSpan { text: String::new(), ..current.clone() };
+        match code.to_ascii_lowercase() {
+            'l' => next.bold = true,
+            'o' => next.italic = true,
+            'n' => next.underline = true,
+            'r' => next = TextSpan::default(),
+            code => match ChatColor::from_code(code) {
+                Some(color) => next = TextSpan { color: Some(color), ..TextSpan::default() },
+                None => continue
+            }
+        }
+        if !current.text.is_empty() {
+            spans.push(current);
+        }
+        current = next;
+    }
+    if !current.text.is_empty() {
+        spans.push(current);
+    }
+    return spans;
+}
+
+/// Text with its formatting codes removed.
+/// ```
+/// # use shared::engine::net::chat;
+/// assert_eq!(chat::strip_formatting("§cRed§r and §zplain"), "Red and plain");
+/// ```
+pub fn strip_formatting(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == FORMAT_CODE {
+            chars.next();
+        } else {
+            stripped.push(c);
+        }
+    }
+    return stripped;
+}
+
+/// Where a message is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChatKind {
+    /// Said by a player, shown in chat under their name.
+    Player,
+    /// From the server, such as join messages and command output, shown in chat.
+    System,
+    /// Shown briefly above the hotbar instead of in chat, replacing the previous one.
+    ActionBar
+}
+
+impl Wire for ChatKind {
+    fn write(&self, writer: &mut PacketWriter) {
+        writer.write_u8(match self {
+            ChatKind::Player => 0,
+            ChatKind::System => 1,
+            ChatKind::ActionBar => 2
+        });
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<ChatKind> {
+        return match reader.read_u8()? {
+            0 => Ok(ChatKind::Player),
+            1 => Ok(ChatKind::System),
+            2 => Ok(ChatKind::ActionBar),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown chat kind"))
+        };
+    }
+}
+
+packet! {
+    /// Something a player typed, either chat or a command starting with a slash.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct ChatMessage = 50 {
+        pub text: String
+    }
+}
+
+packet! {
+    /// A message for the client to show.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct ChatBroadcast = 51 {
+        pub kind: ChatKind,
+        /// Name of the player who said it, for player messages.
+        pub sender: Option<String>,
+        /// Text with formatting codes.
+        pub text: String
+    }
+}
+
+packet! {
+    /// A partly typed command line, sent as the player presses tab. Answered by TabCompletions with the same request number.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct TabComplete = 52 {
+        pub request: u32,
+        /// The line without its slash, up to the cursor.
+        pub text: String
+    }
+}
+
+packet! {
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct TabCompletions = 53 {
+        pub request: u32,
+        /// Words that could replace the last word typed.
+        pub suggestions: Vec<String>
+    }
+}
+
+wire_struct!(ArgumentInfo { name, kind, optional });
+wire_struct!(CommandInfo { name, aliases, description, usage, arguments });
+
+packet! {
+    /// The commands a player may run, sent when they join and whenever that changes, such as being made an operator.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct CommandList = 54 {
+        pub commands: Vec<CommandInfo>
+    }
+}
+
+/// Routes chat on the server between the players in it.
+pub struct ChatRouter {
+    players: HashMap<ClientId, String>,
+    commands: VecDeque<(ClientId, String)>
+}
+
+impl ChatRouter {
+    pub fn new() -> ChatRouter {
+        return ChatRouter { players: HashMap::new(), commands: VecDeque::new() };
+    }
+
+    /// Add a player who can send and receive chat, announcing it to everyone.
+    pub fn join(&mut self, client: ClientId, name: &str) -> Vec<(ClientId, Vec<u8>)> {
+        self.players.insert(client, name.to_string());
+        return self.broadcast(ChatKind::System, &format!("{}e{} joined the game", FORMAT_CODE, name));
+    }
+
+    /// Remove a player, announcing it to everyone left.
+    pub fn leave(&mut self, client: ClientId) -> Vec<(ClientId, Vec<u8>)> {
+        return match self.players.remove(&client) {
+            Some(name) => self.broadcast(ChatKind::System, &format!("{}e{} left the game", FORMAT_CODE, name)),
+            None => Vec::new()
+        };
+    }
+
+    pub fn player_count(&self) -> usize {
+        return self.players.len();
+    }
+
+    /// Handle an encoded ChatMessage from a player, returning the packets to send and who to send each to.
+    /// Chat goes to every player with the sender's formatting codes removed. Commands are queued for take_commands()
+    /// instead. Messages from clients that haven't joined are dropped.
+    /// ```
+    /// # use shared::engine::net::{chat::{ChatBroadcast, ChatMessage, ChatRouter}, packet};
+    /// let mut router = ChatRouter::new();
+    /// router.join(1, "steve");
+    /// router.join(2, "alex");
+    /// let routed = router.handle(1, &packet::encode(&ChatMessage { text: "§chello".to_string() })).unwrap();
+    /// assert_eq!(routed.len(), 2);
+    /// let broadcast = packet::decode::<ChatBroadcast>(&routed[0].1).unwrap();
+    /// assert_eq!((broadcast.sender.as_deref(), broadcast.text.as_str()), (Some("steve"), "hello"));
+    ///
+    /// assert!(router.handle(2, &packet::encode(&ChatMessage { text: "/stop".to_string() })).unwrap().is_empty());
+    /// assert_eq!(router.take_commands(), vec![(2, "stop".to_string())]);
+    /// ```
+    pub fn handle(&mut self, from: ClientId, packet: &[u8]) -> io::Result<Vec<(ClientId, Vec<u8>)>> {
+        let message = codec::decode::<ChatMessage>(packet)?;
+        let Some(name) = self.players.get(&from).cloned() else {
+            return Ok(Vec::new());
+        };
+        let text: String = strip_formatting(&message.text).chars().filter(|c| !c.is_control()).take(MAX_CHAT_LENGTH).collect();
+        let text = text.trim();
+        if let Some(command) = text.strip_prefix('/') {
+            self.commands.push_back((from, command.to_string()));
+            return Ok(Vec::new());
+        }
+        if text.is_empty() {
+            return Ok(Vec::new());
+        }
+        let packet = codec::encode(&ChatBroadcast { kind: ChatKind::Player, sender: Some(name), text: text.to_string() });
+        return Ok(self.recipients().into_iter().map(|client| (client, packet.clone())).collect());
+    }
+
+    /// Commands players typed since the previous call, oldest first, without their slash.
+    pub fn take_commands(&mut self) -> Vec<(ClientId, String)> {
+        return self.commands.drain(..).collect();
+    }
+
+    /// A message from the server to every player.
+    pub fn broadcast(&self, kind: ChatKind, text: &str) -> Vec<(ClientId, Vec<u8>)> {
+        let packet = codec::encode(&ChatBroadcast { kind, sender: None, text: text.to_string() });
+        return self.recipients().into_iter().map(|client| (client, packet.clone())).collect();
+    }
+
+    /// A message from the server to one player, such as command output.
+    pub fn whisper(&self, client: ClientId, kind: ChatKind, text: &str) -> Option<Vec<u8>> {
+        if !self.players.contains_key(&client) {
+            return None;
+        }
+        return Some(codec::encode(&ChatBroadcast { kind, sender: None, text: text.to_string() }));
+    }
+
+    fn recipients(&self) -> Vec<ClientId> {
+        let mut recipients: Vec<ClientId> = self.players.keys().copied().collect();
+        recipients.sort_unstable();
+        return recipients;
+    }
+}
+
+impl Default for ChatRouter {
+    fn default() -> ChatRouter {
+        return ChatRouter::new();
+    }
+}
+
+/// A message in a client's chat history, split into spans ready to draw.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatLine {
+    pub kind: ChatKind,
+    pub sender: Option<String>,
+    pub spans: Vec<TextSpan>
+}
+
+impl ChatLine {
+    /// The line as plain text, with the sender's name in front.
+    pub fn plain_text(&self) -> String {
+        let text: String = self.spans.iter().map(|span| span.text.as_str()).collect();
+        return match &self.sender {
+            Some(sender) => format!("<{}> {}", sender, text),
+            None => text
+        };
+    }
+}
+
+/// The chat a client received, for the UI to draw.
+pub struct ChatHistory {
+    lines: VecDeque<ChatLine>,
+    capacity: usize,
+    action_bar: Option<ChatLine>,
+    /// Bumped on every change, so the UI only rebuilds its text when this changes.
+    version: u64
+}
+
+impl ChatHistory {
+    pub fn new(capacity: usize) -> ChatHistory {
+        return ChatHistory { lines: VecDeque::new(), capacity, action_bar: None, version: 0 };
+    }
+
+    /// Handle an encoded ChatBroadcast from the server. False if the packet isn't one.
+    /// ```
+    /// # use shared::engine::net::chat::{ChatHistory, ChatKind, ChatRouter};
+    /// let mut router = ChatRouter::new();
+    /// let mut history = ChatHistory::new(2);
+    /// for (_, packet) in router.join(1, "steve") {
+    ///     history.handle(&packet);
+    /// }
+    /// for text in ["one", "two", "three"] {
+    ///     history.handle(&router.whisper(1, ChatKind::System, text).unwrap());
+    /// }
+    /// history.handle(&router.whisper(1, ChatKind::ActionBar, "§aSaved").unwrap());
+    /// let lines: Vec<String> = history.lines().map(|line| line.plain_text()).collect();
+    /// assert_eq!(lines, vec!["two", "three"]);
+    /// assert_eq!(history.action_bar().unwrap().plain_text(), "Saved");
+    /// ```
+    pub fn handle(&mut self, packet: &[u8]) -> bool {
+        let Ok(broadcast) = codec::decode::<ChatBroadcast>(packet) else {
+            return false;
+        };
+        self.push(ChatLine { kind: broadcast.kind, sender: broadcast.sender, spans: parse_formatting(&broadcast.text) });
+        return true;
+    }
+
+    /// Add a line, such as one the client made itself. Action bar lines replace the action bar instead.
+    pub fn push(&mut self, line: ChatLine) {
+        self.version += 1;
+        if line.kind == ChatKind::ActionBar {
+            self.action_bar = Some(line);
+            return;
+        }
+        self.lines.push_back(line);
+        while self.lines.len() > self.capacity {
+            self.lines.pop_front();
+        }
+    }
+
+    /// Lines, oldest first.
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &ChatLine> + ExactSizeIterator {
+        return self.lines.iter();
+    }
+
+    /// The newest action bar text. The UI decides how long to show it for.
+    pub fn action_bar(&self) -> Option<&ChatLine> {
+        return self.action_bar.as_ref();
+    }
+
+    pub fn clear_action_bar(&mut self) {
+        if self.action_bar.take().is_some() {
+            self.version += 1;
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        return self.version;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.lines.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.lines.is_empty();
+    }
+}
+
+impl Default for ChatHistory {
+    fn default() -> ChatHistory {
+        return ChatHistory::new(DEFAULT_CHAT_HISTORY);
+    }
+}
+
+/// The packet to send for something the player typed.
+pub fn send(text: &str) -> Vec<u8> {
+    return codec::encode(&ChatMessage { text: text.to_string() });
+}
\ No newline at end of file
@@ -0,0 +1,258 @@
+use std::{fmt, io};
+
+use crate::{
+    engine::{
+        block::BlockId,
+        entity::{kinematics::Transform, Entities},
+        math::coords::BlockPos,
+        world::World
+    },
+    packet
+};
+
+use super::{
+    interaction::BlockUpdate,
+    packet::{self as codec, Packet},
+    prediction::MovementState
+};
+
+packet! {
+    /// Checksum of the blocks the server's updates changed in a player's view during a tick, sent to the player
+    /// after that tick's BlockUpdates whenever there were any.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct TickChecksum = 71 {
+        pub tick: u64,
+        pub blocks: u64
+    }
+}
+
+/// FNV-1a over the bytes of each value written. Unlike std's DefaultHasher, the result never changes between
+/// runs, Rust versions or platforms, so checksums from the server and a client can be compared. Floats are
+/// hashed by their bits, so the smallest difference in a simulation shows up.
+/// ```
+/// # use shared::engine::net::checksum::StateHasher;
+/// let mut a = StateHasher::new();
+/// a.write_u64(1);
+/// a.write_f64(0.1 + 0.2);
+/// let mut b = StateHasher::new();
+/// b.write_u64(1);
+/// b.write_f64(0.3);
+/// assert_ne!(a.finish(), b.finish());
+/// assert_eq!(StateHasher::new().finish(), StateHasher::default().finish());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateHasher {
+    hash: u64
+}
+
+impl StateHasher {
+    pub fn new() -> StateHasher {
+        return StateHasher { hash: 0xcbf29ce484222325 };
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_bytes(&value.to_bits().to_le_bytes());
+    }
+
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_bytes(&value.to_bits().to_le_bytes());
+    }
+
+    pub fn write_block(&mut self, pos: BlockPos, id: Option<BlockId>) {
+        self.write_i32(pos.x);
+        self.write_i32(pos.y);
+        self.write_i32(pos.z);
+        // Unloaded blocks hash differently from every block id.
+        self.write_u64(id.map_or(u64::MAX, |id| id as u64));
+    }
+
+    pub fn finish(&self) -> u64 {
+        return self.hash;
+    }
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        return StateHasher::new();
+    }
+}
+
+/// Checksum of what's now at the blocks changed during a tick, in position order, so the order the changes were
+/// made in doesn't matter but which one was made last does.
+/// ```
+/// # use shared::engine::net::checksum;
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// let (a, b) = (BlockPos::new(1, 2, 3), BlockPos::new(4, 5, 6));
+/// world.set_block(a, 1);
+/// world.set_block(b, 2);
+/// let checksum = checksum::block_checksum(&world, &[a, b, a]);
+/// assert_eq!(checksum, checksum::block_checksum(&world, &[b, a]));
+/// world.set_block(a, 3);
+/// assert_ne!(checksum, checksum::block_checksum(&world, &[b, a]));
+/// ```
+pub fn block_checksum(world: &World, changed: &[BlockPos]) -> u64 {
+    let mut changed = changed.to_vec();
+    changed.sort_by_key(|pos| (pos.x, pos.y, pos.z));
+    changed.dedup();
+    let mut hasher = StateHasher::new();
+    for pos in changed {
+        hasher.write_block(pos, world.get_block(pos));
+    }
+    return hasher.finish();
+}
+
+/// Checksum of where every entity is and how it's turned, in id order.
+/// ```
+/// # use shared::engine::net::checksum;
+/// # use shared::engine::entity::{Entities, kinematics::Transform};
+/// # use shared::engine::math::coords::WorldPos;
+/// let entities = Entities::new();
+/// let empty = checksum::entity_checksum(&entities);
+/// let mob = entities.spawn();
+/// entities.insert(mob, Transform::new(WorldPos::new(1.0, 2.0, 3.0))).unwrap();
+/// let checksum = checksum::entity_checksum(&entities);
+/// assert_ne!(checksum, empty);
+/// entities.insert(mob, Transform::new(WorldPos::new(1.0, 2.0, 3.000001))).unwrap();
+/// assert_ne!(checksum, checksum::entity_checksum(&entities));
+/// ```
+pub fn entity_checksum(entities: &Entities) -> u64 {
+    let storage = entities.storage::<Transform>();
+    let storage = storage.read().unwrap();
+    let mut transforms: Vec<_> = storage.iter().collect();
+    transforms.sort_by_key(|(id, _)| *id);
+    let mut hasher = StateHasher::new();
+    for (id, transform) in transforms {
+        hasher.write_u64(id.to_bits());
+        hasher.write_f64(transform.position.x);
+        hasher.write_f64(transform.position.y);
+        hasher.write_f64(transform.position.z);
+        for part in [transform.rotation.x, transform.rotation.y, transform.rotation.z, transform.rotation.w] {
+            hasher.write_f32(part);
+        }
+    }
+    return hasher.finish();
+}
+
+/// Checksum of a player's simulated movement, compared between the client's prediction and the server.
+pub fn movement_checksum(state: &MovementState) -> u64 {
+    let mut hasher = StateHasher::new();
+    hasher.write_f64(state.position.x);
+    hasher.write_f64(state.position.y);
+    hasher.write_f64(state.position.z);
+    hasher.write_f32(state.velocity.x);
+    hasher.write_f32(state.velocity.y);
+    hasher.write_f32(state.velocity.z);
+    return hasher.finish();
+}
+
+/// Which part of the simulation disagreed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChecksumKind {
+    /// Blocks changed during a server tick.
+    Blocks,
+    /// A player's movement after one of their inputs.
+    Movement
+}
+
+/// The server and a client, or two runs of the same simulation, ended a step in different states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    pub kind: ChecksumKind,
+    /// Server tick for blocks, input sequence for movement.
+    pub step: u64,
+    pub expected: u64,
+    pub actual: u64
+}
+
+impl fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, step) = match self.kind {
+            ChecksumKind::Blocks => ("blocks", "tick"),
+            ChecksumKind::Movement => ("movement", "input")
+        };
+        return write!(f, "{} out of sync at {} {}: expected checksum {:016x}, got {:016x}", kind, step, self.step, self.expected, self.actual);
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
+/// Checks the client's world against the server's TickChecksums. Blocks changed by BlockUpdates are remembered
+/// until the tick's checksum arrives, which the server sends after them on the same channel.
+/// ```
+/// # use shared::engine::net::{checksum::{self, BlockVerifier, TickChecksum}, interaction::{self, BlockUpdate}, packet};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let server = World::new();
+/// let client = World::new();
+/// for world in [&server, &client] {
+///     world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// }
+/// let block = BlockPos::new(1, 1, 1);
+/// server.set_block(block, 4);
+/// let update = packet::encode(&BlockUpdate { block, id: 4 });
+/// let sum = packet::encode(&TickChecksum { tick: 7, blocks: checksum::block_checksum(&server, &[block]) });
+///
+/// let mut verifier = BlockVerifier::new();
+/// interaction::apply_packet(&client, &update).unwrap();
+/// assert_eq!(verifier.handle(&client, &update).unwrap(), None);
+/// assert_eq!(verifier.handle(&client, &sum).unwrap(), Some(Ok(())));
+///
+/// // Something changed the block on the client only.
+/// client.set_block(block, 5);
+/// verifier.handle(&client, &update).unwrap();
+/// let mismatch = verifier.handle(&client, &sum).unwrap().unwrap().unwrap_err();
+/// assert_eq!(mismatch.step, 7);
+/// ```
+pub struct BlockVerifier {
+    changed: Vec<BlockPos>
+}
+
+impl BlockVerifier {
+    pub fn new() -> BlockVerifier {
+        return BlockVerifier { changed: Vec::new() };
+    }
+
+    /// Look at a packet from the server once it's been applied to the world. Returns the result of checking a
+    /// TickChecksum, or None for other packets.
+    pub fn handle(&mut self, world: &World, packet: &[u8]) -> io::Result<Option<Result<(), ChecksumMismatch>>> {
+        let id = codec::packet_id(packet)?;
+        if id == <BlockUpdate as Packet>::ID {
+            self.changed.push(codec::decode::<BlockUpdate>(packet)?.block);
+            return Ok(None);
+        }
+        if id != <TickChecksum as Packet>::ID {
+            return Ok(None);
+        }
+        let checksum = codec::decode::<TickChecksum>(packet)?;
+        let actual = block_checksum(world, &self.changed);
+        self.changed.clear();
+        if actual != checksum.blocks {
+            return Ok(Some(Err(ChecksumMismatch { kind: ChecksumKind::Blocks, step: checksum.tick, expected: checksum.blocks, actual })));
+        }
+        return Ok(Some(Ok(())));
+    }
+}
+
+impl Default for BlockVerifier {
+    fn default() -> Self {
+        return BlockVerifier::new();
+    }
+}
\ No newline at end of file
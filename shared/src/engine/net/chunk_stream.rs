@@ -0,0 +1,224 @@
+use std::{collections::{HashMap, HashSet}, io};
+
+use crate::{
+    engine::{
+        compression::Compression,
+        math::coords::ChunkPos,
+        save::chunk::{decode_chunk, encode_chunk},
+        world::{chunk::Chunk, container::World}
+    },
+    packet
+};
+
+use super::{packet::{self as codec, Packet}, sign};
+
+/// Bytes of chunk data sent to a client per tick by default. At 20 ticks per second, 5 MB/s.
+pub const DEFAULT_CHUNK_BUDGET: usize = 256 * 1024;
+
+/// Chunks sent to a client without an acknowledgement before streaming pauses, so a client that can't keep up
+/// isn't buried under chunks queued in its connection.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 64;
+
+packet! {
+    /// Blocks of a chunk, as the save encoding compressed with the network codec.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct ChunkData = 20 {
+        pub pos: ChunkPos,
+        pub data: Vec<u8>
+    }
+}
+
+packet! {
+    /// Sent by the client once it has decoded a chunk, letting the server send more.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct ChunkAck = 21 {
+        pub pos: ChunkPos
+    }
+}
+
+packet! {
+    /// Tells the client to drop a chunk that's now out of range.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct UnloadChunk = 22 {
+        pub pos: ChunkPos
+    }
+}
+
+impl ChunkData {
+    pub fn new(chunk: &Chunk) -> io::Result<ChunkData> {
+        return Ok(ChunkData { pos: chunk.pos(), data: Compression::NETWORK.compress_tagged(&encode_chunk(chunk))? });
+    }
+
+    /// Decompress and decode the chunk. Corrupt data is an error rather than a panic.
+    pub fn chunk(&self) -> io::Result<Chunk> {
+        return decode_chunk(self.pos, &Compression::decompress_tagged(&self.data)?);
+    }
+}
+
+/// Sends one client the chunks around its player, nearest first, within a per-tick bandwidth budget.
+/// Chunks count as in flight from when they're sent until the client acknowledges them.
+pub struct ChunkStreamer {
+    center: ChunkPos,
+    radius: i32,
+    budget: usize,
+    max_in_flight: usize,
+    /// Every chunk sent and not unloaded, and whether the client has acknowledged it.
+    sent: HashMap<ChunkPos, bool>
+}
+
+impl ChunkStreamer {
+    pub fn new(center: ChunkPos, radius: i32) -> ChunkStreamer {
+        debug_assert!(radius >= 0, "Stream radius must not be negative");
+        return ChunkStreamer { center, radius, budget: DEFAULT_CHUNK_BUDGET, max_in_flight: DEFAULT_MAX_IN_FLIGHT, sent: HashMap::new() };
+    }
+
+    /// Bytes of chunk data to send per tick. A chunk bigger than the whole budget is still sent, on its own.
+    pub fn with_budget(mut self, budget: usize) -> ChunkStreamer {
+        self.budget = budget;
+        return self;
+    }
+
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> ChunkStreamer {
+        self.max_in_flight = max_in_flight;
+        return self;
+    }
+
+    /// Move the chunk streaming is centered on, as the player moves.
+    pub fn set_center(&mut self, center: ChunkPos) {
+        self.center = center;
+    }
+
+    pub fn set_radius(&mut self, radius: i32) {
+        debug_assert!(radius >= 0, "Stream radius must not be negative");
+        self.radius = radius;
+    }
+
+    /// Note that the client decoded a chunk. Acknowledgements for chunks that were since unloaded are ignored.
+    pub fn acknowledge(&mut self, pos: ChunkPos) {
+        if let Some(acknowledged) = self.sent.get_mut(&pos) {
+            *acknowledged = true;
+        }
+    }
+
+    /// Handle an encoded ChunkAck from the client. False if the packet isn't one.
+    pub fn handle(&mut self, packet: &[u8]) -> bool {
+        return match codec::decode::<ChunkAck>(packet) {
+            Ok(ack) => {
+                self.acknowledge(ack.pos);
+                true
+            },
+            Err(_) => false
+        };
+    }
+
+    pub fn in_flight(&self) -> usize {
+        return self.sent.values().filter(|acknowledged| !**acknowledged).count();
+    }
+
+    /// Whether the client has been sent a chunk, whether or not it acknowledged it yet.
+    pub fn is_sent(&self, pos: ChunkPos) -> bool {
+        return self.sent.contains_key(&pos);
+    }
+
+    /// Chunks within a radius as a sphere rather than a cube, skipping the corners.
+    fn in_range(&self, radius: i32) -> impl Iterator<Item = ChunkPos> + '_ {
+        let limit = radius as i64 * radius as i64;
+        return self.center.within_radius(radius).filter(move |pos| pos.distance_squared(self.center) <= limit);
+    }
+
+    /// Packets to send this tick: unloads for chunks now out of range, then the nearest loaded chunks the client
+    /// doesn't have yet, until the budget or in flight limit is reached. Chunks not loaded yet are sent once they are.
+    /// Send them with Priority::Low, so chunks never hold up what the player is doing.
+    /// ```
+    /// # use shared::engine::net::{chunk_stream::{ChunkData, ChunkStreamer, UnloadChunk}, packet};
+    /// # use shared::engine::world::{chunk::Chunk, container::World};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let world = World::new();
+    /// for pos in ChunkPos::ORIGIN.within_radius(2) {
+    ///     world.insert_chunk(Chunk::filled(pos, 1));
+    /// }
+    /// let mut streamer = ChunkStreamer::new(ChunkPos::ORIGIN, 1).with_max_in_flight(4);
+    /// let packets = streamer.tick(&world);
+    /// let first = packet::decode::<ChunkData>(&packets[0]).unwrap();
+    /// assert_eq!(first.chunk().unwrap(), Chunk::filled(ChunkPos::ORIGIN, 1));
+    /// assert_eq!(packets.len(), 4);
+    /// // Nothing more until the client catches up.
+    /// assert!(streamer.tick(&world).is_empty());
+    /// streamer.acknowledge(ChunkPos::ORIGIN);
+    /// assert_eq!(streamer.tick(&world).len(), 1);
+    ///
+    /// streamer.set_center(ChunkPos::new(5, 0, 0));
+    /// let unload = packet::decode::<UnloadChunk>(&streamer.tick(&world)[0]).unwrap();
+    /// assert!(!streamer.is_sent(unload.pos));
+    /// ```
+    pub fn tick(&mut self, world: &World) -> Vec<Vec<u8>> {
+        let mut packets = Vec::new();
+        // Chunks unload one chunk further out than they load, so walking back and forth over a border
+        // doesn't send the same chunks over and over.
+        let keep: HashSet<ChunkPos> = self.in_range(self.radius + 1).collect();
+        let mut unloaded: Vec<ChunkPos> = self.sent.keys().copied().filter(|pos| !keep.contains(pos)).collect();
+        unloaded.sort_unstable_by_key(|pos| (pos.x, pos.y, pos.z));
+        for pos in unloaded {
+            self.sent.remove(&pos);
+            packets.push(codec::encode(&UnloadChunk { pos }));
+        }
+
+        let mut wanted: Vec<ChunkPos> = self.in_range(self.radius).filter(|pos| !self.sent.contains_key(pos)).collect();
+        wanted.sort_by_key(|pos| pos.distance_squared(self.center));
+        let mut in_flight = self.in_flight();
+        let mut sent_bytes = 0;
+        for pos in wanted {
+            if in_flight >= self.max_in_flight {
+                break;
+            }
+            let Some(chunk) = world.chunk(pos) else {
+                continue;
+            };
+            let packet = match ChunkData::new(&chunk.read().unwrap()) {
+                Ok(data) => codec::encode(&data),
+                Err(_) => continue
+            };
+            if sent_bytes > 0 && sent_bytes + packet.len() > self.budget {
+                break;
+            }
+            sent_bytes += packet.len();
+            in_flight += 1;
+            self.sent.insert(pos, false);
+            packets.push(packet);
+            // Block entities aren't part of chunk data, so sign text follows its chunk.
+            packets.extend(sign::chunk_signs(world, pos));
+        }
+        return packets;
+    }
+}
+
+/// Apply a chunk packet from the server to the client's world, returning the acknowledgement to send back.
+/// Packets that aren't chunk packets are left alone.
+/// ```
+/// # use shared::engine::net::{chunk_stream::{self, ChunkData, ChunkStreamer}, packet};
+/// # use shared::engine::world::{chunk::Chunk, container::World};
+/// # use shared::engine::math::coords::ChunkPos;
+/// let server = World::new();
+/// server.insert_chunk(Chunk::filled(ChunkPos::ORIGIN, 3));
+/// let mut streamer = ChunkStreamer::new(ChunkPos::ORIGIN, 0);
+/// let client = World::new();
+/// for packet in streamer.tick(&server) {
+///     let ack = chunk_stream::apply_packet(&client, &packet).unwrap().unwrap();
+///     assert!(streamer.handle(&ack));
+/// }
+/// assert_eq!(streamer.in_flight(), 0);
+/// assert_eq!(client.get_block(ChunkPos::ORIGIN.origin()), Some(3));
+/// ```
+pub fn apply_packet(world: &World, packet: &[u8]) -> io::Result<Option<Vec<u8>>> {
+    let id = codec::packet_id(packet)?;
+    if id == <ChunkData as Packet>::ID {
+        let chunk = codec::decode::<ChunkData>(packet)?.chunk()?;
+        let pos = chunk.pos();
+        world.insert_chunk(chunk);
+        return Ok(Some(codec::encode(&ChunkAck { pos })));
+    }
+    if id == <UnloadChunk as Packet>::ID {
+        world.remove_chunk(codec::decode::<UnloadChunk>(packet)?.pos);
+    }
+    return Ok(None);
+}
\ No newline at end of file
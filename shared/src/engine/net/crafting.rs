@@ -0,0 +1,33 @@
+use crate::packet;
+
+packet! {
+    /// Sent by the client to craft from a grid of its inventory's slots, row by row, with None for empty cells.
+    /// Answered by CraftResult.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct CraftItem = 80 {
+        pub width: u8,
+        pub slots: Vec<Option<u8>>
+    }
+}
+
+packet! {
+    /// Whether the server crafted what a CraftItem asked for. The player's inventory only changes when it did.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct CraftResult = 81 {
+        /// Name of the recipe crafted, or None if nothing was.
+        pub recipe: Option<String>
+    }
+}
+
+impl CraftItem {
+    /// The grid's slots as inventory indices, as recipe::craft() takes them.
+    /// ```
+    /// # use shared::engine::net::{crafting::CraftItem, packet};
+    /// let craft = CraftItem { width: 2, slots: vec![Some(0), None, None, Some(9)] };
+    /// assert_eq!(packet::decode::<CraftItem>(&packet::encode(&craft)).unwrap(), craft);
+    /// assert_eq!(craft.inventory_slots(), vec![Some(0), None, None, Some(9)]);
+    /// ```
+    pub fn inventory_slots(&self) -> Vec<Option<usize>> {
+        return self.slots.iter().map(|slot| slot.map(|slot| slot as usize)).collect();
+    }
+}
\ No newline at end of file
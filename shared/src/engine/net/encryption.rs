@@ -0,0 +1,266 @@
+use std::{fs, io, path::Path};
+
+use snow::{params::NoiseParams, Builder, HandshakeState, StatelessTransportState};
+
+use crate::engine::save::level::write_atomically;
+
+/// Noise pattern for encrypted connections. The server proves it holds its static key, which clients can check
+/// against a key they already know, and both sides get fresh ephemeral keys so recorded traffic stays secret.
+pub const NOISE_PATTERN: &str = "Noise_NX_25519_ChaChaPoly_BLAKE2s";
+
+/// File a server's identity is saved in, in its save directory.
+pub const IDENTITY_FILE: &str = "identity.key";
+
+/// Bytes an encrypted datagram is bigger than the packet in it: the nonce, then the authentication tag.
+pub const DATAGRAM_OVERHEAD: usize = 8 + TAG_SIZE;
+
+/// Mixed into every handshake, so keys can't be confused with those of another protocol using the same pattern.
+const PROLOGUE: &[u8] = b"CubeUniverse connection";
+
+const TAG_SIZE: usize = 16;
+
+/// Largest Noise message. Longer packets are encrypted as several.
+const MAX_MESSAGE_SIZE: usize = 65535;
+
+/// Datagram nonces have the high bit set, so they never reuse a nonce of the reliable channel under the same key.
+const DATAGRAM_NONCE_BIT: u64 = 1 << 63;
+
+/// Largest a reliable packet of a given size can be once encrypted.
+pub(crate) fn encrypted_size(size: usize) -> usize {
+    return size + (size / (MAX_MESSAGE_SIZE - TAG_SIZE) + 1) * TAG_SIZE;
+}
+
+fn params() -> NoiseParams {
+    return NOISE_PATTERN.parse().unwrap();
+}
+
+fn noise_error(error: snow::Error) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, format!("Encryption failed: {}", error));
+}
+
+/// A server's long term key pair. Save the private key with the world so clients that pinned the public key
+/// keep trusting the server across restarts.
+#[derive(Clone)]
+pub struct ServerIdentity {
+    private_key: [u8; 32],
+    public_key: [u8; 32]
+}
+
+impl ServerIdentity {
+    pub fn generate() -> io::Result<ServerIdentity> {
+        let keypair = Builder::new(params()).generate_keypair().map_err(noise_error)?;
+        return Ok(ServerIdentity { private_key: keypair.private.try_into().unwrap(), public_key: keypair.public.try_into().unwrap() });
+    }
+
+    /// Load an identity from a saved private key.
+    /// ```
+    /// # use shared::engine::net::encryption::ServerIdentity;
+    /// let identity = ServerIdentity::generate().unwrap();
+    /// let loaded = ServerIdentity::from_private_key(identity.private_key()).unwrap();
+    /// assert_eq!(loaded.public_key(), identity.public_key());
+    /// ```
+    pub fn from_private_key(private_key: [u8; 32]) -> io::Result<ServerIdentity> {
+        // A handshake with a fixed key is the only way to get Noise to derive the public key.
+        let mut responder = Builder::new(params()).local_private_key(&private_key).prologue(PROLOGUE).build_responder().map_err(noise_error)?;
+        let mut initiator = Builder::new(params()).prologue(PROLOGUE).build_initiator().map_err(noise_error)?;
+        let mut message = [0u8; MAX_MESSAGE_SIZE];
+        let mut payload = [0u8; MAX_MESSAGE_SIZE];
+        let length = initiator.write_message(&[], &mut message).map_err(noise_error)?;
+        responder.read_message(&message[..length], &mut payload).map_err(noise_error)?;
+        let length = responder.write_message(&[], &mut message).map_err(noise_error)?;
+        initiator.read_message(&message[..length], &mut payload).map_err(noise_error)?;
+        let public_key = initiator.get_remote_static().unwrap().try_into().unwrap();
+        return Ok(ServerIdentity { private_key, public_key });
+    }
+
+    /// Load the identity saved at a path, or generate one and save it there if there isn't one, so the server
+    /// keeps its key across restarts.
+    /// ```
+    /// # use shared::engine::net::encryption::{ServerIdentity, IDENTITY_FILE};
+    /// let directory = std::env::temp_dir().join(format!("identity_doctest_{}", std::process::id()));
+    /// # std::fs::create_dir_all(&directory).unwrap();
+    /// let path = directory.join(IDENTITY_FILE);
+    /// let identity = ServerIdentity::load_or_generate(&path).unwrap();
+    /// assert_eq!(ServerIdentity::load_or_generate(&path).unwrap().public_key(), identity.public_key());
+    /// std::fs::write(&path, b"not a key").unwrap();
+    /// assert!(ServerIdentity::load_or_generate(&path).is_err());
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn load_or_generate(path: &Path) -> io::Result<ServerIdentity> {
+        match fs::read(path) {
+            Ok(data) => {
+                let private_key = data.try_into().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Server identity file isn't a key"))?;
+                return ServerIdentity::from_private_key(private_key);
+            },
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {},
+            Err(error) => return Err(error)
+        }
+        let identity = ServerIdentity::generate()?;
+        write_atomically(path, &identity.private_key)?;
+        // Anyone who can read the key can pretend to be the server.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+        return Ok(identity);
+    }
+
+    pub fn private_key(&self) -> [u8; 32] {
+        return self.private_key;
+    }
+
+    /// Key clients can pin, so they know they're talking to this server and not someone in between.
+    pub fn public_key(&self) -> [u8; 32] {
+        return self.public_key;
+    }
+
+    /// The public key in hex, for a server to print so players can check it.
+    pub fn fingerprint(&self) -> String {
+        return self.public_key.iter().map(|byte| format!("{:02x}", byte)).collect();
+    }
+}
+
+/// Keys of an encrypted connection, for both channels.
+pub(crate) struct Cipher {
+    transport: StatelessTransportState,
+    send_nonce: u64,
+    receive_nonce: u64,
+    datagram_nonce: u64,
+    /// Highest datagram nonce received, and a bit for each of the 64 before it that was, to drop replays.
+    datagram_highest: Option<u64>,
+    datagram_seen: u64
+}
+
+impl Cipher {
+    fn new(handshake: HandshakeState) -> io::Result<Cipher> {
+        return Ok(Cipher {
+            transport: handshake.into_stateless_transport_mode().map_err(noise_error)?,
+            send_nonce: 0,
+            receive_nonce: 0,
+            datagram_nonce: DATAGRAM_NONCE_BIT,
+            datagram_highest: None,
+            datagram_seen: 0
+        });
+    }
+
+    /// Encrypt a reliable packet. Reliable packets arrive in order, so nonces aren't sent.
+    pub(crate) fn encrypt(&mut self, packet: &[u8]) -> io::Result<Vec<u8>> {
+        let mut encrypted = Vec::with_capacity(packet.len() + TAG_SIZE);
+        let mut buffer = vec![0u8; MAX_MESSAGE_SIZE];
+        // Empty packets still take one message, so the receiver sees them.
+        for part in packet.chunks(MAX_MESSAGE_SIZE - TAG_SIZE).chain(packet.is_empty().then_some(&[][..])) {
+            let length = self.transport.write_message(self.send_nonce, part, &mut buffer).map_err(noise_error)?;
+            self.send_nonce += 1;
+            encrypted.extend_from_slice(&buffer[..length]);
+        }
+        return Ok(encrypted);
+    }
+
+    pub(crate) fn decrypt(&mut self, encrypted: &[u8]) -> io::Result<Vec<u8>> {
+        let mut packet = Vec::with_capacity(encrypted.len());
+        let mut buffer = vec![0u8; MAX_MESSAGE_SIZE];
+        for part in encrypted.chunks(MAX_MESSAGE_SIZE).chain(encrypted.is_empty().then_some(&[][..])) {
+            let length = self.transport.read_message(self.receive_nonce, part, &mut buffer).map_err(noise_error)?;
+            self.receive_nonce += 1;
+            packet.extend_from_slice(&buffer[..length]);
+        }
+        return Ok(packet);
+    }
+
+    /// Encrypt an unreliable packet, with its nonce in front as datagrams can arrive in any order.
+    pub(crate) fn encrypt_datagram(&mut self, packet: &[u8]) -> io::Result<Vec<u8>> {
+        let mut datagram = vec![0u8; packet.len() + DATAGRAM_OVERHEAD];
+        datagram[..8].copy_from_slice(&self.datagram_nonce.to_le_bytes());
+        let length = self.transport.write_message(self.datagram_nonce, packet, &mut datagram[8..]).map_err(noise_error)?;
+        self.datagram_nonce += 1;
+        datagram.truncate(8 + length);
+        return Ok(datagram);
+    }
+
+    /// Decrypt a datagram, or None if it's forged, corrupt or a replay of one already received.
+    pub(crate) fn decrypt_datagram(&mut self, datagram: &[u8]) -> Option<Vec<u8>> {
+        let nonce = u64::from_le_bytes(datagram.get(..8)?.try_into().unwrap());
+        if nonce & DATAGRAM_NONCE_BIT == 0 {
+            return None;
+        }
+        let age = self.datagram_highest.map(|highest| highest as i128 - nonce as i128);
+        if age.is_some_and(|age| age >= 64 || (age >= 0 && self.datagram_seen & (1 << age) != 0)) {
+            return None;
+        }
+        let mut packet = vec![0u8; datagram.len()];
+        let length = self.transport.read_message(nonce, &datagram[8..], &mut packet).ok()?;
+        packet.truncate(length);
+        match age {
+            Some(age) if age >= 0 => self.datagram_seen |= 1 << age,
+            Some(age) => {
+                let shift = (-age).min(64) as u32;
+                self.datagram_seen = (if shift >= 64 { 0 } else { self.datagram_seen << shift }) | 1;
+                self.datagram_highest = Some(nonce);
+            },
+            None => {
+                self.datagram_seen = 1;
+                self.datagram_highest = Some(nonce);
+            }
+        }
+        return Some(packet);
+    }
+}
+
+/// Where a connection's encryption is.
+pub(crate) enum Security {
+    Plain,
+    /// Client waiting for the server's handshake message, checking the server's key against one if given.
+    Connecting(Box<HandshakeState>, Option<[u8; 32]>),
+    /// Server waiting for the client's handshake message.
+    Accepting(Box<HandshakeState>),
+    /// With the server's key, on the client side.
+    Encrypted(Box<Cipher>, Option<[u8; 32]>)
+}
+
+impl Security {
+    /// Start the client side of a handshake, returning the message to send first.
+    pub(crate) fn connect(server_key: Option<[u8; 32]>) -> io::Result<(Security, Vec<u8>)> {
+        let mut handshake = Builder::new(params()).prologue(PROLOGUE).build_initiator().map_err(noise_error)?;
+        let mut message = vec![0u8; MAX_MESSAGE_SIZE];
+        let length = handshake.write_message(&[], &mut message).map_err(noise_error)?;
+        message.truncate(length);
+        return Ok((Security::Connecting(Box::new(handshake), server_key), message));
+    }
+
+    pub(crate) fn accept(identity: &ServerIdentity) -> io::Result<Security> {
+        let handshake = Builder::new(params()).local_private_key(&identity.private_key).prologue(PROLOGUE).build_responder().map_err(noise_error)?;
+        return Ok(Security::Accepting(Box::new(handshake)));
+    }
+
+    pub(crate) fn is_handshaking(&self) -> bool {
+        return matches!(self, Security::Connecting(..) | Security::Accepting(_));
+    }
+
+    /// Read the other side's handshake message, returning a reply to send, if any.
+    /// The connection is encrypted afterwards.
+    pub(crate) fn handshake(&mut self, message: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let mut payload = vec![0u8; MAX_MESSAGE_SIZE];
+        let (security, reply) = match std::mem::replace(self, Security::Plain) {
+            Security::Connecting(mut handshake, expected) => {
+                handshake.read_message(message, &mut payload).map_err(noise_error)?;
+                let server_key: [u8; 32] = handshake.get_remote_static().unwrap().try_into().unwrap();
+                if expected.is_some_and(|expected| expected != server_key) {
+                    return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Server key doesn't match the expected key"));
+                }
+                (Security::Encrypted(Box::new(Cipher::new(*handshake)?), Some(server_key)), None)
+            },
+            Security::Accepting(mut handshake) => {
+                handshake.read_message(message, &mut payload).map_err(noise_error)?;
+                let mut reply = vec![0u8; MAX_MESSAGE_SIZE];
+                let length = handshake.write_message(&[], &mut reply).map_err(noise_error)?;
+                reply.truncate(length);
+                // NX doesn't authenticate the client, whose identity is up to the login's Authenticator.
+                (Security::Encrypted(Box::new(Cipher::new(*handshake)?), None), Some(reply))
+            },
+            security => (security, None)
+        };
+        *self = security;
+        return Ok(reply);
+    }
+}
\ No newline at end of file
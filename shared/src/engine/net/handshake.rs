@@ -0,0 +1,315 @@
+use std::{fmt, io, sync::Arc};
+
+use crate::{
+    engine::version::{ModInfo, Version, VersionManifest},
+    packet, wire_struct
+};
+
+use super::{
+    auth::{Authenticator, OfflineAuthenticator},
+    packet::{self as codec, PacketReader, PacketWriter, Wire}
+};
+
+wire_struct!(Version { major, minor, patch });
+wire_struct!(ModInfo { id, version });
+wire_struct!(VersionManifest { engine, protocol, save_format, mods });
+
+packet! {
+    /// First packet a client sends. Its layout never changes between protocol versions, so any server can read it
+    /// and turn away clients it doesn't speak the protocol of before reading anything else they send.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Hello = 1 {
+        pub protocol: u32
+    }
+}
+
+packet! {
+    /// The server's answer to a Hello it accepts, so the client can check the server too.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct ServerHello = 2 {
+        pub manifest: VersionManifest
+    }
+}
+
+packet! {
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Login = 3 {
+        pub name: String,
+        /// Proof of who the player is, checked by the server's Authenticator. Empty in offline mode.
+        pub token: String,
+        pub manifest: VersionManifest,
+        /// Port of the client's unreliable channel, at the same address as its TCP connection.
+        pub unreliable_port: Option<u16>
+    }
+}
+
+packet! {
+    /// Sent once the server accepts the login. Everything after it is play packets.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct LoginSuccess = 4 {
+        pub player: u64,
+        pub uuid: u128
+    }
+}
+
+packet! {
+    /// Sent by either side right before it closes the connection, at any point, so the other side can say why.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Disconnect = 5 {
+        pub reason: DisconnectReason
+    }
+}
+
+/// Why a connection was closed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The builds can't play together, with a line per problem.
+    IncompatibleVersion(Vec<String>),
+    /// The other side sent something it shouldn't have at this point.
+    Protocol(String),
+    Kicked(String),
+    /// The server's Authenticator turned the player away.
+    Unauthenticated(String),
+    /// A player closing the game, or a server shutting down.
+    Quit
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            DisconnectReason::IncompatibleVersion(problems) => write!(f, "incompatible version: {}", problems.join(", ")),
+            DisconnectReason::Protocol(message) => write!(f, "protocol error: {}", message),
+            DisconnectReason::Kicked(message) => write!(f, "kicked: {}", message),
+            DisconnectReason::Unauthenticated(message) => write!(f, "authentication failed: {}", message),
+            DisconnectReason::Quit => write!(f, "quit")
+        };
+    }
+}
+
+impl Wire for DisconnectReason {
+    fn write(&self, writer: &mut PacketWriter) {
+        match self {
+            DisconnectReason::IncompatibleVersion(problems) => writer.write(&0u8).write(problems),
+            DisconnectReason::Protocol(message) => writer.write(&1u8).write(message),
+            DisconnectReason::Kicked(message) => writer.write(&2u8).write(message),
+            DisconnectReason::Quit => writer.write(&3u8),
+            DisconnectReason::Unauthenticated(message) => writer.write(&4u8).write(message)
+        };
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<DisconnectReason> {
+        return match reader.read_u8()? {
+            0 => Ok(DisconnectReason::IncompatibleVersion(reader.read()?)),
+            1 => Ok(DisconnectReason::Protocol(reader.read()?)),
+            2 => Ok(DisconnectReason::Kicked(reader.read()?)),
+            3 => Ok(DisconnectReason::Quit),
+            4 => Ok(DisconnectReason::Unauthenticated(reader.read()?)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown disconnect reason"))
+        };
+    }
+}
+
+/// Where a connection is in the handshake. Every connection goes hello → version check → login and authentication → play,
+/// or ends up disconnected at any step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HandshakeState {
+    /// Server waiting for the client's Hello, or client waiting for the ServerHello.
+    Hello,
+    /// Server waiting for the client's Login, or client waiting for LoginSuccess.
+    Login,
+    Play,
+    Disconnected(DisconnectReason)
+}
+
+/// The server's side of the handshake for one connection.
+pub struct ServerHandshake {
+    state: HandshakeState,
+    manifest: VersionManifest,
+    authenticator: Arc<dyn Authenticator>,
+    login: Option<Login>,
+    uuid: Option<u128>
+}
+
+/// The client's side of the handshake.
+pub struct ClientHandshake {
+    state: HandshakeState,
+    manifest: VersionManifest,
+    name: String,
+    token: String,
+    unreliable_port: Option<u16>,
+    player: Option<u64>,
+    uuid: Option<u128>
+}
+
+fn disconnect(state: &mut HandshakeState, reason: DisconnectReason) -> Vec<Vec<u8>> {
+    let packet = codec::encode(&Disconnect { reason: reason.clone() });
+    *state = HandshakeState::Disconnected(reason);
+    return vec![packet];
+}
+
+/// If a packet is a Disconnect, the reason for it.
+fn disconnect_reason(data: &[u8]) -> Option<DisconnectReason> {
+    return codec::decode::<Disconnect>(data).ok().map(|disconnect| disconnect.reason);
+}
+
+impl ServerHandshake {
+    /// A handshake in offline mode, letting in anyone with a valid name.
+    pub fn new(manifest: VersionManifest) -> ServerHandshake {
+        return ServerHandshake { state: HandshakeState::Hello, manifest, authenticator: Arc::new(OfflineAuthenticator), login: None, uuid: None };
+    }
+
+    /// Check logins with an authenticator, usually shared by every connection.
+    pub fn with_authenticator(mut self, authenticator: Arc<dyn Authenticator>) -> ServerHandshake {
+        self.authenticator = authenticator;
+        return self;
+    }
+
+    pub fn state(&self) -> &HandshakeState {
+        return &self.state;
+    }
+
+    /// The client's login, once it reaches play. The token is cleared once checked.
+    pub fn login(&self) -> Option<&Login> {
+        return self.login.as_ref();
+    }
+
+    /// The player's UUID from the authenticator, once it reaches play.
+    pub fn uuid(&self) -> Option<u128> {
+        return self.uuid;
+    }
+
+    /// Handle a packet from the client, returning packets to send back.
+    /// When the state becomes disconnected, send them, then close the connection.
+    /// The player id is given to clients that log in, and is up to the server.
+    /// ```
+    /// # use shared::engine::net::handshake::{ClientHandshake, DisconnectReason, HandshakeState, ServerHandshake};
+    /// # use shared::engine::version::VersionManifest;
+    /// let mut server = ServerHandshake::new(VersionManifest::current(vec![]));
+    /// let mut client = ClientHandshake::new(VersionManifest::current(vec![]), "steve", None);
+    /// let mut to_server = vec![client.start()];
+    /// while !to_server.is_empty() {
+    ///     let to_client: Vec<Vec<u8>> = to_server.iter().flat_map(|packet| server.handle(packet, 7)).collect();
+    ///     to_server = to_client.iter().flat_map(|packet| client.handle(packet)).collect();
+    /// }
+    /// assert_eq!((server.state(), client.state()), (&HandshakeState::Play, &HandshakeState::Play));
+    /// assert_eq!(server.login().unwrap().name, "steve");
+    /// assert_eq!(client.player(), Some(7));
+    /// // Offline mode, so the UUID comes from the name.
+    /// assert_eq!(client.uuid(), Some(shared::engine::net::auth::offline_uuid("steve")));
+    ///
+    /// // A client on another protocol is told so, rather than sent packets it can't read.
+    /// let mut server = ServerHandshake::new(VersionManifest::current(vec![]));
+    /// let mut manifest = VersionManifest::current(vec![]);
+    /// manifest.protocol += 1;
+    /// let mut client = ClientHandshake::new(manifest, "alex", None);
+    /// let reply = server.handle(&client.start(), 8);
+    /// assert!(matches!(server.state(), HandshakeState::Disconnected(DisconnectReason::IncompatibleVersion(_))));
+    /// client.handle(&reply[0]);
+    /// assert_eq!(client.state(), server.state());
+    /// ```
+    pub fn handle(&mut self, data: &[u8], player: u64) -> Vec<Vec<u8>> {
+        if let Some(reason) = disconnect_reason(data) {
+            self.state = HandshakeState::Disconnected(reason);
+            return Vec::new();
+        }
+        match self.state {
+            HandshakeState::Hello => {
+                let Ok(hello) = codec::decode::<Hello>(data) else {
+                    return disconnect(&mut self.state, DisconnectReason::Protocol("expected hello".to_string()));
+                };
+                if hello.protocol != self.manifest.protocol {
+                    let problem = format!("server uses protocol version {}, client uses {}", self.manifest.protocol, hello.protocol);
+                    return disconnect(&mut self.state, DisconnectReason::IncompatibleVersion(vec![problem]));
+                }
+                self.state = HandshakeState::Login;
+                return vec![codec::encode(&ServerHello { manifest: self.manifest.clone() })];
+            },
+            HandshakeState::Login => {
+                let mut login = match codec::decode::<Login>(data) {
+                    Ok(login) => login,
+                    Err(error) => return disconnect(&mut self.state, DisconnectReason::Protocol(format!("invalid login: {}", error)))
+                };
+                if let Err(problems) = login.manifest.check_server(&self.manifest) {
+                    let problems = problems.iter().map(|problem| format!("client {}", problem)).collect();
+                    return disconnect(&mut self.state, DisconnectReason::IncompatibleVersion(problems));
+                }
+                let uuid = match self.authenticator.authenticate(&login.name, &login.token) {
+                    Ok(uuid) => uuid,
+                    Err(message) => return disconnect(&mut self.state, DisconnectReason::Unauthenticated(message))
+                };
+                login.token.clear();
+                self.login = Some(login);
+                self.uuid = Some(uuid);
+                self.state = HandshakeState::Play;
+                return vec![codec::encode(&LoginSuccess { player, uuid })];
+            },
+            HandshakeState::Play | HandshakeState::Disconnected(_) => return Vec::new()
+        }
+    }
+}
+
+impl ClientHandshake {
+    pub fn new(manifest: VersionManifest, name: &str, unreliable_port: Option<u16>) -> ClientHandshake {
+        return ClientHandshake { state: HandshakeState::Hello, manifest, name: name.to_string(), token: String::new(), unreliable_port, player: None, uuid: None };
+    }
+
+    /// Token proving who the player is, for servers that check. Left empty for offline servers.
+    pub fn with_token(mut self, token: &str) -> ClientHandshake {
+        self.token = token.to_string();
+        return self;
+    }
+
+    /// The Hello to send as soon as the connection is made.
+    pub fn start(&self) -> Vec<u8> {
+        return codec::encode(&Hello { protocol: self.manifest.protocol });
+    }
+
+    pub fn state(&self) -> &HandshakeState {
+        return &self.state;
+    }
+
+    /// Id the server gave the player, once logged in.
+    pub fn player(&self) -> Option<u64> {
+        return self.player;
+    }
+
+    /// UUID the server knows the player by, once logged in.
+    pub fn uuid(&self) -> Option<u128> {
+        return self.uuid;
+    }
+
+    /// Handle a packet from the server, returning packets to send back. See ServerHandshake::handle().
+    pub fn handle(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        if let Some(reason) = disconnect_reason(data) {
+            self.state = HandshakeState::Disconnected(reason);
+            return Vec::new();
+        }
+        match self.state {
+            HandshakeState::Hello => {
+                let server = match codec::decode::<ServerHello>(data) {
+                    Ok(hello) => hello.manifest,
+                    Err(error) => return disconnect(&mut self.state, DisconnectReason::Protocol(format!("invalid server hello: {}", error)))
+                };
+                // The server checks too, but a client that knows it can't join doesn't need to ask.
+                if let Err(problems) = self.manifest.check_server(&server) {
+                    let problems = problems.iter().map(|problem| format!("server {}", problem)).collect();
+                    return disconnect(&mut self.state, DisconnectReason::IncompatibleVersion(problems));
+                }
+                self.state = HandshakeState::Login;
+                return vec![codec::encode(&Login { name: self.name.clone(), token: self.token.clone(), manifest: self.manifest.clone(), unreliable_port: self.unreliable_port })];
+            },
+            HandshakeState::Login => {
+                match codec::decode::<LoginSuccess>(data) {
+                    Ok(success) => {
+                        self.player = Some(success.player);
+                        self.uuid = Some(success.uuid);
+                        self.state = HandshakeState::Play;
+                        return Vec::new();
+                    },
+                    Err(_) => return disconnect(&mut self.state, DisconnectReason::Protocol("expected login success".to_string()))
+                }
+            },
+            HandshakeState::Play | HandshakeState::Disconnected(_) => return Vec::new()
+        }
+    }
+}
\ No newline at end of file
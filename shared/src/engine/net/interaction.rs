@@ -0,0 +1,206 @@
+use std::{fmt, io};
+
+use crate::{
+    engine::{
+        block::{BlockId, AIR},
+        math::{coords::{BlockPos, WorldPos}, direction::{Axis, Direction}, ray::Ray, vector::Vec3},
+        world::World
+    },
+    packet
+};
+
+use super::packet::{self as codec, Packet};
+
+/// How far away players can reach blocks from their eyes, in blocks.
+pub const REACH_DISTANCE: f32 = 5.0;
+/// Reach the server allows beyond REACH_DISTANCE, as the player may have moved since their client sent the packet.
+pub const REACH_TOLERANCE: f32 = 1.0;
+
+/// How far in from a face's edges the points checked for line of sight are, so rays don't graze neighbouring blocks.
+const FACE_INSET: f64 = 0.1;
+
+packet! {
+    /// Sent by the client when its player breaks the block it's looking at, through the face it's looking at.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct DigBlock = 10 {
+        pub block: BlockPos,
+        pub face: Direction
+    }
+}
+
+packet! {
+    /// Sent by the client when its player places the block item in a hotbar slot against a face of a block.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct PlaceBlock = 11 {
+        pub against: BlockPos,
+        pub face: Direction,
+        pub slot: u8
+    }
+}
+
+packet! {
+    /// A block changed, sent to every client with its chunk. Also sent back to a player whose dig or place was
+    /// refused, to undo what their client predicted.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct BlockUpdate = 12 {
+        pub block: BlockPos,
+        pub id: BlockId
+    }
+}
+
+/// Reason the server refused to let a player break or place a block.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InteractionError {
+    NotLoaded,
+    /// Distance from the player's eyes to the face.
+    OutOfReach(f32),
+    /// Something is in the way of every part of the face, or the player is behind it.
+    NotVisible,
+    /// Breaking air, or placing against it.
+    NothingThere,
+    /// Placing where there's already a block.
+    Occupied,
+    /// A protected region or the player's permission level doesn't allow it.
+    NotAllowed,
+    /// The slot doesn't hold an item that places a block.
+    NoBlockItem
+}
+
+impl fmt::Display for InteractionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            InteractionError::NotLoaded => write!(f, "the block isn't loaded"),
+            InteractionError::OutOfReach(distance) => write!(f, "the block is {:.1} blocks away, out of reach", distance),
+            InteractionError::NotVisible => write!(f, "the block can't be seen from where the player is"),
+            InteractionError::NothingThere => write!(f, "there's no block there"),
+            InteractionError::Occupied => write!(f, "there's already a block there"),
+            InteractionError::NotAllowed => write!(f, "the player isn't allowed to change blocks there"),
+            InteractionError::NoBlockItem => write!(f, "the slot has no block to place")
+        };
+    }
+}
+
+impl std::error::Error for InteractionError {}
+
+fn to_vec3(pos: WorldPos) -> Vec3 {
+    return Vec3::new(pos.x as f32, pos.y as f32, pos.z as f32);
+}
+
+/// Points on a face that a player could be looking at: its center, and near each of its corners.
+fn face_points(block: BlockPos, face: Direction) -> [WorldPos; 5] {
+    let normal = face.normal();
+    let center = block.center() + WorldPos::new(normal.x as f64, normal.y as f64, normal.z as f64) * 0.5;
+    let (u, v) = match face.axis() {
+        Axis::X => (WorldPos::new(0.0, 1.0, 0.0), WorldPos::new(0.0, 0.0, 1.0)),
+        Axis::Y => (WorldPos::new(1.0, 0.0, 0.0), WorldPos::new(0.0, 0.0, 1.0)),
+        Axis::Z => (WorldPos::new(1.0, 0.0, 0.0), WorldPos::new(0.0, 1.0, 0.0))
+    };
+    let edge = 0.5 - FACE_INSET;
+    return [center, center + (u + v) * edge, center + (u - v) * edge, center - (u - v) * edge, center - (u + v) * edge];
+}
+
+/// Check that a player with their eyes at a position can reach a face of a block and see some part of it, as when
+/// breaking the block or placing against it. The server passes REACH_DISTANCE plus REACH_TOLERANCE.
+/// ```
+/// # use shared::engine::net::interaction::{self, InteractionError};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::{coords::{BlockPos, ChunkPos, WorldPos}, direction::Direction};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// world.set_block(BlockPos::new(5, 2, 2), 1);
+/// let eye = WorldPos::new(1.5, 2.5, 2.5);
+/// assert_eq!(interaction::check_reach(&world, eye, BlockPos::new(5, 2, 2), Direction::NegX, 5.0), Ok(()));
+/// // The far side of the block can't be seen, and a wall in the way hides it entirely.
+/// assert_eq!(interaction::check_reach(&world, eye, BlockPos::new(5, 2, 2), Direction::PosX, 5.0), Err(InteractionError::NotVisible));
+/// for y in 0..6 {
+///     for z in 0..6 {
+///         world.set_block(BlockPos::new(3, y, z), 1);
+///     }
+/// }
+/// assert_eq!(interaction::check_reach(&world, eye, BlockPos::new(5, 2, 2), Direction::NegX, 5.0), Err(InteractionError::NotVisible));
+/// assert!(matches!(interaction::check_reach(&world, eye, BlockPos::new(3, 2, 2), Direction::NegX, 1.0), Err(InteractionError::OutOfReach(_))));
+/// ```
+pub fn check_reach(world: &World, eye: WorldPos, block: BlockPos, face: Direction, reach: f32) -> Result<(), InteractionError> {
+    if !world.is_loaded(block.chunk()) {
+        return Err(InteractionError::NotLoaded);
+    }
+    let points = face_points(block, face);
+    let distance = eye.distance(points[0]) as f32;
+    if distance > reach {
+        return Err(InteractionError::OutOfReach(distance));
+    }
+    // A face can only be seen from in front of it.
+    let normal = face.normal();
+    let offset = eye - points[0];
+    if offset.x * normal.x as f64 + offset.y * normal.y as f64 + offset.z * normal.z as f64 <= 0.0 {
+        return Err(InteractionError::NotVisible);
+    }
+    let origin = to_vec3(eye);
+    for point in points {
+        // Rays end just past the face, so the first block hit is either this one or something in the way.
+        let ray = Ray::new(origin, to_vec3(point) - origin);
+        if world.raycast(ray, 1.01).is_some_and(|hit| hit.block == block) {
+            return Ok(());
+        }
+    }
+    return Err(InteractionError::NotVisible);
+}
+
+/// Check a player may break a block, returning the block that's there.
+pub fn validate_dig(world: &World, eye: WorldPos, dig: &DigBlock, reach: f32) -> Result<BlockId, InteractionError> {
+    let id = world.get_block(dig.block).ok_or(InteractionError::NotLoaded)?;
+    if id == AIR {
+        return Err(InteractionError::NothingThere);
+    }
+    check_reach(world, eye, dig.block, dig.face, reach)?;
+    return Ok(id);
+}
+
+/// Check a player may place a block against another, returning where it goes.
+/// ```
+/// # use shared::engine::net::interaction::{self, InteractionError, PlaceBlock};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::{coords::{BlockPos, ChunkPos, WorldPos}, direction::Direction};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// world.set_block(BlockPos::new(2, 0, 2), 1);
+/// let eye = WorldPos::new(2.5, 2.6, 4.5);
+/// let place = PlaceBlock { against: BlockPos::new(2, 0, 2), face: Direction::PosY, slot: 0 };
+/// assert_eq!(interaction::validate_place(&world, eye, &place, 5.0), Ok(BlockPos::new(2, 1, 2)));
+/// world.set_block(BlockPos::new(2, 1, 2), 1);
+/// assert_eq!(interaction::validate_place(&world, eye, &place, 5.0), Err(InteractionError::NotVisible));
+/// ```
+pub fn validate_place(world: &World, eye: WorldPos, place: &PlaceBlock, reach: f32) -> Result<BlockPos, InteractionError> {
+    if world.get_block(place.against).ok_or(InteractionError::NotLoaded)? == AIR {
+        return Err(InteractionError::NothingThere);
+    }
+    check_reach(world, eye, place.against, place.face, reach)?;
+    let target = place.against + place.face.offset();
+    if world.get_block(target).ok_or(InteractionError::NotLoaded)? != AIR {
+        return Err(InteractionError::Occupied);
+    }
+    return Ok(target);
+}
+
+/// Apply a block update from the server to the client's world. False if the packet wasn't one.
+/// ```
+/// # use shared::engine::net::{interaction::{self, BlockUpdate}, packet};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// let update = packet::encode(&BlockUpdate { block: BlockPos::new(1, 2, 3), id: 4 });
+/// assert!(interaction::apply_packet(&world, &update).unwrap());
+/// assert_eq!(world.get_block(BlockPos::new(1, 2, 3)), Some(4));
+/// ```
+pub fn apply_packet(world: &World, packet: &[u8]) -> io::Result<bool> {
+    if codec::packet_id(packet)? != <BlockUpdate as Packet>::ID {
+        return Ok(false);
+    }
+    let update = codec::decode::<BlockUpdate>(packet)?;
+    // Block entities belong to the block they were made for, such as a sign's text.
+    if world.set_block(update.block, update.id).is_some_and(|old| old != update.id) {
+        world.remove_block_entity(update.block);
+    }
+    return Ok(true);
+}
\ No newline at end of file
@@ -0,0 +1,185 @@
+use std::sync::{Arc, Mutex};
+
+use crate::engine::math::rng::WorldRng;
+
+use super::transport::{Channel, PacketLink, Priority, MAX_DATAGRAM_SIZE};
+
+/// How a simulated link treats packets. Reliable packets are only ever delayed, keeping their order,
+/// while unreliable ones can also be lost or reordered, as over UDP.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NetworkConditions {
+    /// One way delay in milliseconds.
+    pub latency: u32,
+    /// Most extra delay in milliseconds added at random to each packet.
+    pub jitter: u32,
+    /// Chance of losing each unreliable packet, from 0 to 1.
+    pub loss: f64,
+    /// Chance of holding back an unreliable packet by up to a round trip, so later ones overtake it.
+    pub reordering: f64
+}
+
+impl NetworkConditions {
+    /// Delivers everything instantly, in order.
+    pub const PERFECT: NetworkConditions = NetworkConditions { latency: 0, jitter: 0, loss: 0.0, reordering: 0.0 };
+
+    pub fn with_latency(mut self, latency: u32) -> NetworkConditions {
+        self.latency = latency;
+        return self;
+    }
+
+    pub fn with_jitter(mut self, jitter: u32) -> NetworkConditions {
+        self.jitter = jitter;
+        return self;
+    }
+
+    pub fn with_loss(mut self, loss: f64) -> NetworkConditions {
+        self.loss = loss;
+        return self;
+    }
+
+    pub fn with_reordering(mut self, reordering: f64) -> NetworkConditions {
+        self.reordering = reordering;
+        return self;
+    }
+}
+
+impl Default for NetworkConditions {
+    fn default() -> NetworkConditions {
+        return NetworkConditions::PERFECT;
+    }
+}
+
+struct InFlight {
+    arrives: u64,
+    /// Order sent, so packets arriving at the same time keep it.
+    sequence: u64,
+    packet: Vec<u8>
+}
+
+struct SimulatedLink {
+    conditions: NetworkConditions,
+    /// Packets in flight towards each end.
+    in_flight: [Vec<InFlight>; 2],
+    /// When the newest reliable packet towards each end arrives, which later ones can't beat.
+    reliable_arrives: [u64; 2],
+    open: bool
+}
+
+struct NetworkState {
+    now: u64,
+    rng: WorldRng,
+    sequence: u64,
+    links: Vec<SimulatedLink>
+}
+
+/// Simulated network for tests, with a clock that only moves when told to. Links between endpoints delay, lose
+/// and reorder packets by their conditions, the same way every run for the same seed.
+/// ```
+/// # use shared::engine::net::{loopback::{LoopbackNetwork, NetworkConditions}, transport::{Channel, PacketLink}};
+/// let network = LoopbackNetwork::new(1);
+/// let (client, server) = network.connect(NetworkConditions::PERFECT.with_latency(50));
+/// client.send(Channel::Reliable, b"hello".to_vec());
+/// network.advance(49);
+/// assert!(server.receive().is_empty());
+/// network.advance(1);
+/// assert_eq!(server.receive(), vec![b"hello".to_vec()]);
+/// ```
+#[derive(Clone)]
+pub struct LoopbackNetwork {
+    state: Arc<Mutex<NetworkState>>
+}
+
+/// One end of a simulated link.
+pub struct LoopbackEndpoint {
+    state: Arc<Mutex<NetworkState>>,
+    link: usize,
+    side: usize
+}
+
+impl LoopbackNetwork {
+    pub fn new(seed: u64) -> LoopbackNetwork {
+        return LoopbackNetwork { state: Arc::new(Mutex::new(NetworkState { now: 0, rng: WorldRng::new(seed), sequence: 0, links: Vec::new() })) };
+    }
+
+    /// Link two new endpoints, such as a client and its connection on the server.
+    pub fn connect(&self, conditions: NetworkConditions) -> (LoopbackEndpoint, LoopbackEndpoint) {
+        let mut state = self.state.lock().unwrap();
+        let link = state.links.len();
+        state.links.push(SimulatedLink { conditions, in_flight: [Vec::new(), Vec::new()], reliable_arrives: [0, 0], open: true });
+        let endpoint = |side| LoopbackEndpoint { state: self.state.clone(), link, side };
+        return (endpoint(0), endpoint(1));
+    }
+
+    /// Milliseconds since the network was made.
+    pub fn now(&self) -> u64 {
+        return self.state.lock().unwrap().now;
+    }
+
+    /// Move the clock forward, delivering the packets that arrive by then.
+    pub fn advance(&self, milliseconds: u64) {
+        self.state.lock().unwrap().now += milliseconds;
+    }
+}
+
+impl LoopbackEndpoint {
+    /// Change the conditions of the link, in both directions. Packets already in flight keep their timing.
+    pub fn set_conditions(&self, conditions: NetworkConditions) {
+        self.state.lock().unwrap().links[self.link].conditions = conditions;
+    }
+
+    /// Packets in flight towards the other end.
+    pub fn in_flight(&self) -> usize {
+        return self.state.lock().unwrap().links[self.link].in_flight[1 - self.side].len();
+    }
+
+    /// Close the link for both ends, dropping packets in flight.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        let link = &mut state.links[self.link];
+        link.open = false;
+        link.in_flight = [Vec::new(), Vec::new()];
+    }
+}
+
+impl PacketLink for LoopbackEndpoint {
+    fn send_with_priority(&self, channel: Channel, _priority: Priority, packet: Vec<u8>) {
+        let mut state = self.state.lock().unwrap();
+        let NetworkState { now, rng, sequence, links } = &mut *state;
+        let link = &mut links[self.link];
+        if !link.open {
+            return;
+        }
+        let to = 1 - self.side;
+        let conditions = link.conditions;
+        let jitter = if conditions.jitter > 0 { rng.range_i32(0..conditions.jitter as i32 + 1) as u64 } else { 0 };
+        let mut arrives = *now + conditions.latency as u64 + jitter;
+        // Packets too big for a datagram go reliably, as with a real connection.
+        if channel == Channel::Reliable || packet.len() > MAX_DATAGRAM_SIZE {
+            arrives = arrives.max(link.reliable_arrives[to]);
+            link.reliable_arrives[to] = arrives;
+        } else {
+            if rng.chance(conditions.loss) {
+                return;
+            }
+            if rng.chance(conditions.reordering) {
+                arrives += rng.range_i32(1..2 * conditions.latency.max(1) as i32 + 1) as u64;
+            }
+        }
+        *sequence += 1;
+        link.in_flight[to].push(InFlight { arrives, sequence: *sequence, packet });
+    }
+
+    fn receive(&self) -> Vec<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        let now = state.now;
+        let in_flight = &mut state.links[self.link].in_flight[self.side];
+        let (mut arrived, waiting): (Vec<InFlight>, Vec<InFlight>) = in_flight.drain(..).partition(|packet| packet.arrives <= now);
+        *in_flight = waiting;
+        arrived.sort_by_key(|packet| (packet.arrives, packet.sequence));
+        return arrived.into_iter().map(|packet| packet.packet).collect();
+    }
+
+    fn is_open(&self) -> bool {
+        return self.state.lock().unwrap().links[self.link].open;
+    }
+}
\ No newline at end of file
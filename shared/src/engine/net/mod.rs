@@ -0,0 +1,18 @@
+pub mod packet;
+pub mod encryption;
+pub mod transport;
+pub mod loopback;
+pub mod auth;
+pub mod handshake;
+pub mod chunk_stream;
+pub mod snapshot;
+pub mod prediction;
+pub mod chat;
+pub mod admin;
+pub mod time_sync;
+pub mod interaction;
+pub mod movement;
+pub mod checksum;
+pub mod crafting;
+pub mod replay;
+pub mod sign;
\ No newline at end of file
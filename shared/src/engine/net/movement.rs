@@ -0,0 +1,229 @@
+use std::fmt;
+
+use crate::{
+    engine::{
+        block::AIR,
+        entity::kinematics::FIXED_TIMESTEP,
+        math::{coords::{BlockPos, WorldPos}, vector::Vec3},
+        physics::{body::RigidBody, collide_aabb},
+        world::World
+    },
+    packet
+};
+
+use super::prediction::MOVE_SPEED;
+
+/// Fastest a player may move horizontally, or in any direction while flying, in blocks per second. Walking speed
+/// with room for being pushed around.
+pub const MAX_SPEED: f64 = MOVE_SPEED as f64 * 1.5;
+/// Seconds of unused movement a player can bank, so moves that arrive bunched up after lag aren't too fast.
+pub const MAX_MOVE_BURST: f64 = 1.0;
+/// Furthest one move may go, in blocks, whatever was banked. Beyond this the player is teleporting.
+pub const MAX_MOVE_DISTANCE: f64 = 10.0;
+/// Highest a player who can't fly may rise above where they last stood, with room for jumping onto a block.
+pub const MAX_JUMP_HEIGHT: f64 = 1.3;
+/// Moves a player who can't fly may make in the air without falling, enough for the top of a jump.
+pub const MAX_AIR_TICKS: u32 = 10;
+
+/// How far a move may differ from what collision allows, for rounding.
+const COLLISION_TOLERANCE: f32 = 1e-3;
+/// How far under a player's feet a block holds them up.
+const SUPPORT_DEPTH: f64 = 0.1;
+
+packet! {
+    /// Where the client moved its player to this tick. Teleport is the newest PositionCorrection it applied,
+    /// so moves made before a correction arrived are ignored.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PlayerPosition = 42 {
+        pub teleport: u32,
+        pub position: WorldPos
+    }
+}
+
+packet! {
+    /// Sent by the server when it refused a move or moved the player itself. The client moves its player back
+    /// here, and reports the teleport with its following moves.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PositionCorrection = 43 {
+        pub teleport: u32,
+        pub position: WorldPos
+    }
+}
+
+/// Reason the server refused a player's move.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MovementViolation {
+    /// Blocks moved beyond what the player's speed allows.
+    TooFast(f64),
+    /// Blocks moved in one go, beyond MAX_MOVE_DISTANCE.
+    Teleported(f64),
+    /// The move went into or through a block.
+    Collided,
+    /// The player rose or stayed in the air without being allowed to fly.
+    Flying
+}
+
+impl fmt::Display for MovementViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            MovementViolation::TooFast(distance) => write!(f, "moved too fast ({:.1} blocks)", distance),
+            MovementViolation::Teleported(distance) => write!(f, "moved too far at once ({:.1} blocks)", distance),
+            MovementViolation::Collided => write!(f, "moved into a block"),
+            MovementViolation::Flying => write!(f, "flew without being allowed to")
+        };
+    }
+}
+
+impl std::error::Error for MovementViolation {}
+
+/// The server's check of where one player's client says it moved. Moves are refused if they're faster than the
+/// player can go, pass through blocks, or leave the ground for longer than a jump when the player can't fly.
+/// A refused move leaves the player where they were, and the client is sent a correction back there.
+/// ```
+/// # use shared::engine::net::movement::{MovementValidator, MovementViolation, PlayerPosition};
+/// # use shared::engine::physics::body::RigidBody;
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::{coords::{BlockPos, ChunkPos, WorldPos}};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// world.set_block(BlockPos::new(4, 0, 1), 1);
+/// let mut player = MovementValidator::new(WorldPos::new(1.5, 0.0, 1.5), RigidBody::new(0.6, 1.8));
+/// for _ in 0..20 {
+///     player.tick();
+/// }
+/// assert_eq!(player.check(&world, &PlayerPosition { teleport: 0, position: WorldPos::new(2.5, 0.0, 1.5) }), Ok(()));
+/// // Through the wall, into the air, and across the world.
+/// assert_eq!(player.check(&world, &PlayerPosition { teleport: 0, position: WorldPos::new(5.5, 0.0, 1.5) }), Err(MovementViolation::Collided));
+/// assert_eq!(player.check(&world, &PlayerPosition { teleport: 1, position: WorldPos::new(2.5, 3.0, 1.5) }), Err(MovementViolation::Flying));
+/// assert!(matches!(player.check(&world, &PlayerPosition { teleport: 2, position: WorldPos::new(2.5, 0.0, 30.5) }), Err(MovementViolation::Teleported(_))));
+/// assert_eq!(player.correction().teleport, 3);
+/// assert_eq!(player.position(), WorldPos::new(2.5, 0.0, 1.5));
+/// ```
+pub struct MovementValidator {
+    position: WorldPos,
+    body: RigidBody,
+    may_fly: bool,
+    /// Blocks the player may still move, refilled each tick.
+    allowance: f64,
+    /// Height the player last stood at.
+    ground: f64,
+    /// Moves made since the player last stood on something.
+    air_ticks: u32,
+    /// Id of the newest correction sent.
+    teleport: u32
+}
+
+impl MovementValidator {
+    pub fn new(position: WorldPos, body: RigidBody) -> MovementValidator {
+        return MovementValidator { position, body, may_fly: false, allowance: 0.0, ground: position.y, air_ticks: 0, teleport: 0 };
+    }
+
+    /// Whether the player may fly, such as an operator or a player in a creative game mode.
+    pub fn with_flying(mut self, may_fly: bool) -> Self {
+        self.may_fly = may_fly;
+        return self;
+    }
+
+    pub fn set_may_fly(&mut self, may_fly: bool) {
+        self.may_fly = may_fly;
+    }
+
+    pub fn may_fly(&self) -> bool {
+        return self.may_fly;
+    }
+
+    /// Where the player last moved to, as far as the server is concerned.
+    pub fn position(&self) -> WorldPos {
+        return self.position;
+    }
+
+    /// Id of the newest correction sent. Moves reporting an older one are ignored.
+    pub fn teleport(&self) -> u32 {
+        return self.teleport;
+    }
+
+    /// The correction moving the client back to where the server has the player.
+    pub fn correction(&self) -> PositionCorrection {
+        return PositionCorrection { teleport: self.teleport, position: self.position };
+    }
+
+    /// Move the player without them asking, such as by a command, returning the correction to send their client.
+    pub fn teleport_to(&mut self, position: WorldPos) -> PositionCorrection {
+        self.position = position;
+        self.ground = position.y;
+        self.air_ticks = 0;
+        self.teleport = self.teleport.wrapping_add(1);
+        return self.correction();
+    }
+
+    /// Give the player another tick's worth of movement.
+    pub fn tick(&mut self) {
+        self.allowance = (self.allowance + MAX_SPEED * FIXED_TIMESTEP as f64).min(MAX_SPEED * MAX_MOVE_BURST);
+    }
+
+    /// Check a move from the player's client, moving the player if it's allowed. A refused move bumps the teleport,
+    /// and correction() is what to send back. Moves made before the client applied the newest correction are
+    /// ignored, as they started from where the player no longer is.
+    pub fn check(&mut self, world: &World, movement: &PlayerPosition) -> Result<(), MovementViolation> {
+        if movement.teleport != self.teleport {
+            return Ok(());
+        }
+        let result = self.validate(world, movement.position);
+        if result.is_err() {
+            self.teleport = self.teleport.wrapping_add(1);
+        }
+        return result;
+    }
+
+    fn validate(&mut self, world: &World, position: WorldPos) -> Result<(), MovementViolation> {
+        let offset = position - self.position;
+        let distance = offset.length();
+        if !distance.is_finite() || distance > MAX_MOVE_DISTANCE {
+            return Err(MovementViolation::Teleported(distance));
+        }
+        let cost = if self.may_fly { distance } else { WorldPos::new(offset.x, 0.0, offset.z).length() };
+        if cost > self.allowance {
+            return Err(MovementViolation::TooFast(cost));
+        }
+        let motion = Vec3::new(offset.x as f32, offset.y as f32, offset.z as f32);
+        let collided = collide_aabb(world, self.body.aabb(self.position), motion);
+        if !collided.motion.approx_eq(motion, COLLISION_TOLERANCE) {
+            return Err(MovementViolation::Collided);
+        }
+        if !self.may_fly {
+            if self.is_supported(world, position) {
+                self.ground = position.y;
+                self.air_ticks = 0;
+            } else {
+                self.air_ticks += 1;
+                let rising = offset.y >= 0.0;
+                if position.y > self.ground + MAX_JUMP_HEIGHT || (rising && self.air_ticks > MAX_AIR_TICKS) {
+                    return Err(MovementViolation::Flying);
+                }
+            }
+        }
+        self.allowance -= cost;
+        self.position = position;
+        return Ok(());
+    }
+
+    /// Whether anything is under or around the player's feet to stand on, climb or swim in. Anything other than air
+    /// counts, so fluids and blocks without collision don't look like flying.
+    fn is_supported(&self, world: &World, position: WorldPos) -> bool {
+        let half_width = self.body.width as f64 / 2.0;
+        let min = WorldPos::new(position.x - half_width, position.y - SUPPORT_DEPTH, position.z - half_width).block();
+        let max = WorldPos::new(position.x + half_width, position.y, position.z + half_width).block();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    // Unloaded blocks hold the player up, as they may well be standing on them.
+                    if world.get_block(BlockPos::new(x, y, z)).is_none_or(|id| id != AIR) {
+                        return true;
+                    }
+                }
+            }
+        }
+        return false;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,525 @@
+use std::io;
+
+use crate::engine::{
+    entity::EntityId,
+    math::{coords::{BlockPos, ChunkPos, LocalPos, WorldPos, CHUNK_VOLUME}, direction::Direction, quat::Quat, vector::Vec3}
+};
+
+/// Numeric id written before every packet, so the receiver knows how to decode the rest.
+pub type PacketId = u16;
+
+/// Largest encoded packet accepted. Anything bigger, such as a corrupt length, is refused before allocating.
+pub const MAX_PACKET_SIZE: usize = 1 << 21;
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// A message with a fixed numeric id, encoded the same way by client and server.
+/// Declare packets with the packet! macro.
+pub trait Packet: Wire {
+    const ID: PacketId;
+}
+
+/// Something that can be written to and read back from a packet.
+/// Implemented for primitives, strings, collections and engine types. Structs built from these get it with wire_struct!.
+pub trait Wire: Sized {
+    fn write(&self, writer: &mut PacketWriter);
+
+    fn read(reader: &mut PacketReader) -> io::Result<Self>;
+}
+
+/// Builds the little-endian bytes of a packet.
+#[derive(Clone, Debug, Default)]
+pub struct PacketWriter {
+    data: Vec<u8>
+}
+
+/// Reads values back out of packet bytes in the order they were written. Every read fails on truncated data.
+#[derive(Clone, Debug)]
+pub struct PacketReader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl PacketWriter {
+    pub fn new() -> PacketWriter {
+        return PacketWriter { data: Vec::new() };
+    }
+
+    pub fn write<T: Wire>(&mut self, value: &T) -> &mut PacketWriter {
+        value.write(self);
+        return self;
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Unsigned integer in 7 bit groups, low first, with the high bit set on every byte but the last.
+    /// Values below 128 take a single byte.
+    /// ```
+    /// # use shared::engine::net::packet::{PacketReader, PacketWriter};
+    /// let mut writer = PacketWriter::new();
+    /// writer.write_var_u64(1);
+    /// writer.write_var_u64(300);
+    /// writer.write_var_u64(u64::MAX);
+    /// assert_eq!(writer.len(), 1 + 2 + 10);
+    /// let bytes = writer.into_bytes();
+    /// let mut reader = PacketReader::new(&bytes);
+    /// assert_eq!(reader.read_var_u64().unwrap(), 1);
+    /// assert_eq!(reader.read_var_u64().unwrap(), 300);
+    /// assert_eq!(reader.read_var_u64().unwrap(), u64::MAX);
+    /// assert!(reader.read_var_u64().is_err());
+    /// ```
+    pub fn write_var_u64(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.data.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.data.push(value as u8);
+    }
+
+    /// Signed integer zigzag encoded, so small negative numbers are as short as small positive ones.
+    pub fn write_var_i64(&mut self, value: i64) {
+        self.write_var_u64(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    /// Byte length as a var int, then the bytes.
+    pub fn write_byte_array(&mut self, bytes: &[u8]) {
+        self.write_var_u64(bytes.len() as u64);
+        self.write_bytes(bytes);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.data.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.data.is_empty();
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        return self.data;
+    }
+}
+
+impl<'a> PacketReader<'a> {
+    pub fn new(data: &'a [u8]) -> PacketReader<'a> {
+        return PacketReader { data, position: 0 };
+    }
+
+    pub fn read<T: Wire>(&mut self) -> io::Result<T> {
+        return T::read(self);
+    }
+
+    pub fn read_u8(&mut self) -> io::Result<u8> {
+        return Ok(self.read_bytes(1)?[0]);
+    }
+
+    pub fn read_bytes(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        if count > self.remaining() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Packet ended early"));
+        }
+        let bytes = &self.data[self.position..self.position + count];
+        self.position += count;
+        return Ok(bytes);
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        return Ok(self.read_bytes(N)?.try_into().unwrap());
+    }
+
+    pub fn read_var_u64(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        return Err(invalid("Var int is too long"));
+    }
+
+    pub fn read_var_i64(&mut self) -> io::Result<i64> {
+        let value = self.read_var_u64()?;
+        return Ok((value >> 1) as i64 ^ -((value & 1) as i64));
+    }
+
+    /// A var int that must fit in T.
+    pub fn read_var<T: TryFrom<u64>>(&mut self) -> io::Result<T> {
+        return T::try_from(self.read_var_u64()?).map_err(|_| invalid("Var int is out of range"));
+    }
+
+    pub fn read_byte_array(&mut self) -> io::Result<&'a [u8]> {
+        let length = self.read_var::<usize>()?;
+        return self.read_bytes(length);
+    }
+
+    pub fn remaining(&self) -> usize {
+        return self.data.len() - self.position;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.remaining() == 0;
+    }
+
+    /// Fail if anything is left unread, which means the packet was not what the reader expected.
+    pub fn finish(&self) -> io::Result<()> {
+        if !self.is_empty() {
+            return Err(invalid("Packet has trailing bytes"));
+        }
+        return Ok(());
+    }
+}
+
+/// Encode a packet as its id followed by its fields.
+/// ```
+/// # use shared::engine::net::packet::{self, PacketId};
+/// # use shared::engine::math::coords::BlockPos;
+/// shared::packet! {
+///     /// Sent when a player breaks a block.
+///     #[derive(Debug, PartialEq)]
+///     pub struct BreakBlock = 12 {
+///         pub position: BlockPos,
+///         pub tool: Option<String>
+///     }
+/// }
+///
+/// let sent = BreakBlock { position: BlockPos::new(-5, 64, 1_000_000), tool: Some("cube:pickaxe".to_string()) };
+/// let bytes = packet::encode(&sent);
+/// assert_eq!(packet::packet_id(&bytes).unwrap(), 12);
+/// assert_eq!(packet::decode::<BreakBlock>(&bytes).unwrap(), sent);
+/// assert!(packet::decode::<BreakBlock>(&bytes[..bytes.len() - 1]).is_err());
+/// ```
+pub fn encode<P: Packet>(packet: &P) -> Vec<u8> {
+    let mut writer = PacketWriter::new();
+    writer.write_var_u64(P::ID as u64);
+    packet.write(&mut writer);
+    debug_assert!(writer.len() <= MAX_PACKET_SIZE, "Packet is too large to send");
+    return writer.into_bytes();
+}
+
+/// Id of an encoded packet, to decide which packet to decode it as.
+pub fn packet_id(data: &[u8]) -> io::Result<PacketId> {
+    return PacketReader::new(data).read_var();
+}
+
+/// Decode a packet, failing if it has a different id, is truncated or has bytes left over.
+pub fn decode<P: Packet>(data: &[u8]) -> io::Result<P> {
+    if data.len() > MAX_PACKET_SIZE {
+        return Err(invalid("Packet is too large"));
+    }
+    let mut reader = PacketReader::new(data);
+    let id: PacketId = reader.read_var()?;
+    if id != P::ID {
+        return Err(invalid(&format!("Expected packet {}, got {}", P::ID, id)));
+    }
+    let packet = P::read(&mut reader)?;
+    reader.finish()?;
+    return Ok(packet);
+}
+
+impl Wire for u8 {
+    fn write(&self, writer: &mut PacketWriter) {
+        writer.write_u8(*self);
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<u8> {
+        return reader.read_u8();
+    }
+}
+
+impl Wire for bool {
+    fn write(&self, writer: &mut PacketWriter) {
+        writer.write_u8(*self as u8);
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<bool> {
+        return match reader.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid("Invalid bool"))
+        };
+    }
+}
+
+macro_rules! impl_wire_var {
+    ($($t:ty),+) => {
+        $(
+            impl Wire for $t {
+                fn write(&self, writer: &mut PacketWriter) {
+                    writer.write_var_u64(*self as u64);
+                }
+
+                fn read(reader: &mut PacketReader) -> io::Result<$t> {
+                    return reader.read_var();
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! impl_wire_zigzag {
+    ($($t:ty),+) => {
+        $(
+            impl Wire for $t {
+                fn write(&self, writer: &mut PacketWriter) {
+                    writer.write_var_i64(*self as i64);
+                }
+
+                fn read(reader: &mut PacketReader) -> io::Result<$t> {
+                    return <$t>::try_from(reader.read_var_i64()?).map_err(|_| invalid("Var int is out of range"));
+                }
+            }
+        )+
+    };
+}
+
+macro_rules! impl_wire_bytes {
+    ($($t:ty),+) => {
+        $(
+            impl Wire for $t {
+                fn write(&self, writer: &mut PacketWriter) {
+                    writer.write_bytes(&self.to_le_bytes());
+                }
+
+                fn read(reader: &mut PacketReader) -> io::Result<$t> {
+                    return Ok(<$t>::from_le_bytes(reader.read_array()?));
+                }
+            }
+        )+
+    };
+}
+
+impl_wire_var!(u16, u32, u64, usize);
+impl_wire_zigzag!(i8, i16, i32, i64);
+impl_wire_bytes!(u128, f32, f64);
+
+impl Wire for String {
+    fn write(&self, writer: &mut PacketWriter) {
+        writer.write_byte_array(self.as_bytes());
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<String> {
+        return String::from_utf8(reader.read_byte_array()?.to_vec()).map_err(|_| invalid("String is not UTF-8"));
+    }
+}
+
+impl<T: Wire> Wire for Vec<T> {
+    fn write(&self, writer: &mut PacketWriter) {
+        writer.write_var_u64(self.len() as u64);
+        for value in self {
+            value.write(writer);
+        }
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<Vec<T>> {
+        let length: usize = reader.read_var()?;
+        // Every value takes at least a byte, so a length beyond the data left is corrupt.
+        if length > reader.remaining() {
+            return Err(invalid("List is longer than the packet"));
+        }
+        return (0..length).map(|_| T::read(reader)).collect();
+    }
+}
+
+impl<T: Wire> Wire for Option<T> {
+    fn write(&self, writer: &mut PacketWriter) {
+        self.is_some().write(writer);
+        if let Some(value) = self {
+            value.write(writer);
+        }
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<Option<T>> {
+        return match bool::read(reader)? {
+            true => Ok(Some(T::read(reader)?)),
+            false => Ok(None)
+        };
+    }
+}
+
+macro_rules! impl_wire_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Wire),+> Wire for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn write(&self, writer: &mut PacketWriter) {
+                let ($($name,)+) = self;
+                $($name.write(writer);)+
+            }
+
+            fn read(reader: &mut PacketReader) -> io::Result<Self> {
+                return Ok(($($name::read(reader)?,)+));
+            }
+        }
+    };
+}
+
+impl_wire_tuple!(A, B);
+impl_wire_tuple!(A, B, C);
+impl_wire_tuple!(A, B, C, D);
+
+/// Zigzag var ints per axis, so nearby positions are short and every position in the world round trips.
+/// Decoding fails on an axis beyond the range of i32.
+/// ```
+/// # use shared::engine::net::packet::{PacketReader, PacketWriter};
+/// # use shared::engine::math::coords::BlockPos;
+/// let position = BlockPos::new(i32::MIN, -32_769, i32::MAX);
+/// let mut writer = PacketWriter::new();
+/// writer.write(&position);
+/// assert_eq!(PacketReader::new(&writer.into_bytes()).read::<BlockPos>().unwrap(), position);
+///
+/// let mut writer = PacketWriter::new();
+/// writer.write(&(i32::MAX as i64 + 1, 0i32, 0i32));
+/// assert!(PacketReader::new(&writer.into_bytes()).read::<BlockPos>().is_err());
+/// ```
+impl Wire for BlockPos {
+    fn write(&self, writer: &mut PacketWriter) {
+        (self.x, self.y, self.z).write(writer);
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<BlockPos> {
+        let (x, y, z) = <(i32, i32, i32)>::read(reader)?;
+        return Ok(BlockPos::new(x, y, z));
+    }
+}
+
+impl Wire for ChunkPos {
+    fn write(&self, writer: &mut PacketWriter) {
+        (self.x, self.y, self.z).write(writer);
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<ChunkPos> {
+        let (x, y, z) = <(i32, i32, i32)>::read(reader)?;
+        return Ok(ChunkPos::new(x, y, z));
+    }
+}
+
+impl Wire for Direction {
+    fn write(&self, writer: &mut PacketWriter) {
+        writer.write_u8(self.index() as u8);
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<Direction> {
+        let index = reader.read_u8()? as usize;
+        if index >= Direction::ALL.len() {
+            return Err(invalid("Unknown direction"));
+        }
+        return Ok(Direction::from_index(index));
+    }
+}
+
+/// Packed into 2 bytes, as it's always within a chunk.
+impl Wire for LocalPos {
+    fn write(&self, writer: &mut PacketWriter) {
+        (self.index() as u16).write(writer);
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<LocalPos> {
+        let index: u16 = reader.read()?;
+        if index as usize >= CHUNK_VOLUME {
+            return Err(invalid("Local position is outside of the chunk"));
+        }
+        return Ok(LocalPos::from_index(index as usize));
+    }
+}
+
+impl Wire for WorldPos {
+    fn write(&self, writer: &mut PacketWriter) {
+        (self.x, self.y, self.z).write(writer);
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<WorldPos> {
+        let (x, y, z) = <(f64, f64, f64)>::read(reader)?;
+        return Ok(WorldPos::new(x, y, z));
+    }
+}
+
+impl Wire for Vec3 {
+    fn write(&self, writer: &mut PacketWriter) {
+        (self.x, self.y, self.z).write(writer);
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<Vec3> {
+        let (x, y, z) = <(f32, f32, f32)>::read(reader)?;
+        return Ok(Vec3::new(x, y, z));
+    }
+}
+
+impl Wire for Quat {
+    fn write(&self, writer: &mut PacketWriter) {
+        (self.x, self.y, self.z, self.w).write(writer);
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<Quat> {
+        let (x, y, z, w) = <(f32, f32, f32, f32)>::read(reader)?;
+        return Ok(Quat::from_xyzw(x, y, z, w));
+    }
+}
+
+impl Wire for EntityId {
+    fn write(&self, writer: &mut PacketWriter) {
+        (self.index(), self.generation()).write(writer);
+    }
+
+    fn read(reader: &mut PacketReader) -> io::Result<EntityId> {
+        let (index, generation) = <(u32, u32)>::read(reader)?;
+        return Ok(EntityId::new(index, generation));
+    }
+}
+
+/// Implement Wire for a struct by writing its fields in order.
+/// ```
+/// # use shared::engine::net::packet::{PacketReader, PacketWriter};
+/// #[derive(Debug, PartialEq)]
+/// struct Slot {
+///     item: String,
+///     count: u8
+/// }
+/// shared::wire_struct!(Slot { item, count });
+///
+/// let slot = Slot { item: "cube:dirt".to_string(), count: 64 };
+/// let mut writer = PacketWriter::new();
+/// writer.write(&vec![slot]);
+/// let bytes = writer.into_bytes();
+/// assert_eq!(PacketReader::new(&bytes).read::<Vec<Slot>>().unwrap()[0].count, 64);
+/// ```
+#[macro_export]
+macro_rules! wire_struct {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl $crate::engine::net::packet::Wire for $name {
+            #[allow(unused_variables)]
+            fn write(&self, writer: &mut $crate::engine::net::packet::PacketWriter) {
+                $($crate::engine::net::packet::Wire::write(&self.$field, writer);)*
+            }
+
+            #[allow(unused_variables)]
+            fn read(reader: &mut $crate::engine::net::packet::PacketReader) -> std::io::Result<$name> {
+                return Ok($name { $($field: $crate::engine::net::packet::Wire::read(reader)?),* });
+            }
+        }
+    };
+}
+
+/// Declare a packet struct with its id, implementing Wire and Packet for it. See encode().
+#[macro_export]
+macro_rules! packet {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident = $id:literal { $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)? }) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty),*
+        }
+
+        $crate::wire_struct!($name { $($field),* });
+
+        impl $crate::engine::net::packet::Packet for $name {
+            const ID: $crate::engine::net::packet::PacketId = $id;
+        }
+    };
+}
\ No newline at end of file
@@ -0,0 +1,253 @@
+use std::collections::VecDeque;
+
+use crate::{
+    engine::{
+        entity::kinematics::FIXED_TIMESTEP,
+        math::{coords::WorldPos, vector::Vec3}
+    },
+    packet, wire_struct
+};
+
+use super::{
+    checksum::{movement_checksum, ChecksumKind, ChecksumMismatch},
+    packet as codec
+};
+
+/// Unacknowledged inputs sent again with every new one, so a lost datagram costs nothing as long as a later one arrives.
+pub const REDUNDANT_INPUTS: usize = 16;
+
+/// Most queued inputs the server runs in one tick, letting a client catch up after a burst of late packets
+/// without moving faster than it could by sending every tick.
+pub const MAX_INPUTS_PER_TICK: usize = 3;
+
+/// Most inputs the server queues for a player. Anything beyond is dropped rather than run late.
+pub const MAX_QUEUED_INPUTS: usize = 20;
+
+/// Blocks per second a player moves with a full length input direction, in the default movement.
+pub const MOVE_SPEED: f32 = 4.3;
+
+/// What the player asked to do over one fixed tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoveInput {
+    pub sequence: u32,
+    /// Direction to move in, up to unit length. Longer directions are clamped rather than trusted.
+    pub direction: Vec3
+}
+
+wire_struct!(MoveInput { sequence, direction });
+
+/// The part of a player that movement simulates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MovementState {
+    pub position: WorldPos,
+    pub velocity: Vec3
+}
+
+wire_struct!(MovementState { position, velocity });
+
+packet! {
+    /// The newest inputs the server hasn't acknowledged yet, oldest first.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct PlayerInput = 40 {
+        pub inputs: Vec<MoveInput>
+    }
+}
+
+packet! {
+    /// The server's movement state for the player after running its inputs up to and including last_input.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PlayerMovement = 41 {
+        pub last_input: Option<u32>,
+        pub state: MovementState
+    }
+}
+
+/// Runs one input for one fixed tick. Must be deterministic, as the client predicts with the same function
+/// the server runs, and any difference shows up as a correction.
+pub type MoveFn = fn(&mut MovementState, &MoveInput);
+
+/// Free movement at MOVE_SPEED without collision, as when flying.
+/// ```
+/// # use shared::engine::net::prediction::{self, MoveInput, MovementState, MOVE_SPEED};
+/// # use shared::engine::entity::kinematics::FIXED_TIMESTEP;
+/// # use shared::engine::math::vector::Vec3;
+/// let mut state = MovementState::default();
+/// prediction::fly(&mut state, &MoveInput { sequence: 0, direction: Vec3::new(100.0, 0.0, 0.0) });
+/// assert!((state.position.x - (MOVE_SPEED * FIXED_TIMESTEP) as f64).abs() < 1e-6);
+/// ```
+pub fn fly(state: &mut MovementState, input: &MoveInput) {
+    let direction = if input.direction.length_squared() > 1.0 { input.direction.normalize() } else { input.direction };
+    state.velocity = direction * MOVE_SPEED;
+    let moved = state.velocity * FIXED_TIMESTEP;
+    state.position = state.position + WorldPos::new(moved.x as f64, moved.y as f64, moved.z as f64);
+}
+
+/// The server's authoritative movement of one player, run from the inputs the client sends.
+pub struct MovementAuthority {
+    state: MovementState,
+    movement: MoveFn,
+    queued: VecDeque<MoveInput>,
+    /// Newest input run or queued, so inputs sent again are ignored.
+    newest: Option<u32>,
+    last_input: Option<u32>
+}
+
+impl MovementAuthority {
+    pub fn new(state: MovementState, movement: MoveFn) -> MovementAuthority {
+        return MovementAuthority { state, movement, queued: VecDeque::new(), newest: None, last_input: None };
+    }
+
+    pub fn state(&self) -> MovementState {
+        return self.state;
+    }
+
+    /// Move the player without an input, such as teleporting. The client is corrected on the next tick.
+    pub fn set_state(&mut self, state: MovementState) {
+        self.state = state;
+    }
+
+    /// Newest input the server ran.
+    pub fn last_input(&self) -> Option<u32> {
+        return self.last_input;
+    }
+
+    /// Queue the new inputs of an encoded PlayerInput from the client. False if the packet isn't one.
+    pub fn handle(&mut self, packet: &[u8]) -> bool {
+        let Ok(packet) = codec::decode::<PlayerInput>(packet) else {
+            return false;
+        };
+        for input in packet.inputs {
+            if self.newest.is_some_and(|newest| input.sequence <= newest) {
+                continue;
+            }
+            self.newest = Some(input.sequence);
+            if self.queued.len() < MAX_QUEUED_INPUTS {
+                self.queued.push_back(input);
+            }
+        }
+        return true;
+    }
+
+    /// Run up to MAX_INPUTS_PER_TICK queued inputs, returning the PlayerMovement to send to the client.
+    pub fn tick(&mut self) -> Vec<u8> {
+        for _ in 0..MAX_INPUTS_PER_TICK {
+            let Some(input) = self.queued.pop_front() else {
+                break;
+            };
+            (self.movement)(&mut self.state, &input);
+            self.last_input = Some(input.sequence);
+        }
+        return codec::encode(&PlayerMovement { last_input: self.last_input, state: self.state });
+    }
+}
+
+/// The client's predicted movement of its own player. Inputs move the player right away instead of a round trip
+/// later, and when the server's state arrives the inputs it hasn't run yet are replayed on top of it.
+pub struct MovementPredictor {
+    state: MovementState,
+    movement: MoveFn,
+    pending: VecDeque<MoveInput>,
+    /// Checksum of the predicted state after each pending input.
+    predicted: VecDeque<u64>,
+    /// Newest acknowledged input and the checksum of the state after it, kept as the server keeps sending it.
+    confirmed: Option<(u32, u64)>,
+    next_sequence: u32,
+    /// Newest input the server has acknowledged, so movement packets arriving out of order are ignored.
+    acknowledged: Option<u32>,
+    correction: f64,
+    mismatch: Option<ChecksumMismatch>
+}
+
+impl MovementPredictor {
+    pub fn new(state: MovementState, movement: MoveFn) -> MovementPredictor {
+        return MovementPredictor { state, movement, pending: VecDeque::new(), predicted: VecDeque::new(), confirmed: None, next_sequence: 0, acknowledged: None, correction: 0.0, mismatch: None };
+    }
+
+    /// Predicted state, including inputs the server hasn't acknowledged.
+    pub fn state(&self) -> MovementState {
+        return self.state;
+    }
+
+    /// Inputs sent and not yet acknowledged.
+    pub fn pending(&self) -> usize {
+        return self.pending.len();
+    }
+
+    /// How far the most recent reconciliation moved the predicted position, for smoothing it out when rendering.
+    /// Zero while predictions match the server.
+    pub fn correction(&self) -> f64 {
+        return self.correction;
+    }
+
+    /// The newest input the server ended up somewhere other than the prediction did, if any since the last call.
+    /// Either the movement function isn't deterministic, or the server moved the player itself.
+    pub fn take_mismatch(&mut self) -> Option<ChecksumMismatch> {
+        return self.mismatch.take();
+    }
+
+    /// Run this tick's input, returning the PlayerInput to send, best over the unreliable channel.
+    pub fn input(&mut self, direction: Vec3) -> Vec<u8> {
+        let input = MoveInput { sequence: self.next_sequence, direction };
+        self.next_sequence += 1;
+        (self.movement)(&mut self.state, &input);
+        self.pending.push_back(input);
+        self.predicted.push_back(movement_checksum(&self.state));
+        let resend = self.pending.len().saturating_sub(REDUNDANT_INPUTS);
+        return codec::encode(&PlayerInput { inputs: self.pending.iter().skip(resend).copied().collect() });
+    }
+
+    /// Reconcile with an encoded PlayerMovement from the server: start from its state and replay the inputs it
+    /// hasn't run. Stale packets arriving after newer ones are ignored. False if the packet isn't one.
+    /// ```
+    /// # use shared::engine::net::prediction::{self, MovementAuthority, MovementPredictor, MovementState};
+    /// # use shared::engine::math::{coords::WorldPos, vector::Vec3};
+    /// let mut server = MovementAuthority::new(MovementState::default(), prediction::fly);
+    /// let mut client = MovementPredictor::new(MovementState::default(), prediction::fly);
+    /// let sent: Vec<_> = (0..5).map(|_| client.input(Vec3::new(1.0, 0.0, 0.0))).collect();
+    /// // The client has moved before the server has heard of any of it.
+    /// assert!(client.state().position.x > 0.0);
+    /// server.handle(&sent[1]);
+    /// client.handle(&server.tick());
+    /// assert_eq!(client.pending(), 3);
+    /// assert_eq!(client.correction(), 0.0);
+    ///
+    /// server.set_state(MovementState { position: WorldPos::new(0.0, 50.0, 0.0), velocity: Vec3::ZERO });
+    /// client.handle(&server.tick());
+    /// assert_eq!(client.state().position.y, 50.0);
+    /// assert!(client.correction() > 40.0);
+    /// // The server ran input 1 again from somewhere else, so it ended up elsewhere than predicted.
+    /// assert_eq!(client.take_mismatch().unwrap().step, 1);
+    /// ```
+    pub fn handle(&mut self, packet: &[u8]) -> bool {
+        let Ok(movement) = codec::decode::<PlayerMovement>(packet) else {
+            return false;
+        };
+        if self.acknowledged.is_some_and(|acknowledged| movement.last_input.is_none_or(|last_input| last_input < acknowledged)) {
+            return true;
+        }
+        if let Some(last_input) = movement.last_input {
+            self.acknowledged = Some(last_input);
+            while self.pending.front().is_some_and(|input| input.sequence <= last_input) {
+                let input = self.pending.pop_front().unwrap();
+                self.confirmed = Some((input.sequence, self.predicted.pop_front().unwrap()));
+            }
+            let actual = movement_checksum(&movement.state);
+            if let Some((sequence, predicted)) = self.confirmed.as_mut().filter(|(sequence, _)| *sequence == last_input) {
+                if *predicted != actual {
+                    self.mismatch = Some(ChecksumMismatch { kind: ChecksumKind::Movement, step: *sequence as u64, expected: actual, actual: *predicted });
+                    // Reported once, rather than with every movement packet until the next input is acknowledged.
+                    *predicted = actual;
+                }
+            }
+        }
+        let predicted = self.state.position;
+        self.state = movement.state;
+        // Later predictions now start from the server's state.
+        for (input, checksum) in self.pending.iter().zip(self.predicted.iter_mut()) {
+            (self.movement)(&mut self.state, input);
+            *checksum = movement_checksum(&self.state);
+        }
+        self.correction = self.state.position.distance(predicted);
+        return true;
+    }
+}
\ No newline at end of file
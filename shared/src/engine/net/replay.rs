@@ -0,0 +1,330 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{atomic::{AtomicU64, Ordering}, Mutex}
+};
+
+use crate::engine::entity::kinematics::TICKS_PER_SECOND;
+
+use super::{
+    packet::{PacketReader, PacketWriter},
+    transport::{Channel, PacketLink, Priority}
+};
+
+/// Version of the replay encoding, stored after its magic.
+pub const REPLAY_FORMAT_VERSION: u8 = 1;
+
+/// Directory replays are recorded into by default.
+pub const REPLAY_DIRECTORY: &str = "replays";
+
+/// Extension of replay files.
+pub const REPLAY_EXTENSION: &str = "replay";
+
+/// Fastest and slowest a replay can be played back, as a multiple of real time.
+pub const MAX_REPLAY_SPEED: f64 = 16.0;
+pub const MIN_REPLAY_SPEED: f64 = 1.0 / 16.0;
+
+const MAGIC: [u8; 4] = *b"CURP";
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// A packet the server sent, and the client tick it arrived on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayRecord {
+    pub tick: u64,
+    pub packet: Vec<u8>
+}
+
+/// Writes a replay: the magic and format version, then a record per packet until the end of the file. A record is
+/// the ticks since the previous record as a var int, then the packet length prefixed.
+pub struct ReplayWriter<W: Write> {
+    out: W,
+    tick: u64
+}
+
+impl ReplayWriter<BufWriter<File>> {
+    /// Start a replay file, creating its directory if needed.
+    pub fn create(path: &Path) -> io::Result<ReplayWriter<BufWriter<File>>> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        return ReplayWriter::new(BufWriter::new(File::create(path)?));
+    }
+}
+
+impl<W: Write> ReplayWriter<W> {
+    pub fn new(mut out: W) -> io::Result<ReplayWriter<W>> {
+        out.write_all(&MAGIC)?;
+        out.write_all(&[REPLAY_FORMAT_VERSION])?;
+        return Ok(ReplayWriter { out, tick: 0 });
+    }
+
+    /// Add a packet received on a tick. Ticks can't go backwards, as records only store how far they moved on.
+    pub fn record(&mut self, tick: u64, packet: &[u8]) -> io::Result<()> {
+        if tick < self.tick {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Replay tick went back from {} to {}", self.tick, tick)));
+        }
+        let mut writer = PacketWriter::new();
+        writer.write_var_u64(tick - self.tick);
+        writer.write_byte_array(packet);
+        self.out.write_all(&writer.into_bytes())?;
+        self.tick = tick;
+        return Ok(());
+    }
+
+    /// Tick of the newest record.
+    pub fn tick(&self) -> u64 {
+        return self.tick;
+    }
+
+    /// Flush what's recorded so far, returning the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        return Ok(self.out);
+    }
+}
+
+/// Every packet of a recorded replay, in the order they arrived.
+/// ```
+/// # use shared::engine::net::replay::{Replay, ReplayRecord, ReplayWriter};
+/// let mut writer = ReplayWriter::new(Vec::new()).unwrap();
+/// writer.record(0, b"chunk").unwrap();
+/// writer.record(40, b"block").unwrap();
+/// assert!(writer.record(39, b"late").is_err());
+/// let bytes = writer.finish().unwrap();
+///
+/// let replay = Replay::from_bytes(&bytes).unwrap();
+/// assert_eq!(replay.records()[1], ReplayRecord { tick: 40, packet: b"block".to_vec() });
+/// assert_eq!(replay.length(), 40);
+/// // A recording cut off partway through a record keeps the records before it.
+/// assert_eq!(Replay::from_bytes(&bytes[..bytes.len() - 2]).unwrap().records().len(), 1);
+/// assert!(Replay::from_bytes(b"CUBK\x01").is_err());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Replay {
+    records: Vec<ReplayRecord>
+}
+
+impl Replay {
+    pub fn open(path: &Path) -> io::Result<Replay> {
+        return Replay::from_bytes(&fs::read(path)?);
+    }
+
+    /// Read a replay. Recording stops wherever the client did, so a record cut off at the end is dropped rather
+    /// than failing the whole replay.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Replay> {
+        let mut reader = PacketReader::new(bytes);
+        if reader.read_array::<4>().ok() != Some(MAGIC) {
+            return Err(invalid("Not a replay"));
+        }
+        let version = reader.read_u8()?;
+        if version != REPLAY_FORMAT_VERSION {
+            return Err(invalid(&format!("Unsupported replay version {}", version)));
+        }
+        let mut records = Vec::new();
+        let mut tick = 0u64;
+        while !reader.is_empty() {
+            let record = reader.read_var_u64().and_then(|delta| Ok((delta, reader.read_byte_array()?)));
+            let (delta, packet) = match record {
+                Ok(record) => record,
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error)
+            };
+            tick = tick.checked_add(delta).ok_or_else(|| invalid("Replay tick is out of range"))?;
+            records.push(ReplayRecord { tick, packet: packet.to_vec() });
+        }
+        return Ok(Replay { records });
+    }
+
+    pub fn records(&self) -> &[ReplayRecord] {
+        return &self.records;
+    }
+
+    /// Tick of the last packet.
+    pub fn length(&self) -> u64 {
+        return self.records.last().map_or(0, |record| record.tick);
+    }
+}
+
+/// What a RecordingLink writes to, and the error that stopped it if writing failed.
+struct Recording<W: Write> {
+    writer: Option<ReplayWriter<W>>,
+    error: Option<io::Error>
+}
+
+/// Wraps the client's link to the server, recording every packet it receives into a replay. The client sets the
+/// tick as it runs, so playback spaces packets out the way they arrived. Failing to write stops the recording
+/// without affecting the link, and the error can be taken to tell the player.
+/// ```
+/// # use shared::engine::net::{loopback::{LoopbackNetwork, NetworkConditions}, replay::{RecordingLink, Replay, ReplayWriter}, transport::{Channel, PacketLink}};
+/// let network = LoopbackNetwork::new(1);
+/// let (client, server) = network.connect(NetworkConditions::PERFECT);
+/// let client = RecordingLink::new(client, ReplayWriter::new(Vec::new()).unwrap());
+/// server.send(Channel::Reliable, b"hello".to_vec());
+/// assert_eq!(client.receive(), vec![b"hello".to_vec()]);
+/// client.set_tick(3);
+/// server.send(Channel::Reliable, b"world".to_vec());
+/// client.receive();
+///
+/// let replay = Replay::from_bytes(&client.finish().unwrap()).unwrap();
+/// assert_eq!(replay.records().iter().map(|record| record.tick).collect::<Vec<_>>(), vec![0, 3]);
+/// ```
+pub struct RecordingLink<L: PacketLink, W: Write> {
+    link: L,
+    tick: AtomicU64,
+    recording: Mutex<Recording<W>>
+}
+
+impl<L: PacketLink, W: Write> RecordingLink<L, W> {
+    pub fn new(link: L, writer: ReplayWriter<W>) -> RecordingLink<L, W> {
+        return RecordingLink { link, tick: AtomicU64::new(0), recording: Mutex::new(Recording { writer: Some(writer), error: None }) };
+    }
+
+    pub fn link(&self) -> &L {
+        return &self.link;
+    }
+
+    /// Record packets received from now on as arriving on a tick.
+    pub fn set_tick(&self, tick: u64) {
+        self.tick.store(tick, Ordering::Relaxed);
+    }
+
+    /// Whether packets are still being recorded, rather than writing having failed.
+    pub fn is_recording(&self) -> bool {
+        return self.recording.lock().unwrap().writer.is_some();
+    }
+
+    /// The error that stopped the recording, once.
+    pub fn take_error(&self) -> Option<io::Error> {
+        return self.recording.lock().unwrap().error.take();
+    }
+
+    /// Stop recording, flushing the replay and returning what it was written to.
+    pub fn finish(self) -> io::Result<W> {
+        let recording = self.recording.into_inner().unwrap();
+        if let Some(error) = recording.error {
+            return Err(error);
+        }
+        return recording.writer.ok_or_else(|| io::Error::other("Recording already failed"))?.finish();
+    }
+}
+
+impl<L: PacketLink, W: Write> PacketLink for RecordingLink<L, W> {
+    fn send_with_priority(&self, channel: Channel, priority: Priority, packet: Vec<u8>) {
+        self.link.send_with_priority(channel, priority, packet);
+    }
+
+    fn receive(&self) -> Vec<Vec<u8>> {
+        let packets = self.link.receive();
+        let mut recording = self.recording.lock().unwrap();
+        if let Some(writer) = recording.writer.as_mut() {
+            let tick = self.tick.load(Ordering::Relaxed).max(writer.tick());
+            if let Err(error) = packets.iter().try_for_each(|packet| writer.record(tick, packet)) {
+                recording.writer = None;
+                recording.error = Some(error);
+            }
+        }
+        return packets;
+    }
+
+    fn is_open(&self) -> bool {
+        return self.link.is_open();
+    }
+}
+
+/// Plays a replay back over time, handing out its packets as their ticks come up. It can be paused, sped up or
+/// slowed down, and moved to any tick. Packets only make sense applied in order from the start, so moving back
+/// means starting over.
+/// ```
+/// # use shared::engine::net::replay::{Replay, ReplayPlayer, ReplayWriter};
+/// let mut writer = ReplayWriter::new(Vec::new()).unwrap();
+/// for tick in [0, 10, 20, 30] {
+///     writer.record(tick, &[tick as u8]).unwrap();
+/// }
+/// let mut player = ReplayPlayer::new(Replay::from_bytes(&writer.finish().unwrap()).unwrap());
+/// assert_eq!(player.advance(0.0).len(), 1);
+/// // Half a second is ten ticks, or twenty at double speed.
+/// assert_eq!(player.advance(0.5)[0].packet, vec![10]);
+/// player.set_speed(2.0);
+/// assert_eq!(player.advance(0.25)[0].packet, vec![20]);
+/// assert!(!player.is_finished());
+///
+/// // Going back starts over, replaying everything up to the new tick.
+/// assert!(player.seek(15.0));
+/// assert_eq!(player.advance(0.0).len(), 2);
+/// player.set_paused(true);
+/// assert!(player.advance(10.0).is_empty());
+/// ```
+pub struct ReplayPlayer {
+    replay: Replay,
+    /// Index of the next record to hand out.
+    next: usize,
+    /// Ticks played, including part of the current one.
+    tick: f64,
+    speed: f64,
+    paused: bool
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> ReplayPlayer {
+        return ReplayPlayer { replay, next: 0, tick: 0.0, speed: 1.0, paused: false };
+    }
+
+    pub fn replay(&self) -> &Replay {
+        return &self.replay;
+    }
+
+    /// Ticks played so far.
+    pub fn tick(&self) -> f64 {
+        return self.tick;
+    }
+
+    pub fn speed(&self) -> f64 {
+        return self.speed;
+    }
+
+    /// Play at a multiple of real time, between MIN_REPLAY_SPEED and MAX_REPLAY_SPEED.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.clamp(MIN_REPLAY_SPEED, MAX_REPLAY_SPEED);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        return self.paused;
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Whether every packet has been handed out.
+    pub fn is_finished(&self) -> bool {
+        return self.next == self.replay.records.len();
+    }
+
+    /// Move to a tick, returning true if that's back before packets already handed out. Then everything built
+    /// from them, such as the client's world, has to be cleared, as advance() hands them out again from the start.
+    pub fn seek(&mut self, tick: f64) -> bool {
+        let tick = tick.max(0.0);
+        let restart = self.next > 0 && self.replay.records[self.next - 1].tick as f64 > tick;
+        if restart {
+            self.next = 0;
+        }
+        self.tick = tick;
+        return restart;
+    }
+
+    /// Play on by some real time, unless paused, returning the packets whose ticks came up.
+    pub fn advance(&mut self, seconds: f64) -> &[ReplayRecord] {
+        if !self.paused {
+            self.tick = (self.tick + seconds * self.speed * TICKS_PER_SECOND as f64).min(self.replay.length() as f64);
+        }
+        let start = self.next;
+        while self.next < self.replay.records.len() && self.replay.records[self.next].tick as f64 <= self.tick {
+            self.next += 1;
+        }
+        return &self.replay.records[start..self.next];
+    }
+}
\ No newline at end of file
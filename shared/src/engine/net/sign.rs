@@ -0,0 +1,141 @@
+use std::{fmt, io};
+
+use crate::{
+    engine::{
+        block::sign::{self, SignText, SignTextError},
+        math::coords::{BlockPos, ChunkPos, WorldPos},
+        world::World
+    },
+    packet
+};
+
+use super::packet::{self as codec, Packet};
+
+packet! {
+    /// Sent to the player who placed a sign, so their client opens the editor for it.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct OpenSignEditor = 90 {
+        pub block: BlockPos
+    }
+}
+
+packet! {
+    /// Sent by the client when its player finishes editing a sign.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct EditSign = 91 {
+        pub block: BlockPos,
+        pub lines: Vec<String>
+    }
+}
+
+packet! {
+    /// The text of a sign, sent to every client with its chunk when the text changes or the chunk is sent.
+    /// Also sent back to a player whose edit was refused, to undo what their client predicted.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct SignUpdate = 92 {
+        pub block: BlockPos,
+        pub lines: Vec<String>
+    }
+}
+
+/// Reason the server refused a player's sign edit.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SignEditError {
+    NotLoaded,
+    NotASign,
+    /// A protected region doesn't allow it, or the player isn't the one who placed the sign.
+    NotAllowed,
+    /// Distance from the player's eyes to the sign.
+    OutOfReach(f32),
+    InvalidText(SignTextError)
+}
+
+impl fmt::Display for SignEditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            SignEditError::NotLoaded => write!(f, "the sign isn't loaded"),
+            SignEditError::NotASign => write!(f, "there's no sign there"),
+            SignEditError::NotAllowed => write!(f, "the player isn't allowed to write on the sign"),
+            SignEditError::OutOfReach(distance) => write!(f, "the sign is {:.1} blocks away, out of reach", distance),
+            SignEditError::InvalidText(error) => write!(f, "{}", error)
+        };
+    }
+}
+
+impl std::error::Error for SignEditError {}
+
+/// Check a player may set the text of a sign, returning the validated text.
+/// The server passes REACH_DISTANCE plus REACH_TOLERANCE, as for breaking blocks.
+/// ```
+/// # use shared::engine::block::sign::{self, SignText, SignTextError};
+/// # use shared::engine::net::sign::{self as sign_net, EditSign, SignEditError};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos, WorldPos};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// let block = BlockPos::new(2, 1, 2);
+/// let eye = WorldPos::new(2.5, 2.6, 4.5);
+/// let edit = EditSign { block, lines: vec!["Hello".to_string()] };
+/// assert_eq!(sign_net::validate_edit(&world, eye, &edit, 5.0), Err(SignEditError::NotASign));
+///
+/// sign::set_sign_text(&world, block, SignText::default());
+/// assert_eq!(sign_net::validate_edit(&world, eye, &edit, 5.0), Ok(SignText::new(&["Hello"]).unwrap()));
+/// assert!(matches!(sign_net::validate_edit(&world, eye, &edit, 1.0), Err(SignEditError::OutOfReach(_))));
+/// let long = EditSign { block, lines: vec!["x".repeat(30)] };
+/// assert_eq!(sign_net::validate_edit(&world, eye, &long, 5.0), Err(SignEditError::InvalidText(SignTextError::LineTooLong { line: 0, chars: 30 })));
+/// ```
+pub fn validate_edit(world: &World, eye: WorldPos, edit: &EditSign, reach: f32) -> Result<SignText, SignEditError> {
+    if !world.is_loaded(edit.block.chunk()) {
+        return Err(SignEditError::NotLoaded);
+    }
+    if sign::sign_text(world, edit.block).is_none() {
+        return Err(SignEditError::NotASign);
+    }
+    let distance = eye.distance(edit.block.center()) as f32;
+    if distance > reach {
+        return Err(SignEditError::OutOfReach(distance));
+    }
+    return SignText::new(&edit.lines).map_err(SignEditError::InvalidText);
+}
+
+/// Encoded SignUpdate for the sign at a block, or None if there's no sign there.
+pub fn sign_update(world: &World, block: BlockPos) -> Option<Vec<u8>> {
+    let text = sign::sign_text(world, block)?;
+    return Some(codec::encode(&SignUpdate { block, lines: text.lines().to_vec() }));
+}
+
+/// Encoded SignUpdate packets for every sign with text in a chunk, sent after the chunk itself.
+pub fn chunk_signs(world: &World, chunk: ChunkPos) -> Vec<Vec<u8>> {
+    let Some(entities) = world.block_entities(chunk) else {
+        return Vec::new();
+    };
+    let blocks: Vec<BlockPos> = entities.read().unwrap().iter().map(|(block, _)| *block).collect();
+    return blocks.into_iter()
+        .filter(|block| sign::sign_text(world, *block).is_some_and(|text| !text.is_empty()))
+        .filter_map(|block| sign_update(world, block))
+        .collect();
+}
+
+/// Apply a sign update from the server to the client's world. False if the packet wasn't one.
+/// ```
+/// # use shared::engine::block::sign;
+/// # use shared::engine::net::{sign::{self as sign_net, SignUpdate}, packet};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// let block = BlockPos::new(1, 2, 3);
+/// let update = packet::encode(&SignUpdate { block, lines: vec!["North".to_string()] });
+/// assert!(sign_net::apply_packet(&world, &update).unwrap());
+/// assert_eq!(sign::sign_text(&world, block).unwrap().lines()[0], "North");
+/// assert_eq!(sign_net::chunk_signs(&world, ChunkPos::ORIGIN), vec![sign_net::sign_update(&world, block).unwrap()]);
+/// ```
+pub fn apply_packet(world: &World, packet: &[u8]) -> io::Result<bool> {
+    if codec::packet_id(packet)? != <SignUpdate as Packet>::ID {
+        return Ok(false);
+    }
+    let update = codec::decode::<SignUpdate>(packet)?;
+    let text = SignText::new(&update.lines).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))?;
+    sign::set_sign_text(world, update.block, text);
+    return Ok(true);
+}
@@ -0,0 +1,345 @@
+use std::{any::TypeId, collections::{BTreeMap, HashMap, VecDeque}, io};
+
+use crate::{
+    engine::entity::{kinematics::{Transform, Velocity}, storage::Component, Entities, EntityId},
+    packet, wire_struct
+};
+
+use super::packet::{self as codec, Packet, PacketReader, PacketWriter, Wire};
+
+/// Snapshots kept by each side to delta against. A client that hasn't acknowledged any of them gets a full snapshot.
+pub const SNAPSHOT_HISTORY: usize = 32;
+
+wire_struct!(Transform { position, rotation });
+wire_struct!(Velocity { linear, angular });
+
+/// Changes to one entity since the baseline snapshot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntityDelta {
+    /// Entity id on the server. Clients map it to their own with SnapshotReceiver::local().
+    pub id: EntityId,
+    /// Components added or changed, by registration index, with their new data.
+    pub changed: Vec<(u16, Vec<u8>)>,
+    /// Components removed, by registration index.
+    pub removed: Vec<u16>
+}
+
+wire_struct!(EntityDelta { id, changed, removed });
+
+packet! {
+    /// Entity state as changes since a baseline snapshot the client acknowledged, or all of it without a baseline.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct EntitySnapshot = 30 {
+        pub sequence: u32,
+        pub baseline: Option<u32>,
+        /// Entities that are new or changed since the baseline. Unchanged entities aren't sent.
+        pub entities: Vec<EntityDelta>,
+        /// Entities the client should forget.
+        pub destroyed: Vec<EntityId>
+    }
+}
+
+packet! {
+    /// Sent by the client for every snapshot it applies, letting the server delta against it.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct SnapshotAck = 31 {
+        pub sequence: u32
+    }
+}
+
+packet! {
+    /// Sent by the client when it no longer has a snapshot's baseline, asking for a full snapshot.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct SnapshotResync = 32 {}
+}
+
+type ErasedEncoder = Box<dyn Fn(&Entities, &[EntityId]) -> Vec<Option<Vec<u8>>> + Send + Sync>;
+type ErasedApplier = Box<dyn Fn(&Entities, EntityId, Option<&[u8]>) -> io::Result<()> + Send + Sync>;
+
+struct SnapshotComponent {
+    type_id: TypeId,
+    encode: ErasedEncoder,
+    apply: ErasedApplier
+}
+
+/// Encoded components of every entity in a snapshot, indexed by registration.
+type SnapshotState = BTreeMap<EntityId, Vec<Option<Vec<u8>>>>;
+
+/// Component types sent in snapshots. Components are sent by registration index, so the server and client must
+/// register the same types in the same order, which the handshake's version check makes sure of.
+#[derive(Default)]
+pub struct SnapshotComponents {
+    types: Vec<SnapshotComponent>
+}
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+impl SnapshotComponents {
+    pub fn new() -> SnapshotComponents {
+        return SnapshotComponents::default();
+    }
+
+    /// Send a component type in snapshots. Registering a type twice does nothing.
+    pub fn register<T: Component + Wire>(&mut self) {
+        if self.is_registered::<T>() {
+            return;
+        }
+        debug_assert!(self.types.len() < u16::MAX as usize, "Too many snapshot components");
+        self.types.push(SnapshotComponent {
+            type_id: TypeId::of::<T>(),
+            encode: Box::new(|entities, ids| {
+                let storage = entities.storage::<T>();
+                let storage = storage.read().unwrap();
+                return ids.iter().map(|id| storage.get(*id).map(|component| {
+                    let mut writer = PacketWriter::new();
+                    writer.write(component);
+                    writer.into_bytes()
+                })).collect();
+            }),
+            apply: Box::new(|entities, id, data| {
+                match data {
+                    Some(data) => {
+                        let mut reader = PacketReader::new(data);
+                        let component = reader.read::<T>()?;
+                        reader.finish()?;
+                        let _ = entities.insert(id, component);
+                    },
+                    None => {
+                        entities.remove::<T>(id);
+                    }
+                }
+                return Ok(());
+            })
+        });
+    }
+
+    pub fn is_registered<T: Component>(&self) -> bool {
+        return self.types.iter().any(|registered| registered.type_id == TypeId::of::<T>());
+    }
+
+    pub fn len(&self) -> usize {
+        return self.types.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.types.is_empty();
+    }
+
+    fn state(&self, entities: &Entities, ids: &[EntityId]) -> SnapshotState {
+        let ids: Vec<EntityId> = ids.iter().copied().filter(|id| entities.is_alive(*id)).collect();
+        let encoded: Vec<Vec<Option<Vec<u8>>>> = self.types.iter().map(|registered| (registered.encode)(entities, &ids)).collect();
+        return ids.iter().enumerate()
+            .map(|(entity, id)| (*id, encoded.iter().map(|components| components[entity].clone()).collect()))
+            .collect();
+    }
+}
+
+/// Changes that turn the baseline state into the current one.
+fn diff(baseline: &SnapshotState, current: &SnapshotState) -> (Vec<EntityDelta>, Vec<EntityId>) {
+    let mut deltas = Vec::new();
+    for (id, components) in current.iter() {
+        let before = baseline.get(id);
+        let mut delta = EntityDelta { id: *id, changed: Vec::new(), removed: Vec::new() };
+        for (index, component) in components.iter().enumerate() {
+            let previous = before.and_then(|before| before[index].as_ref());
+            match (previous, component) {
+                (previous, Some(data)) if previous != Some(data) => delta.changed.push((index as u16, data.clone())),
+                (Some(_), None) => delta.removed.push(index as u16),
+                _ => ()
+            }
+        }
+        // New entities are sent even without components, so the client knows they exist.
+        if before.is_none() || !delta.changed.is_empty() || !delta.removed.is_empty() {
+            deltas.push(delta);
+        }
+    }
+    let destroyed = baseline.keys().copied().filter(|id| !current.contains_key(id)).collect();
+    return (deltas, destroyed);
+}
+
+/// Builds the snapshots sent to one client, each only holding what changed since the newest snapshot the client
+/// acknowledged. Lost snapshots need no resending, as the next one is still against a snapshot the client has.
+pub struct SnapshotSender {
+    sequence: u32,
+    /// Snapshots sent and not yet older than the acknowledged one, oldest first.
+    history: VecDeque<(u32, SnapshotState)>,
+    acknowledged: Option<u32>
+}
+
+impl SnapshotSender {
+    /// A sender for a client that just joined, whose first snapshot is full.
+    pub fn new() -> SnapshotSender {
+        return SnapshotSender { sequence: 0, history: VecDeque::new(), acknowledged: None };
+    }
+
+    /// Newest snapshot the client acknowledged.
+    pub fn acknowledged(&self) -> Option<u32> {
+        return self.acknowledged;
+    }
+
+    /// Note that the client applied a snapshot. Older acknowledgements arriving late are ignored.
+    pub fn acknowledge(&mut self, sequence: u32) {
+        if sequence >= self.sequence || self.acknowledged.is_some_and(|acknowledged| sequence <= acknowledged) {
+            return;
+        }
+        self.acknowledged = Some(sequence);
+        while self.history.front().is_some_and(|(sent, _)| *sent < sequence) {
+            self.history.pop_front();
+        }
+    }
+
+    /// Send the next snapshot in full, as the client lost track of its baseline.
+    pub fn resync(&mut self) {
+        self.acknowledged = None;
+        self.history.clear();
+    }
+
+    /// Handle an encoded SnapshotAck or SnapshotResync from the client. False if the packet is neither.
+    pub fn handle(&mut self, packet: &[u8]) -> bool {
+        if let Ok(ack) = codec::decode::<SnapshotAck>(packet) {
+            self.acknowledge(ack.sequence);
+            return true;
+        }
+        if codec::decode::<SnapshotResync>(packet).is_ok() {
+            self.resync();
+            return true;
+        }
+        return false;
+    }
+
+    /// The next snapshot of the given entities, which are the ones the client can see, such as those
+    /// InterestManager::known() lists. Entities left out since the baseline are destroyed on the client.
+    /// Send it with Priority::High, as it's what the player sees.
+    /// ```
+    /// # use shared::engine::entity::{kinematics::Transform, Entities};
+    /// # use shared::engine::net::snapshot::{SnapshotComponents, SnapshotReceiver, SnapshotSender};
+    /// # use shared::engine::math::coords::WorldPos;
+    /// let mut components = SnapshotComponents::new();
+    /// components.register::<Transform>();
+    /// let (server, client) = (Entities::new(), Entities::new());
+    /// let ids: Vec<_> = (0..10).map(|_| server.spawn()).collect();
+    /// for id in ids.iter() {
+    ///     server.insert(*id, Transform::default()).unwrap();
+    /// }
+    /// let mut sender = SnapshotSender::new();
+    /// let mut receiver = SnapshotReceiver::new();
+    /// let full = sender.snapshot(&components, &server, &ids);
+    /// sender.handle(&receiver.apply(&components, &client, &full).unwrap().unwrap());
+    /// assert_eq!(client.len(), 10);
+    ///
+    /// server.insert(ids[3], Transform::new(WorldPos::new(1.0, 2.0, 3.0))).unwrap();
+    /// let delta = sender.snapshot(&components, &server, &ids);
+    /// assert!(delta.len() * 5 < full.len());
+    /// receiver.apply(&components, &client, &delta).unwrap();
+    /// assert_eq!(client.get::<Transform>(receiver.local(ids[3]).unwrap()), server.get::<Transform>(ids[3]));
+    /// ```
+    pub fn snapshot(&mut self, components: &SnapshotComponents, entities: &Entities, visible: &[EntityId]) -> Vec<u8> {
+        let current = components.state(entities, visible);
+        let empty = SnapshotState::new();
+        let baseline = self.acknowledged.and_then(|acknowledged| self.history.iter().find(|(sent, _)| *sent == acknowledged));
+        let (entities, destroyed) = diff(baseline.map_or(&empty, |(_, state)| state), &current);
+        let packet = EntitySnapshot { sequence: self.sequence, baseline: baseline.map(|(sent, _)| *sent), entities, destroyed };
+
+        self.history.push_back((self.sequence, current));
+        if self.history.len() > SNAPSHOT_HISTORY {
+            // The client fell too far behind, so the acknowledged snapshot may be gone and the next one full.
+            self.history.pop_front();
+        }
+        self.sequence += 1;
+        return codec::encode(&packet);
+    }
+}
+
+impl Default for SnapshotSender {
+    fn default() -> SnapshotSender {
+        return SnapshotSender::new();
+    }
+}
+
+/// Applies snapshots from the server to the client's entities, which have their own ids.
+pub struct SnapshotReceiver {
+    /// Snapshots applied, oldest first, any of which the server may delta against.
+    history: VecDeque<(u32, SnapshotState)>,
+    local: HashMap<EntityId, EntityId>
+}
+
+impl SnapshotReceiver {
+    pub fn new() -> SnapshotReceiver {
+        return SnapshotReceiver { history: VecDeque::new(), local: HashMap::new() };
+    }
+
+    /// The client's id for an entity the server sent.
+    pub fn local(&self, server: EntityId) -> Option<EntityId> {
+        return self.local.get(&server).copied();
+    }
+
+    /// Sequence of the newest snapshot applied.
+    pub fn latest(&self) -> Option<u32> {
+        return self.history.back().map(|(sequence, _)| *sequence);
+    }
+
+    /// Apply an encoded EntitySnapshot, returning the packet to send back: an acknowledgement, or a resync request
+    /// if the snapshot's baseline is gone. Snapshots older than the newest applied, arriving out of order over the
+    /// unreliable channel, are dropped. Packets that aren't snapshots are left alone.
+    pub fn apply(&mut self, components: &SnapshotComponents, entities: &Entities, packet: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        if codec::packet_id(packet)? != <EntitySnapshot as Packet>::ID {
+            return Ok(None);
+        }
+        let snapshot = codec::decode::<EntitySnapshot>(packet)?;
+        if self.latest().is_some_and(|latest| snapshot.sequence <= latest) {
+            return Ok(None);
+        }
+        let mut state = match snapshot.baseline {
+            Some(baseline) => match self.history.iter().find(|(applied, _)| *applied == baseline) {
+                Some((_, state)) => state.clone(),
+                None => return Ok(Some(codec::encode(&SnapshotResync {})))
+            },
+            None => SnapshotState::new()
+        };
+        for id in snapshot.destroyed.iter() {
+            state.remove(id);
+        }
+        for delta in snapshot.entities.iter() {
+            let entity = state.entry(delta.id).or_insert_with(|| vec![None; components.len()]);
+            for (index, data) in delta.changed.iter() {
+                *entity.get_mut(*index as usize).ok_or_else(|| invalid("Unknown snapshot component"))? = Some(data.clone());
+            }
+            for index in delta.removed.iter() {
+                *entity.get_mut(*index as usize).ok_or_else(|| invalid("Unknown snapshot component"))? = None;
+            }
+        }
+
+        // Apply the difference from what the client has now, rather than from the baseline.
+        let empty = SnapshotState::new();
+        let (changes, destroyed) = diff(self.history.back().map_or(&empty, |(_, state)| state), &state);
+        for id in destroyed {
+            if let Some(local) = self.local.remove(&id) {
+                entities.despawn(local);
+            }
+        }
+        for delta in changes {
+            let local = *self.local.entry(delta.id).or_insert_with(|| entities.spawn());
+            for (index, data) in delta.changed.iter() {
+                (components.types[*index as usize].apply)(entities, local, Some(data))?;
+            }
+            for index in delta.removed.iter() {
+                (components.types[*index as usize].apply)(entities, local, None)?;
+            }
+        }
+
+        // The server only deltas against snapshots at least as new as this one's baseline.
+        while self.history.len() >= SNAPSHOT_HISTORY || self.history.front().is_some_and(|(applied, _)| snapshot.baseline.is_some_and(|baseline| *applied < baseline)) {
+            self.history.pop_front();
+        }
+        self.history.push_back((snapshot.sequence, state));
+        return Ok(Some(codec::encode(&SnapshotAck { sequence: snapshot.sequence })));
+    }
+}
+
+impl Default for SnapshotReceiver {
+    fn default() -> SnapshotReceiver {
+        return SnapshotReceiver::new();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,94 @@
+use crate::{
+    engine::{save::level::DO_DAYLIGHT_CYCLE, world::container::World},
+    packet
+};
+
+/// Ticks between time updates sent to clients, which advance the time themselves in between.
+pub const TIME_SYNC_INTERVAL: u64 = 20;
+
+packet! {
+    /// The world's time, so clients draw the same sky and light as the server.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct TimeUpdate = 70 {
+        pub time: u64,
+        /// Whether time advances each tick, from the doDaylightCycle game rule.
+        pub advancing: bool
+    }
+}
+
+impl TimeUpdate {
+    pub fn from_world(world: &World) -> TimeUpdate {
+        return TimeUpdate { time: world.time(), advancing: world.game_rule(DO_DAYLIGHT_CYCLE) };
+    }
+
+    /// Set the client's world time, and whether World::advance_time() moves it on between updates.
+    pub fn apply(&self, world: &World) {
+        world.set_time(self.time);
+        world.set_game_rule(DO_DAYLIGHT_CYCLE, self.advancing);
+    }
+}
+
+/// Decides when the server broadcasts the time: every TIME_SYNC_INTERVAL ticks, and straight away when the
+/// time jumps, such as from a command, or stops or starts advancing. Clients that join are sent
+/// TimeUpdate::from_world() along with their first chunks.
+/// ```
+/// # use shared::engine::net::time_sync::{TimeSync, TimeUpdate};
+/// # use shared::engine::save::level::DO_DAYLIGHT_CYCLE;
+/// # use shared::engine::world::World;
+/// let server = World::new();
+/// let client = World::new();
+/// let mut sync = TimeSync::new();
+/// assert!(sync.update(&server, 0).is_some());
+/// server.advance_time(1);
+/// assert!(sync.update(&server, 1).is_none());
+///
+/// server.set_time(13000);
+/// let update = sync.update(&server, 2).unwrap();
+/// update.apply(&client);
+/// assert_eq!(client.world_time(), server.world_time());
+/// server.set_game_rule(DO_DAYLIGHT_CYCLE, false);
+/// assert_eq!(sync.update(&server, 3), Some(TimeUpdate { time: 13000, advancing: false }));
+/// ```
+pub struct TimeSync {
+    interval: u64,
+    /// Last update sent and the tick it was sent on.
+    last: Option<(TimeUpdate, u64)>
+}
+
+impl TimeSync {
+    pub fn new() -> TimeSync {
+        return TimeSync { interval: TIME_SYNC_INTERVAL, last: None };
+    }
+
+    pub fn with_interval(mut self, ticks: u64) -> TimeSync {
+        self.interval = ticks.max(1);
+        return self;
+    }
+
+    /// Called once per tick after the world's time advances. Returns the update to broadcast, if one is due.
+    pub fn update(&mut self, world: &World, tick: u64) -> Option<TimeUpdate> {
+        let current = TimeUpdate::from_world(world);
+        let due = match self.last {
+            None => true,
+            Some((last, sent_at)) => {
+                let elapsed = tick.saturating_sub(sent_at);
+                let expected = match last.advancing {
+                    true => last.time + elapsed,
+                    false => last.time
+                };
+                elapsed >= self.interval || current.time != expected || current.advancing != last.advancing
+            }
+        };
+        if !due {
+            return None;
+        }
+        self.last = Some((current, tick));
+        return Some(current);
+    }
+}
+
+impl Default for TimeSync {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
\ No newline at end of file
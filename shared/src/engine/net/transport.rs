@@ -0,0 +1,795 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+    sync::{atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering}, Arc, Mutex, RwLock},
+    time::Instant
+};
+
+use crate::engine::{config::NetConfig, job::system::JobSystem, metrics::standard::engine_metrics};
+
+use super::{
+    encryption::{self, Security, ServerIdentity, DATAGRAM_OVERHEAD},
+    packet::MAX_PACKET_SIZE
+};
+
+/// Largest packet sent over the unreliable channel. Bigger packets are sent reliably instead, as datagrams
+/// beyond a typical path MTU get fragmented, and losing any fragment loses the whole packet.
+pub const MAX_DATAGRAM_SIZE: usize = 1200;
+
+/// Reliable bytes a connection can have queued before it's closed for falling too far behind, by default.
+pub const DEFAULT_MAX_QUEUED_BYTES: usize = 16 * 1024 * 1024;
+
+/// Received bytes a connection can hold before they're handled, by default. A peer sending faster than they're
+/// handled is disconnected, and its datagrams over the limit dropped.
+pub const DEFAULT_MAX_RECEIVED_BYTES: usize = 16 * 1024 * 1024;
+
+/// Bytes read from a socket at a time.
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Framed bytes handed on towards the socket at once. Packets wait in their priority queues until the bytes before
+/// them drop below this, so a packet queued later with a higher priority can still go first on a backed up connection.
+const OUTGOING_WATERMARK: usize = 64 * 1024;
+
+/// Identifies a connection on a server's transport.
+pub type ConnectionId = u32;
+
+/// How a packet is delivered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// Over TCP. Always arrives, in the order sent among packets of the same priority.
+    Reliable,
+    /// Over UDP if the connection has an unreliable channel, otherwise reliably.
+    /// May be lost, duplicated or reordered, so only for state that is resent, such as movement.
+    Unreliable
+}
+
+/// Which packets go first when a connection can't send everything at once.
+/// Reliable packets of a lower priority wait, and unreliable ones are dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Priority {
+    /// What the player sees and does right now: their movement, entities in view and chat.
+    High,
+    Normal,
+    /// Bulk data that can wait, such as chunks. The chunk streamer sends nearer chunks first, so they still go first.
+    Low
+}
+
+/// Both ends of something packets go over, so code such as a server's per-player systems can run over a real
+/// Connection or a simulated link in tests.
+pub trait PacketLink {
+    /// Queue a packet to be sent once the packets of higher priority before it are.
+    fn send_with_priority(&self, channel: Channel, priority: Priority, packet: Vec<u8>);
+
+    /// Every packet received since the last call, in the order they arrived.
+    fn receive(&self) -> Vec<Vec<u8>>;
+
+    fn is_open(&self) -> bool;
+
+    /// Queue a packet with normal priority.
+    fn send(&self, channel: Channel, packet: Vec<u8>) {
+        self.send_with_priority(channel, Priority::Normal, packet);
+    }
+}
+
+const PRIORITIES: usize = 3;
+
+/// Token bucket limiting how many bytes a channel sends per second.
+struct Throttle {
+    /// Bytes per second, or None for no limit.
+    limit: Option<u64>,
+    /// Bytes that can be sent now. Goes negative when a packet bigger than what's left is sent, delaying the next.
+    allowance: f64,
+    refilled: Instant
+}
+
+impl Throttle {
+    fn new() -> Throttle {
+        return Throttle { limit: None, allowance: 0.0, refilled: Instant::now() };
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        if let Some(limit) = self.limit {
+            // Saving up is limited to a tenth of a second, so an idle connection doesn't burst far over its limit.
+            let burst = (limit as f64 / 10.0).max(MAX_DATAGRAM_SIZE as f64);
+            self.allowance = (self.allowance + now.duration_since(self.refilled).as_secs_f64() * limit as f64).min(burst);
+        }
+        self.refilled = now;
+    }
+
+    fn can_send(&self) -> bool {
+        return self.limit.is_none() || self.allowance > 0.0;
+    }
+
+    fn spend(&mut self, bytes: usize) {
+        if self.limit.is_some() {
+            self.allowance -= bytes as f64;
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+struct UdpChannel {
+    socket: Arc<UdpSocket>,
+    peer: SocketAddr,
+    /// A client's own socket, read by the connection. A server's sockets are shared, and read by the transport.
+    owned: bool
+}
+
+/// One end of a connection, with queues of packets to send and packets received.
+/// Sending and receiving only touch the queues, so they never block. The actual socket IO happens in pump(),
+/// which the transport runs for every connection as jobs.
+/// Reliable packets are framed on TCP as a 4 byte little-endian length, then the packet.
+/// Each channel can be limited to a number of bytes per second, with higher priority packets sent first.
+pub struct Connection {
+    stream: Mutex<TcpStream>,
+    peer: SocketAddr,
+    udp: RwLock<Option<UdpChannel>>,
+    security: Mutex<Security>,
+    /// Reliable packets waiting to be framed, by priority.
+    queued: Mutex<[VecDeque<Vec<u8>>; PRIORITIES]>,
+    /// Bytes of the packets in queued, plus their framing.
+    queued_bytes: AtomicUsize,
+    /// Framed reliable packets not yet written to the socket.
+    outgoing: Mutex<VecDeque<u8>>,
+    unreliable_outgoing: Mutex<Vec<(Priority, Vec<u8>)>>,
+    /// Limits of the reliable and unreliable channels.
+    throttles: Mutex<[Throttle; 2]>,
+    max_queued_bytes: AtomicUsize,
+    dropped_datagrams: AtomicU64,
+    /// Bytes read that don't make up a whole frame yet. Frames are taken out after every read, so this never
+    /// holds more than one frame and a read.
+    incoming: Mutex<Vec<u8>>,
+    received: Mutex<VecDeque<Vec<u8>>>,
+    /// Bytes of the packets in received.
+    received_queued_bytes: AtomicUsize,
+    max_received_bytes: AtomicUsize,
+    open: AtomicBool,
+    sent_bytes: AtomicU64,
+    received_bytes: AtomicU64
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        let peer = stream.peer_addr()?;
+        return Ok(Connection {
+            stream: Mutex::new(stream),
+            peer,
+            udp: RwLock::new(None),
+            security: Mutex::new(Security::Plain),
+            queued: Mutex::new(Default::default()),
+            queued_bytes: AtomicUsize::new(0),
+            outgoing: Mutex::new(VecDeque::new()),
+            unreliable_outgoing: Mutex::new(Vec::new()),
+            throttles: Mutex::new([Throttle::new(), Throttle::new()]),
+            max_queued_bytes: AtomicUsize::new(DEFAULT_MAX_QUEUED_BYTES),
+            dropped_datagrams: AtomicU64::new(0),
+            incoming: Mutex::new(Vec::new()),
+            received: Mutex::new(VecDeque::new()),
+            received_queued_bytes: AtomicUsize::new(0),
+            max_received_bytes: AtomicUsize::new(DEFAULT_MAX_RECEIVED_BYTES),
+            open: AtomicBool::new(true),
+            sent_bytes: AtomicU64::new(0),
+            received_bytes: AtomicU64::new(0)
+        });
+    }
+
+    /// Connect to a server, blocking until the TCP connection is made.
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Connection> {
+        return Connection::new(TcpStream::connect(address)?);
+    }
+
+    /// Connect to a server with encryption, blocking until the TCP connection is made. The encryption handshake
+    /// finishes over the next pumps, with packets sent before then held back until they can be encrypted.
+    /// With a server key, such as one saved from an earlier connection, the connection fails if the server's differs.
+    /// ```
+    /// # use shared::engine::net::{encryption::ServerIdentity, transport::{Channel, Connection, Transport}};
+    /// # use shared::engine::job::system::JobSystem;
+    /// let jobs = JobSystem::new(1);
+    /// let identity = ServerIdentity::generate().unwrap();
+    /// let server = Transport::bind("127.0.0.1:0", false).unwrap().with_encryption(identity.clone());
+    /// let client = Connection::connect_encrypted(server.local_addr(), Some(identity.public_key())).unwrap();
+    /// client.send(Channel::Reliable, b"token".to_vec());
+    ///
+    /// let mut received = Vec::new();
+    /// while received.is_empty() {
+    ///     client.pump().unwrap();
+    ///     server.pump(&jobs);
+    ///     for id in server.connection_ids() {
+    ///         received.extend(server.connection(id).unwrap().receive());
+    ///     }
+    /// }
+    /// assert_eq!(received, vec![b"token".to_vec()]);
+    /// assert!(client.is_encrypted());
+    /// ```
+    pub fn connect_encrypted(address: impl ToSocketAddrs, server_key: Option<[u8; 32]>) -> io::Result<Connection> {
+        let connection = Connection::connect(address)?;
+        let (security, message) = Security::connect(server_key)?;
+        *connection.security.lock().unwrap() = security;
+        connection.write_frame(&message);
+        return Ok(connection);
+    }
+
+    /// Connect to a server encrypted or not as the config says, checking the server's key if given and encrypting.
+    /// ```
+    /// # use shared::engine::{config::NetConfig, job::system::JobSystem, net::{encryption::ServerIdentity, transport::{Connection, Transport}}};
+    /// let jobs = JobSystem::new(1);
+    /// let identity = ServerIdentity::generate().unwrap();
+    /// let server = Transport::bind("127.0.0.1:0", false).unwrap().with_encryption(identity.clone());
+    /// let client = Connection::connect_with(server.local_addr(), &NetConfig::default(), Some(identity.public_key())).unwrap();
+    /// while !client.is_encrypted() {
+    ///     client.pump().unwrap();
+    ///     server.pump(&jobs);
+    /// }
+    /// let plain = Connection::connect_with(server.local_addr(), &NetConfig { encryption: false }, None).unwrap();
+    /// assert!(!plain.is_encrypted());
+    /// ```
+    pub fn connect_with(address: impl ToSocketAddrs, config: &NetConfig, server_key: Option<[u8; 32]>) -> io::Result<Connection> {
+        if config.encryption {
+            return Connection::connect_encrypted(address, server_key);
+        }
+        return Connection::connect(address);
+    }
+
+    /// Whether the encryption handshake finished. Unencrypted connections never are.
+    pub fn is_encrypted(&self) -> bool {
+        return matches!(*self.security.lock().unwrap(), Security::Encrypted(..));
+    }
+
+    /// The server's public key, on a client's encrypted connection, for pinning it.
+    pub fn server_key(&self) -> Option<[u8; 32]> {
+        return match *self.security.lock().unwrap() {
+            Security::Encrypted(_, key) => key,
+            _ => None
+        };
+    }
+
+    /// Frame a message straight onto the socket's queue, ahead of anything still waiting by priority.
+    fn write_frame(&self, message: &[u8]) {
+        let mut outgoing = self.outgoing.lock().unwrap();
+        outgoing.extend((message.len() as u32).to_le_bytes());
+        outgoing.extend(message);
+    }
+
+    /// Add an unreliable channel to a client connection, sending to and receiving from the server's UDP address.
+    pub fn open_unreliable(&self, server: SocketAddr) -> io::Result<()> {
+        let local: SocketAddr = if server.is_ipv4() { "0.0.0.0:0".parse().unwrap() } else { "[::]:0".parse().unwrap() };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(server)?;
+        socket.set_nonblocking(true)?;
+        *self.udp.write().unwrap() = Some(UdpChannel { socket: Arc::new(socket), peer: server, owned: true });
+        return Ok(());
+    }
+
+    /// Local address of the client's unreliable channel, which the server needs to send to it.
+    pub fn unreliable_local_addr(&self) -> Option<SocketAddr> {
+        return self.udp.read().unwrap().as_ref().and_then(|udp| udp.socket.local_addr().ok());
+    }
+
+    pub fn has_unreliable(&self) -> bool {
+        return self.udp.read().unwrap().is_some();
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        return self.peer;
+    }
+
+    /// Queue a packet to be sent on the next pump, with normal priority.
+    pub fn send(&self, channel: Channel, packet: Vec<u8>) {
+        self.send_with_priority(channel, Priority::Normal, packet);
+    }
+
+    /// Queue a packet to be sent once the packets of higher priority before it are.
+    /// ```
+    /// # use shared::engine::net::transport::{Channel, Connection, Priority, Transport};
+    /// # use shared::engine::job::system::JobSystem;
+    /// let jobs = JobSystem::new(1);
+    /// let server = Transport::bind("127.0.0.1:0", false).unwrap();
+    /// let client = Connection::connect(server.local_addr()).unwrap();
+    /// client.send_with_priority(Channel::Reliable, Priority::Low, b"chunk".to_vec());
+    /// client.send_with_priority(Channel::Reliable, Priority::High, b"movement".to_vec());
+    ///
+    /// let mut received = Vec::new();
+    /// while received.len() < 2 {
+    ///     client.pump().unwrap();
+    ///     server.pump(&jobs);
+    ///     for id in server.connection_ids() {
+    ///         received.extend(server.connection(id).unwrap().receive());
+    ///     }
+    /// }
+    /// assert_eq!(received, vec![b"movement".to_vec(), b"chunk".to_vec()]);
+    /// ```
+    pub fn send_with_priority(&self, channel: Channel, priority: Priority, packet: Vec<u8>) {
+        debug_assert!(packet.len() <= MAX_PACKET_SIZE, "Packet is too large to send");
+        let datagram_size = match *self.security.lock().unwrap() {
+            Security::Plain => packet.len(),
+            _ => packet.len() + DATAGRAM_OVERHEAD
+        };
+        if channel == Channel::Unreliable && datagram_size <= MAX_DATAGRAM_SIZE && self.has_unreliable() {
+            self.unreliable_outgoing.lock().unwrap().push((priority, packet));
+            return;
+        }
+        self.queued_bytes.fetch_add(packet.len() + 4, Ordering::Relaxed);
+        self.queued.lock().unwrap()[priority as usize].push_back(packet);
+    }
+
+    /// Limit a channel to a number of bytes per second, including framing, or remove its limit with None.
+    /// Reliable packets over the limit wait, and unreliable ones are dropped.
+    pub fn set_bandwidth_limit(&self, channel: Channel, bytes_per_second: Option<u64>) {
+        let mut throttles = self.throttles.lock().unwrap();
+        let throttle = &mut throttles[channel as usize];
+        throttle.limit = bytes_per_second;
+        throttle.allowance = 0.0;
+        throttle.refilled = Instant::now();
+    }
+
+    pub fn bandwidth_limit(&self, channel: Channel) -> Option<u64> {
+        return self.throttles.lock().unwrap()[channel as usize].limit;
+    }
+
+    /// Reliable bytes that can be queued before the connection is closed for falling behind, so a client on
+    /// a slow link can't make the server queue without end.
+    pub fn set_max_queued_bytes(&self, bytes: usize) {
+        self.max_queued_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Received bytes that can wait to be handled before the connection is closed, so a peer can't make the
+    /// other side hold its packets without end.
+    pub fn set_max_received_bytes(&self, bytes: usize) {
+        self.max_received_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    /// Unreliable packets dropped for going over the bandwidth limit or a full socket buffer.
+    pub fn dropped_datagrams(&self) -> u64 {
+        return self.dropped_datagrams.load(Ordering::Relaxed);
+    }
+
+    /// Every packet received since the last call, in the order they arrived.
+    pub fn receive(&self) -> Vec<Vec<u8>> {
+        let packets: Vec<Vec<u8>> = self.received.lock().unwrap().drain(..).collect();
+        self.received_queued_bytes.fetch_sub(packets.iter().map(Vec::len).sum(), Ordering::Relaxed);
+        return packets;
+    }
+
+    /// Bytes queued to be sent reliably, for holding back optional data when a connection falls behind.
+    pub fn queued_bytes(&self) -> usize {
+        return self.queued_bytes.load(Ordering::Relaxed) + self.outgoing.lock().unwrap().len();
+    }
+
+    /// Total bytes sent, including framing.
+    pub fn sent_bytes(&self) -> u64 {
+        return self.sent_bytes.load(Ordering::Relaxed);
+    }
+
+    /// Total bytes received, including framing.
+    pub fn received_bytes(&self) -> u64 {
+        return self.received_bytes.load(Ordering::Relaxed);
+    }
+
+    pub fn is_open(&self) -> bool {
+        return self.open.load(Ordering::Acquire);
+    }
+
+    /// Close the connection. Packets still queued are dropped, and the other side sees the connection end.
+    pub fn close(&self) {
+        if self.open.swap(false, Ordering::AcqRel) {
+            let _ = self.stream.lock().unwrap().shutdown(std::net::Shutdown::Both);
+        }
+    }
+
+    /// Write queued packets and read whatever has arrived, without blocking.
+    /// A connection that errors or was closed by the other side is closed, and the error returned.
+    pub fn pump(&self) -> io::Result<()> {
+        if !self.is_open() {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "Connection is closed"));
+        }
+        let result = self.pump_reliable().and_then(|_| self.pump_unreliable());
+        if result.is_err() {
+            self.close();
+        }
+        return result;
+    }
+
+    /// Frame queued reliable packets, highest priority first, while the bandwidth limit allows.
+    /// Packets wait while an encryption handshake is in progress.
+    fn frame_queued(&self, outgoing: &mut VecDeque<u8>) -> io::Result<()> {
+        let mut security = self.security.lock().unwrap();
+        let mut throttles = self.throttles.lock().unwrap();
+        let throttle = &mut throttles[Channel::Reliable as usize];
+        throttle.refill();
+        let mut queued = self.queued.lock().unwrap();
+        for queue in queued.iter_mut() {
+            while !security.is_handshaking() && outgoing.len() < OUTGOING_WATERMARK && throttle.can_send() {
+                let Some(packet) = queue.pop_front() else {
+                    break;
+                };
+                self.queued_bytes.fetch_sub(packet.len() + 4, Ordering::Relaxed);
+                let packet = match &mut *security {
+                    Security::Encrypted(cipher, _) => cipher.encrypt(&packet)?,
+                    _ => packet
+                };
+                throttle.spend(packet.len() + 4);
+                outgoing.extend((packet.len() as u32).to_le_bytes());
+                outgoing.extend(packet);
+            }
+        }
+        if self.queued_bytes.load(Ordering::Relaxed) + outgoing.len() > self.max_queued_bytes.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Connection fell too far behind"));
+        }
+        return Ok(());
+    }
+
+    fn pump_reliable(&self) -> io::Result<()> {
+        let mut stream = self.stream.lock().unwrap();
+        {
+            let mut outgoing = self.outgoing.lock().unwrap();
+            self.frame_queued(&mut outgoing)?;
+            while !outgoing.is_empty() {
+                let (front, _) = outgoing.as_slices();
+                match stream.write(front) {
+                    Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "Connection closed")),
+                    Ok(written) => {
+                        outgoing.drain(..written);
+                        self.sent_bytes.fetch_add(written as u64, Ordering::Relaxed);
+                        engine_metrics().bytes_sent.add(written as u64);
+                    },
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(error) => return Err(error)
+                }
+            }
+        }
+
+        let mut incoming = self.incoming.lock().unwrap();
+        let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+        let mut replies = Vec::new();
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Connection closed by peer")),
+                Ok(read) => {
+                    incoming.extend_from_slice(&buffer[..read]);
+                    self.received_bytes.fetch_add(read as u64, Ordering::Relaxed);
+                    engine_metrics().bytes_received.add(read as u64);
+                    self.take_frames(&mut incoming, &mut replies)?;
+                },
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error)
+            }
+        }
+        for reply in replies {
+            self.write_frame(&reply);
+        }
+        return Ok(());
+    }
+
+    /// Move every whole frame out of incoming, decrypting packets onto the received queue and collecting replies
+    /// to handshake messages. Fails if a frame is too large or the received queue is full.
+    fn take_frames(&self, incoming: &mut Vec<u8>, replies: &mut Vec<Vec<u8>>) -> io::Result<()> {
+        let mut consumed = 0;
+        let mut security = self.security.lock().unwrap();
+        while incoming.len() - consumed >= 4 {
+            let length = u32::from_le_bytes(incoming[consumed..consumed + 4].try_into().unwrap()) as usize;
+            if length > encryption::encrypted_size(MAX_PACKET_SIZE) {
+                return Err(invalid("Received packet is too large"));
+            }
+            if incoming.len() - consumed - 4 < length {
+                break;
+            }
+            let frame = &incoming[consumed + 4..consumed + 4 + length];
+            let packet = match &mut *security {
+                Security::Plain => Some(frame.to_vec()),
+                Security::Encrypted(cipher, _) => Some(cipher.decrypt(frame)?),
+                handshaking => {
+                    replies.extend(handshaking.handshake(frame)?);
+                    None
+                }
+            };
+            if let Some(packet) = packet {
+                if !self.push_received(packet) {
+                    return Err(invalid("Received more packets than were handled"));
+                }
+            }
+            consumed += 4 + length;
+        }
+        incoming.drain(..consumed);
+        return Ok(());
+    }
+
+    /// Queue a received packet, unless that would go over the limit of received bytes waiting to be handled.
+    fn push_received(&self, packet: Vec<u8>) -> bool {
+        let mut received = self.received.lock().unwrap();
+        let queued = self.received_queued_bytes.load(Ordering::Relaxed) + packet.len();
+        if queued > self.max_received_bytes.load(Ordering::Relaxed) {
+            return false;
+        }
+        self.received_queued_bytes.store(queued, Ordering::Relaxed);
+        received.push_back(packet);
+        return true;
+    }
+
+    fn pump_unreliable(&self) -> io::Result<()> {
+        let udp = self.udp.read().unwrap();
+        let Some(udp) = udp.as_ref() else {
+            return Ok(());
+        };
+        let mut packets: Vec<(Priority, Vec<u8>)> = self.unreliable_outgoing.lock().unwrap().drain(..).collect();
+        packets.sort_by_key(|(priority, _)| *priority);
+        let mut throttles = self.throttles.lock().unwrap();
+        let throttle = &mut throttles[Channel::Unreliable as usize];
+        throttle.refill();
+        let mut security = self.security.lock().unwrap();
+        for (_, packet) in packets {
+            if !throttle.can_send() || security.is_handshaking() {
+                self.dropped_datagrams.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            let packet = match &mut *security {
+                Security::Encrypted(cipher, _) => cipher.encrypt_datagram(&packet)?,
+                _ => packet
+            };
+            throttle.spend(packet.len());
+            let sent = match udp.owned {
+                true => udp.socket.send(&packet),
+                false => udp.socket.send_to(&packet, udp.peer)
+            };
+            match sent {
+                Ok(sent) => {
+                    self.sent_bytes.fetch_add(sent as u64, Ordering::Relaxed);
+                    engine_metrics().bytes_sent.add(sent as u64);
+                },
+                // Unreliable packets can be dropped, rather than holding up the rest.
+                Err(error) if matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::ConnectionRefused) => {
+                    self.dropped_datagrams.fetch_add(1, Ordering::Relaxed);
+                },
+                Err(error) => return Err(error)
+            }
+        }
+        drop(security);
+        if udp.owned {
+            let mut buffer = [0u8; MAX_DATAGRAM_SIZE];
+            loop {
+                match udp.socket.recv(&mut buffer) {
+                    Ok(read) => self.push_datagram(&buffer[..read]),
+                    Err(error) if matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::ConnectionRefused) => break,
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(error) => return Err(error)
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    fn push_datagram(&self, datagram: &[u8]) {
+        self.received_bytes.fetch_add(datagram.len() as u64, Ordering::Relaxed);
+        engine_metrics().bytes_received.add(datagram.len() as u64);
+        // Anyone can send datagrams, so ones that don't decrypt are dropped rather than closing the connection.
+        let packet = match &mut *self.security.lock().unwrap() {
+            Security::Plain => Some(datagram.to_vec()),
+            Security::Encrypted(cipher, _) => cipher.decrypt_datagram(datagram),
+            _ => None
+        };
+        // Datagrams over the limit are dropped like any other lost datagram.
+        if let Some(packet) = packet {
+            self.push_received(packet);
+        }
+    }
+}
+
+impl PacketLink for Connection {
+    fn send_with_priority(&self, channel: Channel, priority: Priority, packet: Vec<u8>) {
+        Connection::send_with_priority(self, channel, priority, packet);
+    }
+
+    fn receive(&self) -> Vec<Vec<u8>> {
+        return Connection::receive(self);
+    }
+
+    fn is_open(&self) -> bool {
+        return Connection::is_open(self);
+    }
+}
+
+/// A server's listening sockets and every connection made to them.
+/// ```
+/// # use shared::engine::net::transport::{Channel, Connection, Transport};
+/// # use shared::engine::job::system::JobSystem;
+/// let jobs = JobSystem::new(2);
+/// let server = Transport::bind("127.0.0.1:0", false).unwrap();
+/// let client = Connection::connect(server.local_addr()).unwrap();
+/// client.send(Channel::Reliable, b"hello".to_vec());
+///
+/// let mut received = Vec::new();
+/// while received.is_empty() {
+///     client.pump().unwrap();
+///     server.pump(&jobs);
+///     for id in server.connection_ids() {
+///         received.extend(server.connection(id).unwrap().receive());
+///     }
+/// }
+/// assert_eq!(received, vec![b"hello".to_vec()]);
+/// ```
+pub struct Transport {
+    listener: TcpListener,
+    udp: Option<Arc<UdpSocket>>,
+    connections: RwLock<HashMap<ConnectionId, Arc<Connection>>>,
+    /// Connection each client's UDP address belongs to.
+    udp_peers: RwLock<HashMap<SocketAddr, ConnectionId>>,
+    next_id: AtomicU32,
+    identity: Option<ServerIdentity>,
+    /// Connections that failed to set up, with why, until take_rejected().
+    rejected: Mutex<Vec<(SocketAddr, io::Error)>>
+}
+
+impl Transport {
+    /// Listen for connections. With unreliable, a UDP socket is bound to the same address and port.
+    pub fn bind(address: impl ToSocketAddrs, unreliable: bool) -> io::Result<Transport> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let udp = match unreliable {
+            true => {
+                let socket = UdpSocket::bind(listener.local_addr()?)?;
+                socket.set_nonblocking(true)?;
+                Some(Arc::new(socket))
+            },
+            false => None
+        };
+        return Ok(Transport { listener, udp, connections: RwLock::new(HashMap::new()), udp_peers: RwLock::new(HashMap::new()), next_id: AtomicU32::new(1), identity: None, rejected: Mutex::new(Vec::new()) });
+    }
+
+    /// Encrypt every connection accepted from now on, proving to clients that the server holds the identity.
+    /// Clients must connect with Connection::connect_encrypted().
+    pub fn with_encryption(mut self, identity: ServerIdentity) -> Transport {
+        self.identity = Some(identity);
+        return self;
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        return self.identity.is_some();
+    }
+
+    /// Identity connections prove the server holds, if they're encrypted.
+    pub fn identity(&self) -> Option<&ServerIdentity> {
+        return self.identity.as_ref();
+    }
+
+    /// Address clients connect to, over both TCP and UDP.
+    pub fn local_addr(&self) -> SocketAddr {
+        return self.listener.local_addr().unwrap();
+    }
+
+    pub fn has_unreliable(&self) -> bool {
+        return self.udp.is_some();
+    }
+
+    /// Accept every pending connection, returning their ids.
+    /// A connection that fails to set up, such as one closed before it could be accepted, is dropped without
+    /// holding up the rest, and kept with why for take_rejected().
+    pub fn accept(&self) -> io::Result<Vec<ConnectionId>> {
+        let mut accepted = Vec::new();
+        loop {
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    let connection = match self.set_up(stream) {
+                        Ok(connection) => connection,
+                        Err(error) => {
+                            self.rejected.lock().unwrap().push((peer, error));
+                            continue;
+                        }
+                    };
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    self.connections.write().unwrap().insert(id, Arc::new(connection));
+                    accepted.push(id);
+                },
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(accepted),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error)
+            }
+        }
+    }
+
+    /// Connections dropped while setting up since the last call, with their address and why, such as a client
+    /// that closed before its encryption was agreed.
+    pub fn take_rejected(&self) -> Vec<(SocketAddr, io::Error)> {
+        return std::mem::take(&mut *self.rejected.lock().unwrap());
+    }
+
+    fn set_up(&self, stream: TcpStream) -> io::Result<Connection> {
+        let connection = Connection::new(stream)?;
+        if let Some(identity) = self.identity.as_ref() {
+            *connection.security.lock().unwrap() = Security::accept(identity)?;
+        }
+        return Ok(connection);
+    }
+
+    /// Route a connection's unreliable packets to and from a client's UDP address, which the client reports
+    /// during the handshake. False if the transport has no UDP socket or the connection doesn't exist.
+    pub fn open_unreliable(&self, id: ConnectionId, client: SocketAddr) -> bool {
+        let (Some(socket), Some(connection)) = (self.udp.as_ref(), self.connection(id)) else {
+            return false;
+        };
+        *connection.udp.write().unwrap() = Some(UdpChannel { socket: socket.clone(), peer: client, owned: false });
+        self.udp_peers.write().unwrap().insert(client, id);
+        return true;
+    }
+
+    pub fn connection(&self, id: ConnectionId) -> Option<Arc<Connection>> {
+        return self.connections.read().unwrap().get(&id).cloned();
+    }
+
+    /// Ids of every connection, in the order they connected.
+    pub fn connection_ids(&self) -> Vec<ConnectionId> {
+        let mut ids: Vec<ConnectionId> = self.connections.read().unwrap().keys().copied().collect();
+        ids.sort_unstable();
+        return ids;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.connections.read().unwrap().len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    /// Close a connection and forget it.
+    pub fn disconnect(&self, id: ConnectionId) -> bool {
+        let Some(connection) = self.connections.write().unwrap().remove(&id) else {
+            return false;
+        };
+        connection.close();
+        if let Some(udp) = connection.udp.read().unwrap().as_ref() {
+            self.udp_peers.write().unwrap().remove(&udp.peer);
+        }
+        return true;
+    }
+
+    /// Accept new connections, route received datagrams, then pump every connection as a job on the IO job pool.
+    /// Connections that closed or failed are removed, and returned with the reason.
+    pub fn pump(&self, jobs: &JobSystem) -> Vec<(ConnectionId, io::Error)> {
+        // Failing to accept, such as when out of file descriptors, leaves the connection pending until the next pump.
+        let _ = self.accept();
+        self.receive_datagrams();
+        let connections: Vec<(ConnectionId, Arc<Connection>)> = self.connections.read().unwrap().iter().map(|(id, connection)| (*id, connection.clone())).collect();
+        // Futures hand back copies of their result, and io::Error can't be cloned.
+        let futures: Vec<_> = connections.into_iter().map(|(id, connection)| {
+            (id, jobs.run_job(move || connection.pump().err().map(|error| (error.kind(), error.to_string()))))
+        }).collect();
+        let mut closed = Vec::new();
+        for (id, future) in futures {
+            if let Some((kind, message)) = future.wait() {
+                self.disconnect(id);
+                closed.push((id, io::Error::new(kind, message)));
+            }
+        }
+        return closed;
+    }
+
+    fn receive_datagrams(&self) {
+        let Some(socket) = self.udp.as_ref() else {
+            return;
+        };
+        let mut buffer = [0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            match socket.recv_from(&mut buffer) {
+                Ok((read, from)) => {
+                    // Datagrams from unknown addresses are dropped, as anyone can send them.
+                    let id = self.udp_peers.read().unwrap().get(&from).copied();
+                    if let Some(connection) = id.and_then(|id| self.connection(id)) {
+                        connection.push_datagram(&buffer[..read]);
+                    }
+                },
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                // WouldBlock once drained. ConnectionRefused and similar come from earlier sends to a client
+                // that went away, and say nothing about the socket.
+                Err(_) => return
+            }
+        }
+    }
+}
\ No newline at end of file
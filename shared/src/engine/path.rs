@@ -0,0 +1,356 @@
+use std::{cmp::Ordering, collections::{BinaryHeap, HashMap}, fmt, sync::Arc};
+
+use crate::engine::{
+    block::BlockId,
+    job::{future::JobFuture, system::JobSystem},
+    math::coords::{BlockPos, ChunkPos},
+    progress::CancelToken,
+    world::{container::SharedChunk, World}
+};
+
+/// Most positions a search explores by default before giving up.
+pub const DEFAULT_MAX_NODES: usize = 10_000;
+/// Positions explored between checks of the cancel token, which is also checked before the first.
+const CANCEL_CHECK_INTERVAL: usize = 256;
+/// Extra cost per block climbed or fallen, so flat routes are preferred over bumpy ones.
+const CLIMB_COST: f32 = 0.5;
+/// Extra cost of a jump, on top of the distance covered.
+const JUMP_COST: f32 = 1.0;
+
+/// Whether entities collide with a block. Blocks they don't, like air and flowers, can be walked through.
+pub type SolidFn = dyn Fn(BlockId) -> bool + Send + Sync;
+
+/// How a path gets to a node from the one before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Movement {
+    /// The first node, where the path starts.
+    Start,
+    /// Across level ground, straight or diagonally.
+    Walk,
+    /// Up onto a block no higher than the step height.
+    Step,
+    /// Off an edge, down no further than the fall limit.
+    Fall,
+    /// Over a gap to ground at the same height.
+    Jump
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathNode {
+    /// Block the walker's feet are in.
+    pub pos: BlockPos,
+    pub movement: Movement
+}
+
+/// A route from the start to the goal, one node per block position the walker stands in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    nodes: Vec<PathNode>,
+    cost: f32,
+    explored: usize
+}
+
+impl Path {
+    /// Nodes from the start to the goal, both included.
+    pub fn nodes(&self) -> &[PathNode] {
+        return &self.nodes;
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = BlockPos> + '_ {
+        return self.nodes.iter().map(|node| node.pos);
+    }
+
+    pub fn start(&self) -> BlockPos {
+        return self.nodes[0].pos;
+    }
+
+    pub fn goal(&self) -> BlockPos {
+        return self.nodes[self.nodes.len() - 1].pos;
+    }
+
+    /// Roughly the blocks travelled, with climbing, falling and jumping costing extra.
+    pub fn cost(&self) -> f32 {
+        return self.cost;
+    }
+
+    /// Positions the search explored to find the path.
+    pub fn explored(&self) -> usize {
+        return self.explored;
+    }
+
+    /// Moves to make, one less than the nodes.
+    pub fn len(&self) -> usize {
+        return self.nodes.len() - 1;
+    }
+
+    /// Whether the start is the goal.
+    pub fn is_empty(&self) -> bool {
+        return self.nodes.len() == 1;
+    }
+}
+
+/// Why a path couldn't be found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathError {
+    /// The walker can't stand at the start, such as when it's inside a block or in the air.
+    BlockedStart,
+    /// Nothing can stand at the goal.
+    BlockedGoal,
+    /// Every position reachable from the start was explored without reaching the goal.
+    Unreachable,
+    /// The search explored as many positions as it's allowed to.
+    SearchLimit,
+    Cancelled
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            PathError::BlockedStart => write!(f, "can't stand at the start of the path"),
+            PathError::BlockedGoal => write!(f, "can't stand at the goal of the path"),
+            PathError::Unreachable => write!(f, "goal can't be reached"),
+            PathError::SearchLimit => write!(f, "path search explored too many positions"),
+            PathError::Cancelled => write!(f, "path search was cancelled")
+        };
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// Reads blocks for one search, holding on to the chunks it has looked up.
+struct Blocks<'a> {
+    world: &'a World,
+    solid: &'a SolidFn,
+    chunks: HashMap<ChunkPos, Option<SharedChunk>>
+}
+
+impl Blocks<'_> {
+    /// Blocks in unloaded chunks count as solid, so paths never lead into them.
+    fn is_solid(&mut self, pos: BlockPos) -> bool {
+        let world = self.world;
+        let chunk = self.chunks.entry(pos.chunk()).or_insert_with(|| world.chunk(pos.chunk()));
+        return match chunk {
+            Some(chunk) => (self.solid)(chunk.read().unwrap().get_block(pos.local())),
+            None => true
+        };
+    }
+
+    /// Whether a body of a height fits with its feet at a position.
+    fn fits(&mut self, pos: BlockPos, height: i32) -> bool {
+        return (0..height).all(|y| !self.is_solid(pos.offset(0, y, 0)));
+    }
+}
+
+/// A* entry, ordered so the heap pops the lowest estimate first.
+struct Open {
+    estimate: f32,
+    cost: f32,
+    pos: BlockPos
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Open) -> bool {
+        return self.cmp(other) == Ordering::Equal;
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Open) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Open) -> Ordering {
+        return other.estimate.total_cmp(&self.estimate).then_with(|| self.cost.total_cmp(&other.cost));
+    }
+}
+
+/// Finds routes for walkers through the voxel world with A*, for mob AI and moving players to a point.
+/// Walkers stand on solid blocks, need room for their height, and can step up, fall down and jump gaps
+/// within limits. Diagonal moves are only made when neither corner is blocked.
+/// ```
+/// # use shared::engine::{path::{Movement, Pathfinder}, progress::CancelToken, world::{World, chunk::Chunk}};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// # use std::sync::Arc;
+/// let world = World::new();
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// // A wall too high to step onto, with a block to step up onto it from.
+/// for z in 0..16 {
+///     world.set_block(BlockPos::new(5, 0, z), 1);
+///     world.set_block(BlockPos::new(5, 1, z), 1);
+/// }
+/// world.set_block(BlockPos::new(4, 0, 8), 1);
+///
+/// let pathfinder = Pathfinder::new(Arc::new(|id| id != 0));
+/// let path = pathfinder.find(&world, BlockPos::new(2, 0, 2), BlockPos::new(8, 0, 2), &CancelToken::new()).unwrap();
+/// assert_eq!((path.start(), path.goal()), (BlockPos::new(2, 0, 2), BlockPos::new(8, 0, 2)));
+/// assert!(path.positions().any(|pos| pos == BlockPos::new(5, 2, 8)));
+/// assert!(path.nodes().iter().any(|node| node.movement == Movement::Step));
+/// assert!(path.nodes().iter().any(|node| node.movement == Movement::Fall));
+/// ```
+#[derive(Clone)]
+pub struct Pathfinder {
+    solid: Arc<SolidFn>,
+    height: i32,
+    step_height: i32,
+    max_fall: i32,
+    max_jump_gap: i32,
+    max_nodes: usize
+}
+
+impl Pathfinder {
+    /// A pathfinder for walkers two blocks tall, which step up one block, fall up to three, and jump one block gaps.
+    pub fn new(solid: Arc<SolidFn>) -> Pathfinder {
+        return Pathfinder { solid, height: 2, step_height: 1, max_fall: 3, max_jump_gap: 1, max_nodes: DEFAULT_MAX_NODES };
+    }
+
+    /// Blocks of room the walker needs above its feet, including the one they're in.
+    pub fn with_height(mut self, height: u32) -> Pathfinder {
+        debug_assert_ne!(height, 0, "Cannot find paths for walkers with no height");
+        self.height = height as i32;
+        return self;
+    }
+
+    pub fn with_step_height(mut self, blocks: u32) -> Pathfinder {
+        self.step_height = blocks as i32;
+        return self;
+    }
+
+    pub fn with_max_fall(mut self, blocks: u32) -> Pathfinder {
+        self.max_fall = blocks as i32;
+        return self;
+    }
+
+    /// Widest gap jumped, in blocks. 0 never jumps.
+    pub fn with_max_jump_gap(mut self, blocks: u32) -> Pathfinder {
+        self.max_jump_gap = blocks as i32;
+        return self;
+    }
+
+    pub fn with_max_nodes(mut self, nodes: usize) -> Pathfinder {
+        self.max_nodes = nodes;
+        return self;
+    }
+
+    /// Cost of the cheapest possible route, for A* to never overestimate.
+    fn heuristic(&self, from: BlockPos, to: BlockPos) -> f32 {
+        let (dx, dz) = ((from.x - to.x).abs() as f32, (from.z - to.z).abs() as f32);
+        let (long, short) = (dx.max(dz), dx.min(dz));
+        return long + (std::f32::consts::SQRT_2 - 1.0) * short + CLIMB_COST * (from.y - to.y).abs() as f32;
+    }
+
+    fn can_stand(&self, blocks: &mut Blocks, pos: BlockPos) -> bool {
+        return blocks.is_solid(pos.offset(0, -1, 0)) && blocks.fits(pos, self.height);
+    }
+
+    /// Whether a walker could stand with its feet at a position, such as to check a goal before searching.
+    pub fn can_stand_at(&self, world: &World, pos: BlockPos) -> bool {
+        let mut blocks = Blocks { world, solid: &*self.solid, chunks: HashMap::new() };
+        return self.can_stand(&mut blocks, pos);
+    }
+
+    /// Positions a walker can move to from one it's standing at, with how and the cost of getting there.
+    fn moves(&self, blocks: &mut Blocks, from: BlockPos, moves: &mut Vec<(BlockPos, Movement, f32)>) {
+        moves.clear();
+        for (dx, dz) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let ahead = from.offset(dx, 0, dz);
+            let climb = (0..=self.step_height).find(|dy| self.can_stand(blocks, ahead.offset(0, *dy, 0)) && blocks.fits(from, self.height + dy));
+            if let Some(dy) = climb {
+                let movement = if dy == 0 { Movement::Walk } else { Movement::Step };
+                moves.push((ahead.offset(0, dy, 0), movement, 1.0 + CLIMB_COST * dy as f32));
+                continue;
+            }
+            if !blocks.fits(ahead, self.height) {
+                continue;
+            }
+            for depth in 1..=self.max_fall {
+                let below = ahead.offset(0, -depth, 0);
+                if blocks.is_solid(below) {
+                    break;
+                }
+                if blocks.is_solid(below.offset(0, -1, 0)) {
+                    moves.push((below, Movement::Fall, 1.0 + CLIMB_COST * depth as f32));
+                    break;
+                }
+            }
+            // Jumping needs a block of headroom over the gap and both ends.
+            if !blocks.fits(from, self.height + 1) {
+                continue;
+            }
+            for gap in 1..=self.max_jump_gap {
+                if !blocks.fits(from.offset(dx * gap, 0, dz * gap), self.height + 1) {
+                    break;
+                }
+                let landing = from.offset(dx * (gap + 1), 0, dz * (gap + 1));
+                if self.can_stand(blocks, landing) && blocks.fits(landing, self.height + 1) {
+                    moves.push((landing, Movement::Jump, (gap + 1) as f32 + JUMP_COST));
+                    break;
+                }
+            }
+        }
+        for (dx, dz) in [(-1, -1), (-1, 1), (1, -1), (1, 1)] {
+            let target = from.offset(dx, 0, dz);
+            if self.can_stand(blocks, target) && blocks.fits(from.offset(dx, 0, 0), self.height) && blocks.fits(from.offset(0, 0, dz), self.height) {
+                moves.push((target, Movement::Walk, std::f32::consts::SQRT_2));
+            }
+        }
+    }
+
+    /// Find the cheapest path between two positions a walker can stand at, on the calling thread.
+    /// The cancel token is checked as the search goes, so a search that's no longer wanted stops early.
+    pub fn find(&self, world: &World, start: BlockPos, goal: BlockPos, cancel: &CancelToken) -> Result<Path, PathError> {
+        let mut blocks = Blocks { world, solid: &*self.solid, chunks: HashMap::new() };
+        if !self.can_stand(&mut blocks, start) {
+            return Err(PathError::BlockedStart);
+        }
+        if !self.can_stand(&mut blocks, goal) {
+            return Err(PathError::BlockedGoal);
+        }
+        let mut open = BinaryHeap::from([Open { estimate: self.heuristic(start, goal), cost: 0.0, pos: start }]);
+        let mut best: HashMap<BlockPos, (f32, BlockPos, Movement)> = HashMap::from([(start, (0.0, start, Movement::Start))]);
+        let mut moves = Vec::new();
+        let mut explored = 0;
+        while let Some(Open { cost, pos, .. }) = open.pop() {
+            if cost > best[&pos].0 {
+                continue;
+            }
+            if pos == goal {
+                let mut nodes = vec![PathNode { pos, movement: best[&pos].2 }];
+                while nodes[nodes.len() - 1].movement != Movement::Start {
+                    let (_, previous, _) = best[&nodes[nodes.len() - 1].pos];
+                    nodes.push(PathNode { pos: previous, movement: best[&previous].2 });
+                }
+                nodes.reverse();
+                return Ok(Path { nodes, cost, explored });
+            }
+            explored += 1;
+            if explored > self.max_nodes {
+                return Err(PathError::SearchLimit);
+            }
+            if explored % CANCEL_CHECK_INTERVAL == 1 && cancel.is_cancelled() {
+                return Err(PathError::Cancelled);
+            }
+            self.moves(&mut blocks, pos, &mut moves);
+            for (next, movement, step) in moves.iter().copied() {
+                let next_cost = cost + step;
+                if best.get(&next).is_some_and(|(known, _, _)| *known <= next_cost) {
+                    continue;
+                }
+                best.insert(next, (next_cost, pos, movement));
+                open.push(Open { estimate: next_cost + self.heuristic(next, goal), cost: next_cost, pos: next });
+            }
+        }
+        return Err(PathError::Unreachable);
+    }
+
+    /// Queue a job finding a path. Cancelling the token stops the search, resolving the future with Cancelled.
+    /// Blocks are read while the job runs, so the world may change under a long search.
+    pub fn queue(&self, jobs: &JobSystem, world: Arc<World>, start: BlockPos, goal: BlockPos, cancel: CancelToken) -> JobFuture<Result<Path, PathError>> {
+        let pathfinder = self.clone();
+        return jobs.run_job(move || pathfinder.find(&world, start, goal, &cancel));
+    }
+}
\ No newline at end of file
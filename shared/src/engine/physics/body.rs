@@ -0,0 +1,104 @@
+use std::io;
+
+use serde::{Serialize, Deserialize};
+
+use crate::engine::{
+    entity::serialize::ComponentTypes,
+    math::{aabb::Aabb, coords::WorldPos, vector::Vec3}
+};
+
+/// An entity that falls, slows down and collides with the world, moved by the PhysicsSystem instead of kinematics.
+/// Its collision box is centered horizontally on the entity's position, and reaches up from it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RigidBody {
+    /// Width and depth of the collision box, in blocks.
+    pub width: f32,
+    /// Height of the collision box, in blocks.
+    pub height: f32,
+    /// Downward acceleration, in blocks per second squared.
+    pub gravity: f32,
+    /// Fraction of linear velocity lost per second, from 0 to 1.
+    pub drag: f32,
+    /// Fraction of horizontal velocity lost per second while standing on a block.
+    pub friction: f32,
+    /// Whether the body landed on a block during its last step.
+    pub on_ground: bool,
+    /// Whether the body touched a fluid during its last step.
+    pub in_fluid: bool
+}
+
+impl RigidBody {
+    pub const DEFAULT_GRAVITY: f32 = 32.0;
+    pub const DEFAULT_DRAG: f32 = 0.4;
+    pub const DEFAULT_FRICTION: f32 = 12.0;
+
+    pub fn new(width: f32, height: f32) -> RigidBody {
+        return RigidBody {
+            width,
+            height,
+            gravity: RigidBody::DEFAULT_GRAVITY,
+            drag: RigidBody::DEFAULT_DRAG,
+            friction: RigidBody::DEFAULT_FRICTION,
+            on_ground: false,
+            in_fluid: false
+        };
+    }
+
+    pub fn with_gravity(mut self, gravity: f32) -> RigidBody {
+        self.gravity = gravity;
+        return self;
+    }
+
+    pub fn with_drag(mut self, drag: f32) -> RigidBody {
+        self.drag = drag;
+        return self;
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> RigidBody {
+        self.friction = friction;
+        return self;
+    }
+
+    /// Collision box at a position, in world block coordinates.
+    /// ```
+    /// # use shared::engine::physics::body::RigidBody;
+    /// # use shared::engine::math::{coords::WorldPos, vector::Vec3};
+    /// let player = RigidBody::new(0.6, 1.8);
+    /// let aabb = player.aabb(WorldPos::new(4.5, 10.0, -2.5));
+    /// assert!(aabb.min.approx_eq(Vec3::new(4.2, 10.0, -2.8), 1e-5));
+    /// assert!(aabb.max.approx_eq(Vec3::new(4.8, 11.8, -2.2), 1e-5));
+    /// ```
+    pub fn aabb(&self, position: WorldPos) -> Aabb {
+        let base = Vec3::new(position.x as f32, position.y as f32, position.z as f32);
+        let half_width = self.width / 2.0;
+        return Aabb::new(base - Vec3::new(half_width, 0.0, half_width), base + Vec3::new(half_width, self.height, half_width));
+    }
+}
+
+/// Save rigid bodies with entities.
+/// ```
+/// # use shared::engine::entity::{serialize::ComponentTypes, Entities};
+/// # use shared::engine::physics::body::{self, RigidBody};
+/// let mut types = ComponentTypes::new();
+/// body::register_components(&mut types);
+/// let entities = Entities::new();
+/// let item = entities.spawn();
+/// let rigid_body = RigidBody { on_ground: true, ..RigidBody::new(0.25, 0.25).with_friction(20.0) };
+/// entities.insert(item, rigid_body).unwrap();
+/// let loaded = types.decode(&entities, &types.encode(&entities, &[item])).unwrap();
+/// assert_eq!(entities.get::<RigidBody>(loaded[0]), Some(rigid_body));
+/// ```
+pub fn register_components(types: &mut ComponentTypes) {
+    types.register::<RigidBody>("cube:rigid_body", |body, out| {
+        for value in [body.width, body.height, body.gravity, body.drag, body.friction] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.push(body.on_ground as u8 | (body.in_fluid as u8) << 1);
+    }, |data| {
+        if data.len() != 21 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Rigid body data has the wrong length"));
+        }
+        let [width, height, gravity, drag, friction]: [f32; 5] = std::array::from_fn(|i| f32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap()));
+        return Ok(RigidBody { width, height, gravity, drag, friction, on_ground: data[20] & 1 != 0, in_fluid: data[20] & 2 != 0 });
+    });
+}
\ No newline at end of file
@@ -0,0 +1,129 @@
+use crate::engine::{
+    math::{aabb::Aabb, coords::BlockPos, vector::Vec3},
+    world::World
+};
+
+/// Gap treated as touching, so boxes resting on a surface stay on it despite rounding.
+const CONTACT_EPSILON: f32 = 1e-5;
+
+/// How far a box moved before hitting the world, and what it hit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollisionResult {
+    /// The motion asked for, shortened along every axis that hit a block.
+    pub motion: Vec3,
+    /// The box after moving.
+    pub aabb: Aabb,
+    /// Whether movement along X, Y and Z was stopped.
+    pub collided: [bool; 3]
+}
+
+impl CollisionResult {
+    pub fn collided(&self) -> bool {
+        return self.collided.iter().any(|collided| *collided);
+    }
+
+    /// Whether the box landed on something while moving down.
+    pub fn on_ground(&self, requested: Vec3) -> bool {
+        return self.collided[1] && requested.y < 0.0;
+    }
+}
+
+/// Collision boxes of every block near a region, in world block coordinates. Blocks in unloaded chunks
+/// are full cubes, so entities don't fall into chunks that haven't arrived yet.
+fn block_boxes(world: &World, region: &Aabb) -> Vec<Aabb> {
+    let shapes = world.block_shapes();
+    let min = BlockPos::new(region.min.x.floor() as i32, region.min.y.floor() as i32 - 1, region.min.z.floor() as i32);
+    let max = BlockPos::new(region.max.x.ceil() as i32, region.max.y.ceil() as i32, region.max.z.ceil() as i32);
+    let region = region.expand(Vec3::splat(CONTACT_EPSILON));
+    let mut boxes = Vec::new();
+    for x in min.x..max.x {
+        for y in min.y..max.y {
+            for z in min.z..max.z {
+                let block = BlockPos::new(x, y, z);
+                let corner = Vec3::new(x as f32, y as f32, z as f32);
+                match world.get_block(block) {
+                    Some(id) => boxes.extend(shapes.boxes(id).iter().map(|shape| shape.translate(corner))),
+                    None => boxes.push(Aabb::new(corner, corner + Vec3::ONE))
+                }
+            }
+        }
+    }
+    boxes.retain(|shape| shape.intersects(&region));
+    return boxes;
+}
+
+/// How far a box can move along one axis before touching another box, from the motion it wants.
+fn clip(moving: &Aabb, other: &Aabb, axis: usize, motion: f32) -> f32 {
+    let (moving_min, moving_max, other_min, other_max) = (moving.min.to_array(), moving.max.to_array(), other.min.to_array(), other.max.to_array());
+    let overlaps = (0..3).filter(|other_axis| *other_axis != axis)
+        .all(|other_axis| moving_min[other_axis] < other_max[other_axis] && moving_max[other_axis] > other_min[other_axis]);
+    if !overlaps {
+        return motion;
+    }
+    if motion > 0.0 && moving_max[axis] <= other_min[axis] + CONTACT_EPSILON {
+        return motion.min((other_min[axis] - moving_max[axis]).max(0.0));
+    }
+    if motion < 0.0 && moving_min[axis] >= other_max[axis] - CONTACT_EPSILON {
+        return motion.max((other_max[axis] - moving_min[axis]).min(0.0));
+    }
+    return motion;
+}
+
+/// Move a box through the world, stopping it at block collision shapes. Axes are resolved one at a time,
+/// Y first so falling boxes land before sliding, so a box hitting a wall still slides along it.
+/// The box is in world block coordinates, like raycasts.
+/// ```
+/// # use shared::engine::physics::collide_aabb;
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::{aabb::Aabb, coords::{BlockPos, ChunkPos}, vector::Vec3};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// world.set_block(BlockPos::new(3, 0, 1), 1);
+/// let player = Aabb::new(Vec3::new(1.2, 0.5, 1.2), Vec3::new(1.8, 2.3, 1.8));
+///
+/// // Falling onto the ground while walking into a wall.
+/// let motion = Vec3::new(2.0, -1.0, 0.5);
+/// let result = collide_aabb(&world, player, motion);
+/// assert!(result.motion.approx_eq(Vec3::new(1.2, -0.5, 0.5), 1e-5));
+/// assert_eq!(result.collided, [true, true, false]);
+/// assert!(result.on_ground(motion));
+/// ```
+pub fn collide_aabb(world: &World, aabb: Aabb, motion: Vec3) -> CollisionResult {
+    let boxes = block_boxes(world, &aabb.union(&aabb.translate(motion)));
+    let requested = motion.to_array();
+    let mut resolved = [0.0; 3];
+    let mut moved = aabb;
+    for axis in [1, 0, 2] {
+        let mut distance = requested[axis];
+        for other in boxes.iter() {
+            distance = clip(&moved, other, axis, distance);
+        }
+        resolved[axis] = distance;
+        let mut offset = [0.0; 3];
+        offset[axis] = distance;
+        moved = moved.translate(Vec3::new(offset[0], offset[1], offset[2]));
+    }
+    let collided = [0, 1, 2].map(|axis| resolved[axis] != requested[axis]);
+    return CollisionResult { motion: Vec3::new(resolved[0], resolved[1], resolved[2]), aabb: moved, collided };
+}
+
+/// Whether a box overlaps no block's collision shape, such as to check there's room to spawn something there.
+/// Touching a block, as a box standing on the ground does, isn't overlapping it. Unloaded chunks are full.
+/// ```
+/// # use shared::engine::physics::aabb_fits;
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::{aabb::Aabb, coords::{BlockPos, ChunkPos}, vector::Vec3};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// world.set_block(BlockPos::new(3, 1, 1), 1);
+/// assert!(aabb_fits(&world, Aabb::new(Vec3::new(1.2, 0.0, 1.2), Vec3::new(1.8, 1.8, 1.8))));
+/// assert!(!aabb_fits(&world, Aabb::new(Vec3::new(2.7, 0.0, 1.2), Vec3::new(3.3, 1.8, 1.8))));
+/// // Sunk into the ground.
+/// assert!(!aabb_fits(&world, Aabb::new(Vec3::new(1.2, -0.5, 1.2), Vec3::new(1.8, 1.3, 1.8))));
+/// ```
+pub fn aabb_fits(world: &World, aabb: Aabb) -> bool {
+    let inner = aabb.expand(Vec3::splat(-2.0 * CONTACT_EPSILON));
+    return block_boxes(world, &inner).iter().all(|shape| !shape.intersects(&inner));
+}
\ No newline at end of file
@@ -0,0 +1,8 @@
+pub mod shape;
+pub mod collision;
+pub mod body;
+pub mod step;
+
+pub use body::RigidBody;
+pub use collision::{aabb_fits, collide_aabb, CollisionResult};
+pub use step::PhysicsSystem;
\ No newline at end of file
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use crate::engine::{asset::block::BlockDefinition, block::{BlockId, BlockRegistry, AIR}, math::{aabb::Aabb, vector::Vec3}};
+
+const FULL_BOXES: [Aabb; 1] = [Aabb { min: Vec3::ZERO, max: Vec3::ONE }];
+
+/// Boxes entities collide with in a block, from 0 to 1 within the block. Boxes may reach past the top of
+/// the block by up to a block, as fences do, and are found by searching the block below too.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CollisionShape {
+    /// Walked through, like air and flowers.
+    Empty,
+    Full,
+    Boxes(Vec<Aabb>)
+}
+
+impl CollisionShape {
+    /// Bottom half of a block.
+    pub fn slab() -> CollisionShape {
+        return CollisionShape::Boxes(vec![Aabb::new(Vec3::ZERO, Vec3::new(1.0, 0.5, 1.0))]);
+    }
+
+    pub fn boxes(&self) -> &[Aabb] {
+        return match self {
+            CollisionShape::Empty => &[],
+            CollisionShape::Full => &FULL_BOXES,
+            CollisionShape::Boxes(boxes) => boxes
+        };
+    }
+}
+
+/// Collision shape of every block. Air is empty and blocks without a shape of their own are full cubes.
+/// ```
+/// # use shared::engine::physics::shape::{BlockShapes, CollisionShape};
+/// # use shared::engine::block::AIR;
+/// let shapes = BlockShapes::new().with_shape(2, CollisionShape::slab()).with_shape(3, CollisionShape::Empty);
+/// assert!(!shapes.is_solid(AIR));
+/// assert_eq!(shapes.shape(1), &CollisionShape::Full);
+/// assert_eq!(shapes.boxes(2)[0].max.y, 0.5);
+/// assert!(!shapes.is_solid(3));
+/// ```
+#[derive(Clone, Debug)]
+pub struct BlockShapes {
+    shapes: HashMap<BlockId, CollisionShape>
+}
+
+impl BlockShapes {
+    pub fn new() -> BlockShapes {
+        return BlockShapes { shapes: HashMap::from([(AIR, CollisionShape::Empty)]) };
+    }
+
+    /// Shapes every block state declared when it was registered.
+    /// ```
+    /// # use shared::engine::block::BlockRegistry;
+    /// # use shared::engine::physics::shape::{BlockShapes, CollisionShape};
+    /// let mut registry = BlockRegistry::new();
+    /// let stone = registry.register("cube:stone").unwrap();
+    /// let slab = registry.builder("cube:stone_slab").collision(CollisionShape::slab()).register().unwrap();
+    /// let flower = registry.builder("cube:flower").collision(CollisionShape::Empty).register().unwrap();
+    /// let shapes = BlockShapes::from_registry(&registry);
+    /// assert_eq!(shapes.shape(stone), &CollisionShape::Full);
+    /// assert_eq!(shapes.shape(slab), &CollisionShape::slab());
+    /// assert!(!shapes.is_solid(flower));
+    /// ```
+    pub fn from_registry(registry: &BlockRegistry) -> BlockShapes {
+        let mut shapes = BlockShapes::new();
+        for id in 0..registry.len() as BlockId {
+            let shape = registry.collision_shape(id);
+            if *shape != CollisionShape::Full {
+                shapes.set_shape(id, shape.clone());
+            }
+        }
+        return shapes;
+    }
+
+    /// Blocks from content packs, with the ones that aren't solid empty.
+    pub fn from_definitions(blocks: &[(BlockId, BlockDefinition)]) -> BlockShapes {
+        let mut shapes = BlockShapes::new();
+        for (id, _) in blocks.iter().filter(|(_, definition)| !definition.solid) {
+            shapes.set_shape(*id, CollisionShape::Empty);
+        }
+        return shapes;
+    }
+
+    pub fn with_shape(mut self, id: BlockId, shape: CollisionShape) -> BlockShapes {
+        self.set_shape(id, shape);
+        return self;
+    }
+
+    pub fn set_shape(&mut self, id: BlockId, shape: CollisionShape) {
+        self.shapes.insert(id, shape);
+    }
+
+    pub fn shape(&self, id: BlockId) -> &CollisionShape {
+        return self.shapes.get(&id).unwrap_or(&CollisionShape::Full);
+    }
+
+    pub fn boxes(&self, id: BlockId) -> &[Aabb] {
+        return self.shape(id).boxes();
+    }
+
+    /// Whether entities collide with any part of the block.
+    pub fn is_solid(&self, id: BlockId) -> bool {
+        return !self.boxes(id).is_empty();
+    }
+}
+
+impl Default for BlockShapes {
+    fn default() -> BlockShapes {
+        return BlockShapes::new();
+    }
+}
\ No newline at end of file
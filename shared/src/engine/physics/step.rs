@@ -0,0 +1,181 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::engine::{
+    block::BlockId,
+    entity::{kinematics::{integrate, Transform, Velocity, FIXED_TIMESTEP}, schedule::{System, SystemAccess, SystemContext}, EntityId},
+    job::system::JobSystem,
+    math::{aabb::Aabb, coords::{BlockPos, ChunkPos, WorldPos}, vector::Vec3},
+    world::World
+};
+
+use super::{body::RigidBody, collision::collide_aabb};
+
+/// Drag of a block, as the fraction of velocity lost per second by bodies inside it. None if it isn't a fluid.
+pub type FluidDragFn = dyn Fn(BlockId) -> Option<f32> + Send + Sync;
+
+/// Width of the regions bodies are batched into, in chunks.
+pub const DEFAULT_REGION_SIZE: i32 = 2;
+
+/// Speed below which a body stops moving along an axis, in blocks per second.
+const RESTING_SPEED: f32 = 1e-3;
+
+/// Strongest drag of the fluids a box touches. None if it touches no fluid.
+fn fluid_drag(world: &World, fluids: &FluidDragFn, aabb: &Aabb) -> Option<f32> {
+    let mut drag: Option<f32> = None;
+    for x in aabb.min.x.floor() as i32..aabb.max.x.ceil() as i32 {
+        for y in aabb.min.y.floor() as i32..aabb.max.y.ceil() as i32 {
+            for z in aabb.min.z.floor() as i32..aabb.max.z.ceil() as i32 {
+                if let Some(block_drag) = world.get_block(BlockPos::new(x, y, z)).and_then(fluids) {
+                    drag = Some(drag.map_or(block_drag, |drag| drag.max(block_drag)));
+                }
+            }
+        }
+    }
+    return drag;
+}
+
+/// Move a body one step through the world. Gravity and drag change the velocity first, friction slows bodies
+/// that were on the ground, then the body moves as far as blocks let it, losing its velocity along any axis it hit.
+/// ```
+/// # use shared::engine::physics::{body::RigidBody, step::step_body};
+/// # use shared::engine::entity::kinematics::{Transform, Velocity, FIXED_TIMESTEP};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::{coords::{ChunkPos, WorldPos}, vector::Vec3};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// let mut transform = Transform::new(WorldPos::new(4.5, 3.0, 4.5));
+/// let mut velocity = Velocity::new(Vec3::new(2.0, 0.0, 0.0));
+/// let mut body = RigidBody::new(0.6, 1.8);
+/// for _ in 0..40 {
+///     step_body(&world, None, &mut transform, &mut velocity, &mut body, FIXED_TIMESTEP);
+/// }
+/// // Landed and slid to a stop.
+/// assert!(body.on_ground);
+/// assert!(transform.position.y.abs() < 1e-4);
+/// assert_eq!(velocity.linear, Vec3::ZERO);
+/// ```
+pub fn step_body(world: &World, fluids: Option<&FluidDragFn>, transform: &mut Transform, velocity: &mut Velocity, body: &mut RigidBody, seconds: f32) {
+    let aabb = body.aabb(transform.position);
+    let fluid = fluids.and_then(|fluids| fluid_drag(world, fluids, &aabb));
+    velocity.linear.y -= body.gravity * seconds;
+    velocity.linear *= (1.0 - (body.drag + fluid.unwrap_or(0.0)) * seconds).max(0.0);
+    if body.on_ground {
+        let slowed = (1.0 - body.friction * seconds).max(0.0);
+        velocity.linear.x *= slowed;
+        velocity.linear.z *= slowed;
+    }
+    let motion = velocity.linear * seconds;
+    let result = collide_aabb(world, aabb, motion);
+    // Tiny speeds left over by drag and friction would keep resting bodies changing forever.
+    let linear = velocity.linear.to_array();
+    let stopped = [0, 1, 2].map(|axis| {
+        if result.collided[axis] || linear[axis].abs() < RESTING_SPEED { 0.0 } else { linear[axis] }
+    });
+    velocity.linear = Vec3::new(stopped[0], stopped[1], stopped[2]);
+    body.on_ground = result.on_ground(motion);
+    body.in_fluid = fluid.is_some();
+    let moved = result.motion;
+    transform.position = transform.position + WorldPos::new(moved.x as f64, moved.y as f64, moved.z as f64);
+    integrate(transform, &Velocity { linear: Vec3::ZERO, angular: velocity.angular }, seconds);
+}
+
+/// A body's components, copied out of the entity storages so regions can step without holding their locks.
+type BodyState = (EntityId, Transform, Velocity, RigidBody);
+
+fn step_region(world: &World, fluids: Option<&FluidDragFn>, bodies: &[BodyState]) -> Vec<BodyState> {
+    return bodies.iter().map(|(id, transform, velocity, body)| {
+        let (mut transform, mut velocity, mut body) = (*transform, *velocity, *body);
+        step_body(world, fluids, &mut transform, &mut velocity, &mut body, FIXED_TIMESTEP);
+        (*id, transform, velocity, body)
+    }).collect();
+}
+
+/// Steps every entity with a Transform, Velocity and RigidBody by one fixed timestep, moving it between chunks
+/// in the entity registry as it crosses chunk borders.
+/// Bodies are batched into regions of chunks, and each region steps as its own job, so bodies in different areas
+/// move at the same time. Bodies only collide with blocks, not each other, so regions never wait on one another.
+pub struct PhysicsSystem {
+    jobs: Arc<JobSystem>,
+    fluids: Option<Arc<FluidDragFn>>,
+    region_size: i32
+}
+
+impl PhysicsSystem {
+    pub fn new(jobs: Arc<JobSystem>) -> PhysicsSystem {
+        return PhysicsSystem { jobs, fluids: None, region_size: DEFAULT_REGION_SIZE };
+    }
+
+    /// Slow bodies down inside fluid blocks.
+    pub fn with_fluids(mut self, fluids: Arc<FluidDragFn>) -> PhysicsSystem {
+        self.fluids = Some(fluids);
+        return self;
+    }
+
+    /// Width of the regions bodies are batched into, in chunks. Will panic in debug mode if not positive.
+    pub fn with_region_size(mut self, chunks: i32) -> PhysicsSystem {
+        debug_assert!(chunks > 0, "Physics region size must be positive");
+        self.region_size = chunks;
+        return self;
+    }
+
+    fn region(&self, chunk: ChunkPos) -> (i32, i32, i32) {
+        return (chunk.x.div_euclid(self.region_size), chunk.y.div_euclid(self.region_size), chunk.z.div_euclid(self.region_size));
+    }
+}
+
+impl System for PhysicsSystem {
+    fn name(&self) -> &str {
+        return "physics";
+    }
+
+    fn access(&self) -> SystemAccess {
+        return SystemAccess::new().write::<Transform>().write::<Velocity>().write::<RigidBody>();
+    }
+
+    fn run(&mut self, context: &SystemContext) {
+        let mut regions: HashMap<(i32, i32, i32), Vec<BodyState>> = HashMap::new();
+        context.query::<(&Transform, &Velocity, &RigidBody), ()>().for_each(|id, (transform, velocity, body)| {
+            regions.entry(self.region(transform.position.chunk())).or_default().push((id, *transform, *velocity, *body));
+        });
+        let stepped: Vec<BodyState> = if regions.len() == 1 {
+            let bodies = regions.into_values().next().unwrap();
+            step_region(context.world, self.fluids.as_deref(), &bodies)
+        }
+        else {
+            let futures: Vec<_> = regions.into_values().map(|bodies| {
+                let world = context.world.clone();
+                let fluids = self.fluids.clone();
+                self.jobs.run_job(move || step_region(&world, fluids.as_deref(), &bodies))
+            }).collect();
+            futures.into_iter().flat_map(|future| future.wait()).collect()
+        };
+
+        let mut stepped: HashMap<EntityId, (Transform, Velocity, RigidBody)> = stepped.into_iter()
+            .map(|(id, transform, velocity, body)| (id, (transform, velocity, body)))
+            .collect();
+        let mut crossed = Vec::new();
+        context.query::<(&mut Transform, &mut Velocity, &mut RigidBody), ()>().for_each(|id, (mut transform, mut velocity, mut body)| {
+            let Some((new_transform, new_velocity, new_body)) = stepped.remove(&id) else {
+                return;
+            };
+            // Only written when they change, so resting bodies don't count as changed every tick.
+            if *transform != new_transform {
+                if transform.position.chunk() != new_transform.position.chunk() {
+                    crossed.push((id, new_transform.position.chunk()));
+                }
+                *transform = new_transform;
+            }
+            if *velocity != new_velocity {
+                *velocity = new_velocity;
+            }
+            if *body != new_body {
+                *body = new_body;
+            }
+        });
+        let entities = context.entities();
+        for (id, chunk) in crossed {
+            entities.set_chunk(id, chunk);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,390 @@
+use std::{cell::{Cell, RefCell}, collections::{BTreeMap, VecDeque}, fs, io, path::Path, sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc, Mutex, OnceLock}, thread, time::{Duration, Instant}};
+
+use serde_json::{json, Value};
+
+use crate::engine::job::hooks::{add_job_hook, remove_job_hook, JobHook, JobHookId};
+
+/// Frames kept for exporting before the oldest are dropped.
+pub const DEFAULT_FRAME_HISTORY: usize = 300;
+/// Category of scopes timed with profile_scope! and profile_function!.
+pub const SCOPE_CATEGORY: &str = "scope";
+/// Category of the jobs run by job threads.
+pub const JOB_CATEGORY: &str = "job";
+/// Category of the frames themselves in a Chrome trace.
+pub const FRAME_CATEGORY: &str = "frame";
+
+/// A timed scope that ended on a thread.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProfileEvent {
+    pub name: &'static str,
+    pub category: &'static str,
+    /// Since the profiler was first used.
+    pub start: Duration,
+    pub duration: Duration,
+    /// Number of scopes it's within on its thread.
+    pub depth: u32
+}
+
+/// Every time a scope of one name ran on a thread during a frame.
+/// Nested scopes of the same name are each counted, so can add up to more than the frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScopeTotal {
+    pub name: &'static str,
+    pub category: &'static str,
+    pub count: u32,
+    pub total: Duration,
+    pub longest: Duration
+}
+
+/// What one thread did during a frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThreadFrame {
+    /// Unique for the process, starting at 1.
+    pub thread_id: u64,
+    pub thread_name: String,
+    /// In the order they ended, so nested scopes come before the scopes they're in.
+    pub events: Vec<ProfileEvent>,
+    /// Longest total first.
+    pub totals: Vec<ScopeTotal>
+}
+
+impl ThreadFrame {
+    pub fn total(&self, name: &str) -> Option<&ScopeTotal> {
+        return self.totals.iter().find(|total| total.name == name);
+    }
+}
+
+/// Everything timed between two calls to end_frame(). Threads that timed nothing during the frame are left out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameProfile {
+    /// Counts up from 0 since the profiler was first used.
+    pub number: u64,
+    /// Since the profiler was first used.
+    pub start: Duration,
+    pub duration: Duration,
+    pub threads: Vec<ThreadFrame>
+}
+
+impl FrameProfile {
+    /// Total of a scope across every thread.
+    pub fn total(&self, name: &str) -> Option<ScopeTotal> {
+        return self.threads.iter().filter_map(|thread| thread.total(name)).copied().reduce(|a, b| ScopeTotal {
+            name: a.name,
+            category: a.category,
+            count: a.count + b.count,
+            total: a.total + b.total,
+            longest: a.longest.max(b.longest)
+        });
+    }
+}
+
+/// Events a thread recorded since the last frame ended.
+struct ThreadBuffer {
+    id: u64,
+    name: String,
+    events: Mutex<Vec<ProfileEvent>>
+}
+
+struct FrameHistory {
+    frames: VecDeque<FrameProfile>,
+    capacity: usize,
+    next_number: u64,
+    current_start: Duration
+}
+
+struct Profiler {
+    enabled: AtomicBool,
+    epoch: Instant,
+    next_thread_id: AtomicU64,
+    threads: Mutex<Vec<Arc<ThreadBuffer>>>,
+    history: Mutex<FrameHistory>,
+    job_hook: Mutex<Option<JobHookId>>
+}
+
+fn profiler() -> &'static Profiler {
+    static PROFILER: OnceLock<Profiler> = OnceLock::new();
+    return PROFILER.get_or_init(|| Profiler {
+        enabled: AtomicBool::new(false),
+        epoch: Instant::now(),
+        next_thread_id: AtomicU64::new(1),
+        threads: Mutex::new(Vec::new()),
+        history: Mutex::new(FrameHistory { frames: VecDeque::new(), capacity: DEFAULT_FRAME_HISTORY, next_number: 0, current_start: Duration::ZERO }),
+        job_hook: Mutex::new(None)
+    });
+}
+
+thread_local! {
+    static BUFFER: RefCell<Option<Arc<ThreadBuffer>>> = const { RefCell::new(None) };
+    static DEPTH: Cell<u32> = const { Cell::new(0) };
+    /// Scopes of the jobs running on this thread, innermost last.
+    static JOB_SCOPES: RefCell<Vec<Scope>> = const { RefCell::new(Vec::new()) };
+}
+
+fn record(event: ProfileEvent) {
+    // Scopes can end while the thread is exiting, after its buffer is gone.
+    let _ = BUFFER.try_with(|buffer| {
+        let mut buffer = buffer.borrow_mut();
+        let buffer = buffer.get_or_insert_with(|| {
+            let profiler = profiler();
+            let id = profiler.next_thread_id.fetch_add(1, Ordering::Relaxed);
+            let name = thread::current().name().map(str::to_string).unwrap_or_else(|| format!("thread {}", id));
+            let buffer = Arc::new(ThreadBuffer { id, name, events: Mutex::new(Vec::new()) });
+            profiler.threads.lock().unwrap().push(buffer.clone());
+            buffer
+        });
+        buffer.events.lock().unwrap().push(event);
+    });
+}
+
+/// Times from when it's made until it's dropped, recording the time on the current thread.
+/// Does nothing when the profiler is disabled. Made by profile_scope! and profile_function!.
+#[must_use = "the scope is timed until it's dropped"]
+pub struct Scope {
+    name: &'static str,
+    category: &'static str,
+    /// None if the profiler was disabled when the scope started.
+    start: Option<Instant>,
+    depth: u32
+}
+
+impl Scope {
+    pub fn new(name: &'static str, category: &'static str) -> Scope {
+        if !is_enabled() {
+            return Scope { name, category, start: None, depth: 0 };
+        }
+        let depth = DEPTH.with(|depth| depth.replace(depth.get() + 1));
+        return Scope { name, category, start: Some(Instant::now()), depth };
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let Some(start) = self.start else {
+            return;
+        };
+        let duration = start.elapsed();
+        let _ = DEPTH.try_with(|depth| depth.set(self.depth));
+        record(ProfileEvent {
+            name: self.name,
+            category: self.category,
+            start: start.saturating_duration_since(profiler().epoch),
+            duration,
+            depth: self.depth
+        });
+    }
+}
+
+/// Time the rest of the enclosing block, optionally with a category other than SCOPE_CATEGORY.
+/// ```
+/// # use shared::{profile_scope, engine::profile};
+/// profile::enable();
+/// {
+///     profile_scope!("load chunks");
+///     profile_scope!("decompress", "io");
+/// }
+/// let frame = profile::end_frame().unwrap();
+/// let thread = &frame.threads[0];
+/// assert_eq!(thread.total("load chunks").unwrap().count, 1);
+/// assert_eq!(thread.total("decompress").unwrap().category, "io");
+/// assert_eq!(thread.events.iter().find(|event| event.name == "decompress").unwrap().depth, 1);
+/// ```
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::engine::profile::Scope::new($name, $crate::engine::profile::SCOPE_CATEGORY);
+    };
+    ($name:expr, $category:expr) => {
+        let _profile_scope = $crate::engine::profile::Scope::new($name, $category);
+    };
+}
+
+/// Time the rest of the enclosing function, named by its path.
+/// ```
+/// # use shared::{profile_function, engine::profile};
+/// fn generate_terrain() {
+///     profile_function!();
+/// }
+/// profile::enable();
+/// generate_terrain();
+/// let frame = profile::end_frame().unwrap();
+/// assert!(frame.threads[0].events[0].name.ends_with("generate_terrain"));
+/// ```
+#[macro_export]
+macro_rules! profile_function {
+    () => {
+        let _profile_scope = $crate::engine::profile::Scope::new({
+            fn f() {}
+            fn type_name_of<T>(_: T) -> &'static str {
+                return std::any::type_name::<T>();
+            }
+            let name = type_name_of(f);
+            &name[..name.len() - 3]
+        }, $crate::engine::profile::SCOPE_CATEGORY);
+    };
+}
+
+/// Times every job job threads run, as JOB_CATEGORY scopes named after the job.
+struct ProfilerJobHook;
+
+impl JobHook for ProfilerJobHook {
+    fn job_started(&self, name: &'static str) {
+        let scope = Scope::new(name, JOB_CATEGORY);
+        JOB_SCOPES.with(|scopes| scopes.borrow_mut().push(scope));
+    }
+
+    fn job_finished(&self, _name: &'static str) {
+        let scope = JOB_SCOPES.with(|scopes| scopes.borrow_mut().pop());
+        drop(scope);
+    }
+}
+
+/// Start timing scopes and jobs. The first frame starts when the profiler is first used.
+pub fn enable() {
+    let profiler = profiler();
+    let mut job_hook = profiler.job_hook.lock().unwrap();
+    if job_hook.is_none() {
+        *job_hook = Some(add_job_hook(Arc::new(ProfilerJobHook)));
+    }
+    profiler.enabled.store(true, Ordering::Release);
+}
+
+/// Stop timing. Scopes already started are still recorded when they end.
+pub fn disable() {
+    let profiler = profiler();
+    profiler.enabled.store(false, Ordering::Release);
+    if let Some(id) = profiler.job_hook.lock().unwrap().take() {
+        remove_job_hook(id);
+    }
+}
+
+pub fn is_enabled() -> bool {
+    return profiler().enabled.load(Ordering::Acquire);
+}
+
+/// Set how many frames are kept, dropping the oldest if there are more.
+pub fn set_frame_history(capacity: usize) {
+    let mut history = profiler().history.lock().unwrap();
+    history.capacity = capacity;
+    while history.frames.len() > capacity {
+        history.frames.pop_front();
+    }
+}
+
+fn totals(events: &[ProfileEvent]) -> Vec<ScopeTotal> {
+    let mut totals: BTreeMap<&'static str, ScopeTotal> = BTreeMap::new();
+    for event in events.iter() {
+        let total = totals.entry(event.name).or_insert(ScopeTotal {
+            name: event.name,
+            category: event.category,
+            count: 0,
+            total: Duration::ZERO,
+            longest: Duration::ZERO
+        });
+        total.count += 1;
+        total.total += event.duration;
+        total.longest = total.longest.max(event.duration);
+    }
+    let mut totals: Vec<ScopeTotal> = totals.into_values().collect();
+    totals.sort_by_key(|total| std::cmp::Reverse(total.total));
+    return totals;
+}
+
+/// End the current frame, gathering what every thread timed during it, and start the next.
+/// Called once per frame or tick by whatever drives the loop. Returns None while the profiler is disabled.
+pub fn end_frame() -> Option<FrameProfile> {
+    if !is_enabled() {
+        return None;
+    }
+    let profiler = profiler();
+    let now = profiler.epoch.elapsed();
+    let mut threads = Vec::new();
+    profiler.threads.lock().unwrap().retain(|buffer| {
+        let events = std::mem::take(&mut *buffer.events.lock().unwrap());
+        if !events.is_empty() {
+            threads.push(ThreadFrame { thread_id: buffer.id, thread_name: buffer.name.clone(), totals: totals(&events), events });
+        }
+        // Only the profiler has the buffer once its thread has exited.
+        return Arc::strong_count(buffer) > 1;
+    });
+    threads.sort_by_key(|thread| thread.thread_id);
+
+    let mut history = profiler.history.lock().unwrap();
+    let frame = FrameProfile { number: history.next_number, start: history.current_start, duration: now - history.current_start, threads };
+    history.next_number += 1;
+    history.current_start = now;
+    if history.capacity > 0 {
+        if history.frames.len() == history.capacity {
+            history.frames.pop_front();
+        }
+        history.frames.push_back(frame.clone());
+    }
+    return Some(frame);
+}
+
+/// The frames kept, oldest first.
+pub fn frames() -> Vec<FrameProfile> {
+    return profiler().history.lock().unwrap().frames.iter().cloned().collect();
+}
+
+/// Forget the frames kept.
+pub fn clear_frames() {
+    profiler().history.lock().unwrap().frames.clear();
+}
+
+fn micros(duration: Duration) -> f64 {
+    return duration.as_nanos() as f64 / 1000.0;
+}
+
+/// Convert frames to Chrome's trace event format, to open in chrome://tracing or Perfetto.
+/// Each thread gets a track named after it, and the frames are drawn on a track of their own.
+/// ```
+/// # use shared::{profile_scope, engine::profile};
+/// profile::enable();
+/// {
+///     profile_scope!("mesh");
+/// }
+/// profile::end_frame();
+/// let trace: serde_json::Value = serde_json::from_str(&profile::chrome_trace(&profile::frames())).unwrap();
+/// let mesh = trace["traceEvents"].as_array().unwrap().iter().find(|event| event["name"] == "mesh").unwrap();
+/// assert_eq!(mesh["ph"], "X");
+/// assert_eq!(mesh["cat"], "scope");
+/// ```
+pub fn chrome_trace(frames: &[FrameProfile]) -> String {
+    let pid = std::process::id();
+    let mut events: Vec<Value> = vec![json!({ "name": "thread_name", "ph": "M", "pid": pid, "tid": 0, "args": { "name": "frames" } })];
+    let mut named_threads = Vec::new();
+    for frame in frames.iter() {
+        events.push(json!({
+            "name": format!("frame {}", frame.number),
+            "cat": FRAME_CATEGORY,
+            "ph": "X",
+            "ts": micros(frame.start),
+            "dur": micros(frame.duration),
+            "pid": pid,
+            "tid": 0
+        }));
+        for thread in frame.threads.iter() {
+            if !named_threads.contains(&thread.thread_id) {
+                named_threads.push(thread.thread_id);
+                events.push(json!({ "name": "thread_name", "ph": "M", "pid": pid, "tid": thread.thread_id, "args": { "name": thread.thread_name } }));
+            }
+            for event in thread.events.iter() {
+                events.push(json!({
+                    "name": event.name,
+                    "cat": event.category,
+                    "ph": "X",
+                    "ts": micros(event.start),
+                    "dur": micros(event.duration),
+                    "pid": pid,
+                    "tid": thread.thread_id
+                }));
+            }
+        }
+    }
+    return json!({ "traceEvents": events, "displayTimeUnit": "ms" }).to_string();
+}
+
+/// Write the frames kept to a Chrome trace file.
+pub fn write_chrome_trace(path: &Path) -> io::Result<()> {
+    return fs::write(path, chrome_trace(&frames()));
+}
\ No newline at end of file
@@ -0,0 +1,206 @@
+use std::sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, Mutex};
+
+/// Shared flag used to request that a long running operation stops early.
+/// Cloning the token shares the same flag, so any clone can cancel the operation.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancelToken {
+    /// Create a new token that is not cancelled.
+    /// ```
+    /// # use shared::engine::progress::CancelToken;
+    /// let token = CancelToken::new();
+    /// assert!(!token.is_cancelled());
+    /// ```
+    pub fn new() -> Self {
+        return CancelToken { cancelled: Arc::new(AtomicBool::new(false)) };
+    }
+
+    /// Request cancellation. All clones of this token will observe it.
+    /// ```
+    /// # use shared::engine::progress::CancelToken;
+    /// let token = CancelToken::new();
+    /// let clone = token.clone();
+    /// clone.cancel();
+    /// assert!(token.is_cancelled());
+    /// ```
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Atomically check if cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        return self.cancelled.load(Ordering::Acquire);
+    }
+}
+
+/// A snapshot of the progress of an operation, passed to progress callbacks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProgressEvent {
+    pub stage: &'static str,
+    pub completed: usize,
+    pub total: usize
+}
+
+impl ProgressEvent {
+    /// Completion in the range 0.0 to 1.0. An operation with no work is considered complete.
+    /// ```
+    /// # use shared::engine::progress::ProgressEvent;
+    /// let event = ProgressEvent { stage: "generate", completed: 25, total: 100 };
+    /// assert_eq!(event.fraction(), 0.25);
+    /// ```
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        return (self.completed.min(self.total) as f32) / (self.total as f32);
+    }
+
+    /// Completion as a whole percentage, suitable for a loading screen.
+    /// ```
+    /// # use shared::engine::progress::ProgressEvent;
+    /// let event = ProgressEvent { stage: "generate", completed: 1, total: 3 };
+    /// assert_eq!(event.percent(), 33);
+    /// ```
+    pub fn percent(&self) -> u32 {
+        return (self.fraction() * 100.0) as u32;
+    }
+}
+
+type ProgressCallback = Arc<dyn Fn(&ProgressEvent) + Send + Sync>;
+
+struct Inner {
+    stage: Mutex<&'static str>,
+    completed: AtomicUsize,
+    total: AtomicUsize,
+    cancel: CancelToken,
+    callbacks: Mutex<Vec<ProgressCallback>>
+}
+
+/// Tracks the progress of a long running operation, such as generating the spawn area of a new world.
+/// Work can be reported from any thread (including jobs), and every report invokes the registered callbacks.
+/// The tracker also carries a CancelToken so the owner of the operation can abort it cleanly.
+/// Cloning the tracker shares the same state.
+#[derive(Clone)]
+pub struct ProgressTracker {
+    inner: Arc<Inner>
+}
+
+impl ProgressTracker {
+    /// Create a new tracker for an operation with a total amount of work units.
+    /// ```
+    /// # use shared::engine::progress::ProgressTracker;
+    /// let tracker = ProgressTracker::new("spawn area", 100);
+    /// assert_eq!(tracker.snapshot().total, 100);
+    /// ```
+    pub fn new(stage: &'static str, total: usize) -> Self {
+        return ProgressTracker { inner: Arc::new(Inner {
+            stage: Mutex::new(stage),
+            completed: AtomicUsize::new(0),
+            total: AtomicUsize::new(total),
+            cancel: CancelToken::new(),
+            callbacks: Mutex::new(Vec::new())
+        })};
+    }
+
+    /// Register a callback that is invoked every time progress is reported.
+    /// The callback is called on whichever thread reported the progress, without the tracker locked,
+    /// so it may itself check or report progress.
+    /// ```
+    /// # use shared::engine::progress::ProgressTracker;
+    /// # use std::sync::{Arc, atomic::{AtomicU32, Ordering}};
+    /// let tracker = ProgressTracker::new("spawn area", 4);
+    /// let last_percent = Arc::new(AtomicU32::new(0));
+    /// let captured = last_percent.clone();
+    /// tracker.on_progress(move |event| captured.store(event.percent(), Ordering::Relaxed));
+    /// tracker.advance(2);
+    /// assert_eq!(last_percent.load(Ordering::Relaxed), 50);
+    ///
+    /// let nested = tracker.clone();
+    /// tracker.on_progress(move |event| if event.completed == 3 { nested.advance(1); });
+    /// tracker.advance(1);
+    /// assert!(tracker.is_finished());
+    /// ```
+    pub fn on_progress<F>(&self, callback: F)
+    where F: Fn(&ProgressEvent) + Send + Sync + 'static {
+        self.inner.callbacks.lock().unwrap().push(Arc::new(callback));
+    }
+
+    /// Report that some units of work were completed.
+    /// ```
+    /// # use shared::engine::progress::ProgressTracker;
+    /// let tracker = ProgressTracker::new("spawn area", 10);
+    /// tracker.advance(3);
+    /// tracker.advance(7);
+    /// assert!(tracker.is_finished());
+    /// ```
+    pub fn advance(&self, units: usize) {
+        self.inner.completed.fetch_add(units, Ordering::AcqRel);
+        self.notify();
+    }
+
+    /// Begin a new stage of the operation, resetting the completed count.
+    /// ```
+    /// # use shared::engine::progress::ProgressTracker;
+    /// let tracker = ProgressTracker::new("generate", 10);
+    /// tracker.advance(10);
+    /// tracker.set_stage("light", 5);
+    /// let event = tracker.snapshot();
+    /// assert_eq!(event.stage, "light");
+    /// assert_eq!(event.completed, 0);
+    /// ```
+    pub fn set_stage(&self, stage: &'static str, total: usize) {
+        *self.inner.stage.lock().unwrap() = stage;
+        self.inner.total.store(total, Ordering::Release);
+        self.inner.completed.store(0, Ordering::Release);
+        self.notify();
+    }
+
+    /// Get the current progress without notifying any callbacks.
+    pub fn snapshot(&self) -> ProgressEvent {
+        return ProgressEvent {
+            stage: *self.inner.stage.lock().unwrap(),
+            completed: self.inner.completed.load(Ordering::Acquire),
+            total: self.inner.total.load(Ordering::Acquire)
+        };
+    }
+
+    /// Check if all work units of the current stage are completed.
+    pub fn is_finished(&self) -> bool {
+        let event = self.snapshot();
+        return event.completed >= event.total;
+    }
+
+    /// Request that the operation stops. Work in progress should check is_cancelled() and exit early.
+    /// ```
+    /// # use shared::engine::progress::ProgressTracker;
+    /// let tracker = ProgressTracker::new("spawn area", 100);
+    /// let worker_view = tracker.clone();
+    /// tracker.cancel();
+    /// assert!(worker_view.is_cancelled());
+    /// ```
+    pub fn cancel(&self) {
+        self.inner.cancel.cancel();
+    }
+
+    /// Atomically check if cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        return self.inner.cancel.is_cancelled();
+    }
+
+    /// Get a token sharing this tracker's cancellation state, for code that only needs to observe cancellation.
+    pub fn cancel_token(&self) -> CancelToken {
+        return self.inner.cancel.clone();
+    }
+
+    fn notify(&self) {
+        let event = self.snapshot();
+        // Cloned out so a callback registering another doesn't deadlock.
+        let callbacks = self.inner.callbacks.lock().unwrap().clone();
+        for callback in callbacks.iter() {
+            callback(&event);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::{collections::{BTreeMap, HashSet}, fs, io, path::Path};
+
+use serde::{Serialize, Deserialize};
+
+use super::{math::coords::BlockPos, save::level::write_atomically};
+
+/// Name of the file in a save's directory holding its protection regions and teams.
+pub const PROTECTION_FILE: &str = "protection.json";
+
+/// What a player is trying to do to a protected block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ProtectedAction {
+    Build,
+    Break,
+    /// Using blocks such as doors and chests without changing the world.
+    Interact
+}
+
+/// Permissions for a protected cuboid of blocks.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtectionRegion {
+    pub name: String,
+    /// Minimum corner, inclusive.
+    pub min: BlockPos,
+    /// Maximum corner, inclusive.
+    pub max: BlockPos,
+    /// Where regions overlap, only those with the highest priority apply. Lets a claim sit inside spawn protection.
+    pub priority: i32,
+    /// Players (by UUID) allowed to do anything in the region.
+    pub members: HashSet<u128>,
+    /// Teams whose members are allowed to do anything in the region.
+    pub teams: HashSet<String>,
+    /// Whether players who aren't members may interact with blocks.
+    pub public_interact: bool
+}
+
+impl ProtectionRegion {
+    /// Create a region with no members from any two opposite corners.
+    pub fn new(name: &str, a: BlockPos, b: BlockPos) -> ProtectionRegion {
+        return ProtectionRegion {
+            name: name.to_string(),
+            min: BlockPos::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: BlockPos::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+            priority: 0,
+            members: HashSet::new(),
+            teams: HashSet::new(),
+            public_interact: true
+        };
+    }
+
+    pub fn contains(&self, block: BlockPos) -> bool {
+        return block.x >= self.min.x && block.x <= self.max.x
+            && block.y >= self.min.y && block.y <= self.max.y
+            && block.z >= self.min.z && block.z <= self.max.z;
+    }
+
+    /// Check if a player, who is a member of the given teams, may perform an action within this region.
+    pub fn allows(&self, player: u128, teams: &[String], action: ProtectedAction) -> bool {
+        if self.members.contains(&player) || teams.iter().any(|team| self.teams.contains(team)) {
+            return true;
+        }
+        return action == ProtectedAction::Interact && self.public_interact;
+    }
+}
+
+/// Reason a region could not be added.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProtectionError {
+    DuplicateName(String),
+    UnknownRegion(String)
+}
+
+impl std::fmt::Display for ProtectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            ProtectionError::DuplicateName(name) => write!(f, "a region named {} already exists", name),
+            ProtectionError::UnknownRegion(name) => write!(f, "no region named {}", name)
+        };
+    }
+}
+
+impl std::error::Error for ProtectionError {}
+
+/// Named groups of players, which protection regions can let in all at once.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Teams {
+    teams: BTreeMap<String, HashSet<u128>>
+}
+
+impl Teams {
+    pub fn new() -> Teams {
+        return Teams::default();
+    }
+
+    /// Add a player to a team, creating the team if it's new. False if they were already in it.
+    pub fn join(&mut self, team: &str, player: u128) -> bool {
+        return self.teams.entry(team.to_string()).or_default().insert(player);
+    }
+
+    /// Take a player out of a team, dropping the team once it's empty. False if they weren't in it.
+    pub fn leave(&mut self, team: &str, player: u128) -> bool {
+        let Some(members) = self.teams.get_mut(team) else {
+            return false;
+        };
+        let left = members.remove(&player);
+        if members.is_empty() {
+            self.teams.remove(team);
+        }
+        return left;
+    }
+
+    pub fn members(&self, team: &str) -> Option<&HashSet<u128>> {
+        return self.teams.get(team);
+    }
+
+    /// Names of the teams a player is in, in order.
+    pub fn teams_of(&self, player: u128) -> Vec<String> {
+        return self.teams.iter().filter(|(_, members)| members.contains(&player)).map(|(name, _)| name.clone()).collect();
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        return self.teams.keys();
+    }
+}
+
+/// All protection regions of a world, and the teams they can let in. Checked by the server before applying any
+/// block change or interaction, and saved with the world data.
+/// Blocks outside every region are unprotected.
+/// ```
+/// # use shared::engine::protection::{ProtectionRegions, ProtectionRegion, ProtectedAction};
+/// # use shared::engine::math::coords::BlockPos;
+/// let directory = std::env::temp_dir().join(format!("protection_doctest_{}", std::process::id()));
+/// # std::fs::create_dir_all(&directory).unwrap();
+/// assert_eq!(ProtectionRegions::load(&directory).unwrap(), ProtectionRegions::new());
+/// let mut regions = ProtectionRegions::new();
+/// let mut base = ProtectionRegion::new("base", BlockPos::new(0, 0, 0), BlockPos::new(9, 9, 9));
+/// base.teams.insert("builders".to_string());
+/// regions.add(base).unwrap();
+/// regions.teams_mut().join("builders", 7);
+/// regions.save(&directory).unwrap();
+///
+/// let regions = ProtectionRegions::load(&directory).unwrap();
+/// let teams = regions.teams().teams_of(7);
+/// assert!(regions.can(7, &teams, BlockPos::new(5, 5, 5), ProtectedAction::Build));
+/// assert!(!regions.can(8, &regions.teams().teams_of(8), BlockPos::new(5, 5, 5), ProtectedAction::Build));
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtectionRegions {
+    regions: Vec<ProtectionRegion>,
+    #[serde(default)]
+    teams: Teams
+}
+
+impl ProtectionRegions {
+    pub fn new() -> ProtectionRegions {
+        return ProtectionRegions { regions: Vec::new(), teams: Teams::new() };
+    }
+
+    /// Read the regions and teams saved in a save's directory, or none for a save without any.
+    pub fn load(directory: &Path) -> io::Result<ProtectionRegions> {
+        let path = directory.join(PROTECTION_FILE);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(ProtectionRegions::new()),
+            Err(error) => return Err(error)
+        };
+        return serde_json::from_slice(&data).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), error)));
+    }
+
+    /// Write the regions and teams into a save's directory, replacing the old file only once the new one is written.
+    pub fn save(&self, directory: &Path) -> io::Result<()> {
+        let data = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        return write_atomically(&directory.join(PROTECTION_FILE), &data);
+    }
+
+    pub fn teams(&self) -> &Teams {
+        return &self.teams;
+    }
+
+    pub fn teams_mut(&mut self) -> &mut Teams {
+        return &mut self.teams;
+    }
+
+    /// Add a region. Names are unique, as commands refer to regions by name.
+    pub fn add(&mut self, region: ProtectionRegion) -> Result<(), ProtectionError> {
+        if self.get(&region.name).is_some() {
+            return Err(ProtectionError::DuplicateName(region.name));
+        }
+        self.regions.push(region);
+        return Ok(());
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<ProtectionRegion, ProtectionError> {
+        return match self.regions.iter().position(|region| region.name == name) {
+            Some(index) => Ok(self.regions.remove(index)),
+            None => Err(ProtectionError::UnknownRegion(name.to_string()))
+        };
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ProtectionRegion> {
+        return self.regions.iter().find(|region| region.name == name);
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut ProtectionRegion> {
+        return self.regions.iter_mut().find(|region| region.name == name);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ProtectionRegion> {
+        return self.regions.iter();
+    }
+
+    /// Every region containing a block.
+    pub fn regions_at(&self, block: BlockPos) -> impl Iterator<Item = &ProtectionRegion> {
+        return self.regions.iter().filter(move |region| region.contains(block));
+    }
+
+    /// Check if a player may perform an action on a block.
+    /// Only the highest priority regions containing the block are considered, and any one of them allowing it is enough.
+    /// ```
+    /// # use shared::engine::protection::{ProtectionRegions, ProtectionRegion, ProtectedAction};
+    /// # use shared::engine::math::coords::BlockPos;
+    /// let mut regions = ProtectionRegions::new();
+    /// regions.add(ProtectionRegion::new("spawn", BlockPos::new(-100, -64, -100), BlockPos::new(100, 320, 100))).unwrap();
+    /// let mut claim = ProtectionRegion::new("alice_house", BlockPos::new(10, 60, 10), BlockPos::new(20, 80, 20));
+    /// claim.priority = 1;
+    /// claim.members.insert(7);
+    /// regions.add(claim).unwrap();
+    ///
+    /// let alice = 7;
+    /// let bob = 8;
+    /// assert!(regions.can(alice, &[], BlockPos::new(15, 70, 15), ProtectedAction::Build));
+    /// assert!(!regions.can(bob, &[], BlockPos::new(15, 70, 15), ProtectedAction::Build));
+    /// assert!(!regions.can(alice, &[], BlockPos::new(0, 70, 0), ProtectedAction::Break));
+    /// assert!(regions.can(bob, &[], BlockPos::new(500, 70, 0), ProtectedAction::Break));
+    /// ```
+    pub fn can(&self, player: u128, teams: &[String], block: BlockPos, action: ProtectedAction) -> bool {
+        let Some(priority) = self.regions_at(block).map(|region| region.priority).max() else {
+            return true;
+        };
+        return self.regions_at(block)
+            .filter(|region| region.priority == priority)
+            .any(|region| region.allows(player, teams, action));
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::item::{ItemRegistry, ItemStack};
+
+use super::{Ingredient, Recipe, MAX_GRID_SIZE};
+
+/// An ingredient as written in a content pack: an item, or a list of items any of which will do.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IngredientDefinition {
+    Item(String),
+    AnyOf(Vec<String>)
+}
+
+impl IngredientDefinition {
+    fn names(&self) -> &[String] {
+        return match self {
+            IngredientDefinition::Item(name) => std::slice::from_ref(name),
+            IngredientDefinition::AnyOf(names) => names
+        };
+    }
+}
+
+/// A recipe as written in a content pack's recipes directory, with items named rather than numbered.
+/// Shaped recipes have a pattern of rows, each character a key into the ingredients and spaces empty,
+/// while shapeless recipes list their ingredients.
+/// ```
+/// # use shared::engine::item::{ItemRegistry, ItemStack};
+/// # use shared::engine::recipe::{CraftingGrid, RecipeDefinition};
+/// let mut items = ItemRegistry::new();
+/// let (log, stick) = (items.register("cube:log").unwrap(), items.register("cube:stick").unwrap());
+/// let definition: RecipeDefinition = toml::from_str(r##"
+///     name = "cube:sticks"
+///     result = "cube:stick"
+///     count = 4
+///     pattern = ["#", "#"]
+///     key = { "#" = "cube:log" }
+/// "##).unwrap();
+/// assert_eq!(definition.validate(), Ok(()));
+/// let recipe = definition.resolve(&items).unwrap();
+/// assert_eq!(recipe.result, ItemStack::new(stick, 4));
+/// assert!(recipe.matches(&CraftingGrid::new(1, vec![Some(log), Some(log)])));
+///
+/// let unknown: RecipeDefinition = toml::from_str(r#"
+///     name = "cube:torch"
+///     result = "cube:torch"
+///     ingredients = ["cube:stick", ["cube:coal", "cube:charcoal"]]
+/// "#).unwrap();
+/// assert_eq!(unknown.resolve(&items), Err("recipe cube:torch uses unknown item cube:torch".to_string()));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecipeDefinition {
+    pub name: String,
+    pub result: String,
+    #[serde(default = "default_count")]
+    pub count: u32,
+    #[serde(default)]
+    pub pattern: Vec<String>,
+    #[serde(default)]
+    pub key: BTreeMap<String, IngredientDefinition>,
+    #[serde(default)]
+    pub ingredients: Vec<IngredientDefinition>
+}
+
+fn default_count() -> u32 {
+    return 1;
+}
+
+impl RecipeDefinition {
+    /// Check the recipe is either shaped or shapeless, fits the grid, and makes something.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.count == 0 {
+            return Err(format!("recipe {} must make at least 1 item", self.name));
+        }
+        if self.pattern.is_empty() == self.ingredients.is_empty() {
+            return Err(format!("recipe {} must have either a pattern or ingredients", self.name));
+        }
+        if self.ingredients.len() > MAX_GRID_SIZE * MAX_GRID_SIZE {
+            return Err(format!("recipe {} has more ingredients than fit in the grid", self.name));
+        }
+        if self.pattern.len() > MAX_GRID_SIZE || self.pattern.iter().any(|row| row.chars().count() > MAX_GRID_SIZE) {
+            return Err(format!("recipe {} has a pattern larger than {}x{}", self.name, MAX_GRID_SIZE, MAX_GRID_SIZE));
+        }
+        if let Some(key) = self.pattern.iter().flat_map(|row| row.chars()).find(|key| *key != ' ' && !self.key.contains_key(&key.to_string())) {
+            return Err(format!("recipe {} has no ingredient for '{}' in its pattern", self.name, key));
+        }
+        if self.key.values().chain(self.ingredients.iter()).any(|ingredient| ingredient.names().is_empty()) {
+            return Err(format!("recipe {} has an ingredient with no items", self.name));
+        }
+        return Ok(());
+    }
+
+    /// The recipe, with its items looked up in a registry. Fails naming the first item that isn't registered.
+    pub fn resolve(&self, items: &ItemRegistry) -> Result<Recipe, String> {
+        self.validate()?;
+        let item = |name: &str| items.id(name).ok_or_else(|| format!("recipe {} uses unknown item {}", self.name, name));
+        let ingredient = |definition: &IngredientDefinition| -> Result<Ingredient, String> {
+            return Ok(Ingredient::new(definition.names().iter().map(|name| item(name)).collect::<Result<Vec<_>, _>>()?));
+        };
+        let result = ItemStack::new(item(&self.result)?, self.count);
+        if self.ingredients.is_empty() {
+            let mut rows = Vec::new();
+            for row in self.pattern.iter() {
+                let cells = row.chars().map(|key| match key {
+                    ' ' => Ok(None),
+                    key => ingredient(&self.key[&key.to_string()]).map(Some)
+                }).collect::<Result<Vec<_>, _>>()?;
+                rows.push(cells);
+            }
+            return Ok(Recipe::shaped(&self.name, rows, result));
+        }
+        let ingredients = self.ingredients.iter().map(ingredient).collect::<Result<Vec<_>, _>>()?;
+        return Ok(Recipe::shapeless(&self.name, ingredients, result));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,247 @@
+use std::fmt;
+
+use crate::engine::item::{Inventory, ItemId, ItemRegistry, ItemStack};
+
+/// Dense numeric runtime id of a recipe, in the order recipes were registered.
+pub type RecipeId = u16;
+
+/// Widest and tallest crafting grid, and so recipe pattern.
+pub const MAX_GRID_SIZE: usize = 3;
+
+pub mod registry;
+pub mod definition;
+
+pub use registry::RecipeRegistry;
+pub use definition::RecipeDefinition;
+
+/// Items that can fill one place in a recipe, any of which will do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ingredient {
+    items: Vec<ItemId>
+}
+
+impl Ingredient {
+    pub fn new(items: Vec<ItemId>) -> Ingredient {
+        return Ingredient { items };
+    }
+
+    /// An ingredient only one item fills.
+    pub fn item(item: ItemId) -> Ingredient {
+        return Ingredient { items: vec![item] };
+    }
+
+    pub fn items(&self) -> &[ItemId] {
+        return &self.items;
+    }
+
+    pub fn matches(&self, item: ItemId) -> bool {
+        return self.items.contains(&item);
+    }
+}
+
+/// How a recipe's ingredients are laid out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecipeShape {
+    /// Ingredients in a pattern, row by row, which may be placed anywhere in the grid and mirrored left to right.
+    /// Patterns are trimmed to the ingredients in them, so they have no empty rows or columns at their edges.
+    Shaped {
+        width: usize,
+        height: usize,
+        cells: Vec<Option<Ingredient>>
+    },
+    /// Ingredients anywhere in the grid, one item each.
+    Shapeless(Vec<Ingredient>)
+}
+
+/// A recipe: the ingredients that make something, and what they make.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Recipe {
+    pub name: String,
+    pub shape: RecipeShape,
+    pub result: ItemStack
+}
+
+impl Recipe {
+    /// A shaped recipe from rows of cells, trimmed of empty rows and columns at its edges.
+    pub fn shaped(name: &str, rows: Vec<Vec<Option<Ingredient>>>, result: ItemStack) -> Recipe {
+        let filled = |row: &Vec<Option<Ingredient>>| row.iter().any(|cell| cell.is_some());
+        let top = rows.iter().position(filled).unwrap_or(0);
+        let bottom = rows.iter().rposition(filled).map_or(top, |bottom| bottom + 1);
+        let rows = &rows[top..bottom];
+        let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let filled_column = |x: usize| rows.iter().any(|row| row.get(x).is_some_and(|cell| cell.is_some()));
+        let left = (0..columns).find(|x| filled_column(*x)).unwrap_or(0);
+        let right = (0..columns).rfind(|x| filled_column(*x)).map_or(left, |right| right + 1);
+        let cells = rows.iter().flat_map(|row| (left..right).map(|x| row.get(x).cloned().flatten())).collect();
+        let shape = RecipeShape::Shaped { width: right - left, height: rows.len(), cells };
+        return Recipe { name: name.to_string(), shape, result };
+    }
+
+    pub fn shapeless(name: &str, ingredients: Vec<Ingredient>, result: ItemStack) -> Recipe {
+        return Recipe { name: name.to_string(), shape: RecipeShape::Shapeless(ingredients), result };
+    }
+
+    /// Whether the items in a grid make this recipe.
+    /// ```
+    /// # use shared::engine::item::ItemStack;
+    /// # use shared::engine::recipe::{CraftingGrid, Ingredient, Recipe};
+    /// let (log, stick, torch) = (1, 2, 3);
+    /// let sticks = Recipe::shaped("cube:sticks", vec![vec![Some(Ingredient::item(log))], vec![Some(Ingredient::item(log))]], ItemStack::new(stick, 4));
+    /// // Anywhere in the grid, but not on its side.
+    /// assert!(sticks.matches(&CraftingGrid::new(3, vec![None, None, Some(log), None, None, Some(log), None, None, None])));
+    /// assert!(!sticks.matches(&CraftingGrid::new(2, vec![Some(log), Some(log), None, None])));
+    ///
+    /// let torches = Recipe::shapeless("cube:torches", vec![Ingredient::item(stick), Ingredient::new(vec![log, torch])], ItemStack::new(torch, 4));
+    /// assert!(torches.matches(&CraftingGrid::new(2, vec![None, Some(log), Some(stick), None])));
+    /// assert!(!torches.matches(&CraftingGrid::new(2, vec![Some(stick), Some(stick), None, None])));
+    /// assert!(!torches.matches(&CraftingGrid::new(2, vec![Some(stick), Some(log), Some(log), None])));
+    /// ```
+    pub fn matches(&self, grid: &CraftingGrid) -> bool {
+        return match &self.shape {
+            RecipeShape::Shaped { width, height, cells } => {
+                let (grid_width, grid_height, items) = grid.trimmed();
+                if (grid_width, grid_height) != (*width, *height) {
+                    return false;
+                }
+                let fits = |mirrored: bool| (0..*height).all(|y| (0..*width).all(|x| {
+                    let item = items[y * width + if mirrored { width - 1 - x } else { x }];
+                    return match (&cells[y * width + x], item) {
+                        (Some(ingredient), Some(item)) => ingredient.matches(item),
+                        (None, None) => true,
+                        _ => false
+                    };
+                }));
+                fits(false) || fits(true)
+            },
+            RecipeShape::Shapeless(ingredients) => {
+                let items: Vec<ItemId> = grid.cells.iter().flatten().copied().collect();
+                items.len() == ingredients.len() && assign(ingredients, &items, &mut vec![false; items.len()])
+            }
+        };
+    }
+}
+
+/// Whether every ingredient can be given a different item, trying each item that fits the first ingredient in turn.
+fn assign(ingredients: &[Ingredient], items: &[ItemId], used: &mut Vec<bool>) -> bool {
+    let Some((ingredient, rest)) = ingredients.split_first() else {
+        return true;
+    };
+    for index in 0..items.len() {
+        if used[index] || !ingredient.matches(items[index]) {
+            continue;
+        }
+        used[index] = true;
+        if assign(rest, items, used) {
+            return true;
+        }
+        used[index] = false;
+    }
+    return false;
+}
+
+/// The items laid out in a crafting grid, row by row, one of each per cell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CraftingGrid {
+    width: usize,
+    cells: Vec<Option<ItemId>>
+}
+
+impl CraftingGrid {
+    /// A grid of rows a number of cells wide. A last row that's short is filled with empty cells.
+    pub fn new(width: usize, mut cells: Vec<Option<ItemId>>) -> CraftingGrid {
+        let width = width.max(1);
+        cells.resize(cells.len().div_ceil(width) * width, None);
+        return CraftingGrid { width, cells };
+    }
+
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.cells.len() / self.width;
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<ItemId> {
+        return if x < self.width { self.cells.get(y * self.width + x).copied().flatten() } else { None };
+    }
+
+    /// Size of the smallest area holding every item, and its cells row by row.
+    fn trimmed(&self) -> (usize, usize, Vec<Option<ItemId>>) {
+        let filled: Vec<(usize, usize)> = (0..self.cells.len()).filter(|index| self.cells[*index].is_some())
+            .map(|index| (index % self.width, index / self.width))
+            .collect();
+        let (Some(left), Some(right)) = (filled.iter().map(|(x, _)| *x).min(), filled.iter().map(|(x, _)| *x).max()) else {
+            return (0, 0, Vec::new());
+        };
+        let (top, bottom) = (filled[0].1, filled[filled.len() - 1].1);
+        let cells = (top..=bottom).flat_map(|y| (left..=right).map(move |x| (x, y))).map(|(x, y)| self.get(x, y)).collect();
+        return (right - left + 1, bottom - top + 1, cells);
+    }
+}
+
+/// Why a player couldn't craft.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CraftError {
+    /// The grid is larger than MAX_GRID_SIZE, or names a slot twice or one the inventory doesn't have.
+    InvalidGrid,
+    /// No recipe is made from what's in the grid.
+    NoRecipe,
+    /// The inventory has no room for what the recipe makes.
+    NoRoom
+}
+
+impl fmt::Display for CraftError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            CraftError::InvalidGrid => write!(f, "the crafting grid isn't valid"),
+            CraftError::NoRecipe => write!(f, "nothing can be crafted from those items"),
+            CraftError::NoRoom => write!(f, "there's no room for what would be crafted")
+        };
+    }
+}
+
+impl std::error::Error for CraftError {}
+
+/// Craft from a grid of an inventory's slots, row by row, with None for empty cells: find the recipe the items in
+/// those slots make, take one item from each, and put what the recipe makes into the inventory. Nothing changes
+/// unless the whole craft succeeds.
+/// ```
+/// # use shared::engine::item::{Inventory, ItemRegistry, ItemStack};
+/// # use shared::engine::recipe::{craft, CraftError, Ingredient, Recipe, RecipeRegistry};
+/// let mut items = ItemRegistry::new();
+/// let (log, stick) = (items.register("cube:log").unwrap(), items.register("cube:stick").unwrap());
+/// let mut recipes = RecipeRegistry::new();
+/// let sticks = recipes.register(Recipe::shaped("cube:sticks", vec![vec![Some(Ingredient::item(log))], vec![Some(Ingredient::item(log))]], ItemStack::new(stick, 4))).unwrap();
+///
+/// let mut inventory = Inventory::new(4);
+/// inventory.set(0, Some(ItemStack::new(log, 3)));
+/// inventory.set(1, Some(ItemStack::new(log, 1)));
+/// assert_eq!(craft(&mut inventory, &[Some(0), Some(1)], 1, &recipes, &items), Ok(sticks));
+/// assert_eq!(inventory.count(log), 2);
+/// assert_eq!(inventory.count(stick), 4);
+/// // Slot 1 is empty now, and a grid can't use a slot twice.
+/// assert_eq!(craft(&mut inventory, &[Some(0), Some(1)], 1, &recipes, &items), Err(CraftError::NoRecipe));
+/// assert_eq!(craft(&mut inventory, &[Some(0), Some(0)], 1, &recipes, &items), Err(CraftError::InvalidGrid));
+/// ```
+pub fn craft(inventory: &mut Inventory, slots: &[Option<usize>], width: usize, recipes: &RecipeRegistry, items: &ItemRegistry) -> Result<RecipeId, CraftError> {
+    if width == 0 || width > MAX_GRID_SIZE || slots.len() > width * MAX_GRID_SIZE {
+        return Err(CraftError::InvalidGrid);
+    }
+    let used: Vec<usize> = slots.iter().flatten().copied().collect();
+    if used.iter().enumerate().any(|(index, slot)| *slot >= inventory.size() || used[..index].contains(slot)) {
+        return Err(CraftError::InvalidGrid);
+    }
+    let grid = CraftingGrid::new(width, slots.iter().map(|slot| slot.and_then(|slot| inventory.get(slot)).map(|stack| stack.item)).collect());
+    let id = recipes.find(&grid).ok_or(CraftError::NoRecipe)?;
+    let mut crafted = inventory.clone();
+    for slot in used {
+        crafted.extract_slot(slot, 1);
+    }
+    let result = recipes.get(id).expect("found recipes are registered").result.clone();
+    if crafted.insert(result, items).is_some() {
+        return Err(CraftError::NoRoom);
+    }
+    *inventory = crafted;
+    return Ok(id);
+}
\ No newline at end of file
@@ -0,0 +1,101 @@
+use std::{collections::HashMap, fmt};
+
+use crate::engine::block::registry::is_valid_name;
+
+use super::{CraftingGrid, Recipe, RecipeId};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecipeRegistryError {
+    /// Names must be "namespace:path", using only lowercase letters, digits, and underscores.
+    InvalidName(String),
+    Duplicate(String),
+    Full
+}
+
+impl fmt::Display for RecipeRegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            RecipeRegistryError::InvalidName(name) => write!(f, "invalid recipe id {}, expected namespace:path", name),
+            RecipeRegistryError::Duplicate(name) => write!(f, "recipe {} is already registered", name),
+            RecipeRegistryError::Full => write!(f, "cannot register more than {} recipes", RecipeId::MAX as usize + 1)
+        };
+    }
+}
+
+impl std::error::Error for RecipeRegistryError {}
+
+/// Every recipe players can craft, looked up by id, name, or what's in a crafting grid.
+/// ```
+/// # use shared::engine::item::ItemStack;
+/// # use shared::engine::recipe::{CraftingGrid, Ingredient, Recipe, RecipeRegistry, registry::RecipeRegistryError};
+/// let (stone, cobblestone, gravel) = (1, 2, 3);
+/// let mut recipes = RecipeRegistry::new();
+/// let crush = recipes.register(Recipe::shapeless("cube:crush", vec![Ingredient::item(stone)], ItemStack::new(cobblestone, 1))).unwrap();
+/// let grind = recipes.register(Recipe::shapeless("cube:grind", vec![Ingredient::item(cobblestone)], ItemStack::new(gravel, 1))).unwrap();
+/// assert_eq!(recipes.find(&CraftingGrid::new(3, vec![None, Some(cobblestone)])), Some(grind));
+/// assert_eq!(recipes.find(&CraftingGrid::new(3, vec![Some(gravel)])), None);
+///
+/// // Replacing a recipe keeps its id.
+/// assert_eq!(recipes.replace(Recipe::shapeless("cube:crush", vec![Ingredient::item(stone)], ItemStack::new(gravel, 2))), Some(crush));
+/// assert_eq!(recipes.get(crush).unwrap().result, ItemStack::new(gravel, 2));
+/// assert_eq!(recipes.register(Recipe::shapeless("crush", vec![], ItemStack::new(gravel, 1))), Err(RecipeRegistryError::InvalidName("crush".to_string())));
+/// ```
+#[derive(Default)]
+pub struct RecipeRegistry {
+    recipes: Vec<Recipe>,
+    ids: HashMap<String, RecipeId>
+}
+
+impl RecipeRegistry {
+    pub fn new() -> RecipeRegistry {
+        return RecipeRegistry::default();
+    }
+
+    pub fn register(&mut self, recipe: Recipe) -> Result<RecipeId, RecipeRegistryError> {
+        if !is_valid_name(&recipe.name) {
+            return Err(RecipeRegistryError::InvalidName(recipe.name));
+        }
+        if self.ids.contains_key(&recipe.name) {
+            return Err(RecipeRegistryError::Duplicate(recipe.name));
+        }
+        if self.recipes.len() > RecipeId::MAX as usize {
+            return Err(RecipeRegistryError::Full);
+        }
+        let id = self.recipes.len() as RecipeId;
+        self.ids.insert(recipe.name.clone(), id);
+        self.recipes.push(recipe);
+        return Ok(id);
+    }
+
+    /// Replace a registered recipe with one of the same name, keeping its id. None if it isn't registered.
+    pub fn replace(&mut self, recipe: Recipe) -> Option<RecipeId> {
+        let id = self.id(&recipe.name)?;
+        self.recipes[id as usize] = recipe;
+        return Some(id);
+    }
+
+    pub fn id(&self, name: &str) -> Option<RecipeId> {
+        return self.ids.get(name).copied();
+    }
+
+    pub fn get(&self, id: RecipeId) -> Option<&Recipe> {
+        return self.recipes.get(id as usize);
+    }
+
+    pub fn recipes(&self) -> &[Recipe] {
+        return &self.recipes;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.recipes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.recipes.is_empty();
+    }
+
+    /// The first recipe, in the order they were registered, that the items in a grid make.
+    pub fn find(&self, grid: &CraftingGrid) -> Option<RecipeId> {
+        return self.recipes.iter().position(|recipe| recipe.matches(grid)).map(|id| id as RecipeId);
+    }
+}
\ No newline at end of file
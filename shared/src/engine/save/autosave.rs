@@ -0,0 +1,161 @@
+use std::time::{Duration, Instant};
+
+use crate::engine::{config::SaveConfig, progress::ProgressTracker, world::World};
+
+use super::manager::{SaveError, SaveManager};
+
+/// Time between autosaves by default.
+pub const DEFAULT_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Dirty chunks an autosave queues per tick by default. At 20 ticks per second this saves over a thousand
+/// chunks a second, while keeping the copies taken on the tick thread small.
+pub const DEFAULT_AUTOSAVE_CHUNKS_PER_TICK: usize = 64;
+
+/// Stage name of autosave progress events.
+pub const AUTOSAVE_STAGE: &str = "autosave";
+
+/// How an autosave went, for the console or a chat message to admins.
+#[derive(Debug)]
+pub struct AutosaveReport {
+    /// Chunks and entity chunks saved.
+    pub chunks: usize,
+    pub elapsed: Duration,
+    /// Chunks that failed to save. They're dirty again, so the next autosave retries them.
+    pub errors: Vec<SaveError>
+}
+
+struct AutosaveRun {
+    started: Instant,
+    /// Dirty when the run started. Chunks dirtied during the run may be saved by it, but aren't waited for.
+    total: usize,
+    queued: usize,
+    reported: usize
+}
+
+/// Saves dirty chunks every few minutes. Rather than saving everything in one tick, which would stall it,
+/// each run queues a few chunks per tick on the SaveManager until everything dirty when it started is written.
+/// Progress of each run is reported through a ProgressTracker, so a UI or console can show it.
+/// ```
+/// # use shared::engine::job::system::JobSystem;
+/// # use shared::engine::save::{autosave::Autosave, manager::SaveManager, region::RegionStorage};
+/// # use shared::engine::world::{chunk::Chunk, World};
+/// # use shared::engine::math::coords::ChunkPos;
+/// # use std::{sync::Arc, time::{Duration, Instant}};
+/// let directory = std::env::temp_dir().join(format!("autosave_doctest_{}", std::process::id()));
+/// let jobs = Arc::new(JobSystem::new(2));
+/// let mut saves = SaveManager::new(jobs.clone(), jobs, Arc::new(RegionStorage::new(&directory).unwrap()));
+/// let world = World::new();
+/// for x in 0..10 {
+///     world.insert_chunk(Chunk::new(ChunkPos::new(x, 0, 0)));
+///     saves.mark_chunk(ChunkPos::new(x, 0, 0));
+/// }
+/// let start = Instant::now();
+/// let mut autosave = Autosave::new(Duration::from_secs(60)).with_chunks_per_tick(4);
+/// assert!(autosave.update(&mut saves, &world, start).is_none());
+/// assert!(!autosave.is_running());
+///
+/// let mut now = start + Duration::from_secs(60);
+/// let report = loop {
+///     let dirty = saves.dirty_count();
+///     if let Some(report) = autosave.update(&mut saves, &world, now) {
+///         break report;
+///     }
+///     // Spread over ticks rather than all at once.
+///     assert!(dirty - saves.dirty_count() <= 4);
+///     now += Duration::from_millis(50);
+/// };
+/// assert_eq!(report.chunks, 10);
+/// assert!(autosave.progress().is_finished());
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct Autosave {
+    interval: Duration,
+    chunks_per_tick: usize,
+    last: Option<Instant>,
+    run: Option<AutosaveRun>,
+    progress: ProgressTracker
+}
+
+impl Autosave {
+    pub fn new(interval: Duration) -> Autosave {
+        return Autosave { interval, chunks_per_tick: DEFAULT_AUTOSAVE_CHUNKS_PER_TICK, last: None, run: None, progress: ProgressTracker::new(AUTOSAVE_STAGE, 0) };
+    }
+
+    /// Autosave as configured in the engine config's [save] section.
+    pub fn from_config(config: &SaveConfig) -> Autosave {
+        return Autosave::new(config.autosave_interval()).with_chunks_per_tick(config.autosave_chunks_per_tick);
+    }
+
+    /// Most dirty chunks queued each tick of a run.
+    pub fn with_chunks_per_tick(mut self, chunks_per_tick: usize) -> Autosave {
+        self.chunks_per_tick = chunks_per_tick.max(1);
+        return self;
+    }
+
+    pub fn interval(&self) -> Duration {
+        return self.interval;
+    }
+
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    pub fn is_running(&self) -> bool {
+        return self.run.is_some();
+    }
+
+    /// Progress of the current run, or the last one. Register callbacks on it to show progress as it's made.
+    pub fn progress(&self) -> &ProgressTracker {
+        return &self.progress;
+    }
+
+    /// Start a run now, such as for a save command, unless one is running.
+    pub fn start(&mut self, saves: &SaveManager, now: Instant) {
+        if self.run.is_some() {
+            return;
+        }
+        let total = saves.dirty_count();
+        self.run = Some(AutosaveRun { started: now, total, queued: 0, reported: 0 });
+        self.progress.set_stage(AUTOSAVE_STAGE, total);
+    }
+
+    /// Called every tick. Starts a run once the interval has passed since the last one ended, and queues the
+    /// next chunks of a running one, returning its report once every chunk it queued is written.
+    /// The first call only starts the clock.
+    pub fn update(&mut self, saves: &mut SaveManager, world: &World, now: Instant) -> Option<AutosaveReport> {
+        let last = *self.last.get_or_insert(now);
+        if self.run.is_none() && now.saturating_duration_since(last) >= self.interval {
+            self.start(saves, now);
+        }
+        let run = self.run.as_mut()?;
+        if run.queued < run.total {
+            run.queued += saves.save_up_to(world, self.chunks_per_tick.min(run.total - run.queued));
+            // Chunks that unloaded since the run started are dropped, so there may be fewer left to queue.
+            if saves.dirty_count() == 0 {
+                run.total = run.queued;
+            }
+        }
+        let pending = saves.pending_count();
+        let written = run.queued.saturating_sub(pending);
+        if written > run.reported {
+            self.progress.advance(written - run.reported);
+            run.reported = written;
+        }
+        if run.queued < run.total || pending > 0 {
+            return None;
+        }
+        let run = self.run.take().unwrap();
+        let progress = self.progress.snapshot();
+        if progress.completed < progress.total {
+            self.progress.advance(progress.total - progress.completed);
+        }
+        self.last = Some(now);
+        return Some(AutosaveReport { chunks: run.queued, elapsed: now.saturating_duration_since(run.started), errors: saves.take_errors() });
+    }
+}
+
+impl Default for Autosave {
+    fn default() -> Autosave {
+        return Autosave::new(DEFAULT_AUTOSAVE_INTERVAL);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,256 @@
+use std::{fs::{self, File}, io::{self, BufReader, BufWriter, Read, Write}, path::{Component, Path, PathBuf}};
+
+use crate::engine::{compression::Compression, job::future::JobFuture, progress::ProgressTracker};
+
+use super::region::RegionStorage;
+
+/// Version of the backup archive encoding, stored after its magic.
+pub const BACKUP_FORMAT_VERSION: u8 = 1;
+
+/// Directory backups are written to by default, beside the save's region directories.
+pub const BACKUP_DIRECTORY: &str = "backups";
+
+/// Extension of backup archives.
+pub const BACKUP_EXTENSION: &str = "cubk";
+
+/// Stage name of backup progress events.
+pub const BACKUP_STAGE: &str = "backup";
+
+const MAGIC: [u8; 4] = *b"CUBK";
+/// Files are compressed in pieces of at most this many bytes, so each stays under the decompression limit.
+const PIECE_SIZE: usize = 16 * 1024 * 1024;
+/// Directory a backup is extracted into before replacing the save's files.
+const RESTORE_DIRECTORY: &str = ".restoring";
+
+const ENTRY_END: u8 = 0;
+const ENTRY_FILE: u8 = 1;
+const ENTRY_DIRECTORY: u8 = 2;
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// Backup names are used as file names, so may only contain lowercase letters, digits, '_' and '-'.
+pub fn is_valid_backup_name(name: &str) -> bool {
+    return !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+}
+
+/// Path of a named backup in a backup directory.
+pub fn backup_path(directory: &Path, name: &str) -> PathBuf {
+    return directory.join(format!("{}.{}", name, BACKUP_EXTENSION));
+}
+
+/// Every backup in a backup directory, newest first. Empty if the directory doesn't exist.
+pub fn list_backups(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error)
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_some_and(|extension| extension == BACKUP_EXTENSION) {
+            backups.push((entry.metadata()?.modified()?, path));
+        }
+    }
+    backups.sort_by(|a, b| b.cmp(a));
+    return Ok(backups.into_iter().map(|(_, path)| path).collect());
+}
+
+/// A backup being written in the background by SaveManager::create_backup().
+pub struct Backup {
+    pub(crate) path: PathBuf,
+    pub(crate) progress: ProgressTracker,
+    pub(crate) result: JobFuture<io::Result<()>>
+}
+
+impl Backup {
+    /// Where the archive is written. It only appears there once it's complete.
+    pub fn path(&self) -> &Path {
+        return &self.path;
+    }
+
+    /// Files archived so far, out of every file in the backup.
+    pub fn progress(&self) -> &ProgressTracker {
+        return &self.progress;
+    }
+
+    pub fn is_done(&self) -> bool {
+        return self.result.is_ready();
+    }
+
+    /// Wait for the archive to be written, returning its path.
+    pub fn wait(self) -> io::Result<PathBuf> {
+        self.result.wait()?;
+        return Ok(self.path);
+    }
+}
+
+/// Writes a backup archive: the magic and format version, then entries until an end entry.
+/// An entry is its kind and its path, '/' separated and length prefixed. Files follow their path with their
+/// data in tagged compressed pieces, each length prefixed, ending with an empty piece.
+pub(crate) struct ArchiveWriter {
+    out: BufWriter<File>
+}
+
+impl ArchiveWriter {
+    pub(crate) fn create(path: &Path) -> io::Result<ArchiveWriter> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(&MAGIC)?;
+        out.write_all(&[BACKUP_FORMAT_VERSION])?;
+        return Ok(ArchiveWriter { out });
+    }
+
+    fn entry(&mut self, kind: u8, path: &str) -> io::Result<()> {
+        self.out.write_all(&[kind])?;
+        self.out.write_all(&(path.len() as u16).to_le_bytes())?;
+        return self.out.write_all(path.as_bytes());
+    }
+
+    /// Add a directory, which restoring creates even if no files are in it.
+    pub(crate) fn directory(&mut self, path: &str) -> io::Result<()> {
+        return self.entry(ENTRY_DIRECTORY, path);
+    }
+
+    pub(crate) fn file(&mut self, path: &str, data: &[u8]) -> io::Result<()> {
+        self.entry(ENTRY_FILE, path)?;
+        for piece in data.chunks(PIECE_SIZE) {
+            let compressed = Compression::SAVE.compress_tagged(piece)?;
+            self.out.write_all(&(compressed.len() as u32).to_le_bytes())?;
+            self.out.write_all(&compressed)?;
+        }
+        return self.out.write_all(&0u32.to_le_bytes());
+    }
+
+    /// Add every region file of a storage's running snapshot under a directory, advancing progress for each.
+    pub(crate) fn regions(&mut self, directory: &str, regions: &RegionStorage, progress: &ProgressTracker) -> io::Result<()> {
+        self.directory(directory)?;
+        while let Some((name, data)) = regions.next_snapshot_file()? {
+            self.file(&format!("{}/{}", directory, name), &data)?;
+            progress.advance(1);
+        }
+        return Ok(());
+    }
+
+    /// End the archive and flush it to disk.
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        self.out.write_all(&[ENTRY_END])?;
+        self.out.flush()?;
+        return self.out.get_ref().sync_all();
+    }
+}
+
+struct ArchiveReader {
+    input: BufReader<File>
+}
+
+impl ArchiveReader {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut bytes = [0u8; N];
+        self.input.read_exact(&mut bytes).map_err(|_| invalid("Backup ended early"))?;
+        return Ok(bytes);
+    }
+
+    /// The next entry's kind and path, checked to stay inside the directory being restored.
+    fn entry(&mut self) -> io::Result<(u8, PathBuf)> {
+        let [kind] = self.bytes::<1>()?;
+        if kind == ENTRY_END {
+            return Ok((kind, PathBuf::new()));
+        }
+        let mut path = vec![0u8; u16::from_le_bytes(self.bytes()?) as usize];
+        self.input.read_exact(&mut path).map_err(|_| invalid("Backup ended early"))?;
+        let path = PathBuf::from(String::from_utf8(path).map_err(|_| invalid("Backup path is not UTF-8"))?);
+        if path.as_os_str().is_empty() || !path.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(invalid("Backup path leaves the save directory"));
+        }
+        return Ok((kind, path));
+    }
+
+    fn file(&mut self) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        loop {
+            let length = u32::from_le_bytes(self.bytes()?) as usize;
+            if length == 0 {
+                return Ok(data);
+            }
+            let mut piece = vec![0u8; length];
+            self.input.read_exact(&mut piece).map_err(|_| invalid("Backup ended early"))?;
+            data.extend_from_slice(&Compression::decompress_tagged(&piece)?);
+        }
+    }
+}
+
+/// Extract a whole archive into a directory, returning the top level files and directories in it.
+/// Restoring uses this, and it lets chunks be read back out of a backup without restoring the rest of it.
+pub fn extract_backup(archive: &Path, directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut reader = ArchiveReader { input: BufReader::new(File::open(archive)?) };
+    if reader.bytes::<4>()? != MAGIC {
+        return Err(invalid("Not a backup"));
+    }
+    if reader.bytes::<1>()?[0] != BACKUP_FORMAT_VERSION {
+        return Err(invalid("Unsupported backup format version"));
+    }
+    let mut top_level = Vec::new();
+    loop {
+        let (kind, path) = reader.entry()?;
+        let target = directory.join(&path);
+        match kind {
+            ENTRY_END => return Ok(top_level),
+            ENTRY_FILE => {
+                let data = reader.file()?;
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, data)?;
+            },
+            ENTRY_DIRECTORY => fs::create_dir_all(&target)?,
+            _ => return Err(invalid("Unknown backup entry"))
+        }
+        let first = PathBuf::from(path.components().next().unwrap().as_os_str());
+        if !top_level.contains(&first) {
+            top_level.push(first);
+        }
+    }
+}
+
+/// Replace a save's files with those in a backup, returning how many top level files and directories were
+/// replaced. The backup is fully extracted beside them first, so a damaged archive leaves the save as it was.
+/// Directories in the backup replace the save's whole directory, so regions created after the backup are
+/// removed, while anything the backup doesn't contain, like the backups themselves, is left alone.
+/// The save mustn't be loaded, as its open region files would write over the restored ones.
+/// ```
+/// # use shared::engine::save::backup::restore_backup;
+/// let directory = std::env::temp_dir().join(format!("restore_backup_doctest_{}", std::process::id()));
+/// # std::fs::create_dir_all(&directory).unwrap();
+/// std::fs::write(directory.join("not_a_backup.cubk"), b"hello").unwrap();
+/// assert!(restore_backup(&directory.join("not_a_backup.cubk"), &directory).is_err());
+/// assert!(restore_backup(&directory.join("missing.cubk"), &directory).is_err());
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub fn restore_backup(archive: &Path, directory: &Path) -> io::Result<usize> {
+    let staging = directory.join(RESTORE_DIRECTORY);
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+    let top_level = match extract_backup(archive, &staging) {
+        Ok(top_level) => top_level,
+        Err(error) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(error);
+        }
+    };
+    for path in top_level.iter() {
+        let target = directory.join(path);
+        if target.is_dir() {
+            fs::remove_dir_all(&target)?;
+        } else if target.exists() {
+            fs::remove_file(&target)?;
+        }
+        fs::rename(staging.join(path), &target)?;
+    }
+    fs::remove_dir_all(&staging)?;
+    return Ok(top_level.len());
+}
\ No newline at end of file
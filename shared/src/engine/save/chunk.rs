@@ -0,0 +1,92 @@
+use std::io::{self, Read, Write};
+
+use crate::engine::{math::coords::ChunkPos, world::{chunk::{Chunk, SECTIONS_PER_CHUNK}, palette::{PalettedSection, SECTION_VOLUME}}};
+
+/// Version of the chunk encoding, stored at the start of every encoded chunk.
+pub const CHUNK_FORMAT_VERSION: u8 = 1;
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// Encode a chunk's blocks as compact little-endian binary, used by both saves and chunk streaming.
+/// Sections are written as their palette and packed entries directly, so encoding is mostly a copy.
+/// The chunk position is not included, as both region files and packets already identify the chunk.
+/// ```
+/// # use shared::engine::save::chunk::{encode_chunk, decode_chunk};
+/// # use shared::engine::world::chunk::Chunk;
+/// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+/// let pos = ChunkPos::new(1, 2, 3);
+/// let mut chunk = Chunk::new(pos);
+/// chunk.set_block(LocalPos::new(1, 2, 3), 9);
+/// let bytes = encode_chunk(&chunk);
+/// assert_eq!(decode_chunk(pos, &bytes).unwrap(), chunk);
+/// ```
+pub fn encode_chunk(chunk: &Chunk) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_chunk(chunk, &mut out).unwrap();
+    return out;
+}
+
+pub fn write_chunk<W: Write>(chunk: &Chunk, out: &mut W) -> io::Result<()> {
+    out.write_all(&[CHUNK_FORMAT_VERSION])?;
+    for section in chunk.sections().iter() {
+        out.write_all(&[section.bits_per_entry()])?;
+        out.write_all(&(section.palette().len() as u16).to_le_bytes())?;
+        for id in section.palette() {
+            out.write_all(&id.to_le_bytes())?;
+        }
+        for word in section.data() {
+            out.write_all(&word.to_le_bytes())?;
+        }
+    }
+    return Ok(());
+}
+
+/// Decode a chunk written by encode_chunk(). Corrupt data is an InvalidData error rather than a panic.
+/// ```
+/// # use shared::engine::save::chunk::decode_chunk;
+/// # use shared::engine::math::coords::ChunkPos;
+/// assert!(decode_chunk(ChunkPos::new(0, 0, 0), &[1, 3, 0]).is_err());
+/// ```
+pub fn decode_chunk(pos: ChunkPos, bytes: &[u8]) -> io::Result<Chunk> {
+    let mut reader = bytes;
+    return read_chunk(pos, &mut reader);
+}
+
+pub fn read_chunk<R: Read>(pos: ChunkPos, input: &mut R) -> io::Result<Chunk> {
+    let mut byte = [0u8; 1];
+    input.read_exact(&mut byte)?;
+    if byte[0] != CHUNK_FORMAT_VERSION {
+        return Err(invalid("unsupported chunk format version"));
+    }
+    let mut sections: Vec<PalettedSection> = Vec::with_capacity(SECTIONS_PER_CHUNK);
+    for _ in 0..SECTIONS_PER_CHUNK {
+        input.read_exact(&mut byte)?;
+        let bits = byte[0];
+        let mut len = [0u8; 2];
+        input.read_exact(&mut len)?;
+        let mut palette = Vec::with_capacity(u16::from_le_bytes(len) as usize);
+        for _ in 0..u16::from_le_bytes(len) {
+            let mut id = [0u8; 2];
+            input.read_exact(&mut id)?;
+            palette.push(u16::from_le_bytes(id));
+        }
+        let word_count = match bits {
+            0 | 1 | 2 | 4 | 8 | 16 => SECTION_VOLUME * bits as usize / 64,
+            _ => return Err(invalid("invalid section entry width"))
+        };
+        let mut data = Vec::with_capacity(word_count);
+        for _ in 0..word_count {
+            let mut word = [0u8; 8];
+            input.read_exact(&mut word)?;
+            data.push(u64::from_le_bytes(word));
+        }
+        match PalettedSection::from_raw(palette, bits, data) {
+            Some(section) => sections.push(section),
+            None => return Err(invalid("inconsistent chunk section"))
+        }
+    }
+    let sections: [PalettedSection; SECTIONS_PER_CHUNK] = sections.try_into().unwrap();
+    return Ok(Chunk::from_sections(pos, sections));
+}
@@ -0,0 +1,122 @@
+use std::{io, path::Path, sync::Arc};
+
+use crate::engine::{entity::{serialize::{ComponentTypes, Unsaved}, Entities, EntityId}, math::coords::ChunkPos};
+
+use super::{migration::{Migrations, SaveFormat}, region::RegionStorage};
+
+/// Entities in a chunk that are saved with it.
+fn saved_in_chunk(entities: &Entities, chunk: ChunkPos) -> Vec<EntityId> {
+    let unsaved = entities.storage::<Unsaved>();
+    let unsaved = unsaved.read().unwrap();
+    return entities.in_chunk(chunk).into_iter().filter(|id| !unsaved.contains(*id)).collect();
+}
+
+/// Saves the entities of each chunk into their own region files, beside the chunk's blocks.
+/// Entities are saved and loaded along with their chunk, using the chunk the entity registry has them in.
+pub struct EntityStorage {
+    regions: RegionStorage,
+    types: Arc<ComponentTypes>,
+    migrations: Arc<Migrations>
+}
+
+impl EntityStorage {
+    /// Store entity regions in a directory, creating it if needed.
+    pub fn new(directory: &Path, types: Arc<ComponentTypes>) -> io::Result<EntityStorage> {
+        return Ok(EntityStorage { regions: RegionStorage::new(directory)?, types, migrations: Arc::new(Migrations::new()) });
+    }
+
+    /// Upgrade entities saved in older formats as they're loaded.
+    pub fn with_migrations(mut self, migrations: Arc<Migrations>) -> EntityStorage {
+        self.migrations = migrations;
+        return self;
+    }
+
+    pub fn directory(&self) -> &Path {
+        return self.regions.directory();
+    }
+
+    pub fn types(&self) -> &Arc<ComponentTypes> {
+        return &self.types;
+    }
+
+    /// Write every entity in a chunk but those marked Unsaved, returning how many were saved. Chunks without entities have their data removed.
+    /// ```
+    /// # use shared::engine::save::entities::EntityStorage;
+    /// # use shared::engine::entity::{serialize::{ComponentTypes, Unsaved}, Entities};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// # use std::sync::Arc;
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Name(String);
+    ///
+    /// let directory = std::env::temp_dir().join(format!("entity_storage_doctest_{}", std::process::id()));
+    /// let mut types = ComponentTypes::new();
+    /// types.register::<Name>("cube:name", |name, out| out.extend_from_slice(name.0.as_bytes()),
+    ///     |data| Ok(Name(String::from_utf8_lossy(data).into_owned())));
+    /// let storage = EntityStorage::new(&directory, Arc::new(types)).unwrap();
+    ///
+    /// let entities = Entities::new();
+    /// let chunk = ChunkPos::new(2, 0, -7);
+    /// let villager = entities.spawn();
+    /// entities.insert(villager, Name("Alex".to_string())).unwrap();
+    /// entities.set_chunk(villager, chunk);
+    /// let player = entities.spawn();
+    /// entities.insert(player, Unsaved).unwrap();
+    /// entities.set_chunk(player, chunk);
+    /// assert_eq!(storage.unload_chunk(&entities, chunk).unwrap(), 1);
+    /// assert_eq!(entities.in_chunk(chunk), vec![player]);
+    /// entities.despawn(player);
+    ///
+    /// let loaded = storage.load_chunk(&entities, chunk).unwrap();
+    /// assert_eq!(entities.get::<Name>(loaded[0]), Some(Name("Alex".to_string())));
+    /// assert_eq!(entities.chunk_of(loaded[0]), Some(chunk));
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn save_chunk(&self, entities: &Entities, chunk: ChunkPos) -> io::Result<usize> {
+        let (count, data) = self.encode_chunk(entities, chunk);
+        match data {
+            Some(data) => self.regions.write_data(chunk, &data)?,
+            None => self.regions.remove_data(chunk)?
+        }
+        return Ok(count);
+    }
+
+    /// Encode every entity in a chunk without writing it, returning how many there were,
+    /// and their data unless there were none.
+    pub fn encode_chunk(&self, entities: &Entities, chunk: ChunkPos) -> (usize, Option<Vec<u8>>) {
+        let ids = saved_in_chunk(entities, chunk);
+        if ids.is_empty() {
+            return (0, None);
+        }
+        return (ids.len(), Some(self.types.encode(entities, &ids)));
+    }
+
+    /// Region files the entities are written to.
+    pub fn regions(&self) -> &RegionStorage {
+        return &self.regions;
+    }
+
+    /// Save every entity in a chunk, then despawn those saved, such as when the chunk unloads.
+    pub fn unload_chunk(&self, entities: &Entities, chunk: ChunkPos) -> io::Result<usize> {
+        let count = self.save_chunk(entities, chunk)?;
+        for id in saved_in_chunk(entities, chunk) {
+            entities.despawn(id);
+        }
+        return Ok(count);
+    }
+
+    /// Spawn the saved entities of a chunk, placing them in that chunk.
+    pub fn load_chunk(&self, entities: &Entities, chunk: ChunkPos) -> io::Result<Vec<EntityId>> {
+        let Some(data) = self.regions.read_data(chunk)? else {
+            return Ok(Vec::new());
+        };
+        let ids = self.types.decode(entities, &self.migrations.upgrade(SaveFormat::Entities, data)?)?;
+        for id in ids.iter() {
+            entities.set_chunk(*id, chunk);
+        }
+        return Ok(ids);
+    }
+
+    pub fn sync_all(&self) -> io::Result<()> {
+        return self.regions.sync_all();
+    }
+}
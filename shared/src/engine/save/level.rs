@@ -0,0 +1,272 @@
+use std::{collections::BTreeMap, fmt, fs::{self, File}, io::{self, Write}, path::Path};
+
+use crate::engine::math::coords::BlockPos;
+
+use super::migration::{Migrations, SaveFormat};
+
+/// Version of the level encoding, stored at the start of every level file.
+pub const LEVEL_FORMAT_VERSION: u8 = 1;
+
+/// Name of the level file in a dimension's save directory.
+pub const LEVEL_FILE: &str = "level.dat";
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// Write a file so it's either the old or the new data after a crash, never half of each:
+/// the data goes to a temporary file beside it, which is flushed to disk and then renamed over it.
+pub(crate) fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temporary = path.with_extension("tmp");
+    let mut file = File::create(&temporary)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+    return fs::rename(&temporary, path);
+}
+
+struct Reader<'a> {
+    data: &'a [u8]
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < count {
+            return Err(invalid("Level data ended early"));
+        }
+        let (taken, rest) = self.data.split_at(count);
+        self.data = rest;
+        return Ok(taken);
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        return Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()));
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        return Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let length = self.u32()? as usize;
+        return std::str::from_utf8(self.take(length)?).map(str::to_string).map_err(|_| invalid("Level string is not UTF-8"));
+    }
+}
+
+/// Value of a game rule, as saved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameRuleValue {
+    Bool(bool),
+    Int(i64)
+}
+
+impl fmt::Display for GameRuleValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            GameRuleValue::Bool(value) => write!(f, "{}", value),
+            GameRuleValue::Int(value) => write!(f, "{}", value)
+        };
+    }
+}
+
+/// Types game rules can have.
+pub trait GameRuleType: Copy {
+    fn to_value(self) -> GameRuleValue;
+
+    /// None if the value is of another type.
+    fn from_value(value: GameRuleValue) -> Option<Self>;
+}
+
+impl GameRuleType for bool {
+    fn to_value(self) -> GameRuleValue {
+        return GameRuleValue::Bool(self);
+    }
+
+    fn from_value(value: GameRuleValue) -> Option<bool> {
+        return match value {
+            GameRuleValue::Bool(value) => Some(value),
+            _ => None
+        };
+    }
+}
+
+impl GameRuleType for i64 {
+    fn to_value(self) -> GameRuleValue {
+        return GameRuleValue::Int(self);
+    }
+
+    fn from_value(value: GameRuleValue) -> Option<i64> {
+        return match value {
+            GameRuleValue::Int(value) => Some(value),
+            _ => None
+        };
+    }
+}
+
+/// A game rule's name and the value it has until changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameRule<T: GameRuleType> {
+    pub name: &'static str,
+    pub default: T
+}
+
+impl<T: GameRuleType> GameRule<T> {
+    pub const fn new(name: &'static str, default: T) -> GameRule<T> {
+        return GameRule { name, default };
+    }
+}
+
+/// Whether world time advances each tick.
+pub const DO_DAYLIGHT_CYCLE: GameRule<bool> = GameRule::new("doDaylightCycle", true);
+pub const DO_FIRE_TICK: GameRule<bool> = GameRule::new("doFireTick", true);
+pub const DO_MOB_SPAWNING: GameRule<bool> = GameRule::new("doMobSpawning", true);
+pub const KEEP_INVENTORY: GameRule<bool> = GameRule::new("keepInventory", false);
+/// Random block ticks per chunk section each tick.
+pub const RANDOM_TICK_SPEED: GameRule<i64> = GameRule::new("randomTickSpeed", 3);
+
+/// Game rules changed from their defaults. Rules are kept by name, so ones added by mods that aren't installed
+/// anymore are kept too.
+/// ```
+/// # use shared::engine::save::level::{GameRules, GameRuleValue, KEEP_INVENTORY, RANDOM_TICK_SPEED};
+/// let mut rules = GameRules::new();
+/// assert!(!rules.get(KEEP_INVENTORY));
+/// rules.set(KEEP_INVENTORY, true);
+/// assert!(rules.get(KEEP_INVENTORY));
+/// // Set as typed on the console, which doesn't know the rule's type.
+/// rules.set_value("randomTickSpeed", GameRuleValue::Bool(true));
+/// assert_eq!(rules.get(RANDOM_TICK_SPEED), 3);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GameRules {
+    values: BTreeMap<String, GameRuleValue>
+}
+
+impl GameRules {
+    pub fn new() -> GameRules {
+        return GameRules { values: BTreeMap::new() };
+    }
+
+    /// A rule's value, or its default if unset or set to a value of another type.
+    pub fn get<T: GameRuleType>(&self, rule: GameRule<T>) -> T {
+        return self.values.get(rule.name).and_then(|value| T::from_value(*value)).unwrap_or(rule.default);
+    }
+
+    pub fn set<T: GameRuleType>(&mut self, rule: GameRule<T>, value: T) {
+        self.values.insert(rule.name.to_string(), value.to_value());
+    }
+
+    pub fn value(&self, name: &str) -> Option<GameRuleValue> {
+        return self.values.get(name).copied();
+    }
+
+    /// Set a rule by name, such as from a command.
+    pub fn set_value(&mut self, name: &str, value: GameRuleValue) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    /// Every rule set, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, GameRuleValue)> {
+        return self.values.iter().map(|(name, value)| (name.as_str(), *value));
+    }
+}
+
+/// Metadata of a dimension's world, saved in its level file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LevelData {
+    pub seed: u64,
+    /// Name of the generator, from WorldGenerator::name(), so the world is reopened with the same one.
+    pub generator: String,
+    /// Settings the generator was made with, in whatever form it reads them.
+    pub generator_settings: String,
+    /// Ticks of world time, which the day cycle follows.
+    pub time: u64,
+    /// Where new players appear.
+    pub spawn: BlockPos,
+    pub game_rules: GameRules
+}
+
+impl LevelData {
+    pub fn new(seed: u64, generator: &str) -> LevelData {
+        return LevelData { seed, generator: generator.to_string(), generator_settings: String::new(), time: 0, spawn: BlockPos::ORIGIN, game_rules: GameRules::new() };
+    }
+
+    /// Encode as little-endian binary, starting with the format version. Strings are length prefixed.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![LEVEL_FORMAT_VERSION];
+        let write_str = |out: &mut Vec<u8>, text: &str| {
+            out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            out.extend_from_slice(text.as_bytes());
+        };
+        out.extend_from_slice(&self.seed.to_le_bytes());
+        write_str(&mut out, &self.generator);
+        write_str(&mut out, &self.generator_settings);
+        out.extend_from_slice(&self.time.to_le_bytes());
+        for coord in [self.spawn.x, self.spawn.y, self.spawn.z] {
+            out.extend_from_slice(&coord.to_le_bytes());
+        }
+        out.extend_from_slice(&(self.game_rules.values.len() as u32).to_le_bytes());
+        for (name, value) in self.game_rules.iter() {
+            write_str(&mut out, name);
+            match value {
+                GameRuleValue::Bool(value) => out.extend_from_slice(&[0, value as u8]),
+                GameRuleValue::Int(value) => {
+                    out.push(1);
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+        return out;
+    }
+
+    /// Decode data written by encode(), in the current format.
+    /// ```
+    /// # use shared::engine::save::level::{LevelData, DO_DAYLIGHT_CYCLE, RANDOM_TICK_SPEED};
+    /// # use shared::engine::math::coords::BlockPos;
+    /// let mut level = LevelData::new(42, "noise");
+    /// level.time = 6000;
+    /// level.spawn = BlockPos::new(10, 70, -3);
+    /// level.game_rules.set(DO_DAYLIGHT_CYCLE, false);
+    /// level.game_rules.set(RANDOM_TICK_SPEED, 10);
+    /// let data = level.encode();
+    /// assert_eq!(LevelData::decode(&data).unwrap(), level);
+    /// assert!(LevelData::decode(&data[..data.len() - 1]).is_err());
+    /// ```
+    pub fn decode(data: &[u8]) -> io::Result<LevelData> {
+        let mut reader = Reader { data };
+        if reader.take(1)?[0] != LEVEL_FORMAT_VERSION {
+            return Err(invalid("Unsupported level format version"));
+        }
+        let seed = reader.u64()?;
+        let generator = reader.string()?;
+        let generator_settings = reader.string()?;
+        let time = reader.u64()?;
+        let spawn = BlockPos::new(reader.u32()? as i32, reader.u32()? as i32, reader.u32()? as i32);
+        let mut game_rules = GameRules::new();
+        for _ in 0..reader.u32()? {
+            let name = reader.string()?;
+            let value = match reader.take(1)?[0] {
+                0 => GameRuleValue::Bool(reader.take(1)?[0] != 0),
+                1 => GameRuleValue::Int(reader.u64()? as i64),
+                _ => return Err(invalid("Unknown game rule type"))
+            };
+            game_rules.set_value(&name, value);
+        }
+        return Ok(LevelData { seed, generator, generator_settings, time, spawn, game_rules });
+    }
+
+    /// Write the level file into a dimension's directory. Written atomically, so a crash while saving
+    /// leaves the previous level file rather than a corrupt one.
+    pub fn save(&self, directory: &Path) -> io::Result<()> {
+        return write_atomically(&directory.join(LEVEL_FILE), &self.encode());
+    }
+
+    /// Read a dimension's level file, upgrading it if an older version wrote it. Ok(None) if there isn't one yet.
+    pub fn load(directory: &Path, migrations: &Migrations) -> io::Result<Option<LevelData>> {
+        let data = match fs::read(directory.join(LEVEL_FILE)) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error)
+        };
+        return LevelData::decode(&migrations.upgrade(SaveFormat::Level, data)?).map(Some);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,517 @@
+use std::{collections::{HashMap, HashSet}, fmt, fs, io, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::Duration};
+
+use crate::engine::{job::system::JobSystem, math::coords::ChunkPos, progress::ProgressTracker, world::{chunk::Chunk, World}};
+
+use super::{backup::{backup_path, is_valid_backup_name, ArchiveWriter, Backup, BACKUP_DIRECTORY, BACKUP_STAGE}, chunk::encode_chunk, entities::EntityStorage, region::RegionStorage};
+
+/// Dirty chunks and entity chunks queued per save() by default, so an autosave spreads over several ticks.
+pub const DEFAULT_SAVE_BATCH: usize = 64;
+
+/// What of a chunk is saved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SaveKind {
+    Blocks,
+    Entities
+}
+
+/// A chunk that couldn't be saved. It's marked dirty again, to be retried by the next save().
+#[derive(Debug)]
+pub struct SaveError {
+    pub chunk: ChunkPos,
+    pub kind: SaveKind,
+    pub error: io::Error
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            SaveKind::Blocks => "chunk",
+            SaveKind::Entities => "entities of chunk"
+        };
+        return write!(f, "failed to save {} ({}, {}, {}): {}", kind, self.chunk.x, self.chunk.y, self.chunk.z, self.error);
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+type SaveKey = (ChunkPos, SaveKind);
+
+/// A save ready to write, with the order it was queued in.
+type Serialized = (SaveKey, u64, io::Result<Option<Vec<u8>>>);
+
+/// Copy of what to save, taken on the tick thread.
+enum Snapshot {
+    Blocks(Box<Chunk>),
+    /// Already encoded, as entities can't be copied out of the registry. None if the chunk has no entities.
+    Entities(Option<Vec<u8>>)
+}
+
+/// Shared with the save jobs.
+struct SaveState {
+    chunks: Arc<RegionStorage>,
+    entities: Option<Arc<EntityStorage>>,
+    /// Chunks and entity chunks to save, shared so an emergency flush can save them without the manager.
+    dirty: Mutex<HashSet<SaveKey>>,
+    /// Saves queued and not yet written, for each chunk.
+    queued: Mutex<HashMap<SaveKey, usize>>,
+    /// Newest save written for each chunk with saves still queued. Older saves finishing later are dropped,
+    /// so a chunk saved again while its last save is still being written never ends up with the older data.
+    written: Mutex<HashMap<SaveKey, u64>>,
+    failed: Mutex<Vec<SaveError>>
+}
+
+impl SaveState {
+    fn regions(&self, kind: SaveKind) -> &RegionStorage {
+        return match kind {
+            SaveKind::Blocks => &self.chunks,
+            SaveKind::Entities => self.entities.as_ref().unwrap().regions()
+        };
+    }
+
+    /// Encode and compress a snapshot, or None if the saved data should be removed.
+    fn serialize(&self, key: SaveKey, snapshot: Snapshot) -> io::Result<Option<Vec<u8>>> {
+        let data = match snapshot {
+            Snapshot::Blocks(chunk) => Some(encode_chunk(&chunk)),
+            Snapshot::Entities(data) => data
+        };
+        return data.map(|data| self.regions(key.1).compress(&data)).transpose();
+    }
+
+    /// Copy what to save of a chunk, or None if it isn't loaded.
+    fn snapshot(&self, world: &World, key: SaveKey) -> Option<Snapshot> {
+        if !world.is_loaded(key.0) {
+            return None;
+        }
+        return match key.1 {
+            SaveKind::Blocks => world.chunk(key.0).map(|chunk| Snapshot::Blocks(Box::new(chunk.read().unwrap().clone()))),
+            SaveKind::Entities => Some(Snapshot::Entities(self.entities.as_ref().unwrap().encode_chunk(world.entities(), key.0).1))
+        };
+    }
+
+    fn write(&self, key: SaveKey, sequence: u64, data: io::Result<Option<Vec<u8>>>) {
+        let mut written = self.written.lock().unwrap();
+        let result = match data {
+            _ if written.get(&key).is_some_and(|newest| *newest > sequence) => Ok(()),
+            Ok(data) => {
+                written.insert(key, sequence);
+                match data {
+                    Some(data) => self.regions(key.1).write_compressed(key.0, &data),
+                    None => self.regions(key.1).remove_data(key.0)
+                }
+            },
+            Err(error) => Err(error)
+        };
+        if let Err(error) = result {
+            self.failed.lock().unwrap().push(SaveError { chunk: key.0, kind: key.1, error });
+        }
+        let mut queued = self.queued.lock().unwrap();
+        let count = queued.get_mut(&key).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            queued.remove(&key);
+            written.remove(&key);
+        }
+    }
+}
+
+/// Saves dirty chunks and their entities in the background. Each save() copies a batch of them on the tick
+/// thread, serializes and compresses the copies on a compute job, then writes them to their region files on
+/// the IO job system, so saving never waits on the disk. Both may be the same job system.
+/// ```
+/// # use shared::engine::job::system::JobSystem;
+/// # use shared::engine::save::{manager::SaveManager, region::RegionStorage};
+/// # use shared::engine::world::{chunk::Chunk, loader::ChunkStorage, World};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// # use std::sync::Arc;
+/// let directory = std::env::temp_dir().join(format!("save_manager_doctest_{}", std::process::id()));
+/// let jobs = Arc::new(JobSystem::new(2));
+/// let storage = Arc::new(RegionStorage::new(&directory).unwrap());
+/// let mut saves = SaveManager::new(jobs.clone(), jobs, storage.clone());
+///
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// world.set_block(BlockPos::new(1, 2, 3), 7);
+/// saves.mark_chunk(ChunkPos::new(0, 0, 0));
+/// assert_eq!(saves.save(&world), 1);
+/// saves.flush_all(&world).unwrap();
+/// assert_eq!(storage.read(ChunkPos::new(0, 0, 0)).unwrap().unwrap().get_block(BlockPos::new(1, 2, 3).local()), 7);
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct SaveManager {
+    io: Arc<JobSystem>,
+    compute: Arc<JobSystem>,
+    state: Arc<SaveState>,
+    batch_size: usize,
+    backups: PathBuf,
+    next_sequence: u64,
+    errors: Vec<SaveError>
+}
+
+impl SaveManager {
+    pub fn new(io: Arc<JobSystem>, compute: Arc<JobSystem>, chunks: Arc<RegionStorage>) -> SaveManager {
+        let state = SaveState { chunks, entities: None, dirty: Mutex::new(HashSet::new()), queued: Mutex::new(HashMap::new()), written: Mutex::new(HashMap::new()), failed: Mutex::new(Vec::new()) };
+        let backups = SaveManager::root(&state.chunks).join(BACKUP_DIRECTORY);
+        return SaveManager { io, compute, state: Arc::new(state), batch_size: DEFAULT_SAVE_BATCH, backups, next_sequence: 0, errors: Vec::new() };
+    }
+
+    /// Also save entities, into their own storage. Without one, marking entities dirty does nothing.
+    pub fn with_entities(mut self, entities: Arc<EntityStorage>) -> SaveManager {
+        Arc::get_mut(&mut self.state).unwrap().entities = Some(entities);
+        return self;
+    }
+
+    /// Most dirty chunks queued per save().
+    pub fn with_batch_size(mut self, batch_size: usize) -> SaveManager {
+        self.batch_size = batch_size.max(1);
+        return self;
+    }
+
+    /// Write backups into a directory rather than the backups directory beside the region directories.
+    pub fn with_backup_directory(mut self, directory: &Path) -> SaveManager {
+        self.backups = directory.to_path_buf();
+        return self;
+    }
+
+    pub fn backup_directory(&self) -> &Path {
+        return &self.backups;
+    }
+
+    /// Directory holding the region directories, which backups are made of.
+    fn root(chunks: &RegionStorage) -> &Path {
+        return chunks.directory().parent().unwrap_or(chunks.directory());
+    }
+
+    /// Save a chunk's blocks in a later save(), such as after a block in it changed.
+    pub fn mark_chunk(&mut self, chunk: ChunkPos) {
+        self.state.dirty.lock().unwrap().insert((chunk, SaveKind::Blocks));
+    }
+
+    /// Save the entities in a chunk in a later save().
+    pub fn mark_entities(&mut self, chunk: ChunkPos) {
+        if self.state.entities.is_some() {
+            self.state.dirty.lock().unwrap().insert((chunk, SaveKind::Entities));
+        }
+    }
+
+    /// Chunks and entity chunks marked dirty and not yet queued.
+    pub fn dirty_count(&self) -> usize {
+        return self.state.dirty.lock().unwrap().len();
+    }
+
+    /// Saves queued on the job systems and not yet written.
+    pub fn pending_count(&self) -> usize {
+        return self.state.queued.lock().unwrap().values().sum();
+    }
+
+    /// Chunks that failed to save since the last call.
+    pub fn take_errors(&mut self) -> Vec<SaveError> {
+        self.collect_failures();
+        return std::mem::take(&mut self.errors);
+    }
+
+    fn collect_failures(&mut self) {
+        let failed = std::mem::take(&mut *self.state.failed.lock().unwrap());
+        for error in failed {
+            self.state.dirty.lock().unwrap().insert((error.chunk, error.kind));
+            self.errors.push(error);
+        }
+    }
+
+    /// Queue snapshots to be serialized on a compute job, then written on an IO job.
+    fn queue(&mut self, snapshots: Vec<(SaveKey, Snapshot)>) {
+        if snapshots.is_empty() {
+            return;
+        }
+        let mut queued = self.state.queued.lock().unwrap();
+        let mut batch = Vec::with_capacity(snapshots.len());
+        for (key, snapshot) in snapshots {
+            *queued.entry(key).or_default() += 1;
+            self.next_sequence += 1;
+            batch.push((key, self.next_sequence, snapshot));
+        }
+        drop(queued);
+        let state = self.state.clone();
+        let io = self.io.clone();
+        let mut batch = Some(batch);
+        self.compute.run_job(move || {
+            let serialized: Vec<Serialized> = batch.take().unwrap().into_iter()
+                .map(|(key, sequence, snapshot)| (key, sequence, state.serialize(key, snapshot)))
+                .collect();
+            let state = state.clone();
+            let mut serialized = Some(serialized);
+            io.run_job(move || {
+                for (key, sequence, data) in serialized.take().unwrap() {
+                    state.write(key, sequence, data);
+                }
+            });
+        });
+    }
+
+    /// Queue up to the batch size of dirty chunks to be saved, returning how many were queued.
+    /// Called every tick, or every few ticks for autosaves. Dirty chunks no longer loaded are dropped,
+    /// so chunks should be unloaded through unload_chunk().
+    pub fn save(&mut self, world: &World) -> usize {
+        return self.save_up_to(world, self.batch_size);
+    }
+
+    /// Queue up to a number of dirty chunks to be saved, returning how many were queued.
+    pub fn save_up_to(&mut self, world: &World, count: usize) -> usize {
+        self.collect_failures();
+        let keys: Vec<SaveKey> = {
+            let mut dirty = self.state.dirty.lock().unwrap();
+            let keys: Vec<SaveKey> = dirty.iter().take(count).copied().collect();
+            for key in keys.iter() {
+                dirty.remove(key);
+            }
+            keys
+        };
+        let mut snapshots = Vec::with_capacity(keys.len());
+        for key in keys {
+            if let Some(snapshot) = self.state.snapshot(world, key) {
+                snapshots.push((key, snapshot));
+            }
+        }
+        let count = snapshots.len();
+        self.queue(snapshots);
+        return count;
+    }
+
+    /// Queue a chunk's blocks and entities to be saved if dirty, then remove the chunk from the world,
+    /// despawning its entities if they're saved.
+    pub fn unload_chunk(&mut self, world: &World, chunk: ChunkPos) {
+        let mut snapshots = Vec::new();
+        for kind in [SaveKind::Blocks, SaveKind::Entities] {
+            if self.state.dirty.lock().unwrap().remove(&(chunk, kind)) {
+                if let Some(snapshot) = self.state.snapshot(world, (chunk, kind)) {
+                    snapshots.push(((chunk, kind), snapshot));
+                }
+            }
+        }
+        self.queue(snapshots);
+        world.remove_chunk(chunk);
+        if self.state.entities.is_some() {
+            for id in world.entities().in_chunk(chunk) {
+                world.entities().despawn(id);
+            }
+        }
+    }
+
+    /// Save every dirty chunk and wait for every queued save to be written, then flush the region files to disk.
+    /// Used on shutdown. Fails if any chunk couldn't be saved, with every failure in take_errors().
+    pub fn flush_all(&mut self, world: &World) -> io::Result<()> {
+        let dirty = std::mem::take(&mut *self.state.dirty.lock().unwrap());
+        let snapshots: Vec<(SaveKey, Snapshot)> = dirty.into_iter()
+            .filter_map(|key| self.state.snapshot(world, key).map(|snapshot| (key, snapshot)))
+            .collect();
+        self.queue(snapshots);
+        while !self.state.queued.lock().unwrap().is_empty() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        self.state.chunks.sync_all()?;
+        if let Some(entities) = self.state.entities.as_ref() {
+            entities.sync_all()?;
+        }
+        let failed = self.state.failed.lock().unwrap().len();
+        self.collect_failures();
+        if failed > 0 {
+            let first = &self.errors[self.errors.len() - failed];
+            return Err(io::Error::new(first.error.kind(), format!("{} saves failed, the first with {}", failed, first)));
+        }
+        return Ok(());
+    }
+
+    /// A handle saving what this manager would, for a crash handler, which can't borrow the manager.
+    pub fn emergency_flush(&self, world: Arc<World>) -> EmergencyFlush {
+        return EmergencyFlush { state: self.state.clone(), world };
+    }
+
+    /// Write a compressed archive of the save as it is on disk now, in the background while the world keeps
+    /// running. Region files are copied as the backup gets to them, except ones about to be changed, which
+    /// are copied first, so the archive holds every region as it was when the backup started. Small files
+    /// beside the region directories, like the level file, are read right away. Dirty chunks aren't saved
+    /// first, so a save-all should come before a backup for it to have the latest changes.
+    /// Fails if the name isn't valid, a backup of that name exists, or another backup is running.
+    /// ```
+    /// # use shared::engine::job::system::JobSystem;
+    /// # use shared::engine::save::{backup::restore_backup, manager::SaveManager, region::RegionStorage};
+    /// # use shared::engine::world::{chunk::Chunk, loader::ChunkStorage, World};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// # use std::sync::Arc;
+    /// let directory = std::env::temp_dir().join(format!("backup_doctest_{}", std::process::id()));
+    /// let jobs = Arc::new(JobSystem::new(2));
+    /// let storage = Arc::new(RegionStorage::new(&directory.join("region")).unwrap());
+    /// let mut saves = SaveManager::new(jobs.clone(), jobs, storage.clone());
+    /// storage.write_chunk(&Chunk::filled(ChunkPos::new(0, 0, 0), 1)).unwrap();
+    ///
+    /// let backup = saves.create_backup("before").unwrap();
+    /// // Changed while the backup runs, which doesn't change what's backed up.
+    /// storage.write_chunk(&Chunk::filled(ChunkPos::new(0, 0, 0), 2)).unwrap();
+    /// let archive = backup.wait().unwrap();
+    /// assert!(saves.create_backup("before").is_err());
+    ///
+    /// drop(saves);
+    /// drop(storage);
+    /// restore_backup(&archive, &directory).unwrap();
+    /// let storage = RegionStorage::new(&directory.join("region")).unwrap();
+    /// assert_eq!(storage.read(ChunkPos::new(0, 0, 0)).unwrap(), Some(Chunk::filled(ChunkPos::new(0, 0, 0), 1)));
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn create_backup(&mut self, name: &str) -> io::Result<Backup> {
+        if !is_valid_backup_name(name) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid backup name {}", name)));
+        }
+        let path = backup_path(&self.backups, name);
+        if path.exists() {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("backup {} already exists", name)));
+        }
+        let root = SaveManager::root(&self.state.chunks).to_path_buf();
+        let mut kinds = vec![SaveKind::Blocks];
+        if self.state.entities.is_some() {
+            kinds.push(SaveKind::Entities);
+        }
+        let mut directories = Vec::new();
+        for kind in kinds.iter() {
+            let regions = self.state.regions(*kind);
+            let Ok(directory) = regions.directory().strip_prefix(&root) else {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "region directories must share a parent directory to be backed up"));
+            };
+            directories.push((*kind, directory.to_string_lossy().replace('\\', "/")));
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&root)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type()?.is_file() && !file_name.ends_with(".tmp") {
+                files.push((file_name, fs::read(entry.path())?));
+            }
+        }
+        let mut total = files.len();
+        for (index, (kind, _)) in directories.iter().enumerate() {
+            match self.state.regions(*kind).begin_snapshot() {
+                Ok(names) => total += names.len(),
+                Err(error) => {
+                    for (kind, _) in directories[..index].iter() {
+                        self.state.regions(*kind).end_snapshot();
+                    }
+                    return Err(error);
+                }
+            }
+        }
+
+        fs::create_dir_all(&self.backups)?;
+        let progress = ProgressTracker::new(BACKUP_STAGE, total);
+        let state = self.state.clone();
+        let temporary = path.with_extension("tmp");
+        let (destination, tracker) = (path.clone(), progress.clone());
+        let mut work = Some((files, directories));
+        let result = self.io.run_job(move || {
+            let (files, directories) = work.take().unwrap();
+            let written = (|| {
+                let mut archive = ArchiveWriter::create(&temporary)?;
+                for (name, data) in files.iter() {
+                    archive.file(name, data)?;
+                    tracker.advance(1);
+                }
+                for (kind, directory) in directories.iter() {
+                    archive.regions(directory, state.regions(*kind), &tracker)?;
+                }
+                archive.finish()?;
+                return fs::rename(&temporary, &destination);
+            })();
+            for (kind, _) in directories.iter() {
+                state.regions(*kind).end_snapshot();
+            }
+            if written.is_err() {
+                let _ = fs::remove_file(&temporary);
+            }
+            return written;
+        });
+        return Ok(Backup { path, progress, result });
+    }
+}
+
+/// Saves a SaveManager's dirty chunks, and the entities of every loaded chunk, on the calling thread, such as from a
+/// panic hook when the job systems may never run the manager's jobs. Its writes win over saves still queued.
+/// Chunks locked when it runs are skipped, as the panicking thread may hold their locks and never let go.
+/// ```
+/// # use shared::engine::job::system::JobSystem;
+/// # use shared::engine::save::{manager::SaveManager, region::RegionStorage};
+/// # use shared::engine::world::{chunk::Chunk, loader::ChunkStorage, World};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// # use std::sync::Arc;
+/// let directory = std::env::temp_dir().join(format!("emergency_flush_doctest_{}", std::process::id()));
+/// let jobs = Arc::new(JobSystem::new(2));
+/// let storage = Arc::new(RegionStorage::new(&directory).unwrap());
+/// let mut saves = SaveManager::new(jobs.clone(), jobs, storage.clone());
+/// let world = Arc::new(World::new());
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// world.set_block(BlockPos::new(1, 2, 3), 7);
+/// saves.mark_chunk(ChunkPos::new(0, 0, 0));
+///
+/// let flush = saves.emergency_flush(world.clone());
+/// world.set_block(BlockPos::new(4, 5, 6), 8);
+/// flush.mark_chunk(ChunkPos::new(0, 0, 0));
+/// assert_eq!(flush.flush().unwrap(), 1);
+/// assert_eq!(saves.dirty_count(), 0);
+/// let saved = storage.read(ChunkPos::new(0, 0, 0)).unwrap().unwrap();
+/// assert_eq!((saved.get_block(BlockPos::new(1, 2, 3).local()), saved.get_block(BlockPos::new(4, 5, 6).local())), (7, 8));
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+pub struct EmergencyFlush {
+    state: Arc<SaveState>,
+    world: Arc<World>
+}
+
+impl EmergencyFlush {
+    /// Save a chunk's blocks in the flush, such as one changed since the manager last heard of changes.
+    pub fn mark_chunk(&self, chunk: ChunkPos) {
+        if let Ok(mut dirty) = self.state.dirty.try_lock() {
+            dirty.insert((chunk, SaveKind::Blocks));
+        }
+    }
+
+    /// Write every dirty chunk and the entities of every loaded chunk, then flush the region files to disk,
+    /// returning how many were written. Fails if any couldn't be, including ones skipped for being locked.
+    pub fn flush(&self) -> io::Result<usize> {
+        let mut keys = match self.state.dirty.try_lock() {
+            Ok(mut dirty) => std::mem::take(&mut *dirty),
+            Err(_) => return Err(io::Error::other("the dirty chunks are locked"))
+        };
+        if self.state.entities.is_some() {
+            keys.extend(self.world.loaded_chunks().into_iter().map(|chunk| (chunk, SaveKind::Entities)));
+        }
+        let failed = self.state.failed.lock().unwrap().len();
+        let (mut written, mut locked) = (0, 0);
+        for key in keys {
+            let snapshot = match key.1 {
+                SaveKind::Blocks => match self.world.chunk(key.0) {
+                    Some(chunk) => match chunk.try_read() {
+                        Ok(chunk) => Snapshot::Blocks(Box::new(chunk.clone())),
+                        Err(_) => {
+                            locked += 1;
+                            continue;
+                        }
+                    },
+                    None => continue
+                },
+                SaveKind::Entities => match self.state.snapshot(&self.world, key) {
+                    Some(snapshot) => snapshot,
+                    None => continue
+                }
+            };
+            *self.state.queued.lock().unwrap().entry(key).or_default() += 1;
+            self.state.write(key, u64::MAX, self.state.serialize(key, snapshot));
+            written += 1;
+        }
+        self.state.chunks.sync_all()?;
+        if let Some(entities) = self.state.entities.as_ref() {
+            entities.sync_all()?;
+        }
+        let failed = self.state.failed.lock().unwrap().len() - failed;
+        if failed > 0 || locked > 0 {
+            return Err(io::Error::other(format!("{} of {} chunks couldn't be saved, {} of them locked", failed + locked, written + locked, locked)));
+        }
+        return Ok(written);
+    }
+}
\ No newline at end of file
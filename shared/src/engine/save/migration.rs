@@ -0,0 +1,108 @@
+use std::{collections::HashMap, io};
+
+use crate::engine::entity::serialize::ENTITY_FORMAT_VERSION;
+
+use super::{chunk::CHUNK_FORMAT_VERSION, level::LEVEL_FORMAT_VERSION, world_info::WORLD_FORMAT_VERSION};
+
+/// Upgrades data from one format version to the next. Given the data after its version byte,
+/// returns it as the next version would have written it, also without the version byte.
+pub type MigrationStep = Box<dyn Fn(&[u8]) -> io::Result<Vec<u8>> + Send + Sync>;
+
+/// Kinds of saved data, each starting with its own format version byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SaveFormat {
+    /// The world's info file.
+    World,
+    /// A dimension's level file.
+    Level,
+    Chunk,
+    Entities
+}
+
+impl SaveFormat {
+    /// Version this build writes.
+    pub fn current_version(self) -> u8 {
+        return match self {
+            SaveFormat::World => WORLD_FORMAT_VERSION,
+            SaveFormat::Level => LEVEL_FORMAT_VERSION,
+            SaveFormat::Chunk => CHUNK_FORMAT_VERSION,
+            SaveFormat::Entities => ENTITY_FORMAT_VERSION
+        };
+    }
+
+    fn name(self) -> &'static str {
+        return match self {
+            SaveFormat::World => "world",
+            SaveFormat::Level => "level",
+            SaveFormat::Chunk => "chunk",
+            SaveFormat::Entities => "entity"
+        };
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message);
+}
+
+/// Steps upgrading each save format one version at a time, run on data as it's loaded, so worlds saved by older
+/// builds keep loading after block ids or chunk layouts change. Bumping a format's version means registering
+/// a step from the old version. Upgraded data is written in the current format the next time it's saved.
+/// ```
+/// # use shared::engine::save::migration::{Migrations, SaveFormat};
+/// let mut migrations = Migrations::new();
+/// // A made up format where version 0 chunks stored one byte ids and the current version stores two.
+/// migrations.register(SaveFormat::Chunk, 0, |data| Ok(data.iter().flat_map(|id| [*id, 0]).collect()));
+/// let current = SaveFormat::Chunk.current_version();
+/// assert_eq!(migrations.upgrade(SaveFormat::Chunk, vec![0, 5, 6]).unwrap(), vec![current, 5, 0, 6, 0]);
+/// assert_eq!(migrations.upgrade(SaveFormat::Chunk, vec![current, 1]).unwrap(), vec![current, 1]);
+/// // Saved by a newer build, or by a version with no step to upgrade it.
+/// assert!(migrations.upgrade(SaveFormat::Chunk, vec![current + 1]).is_err());
+/// assert!(migrations.upgrade(SaveFormat::Entities, vec![0]).is_err());
+/// ```
+#[derive(Default)]
+pub struct Migrations {
+    steps: HashMap<(SaveFormat, u8), MigrationStep>
+}
+
+impl Migrations {
+    pub fn new() -> Migrations {
+        return Migrations { steps: HashMap::new() };
+    }
+
+    /// Register the step upgrading a format from a version to the next. Replaces any step already registered for it.
+    pub fn register<F>(&mut self, format: SaveFormat, from_version: u8, step: F)
+    where F: Fn(&[u8]) -> io::Result<Vec<u8>> + Send + Sync + 'static {
+        debug_assert!(from_version < format.current_version(), "Migrations upgrade from versions before the current one");
+        self.steps.insert((format, from_version), Box::new(step));
+    }
+
+    /// Whether data of a format was saved by an older version.
+    pub fn needs_upgrade(format: SaveFormat, data: &[u8]) -> bool {
+        return data.first().is_some_and(|version| *version < format.current_version());
+    }
+
+    /// Upgrade data to the current version of its format, running every step from its version in order.
+    /// Data already current is returned as is. Fails if the data is newer than this build or a step is missing.
+    pub fn upgrade(&self, format: SaveFormat, data: Vec<u8>) -> io::Result<Vec<u8>> {
+        let Some(mut version) = data.first().copied() else {
+            return Err(invalid(format!("empty {} data", format.name())));
+        };
+        let current = format.current_version();
+        if version == current {
+            return Ok(data);
+        }
+        if version > current {
+            return Err(invalid(format!("{} data is version {}, newer than this build's {}", format.name(), version, current)));
+        }
+        let mut body = data[1..].to_vec();
+        while version < current {
+            let Some(step) = self.steps.get(&(format, version)) else {
+                return Err(invalid(format!("no migration for {} data from version {}", format.name(), version)));
+            };
+            body = step(&body)?;
+            version += 1;
+        }
+        body.insert(0, current);
+        return Ok(body);
+    }
+}
\ No newline at end of file
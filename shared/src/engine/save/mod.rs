@@ -0,0 +1,10 @@
+pub mod chunk;
+pub mod region;
+pub mod entities;
+pub mod manager;
+pub mod migration;
+pub mod world_info;
+pub mod level;
+pub mod autosave;
+pub mod backup;
+pub mod players;
\ No newline at end of file
@@ -0,0 +1,82 @@
+use std::{fs, io, path::{Path, PathBuf}, sync::Arc};
+
+use crate::engine::{entity::{serialize::ComponentTypes, Entities, EntityId}, net::auth::format_uuid};
+
+use super::{level::write_atomically, migration::{Migrations, SaveFormat}};
+
+/// Directory in a save holding each player's data.
+pub const PLAYERS_DIRECTORY: &str = "players";
+
+/// Saves each player's entity in a file of its own, named by their UUID, so it's loaded wherever they log in rather
+/// than with whatever chunk they logged out in. Uses the same encoding as the entities saved with chunks.
+/// ```
+/// # use shared::engine::save::players::PlayerStorage;
+/// # use shared::engine::entity::{serialize::ComponentTypes, Entities};
+/// # use std::sync::Arc;
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Health(u32);
+///
+/// let directory = std::env::temp_dir().join(format!("player_storage_doctest_{}", std::process::id()));
+/// let mut types = ComponentTypes::new();
+/// types.register::<Health>("cube:health", |health, out| out.extend_from_slice(&health.0.to_le_bytes()),
+///     |data| Ok(Health(u32::from_le_bytes(data.try_into().unwrap()))));
+/// let storage = PlayerStorage::new(&directory, Arc::new(types)).unwrap();
+///
+/// let entities = Entities::new();
+/// let uuid = 0x123e4567_e89b_12d3_a456_426614174000;
+/// assert_eq!(storage.load(&entities, uuid).unwrap(), None);
+/// let player = entities.spawn();
+/// entities.insert(player, Health(7)).unwrap();
+/// storage.save(&entities, uuid, player).unwrap();
+/// entities.despawn(player);
+///
+/// let loaded = storage.load(&entities, uuid).unwrap().unwrap();
+/// assert_eq!(entities.get::<Health>(loaded), Some(Health(7)));
+/// # std::fs::remove_dir_all(&directory).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct PlayerStorage {
+    directory: PathBuf,
+    types: Arc<ComponentTypes>,
+    migrations: Arc<Migrations>
+}
+
+impl PlayerStorage {
+    /// Store player files in a directory, creating it if needed.
+    pub fn new(directory: &Path, types: Arc<ComponentTypes>) -> io::Result<PlayerStorage> {
+        fs::create_dir_all(directory)?;
+        return Ok(PlayerStorage { directory: directory.to_path_buf(), types, migrations: Arc::new(Migrations::new()) });
+    }
+
+    /// Upgrade players saved in older formats as they're loaded.
+    pub fn with_migrations(mut self, migrations: Arc<Migrations>) -> PlayerStorage {
+        self.migrations = migrations;
+        return self;
+    }
+
+    pub fn directory(&self) -> &Path {
+        return &self.directory;
+    }
+
+    /// File a player's data is saved in.
+    pub fn path(&self, uuid: u128) -> PathBuf {
+        return self.directory.join(format!("{}.dat", format_uuid(uuid)));
+    }
+
+    /// Write a player's entity, replacing what was saved before. Written atomically, so a crash while saving
+    /// leaves the previous data.
+    pub fn save(&self, entities: &Entities, uuid: u128, player: EntityId) -> io::Result<()> {
+        return write_atomically(&self.path(uuid), &self.types.encode(entities, &[player]));
+    }
+
+    /// Spawn a player's saved entity. Ok(None) for a player who has never played here.
+    pub fn load(&self, entities: &Entities, uuid: u128) -> io::Result<Option<EntityId>> {
+        let data = match fs::read(self.path(uuid)) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error)
+        };
+        let ids = self.types.decode(entities, &self.migrations.upgrade(SaveFormat::Entities, data)?)?;
+        return Ok(ids.first().copied());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,650 @@
+use std::{collections::{hash_map::Entry as HashEntry, HashMap, HashSet}, fs::{self, File, OpenOptions}, io::{self, Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::{SystemTime, UNIX_EPOCH}};
+
+use crate::engine::{block::remap::BlockIdRemap, compression::Compression, math::coords::ChunkPos, world::{chunk::Chunk, loader::ChunkStorage}};
+
+use super::{chunk::{encode_chunk, decode_chunk}, migration::{Migrations, SaveFormat}};
+
+/// Number of chunks along each axis of a region. Chunks are cubes, so regions are too.
+pub const REGION_SIZE: i32 = 8;
+/// Number of chunks stored in one region file.
+pub const REGION_CHUNKS: usize = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as usize;
+/// Region files are allocated in sectors of this many bytes.
+pub const SECTOR_SIZE: u64 = 4096;
+
+const MAGIC: [u8; 4] = *b"CURG";
+const REGION_FORMAT_VERSION: u32 = 2;
+/// Regions written before chunks had checksums. They're still read and written without them.
+const UNCHECKED_FORMAT_VERSION: u32 = 1;
+/// Offset in sectors, length in bytes, timestamp, and checksum of every chunk.
+const ENTRY_SIZE: usize = 20;
+/// Entries of unchecked regions, without the checksum.
+const UNCHECKED_ENTRY_SIZE: usize = 16;
+const HEADER_SIZE: usize = 8 + REGION_CHUNKS * ENTRY_SIZE;
+const HEADER_SECTORS: u32 = HEADER_SIZE.div_ceil(SECTOR_SIZE as usize) as u32;
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// CRC-32 of a chunk's data, so a damaged chunk is found rather than decoded as garbage.
+/// ```
+/// # use shared::engine::save::region::crc32;
+/// assert_eq!(crc32(b"123456789"), 0xCBF43926);
+/// ```
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB88320 & (crc & 1).wrapping_neg());
+        }
+    }
+    return !crc;
+}
+
+/// Position of a region, in units of regions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RegionPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32
+}
+
+impl RegionPos {
+    /// ```
+    /// # use shared::engine::save::region::RegionPos;
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let region = RegionPos::of_chunk(ChunkPos::new(9, -1, 0));
+    /// assert_eq!((region.x, region.y, region.z), (1, -1, 0));
+    /// assert_eq!(region.file_name(), "r.1.-1.0.cur");
+    /// ```
+    pub fn of_chunk(chunk: ChunkPos) -> RegionPos {
+        return RegionPos { x: chunk.x.div_euclid(REGION_SIZE), y: chunk.y.div_euclid(REGION_SIZE), z: chunk.z.div_euclid(REGION_SIZE) };
+    }
+
+    pub fn file_name(&self) -> String {
+        return format!("r.{}.{}.{}.cur", self.x, self.y, self.z);
+    }
+
+    /// Parse a region file name written by file_name(). None for any other file.
+    /// ```
+    /// # use shared::engine::save::region::RegionPos;
+    /// let region = RegionPos::from_file_name("r.1.-1.0.cur").unwrap();
+    /// assert_eq!((region.x, region.y, region.z), (1, -1, 0));
+    /// assert_eq!(RegionPos::from_file_name("r.1.-1.cur"), None);
+    /// assert_eq!(RegionPos::from_file_name("r.1.-1.0.cur.tmp"), None);
+    /// ```
+    pub fn from_file_name(name: &str) -> Option<RegionPos> {
+        let coords: Vec<&str> = name.strip_prefix("r.")?.strip_suffix(".cur")?.split('.').collect();
+        let [x, y, z] = coords.as_slice() else {
+            return None;
+        };
+        return Some(RegionPos { x: x.parse().ok()?, y: y.parse().ok()?, z: z.parse().ok()? });
+    }
+
+    /// Index of a chunk within its region's tables.
+    fn chunk_index(chunk: ChunkPos) -> usize {
+        let (x, y, z) = (chunk.x.rem_euclid(REGION_SIZE), chunk.y.rem_euclid(REGION_SIZE), chunk.z.rem_euclid(REGION_SIZE));
+        return (x + z * REGION_SIZE + y * REGION_SIZE * REGION_SIZE) as usize;
+    }
+
+    fn chunk_at(&self, index: usize) -> ChunkPos {
+        let index = index as i32;
+        return ChunkPos::new(
+            self.x * REGION_SIZE + index % REGION_SIZE,
+            self.y * REGION_SIZE + index / (REGION_SIZE * REGION_SIZE),
+            self.z * REGION_SIZE + (index / REGION_SIZE) % REGION_SIZE
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Entry {
+    /// First sector of the chunk's data, or 0 if the chunk isn't stored.
+    sector: u32,
+    /// Length of the chunk's data in bytes.
+    length: u32,
+    /// Unix time in seconds the chunk was last written.
+    timestamp: u64,
+    /// crc32() of the chunk's data. Unused in unchecked regions.
+    checksum: u32
+}
+
+impl Entry {
+    fn sector_count(&self) -> u32 {
+        return (self.length as u64).div_ceil(SECTOR_SIZE) as u32;
+    }
+}
+
+/// A file holding up to REGION_CHUNKS chunks, so a world isn't thousands of tiny files.
+/// The header is a table of where each chunk's data starts, its length, when it was written, and its checksum.
+/// Chunk data is allocated in whole sectors, and a chunk that grows past its sectors is moved
+/// to the first free run of sectors large enough, reusing space freed by other chunks.
+pub struct RegionFile {
+    pos: RegionPos,
+    file: File,
+    entries: Vec<Entry>,
+    /// Whether each sector of the file is in use.
+    used: Vec<bool>,
+    /// False for regions from before chunks had checksums.
+    checksums: bool,
+    /// Chunks whose entries were forgotten when the file was opened.
+    damaged: Vec<ChunkPos>
+}
+
+impl RegionFile {
+    /// Open a region file, creating it if it doesn't exist.
+    pub fn open(path: &Path, pos: RegionPos) -> io::Result<RegionFile> {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let length = file.metadata()?.len();
+        let mut entries = vec![Entry::default(); REGION_CHUNKS];
+        let mut checksums = true;
+        if length == 0 {
+            let mut header = vec![0u8; HEADER_SECTORS as usize * SECTOR_SIZE as usize];
+            header[0..4].copy_from_slice(&MAGIC);
+            header[4..8].copy_from_slice(&REGION_FORMAT_VERSION.to_le_bytes());
+            file.write_all(&header)?;
+        } else {
+            let mut start = [0u8; 8];
+            file.read_exact(&mut start).map_err(|_| invalid("region header ended early"))?;
+            if start[0..4] != MAGIC {
+                return Err(invalid("not a region file"));
+            }
+            checksums = match u32::from_le_bytes(start[4..8].try_into().unwrap()) {
+                REGION_FORMAT_VERSION => true,
+                UNCHECKED_FORMAT_VERSION => false,
+                _ => return Err(invalid("unsupported region format version"))
+            };
+            let entry_size = if checksums { ENTRY_SIZE } else { UNCHECKED_ENTRY_SIZE };
+            let mut header = vec![0u8; REGION_CHUNKS * entry_size];
+            file.read_exact(&mut header).map_err(|_| invalid("region header ended early"))?;
+            for (index, entry) in entries.iter_mut().enumerate() {
+                let bytes = &header[index * entry_size..(index + 1) * entry_size];
+                entry.sector = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+                entry.length = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+                entry.timestamp = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+                if checksums {
+                    entry.checksum = u32::from_le_bytes(bytes[16..20].try_into().unwrap());
+                }
+            }
+        }
+
+        let sector_count = (file.metadata()?.len().div_ceil(SECTOR_SIZE) as usize).max(HEADER_SECTORS as usize);
+        let mut used = vec![false; sector_count];
+        used[..HEADER_SECTORS as usize].fill(true);
+        let mut damaged = Vec::new();
+        for (index, entry) in entries.iter_mut().enumerate() {
+            if entry.sector == 0 {
+                continue;
+            }
+            let end = (entry.sector as usize).saturating_add(entry.sector_count() as usize);
+            if entry.sector < HEADER_SECTORS || end > sector_count || used[entry.sector as usize..end].iter().any(|u| *u) {
+                // Points outside the file or overlaps another chunk. Forget it rather than refuse the whole region.
+                *entry = Entry::default();
+                damaged.push(pos.chunk_at(index));
+                continue;
+            }
+            used[entry.sector as usize..end].fill(true);
+        }
+        return Ok(RegionFile { pos, file, entries, used, checksums, damaged });
+    }
+
+    /// Chunks that were forgotten when the file was opened, as their entries pointed past the end of the file or
+    /// over another chunk, such as after the file was cut short. They read as not stored.
+    pub fn damaged(&self) -> &[ChunkPos] {
+        return &self.damaged;
+    }
+
+    pub fn pos(&self) -> RegionPos {
+        return self.pos;
+    }
+
+    /// Will panic in debug mode if the chunk is not within this region.
+    fn entry_index(&self, chunk: ChunkPos) -> usize {
+        debug_assert_eq!(RegionPos::of_chunk(chunk), self.pos, "Chunk is not in this region");
+        return RegionPos::chunk_index(chunk);
+    }
+
+    pub fn contains(&self, chunk: ChunkPos) -> bool {
+        return self.entries[self.entry_index(chunk)].sector != 0;
+    }
+
+    /// Unix time in seconds a chunk was last written. None if it isn't stored.
+    pub fn timestamp(&self, chunk: ChunkPos) -> Option<u64> {
+        let entry = self.entries[self.entry_index(chunk)];
+        return if entry.sector == 0 { None } else { Some(entry.timestamp) };
+    }
+
+    /// Every chunk stored in the region.
+    pub fn chunks(&self) -> Vec<ChunkPos> {
+        return (0..REGION_CHUNKS).filter(|index| self.entries[*index].sector != 0).map(|index| self.pos.chunk_at(index)).collect();
+    }
+
+    /// Read a chunk's stored bytes. Ok(None) if it isn't stored, and an InvalidData error if they don't match
+    /// their checksum.
+    pub fn read(&mut self, chunk: ChunkPos) -> io::Result<Option<Vec<u8>>> {
+        let entry = self.entries[self.entry_index(chunk)];
+        if entry.sector == 0 {
+            return Ok(None);
+        }
+        let mut data = vec![0u8; entry.length as usize];
+        self.file.seek(SeekFrom::Start(entry.sector as u64 * SECTOR_SIZE))?;
+        self.file.read_exact(&mut data)?;
+        if self.checksums && crc32(&data) != entry.checksum {
+            return Err(invalid("chunk data doesn't match its checksum"));
+        }
+        return Ok(Some(data));
+    }
+
+    /// Store a chunk's bytes with a timestamp, replacing any previous data.
+    /// ```
+    /// # use shared::engine::save::region::{RegionFile, RegionPos};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let path = std::env::temp_dir().join("region_file_write_doctest.cur");
+    /// # let _ = std::fs::remove_file(&path);
+    /// let chunk = ChunkPos::new(3, 1, 4);
+    /// {
+    ///     let mut region = RegionFile::open(&path, RegionPos::of_chunk(chunk)).unwrap();
+    ///     region.write(chunk, &vec![7u8; 10_000], 1234).unwrap();
+    /// }
+    /// let mut region = RegionFile::open(&path, RegionPos::of_chunk(chunk)).unwrap();
+    /// assert_eq!(region.read(chunk).unwrap().unwrap(), vec![7u8; 10_000]);
+    /// assert_eq!(region.timestamp(chunk), Some(1234));
+    /// assert_eq!(region.read(ChunkPos::new(0, 0, 0)).unwrap(), None);
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn write(&mut self, chunk: ChunkPos, data: &[u8], timestamp: u64) -> io::Result<()> {
+        if data.is_empty() || data.len() > u32::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "chunk data must be between 1 byte and 4GB"));
+        }
+        let index = self.entry_index(chunk);
+        let old = self.entries[index];
+        let needed = (data.len() as u64).div_ceil(SECTOR_SIZE) as u32;
+
+        let sector = if old.sector != 0 && needed <= old.sector_count() {
+            self.used[(old.sector + needed) as usize..(old.sector + old.sector_count()) as usize].fill(false);
+            old.sector
+        } else {
+            if old.sector != 0 {
+                self.used[old.sector as usize..(old.sector + old.sector_count()) as usize].fill(false);
+            }
+            self.allocate(needed)
+        };
+        self.used[sector as usize..(sector + needed) as usize].fill(true);
+
+        self.file.seek(SeekFrom::Start(sector as u64 * SECTOR_SIZE))?;
+        self.file.write_all(data)?;
+        let end = (sector + needed) as u64 * SECTOR_SIZE;
+        if self.file.metadata()?.len() < end {
+            self.file.set_len(end)?;
+        }
+        // The header is only updated after the data is written, so a crash mid-write leaves the old chunk intact
+        // unless it was being overwritten in place.
+        self.entries[index] = Entry { sector, length: data.len() as u32, timestamp, checksum: crc32(data) };
+        self.damaged.retain(|damaged| *damaged != chunk);
+        return self.write_entry(index);
+    }
+
+    /// Remove a chunk, freeing its sectors for reuse.
+    pub fn remove(&mut self, chunk: ChunkPos) -> io::Result<()> {
+        let index = self.entry_index(chunk);
+        let old = self.entries[index];
+        if self.damaged.contains(&chunk) {
+            // The header still points past the end of the file, so it's cleared even though nothing is stored.
+            self.damaged.retain(|damaged| *damaged != chunk);
+            return self.write_entry(index);
+        }
+        if old.sector == 0 {
+            return Ok(());
+        }
+        self.used[old.sector as usize..(old.sector + old.sector_count()) as usize].fill(false);
+        self.entries[index] = Entry::default();
+        return self.write_entry(index);
+    }
+
+    /// Flush written data to disk.
+    pub fn sync(&mut self) -> io::Result<()> {
+        return self.file.sync_data();
+    }
+
+    /// First run of free sectors long enough, growing the file if there isn't one.
+    fn allocate(&mut self, count: u32) -> u32 {
+        let mut run_start = 0;
+        let mut run_length = 0;
+        for (sector, used) in self.used.iter().enumerate() {
+            if *used {
+                run_length = 0;
+                continue;
+            }
+            if run_length == 0 {
+                run_start = sector;
+            }
+            run_length += 1;
+            if run_length == count as usize {
+                return run_start as u32;
+            }
+        }
+        // Extend from the trailing free run, if any.
+        let start = if run_length > 0 { run_start } else { self.used.len() };
+        self.used.resize(start + count as usize, false);
+        return start as u32;
+    }
+
+    fn write_entry(&mut self, index: usize) -> io::Result<()> {
+        let entry = self.entries[index];
+        let entry_size = if self.checksums { ENTRY_SIZE } else { UNCHECKED_ENTRY_SIZE };
+        let mut bytes = [0u8; ENTRY_SIZE];
+        bytes[0..4].copy_from_slice(&entry.sector.to_le_bytes());
+        bytes[4..8].copy_from_slice(&entry.length.to_le_bytes());
+        bytes[8..16].copy_from_slice(&entry.timestamp.to_le_bytes());
+        bytes[16..20].copy_from_slice(&entry.checksum.to_le_bytes());
+        self.file.seek(SeekFrom::Start((8 + index * entry_size) as u64))?;
+        return self.file.write_all(&bytes[..entry_size]);
+    }
+}
+
+/// What RegionStorage::check() found.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RegionCheck {
+    /// Region files checked, including those that couldn't be read.
+    pub regions: usize,
+    /// Chunks that read back intact.
+    pub chunks: usize,
+    /// Chunks that are cut short, don't match their checksum, or can't be decoded, with why.
+    pub damaged: Vec<(ChunkPos, String)>,
+    /// Region files whose header can't be read, with why. Which chunks they held is lost with it.
+    pub unreadable: Vec<(RegionPos, String)>
+}
+
+/// How a region is used, deciding whether its file is created, and preserved for a running backup first.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RegionAccess {
+    Read,
+    /// Change a region that already exists.
+    Modify,
+    /// Change a region, creating its file if it doesn't exist.
+    Create
+}
+
+/// Region files as they were when a backup started. Files are copied by the backup as it gets to them,
+/// or just before they're first changed, whichever comes first.
+#[derive(Default)]
+struct RegionSnapshot {
+    uncopied: HashSet<String>,
+    preserved: HashMap<String, Vec<u8>>
+}
+
+/// A directory of region files, caching open files. Used as the world's ChunkStorage.
+/// Chunks are compressed, tagged with their codec, so changing the codec doesn't break existing regions.
+pub struct RegionStorage {
+    directory: PathBuf,
+    compression: Compression,
+    migrations: Arc<Migrations>,
+    regions: Mutex<HashMap<RegionPos, RegionFile>>,
+    snapshot: Mutex<Option<RegionSnapshot>>
+}
+
+impl RegionStorage {
+    /// Store regions in a directory, creating it if needed. Chunks are compressed with Compression::SAVE.
+    pub fn new(directory: &Path) -> io::Result<RegionStorage> {
+        return RegionStorage::with_compression(directory, Compression::SAVE);
+    }
+
+    /// Store regions in a directory, compressing newly written chunks with a specific codec.
+    pub fn with_compression(directory: &Path, compression: Compression) -> io::Result<RegionStorage> {
+        fs::create_dir_all(directory)?;
+        return Ok(RegionStorage { directory: directory.to_path_buf(), compression, migrations: Arc::new(Migrations::new()), regions: Mutex::new(HashMap::new()), snapshot: Mutex::new(None) });
+    }
+
+    /// Upgrade chunks saved in older formats as they're read.
+    pub fn with_migrations(mut self, migrations: Arc<Migrations>) -> RegionStorage {
+        self.migrations = migrations;
+        return self;
+    }
+
+    pub fn directory(&self) -> &Path {
+        return &self.directory;
+    }
+
+    /// Run a function on an open region, opening it first if needed.
+    /// Unless the access creates it and the region file doesn't exist, returns Ok(None) without creating it.
+    fn with_region<T, F>(&self, pos: RegionPos, access: RegionAccess, func: F) -> io::Result<Option<T>>
+    where F: FnOnce(&mut RegionFile) -> io::Result<T> {
+        let mut regions = self.regions.lock().unwrap();
+        if access != RegionAccess::Read {
+            self.preserve(&pos.file_name())?;
+        }
+        let region = match regions.entry(pos) {
+            HashEntry::Occupied(occupied) => occupied.into_mut(),
+            HashEntry::Vacant(vacant) => {
+                let path = self.directory.join(pos.file_name());
+                if access != RegionAccess::Create && !path.exists() {
+                    return Ok(None);
+                }
+                vacant.insert(RegionFile::open(&path, pos)?)
+            }
+        };
+        return func(region).map(Some);
+    }
+
+    /// Copy a region file for the running backup before it's changed, if the backup hasn't copied it yet.
+    /// Called with the regions locked, so no write can happen in between.
+    fn preserve(&self, file_name: &str) -> io::Result<()> {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let Some(snapshot) = snapshot.as_mut() else {
+            return Ok(());
+        };
+        if snapshot.uncopied.contains(file_name) {
+            let data = fs::read(self.directory.join(file_name))?;
+            snapshot.uncopied.remove(file_name);
+            snapshot.preserved.insert(file_name.to_string(), data);
+        }
+        return Ok(());
+    }
+
+    /// Start a backup of every region file as it is now, returning their file names.
+    /// Fails if a backup is already running.
+    pub(crate) fn begin_snapshot(&self) -> io::Result<Vec<String>> {
+        let _regions = self.regions.lock().unwrap();
+        let mut snapshot = self.snapshot.lock().unwrap();
+        if snapshot.is_some() {
+            return Err(io::Error::other("a backup of these regions is already running"));
+        }
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".cur") {
+                names.push(name);
+            }
+        }
+        *snapshot = Some(RegionSnapshot { uncopied: names.iter().cloned().collect(), preserved: HashMap::new() });
+        return Ok(names);
+    }
+
+    /// Next region file of the running backup, as it was when the backup started. Copies preserved before
+    /// they were changed come first, to free their memory. Ok(None) once every file has been taken.
+    pub(crate) fn next_snapshot_file(&self) -> io::Result<Option<(String, Vec<u8>)>> {
+        let _regions = self.regions.lock().unwrap();
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let Some(snapshot) = snapshot.as_mut() else {
+            return Ok(None);
+        };
+        if let Some(name) = snapshot.preserved.keys().next().cloned() {
+            let data = snapshot.preserved.remove(&name).unwrap();
+            return Ok(Some((name, data)));
+        }
+        let Some(name) = snapshot.uncopied.iter().next().cloned() else {
+            return Ok(None);
+        };
+        snapshot.uncopied.remove(&name);
+        let data = fs::read(self.directory.join(&name))?;
+        return Ok(Some((name, data)));
+    }
+
+    /// Stop the running backup, dropping any copies it didn't take.
+    pub(crate) fn end_snapshot(&self) {
+        *self.snapshot.lock().unwrap() = None;
+    }
+
+    /// Encode, compress, and write a chunk, timestamped with the current time.
+    /// ```
+    /// # use shared::engine::save::region::RegionStorage;
+    /// # use shared::engine::world::{chunk::Chunk, loader::ChunkStorage};
+    /// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+    /// let directory = std::env::temp_dir().join("region_storage_doctest");
+    /// # let _ = std::fs::remove_dir_all(&directory);
+    /// let storage = RegionStorage::new(&directory).unwrap();
+    /// let mut chunk = Chunk::new(ChunkPos::new(-20, 3, 9));
+    /// chunk.set_block(LocalPos::new(0, 1, 2), 5);
+    /// storage.write_chunk(&chunk).unwrap();
+    /// assert_eq!(storage.read(chunk.pos()).unwrap(), Some(chunk));
+    /// assert_eq!(storage.read(ChunkPos::new(100, 0, 0)).unwrap(), None);
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn write_chunk(&self, chunk: &Chunk) -> io::Result<()> {
+        return self.write_data(chunk.pos(), &encode_chunk(chunk));
+    }
+
+    /// Compress and write any data stored per chunk, timestamped with the current time.
+    /// Storages for other per chunk data, such as entities, use their own directory of regions.
+    pub fn write_data(&self, chunk: ChunkPos, data: &[u8]) -> io::Result<()> {
+        return self.write_compressed(chunk, &self.compress(data)?);
+    }
+
+    /// Compress data the way write_data() does, so it can be done on a compute job and written on the IO jobs.
+    pub fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        return self.compression.compress_tagged(data);
+    }
+
+    /// Write data already compressed by compress(), timestamped with the current time.
+    pub fn write_compressed(&self, chunk: ChunkPos, data: &[u8]) -> io::Result<()> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        self.with_region(RegionPos::of_chunk(chunk), RegionAccess::Create, |region| region.write(chunk, data, timestamp))?;
+        return Ok(());
+    }
+
+    /// Read and decompress data written by write_data(). Ok(None) if nothing was written for the chunk.
+    pub fn read_data(&self, chunk: ChunkPos) -> io::Result<Option<Vec<u8>>> {
+        let data = self.with_region(RegionPos::of_chunk(chunk), RegionAccess::Read, |region| region.read(chunk))?.flatten();
+        return match data {
+            Some(data) => Compression::decompress_tagged(&data).map(Some),
+            None => Ok(None)
+        };
+    }
+
+    /// Remove a chunk's data, freeing its space in the region.
+    pub fn remove_data(&self, chunk: ChunkPos) -> io::Result<()> {
+        self.with_region(RegionPos::of_chunk(chunk), RegionAccess::Modify, |region| region.remove(chunk))?;
+        return Ok(());
+    }
+
+    /// Positions of every chunk saved in the directory, whether or not its region is open.
+    pub fn saved_chunks(&self) -> io::Result<Vec<ChunkPos>> {
+        let mut chunks = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let Some(pos) = RegionPos::from_file_name(&entry?.file_name().to_string_lossy()) else {
+                continue;
+            };
+            if let Some(saved) = self.with_region(pos, RegionAccess::Read, |region| Ok(region.chunks()))? {
+                chunks.extend(saved);
+            }
+        }
+        return Ok(chunks);
+    }
+
+    /// Read back every chunk saved in the directory, finding the damaged ones, such as before repairing a save.
+    /// Regions opened for the check stay open, so the storage mustn't be in use by a running server.
+    /// ```
+    /// # use shared::engine::save::region::{RegionPos, RegionStorage, SECTOR_SIZE};
+    /// # use shared::engine::world::chunk::Chunk;
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// # use std::io::{Seek, SeekFrom, Write};
+    /// let directory = std::env::temp_dir().join(format!("region_check_doctest_{}", std::process::id()));
+    /// # let _ = std::fs::remove_dir_all(&directory);
+    /// let storage = RegionStorage::new(&directory).unwrap();
+    /// for x in 0..3 {
+    ///     storage.write_chunk(&Chunk::filled(ChunkPos::new(x, 0, 0), 1)).unwrap();
+    /// }
+    /// storage.sync_all().unwrap();
+    /// drop(storage);
+    /// // Damage the first chunk written, which is just after the header.
+    /// let path = directory.join(RegionPos::of_chunk(ChunkPos::ORIGIN).file_name());
+    /// let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+    /// file.seek(SeekFrom::Start(3 * SECTOR_SIZE + 4)).unwrap();
+    /// file.write_all(&[0xFF, 0xFF]).unwrap();
+    /// drop(file);
+    ///
+    /// let check = RegionStorage::new(&directory).unwrap().check().unwrap();
+    /// assert_eq!((check.regions, check.chunks), (1, 2));
+    /// assert_eq!(check.damaged[0].0, ChunkPos::ORIGIN);
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn check(&self) -> io::Result<RegionCheck> {
+        let mut check = RegionCheck::default();
+        for entry in fs::read_dir(&self.directory)? {
+            let Some(pos) = RegionPos::from_file_name(&entry?.file_name().to_string_lossy()) else {
+                continue;
+            };
+            check.regions += 1;
+            let (chunks, damaged) = match self.with_region(pos, RegionAccess::Read, |region| Ok((region.chunks(), region.damaged().to_vec()))) {
+                Ok(Some(found)) => found,
+                Ok(None) => continue,
+                Err(error) => {
+                    check.unreadable.push((pos, error.to_string()));
+                    continue;
+                }
+            };
+            check.damaged.extend(damaged.into_iter().map(|chunk| (chunk, "its data is past the end of the region file".to_string())));
+            for chunk in chunks {
+                match self.read(chunk) {
+                    Ok(_) => check.chunks += 1,
+                    Err(error) => check.damaged.push((chunk, error.to_string()))
+                }
+            }
+        }
+        return Ok(check);
+    }
+
+    /// Move a region file that can't be read out of the way, so the region starts again without it.
+    /// Returns where it was moved to.
+    pub fn set_aside(&self, pos: RegionPos) -> io::Result<PathBuf> {
+        let mut regions = self.regions.lock().unwrap();
+        regions.remove(&pos);
+        let path = self.directory.join(pos.file_name());
+        let damaged = self.directory.join(format!("{}.damaged", pos.file_name()));
+        fs::rename(&path, &damaged)?;
+        return Ok(damaged);
+    }
+
+    /// Read every saved chunk, translate its blocks into the current registry, and write it to another storage.
+    /// Returns the number of chunks written.
+    pub fn remap_into(&self, target: &RegionStorage, remap: &BlockIdRemap) -> io::Result<usize> {
+        let chunks = self.saved_chunks()?;
+        for pos in chunks.iter() {
+            let Some(mut chunk) = self.read(*pos)? else {
+                continue;
+            };
+            chunk.remap_blocks(remap);
+            target.write_chunk(&chunk)?;
+        }
+        target.sync_all()?;
+        return Ok(chunks.len());
+    }
+
+    /// Flush every open region to disk.
+    pub fn sync_all(&self) -> io::Result<()> {
+        for region in self.regions.lock().unwrap().values_mut() {
+            region.sync()?;
+        }
+        return Ok(());
+    }
+}
+
+impl ChunkStorage for RegionStorage {
+    fn read(&self, pos: ChunkPos) -> io::Result<Option<Chunk>> {
+        return match self.read_data(pos)? {
+            Some(data) => decode_chunk(pos, &self.migrations.upgrade(SaveFormat::Chunk, data)?).map(Some),
+            None => Ok(None)
+        };
+    }
+}
@@ -0,0 +1,146 @@
+use std::{fs, io, path::Path};
+
+use crate::engine::version::{ModInfo, Version, VersionManifest};
+
+use super::{level::write_atomically, migration::{Migrations, SaveFormat}};
+
+/// Version of the world info encoding, stored at the start of the world info file.
+pub const WORLD_FORMAT_VERSION: u8 = 1;
+
+/// Name of the world info file in a save's directory.
+pub const WORLD_INFO_FILE: &str = "world.dat";
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// Data about a whole save, rather than any chunk of it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorldInfo {
+    /// Name of the block each numeric id in the save's chunks stands for, from BlockRegistry::saved_names(),
+    /// so chunks can be remapped when blocks are added or removed.
+    pub block_names: Vec<String>,
+    /// Versions of the engine, save format and mods the save was last opened with, checked before it's opened again.
+    /// None for saves written before manifests were recorded.
+    pub manifest: Option<VersionManifest>
+}
+
+/// Split the next bytes off the front of the data.
+fn take<'a>(reader: &mut &'a [u8], count: usize) -> io::Result<&'a [u8]> {
+    if reader.len() < count {
+        return Err(invalid("World info ended early"));
+    }
+    let (taken, rest) = reader.split_at(count);
+    *reader = rest;
+    return Ok(taken);
+}
+
+fn decode_name(reader: &mut &[u8], not_utf8: &str) -> io::Result<String> {
+    let length = u16::from_le_bytes(take(reader, 2)?.try_into().unwrap()) as usize;
+    return std::str::from_utf8(take(reader, length)?).map(str::to_string).map_err(|_| invalid(not_utf8));
+}
+
+fn decode_version(reader: &mut &[u8]) -> io::Result<Version> {
+    let mut parts = [0u32; 3];
+    for part in parts.iter_mut() {
+        *part = u32::from_le_bytes(take(reader, 4)?.try_into().unwrap());
+    }
+    return Ok(Version::new(parts[0], parts[1], parts[2]));
+}
+
+fn encode_version(out: &mut Vec<u8>, version: Version) {
+    for part in [version.major, version.minor, version.patch] {
+        out.extend_from_slice(&part.to_le_bytes());
+    }
+}
+
+impl WorldInfo {
+    pub fn new(block_names: Vec<String>) -> WorldInfo {
+        return WorldInfo { block_names, manifest: None };
+    }
+
+    pub fn with_manifest(mut self, manifest: VersionManifest) -> WorldInfo {
+        self.manifest = Some(manifest);
+        return self;
+    }
+
+    /// Encode as little-endian binary: the format version, then the block name count and each name, length prefixed.
+    /// The manifest follows if there is one: the engine version, protocol and save format, then each mod's
+    /// id, length prefixed, and version. Older saves simply end after the block names.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![WORLD_FORMAT_VERSION];
+        out.extend_from_slice(&(self.block_names.len() as u32).to_le_bytes());
+        for name in self.block_names.iter() {
+            out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            out.extend_from_slice(name.as_bytes());
+        }
+        if let Some(manifest) = &self.manifest {
+            encode_version(&mut out, manifest.engine);
+            out.extend_from_slice(&manifest.protocol.to_le_bytes());
+            out.extend_from_slice(&manifest.save_format.to_le_bytes());
+            out.extend_from_slice(&(manifest.mods.len() as u16).to_le_bytes());
+            for info in manifest.mods.iter() {
+                out.extend_from_slice(&(info.id.len() as u16).to_le_bytes());
+                out.extend_from_slice(info.id.as_bytes());
+                encode_version(&mut out, info.version);
+            }
+        }
+        return out;
+    }
+
+    /// Decode data written by encode(), in the current format.
+    pub fn decode(data: &[u8]) -> io::Result<WorldInfo> {
+        let mut reader = data;
+        if take(&mut reader, 1)?[0] != WORLD_FORMAT_VERSION {
+            return Err(invalid("Unsupported world format version"));
+        }
+        let count = u32::from_le_bytes(take(&mut reader, 4)?.try_into().unwrap());
+        let mut block_names = Vec::new();
+        for _ in 0..count {
+            block_names.push(decode_name(&mut reader, "Block name is not UTF-8")?);
+        }
+        let mut manifest = None;
+        if !reader.is_empty() {
+            let engine = decode_version(&mut reader)?;
+            let protocol = u32::from_le_bytes(take(&mut reader, 4)?.try_into().unwrap());
+            let save_format = u32::from_le_bytes(take(&mut reader, 4)?.try_into().unwrap());
+            let count = u16::from_le_bytes(take(&mut reader, 2)?.try_into().unwrap());
+            let mut mods = Vec::new();
+            for _ in 0..count {
+                let id = decode_name(&mut reader, "Mod id is not UTF-8")?;
+                mods.push(ModInfo { id, version: decode_version(&mut reader)? });
+            }
+            manifest = Some(VersionManifest { engine, protocol, save_format, mods });
+        }
+        return Ok(WorldInfo { block_names, manifest });
+    }
+
+    /// Write the info file into a save's directory, replacing the old one only once the new one is written.
+    /// ```
+    /// # use shared::engine::save::{migration::Migrations, world_info::WorldInfo};
+    /// # use shared::engine::version::{ModInfo, Version, VersionManifest};
+    /// let directory = std::env::temp_dir().join(format!("world_info_doctest_{}", std::process::id()));
+    /// # std::fs::create_dir_all(&directory).unwrap();
+    /// assert_eq!(WorldInfo::load(&directory, &Migrations::new()).unwrap(), None);
+    /// let info = WorldInfo::new(vec!["cube:air".to_string(), "cube:stone".to_string()]);
+    /// info.save(&directory).unwrap();
+    /// assert_eq!(WorldInfo::load(&directory, &Migrations::new()).unwrap(), Some(info.clone()));
+    /// let info = info.with_manifest(VersionManifest::current(vec![ModInfo { id: "gems".to_string(), version: Version::new(2, 1, 0) }]));
+    /// info.save(&directory).unwrap();
+    /// assert_eq!(WorldInfo::load(&directory, &Migrations::new()).unwrap(), Some(info));
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn save(&self, directory: &Path) -> io::Result<()> {
+        return write_atomically(&directory.join(WORLD_INFO_FILE), &self.encode());
+    }
+
+    /// Read a save's info file, upgrading it if an older version wrote it. Ok(None) for a new save without one.
+    pub fn load(directory: &Path, migrations: &Migrations) -> io::Result<Option<WorldInfo>> {
+        let data = match fs::read(directory.join(WORLD_INFO_FILE)) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error)
+        };
+        return WorldInfo::decode(&migrations.upgrade(SaveFormat::World, data)?).map(Some);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,118 @@
+use wasmtime::{Caller, Extern, Linker, StoreLimits};
+
+use crate::engine::block::registry::BlockRegistry;
+
+/// Module scripts import the host API from.
+pub const HOST_MODULE: &str = "cube";
+/// Longest string a script can pass to the host, in bytes.
+pub const MAX_HOST_STRING: usize = 1024;
+
+/// Engine events a script can subscribe to, by the code passed to subscribe() and on_event().
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScriptEvent {
+    /// on_event(0, x, y, z, old, new) with the block's position and ids.
+    BlockChanged = 0,
+    /// on_event(1, x, y, z, 0, 0) with the chunk's position.
+    ChunkLoaded = 1,
+    /// on_event(2, x, y, z, 0, 0) with the chunk's position.
+    ChunkUnloaded = 2,
+    /// on_event(3, 0, 0, 0, client, 0). Names aren't passed.
+    PlayerJoined = 3,
+    /// on_event(4, 0, 0, 0, client, 0).
+    PlayerLeft = 4
+}
+
+impl ScriptEvent {
+    pub const ALL: [ScriptEvent; 5] = [ScriptEvent::BlockChanged, ScriptEvent::ChunkLoaded, ScriptEvent::ChunkUnloaded, ScriptEvent::PlayerJoined, ScriptEvent::PlayerLeft];
+
+    pub fn from_code(code: i32) -> Option<ScriptEvent> {
+        return ScriptEvent::ALL.iter().copied().find(|event| *event as i32 == code);
+    }
+}
+
+/// What the host functions of one script can reach.
+pub(crate) struct HostState {
+    /// Namespace the script's blocks are registered under, its mod id.
+    pub(crate) namespace: String,
+    /// Only lent to the script while init() runs, as blocks can't be registered once worlds load.
+    pub(crate) blocks: Option<BlockRegistry>,
+    pub(crate) subscriptions: Vec<ScriptEvent>,
+    /// Tasks scheduled during the current call, run as jobs once it returns.
+    pub(crate) scheduled: Vec<i32>,
+    pub(crate) log: Vec<String>,
+    pub(crate) limits: StoreLimits
+}
+
+impl HostState {
+    pub(crate) fn new(namespace: &str, limits: StoreLimits) -> HostState {
+        return HostState { namespace: namespace.to_string(), blocks: None, subscriptions: Vec::new(), scheduled: Vec::new(), log: Vec::new(), limits };
+    }
+
+    /// Full name of a block the script registers. Names without a namespace go in the script's own,
+    /// and scripts can't register blocks in any other.
+    fn block_name(&self, name: &str) -> Option<String> {
+        return match name.split_once(':') {
+            None => Some(format!("{}:{}", self.namespace, name)),
+            Some((namespace, _)) if namespace == self.namespace => Some(name.to_string()),
+            Some(_) => None
+        };
+    }
+}
+
+/// Read a string out of the script's exported memory.
+fn read_string(caller: &mut Caller<'_, HostState>, pointer: i32, length: i32) -> Option<String> {
+    let length = usize::try_from(length).ok().filter(|length| *length <= MAX_HOST_STRING)?;
+    let memory = match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => memory,
+        _ => return None
+    };
+    let mut bytes = vec![0; length];
+    memory.read(&*caller, usize::try_from(pointer).ok()?, &mut bytes).ok()?;
+    return String::from_utf8(bytes).ok();
+}
+
+/// Add the host API to a linker. Scripts can import nothing else, so these are all they can do:
+/// - log(pointer, length): write a line to the script's log.
+/// - register_block(pointer, length) -> id: register a block in the script's namespace during init(), or -1.
+/// - block_id(pointer, length) -> id: look up any block during init(), or -1.
+/// - subscribe(event) -> 0: have on_event() called for an event, or -1 for an unknown event.
+/// - schedule(task): run run_task(task) as a job once the current call returns.
+pub(crate) fn link(linker: &mut Linker<HostState>) -> wasmtime::Result<()> {
+    linker.func_wrap(HOST_MODULE, "log", |mut caller: Caller<'_, HostState>, pointer: i32, length: i32| {
+        let line = read_string(&mut caller, pointer, length).unwrap_or_else(|| "<invalid string>".to_string());
+        caller.data_mut().log.push(line);
+    })?;
+    linker.func_wrap(HOST_MODULE, "register_block", |mut caller: Caller<'_, HostState>, pointer: i32, length: i32| -> i32 {
+        let Some(name) = read_string(&mut caller, pointer, length) else {
+            return -1;
+        };
+        let state = caller.data_mut();
+        let Some(name) = state.block_name(&name) else {
+            return -1;
+        };
+        return match state.blocks.as_mut().map(|blocks| blocks.register(&name)) {
+            Some(Ok(id)) => id as i32,
+            _ => -1
+        };
+    })?;
+    linker.func_wrap(HOST_MODULE, "block_id", |mut caller: Caller<'_, HostState>, pointer: i32, length: i32| -> i32 {
+        let Some(name) = read_string(&mut caller, pointer, length) else {
+            return -1;
+        };
+        return caller.data().blocks.as_ref().and_then(|blocks| blocks.id(&name)).map(|id| id as i32).unwrap_or(-1);
+    })?;
+    linker.func_wrap(HOST_MODULE, "subscribe", |mut caller: Caller<'_, HostState>, code: i32| -> i32 {
+        let Some(event) = ScriptEvent::from_code(code) else {
+            return -1;
+        };
+        let subscriptions = &mut caller.data_mut().subscriptions;
+        if !subscriptions.contains(&event) {
+            subscriptions.push(event);
+        }
+        return 0;
+    })?;
+    linker.func_wrap(HOST_MODULE, "schedule", |mut caller: Caller<'_, HostState>, task: i32| {
+        caller.data_mut().scheduled.push(task);
+    })?;
+    return Ok(());
+}
\ No newline at end of file
@@ -0,0 +1,4 @@
+pub mod host;
+pub mod runtime;
+
+pub use runtime::{Script, ScriptEngine};
\ No newline at end of file
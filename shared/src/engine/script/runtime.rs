@@ -0,0 +1,214 @@
+use std::{fmt, mem, sync::{Arc, Mutex}};
+
+use wasmtime::{Config, Engine, Instance, Linker, Module, Store, StoreLimitsBuilder, Trap, WasmParams};
+
+use crate::engine::{
+    block::registry::BlockRegistry,
+    event::{bus::{MessageBus, Subscription}, events::{BlockChanged, ChunkLoaded, ChunkUnloaded, PlayerJoined, PlayerLeft}},
+    job::system::JobSystem
+};
+
+use super::host::{self, HostState, ScriptEvent};
+
+/// Fuel each call into a script gets by default. Roughly one unit is used per instruction run.
+pub const DEFAULT_FUEL_PER_CALL: u64 = 10_000_000;
+/// Bytes of memory a script can grow to by default.
+pub const DEFAULT_MAX_MEMORY: usize = 16 * 1024 * 1024;
+
+/// Why a script couldn't be loaded, or a call into it failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptError {
+    /// The module isn't valid WebAssembly, or imports something the host API doesn't have.
+    Invalid(String),
+    /// The script doesn't export a function it needs to, with the parameters it needs.
+    MissingExport(String),
+    /// The function ran past its fuel, such as by looping forever.
+    OutOfFuel(String),
+    /// The function trapped, such as by panicking or reaching past its memory.
+    Trapped { function: String, message: String }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ScriptError::Invalid(message) => write!(f, "invalid script: {}", message),
+            ScriptError::MissingExport(function) => write!(f, "script doesn't export {}", function),
+            ScriptError::OutOfFuel(function) => write!(f, "script ran out of fuel in {}", function),
+            ScriptError::Trapped { function, message } => write!(f, "script trapped in {}: {}", function, message)
+        };
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Compiles and sandboxes WebAssembly mods. Scripts only see the host API in script::host, get a fixed
+/// amount of fuel for every call so they can't hang the thread calling them, and can only grow their
+/// memory so far.
+/// ```
+/// # use shared::engine::{block::BlockRegistry, event::{bus::MessageBus, events::BlockChanged}, job::system::JobSystem, math::coords::BlockPos, script::ScriptEngine};
+/// # use std::sync::Arc;
+/// let jobs = Arc::new(JobSystem::new(1));
+/// let scripts = ScriptEngine::new(jobs.clone());
+/// let mut blocks = BlockRegistry::new();
+/// let script = scripts.load("ruby", br#"(module
+///     (import "cube" "log" (func $log (param i32 i32)))
+///     (import "cube" "register_block" (func $register_block (param i32 i32) (result i32)))
+///     (import "cube" "subscribe" (func $subscribe (param i32) (result i32)))
+///     (import "cube" "schedule" (func $schedule (param i32)))
+///     (memory (export "memory") 1)
+///     (data (i32.const 0) "ruby_oreplaced")
+///     (func (export "init")
+///         (drop (call $register_block (i32.const 0) (i32.const 8)))
+///         (drop (call $subscribe (i32.const 0))))
+///     (func (export "on_event") (param i32 i32 i32 i32 i32 i32)
+///         (call $schedule (local.get 5)))
+///     (func (export "run_task") (param i32)
+///         (call $log (i32.const 8) (i32.const 6))))"#, &mut blocks).unwrap();
+/// let ruby = blocks.id("ruby:ruby_ore").unwrap();
+///
+/// let bus = MessageBus::new();
+/// script.attach(&bus);
+/// bus.publish(BlockChanged { pos: BlockPos::new(1, 2, 3), old: 0, new: ruby });
+/// bus.dispatch();
+/// jobs.wait();
+/// assert_eq!(script.take_log(), vec!["placed"]);
+/// ```
+pub struct ScriptEngine {
+    engine: Engine,
+    linker: Linker<HostState>,
+    jobs: Arc<JobSystem>,
+    fuel_per_call: u64,
+    max_memory: usize
+}
+
+impl ScriptEngine {
+    /// Run tasks scripts schedule on a job system.
+    pub fn new(jobs: Arc<JobSystem>) -> ScriptEngine {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).expect("Script engine config is valid");
+        let mut linker = Linker::new(&engine);
+        host::link(&mut linker).expect("Host functions are linked once each");
+        return ScriptEngine { engine, linker, jobs, fuel_per_call: DEFAULT_FUEL_PER_CALL, max_memory: DEFAULT_MAX_MEMORY };
+    }
+
+    pub fn with_fuel_per_call(mut self, fuel: u64) -> ScriptEngine {
+        self.fuel_per_call = fuel;
+        return self;
+    }
+
+    pub fn with_max_memory(mut self, bytes: usize) -> ScriptEngine {
+        self.max_memory = bytes;
+        return self;
+    }
+
+    /// Compile a script, given as a binary or text module, and run its init() export if it has one.
+    /// Blocks can only be registered during init(), in a namespace named after the mod id, so the
+    /// registry is only lent to the script while it runs.
+    pub fn load(&self, id: &str, wasm: &[u8], blocks: &mut BlockRegistry) -> Result<Arc<Script>, ScriptError> {
+        let module = Module::new(&self.engine, wasm).map_err(|error| ScriptError::Invalid(format!("{:#}", error)))?;
+        let limits = StoreLimitsBuilder::new().memory_size(self.max_memory).instances(1).build();
+        let mut store = Store::new(&self.engine, HostState::new(id, limits));
+        store.limiter(|state| &mut state.limits);
+        let _ = store.set_fuel(self.fuel_per_call);
+        let instance = self.linker.instantiate(&mut store, &module).map_err(|error| ScriptError::Invalid(format!("{:#}", error)))?;
+        let script = Arc::new(Script {
+            id: id.to_string(),
+            store: Mutex::new(store),
+            instance,
+            jobs: self.jobs.clone(),
+            fuel_per_call: self.fuel_per_call,
+            errors: Mutex::new(Vec::new())
+        });
+        if instance.get_func(&mut *script.store.lock().unwrap(), "init").is_some() {
+            script.store.lock().unwrap().data_mut().blocks = Some(mem::take(blocks));
+            let result = script.invoke("init", ());
+            *blocks = script.store.lock().unwrap().data_mut().blocks.take().unwrap();
+            result?;
+        }
+        return Ok(script);
+    }
+}
+
+/// A loaded script. Calls into it are made one at a time, from whichever thread makes them.
+pub struct Script {
+    id: String,
+    store: Mutex<Store<HostState>>,
+    instance: Instance,
+    jobs: Arc<JobSystem>,
+    fuel_per_call: u64,
+    /// Failures of calls nothing was waiting on, such as event handlers and tasks.
+    errors: Mutex<Vec<ScriptError>>
+}
+
+impl Script {
+    /// The mod id the script was loaded as.
+    pub fn id(&self) -> &str {
+        return &self.id;
+    }
+
+    /// Events the script subscribed to.
+    pub fn subscriptions(&self) -> Vec<ScriptEvent> {
+        return self.store.lock().unwrap().data().subscriptions.clone();
+    }
+
+    /// Lines the script logged since the previous call.
+    pub fn take_log(&self) -> Vec<String> {
+        return mem::take(&mut self.store.lock().unwrap().data_mut().log);
+    }
+
+    /// Event handlers and tasks that failed since the previous call.
+    pub fn take_errors(&self) -> Vec<ScriptError> {
+        return mem::take(&mut *self.errors.lock().unwrap());
+    }
+
+    /// Call an export taking and returning nothing, such as one a command runs.
+    pub fn call(self: &Arc<Self>, function: &str) -> Result<(), ScriptError> {
+        return self.invoke(function, ());
+    }
+
+    /// Call on_event() for every event the script subscribed to published on a bus.
+    /// Returns the subscriptions, to unsubscribe when the script is unloaded.
+    pub fn attach(self: &Arc<Self>, bus: &MessageBus) -> Vec<Subscription> {
+        return self.subscriptions().into_iter().map(|event| {
+            let script = self.clone();
+            return match event {
+                ScriptEvent::BlockChanged => bus.subscribe(move |change: &BlockChanged| {
+                    script.event(event, [change.pos.x, change.pos.y, change.pos.z, change.old as i32, change.new as i32]);
+                }),
+                ScriptEvent::ChunkLoaded => bus.subscribe(move |chunk: &ChunkLoaded| script.event(event, [chunk.pos.x, chunk.pos.y, chunk.pos.z, 0, 0])),
+                ScriptEvent::ChunkUnloaded => bus.subscribe(move |chunk: &ChunkUnloaded| script.event(event, [chunk.pos.x, chunk.pos.y, chunk.pos.z, 0, 0])),
+                ScriptEvent::PlayerJoined => bus.subscribe(move |player: &PlayerJoined| script.event(event, [0, 0, 0, player.client as i32, 0])),
+                ScriptEvent::PlayerLeft => bus.subscribe(move |player: &PlayerLeft| script.event(event, [0, 0, 0, player.client as i32, 0]))
+            };
+        }).collect();
+    }
+
+    fn event(self: &Arc<Self>, event: ScriptEvent, [x, y, z, a, b]: [i32; 5]) {
+        if let Err(error) = self.invoke("on_event", (event as i32, x, y, z, a, b)) {
+            self.errors.lock().unwrap().push(error);
+        }
+    }
+
+    /// Call an export with a full tank of fuel, then start the tasks it scheduled.
+    fn invoke<P: WasmParams>(self: &Arc<Self>, function: &str, params: P) -> Result<(), ScriptError> {
+        let mut store = self.store.lock().unwrap();
+        let export = self.instance.get_typed_func::<P, ()>(&mut *store, function).map_err(|_| ScriptError::MissingExport(function.to_string()))?;
+        let _ = store.set_fuel(self.fuel_per_call);
+        let result = export.call(&mut *store, params);
+        let scheduled = mem::take(&mut store.data_mut().scheduled);
+        drop(store);
+        for task in scheduled {
+            let script = self.clone();
+            self.jobs.run_job(move || {
+                if let Err(error) = script.invoke("run_task", (task,)) {
+                    script.errors.lock().unwrap().push(error);
+                }
+            });
+        }
+        return result.map_err(|error| match error.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => ScriptError::OutOfFuel(function.to_string()),
+            _ => ScriptError::Trapped { function: function.to_string(), message: format!("{:#}", error) }
+        });
+    }
+}
\ No newline at end of file
@@ -0,0 +1,314 @@
+use std::{collections::{BTreeSet, HashMap}, marker::PhantomData, ops::RangeInclusive, sync::Arc};
+
+use crate::engine::{
+    block::BlockId,
+    entity::{kinematics::Transform, storage::Component, EntityId},
+    light::MAX_LIGHT,
+    math::{aabb::Aabb, coords::{BlockPos, ChunkPos, WorldPos, CHUNK_SIZE}, rng::WorldRng, vector::Vec3},
+    physics::aabb_fits,
+    world::World
+};
+
+/// Default most entities of a kind spawned across the whole world.
+pub const DEFAULT_GLOBAL_CAP: usize = 70;
+/// Default most entities of a kind spawned in any one chunk.
+pub const DEFAULT_CHUNK_CAP: usize = 4;
+/// Default radius in chunks around players that entities spawn in.
+pub const DEFAULT_SPAWN_RADIUS: u32 = 4;
+/// Default blocks from every player that entities spawn at least, so they don't appear in plain sight.
+pub const DEFAULT_MIN_SPAWN_DISTANCE: f64 = 24.0;
+/// Default blocks from every player beyond which spawned entities are despawned.
+pub const DEFAULT_DESPAWN_DISTANCE: f64 = 128.0;
+
+/// Whether entities may spawn standing on a block.
+pub type SpawnGroundFn = dyn Fn(BlockId) -> bool + Send + Sync;
+
+/// Light level at a block, the brightest of its block light and its sky light darkened for the time of day.
+/// Sky light comes from the heightmap rather than the stored light, as the server doesn't propagate light:
+/// anything above its column's surface sees the whole sky.
+/// ```
+/// # use shared::engine::spawning::light_level;
+/// # use shared::engine::world::{World, chunk::Chunk, time::{MIDNIGHT, NOON}};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// world.set_time(NOON);
+/// assert_eq!(light_level(&world, BlockPos::new(4, 0, 4)), 15);
+/// // Under a roof, and at midnight.
+/// world.set_block(BlockPos::new(4, 3, 4), 1);
+/// assert_eq!(light_level(&world, BlockPos::new(4, 0, 4)), 0);
+/// world.set_time(MIDNIGHT);
+/// assert_eq!(light_level(&world, BlockPos::new(8, 0, 8)), 4);
+/// ```
+pub fn light_level(world: &World, pos: BlockPos) -> u8 {
+    let stored = world.light(pos.chunk()).map(|light| light.read().unwrap().get(pos.local())).unwrap_or_default();
+    let sky = match world.surface_height(pos.x, pos.z) {
+        Some(surface) if pos.y > surface => MAX_LIGHT,
+        Some(_) => stored.sky,
+        None => MAX_LIGHT
+    };
+    let block = stored.block.into_iter().max().unwrap_or(0);
+    return block.max(sky.saturating_sub(world.world_time().sky_darkening()));
+}
+
+/// Where an entity may spawn: on which blocks, in what light, and the size of the box it needs free of blocks
+/// and other entities.
+/// ```
+/// # use shared::engine::spawning::SpawnRules;
+/// # use shared::engine::world::{World, chunk::Chunk, time::{MIDNIGHT, NOON}};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// # use std::sync::Arc;
+/// let world = World::new();
+/// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 2));
+/// world.insert_chunk(Chunk::new(ChunkPos::ORIGIN));
+/// world.set_time(NOON);
+/// // Standing on block 2, in daylight.
+/// let rules = SpawnRules::new(Arc::new(|id| id == 2), 0.9, 1.3).with_light(9..=15);
+/// assert!(rules.can_spawn(&world, BlockPos::new(4, 0, 4)));
+/// // In the air, on the wrong block, and in a block.
+/// assert!(!rules.can_spawn(&world, BlockPos::new(4, 3, 4)));
+/// world.set_block(BlockPos::new(6, -1, 4), 1);
+/// assert!(!rules.can_spawn(&world, BlockPos::new(6, 0, 4)));
+/// world.set_block(BlockPos::new(8, 1, 4), 2);
+/// assert!(!rules.can_spawn(&world, BlockPos::new(8, 0, 4)));
+/// // Too dark at night.
+/// world.set_time(MIDNIGHT);
+/// assert!(!rules.can_spawn(&world, BlockPos::new(4, 0, 4)));
+/// ```
+#[derive(Clone)]
+pub struct SpawnRules {
+    ground: Arc<SpawnGroundFn>,
+    width: f32,
+    height: f32,
+    light: RangeInclusive<u8>
+}
+
+impl SpawnRules {
+    /// Rules for entities of a size, in blocks, spawning on the blocks ground allows in any light.
+    pub fn new(ground: Arc<SpawnGroundFn>, width: f32, height: f32) -> SpawnRules {
+        return SpawnRules { ground, width, height, light: 0..=MAX_LIGHT };
+    }
+
+    /// Light levels, from light_level(), that entities may spawn in.
+    pub fn with_light(mut self, light: RangeInclusive<u8>) -> SpawnRules {
+        self.light = light;
+        return self;
+    }
+
+    /// Box an entity spawned with its feet on the bottom center of a block would take up.
+    pub fn aabb(&self, feet: BlockPos) -> Aabb {
+        let half_width = self.width / 2.0;
+        let center = feet.center();
+        let (x, y, z) = (center.x as f32, feet.y as f32, center.z as f32);
+        return Aabb::new(Vec3::new(x - half_width, y, z - half_width), Vec3::new(x + half_width, y + self.height, z + half_width));
+    }
+
+    /// Whether an entity could spawn with its feet on the bottom center of a block: standing on an allowed block,
+    /// in allowed light, and with room for its box without touching a block or another entity.
+    pub fn can_spawn(&self, world: &World, feet: BlockPos) -> bool {
+        if !world.get_block(feet.offset(0, -1, 0)).is_some_and(|id| (self.ground)(id)) {
+            return false;
+        }
+        if !self.light.contains(&light_level(world, feet)) {
+            return false;
+        }
+        let aabb = self.aabb(feet);
+        return aabb_fits(world, aabb) && world.entities_in_aabb(aabb).is_empty();
+    }
+}
+
+/// Picks where to spawn entities of a kind, marked by a component, around players. Each call tries a few random
+/// columns of every loaded chunk near a player, spawning on the surface of the column where the rules allow it,
+/// and stops at the caps of how many of the kind may be in a chunk and in the world.
+/// Columns are tried in order with the generator given, so the same world and generator give the same positions.
+/// ```
+/// # use shared::engine::spawning::{SpawnRules, Spawner};
+/// # use shared::engine::world::{World, chunk::Chunk};
+/// # use shared::engine::math::{coords::{ChunkPos, WorldPos}, rng::WorldRng};
+/// # use shared::engine::entity::kinematics::Transform;
+/// # use std::sync::Arc;
+/// #[derive(Debug)]
+/// struct Sheep;
+///
+/// let world = World::new();
+/// for x in -2..=2 {
+///     for z in -2..=2 {
+///         world.insert_chunk(Chunk::filled(ChunkPos::new(x, -1, z), 1));
+///         world.insert_chunk(Chunk::new(ChunkPos::new(x, 0, z)));
+///     }
+/// }
+/// let spawner = Spawner::<Sheep>::new(SpawnRules::new(Arc::new(|id| id == 1), 0.9, 1.3))
+///     .with_caps(10, 2)
+///     .with_attempts(4);
+/// let player = WorldPos::new(0.0, 0.0, 0.0);
+/// let positions = spawner.positions(&world, &[player], &mut WorldRng::new(1));
+/// assert_eq!(positions.len(), 10);
+/// assert!(positions.iter().all(|position| position.y == 0.0 && position.distance(player) >= 24.0));
+/// assert_eq!(positions, spawner.positions(&world, &[player], &mut WorldRng::new(1)));
+///
+/// for position in positions {
+///     let sheep = world.entities().spawn();
+///     world.entities().insert(sheep, Sheep).unwrap();
+///     world.entities().insert(sheep, Transform::new(position)).unwrap();
+///     world.entities().set_chunk(sheep, position.chunk());
+/// }
+/// // The world is full.
+/// assert!(spawner.positions(&world, &[player], &mut WorldRng::new(2)).is_empty());
+/// ```
+pub struct Spawner<T: Component> {
+    rules: SpawnRules,
+    global_cap: usize,
+    chunk_cap: usize,
+    radius: i32,
+    min_distance: f64,
+    /// Columns tried in each chunk per call.
+    attempts: usize,
+    marker: PhantomData<fn() -> T>
+}
+
+impl<T: Component> Spawner<T> {
+    pub fn new(rules: SpawnRules) -> Spawner<T> {
+        return Spawner {
+            rules,
+            global_cap: DEFAULT_GLOBAL_CAP,
+            chunk_cap: DEFAULT_CHUNK_CAP,
+            radius: DEFAULT_SPAWN_RADIUS as i32,
+            min_distance: DEFAULT_MIN_SPAWN_DISTANCE,
+            attempts: 1,
+            marker: PhantomData
+        };
+    }
+
+    /// Most entities of the kind in the world, and in any one chunk.
+    pub fn with_caps(mut self, global: usize, chunk: usize) -> Spawner<T> {
+        self.global_cap = global;
+        self.chunk_cap = chunk;
+        return self;
+    }
+
+    /// Radius in chunks around each player of the chunks spawned in.
+    pub fn with_radius(mut self, chunks: u32) -> Spawner<T> {
+        self.radius = chunks as i32;
+        return self;
+    }
+
+    pub fn with_min_distance(mut self, blocks: f64) -> Spawner<T> {
+        self.min_distance = blocks;
+        return self;
+    }
+
+    pub fn with_attempts(mut self, columns: usize) -> Spawner<T> {
+        self.attempts = columns;
+        return self;
+    }
+
+    pub fn rules(&self) -> &SpawnRules {
+        return &self.rules;
+    }
+
+    /// Entities of the kind in each chunk, from where their transforms are.
+    fn counts(&self, world: &World) -> HashMap<ChunkPos, usize> {
+        let entities = world.entities();
+        let marked = entities.storage::<T>();
+        let marked = marked.read().unwrap();
+        let transforms = entities.storage::<Transform>();
+        let transforms = transforms.read().unwrap();
+        let mut counts = HashMap::new();
+        for id in marked.entities() {
+            if let Some(transform) = transforms.get(*id) {
+                *counts.entry(transform.position.chunk()).or_insert(0) += 1;
+            }
+        }
+        return counts;
+    }
+
+    /// Feet positions to spawn entities of the kind at around players, without going over either cap.
+    /// Nothing is spawned without players.
+    pub fn positions(&self, world: &World, players: &[WorldPos], rng: &mut WorldRng) -> Vec<WorldPos> {
+        let mut counts = self.counts(world);
+        let mut total = world.entities().storage::<T>().read().unwrap().len();
+        let centers: Vec<ChunkPos> = players.iter().map(|player| player.chunk()).collect();
+        let columns: BTreeSet<(i32, i32)> = world.loaded_chunks().into_iter()
+            .filter(|chunk| centers.iter().any(|center| (chunk.x - center.x).abs().max((chunk.z - center.z).abs()) <= self.radius))
+            .map(|chunk| (chunk.x, chunk.z))
+            .collect();
+        let mut chosen: Vec<Aabb> = Vec::new();
+        let mut positions = Vec::new();
+        for (column_x, column_z) in columns {
+            for _ in 0..self.attempts {
+                if total >= self.global_cap {
+                    return positions;
+                }
+                let x = column_x * CHUNK_SIZE + rng.range_i32(0..CHUNK_SIZE);
+                let z = column_z * CHUNK_SIZE + rng.range_i32(0..CHUNK_SIZE);
+                let Some(surface) = world.surface_height(x, z) else {
+                    continue;
+                };
+                let feet = BlockPos::new(x, surface + 1, z);
+                let position = WorldPos::new(x as f64 + 0.5, feet.y as f64, z as f64 + 0.5);
+                if players.iter().any(|player| player.distance(position) < self.min_distance) {
+                    continue;
+                }
+                let count = counts.entry(feet.chunk()).or_insert(0);
+                if *count >= self.chunk_cap {
+                    continue;
+                }
+                let aabb = self.rules.aabb(feet);
+                if chosen.iter().any(|other| other.intersects(&aabb)) || !self.rules.can_spawn(world, feet) {
+                    continue;
+                }
+                *count += 1;
+                total += 1;
+                chosen.push(aabb);
+                positions.push(position);
+            }
+        }
+        return positions;
+    }
+}
+
+/// Despawn entities of a kind, marked by a component, further than a distance from every player, returning each
+/// with where it was. Nothing is despawned without players, as entities then stay with their chunks.
+/// ```
+/// # use shared::engine::spawning::despawn_far;
+/// # use shared::engine::world::World;
+/// # use shared::engine::math::coords::WorldPos;
+/// # use shared::engine::entity::kinematics::Transform;
+/// #[derive(Debug)]
+/// struct Sheep;
+///
+/// let world = World::new();
+/// let spawn = |position: WorldPos| {
+///     let sheep = world.entities().spawn();
+///     world.entities().insert(sheep, Sheep).unwrap();
+///     world.entities().insert(sheep, Transform::new(position)).unwrap();
+///     sheep
+/// };
+/// let near = spawn(WorldPos::new(10.0, 0.0, 0.0));
+/// let far = spawn(WorldPos::new(500.0, 0.0, 0.0));
+/// assert!(despawn_far::<Sheep>(&world, &[], 128.0).is_empty());
+/// assert_eq!(despawn_far::<Sheep>(&world, &[WorldPos::new(0.0, 0.0, 0.0)], 128.0), vec![(far, WorldPos::new(500.0, 0.0, 0.0))]);
+/// assert!(world.entities().is_alive(near));
+/// assert!(!world.entities().is_alive(far));
+/// ```
+pub fn despawn_far<T: Component>(world: &World, players: &[WorldPos], distance: f64) -> Vec<(EntityId, WorldPos)> {
+    if players.is_empty() {
+        return Vec::new();
+    }
+    let far: Vec<(EntityId, WorldPos)> = {
+        let entities = world.entities();
+        let marked = entities.storage::<T>();
+        let marked = marked.read().unwrap();
+        let transforms = entities.storage::<Transform>();
+        let transforms = transforms.read().unwrap();
+        marked.entities().iter()
+            .filter_map(|id| transforms.get(*id).map(|transform| (*id, transform.position)))
+            .filter(|(_, position)| players.iter().all(|player| player.distance(*position) > distance))
+            .collect()
+    };
+    for (id, _) in far.iter() {
+        world.entities().despawn(*id);
+    }
+    return far;
+}
\ No newline at end of file
@@ -0,0 +1,174 @@
+use std::{thread, time::{Duration, Instant}};
+
+use crate::engine::entity::kinematics::{FixedTimestep, TICKS_PER_SECOND};
+
+/// Most ticks run to catch up in one frame by default. Past it, the simulation slows down instead of
+/// spending ever longer frames catching up.
+pub const DEFAULT_MAX_CATCH_UP_TICKS: u32 = 10;
+
+type TickHook = Box<dyn FnMut(u64)>;
+type SkipHook = Box<dyn FnMut(u32)>;
+
+/// Runs the simulation at a fixed number of ticks per second, however often it's updated, so the client
+/// and server step the world identically. Frame times are accumulated and turned into whole ticks, with
+/// the remainder carried to the next frame and given as the interpolation alpha for rendering.
+/// ```
+/// # use shared::engine::tick::GameLoop;
+/// # use std::{cell::RefCell, rc::Rc, time::Duration};
+/// let order = Rc::new(RefCell::new(Vec::new()));
+/// let (pre, post) = (order.clone(), order.clone());
+/// let mut game_loop = GameLoop::new(20)
+///     .with_pre_tick(move |tick| pre.borrow_mut().push(format!("pre {}", tick)))
+///     .with_post_tick(move |tick| post.borrow_mut().push(format!("post {}", tick)));
+///
+/// assert_eq!(game_loop.advance(Duration::from_millis(30), |tick| order.borrow_mut().push(format!("tick {}", tick))), 0);
+/// assert_eq!(game_loop.advance(Duration::from_millis(80), |tick| order.borrow_mut().push(format!("tick {}", tick))), 2);
+/// assert_eq!(*order.borrow(), ["pre 0", "tick 0", "post 0", "pre 1", "tick 1", "post 1"]);
+/// assert_eq!(game_loop.tick_count(), 2);
+/// assert!((game_loop.alpha() - 0.2).abs() < 1e-6);
+///
+/// // A stall past the catch-up limit runs the limit's worth of ticks and skips the rest.
+/// let mut game_loop = GameLoop::new(20).with_max_catch_up(4).with_skip(|skipped| assert_eq!(skipped, 6));
+/// assert_eq!(game_loop.advance(Duration::from_millis(500), |_| {}), 4);
+/// assert_eq!(game_loop.skipped_ticks(), 6);
+/// ```
+pub struct GameLoop {
+    tick_rate: u32,
+    timestep: FixedTimestep,
+    tick: u64,
+    last_frame: Option<Instant>,
+    pre_tick: Vec<TickHook>,
+    post_tick: Vec<TickHook>,
+    skip: Vec<SkipHook>,
+    skipped: u64
+}
+
+impl GameLoop {
+    /// A loop running a number of ticks per second.
+    pub fn new(tick_rate: u32) -> GameLoop {
+        debug_assert_ne!(tick_rate, 0, "Cannot run a game loop at 0 ticks per second");
+        return GameLoop {
+            tick_rate,
+            timestep: FixedTimestep::new(1.0 / tick_rate as f64, DEFAULT_MAX_CATCH_UP_TICKS),
+            tick: 0,
+            last_frame: None,
+            pre_tick: Vec::new(),
+            post_tick: Vec::new(),
+            skip: Vec::new(),
+            skipped: 0
+        };
+    }
+
+    /// Limit how many ticks one frame runs to catch up after a stall.
+    pub fn with_max_catch_up(mut self, ticks: u32) -> GameLoop {
+        self.timestep = FixedTimestep::new(1.0 / self.tick_rate as f64, ticks);
+        return self;
+    }
+
+    /// Start counting ticks from a saved tick, such as a dimension's tick count.
+    pub fn with_tick_count(mut self, tick: u64) -> GameLoop {
+        self.tick = tick;
+        return self;
+    }
+
+    /// Run before every tick with the tick's number, such as to apply received input.
+    pub fn with_pre_tick<F>(mut self, hook: F) -> GameLoop
+    where F: FnMut(u64) + 'static {
+        self.pre_tick.push(Box::new(hook));
+        return self;
+    }
+
+    /// Run after every tick with the tick's number, such as to send snapshots to clients.
+    pub fn with_post_tick<F>(mut self, hook: F) -> GameLoop
+    where F: FnMut(u64) + 'static {
+        self.post_tick.push(Box::new(hook));
+        return self;
+    }
+
+    /// Run when a frame falls so far behind that ticks past the catch-up limit are skipped, with how many were,
+    /// such as to warn that the server is overloaded.
+    pub fn with_skip<F>(mut self, hook: F) -> GameLoop
+    where F: FnMut(u32) + 'static {
+        self.skip.push(Box::new(hook));
+        return self;
+    }
+
+    pub fn tick_rate(&self) -> u32 {
+        return self.tick_rate;
+    }
+
+    /// Time simulated by each tick.
+    pub fn tick_duration(&self) -> Duration {
+        return Duration::from_secs_f64(1.0 / self.tick_rate as f64);
+    }
+
+    /// Ticks run so far, which is also the number of the next tick.
+    pub fn tick_count(&self) -> u64 {
+        return self.tick;
+    }
+
+    /// Ticks skipped so far for being past the catch-up limit. Skipped ticks aren't counted by tick_count.
+    pub fn skipped_ticks(&self) -> u64 {
+        return self.skipped;
+    }
+
+    /// Progress towards the next tick from 0 to 1, for rendering positions between the last two ticks.
+    pub fn alpha(&self) -> f64 {
+        return self.timestep.alpha();
+    }
+
+    /// Time left before the next tick is due.
+    pub fn time_until_next_tick(&self) -> Duration {
+        return self.tick_duration().mul_f64((1.0 - self.alpha()).clamp(0.0, 1.0));
+    }
+
+    /// Add time that passed, running every tick it makes due along with the hooks. Returns the ticks run.
+    pub fn advance<F>(&mut self, elapsed: Duration, mut tick: F) -> u32
+    where F: FnMut(u64) {
+        let ticks = self.timestep.advance(elapsed.as_secs_f64());
+        let dropped = self.timestep.dropped();
+        if dropped > 0 {
+            self.skipped += dropped as u64;
+            for hook in self.skip.iter_mut() {
+                hook(dropped);
+            }
+        }
+        for _ in 0..ticks {
+            for hook in self.pre_tick.iter_mut() {
+                hook(self.tick);
+            }
+            tick(self.tick);
+            for hook in self.post_tick.iter_mut() {
+                hook(self.tick);
+            }
+            self.tick += 1;
+        }
+        return ticks;
+    }
+
+    /// Advance by the time since the last frame, measured with the clock. The first frame runs no ticks.
+    /// Called once per rendered frame by the client.
+    pub fn frame<F>(&mut self, tick: F) -> u32
+    where F: FnMut(u64) {
+        let now = Instant::now();
+        let elapsed = self.last_frame.map(|last| now - last).unwrap_or(Duration::ZERO);
+        self.last_frame = Some(now);
+        return self.advance(elapsed, tick);
+    }
+
+    /// Run ticks on time until told to stop, sleeping between them. Used by the server, which has no
+    /// frames to render. Stopping is checked once per tick.
+    pub fn run_while<R, F>(&mut self, mut running: R, mut tick: F)
+    where R: FnMut() -> bool, F: FnMut(u64) {
+        while running() {
+            self.frame(&mut tick);
+            thread::sleep(self.time_until_next_tick());
+        }
+    }
+}
+
+impl Default for GameLoop {
+    fn default() -> GameLoop {
+        return GameLoop::new(TICKS_PER_SECOND);
+    }
+}
\ No newline at end of file
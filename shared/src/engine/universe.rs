@@ -0,0 +1,478 @@
+use std::{collections::HashMap, fmt, fs, io, path::{Path, PathBuf}, sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc, Mutex, RwLock}};
+
+use crate::engine::{
+    block::{remap::BlockIdRemap, BlockRegistry},
+    entity::serialize::ComponentTypes,
+    event::bus::MessageBus,
+    job::system::JobSystem,
+    math::coords::WorldPos,
+    metrics::standard::engine_metrics,
+    save::{entities::EntityStorage, level::{write_atomically, LevelData}, migration::Migrations, region::RegionStorage, world_info::WorldInfo},
+    version::{Incompatibility, VersionManifest},
+    world::{loader::ChunkLoader, tick::{TickHandlers, TickScheduler, TickStats}, World},
+    worldgen::WorldGenerator
+};
+
+/// Name of the dimension players start in.
+pub const OVERWORLD: &str = "overworld";
+
+/// Directory of a dimension's save its chunks are stored in.
+pub const REGION_DIRECTORY: &str = "region";
+/// Directory beside a dimension's regions that its remapped chunks are written to before replacing them.
+const REMAP_DIRECTORY: &str = "region.remap";
+/// Written once every dimension's chunks are remapped, holding the world info to save once they replace the old ones.
+const REMAP_PENDING_FILE: &str = "world.dat.remap";
+
+/// Id of an entity moving between dimensions, as assigned by the entity system.
+pub type TransferEntityId = u64;
+
+#[derive(Debug)]
+pub enum UniverseError {
+    /// Dimension names are used as directory names, so may only contain lowercase letters, digits, '_' and '-'.
+    InvalidName(String),
+    DuplicateDimension(String),
+    UnknownDimension(String),
+    /// The save needs something this build doesn't have, such as a newer save format or a missing mod.
+    Incompatible(Vec<Incompatibility>),
+    Io(io::Error)
+}
+
+impl fmt::Display for UniverseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UniverseError::InvalidName(name) => write!(f, "invalid dimension name {}", name),
+            UniverseError::DuplicateDimension(name) => write!(f, "dimension {} already exists", name),
+            UniverseError::UnknownDimension(name) => write!(f, "no dimension named {}", name),
+            UniverseError::Incompatible(problems) => {
+                let problems: Vec<String> = problems.iter().map(|problem| problem.to_string()).collect();
+                write!(f, "the save {}", problems.join(", "))
+            },
+            UniverseError::Io(error) => write!(f, "dimension io error: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for UniverseError {}
+
+impl From<io::Error> for UniverseError {
+    fn from(error: io::Error) -> UniverseError {
+        return UniverseError::Io(error);
+    }
+}
+
+/// An entity on its way into a dimension. The entity system removes the entity from its old dimension,
+/// serializes it into data, then recreates it in the new dimension from the transfer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityTransfer {
+    pub entity: TransferEntityId,
+    /// Dimension the entity left.
+    pub from: String,
+    /// Where the entity arrives in its new dimension.
+    pub position: WorldPos,
+    pub data: Vec<u8>
+}
+
+/// A named world within a universe, such as the overworld or a pocket dimension,
+/// with its own generator, tick loop, and save directory.
+pub struct Dimension {
+    name: String,
+    directory: PathBuf,
+    world: Arc<World>,
+    generator: Arc<dyn WorldGenerator>,
+    scheduler: Arc<TickScheduler>,
+    storage: Arc<RegionStorage>,
+    entity_storage: Arc<EntityStorage>,
+    tick: AtomicU64,
+    paused: AtomicBool,
+    arrivals: Mutex<Vec<EntityTransfer>>
+}
+
+impl Dimension {
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    pub fn world(&self) -> &Arc<World> {
+        return &self.world;
+    }
+
+    /// Directory the dimension saves into.
+    pub fn directory(&self) -> &Path {
+        return &self.directory;
+    }
+
+    /// Write the world's metadata to the dimension's level file.
+    pub fn save_level(&self) -> io::Result<()> {
+        return self.world.level().save(&self.directory);
+    }
+
+    pub fn generator(&self) -> &Arc<dyn WorldGenerator> {
+        return &self.generator;
+    }
+
+    pub fn scheduler(&self) -> &Arc<TickScheduler> {
+        return &self.scheduler;
+    }
+
+    /// Region files of the dimension's chunks.
+    pub fn storage(&self) -> &Arc<RegionStorage> {
+        return &self.storage;
+    }
+
+    /// Region files of the dimension's entities, saved with the universe's component types.
+    pub fn entity_storage(&self) -> &Arc<EntityStorage> {
+        return &self.entity_storage;
+    }
+
+    /// Chunk loader reading from the dimension's save, and generating chunks that were never saved with its generator.
+    pub fn loader(&self, io: Arc<JobSystem>, compute: Arc<JobSystem>) -> ChunkLoader {
+        return ChunkLoader::new(io, compute, self.storage.clone(), self.generator.clone());
+    }
+
+    /// Number of game ticks the dimension has run.
+    pub fn tick_count(&self) -> u64 {
+        return self.tick.load(Ordering::Acquire);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        return self.paused.load(Ordering::Acquire);
+    }
+
+    /// Stop ticking the dimension, such as a pocket dimension nobody is in. Its chunks stay loaded.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Release);
+    }
+
+    /// Run one game tick of block updates and block entities, unless paused.
+    pub fn run_tick(&self, jobs: &JobSystem) -> TickStats {
+        if self.is_paused() {
+            return TickStats::default();
+        }
+        crate::profile_scope!("tick");
+        let _tick_timer = engine_metrics().tick_duration.start_timer();
+        let stats = self.scheduler.run_tick(&self.world, jobs);
+        self.world.advance_time(1);
+        self.world.tick_block_entities(jobs, self.tick.load(Ordering::Acquire));
+        self.world.dispatch_events();
+        self.world.entities().update_events();
+        self.tick.fetch_add(1, Ordering::AcqRel);
+        return stats;
+    }
+
+    /// Remove and return the entities that arrived since the last call, for the entity system to spawn.
+    pub fn take_arrivals(&self) -> Vec<EntityTransfer> {
+        return std::mem::take(&mut self.arrivals.lock().unwrap());
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    return !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+}
+
+/// Every dimension of a save. Each dimension saves into its own directory under the universe's.
+pub struct Universe {
+    directory: PathBuf,
+    component_types: Arc<ComponentTypes>,
+    migrations: Arc<Migrations>,
+    dimensions: RwLock<HashMap<String, Arc<Dimension>>>,
+    /// Dimension each transferred entity was last sent to.
+    locations: Mutex<HashMap<TransferEntityId, String>>
+}
+
+impl Universe {
+    pub fn new(directory: &Path) -> Universe {
+        return Universe { directory: directory.to_path_buf(), component_types: Arc::new(ComponentTypes::new()), migrations: Arc::new(Migrations::new()), dimensions: RwLock::new(HashMap::new()), locations: Mutex::new(HashMap::new()) };
+    }
+
+    /// Save entities with these component types, in dimensions created afterwards.
+    pub fn with_component_types(mut self, types: Arc<ComponentTypes>) -> Universe {
+        self.component_types = types;
+        return self;
+    }
+
+    pub fn component_types(&self) -> &Arc<ComponentTypes> {
+        return &self.component_types;
+    }
+
+    /// Upgrade chunks and entities saved in older formats as they load, in dimensions created afterwards.
+    pub fn with_migrations(mut self, migrations: Arc<Migrations>) -> Universe {
+        self.migrations = migrations;
+        return self;
+    }
+
+    pub fn migrations(&self) -> &Arc<Migrations> {
+        return &self.migrations;
+    }
+
+    /// Read the save's world info, upgrading it if needed. Ok(None) for a new save.
+    pub fn load_info(&self) -> io::Result<Option<WorldInfo>> {
+        return WorldInfo::load(&self.directory, &self.migrations);
+    }
+
+    /// Write the save's world info, creating the save's directory if it doesn't exist.
+    pub fn save_info(&self, info: &WorldInfo) -> io::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        return info.save(&self.directory);
+    }
+
+    /// Check that this build, with the mods in its manifest, can open the save it was last opened with,
+    /// before anything in the save is touched. Saves without a recorded manifest are assumed compatible.
+    /// ```
+    /// # use shared::engine::{universe::{Universe, UniverseError}, save::world_info::WorldInfo, version::{ModInfo, Version, VersionManifest}};
+    /// let directory = std::env::temp_dir().join(format!("universe_version_doctest_{}", std::process::id()));
+    /// let universe = Universe::new(&directory);
+    /// let gems = VersionManifest::current(vec![ModInfo { id: "gems".to_string(), version: Version::new(2, 1, 0) }]);
+    /// assert!(universe.check_version(&VersionManifest::current(vec![])).is_ok());
+    /// universe.save_info(&WorldInfo::new(vec![])).unwrap();
+    /// universe.record_version(&gems).unwrap();
+    /// assert!(universe.check_version(&gems).is_ok());
+    /// let error = universe.check_version(&VersionManifest::current(vec![])).unwrap_err();
+    /// assert_eq!(error.to_string(), "the save requires mod gems v2.1.0");
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn check_version(&self, running: &VersionManifest) -> Result<(), UniverseError> {
+        if let Some(saved) = self.load_info()?.and_then(|info| info.manifest) {
+            running.check_world(&saved).map_err(UniverseError::Incompatible)?;
+        }
+        return Ok(());
+    }
+
+    /// Record the manifest the save is opened with in its world info, once it's checked and its blocks remapped.
+    pub fn record_version(&self, running: &VersionManifest) -> io::Result<()> {
+        let info = self.load_info()?.unwrap_or_default();
+        return self.save_info(&info.with_manifest(running.clone()));
+    }
+
+    /// Bring the save's chunks in line with the current block registry before any dimension is created.
+    /// If the blocks the save was written with were registered in a different order, or some were removed,
+    /// every saved chunk of every dimension is remapped and the world info rewritten with the current names.
+    /// Returns the remap if chunks were rewritten, so blocks that no longer exist can be reported.
+    /// A new save just records the current names.
+    ///
+    /// Remapped chunks are written beside the old ones and only swapped in once every dimension is done,
+    /// so a crash part way through either redoes the remap or finishes the swap on the next start.
+    pub fn remap_blocks(&self, blocks: &BlockRegistry) -> io::Result<Option<BlockIdRemap>> {
+        if !self.dimensions.read().unwrap().is_empty() {
+            return Err(io::Error::other("blocks must be remapped before any dimension is created"));
+        }
+        self.finish_remap()?;
+        let names = blocks.saved_names().to_vec();
+        let Some(info) = self.load_info()? else {
+            self.save_info(&WorldInfo::new(names))?;
+            return Ok(None);
+        };
+        if info.block_names == names {
+            return Ok(None);
+        }
+        let remap = blocks.remap_saved(&info.block_names);
+        let updated = WorldInfo { block_names: names, manifest: info.manifest };
+        if remap.is_identity() {
+            // Only new blocks were added after the saved ones, so no saved id changes.
+            self.save_info(&updated)?;
+            return Ok(None);
+        }
+        for directory in self.saved_dimension_directories()? {
+            let staging = directory.join(REMAP_DIRECTORY);
+            if staging.exists() {
+                fs::remove_dir_all(&staging)?;
+            }
+            let storage = RegionStorage::new(&directory.join(REGION_DIRECTORY))?.with_migrations(self.migrations.clone());
+            storage.remap_into(&RegionStorage::new(&staging)?, &remap)?;
+        }
+        write_atomically(&self.directory.join(REMAP_PENDING_FILE), &updated.encode())?;
+        self.finish_remap()?;
+        return Ok(Some(remap));
+    }
+
+    /// Finish a block remap that got as far as remapping every dimension, swapping in the remapped chunks and
+    /// then saving the new world info. A remap that didn't get that far is thrown away, leaving the save as it was.
+    fn finish_remap(&self) -> io::Result<()> {
+        let pending = self.directory.join(REMAP_PENDING_FILE);
+        let info = match fs::read(&pending) {
+            Ok(data) => Some(WorldInfo::decode(&data)?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => return Err(error)
+        };
+        for directory in self.saved_dimension_directories()? {
+            let staging = directory.join(REMAP_DIRECTORY);
+            if !staging.exists() {
+                continue;
+            }
+            if info.is_none() {
+                fs::remove_dir_all(&staging)?;
+                continue;
+            }
+            let regions = directory.join(REGION_DIRECTORY);
+            if regions.exists() {
+                fs::remove_dir_all(&regions)?;
+            }
+            fs::rename(&staging, &regions)?;
+        }
+        if let Some(info) = info {
+            self.save_info(&info)?;
+            fs::remove_file(&pending)?;
+        }
+        return Ok(());
+    }
+
+    /// Directory of every dimension that has been saved, whether or not it's created.
+    pub fn saved_dimension_directories(&self) -> io::Result<Vec<PathBuf>> {
+        let mut directories = Vec::new();
+        let entries = match fs::read_dir(self.directory.join("dimensions")) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(directories),
+            Err(error) => return Err(error)
+        };
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                directories.push(entry.path());
+            }
+        }
+        return Ok(directories);
+    }
+
+    pub fn directory(&self) -> &Path {
+        return &self.directory;
+    }
+
+    /// Directory a dimension saves into.
+    pub fn dimension_directory(&self, name: &str) -> PathBuf {
+        return self.directory.join("dimensions").join(name);
+    }
+
+    /// Read a dimension's level file, such as to make its generator with the seed it was saved with
+    /// before creating it. Ok(None) if the dimension has never been saved.
+    pub fn load_level(&self, name: &str) -> io::Result<Option<LevelData>> {
+        return LevelData::load(&self.dimension_directory(name), &self.migrations);
+    }
+
+    /// Add a dimension, creating its save directory and level file if they don't exist.
+    /// A dimension that was saved before keeps the level it was saved with, including its seed.
+    /// Each dimension's world publishes its events on its own bus, delivered at the end of each of its ticks.
+    /// ```
+    /// # use shared::engine::universe::{Universe, UniverseError, OVERWORLD};
+    /// # use shared::engine::world::tick::TickHandlers;
+    /// # use shared::engine::worldgen::generator::VoidGenerator;
+    /// # use std::sync::Arc;
+    /// let directory = std::env::temp_dir().join(format!("cube_universe_doc_{}", std::process::id()));
+    /// let universe = Universe::new(&directory);
+    /// universe.create_dimension(OVERWORLD, Arc::new(VoidGenerator), TickHandlers::new(), 1).unwrap();
+    /// let pocket = universe.create_dimension("pocket_1", Arc::new(VoidGenerator), TickHandlers::new(), 2).unwrap();
+    /// assert!(pocket.storage().directory().starts_with(&directory));
+    /// assert!(matches!(universe.create_dimension("pocket_1", Arc::new(VoidGenerator), TickHandlers::new(), 2), Err(UniverseError::DuplicateDimension(_))));
+    /// assert!(matches!(universe.create_dimension("Bad Name", Arc::new(VoidGenerator), TickHandlers::new(), 2), Err(UniverseError::InvalidName(_))));
+    /// assert_eq!(universe.dimension_names(), vec!["overworld".to_string(), "pocket_1".to_string()]);
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn create_dimension(&self, name: &str, generator: Arc<dyn WorldGenerator>, handlers: TickHandlers, seed: u64) -> Result<Arc<Dimension>, UniverseError> {
+        if !is_valid_name(name) {
+            return Err(UniverseError::InvalidName(name.to_string()));
+        }
+        let mut dimensions = self.dimensions.write().unwrap();
+        if dimensions.contains_key(name) {
+            return Err(UniverseError::DuplicateDimension(name.to_string()));
+        }
+        let directory = self.dimension_directory(name);
+        let storage = RegionStorage::new(&directory.join(REGION_DIRECTORY))?.with_migrations(self.migrations.clone());
+        let entity_storage = EntityStorage::new(&directory.join("entities"), self.component_types.clone())?.with_migrations(self.migrations.clone());
+        let level = match LevelData::load(&directory, &self.migrations)? {
+            Some(level) => level,
+            None => {
+                let level = LevelData::new(seed, generator.name());
+                level.save(&directory)?;
+                level
+            }
+        };
+        let seed = level.seed;
+        let mut world = World::new().with_level(level).with_events(Arc::new(MessageBus::new()));
+        if let Some(biomes) = generator.biomes() {
+            world = world.with_biomes(biomes.clone());
+        }
+        let dimension = Arc::new(Dimension {
+            name: name.to_string(),
+            directory,
+            world: Arc::new(world),
+            generator,
+            scheduler: Arc::new(TickScheduler::new(handlers, seed)),
+            storage: Arc::new(storage),
+            entity_storage: Arc::new(entity_storage),
+            tick: AtomicU64::new(0),
+            paused: AtomicBool::new(false),
+            arrivals: Mutex::new(Vec::new())
+        });
+        dimensions.insert(name.to_string(), dimension.clone());
+        return Ok(dimension);
+    }
+
+    /// Remove a dimension, saving its level and flushing its region files. Its save directory is kept.
+    pub fn remove_dimension(&self, name: &str) -> Result<Arc<Dimension>, UniverseError> {
+        let dimension = self.dimensions.write().unwrap().remove(name).ok_or_else(|| UniverseError::UnknownDimension(name.to_string()))?;
+        dimension.save_level()?;
+        dimension.storage.sync_all()?;
+        dimension.entity_storage.sync_all()?;
+        return Ok(dimension);
+    }
+
+    pub fn dimension(&self, name: &str) -> Option<Arc<Dimension>> {
+        return self.dimensions.read().unwrap().get(name).cloned();
+    }
+
+    /// Names of every dimension, sorted.
+    pub fn dimension_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.dimensions.read().unwrap().keys().cloned().collect();
+        names.sort_unstable();
+        return names;
+    }
+
+    /// Run one game tick of every dimension that isn't paused. Each dimension keeps its own tick count.
+    pub fn tick_all(&self, jobs: &JobSystem) {
+        let dimensions: Vec<Arc<Dimension>> = self.dimensions.read().unwrap().values().cloned().collect();
+        for dimension in dimensions {
+            dimension.run_tick(jobs);
+        }
+    }
+
+    /// Send an entity to another dimension. The entity system of the target dimension spawns it from take_arrivals().
+    /// ```
+    /// # use shared::engine::universe::{Universe, OVERWORLD};
+    /// # use shared::engine::world::tick::TickHandlers;
+    /// # use shared::engine::worldgen::generator::VoidGenerator;
+    /// # use shared::engine::math::coords::WorldPos;
+    /// # use std::sync::Arc;
+    /// let directory = std::env::temp_dir().join(format!("cube_universe_move_doc_{}", std::process::id()));
+    /// let universe = Universe::new(&directory);
+    /// universe.create_dimension(OVERWORLD, Arc::new(VoidGenerator), TickHandlers::new(), 1).unwrap();
+    /// let pocket = universe.create_dimension("pocket", Arc::new(VoidGenerator), TickHandlers::new(), 2).unwrap();
+    ///
+    /// universe.move_entity(42, OVERWORLD, "pocket", WorldPos::new(0.5, 64.0, 0.5), vec![1, 2, 3]).unwrap();
+    /// assert_eq!(universe.entity_dimension(42).as_deref(), Some("pocket"));
+    /// let arrivals = pocket.take_arrivals();
+    /// assert_eq!(arrivals[0].from, OVERWORLD);
+    /// assert_eq!(arrivals[0].data, vec![1, 2, 3]);
+    /// assert!(universe.move_entity(42, "pocket", "nowhere", WorldPos::new(0.0, 0.0, 0.0), Vec::new()).is_err());
+    /// # std::fs::remove_dir_all(&directory).unwrap();
+    /// ```
+    pub fn move_entity(&self, entity: TransferEntityId, from: &str, to: &str, position: WorldPos, data: Vec<u8>) -> Result<(), UniverseError> {
+        let target = self.dimension(to).ok_or_else(|| UniverseError::UnknownDimension(to.to_string()))?;
+        target.arrivals.lock().unwrap().push(EntityTransfer { entity, from: from.to_string(), position, data });
+        self.locations.lock().unwrap().insert(entity, to.to_string());
+        return Ok(());
+    }
+
+    /// Dimension an entity was last moved to.
+    pub fn entity_dimension(&self, entity: TransferEntityId) -> Option<String> {
+        return self.locations.lock().unwrap().get(&entity).cloned();
+    }
+
+    /// Save the level of every dimension and flush their region files.
+    pub fn sync_all(&self) -> io::Result<()> {
+        let dimensions: Vec<Arc<Dimension>> = self.dimensions.read().unwrap().values().cloned().collect();
+        for dimension in dimensions {
+            dimension.save_level()?;
+            dimension.storage.sync_all()?;
+            dimension.entity_storage.sync_all()?;
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,191 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Serialize, Deserialize};
+
+/// Version of the wire protocol. Bumped whenever a packet changes in a way older builds can't read.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Version of the save format. Older saves are upgraded on load, newer saves are refused.
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+/// major.minor.patch version. Versions with the same major version are compatible,
+/// as long as the available version is at least the required one. Written as "major.minor.patch" in config files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Version {
+        return Version { major, minor, patch };
+    }
+
+    /// Check if this version can stand in for a required version.
+    /// ```
+    /// # use shared::engine::version::Version;
+    /// let required = Version::new(2, 1, 0);
+    /// assert!(Version::new(2, 3, 0).satisfies(required));
+    /// assert!(!Version::new(2, 0, 9).satisfies(required));
+    /// assert!(!Version::new(3, 0, 0).satisfies(required));
+    /// ```
+    pub fn satisfies(&self, required: Version) -> bool {
+        return self.major == required.major && *self >= required;
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}.{}.{}", self.major, self.minor, self.patch);
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    /// Parse "major.minor.patch". Missing minor or patch versions are 0.
+    /// ```
+    /// # use shared::engine::version::Version;
+    /// assert_eq!("1.4.2".parse::<Version>(), Ok(Version::new(1, 4, 2)));
+    /// assert_eq!("2".parse::<Version>(), Ok(Version::new(2, 0, 0)));
+    /// assert!("one".parse::<Version>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Version, String> {
+        let mut parts = [0u32; 3];
+        for (index, part) in s.split('.').enumerate() {
+            if index == 3 {
+                return Err(format!("invalid version {}", s));
+            }
+            parts[index] = part.parse().map_err(|_| format!("invalid version {}", s))?;
+        }
+        return Ok(Version::new(parts[0], parts[1], parts[2]));
+    }
+}
+
+impl TryFrom<String> for Version {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Version, String> {
+        return text.parse();
+    }
+}
+
+impl From<Version> for String {
+    fn from(version: Version) -> String {
+        return version.to_string();
+    }
+}
+
+/// The running engine's version, from the shared crate's package version.
+pub fn engine_version() -> Version {
+    return env!("CARGO_PKG_VERSION").parse().unwrap();
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ModInfo {
+    pub id: String,
+    pub version: Version
+}
+
+/// A reason a world can't be loaded, or a server can't be joined, by this build.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Incompatibility {
+    /// The other side speaks a different protocol.
+    Protocol { required: u32, available: u32 },
+    /// The world was saved by a newer build.
+    SaveFormat { required: u32, available: u32 },
+    Engine { required: Version, available: Version },
+    MissingMod { id: String, required: Version },
+    ModVersion { id: String, required: Version, available: Version }
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Incompatibility::Protocol { required, available } =>
+                write!(f, "requires protocol version {}, this build uses {}", required, available),
+            Incompatibility::SaveFormat { required, available } =>
+                write!(f, "was saved in format version {}, this build supports up to {}", required, available),
+            Incompatibility::Engine { required, available } =>
+                write!(f, "requires engine v{}, this build is v{}", required, available),
+            Incompatibility::MissingMod { id, required } =>
+                write!(f, "requires mod {} v{}", id, required),
+            Incompatibility::ModVersion { id, required, available } =>
+                write!(f, "requires mod {} v{}, v{} is installed", id, required, available)
+        };
+    }
+}
+
+/// Versions embedded in world metadata and sent during the handshake, so mismatches can be reported
+/// clearly before loading or joining instead of failing partway through.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionManifest {
+    pub engine: Version,
+    pub protocol: u32,
+    pub save_format: u32,
+    pub mods: Vec<ModInfo>
+}
+
+impl VersionManifest {
+    /// Manifest of this build with the given mods enabled.
+    pub fn current(mods: Vec<ModInfo>) -> VersionManifest {
+        return VersionManifest { engine: engine_version(), protocol: PROTOCOL_VERSION, save_format: SAVE_FORMAT_VERSION, mods };
+    }
+
+    /// Check that this build can load a world saved with the given manifest.
+    /// Older save formats are fine as they are migrated, but every mod the world used must be present.
+    /// ```
+    /// # use shared::engine::version::{VersionManifest, ModInfo, Version};
+    /// let saved = VersionManifest::current(vec![ModInfo { id: "machines".to_string(), version: Version::new(2, 0, 0) }]);
+    /// let running = VersionManifest::current(vec![]);
+    /// let problems = running.check_world(&saved).unwrap_err();
+    /// assert_eq!(problems[0].to_string(), "requires mod machines v2.0.0");
+    /// assert!(saved.check_world(&saved).is_ok());
+    /// ```
+    pub fn check_world(&self, saved: &VersionManifest) -> Result<(), Vec<Incompatibility>> {
+        let mut problems = Vec::new();
+        if saved.save_format > self.save_format {
+            problems.push(Incompatibility::SaveFormat { required: saved.save_format, available: self.save_format });
+        }
+        self.check_mods(&saved.mods, &mut problems);
+        return if problems.is_empty() { Ok(()) } else { Err(problems) };
+    }
+
+    /// Check that a client with this manifest can join a server with the given one.
+    /// The protocol must match exactly, and the client must have every mod the server runs.
+    /// ```
+    /// # use shared::engine::version::{VersionManifest, Incompatibility};
+    /// let client = VersionManifest::current(vec![]);
+    /// let mut server = VersionManifest::current(vec![]);
+    /// assert!(client.check_server(&server).is_ok());
+    /// server.protocol += 1;
+    /// assert!(matches!(client.check_server(&server).unwrap_err()[0], Incompatibility::Protocol { .. }));
+    /// ```
+    pub fn check_server(&self, server: &VersionManifest) -> Result<(), Vec<Incompatibility>> {
+        let mut problems = Vec::new();
+        if server.protocol != self.protocol {
+            problems.push(Incompatibility::Protocol { required: server.protocol, available: self.protocol });
+        }
+        if server.engine.major != self.engine.major {
+            problems.push(Incompatibility::Engine { required: server.engine, available: self.engine });
+        }
+        self.check_mods(&server.mods, &mut problems);
+        return if problems.is_empty() { Ok(()) } else { Err(problems) };
+    }
+
+    fn check_mods(&self, required: &[ModInfo], problems: &mut Vec<Incompatibility>) {
+        for needed in required {
+            match self.mods.iter().find(|installed| installed.id == needed.id) {
+                None => problems.push(Incompatibility::MissingMod { id: needed.id.clone(), required: needed.version }),
+                Some(installed) => if !installed.version.satisfies(needed.version) {
+                    problems.push(Incompatibility::ModVersion {
+                        id: needed.id.clone(),
+                        required: needed.version,
+                        available: installed.version
+                    });
+                }
+            }
+        }
+    }
+}
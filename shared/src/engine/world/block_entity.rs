@@ -0,0 +1,257 @@
+use std::{any::Any, collections::HashMap, io, sync::{Arc, Mutex, RwLock}};
+
+use crate::engine::{
+    job::system::JobSystem,
+    math::coords::{BlockPos, ChunkPos, LocalPos, CHUNK_VOLUME}
+};
+
+use super::World;
+
+/// Chunks ticked by each block entity tick job.
+const CHUNKS_PER_JOB: usize = 16;
+
+/// Per instance data of a block beyond its block state, such as a chest's items or a machine's progress.
+pub trait BlockEntity: Send + Sync + Any {
+    /// Namespaced id of the block entity's type, used to load it again.
+    fn kind(&self) -> &str;
+
+    /// Run once per game tick while the chunk is loaded.
+    fn tick(&mut self, _context: &BlockEntityContext) {}
+
+    /// Whether tick() does anything, so idle block entities such as signs can be skipped.
+    fn ticks(&self) -> bool {
+        return false;
+    }
+
+    /// Write the block entity's data, to be read by the loader registered for its kind.
+    fn save(&self, out: &mut Vec<u8>);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// A block entity, locked individually so ticks on other threads don't block each other.
+pub type SharedBlockEntity = Arc<Mutex<Box<dyn BlockEntity>>>;
+
+/// Block entities of a loaded chunk, shared between the world and jobs.
+pub type SharedBlockEntities = Arc<RwLock<BlockEntityMap>>;
+
+/// What a block entity can reach while it ticks. Locking another block entity from a tick can deadlock
+/// if that one is ticking at the same time and does the same, so interactions between them should go through blocks or events.
+pub struct BlockEntityContext<'a> {
+    pub world: &'a World,
+    pub block: BlockPos,
+    pub tick: u64
+}
+
+/// Reads a block entity's data written by BlockEntity::save().
+pub type BlockEntityLoader = Arc<dyn Fn(&[u8]) -> io::Result<Box<dyn BlockEntity>> + Send + Sync>;
+
+/// Loaders for each kind of block entity.
+#[derive(Default, Clone)]
+pub struct BlockEntityTypes {
+    loaders: HashMap<String, BlockEntityLoader>
+}
+
+impl BlockEntityTypes {
+    pub fn new() -> BlockEntityTypes {
+        return BlockEntityTypes::default();
+    }
+
+    /// The loader may capture what it needs, such as a registry to load ids saved by name.
+    pub fn register<F>(&mut self, kind: &str, loader: F)
+    where F: Fn(&[u8]) -> io::Result<Box<dyn BlockEntity>> + Send + Sync + 'static {
+        self.loaders.insert(kind.to_string(), Arc::new(loader));
+    }
+
+    pub fn load(&self, kind: &str, data: &[u8]) -> Option<io::Result<Box<dyn BlockEntity>>> {
+        return self.loaders.get(kind).map(|loader| loader(data));
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// Block entities of one chunk.
+#[derive(Default)]
+pub struct BlockEntityMap {
+    entities: HashMap<BlockPos, SharedBlockEntity>
+}
+
+impl BlockEntityMap {
+    pub fn new() -> BlockEntityMap {
+        return BlockEntityMap::default();
+    }
+
+    pub fn insert(&mut self, block: BlockPos, entity: Box<dyn BlockEntity>) -> Option<SharedBlockEntity> {
+        return self.entities.insert(block, Arc::new(Mutex::new(entity)));
+    }
+
+    pub fn remove(&mut self, block: BlockPos) -> Option<SharedBlockEntity> {
+        return self.entities.remove(&block);
+    }
+
+    pub fn get(&self, block: BlockPos) -> Option<&SharedBlockEntity> {
+        return self.entities.get(&block);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&BlockPos, &SharedBlockEntity)> {
+        return self.entities.iter();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entities.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entities.is_empty();
+    }
+
+    /// Encode every block entity as little-endian binary: a count, then for each its local block index,
+    /// kind, and data, each length prefixed. Positions are stored relative to the chunk, which isn't included.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut sorted: Vec<_> = self.entities.iter().collect();
+        sorted.sort_unstable_by_key(|(block, _)| **block);
+        let mut out = Vec::new();
+        out.extend_from_slice(&(sorted.len() as u32).to_le_bytes());
+        let mut data = Vec::new();
+        for (block, entity) in sorted {
+            let entity = entity.lock().unwrap();
+            data.clear();
+            entity.save(&mut data);
+            out.extend_from_slice(&(block.local().index() as u16).to_le_bytes());
+            out.extend_from_slice(&(entity.kind().len() as u16).to_le_bytes());
+            out.extend_from_slice(entity.kind().as_bytes());
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&data);
+        }
+        return out;
+    }
+
+    /// Decode block entities written by encode(). Kinds without a registered loader, such as from a removed mod, are skipped.
+    /// ```
+    /// # use shared::engine::world::block_entity::{BlockEntity, BlockEntityMap, BlockEntityTypes};
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// # use std::any::Any;
+    /// struct Counter(u32);
+    /// impl BlockEntity for Counter {
+    ///     fn kind(&self) -> &str { "cube:counter" }
+    ///     fn save(&self, out: &mut Vec<u8>) { out.extend_from_slice(&self.0.to_le_bytes()); }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    ///     fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    /// }
+    /// let mut types = BlockEntityTypes::new();
+    /// types.register("cube:counter", |data| Ok(Box::new(Counter(u32::from_le_bytes(data.try_into().unwrap())))));
+    ///
+    /// let mut map = BlockEntityMap::new();
+    /// map.insert(BlockPos::new(-3, 4, 5), Box::new(Counter(7)));
+    /// let decoded = BlockEntityMap::decode(&map.encode(), ChunkPos::new(-1, 0, 0), &types).unwrap();
+    /// let entity = decoded.get(BlockPos::new(-3, 4, 5)).unwrap().lock().unwrap();
+    /// assert_eq!(entity.as_any().downcast_ref::<Counter>().unwrap().0, 7);
+    /// ```
+    pub fn decode(data: &[u8], chunk: ChunkPos, types: &BlockEntityTypes) -> io::Result<BlockEntityMap> {
+        let mut reader = data;
+        let mut take = |count: usize| -> io::Result<&[u8]> {
+            if reader.len() < count {
+                return Err(invalid("Block entity data ended early"));
+            }
+            let (taken, rest) = reader.split_at(count);
+            reader = rest;
+            return Ok(taken);
+        };
+        let count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        let mut map = BlockEntityMap::new();
+        for _ in 0..count {
+            let index = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+            if index >= CHUNK_VOLUME {
+                return Err(invalid("Block entity position is outside its chunk"));
+            }
+            let kind_len = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+            let kind = std::str::from_utf8(take(kind_len)?).map_err(|_| invalid("Block entity kind is not UTF-8"))?.to_string();
+            let data_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+            let entity_data = take(data_len)?;
+            if let Some(entity) = types.load(&kind, entity_data) {
+                map.insert(chunk.block(LocalPos::from_index(index)), entity?);
+            }
+        }
+        return Ok(map);
+    }
+}
+
+impl World {
+    /// Add a block entity to a loaded chunk, returning the one it replaced.
+    /// Gives the block entity back if the chunk isn't loaded.
+    pub fn insert_block_entity(&self, block: BlockPos, entity: Box<dyn BlockEntity>) -> Result<Option<SharedBlockEntity>, Box<dyn BlockEntity>> {
+        let Some(map) = self.block_entities(block.chunk()) else {
+            return Err(entity);
+        };
+        let old = map.write().unwrap().insert(block, entity);
+        return Ok(old);
+    }
+
+    pub fn remove_block_entity(&self, block: BlockPos) -> Option<SharedBlockEntity> {
+        return self.block_entities(block.chunk())?.write().unwrap().remove(block);
+    }
+
+    pub fn block_entity(&self, block: BlockPos) -> Option<SharedBlockEntity> {
+        return self.block_entities(block.chunk())?.read().unwrap().get(block).cloned();
+    }
+
+    /// Run one game tick of every ticking block entity in loaded chunks, as jobs, waiting for them to finish.
+    /// ```
+    /// # use shared::engine::world::{World, chunk::Chunk, block_entity::{BlockEntity, BlockEntityContext}};
+    /// # use shared::engine::job::system::JobSystem;
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// # use std::{any::Any, sync::Arc};
+    /// // Sets the block above it to its progress every tick.
+    /// struct Machine(u16);
+    /// impl BlockEntity for Machine {
+    ///     fn kind(&self) -> &str { "cube:machine" }
+    ///     fn tick(&mut self, context: &BlockEntityContext) {
+    ///         self.0 += 1;
+    ///         context.world.set_block(context.block.offset(0, 1, 0), self.0);
+    ///     }
+    ///     fn ticks(&self) -> bool { true }
+    ///     fn save(&self, out: &mut Vec<u8>) {}
+    ///     fn as_any(&self) -> &dyn Any { self }
+    ///     fn as_any_mut(&mut self) -> &mut dyn Any { self }
+    /// }
+    /// let world = Arc::new(World::new());
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    /// assert!(world.insert_block_entity(BlockPos::new(1, 1, 1), Box::new(Machine(0))).is_ok());
+    /// assert!(world.insert_block_entity(BlockPos::new(1, 100, 1), Box::new(Machine(0))).is_err());
+    /// let jobs = JobSystem::new(2);
+    /// for tick in 0..3 {
+    ///     world.tick_block_entities(&jobs, tick);
+    /// }
+    /// assert_eq!(world.get_block(BlockPos::new(1, 2, 1)), Some(3));
+    /// ```
+    pub fn tick_block_entities(self: &Arc<World>, jobs: &JobSystem, tick: u64) {
+        let mut futures = Vec::new();
+        let chunks = self.loaded_chunks();
+        for batch in chunks.chunks(CHUNKS_PER_JOB) {
+            let batch = batch.to_vec();
+            let world = self.clone();
+            futures.push(jobs.run_job(move || {
+                for pos in batch.iter() {
+                    let Some(map) = world.block_entities(*pos) else {
+                        continue;
+                    };
+                    // Copy the entities out, so ticks can add or remove block entities in their own chunk.
+                    let entities: Vec<(BlockPos, SharedBlockEntity)> = map.read().unwrap().iter().map(|(block, entity)| (*block, entity.clone())).collect();
+                    for (block, entity) in entities {
+                        let mut entity = entity.lock().unwrap();
+                        if entity.ticks() {
+                            entity.tick(&BlockEntityContext { world: &world, block, tick });
+                        }
+                    }
+                }
+            }));
+        }
+        for future in futures {
+            future.wait();
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,304 @@
+use crate::engine::{block::{remap::BlockIdRemap, BlockId, AIR}, math::{coords::{ChunkPos, LocalPos, CHUNK_SIZE}, direction::{Axis, Direction}}};
+
+use super::palette::{PalettedSection, SECTION_SIZE};
+
+/// Number of sections along each axis of a chunk.
+pub const SECTIONS_PER_AXIS: usize = CHUNK_SIZE as usize / SECTION_SIZE;
+/// Number of sections in a chunk.
+pub const SECTIONS_PER_CHUNK: usize = SECTIONS_PER_AXIS * SECTIONS_PER_AXIS * SECTIONS_PER_AXIS;
+/// Number of columns in a chunk's heightmap.
+pub const CHUNK_COLUMNS: usize = CHUNK_SIZE as usize * CHUNK_SIZE as usize;
+
+/// A cube of CHUNK_SIZE blocks along each axis, stored as 8 independently paletted 16x16x16 sections.
+/// Sections let mostly uniform regions, such as the air above terrain or solid stone below it, stay tiny
+/// even when a different part of the chunk is detailed.
+/// Edits are tracked per section, so only the parts of the chunk that changed are remeshed.
+/// The highest block of each column is cached, and kept up to date as blocks change.
+#[derive(Clone, Debug)]
+pub struct Chunk {
+    pos: ChunkPos,
+    sections: [PalettedSection; SECTIONS_PER_CHUNK],
+    /// Bit per section edited since the flags were last taken.
+    dirty_sections: u8,
+    /// Bit per Direction of the faces an edit touched, whose neighbor chunks need remeshing too.
+    dirty_borders: u8,
+    /// Per column, 1 above the local y of its highest non air block, or 0 if the column is empty.
+    heights: Box<[u8; CHUNK_COLUMNS]>
+}
+
+/// Chunks are equal if their blocks are, regardless of which have been edited.
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Chunk) -> bool {
+        return self.pos == other.pos && self.sections == other.sections;
+    }
+}
+
+impl Eq for Chunk {}
+
+impl Chunk {
+    /// Create a chunk filled with air.
+    /// ```
+    /// # use shared::engine::world::chunk::Chunk;
+    /// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+    /// let chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    /// assert!(chunk.is_empty());
+    /// assert_eq!(chunk.get_block(LocalPos::new(5, 5, 5)), 0);
+    /// ```
+    pub fn new(pos: ChunkPos) -> Chunk {
+        return Chunk::filled(pos, AIR);
+    }
+
+    /// Create a chunk with every block set to one id.
+    pub fn filled(pos: ChunkPos, id: BlockId) -> Chunk {
+        return Chunk::from_sections(pos, std::array::from_fn(|_| PalettedSection::new(id)));
+    }
+
+    /// Create a chunk from existing sections, such as ones decoded from a save.
+    pub fn from_sections(pos: ChunkPos, sections: [PalettedSection; SECTIONS_PER_CHUNK]) -> Chunk {
+        let mut chunk = Chunk { pos, sections, dirty_sections: 0, dirty_borders: 0, heights: Box::new([0; CHUNK_COLUMNS]) };
+        chunk.update_heightmap();
+        return chunk;
+    }
+
+    pub fn pos(&self) -> ChunkPos {
+        return self.pos;
+    }
+
+    pub fn get_block(&self, local: LocalPos) -> BlockId {
+        let (section, index) = Self::section_index(local);
+        return self.sections[section].get(index);
+    }
+
+    /// Set a block, returning the previous id.
+    /// ```
+    /// # use shared::engine::world::chunk::Chunk;
+    /// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+    /// let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    /// assert_eq!(chunk.set_block(LocalPos::new(31, 0, 16), 3), 0);
+    /// assert_eq!(chunk.get_block(LocalPos::new(31, 0, 16)), 3);
+    /// assert_eq!(chunk.non_air_count(), 1);
+    /// ```
+    pub fn set_block(&mut self, local: LocalPos, id: BlockId) -> BlockId {
+        let (section, index) = Self::section_index(local);
+        let old = self.sections[section].set(index, id);
+        if old != id {
+            self.mark_dirty(local);
+            let column = Self::column_index(local.x, local.z);
+            let height = self.heights[column];
+            if id != AIR && local.y >= height {
+                self.heights[column] = local.y + 1;
+            } else if id == AIR && local.y + 1 == height {
+                self.heights[column] = self.scan_height(local.x, local.z, local.y);
+            }
+        }
+        return old;
+    }
+
+    /// Local y of the highest non air block in a column, None if the column is empty.
+    /// Blocks don't know their properties at this level, so any block other than air counts.
+    /// ```
+    /// # use shared::engine::world::chunk::Chunk;
+    /// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+    /// let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    /// assert_eq!(chunk.height(3, 4), None);
+    /// chunk.set_block(LocalPos::new(3, 2, 4), 1);
+    /// chunk.set_block(LocalPos::new(3, 20, 4), 1);
+    /// assert_eq!(chunk.height(3, 4), Some(20));
+    /// chunk.set_block(LocalPos::new(3, 20, 4), 0);
+    /// assert_eq!(chunk.height(3, 4), Some(2));
+    /// ```
+    pub fn height(&self, x: u8, z: u8) -> Option<u8> {
+        return self.heights[Self::column_index(x, z)].checked_sub(1);
+    }
+
+    /// Local y of the highest non air block in the chunk, None if the chunk is empty.
+    pub fn max_height(&self) -> Option<u8> {
+        return self.heights.iter().max().unwrap().checked_sub(1);
+    }
+
+    /// Recalculate every column's height, such as after editing sections directly.
+    pub fn update_heightmap(&mut self) {
+        for z in 0..CHUNK_SIZE as u8 {
+            for x in 0..CHUNK_SIZE as u8 {
+                self.heights[Self::column_index(x, z)] = self.scan_height(x, z, CHUNK_SIZE as u8);
+            }
+        }
+    }
+
+    /// Height value of a column, only considering blocks below y.
+    fn scan_height(&self, x: u8, z: u8, below: u8) -> u8 {
+        let mut y = below;
+        while y > 0 {
+            let section = Self::section_index(LocalPos::new(x, y - 1, z)).0;
+            if self.sections[section].is_empty() {
+                // Skip down to the section below.
+                y -= (y - 1) % SECTION_SIZE as u8 + 1;
+                continue;
+            }
+            if self.get_block(LocalPos::new(x, y - 1, z)) != AIR {
+                return y;
+            }
+            y -= 1;
+        }
+        return 0;
+    }
+
+    fn column_index(x: u8, z: u8) -> usize {
+        return x as usize + z as usize * CHUNK_SIZE as usize;
+    }
+
+    /// Flag the sections whose faces an edited block affects: its own, any section it borders within the chunk,
+    /// and the faces of the chunk it touches.
+    fn mark_dirty(&mut self, local: LocalPos) {
+        let (section, _) = Self::section_index(local);
+        self.dirty_sections |= 1 << section;
+        let coords = [local.x as usize, local.y as usize, local.z as usize];
+        for axis in Axis::ALL {
+            let coord = coords[axis as usize];
+            let stride = [1, SECTIONS_PER_AXIS * SECTIONS_PER_AXIS, SECTIONS_PER_AXIS][axis as usize];
+            if coord == 0 {
+                self.dirty_borders |= 1 << axis.direction(false).index();
+            } else if coord == CHUNK_SIZE as usize - 1 {
+                self.dirty_borders |= 1 << axis.direction(true).index();
+            } else if coord == SECTION_SIZE - 1 {
+                self.dirty_sections |= 1 << (section + stride);
+            } else if coord == SECTION_SIZE {
+                self.dirty_sections |= 1 << (section - stride);
+            }
+        }
+    }
+
+    /// Set every block in the chunk.
+    pub fn fill(&mut self, id: BlockId) {
+        for section in self.sections.iter_mut() {
+            section.fill(id);
+        }
+        self.mark_all_dirty();
+        self.heights.fill(if id == AIR { 0 } else { CHUNK_SIZE as u8 });
+    }
+
+    /// Sections edited since the flags were last taken, as a bit per section index.
+    pub fn dirty_sections(&self) -> u8 {
+        return self.dirty_sections;
+    }
+
+    /// Faces of the chunk edits touched, as a bit per Direction index.
+    pub fn dirty_borders(&self) -> u8 {
+        return self.dirty_borders;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        return self.dirty_sections != 0 || self.dirty_borders != 0;
+    }
+
+    /// Return and clear the dirty sections and borders.
+    /// ```
+    /// # use shared::engine::world::chunk::Chunk;
+    /// # use shared::engine::math::{coords::{ChunkPos, LocalPos}, direction::Direction};
+    /// let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    /// chunk.set_block(LocalPos::new(1, 1, 1), 1);
+    /// assert_eq!(chunk.take_dirty(), (0b1, 0));
+    /// // On the border between the first two sections along x, and on the chunk's top face.
+    /// chunk.set_block(LocalPos::new(15, 31, 1), 1);
+    /// assert_eq!(chunk.take_dirty(), (0b11 << 4, 1 << Direction::PosY.index()));
+    /// assert!(!chunk.is_dirty());
+    /// ```
+    pub fn take_dirty(&mut self) -> (u8, u8) {
+        let dirty = (self.dirty_sections, self.dirty_borders);
+        self.dirty_sections = 0;
+        self.dirty_borders = 0;
+        return dirty;
+    }
+
+    /// Flag every section and face, such as after editing sections directly.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty_sections = u8::MAX;
+        self.dirty_borders = (1 << Direction::ALL.len()) - 1;
+    }
+
+    /// Check if every block is air.
+    pub fn is_empty(&self) -> bool {
+        return self.sections.iter().all(|section| section.is_empty());
+    }
+
+    pub fn non_air_count(&self) -> usize {
+        return self.sections.iter().map(|section| section.non_air_count()).sum();
+    }
+
+    pub fn sections(&self) -> &[PalettedSection; SECTIONS_PER_CHUNK] {
+        return &self.sections;
+    }
+
+    /// Edits through the sections aren't tracked, see mark_all_dirty() and update_heightmap().
+    pub fn sections_mut(&mut self) -> &mut [PalettedSection; SECTIONS_PER_CHUNK] {
+        return &mut self.sections;
+    }
+
+    /// Shrink every section's palette to the blocks still in use.
+    pub fn compact(&mut self) {
+        for section in self.sections.iter_mut() {
+            section.compact();
+        }
+    }
+
+    /// Translate every block from the registry the chunk was saved with into the current one.
+    pub fn remap_blocks(&mut self, remap: &BlockIdRemap) {
+        for section in self.sections.iter_mut() {
+            section.remap(|id| remap.map(id));
+        }
+        self.update_heightmap();
+    }
+
+    /// Approximate bytes used by the chunk.
+    /// ```
+    /// # use shared::engine::world::chunk::Chunk;
+    /// # use shared::engine::math::coords::{ChunkPos, CHUNK_VOLUME};
+    /// let chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    /// // Far smaller than a flat array of u16 ids.
+    /// assert!(chunk.memory_usage() < CHUNK_VOLUME * 2 / 100);
+    /// ```
+    pub fn memory_usage(&self) -> usize {
+        return std::mem::size_of::<ChunkPos>() + self.sections.iter().map(|section| section.memory_usage()).sum::<usize>();
+    }
+
+    /// Section index, and index within that section, of a block. Sections are ordered X fastest, then Z, then Y,
+    /// the same as blocks within them.
+    /// ```
+    /// # use shared::engine::world::chunk::Chunk;
+    /// # use shared::engine::math::coords::LocalPos;
+    /// assert_eq!(Chunk::section_index(LocalPos::new(0, 0, 0)), (0, 0));
+    /// assert_eq!(Chunk::section_index(LocalPos::new(17, 0, 0)), (1, 1));
+    /// assert_eq!(Chunk::section_index(LocalPos::new(0, 16, 0)), (4, 0));
+    /// assert_eq!(Chunk::section_index(LocalPos::new(31, 31, 31)), (7, 4095));
+    /// ```
+    pub fn section_index(local: LocalPos) -> (usize, usize) {
+        let (x, y, z) = (local.x as usize, local.y as usize, local.z as usize);
+        let section = (x / SECTION_SIZE) + (z / SECTION_SIZE) * SECTIONS_PER_AXIS + (y / SECTION_SIZE) * SECTIONS_PER_AXIS * SECTIONS_PER_AXIS;
+        let (sx, sy, sz) = (x % SECTION_SIZE, y % SECTION_SIZE, z % SECTION_SIZE);
+        let index = sx + sz * SECTION_SIZE + sy * SECTION_SIZE * SECTION_SIZE;
+        return (section, index);
+    }
+
+    /// Sections touching a face of the chunk, as a bit per section index.
+    pub fn sections_on_face(direction: Direction) -> u8 {
+        let mut mask = 0;
+        for section in 0..SECTIONS_PER_CHUNK {
+            let origin = Self::section_origin(section);
+            let coord = [origin.x, origin.y, origin.z][direction.axis() as usize] as usize;
+            let on_face = if direction.is_positive() { coord + SECTION_SIZE == CHUNK_SIZE as usize } else { coord == 0 };
+            if on_face {
+                mask |= 1 << section;
+            }
+        }
+        return mask;
+    }
+
+    /// Local position of the minimum corner of a section.
+    pub fn section_origin(section: usize) -> LocalPos {
+        debug_assert!(section < SECTIONS_PER_CHUNK, "Section index out of range");
+        let x = section % SECTIONS_PER_AXIS;
+        let z = (section / SECTIONS_PER_AXIS) % SECTIONS_PER_AXIS;
+        let y = section / (SECTIONS_PER_AXIS * SECTIONS_PER_AXIS);
+        return LocalPos::new((x * SECTION_SIZE) as u8, (y * SECTION_SIZE) as u8, (z * SECTION_SIZE) as u8);
+    }
+}
@@ -0,0 +1,438 @@
+use std::{collections::{BTreeSet, HashMap}, sync::{Arc, Mutex, RwLock}};
+
+use crate::engine::{
+    block::BlockId,
+    entity::{events::{EntityEvent, EventReader}, Entities},
+    event::{bus::MessageBus, events::{BlockChanged, ChunkLoaded, ChunkUnloaded, EntitySpawned}},
+    light::storage::ChunkLight,
+    math::{coords::{BlockPos, ChunkPos}, morton::MortonKey},
+    metrics::standard::engine_metrics,
+    physics::shape::BlockShapes,
+    save::level::{GameRule, GameRuleType, GameRules, LevelData, DO_DAYLIGHT_CYCLE},
+    worldgen::biome::{Biome, BiomeId, BiomeSource}
+};
+
+use super::{block_entity::{BlockEntityMap, SharedBlockEntities}, chunk::Chunk, time::WorldTime};
+
+/// Default number of shards. More shards than job threads keeps contention between writers low.
+pub const DEFAULT_WORLD_SHARDS: usize = 64;
+
+/// A loaded chunk, shared between the world and any jobs working on it.
+pub type SharedChunk = Arc<RwLock<Chunk>>;
+
+/// Light of a loaded chunk, kept beside it so relighting doesn't block readers of the chunk's blocks.
+pub type SharedLight = Arc<RwLock<ChunkLight>>;
+
+#[derive(Clone)]
+struct Entry {
+    chunk: SharedChunk,
+    light: SharedLight,
+    block_entities: SharedBlockEntities
+}
+
+type Shard = RwLock<HashMap<MortonKey, Entry>>;
+
+/// Every loaded chunk of a world, safe to access from jobs on many threads at once.
+/// Chunks are spread across independently locked shards, so threads working on different chunks rarely
+/// contend on the same map lock, and each chunk has its own lock so block edits only block that chunk.
+pub struct World {
+    shards: Box<[Shard]>,
+    /// Y of every loaded chunk in each column, so the surface can be found without searching every chunk.
+    columns: RwLock<HashMap<(i32, i32), BTreeSet<i32>>>,
+    entities: Entities,
+    biomes: Option<Arc<BiomeSource>>,
+    block_shapes: Arc<BlockShapes>,
+    level: RwLock<LevelData>,
+    events: Option<Arc<MessageBus>>,
+    /// Entity events already published on the bus.
+    entity_events: Mutex<EventReader>
+}
+
+impl World {
+    pub fn new() -> World {
+        return World::with_shards(DEFAULT_WORLD_SHARDS);
+    }
+
+    /// Will panic in debug mode if shard_count is 0.
+    pub fn with_shards(shard_count: usize) -> World {
+        debug_assert!(shard_count > 0, "Cannot create a world with 0 shards");
+        let entities = Entities::new();
+        let entity_events = Mutex::new(entities.event_reader());
+        return World { shards: (0..shard_count).map(|_| RwLock::new(HashMap::new())).collect(), columns: RwLock::new(HashMap::new()), entities, biomes: None, block_shapes: Arc::new(BlockShapes::new()), level: RwLock::new(LevelData::new(0, "")), events: None, entity_events };
+    }
+
+    /// Use the biomes the world was generated with, usually from WorldGenerator::biomes().
+    pub fn with_biomes(mut self, biomes: Arc<BiomeSource>) -> World {
+        self.biomes = Some(biomes);
+        return self;
+    }
+
+    /// Use the collision shapes of the game's blocks, rather than every block but air being a full cube.
+    pub fn with_block_shapes(mut self, shapes: Arc<BlockShapes>) -> World {
+        self.block_shapes = shapes;
+        return self;
+    }
+
+    /// Use the metadata the world was saved with, or made with when new.
+    pub fn with_level(self, level: LevelData) -> World {
+        *self.level.write().unwrap() = level;
+        return self;
+    }
+
+    /// Publish the world's events, such as blocks changing and chunks loading, on a bus, so other systems can
+    /// react to them without the world knowing about them.
+    /// ```
+    /// # use shared::engine::event::{bus::MessageBus, events::{BlockChanged, ChunkUnloaded}};
+    /// # use shared::engine::world::{World, chunk::Chunk};
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// # use std::sync::{Arc, Mutex};
+    /// let bus = Arc::new(MessageBus::new());
+    /// let world = World::new().with_events(bus.clone());
+    /// let changes = Arc::new(Mutex::new(Vec::new()));
+    /// let captured = changes.clone();
+    /// bus.subscribe(move |change: &BlockChanged| captured.lock().unwrap().push(change.new));
+    /// let unloaded = Arc::new(Mutex::new(Vec::new()));
+    /// let captured = unloaded.clone();
+    /// bus.subscribe(move |chunk: &ChunkUnloaded| captured.lock().unwrap().push(chunk.pos));
+    ///
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    /// world.set_block(BlockPos::new(1, 1, 1), 4);
+    /// world.set_block(BlockPos::new(1, 1, 1), 4);
+    /// assert!(changes.lock().unwrap().is_empty());
+    /// world.dispatch_events();
+    /// assert_eq!(*changes.lock().unwrap(), vec![4]);
+    /// // Delivered before the chunk is gone, rather than at the end of the tick.
+    /// world.remove_chunk(ChunkPos::new(0, 0, 0));
+    /// assert_eq!(*unloaded.lock().unwrap(), vec![ChunkPos::new(0, 0, 0)]);
+    /// ```
+    pub fn with_events(mut self, events: Arc<MessageBus>) -> World {
+        self.events = Some(events);
+        return self;
+    }
+
+    pub fn events(&self) -> Option<&Arc<MessageBus>> {
+        return self.events.as_ref();
+    }
+
+    /// Publish entities spawned since the last call, then deliver every event queued on the world's bus.
+    /// Called at the end of every tick. Returns the number of queued events delivered.
+    pub fn dispatch_events(&self) -> usize {
+        let Some(events) = self.events.as_ref() else {
+            return 0;
+        };
+        for event in self.entities.read_events(&mut self.entity_events.lock().unwrap()) {
+            if let EntityEvent::Spawned(entity) = event {
+                events.publish(EntitySpawned { entity });
+            }
+        }
+        return events.dispatch();
+    }
+
+    /// Copy of the world's metadata, for saving its level file.
+    pub fn level(&self) -> LevelData {
+        return self.level.read().unwrap().clone();
+    }
+
+    pub fn seed(&self) -> u64 {
+        return self.level.read().unwrap().seed;
+    }
+
+    /// Ticks of world time.
+    pub fn time(&self) -> u64 {
+        return self.level.read().unwrap().time;
+    }
+
+    /// World time as the time of day and moon phase, for the sky and lighting.
+    pub fn world_time(&self) -> WorldTime {
+        return WorldTime::new(self.time());
+    }
+
+    pub fn set_time(&self, time: u64) {
+        self.level.write().unwrap().time = time;
+    }
+
+    /// Advance world time by a number of ticks, unless the daylight cycle is turned off, returning the new time.
+    /// ```
+    /// # use shared::engine::world::World;
+    /// # use shared::engine::save::level::DO_DAYLIGHT_CYCLE;
+    /// let world = World::new();
+    /// assert_eq!(world.advance_time(20), 20);
+    /// world.set_game_rule(DO_DAYLIGHT_CYCLE, false);
+    /// assert_eq!(world.advance_time(20), 20);
+    /// ```
+    pub fn advance_time(&self, ticks: u64) -> u64 {
+        let mut level = self.level.write().unwrap();
+        if level.game_rules.get(DO_DAYLIGHT_CYCLE) {
+            level.time += ticks;
+        }
+        return level.time;
+    }
+
+    /// Where new players appear.
+    pub fn spawn_point(&self) -> BlockPos {
+        return self.level.read().unwrap().spawn;
+    }
+
+    pub fn set_spawn_point(&self, spawn: BlockPos) {
+        self.level.write().unwrap().spawn = spawn;
+    }
+
+    pub fn game_rule<T: GameRuleType>(&self, rule: GameRule<T>) -> T {
+        return self.level.read().unwrap().game_rules.get(rule);
+    }
+
+    pub fn set_game_rule<T: GameRuleType>(&self, rule: GameRule<T>, value: T) {
+        self.level.write().unwrap().game_rules.set(rule, value);
+    }
+
+    /// Every game rule changed from its default.
+    pub fn game_rules(&self) -> GameRules {
+        return self.level.read().unwrap().game_rules.clone();
+    }
+
+    /// What entities collide with in each block.
+    pub fn block_shapes(&self) -> &Arc<BlockShapes> {
+        return &self.block_shapes;
+    }
+
+    pub fn biome_source(&self) -> Option<&Arc<BiomeSource>> {
+        return self.biomes.as_ref();
+    }
+
+    /// Biome of the column holding a block, for gameplay and grass tinting. Biomes only vary horizontally,
+    /// and don't need the chunk to be loaded. None if the world has no biomes.
+    /// ```
+    /// # use shared::engine::block::BlockRegistry;
+    /// # use shared::engine::world::World;
+    /// # use shared::engine::worldgen::{blocks::TerrainBlocks, terrain::NoiseGenerator, WorldGenerator};
+    /// # use shared::engine::math::coords::BlockPos;
+    /// let blocks = TerrainBlocks::register(&mut BlockRegistry::new()).unwrap();
+    /// let generator = NoiseGenerator::new(3, blocks);
+    /// let world = World::new().with_biomes(generator.biomes().unwrap().clone());
+    /// let biome = world.biome_at(BlockPos::new(10, 64, -10)).unwrap();
+    /// assert_eq!(world.biome_at(BlockPos::new(10, -64, -10)), Some(biome));
+    /// assert!(World::new().biome_at(BlockPos::new(10, 64, -10)).is_none());
+    /// ```
+    pub fn biome_at(&self, block: BlockPos) -> Option<BiomeId> {
+        return self.biomes.as_ref().map(|biomes| biomes.biome_at(block.x, block.z));
+    }
+
+    /// Properties of the biome at a block, such as its tint.
+    pub fn biome(&self, block: BlockPos) -> Option<&Biome> {
+        let biomes = self.biomes.as_ref()?;
+        return Some(biomes.biome(biomes.biome_at(block.x, block.z)));
+    }
+
+    /// Players, mobs, dropped items and every other entity in the world.
+    /// ```
+    /// # use shared::engine::world::World;
+    /// let world = World::new();
+    /// let player = world.entities().spawn();
+    /// world.entities().insert(player, String::from("Steve")).unwrap();
+    /// assert_eq!(world.entities().get::<String>(player).as_deref(), Some("Steve"));
+    /// ```
+    pub fn entities(&self) -> &Entities {
+        return &self.entities;
+    }
+
+    fn shard(&self, key: MortonKey) -> &Shard {
+        // Mix the key, as neighboring chunks share most of their Morton bits.
+        let hash = key.0.wrapping_mul(0x9e3779b97f4a7c15) >> 32;
+        return &self.shards[hash as usize % self.shards.len()];
+    }
+
+    /// Add a loaded chunk, returning the chunk previously at that position. The chunk starts dark until it is lit.
+    pub fn insert_chunk(&self, chunk: Chunk) -> Option<SharedChunk> {
+        let pos = chunk.pos();
+        let key = pos.morton();
+        self.columns.write().unwrap().entry((pos.x, pos.z)).or_default().insert(pos.y);
+        let entry = Entry {
+            chunk: Arc::new(RwLock::new(chunk)),
+            light: Arc::new(RwLock::new(ChunkLight::new())),
+            block_entities: Arc::new(RwLock::new(BlockEntityMap::new()))
+        };
+        let old = self.shard(key).write().unwrap().insert(key, entry).map(|old| old.chunk);
+        if old.is_none() {
+            engine_metrics().chunks_loaded.inc();
+        }
+        if let Some(events) = self.events.as_ref() {
+            events.publish(ChunkLoaded { pos });
+        }
+        return old;
+    }
+
+    /// Remove a chunk, such as when unloading it. Jobs still holding the chunk keep it alive until they finish.
+    /// ChunkUnloaded is delivered right away, while the chunk can still be read from the world.
+    pub fn remove_chunk(&self, pos: ChunkPos) -> Option<SharedChunk> {
+        let key = pos.morton();
+        if let Some(events) = self.events.as_ref().filter(|_| self.is_loaded(pos)) {
+            events.publish_now(ChunkUnloaded { pos });
+        }
+        let old = self.shard(key).write().unwrap().remove(&key).map(|old| old.chunk);
+        if old.is_some() {
+            engine_metrics().chunks_loaded.dec();
+            let mut columns = self.columns.write().unwrap();
+            if let Some(column) = columns.get_mut(&(pos.x, pos.z)) {
+                column.remove(&pos.y);
+                if column.is_empty() {
+                    columns.remove(&(pos.x, pos.z));
+                }
+            }
+        }
+        return old;
+    }
+
+    pub fn chunk(&self, pos: ChunkPos) -> Option<SharedChunk> {
+        let key = pos.morton();
+        return self.shard(key).read().unwrap().get(&key).map(|entry| entry.chunk.clone());
+    }
+
+    /// Light of a loaded chunk.
+    pub fn light(&self, pos: ChunkPos) -> Option<SharedLight> {
+        let key = pos.morton();
+        return self.shard(key).read().unwrap().get(&key).map(|entry| entry.light.clone());
+    }
+
+    /// Block entities of a loaded chunk.
+    pub fn block_entities(&self, pos: ChunkPos) -> Option<SharedBlockEntities> {
+        let key = pos.morton();
+        return self.shard(key).read().unwrap().get(&key).map(|entry| entry.block_entities.clone());
+    }
+
+    /// A loaded chunk and its light, from a single lookup.
+    pub fn chunk_and_light(&self, pos: ChunkPos) -> Option<(SharedChunk, SharedLight)> {
+        let key = pos.morton();
+        return self.shard(key).read().unwrap().get(&key).map(|entry| (entry.chunk.clone(), entry.light.clone()));
+    }
+
+    pub fn is_loaded(&self, pos: ChunkPos) -> bool {
+        let key = pos.morton();
+        return self.shard(key).read().unwrap().contains_key(&key);
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        return self.shards.iter().map(|shard| shard.read().unwrap().len()).sum();
+    }
+
+    /// Block id at a position. None if the chunk holding it isn't loaded.
+    /// ```
+    /// # use shared::engine::world::{World, chunk::Chunk};
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// let world = World::new();
+    /// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+    /// assert_eq!(world.get_block(BlockPos::new(3, -1, 3)), Some(1));
+    /// assert_eq!(world.get_block(BlockPos::new(3, 0, 3)), None);
+    /// ```
+    pub fn get_block(&self, block: BlockPos) -> Option<BlockId> {
+        let chunk = self.chunk(block.chunk())?;
+        let id = chunk.read().unwrap().get_block(block.local());
+        return Some(id);
+    }
+
+    /// Set a block, returning the previous id. None, without changing anything, if the chunk holding it isn't loaded.
+    /// ```
+    /// # use shared::engine::world::{World, chunk::Chunk};
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// let world = World::new();
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(-1, 0, 0)));
+    /// assert_eq!(world.set_block(BlockPos::new(-1, 5, 5), 7), Some(0));
+    /// assert_eq!(world.get_block(BlockPos::new(-1, 5, 5)), Some(7));
+    /// assert_eq!(world.set_block(BlockPos::new(1, 5, 5), 7), None);
+    /// ```
+    pub fn set_block(&self, block: BlockPos, id: BlockId) -> Option<BlockId> {
+        let chunk = self.chunk(block.chunk())?;
+        let old = chunk.write().unwrap().set_block(block.local(), id);
+        if let Some(events) = self.events.as_ref().filter(|_| old != id) {
+            events.publish(BlockChanged { pos: block, old, new: id });
+        }
+        return Some(old);
+    }
+
+    /// Y of the highest non air block of a column, from the cached heightmaps of its loaded chunks.
+    /// None if no loaded chunk of the column has any blocks.
+    /// ```
+    /// # use shared::engine::world::{World, chunk::Chunk};
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// let world = World::new();
+    /// world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), 1));
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    /// assert_eq!(world.surface_height(5, 5), Some(-1));
+    /// world.set_block(BlockPos::new(5, 40, 5), 1);
+    /// assert_eq!(world.surface_height(5, 5), Some(-1));
+    /// world.set_block(BlockPos::new(5, 10, 5), 1);
+    /// assert_eq!(world.surface_height(5, 5), Some(10));
+    /// assert_eq!(world.surface_height(40, 5), None);
+    /// ```
+    pub fn surface_height(&self, x: i32, z: i32) -> Option<i32> {
+        let column = BlockPos::new(x, 0, z).chunk();
+        let ys: Vec<i32> = self.columns.read().unwrap().get(&(column.x, column.z))?.iter().rev().copied().collect();
+        let local = BlockPos::new(x, 0, z).local();
+        for y in ys {
+            let pos = ChunkPos::new(column.x, y, column.z);
+            let Some(chunk) = self.chunk(pos) else {
+                continue;
+            };
+            let height = chunk.read().unwrap().height(local.x, local.z);
+            if let Some(height) = height {
+                return Some(pos.origin().y + height as i32);
+            }
+        }
+        return None;
+    }
+
+    /// Every loaded chunk at the time of the call, in Morton order so nearby chunks are visited together.
+    /// Chunks loaded or unloaded afterwards don't affect the snapshot, and no world lock is held while iterating it.
+    /// ```
+    /// # use shared::engine::world::{World, chunk::Chunk};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let world = World::new();
+    /// for x in 0..4 {
+    ///     world.insert_chunk(Chunk::new(ChunkPos::new(x, 0, 0)));
+    /// }
+    /// let snapshot = world.snapshot();
+    /// world.remove_chunk(ChunkPos::new(0, 0, 0));
+    /// assert_eq!(snapshot.len(), 4);
+    /// assert_eq!(snapshot[0].read().unwrap().pos(), ChunkPos::new(0, 0, 0));
+    /// ```
+    pub fn snapshot(&self) -> Vec<SharedChunk> {
+        let mut chunks: Vec<(MortonKey, SharedChunk)> = Vec::new();
+        for shard in self.shards.iter() {
+            let lock = shard.read().unwrap();
+            chunks.extend((*lock).iter().map(|(key, entry)| (*key, entry.chunk.clone())));
+        }
+        chunks.sort_unstable_by_key(|(key, _)| *key);
+        return chunks.into_iter().map(|(_, chunk)| chunk).collect();
+    }
+
+    /// Positions of every loaded chunk, in Morton order.
+    pub fn loaded_chunks(&self) -> Vec<ChunkPos> {
+        let mut keys: Vec<MortonKey> = Vec::new();
+        for shard in self.shards.iter() {
+            keys.extend(shard.read().unwrap().keys().copied());
+        }
+        keys.sort_unstable();
+        return keys.into_iter().map(|key| key.chunk()).collect();
+    }
+
+    /// Call a function with every chunk in a snapshot, read locking one chunk at a time.
+    pub fn for_each_chunk<F>(&self, mut func: F)
+    where F: FnMut(&Chunk) {
+        for chunk in self.snapshot() {
+            func(&chunk.read().unwrap());
+        }
+    }
+}
+
+impl Default for World {
+    fn default() -> World {
+        return World::new();
+    }
+}
+
+
+impl Drop for World {
+    fn drop(&mut self) {
+        // Chunks still loaded when a world is dropped are unloaded with it.
+        engine_metrics().chunks_loaded.add(-(self.chunk_count() as f64));
+    }
+}
\ No newline at end of file
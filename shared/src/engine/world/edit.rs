@@ -0,0 +1,271 @@
+use std::{collections::{BTreeMap, VecDeque}, fmt};
+
+use crate::engine::{
+    block::BlockId,
+    event::events::BlockChanged,
+    light::propagation::{update_blocks, LightingFn},
+    math::coords::{BlockPos, ChunkPos}
+};
+
+use super::World;
+
+/// Edits kept for undoing by default, oldest forgotten first.
+pub const DEFAULT_HISTORY_LIMIT: usize = 32;
+
+/// A block an edit changed, from what to what.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockChange {
+    pub pos: BlockPos,
+    pub before: BlockId,
+    pub after: BlockId
+}
+
+/// Why an edit couldn't be applied. Nothing in the world changes when it fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditError {
+    /// A chunk the edit touches isn't loaded.
+    Unloaded(ChunkPos)
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            EditError::Unloaded(pos) => write!(f, "chunk ({}, {}, {}) isn't loaded", pos.x, pos.y, pos.z)
+        };
+    }
+}
+
+impl std::error::Error for EditError {}
+
+/// Block changes batched to be applied together, such as by a fill command or a creative tool.
+/// Every chunk the edit touches is locked before any block changes, so other threads never see half an edit.
+/// Changed chunks mark their sections for remeshing as usual, and the world relights once for the whole edit
+/// rather than once per block.
+/// ```
+/// # use shared::engine::world::{World, chunk::Chunk, edit::{EditError, WorldEdit}};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// let mut edit = WorldEdit::new();
+/// edit.fill(BlockPos::new(0, 0, 0), BlockPos::new(3, 0, 3), 1);
+/// edit.set(BlockPos::new(0, 0, 0), 2);
+/// let transaction = edit.apply(&world, None).unwrap();
+/// assert_eq!(transaction.len(), 16);
+/// assert_eq!(world.get_block(BlockPos::new(0, 0, 0)), Some(2));
+///
+/// transaction.undo(&world, None).unwrap();
+/// assert_eq!(world.get_block(BlockPos::new(3, 0, 3)), Some(0));
+/// transaction.redo(&world, None).unwrap();
+/// assert_eq!(world.get_block(BlockPos::new(3, 0, 3)), Some(1));
+///
+/// // Edits reaching an unloaded chunk change nothing.
+/// let mut edit = WorldEdit::new();
+/// edit.fill(BlockPos::new(31, 0, 0), BlockPos::new(32, 0, 0), 3);
+/// assert_eq!(edit.apply(&world, None).unwrap_err(), EditError::Unloaded(ChunkPos::new(1, 0, 0)));
+/// assert_eq!(world.get_block(BlockPos::new(31, 0, 0)), Some(0));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct WorldEdit {
+    blocks: BTreeMap<BlockPos, BlockId>
+}
+
+impl WorldEdit {
+    pub fn new() -> WorldEdit {
+        return WorldEdit::default();
+    }
+
+    /// Set a block, replacing anything this edit set it to before.
+    pub fn set(&mut self, pos: BlockPos, id: BlockId) {
+        self.blocks.insert(pos, id);
+    }
+
+    /// Set every block in the box between two corners, inclusive.
+    pub fn fill(&mut self, from: BlockPos, to: BlockPos, id: BlockId) {
+        for x in from.x.min(to.x)..=from.x.max(to.x) {
+            for y in from.y.min(to.y)..=from.y.max(to.y) {
+                for z in from.z.min(to.z)..=from.z.max(to.z) {
+                    self.blocks.insert(BlockPos::new(x, y, z), id);
+                }
+            }
+        }
+    }
+
+    /// Blocks the edit sets, whether or not they'll change.
+    pub fn len(&self) -> usize {
+        return self.blocks.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.blocks.is_empty();
+    }
+
+    /// Apply the edit, relighting with lighting if given, returning the blocks it changed so it can be undone.
+    pub fn apply(self, world: &World, lighting: Option<&LightingFn>) -> Result<EditTransaction, EditError> {
+        let changes = write(world, self.blocks.into_iter().collect(), lighting)?;
+        return Ok(EditTransaction { changes });
+    }
+}
+
+/// An applied edit, recording each block it changed so it can be undone and redone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EditTransaction {
+    changes: Vec<BlockChange>
+}
+
+impl EditTransaction {
+    pub fn changes(&self) -> &[BlockChange] {
+        return &self.changes;
+    }
+
+    /// Blocks the edit changed. Blocks it set to what they already were aren't counted.
+    pub fn len(&self) -> usize {
+        return self.changes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.changes.is_empty();
+    }
+
+    /// Put back every block the edit changed, even ones changed again since.
+    pub fn undo(&self, world: &World, lighting: Option<&LightingFn>) -> Result<(), EditError> {
+        write(world, self.changes.iter().map(|change| (change.pos, change.before)).collect(), lighting)?;
+        return Ok(());
+    }
+
+    /// Change every block back to what the edit set it to.
+    pub fn redo(&self, world: &World, lighting: Option<&LightingFn>) -> Result<(), EditError> {
+        write(world, self.changes.iter().map(|change| (change.pos, change.after)).collect(), lighting)?;
+        return Ok(());
+    }
+}
+
+/// Set blocks atomically, publishing BlockChanged for each that changed and relighting them together.
+fn write(world: &World, blocks: Vec<(BlockPos, BlockId)>, lighting: Option<&LightingFn>) -> Result<Vec<BlockChange>, EditError> {
+    let mut by_chunk: BTreeMap<ChunkPos, Vec<(BlockPos, BlockId)>> = BTreeMap::new();
+    for (pos, id) in blocks {
+        by_chunk.entry(pos.chunk()).or_default().push((pos, id));
+    }
+    let mut chunks = Vec::with_capacity(by_chunk.len());
+    for pos in by_chunk.keys() {
+        chunks.push(world.chunk(*pos).ok_or(EditError::Unloaded(*pos))?);
+    }
+    let mut changes = Vec::new();
+    {
+        // Locked in position order, so two edits locking the same chunks can't deadlock.
+        let mut locks: Vec<_> = chunks.iter().map(|chunk| chunk.write().unwrap()).collect();
+        for (chunk, blocks) in locks.iter_mut().zip(by_chunk.values()) {
+            for (pos, id) in blocks.iter() {
+                let before = chunk.set_block(pos.local(), *id);
+                if before != *id {
+                    changes.push(BlockChange { pos: *pos, before, after: *id });
+                }
+            }
+        }
+    }
+    if let Some(events) = world.events() {
+        for change in changes.iter() {
+            events.publish(BlockChanged { pos: change.pos, old: change.before, new: change.after });
+        }
+    }
+    if let Some(lighting) = lighting {
+        update_blocks(world, &changes.iter().map(|change| change.pos).collect::<Vec<_>>(), lighting);
+    }
+    return Ok(changes);
+}
+
+/// Edits that can be undone, newest last, and those undone that can be redone. Applying a new edit forgets
+/// what could be redone.
+/// ```
+/// # use shared::engine::world::{World, chunk::Chunk, edit::{EditHistory, WorldEdit}};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// let mut history = EditHistory::new(2);
+/// for id in 1..=3 {
+///     let mut edit = WorldEdit::new();
+///     edit.set(BlockPos::new(1, 1, 1), id);
+///     history.apply(&world, edit, None).unwrap();
+/// }
+/// assert_eq!(history.undo(&world, None), Ok(Some(1)));
+/// assert_eq!(world.get_block(BlockPos::new(1, 1, 1)), Some(2));
+/// assert_eq!(history.undo(&world, None), Ok(Some(1)));
+/// // Only the last 2 edits were kept.
+/// assert_eq!(history.undo(&world, None), Ok(None));
+/// assert_eq!(world.get_block(BlockPos::new(1, 1, 1)), Some(1));
+/// assert_eq!(history.redo(&world, None), Ok(Some(1)));
+/// assert_eq!(world.get_block(BlockPos::new(1, 1, 1)), Some(2));
+/// ```
+pub struct EditHistory {
+    undo: VecDeque<EditTransaction>,
+    redo: Vec<EditTransaction>,
+    limit: usize
+}
+
+impl EditHistory {
+    /// A history keeping up to limit edits to undo.
+    pub fn new(limit: usize) -> EditHistory {
+        return EditHistory { undo: VecDeque::new(), redo: Vec::new(), limit };
+    }
+
+    /// Apply an edit and remember it for undoing, returning the number of blocks it changed.
+    /// Edits that change nothing aren't remembered.
+    pub fn apply(&mut self, world: &World, edit: WorldEdit, lighting: Option<&LightingFn>) -> Result<usize, EditError> {
+        let transaction = edit.apply(world, lighting)?;
+        let changed = transaction.len();
+        if !transaction.is_empty() {
+            self.push(transaction);
+        }
+        return Ok(changed);
+    }
+
+    /// Remember an edit already applied, forgetting the oldest edit when over the limit.
+    pub fn push(&mut self, transaction: EditTransaction) {
+        self.redo.clear();
+        self.undo.push_back(transaction);
+        while self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Undo the newest edit, returning the number of blocks it put back. None if there's nothing to undo.
+    pub fn undo(&mut self, world: &World, lighting: Option<&LightingFn>) -> Result<Option<usize>, EditError> {
+        let Some(transaction) = self.undo.pop_back() else {
+            return Ok(None);
+        };
+        if let Err(error) = transaction.undo(world, lighting) {
+            self.undo.push_back(transaction);
+            return Err(error);
+        }
+        let changed = transaction.len();
+        self.redo.push(transaction);
+        return Ok(Some(changed));
+    }
+
+    /// Redo the edit undone last, returning the number of blocks it changed. None if there's nothing to redo.
+    pub fn redo(&mut self, world: &World, lighting: Option<&LightingFn>) -> Result<Option<usize>, EditError> {
+        let Some(transaction) = self.redo.pop() else {
+            return Ok(None);
+        };
+        if let Err(error) = transaction.redo(world, lighting) {
+            self.redo.push(transaction);
+            return Err(error);
+        }
+        let changed = transaction.len();
+        self.undo.push_back(transaction);
+        return Ok(Some(changed));
+    }
+
+    pub fn can_undo(&self) -> bool {
+        return !self.undo.is_empty();
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return !self.redo.is_empty();
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> EditHistory {
+        return EditHistory::new(DEFAULT_HISTORY_LIMIT);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,149 @@
+use std::{collections::HashSet, fmt, io, sync::{Arc, Mutex}};
+
+use crate::engine::{job::{system::JobSystem, future::{JobFuture, WithinJobFuture}}, math::coords::ChunkPos};
+
+use super::chunk::Chunk;
+
+/// Reads saved chunks. Called from jobs on the IO job system.
+pub trait ChunkStorage: Send + Sync {
+    /// Read and decode a chunk. Ok(None) if the chunk has never been saved.
+    fn read(&self, pos: ChunkPos) -> io::Result<Option<Chunk>>;
+}
+
+/// Creates chunks that have never been saved. Called from jobs on the compute job system.
+pub trait ChunkGenerator: Send + Sync {
+    fn generate(&self, pos: ChunkPos) -> Chunk;
+}
+
+impl<F> ChunkGenerator for F
+where F: Fn(ChunkPos) -> Chunk + Send + Sync {
+    fn generate(&self, pos: ChunkPos) -> Chunk {
+        return self(pos);
+    }
+}
+
+/// Storage with nothing saved, so every chunk is generated.
+pub struct NoChunkStorage;
+
+impl ChunkStorage for NoChunkStorage {
+    fn read(&self, _pos: ChunkPos) -> io::Result<Option<Chunk>> {
+        return Ok(None);
+    }
+}
+
+/// Where a loaded chunk came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkOrigin {
+    Loaded,
+    /// The chunk was newly generated, and should be saved.
+    Generated
+}
+
+#[derive(Debug)]
+pub struct LoadedChunk {
+    pub chunk: Chunk,
+    pub origin: ChunkOrigin
+}
+
+/// A chunk that exists on disk but couldn't be read. It is not regenerated, as that would overwrite the saved chunk.
+#[derive(Debug)]
+pub struct ChunkLoadError {
+    pub pos: ChunkPos,
+    pub error: io::Error
+}
+
+impl fmt::Display for ChunkLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "failed to load chunk ({}, {}, {}): {}", self.pos.x, self.pos.y, self.pos.z, self.error);
+    }
+}
+
+impl std::error::Error for ChunkLoadError {}
+
+pub type ChunkLoadResult = Result<LoadedChunk, ChunkLoadError>;
+
+/// Loads chunks asynchronously on the job system.
+/// Each request first reads the chunk on the IO job system, which spends most of its time blocked on disk,
+/// and only if it was never saved generates it on the compute job system, which should have a thread per core.
+/// Both may be the same job system.
+pub struct ChunkLoader {
+    io: Arc<JobSystem>,
+    compute: Arc<JobSystem>,
+    storage: Arc<dyn ChunkStorage>,
+    generator: Arc<dyn ChunkGenerator>,
+    pending: Arc<Mutex<HashSet<ChunkPos>>>
+}
+
+impl ChunkLoader {
+    pub fn new(io: Arc<JobSystem>, compute: Arc<JobSystem>, storage: Arc<dyn ChunkStorage>, generator: Arc<dyn ChunkGenerator>) -> ChunkLoader {
+        return ChunkLoader { io, compute, storage, generator, pending: Arc::new(Mutex::new(HashSet::new())) };
+    }
+
+    /// Start loading a chunk, returning a future for it.
+    /// Requests are not deduplicated, so callers should check is_pending() if the same chunk may be requested twice.
+    /// ```
+    /// # use shared::engine::job::system::JobSystem;
+    /// # use shared::engine::world::{chunk::Chunk, loader::{ChunkLoader, ChunkOrigin, NoChunkStorage}};
+    /// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+    /// # use std::sync::Arc;
+    /// let jobs = Arc::new(JobSystem::new(2));
+    /// let generator = Arc::new(|pos: ChunkPos| Chunk::filled(pos, if pos.y < 0 { 1 } else { 0 }));
+    /// let loader = ChunkLoader::new(jobs.clone(), jobs.clone(), Arc::new(NoChunkStorage), generator);
+    ///
+    /// let loaded = loader.request(ChunkPos::new(0, -1, 0)).wait().unwrap();
+    /// assert_eq!(loaded.origin, ChunkOrigin::Generated);
+    /// assert_eq!(loaded.chunk.get_block(LocalPos::new(0, 0, 0)), 1);
+    /// assert!(!loader.is_pending(ChunkPos::new(0, -1, 0)));
+    /// ```
+    pub fn request(&self, pos: ChunkPos) -> JobFuture<ChunkLoadResult> {
+        let (future, promise) = WithinJobFuture::<ChunkLoadResult>::new();
+        self.pending.lock().unwrap().insert(pos);
+
+        let compute = self.compute.clone();
+        let storage = self.storage.clone();
+        let generator = self.generator.clone();
+        let pending = self.pending.clone();
+        let mut promise = Some(promise);
+        self.io.run_job(move || {
+            let promise = promise.take().unwrap();
+            let pending = pending.clone();
+            match storage.read(pos) {
+                Ok(Some(chunk)) => {
+                    pending.lock().unwrap().remove(&pos);
+                    promise.set(Ok(LoadedChunk { chunk, origin: ChunkOrigin::Loaded }));
+                },
+                Ok(None) => {
+                    let generator = generator.clone();
+                    let mut promise = Some(promise);
+                    compute.run_job(move || {
+                        let chunk = generator.generate(pos);
+                        pending.lock().unwrap().remove(&pos);
+                        promise.take().unwrap().set(Ok(LoadedChunk { chunk, origin: ChunkOrigin::Generated }));
+                    });
+                },
+                Err(error) => {
+                    pending.lock().unwrap().remove(&pos);
+                    promise.set(Err(ChunkLoadError { pos, error }));
+                }
+            }
+        });
+        return future;
+    }
+
+    /// Start loading a chunk, calling a function with the result once it finishes.
+    /// The callback runs on whichever job thread finished the chunk.
+    pub fn request_then<F>(&self, pos: ChunkPos, callback: F)
+    where F: FnOnce(ChunkLoadResult) + 'static {
+        self.request(pos).then(callback);
+    }
+
+    /// Check if a chunk has been requested and hasn't finished loading.
+    pub fn is_pending(&self, pos: ChunkPos) -> bool {
+        return self.pending.lock().unwrap().contains(&pos);
+    }
+
+    /// Number of chunks that have been requested and haven't finished loading.
+    pub fn pending_count(&self) -> usize {
+        return self.pending.lock().unwrap().len();
+    }
+}
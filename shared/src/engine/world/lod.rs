@@ -0,0 +1,279 @@
+use std::{collections::HashSet, sync::Arc};
+
+use crate::engine::{
+    block::{BlockId, AIR},
+    job::{system::JobSystem, future::JobFuture},
+    math::coords::{ChunkPos, LocalPos, CHUNK_SIZE},
+    mesh::{greedy::{greedy_mesh, ChunkBorders, OpacityFn}, vertex::MeshData}
+};
+
+use super::chunk::Chunk;
+
+/// Coarsest level of detail. Level n covers 2^n chunks along each axis at 1/2^n the resolution, so 3 is 8x.
+pub const MAX_LOD_LEVEL: u8 = 3;
+
+const SIZE: i32 = CHUNK_SIZE;
+
+/// A node of the terrain LOD octree. Level 0 nodes are ordinary chunks, and each level above covers
+/// twice as many chunks along each axis, with coordinates in units of its own size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LodPos {
+    pub level: u8,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32
+}
+
+impl LodPos {
+    pub fn new(level: u8, x: i32, y: i32, z: i32) -> LodPos {
+        return LodPos { level, x, y, z };
+    }
+
+    /// The node at a level containing a chunk.
+    /// ```
+    /// # use shared::engine::world::lod::LodPos;
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// assert_eq!(LodPos::containing(ChunkPos::new(5, -1, 8), 2), LodPos::new(2, 1, -1, 2));
+    /// assert_eq!(LodPos::new(2, 1, -1, 2).min_chunk(), ChunkPos::new(4, -4, 8));
+    /// ```
+    pub fn containing(chunk: ChunkPos, level: u8) -> LodPos {
+        let size = 1 << level;
+        return LodPos::new(level, chunk.x.div_euclid(size), chunk.y.div_euclid(size), chunk.z.div_euclid(size));
+    }
+
+    /// Number of chunks the node covers along each axis.
+    pub fn chunks_per_axis(&self) -> i32 {
+        return 1 << self.level;
+    }
+
+    /// Blocks covered by each cell of the node's grid, along each axis.
+    pub fn scale(&self) -> i32 {
+        return 1 << self.level;
+    }
+
+    pub fn min_chunk(&self) -> ChunkPos {
+        let size = self.chunks_per_axis();
+        return ChunkPos::new(self.x * size, self.y * size, self.z * size);
+    }
+
+    pub fn contains(&self, chunk: ChunkPos) -> bool {
+        return LodPos::containing(chunk, self.level) == *self;
+    }
+
+    /// The 8 nodes one level finer covering the same space. Empty for level 0.
+    pub fn children(&self) -> Vec<LodPos> {
+        if self.level == 0 {
+            return Vec::new();
+        }
+        let mut children = Vec::with_capacity(8);
+        for i in 0..8 {
+            children.push(LodPos::new(self.level - 1, self.x * 2 + (i & 1), self.y * 2 + ((i >> 1) & 1), self.z * 2 + ((i >> 2) & 1)));
+        }
+        return children;
+    }
+
+    /// Distance in nodes of this level, along the furthest axis.
+    fn distance(&self, other: LodPos) -> i32 {
+        return (self.x - other.x).abs().max((self.y - other.y).abs()).max((self.z - other.z).abs());
+    }
+}
+
+/// Downsampled terrain of a LOD node, as a CHUNK_SIZE grid where each cell summarizes scale^3 blocks.
+pub struct LodChunk {
+    pos: LodPos,
+    grid: Chunk
+}
+
+impl LodChunk {
+    pub fn new(pos: LodPos) -> LodChunk {
+        return LodChunk { pos, grid: Chunk::new(ChunkPos::new(pos.x, pos.y, pos.z)) };
+    }
+
+    pub fn pos(&self) -> LodPos {
+        return self.pos;
+    }
+
+    /// Cell of the grid, see LodPos::scale for the blocks each covers.
+    pub fn get(&self, local: LocalPos) -> BlockId {
+        return self.grid.get_block(local);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.grid.is_empty();
+    }
+
+    /// Downsample a full detail chunk into its part of the grid. Chunks outside the node are ignored.
+    /// Each cell becomes the most common solid block it covers, or air if it is mostly air.
+    /// ```
+    /// # use shared::engine::world::{chunk::Chunk, lod::{LodChunk, LodPos}};
+    /// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+    /// let mut chunk = Chunk::new(ChunkPos::new(1, 0, 0));
+    /// for x in 0..32 {
+    ///     for z in 0..32 {
+    ///         chunk.set_block(LocalPos::new(x, 0, z), 1);
+    ///         chunk.set_block(LocalPos::new(x, 1, z), 2);
+    ///     }
+    /// }
+    /// let mut lod = LodChunk::new(LodPos::new(1, 0, 0, 0));
+    /// lod.add_chunk(&chunk);
+    /// // Cells of 2x2x2 blocks, with the chunk in the upper x half of the node.
+    /// assert!(lod.get(LocalPos::new(16, 0, 0)) != 0);
+    /// assert_eq!(lod.get(LocalPos::new(16, 1, 0)), 0);
+    /// assert_eq!(lod.get(LocalPos::new(0, 0, 0)), 0);
+    /// ```
+    pub fn add_chunk(&mut self, chunk: &Chunk) {
+        if !self.pos.contains(chunk.pos()) {
+            return;
+        }
+        let offset = chunk.pos() - self.pos.min_chunk();
+        let factor = self.pos.scale();
+        self.downsample(offset * (SIZE / factor), factor, |x, y, z| chunk.get_block(LocalPos::new(x as u8, y as u8, z as u8)));
+    }
+
+    /// Downsample a node one level finer into its part of the grid, so coarse levels can be built from finer ones
+    /// without revisiting every chunk. Nodes that aren't children of this one are ignored.
+    pub fn add_child(&mut self, child: &LodChunk) {
+        if child.pos.level + 1 != self.pos.level || !self.pos.children().contains(&child.pos) {
+            return;
+        }
+        let offset = ChunkPos::new(child.pos.x - self.pos.x * 2, child.pos.y - self.pos.y * 2, child.pos.z - self.pos.z * 2);
+        self.downsample(offset * (SIZE / 2), 2, |x, y, z| child.get(LocalPos::new(x as u8, y as u8, z as u8)));
+    }
+
+    /// Fill the cells of a SIZE / factor cube of the grid starting at start, from a full SIZE cube of source blocks.
+    fn downsample<F>(&mut self, start: ChunkPos, factor: i32, source: F)
+    where F: Fn(i32, i32, i32) -> BlockId {
+        let cells = SIZE / factor;
+        let mut counts: Vec<(BlockId, u32)> = Vec::new();
+        for y in 0..cells {
+            for z in 0..cells {
+                for x in 0..cells {
+                    counts.clear();
+                    let mut solid = 0;
+                    for dy in 0..factor {
+                        for dz in 0..factor {
+                            for dx in 0..factor {
+                                let id = source(x * factor + dx, y * factor + dy, z * factor + dz);
+                                if id == AIR {
+                                    continue;
+                                }
+                                solid += 1;
+                                match counts.iter_mut().find(|(counted, _)| *counted == id) {
+                                    Some((_, count)) => *count += 1,
+                                    None => counts.push((id, 1))
+                                }
+                            }
+                        }
+                    }
+                    // Half full cells stay solid, so thin floors and walls don't vanish in the distance.
+                    let id = if solid * 2 >= factor * factor * factor {
+                        counts.iter().max_by_key(|(_, count)| *count).map(|(id, _)| *id).unwrap_or(AIR)
+                    } else {
+                        AIR
+                    };
+                    let local = LocalPos::new((start.x + x) as u8, (start.y + y) as u8, (start.z + z) as u8);
+                    self.grid.set_block(local, id);
+                }
+            }
+        }
+    }
+
+    /// Mesh the grid, with positions in blocks relative to the node's minimum corner.
+    /// Faces on the node's edges are always drawn, as neighbors may be at a different level.
+    pub fn mesh(&self, opaque: &OpacityFn) -> MeshData {
+        let mut mesh = greedy_mesh(&self.grid, &ChunkBorders::empty(), opaque);
+        let scale = self.pos.scale() as f32;
+        for vertex in mesh.vertices.iter_mut() {
+            for axis in vertex.position.iter_mut() {
+                *axis *= scale;
+            }
+            for axis in vertex.uv.iter_mut() {
+                *axis *= scale;
+            }
+        }
+        return mesh;
+    }
+}
+
+/// Queue a job meshing a LOD node.
+pub fn queue_lod_mesh_job(jobs: &JobSystem, lod: Arc<LodChunk>, opaque: Arc<OpacityFn>) -> JobFuture<MeshData> {
+    return jobs.run_job(move || lod.mesh(&*opaque));
+}
+
+/// Nodes that were added and removed after the viewer moved.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LodChanges {
+    pub added: Vec<LodPos>,
+    pub removed: Vec<LodPos>
+}
+
+/// Picks the LOD node for every part of the terrain around the viewer, as distance bands.
+/// Starting from the coarsest nodes in view, any node within detail_radius nodes of the viewer's node of the same level
+/// is split into its finer children, so detail halves with each band further out, and the nodes never overlap or leave gaps.
+pub struct TerrainLod {
+    detail_radius: i32,
+    view_radius: i32,
+    selected: HashSet<LodPos>
+}
+
+impl TerrainLod {
+    /// view_radius is in nodes of MAX_LOD_LEVEL. Will panic in debug mode if detail_radius is negative.
+    pub fn new(detail_radius: i32, view_radius: i32) -> TerrainLod {
+        debug_assert!(detail_radius >= 0, "LOD detail radius cannot be negative");
+        return TerrainLod { detail_radius, view_radius, selected: HashSet::new() };
+    }
+
+    /// Minimum view distance in chunks along each axis, which is further depending on where the viewer is within their coarsest node.
+    pub fn view_distance(&self) -> i32 {
+        return self.view_radius << MAX_LOD_LEVEL;
+    }
+
+    /// Every node to show for a viewer in a chunk.
+    /// ```
+    /// # use shared::engine::world::lod::{TerrainLod, LodPos};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let lod = TerrainLod::new(1, 2);
+    /// let nodes = lod.select(ChunkPos::new(0, 0, 0));
+    /// assert!(nodes.contains(&LodPos::new(0, 0, 0, 0)));
+    /// assert!(nodes.contains(&LodPos::new(3, 2, 2, 2)));
+    /// assert!(!nodes.contains(&LodPos::new(3, 0, 0, 0)));
+    /// ```
+    pub fn select(&self, viewer: ChunkPos) -> Vec<LodPos> {
+        let mut selected = Vec::new();
+        let center = LodPos::containing(viewer, MAX_LOD_LEVEL);
+        let mut stack = Vec::new();
+        for y in -self.view_radius..=self.view_radius {
+            for z in -self.view_radius..=self.view_radius {
+                for x in -self.view_radius..=self.view_radius {
+                    stack.push(LodPos::new(MAX_LOD_LEVEL, center.x + x, center.y + y, center.z + z));
+                }
+            }
+        }
+        while let Some(node) = stack.pop() {
+            if node.level > 0 && node.distance(LodPos::containing(viewer, node.level)) <= self.detail_radius {
+                stack.extend(node.children());
+            } else {
+                selected.push(node);
+            }
+        }
+        selected.sort_unstable();
+        return selected;
+    }
+
+    /// Select nodes for the viewer's new position, returning what changed since the last update.
+    pub fn update(&mut self, viewer: ChunkPos) -> LodChanges {
+        let selected: HashSet<LodPos> = self.select(viewer).into_iter().collect();
+        let mut changes = LodChanges {
+            added: selected.difference(&self.selected).copied().collect(),
+            removed: self.selected.difference(&selected).copied().collect()
+        };
+        changes.added.sort_unstable();
+        changes.removed.sort_unstable();
+        self.selected = selected;
+        return changes;
+    }
+
+    pub fn selected(&self) -> &HashSet<LodPos> {
+        return &self.selected;
+    }
+}
@@ -0,0 +1,15 @@
+pub mod palette;
+pub mod chunk;
+pub mod loader;
+pub mod container;
+pub mod lod;
+pub mod universe;
+pub mod tick;
+pub mod raycast;
+pub mod spatial;
+pub mod block_entity;
+pub mod time;
+pub mod edit;
+pub mod schematic;
+
+pub use container::World;
\ No newline at end of file
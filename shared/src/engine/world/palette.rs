@@ -0,0 +1,307 @@
+use serde::{Serialize, Deserialize};
+
+use crate::engine::block::{BlockId, AIR};
+
+/// Number of blocks along each axis of a section.
+pub const SECTION_SIZE: usize = 16;
+/// Number of blocks in a section.
+pub const SECTION_VOLUME: usize = SECTION_SIZE * SECTION_SIZE * SECTION_SIZE;
+
+/// Entry size used once a section holds more unique blocks than an 8 bit palette can index.
+/// Entries are then the block ids themselves, and the palette is unused.
+const DIRECT_BITS: u8 = 16;
+
+/// Block storage for a 16x16x16 section of a chunk.
+/// Rather than a u16 per block, each block stores a small index into a palette of the unique blocks in the section.
+/// Index width grows through 0, 1, 2, 4, and 8 bits as more unique blocks are added, so a section of
+/// a few block types uses a fraction of the memory. Widths divide 64, so entries never straddle words.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PalettedSection {
+    palette: Vec<BlockId>,
+    bits: u8,
+    data: Vec<u64>,
+    non_air: u16
+}
+
+impl PalettedSection {
+    /// Create a section with every block set to one id. Takes no entry storage until a different block is set.
+    /// ```
+    /// # use shared::engine::world::palette::PalettedSection;
+    /// let section = PalettedSection::new(5);
+    /// assert_eq!(section.get(100), 5);
+    /// assert_eq!(section.bits_per_entry(), 0);
+    /// ```
+    pub fn new(fill: BlockId) -> PalettedSection {
+        let non_air = if fill == AIR { 0 } else { SECTION_VOLUME as u16 };
+        return PalettedSection { palette: vec![fill], bits: 0, data: Vec::new(), non_air };
+    }
+
+    /// Block id at a section index, where X varies fastest, then Z, then Y.
+    pub fn get(&self, index: usize) -> BlockId {
+        debug_assert!(index < SECTION_VOLUME, "Section index out of range");
+        return match self.bits {
+            0 => self.palette[0],
+            DIRECT_BITS => self.read(index) as BlockId,
+            _ => self.palette[self.read(index)]
+        };
+    }
+
+    /// Set the block id at a section index, returning the previous id.
+    /// The palette grows, widening every entry, when a new unique block no longer fits.
+    /// ```
+    /// # use shared::engine::world::palette::PalettedSection;
+    /// let mut section = PalettedSection::new(0);
+    /// section.set(0, 1);
+    /// assert_eq!(section.bits_per_entry(), 1);
+    /// section.set(1, 2);
+    /// assert_eq!(section.bits_per_entry(), 2);
+    /// for id in 3..20 {
+    ///     section.set(id as usize, id);
+    /// }
+    /// assert_eq!(section.bits_per_entry(), 8);
+    /// assert_eq!(section.get(0), 1);
+    /// assert_eq!(section.get(19), 19);
+    /// assert_eq!(section.get(4000), 0);
+    /// ```
+    pub fn set(&mut self, index: usize, id: BlockId) -> BlockId {
+        let old = self.get(index);
+        if old == id {
+            return old;
+        }
+        if old == AIR {
+            self.non_air += 1;
+        } else if id == AIR {
+            self.non_air -= 1;
+        }
+
+        if self.bits == DIRECT_BITS {
+            self.write(index, id as usize);
+            return old;
+        }
+        let palette_index = match self.palette.iter().position(|entry| *entry == id) {
+            Some(palette_index) => palette_index,
+            None => {
+                if self.palette.len() == 1 << self.bits {
+                    self.grow();
+                    if self.bits == DIRECT_BITS {
+                        self.write(index, id as usize);
+                        return old;
+                    }
+                }
+                self.palette.push(id);
+                self.palette.len() - 1
+            }
+        };
+        self.write(index, palette_index);
+        return old;
+    }
+
+    /// Rebuild a section from the parts returned by palette(), bits_per_entry(), and data(), such as when decoding
+    /// a saved or received chunk. None if the parts are inconsistent, so corrupt data can't cause out of bounds reads.
+    /// ```
+    /// # use shared::engine::world::palette::PalettedSection;
+    /// let mut section = PalettedSection::new(0);
+    /// section.set(10, 4);
+    /// let copy = PalettedSection::from_raw(section.palette().to_vec(), section.bits_per_entry(), section.data().to_vec()).unwrap();
+    /// assert_eq!(copy, section);
+    /// assert!(PalettedSection::from_raw(vec![0, 1], 3, vec![]).is_none());
+    /// ```
+    pub fn from_raw(palette: Vec<BlockId>, bits: u8, data: Vec<u64>) -> Option<PalettedSection> {
+        let valid_layout = match bits {
+            0 => palette.len() == 1,
+            1 | 2 | 4 | 8 => !palette.is_empty() && palette.len() <= 1 << bits,
+            DIRECT_BITS => palette.is_empty(),
+            _ => false
+        };
+        if !valid_layout || data.len() != Self::word_count(bits) {
+            return None;
+        }
+        let mut section = PalettedSection { palette, bits, data, non_air: 0 };
+        if bits != 0 && bits != DIRECT_BITS && (0..SECTION_VOLUME).any(|index| section.read(index) >= section.palette.len()) {
+            return None;
+        }
+        section.non_air = (0..SECTION_VOLUME).filter(|index| section.get(*index) != AIR).count() as u16;
+        return Some(section);
+    }
+
+    /// Packed entries, least significant bits first within each word. Empty when the section is uniform.
+    pub fn data(&self) -> &[u64] {
+        return &self.data;
+    }
+
+    /// Set every block in the section, releasing all entry storage.
+    pub fn fill(&mut self, id: BlockId) {
+        *self = PalettedSection::new(id);
+    }
+
+    /// Number of bits each block currently uses. 0 means every block is the same.
+    pub fn bits_per_entry(&self) -> u8 {
+        return self.bits;
+    }
+
+    /// Unique block ids that are, or were, in the section. Empty when entries are stored directly.
+    /// May contain ids no longer used until compact() is called.
+    pub fn palette(&self) -> &[BlockId] {
+        return &self.palette;
+    }
+
+    /// Number of blocks that are not air.
+    pub fn non_air_count(&self) -> usize {
+        return self.non_air as usize;
+    }
+
+    /// Check if the section is entirely air, so meshing and ticking can skip it.
+    pub fn is_empty(&self) -> bool {
+        return self.non_air == 0;
+    }
+
+    /// Check if every block in the section has the same id.
+    pub fn is_uniform(&self) -> bool {
+        return self.bits == 0;
+    }
+
+    /// Approximate bytes used by the section, including its own size.
+    /// ```
+    /// # use shared::engine::world::palette::{PalettedSection, SECTION_VOLUME};
+    /// let mut section = PalettedSection::new(0);
+    /// let uniform = section.memory_usage();
+    /// section.set(0, 1);
+    /// // 1 bit per block.
+    /// assert_eq!(section.memory_usage(), uniform + SECTION_VOLUME / 8 + 2);
+    /// ```
+    pub fn memory_usage(&self) -> usize {
+        return std::mem::size_of::<PalettedSection>()
+            + self.palette.len() * std::mem::size_of::<BlockId>()
+            + self.data.len() * std::mem::size_of::<u64>();
+    }
+
+    /// Rebuild the palette with only the ids still in use, shrinking entries to the smallest width that fits.
+    /// Palettes only grow while blocks are edited, so this is done before saving or sending a section.
+    /// ```
+    /// # use shared::engine::world::palette::PalettedSection;
+    /// let mut section = PalettedSection::new(0);
+    /// for id in 1..10 {
+    ///     section.set(0, id);
+    /// }
+    /// assert_eq!(section.bits_per_entry(), 4);
+    /// section.compact();
+    /// assert_eq!(section.bits_per_entry(), 1);
+    /// assert_eq!(section.palette(), &[9, 0]);
+    /// assert_eq!(section.get(0), 9);
+    /// ```
+    pub fn compact(&mut self) {
+        let ids: Vec<BlockId> = (0..SECTION_VOLUME).map(|index| self.get(index)).collect();
+        let mut palette: Vec<BlockId> = Vec::new();
+        for id in ids.iter() {
+            if !palette.contains(id) {
+                palette.push(*id);
+                if palette.len() > 256 {
+                    // Too many unique blocks for a palette, nothing to shrink.
+                    return;
+                }
+            }
+        }
+        let bits = Self::bits_for(palette.len());
+        self.bits = bits;
+        self.palette = palette;
+        self.data = vec![0; Self::word_count(bits)];
+        if bits == 0 {
+            return;
+        }
+        for (index, id) in ids.iter().enumerate() {
+            let palette_index = self.palette.iter().position(|entry| entry == id).unwrap();
+            self.write(index, palette_index);
+        }
+    }
+
+    /// Change every block id in the section, such as when the save was written with a different block registry.
+    /// Only the palette is rewritten unless entries are stored directly.
+    /// ```
+    /// # use shared::engine::world::palette::PalettedSection;
+    /// let mut section = PalettedSection::new(0);
+    /// section.set(0, 1);
+    /// section.set(1, 2);
+    /// section.remap(|id| if id == 2 { 0 } else { id + 3 });
+    /// assert_eq!((section.get(0), section.get(1), section.get(2)), (4, 0, 3));
+    /// assert_eq!(section.non_air_count(), 4095);
+    /// ```
+    pub fn remap(&mut self, map: impl Fn(BlockId) -> BlockId) {
+        if self.bits == DIRECT_BITS {
+            for index in 0..SECTION_VOLUME {
+                let id = map(self.read(index) as BlockId);
+                self.write(index, id as usize);
+            }
+        } else {
+            for id in self.palette.iter_mut() {
+                *id = map(*id);
+            }
+        }
+        self.non_air = (0..SECTION_VOLUME).filter(|index| self.get(*index) != AIR).count() as u16;
+    }
+
+    /// Smallest entry width able to index a palette of a given length.
+    fn bits_for(palette_len: usize) -> u8 {
+        return match palette_len {
+            0..=1 => 0,
+            2 => 1,
+            3..=4 => 2,
+            5..=16 => 4,
+            17..=256 => 8,
+            _ => DIRECT_BITS
+        };
+    }
+
+    fn word_count(bits: u8) -> usize {
+        return SECTION_VOLUME * bits as usize / 64;
+    }
+
+    /// Double the entry width, re-encoding every entry. Past 8 bits, entries become block ids directly.
+    fn grow(&mut self) {
+        let new_bits = match self.bits {
+            0 => 1,
+            8 => DIRECT_BITS,
+            bits => bits * 2
+        };
+        let ids: Vec<BlockId> = (0..SECTION_VOLUME).map(|index| self.get(index)).collect();
+        let old_palette_indices: Vec<usize> = if self.bits == 0 {
+            vec![0; SECTION_VOLUME]
+        } else {
+            (0..SECTION_VOLUME).map(|index| self.read(index)).collect()
+        };
+        self.bits = new_bits;
+        self.data = vec![0; Self::word_count(new_bits)];
+        if new_bits == DIRECT_BITS {
+            self.palette.clear();
+            for (index, id) in ids.iter().enumerate() {
+                self.write(index, *id as usize);
+            }
+            return;
+        }
+        for (index, palette_index) in old_palette_indices.iter().enumerate() {
+            self.write(index, *palette_index);
+        }
+    }
+
+    fn read(&self, index: usize) -> usize {
+        let bits = self.bits as usize;
+        let per_word = 64 / bits;
+        let shift = (index % per_word) * bits;
+        let mask = (1u64 << bits) - 1;
+        return ((self.data[index / per_word] >> shift) & mask) as usize;
+    }
+
+    fn write(&mut self, index: usize, value: usize) {
+        let bits = self.bits as usize;
+        let per_word = 64 / bits;
+        let shift = (index % per_word) * bits;
+        let mask = (1u64 << bits) - 1;
+        let word = &mut self.data[index / per_word];
+        *word = (*word & !(mask << shift)) | (((value as u64) & mask) << shift);
+    }
+}
+
+impl Default for PalettedSection {
+    fn default() -> PalettedSection {
+        return PalettedSection::new(AIR);
+    }
+}
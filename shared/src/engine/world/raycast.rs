@@ -0,0 +1,252 @@
+use crate::engine::{
+    block::{BlockId, AIR},
+    entity::{kinematics::Transform, query::Query, EntityId},
+    math::{aabb::Aabb, coords::BlockPos, direction::{Axis, Direction}, ray::Ray, vector::Vec3},
+    physics::RigidBody
+};
+
+use super::World;
+
+/// The first block a ray hit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockHit {
+    pub block: BlockPos,
+    pub id: BlockId,
+    /// Face the ray entered through. None if the ray started inside the block.
+    pub face: Option<Direction>,
+    /// Exact point the ray entered the block.
+    pub point: Vec3,
+    /// Distance along the ray to the point, in multiples of the ray direction's length.
+    pub distance: f32
+}
+
+impl BlockHit {
+    /// Where a block placed against the hit face goes. The hit block itself if the ray started inside it.
+    pub fn adjacent(&self) -> BlockPos {
+        return match self.face {
+            Some(face) => self.block + face.offset(),
+            None => self.block
+        };
+    }
+}
+
+/// The first entity a ray hit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EntityHit {
+    pub entity: EntityId,
+    /// Normal of the face of the entity's box the ray entered through. Zero if the ray started inside it.
+    pub normal: Vec3,
+    /// Exact point the ray entered the entity's box.
+    pub point: Vec3,
+    /// Distance along the ray to the point, in multiples of the ray direction's length.
+    pub distance: f32
+}
+
+/// Whichever block or entity a ray hit first, for attacking and interacting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PickHit {
+    Block(BlockHit),
+    Entity(EntityHit)
+}
+
+impl PickHit {
+    pub fn point(&self) -> Vec3 {
+        return match self {
+            PickHit::Block(hit) => hit.point,
+            PickHit::Entity(hit) => hit.point
+        };
+    }
+
+    pub fn distance(&self) -> f32 {
+        return match self {
+            PickHit::Block(hit) => hit.distance,
+            PickHit::Entity(hit) => hit.distance
+        };
+    }
+}
+
+impl World {
+    /// Find the first non air block along a ray within max_distance, for breaking and placing blocks, or projectiles.
+    /// The ray is in world block coordinates. Unloaded chunks are passed through as if empty.
+    /// ```
+    /// # use shared::engine::world::{World, chunk::Chunk};
+    /// # use shared::engine::math::{coords::{BlockPos, ChunkPos}, direction::Direction, ray::Ray, vector::Vec3};
+    /// let world = World::new();
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    /// world.set_block(BlockPos::new(5, 2, 2), 1);
+    /// let hit = world.raycast(Ray::new(Vec3::new(0.5, 2.5, 2.25), Vec3::X), 10.0).unwrap();
+    /// assert_eq!(hit.block, BlockPos::new(5, 2, 2));
+    /// assert_eq!(hit.face, Some(Direction::NegX));
+    /// assert_eq!(hit.point, Vec3::new(5.0, 2.5, 2.25));
+    /// assert_eq!(hit.distance, 4.5);
+    /// assert_eq!(hit.adjacent(), BlockPos::new(4, 2, 2));
+    /// assert!(world.raycast(Ray::new(Vec3::new(0.5, 2.5, 2.25), Vec3::X), 4.0).is_none());
+    /// ```
+    pub fn raycast(&self, ray: Ray, max_distance: f32) -> Option<BlockHit> {
+        return self.raycast_filtered(ray, max_distance, |id| id != AIR);
+    }
+
+    /// Raycast hitting only blocks the filter accepts, such as to pass through fluids.
+    pub fn raycast_filtered<F>(&self, ray: Ray, max_distance: f32, hits: F) -> Option<BlockHit>
+    where F: Fn(BlockId) -> bool {
+        return traverse(ray, max_distance, |block, face, distance| {
+            let id = self.get_block(block).filter(|id| hits(*id))?;
+            return Some(BlockHit { block, id, face, point: ray.at(distance), distance });
+        });
+    }
+
+    /// Raycast hitting the collision boxes of blocks, so rays pass over slabs and through flowers and fluids,
+    /// rather than hitting every non air block as a full cube.
+    /// ```
+    /// # use shared::engine::world::{World, chunk::Chunk};
+    /// # use shared::engine::math::{coords::{BlockPos, ChunkPos}, direction::Direction, ray::Ray, vector::Vec3};
+    /// # use shared::engine::physics::shape::{BlockShapes, CollisionShape};
+    /// # use std::sync::Arc;
+    /// let world = World::new().with_block_shapes(Arc::new(BlockShapes::new().with_shape(2, CollisionShape::slab())));
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    /// world.set_block(BlockPos::new(3, 2, 2), 2);
+    /// world.set_block(BlockPos::new(5, 2, 2), 1);
+    /// let ray = Ray::new(Vec3::new(0.5, 2.75, 2.5), Vec3::X);
+    /// let hit = world.raycast_collision(ray, 10.0).unwrap();
+    /// assert_eq!(hit.block, BlockPos::new(5, 2, 2));
+    /// assert_eq!(hit.face, Some(Direction::NegX));
+    ///
+    /// // Lower down, the slab is in the way.
+    /// let hit = world.raycast_collision(Ray::new(Vec3::new(0.5, 2.25, 2.5), Vec3::X), 10.0).unwrap();
+    /// assert_eq!(hit.block, BlockPos::new(3, 2, 2));
+    /// assert_eq!(hit.distance, 2.5);
+    /// ```
+    pub fn raycast_collision(&self, ray: Ray, max_distance: f32) -> Option<BlockHit> {
+        let shapes = self.block_shapes();
+        let mut nearest: Option<BlockHit> = None;
+        traverse(ray, max_distance, |cell, _, distance| {
+            // Every block after this one is further than the hit already found.
+            if nearest.is_some_and(|hit| hit.distance <= distance) {
+                return Some(());
+            }
+            // Boxes of the block below can reach up into this one, as fences do.
+            for block in [cell, cell - BlockPos::new(0, 1, 0)] {
+                let Some(id) = self.get_block(block) else {
+                    continue;
+                };
+                let corner = Vec3::new(block.x as f32, block.y as f32, block.z as f32);
+                for shape in shapes.boxes(id).iter().filter(|shape| block == cell || shape.max.y > 1.0) {
+                    let Some(hit) = ray.intersect_aabb(&shape.translate(corner), max_distance) else {
+                        continue;
+                    };
+                    if nearest.is_none_or(|nearest| hit.distance < nearest.distance) {
+                        let face = (hit.normal != Vec3::ZERO).then(|| Direction::nearest(hit.normal));
+                        nearest = Some(BlockHit { block, id, face, point: ray.at(hit.distance), distance: hit.distance });
+                    }
+                }
+            }
+            return None;
+        });
+        return nearest;
+    }
+
+    /// Find the first block or entity along a ray within max_distance, as targeted by a player's crosshair,
+    /// or checked by the server when a player attacks or interacts. Blocks are hit by their collision boxes,
+    /// entities by the boxes of their RigidBody, and the entity casting the ray, if any, is skipped so a player
+    /// never picks themselves.
+    /// ```
+    /// # use shared::engine::world::{World, chunk::Chunk, raycast::PickHit};
+    /// # use shared::engine::entity::kinematics::Transform;
+    /// # use shared::engine::math::{coords::{BlockPos, ChunkPos, WorldPos}, ray::Ray, vector::Vec3};
+    /// # use shared::engine::physics::RigidBody;
+    /// let world = World::new();
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    /// world.set_block(BlockPos::new(8, 2, 2), 1);
+    /// let entities = world.entities();
+    /// let player = entities.spawn();
+    /// entities.insert(player, Transform::new(WorldPos::new(0.5, 1.0, 2.5))).unwrap();
+    /// entities.insert(player, RigidBody::new(0.6, 1.8)).unwrap();
+    /// entities.set_chunk(player, ChunkPos::new(0, 0, 0));
+    /// let ray = Ray::new(Vec3::new(0.5, 2.5, 2.5), Vec3::X);
+    /// assert!(matches!(world.pick(ray, 10.0, Some(player)), Some(PickHit::Block(hit)) if hit.block == BlockPos::new(8, 2, 2)));
+    ///
+    /// // A mob in front of the block is hit instead.
+    /// let mob = entities.spawn();
+    /// entities.insert(mob, Transform::new(WorldPos::new(5.0, 2.0, 2.5))).unwrap();
+    /// entities.insert(mob, RigidBody::new(1.0, 1.0)).unwrap();
+    /// entities.set_chunk(mob, ChunkPos::new(0, 0, 0));
+    /// let Some(PickHit::Entity(hit)) = world.pick(ray, 10.0, Some(player)) else { panic!() };
+    /// assert_eq!(hit.entity, mob);
+    /// assert_eq!(hit.distance, 4.0);
+    /// assert_eq!(hit.normal, -Vec3::X);
+    /// ```
+    pub fn pick(&self, ray: Ray, max_distance: f32, caster: Option<EntityId>) -> Option<PickHit> {
+        let block = self.raycast_collision(ray, max_distance);
+        let entity = self.raycast_entities(ray, block.map_or(max_distance, |hit| hit.distance), caster);
+        return match (block, entity) {
+            (_, Some(entity)) => Some(PickHit::Entity(entity)),
+            (Some(block), None) => Some(PickHit::Block(block)),
+            (None, None) => None
+        };
+    }
+
+    /// Find the first entity with a RigidBody along a ray within max_distance, skipping the caster.
+    /// Only entities the registry has placed in chunks near the ray are tested.
+    pub fn raycast_entities(&self, ray: Ray, max_distance: f32, caster: Option<EntityId>) -> Option<EntityHit> {
+        let end = ray.at(max_distance);
+        let bodies = Query::<(&Transform, &RigidBody)>::new(self.entities());
+        let mut nearest: Option<EntityHit> = None;
+        for entity in self.entities_near(&Aabb::new(ray.origin.min(end), ray.origin.max(end))) {
+            if Some(entity) == caster {
+                continue;
+            }
+            let Some(aabb) = bodies.get(entity, |(transform, body)| body.aabb(transform.position)) else {
+                continue;
+            };
+            let reach = nearest.map_or(max_distance, |hit| hit.distance);
+            if let Some(hit) = ray.intersect_aabb(&aabb, reach) {
+                if nearest.is_none_or(|nearest| hit.distance < nearest.distance) {
+                    nearest = Some(EntityHit { entity, normal: hit.normal, point: ray.at(hit.distance), distance: hit.distance });
+                }
+            }
+        }
+        return nearest;
+    }
+}
+
+/// Visit every block a ray passes through in order, with the face it entered through and the distance it entered at,
+/// until the visitor returns something or the ray passes max_distance.
+fn traverse<R, F>(ray: Ray, max_distance: f32, mut visit: F) -> Option<R>
+where F: FnMut(BlockPos, Option<Direction>, f32) -> Option<R> {
+    // Amanatides and Woo voxel traversal.
+    let origin = ray.origin.to_array();
+    let direction = ray.direction.to_array();
+    let mut block = [origin[0].floor() as i32, origin[1].floor() as i32, origin[2].floor() as i32];
+    let mut step = [0i32; 3];
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+    for axis in 0..3 {
+        if direction[axis] > 0.0 {
+            step[axis] = 1;
+            t_max[axis] = ((block[axis] + 1) as f32 - origin[axis]) / direction[axis];
+            t_delta[axis] = 1.0 / direction[axis];
+        } else if direction[axis] < 0.0 {
+            step[axis] = -1;
+            t_max[axis] = (block[axis] as f32 - origin[axis]) / direction[axis];
+            t_delta[axis] = -1.0 / direction[axis];
+        }
+    }
+
+    let mut distance = 0.0;
+    let mut face = None;
+    loop {
+        if let Some(result) = visit(BlockPos::new(block[0], block[1], block[2]), face, distance) {
+            return Some(result);
+        }
+        let axis = if t_max[0] < t_max[1] {
+            if t_max[0] < t_max[2] { 0 } else { 2 }
+        } else if t_max[1] < t_max[2] { 1 } else { 2 };
+        distance = t_max[axis];
+        if distance > max_distance || !distance.is_finite() {
+            return None;
+        }
+        block[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+        face = Some([Axis::X, Axis::Y, Axis::Z][axis].direction(step[axis] < 0));
+    }
+}
\ No newline at end of file
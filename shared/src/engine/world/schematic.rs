@@ -0,0 +1,393 @@
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use crate::engine::{
+    block::{registry::AIR_NAME, BlockId, BlockRegistry},
+    compression::Compression,
+    light::propagation::LightingFn,
+    math::coords::{BlockPos, ChunkPos},
+    save::level::write_atomically
+};
+
+use super::{
+    block_entity::BlockEntityTypes,
+    edit::{EditError, EditTransaction, WorldEdit},
+    World
+};
+
+/// Bytes every schematic file starts with.
+pub const SCHEMATIC_MAGIC: &[u8; 4] = b"CUBS";
+/// Version of the schematic encoding, stored after the magic.
+pub const SCHEMATIC_FORMAT_VERSION: u8 = 1;
+/// Extension of schematic files.
+pub const SCHEMATIC_EXTENSION: &str = "schem";
+/// Directory of a world holding schematics saved by commands.
+pub const SCHEMATICS_DIRECTORY: &str = "schematics";
+/// Most blocks a schematic may hold, so a corrupt file can't exhaust memory.
+pub const MAX_SCHEMATIC_VOLUME: u64 = 256 * 256 * 256;
+
+fn invalid(message: &str) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+}
+
+/// A block entity in a schematic, saved with BlockEntity::save() so it can be loaded into another world.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchematicBlockEntity {
+    /// Position relative to the schematic's minimum corner.
+    pub pos: BlockPos,
+    pub kind: String,
+    pub data: Vec<u8>
+}
+
+/// A box of blocks copied out of a world, to be pasted into the same or another world, or placed by structure
+/// generation. Blocks are stored by state name in a palette rather than by id, as ids differ between worlds.
+/// ```
+/// # use shared::engine::block::BlockRegistry;
+/// # use shared::engine::world::{World, chunk::Chunk, schematic::Schematic};
+/// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+/// let mut blocks = BlockRegistry::new();
+/// let stone = blocks.register("cube:stone").unwrap();
+/// let world = World::new();
+/// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+/// world.set_block(BlockPos::new(2, 3, 4), stone);
+/// let schematic = world.copy_region(BlockPos::new(2, 3, 4), BlockPos::new(3, 4, 4), &blocks).unwrap();
+/// assert_eq!(schematic.size(), (2, 2, 1));
+/// assert_eq!(schematic.block(0, 0, 0), Some("cube:stone"));
+/// assert_eq!(schematic.block(1, 1, 0), Some("cube:air"));
+/// assert_eq!(Schematic::decode(&schematic.encode().unwrap()).unwrap(), schematic);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schematic {
+    width: u32,
+    height: u32,
+    length: u32,
+    palette: Vec<String>,
+    /// Palette index of each block, x fastest, then z, then y.
+    blocks: Vec<u16>,
+    block_entities: Vec<SchematicBlockEntity>
+}
+
+impl Schematic {
+    /// A schematic of air. Will panic in debug mode if it's larger than MAX_SCHEMATIC_VOLUME.
+    pub fn new(width: u32, height: u32, length: u32) -> Schematic {
+        let volume = width as u64 * height as u64 * length as u64;
+        debug_assert!(volume <= MAX_SCHEMATIC_VOLUME, "Schematic is too large");
+        return Schematic { width, height, length, palette: vec![AIR_NAME.to_string()], blocks: vec![0; volume as usize], block_entities: Vec::new() };
+    }
+
+    /// Width, height and length, along x, y and z.
+    pub fn size(&self) -> (u32, u32, u32) {
+        return (self.width, self.height, self.length);
+    }
+
+    /// Every state name the schematic uses, and any it no longer does.
+    pub fn palette(&self) -> &[String] {
+        return &self.palette;
+    }
+
+    pub fn block_entities(&self) -> &[SchematicBlockEntity] {
+        return &self.block_entities;
+    }
+
+    fn index(&self, x: u32, y: u32, z: u32) -> Option<usize> {
+        if x >= self.width || y >= self.height || z >= self.length {
+            return None;
+        }
+        return Some(((y as usize * self.length as usize) + z as usize) * self.width as usize + x as usize);
+    }
+
+    /// State name of a block. None outside the schematic.
+    pub fn block(&self, x: u32, y: u32, z: u32) -> Option<&str> {
+        let index = self.index(x, y, z)?;
+        return Some(&self.palette[self.blocks[index] as usize]);
+    }
+
+    /// Set a block by state name, adding it to the palette. Does nothing outside the schematic.
+    pub fn set_block(&mut self, x: u32, y: u32, z: u32, name: &str) {
+        let Some(index) = self.index(x, y, z) else {
+            return;
+        };
+        let entry = match self.palette.iter().position(|existing| existing == name) {
+            Some(entry) => entry,
+            None => {
+                self.palette.push(name.to_string());
+                self.palette.len() - 1
+            }
+        };
+        self.blocks[index] = entry as u16;
+    }
+
+    /// Add a block entity, replacing any at the same position. Positions outside the schematic are ignored.
+    pub fn add_block_entity(&mut self, entity: SchematicBlockEntity) {
+        let pos = entity.pos;
+        if pos.x < 0 || pos.y < 0 || pos.z < 0 || self.index(pos.x as u32, pos.y as u32, pos.z as u32).is_none() {
+            return;
+        }
+        self.block_entities.retain(|existing| existing.pos != pos);
+        self.block_entities.push(entity);
+    }
+
+    /// Encode as the magic and format version, then compressed little-endian binary: the size, the palette as
+    /// length prefixed names, a palette index per block, and each block entity's position, kind and data.
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        for size in [self.width, self.height, self.length] {
+            body.extend_from_slice(&size.to_le_bytes());
+        }
+        body.extend_from_slice(&(self.palette.len() as u16).to_le_bytes());
+        for name in self.palette.iter() {
+            body.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            body.extend_from_slice(name.as_bytes());
+        }
+        for entry in self.blocks.iter() {
+            body.extend_from_slice(&entry.to_le_bytes());
+        }
+        body.extend_from_slice(&(self.block_entities.len() as u32).to_le_bytes());
+        for entity in self.block_entities.iter() {
+            for coord in [entity.pos.x, entity.pos.y, entity.pos.z] {
+                body.extend_from_slice(&(coord as u32).to_le_bytes());
+            }
+            body.extend_from_slice(&(entity.kind.len() as u16).to_le_bytes());
+            body.extend_from_slice(entity.kind.as_bytes());
+            body.extend_from_slice(&(entity.data.len() as u32).to_le_bytes());
+            body.extend_from_slice(&entity.data);
+        }
+        let mut out = SCHEMATIC_MAGIC.to_vec();
+        out.push(SCHEMATIC_FORMAT_VERSION);
+        out.extend_from_slice(&Compression::SAVE.compress_tagged(&body)?);
+        return Ok(out);
+    }
+
+    /// Decode a schematic written by encode(). Corrupt data is an InvalidData error rather than a panic.
+    /// ```
+    /// # use shared::engine::world::schematic::Schematic;
+    /// assert!(Schematic::decode(b"CUBS\x01").is_err());
+    /// assert!(Schematic::decode(b"PNG!").is_err());
+    /// ```
+    pub fn decode(data: &[u8]) -> io::Result<Schematic> {
+        let Some(compressed) = data.strip_prefix(SCHEMATIC_MAGIC.as_slice()) else {
+            return Err(invalid("not a schematic"));
+        };
+        let Some((version, compressed)) = compressed.split_first() else {
+            return Err(invalid("schematic ended early"));
+        };
+        if *version != SCHEMATIC_FORMAT_VERSION {
+            return Err(invalid("unsupported schematic format version"));
+        }
+        let body = Compression::decompress_tagged(compressed)?;
+        let mut reader = body.as_slice();
+        let mut take = |count: usize| -> io::Result<&[u8]> {
+            if reader.len() < count {
+                return Err(invalid("schematic ended early"));
+            }
+            let (taken, rest) = reader.split_at(count);
+            reader = rest;
+            return Ok(taken);
+        };
+        let mut size = [0u32; 3];
+        for value in size.iter_mut() {
+            *value = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        }
+        let [width, height, length] = size;
+        let volume = width as u64 * height as u64 * length as u64;
+        if volume > MAX_SCHEMATIC_VOLUME {
+            return Err(invalid("schematic is too large"));
+        }
+        let palette_len = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+        if palette_len == 0 {
+            return Err(invalid("schematic has an empty palette"));
+        }
+        let mut palette = Vec::with_capacity(palette_len);
+        for _ in 0..palette_len {
+            let len = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+            palette.push(std::str::from_utf8(take(len)?).map_err(|_| invalid("block name is not UTF-8"))?.to_string());
+        }
+        let mut blocks = Vec::with_capacity(volume as usize);
+        for _ in 0..volume {
+            let entry = u16::from_le_bytes(take(2)?.try_into().unwrap());
+            if entry as usize >= palette_len {
+                return Err(invalid("block is outside the palette"));
+            }
+            blocks.push(entry);
+        }
+        let mut schematic = Schematic { width, height, length, palette, blocks, block_entities: Vec::new() };
+        let count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        for _ in 0..count {
+            let mut pos = [0i32; 3];
+            for coord in pos.iter_mut() {
+                *coord = u32::from_le_bytes(take(4)?.try_into().unwrap()) as i32;
+            }
+            let pos = BlockPos::new(pos[0], pos[1], pos[2]);
+            if pos.x < 0 || pos.y < 0 || pos.z < 0 || schematic.index(pos.x as u32, pos.y as u32, pos.z as u32).is_none() {
+                return Err(invalid("block entity is outside the schematic"));
+            }
+            let kind_len = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+            let kind = std::str::from_utf8(take(kind_len)?).map_err(|_| invalid("block entity kind is not UTF-8"))?.to_string();
+            let data_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+            let data = take(data_len)?.to_vec();
+            schematic.block_entities.push(SchematicBlockEntity { pos, kind, data });
+        }
+        return Ok(schematic);
+    }
+
+    /// Write to a file, replacing it whole so a crash never leaves half a schematic.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        return write_atomically(path, &self.encode()?);
+    }
+
+    pub fn load(path: &Path) -> io::Result<Schematic> {
+        return Schematic::decode(&fs::read(path)?);
+    }
+}
+
+/// Why a schematic couldn't be pasted. Nothing in the world changes when it fails.
+#[derive(Debug)]
+pub enum SchematicError {
+    /// A block entity's data couldn't be loaded.
+    Io(io::Error),
+    Edit(EditError)
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            SchematicError::Io(error) => write!(f, "couldn't load a block entity: {}", error),
+            SchematicError::Edit(error) => write!(f, "{}", error)
+        };
+    }
+}
+
+impl std::error::Error for SchematicError {}
+
+impl From<io::Error> for SchematicError {
+    fn from(error: io::Error) -> SchematicError {
+        return SchematicError::Io(error);
+    }
+}
+
+impl From<EditError> for SchematicError {
+    fn from(error: EditError) -> SchematicError {
+        return SchematicError::Edit(error);
+    }
+}
+
+impl World {
+    /// Copy the blocks and block entities in the box between two corners, inclusive, into a schematic.
+    /// Every chunk the box touches must be loaded.
+    pub fn copy_region(&self, from: BlockPos, to: BlockPos, blocks: &BlockRegistry) -> Result<Schematic, EditError> {
+        let min = BlockPos::new(from.x.min(to.x), from.y.min(to.y), from.z.min(to.z));
+        let max = BlockPos::new(from.x.max(to.x), from.y.max(to.y), from.z.max(to.z));
+        let mut schematic = Schematic::new((max.x - min.x + 1) as u32, (max.y - min.y + 1) as u32, (max.z - min.z + 1) as u32);
+        let (min_chunk, max_chunk) = (min.chunk(), max.chunk());
+        let mut entries: HashMap<BlockId, u16> = HashMap::new();
+        let mut ids: Vec<BlockId> = Vec::new();
+        for cx in min_chunk.x..=max_chunk.x {
+            for cy in min_chunk.y..=max_chunk.y {
+                for cz in min_chunk.z..=max_chunk.z {
+                    let pos = ChunkPos::new(cx, cy, cz);
+                    let chunk = self.chunk(pos).ok_or(EditError::Unloaded(pos))?;
+                    let chunk = chunk.read().unwrap();
+                    let (origin, end) = (pos.origin(), pos.offset(1, 1, 1).origin());
+                    for y in min.y.max(origin.y)..=max.y.min(end.y - 1) {
+                        for z in min.z.max(origin.z)..=max.z.min(end.z - 1) {
+                            for x in min.x.max(origin.x)..=max.x.min(end.x - 1) {
+                                let id = chunk.get_block(BlockPos::new(x, y, z).local());
+                                let entry = *entries.entry(id).or_insert_with(|| {
+                                    ids.push(id);
+                                    return ids.len() as u16 - 1;
+                                });
+                                let index = schematic.index((x - min.x) as u32, (y - min.y) as u32, (z - min.z) as u32).unwrap();
+                                schematic.blocks[index] = entry;
+                            }
+                        }
+                    }
+                    drop(chunk);
+                    let Some(map) = self.block_entities(pos) else {
+                        continue;
+                    };
+                    for (block, entity) in map.read().unwrap().iter() {
+                        if block.x < min.x || block.y < min.y || block.z < min.z || block.x > max.x || block.y > max.y || block.z > max.z {
+                            continue;
+                        }
+                        let entity = entity.lock().unwrap();
+                        let mut data = Vec::new();
+                        entity.save(&mut data);
+                        schematic.block_entities.push(SchematicBlockEntity { pos: *block - min, kind: entity.kind().to_string(), data });
+                    }
+                }
+            }
+        }
+        schematic.palette = ids.iter().map(|id| match blocks.state_name(*id) {
+            "" => AIR_NAME.to_string(),
+            name => name.to_string()
+        }).collect();
+        schematic.block_entities.sort_unstable_by_key(|entity| entity.pos);
+        return Ok(schematic);
+    }
+
+    /// Paste a schematic with its minimum corner at a position, as one world edit that can be undone, relighting
+    /// with lighting if given. Blocks this world doesn't have become air, and block entities of kinds it doesn't
+    /// have are left out. Block entities already in the box are removed, and aren't put back by undoing.
+    /// ```
+    /// # use shared::engine::block::BlockRegistry;
+    /// # use shared::engine::world::{World, chunk::Chunk, schematic::Schematic, block_entity::BlockEntityTypes};
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// let mut blocks = BlockRegistry::new();
+    /// let stone = blocks.register("cube:stone").unwrap();
+    /// let mut schematic = Schematic::new(3, 1, 1);
+    /// schematic.set_block(0, 0, 0, "cube:stone");
+    /// schematic.set_block(2, 0, 0, "somemod:ruby");
+    ///
+    /// let world = World::new();
+    /// world.insert_chunk(Chunk::filled(ChunkPos::new(0, 0, 0), stone));
+    /// let pasted = world.paste_region(&schematic, BlockPos::new(5, 5, 5), &blocks, &BlockEntityTypes::new(), None).unwrap();
+    /// assert_eq!(pasted.len(), 2);
+    /// assert_eq!(world.get_block(BlockPos::new(6, 5, 5)), Some(0));
+    /// assert_eq!(world.get_block(BlockPos::new(7, 5, 5)), Some(0));
+    /// pasted.undo(&world, None).unwrap();
+    /// assert_eq!(world.get_block(BlockPos::new(7, 5, 5)), Some(stone));
+    /// ```
+    pub fn paste_region(&self, schematic: &Schematic, origin: BlockPos, blocks: &BlockRegistry, types: &BlockEntityTypes, lighting: Option<&LightingFn>) -> Result<EditTransaction, SchematicError> {
+        let mut entities = Vec::new();
+        for entity in schematic.block_entities.iter() {
+            if let Some(loaded) = types.load(&entity.kind, &entity.data) {
+                entities.push((origin + entity.pos, loaded?));
+            }
+        }
+        let remap = blocks.remap_saved(&schematic.palette);
+        let mut edit = WorldEdit::new();
+        for y in 0..schematic.height {
+            for z in 0..schematic.length {
+                for x in 0..schematic.width {
+                    let entry = schematic.blocks[schematic.index(x, y, z).unwrap()];
+                    edit.set(origin + BlockPos::new(x as i32, y as i32, z as i32), remap.map(entry));
+                }
+            }
+        }
+        let transaction = edit.apply(self, lighting)?;
+        let max = origin + BlockPos::new(schematic.width as i32 - 1, schematic.height as i32 - 1, schematic.length as i32 - 1);
+        let (min_chunk, max_chunk) = (origin.chunk(), max.chunk());
+        for cx in min_chunk.x..=max_chunk.x {
+            for cy in min_chunk.y..=max_chunk.y {
+                for cz in min_chunk.z..=max_chunk.z {
+                    let Some(map) = self.block_entities(ChunkPos::new(cx, cy, cz)) else {
+                        continue;
+                    };
+                    let mut map = map.write().unwrap();
+                    let inside: Vec<BlockPos> = map.iter().map(|(block, _)| *block)
+                        .filter(|block| block.x >= origin.x && block.y >= origin.y && block.z >= origin.z && block.x <= max.x && block.y <= max.y && block.z <= max.z)
+                        .collect();
+                    for block in inside {
+                        map.remove(block);
+                    }
+                }
+            }
+        }
+        for (block, entity) in entities {
+            let _ = self.insert_block_entity(block, entity);
+        }
+        return Ok(transaction);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,123 @@
+use crate::engine::{
+    entity::{kinematics::Transform, EntityId},
+    math::{aabb::Aabb, coords::{ChunkPos, CHUNK_SIZE}, vector::Vec3},
+    physics::RigidBody
+};
+
+use super::World;
+
+/// Furthest a RigidBody's box reaches from its entity's position, in blocks. Queries look this far past their area
+/// for entities in neighboring chunks, so bodies larger than this can be missed near chunk borders.
+pub const MAX_BODY_REACH: f32 = 4.0;
+
+impl World {
+    /// Entities the registry has placed in chunks overlapping an area, or within MAX_BODY_REACH of it.
+    /// The chunk index is kept up to date as transforms change by the KinematicsSystem and PhysicsSystem.
+    pub(crate) fn entities_near(&self, area: &Aabb) -> Vec<EntityId> {
+        let area = area.expand(Vec3::splat(MAX_BODY_REACH));
+        let chunk = |point: Vec3| ChunkPos::new(
+            (point.x.floor() as i32).div_euclid(CHUNK_SIZE),
+            (point.y.floor() as i32).div_euclid(CHUNK_SIZE),
+            (point.z.floor() as i32).div_euclid(CHUNK_SIZE)
+        );
+        let (min, max) = (chunk(area.min), chunk(area.max));
+        let mut entities = Vec::new();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    entities.extend(self.entities().in_chunk(ChunkPos::new(x, y, z)));
+                }
+            }
+        }
+        return entities;
+    }
+
+    /// Call a function with the bounds of every entity near an area: the box of its RigidBody,
+    /// or just its position if it has none.
+    fn for_each_bounds<F>(&self, area: &Aabb, mut func: F)
+    where F: FnMut(EntityId, Aabb) {
+        let transforms = self.entities().storage::<Transform>();
+        let transforms = transforms.read().unwrap();
+        let bodies = self.entities().storage::<RigidBody>();
+        let bodies = bodies.read().unwrap();
+        for entity in self.entities_near(area) {
+            let Some(transform) = transforms.get(entity) else {
+                continue;
+            };
+            let bounds = match bodies.get(entity) {
+                Some(body) => body.aabb(transform.position),
+                None => {
+                    let position = transform.position;
+                    let point = Vec3::new(position.x as f32, position.y as f32, position.z as f32);
+                    Aabb::new(point, point)
+                }
+            };
+            func(entity, bounds);
+        }
+    }
+
+    /// Every entity whose bounds overlap an area, in world block coordinates, for collision and interest management.
+    /// Entities with a RigidBody are bounded by its box, and others by their position.
+    /// ```
+    /// # use shared::engine::entity::kinematics::Transform;
+    /// # use shared::engine::math::{aabb::Aabb, coords::{ChunkPos, WorldPos}, vector::Vec3};
+    /// # use shared::engine::physics::RigidBody;
+    /// # use shared::engine::world::World;
+    /// let world = World::new();
+    /// let entities = world.entities();
+    /// let spawn = |position: WorldPos| {
+    ///     let entity = entities.spawn();
+    ///     entities.insert(entity, Transform::new(position)).unwrap();
+    ///     entities.set_chunk(entity, position.chunk());
+    ///     entity
+    /// };
+    /// let item = spawn(WorldPos::new(1.5, 0.5, 1.5));
+    /// let mob = spawn(WorldPos::new(-0.5, 0.0, 1.5));
+    /// entities.insert(mob, RigidBody::new(1.4, 2.0)).unwrap();
+    /// spawn(WorldPos::new(40.0, 0.0, 0.0));
+    ///
+    /// let mut found = world.entities_in_aabb(Aabb::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 2.0, 2.0)));
+    /// found.sort();
+    /// assert_eq!(found, vec![item, mob]);
+    /// assert!(world.entities_in_aabb(Aabb::new(Vec3::new(10.0, 0.0, 0.0), Vec3::new(12.0, 2.0, 2.0))).is_empty());
+    /// ```
+    pub fn entities_in_aabb(&self, area: Aabb) -> Vec<EntityId> {
+        let mut entities = Vec::new();
+        self.for_each_bounds(&area, |entity, bounds| {
+            if bounds.intersects(&area) {
+                entities.push(entity);
+            }
+        });
+        return entities;
+    }
+
+    /// Every entity whose bounds are within a radius of a point, nearest first, for AI looking for targets.
+    /// ```
+    /// # use shared::engine::entity::kinematics::Transform;
+    /// # use shared::engine::math::{coords::WorldPos, vector::Vec3};
+    /// # use shared::engine::world::World;
+    /// let world = World::new();
+    /// let entities = world.entities();
+    /// let spawn = |position: WorldPos| {
+    ///     let entity = entities.spawn();
+    ///     entities.insert(entity, Transform::new(position)).unwrap();
+    ///     entities.set_chunk(entity, position.chunk());
+    ///     entity
+    /// };
+    /// let far = spawn(WorldPos::new(-7.0, 0.0, 0.0));
+    /// let near = spawn(WorldPos::new(33.0, 0.0, 0.0));
+    /// spawn(WorldPos::new(0.0, 0.0, 40.0));
+    /// assert_eq!(world.entities_in_radius(Vec3::new(31.0, 0.0, 0.0), 40.0), vec![near, far]);
+    /// ```
+    pub fn entities_in_radius(&self, center: Vec3, radius: f32) -> Vec<EntityId> {
+        let mut entities = Vec::new();
+        self.for_each_bounds(&Aabb::from_center_half_extents(center, Vec3::splat(radius)), |entity, bounds| {
+            let distance = bounds.closest_point(center).distance(center);
+            if distance <= radius {
+                entities.push((distance, entity));
+            }
+        });
+        entities.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        return entities.into_iter().map(|(_, entity)| entity).collect();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,282 @@
+use std::{collections::{BTreeSet, HashMap}, sync::{atomic::{AtomicU32, Ordering}, Arc, Mutex}};
+
+use crate::engine::{
+    block::BlockId,
+    job::system::JobSystem,
+    math::{coords::{BlockPos, ChunkPos, LocalPos}, rng::WorldRng},
+    world::chunk::Chunk
+};
+
+use super::{palette::SECTION_SIZE, World};
+
+/// Default number of random ticks given to each chunk section per game tick.
+pub const DEFAULT_RANDOM_TICK_SPEED: u32 = 3;
+
+/// Chunks updated by each tick job.
+const CHUNKS_PER_JOB: usize = 16;
+
+/// Behaviour of a block when it is ticked. Handlers run inside jobs, on many threads at once.
+pub trait BlockTicker: Send + Sync {
+    /// Run when an update scheduled for the block is due. The block may have changed since it was scheduled.
+    fn scheduled_tick(&self, _context: &TickContext, _block: BlockPos, _id: BlockId) {}
+
+    /// Run when the block is picked for a random tick, such as crops growing.
+    fn random_tick(&self, _context: &TickContext, _block: BlockPos, _id: BlockId, _rng: &mut WorldRng) {}
+
+    /// Whether the block wants random ticks. Sections without any such blocks are skipped.
+    fn random_ticks(&self) -> bool {
+        return false;
+    }
+}
+
+/// Tick handlers for each block id.
+#[derive(Default, Clone)]
+pub struct TickHandlers {
+    handlers: HashMap<BlockId, Arc<dyn BlockTicker>>
+}
+
+impl TickHandlers {
+    pub fn new() -> TickHandlers {
+        return TickHandlers::default();
+    }
+
+    /// Set the handler of a block, returning the one it replaced.
+    pub fn register(&mut self, id: BlockId, handler: Arc<dyn BlockTicker>) -> Option<Arc<dyn BlockTicker>> {
+        return self.handlers.insert(id, handler);
+    }
+
+    pub fn get(&self, id: BlockId) -> Option<&Arc<dyn BlockTicker>> {
+        return self.handlers.get(&id);
+    }
+
+    fn random_ticks(&self, id: BlockId) -> bool {
+        return self.handlers.get(&id).is_some_and(|handler| handler.random_ticks());
+    }
+}
+
+/// What a tick handler can reach while it runs.
+pub struct TickContext<'a> {
+    pub world: &'a World,
+    pub scheduler: &'a TickScheduler,
+    /// The game tick being run.
+    pub tick: u64
+}
+
+impl TickContext<'_> {
+    /// Schedule an update for a block a number of ticks from now. 0 runs it next tick.
+    pub fn schedule(&self, block: BlockPos, delay: u64) {
+        self.scheduler.schedule_at(block, self.tick + delay.max(1));
+    }
+}
+
+/// Number of updates run by a game tick.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickStats {
+    pub scheduled: usize,
+    pub random: usize
+}
+
+struct TickQueue {
+    tick: u64,
+    /// Due tick, then order of scheduling, so updates due together run in the order they were scheduled.
+    queue: BTreeSet<(u64, u64, BlockPos)>,
+    /// Blocks with an update pending at a tick, so scheduling the same update twice only runs it once.
+    pending: HashMap<(BlockPos, u64), u64>,
+    sequence: u64
+}
+
+/// Per world block updates, both scheduled ("update this block in N ticks") and random ticks of chunk sections.
+/// Each game tick runs due updates as jobs grouped by chunk, and handlers may schedule more updates from those jobs.
+pub struct TickScheduler {
+    handlers: TickHandlers,
+    queue: Mutex<TickQueue>,
+    random_tick_speed: AtomicU32,
+    seed: u64
+}
+
+impl TickScheduler {
+    /// The seed picks which blocks get random ticks.
+    pub fn new(handlers: TickHandlers, seed: u64) -> TickScheduler {
+        return TickScheduler {
+            handlers,
+            queue: Mutex::new(TickQueue { tick: 0, queue: BTreeSet::new(), pending: HashMap::new(), sequence: 0 }),
+            random_tick_speed: AtomicU32::new(DEFAULT_RANDOM_TICK_SPEED),
+            seed
+        };
+    }
+
+    pub fn handlers(&self) -> &TickHandlers {
+        return &self.handlers;
+    }
+
+    /// The next game tick to run.
+    pub fn current_tick(&self) -> u64 {
+        return self.queue.lock().unwrap().tick;
+    }
+
+    pub fn random_tick_speed(&self) -> u32 {
+        return self.random_tick_speed.load(Ordering::Relaxed);
+    }
+
+    /// Random ticks per chunk section per game tick. 0 disables random ticks.
+    pub fn set_random_tick_speed(&self, speed: u32) {
+        self.random_tick_speed.store(speed, Ordering::Relaxed);
+    }
+
+    /// Schedule an update for a block a number of ticks after the current one. A delay of 0 runs it in the current tick
+    /// if it hasn't run yet. Scheduling an update already pending for the same block and tick does nothing.
+    pub fn schedule(&self, block: BlockPos, delay: u64) {
+        let tick = self.current_tick();
+        self.schedule_at(block, tick + delay);
+    }
+
+    /// Schedule an update for a block at a specific game tick.
+    pub fn schedule_at(&self, block: BlockPos, tick: u64) {
+        let mut lock = self.queue.lock().unwrap();
+        let queue = &mut *lock;
+        if queue.pending.contains_key(&(block, tick)) {
+            return;
+        }
+        let sequence = queue.sequence;
+        queue.sequence += 1;
+        queue.pending.insert((block, tick), sequence);
+        queue.queue.insert((tick, sequence, block));
+    }
+
+    /// Check if an update is pending for a block at any tick.
+    pub fn is_scheduled(&self, block: BlockPos) -> bool {
+        return self.queue.lock().unwrap().pending.keys().any(|(pending, _)| *pending == block);
+    }
+
+    pub fn pending_count(&self) -> usize {
+        return self.queue.lock().unwrap().queue.len();
+    }
+
+    /// Run one game tick: every scheduled update that is due, and random ticks in every loaded chunk.
+    /// Updates run as jobs grouped by chunk, and this waits for them all before advancing to the next tick.
+    /// Updates scheduled for unloaded chunks are dropped.
+    /// ```
+    /// # use shared::engine::world::{World, chunk::Chunk, tick::{BlockTicker, TickContext, TickHandlers, TickScheduler}};
+    /// # use shared::engine::job::system::JobSystem;
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos};
+    /// # use std::sync::Arc;
+    /// // A block that turns into the next id each time it is updated, and schedules another update.
+    /// struct Counter;
+    /// impl BlockTicker for Counter {
+    ///     fn scheduled_tick(&self, context: &TickContext, block: BlockPos, id: u16) {
+    ///         context.world.set_block(block, id + 1);
+    ///         context.schedule(block, 2);
+    ///     }
+    /// }
+    /// let mut handlers = TickHandlers::new();
+    /// for id in 1..10 {
+    ///     handlers.register(id, Arc::new(Counter));
+    /// }
+    /// let scheduler = Arc::new(TickScheduler::new(handlers, 0));
+    /// let world = Arc::new(World::new());
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    /// world.set_block(BlockPos::new(1, 1, 1), 1);
+    /// scheduler.schedule(BlockPos::new(1, 1, 1), 1);
+    ///
+    /// let jobs = JobSystem::new(2);
+    /// for _ in 0..6 {
+    ///     scheduler.run_tick(&world, &jobs);
+    /// }
+    /// // Updated at ticks 1, 3, and 5.
+    /// assert_eq!(world.get_block(BlockPos::new(1, 1, 1)), Some(4));
+    /// ```
+    pub fn run_tick(self: &Arc<Self>, world: &Arc<World>, jobs: &JobSystem) -> TickStats {
+        let tick = self.current_tick();
+        let mut due: HashMap<ChunkPos, Vec<BlockPos>> = HashMap::new();
+        {
+            let mut lock = self.queue.lock().unwrap();
+            let queue = &mut *lock;
+            while let Some(first) = queue.queue.first().copied() {
+                if first.0 > tick {
+                    break;
+                }
+                queue.queue.pop_first();
+                queue.pending.remove(&(first.2, first.0));
+                due.entry(first.2.chunk()).or_default().push(first.2);
+            }
+        }
+        let random_speed = self.random_tick_speed();
+        let chunks: Vec<ChunkPos> = if random_speed > 0 { world.loaded_chunks() } else { due.keys().copied().collect() };
+
+        let mut futures = Vec::new();
+        for batch in chunks.chunks(CHUNKS_PER_JOB) {
+            let batch: Vec<(ChunkPos, Vec<BlockPos>)> = batch.iter().map(|pos| (*pos, due.remove(pos).unwrap_or_default())).collect();
+            let scheduler = self.clone();
+            let world = world.clone();
+            futures.push(jobs.run_job(move || {
+                let context = TickContext { world: &world, scheduler: &scheduler, tick };
+                let mut stats = TickStats::default();
+                for (pos, blocks) in batch.iter() {
+                    stats.scheduled += scheduler.run_scheduled(&context, blocks);
+                    stats.random += scheduler.run_random(&context, *pos, random_speed);
+                }
+                return stats;
+            }));
+        }
+        let mut stats = TickStats::default();
+        for future in futures {
+            let batch = future.wait();
+            stats.scheduled += batch.scheduled;
+            stats.random += batch.random;
+        }
+        self.queue.lock().unwrap().tick = tick + 1;
+        return stats;
+    }
+
+    fn run_scheduled(&self, context: &TickContext, blocks: &[BlockPos]) -> usize {
+        let mut count = 0;
+        for block in blocks {
+            let Some(id) = context.world.get_block(*block) else {
+                continue;
+            };
+            if let Some(handler) = self.handlers.get(id) {
+                handler.scheduled_tick(context, *block, id);
+                count += 1;
+            }
+        }
+        return count;
+    }
+
+    fn run_random(&self, context: &TickContext, pos: ChunkPos, speed: u32) -> usize {
+        if speed == 0 {
+            return 0;
+        }
+        let Some(chunk) = context.world.chunk(pos) else {
+            return 0;
+        };
+        let mut rng = WorldRng::for_chunk(self.seed.wrapping_add(context.tick), pos);
+        // Pick blocks up front, so no lock is held while handlers run.
+        let mut picked = Vec::new();
+        {
+            let lock = chunk.read().unwrap();
+            for (index, section) in lock.sections().iter().enumerate() {
+                if !section.palette().iter().any(|id| self.handlers.random_ticks(*id)) {
+                    continue;
+                }
+                let origin = Chunk::section_origin(index);
+                for _ in 0..speed {
+                    let local = LocalPos::new(
+                        origin.x + rng.range_i32(0..SECTION_SIZE as i32) as u8,
+                        origin.y + rng.range_i32(0..SECTION_SIZE as i32) as u8,
+                        origin.z + rng.range_i32(0..SECTION_SIZE as i32) as u8
+                    );
+                    let id = lock.get_block(local);
+                    if self.handlers.random_ticks(id) {
+                        picked.push((pos.block(local), id));
+                    }
+                }
+            }
+        }
+        for (block, id) in picked.iter() {
+            if let Some(handler) = self.handlers.get(*id) {
+                handler.random_tick(context, *block, *id, &mut rng);
+            }
+        }
+        return picked.len();
+    }
+}
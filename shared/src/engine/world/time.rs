@@ -0,0 +1,95 @@
+use std::f32::consts::TAU;
+
+use crate::engine::math::vector::Vec3;
+
+/// Ticks in a full day and night, 20 minutes at 20 ticks per second.
+pub const DAY_LENGTH: u64 = 24000;
+/// Time of day the sun rises, with noon a quarter day later.
+pub const SUNRISE: u64 = 0;
+pub const NOON: u64 = 6000;
+pub const SUNSET: u64 = 12000;
+pub const MIDNIGHT: u64 = 18000;
+/// Days in the moon's cycle, starting at a full moon.
+pub const MOON_PHASES: u64 = 8;
+/// Daylight at midnight, so the night sky never lights things as if they were underground.
+pub const MIN_DAYLIGHT: f32 = 0.2;
+/// Levels taken off sky light at night. Sky light 15 outside at midnight acts as 4.
+pub const MAX_SKY_DARKENING: u8 = 11;
+
+/// A point in world time, from the ticks the world has existed for with the daylight cycle on.
+/// Turns the tick counter into the time of day and moon phase, and the daylight the sky and lighting use.
+/// ```
+/// # use shared::engine::world::time::{WorldTime, DAY_LENGTH, NOON, MIDNIGHT, MIN_DAYLIGHT};
+/// let noon = WorldTime::new(3 * DAY_LENGTH + NOON);
+/// assert_eq!(noon.day(), 3);
+/// assert_eq!(noon.time_of_day(), NOON);
+/// assert_eq!(noon.moon_phase(), 3);
+/// assert!(noon.is_day());
+/// assert_eq!(noon.daylight(), 1.0);
+/// assert!(noon.sun_direction().y > 0.99);
+/// let midnight = WorldTime::new(MIDNIGHT);
+/// assert!(!midnight.is_day());
+/// assert_eq!(midnight.daylight(), MIN_DAYLIGHT);
+/// assert_eq!(midnight.sky_darkening(), 11);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WorldTime(u64);
+
+impl WorldTime {
+    pub const fn new(ticks: u64) -> WorldTime {
+        return WorldTime(ticks);
+    }
+
+    pub fn ticks(&self) -> u64 {
+        return self.0;
+    }
+
+    /// Days since the world was made, starting at 0.
+    pub fn day(&self) -> u64 {
+        return self.0 / DAY_LENGTH;
+    }
+
+    /// Ticks since the sun last rose, from 0 to DAY_LENGTH.
+    pub fn time_of_day(&self) -> u64 {
+        return self.0 % DAY_LENGTH;
+    }
+
+    /// Moon phase from 0, a full moon, to 7, waxing gibbous.
+    pub fn moon_phase(&self) -> u8 {
+        return (self.day() % MOON_PHASES) as u8;
+    }
+
+    /// How full the moon is, from 0.0 for a new moon to 1.0 for a full moon.
+    pub fn moon_fullness(&self) -> f32 {
+        let phase = self.moon_phase() as f32 / MOON_PHASES as f32;
+        return ((phase * TAU).cos() + 1.0) / 2.0;
+    }
+
+    /// Angle of the sun around the sky in radians, 0 when it rises in the east and PI when it sets in the west.
+    pub fn sun_angle(&self) -> f32 {
+        return self.time_of_day() as f32 / DAY_LENGTH as f32 * TAU;
+    }
+
+    /// Unit vector towards the sun, rising along +X and setting along -X. The moon is opposite it.
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = self.sun_angle();
+        return Vec3::new(angle.cos(), angle.sin(), 0.0);
+    }
+
+    /// Whether the sun is up.
+    pub fn is_day(&self) -> bool {
+        return self.time_of_day() < SUNSET;
+    }
+
+    /// Brightness of sky light from MIN_DAYLIGHT to 1.0. Full through most of the day, fading through dusk and dawn.
+    pub fn daylight(&self) -> f32 {
+        let brightness = (self.sun_direction().y * 2.0 + 0.5).clamp(0.0, 1.0);
+        return MIN_DAYLIGHT + (1.0 - MIN_DAYLIGHT) * brightness;
+    }
+
+    /// Levels to take off sky light when comparing it with block light, such as to decide where monsters spawn.
+    pub fn sky_darkening(&self) -> u8 {
+        let darkness = (1.0 - self.daylight()) / (1.0 - MIN_DAYLIGHT);
+        return (darkness * MAX_SKY_DARKENING as f32).round() as u8;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+
+use crate::engine::{
+    block::{BlockId, AIR},
+    math::coords::{BlockPos, ChunkPos, CHUNK_VOLUME},
+    world::loader::ChunkGenerator
+};
+
+use super::{chunk::Chunk, lod::LodPos, palette::SECTION_VOLUME};
+
+/// Default level of the tree's roots, each covering 2^8 = 256 chunks along each axis.
+pub const DEFAULT_ROOT_LEVEL: u8 = 8;
+
+/// Summary of everything materialized below a node, so whole regions can be reasoned about
+/// without visiting their chunks, such as rendering a distant universe as a single block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NodeSummary {
+    /// Chunks materialized below the node.
+    pub chunks: u64,
+    /// Non air blocks in those chunks.
+    pub non_air: u64,
+    /// Most common non air block, AIR if there are none. Approximate for inner nodes, being the dominant block of their fullest child.
+    pub dominant: BlockId,
+    /// How many of the dominant block there are, used to compare children.
+    dominant_count: u64
+}
+
+impl NodeSummary {
+    fn of_chunk(chunk: &Chunk) -> NodeSummary {
+        let mut counts: Vec<(BlockId, u64)> = Vec::new();
+        let mut count = |id: BlockId, amount: u64| {
+            if id == AIR {
+                return;
+            }
+            match counts.iter_mut().find(|(counted, _)| *counted == id) {
+                Some((_, total)) => *total += amount,
+                None => counts.push((id, amount))
+            }
+        };
+        for section in chunk.sections().iter() {
+            if section.is_uniform() {
+                count(section.palette()[0], SECTION_VOLUME as u64);
+                continue;
+            }
+            for index in 0..SECTION_VOLUME {
+                count(section.get(index), 1);
+            }
+        }
+        let (dominant, dominant_count) = counts.iter().max_by_key(|(_, total)| *total).copied().unwrap_or((AIR, 0));
+        return NodeSummary { chunks: 1, non_air: chunk.non_air_count() as u64, dominant, dominant_count };
+    }
+
+    fn combine<'a, I>(summaries: I) -> NodeSummary
+    where I: Iterator<Item = &'a NodeSummary> {
+        let mut combined = NodeSummary::default();
+        for summary in summaries {
+            combined.chunks += summary.chunks;
+            combined.non_air += summary.non_air;
+            if summary.dominant_count > combined.dominant_count {
+                combined.dominant = summary.dominant;
+                combined.dominant_count = summary.dominant_count;
+            }
+        }
+        return combined;
+    }
+
+    /// Fraction of the materialized volume that isn't air.
+    pub fn density(&self) -> f64 {
+        if self.chunks == 0 {
+            return 0.0;
+        }
+        return self.non_air as f64 / (self.chunks * CHUNK_VOLUME as u64) as f64;
+    }
+}
+
+enum Content {
+    Branch(Box<[Option<Node>; 8]>),
+    Leaf(Box<Chunk>)
+}
+
+struct Node {
+    summary: NodeSummary,
+    content: Content
+}
+
+impl Node {
+    fn branch() -> Node {
+        return Node { summary: NodeSummary::default(), content: Content::Branch(Box::default()) };
+    }
+
+    fn leaf(chunk: Chunk) -> Node {
+        return Node { summary: NodeSummary::of_chunk(&chunk), content: Content::Leaf(Box::new(chunk)) };
+    }
+
+    fn refresh(&mut self) {
+        self.summary = match &self.content {
+            Content::Branch(children) => NodeSummary::combine(children.iter().flatten().map(|child| &child.summary)),
+            Content::Leaf(chunk) => NodeSummary::of_chunk(chunk)
+        };
+    }
+
+    fn children(&self) -> Option<&[Option<Node>; 8]> {
+        return match &self.content {
+            Content::Branch(children) => Some(children),
+            Content::Leaf(_) => None
+        };
+    }
+}
+
+/// Index of a node within its parent, in the same order as LodPos::children.
+fn child_index(pos: LodPos) -> usize {
+    return ((pos.x & 1) | (pos.y & 1) << 1 | (pos.z & 1) << 2) as usize;
+}
+
+/// Ancestor of a node at a coarser level.
+fn ancestor(pos: LodPos, level: u8) -> LodPos {
+    let shift = level - pos.level;
+    return LodPos::new(level, pos.x >> shift, pos.y >> shift, pos.z >> shift);
+}
+
+/// The universe as a sparse octree. Leaves hold chunks, and inner nodes summarize what's below them,
+/// so the world can be queried at any scale, from a single chunk to a summary of a whole root.
+/// Nodes are addressed the same way as terrain LOD nodes, with level 0 being chunks.
+/// Only materialized chunks take memory, and children are materialized lazily from a generator when first needed.
+pub struct UniverseTree {
+    root_level: u8,
+    roots: HashMap<(i32, i32, i32), Node>
+}
+
+impl UniverseTree {
+    pub fn new() -> UniverseTree {
+        return UniverseTree::with_root_level(DEFAULT_ROOT_LEVEL);
+    }
+
+    /// Will panic in debug mode if the root level is 0 or too high for chunk coordinates.
+    pub fn with_root_level(root_level: u8) -> UniverseTree {
+        debug_assert!(root_level > 0 && root_level < 31, "Universe tree root level must be between 1 and 30");
+        return UniverseTree { root_level, roots: HashMap::new() };
+    }
+
+    pub fn root_level(&self) -> u8 {
+        return self.root_level;
+    }
+
+    /// Number of materialized chunks.
+    pub fn chunk_count(&self) -> u64 {
+        return self.roots.values().map(|root| root.summary.chunks).sum();
+    }
+
+    /// Nodes from the root down to a node, or None where the path isn't materialized.
+    fn node(&self, pos: LodPos) -> Option<&Node> {
+        if pos.level > self.root_level {
+            return None;
+        }
+        let root = ancestor(pos, self.root_level);
+        let mut node = self.roots.get(&(root.x, root.y, root.z))?;
+        for level in (pos.level..self.root_level).rev() {
+            node = node.children()?[child_index(ancestor(pos, level))].as_ref()?;
+        }
+        return Some(node);
+    }
+
+    /// Run a function on the path from a root to a chunk's leaf, creating branches as needed, then refresh the summaries along it.
+    /// The function is given the leaf slot, and the path is pruned if it leaves the slot empty.
+    fn with_leaf<F, T>(&mut self, pos: ChunkPos, func: F) -> T
+    where F: FnOnce(&mut Option<Node>) -> T {
+        let leaf = LodPos::new(0, pos.x, pos.y, pos.z);
+        let root = ancestor(leaf, self.root_level);
+        let mut slot = self.roots.remove(&(root.x, root.y, root.z));
+        let result = Self::descend(&mut slot, leaf, self.root_level, func);
+        if let Some(node) = slot {
+            self.roots.insert((root.x, root.y, root.z), node);
+        }
+        return result;
+    }
+
+    fn descend<F, T>(slot: &mut Option<Node>, leaf: LodPos, level: u8, func: F) -> T
+    where F: FnOnce(&mut Option<Node>) -> T {
+        if level == 0 {
+            return func(slot);
+        }
+        let node = slot.get_or_insert_with(Node::branch);
+        let Content::Branch(children) = &mut node.content else {
+            unreachable!("Universe tree leaves are only at level 0");
+        };
+        let result = Self::descend(&mut children[child_index(ancestor(leaf, level - 1))], leaf, level - 1, func);
+        if children.iter().all(|child| child.is_none()) {
+            *slot = None;
+        } else {
+            node.refresh();
+        }
+        return result;
+    }
+
+    /// Add a chunk, returning the chunk it replaced.
+    /// ```
+    /// # use shared::engine::world::{chunk::Chunk, lod::LodPos, universe::UniverseTree};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let mut tree = UniverseTree::with_root_level(4);
+    /// tree.insert_chunk(Chunk::filled(ChunkPos::new(1, 0, 0), 3));
+    /// tree.insert_chunk(Chunk::new(ChunkPos::new(-20, 0, 0)));
+    /// assert_eq!(tree.chunk_count(), 2);
+    /// let summary = tree.summary(LodPos::new(2, 0, 0, 0)).unwrap();
+    /// assert_eq!(summary.dominant, 3);
+    /// assert_eq!(summary.density(), 1.0);
+    /// assert!(tree.summary(LodPos::new(2, 1, 0, 0)).is_none());
+    /// ```
+    pub fn insert_chunk(&mut self, chunk: Chunk) -> Option<Chunk> {
+        let pos = chunk.pos();
+        return self.with_leaf(pos, |slot| {
+            let old = slot.replace(Node::leaf(chunk));
+            return old.and_then(|node| match node.content {
+                Content::Leaf(chunk) => Some(*chunk),
+                Content::Branch(_) => None
+            });
+        });
+    }
+
+    pub fn remove_chunk(&mut self, pos: ChunkPos) -> Option<Chunk> {
+        return self.with_leaf(pos, |slot| {
+            return slot.take().and_then(|node| match node.content {
+                Content::Leaf(chunk) => Some(*chunk),
+                Content::Branch(_) => None
+            });
+        });
+    }
+
+    pub fn chunk(&self, pos: ChunkPos) -> Option<&Chunk> {
+        return match &self.node(LodPos::new(0, pos.x, pos.y, pos.z))?.content {
+            Content::Leaf(chunk) => Some(chunk),
+            Content::Branch(_) => None
+        };
+    }
+
+    /// Change a materialized chunk, updating the summaries above it. None if the chunk isn't materialized.
+    pub fn modify_chunk<F, T>(&mut self, pos: ChunkPos, func: F) -> Option<T>
+    where F: FnOnce(&mut Chunk) -> T {
+        self.chunk(pos)?;
+        return self.with_leaf(pos, |slot| {
+            let node = slot.as_mut()?;
+            let Content::Leaf(chunk) = &mut node.content else {
+                return None;
+            };
+            let result = func(chunk);
+            node.refresh();
+            return Some(result);
+        });
+    }
+
+    /// Set a block in a materialized chunk, returning the previous id.
+    pub fn set_block(&mut self, block: BlockPos, id: BlockId) -> Option<BlockId> {
+        return self.modify_chunk(block.chunk(), |chunk| chunk.set_block(block.local(), id));
+    }
+
+    pub fn get_block(&self, block: BlockPos) -> Option<BlockId> {
+        return Some(self.chunk(block.chunk())?.get_block(block.local()));
+    }
+
+    /// Get a chunk, generating and inserting it first if it isn't materialized.
+    /// ```
+    /// # use shared::engine::world::{chunk::Chunk, universe::UniverseTree};
+    /// # use shared::engine::math::coords::ChunkPos;
+    /// let mut tree = UniverseTree::new();
+    /// let generator = |pos: ChunkPos| Chunk::filled(pos, if pos.y < 0 { 1 } else { 0 });
+    /// assert_eq!(tree.chunk_or_generate(ChunkPos::new(0, -1, 0), &generator).non_air_count(), 32 * 32 * 32);
+    /// assert_eq!(tree.chunk_count(), 1);
+    /// ```
+    pub fn chunk_or_generate(&mut self, pos: ChunkPos, generator: &dyn ChunkGenerator) -> &Chunk {
+        if self.chunk(pos).is_none() {
+            self.insert_chunk(generator.generate(pos));
+        }
+        return self.chunk(pos).unwrap();
+    }
+
+    /// Summary of a node at any level up to the root level. None if nothing below it is materialized.
+    pub fn summary(&self, pos: LodPos) -> Option<NodeSummary> {
+        return self.node(pos).map(|node| node.summary);
+    }
+
+    /// Materialized children of a node, with their summaries, for walking the tree down from any depth.
+    pub fn children(&self, pos: LodPos) -> Vec<(LodPos, NodeSummary)> {
+        let Some(children) = self.node(pos).and_then(|node| node.children()) else {
+            return Vec::new();
+        };
+        return pos.children().into_iter()
+            .filter_map(|child| children[child_index(child)].as_ref().map(|node| (child, node.summary)))
+            .collect();
+    }
+
+    /// Every materialized node at a level, with its summary.
+    pub fn nodes_at(&self, level: u8) -> Vec<(LodPos, NodeSummary)> {
+        let mut found = Vec::new();
+        let mut stack: Vec<LodPos> = self.roots.keys().map(|(x, y, z)| LodPos::new(self.root_level, *x, *y, *z)).collect();
+        while let Some(pos) = stack.pop() {
+            if pos.level == level {
+                if let Some(summary) = self.summary(pos) {
+                    found.push((pos, summary));
+                }
+            } else if pos.level > level {
+                stack.extend(self.children(pos).into_iter().map(|(child, _)| child));
+            }
+        }
+        found.sort_unstable_by_key(|(pos, _)| *pos);
+        return found;
+    }
+
+    /// Materialize every chunk below a node with a generator, skipping ones already materialized.
+    /// Intended for small nodes, as a node at level n holds 8^n chunks.
+    pub fn materialize(&mut self, pos: LodPos, generator: &dyn ChunkGenerator) {
+        let min = pos.min_chunk();
+        let size = pos.chunks_per_axis();
+        for y in 0..size {
+            for z in 0..size {
+                for x in 0..size {
+                    self.chunk_or_generate(min + ChunkPos::new(x, y, z), generator);
+                }
+            }
+        }
+    }
+}
+
+impl Default for UniverseTree {
+    fn default() -> UniverseTree {
+        return UniverseTree::new();
+    }
+}
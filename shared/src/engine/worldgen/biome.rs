@@ -0,0 +1,260 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{block::{registry::BlockRegistry, BlockId}, math::{coords::ChunkPos, noise::FractalNoise, rng::WorldRng, vector::Vec3}};
+
+use super::blocks::TerrainBlocks;
+
+pub type BiomeId = u8;
+
+/// How a region of the world looks and generates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Biome {
+    pub name: String,
+    /// Climate the biome is found in, from -1 (cold) to 1 (hot).
+    pub temperature: f32,
+    /// Climate the biome is found in, from -1 (dry) to 1 (wet).
+    pub humidity: f32,
+    /// Top block of land columns above sea level.
+    pub surface: BlockId,
+    /// Blocks between the surface and stone.
+    pub subsurface: BlockId,
+    /// Blocks added to the base terrain height.
+    pub height_offset: f32,
+    /// Multiplier on hill height.
+    pub height_scale: f32,
+    /// Chance per column of placing a decoration, such as a tree.
+    pub decoration_density: f32,
+    /// Grass and foliage color, multiplied with their textures.
+    pub tint: Vec3
+}
+
+/// A biome added by a content pack, naming its blocks rather than giving their ids.
+/// ```
+/// # use shared::engine::block::BlockRegistry;
+/// # use shared::engine::worldgen::biome::BiomeDefinition;
+/// let mut blocks = BlockRegistry::new();
+/// let sand = blocks.register("cube:sand").unwrap();
+/// let definition: BiomeDefinition = toml::from_str(r#"
+///     name = "dunes:dunes"
+///     temperature = 0.9
+///     humidity = -0.9
+///     surface = "cube:sand"
+///     subsurface = "cube:sand"
+///     height_scale = 1.5
+/// "#).unwrap();
+/// let biome = definition.resolve(&blocks).unwrap();
+/// assert_eq!((biome.surface, biome.height_scale, biome.height_offset), (sand, 1.5, 0.0));
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BiomeDefinition {
+    pub name: String,
+    pub temperature: f32,
+    pub humidity: f32,
+    pub surface: String,
+    pub subsurface: String,
+    #[serde(default)]
+    pub height_offset: f32,
+    #[serde(default = "default_height_scale")]
+    pub height_scale: f32,
+    #[serde(default)]
+    pub decoration_density: f32,
+    #[serde(default = "default_tint")]
+    pub tint: [f32; 3]
+}
+
+fn default_height_scale() -> f32 {
+    return 1.0;
+}
+
+fn default_tint() -> [f32; 3] {
+    return [1.0; 3];
+}
+
+impl BiomeDefinition {
+    /// The biome, with its blocks looked up in a registry. Fails naming the first block that isn't registered,
+    /// or a climate outside -1 to 1.
+    pub fn resolve(&self, blocks: &BlockRegistry) -> Result<Biome, String> {
+        if !(-1.0..=1.0).contains(&self.temperature) || !(-1.0..=1.0).contains(&self.humidity) {
+            return Err(format!("biome {} has a climate outside -1 to 1", self.name));
+        }
+        let block = |name: &str| blocks.id(name).ok_or_else(|| format!("biome {} uses unknown block {}", self.name, name));
+        return Ok(Biome {
+            name: self.name.clone(),
+            temperature: self.temperature,
+            humidity: self.humidity,
+            surface: block(&self.surface)?,
+            subsurface: block(&self.subsurface)?,
+            height_offset: self.height_offset,
+            height_scale: self.height_scale,
+            decoration_density: self.decoration_density,
+            tint: Vec3::new(self.tint[0], self.tint[1], self.tint[2])
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BiomeRegistryError {
+    Duplicate(String),
+    Full
+}
+
+impl fmt::Display for BiomeRegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BiomeRegistryError::Duplicate(name) => write!(f, "biome {} is already registered", name),
+            BiomeRegistryError::Full => write!(f, "cannot register more than {} biomes", BiomeId::MAX as usize + 1)
+        }
+    }
+}
+
+impl std::error::Error for BiomeRegistryError {}
+
+/// Every biome the generator can place, looked up by id or name.
+#[derive(Default)]
+pub struct BiomeRegistry {
+    biomes: Vec<Biome>,
+    ids: HashMap<String, BiomeId>
+}
+
+impl BiomeRegistry {
+    pub fn new() -> BiomeRegistry {
+        return BiomeRegistry::default();
+    }
+
+    /// Plains, forest, desert, tundra and mountains, built from the terrain blocks.
+    /// ```
+    /// # use shared::engine::block::BlockRegistry;
+    /// # use shared::engine::worldgen::{biome::BiomeRegistry, blocks::TerrainBlocks};
+    /// let blocks = TerrainBlocks::register(&mut BlockRegistry::new()).unwrap();
+    /// let biomes = BiomeRegistry::defaults(&blocks);
+    /// let desert = biomes.id("cube:desert").unwrap();
+    /// assert_eq!(biomes.get(desert).unwrap().surface, blocks.sand);
+    /// assert_eq!(biomes.nearest(0.9, -0.9), Some(desert));
+    /// ```
+    pub fn defaults(blocks: &TerrainBlocks) -> BiomeRegistry {
+        let biome = |name: &str, temperature: f32, humidity: f32, surface: BlockId, subsurface: BlockId| Biome {
+            name: name.to_string(),
+            temperature,
+            humidity,
+            surface,
+            subsurface,
+            height_offset: 0.0,
+            height_scale: 1.0,
+            decoration_density: 0.0,
+            tint: Vec3::ONE
+        };
+        let mut registry = BiomeRegistry::new();
+        let defaults = [
+            Biome { decoration_density: 0.002, tint: Vec3::new(0.55, 0.8, 0.35), ..biome("cube:plains", 0.2, 0.0, blocks.grass, blocks.dirt) },
+            Biome { decoration_density: 0.04, tint: Vec3::new(0.35, 0.65, 0.25), ..biome("cube:forest", 0.3, 0.6, blocks.grass, blocks.dirt) },
+            Biome { height_offset: 2.0, height_scale: 0.5, tint: Vec3::new(0.75, 0.7, 0.4), ..biome("cube:desert", 0.8, -0.7, blocks.sand, blocks.sand) },
+            Biome { decoration_density: 0.005, tint: Vec3::new(0.5, 0.65, 0.6), ..biome("cube:tundra", -0.7, -0.2, blocks.grass, blocks.dirt) },
+            Biome { height_offset: 24.0, height_scale: 2.5, tint: Vec3::new(0.45, 0.6, 0.45), ..biome("cube:mountains", -0.3, 0.5, blocks.stone, blocks.stone) }
+        ];
+        for biome in defaults {
+            registry.register(biome).unwrap();
+        }
+        return registry;
+    }
+
+    pub fn register(&mut self, biome: Biome) -> Result<BiomeId, BiomeRegistryError> {
+        if self.ids.contains_key(&biome.name) {
+            return Err(BiomeRegistryError::Duplicate(biome.name));
+        }
+        if self.biomes.len() > BiomeId::MAX as usize {
+            return Err(BiomeRegistryError::Full);
+        }
+        let id = self.biomes.len() as BiomeId;
+        self.ids.insert(biome.name.clone(), id);
+        self.biomes.push(biome);
+        return Ok(id);
+    }
+
+    /// Replace a registered biome with one of the same name, keeping its id. None if it isn't registered.
+    pub fn replace(&mut self, biome: Biome) -> Option<BiomeId> {
+        let id = self.id(&biome.name)?;
+        self.biomes[id as usize] = biome;
+        return Some(id);
+    }
+
+    pub fn id(&self, name: &str) -> Option<BiomeId> {
+        return self.ids.get(name).copied();
+    }
+
+    pub fn get(&self, id: BiomeId) -> Option<&Biome> {
+        return self.biomes.get(id as usize);
+    }
+
+    pub fn biomes(&self) -> &[Biome] {
+        return &self.biomes;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.biomes.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.biomes.is_empty();
+    }
+
+    /// The biome whose climate is closest to the given one. None if no biomes are registered.
+    pub fn nearest(&self, temperature: f32, humidity: f32) -> Option<BiomeId> {
+        let distance = |biome: &Biome| (biome.temperature - temperature).powi(2) + (biome.humidity - humidity).powi(2);
+        return self.biomes.iter().enumerate()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(id, _)| id as BiomeId);
+    }
+}
+
+/// Decides the biome of every column from a noise based climate map of temperature and humidity.
+pub struct BiomeSource {
+    registry: Arc<BiomeRegistry>,
+    temperature: FractalNoise,
+    humidity: FractalNoise
+}
+
+impl BiomeSource {
+    /// Will panic in debug mode if the registry is empty.
+    /// ```
+    /// # use shared::engine::block::BlockRegistry;
+    /// # use shared::engine::worldgen::{biome::{BiomeRegistry, BiomeSource}, blocks::TerrainBlocks};
+    /// # use std::sync::Arc;
+    /// let blocks = TerrainBlocks::register(&mut BlockRegistry::new()).unwrap();
+    /// let source = BiomeSource::new(5, Arc::new(BiomeRegistry::defaults(&blocks)));
+    /// let biome = source.biome_at(100, -40);
+    /// assert_eq!(biome, BiomeSource::new(5, source.registry().clone()).biome_at(100, -40));
+    /// assert!(source.registry().get(biome).is_some());
+    /// ```
+    pub fn new(seed: u64, registry: Arc<BiomeRegistry>) -> BiomeSource {
+        debug_assert!(!registry.is_empty(), "Cannot create a biome source without biomes");
+        let mut rng = WorldRng::for_feature(seed, ChunkPos::new(0, 0, 0), "biomes");
+        return BiomeSource {
+            registry,
+            temperature: FractalNoise::new(rng.next_u64(), 3, 1.0 / 768.0),
+            humidity: FractalNoise::new(rng.next_u64(), 3, 1.0 / 640.0)
+        };
+    }
+
+    pub fn registry(&self) -> &Arc<BiomeRegistry> {
+        return &self.registry;
+    }
+
+    /// Temperature and humidity of a column, each from -1 to 1.
+    pub fn climate(&self, x: i32, z: i32) -> (f32, f32) {
+        // Fractal noise rarely reaches its extremes, so stretch it to reach every biome.
+        let stretch = |value: f64| (value * 1.8).clamp(-1.0, 1.0) as f32;
+        return (stretch(self.temperature.sample2(x as f64, z as f64)), stretch(self.humidity.sample2(x as f64, z as f64)));
+    }
+
+    pub fn biome_at(&self, x: i32, z: i32) -> BiomeId {
+        let (temperature, humidity) = self.climate(x, z);
+        return self.registry.nearest(temperature, humidity).unwrap_or(0);
+    }
+
+    pub fn biome(&self, id: BiomeId) -> &Biome {
+        return &self.registry.biomes()[id as usize];
+    }
+}
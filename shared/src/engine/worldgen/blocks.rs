@@ -0,0 +1,46 @@
+use crate::engine::block::{BlockId, BlockRegistry, registry::BlockRegistryError};
+
+/// Blocks the built in generators place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TerrainBlocks {
+    pub stone: BlockId,
+    pub dirt: BlockId,
+    pub grass: BlockId,
+    pub sand: BlockId,
+    pub water: BlockId,
+    pub log: BlockId,
+    pub leaves: BlockId,
+    pub cobblestone: BlockId,
+    pub coal_ore: BlockId,
+    pub iron_ore: BlockId
+}
+
+impl TerrainBlocks {
+    /// Register the terrain blocks, or look them up if another system already registered them.
+    /// ```
+    /// # use shared::engine::block::BlockRegistry;
+    /// # use shared::engine::worldgen::blocks::TerrainBlocks;
+    /// let mut registry = BlockRegistry::new();
+    /// let blocks = TerrainBlocks::register(&mut registry).unwrap();
+    /// assert_eq!(registry.name(blocks.grass), Some("cube:grass"));
+    /// assert_eq!(TerrainBlocks::register(&mut registry).unwrap(), blocks);
+    /// ```
+    pub fn register(registry: &mut BlockRegistry) -> Result<TerrainBlocks, BlockRegistryError> {
+        let mut get = |name: &str| match registry.id(name) {
+            Some(id) => Ok(id),
+            None => registry.register(name)
+        };
+        return Ok(TerrainBlocks {
+            stone: get("cube:stone")?,
+            dirt: get("cube:dirt")?,
+            grass: get("cube:grass")?,
+            sand: get("cube:sand")?,
+            water: get("cube:water")?,
+            log: get("cube:log")?,
+            leaves: get("cube:leaves")?,
+            cobblestone: get("cube:cobblestone")?,
+            coal_ore: get("cube:coal_ore")?,
+            iron_ore: get("cube:iron_ore")?
+        });
+    }
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use crate::engine::{block::{BlockId, AIR}, math::{coords::{BlockPos, CHUNK_SIZE}, rng::WorldRng}};
+
+use super::{blocks::TerrainBlocks, feature::{Feature, FeatureContext}};
+
+/// The built in trees, ores, and ruins.
+pub fn default_features(blocks: &TerrainBlocks) -> Vec<Arc<dyn Feature>> {
+    return vec![
+        Arc::new(OreFeature::new("cube:coal_ore", blocks.coal_ore, blocks.stone, 12, 10, -256..64)),
+        Arc::new(OreFeature::new("cube:iron_ore", blocks.iron_ore, blocks.stone, 6, 6, -256..16)),
+        Arc::new(TreeFeature::new(blocks.log, blocks.leaves, blocks.grass)),
+        Arc::new(RuinFeature::new(blocks.cobblestone, 0.01))
+    ];
+}
+
+/// Picks how many of something to place from an expected count, keeping the fractional part as a chance of one more.
+fn count(rng: &mut WorldRng, expected: f32) -> u32 {
+    let whole = expected.floor();
+    return whole as u32 + rng.chance((expected - whole) as f64) as u32;
+}
+
+/// A random column within the chunk being decorated.
+fn random_column(context: &FeatureContext, rng: &mut WorldRng) -> (i32, i32) {
+    let origin = context.chunk_pos().origin();
+    return (origin.x + rng.range_i32(0..CHUNK_SIZE), origin.z + rng.range_i32(0..CHUNK_SIZE));
+}
+
+/// Trees growing on a ground block, as often as the biome's decoration density.
+pub struct TreeFeature {
+    log: BlockId,
+    leaves: BlockId,
+    ground: BlockId,
+    /// Density used when the generator has no biomes.
+    density: f32
+}
+
+impl TreeFeature {
+    pub fn new(log: BlockId, leaves: BlockId, ground: BlockId) -> TreeFeature {
+        return TreeFeature { log, leaves, ground, density: 0.005 };
+    }
+
+    fn grow(&self, context: &mut FeatureContext, rng: &mut WorldRng, base: BlockPos) {
+        let height = rng.range_i32(4..7);
+        let top = base.offset(0, height, 0);
+        for y in -2..=1 {
+            let radius: i32 = if y < 0 { 2 } else { 1 };
+            for z in -radius..=radius {
+                for x in -radius..=radius {
+                    // Trim the corners of the wide layers at random, so trees aren't perfect boxes.
+                    if radius == 2 && x.abs() == 2 && z.abs() == 2 && rng.chance(0.5) {
+                        continue;
+                    }
+                    context.replace_block(top.offset(x, y, z), AIR, self.leaves);
+                }
+            }
+        }
+        context.replace_block(top.offset(0, 2, 0), AIR, self.leaves);
+        for y in 0..height {
+            context.set_block(base.offset(0, y, 0), self.log);
+        }
+    }
+}
+
+impl Feature for TreeFeature {
+    fn name(&self) -> &str {
+        return "cube:trees";
+    }
+
+    fn place(&self, context: &mut FeatureContext, rng: &mut WorldRng) {
+        let center = context.chunk_pos().origin().offset(CHUNK_SIZE / 2, 0, CHUNK_SIZE / 2);
+        let density = context.biome(center.x, center.z).map(|biome| biome.decoration_density).unwrap_or(self.density);
+        for _ in 0..count(rng, density * (CHUNK_SIZE * CHUNK_SIZE) as f32) {
+            let (x, z) = random_column(context, rng);
+            let surface = match context.surface(x, z) {
+                Some(surface) => surface,
+                None => continue
+            };
+            if context.get_block(BlockPos::new(x, surface, z)) != Some(self.ground) {
+                continue;
+            }
+            self.grow(context, rng, BlockPos::new(x, surface + 1, z));
+        }
+    }
+}
+
+/// Veins of ore replacing a host block within a height range.
+pub struct OreFeature {
+    name: String,
+    ore: BlockId,
+    replace: BlockId,
+    veins_per_chunk: u32,
+    vein_size: u32,
+    heights: std::ops::Range<i32>
+}
+
+impl OreFeature {
+    pub fn new(name: &str, ore: BlockId, replace: BlockId, veins_per_chunk: u32, vein_size: u32, heights: std::ops::Range<i32>) -> OreFeature {
+        return OreFeature { name: name.to_string(), ore, replace, veins_per_chunk, vein_size, heights };
+    }
+}
+
+impl Feature for OreFeature {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn place(&self, context: &mut FeatureContext, rng: &mut WorldRng) {
+        let origin = context.chunk_pos().origin();
+        if origin.y >= self.heights.end || origin.y + CHUNK_SIZE <= self.heights.start {
+            return;
+        }
+        for _ in 0..self.veins_per_chunk {
+            let (x, z) = random_column(context, rng);
+            let y = origin.y + rng.range_i32(0..CHUNK_SIZE);
+            if !self.heights.contains(&y) {
+                continue;
+            }
+            // A random walk, so veins are irregular blobs that can cross into neighboring chunks.
+            let mut block = BlockPos::new(x, y, z);
+            for _ in 0..self.vein_size {
+                context.replace_block(block, self.replace, self.ore);
+                block = block.offset(rng.range_i32(-1..2), rng.range_i32(-1..2), rng.range_i32(-1..2));
+            }
+        }
+    }
+}
+
+/// Rare crumbling walls left on the surface.
+pub struct RuinFeature {
+    wall: BlockId,
+    chance: f64
+}
+
+impl RuinFeature {
+    /// chance is per chunk.
+    pub fn new(wall: BlockId, chance: f64) -> RuinFeature {
+        return RuinFeature { wall, chance };
+    }
+}
+
+impl Feature for RuinFeature {
+    fn name(&self) -> &str {
+        return "cube:ruins";
+    }
+
+    fn place(&self, context: &mut FeatureContext, rng: &mut WorldRng) {
+        if !rng.chance(self.chance) {
+            return;
+        }
+        let (x, z) = random_column(context, rng);
+        let surface = match context.surface(x, z) {
+            Some(surface) => surface,
+            None => return
+        };
+        let size = rng.range_i32(5..9);
+        let base = BlockPos::new(x, surface + 1, z);
+        for i in 0..size {
+            for (dx, dz) in [(i, 0), (i, size - 1), (0, i), (size - 1, i)] {
+                let height = rng.range_i32(0..4);
+                for y in -1..height {
+                    context.set_block(base.offset(dx, y, dz), self.wall);
+                }
+            }
+        }
+    }
+}
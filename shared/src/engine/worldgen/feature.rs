@@ -0,0 +1,232 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use crate::engine::{
+    block::BlockId,
+    math::{coords::{BlockPos, ChunkPos, LocalPos, CHUNK_SIZE}, rng::WorldRng},
+    world::{chunk::Chunk, loader::ChunkGenerator, World}
+};
+
+use super::{biome::{Biome, BiomeSource}, generator::WorldGenerator};
+
+/// Something placed into terrain after it is shaped, such as a tree, ore vein, or ruin.
+/// Features are placed once per chunk with a random number generator seeded from the world seed, chunk, and feature name,
+/// so the same chunk always gets the same features.
+pub trait Feature: Send + Sync {
+    /// Unique name, which also seeds the feature's placement.
+    fn name(&self) -> &str;
+
+    /// Place any instances of the feature belonging to the chunk being decorated.
+    /// Blocks may be placed outside the chunk, and are queued until the chunk they land in is generated or loaded.
+    fn place(&self, context: &mut FeatureContext, rng: &mut WorldRng);
+}
+
+/// A block waiting for its chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub local: LocalPos,
+    pub id: BlockId,
+    /// Only place over this block, such as leaves only replacing air. None to always place.
+    pub replace: Option<BlockId>
+}
+
+impl Placement {
+    /// Apply to a chunk, returning if the block was placed.
+    pub fn apply(&self, chunk: &mut Chunk) -> bool {
+        if let Some(replace) = self.replace {
+            if chunk.get_block(self.local) != replace {
+                return false;
+            }
+        }
+        chunk.set_block(self.local, self.id);
+        return true;
+    }
+}
+
+/// Blocks features placed in chunks other than the one being decorated, waiting for their chunk to exist.
+#[derive(Default)]
+pub struct PendingPlacements {
+    chunks: Mutex<HashMap<ChunkPos, Vec<Placement>>>
+}
+
+impl PendingPlacements {
+    pub fn new() -> PendingPlacements {
+        return PendingPlacements::default();
+    }
+
+    pub fn push(&self, chunk: ChunkPos, placement: Placement) {
+        self.chunks.lock().unwrap().entry(chunk).or_default().push(placement);
+    }
+
+    /// Remove and return every placement waiting for a chunk.
+    pub fn take(&self, chunk: ChunkPos) -> Vec<Placement> {
+        return self.chunks.lock().unwrap().remove(&chunk).unwrap_or_default();
+    }
+
+    /// Number of chunks with placements waiting.
+    pub fn chunk_count(&self) -> usize {
+        return self.chunks.lock().unwrap().len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.chunks.lock().unwrap().is_empty();
+    }
+
+    /// Apply placements waiting for chunks already loaded into a world, such as when a tree is decorated
+    /// next to a chunk that was generated first. Placements for unloaded chunks keep waiting.
+    /// ```
+    /// # use shared::engine::worldgen::feature::{PendingPlacements, Placement};
+    /// # use shared::engine::world::{World, chunk::Chunk};
+    /// # use shared::engine::math::coords::{BlockPos, ChunkPos, LocalPos};
+    /// let pending = PendingPlacements::new();
+    /// pending.push(ChunkPos::new(0, 0, 0), Placement { local: LocalPos::new(1, 2, 3), id: 5, replace: None });
+    /// pending.push(ChunkPos::new(1, 0, 0), Placement { local: LocalPos::new(1, 2, 3), id: 5, replace: None });
+    /// let world = World::new();
+    /// world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    /// pending.apply_loaded(&world);
+    /// assert_eq!(world.get_block(BlockPos::new(1, 2, 3)), Some(5));
+    /// assert_eq!(pending.chunk_count(), 1);
+    /// ```
+    pub fn apply_loaded(&self, world: &World) {
+        let mut chunks = self.chunks.lock().unwrap();
+        (*chunks).retain(|pos, placements| {
+            let chunk = match world.chunk(*pos) {
+                Some(chunk) => chunk,
+                None => return true
+            };
+            let mut lock = chunk.write().unwrap();
+            for placement in placements.iter() {
+                placement.apply(&mut lock);
+            }
+            return false;
+        });
+    }
+}
+
+/// The chunk being decorated, and a way to place blocks around it.
+pub struct FeatureContext<'a> {
+    chunk: &'a mut Chunk,
+    biomes: Option<&'a BiomeSource>,
+    pending: &'a PendingPlacements
+}
+
+impl<'a> FeatureContext<'a> {
+    pub fn new(chunk: &'a mut Chunk, biomes: Option<&'a BiomeSource>, pending: &'a PendingPlacements) -> FeatureContext<'a> {
+        return FeatureContext { chunk, biomes, pending };
+    }
+
+    pub fn chunk_pos(&self) -> ChunkPos {
+        return self.chunk.pos();
+    }
+
+    /// Block at a position. None if it is outside the chunk being decorated.
+    pub fn get_block(&self, block: BlockPos) -> Option<BlockId> {
+        if block.chunk() != self.chunk.pos() {
+            return None;
+        }
+        return Some(self.chunk.get_block(block.local()));
+    }
+
+    /// Place a block, queueing it if it is outside the chunk being decorated.
+    pub fn set_block(&mut self, block: BlockPos, id: BlockId) {
+        self.place(block, Placement { local: block.local(), id, replace: None });
+    }
+
+    /// Place a block only where it replaces a specific block, such as ore replacing stone.
+    pub fn replace_block(&mut self, block: BlockPos, replace: BlockId, id: BlockId) {
+        self.place(block, Placement { local: block.local(), id, replace: Some(replace) });
+    }
+
+    fn place(&mut self, block: BlockPos, placement: Placement) {
+        let chunk = block.chunk();
+        if chunk == self.chunk.pos() {
+            placement.apply(self.chunk);
+        } else {
+            self.pending.push(chunk, placement);
+        }
+    }
+
+    /// Biome of a column. None if the generator doesn't use biomes.
+    pub fn biome(&self, x: i32, z: i32) -> Option<&Biome> {
+        let biomes = self.biomes?;
+        return Some(biomes.biome(biomes.biome_at(x, z)));
+    }
+
+    /// Y of the highest non air block of a column within the chunk being decorated.
+    /// None if the column is outside the chunk, has no blocks, or its top block is the top of the chunk,
+    /// as then the real surface may be in the chunk above.
+    pub fn surface(&self, x: i32, z: i32) -> Option<i32> {
+        let origin = self.chunk.pos().origin();
+        if x.div_euclid(CHUNK_SIZE) != self.chunk.pos().x || z.div_euclid(CHUNK_SIZE) != self.chunk.pos().z {
+            return None;
+        }
+        let top = self.chunk.height(x.rem_euclid(CHUNK_SIZE) as u8, z.rem_euclid(CHUNK_SIZE) as u8)?;
+        if top == CHUNK_SIZE as u8 - 1 {
+            return None;
+        }
+        return Some(origin.y + top as i32);
+    }
+}
+
+/// Generates terrain with another generator, then decorates each chunk with features.
+pub struct FeatureGenerator {
+    seed: u64,
+    base: Arc<dyn WorldGenerator>,
+    features: Vec<Arc<dyn Feature>>,
+    pending: PendingPlacements
+}
+
+impl FeatureGenerator {
+    pub fn new(seed: u64, base: Arc<dyn WorldGenerator>) -> FeatureGenerator {
+        return FeatureGenerator { seed, base, features: Vec::new(), pending: PendingPlacements::new() };
+    }
+
+    /// Features are placed in the order they are added.
+    pub fn add_feature(&mut self, feature: Arc<dyn Feature>) {
+        self.features.push(feature);
+    }
+
+    pub fn features(&self) -> &[Arc<dyn Feature>] {
+        return &self.features;
+    }
+
+    /// Blocks placed across chunk borders that haven't reached their chunk yet.
+    /// Call PendingPlacements::apply_loaded() after inserting generated chunks into the world.
+    pub fn pending(&self) -> &PendingPlacements {
+        return &self.pending;
+    }
+
+    /// Place every feature into a generated chunk, then any blocks neighboring chunks placed into it.
+    pub fn decorate(&self, chunk: &mut Chunk) {
+        let pos = chunk.pos();
+        let mut context = FeatureContext::new(chunk, self.base.biomes().map(|biomes| biomes.as_ref()), &self.pending);
+        for feature in self.features.iter() {
+            let mut rng = WorldRng::for_feature(self.seed, pos, feature.name());
+            feature.place(&mut context, &mut rng);
+        }
+        for placement in self.pending.take(pos) {
+            placement.apply(chunk);
+        }
+    }
+}
+
+impl ChunkGenerator for FeatureGenerator {
+    fn generate(&self, pos: ChunkPos) -> Chunk {
+        let mut chunk = self.base.generate(pos);
+        self.decorate(&mut chunk);
+        return chunk;
+    }
+}
+
+impl WorldGenerator for FeatureGenerator {
+    fn name(&self) -> &str {
+        return self.base.name();
+    }
+
+    fn surface_height(&self, x: i32, z: i32) -> Option<i32> {
+        return self.base.surface_height(x, z);
+    }
+
+    fn biomes(&self) -> Option<&Arc<BiomeSource>> {
+        return self.base.biomes();
+    }
+}
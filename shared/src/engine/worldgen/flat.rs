@@ -0,0 +1,64 @@
+use crate::engine::{block::{BlockId, AIR}, math::coords::{ChunkPos, LocalPos, CHUNK_SIZE}, world::{chunk::Chunk, loader::ChunkGenerator}};
+
+use super::generator::WorldGenerator;
+
+/// Generates horizontal layers of blocks starting at y = 0, with air everywhere else. Useful for testing and creative worlds.
+pub struct FlatGenerator {
+    /// Block at each y, from 0 upwards.
+    layers: Vec<BlockId>
+}
+
+impl FlatGenerator {
+    /// Create from (block, thickness) pairs, bottom layer first.
+    /// ```
+    /// # use shared::engine::worldgen::{flat::FlatGenerator, generator::WorldGenerator};
+    /// # use shared::engine::world::loader::ChunkGenerator;
+    /// # use shared::engine::math::coords::{ChunkPos, LocalPos};
+    /// let generator = FlatGenerator::new(&[(1, 60), (2, 3), (3, 1)]);
+    /// assert_eq!(generator.surface_height(0, 0), Some(63));
+    /// let chunk = generator.generate(ChunkPos::new(0, 1, 0));
+    /// assert_eq!(chunk.get_block(LocalPos::new(0, 27, 0)), 1);
+    /// assert_eq!(chunk.get_block(LocalPos::new(0, 31, 0)), 3);
+    /// assert!(generator.generate(ChunkPos::new(0, 2, 0)).is_empty());
+    /// ```
+    pub fn new(layers: &[(BlockId, u32)]) -> FlatGenerator {
+        let layers = layers.iter().flat_map(|(id, thickness)| std::iter::repeat_n(*id, *thickness as usize)).collect();
+        return FlatGenerator { layers };
+    }
+
+    fn block_at(&self, y: i32) -> BlockId {
+        if y < 0 {
+            return AIR;
+        }
+        return self.layers.get(y as usize).copied().unwrap_or(AIR);
+    }
+}
+
+impl ChunkGenerator for FlatGenerator {
+    fn generate(&self, pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        let origin = pos.origin();
+        for y in 0..CHUNK_SIZE {
+            let id = self.block_at(origin.y + y);
+            if id == AIR {
+                continue;
+            }
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    chunk.set_block(LocalPos::new(x as u8, y as u8, z as u8), id);
+                }
+            }
+        }
+        return chunk;
+    }
+}
+
+impl WorldGenerator for FlatGenerator {
+    fn name(&self) -> &str {
+        return "flat";
+    }
+
+    fn surface_height(&self, _x: i32, _z: i32) -> Option<i32> {
+        return self.layers.iter().rposition(|id| *id != AIR).map(|y| y as i32);
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use crate::engine::{job::{system::JobSystem, future::JobFuture}, math::coords::ChunkPos, world::{chunk::Chunk, loader::ChunkGenerator}};
+
+use super::biome::BiomeSource;
+
+/// Generates the chunks of a world from its seed. Generators are deterministic, so a chunk generated twice,
+/// on any thread or machine, is identical. Any generator can be given to a ChunkLoader.
+pub trait WorldGenerator: ChunkGenerator {
+    /// Name stored in world metadata, so the world is reopened with the same generator.
+    fn name(&self) -> &str;
+
+    /// Y of the highest solid block in a column, used to pick spawn points. None if the column has no blocks.
+    fn surface_height(&self, x: i32, z: i32) -> Option<i32>;
+
+    /// Biomes the generator places, for looking up the biome of loaded terrain. None if it doesn't use biomes.
+    fn biomes(&self) -> Option<&Arc<BiomeSource>> {
+        return None;
+    }
+}
+
+/// Queue a job generating a chunk.
+/// ```
+/// # use shared::engine::worldgen::{generator::{queue_generate_job, VoidGenerator}};
+/// # use shared::engine::job::system::JobSystem;
+/// # use shared::engine::math::coords::ChunkPos;
+/// # use std::sync::Arc;
+/// let jobs = JobSystem::new(1);
+/// let chunk = queue_generate_job(&jobs, Arc::new(VoidGenerator), ChunkPos::new(0, 0, 0)).wait();
+/// assert!(chunk.is_empty());
+/// ```
+pub fn queue_generate_job(jobs: &JobSystem, generator: Arc<dyn WorldGenerator>, pos: ChunkPos) -> JobFuture<Chunk> {
+    return jobs.run_job(move || generator.generate(pos));
+}
+
+/// Generates nothing but air.
+pub struct VoidGenerator;
+
+impl ChunkGenerator for VoidGenerator {
+    fn generate(&self, pos: ChunkPos) -> Chunk {
+        return Chunk::new(pos);
+    }
+}
+
+impl WorldGenerator for VoidGenerator {
+    fn name(&self) -> &str {
+        return "void";
+    }
+
+    fn surface_height(&self, _x: i32, _z: i32) -> Option<i32> {
+        return None;
+    }
+}
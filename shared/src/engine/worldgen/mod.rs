@@ -0,0 +1,9 @@
+pub mod generator;
+pub mod blocks;
+pub mod biome;
+pub mod flat;
+pub mod terrain;
+pub mod feature;
+pub mod decoration;
+
+pub use generator::WorldGenerator;
\ No newline at end of file
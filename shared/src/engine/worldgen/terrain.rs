@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use crate::engine::{
+    block::AIR,
+    math::{coords::{ChunkPos, LocalPos, CHUNK_SIZE}, noise::FractalNoise, rng::WorldRng},
+    world::{chunk::Chunk, loader::ChunkGenerator}
+};
+
+use super::{biome::{BiomeRegistry, BiomeSource}, blocks::TerrainBlocks, generator::WorldGenerator};
+
+/// Default y of the sea surface.
+pub const DEFAULT_SEA_LEVEL: i32 = 0;
+
+const SIZE: usize = CHUNK_SIZE as usize;
+
+/// Spacing of the grid biome heights are blended across, so terrain doesn't step at biome borders.
+const BIOME_BLEND_SPACING: i32 = 8;
+
+/// The default generator, shaping terrain from layered noise.
+/// Continentalness decides between ocean and land, erosion decides how rough the land is,
+/// 3D detail noise adds overhangs on rough terrain, and cave noise carves tunnels underground.
+/// Biomes raise or flatten the terrain and choose the surface blocks.
+pub struct NoiseGenerator {
+    blocks: TerrainBlocks,
+    biomes: Arc<BiomeSource>,
+    sea_level: i32,
+    continentalness: FractalNoise,
+    erosion: FractalNoise,
+    hills: FractalNoise,
+    detail: FractalNoise,
+    caves: FractalNoise
+}
+
+impl NoiseGenerator {
+    /// Uses the default biomes.
+    pub fn new(seed: u64, blocks: TerrainBlocks) -> NoiseGenerator {
+        let biomes = BiomeSource::new(seed, Arc::new(BiomeRegistry::defaults(&blocks)));
+        return NoiseGenerator::with_biomes(seed, blocks, Arc::new(biomes));
+    }
+
+    pub fn with_biomes(seed: u64, blocks: TerrainBlocks, biomes: Arc<BiomeSource>) -> NoiseGenerator {
+        let mut rng = WorldRng::new(seed);
+        return NoiseGenerator {
+            blocks,
+            biomes,
+            sea_level: DEFAULT_SEA_LEVEL,
+            continentalness: FractalNoise::new(rng.next_u64(), 4, 1.0 / 1024.0),
+            erosion: FractalNoise::new(rng.next_u64(), 3, 1.0 / 512.0),
+            hills: FractalNoise::new(rng.next_u64(), 4, 1.0 / 128.0),
+            detail: FractalNoise::new(rng.next_u64(), 3, 1.0 / 48.0),
+            caves: FractalNoise::new(rng.next_u64(), 2, 1.0 / 64.0)
+        };
+    }
+
+    pub fn sea_level(&self) -> i32 {
+        return self.sea_level;
+    }
+
+    /// Biome height offset and scale, bilinearly blended between the biomes at the corners of the blend grid cell.
+    fn biome_height(&self, x: i32, z: i32) -> (f64, f64) {
+        let (cell_x, cell_z) = (x.div_euclid(BIOME_BLEND_SPACING), z.div_euclid(BIOME_BLEND_SPACING));
+        let tx = x.rem_euclid(BIOME_BLEND_SPACING) as f64 / BIOME_BLEND_SPACING as f64;
+        let tz = z.rem_euclid(BIOME_BLEND_SPACING) as f64 / BIOME_BLEND_SPACING as f64;
+        let mut offset = 0.0;
+        let mut scale = 0.0;
+        for (dx, dz, weight) in [(0, 0, (1.0 - tx) * (1.0 - tz)), (1, 0, tx * (1.0 - tz)), (0, 1, (1.0 - tx) * tz), (1, 1, tx * tz)] {
+            let biome = self.biomes.biome(self.biomes.biome_at((cell_x + dx) * BIOME_BLEND_SPACING, (cell_z + dz) * BIOME_BLEND_SPACING));
+            offset += biome.height_offset as f64 * weight;
+            scale += biome.height_scale as f64 * weight;
+        }
+        return (offset, scale);
+    }
+
+    /// Terrain height of a column before 3D detail and caves.
+    fn height(&self, x: i32, z: i32) -> (f64, f64) {
+        let (fx, fz) = (x as f64, z as f64);
+        let continentalness = self.continentalness.sample2(fx, fz);
+        let roughness = 1.0 - (self.erosion.sample2(fx, fz) + 1.0) * 0.5;
+        let (offset, scale) = self.biome_height(x, z);
+        // Biomes only shape land, so oceans keep their depth.
+        let inland = ((continentalness + 0.05) * 10.0).clamp(0.0, 1.0);
+        let base = self.sea_level as f64 + continentalness * 64.0 + 8.0 + offset * inland;
+        let hills = self.hills.sample2(fx, fz) * 40.0 * roughness * (1.0 + (scale - 1.0) * inland);
+        return (base + hills, roughness);
+    }
+}
+
+impl ChunkGenerator for NoiseGenerator {
+    fn generate(&self, pos: ChunkPos) -> Chunk {
+        let mut chunk = Chunk::new(pos);
+        let origin = pos.origin();
+        let mut columns = [(0.0, 0.0); SIZE * SIZE];
+        let mut surfaces = [(AIR, AIR); SIZE * SIZE];
+        for z in 0..SIZE {
+            for x in 0..SIZE {
+                let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+                columns[x + z * SIZE] = self.height(world_x, world_z);
+                let biome = self.biomes.biome(self.biomes.biome_at(world_x, world_z));
+                surfaces[x + z * SIZE] = (biome.surface, biome.subsurface);
+            }
+        }
+        let lowest = columns.iter().map(|(height, _)| *height).fold(f64::MAX, f64::min);
+        let highest = columns.iter().map(|(height, _)| *height).fold(f64::MIN, f64::max);
+        // Detail noise shifts the surface by at most 12 blocks.
+        if origin.y as f64 > highest + 12.0 && origin.y >= self.sea_level {
+            return chunk;
+        }
+        let deep = (origin.y + CHUNK_SIZE) as f64 + 12.0 < lowest;
+
+        for y in 0..SIZE {
+            let world_y = origin.y + y as i32;
+            for z in 0..SIZE {
+                for x in 0..SIZE {
+                    let (world_x, world_z) = ((origin.x + x as i32) as f64, (origin.z + z as i32) as f64);
+                    let (height, roughness) = columns[x + z * SIZE];
+                    let (surface, subsurface) = surfaces[x + z * SIZE];
+                    let depth = height - world_y as f64;
+                    let solid = deep || depth + self.detail.sample3(world_x, world_y as f64, world_z) * 12.0 * roughness > 0.0;
+                    let id = if !solid {
+                        if world_y < self.sea_level { self.blocks.water } else { AIR }
+                    } else if depth > 6.0 && self.caves.sample3(world_x, world_y as f64 * 1.5, world_z).abs() < 0.05 {
+                        AIR
+                    } else if depth < 1.0 {
+                        if world_y > self.sea_level { surface } else { self.blocks.sand }
+                    } else if depth < 4.0 {
+                        if world_y >= self.sea_level - 1 { subsurface } else { self.blocks.sand }
+                    } else {
+                        self.blocks.stone
+                    };
+                    if id != AIR {
+                        chunk.set_block(LocalPos::new(x as u8, y as u8, z as u8), id);
+                    }
+                }
+            }
+        }
+        return chunk;
+    }
+}
+
+impl WorldGenerator for NoiseGenerator {
+    fn name(&self) -> &str {
+        return "noise";
+    }
+
+    /// Approximate, as 3D detail can add overhangs above the base height.
+    fn surface_height(&self, x: i32, z: i32) -> Option<i32> {
+        return Some(self.height(x, z).0.floor() as i32);
+    }
+
+    fn biomes(&self) -> Option<&Arc<BiomeSource>> {
+        return Some(&self.biomes);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{fs, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use shared::engine::{
+    asset::{block::BlockDefinition, handle::{AssetState, Handle}, manager::AssetReloaded, model::Model, pack::{ContentPacks, PackError}, texture::Texture, AssetManager},
+    block::BlockRegistry,
+    event::bus::MessageBus,
+    item::{ItemRegistry, ItemStack},
+    job::system::JobSystem,
+    math::direction::Direction,
+    recipe::{CraftingGrid, RecipeRegistry},
+    version::Version,
+    worldgen::{biome::BiomeRegistry, blocks::TerrainBlocks}
+};
+
+fn temp_path(name: &str) -> PathBuf {
+    return std::env::temp_dir().join(format!("cube_asset_test_{}_{}", name, std::process::id()));
+}
+
+fn write_png(path: &Path, width: u32, height: u32, color: png::ColorType, data: &[u8]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let mut encoder = png::Encoder::new(fs::File::create(path).unwrap(), width, height);
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().unwrap().write_image_data(data).unwrap();
+}
+
+#[test]
+fn assets_load_once_on_io_jobs_and_resolve_every_handle() {
+    let directory = temp_path("load");
+    write_png(&directory.join("textures/blocks/stone.png"), 2, 1, png::ColorType::Rgb, &[10, 20, 30, 40, 50, 60]);
+    write_png(&directory.join("textures/blocks/glass.png"), 1, 1, png::ColorType::Rgba, &[1, 2, 3, 4]);
+    fs::create_dir_all(directory.join("models")).unwrap();
+    fs::write(directory.join("models/cube.json"), r#"{ "textures": { "all": "blocks/stone" },
+        "elements": [{ "from": [0, 0, 0], "to": [16, 16, 16], "faces": { "PosY": "all" } }] }"#).unwrap();
+    fs::create_dir_all(directory.join("blocks")).unwrap();
+    fs::write(directory.join("blocks/stone.json"), r#"{ "name": "cube:stone", "model": "cube" }"#).unwrap();
+    fs::write(directory.join("blocks/broken.json"), r#"{ "name": "cube:broken", "model": "cube", "light": 99 }"#).unwrap();
+
+    let assets = Arc::new(AssetManager::new(&directory, Arc::new(JobSystem::new(2))));
+    // Requests from many threads at once share one load.
+    let requests: Vec<_> = (0..8).map(|_| {
+        let assets = assets.clone();
+        std::thread::spawn(move || assets.load::<Texture>("blocks/stone"))
+    }).collect();
+    let handles: Vec<_> = requests.into_iter().map(|request| request.join().unwrap()).collect();
+    assert!(handles.iter().all(|handle| *handle == handles[0]));
+    let stone = handles[0].wait().unwrap();
+    assert_eq!((stone.width, stone.height), (2, 1));
+    assert_eq!(stone.pixel(1, 0), [40, 50, 60, 255]);
+    assert_eq!(assets.load::<Texture>("blocks/glass").wait().unwrap().pixel(0, 0), [1, 2, 3, 4]);
+
+    // Blocks name their model, which names its textures.
+    let block = assets.load::<BlockDefinition>("stone").wait().unwrap();
+    let model = assets.load::<Model>(&block.model).wait().unwrap();
+    let texture = assets.load::<Texture>(model.face_texture(0, Direction::PosY).unwrap());
+    assert_eq!(texture, handles[0]);
+    let broken = assets.load::<BlockDefinition>("broken");
+    assert!(broken.wait().unwrap_err().message.contains("light"));
+    assert_eq!(broken.state(), AssetState::Failed);
+    // The same name is a different asset for each type.
+    assert!(assets.load::<Model>("stone").wait().is_err());
+
+    assert_eq!(assets.len(), 6);
+    // Only the stone texture still has a handle outside the manager.
+    drop(handles);
+    assert_eq!(assets.unload_unused::<Texture>(), 1);
+    assert!(assets.get::<Texture>("blocks/glass").is_none());
+    assert_eq!(assets.get::<Texture>("blocks/stone"), Some(texture));
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+/// Reload changed assets until a handle's version passes one, delivering reload events as a frame would.
+fn reload_until<T>(assets: &AssetManager, bus: &MessageBus, handle: &Handle<T>, version: u64) {
+    let start = Instant::now();
+    while handle.version() <= version {
+        assert!(start.elapsed() < Duration::from_secs(10), "Asset was never reloaded");
+        assets.reload_changed();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    while bus.pending_count() == 0 {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    bus.dispatch();
+}
+
+#[test]
+fn edited_assets_reload_and_dependents_rebuild() {
+    let directory = temp_path("reload");
+    let texture_path = directory.join("textures/grass.png");
+    write_png(&texture_path, 1, 1, png::ColorType::Rgba, &[0, 255, 0, 255]);
+    let block_path = directory.join("blocks/lamp.json");
+    fs::create_dir_all(block_path.parent().unwrap()).unwrap();
+    fs::write(&block_path, r#"{ "name": "cube:lamp", "model": "cube", "light": 10 }"#).unwrap();
+
+    let bus = Arc::new(MessageBus::new());
+    let assets = AssetManager::new(&directory, Arc::new(JobSystem::new(1))).with_events(bus.clone());
+    let grass = assets.load::<Texture>("grass");
+    let lamp = assets.load::<BlockDefinition>("lamp");
+    let missing = assets.load::<BlockDefinition>("torch");
+    assert!(grass.wait().is_ok() && lamp.wait().is_ok() && missing.wait().is_err());
+    assert_eq!(assets.reload_changed(), 0, "Nothing changed");
+
+    // Client data built from block definitions, rebuilt whenever one reloads.
+    let light = Arc::new(Mutex::new(lamp.get().unwrap().light));
+    let (captured, handle) = (light.clone(), lamp.clone());
+    bus.subscribe(move |reloaded: &AssetReloaded| {
+        if reloaded.directory == "blocks" && reloaded.name == "lamp" && reloaded.error.is_none() {
+            *captured.lock().unwrap() = handle.get().unwrap().light;
+        }
+    });
+
+    let version = lamp.version();
+    fs::write(&block_path, r#"{ "name": "cube:lamp", "model": "cube", "light": 15 }"#).unwrap();
+    reload_until(&assets, &bus, &lamp, version);
+    assert_eq!(*light.lock().unwrap(), 15);
+
+    let version = grass.version();
+    write_png(&texture_path, 1, 1, png::ColorType::Rgba, &[200, 255, 0, 255]);
+    reload_until(&assets, &bus, &grass, version);
+    assert_eq!(grass.get().unwrap().pixel(0, 0), [200, 255, 0, 255]);
+
+    // A broken edit keeps the block as it was, and a fixed one loads.
+    fs::write(&block_path, r#"{ "name": "cube:lamp", "#).unwrap();
+    let start = Instant::now();
+    while assets.reload_changed() == 0 {
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+    let failed = Arc::new(Mutex::new(None));
+    let captured = failed.clone();
+    bus.subscribe(move |reloaded: &AssetReloaded| *captured.lock().unwrap() = reloaded.error.clone());
+    while failed.lock().unwrap().is_none() {
+        bus.dispatch();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(lamp.get().unwrap().light, 15);
+
+    // Files that were missing load once they're added.
+    let version = missing.version();
+    fs::write(directory.join("blocks/torch.json"), r#"{ "name": "cube:torch", "model": "torch", "solid": false }"#).unwrap();
+    reload_until(&assets, &bus, &missing, version);
+    assert!(!missing.get().unwrap().solid);
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+fn write_file(path: &Path, text: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, text).unwrap();
+}
+
+#[test]
+fn content_packs_register_in_order_and_override_earlier_content() {
+    let directory = temp_path("packs");
+    let _ = fs::remove_dir_all(&directory);
+    write_file(&directory.join("gems/pack.toml"), "id = \"gems\"\ndescription = \"Shiny ores\"\nversion = \"2.1.0\"");
+    write_file(&directory.join("gems/blocks/ruby_ore.toml"), "name = \"gems:ruby_ore\"\nmodel = \"cube_all\"\nlight = 3");
+    write_file(&directory.join("gems/blocks/glass.json"), r#"{ "name": "gems:glass", "model": "cube_all", "opaque": false }"#);
+    write_file(&directory.join("gems/biomes/geodes.toml"), r#"
+        name = "gems:geodes"
+        temperature = -0.5
+        humidity = 0.9
+        surface = "gems:ruby_ore"
+        subsurface = "cube:stone"
+    "#);
+    // Loaded after gems despite its name, so its glass and desert win.
+    write_file(&directory.join("a_tweaks/pack.json"), r#"{ "id": "tweaks", "priority": 10 }"#);
+    write_file(&directory.join("a_tweaks/blocks/glass.json"), r#"{ "name": "gems:glass", "model": "glass", "opaque": false, "light": 15 }"#);
+    write_file(&directory.join("a_tweaks/biomes/desert.json"), r#"{ "name": "cube:desert", "temperature": 1.0, "humidity": -1.0, "surface": "gems:glass", "subsurface": "cube:sand" }"#);
+
+    let mut blocks = BlockRegistry::new();
+    let terrain = TerrainBlocks::register(&mut blocks).unwrap();
+    let mut biomes = BiomeRegistry::defaults(&terrain);
+    let desert = biomes.id("cube:desert").unwrap();
+    let packs = ContentPacks::load(&directory).unwrap();
+    assert_eq!(packs.packs().iter().map(|pack| pack.id.as_str()).collect::<Vec<_>>(), vec!["gems", "tweaks"]);
+    assert_eq!((packs.source("gems:glass"), packs.source("gems:ruby_ore"), packs.source("cube:desert")), (Some("tweaks"), Some("gems"), Some("tweaks")));
+    assert_eq!(packs.mods().iter().map(|pack| (pack.id.as_str(), pack.version)).collect::<Vec<_>>(), vec![("gems", Version::new(2, 1, 0)), ("tweaks", Version::new(1, 0, 0))]);
+    let content = packs.register(&mut blocks, &mut biomes).unwrap();
+
+    let glass = blocks.id("gems:glass").unwrap();
+    let ruby = blocks.id("gems:ruby_ore").unwrap();
+    // Ids follow the order names were first defined in, not which pack won.
+    assert_eq!(content.blocks.iter().map(|(id, block)| (*id, block.model.as_str(), block.light)).collect::<Vec<_>>(), vec![(glass, "glass", 15), (ruby, "cube_all", 3)]);
+    assert_eq!(content.biomes, vec![biomes.id("gems:geodes").unwrap(), desert]);
+    assert_eq!(biomes.get(biomes.id("gems:geodes").unwrap()).unwrap().surface, ruby);
+    assert_eq!(biomes.get(desert).unwrap().surface, glass);
+    assert_eq!(biomes.len(), 6);
+
+    // Packs can't add content to another pack's namespace.
+    write_file(&directory.join("sneaky/pack.toml"), "id = \"sneaky\"");
+    write_file(&directory.join("sneaky/blocks/diamond.toml"), "name = \"gems:diamond\"\nmodel = \"cube_all\"");
+    let result = ContentPacks::load(&directory).unwrap().register(&mut BlockRegistry::new(), &mut BiomeRegistry::new());
+    assert!(matches!(result, Err(PackError::Invalid { pack, .. }) if pack == "sneaky"));
+    write_file(&directory.join("sneaky/blocks/diamond.toml"), "name = \"sneaky:diamond\"\nmodel = \"cube_all\"\nlight = 20");
+    assert!(matches!(ContentPacks::load(&directory), Err(PackError::Parse { .. })));
+    fs::remove_dir_all(&directory).unwrap();
+}
+#[test]
+fn content_pack_recipes_resolve_items_and_replace_earlier_recipes() {
+    let directory = temp_path("recipe_packs");
+    let _ = fs::remove_dir_all(&directory);
+    write_file(&directory.join("tools/pack.toml"), "id = \"tools\"");
+    write_file(&directory.join("tools/recipes/sticks.toml"), "name = \"tools:sticks\"\nresult = \"cube:stick\"\ncount = 4\npattern = [\"#\", \"#\"]\nkey = { \"#\" = \"cube:log\" }");
+    write_file(&directory.join("tools/recipes/torch.json"), r#"{ "name": "tools:torch", "result": "cube:torch", "ingredients": ["cube:stick", ["cube:coal", "cube:log"]] }"#);
+    // Loaded later, so its sticks win.
+    write_file(&directory.join("more/pack.toml"), "id = \"more\"\npriority = 1");
+    write_file(&directory.join("more/recipes/sticks.toml"), "name = \"tools:sticks\"\nresult = \"cube:stick\"\ncount = 8\npattern = [\"#\", \"#\"]\nkey = { \"#\" = \"cube:log\" }");
+
+    let mut items = ItemRegistry::new();
+    let [log, stick, coal, torch] = ["cube:log", "cube:stick", "cube:coal", "cube:torch"].map(|name| items.register(name).unwrap());
+    let mut recipes = RecipeRegistry::new();
+    let packs = ContentPacks::load(&directory).unwrap();
+    assert_eq!(packs.source("tools:sticks"), Some("more"));
+    let ids = packs.register_recipes(&items, &mut recipes).unwrap();
+    assert_eq!(ids.len(), 2);
+    let sticks = recipes.find(&CraftingGrid::new(3, vec![Some(log), None, None, Some(log)])).unwrap();
+    assert_eq!(recipes.get(sticks).unwrap().result, ItemStack::new(stick, 8));
+    let torches = recipes.find(&CraftingGrid::new(2, vec![Some(coal), Some(stick)])).unwrap();
+    assert_eq!(recipes.get(torches).unwrap().result, ItemStack::new(torch, 1));
+
+    // Recipes using items that don't exist are refused, naming the pack.
+    write_file(&directory.join("more/recipes/gem.toml"), "name = \"more:gem\"\nresult = \"cube:gem\"\ningredients = [\"cube:coal\"]");
+    let result = ContentPacks::load(&directory).unwrap().register_recipes(&items, &mut RecipeRegistry::new());
+    assert!(matches!(result, Err(PackError::Invalid { pack, .. }) if pack == "more"));
+    write_file(&directory.join("more/recipes/gem.toml"), "name = \"more:gem\"\nresult = \"cube:coal\"\npattern = [\"##\"]");
+    assert!(matches!(ContentPacks::load(&directory), Err(PackError::Parse { .. })));
+    fs::remove_dir_all(&directory).unwrap();
+}
\ No newline at end of file
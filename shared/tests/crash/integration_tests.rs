@@ -0,0 +1,47 @@
+use std::{fs, panic, path::PathBuf, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread};
+
+use shared::engine::{crash::CrashHandler, job::system::JobSystem, math::coords::ChunkPos, world::{chunk::Chunk, World}};
+
+fn temp_path(name: &str) -> PathBuf {
+    return std::env::temp_dir().join(format!("cube_crash_test_{}_{}", name, std::process::id()));
+}
+
+#[test]
+fn panics_write_a_crash_report_and_save_the_world() {
+    let directory = temp_path("reports");
+    let jobs = Arc::new(JobSystem::new(1));
+    let world = Arc::new(World::new());
+    world.insert_chunk(Chunk::filled(ChunkPos::new(0, 0, 0), 1));
+    world.insert_chunk(Chunk::filled(ChunkPos::new(1, 0, 0), 1));
+    let saved = Arc::new(AtomicBool::new(false));
+    let saved_by_handler = saved.clone();
+    CrashHandler::new(&directory)
+        .with_job_system("compute", &jobs)
+        .with_world("overworld", &world)
+        .with_emergency_save(move || {
+            saved_by_handler.store(true, Ordering::Release);
+            return Ok(());
+        })
+        .with_abort(false)
+        .install();
+
+    let crashed = thread::Builder::new().name("tick".to_string()).spawn(|| panic!("chunk 3 has no sections")).unwrap().join();
+    // Put back the default hook, so the handler doesn't outlive the test.
+    let _ = panic::take_hook();
+    assert!(crashed.is_err());
+    assert!(saved.load(Ordering::Acquire));
+
+    let reports: Vec<PathBuf> = fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().path()).collect();
+    assert_eq!(reports.len(), 1);
+    let name = reports[0].file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("crash-") && name.ends_with(".txt"));
+    let report = fs::read_to_string(&reports[0]).unwrap();
+    assert!(report.contains("Panic: chunk 3 has no sections\n"));
+    assert!(report.contains("Thread: tick\n"));
+    assert!(report.contains("integration_tests.rs"));
+    assert!(report.contains("-- Backtrace --"));
+    assert!(report.contains("compute: job system: 1 threads"));
+    assert!(report.contains("overworld: 2\n"));
+    assert!(report.ends_with("-- Emergency save --\nsaved\n"));
+    fs::remove_dir_all(&directory).unwrap();
+}
\ No newline at end of file
@@ -0,0 +1 @@
+pub mod integration_tests;
\ No newline at end of file
@@ -0,0 +1,425 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}, thread};
+
+use shared::engine::{
+    entity::{
+        events::{EntityEvent, EventReader},
+        hierarchy::{LocalTransform, TransformPropagationSystem},
+        kinematics::{FixedTimestep, Kinematics, KinematicsSystem, Transform, Velocity},
+        query::{Changed, Query},
+        replication::{InterestManager, Replicated},
+        schedule::{SystemAccess, SystemSchedule},
+        serialize::ComponentTypes,
+        Entities, EntityId
+    },
+    job::system::JobSystem,
+    math::{aabb::Aabb, coords::{ChunkPos, WorldPos}, rng::WorldRng, vector::Vec3},
+    save::entities::EntityStorage,
+    world::World
+};
+
+#[test]
+fn entities_match_reference_model() {
+    let entities = Entities::new();
+    let mut rng = WorldRng::new(825);
+    let mut model: HashMap<EntityId, Option<i32>> = HashMap::new();
+    let mut despawned = Vec::new();
+    for step in 0..5000 {
+        let living: Vec<EntityId> = model.keys().copied().collect();
+        match rng.range_i32(0..4) {
+            0 => {
+                model.insert(entities.spawn(), None);
+            },
+            1 if !living.is_empty() => {
+                let id = living[rng.range_i32(0..living.len() as i32) as usize];
+                assert!(entities.despawn(id));
+                model.remove(&id);
+                despawned.push(id);
+            },
+            2 if !living.is_empty() => {
+                let id = living[rng.range_i32(0..living.len() as i32) as usize];
+                assert_eq!(entities.insert(id, step).unwrap(), model.insert(id, Some(step)).flatten());
+            },
+            3 if !living.is_empty() => {
+                let id = living[rng.range_i32(0..living.len() as i32) as usize];
+                assert_eq!(entities.remove::<i32>(id), model.insert(id, None).flatten());
+            },
+            _ => ()
+        }
+    }
+
+    assert_eq!(entities.len(), model.len());
+    for (id, value) in model.iter() {
+        assert!(entities.is_alive(*id));
+        assert_eq!(entities.get::<i32>(*id), *value);
+    }
+    // Stale handles never see the components of entities that reused their index.
+    for id in despawned {
+        assert!(!entities.is_alive(id));
+        assert_eq!(entities.get::<i32>(id), None);
+        assert!(entities.insert(id, 0).is_err());
+    }
+    let storage = entities.storage::<i32>();
+    assert_eq!(storage.read().unwrap().len(), model.values().filter(|value| value.is_some()).count());
+}
+
+#[test]
+fn entities_spawn_from_many_threads() {
+    let entities = Arc::new(Entities::new());
+    let threads: Vec<_> = (0..4).map(|thread| {
+        let entities = entities.clone();
+        thread::spawn(move || {
+            for i in 0..1000 {
+                let id = entities.spawn();
+                entities.insert(id, thread * 1000 + i).unwrap();
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(entities.len(), 4000);
+    let mut values: Vec<i32> = entities.storage::<i32>().read().unwrap().iter().map(|(_, value)| *value).collect();
+    values.sort_unstable();
+    assert_eq!(values, (0..4000).collect::<Vec<i32>>());
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Counter(u64);
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Mirror(u64);
+
+#[test]
+fn schedule_matches_sequential_order() {
+    let world = Arc::new(World::new());
+    for i in 0..500 {
+        let id = world.entities().spawn();
+        world.entities().insert(id, Counter(i)).unwrap();
+        world.entities().insert(id, Mirror(0)).unwrap();
+    }
+
+    let mut schedule = SystemSchedule::new();
+    for step in 1..=3 {
+        schedule.add_fn(&format!("double {}", step), SystemAccess::new().write::<Counter>(), move |context| {
+            for (_, mut counter) in context.entities().storage::<Counter>().write().unwrap().iter_mut() {
+                counter.0 = counter.0 * 2 + step;
+            }
+        });
+    }
+    schedule.add_fn("mirror", Query::<(&mut Mirror, &Counter)>::access(SystemAccess::new()), |context| {
+        context.query::<(&mut Mirror, &Counter), ()>().for_each(|_, (mut mirror, counter)| mirror.0 = counter.0);
+    });
+    schedule.add_fn("count", SystemAccess::new().read::<Counter>(), |_| {});
+    assert_eq!(schedule.stage_count(), 4);
+
+    let jobs = JobSystem::new(4);
+    for _ in 0..10 {
+        schedule.run(&jobs, &world);
+    }
+    for id in world.entities().alive() {
+        let mut expected = id.index() as u64;
+        for _ in 0..10 {
+            for step in 1..=3 {
+                expected = expected.wrapping_mul(2).wrapping_add(step);
+            }
+        }
+        assert_eq!(world.entities().get::<Counter>(id), Some(Counter(expected)));
+        assert_eq!(world.entities().get::<Mirror>(id), Some(Mirror(expected)));
+    }
+}
+
+fn counter_types() -> ComponentTypes {
+    let mut types = ComponentTypes::new();
+    types.register::<Counter>("test:counter", |counter, out| out.extend_from_slice(&counter.0.to_le_bytes()),
+        |data| Ok(Counter(u64::from_le_bytes(data.try_into().map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidData))?))));
+    return types;
+}
+
+#[test]
+fn entities_save_with_their_chunks() {
+    let directory = std::env::temp_dir().join(format!("cube_entity_save_test_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let mut types = counter_types();
+    types.register::<Mirror>("test:mirror", |mirror, out| out.extend_from_slice(&mirror.0.to_le_bytes()),
+        |data| Ok(Mirror(u64::from_le_bytes(data.try_into().unwrap()))));
+    let storage = EntityStorage::new(&directory, Arc::new(types)).unwrap();
+
+    let entities = Entities::new();
+    let chunks: Vec<ChunkPos> = (0..4).map(|x| ChunkPos::new(x * 5 - 8, 0, x)).collect();
+    for i in 0..200u64 {
+        let id = entities.spawn();
+        entities.insert(id, Counter(i)).unwrap();
+        entities.insert(id, Mirror(i * 2)).unwrap();
+        entities.set_chunk(id, chunks[i as usize % chunks.len()]);
+    }
+    for chunk in chunks.iter() {
+        assert_eq!(storage.unload_chunk(&entities, *chunk).unwrap(), 50);
+    }
+    assert!(entities.is_empty());
+
+    // Loaded by a version that no longer has the mirror component.
+    let reloaded = EntityStorage::new(&directory, Arc::new(counter_types())).unwrap();
+    let mut counters = Vec::new();
+    for chunk in chunks.iter() {
+        for id in reloaded.load_chunk(&entities, *chunk).unwrap() {
+            let counter = entities.get::<Counter>(id).unwrap();
+            assert_eq!(chunks[counter.0 as usize % chunks.len()], *chunk);
+            assert!(!entities.has::<Mirror>(id));
+            counters.push(counter.0);
+        }
+    }
+    counters.sort_unstable();
+    assert_eq!(counters, (0..200).collect::<Vec<u64>>());
+
+    // Saving an emptied chunk removes its entities from the save.
+    for id in entities.in_chunk(chunks[0]) {
+        entities.despawn(id);
+    }
+    assert_eq!(reloaded.save_chunk(&entities, chunks[0]).unwrap(), 0);
+    assert!(reloaded.load_chunk(&entities, chunks[0]).unwrap().is_empty());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+/// Simulate falling entities for 3 seconds of frames of the given lengths, returning their final transforms.
+fn simulate_frames(frame_seconds: &[f64]) -> Vec<Transform> {
+    let world = Arc::new(World::new());
+    let mut rng = WorldRng::new(828);
+    let ids: Vec<EntityId> = (0..50).map(|_| {
+        let id = world.entities().spawn();
+        let position = WorldPos::new(rng.range_i32(-100..100) as f64, 80.0, rng.range_i32(-100..100) as f64);
+        world.entities().insert(id, Transform::new(position)).unwrap();
+        world.entities().insert(id, Velocity { linear: Vec3::new(rng.range_i32(-5..5) as f32, 8.0, 0.0), angular: Vec3::Y }).unwrap();
+        world.entities().insert(id, Kinematics::FALLING).unwrap();
+        id
+    }).collect();
+    let mut schedule = SystemSchedule::new();
+    schedule.add_system(KinematicsSystem);
+    let jobs = JobSystem::new(2);
+    let mut timestep = FixedTimestep::default();
+    for seconds in frame_seconds.iter().cycle().scan(0.0, |total, seconds| {
+        *total += seconds;
+        (*total <= 3.0 + 1e-9).then_some(*seconds)
+    }) {
+        for _ in 0..timestep.advance(seconds) {
+            schedule.run(&jobs, &world);
+        }
+    }
+    for id in ids.iter() {
+        let transform = world.entities().get::<Transform>(*id).unwrap();
+        assert_eq!(world.entities().chunk_of(*id), Some(transform.position.chunk()));
+    }
+    return ids.iter().map(|id| world.entities().get::<Transform>(*id).unwrap()).collect();
+}
+
+#[test]
+fn kinematics_are_independent_of_frame_rate() {
+    let ticks = simulate_frames(&[0.05]);
+    assert_eq!(simulate_frames(&[0.0125]), ticks);
+    assert_eq!(simulate_frames(&[0.1, 0.025, 0.025]), ticks);
+    assert!(ticks.iter().all(|transform| transform.position.y < 80.0));
+}
+
+#[test]
+fn entity_events_follow_moving_entities() {
+    let world = Arc::new(World::new());
+    let entities = world.entities();
+    let mut replication = EventReader::new();
+    let arrow = entities.spawn();
+    entities.insert(arrow, Transform::new(WorldPos::new(30.0, 1.0, 1.0))).unwrap();
+    entities.insert(arrow, Velocity::new(Vec3::new(20.0, 0.0, 0.0))).unwrap();
+    let mut schedule = SystemSchedule::new();
+    schedule.add_system(KinematicsSystem);
+    let jobs = JobSystem::new(1);
+
+    let mut chunks = Vec::new();
+    for _ in 0..80 {
+        schedule.run(&jobs, &world);
+        entities.update_events();
+        for event in entities.read_events(&mut replication) {
+            assert_eq!(event.entity(), arrow);
+            if let EntityEvent::EnteredChunk { chunk, .. } = event {
+                chunks.push(chunk.x);
+            }
+        }
+    }
+    // 80 blocks at 1 block per tick, starting 2 blocks from the next chunk.
+    assert_eq!(chunks, vec![0, 1, 2, 3]);
+
+    // Events are kept for two updates, then dropped for readers that fell behind.
+    let mut late = EventReader::new();
+    entities.despawn(arrow);
+    entities.update_events();
+    entities.update_events();
+    assert!(entities.read_events(&mut late).is_empty());
+    assert!(entities.read_events(&mut replication).is_empty());
+}
+
+#[test]
+fn systems_only_see_changes_since_they_last_ran() {
+    let world = Arc::new(World::new());
+    let entities = world.entities();
+    let ids: Vec<EntityId> = (0..10).map(|i| {
+        let id = entities.spawn();
+        entities.insert(id, Transform::new(WorldPos::new(i as f64, 1.0, 1.0))).unwrap();
+        entities.insert(id, Velocity::new(if i < 3 { Vec3::X } else { Vec3::ZERO })).unwrap();
+        id
+    }).collect();
+
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let mut schedule = SystemSchedule::new();
+    schedule.add_system(KinematicsSystem);
+    let replicated = sent.clone();
+    schedule.add_fn("replicate", Query::<&Transform, Changed<Transform>>::access(SystemAccess::new()), move |context| {
+        let mut changed = Vec::new();
+        context.query::<&Transform, Changed<Transform>>().for_each(|id, _| changed.push(id));
+        replicated.lock().unwrap().push(changed);
+    });
+    let jobs = JobSystem::new(2);
+
+    schedule.run(&jobs, &world);
+    schedule.run(&jobs, &world);
+    // Moved outside the schedule, between runs.
+    entities.storage::<Transform>().write().unwrap().get_mut(ids[9]).unwrap().position.y = 2.0;
+    schedule.run(&jobs, &world);
+    schedule.run(&jobs, &world);
+
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent[0], ids);
+    assert_eq!(sent[1], ids[..3]);
+    assert_eq!(sent[2], [ids[0], ids[1], ids[2], ids[9]]);
+    assert_eq!(sent[3], ids[..3]);
+}
+
+#[test]
+fn attached_entities_follow_their_parents() {
+    let world = Arc::new(World::new());
+    let entities = world.entities();
+    let horse = entities.spawn();
+    entities.insert(horse, Transform::new(WorldPos::new(28.0, 64.0, 0.0))).unwrap();
+    entities.insert(horse, Velocity { linear: Vec3::new(10.0, 0.0, 0.0), angular: Vec3::new(0.0, 0.5, 0.0) }).unwrap();
+    let rider = entities.spawn();
+    entities.insert(rider, LocalTransform::new(Vec3::new(0.0, 1.0, 0.0))).unwrap();
+    let sword = entities.spawn();
+    entities.insert(sword, LocalTransform::new(Vec3::new(0.5, 0.0, 0.0))).unwrap();
+    assert!(entities.set_parent(rider, horse));
+    assert!(entities.set_parent(sword, rider));
+
+    let mut schedule = SystemSchedule::new();
+    schedule.add_system(KinematicsSystem);
+    schedule.add_system(TransformPropagationSystem);
+    assert_eq!(schedule.stage_count(), 2);
+    let jobs = JobSystem::new(2);
+    for _ in 0..40 {
+        schedule.run(&jobs, &world);
+    }
+
+    let horse_transform = entities.get::<Transform>(horse).unwrap();
+    let rider_transform = entities.get::<Transform>(rider).unwrap();
+    let sword_transform = entities.get::<Transform>(sword).unwrap();
+    assert!((horse_transform.position.x - 48.0).abs() < 1e-3);
+    assert_eq!(rider_transform, LocalTransform::new(Vec3::new(0.0, 1.0, 0.0)).apply(&horse_transform));
+    assert_eq!(sword_transform, LocalTransform::new(Vec3::new(0.5, 0.0, 0.0)).apply(&rider_transform));
+    assert_eq!(entities.chunk_of(sword), Some(sword_transform.position.chunk()));
+
+    // Dismounting leaves the rider where it was, and takes the sword with it.
+    assert_eq!(entities.remove_parent(rider), Some(horse));
+    entities.despawn(horse);
+    assert!(entities.is_alive(rider) && entities.is_alive(sword));
+    entities.despawn(rider);
+    assert!(entities.is_empty());
+    assert!(entities.storage::<LocalTransform>().read().unwrap().is_empty());
+}
+
+#[test]
+fn clients_only_hear_about_entities_near_them() {
+    let world = Arc::new(World::new());
+    let entities = world.entities();
+    let mut interest = InterestManager::new();
+    interest.watch::<Transform>();
+    interest.add_client(1, ChunkPos::new(0, 0, 0), 1);
+    interest.add_client(2, ChunkPos::new(4, 0, 0), 1);
+    // Moves 4 blocks per tick, so it leaves client 1's chunks after 12 ticks and reaches client 2's after 20.
+    let arrow = entities.spawn();
+    entities.insert(arrow, Transform::new(WorldPos::new(16.0, 16.0, 16.0))).unwrap();
+    entities.insert(arrow, Velocity::new(Vec3::new(80.0, 0.0, 0.0))).unwrap();
+    entities.insert(arrow, Replicated).unwrap();
+    let hidden = entities.spawn();
+    entities.insert(hidden, Transform::new(WorldPos::new(1.0, 1.0, 1.0))).unwrap();
+    entities.set_chunk(hidden, ChunkPos::new(0, 0, 0));
+
+    let mut schedule = SystemSchedule::new();
+    schedule.add_system(KinematicsSystem);
+    let jobs = JobSystem::new(1);
+    let mut sent: HashMap<u32, Vec<(u32, &str)>> = HashMap::new();
+    for tick in 0..40 {
+        schedule.run(&jobs, &world);
+        for (client, delta) in interest.update(entities) {
+            assert!(!delta.creates.contains(&hidden) && !delta.updates.contains(&hidden));
+            let log = sent.entry(client).or_default();
+            log.extend(delta.creates.iter().map(|_| (tick, "create")));
+            log.extend(delta.destroys.iter().map(|_| (tick, "destroy")));
+            log.extend(delta.updates.iter().map(|_| (tick, "update")));
+        }
+    }
+    let expected = |created: u32, destroyed: Option<u32>, last: u32| {
+        let mut log = vec![(created, "create")];
+        log.extend((created + 1..=last).map(|tick| (tick, "update")));
+        log.extend(destroyed.map(|tick| (tick, "destroy")));
+        log
+    };
+    assert_eq!(sent[&1], expected(0, Some(11), 10));
+    assert_eq!(sent[&2], expected(19, None, 39));
+
+    entities.remove::<Replicated>(arrow);
+    assert_eq!(interest.update(entities)[&2].destroys, vec![arrow]);
+    assert!(interest.known(2).is_empty());
+}
+
+#[test]
+fn spatial_queries_follow_moving_and_teleported_entities() {
+    let world = Arc::new(World::new());
+    let entities = world.entities();
+    let mut rng = WorldRng::new(872);
+    let mut spawned = Vec::new();
+    for _ in 0..300 {
+        let entity = entities.spawn();
+        let position = WorldPos::new(rng.range_f32(-80.0..80.0) as f64, rng.range_f32(-20.0..20.0) as f64, rng.range_f32(-80.0..80.0) as f64);
+        let velocity = Vec3::new(rng.range_f32(-30.0..30.0), 0.0, rng.range_f32(-30.0..30.0));
+        entities.insert(entity, Transform::new(position)).unwrap();
+        entities.insert(entity, Velocity::new(velocity)).unwrap();
+        spawned.push(entity);
+    }
+    let mut schedule = SystemSchedule::new();
+    schedule.add_system(KinematicsSystem);
+    let jobs = JobSystem::new(2);
+    for tick in 0..40 {
+        schedule.run(&jobs, &world);
+        // Teleport a few entities, which doesn't go through kinematics.
+        if tick % 10 == 0 {
+            for entity in spawned.iter().take(5) {
+                let position = WorldPos::new(rng.range_f32(-80.0..80.0) as f64, 0.0, rng.range_f32(-80.0..80.0) as f64);
+                entities.insert(*entity, Transform::new(position)).unwrap();
+            }
+        }
+    }
+    schedule.run(&jobs, &world);
+
+    let position = |entity: EntityId| {
+        let position = entities.get::<Transform>(entity).unwrap().position;
+        Vec3::new(position.x as f32, position.y as f32, position.z as f32)
+    };
+    for _ in 0..50 {
+        let center = Vec3::new(rng.range_f32(-80.0..80.0), rng.range_f32(-20.0..20.0), rng.range_f32(-80.0..80.0));
+        let area = Aabb::from_center_half_extents(center, Vec3::new(rng.range_f32(1.0..40.0), rng.range_f32(1.0..40.0), rng.range_f32(1.0..40.0)));
+        let mut found = world.entities_in_aabb(area);
+        found.sort();
+        let expected: Vec<EntityId> = spawned.iter().copied().filter(|entity| area.contains_point(position(*entity))).collect();
+        assert_eq!(found, expected);
+
+        let radius = rng.range_f32(1.0..40.0);
+        let nearest = world.entities_in_radius(center, radius);
+        let mut expected: Vec<EntityId> = spawned.iter().copied().filter(|entity| position(*entity).distance(center) <= radius).collect();
+        expected.sort_by(|a, b| position(*a).distance(center).total_cmp(&position(*b).distance(center)));
+        assert_eq!(nearest, expected);
+    }
+}
\ No newline at end of file
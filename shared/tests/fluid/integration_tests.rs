@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use shared::engine::{
+    block::{BlockRegistry, AIR},
+    fluid::{FluidSettings, FluidType, FALLING_LEVEL},
+    job::system::JobSystem,
+    math::coords::{BlockPos, ChunkPos, LocalPos},
+    world::{chunk::Chunk, tick::{TickHandlers, TickScheduler}, World}
+};
+
+fn setup() -> (Arc<World>, Arc<TickScheduler>, Arc<FluidType>) {
+    let mut registry = BlockRegistry::new();
+    let stone = registry.register("cube:stone").unwrap();
+    let mut handlers = TickHandlers::new();
+    let water = FluidType::register(&mut registry, &mut handlers, "cube:water", FluidSettings::WATER).unwrap();
+    let scheduler = Arc::new(TickScheduler::new(handlers, 0));
+    scheduler.set_random_tick_speed(0);
+
+    let world = Arc::new(World::new());
+    for x in 0..2 {
+        let mut chunk = Chunk::new(ChunkPos::new(x, 0, 0));
+        for local in LocalPos::all().filter(|local| local.y == 0) {
+            chunk.set_block(local, stone);
+        }
+        world.insert_chunk(chunk);
+    }
+    return (world, scheduler, water);
+}
+
+fn run_ticks(world: &Arc<World>, scheduler: &Arc<TickScheduler>, jobs: &JobSystem, ticks: usize) {
+    for _ in 0..ticks {
+        scheduler.run_tick(world, jobs);
+    }
+}
+
+#[test]
+fn water_spreads_across_chunks_and_drains() {
+    let (world, scheduler, water) = setup();
+    let jobs = JobSystem::new(2);
+    let source = BlockPos::new(29, 1, 16);
+    world.set_block(source, water.source());
+    water.notify(&scheduler, source);
+    run_ticks(&world, &scheduler, &jobs, 60);
+
+    assert_eq!(world.get_block(BlockPos::new(32, 1, 16)).and_then(|id| water.level(id)), Some(3));
+    assert_eq!(world.get_block(BlockPos::new(30, 1, 18)).and_then(|id| water.level(id)), Some(3));
+    assert_eq!(world.get_block(BlockPos::new(36, 1, 16)).and_then(|id| water.level(id)), Some(7));
+    assert_eq!(world.get_block(BlockPos::new(37, 1, 16)), Some(AIR));
+    assert_eq!(world.get_block(BlockPos::new(29, 2, 16)), Some(AIR));
+
+    world.set_block(source, AIR);
+    water.notify(&scheduler, source);
+    run_ticks(&world, &scheduler, &jobs, 60);
+    let mut remaining = 0;
+    world.for_each_chunk(|chunk| remaining += LocalPos::all().filter(|local| water.is_fluid(chunk.get_block(*local))).count());
+    assert_eq!(remaining, 0);
+}
+
+#[test]
+fn water_falls_then_spreads_and_fills_between_sources() {
+    let (world, scheduler, water) = setup();
+    let jobs = JobSystem::new(2);
+    let source = BlockPos::new(8, 6, 8);
+    world.set_block(source, water.source());
+    water.notify(&scheduler, source);
+    run_ticks(&world, &scheduler, &jobs, 60);
+
+    assert_eq!(world.get_block(BlockPos::new(8, 3, 8)).and_then(|id| water.level(id)), Some(FALLING_LEVEL));
+    assert_eq!(world.get_block(BlockPos::new(10, 1, 8)).and_then(|id| water.level(id)), Some(2));
+
+    // Flowing water with a source on each side becomes a source itself.
+    let between = BlockPos::new(20, 1, 20);
+    for x in [19, 21] {
+        world.set_block(BlockPos::new(x, 1, 20), water.source());
+        water.notify(&scheduler, BlockPos::new(x, 1, 20));
+    }
+    run_ticks(&world, &scheduler, &jobs, 20);
+    assert_eq!(world.get_block(between), Some(water.source()));
+}
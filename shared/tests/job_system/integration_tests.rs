@@ -1,6 +1,6 @@
-use std::time::Duration;
+use std::{sync::Arc, time::{Duration, Instant}};
 
-use shared::engine::job::system::{job_system_run, job_system_wait};
+use shared::engine::job::{priority::JobPriority, system::{job_system_run, job_system_run_background, job_system_wait, JobSystem}};
 
 use super::initialize_job_system_integration_test;
 
@@ -31,3 +31,53 @@ fn add_many_jobs_with_delays_and_futures() {
         assert_eq!(v[i].wait(), i);
     }
 }
+
+
+#[test]
+fn background_job_yields_to_short_jobs() {
+    initialize_job_system_integration_test();
+
+    let background = job_system_run_background(|ctx| {
+        for _ in 0..50 {
+            std::thread::sleep(Duration::from_millis(1));
+            ctx.yield_now();
+        }
+    });
+
+    let mut v = vec![];
+    for i in 0..100 {
+        v.push(job_system_run(move || i * 2));
+    }
+    for i in 0..100 {
+        assert_eq!(v[i].wait(), i * 2);
+    }
+    background.wait();
+}
+
+/// Keeps a high priority job queued at all times, by having each one queue its successor before finishing.
+fn queue_high_priority_chain(job_system: Arc<JobSystem>, until: Instant) {
+    let system = job_system.clone();
+    job_system.run_job_with_priority(JobPriority::High, move || {
+        std::thread::sleep(Duration::from_millis(1));
+        if Instant::now() < until {
+            queue_high_priority_chain(system.clone(), until);
+        }
+    });
+}
+
+#[test]
+fn low_priority_job_runs_within_aging_window_under_sustained_load() {
+    let aging_window = Duration::from_millis(20);
+    let load_duration = Duration::from_millis(500);
+    let job_system = Arc::new(JobSystem::new(1));
+    job_system.set_aging_window(aging_window);
+
+    let submitted_at = Instant::now();
+    queue_high_priority_chain(job_system.clone(), submitted_at + load_duration);
+    let low = job_system.run_job_with_priority(JobPriority::Low, Instant::now);
+
+    let waited = low.wait().duration_since(submitted_at);
+    job_system.wait();
+    // The bound is the aging window plus the batch executing at the time, with slack for thread wake up.
+    assert!(waited < aging_window + Duration::from_millis(100), "low priority job waited {:?}", waited);
+}
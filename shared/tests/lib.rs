@@ -1 +1,16 @@
-pub mod job_system;
\ No newline at end of file
+pub mod job_system;
+pub mod world;
+pub mod mesh;
+pub mod save;
+pub mod worldgen;
+pub mod light;
+pub mod fluid;
+pub mod entity;
+pub mod net;
+pub mod asset;
+pub mod profile;
+pub mod metrics;
+pub mod crash;
+pub mod script;
+pub mod path;
+pub mod physics;
\ No newline at end of file
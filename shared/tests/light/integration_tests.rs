@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use shared::engine::{
+    job::system::JobSystem,
+    light::{propagation::{light_chunk, queue_light_job, update_block, BlockLighting, LightingFn}, MAX_LIGHT},
+    math::coords::{BlockPos, ChunkPos},
+    mesh::greedy::queue_world_mesh_job,
+    world::{chunk::Chunk, World}
+};
+
+const STONE: u16 = 1;
+const TORCH: u16 = 2;
+
+fn lighting() -> Arc<LightingFn> {
+    return Arc::new(|id| match id {
+        0 => BlockLighting::TRANSPARENT,
+        TORCH => BlockLighting { emission: [14, 10, 4], opacity: 0 },
+        _ => BlockLighting::OPAQUE
+    });
+}
+
+fn sample(world: &World, block: BlockPos) -> shared::engine::light::LightSample {
+    let light = world.light(block.chunk()).unwrap();
+    let sample = light.read().unwrap().get(block.local());
+    return sample;
+}
+
+#[test]
+fn block_light_spreads_across_chunk_borders() {
+    let world = Arc::new(World::new());
+    for x in -1..1 {
+        world.insert_chunk(Chunk::new(ChunkPos::new(x, 0, 0)));
+    }
+    world.set_block(BlockPos::new(1, 10, 10), TORCH);
+    let jobs = JobSystem::new(1);
+    queue_light_job(&jobs, world.clone(), vec![ChunkPos::new(-1, 0, 0), ChunkPos::new(0, 0, 0)], lighting()).wait();
+
+    assert_eq!(sample(&world, BlockPos::new(1, 10, 10)).block, [14, 10, 4]);
+    assert_eq!(sample(&world, BlockPos::new(-3, 10, 10)).block, [10, 6, 0]);
+
+    world.set_block(BlockPos::new(1, 10, 10), 0);
+    update_block(&world, BlockPos::new(1, 10, 10), &*lighting());
+    assert_eq!(sample(&world, BlockPos::new(-3, 10, 10)).block, [0, 0, 0]);
+}
+
+#[test]
+fn roof_shades_sky_light_until_removed() {
+    let world = World::new();
+    world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    let lighting = lighting();
+    light_chunk(&world, ChunkPos::new(0, 0, 0), &*lighting);
+    assert_eq!(sample(&world, BlockPos::new(16, 0, 16)).sky, MAX_LIGHT);
+
+    for z in 10..23 {
+        for x in 10..23 {
+            world.set_block(BlockPos::new(x, 20, z), STONE);
+            update_block(&world, BlockPos::new(x, 20, z), &*lighting);
+        }
+    }
+    // Directly below the middle of the roof, light only arrives sideways from the edges.
+    assert_eq!(sample(&world, BlockPos::new(16, 19, 16)).sky, MAX_LIGHT - 7);
+    assert_eq!(sample(&world, BlockPos::new(16, 21, 16)).sky, MAX_LIGHT);
+
+    world.set_block(BlockPos::new(16, 20, 16), 0);
+    update_block(&world, BlockPos::new(16, 20, 16), &*lighting);
+    assert_eq!(sample(&world, BlockPos::new(16, 0, 16)).sky, MAX_LIGHT);
+}
+
+#[test]
+fn world_mesh_faces_carry_light() {
+    let world = Arc::new(World::new());
+    let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    chunk.set_block(BlockPos::new(4, 4, 4).local(), STONE);
+    world.insert_chunk(chunk);
+    light_chunk(&world, ChunkPos::new(0, 0, 0), &*lighting());
+
+    let jobs = JobSystem::new(1);
+    let mesh = queue_world_mesh_job(&jobs, world.clone(), ChunkPos::new(0, 0, 0), Arc::new(|id| id != 0)).unwrap().wait();
+    assert_eq!(mesh.quad_count(), 6);
+    // The bottom face is lit by sky light spreading in from the side.
+    assert!(mesh.vertices.iter().all(|vertex| vertex.light as u8 & 0xF >= MAX_LIGHT - 1));
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use shared::engine::{
+    job::system::JobSystem,
+    math::{coords::{BlockPos, ChunkPos, LocalPos}, direction::Direction, rng::WorldRng, vector::Vec3},
+    mesh::{allocator::RangeAllocator, greedy::{greedy_mesh, ChunkBorders, OpacityFn, TranslucencyFn}, remesh::RemeshQueue, texture::{FaceTextures, UvRect}},
+    world::{chunk::Chunk, World}
+};
+
+#[test]
+fn greedy_mesh_quads_face_outwards() {
+    let mut rng = WorldRng::new(809);
+    let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    for _ in 0..2000 {
+        let local = LocalPos::new(rng.range_i32(0..32) as u8, rng.range_i32(0..32) as u8, rng.range_i32(0..32) as u8);
+        chunk.set_block(local, rng.range_i32(1..4) as u16);
+    }
+    let mesh = greedy_mesh(&chunk, &ChunkBorders::empty(), &|id| id != 0);
+    assert!(!mesh.is_empty());
+
+    for triangle in mesh.indices.chunks(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+        let normal = (Vec3::from(b.position) - Vec3::from(a.position)).cross(Vec3::from(c.position) - Vec3::from(a.position));
+        assert_eq!(Direction::nearest(normal), Direction::from_index(a.face as usize));
+    }
+}
+
+#[test]
+fn greedy_mesh_neighbors_hide_border_faces() {
+    let stone = Chunk::filled(ChunkPos::new(0, 0, 0), 1);
+    let neighbor = Chunk::filled(ChunkPos::new(1, 0, 0), 1);
+    let glass = Chunk::filled(ChunkPos::new(-1, 0, 0), 2);
+    let mut neighbors: [Option<&Chunk>; 6] = [None; 6];
+    neighbors[Direction::PosX.index()] = Some(&neighbor);
+    neighbors[Direction::NegX.index()] = Some(&glass);
+    let borders = ChunkBorders::from_neighbors(neighbors);
+
+    // Glass doesn't hide faces behind it.
+    let mesh = greedy_mesh(&stone, &borders, &|id| id == 1);
+    assert_eq!(mesh.quad_count(), 5);
+    assert!(mesh.vertices.iter().all(|vertex| vertex.face != Direction::PosX.index() as u32));
+}
+
+
+#[test]
+fn remesh_queue_coalesces_edits_into_sections() {
+    let world = Arc::new(World::new());
+    for x in 0..2 {
+        let mut chunk = Chunk::filled(ChunkPos::new(x, 0, 0), 1);
+        chunk.take_dirty();
+        world.insert_chunk(chunk);
+    }
+    let opaque: Arc<OpacityFn> = Arc::new(|id| id != 0);
+    let jobs = JobSystem::new(2);
+    let queue = RemeshQueue::new();
+
+    // Several edits in one frame, all within the first section of the chunk.
+    for y in 1..4 {
+        world.set_block(BlockPos::new(4, y, 4), 0);
+    }
+    queue.collect_from_world(&world);
+    let remeshes = queue.dispatch(&jobs, &world, &opaque);
+    assert_eq!(remeshes.len(), 1);
+    assert_eq!(queue.pending_count(), 0);
+    let remesh = remeshes.into_iter().next().unwrap();
+    assert_eq!((remesh.pos, remesh.sections), (ChunkPos::new(0, 0, 0), 0b1));
+    let meshes = remesh.future.wait();
+    assert_eq!(meshes.len(), 1);
+    // The six sides of the hole, with each wall split into its top, middle and bottom faces as their corners are
+    // darkened differently, and the section's three faces on the edge of the world.
+    assert_eq!(meshes[0].1.quad_count(), 17);
+
+    // An edit on the shared face also remeshes the neighbor's touching section.
+    world.set_block(BlockPos::new(31, 1, 1), 0);
+    queue.collect_from_world(&world);
+    let mut remeshes: Vec<_> = queue.dispatch(&jobs, &world, &opaque).into_iter().map(|remesh| (remesh.pos, remesh.future.wait().len())).collect();
+    remeshes.sort_by_key(|(pos, _)| pos.x);
+    assert_eq!(remeshes, vec![(ChunkPos::new(0, 0, 0), 1), (ChunkPos::new(1, 0, 0), 4)]);
+}
+
+#[test]
+fn remesh_jobs_give_faces_their_atlas_rects() {
+    let world = Arc::new(World::new());
+    let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    chunk.set_block(LocalPos::new(1, 1, 1), 1);
+    chunk.set_block(LocalPos::new(3, 1, 1), 2);
+    world.insert_chunk(chunk);
+    let opaque: Arc<OpacityFn> = Arc::new(|id| id != 0);
+    let jobs = JobSystem::new(2);
+    let queue = RemeshQueue::new();
+
+    // Without textures, faces show the whole texture.
+    queue.mark_chunk(ChunkPos::new(0, 0, 0));
+    let meshes = queue.dispatch(&jobs, &world, &opaque).pop().unwrap().future.wait();
+    assert!(meshes.iter().flat_map(|(_, mesh)| mesh.vertices.iter()).all(|vertex| vertex.texture == UvRect::FULL.to_array()));
+
+    let missing = UvRect::new([0.0, 0.0], [0.25, 0.25]);
+    let log_side = UvRect::new([0.25, 0.0], [0.25, 0.25]);
+    let log_top = UvRect::new([0.5, 0.0], [0.25, 0.25]);
+    let mut textures = FaceTextures::new(missing);
+    textures.set_all(1, log_side);
+    textures.set(1, Direction::PosY, log_top);
+    textures.set(1, Direction::NegY, log_top);
+    queue.set_textures(Some(Arc::new(textures)));
+    queue.mark_chunk(ChunkPos::new(0, 0, 0));
+    let meshes = queue.dispatch(&jobs, &world, &opaque).pop().unwrap().future.wait();
+    let vertices: Vec<_> = meshes.iter().flat_map(|(_, mesh)| mesh.vertices.iter()).collect();
+    assert_eq!(vertices.len(), 48);
+    for vertex in vertices {
+        let face = Direction::from_index(vertex.face as usize);
+        let expected = match vertex.block {
+            1 if face.axis() == Direction::PosY.axis() => log_top,
+            1 => log_side,
+            // Block 2 has no textures.
+            _ => missing
+        };
+        assert_eq!(vertex.texture, expected.to_array());
+    }
+}
+
+#[test]
+fn remesh_jobs_mesh_translucent_blocks_apart() {
+    const WATER: u16 = 2;
+    let world = Arc::new(World::new());
+    let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    // A pool of water 2 wide on a stone floor.
+    for x in 0..2 {
+        chunk.set_block(LocalPos::new(x, 0, 0), 1);
+        chunk.set_block(LocalPos::new(x, 1, 0), WATER);
+    }
+    world.insert_chunk(chunk);
+    let opaque: Arc<OpacityFn> = Arc::new(|id| id == 1);
+    let jobs = JobSystem::new(2);
+    let queue = RemeshQueue::new();
+    queue.set_translucency(Some(Arc::new(|id| id == WATER) as Arc<TranslucencyFn>));
+    queue.mark_chunk(ChunkPos::new(0, 0, 0));
+    let meshes = queue.dispatch(&jobs, &world, &opaque).pop().unwrap().future.wait();
+    let (_, mesh) = meshes.iter().find(|(_, mesh)| !mesh.is_empty()).unwrap();
+    // The water's bottom face isn't drawn against the stone, but the stone's top face shows through the water.
+    assert_eq!(mesh.translucent.len() / 6, 5);
+    assert_eq!(mesh.indices.len() / 6, 6);
+    for (indices, translucent) in [(&mesh.indices, false), (&mesh.translucent, true)] {
+        assert!(indices.iter().all(|&index| (mesh.vertices[index as usize].block == WATER as u32) == translucent));
+    }
+}
+
+#[test]
+fn range_allocator_never_overlaps_and_merges_free_space() {
+    let mut rng = WorldRng::new(874);
+    let mut pool = RangeAllocator::new(1000);
+    let mut allocated: Vec<std::ops::Range<u64>> = Vec::new();
+    for _ in 0..5000 {
+        if allocated.is_empty() || rng.range_i32(0..3) > 0 {
+            let length = rng.range_i32(1..120) as u64;
+            match pool.alloc(length) {
+                Some(range) => {
+                    assert_eq!(range.end - range.start, length);
+                    assert!(range.end <= pool.capacity());
+                    assert!(allocated.iter().all(|other| range.end <= other.start || range.start >= other.end));
+                    allocated.push(range);
+                }
+                // Full, like a GPU buffer that needs replacing with a bigger one.
+                None => pool.grow(pool.capacity() * 2)
+            }
+        } else {
+            let index = rng.range_i32(0..allocated.len() as i32) as usize;
+            pool.free(allocated.swap_remove(index));
+        }
+        assert_eq!(pool.used(), allocated.iter().map(|range| range.end - range.start).sum::<u64>());
+    }
+    for range in allocated.drain(..) {
+        pool.free(range);
+    }
+    assert_eq!(pool.largest_free(), pool.capacity());
+}
\ No newline at end of file
@@ -0,0 +1,88 @@
+use std::{io::{ErrorKind, Read, Write}, net::TcpStream, thread, time::Duration};
+
+use shared::engine::{
+    job::system::JobSystem,
+    math::coords::ChunkPos,
+    metrics::{http::{MetricsEndpoint, CONNECTION_TIMEOUT, MAX_CONNECTIONS}, registry::global_registry, standard::engine_metrics},
+    world::{chunk::Chunk, World}
+};
+
+fn scrape(endpoint: &MetricsEndpoint, path: &str) -> String {
+    let mut scraper = TcpStream::connect(endpoint.local_addr()).unwrap();
+    scraper.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes()).unwrap();
+    while endpoint.pump().unwrap() == 0 {}
+    let mut response = String::new();
+    scraper.read_to_string(&mut response).unwrap();
+    return response;
+}
+
+#[test]
+fn engine_metrics_are_counted_and_scraped_over_http() {
+    let jobs = JobSystem::new(2);
+    let before = global_registry().snapshot();
+    let world = World::new();
+    for x in 0..3 {
+        world.insert_chunk(Chunk::filled(ChunkPos::new(x, 0, 0), 1));
+    }
+    // Other tests load chunks too, but never make the count go below this world's.
+    assert!(engine_metrics().chunks_loaded.get() >= 3.0);
+    for i in 0..10 {
+        jobs.run_job(move || i);
+    }
+    jobs.wait();
+    let after = global_registry().snapshot();
+    assert!(after.counter("jobs_executed_total").unwrap() >= before.counter("jobs_executed_total").unwrap_or(0) + 10);
+    assert!(after.rate(&before, "jobs_executed_total").unwrap() > 0.0);
+
+    let endpoint = MetricsEndpoint::bind("127.0.0.1:0", global_registry().clone()).unwrap();
+    let response = scrape(&endpoint, "/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+    assert!(response.contains("# TYPE world_chunks_loaded gauge\n"));
+    assert!(response.contains("# TYPE tick_duration_seconds histogram\n"));
+    assert!(response.contains("tick_duration_seconds_bucket{le=\"+Inf\"}"));
+    assert!(scrape(&endpoint, "/status").starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+/// Check if the endpoint hung up on a client, without waiting for it to.
+fn hung_up(client: &mut TcpStream) -> bool {
+    client.set_nonblocking(true).unwrap();
+    let closed = match client.read(&mut [0u8; 64]) {
+        Ok(0) => true,
+        Ok(_) => false,
+        Err(error) if error.kind() == ErrorKind::WouldBlock => false,
+        Err(_) => true
+    };
+    client.set_nonblocking(false).unwrap();
+    return closed;
+}
+
+#[test]
+fn slow_scrapers_are_served_without_blocking_and_limited() {
+    let endpoint = MetricsEndpoint::bind("127.0.0.1:0", global_registry().clone()).unwrap();
+    let mut idle: Vec<TcpStream> = (0..MAX_CONNECTIONS - 1).map(|_| TcpStream::connect(endpoint.local_addr()).unwrap()).collect();
+    // A request arriving in pieces is kept until the rest of it arrives, while pump() returns at once.
+    let mut slow = TcpStream::connect(endpoint.local_addr()).unwrap();
+    slow.write_all(b"GET /metr").unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(endpoint.pump().unwrap(), 0);
+
+    // Every connection slot is taken, so the next scraper is hung up on.
+    let mut turned_away = TcpStream::connect(endpoint.local_addr()).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(endpoint.pump().unwrap(), 0);
+    assert!(hung_up(&mut turned_away));
+    assert!(!hung_up(&mut idle[0]));
+
+    slow.write_all(b"ics HTTP/1.1\r\n\r\n").unwrap();
+    while endpoint.pump().unwrap() == 0 {}
+    let mut response = String::new();
+    slow.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+
+    // Scrapers that never finish their request are dropped once their time is up, freeing their slots.
+    thread::sleep(CONNECTION_TIMEOUT + Duration::from_millis(100));
+    assert_eq!(endpoint.pump().unwrap(), 0);
+    assert!(idle.iter_mut().all(hung_up));
+    assert!(scrape(&endpoint, "/metrics").starts_with("HTTP/1.1 200 OK\r\n"));
+}
\ No newline at end of file
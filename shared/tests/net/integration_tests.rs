@@ -0,0 +1,1013 @@
+use std::{collections::HashMap, sync::Arc, time::{Duration, Instant}};
+
+use shared::{
+    engine::{
+        block::{sign::{self, SignText}, BlockId, BlockRegistry},
+        command::{argument::{ArgumentKind, CommandEnvironment}, dispatcher::{CommandError, CommandSource, PermissionLevel}, CommandDispatcher},
+        entity::{kinematics::{Transform, Velocity}, Entities, EntityId},
+        job::system::JobSystem,
+        math::{coords::{BlockPos, ChunkPos, LocalPos, WorldPos}, rng::WorldRng, vector::Vec3},
+        net::{
+            admin::{AdminClient, AdminCommand, AdminServer, CommandConsole},
+            auth::{self, Authenticator},
+            chat::{self, ChatHistory, ChatKind, ChatRouter, CommandList, TabComplete, TabCompletions},
+            chunk_stream::{self, ChunkStreamer},
+            encryption::ServerIdentity,
+            handshake::{ClientHandshake, DisconnectReason, HandshakeState, ServerHandshake},
+            interaction::{self, BlockUpdate},
+            loopback::{LoopbackNetwork, NetworkConditions},
+            packet::{self, PacketReader, PacketWriter},
+            prediction::{self, MovementAuthority, MovementPredictor, MovementState},
+            sign::{self as sign_net, EditSign, SignEditError},
+            snapshot::{EntitySnapshot, SnapshotComponents, SnapshotReceiver, SnapshotSender},
+            time_sync::{TimeSync, TimeUpdate},
+            transport::{Channel, Connection, PacketLink, Priority, Transport}
+        },
+        version::{ModInfo, Version, VersionManifest},
+        world::{chunk::Chunk, container::World, time::{DAY_LENGTH, MIDNIGHT}}
+    },
+    packet
+};
+
+packet! {
+    #[derive(Clone, Debug, PartialEq)]
+    struct Everything = 900 {
+        small: u8,
+        flag: bool,
+        count: u32,
+        big: u64,
+        offset: i32,
+        delta: i64,
+        scale: f32,
+        name: String,
+        block: BlockPos,
+        chunk: ChunkPos,
+        position: WorldPos,
+        list: Vec<(i16, Option<String>)>
+    }
+}
+
+fn random_packet(rng: &mut WorldRng) -> Everything {
+    let text = |rng: &mut WorldRng| (0..rng.range_i32(0..20)).map(|_| char::from_u32(rng.range_i32(32..0x3000) as u32).unwrap_or('?')).collect::<String>();
+    return Everything {
+        small: rng.next_u32() as u8,
+        flag: rng.chance(0.5),
+        count: rng.next_u32() >> rng.range_i32(0..32),
+        big: rng.next_u64() >> rng.range_i32(0..64),
+        offset: rng.next_u32() as i32 >> rng.range_i32(0..32),
+        delta: rng.next_u64() as i64 >> rng.range_i32(0..64),
+        scale: rng.range_f32(-1e6..1e6),
+        name: text(rng),
+        block: BlockPos::new(rng.next_u32() as i32, rng.range_i32(-32_768..32_768), rng.next_u32() as i32),
+        chunk: ChunkPos::new(rng.next_u32() as i32, rng.range_i32(-1000..1000), rng.next_u32() as i32),
+        position: WorldPos::new(rng.next_f64() * 1e7, rng.next_f64(), -rng.next_f64()),
+        list: (0..rng.range_i32(0..8)).map(|_| (rng.next_u32() as i16, rng.chance(0.5).then(|| text(rng)))).collect()
+    };
+}
+
+#[test]
+fn packets_round_trip_and_reject_corruption() {
+    let mut rng = WorldRng::new(833);
+    for _ in 0..2000 {
+        let sent = random_packet(&mut rng);
+        let bytes = packet::encode(&sent);
+        assert_eq!(packet::packet_id(&bytes).unwrap(), 900);
+        assert_eq!(packet::decode::<Everything>(&bytes).unwrap(), sent);
+
+        // Truncated, extended or corrupted packets fail, or decode to something, but never panic.
+        assert!(packet::decode::<Everything>(&bytes[..rng.range_i32(0..bytes.len() as i32) as usize]).is_err());
+        let mut extended = bytes.clone();
+        extended.push(0);
+        assert!(packet::decode::<Everything>(&extended).is_err());
+        let mut corrupted = bytes.clone();
+        let index = rng.range_i32(0..corrupted.len() as i32) as usize;
+        corrupted[index] ^= 1 << rng.range_i32(0..8);
+        let _ = packet::decode::<Everything>(&corrupted);
+    }
+}
+
+#[test]
+fn small_values_stay_small() {
+    let entities = Entities::new();
+    let id = entities.spawn();
+    let mut writer = PacketWriter::new();
+    writer.write(&id).write(&-1i32).write(&ChunkPos::new(-2, 3, 60)).write(&vec![1u32; 100]);
+    assert_eq!(writer.len(), 2 + 1 + 3 + 1 + 100);
+    let bytes = writer.into_bytes();
+    let mut reader = PacketReader::new(&bytes);
+    assert_eq!(reader.read::<shared::engine::entity::EntityId>().unwrap(), id);
+    assert_eq!(reader.read::<i32>().unwrap(), -1);
+    assert_eq!(reader.read::<ChunkPos>().unwrap(), ChunkPos::new(-2, 3, 60));
+    assert_eq!(reader.read::<Vec<u32>>().unwrap().len(), 100);
+    assert!(reader.finish().is_ok());
+
+    // A list claiming more entries than the packet has bytes is refused without allocating them.
+    let mut writer = PacketWriter::new();
+    writer.write_var_u64(u64::MAX >> 1);
+    assert!(PacketReader::new(&writer.into_bytes()).read::<Vec<u64>>().is_err());
+}
+
+/// Pump both ends until a condition holds, failing the test after a few seconds.
+fn pump_until(server: &Transport, client: &Connection, jobs: &JobSystem, mut done: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while !done() {
+        assert!(start.elapsed() < Duration::from_secs(10), "Timed out waiting for packets");
+        let _ = client.pump();
+        server.pump(jobs);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn connections_deliver_reliable_and_unreliable_packets() {
+    let jobs = JobSystem::new(2);
+    let server = Transport::bind("127.0.0.1:0", true).unwrap();
+    let client = Connection::connect(server.local_addr()).unwrap();
+    client.open_unreliable(server.local_addr()).unwrap();
+    pump_until(&server, &client, &jobs, || server.len() == 1);
+    let id = server.connection_ids()[0];
+    assert!(server.open_unreliable(id, client.unreliable_local_addr().unwrap()));
+    let remote = server.connection(id).unwrap();
+
+    // Reliable packets arrive whole and in order, however the stream splits them.
+    let mut rng = WorldRng::new(834);
+    let sent: Vec<Vec<u8>> = (0..200).map(|i| (0..rng.range_i32(0..if i == 7 { 1_000_000 } else { 3000 })).map(|_| rng.next_u32() as u8).collect()).collect();
+    for packet in sent.iter() {
+        client.send(Channel::Reliable, packet.clone());
+    }
+    let mut received = Vec::new();
+    pump_until(&server, &client, &jobs, || {
+        received.extend(remote.receive());
+        received.len() == sent.len()
+    });
+    assert_eq!(received, sent);
+
+    // Small unreliable packets go over UDP in both directions. Localhost doesn't drop them.
+    let reliable_bytes = client.received_bytes();
+    remote.send(Channel::Unreliable, b"position".to_vec());
+    client.send(Channel::Unreliable, b"input".to_vec());
+    let (mut to_client, mut to_server) = (Vec::new(), Vec::new());
+    pump_until(&server, &client, &jobs, || {
+        to_client.extend(client.receive());
+        to_server.extend(remote.receive());
+        !to_client.is_empty() && !to_server.is_empty()
+    });
+    assert_eq!((to_client[0].as_slice(), to_server[0].as_slice()), (&b"position"[..], &b"input"[..]));
+    assert_eq!(client.received_bytes(), reliable_bytes + 8);
+
+    // Closing one end closes the other.
+    client.close();
+    let start = Instant::now();
+    while server.len() == 1 {
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(server.pump(&jobs).iter().all(|(closed, _)| *closed == id));
+    }
+    assert!(!remote.is_open());
+}
+
+#[test]
+fn throttled_connections_send_what_matters_first() {
+    let jobs = JobSystem::new(2);
+    let server = Transport::bind("127.0.0.1:0", true).unwrap();
+    let client = Connection::connect(server.local_addr()).unwrap();
+    client.open_unreliable(server.local_addr()).unwrap();
+    pump_until(&server, &client, &jobs, || server.len() == 1);
+    let id = server.connection_ids()[0];
+    server.open_unreliable(id, client.unreliable_local_addr().unwrap());
+    let remote = server.connection(id).unwrap();
+
+    // 40 KB of chunks at 100 KB/s takes a while, and movement queued after them still goes first.
+    remote.set_bandwidth_limit(Channel::Reliable, Some(100_000));
+    for index in 0..40u8 {
+        remote.send_with_priority(Channel::Reliable, Priority::Low, vec![index; 1000]);
+    }
+    let start = Instant::now();
+    let mut received = Vec::new();
+    let mut moved = false;
+    pump_until(&server, &client, &jobs, || {
+        if !received.is_empty() && !moved {
+            remote.send_with_priority(Channel::Reliable, Priority::High, b"movement".to_vec());
+            moved = true;
+        }
+        received.extend(client.receive());
+        received.len() == 41
+    });
+    assert!(start.elapsed() > Duration::from_millis(250), "Sent 40 KB in {:?}", start.elapsed());
+    let movement = received.iter().position(|packet| packet == b"movement").unwrap();
+    assert!(movement < 10, "Movement arrived after {} chunks", movement);
+    let chunks: Vec<u8> = received.iter().filter(|packet| packet.len() == 1000).map(|packet| packet[0]).collect();
+    assert_eq!(chunks, (0..40).collect::<Vec<u8>>());
+
+    // Datagrams over the limit are dropped rather than queued.
+    remote.set_bandwidth_limit(Channel::Unreliable, Some(10_000));
+    for _ in 0..100 {
+        remote.send(Channel::Unreliable, vec![0; 1000]);
+    }
+    server.pump(&jobs);
+    assert!(remote.dropped_datagrams() >= 80);
+    assert_eq!(remote.bandwidth_limit(Channel::Unreliable), Some(10_000));
+
+    // A client that can't keep up is dropped rather than queued for without end.
+    remote.set_max_queued_bytes(20_000);
+    for _ in 0..40 {
+        remote.send(Channel::Reliable, vec![0; 1000]);
+    }
+    assert_eq!(server.pump(&jobs).len(), 1);
+    assert!(server.is_empty());
+}
+
+#[test]
+fn peers_sending_faster_than_they_are_handled_are_dropped() {
+    let jobs = JobSystem::new(2);
+    let server = Transport::bind("127.0.0.1:0", false).unwrap();
+    let client = Connection::connect(server.local_addr()).unwrap();
+    pump_until(&server, &client, &jobs, || !server.is_empty());
+    let remote = server.connection(server.connection_ids()[0]).unwrap();
+    remote.set_max_received_bytes(10_000);
+
+    // Packets handled as they come in never fill the queue.
+    for _ in 0..20 {
+        client.send(Channel::Reliable, vec![0; 1000]);
+        pump_until(&server, &client, &jobs, || remote.receive().len() == 1);
+    }
+    assert_eq!(server.len(), 1);
+
+    for _ in 0..20 {
+        client.send(Channel::Reliable, vec![0; 1000]);
+    }
+    pump_until(&server, &client, &jobs, || server.is_empty());
+    assert!(!remote.is_open());
+}
+
+#[test]
+fn encrypted_connections_carry_both_channels_and_check_the_server() {
+    let jobs = JobSystem::new(2);
+    let identity = ServerIdentity::generate().unwrap();
+    let server = Transport::bind("127.0.0.1:0", true).unwrap().with_encryption(ServerIdentity::from_private_key(identity.private_key()).unwrap());
+    assert!(server.is_encrypted());
+    let client = Connection::connect_encrypted(server.local_addr(), Some(identity.public_key())).unwrap();
+    client.open_unreliable(server.local_addr()).unwrap();
+    // Bigger than one encrypted message, so it's split and joined back up.
+    let big: Vec<u8> = (0..200_000u32).map(|index| index as u8).collect();
+    client.send(Channel::Reliable, big.clone());
+    client.send(Channel::Reliable, Vec::new());
+    pump_until(&server, &client, &jobs, || client.is_encrypted());
+    assert_eq!(client.server_key(), Some(identity.public_key()));
+
+    let id = server.connection_ids()[0];
+    server.open_unreliable(id, client.unreliable_local_addr().unwrap());
+    let remote = server.connection(id).unwrap();
+    let mut received = Vec::new();
+    pump_until(&server, &client, &jobs, || {
+        client.send(Channel::Unreliable, b"movement".to_vec());
+        received.extend(remote.receive());
+        received.iter().any(|packet| packet == b"movement") && received.iter().any(|packet| packet.is_empty())
+    });
+    // Datagrams can overtake reliable packets, but reliable ones stay in order.
+    let reliable: Vec<&Vec<u8>> = received.iter().filter(|packet| *packet != b"movement").collect();
+    assert_eq!(reliable, vec![&big, &Vec::new()]);
+    remote.send(Channel::Reliable, b"chat".to_vec());
+    let mut replies = Vec::new();
+    pump_until(&server, &client, &jobs, || {
+        replies.extend(client.receive());
+        !replies.is_empty()
+    });
+    assert_eq!(replies, vec![b"chat".to_vec()]);
+
+    // Someone in the middle, or a server that lost its key, fails the check.
+    let impostor = Connection::connect_encrypted(server.local_addr(), Some(ServerIdentity::generate().unwrap().public_key())).unwrap();
+    let start = Instant::now();
+    let error = loop {
+        assert!(start.elapsed() < Duration::from_secs(10));
+        server.pump(&jobs);
+        if let Err(error) = impostor.pump() {
+            break error;
+        }
+    };
+    assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+
+    // Clients that don't encrypt are cut off rather than read.
+    let plain = Connection::connect(server.local_addr()).unwrap();
+    plain.send(Channel::Reliable, b"token".to_vec());
+    let start = Instant::now();
+    while plain.pump().is_ok() {
+        assert!(start.elapsed() < Duration::from_secs(10));
+        server.pump(&jobs);
+    }
+    assert_eq!(server.len(), 1);
+}
+
+/// Connect a client with a manifest to a server with another, running both handshakes until they settle.
+fn handshake(server_manifest: VersionManifest, client_manifest: VersionManifest) -> (HandshakeState, HandshakeState, Option<u64>) {
+    let jobs = JobSystem::new(1);
+    let server = Transport::bind("127.0.0.1:0", false).unwrap();
+    let client = Connection::connect(server.local_addr()).unwrap();
+    let mut client_handshake = ClientHandshake::new(client_manifest, "steve", None);
+    client.send(Channel::Reliable, client_handshake.start());
+    let mut handshakes: HashMap<u32, ServerHandshake> = HashMap::new();
+    let mut server_state = None;
+    pump_until(&server, &client, &jobs, || {
+        for id in server.connection_ids() {
+            let connection = server.connection(id).unwrap();
+            let handshake = handshakes.entry(id).or_insert_with(|| ServerHandshake::new(server_manifest.clone()));
+            for packet in connection.receive() {
+                for reply in handshake.handle(&packet, 100 + id as u64) {
+                    connection.send(Channel::Reliable, reply);
+                }
+            }
+            if !matches!(handshake.state(), HandshakeState::Hello | HandshakeState::Login) {
+                server_state = Some(handshake.state().clone());
+            }
+        }
+        for packet in client.receive() {
+            for reply in client_handshake.handle(&packet) {
+                client.send(Channel::Reliable, reply);
+            }
+        }
+        server_state.is_some() && !matches!(client_handshake.state(), HandshakeState::Hello | HandshakeState::Login)
+    });
+    return (server_state.unwrap(), client_handshake.state().clone(), client_handshake.player());
+}
+
+#[test]
+fn handshakes_log_in_matching_builds_and_turn_away_others() {
+    let current = VersionManifest::current(vec![]);
+    assert_eq!(handshake(current.clone(), current.clone()), (HandshakeState::Play, HandshakeState::Play, Some(101)));
+
+    let mut old_protocol = current.clone();
+    old_protocol.protocol -= 1;
+    let (server, client, player) = handshake(current.clone(), old_protocol);
+    assert_eq!(server, client);
+    assert!(matches!(server, HandshakeState::Disconnected(DisconnectReason::IncompatibleVersion(_))));
+    assert_eq!(player, None);
+
+    // A server running a mod the client lacks is refused by the client, which tells the server why.
+    let modded = VersionManifest::current(vec![ModInfo { id: "machines".to_string(), version: Version::new(1, 2, 0) }]);
+    let (server, client, _) = handshake(modded, current);
+    let HandshakeState::Disconnected(DisconnectReason::IncompatibleVersion(problems)) = client else {
+        panic!("Client joined a server it's missing mods for");
+    };
+    assert_eq!(problems, vec!["server requires mod machines v1.2.0".to_string()]);
+    assert_eq!(server, HandshakeState::Disconnected(DisconnectReason::IncompatibleVersion(problems)));
+}
+
+/// Accounts with a password each, as a game's account service would check.
+struct Accounts(HashMap<String, (String, u128)>);
+
+impl Authenticator for Accounts {
+    fn authenticate(&self, name: &str, token: &str) -> Result<u128, String> {
+        return match self.0.get(name) {
+            Some((password, uuid)) if password == token => Ok(*uuid),
+            Some(_) => Err("wrong token".to_string()),
+            None => Err(format!("no account named {}", name))
+        };
+    }
+}
+
+/// Run both sides of a handshake in memory until neither has anything left to send.
+fn log_in(mut server: ServerHandshake, mut client: ClientHandshake) -> (ServerHandshake, ClientHandshake) {
+    let mut to_server = vec![client.start()];
+    while !to_server.is_empty() {
+        let to_client: Vec<Vec<u8>> = to_server.iter().flat_map(|packet| server.handle(packet, 1)).collect();
+        to_server = to_client.iter().flat_map(|packet| client.handle(packet)).collect();
+    }
+    return (server, client);
+}
+
+#[test]
+fn handshakes_authenticate_players() {
+    let manifest = VersionManifest::current(vec![]);
+    let accounts: Arc<dyn Authenticator> = Arc::new(Accounts(HashMap::from([("steve".to_string(), ("hunter2".to_string(), 42))])));
+    let server = || ServerHandshake::new(manifest.clone()).with_authenticator(accounts.clone());
+
+    let (server_side, client_side) = log_in(server(), ClientHandshake::new(manifest.clone(), "steve", None).with_token("hunter2"));
+    assert_eq!((server_side.state(), client_side.state()), (&HandshakeState::Play, &HandshakeState::Play));
+    assert_eq!((server_side.uuid(), client_side.uuid()), (Some(42), Some(42)));
+    // The token isn't kept around once checked.
+    assert!(server_side.login().unwrap().token.is_empty());
+
+    for (name, token, problem) in [("steve", "hunter3", "wrong token"), ("alex", "", "no account named alex")] {
+        let (server_side, client_side) = log_in(server(), ClientHandshake::new(manifest.clone(), name, None).with_token(token));
+        assert_eq!(server_side.state(), &HandshakeState::Disconnected(DisconnectReason::Unauthenticated(problem.to_string())));
+        assert_eq!(client_side.state(), server_side.state());
+        assert_eq!(client_side.uuid(), None);
+    }
+
+    // Offline servers let anyone in under a valid name, always with the same UUID for the same name.
+    let (_, client_side) = log_in(ServerHandshake::new(manifest.clone()), ClientHandshake::new(manifest.clone(), "alex", None));
+    assert_eq!(client_side.uuid(), Some(auth::offline_uuid("alex")));
+    let (server_side, _) = log_in(ServerHandshake::new(manifest.clone()), ClientHandshake::new(manifest.clone(), "a lex", None));
+    assert!(matches!(server_side.state(), HandshakeState::Disconnected(DisconnectReason::Unauthenticated(_))));
+}
+
+#[test]
+fn chunks_stream_nearest_first_within_budget() {
+    let jobs = JobSystem::new(2);
+    let server = Transport::bind("127.0.0.1:0", false).unwrap();
+    let client = Connection::connect(server.local_addr()).unwrap();
+    pump_until(&server, &client, &jobs, || server.len() == 1);
+    let remote = server.connection(server.connection_ids()[0]).unwrap();
+
+    let server_world = World::new();
+    let mut rng = WorldRng::new(836);
+    for pos in ChunkPos::ORIGIN.within_radius(4) {
+        let mut chunk = Chunk::filled(pos, 1);
+        for _ in 0..rng.range_i32(0..2000) {
+            chunk.set_block(LocalPos::new(rng.range_i32(0..32) as u8, rng.range_i32(0..32) as u8, rng.range_i32(0..32) as u8), rng.range_i32(0..40) as u16);
+        }
+        server_world.insert_chunk(chunk);
+    }
+    let budget = 16 * 1024;
+    let mut streamer = ChunkStreamer::new(ChunkPos::ORIGIN, 3).with_budget(budget).with_max_in_flight(16);
+    let client_world = World::new();
+    let mut arrived = Vec::new();
+    let start = Instant::now();
+    while arrived.len() < 123 {
+        assert!(start.elapsed() < Duration::from_secs(20), "Timed out streaming chunks");
+        let packets = streamer.tick(&server_world);
+        let bytes: usize = packets.iter().map(|packet| packet.len()).sum();
+        assert!(packets.len() <= 1 || bytes <= budget);
+        for packet in packets {
+            remote.send(Channel::Reliable, packet);
+        }
+        server.pump(&jobs);
+        let _ = client.pump();
+        for packet in client.receive() {
+            let ack = chunk_stream::apply_packet(&client_world, &packet).unwrap().unwrap();
+            arrived.push(packet::decode::<shared::engine::net::chunk_stream::ChunkAck>(&ack).unwrap().pos);
+            client.send(Channel::Reliable, ack);
+        }
+        for packet in remote.receive() {
+            assert!(streamer.handle(&packet));
+        }
+    }
+    // Every chunk within the radius as a sphere, nearest first.
+    assert!(arrived.windows(2).all(|pair| pair[0].distance_squared(ChunkPos::ORIGIN) <= pair[1].distance_squared(ChunkPos::ORIGIN)));
+    for pos in arrived.iter() {
+        assert!(*client_world.chunk(*pos).unwrap().read().unwrap() == *server_world.chunk(*pos).unwrap().read().unwrap());
+    }
+    assert_eq!(client_world.chunk_count(), 123);
+
+    // Moving away unloads chunks beyond the radius plus one.
+    streamer.set_center(ChunkPos::new(3, 0, 0));
+    for packet in streamer.tick(&server_world) {
+        chunk_stream::apply_packet(&client_world, &packet).unwrap();
+    }
+    assert!(!client_world.is_loaded(ChunkPos::new(-2, 0, 0)));
+    assert!(client_world.is_loaded(ChunkPos::new(-1, 0, 0)));
+}
+
+fn assert_replicated(server: &Entities, client: &Entities, receiver: &SnapshotReceiver, ids: &[EntityId]) {
+    assert_eq!(client.len(), ids.len());
+    for id in ids {
+        let local = receiver.local(*id).unwrap();
+        assert_eq!(client.get::<Transform>(local), server.get::<Transform>(*id));
+        assert_eq!(client.get::<Velocity>(local), server.get::<Velocity>(*id));
+    }
+}
+
+#[test]
+fn snapshots_send_changes_and_survive_packet_loss() {
+    let mut components = SnapshotComponents::new();
+    components.register::<Transform>();
+    components.register::<Velocity>();
+    let (server, client) = (Entities::new(), Entities::new());
+    let mut rng = WorldRng::new(837);
+    let mut ids: Vec<EntityId> = (0..200).map(|_| server.spawn()).collect();
+    for id in ids.iter() {
+        let position = WorldPos::new(rng.next_f64() * 100.0, rng.next_f64() * 100.0, rng.next_f64() * 100.0);
+        server.insert(*id, Transform::new(position)).unwrap();
+    }
+    let mut sender = SnapshotSender::new();
+    let mut receiver = SnapshotReceiver::new();
+
+    let full = sender.snapshot(&components, &server, &ids);
+    assert_eq!(packet::decode::<EntitySnapshot>(&full).unwrap().baseline, None);
+    sender.handle(&receiver.apply(&components, &client, &full).unwrap().unwrap());
+    assert_replicated(&server, &client, &receiver, &ids);
+
+    let mut delta_bytes = 0;
+    for tick in 0..100 {
+        for _ in 0..5 {
+            let id = ids[rng.range_i32(0..ids.len() as i32) as usize];
+            let mut transform = server.get::<Transform>(id).unwrap();
+            transform.position.x += 1.0;
+            server.insert(id, transform).unwrap();
+        }
+        match tick % 10 {
+            3 => {
+                let id = server.spawn();
+                server.insert(id, Transform::default()).unwrap();
+                server.insert(id, Velocity::new(Default::default())).unwrap();
+                ids.push(id);
+            },
+            6 => {
+                let id = ids.remove(rng.range_i32(0..ids.len() as i32) as usize);
+                server.despawn(id);
+            },
+            8 => {
+                server.remove::<Velocity>(*ids.last().unwrap());
+            },
+            _ => ()
+        }
+        let snapshot = sender.snapshot(&components, &server, &ids);
+        delta_bytes += snapshot.len();
+        // Every third snapshot is lost, and every fourth acknowledgement.
+        if tick % 3 == 0 {
+            continue;
+        }
+        let ack = receiver.apply(&components, &client, &snapshot).unwrap().unwrap();
+        if tick % 4 != 0 {
+            assert!(sender.handle(&ack));
+        }
+        assert_replicated(&server, &client, &receiver, &ids);
+    }
+    assert!(delta_bytes / 100 * 10 < full.len(), "Deltas averaged {} bytes against {} for a full snapshot", delta_bytes / 100, full.len());
+
+    // A client that hears nothing back for longer than the history gets a full snapshot.
+    for _ in 0..40 {
+        sender.snapshot(&components, &server, &ids);
+    }
+    let snapshot = sender.snapshot(&components, &server, &ids);
+    assert_eq!(packet::decode::<EntitySnapshot>(&snapshot).unwrap().baseline, None);
+    sender.handle(&receiver.apply(&components, &client, &snapshot).unwrap().unwrap());
+    assert_replicated(&server, &client, &receiver, &ids);
+
+    // A client that lost its baseline asks for a resync.
+    let mut forgetful = SnapshotReceiver::new();
+    let fresh = Entities::new();
+    let delta = sender.snapshot(&components, &server, &ids);
+    assert!(packet::decode::<EntitySnapshot>(&delta).unwrap().baseline.is_some());
+    assert!(sender.handle(&forgetful.apply(&components, &fresh, &delta).unwrap().unwrap()));
+    let resync = sender.snapshot(&components, &server, &ids);
+    forgetful.apply(&components, &fresh, &resync).unwrap().unwrap();
+    assert_replicated(&server, &fresh, &forgetful, &ids);
+}
+
+#[test]
+fn loopback_links_delay_lose_and_reorder_deterministically() {
+    let run = |seed| {
+        let network = LoopbackNetwork::new(seed);
+        let conditions = NetworkConditions::PERFECT.with_latency(40).with_jitter(20).with_loss(0.1).with_reordering(0.1);
+        let (client, server) = network.connect(conditions);
+        let mut received = Vec::new();
+        for tick in 0..200u32 {
+            client.send(Channel::Reliable, [&[0], &tick.to_le_bytes()[..]].concat());
+            client.send(Channel::Unreliable, [&[1], &tick.to_le_bytes()[..]].concat());
+            network.advance(10);
+            received.extend(server.receive());
+        }
+        network.advance(1000);
+        received.extend(server.receive());
+        return received;
+    };
+    let received = run(846);
+    assert_eq!(received, run(846));
+    assert_ne!(received, run(847));
+
+    let sequences = |channel| received.iter().filter(|packet| packet[0] == channel).map(|packet| u32::from_le_bytes(packet[1..].try_into().unwrap())).collect::<Vec<u32>>();
+    // Reliable packets all arrive in order, while unreliable ones are lost and overtaken.
+    assert_eq!(sequences(0), (0..200).collect::<Vec<u32>>());
+    let unreliable = sequences(1);
+    assert!((150..195).contains(&unreliable.len()), "{} of 200 datagrams arrived", unreliable.len());
+    assert!(unreliable.windows(2).any(|pair| pair[0] > pair[1]));
+
+    // Nothing arrives before the latency, and a closed link carries nothing.
+    let network = LoopbackNetwork::new(1);
+    let (client, server) = network.connect(NetworkConditions::PERFECT.with_latency(100));
+    client.send(Channel::Unreliable, b"input".to_vec());
+    network.advance(99);
+    assert!(server.receive().is_empty());
+    assert_eq!(client.in_flight(), 1);
+    server.close();
+    network.advance(1);
+    assert!(server.receive().is_empty());
+    client.send(Channel::Reliable, b"input".to_vec());
+    assert!(!client.is_open());
+    assert_eq!(client.in_flight(), 0);
+}
+
+#[test]
+fn chunks_stream_over_a_slow_link_without_overfilling_it() {
+    let network = LoopbackNetwork::new(846);
+    let (client, server) = network.connect(NetworkConditions::PERFECT.with_latency(100).with_jitter(50));
+    let server_world = World::new();
+    for pos in ChunkPos::ORIGIN.within_radius(3) {
+        server_world.insert_chunk(Chunk::filled(pos, 1));
+    }
+    let mut streamer = ChunkStreamer::new(ChunkPos::ORIGIN, 2).with_max_in_flight(8);
+    let client_world = World::new();
+    let mut ticks = 0;
+    while client_world.chunk_count() < 33 {
+        ticks += 1;
+        assert!(ticks < 1000, "Timed out streaming chunks");
+        for packet in streamer.tick(&server_world) {
+            server.send_with_priority(Channel::Reliable, Priority::Low, packet);
+        }
+        assert!(streamer.in_flight() <= 8);
+        network.advance(50);
+        for packet in client.receive() {
+            client.send(Channel::Reliable, chunk_stream::apply_packet(&client_world, &packet).unwrap().unwrap());
+        }
+        for packet in server.receive() {
+            assert!(streamer.handle(&packet));
+        }
+    }
+    // 33 chunks at 8 a round trip of 200 to 300 ms is at least 5 round trips, or 20 ticks of 50 ms.
+    assert!(ticks >= 20, "Streamed in {} ticks", ticks);
+}
+
+#[test]
+fn predicted_movement_is_immediate_and_matches_the_server() {
+    let mut rng = WorldRng::new(838);
+    let start = MovementState { position: WorldPos::new(0.5, 64.0, 0.5), velocity: Vec3::ZERO };
+    // 75 ms each way is three ticks of round trip at 20 ticks per second.
+    let steady = NetworkConditions::PERFECT.with_latency(75);
+    for conditions in [steady, steady.with_jitter(40).with_loss(0.2).with_reordering(0.1)] {
+        let network = LoopbackNetwork::new(838);
+        let (to_server, to_client) = network.connect(conditions);
+        let mut server = MovementAuthority::new(start, prediction::fly);
+        let mut client = MovementPredictor::new(start, prediction::fly);
+        let mut direction = Vec3::new(1.0, 0.0, 0.0);
+        for tick in 0..300 {
+            if tick < 200 {
+                if tick % 25 == 0 {
+                    direction = Vec3::new(rng.next_f32() * 2.0 - 1.0, rng.next_f32() * 2.0 - 1.0, rng.next_f32() * 2.0 - 1.0);
+                }
+                let before = client.state().position;
+                let packet = client.input(direction);
+                // The player moves this tick, not a round trip later.
+                assert!(client.state().position.distance(before) > 0.0);
+                to_server.send(Channel::Unreliable, packet);
+            }
+            for packet in to_client.receive() {
+                assert!(server.handle(&packet));
+            }
+            to_client.send(Channel::Unreliable, server.tick());
+            for packet in to_server.receive() {
+                assert!(client.handle(&packet));
+                if conditions == steady {
+                    assert_eq!(client.correction(), 0.0);
+                }
+            }
+            network.advance(50);
+        }
+        assert_eq!(client.pending(), 0);
+        assert_eq!(server.last_input(), Some(199));
+        assert_eq!(client.state(), server.state());
+    }
+}
+
+#[test]
+fn chat_reaches_every_player_and_commands_reach_the_server() {
+    let jobs = JobSystem::new(2);
+    let server = Transport::bind("127.0.0.1:0", false).unwrap();
+    let clients = [Connection::connect(server.local_addr()).unwrap(), Connection::connect(server.local_addr()).unwrap()];
+    pump_until(&server, &clients[0], &jobs, || { let _ = clients[1].pump(); server.len() == 2 });
+    let mut histories = [ChatHistory::default(), ChatHistory::default()];
+    let mut router = ChatRouter::new();
+    let mut ids = server.connection_ids();
+    ids.sort_unstable();
+    let mut outgoing = router.join(ids[0], "steve");
+    outgoing.extend(router.join(ids[1], "alex"));
+
+    clients[0].send(Channel::Reliable, chat::send("§4Hello §lthere"));
+    clients[1].send(Channel::Reliable, chat::send("/kick steve"));
+    let mut commands = Vec::new();
+    pump_until(&server, &clients[0], &jobs, || {
+        let _ = clients[1].pump();
+        for id in ids.iter() {
+            for packet in server.connection(*id).unwrap().receive() {
+                outgoing.extend(router.handle(*id, &packet).unwrap());
+            }
+        }
+        commands.extend(router.take_commands());
+        for (to, packet) in outgoing.drain(..) {
+            server.connection(to).unwrap().send(Channel::Reliable, packet);
+        }
+        for (client, history) in clients.iter().zip(histories.iter_mut()) {
+            for packet in client.receive() {
+                assert!(history.handle(&packet));
+            }
+        }
+        histories[0].len() == 3 && histories[1].len() == 2
+    });
+    assert_eq!(commands, vec![(ids[1], "kick steve".to_string())]);
+    // Steve joined before alex, so only steve saw both join.
+    for history in histories.iter() {
+        let last = history.lines().last().unwrap();
+        assert_eq!((last.kind, last.plain_text()), (ChatKind::Player, "<steve> Hello there".to_string()));
+        // Players can't format their chat.
+        assert_eq!(last.spans.len(), 1);
+        assert!(history.lines().rev().skip(1).all(|line| line.kind == ChatKind::System && line.plain_text().ends_with("joined the game")));
+    }
+}
+
+/// A server console that only knows who's online.
+#[derive(Default)]
+struct TestConsole {
+    players: Vec<String>,
+    saves: u32,
+    stopped: bool
+}
+
+impl CommandConsole for TestConsole {
+    fn execute(&mut self, command: AdminCommand) -> Result<String, String> {
+        return match command {
+            AdminCommand::List => Ok(format!("{} players online: {}", self.players.len(), self.players.join(", "))),
+            AdminCommand::Kick { player, reason } => match self.players.iter().position(|name| *name == player) {
+                Some(index) => {
+                    self.players.remove(index);
+                    Ok(format!("kicked {}: {}", player, reason.unwrap_or_default()))
+                },
+                None => Err(format!("{} isn't online", player))
+            },
+            AdminCommand::SaveAll => {
+                self.saves += 1;
+                Ok("saved".to_string())
+            },
+            AdminCommand::Backup { name } | AdminCommand::Restore { name } => Err(format!("no save to back up as {}", name)),
+            AdminCommand::Stop => {
+                self.stopped = true;
+                Ok("stopping".to_string())
+            }
+        };
+    }
+}
+
+#[test]
+fn admins_run_console_commands_remotely() {
+    let identity = ServerIdentity::generate().unwrap();
+    let key = identity.public_key();
+    assert!(AdminServer::bind("127.0.0.1:0", "", identity.clone()).is_err());
+    let lockout = Duration::from_secs(2);
+    let mut server = AdminServer::bind("127.0.0.1:0", "swordfish", identity).unwrap().with_lockout(lockout);
+    let address = server.local_addr();
+    let running = std::thread::spawn(move || {
+        let jobs = JobSystem::new(1);
+        let mut console = TestConsole { players: vec!["steve".to_string(), "alex".to_string()], ..TestConsole::default() };
+        let start = Instant::now();
+        while !console.stopped {
+            assert!(start.elapsed() < Duration::from_secs(20), "Server was never stopped");
+            server.pump(&jobs, &mut console);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        // Send the last reply.
+        server.pump(&jobs, &mut console);
+        return console;
+    });
+    let timeout = Duration::from_secs(10);
+
+    // Too many wrong passwords close the connection.
+    let mut guesser = AdminClient::connect(address, "password", None).unwrap();
+    let (start, mut guesses) = (Instant::now(), 0);
+    while guesser.pump().is_ok() {
+        assert!(start.elapsed() < timeout, "Server let a client keep guessing");
+        if guesser.is_authenticated() == Some(false) {
+            guesses += 1;
+            guesser.login(&format!("password{}", guesses));
+        }
+    }
+    // The last rejection can arrive in the pump that sees the connection close. Guesses sent after it go unanswered.
+    if guesser.is_authenticated() == Some(false) {
+        guesses += 1;
+    }
+    assert_eq!(guesses, 3, "Server answered {} guesses", guesses);
+
+    // Reconnecting doesn't give more guesses: the address is refused, even with the right password, until the
+    // lockout passes.
+    let locked_out = Instant::now();
+    let mut retry = AdminClient::connect(address, "swordfish", Some(key)).unwrap();
+    while retry.pump().is_ok() {
+        assert!(locked_out.elapsed() < lockout, "Server kept a locked out client connected");
+    }
+    assert_eq!(retry.is_authenticated(), Some(false));
+    std::thread::sleep(lockout);
+
+    // Commands before logging in close the connection without running.
+    let mut sneaky = AdminClient::connect(address, "", None).unwrap();
+    assert!(sneaky.run("stop", timeout).is_err());
+
+    // A client pinning the server's key won't send the password to anyone else.
+    let mut misdirected = AdminClient::connect(address, "swordfish", Some(ServerIdentity::generate().unwrap().public_key())).unwrap();
+    assert!(misdirected.run("list", timeout).is_err());
+    assert_eq!(misdirected.is_authenticated(), None);
+
+    let mut admin = AdminClient::connect(address, "swordfish", Some(key)).unwrap();
+    let response = admin.run("list", timeout).unwrap();
+    assert_eq!(admin.is_authenticated(), Some(true));
+    assert_eq!(admin.server_key(), Some(key));
+    assert_eq!((response.success, response.output.as_str()), (true, "2 players online: steve, alex"));
+    let response = admin.run("/kick steve griefing spawn", timeout).unwrap();
+    assert_eq!(response.output, "kicked steve: griefing spawn");
+    let response = admin.run("kick steve", timeout).unwrap();
+    assert_eq!((response.success, response.output.as_str()), (false, "steve isn't online"));
+    assert!(!admin.run("op alex", timeout).unwrap().success);
+    assert!(admin.run("save-all", timeout).unwrap().success);
+    assert_eq!(admin.run("stop", timeout).unwrap().output, "stopping");
+
+    let console = running.join().unwrap();
+    assert_eq!((console.players, console.saves), (vec!["alex".to_string()], 1));
+}
+
+#[test]
+fn world_time_is_replicated_to_clients() {
+    let network = LoopbackNetwork::new(862);
+    let (client_link, server_link) = network.connect(NetworkConditions::PERFECT.with_latency(100));
+    let server = World::new();
+    let client = World::new();
+    let mut sync = TimeSync::new();
+    for tick in 0..80 {
+        if tick == 40 {
+            server.set_time(DAY_LENGTH + MIDNIGHT);
+        }
+        server.advance_time(1);
+        if let Some(update) = sync.update(&server, tick) {
+            server_link.send(Channel::Reliable, packet::encode(&update));
+        }
+        network.advance(50);
+        for received in client_link.receive() {
+            packet::decode::<TimeUpdate>(&received).unwrap().apply(&client);
+        }
+        client.advance_time(1);
+        // Between updates the client keeps time itself, only ever behind by the link's latency.
+        if tick >= 2 && tick != 40 && tick != 41 {
+            assert!(server.time() - client.time() <= 2, "tick {}: server {} client {}", tick, server.time(), client.time());
+        }
+    }
+    assert_eq!(client.world_time().day(), 1);
+    assert!(!client.world_time().is_day());
+    assert_eq!(client.world_time().moon_phase(), server.world_time().moon_phase());
+}
+
+#[test]
+fn signs_follow_their_chunks_and_edits_are_checked() {
+    let server = World::new();
+    for pos in ChunkPos::ORIGIN.within_radius(1) {
+        server.insert_chunk(Chunk::new(pos));
+    }
+    let block = BlockPos::new(4, 1, 4);
+    server.set_block(block, 1);
+    assert!(sign::set_sign_text(&server, block, SignText::new(&["Welcome"]).unwrap()));
+    // A sign nobody has written on isn't sent.
+    assert!(sign::set_sign_text(&server, BlockPos::new(-4, 1, 4), SignText::default()));
+
+    let client = World::new();
+    let mut streamer = ChunkStreamer::new(ChunkPos::ORIGIN, 1);
+    let mut updates = 0;
+    for packet in streamer.tick(&server) {
+        if chunk_stream::apply_packet(&client, &packet).unwrap().is_none() {
+            assert!(sign_net::apply_packet(&client, &packet).unwrap());
+            updates += 1;
+        }
+    }
+    assert_eq!(updates, 1);
+    assert_eq!(sign::sign_text(&client, block), sign::sign_text(&server, block));
+    assert_eq!(sign::sign_text(&client, BlockPos::new(-4, 1, 4)), None);
+
+    let eye = WorldPos::new(4.5, 2.6, 7.5);
+    let edit = EditSign { block, lines: vec!["Spawn".to_string(), "\u{7}".to_string()] };
+    assert!(matches!(sign_net::validate_edit(&server, eye, &edit, 6.0), Err(SignEditError::InvalidText(_))));
+    let edit = EditSign { block: block + BlockPos::new(0, 1, 0), lines: vec!["Spawn".to_string()] };
+    assert_eq!(sign_net::validate_edit(&server, eye, &edit, 6.0), Err(SignEditError::NotASign));
+    let edit = EditSign { block, lines: vec!["Spawn".to_string()] };
+    let text = sign_net::validate_edit(&server, eye, &edit, 6.0).unwrap();
+    sign::set_sign_text(&server, block, text);
+    sign_net::apply_packet(&client, &sign_net::sign_update(&server, block).unwrap()).unwrap();
+    assert_eq!(sign::sign_text(&client, block).unwrap().lines()[0], "Spawn");
+
+    // Breaking the sign takes its text with it.
+    server.set_block(block, 0);
+    interaction::apply_packet(&client, &packet::encode(&BlockUpdate { block, id: 0 })).unwrap();
+    assert_eq!(sign::sign_text(&client, block), None);
+}
+
+/// What the commands in command_dispatcher() run on.
+struct CommandServer {
+    blocks: BlockRegistry,
+    players: Vec<String>,
+    placed: Vec<(BlockPos, BlockId)>,
+    stopped: bool
+}
+
+impl CommandEnvironment for CommandServer {
+    fn player_names(&self) -> Vec<String> {
+        return self.players.clone();
+    }
+
+    fn blocks(&self) -> Option<&BlockRegistry> {
+        return Some(&self.blocks);
+    }
+}
+
+fn command_dispatcher() -> CommandDispatcher<CommandServer> {
+    let mut commands = CommandDispatcher::new();
+    commands.register("setblock")
+        .description("Place a block.")
+        .argument("position", ArgumentKind::Position)
+        .argument("block", ArgumentKind::Block)
+        .operator_only()
+        .executes(|server: &mut CommandServer, context| {
+            server.placed.push((context.position("position").unwrap(), context.block("block").unwrap()));
+            return Ok("placed".to_string());
+        }).unwrap();
+    commands.register("msg")
+        .alias("tell")
+        .argument("player", ArgumentKind::Player)
+        .argument("message", ArgumentKind::Text)
+        .executes(|_, context| Ok(format!("{} -> {}: {}", context.source.name, context.player("player").unwrap(), context.text("message").unwrap())))
+        .unwrap();
+    commands.register("stop").operator_only().executes(|server: &mut CommandServer, _| {
+        server.stopped = true;
+        return Ok("stopping".to_string());
+    }).unwrap();
+    return commands;
+}
+
+/// A console running the registered commands rather than the built in admin commands.
+struct DispatcherConsole {
+    commands: CommandDispatcher<CommandServer>,
+    server: CommandServer
+}
+
+impl CommandConsole for DispatcherConsole {
+    fn execute(&mut self, command: AdminCommand) -> Result<String, String> {
+        return self.execute_line(&command.to_string());
+    }
+
+    fn execute_line(&mut self, line: &str) -> Result<String, String> {
+        return self.commands.execute(&mut self.server, &CommandSource::remote(), line).map_err(|error| error.to_string());
+    }
+}
+
+#[test]
+fn commands_run_from_chat_and_the_admin_port() {
+    let mut blocks = BlockRegistry::new();
+    let stone = blocks.register("cube:stone").unwrap();
+    blocks.register("cube:sand").unwrap();
+    let players = vec!["steve".to_string(), "alex".to_string()];
+    let mut server = CommandServer { blocks, players: players.clone(), placed: Vec::new(), stopped: false };
+    let commands = command_dispatcher();
+
+    // A player's chat commands run where they stand.
+    let mut router = ChatRouter::new();
+    router.join(1, "steve");
+    router.join(2, "alex");
+    router.handle(2, &chat::send("/setblock ~1 ~-1 ~ stone")).unwrap();
+    router.handle(1, &chat::send("/tell ALEX  meet at   spawn")).unwrap();
+    router.handle(1, &chat::send("/setblock 0 0 0 cube:stone")).unwrap();
+    let positions = HashMap::from([(1, BlockPos::new(0, 70, 0)), (2, BlockPos::new(-5, 64, 12))]);
+    let mut outputs = Vec::new();
+    for (client, line) in router.take_commands() {
+        let source = CommandSource::player(client, &players[client as usize - 1], positions[&client]).with_permission(if client == 2 { PermissionLevel::OPERATOR } else { PermissionLevel::Player });
+        outputs.push(commands.execute(&mut server, &source, &line));
+    }
+    assert_eq!(outputs, vec![
+        Ok("placed".to_string()),
+        Ok("steve -> alex: meet at   spawn".to_string()),
+        Err(CommandError::PermissionDenied("setblock".to_string()))
+    ]);
+    assert_eq!(server.placed, vec![(BlockPos::new(-4, 63, 12), stone)]);
+
+    // Completions and the command list are sent to the client, which only sees what it may run.
+    let steve = CommandSource::player(1, "steve", positions[&1]);
+    let alex = steve.clone().with_permission(PermissionLevel::OPERATOR);
+    let request = packet::decode::<TabComplete>(&packet::encode(&TabComplete { request: 7, text: "setblock ~ ~ ~ s".to_string() })).unwrap();
+    let suggestions = commands.complete(&server, &alex, &request.text);
+    assert_eq!(suggestions, vec!["cube:sand", "cube:stone"]);
+    let reply = packet::encode(&TabCompletions { request: request.request, suggestions });
+    assert_eq!(packet::decode::<TabCompletions>(&reply).unwrap().suggestions.len(), 2);
+    assert_eq!(commands.complete(&server, &steve, "msg "), vec!["alex", "steve"]);
+    assert_eq!(commands.complete(&server, &steve, "s"), Vec::<String>::new());
+    assert_eq!(commands.complete(&server, &alex, "s"), vec!["setblock", "stop"]);
+    let list = CommandList { commands: commands.commands(&steve) };
+    let received = packet::decode::<CommandList>(&packet::encode(&list)).unwrap();
+    assert_eq!(received, list);
+    assert_eq!(received.commands.len(), 1);
+    assert_eq!((received.commands[0].usage.as_str(), received.commands[0].arguments[1].kind.as_str()), ("msg <player> <message>", "text"));
+
+    // Admins run the same commands remotely, with no position to be relative to.
+    let mut admin_server = AdminServer::bind("127.0.0.1:0", "swordfish", ServerIdentity::generate().unwrap()).unwrap();
+    let address = admin_server.local_addr();
+    let running = std::thread::spawn(move || {
+        let jobs = JobSystem::new(1);
+        let mut console = DispatcherConsole { commands, server };
+        let start = Instant::now();
+        while !console.server.stopped {
+            assert!(start.elapsed() < Duration::from_secs(20), "Server was never stopped");
+            admin_server.pump(&jobs, &mut console);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        admin_server.pump(&jobs, &mut console);
+        return console.server;
+    });
+    let timeout = Duration::from_secs(10);
+    let mut admin = AdminClient::connect(address, "swordfish", None).unwrap();
+    let response = admin.run("setblock ~ 0 0 stone", timeout).unwrap();
+    assert_eq!((response.success, response.output.as_str()), (false, "invalid position: relative coordinates need a position to be relative to"));
+    assert_eq!(admin.run("setblock 1 2 3 cube:sand", timeout).unwrap().output, "placed");
+    assert_eq!(admin.run("setblock 1 2", timeout).unwrap().output, "missing position, usage: setblock <position: x y z> <block>");
+    assert_eq!(admin.run("stop", timeout).unwrap().output, "stopping");
+    let server = running.join().unwrap();
+    assert_eq!(server.placed.len(), 2);
+}
\ No newline at end of file
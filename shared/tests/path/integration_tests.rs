@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use shared::engine::{
+    job::system::JobSystem,
+    math::coords::{BlockPos, ChunkPos, CHUNK_SIZE},
+    path::{Movement, PathError, Pathfinder},
+    progress::CancelToken,
+    world::{chunk::Chunk, World}
+};
+
+/// A platform at y 0 split along x = 16 by a trench two blocks wide, with a six block high ledge at x = 40.
+fn platforms() -> Arc<World> {
+    let world = Arc::new(World::new());
+    for x in 0..2 {
+        world.insert_chunk(Chunk::filled(ChunkPos::new(x, -1, 0), 1));
+        world.insert_chunk(Chunk::new(ChunkPos::new(x, 0, 0)));
+    }
+    for z in 0..CHUNK_SIZE {
+        for x in 16..18 {
+            for y in -16..0 {
+                world.set_block(BlockPos::new(x, y, z), 0);
+            }
+        }
+        for x in 40..48 {
+            for y in 0..6 {
+                world.set_block(BlockPos::new(x, y, z), 1);
+            }
+        }
+    }
+    return world;
+}
+
+#[test]
+fn paths_are_found_on_jobs_around_gaps_and_ledges() {
+    let jobs = JobSystem::new(2);
+    let world = platforms();
+    let solid: Arc<dyn Fn(u16) -> bool + Send + Sync> = Arc::new(|id| id != 0);
+    let start = BlockPos::new(4, 0, 4);
+    let across = BlockPos::new(30, 0, 12);
+
+    // Trenches two wide need a pathfinder that jumps them.
+    let short_jumps = Pathfinder::new(solid.clone());
+    let long_jumps = Pathfinder::new(solid.clone()).with_max_jump_gap(2);
+    let blocked = short_jumps.queue(&jobs, world.clone(), start, across, CancelToken::new());
+    let jumped = long_jumps.queue(&jobs, world.clone(), start, across, CancelToken::new());
+    assert_eq!(blocked.wait(), Err(PathError::Unreachable));
+    let path = jumped.wait().unwrap();
+    let jumps: Vec<_> = path.nodes().iter().filter(|node| node.movement == Movement::Jump).collect();
+    assert_eq!(jumps.len(), 1);
+    assert_eq!(jumps[0].pos.x, 18);
+    assert_eq!((path.start(), path.goal()), (start, across));
+    // Every move is to a neighbouring column, other than the jump.
+    for pair in path.nodes().windows(2) {
+        let (dx, dz) = ((pair[1].pos.x - pair[0].pos.x).abs(), (pair[1].pos.z - pair[0].pos.z).abs());
+        assert!(dx.max(dz) == 1 || pair[1].movement == Movement::Jump, "{:?} to {:?}", pair[0], pair[1]);
+    }
+    assert!(path.cost() >= path.len() as f32);
+
+    // A six block ledge can be fallen off but not climbed.
+    let top = BlockPos::new(44, 6, 8);
+    let down = long_jumps.find(&world, top, BlockPos::new(34, 0, 8), &CancelToken::new());
+    assert_eq!(down, Err(PathError::Unreachable));
+    let down = long_jumps.clone().with_max_fall(6).find(&world, top, BlockPos::new(34, 0, 8), &CancelToken::new()).unwrap();
+    let fall = down.nodes().iter().position(|node| node.movement == Movement::Fall).unwrap();
+    assert_eq!((down.nodes()[fall - 1].pos.y, down.nodes()[fall].pos.y), (6, 0));
+    assert_eq!(long_jumps.find(&world, BlockPos::new(34, 0, 8), top, &CancelToken::new()), Err(PathError::Unreachable));
+
+    assert_eq!(long_jumps.find(&world, BlockPos::new(4, 3, 4), across, &CancelToken::new()), Err(PathError::BlockedStart));
+    assert_eq!(long_jumps.find(&world, start, BlockPos::new(44, 2, 8), &CancelToken::new()), Err(PathError::BlockedGoal));
+    assert_eq!(long_jumps.clone().with_max_nodes(10).find(&world, start, across, &CancelToken::new()), Err(PathError::SearchLimit));
+
+    // Cancelled searches stop early, whether or not they've started.
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    assert_eq!(long_jumps.queue(&jobs, world.clone(), start, across, cancel.clone()).wait(), Err(PathError::Cancelled));
+    assert_eq!(long_jumps.find(&world, start, BlockPos::new(34, 0, 8), &cancel), Err(PathError::Cancelled));
+}
\ No newline at end of file
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use shared::engine::{
+    block::{state::{Property, PropertyValue}, BlockRegistry},
+    entity::{kinematics::{KinematicsSystem, Transform, Velocity}, schedule::SystemSchedule},
+    job::system::JobSystem,
+    math::{aabb::Aabb, coords::{BlockPos, ChunkPos, WorldPos, CHUNK_SIZE}, ray::Ray, vector::Vec3},
+    physics::{collide_aabb, shape::{BlockShapes, CollisionShape}, PhysicsSystem, RigidBody},
+    world::{chunk::Chunk, raycast::PickHit, World}
+};
+
+const STONE: u16 = 1;
+const SLAB: u16 = 2;
+const FLOWER: u16 = 3;
+const WATER: u16 = 4;
+
+fn player_at(feet: Vec3) -> Aabb {
+    return Aabb::new(feet - Vec3::new(0.3, 0.0, 0.3), feet + Vec3::new(0.3, 1.8, 0.3));
+}
+
+#[test]
+fn boxes_collide_with_block_shapes_axis_by_axis() {
+    let shapes = BlockShapes::new().with_shape(SLAB, CollisionShape::slab()).with_shape(FLOWER, CollisionShape::Empty);
+    let world = World::new().with_block_shapes(Arc::new(shapes));
+    world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), STONE));
+    world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    world.set_block(BlockPos::new(4, 0, 4), SLAB);
+    world.set_block(BlockPos::new(6, 0, 4), FLOWER);
+    world.set_block(BlockPos::new(8, 3, 4), STONE);
+
+    // Falling fast onto a slab doesn't pass through it.
+    let falling = collide_aabb(&world, player_at(Vec3::new(4.5, 20.0, 4.5)), Vec3::new(0.0, -40.0, 0.0));
+    assert!(falling.on_ground(Vec3::new(0.0, -40.0, 0.0)));
+    assert!((falling.aabb.min.y - 0.5).abs() < 1e-5);
+
+    // Standing on the ground, small pushes down stop at it and sideways moves pass through flowers.
+    let standing = player_at(Vec3::new(5.5, 0.0, 4.5));
+    let pushed = collide_aabb(&world, standing, Vec3::new(1.0, -0.08, 0.0));
+    assert_eq!(pushed.collided, [false, true, false]);
+    assert!(pushed.motion.approx_eq(Vec3::new(1.0, 0.0, 0.0), 1e-6));
+    let still = collide_aabb(&world, standing, Vec3::ZERO);
+    assert!(!still.collided());
+
+    // Walking diagonally into the slab's side slides along it.
+    let sliding = collide_aabb(&world, player_at(Vec3::new(3.5, 0.0, 2.5)), Vec3::new(0.5, 0.0, 2.0));
+    assert_eq!(sliding.collided, [false, false, true]);
+    assert!((sliding.aabb.max.z - 4.0).abs() < 1e-5);
+
+    // Jumping into a block overhead stops at its underside.
+    let jumping = collide_aabb(&world, player_at(Vec3::new(8.5, 0.0, 4.5)), Vec3::new(0.0, 2.0, 0.0));
+    assert_eq!(jumping.collided, [false, true, false]);
+    assert!((jumping.aabb.max.y - 3.0).abs() < 1e-5);
+
+    // Unloaded chunks are walls.
+    let edge = collide_aabb(&world, player_at(Vec3::new(31.0, 0.0, 4.5)), Vec3::new(3.0, 0.0, 0.0));
+    assert!((edge.aabb.max.x - 32.0).abs() < 1e-5);
+}
+
+#[test]
+fn bodies_fall_slide_and_swim_in_parallel_regions() {
+    let shapes = BlockShapes::new().with_shape(WATER, CollisionShape::Empty);
+    let world = Arc::new(World::new().with_block_shapes(Arc::new(shapes)));
+    for x in -1..3 {
+        world.insert_chunk(Chunk::filled(ChunkPos::new(x, -1, 0), STONE));
+        world.insert_chunk(Chunk::new(ChunkPos::new(x, 0, 0)));
+    }
+    let pool = CHUNK_SIZE * 2 + 4;
+    for y in 0..8 {
+        world.set_block(BlockPos::new(pool, y, 4), WATER);
+    }
+
+    let entities = world.entities();
+    let spawn = |position: WorldPos, velocity: Vec3| {
+        let id = entities.spawn();
+        entities.insert(id, Transform::new(position)).unwrap();
+        entities.insert(id, Velocity::new(velocity)).unwrap();
+        entities.insert(id, RigidBody::new(0.6, 1.8)).unwrap();
+        return id;
+    };
+    // Far enough apart to step in different regions.
+    let sliding = spawn(WorldPos::new(-4.5, 0.0, 4.5), Vec3::new(-6.0, 0.0, 0.0));
+    let falling = spawn(WorldPos::new(4.5, 10.0, 4.5), Vec3::ZERO);
+    let crossing = spawn(WorldPos::new(CHUNK_SIZE as f64 - 0.2, 0.0, 4.5), Vec3::new(4.0, 0.0, 0.0));
+    let swimming = spawn(WorldPos::new(pool as f64 + 0.5, 10.0, 4.5), Vec3::ZERO);
+    let dry = spawn(WorldPos::new(pool as f64 + 0.5, 10.0, 12.5), Vec3::ZERO);
+
+    let mut schedule = SystemSchedule::new();
+    let jobs = Arc::new(JobSystem::new(4));
+    schedule.add_system(KinematicsSystem);
+    schedule.add_system(PhysicsSystem::new(jobs.clone()).with_fluids(Arc::new(|id| (id == WATER).then_some(6.0))));
+    let mut swim_time = None;
+    let mut dry_time = None;
+    for tick in 0..60 {
+        schedule.run(&jobs, &world);
+        let landed = |id| entities.get::<RigidBody>(id).unwrap().on_ground;
+        if swim_time.is_none() && landed(swimming) {
+            swim_time = Some(tick);
+        }
+        if dry_time.is_none() && landed(dry) {
+            dry_time = Some(tick);
+        }
+    }
+
+    // Friction stops the body sliding along the ground.
+    let slid = entities.get::<Transform>(sliding).unwrap().position;
+    assert!(slid.x < -4.5 && slid.x > -6.0);
+    assert_eq!(slid.y, 0.0);
+    assert_eq!(entities.get::<Velocity>(sliding).unwrap().linear, Vec3::ZERO);
+
+    // Gravity pulls the body down to the ground, where it stays.
+    let fell = entities.get::<Transform>(falling).unwrap().position;
+    assert!(fell.y.abs() < 1e-4);
+    assert!(entities.get::<RigidBody>(falling).unwrap().on_ground);
+
+    // Moving into the next chunk moves the entity between chunks.
+    assert!(entities.get::<Transform>(crossing).unwrap().position.x > CHUNK_SIZE as f64);
+    assert_eq!(entities.chunk_of(crossing), Some(ChunkPos::new(1, 0, 0)));
+
+    // Water drags the falling body, so it lands later.
+    assert!(entities.get::<RigidBody>(swimming).unwrap().in_fluid);
+    assert!(!entities.get::<RigidBody>(dry).unwrap().in_fluid);
+    assert!(swim_time.unwrap() > dry_time.unwrap());
+}
+
+#[test]
+fn registered_collision_shapes_drive_collision_and_picking() {
+    let mut registry = BlockRegistry::new();
+    let stone = registry.register("cube:stone").unwrap();
+    let slab = registry.builder("cube:stone_slab")
+        .property(Property::boolean("top"))
+        .state_collision(|block, id| match block.get(id, "top") {
+            Some(PropertyValue::Bool(true)) => CollisionShape::Boxes(vec![Aabb::new(Vec3::new(0.0, 0.5, 0.0), Vec3::ONE)]),
+            _ => CollisionShape::slab()
+        })
+        .register()
+        .unwrap();
+    let top_slab = registry.with(slab, "top", PropertyValue::Bool(true)).unwrap();
+    let fence = registry.builder("cube:fence")
+        .collision(CollisionShape::Boxes(vec![Aabb::new(Vec3::new(0.375, 0.0, 0.375), Vec3::new(0.625, 1.5, 0.625))]))
+        .register()
+        .unwrap();
+    let grass = registry.builder("cube:tall_grass").collision(CollisionShape::Empty).register().unwrap();
+    registry.freeze();
+
+    let world = World::new().with_block_shapes(Arc::new(BlockShapes::from_registry(&registry)));
+    world.insert_chunk(Chunk::filled(ChunkPos::new(0, -1, 0), stone));
+    world.insert_chunk(Chunk::new(ChunkPos::new(0, 0, 0)));
+    world.set_block(BlockPos::new(2, 0, 2), slab);
+    world.set_block(BlockPos::new(4, 0, 2), top_slab);
+    world.set_block(BlockPos::new(6, 0, 2), grass);
+    world.set_block(BlockPos::new(8, 0, 2), fence);
+
+    // Bodies rest on the top of each shape, and fall through grass.
+    for (x, height) in [(2, 0.5), (4, 1.0), (6, 0.0), (8, 1.5)] {
+        let falling = collide_aabb(&world, Aabb::new(Vec3::new(x as f32 + 0.4, 5.0, 2.4), Vec3::new(x as f32 + 0.6, 6.0, 2.6)), Vec3::new(0.0, -10.0, 0.0));
+        assert!((falling.aabb.min.y - height).abs() < 1e-5, "landed at {} on block {}", falling.aabb.min.y, x);
+    }
+
+    // Rays pass under the top slab, through grass, and over the bottom slab, but hit the fence where it reaches
+    // into the block above it.
+    let low = Ray::new(Vec3::new(0.5, 0.25, 2.5), Vec3::X);
+    assert!(matches!(world.pick(low, 20.0, None), Some(PickHit::Block(hit)) if hit.block == BlockPos::new(2, 0, 2)));
+    let middle = Ray::new(Vec3::new(3.0, 0.25, 2.5), Vec3::X);
+    assert!(matches!(world.pick(middle, 20.0, None), Some(PickHit::Block(hit)) if hit.block == BlockPos::new(8, 0, 2)));
+    let high = Ray::new(Vec3::new(0.5, 1.25, 2.5), Vec3::X);
+    let Some(PickHit::Block(hit)) = world.pick(high, 20.0, None) else { panic!("missed the fence") };
+    assert_eq!(hit.block, BlockPos::new(8, 0, 2));
+    assert_eq!(hit.id, fence);
+    assert!((hit.distance - 7.875).abs() < 1e-5);
+}
\ No newline at end of file
@@ -0,0 +1,43 @@
+use std::{fs, path::PathBuf};
+
+use shared::{profile_scope, engine::{job::system::JobSystem, profile::{self, JOB_CATEGORY}}};
+
+fn temp_path(name: &str) -> PathBuf {
+    return std::env::temp_dir().join(format!("cube_profile_test_{}_{}", name, std::process::id()));
+}
+
+fn mesh_chunk() -> u32 {
+    profile_scope!("profile test mesh");
+    return 7;
+}
+
+#[test]
+fn jobs_and_scopes_show_up_on_the_frame_timeline() {
+    let job_system = JobSystem::new(2);
+    profile::enable();
+    {
+        profile_scope!("profile test frame");
+        let future = job_system.run_job(mesh_chunk);
+        assert_eq!(future.wait(), 7);
+        job_system.wait();
+    }
+    let frame = profile::end_frame().unwrap();
+
+    let main = frame.threads.iter().find(|thread| thread.total("profile test frame").is_some()).unwrap();
+    let job_thread = frame.threads.iter().find(|thread| thread.total("profile test mesh").is_some()).unwrap();
+    assert_ne!(main.thread_id, job_thread.thread_id);
+    let job = job_thread.events.iter().find(|event| event.category == JOB_CATEGORY && event.name.contains("mesh_chunk")).unwrap();
+    let mesh = job_thread.events.iter().find(|event| event.name == "profile test mesh").unwrap();
+    assert_eq!(mesh.depth, job.depth + 1);
+    assert!(mesh.start >= job.start && mesh.duration <= job.duration);
+    assert_eq!(frame.total("profile test mesh").unwrap().count, 1);
+
+    let path = temp_path("trace");
+    profile::write_chrome_trace(&path).unwrap();
+    let trace: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    assert!(events.iter().any(|event| event["cat"] == JOB_CATEGORY && event["tid"] == job_thread.thread_id));
+    assert!(events.iter().any(|event| event["ph"] == "M" && event["tid"] == job_thread.thread_id));
+    assert!(events.iter().any(|event| event["cat"] == "frame" && event["name"] == format!("frame {}", frame.number)));
+    fs::remove_file(&path).unwrap();
+}
\ No newline at end of file
@@ -0,0 +1,386 @@
+use std::{path::PathBuf, sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use shared::engine::{
+    block::{BlockRegistry, AIR},
+    entity::{serialize::{ComponentTypes, ENTITY_FORMAT_VERSION}, Entities},
+    job::system::JobSystem,
+    math::coords::{BlockPos, ChunkPos, LocalPos},
+    save::{
+        autosave::Autosave,
+        backup::restore_backup,
+        chunk::encode_chunk,
+        entities::EntityStorage,
+        level::{LevelData, LEVEL_FILE, KEEP_INVENTORY, RANDOM_TICK_SPEED},
+        manager::SaveManager,
+        migration::{Migrations, SaveFormat},
+        region::{RegionFile, RegionPos, RegionStorage, SECTOR_SIZE},
+        world_info::WorldInfo
+    },
+    universe::Universe,
+    world::{chunk::Chunk, loader::{ChunkLoader, ChunkOrigin, ChunkStorage}, tick::TickHandlers, World},
+    worldgen::generator::VoidGenerator
+};
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cube_universe_test_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_dir_all(&path);
+    return path;
+}
+
+#[test]
+fn region_file_reuses_freed_sectors() {
+    let path = temp_path("region_sectors");
+    let region_pos = RegionPos::of_chunk(ChunkPos::new(0, 0, 0));
+    let mut region = RegionFile::open(&path, region_pos).unwrap();
+    let a = ChunkPos::new(0, 0, 0);
+    let b = ChunkPos::new(1, 0, 0);
+    let c = ChunkPos::new(2, 0, 0);
+
+    region.write(a, &vec![1u8; SECTOR_SIZE as usize * 2], 1).unwrap();
+    region.write(b, &[2u8; 100], 2).unwrap();
+    let length = std::fs::metadata(&path).unwrap().len();
+
+    // Growing a moves it past b, freeing its old sectors, which c then fits into without growing the file.
+    region.write(a, &vec![3u8; SECTOR_SIZE as usize * 3], 3).unwrap();
+    let grown = std::fs::metadata(&path).unwrap().len();
+    assert_eq!(grown, length + SECTOR_SIZE * 3);
+    region.write(c, &vec![4u8; SECTOR_SIZE as usize * 2], 4).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), grown);
+
+    drop(region);
+    let mut region = RegionFile::open(&path, region_pos).unwrap();
+    assert_eq!(region.read(a).unwrap().unwrap(), vec![3u8; SECTOR_SIZE as usize * 3]);
+    assert_eq!(region.read(b).unwrap().unwrap(), vec![2u8; 100]);
+    assert_eq!(region.read(c).unwrap().unwrap(), vec![4u8; SECTOR_SIZE as usize * 2]);
+    assert_eq!(region.chunks(), vec![a, b, c]);
+
+    region.remove(b).unwrap();
+    assert!(!region.contains(b));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn chunk_loader_reads_chunks_saved_to_regions() {
+    let directory = temp_path("region_loader");
+    let storage = Arc::new(RegionStorage::new(&directory).unwrap());
+    for x in -10..10 {
+        let mut chunk = Chunk::new(ChunkPos::new(x, 0, 0));
+        chunk.set_block(LocalPos::new(0, 0, 0), (x + 100) as u16);
+        storage.write_chunk(&chunk).unwrap();
+    }
+
+    let jobs = Arc::new(JobSystem::new(2));
+    let loader = ChunkLoader::new(jobs.clone(), jobs, storage, Arc::new(|pos: ChunkPos| Chunk::new(pos)));
+    for x in -12..12 {
+        let loaded = loader.request(ChunkPos::new(x, 0, 0)).wait().unwrap();
+        if (-10..10).contains(&x) {
+            assert_eq!(loaded.origin, ChunkOrigin::Loaded);
+            assert_eq!(loaded.chunk.get_block(LocalPos::new(0, 0, 0)), (x + 100) as u16);
+        } else {
+            assert_eq!(loaded.origin, ChunkOrigin::Generated);
+        }
+    }
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+struct Name(String);
+
+#[test]
+fn save_manager_saves_in_batches_and_keeps_the_newest_data() {
+    let directory = temp_path("save_manager");
+    let jobs = Arc::new(JobSystem::new(2));
+    let storage = Arc::new(RegionStorage::new(&directory.join("region")).unwrap());
+    let mut types = ComponentTypes::new();
+    types.register::<Name>("cube:name", |name, out| out.extend_from_slice(name.0.as_bytes()),
+        |data| Ok(Name(String::from_utf8_lossy(data).into_owned())));
+    let entity_storage = Arc::new(EntityStorage::new(&directory.join("entities"), Arc::new(types)).unwrap());
+    let mut saves = SaveManager::new(jobs.clone(), jobs.clone(), storage.clone()).with_entities(entity_storage.clone()).with_batch_size(8);
+
+    let world = World::new();
+    for x in 0..40 {
+        let pos = ChunkPos::new(x, 0, 0);
+        world.insert_chunk(Chunk::filled(pos, x as u16));
+        saves.mark_chunk(pos);
+    }
+    let villager = world.entities().spawn();
+    world.entities().insert(villager, Name("Alex".to_string())).unwrap();
+    world.entities().set_chunk(villager, ChunkPos::new(3, 0, 0));
+    saves.mark_entities(ChunkPos::new(3, 0, 0));
+
+    // Each save queues one batch, and changes made while a chunk's save is in flight are saved after it.
+    let mut queued = 0;
+    for tick in 0..3 {
+        let count = saves.save(&world);
+        assert!(count <= 8);
+        queued += count;
+        world.chunk(ChunkPos::new(0, 0, 0)).unwrap().write().unwrap().set_block(LocalPos::new(tick, 0, 0), 100 + tick as u16);
+        saves.mark_chunk(ChunkPos::new(0, 0, 0));
+    }
+    assert_eq!(queued, 24);
+    assert!(saves.dirty_count() > 0);
+
+    // Unloading saves the chunk's entities and despawns them.
+    saves.unload_chunk(&world, ChunkPos::new(3, 0, 0));
+    assert!(!world.is_loaded(ChunkPos::new(3, 0, 0)));
+    assert!(world.entities().is_empty());
+
+    saves.flush_all(&world).unwrap();
+    assert_eq!(saves.dirty_count(), 0);
+    assert_eq!(saves.pending_count(), 0);
+    assert!(saves.take_errors().is_empty());
+    for x in 0..40 {
+        let pos = ChunkPos::new(x, 0, 0);
+        let saved = storage.read(pos).unwrap().unwrap();
+        if x == 3 {
+            assert_eq!(saved, Chunk::filled(pos, 3));
+        } else {
+            assert!(saved == *world.chunk(pos).unwrap().read().unwrap());
+        }
+    }
+    let loaded = Entities::new();
+    let ids = entity_storage.load_chunk(&loaded, ChunkPos::new(3, 0, 0)).unwrap();
+    assert_eq!(loaded.get::<Name>(ids[0]), Some(Name("Alex".to_string())));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn old_saves_are_migrated_as_they_load() {
+    let directory = temp_path("migrations");
+    // Version 0 chunks were a single block id filling the whole chunk.
+    let mut migrations = Migrations::new();
+    migrations.register(SaveFormat::Chunk, 0, |data| {
+        let id = u16::from_le_bytes(data.try_into().map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "bad version 0 chunk"))?);
+        return Ok(encode_chunk(&Chunk::filled(ChunkPos::ORIGIN, id))[1..].to_vec());
+    });
+    let universe = Universe::new(&directory).with_migrations(Arc::new(migrations));
+    let overworld = universe.create_dimension("overworld", Arc::new(VoidGenerator), TickHandlers::new(), 1).unwrap();
+    let old = ChunkPos::new(4, 0, 0);
+    overworld.storage().write_data(old, &[0, 7, 0]).unwrap();
+    assert_eq!(overworld.storage().read(old).unwrap(), Some(Chunk::filled(old, 7)));
+    // Chunks already current load as they are, and saving an upgraded chunk writes the current format.
+    let current = ChunkPos::new(5, 0, 0);
+    overworld.storage().write_chunk(&Chunk::filled(current, 3)).unwrap();
+    assert_eq!(overworld.storage().read(current).unwrap(), Some(Chunk::filled(current, 3)));
+    overworld.storage().write_chunk(&overworld.storage().read(old).unwrap().unwrap()).unwrap();
+    assert!(!Migrations::needs_upgrade(SaveFormat::Chunk, &overworld.storage().read_data(old).unwrap().unwrap()));
+
+    // Without the step the old chunk fails to load, rather than loading as garbage.
+    let unmigrated = RegionStorage::new(&universe.dimension_directory("overworld").join("region")).unwrap();
+    unmigrated.write_data(old, &[0, 7, 0]).unwrap();
+    assert!(unmigrated.read(old).is_err());
+    // Entities saved by a newer build fail to load too.
+    overworld.entity_storage().regions().write_data(old, &[ENTITY_FORMAT_VERSION + 1]).unwrap();
+    assert!(overworld.entity_storage().load_chunk(&Entities::new(), old).is_err());
+
+    // The world info keeps the block names, so ids can be remapped once blocks change.
+    assert_eq!(universe.load_info().unwrap(), None);
+    let mut blocks = BlockRegistry::new();
+    let ruby = blocks.register("somemod:ruby").unwrap();
+    universe.save_info(&WorldInfo::new(blocks.saved_names().to_vec())).unwrap();
+    let mut updated = BlockRegistry::new();
+    updated.register("cube:stone").unwrap();
+    let new_ruby = updated.register("somemod:ruby").unwrap();
+    let remap = updated.remap_saved(&universe.load_info().unwrap().unwrap().block_names);
+    assert_eq!(remap.map(ruby), new_ruby);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn level_metadata_survives_reopening_the_universe() {
+    let directory = temp_path("level");
+    let jobs = JobSystem::new(1);
+    let universe = Universe::new(&directory);
+    let overworld = universe.create_dimension("overworld", Arc::new(VoidGenerator), TickHandlers::new(), 850).unwrap();
+    let world = overworld.world();
+    assert_eq!(world.seed(), 850);
+    assert_eq!(world.level().generator, "void");
+    for _ in 0..100 {
+        overworld.run_tick(&jobs);
+    }
+    assert_eq!(world.time(), 100);
+    world.set_spawn_point(BlockPos::new(8, 65, -8));
+    world.set_game_rule(KEEP_INVENTORY, true);
+    world.set_game_rule(RANDOM_TICK_SPEED, 20);
+    universe.sync_all().unwrap();
+    let saved = world.level();
+
+    // A crash while writing leaves a temporary file beside the level file, which loading ignores.
+    std::fs::write(overworld.directory().join(LEVEL_FILE).with_extension("tmp"), [1, 2, 3]).unwrap();
+    let reopened = Universe::new(&directory);
+    assert_eq!(reopened.load_level("overworld").unwrap(), Some(saved.clone()));
+    assert_eq!(reopened.load_level("nether").unwrap(), None);
+    // The saved seed wins over the one given, as the world was generated with it.
+    let overworld = reopened.create_dimension("overworld", Arc::new(VoidGenerator), TickHandlers::new(), 1).unwrap();
+    let world = overworld.world();
+    assert_eq!(world.level(), saved);
+    assert_eq!(world.seed(), 850);
+    assert_eq!(world.spawn_point(), BlockPos::new(8, 65, -8));
+    assert!(world.game_rule(KEEP_INVENTORY));
+    assert_eq!(world.game_rule(RANDOM_TICK_SPEED), 20);
+    assert!(LevelData::decode(&std::fs::read(overworld.directory().join(LEVEL_FILE)).unwrap()).is_ok());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn saved_chunks_keep_their_blocks_when_the_registry_is_reordered() {
+    let directory = temp_path("remap");
+    let mut blocks = BlockRegistry::new();
+    let stone = blocks.register("cube:stone").unwrap();
+    let ruby = blocks.register("somemod:ruby").unwrap();
+    let universe = Universe::new(&directory);
+    assert!(universe.remap_blocks(&blocks).unwrap().is_none());
+    let overworld = universe.create_dimension("overworld", Arc::new(VoidGenerator), TickHandlers::new(), 1).unwrap();
+    let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    chunk.set_block(LocalPos::new(0, 0, 0), stone);
+    chunk.set_block(LocalPos::new(1, 0, 0), ruby);
+    overworld.storage().write_chunk(&chunk).unwrap();
+    // Far more unique blocks than a palette holds, so the section stores ids directly.
+    let mut crowded = Chunk::new(ChunkPos::new(-9, 0, 0));
+    for x in 0..16 {
+        for z in 0..16 {
+            crowded.set_block(LocalPos::new(x, 0, z), if (x + z) % 2 == 0 { stone } else { ruby });
+            crowded.set_block(LocalPos::new(x, 1, z), 1000 + (x as u16) * 16 + z as u16);
+        }
+    }
+    overworld.storage().write_chunk(&crowded).unwrap();
+    universe.sync_all().unwrap();
+    drop(overworld);
+    drop(universe);
+
+    // A mod registering its block first shifts every id, and the ruby's mod is gone.
+    let mut reordered = BlockRegistry::new();
+    reordered.register("othermod:glass").unwrap();
+    let new_stone = reordered.register("cube:stone").unwrap();
+    assert_ne!(new_stone, stone);
+    // A remap that crashed before it finished is thrown away.
+    let staging = directory.join("dimensions").join("overworld").join("region.remap");
+    std::fs::create_dir_all(&staging).unwrap();
+    std::fs::write(staging.join("r.0.0.0.cur"), [1, 2, 3]).unwrap();
+    let universe = Universe::new(&directory);
+    let remap = universe.remap_blocks(&reordered).unwrap().unwrap();
+    assert_eq!(remap.missing().len(), 1);
+    assert_eq!(remap.missing()[0].name, "somemod:ruby");
+    assert!(!staging.exists());
+    assert_eq!(universe.load_info().unwrap().unwrap().block_names, reordered.saved_names());
+    let overworld = universe.create_dimension("overworld", Arc::new(VoidGenerator), TickHandlers::new(), 1).unwrap();
+    let loaded = overworld.storage().read(ChunkPos::new(0, 0, 0)).unwrap().unwrap();
+    assert_eq!(loaded.get_block(LocalPos::new(0, 0, 0)), new_stone);
+    assert_eq!(loaded.get_block(LocalPos::new(1, 0, 0)), AIR);
+    assert_eq!(loaded.get_block(LocalPos::new(2, 0, 0)), AIR);
+    assert_eq!(loaded.non_air_count(), 1);
+    let loaded = overworld.storage().read(ChunkPos::new(-9, 0, 0)).unwrap().unwrap();
+    assert_eq!(loaded.get_block(LocalPos::new(0, 0, 0)), new_stone);
+    assert_eq!(loaded.get_block(LocalPos::new(1, 0, 0)), AIR);
+    // Ids the save never named become the placeholder too.
+    assert_eq!(loaded.get_block(LocalPos::new(0, 1, 0)), AIR);
+    drop(overworld);
+
+    // Once remapped, starting again with the same registry changes nothing.
+    let universe = Universe::new(&directory);
+    assert!(universe.remap_blocks(&reordered).unwrap().is_none());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn autosaves_spread_over_ticks_and_report_progress() {
+    let directory = temp_path("autosave");
+    let jobs = Arc::new(JobSystem::new(2));
+    let storage = Arc::new(RegionStorage::new(&directory).unwrap());
+    let mut saves = SaveManager::new(jobs.clone(), jobs, storage.clone());
+    let world = World::new();
+    for x in 0..100 {
+        world.insert_chunk(Chunk::filled(ChunkPos::new(x, 0, 0), 1));
+        saves.mark_chunk(ChunkPos::new(x, 0, 0));
+    }
+    let mut autosave = Autosave::new(Duration::from_secs(300)).with_chunks_per_tick(10);
+    let percents = Arc::new(Mutex::new(Vec::new()));
+    let captured = percents.clone();
+    autosave.progress().on_progress(move |event| captured.lock().unwrap().push(event.percent()));
+
+    // Nothing happens until the interval passes, then the run takes at least a tick per 10 chunks.
+    let start = Instant::now();
+    let mut now = start;
+    let mut ticks = 0;
+    let report = loop {
+        if let Some(report) = autosave.update(&mut saves, &world, now) {
+            break report;
+        }
+        if autosave.is_running() {
+            ticks += 1;
+        } else {
+            assert_eq!(saves.dirty_count(), 100);
+        }
+        now += Duration::from_millis(50);
+        std::thread::sleep(Duration::from_millis(1));
+    };
+    assert!(now - start >= Duration::from_secs(300));
+    assert!(ticks >= 9, "Saved in {} ticks", ticks);
+    assert_eq!(report.chunks, 100);
+    assert!(report.errors.is_empty());
+    for x in 0..100 {
+        assert_eq!(storage.read(ChunkPos::new(x, 0, 0)).unwrap(), Some(Chunk::filled(ChunkPos::new(x, 0, 0), 1)));
+    }
+    let percents = percents.lock().unwrap().clone();
+    assert!(percents.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(percents.last(), Some(&100));
+
+    // A save command starts a run right away, and the interval restarts after it.
+    saves.mark_chunk(ChunkPos::new(0, 0, 0));
+    autosave.start(&saves, now);
+    let mut report = None;
+    while report.is_none() {
+        report = autosave.update(&mut saves, &world, now);
+    }
+    assert_eq!(report.unwrap().chunks, 1);
+    saves.mark_chunk(ChunkPos::new(1, 0, 0));
+    assert!(autosave.update(&mut saves, &world, now + Duration::from_secs(299)).is_none());
+    assert!(!autosave.is_running());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn backups_keep_the_world_as_it_was_when_they_started() {
+    let directory = temp_path("backup");
+    let jobs = Arc::new(JobSystem::new(2));
+    let storage = Arc::new(RegionStorage::new(&directory.join("region")).unwrap());
+    let mut saves = SaveManager::new(jobs.clone(), jobs, storage.clone());
+    let world = World::new();
+    let mut level = LevelData::new(7, "void");
+    level.time = 100;
+    level.save(&directory).unwrap();
+    for x in [0, 8, 16] {
+        world.insert_chunk(Chunk::filled(ChunkPos::new(x, 0, 0), 1));
+        saves.mark_chunk(ChunkPos::new(x, 0, 0));
+    }
+    saves.flush_all(&world).unwrap();
+
+    let backup = saves.create_backup("first").unwrap();
+    assert!(saves.create_backup("second").is_err(), "Only one backup runs at a time");
+    // Saves keep going while the backup is written, into regions it has and hasn't archived yet.
+    for x in [0, 16, 100] {
+        world.insert_chunk(Chunk::filled(ChunkPos::new(x, 0, 0), 2));
+        saves.mark_chunk(ChunkPos::new(x, 0, 0));
+    }
+    saves.flush_all(&world).unwrap();
+    level.time = 200;
+    level.save(&directory).unwrap();
+    let archive = backup.wait().unwrap();
+    assert!(archive.starts_with(saves.backup_directory()));
+    assert!(saves.create_backup("first").is_err());
+    saves.create_backup("second").unwrap().wait().unwrap();
+
+    drop(saves);
+    drop(storage);
+    assert_eq!(restore_backup(&archive, &directory).unwrap(), 2);
+    let storage = RegionStorage::new(&directory.join("region")).unwrap();
+    for x in [0, 8, 16] {
+        assert_eq!(storage.read(ChunkPos::new(x, 0, 0)).unwrap(), Some(Chunk::filled(ChunkPos::new(x, 0, 0), 1)));
+    }
+    assert_eq!(storage.read(ChunkPos::new(100, 0, 0)).unwrap(), None, "Regions made after the backup are removed");
+    assert_eq!(LevelData::load(&directory, &Migrations::new()).unwrap().unwrap().time, 100);
+    assert!(archive.exists(), "Backups survive restoring");
+    std::fs::remove_dir_all(&directory).unwrap();
+}
\ No newline at end of file
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use shared::engine::{
+    block::BlockRegistry,
+    event::bus::MessageBus,
+    job::system::JobSystem,
+    math::coords::{BlockPos, ChunkPos},
+    script::{host::ScriptEvent, runtime::ScriptError, ScriptEngine},
+    world::{chunk::Chunk, World}
+};
+
+/// Registers a block in its own namespace and tries one in the engine's, logs every block placed,
+/// and loops forever or grows past its memory when told to.
+const MOD: &str = r#"(module
+    (import "cube" "log" (func $log (param i32 i32)))
+    (import "cube" "register_block" (func $register_block (param i32 i32) (result i32)))
+    (import "cube" "block_id" (func $block_id (param i32 i32) (result i32)))
+    (import "cube" "subscribe" (func $subscribe (param i32) (result i32)))
+    (import "cube" "schedule" (func $schedule (param i32)))
+    (memory (export "memory") 1)
+    (global $lamp (mut i32) (i32.const -1))
+    (data (i32.const 0) "lampcube:lampplacedlamp placedunknown blockgrew")
+    (func (export "init")
+        (global.set $lamp (call $register_block (i32.const 0) (i32.const 4)))
+        (if (i32.ne (call $register_block (i32.const 4) (i32.const 9)) (i32.const -1))
+            (then (unreachable)))
+        (if (i32.ne (call $block_id (i32.const 4) (i32.const 9)) (i32.const -1))
+            (then (unreachable)))
+        (drop (call $subscribe (i32.const 0)))
+        (drop (call $subscribe (i32.const 1)))
+        (if (i32.ne (call $subscribe (i32.const 99)) (i32.const -1))
+            (then (unreachable))))
+    (func (export "on_event") (param $event i32) (param i32 i32 i32) (param $old i32) (param $new i32)
+        (if (i32.eq (local.get $event) (i32.const 0))
+            (then (call $schedule (local.get $new)))))
+    (func (export "run_task") (param $block i32)
+        (if (i32.eq (local.get $block) (global.get $lamp))
+            (then (call $log (i32.const 19) (i32.const 11)))
+            (else (call $log (i32.const 30) (i32.const 13)))))
+    (func (export "spin")
+        (loop $forever (br $forever)))
+    (func (export "grow")
+        (if (i32.ne (memory.grow (i32.const 1000)) (i32.const -1))
+            (then (call $log (i32.const 43) (i32.const 4))))))"#;
+
+#[test]
+fn scripts_react_to_world_events_inside_their_sandbox() {
+    let jobs = Arc::new(JobSystem::new(2));
+    let scripts = ScriptEngine::new(jobs.clone()).with_fuel_per_call(100_000).with_max_memory(1 << 20);
+    let mut blocks = BlockRegistry::new();
+    let stone = blocks.register("cube:stone").unwrap();
+    let script = scripts.load("lamps", MOD.as_bytes(), &mut blocks).unwrap();
+    let lamp = blocks.id("lamps:lamp").unwrap();
+    assert_eq!(blocks.id("cube:lamp"), None);
+    assert_eq!(script.subscriptions(), vec![ScriptEvent::BlockChanged, ScriptEvent::ChunkLoaded]);
+
+    let bus = Arc::new(MessageBus::new());
+    let subscriptions = script.attach(&bus);
+    let world = World::new().with_events(bus.clone());
+    world.insert_chunk(Chunk::filled(ChunkPos::new(0, 0, 0), stone));
+    world.set_block(BlockPos::new(1, 2, 3), lamp);
+    world.set_block(BlockPos::new(1, 3, 3), 0);
+    bus.dispatch();
+    jobs.wait();
+    let mut log = script.take_log();
+    log.sort();
+    assert_eq!(log, vec!["lamp placed", "unknown block"]);
+
+    // Scripts can't hang the thread calling them or take all of its memory.
+    assert_eq!(script.call("spin"), Err(ScriptError::OutOfFuel("spin".to_string())));
+    script.call("grow").unwrap();
+    assert!(script.take_log().is_empty());
+    assert_eq!(script.call("missing"), Err(ScriptError::MissingExport("missing".to_string())));
+    // Nor reach anything outside the host API.
+    let wasi = r#"(module (import "wasi_snapshot_preview1" "proc_exit" (func (param i32))))"#;
+    assert!(matches!(scripts.load("wasi", wasi.as_bytes(), &mut blocks), Err(ScriptError::Invalid(_))));
+
+    for subscription in subscriptions {
+        assert!(bus.unsubscribe(subscription));
+    }
+    world.set_block(BlockPos::new(1, 2, 3), stone);
+    bus.dispatch();
+    jobs.wait();
+    assert!(script.take_log().is_empty());
+    assert!(script.take_errors().is_empty());
+}
\ No newline at end of file
@@ -0,0 +1,479 @@
+use std::{io, sync::{Arc, Mutex}, time::Instant};
+
+use shared::engine::{
+    block::{BlockId, BlockRegistry, AIR},
+    entity::kinematics::Transform,
+    item::{inventory::{self, ContainerBlockEntity}, ItemRegistry, ItemStack},
+    event::{bus::MessageBus, events::{BlockChanged, ChunkLoaded, EntitySpawned}},
+    job::system::JobSystem,
+    math::{aabb::Aabb, coords::{BlockPos, ChunkPos, LocalPos, WorldPos, CHUNK_VOLUME}, ray::Ray, rng::WorldRng, vector::Vec3},
+    physics::RigidBody,
+    tick::GameLoop,
+    world::{block_entity::BlockEntityTypes, chunk::Chunk, edit::WorldEdit, schematic::Schematic, loader::{ChunkLoader, ChunkOrigin, ChunkStorage}, raycast::PickHit, lod::{LodChunk, LodPos, TerrainLod, MAX_LOD_LEVEL}, tick::{BlockTicker, TickContext, TickHandlers, TickScheduler}, universe::UniverseTree, World}
+};
+
+#[test]
+fn paletted_chunk_matches_flat_storage() {
+    let mut rng = WorldRng::new(804);
+    let mut chunk = Chunk::new(ChunkPos::new(0, 0, 0));
+    let mut flat = vec![0 as BlockId; CHUNK_VOLUME];
+
+    // Grow the number of unique ids past every palette width, including direct storage.
+    for unique in [2, 4, 16, 256, 1000] {
+        for _ in 0..5000 {
+            let index = rng.range_i32(0..CHUNK_VOLUME as i32) as usize;
+            let id = rng.range_i32(0..unique) as BlockId;
+            let old = chunk.set_block(LocalPos::from_index(index), id);
+            assert_eq!(old, flat[index]);
+            flat[index] = id;
+        }
+        for (index, id) in flat.iter().enumerate() {
+            assert_eq!(chunk.get_block(LocalPos::from_index(index)), *id);
+        }
+        assert_eq!(chunk.non_air_count(), flat.iter().filter(|id| **id != 0).count());
+    }
+
+    chunk.compact();
+    for (index, id) in flat.iter().enumerate() {
+        assert_eq!(chunk.get_block(LocalPos::from_index(index)), *id);
+    }
+}
+
+
+struct EvenChunksSaved;
+
+impl ChunkStorage for EvenChunksSaved {
+    fn read(&self, pos: ChunkPos) -> io::Result<Option<Chunk>> {
+        if pos.x < 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "corrupt chunk"));
+        }
+        if pos.x % 2 == 0 {
+            return Ok(Some(Chunk::filled(pos, 2)));
+        }
+        return Ok(None);
+    }
+}
+
+#[test]
+fn chunk_loader_reads_saved_chunks_and_generates_the_rest() {
+    let io_jobs = Arc::new(JobSystem::new(1));
+    let compute_jobs = Arc::new(JobSystem::new(2));
+    let generator = Arc::new(|pos: ChunkPos| Chunk::filled(pos, 1));
+    let loader = ChunkLoader::new(io_jobs, compute_jobs, Arc::new(EvenChunksSaved), generator);
+
+    let futures: Vec<_> = (-2..16).map(|x| loader.request(ChunkPos::new(x, 0, 0))).collect();
+    for (future, x) in futures.iter().zip(-2..16) {
+        let result = future.wait();
+        if x < 0 {
+            assert_eq!(result.unwrap_err().pos, ChunkPos::new(x, 0, 0));
+            continue;
+        }
+        let loaded = result.unwrap();
+        assert_eq!(loaded.chunk.pos(), ChunkPos::new(x, 0, 0));
+        if x % 2 == 0 {
+            assert_eq!(loaded.origin, ChunkOrigin::Loaded);
+            assert_eq!(loaded.chunk.get_block(LocalPos::new(0, 0, 0)), 2);
+        } else {
+            assert_eq!(loaded.origin, ChunkOrigin::Generated);
+            assert_eq!(loaded.chunk.get_block(LocalPos::new(0, 0, 0)), 1);
+        }
+    }
+    assert_eq!(loader.pending_count(), 0);
+}
+
+
+#[test]
+fn world_set_block_from_many_jobs() {
+    let jobs = JobSystem::new(4);
+    let world = Arc::new(World::new());
+    for x in 0..4 {
+        world.insert_chunk(Chunk::new(ChunkPos::new(x, 0, 0)));
+    }
+
+    let futures: Vec<_> = (0..128).map(|x| {
+        let world = world.clone();
+        jobs.run_job(move || {
+            for y in 0..32 {
+                world.set_block(BlockPos::new(x, y, 0), (x + 1) as BlockId);
+            }
+        })
+    }).collect();
+    for future in futures.iter() {
+        future.wait();
+    }
+
+    for x in 0..128 {
+        for y in 0..32 {
+            assert_eq!(world.get_block(BlockPos::new(x, y, 0)), Some((x + 1) as BlockId));
+        }
+    }
+    let mut non_air = 0;
+    world.for_each_chunk(|chunk| non_air += chunk.non_air_count());
+    assert_eq!(non_air, 128 * 32);
+}
+
+#[test]
+fn terrain_lod_covers_every_chunk_once() {
+    let mut lod = TerrainLod::new(1, 2);
+    let viewer = ChunkPos::new(3, -2, 9);
+    lod.update(viewer);
+    let mut covered = std::collections::HashMap::new();
+    for node in lod.selected().iter() {
+        let min = node.min_chunk();
+        let size = node.chunks_per_axis();
+        for y in 0..size {
+            for z in 0..size {
+                for x in 0..size {
+                    *covered.entry(min + ChunkPos::new(x, y, z)).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    assert!(covered.values().all(|count| *count == 1));
+    assert_eq!(covered.len(), 40 * 40 * 40);
+    // Full detail around the viewer, coarser further out.
+    assert!(lod.selected().contains(&LodPos::new(0, 3, -2, 9)));
+    assert!(lod.selected().iter().any(|node| node.level == MAX_LOD_LEVEL));
+
+    let changes = lod.update(viewer + ChunkPos::new(8, 0, 0));
+    assert!(!changes.added.is_empty() && !changes.removed.is_empty());
+    assert!(lod.update(viewer + ChunkPos::new(8, 0, 0)).added.is_empty());
+}
+
+#[test]
+fn lod_chunk_built_from_children_matches_built_from_chunks() {
+    let chunks: Vec<Chunk> = (0..4).map(|i| {
+        let mut chunk = Chunk::new(ChunkPos::new(i % 2, 0, i / 2));
+        for x in 0..32 {
+            for z in 0..32 {
+                for y in 0..(8 + i as u8 * 4) {
+                    chunk.set_block(LocalPos::new(x, y, z), 1 + i as u16);
+                }
+            }
+        }
+        chunk
+    }).collect();
+
+    let mut direct = LodChunk::new(LodPos::new(2, 0, 0, 0));
+    let mut fine = LodChunk::new(LodPos::new(1, 0, 0, 0));
+    for chunk in chunks.iter() {
+        direct.add_chunk(chunk);
+        fine.add_chunk(chunk);
+    }
+    let mut coarse = LodChunk::new(LodPos::new(2, 0, 0, 0));
+    coarse.add_child(&fine);
+    for local in LocalPos::all() {
+        assert_eq!(direct.get(local), coarse.get(local));
+    }
+    let mesh = direct.mesh(&|id| id != 0);
+    assert!(mesh.vertices.iter().all(|vertex| vertex.position.iter().all(|axis| *axis <= 128.0)));
+    assert!(!mesh.is_empty());
+}
+
+#[test]
+fn universe_tree_summaries_track_chunks_at_every_level() {
+    let mut tree = UniverseTree::with_root_level(3);
+    let mut rng = WorldRng::new(817);
+    let mut positions = Vec::new();
+    for _ in 0..40 {
+        let pos = ChunkPos::new(rng.range_i32(-12..12), rng.range_i32(-12..12), rng.range_i32(-12..12));
+        if tree.chunk(pos).is_none() {
+            tree.insert_chunk(Chunk::filled(pos, 1));
+            positions.push(pos);
+        }
+    }
+    for level in 0..=3 {
+        let nodes = tree.nodes_at(level);
+        assert_eq!(nodes.iter().map(|(_, summary)| summary.chunks).sum::<u64>(), positions.len() as u64);
+        assert!(nodes.iter().all(|(_, summary)| summary.dominant == 1));
+    }
+
+    tree.set_block(positions[0].origin(), 0);
+    assert_eq!(tree.summary(LodPos::new(0, positions[0].x, positions[0].y, positions[0].z)).unwrap().non_air, CHUNK_VOLUME as u64 - 1);
+
+    for pos in positions.iter() {
+        assert!(tree.remove_chunk(*pos).is_some());
+    }
+    assert_eq!(tree.chunk_count(), 0);
+    assert!(tree.nodes_at(3).is_empty());
+}
+
+/// Grows into the next id on random ticks, up to fully grown.
+struct Crop;
+
+const CROP_SEED: BlockId = 10;
+const CROP_GROWN: BlockId = 13;
+
+impl BlockTicker for Crop {
+    fn random_tick(&self, context: &TickContext, block: BlockPos, id: BlockId, _rng: &mut WorldRng) {
+        if id < CROP_GROWN {
+            context.world.set_block(block, id + 1);
+        }
+    }
+
+    fn random_ticks(&self) -> bool {
+        return true;
+    }
+}
+
+#[test]
+fn random_ticks_grow_crops_in_loaded_chunks() {
+    let mut handlers = TickHandlers::new();
+    for id in CROP_SEED..=CROP_GROWN {
+        handlers.register(id, Arc::new(Crop));
+    }
+    let scheduler = Arc::new(TickScheduler::new(handlers, 818));
+    scheduler.set_random_tick_speed(64);
+    let world = Arc::new(World::new());
+    for x in 0..4 {
+        let mut chunk = Chunk::new(ChunkPos::new(x, 0, 0));
+        for local in LocalPos::all().filter(|local| local.y < 16) {
+            chunk.set_block(local, CROP_SEED);
+        }
+        world.insert_chunk(chunk);
+    }
+
+    let jobs = JobSystem::new(4);
+    let mut random = 0;
+    for _ in 0..20 {
+        random += scheduler.run_tick(&world, &jobs).random;
+    }
+    // Only the bottom sections hold crops, and every tick picks from them.
+    assert_eq!(random, 20 * 4 * 4 * 64);
+    assert!(world.get_block(BlockPos::new(0, 20, 0)) == Some(0));
+    let mut grown = 0;
+    world.for_each_chunk(|chunk| {
+        grown += LocalPos::all().filter(|local| chunk.get_block(*local) > CROP_SEED).count();
+    });
+    assert!(grown > 1000);
+}
+
+#[test]
+fn raycast_matches_brute_force_box_tests() {
+    let world = World::new();
+    let mut rng = WorldRng::new(820);
+    let mut chunk = Chunk::new(ChunkPos::new(-1, 0, 0));
+    for _ in 0..600 {
+        chunk.set_block(LocalPos::new(rng.range_i32(0..32) as u8, rng.range_i32(0..32) as u8, rng.range_i32(0..32) as u8), 1);
+    }
+    world.insert_chunk(chunk);
+
+    for _ in 0..200 {
+        let origin = Vec3::new(rng.range_f32(-31.0..-1.0), rng.range_f32(1.0..31.0), rng.range_f32(1.0..31.0));
+        let direction = Vec3::new(rng.range_f32(-1.0..1.0), rng.range_f32(-1.0..1.0), rng.range_f32(-1.0..1.0)).normalize_or_zero();
+        if direction == Vec3::ZERO || world.get_block(BlockPos::new(origin.x.floor() as i32, origin.y.floor() as i32, origin.z.floor() as i32)) != Some(0) {
+            continue;
+        }
+        let ray = Ray::new(origin, direction);
+        let mut nearest: Option<(f32, BlockPos)> = None;
+        world.for_each_chunk(|chunk| {
+            for local in LocalPos::all().filter(|local| chunk.get_block(*local) != 0) {
+                let block = chunk.pos().block(local);
+                let aabb = Aabb::block(block, BlockPos::new(0, 0, 0));
+                if let Some(hit) = ray.intersect_aabb(&aabb, 20.0) {
+                    if nearest.is_none_or(|(distance, _)| hit.distance < distance) {
+                        nearest = Some((hit.distance, block));
+                    }
+                }
+            }
+        });
+        let hit = world.raycast(ray, 20.0);
+        match nearest {
+            Some((distance, _)) => {
+                let hit = hit.expect("raycast missed a block the box test hit");
+                assert!((hit.distance - distance).abs() < 1e-3);
+                assert!(hit.point.distance(ray.at(distance)) < 1e-3);
+                let face = hit.face.unwrap();
+                assert_eq!(world.get_block(hit.block + face.offset()), Some(0));
+            }
+            None => assert!(hit.is_none())
+        }
+    }
+}
+
+
+#[test]
+fn picking_hits_the_nearest_block_or_entity_across_chunks() {
+    let world = World::new();
+    let mut rng = WorldRng::new(870);
+    for x in -1..1 {
+        let mut chunk = Chunk::new(ChunkPos::new(x, 0, 0));
+        for _ in 0..150 {
+            chunk.set_block(LocalPos::new(rng.range_i32(0..32) as u8, rng.range_i32(0..32) as u8, rng.range_i32(0..32) as u8), 1);
+        }
+        world.insert_chunk(chunk);
+    }
+    let entities = world.entities();
+    let mut boxes = Vec::new();
+    for _ in 0..60 {
+        let entity = entities.spawn();
+        let position = WorldPos::new(rng.range_f32(-31.0..31.0) as f64, rng.range_f32(1.0..30.0) as f64, rng.range_f32(1.0..31.0) as f64);
+        let body = RigidBody::new(rng.range_f32(0.3..1.5), rng.range_f32(0.3..2.0));
+        entities.insert(entity, Transform::new(position)).unwrap();
+        entities.insert(entity, body).unwrap();
+        entities.set_chunk(entity, position.chunk());
+        boxes.push((entity, body.aabb(position)));
+    }
+
+    let mut entity_hits = 0;
+    for _ in 0..300 {
+        let origin = Vec3::new(rng.range_f32(-31.0..31.0), rng.range_f32(1.0..31.0), rng.range_f32(1.0..31.0));
+        let direction = Vec3::new(rng.range_f32(-1.0..1.0), rng.range_f32(-1.0..1.0), rng.range_f32(-1.0..1.0)).normalize_or_zero();
+        let inside = boxes.iter().any(|(_, aabb)| aabb.contains_point(origin));
+        if direction == Vec3::ZERO || inside || world.get_block(BlockPos::new(origin.x.floor() as i32, origin.y.floor() as i32, origin.z.floor() as i32)) != Some(0) {
+            continue;
+        }
+        let ray = Ray::new(origin, direction);
+        let block = world.raycast(ray, 24.0).map(|hit| hit.distance);
+        let entity = boxes.iter()
+            .filter_map(|(entity, aabb)| ray.intersect_aabb(aabb, 24.0).map(|hit| (hit.distance, *entity)))
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        match (world.pick(ray, 24.0, None), entity) {
+            (Some(PickHit::Entity(hit)), Some((distance, entity))) => {
+                assert!((hit.distance - distance).abs() < 1e-4);
+                assert_eq!(hit.entity, entity);
+                assert!(block.is_none_or(|block| block >= distance));
+                entity_hits += 1;
+            }
+            (Some(PickHit::Block(hit)), entity) => {
+                assert!(block.is_some_and(|block| (hit.distance - block).abs() < 1e-4));
+                assert!(entity.is_none_or(|(distance, _)| distance >= hit.distance));
+            }
+            (None, entity) => assert!(block.is_none() && entity.is_none()),
+            (hit, entity) => panic!("picked {hit:?} but the nearest entity was {entity:?}")
+        }
+    }
+    assert!(entity_hits > 0);
+}
+
+#[test]
+fn world_events_are_delivered_at_the_end_of_the_tick() {
+    let mut handlers = TickHandlers::new();
+    for id in CROP_SEED..=CROP_GROWN {
+        handlers.register(id, Arc::new(Crop));
+    }
+    let scheduler = Arc::new(TickScheduler::new(handlers, 853));
+    scheduler.set_random_tick_speed(16);
+    let bus = Arc::new(MessageBus::new());
+    let world = Arc::new(World::new().with_events(bus.clone()));
+
+    // A system that only learns about the world through its events.
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let captured = log.clone();
+    bus.subscribe(move |chunk: &ChunkLoaded| captured.lock().unwrap().push(format!("loaded {}", chunk.pos.x)));
+    let captured = log.clone();
+    bus.subscribe(move |_: &EntitySpawned| captured.lock().unwrap().push("spawned".to_string()));
+    let grown = Arc::new(Mutex::new(0));
+    let captured = grown.clone();
+    bus.subscribe(move |change: &BlockChanged| {
+        assert_eq!(change.new, change.old + 1);
+        *captured.lock().unwrap() += 1;
+    });
+
+    for x in 0..2 {
+        let mut chunk = Chunk::new(ChunkPos::new(x, 0, 0));
+        for local in LocalPos::all().filter(|local| local.y < 16) {
+            chunk.set_block(local, CROP_SEED);
+        }
+        world.insert_chunk(chunk);
+    }
+    world.entities().spawn();
+    let jobs = JobSystem::new(4);
+    for _ in 0..10 {
+        // Crops grow on job threads, whose events wait for the tick to end.
+        scheduler.run_tick(&world, &jobs);
+        assert!(bus.pending_count() > 0);
+        world.dispatch_events();
+        assert_eq!(bus.pending_count(), 0);
+    }
+    assert_eq!(*log.lock().unwrap(), vec!["loaded 0", "loaded 1", "spawned"]);
+    let mut grown_blocks = 0;
+    world.for_each_chunk(|chunk| {
+        grown_blocks += LocalPos::all().map(|local| chunk.get_block(local).saturating_sub(CROP_SEED) as usize).sum::<usize>();
+    });
+    assert_eq!(*grown.lock().unwrap(), grown_blocks);
+}
+
+#[test]
+fn game_loop_ticks_the_world_at_a_fixed_rate() {
+    let scheduler = Arc::new(TickScheduler::new(TickHandlers::new(), 861));
+    let bus = Arc::new(MessageBus::new());
+    let world = Arc::new(World::new().with_events(bus.clone()));
+    let loaded = Arc::new(Mutex::new(0));
+    let captured = loaded.clone();
+    bus.subscribe(move |_: &ChunkLoaded| *captured.lock().unwrap() += 1);
+
+    let (pre_world, post_world) = (world.clone(), world.clone());
+    let mut game_loop = GameLoop::new(100)
+        .with_pre_tick(move |tick| {
+            pre_world.insert_chunk(Chunk::new(ChunkPos::new(tick as i32, 0, 0)));
+        })
+        .with_post_tick(move |_| {
+            post_world.dispatch_events();
+        });
+    let jobs = JobSystem::new(2);
+    let start = Instant::now();
+    game_loop.run_while(|| *loaded.lock().unwrap() < 5, |_| {
+        scheduler.run_tick(&world, &jobs);
+    });
+
+    // A slow frame catches up with several ticks, but never runs ticks before they're due.
+    let ticks = game_loop.tick_count();
+    assert!(ticks >= 5);
+    assert!(start.elapsed() >= game_loop.tick_duration() * ticks as u32);
+    assert_eq!(world.chunk_count() as u64, ticks);
+    assert_eq!(*loaded.lock().unwrap() as u64, ticks);
+    assert_eq!(bus.pending_count(), 0);
+}
+
+#[test]
+fn schematics_carry_builds_and_block_entities_between_worlds() {
+    let mut items = ItemRegistry::new();
+    let coal = items.register("cube:coal").unwrap();
+    let items = Arc::new(items);
+    let mut types = BlockEntityTypes::new();
+    inventory::register_block_entities(&mut types, items.clone());
+
+    // The worlds register their blocks in different orders, so the same block has different ids in each.
+    let mut source_blocks = BlockRegistry::new();
+    let (stone, chest) = (source_blocks.register("cube:stone").unwrap(), source_blocks.register("cube:chest").unwrap());
+    let mut target_blocks = BlockRegistry::new();
+    let (target_chest, target_stone) = (target_blocks.register("cube:chest").unwrap(), target_blocks.register("cube:stone").unwrap());
+    assert_ne!(stone, target_stone);
+
+    let source = World::new();
+    for x in -1..=0 {
+        source.insert_chunk(Chunk::new(ChunkPos::new(x, 0, 0)));
+    }
+    // A wall across the chunk border, with a chest in front of it.
+    let mut edit = WorldEdit::new();
+    edit.fill(BlockPos::new(-2, 0, 0), BlockPos::new(1, 2, 0), stone);
+    edit.set(BlockPos::new(0, 0, 1), chest);
+    edit.apply(&source, None).unwrap();
+    let mut container = ContainerBlockEntity::new(27, items.clone());
+    container.inventory.set(0, Some(ItemStack::new(coal, 5)));
+    assert!(source.insert_block_entity(BlockPos::new(0, 0, 1), Box::new(container)).is_ok());
+
+    let schematic = source.copy_region(BlockPos::new(1, 2, 1), BlockPos::new(-2, 0, 0), &source_blocks).unwrap();
+    assert_eq!(schematic.size(), (4, 3, 2));
+    assert_eq!(schematic.block_entities().len(), 1);
+    let path = std::env::temp_dir().join(format!("cube_schematic_test_{}.schem", std::process::id()));
+    schematic.save(&path).unwrap();
+    let loaded = Schematic::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, schematic);
+
+    let target = World::new();
+    target.insert_chunk(Chunk::filled(ChunkPos::new(0, 0, 0), target_stone));
+    let pasted = target.paste_region(&loaded, BlockPos::new(10, 10, 10), &target_blocks, &types, None).unwrap();
+    // Everything but the wall, which was stone already, changed.
+    assert_eq!(pasted.len(), 4 * 3 * 2 - 4 * 3);
+    assert_eq!(target.get_block(BlockPos::new(10, 12, 10)), Some(target_stone));
+    assert_eq!(target.get_block(BlockPos::new(12, 10, 11)), Some(target_chest));
+    assert_eq!(target.get_block(BlockPos::new(13, 10, 11)), Some(AIR));
+    let entity = target.block_entity(BlockPos::new(12, 10, 11)).unwrap();
+    assert_eq!(entity.lock().unwrap().as_any().downcast_ref::<ContainerBlockEntity>().unwrap().inventory.count(coal), 5);
+
+    // Regions reaching unloaded chunks can't be copied.
+    assert!(source.copy_region(BlockPos::new(0, 0, 0), BlockPos::new(40, 0, 0), &source_blocks).is_err());
+}
\ No newline at end of file
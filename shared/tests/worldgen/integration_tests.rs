@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use shared::engine::{
+    block::{BlockRegistry, AIR},
+    job::system::JobSystem,
+    math::coords::{BlockPos, ChunkPos, LocalPos, CHUNK_SIZE},
+    world::{loader::ChunkGenerator, World},
+    worldgen::{blocks::TerrainBlocks, decoration::default_features, feature::FeatureGenerator, generator::{queue_generate_job, WorldGenerator}, terrain::NoiseGenerator}
+};
+
+#[test]
+fn noise_generator_is_deterministic_across_jobs() {
+    let blocks = TerrainBlocks::register(&mut BlockRegistry::new()).unwrap();
+    let generator: Arc<dyn WorldGenerator> = Arc::new(NoiseGenerator::new(42, blocks));
+    let jobs = JobSystem::new(2);
+
+    let positions = [ChunkPos::new(0, 0, 0), ChunkPos::new(-1, -1, 2), ChunkPos::new(3, 0, -4)];
+    let futures: Vec<_> = positions.iter().map(|pos| queue_generate_job(&jobs, generator.clone(), *pos)).collect();
+    for (pos, future) in positions.iter().zip(futures) {
+        let chunk = future.wait();
+        let direct = generator.generate(*pos);
+        for y in 0..CHUNK_SIZE as u8 {
+            for z in 0..CHUNK_SIZE as u8 {
+                for x in 0..CHUNK_SIZE as u8 {
+                    let local = LocalPos::new(x, y, z);
+                    assert_eq!(chunk.get_block(local), direct.get_block(local));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn noise_generator_fills_deep_chunks_and_empties_high_chunks() {
+    let blocks = TerrainBlocks::register(&mut BlockRegistry::new()).unwrap();
+    let generator = NoiseGenerator::new(7, blocks);
+
+    let high = generator.generate(ChunkPos::new(0, 16, 0));
+    assert!(high.is_empty());
+
+    let deep = generator.generate(ChunkPos::new(0, -16, 0));
+    assert!(deep.non_air_count() > 0);
+    assert_ne!(deep.get_block(LocalPos::new(0, 0, 0)), blocks.water);
+
+    let surface = generator.surface_height(0, 0).unwrap();
+    assert!(surface > -100 && surface < 100);
+    let chunk = generator.generate(ChunkPos::new(0, (surface - 40).div_euclid(CHUNK_SIZE), 0));
+    assert!((0..CHUNK_SIZE as u8).any(|y| chunk.get_block(LocalPos::new(0, y, 0)) != AIR));
+}
+
+#[test]
+fn noise_generator_uses_biome_surface_blocks() {
+    let blocks = TerrainBlocks::register(&mut BlockRegistry::new()).unwrap();
+    let generator = NoiseGenerator::new(11, blocks);
+    let biomes = generator.biomes().unwrap().clone();
+    let world = World::new().with_biomes(biomes.clone());
+
+    let mut checked = 0;
+    for i in 0..64 {
+        let (x, z) = (i * 97, i * -61);
+        let surface = generator.surface_height(x, z).unwrap();
+        if surface < 8 {
+            continue;
+        }
+        let biome = world.biome(BlockPos::new(x, surface, z)).unwrap();
+        assert_eq!(biome, biomes.biome(biomes.biome_at(x, z)));
+        let top = BlockPos::new(x, surface, z);
+        let chunk = generator.generate(top.chunk());
+        let local = top.local();
+        let column: Vec<_> = (0..CHUNK_SIZE as u8).map(|y| chunk.get_block(LocalPos::new(local.x, y, local.z))).collect();
+        if column.contains(&biome.surface) || column.contains(&biome.subsurface) {
+            checked += 1;
+        }
+    }
+    assert!(checked > 0);
+}
+
+fn decorated_generator(seed: u64) -> (FeatureGenerator, TerrainBlocks) {
+    let blocks = TerrainBlocks::register(&mut BlockRegistry::new()).unwrap();
+    let mut generator = FeatureGenerator::new(seed, Arc::new(NoiseGenerator::new(seed, blocks)));
+    for feature in default_features(&blocks) {
+        generator.add_feature(feature);
+    }
+    return (generator, blocks);
+}
+
+#[test]
+fn features_are_deterministic_and_cross_chunk_borders() {
+    let (first, blocks) = decorated_generator(99);
+    let (second, _) = decorated_generator(99);
+
+    // Generate a column of chunks around the surface, in a different order for each generator.
+    let mut positions = Vec::new();
+    for y in -2..3 {
+        for z in -1..2 {
+            for x in -1..2 {
+                positions.push(ChunkPos::new(x, y, z));
+            }
+        }
+    }
+    let world_a = World::new();
+    for pos in positions.iter() {
+        world_a.insert_chunk(first.generate(*pos));
+    }
+    first.pending().apply_loaded(&world_a);
+    let world_b = World::new();
+    for pos in positions.iter().rev() {
+        world_b.insert_chunk(second.generate(*pos));
+    }
+    second.pending().apply_loaded(&world_b);
+
+    let mut ores = 0;
+    for pos in positions.iter() {
+        let a = world_a.chunk(*pos).unwrap();
+        let b = world_b.chunk(*pos).unwrap();
+        let (a, b) = (a.read().unwrap(), b.read().unwrap());
+        for local in LocalPos::all() {
+            assert_eq!(a.get_block(local), b.get_block(local), "chunk {:?} differs at {:?}", pos, local);
+            if a.get_block(local) == blocks.coal_ore || a.get_block(local) == blocks.iron_ore {
+                ores += 1;
+            }
+        }
+    }
+    assert!(ores > 0);
+}